@@ -0,0 +1,105 @@
+//! Writes the seed corpora of the fuzz targets, generated from the valid inputs of the seeded
+//! chain (`cargo run --example generate_corpus [corpus directory]`, by default `corpus`)
+use std::fs;
+use std::path::Path;
+
+use abci::{RequestBeginBlock, RequestQuery};
+use chain_abci::app::query_stats::QUERY_STATS_PATH;
+use chain_abci::app::tx_trace::{TX_TRACE_ARM_PATH, TX_TRACE_PATH_PREFIX};
+use chain_abci_fuzz::SeededChain;
+use chain_core::tx::result::TX_RESULT_PATH_PREFIX;
+use parity_scale_codec::Encode;
+use protobuf::Message;
+
+fn write_corpus(dir: &Path, target: &str, seeds: Vec<Vec<u8>>) {
+    let target_dir = dir.join(target);
+    fs::create_dir_all(&target_dir).expect("create corpus directory");
+    for (i, seed) in seeds.iter().enumerate() {
+        fs::write(target_dir.join(format!("seed-{}", i)), seed).expect("write seed");
+    }
+    println!("{}: {} seeds", target, seeds.len());
+}
+
+fn query(path: &str, data: Vec<u8>, height: i64, prove: bool) -> Vec<u8> {
+    RequestQuery {
+        path: path.to_owned(),
+        data,
+        height,
+        prove,
+        ..Default::default()
+    }
+    .write_to_bytes()
+    .expect("encode query")
+}
+
+fn main() {
+    let dir = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "corpus".to_owned());
+    let dir = Path::new(&dir);
+    let chain = SeededChain::new();
+
+    let mut txs = vec![chain.committed_tx.encode()];
+    txs.extend(chain.valid_txs().iter().map(Encode::encode));
+    write_corpus(dir, "tx-process", txs);
+
+    let txid = chain.committed_tx.tx_id().to_vec();
+    let app_hash = chain.app.last_state.as_ref().unwrap().last_apphash.to_vec();
+    let staking_address = chain.env.accounts[0].staking_address().encode();
+    let validator_address = <[u8; 20]>::from(&chain.env.validator_address(0)).to_vec();
+    let queries = vec![
+        query("store", txid.clone(), 0, true),
+        query("store", txid.clone(), 1, true),
+        query("meta", txid.clone(), 0, false),
+        query("witness", txid.clone(), 0, false),
+        query("sealed", txid.clone(), 0, false),
+        query("merkle", app_hash, 0, false),
+        query("account", staking_address.clone(), 0, false),
+        query("staking", staking_address.clone(), 1, true),
+        query("staking", staking_address, -1, false),
+        query("state", vec![], 1, false),
+        query("council-nodes", vec![], 0, false),
+        query("validator_owner", validator_address, 0, false),
+        query(TX_TRACE_ARM_PATH, txid.clone(), 0, false),
+        query(
+            &format!("{}{}", TX_TRACE_PATH_PREFIX, hex::encode(&txid)),
+            vec![],
+            0,
+            false,
+        ),
+        query(
+            &format!("{}{}", TX_RESULT_PATH_PREFIX, hex::encode(&txid)),
+            vec![],
+            0,
+            false,
+        ),
+        query(QUERY_STATS_PATH, vec![], 0, false),
+        query("txquery", vec![], 0, false),
+    ];
+    write_corpus(dir, "abci-query", queries);
+
+    // only the votes and evidence are used, the header is the one of the second block
+    let begin_blocks = vec![
+        RequestBeginBlock {
+            last_commit_info: Some(chain.env.last_commit_info_signed()).into(),
+            ..Default::default()
+        },
+        RequestBeginBlock {
+            last_commit_info: Some(chain.env.last_commit_info(0, false)).into(),
+            ..Default::default()
+        },
+        RequestBeginBlock {
+            last_commit_info: Some(chain.env.last_commit_info_signed()).into(),
+            byzantine_validators: vec![chain.env.byzantine_evidence(0)].into(),
+            ..Default::default()
+        },
+    ];
+    write_corpus(
+        dir,
+        "begin-block",
+        begin_blocks
+            .iter()
+            .map(|req| req.write_to_bytes().expect("encode begin block"))
+            .collect(),
+    );
+}
@@ -11,7 +11,6 @@ use chain_core::common::MerkleTree;
 use chain_core::compute_app_hash;
 use chain_core::init::coin::Coin;
 use chain_core::init::config::InitConfig;
-use chain_core::init::config::NetworkParameters;
 use chain_core::state::tendermint::{TendermintValidatorPubKey, TendermintVotePower};
 use chain_storage::{Storage, NUM_COLUMNS};
 use kvdb::KeyValueDB;
@@ -165,7 +164,7 @@ fuzz_target!(|data: &[u8]| {
                             let result =
                                 c.validate_config_get_genesis(t.get_seconds().try_into().unwrap());
                             if let Ok(state) = result {
-                                let network_params = NetworkParameters::Genesis(c.network_params);
+                                let network_params = c.get_network_parameters();
                                 let r = check_validators(
                                     &state.validators,
                                     req.validators.clone().into_vec(),
@@ -185,8 +184,10 @@ fuzz_target!(|data: &[u8]| {
                                         &network_params,
                                     );
                                     if req.chain_id.len() > 3 {
-                                        if let Ok(netid) =
-                                            hex::decode(&req.chain_id[req.chain_id.len() - 2..])
+                                        if let Some(Ok(netid)) = req
+                                            .chain_id
+                                            .get(req.chain_id.len() - 2..)
+                                            .map(hex::decode)
                                         {
                                             (
                                                 req.clone(),
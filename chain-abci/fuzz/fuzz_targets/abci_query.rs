@@ -0,0 +1,24 @@
+#![no_main]
+use abci::{Application, RequestQuery};
+use chain_abci::app::BufferType;
+use chain_abci_fuzz::{state_fingerprint, SeededChain};
+use libfuzzer_sys::fuzz_target;
+
+// protobuf-encoded query requests (any path, data, height), which never change the state
+fuzz_target!(|data: &[u8]| {
+    let req = match protobuf::parse_from_bytes::<RequestQuery>(data) {
+        Ok(req) => req,
+        Err(_) => return,
+    };
+    let mut chain = SeededChain::new();
+    let app = &mut chain.app;
+
+    let consensus = state_fingerprint(app, BufferType::Consensus);
+    let mempool = state_fingerprint(app, BufferType::Mempool);
+    // twice: computed, then possibly served from the query cache
+    for _ in 0..2 {
+        app.query(&req);
+    }
+    assert_eq!(consensus, state_fingerprint(app, BufferType::Consensus));
+    assert_eq!(mempool, state_fingerprint(app, BufferType::Mempool));
+});
@@ -0,0 +1,34 @@
+#![no_main]
+use abci::{Application, RequestBeginBlock};
+use chain_abci::app::BufferType;
+use chain_abci_fuzz::{state_fingerprint, SeededChain};
+use libfuzzer_sys::fuzz_target;
+
+// protobuf-encoded begin block requests: their votes and evidence are delivered in the
+// (well-formed) header of the second block, the header of the input is ignored
+fuzz_target!(|data: &[u8]| {
+    let req = match protobuf::parse_from_bytes::<RequestBeginBlock>(data) {
+        Ok(req) => req,
+        Err(_) => return,
+    };
+    let mut fingerprints = Vec::with_capacity(2);
+    // twice on a fresh chain: the invalid votes and evidence are ignored the same way
+    for _ in 0..2 {
+        let mut chain = SeededChain::new();
+        let next = chain.next_begin_block();
+        let next_height = next.header.as_ref().expect("seeded header").height;
+        let app = &mut chain.app;
+        app.begin_block(&RequestBeginBlock {
+            last_commit_info: req.last_commit_info.clone(),
+            byzantine_validators: req.byzantine_validators.clone(),
+            ..next
+        });
+        let last_state = app.last_state.as_ref().expect("seeded state");
+        assert_eq!(next_height as u64, last_state.block_height.value());
+        fingerprints.push(state_fingerprint(app, BufferType::Consensus));
+    }
+    assert_eq!(
+        fingerprints[0], fingerprints[1],
+        "begin block isn't deterministic"
+    );
+});
@@ -0,0 +1,46 @@
+#![no_main]
+use abci::{Application, RequestCheckTx, RequestDeliverTx};
+use chain_abci::app::BufferType;
+use chain_abci_fuzz::{state_fingerprint, SeededChain};
+use libfuzzer_sys::fuzz_target;
+
+// arbitrary bytes as the transaction of check tx (mempool) and deliver tx (consensus) requests
+fuzz_target!(|data: &[u8]| {
+    let mut chain = SeededChain::new();
+    let begin_block = chain.next_begin_block();
+    let app = &mut chain.app;
+    app.begin_block(&begin_block);
+
+    let consensus = state_fingerprint(app, BufferType::Consensus);
+    let mempool = state_fingerprint(app, BufferType::Mempool);
+    let rsp = app.check_tx(&RequestCheckTx {
+        tx: data.to_vec(),
+        ..Default::default()
+    });
+    assert_eq!(
+        consensus,
+        state_fingerprint(app, BufferType::Consensus),
+        "check tx changed the consensus state"
+    );
+    if rsp.code != 0 {
+        assert_eq!(
+            mempool,
+            state_fingerprint(app, BufferType::Mempool),
+            "rejected check tx changed the mempool state: {}",
+            rsp.log
+        );
+    }
+
+    let rsp = app.deliver_tx(&RequestDeliverTx {
+        tx: data.to_vec(),
+        ..Default::default()
+    });
+    if rsp.code != 0 {
+        assert_eq!(
+            consensus,
+            state_fingerprint(app, BufferType::Consensus),
+            "rejected deliver tx changed the consensus state: {}",
+            rsp.log
+        );
+    }
+});
@@ -0,0 +1,119 @@
+//! Shared setup of the fuzz targets: a small chain with a seeded state (two council nodes and
+//! a committed block with a transaction) and fingerprints of the state of each ABCI connection,
+//! so that the targets can check that the rejected inputs leave it untouched.
+use abci::{Application, RequestBeginBlock, RequestCommit, RequestDeliverTx, RequestEndBlock};
+use chain_abci::app::tx_result_index::TxResultIndex;
+use chain_abci::app::{BufferType, ChainNodeApp};
+use chain_abci::enclave_bridge::mock::MockClient;
+use chain_core::init::coin::Coin;
+use chain_core::tx::TxAux;
+use parity_scale_codec::Encode;
+use test_common::chain_env::{get_enclave_bridge_mock, ChainEnv};
+
+/// Number of the council nodes of the seeded chain
+const NUM_NODES: usize = 2;
+/// Tx query address of the seeded chain (so that the historical states are stored)
+const TX_QUERY_ADDRESS: &str = "127.0.0.1:3443";
+
+/// Chain initialized from `ChainEnv`, with the first block committed
+pub struct SeededChain {
+    pub env: ChainEnv,
+    pub app: ChainNodeApp<MockClient>,
+    /// the transaction committed in the first block
+    pub committed_tx: TxAux,
+}
+
+impl SeededChain {
+    pub fn new() -> Self {
+        std::env::set_var("CRYPTO_CHAIN_ENABLE_SANITY_CHECKS", "1");
+        let (env, storage) =
+            ChainEnv::new_with_customizer(Coin::max(), Coin::zero(), NUM_NODES, |parameters| {
+                parameters.required_council_node_stake = (Coin::max() / 10).unwrap();
+            });
+        let init_chain = env.req_init_chain();
+        let mut app = ChainNodeApp::new_with_storage(
+            get_enclave_bridge_mock(),
+            &hex::encode_upper(env.genesis_app_hash),
+            &init_chain.chain_id,
+            storage,
+            Some(TX_QUERY_ADDRESS.to_owned()),
+            "".to_owned(),
+        );
+        app.tx_result_index = TxResultIndex::new(true);
+        app.query_stats_admin = true;
+        app.init_chain(&init_chain);
+
+        app.begin_block(&env.req_begin_block(1, 0));
+        let committed_tx = env.unbond_tx(Coin::unit(), 0, 1);
+        let rsp = app.deliver_tx(&RequestDeliverTx {
+            tx: committed_tx.encode(),
+            ..Default::default()
+        });
+        assert_eq!(0, rsp.code, "seeded transaction rejected: {}", rsp.log);
+        app.end_block(&RequestEndBlock {
+            height: 1,
+            ..Default::default()
+        });
+        app.commit(&RequestCommit::new());
+
+        SeededChain {
+            env,
+            app,
+            committed_tx,
+        }
+    }
+
+    /// Request of the second block, with the votes of all the council nodes
+    pub fn next_begin_block(&self) -> RequestBeginBlock {
+        RequestBeginBlock {
+            last_commit_info: Some(self.env.last_commit_info_signed()).into(),
+            ..self.env.req_begin_block(2, 1)
+        }
+    }
+
+    /// Transactions which are valid in the second block
+    pub fn valid_txs(&self) -> Vec<TxAux> {
+        vec![
+            self.env.unbond_tx(Coin::unit(), 1, 1),
+            self.env.unbond_tx(Coin::unit(), 0, 0),
+        ]
+    }
+}
+
+impl Default for SeededChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fingerprint of the state of a connection: the app state and the buffered (not yet flushed)
+/// writes, as well as the delivered transactions of the consensus connection. The block budget
+/// isn't included, as the transactions are charged regardless of their outcome.
+pub fn state_fingerprint(app: &ChainNodeApp<MockClient>, buffer_type: BufferType) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    match buffer_type {
+        BufferType::Consensus => {
+            hasher.update(&app.last_state.encode());
+            hasher.update(&app.delivered_txs.encode());
+            hasher.update(&sorted_encoded(app.kv_buffer.iter()));
+            hasher.update(&sorted_encoded(app.staking_buffer.iter()));
+        }
+        BufferType::Mempool => {
+            hasher.update(&app.mempool_state.encode());
+            hasher.update(&sorted_encoded(app.mempool_kv_buffer.iter()));
+            hasher.update(&sorted_encoded(app.mempool_staking_buffer.iter()));
+        }
+    }
+    hasher.finalize().into()
+}
+
+/// the entries of a hash map in a deterministic order
+fn sorted_encoded<'a, K: Encode + 'a, V: Encode + 'a>(
+    entries: impl Iterator<Item = (&'a K, &'a V)>,
+) -> Vec<u8> {
+    let mut entries = entries
+        .map(|(key, value)| (key.encode(), value.encode()))
+        .collect::<Vec<_>>();
+    entries.sort();
+    entries.encode()
+}
@@ -8,10 +8,18 @@ use parity_scale_codec::{Decode, Encode};
 use protobuf::Message;
 use serde::{Deserialize, Serialize};
 
+use super::block_budget::BlockResources;
+use super::crash_report::CrashContext;
+use super::proposer_stats::ProposerStats;
+use super::query_cache::QueryCache;
+use super::query_stats::QueryStats;
+use super::storage_check::{format_report, verify_storage, StorageCheckLevel};
+use super::tx_result_index::TxResultIndex;
+use super::tx_trace::TxTracer;
 use crate::enclave_bridge::EnclaveProxy;
 use crate::staking::StakingTable;
 use chain_core::common::MerkleTree;
-use chain_core::common::Timespec;
+use chain_core::common::{ConsensusCodec, Timespec};
 use chain_core::common::{H256, HASH_SIZE_256};
 use chain_core::compute_app_hash;
 use chain_core::init::address::RedeemAddress;
@@ -29,6 +37,7 @@ use chain_storage::buffer::{
 };
 use chain_storage::jellyfish::{compute_staking_root, sum_staking_coins, StakingGetter, Version};
 use chain_storage::{Storage, StoredChainState};
+use chain_tx_filter::BlockFilter;
 
 /// ABCI app state snapshot
 #[derive(Serialize, Deserialize, Clone, Encode, Decode)]
@@ -78,6 +87,8 @@ impl StoredChainState for ChainNodeState {
     }
 }
 
+impl ConsensusCodec for ChainNodeState {}
+
 impl ChainNodeState {
     #[allow(clippy::too_many_arguments)]
     pub fn genesis(
@@ -115,6 +126,7 @@ impl ChainNodeState {
 }
 
 /// Two types of storage buffer
+#[derive(Debug, Clone, Copy)]
 pub enum BufferType {
     Consensus,
     Mempool,
@@ -126,6 +138,8 @@ pub struct ChainNodeApp<T: EnclaveProxy> {
     pub storage: Storage,
     /// valid transactions after DeliverTx before EndBlock/Commit
     pub delivered_txs: Vec<TxAux>,
+    /// staked state addresses of the valid transactions after DeliverTx before EndBlock/Commit
+    pub delivered_staking_addresses: Vec<StakedStateAddress>,
     /// a reference to genesis (used when there is no committed state)
     pub genesis_app_hash: H256,
     /// last two hex digits in chain_id
@@ -138,10 +152,33 @@ pub struct ChainNodeApp<T: EnclaveProxy> {
     pub tx_validator: T,
     /// was rewards pool updated in the current block?
     pub rewards_pool_updated: bool,
+    /// resource units spent by the transactions delivered in the current block
+    pub block_resources: BlockResources,
     /// address of tx query enclave to supply to clients (if any)
     pub tx_query_address: Option<String>,
     /// Address of TDBE to supply to clients
     pub tdbe_address: String,
+    /// per-path counters of the served queries
+    pub query_stats: QueryStats,
+    /// responses to the queries of the committed state (invalidated on each commit)
+    pub query_cache: QueryCache,
+    /// whether the admin query paths (`query_stats/reset`, `trace/...`) are enabled
+    /// (should only be enabled on nodes which don't expose Tendermint RPC publicly)
+    pub query_stats_admin: bool,
+    /// block proposer statistics of the current and of the last rewards period
+    /// (node-local, restarted with the node)
+    pub proposer_stats: ProposerStats,
+    /// the transaction armed for diagnostic tracing (if any)
+    pub tx_tracer: TxTracer,
+    /// results of the delivered transactions (if indexed)
+    pub tx_result_index: TxResultIndex,
+    /// number of the most recent blocks to keep (reported to Tendermint as the retain height,
+    /// the tx result index is pruned to the same window); all blocks are kept if not set
+    pub retain_blocks: Option<u64>,
+    /// context of the crash reports (shared with the panic hook)
+    pub crash_context: CrashContext,
+    /// filter of the current block (set in end block), rolled up into its filter range in commit
+    pub block_filter: Option<BlockFilter>,
 
     /// consensus buffer of staking merkle trie storage
     pub staking_buffer: StakingBuffer,
@@ -236,7 +273,7 @@ pub fn init_app_hash(conf: &InitConfig, genesis_time: Timespec) -> H256 {
         &MerkleTree::empty(),
         &compute_staking_root(&state.accounts),
         &state.rewards_pool,
-        &NetworkParameters::Genesis(conf.network_params.clone()),
+        &conf.get_network_parameters(),
     )
 }
 
@@ -250,36 +287,32 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
         tx_query_address: Option<String>,
         tdbe_address: String,
     ) -> Self {
-        let stored_genesis = storage.get_genesis_app_hash();
-
-        if stored_genesis != genesis_app_hash {
-            panic!(
-                "stored genesis app hash: {} does not match the provided genesis app hash: {}",
-                hex::encode(stored_genesis),
-                hex::encode(genesis_app_hash)
-            );
-        }
-        let stored_chain_id = storage.get_stored_chain_id();
-        if stored_chain_id != chain_id.as_bytes() {
-            panic!(
-                "stored chain id: {:?} does not match the provided chain id: {:?}",
-                stored_chain_id, chain_id
-            );
-        }
         let chain_hex_id = hex::decode(&chain_id[chain_id.len() - 2..])
             .expect("failed to decode two last hex digits in chain ID")[0];
 
+        let crash_context = CrashContext::new(chain_id, Some(&last_app_state));
         ChainNodeApp {
             storage,
             delivered_txs: Vec::new(),
+            delivered_staking_addresses: Vec::new(),
             chain_hex_id,
             genesis_app_hash,
             last_state: Some(last_app_state.clone()),
             mempool_state: Some(last_app_state),
             tx_validator,
             rewards_pool_updated: false,
+            block_resources: BlockResources::default(),
             tx_query_address,
             tdbe_address,
+            query_stats: QueryStats::default(),
+            query_cache: QueryCache::default(),
+            query_stats_admin: false,
+            proposer_stats: ProposerStats::default(),
+            tx_tracer: TxTracer::default(),
+            tx_result_index: TxResultIndex::default(),
+            retain_blocks: None,
+            crash_context,
+            block_filter: None,
 
             staking_buffer: HashMap::new(),
             mempool_staking_buffer: HashMap::new(),
@@ -314,6 +347,17 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
 
         if let Some(data) = storage.get_last_app_state() {
             info!("last app state stored");
+            if let Err(inconsistencies) = verify_storage(
+                &storage,
+                &genesis_app_hash,
+                chain_id,
+                StorageCheckLevel::Quick,
+            ) {
+                panic!(
+                    "inconsistent storage, refusing to start:\n{}",
+                    format_report(&inconsistencies)
+                );
+            }
             let mut last_state =
                 ChainNodeState::decode(&mut data.as_slice()).expect("deserialize app state");
 
@@ -352,6 +396,12 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
                     .network_params
                     .get_required_council_node_stake(),
             );
+            last_state.staking_table.set_minimum_bond_remainder(
+                last_state
+                    .top_level
+                    .network_params
+                    .get_minimum_bond_remainder(),
+            );
             ChainNodeApp::restore_from_storage(
                 tx_validator,
                 last_state,
@@ -377,14 +427,25 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
             ChainNodeApp {
                 storage,
                 delivered_txs: Vec::new(),
+                delivered_staking_addresses: Vec::new(),
                 chain_hex_id,
                 genesis_app_hash,
                 last_state: None,
                 mempool_state: None,
                 tx_validator,
                 rewards_pool_updated: false,
+                block_resources: BlockResources::default(),
                 tx_query_address,
                 tdbe_address,
+                query_stats: QueryStats::default(),
+                query_cache: QueryCache::default(),
+                query_stats_admin: false,
+                proposer_stats: ProposerStats::default(),
+                tx_tracer: TxTracer::default(),
+                tx_result_index: TxResultIndex::default(),
+                retain_blocks: None,
+                crash_context: CrashContext::new(chain_id, None),
+                block_filter: None,
 
                 staking_buffer: HashMap::new(),
                 mempool_staking_buffer: HashMap::new(),
@@ -433,7 +494,7 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
             );
         }
 
-        let network_params = NetworkParameters::Genesis(conf.network_params);
+        let network_params = conf.get_network_parameters();
         let new_account_root = self.storage.put_stakings(0, &state.accounts);
         let genesis_app_hash = compute_app_hash(
             &MerkleTree::empty(),
@@ -465,12 +526,13 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
             .iter()
             .map(|(addr, _)| *addr)
             .collect::<Vec<_>>();
-        let staking_table = StakingTable::from_genesis(
+        let mut staking_table = StakingTable::from_genesis(
             &staking_getter!(self, 0),
             network_params.get_required_council_node_stake(),
             network_params.get_max_validators(),
             &val_addresses,
         );
+        staking_table.set_minimum_bond_remainder(network_params.get_minimum_bond_remainder());
 
         let genesis_state = ChainNodeState::genesis(
             genesis_app_hash,
@@ -489,6 +551,7 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
         );
         flush_storage(&mut self.storage, mem::take(&mut self.kv_buffer)).expect("storage io error");
 
+        self.crash_context.commit(&genesis_state);
         self.last_state = Some(genesis_state);
         self.mempool_state = self.last_state.clone();
         ResponseInitChain::new()
@@ -0,0 +1,135 @@
+//! Cumulative accounting of the work done by transactions in a block (akin to block gas).
+//! Tendermint only bounds the block size in bytes, so a block of the "expensive" transactions
+//! (enclave calls, staking writes) could exceed the enclave's memory or the consensus timeouts.
+//! Each transaction is charged the weighted sum of its resources (as estimated from its type
+//! before it's executed, so that all the nodes charge the same amount regardless of the outcome),
+//! and transactions which don't fit into the rest of the block budget are rejected.
+
+use chain_core::init::config::BlockBudget;
+use chain_core::tx::{TxAux, TxEnclaveAux};
+
+use crate::tx_error::TxError;
+
+/// Resources used by executing a transaction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TxResources {
+    /// size of the encoded transaction
+    pub encoded_size: usize,
+    /// number of the transaction validation calls to the enclave
+    pub enclave_calls: u64,
+    /// number of the written staking states
+    pub staking_writes: u64,
+}
+
+impl TxResources {
+    /// Estimates the resources of a (decoded) transaction
+    pub fn of_tx(txaux: &TxAux, encoded_size: usize) -> Self {
+        let (enclave_calls, staking_writes) = match txaux {
+            TxAux::EnclaveTx(TxEnclaveAux::TransferTx { .. }) => (1, 0),
+            TxAux::EnclaveTx(TxEnclaveAux::DepositStakeTx { .. }) => (1, 1),
+            TxAux::EnclaveTx(TxEnclaveAux::WithdrawUnbondedStakeTx { .. }) => (1, 1),
+            TxAux::PublicTx(_) => (0, 1),
+            TxAux::MLSHandshake(_) => (0, 0),
+        };
+        TxResources {
+            encoded_size,
+            enclave_calls,
+            staking_writes,
+        }
+    }
+
+    /// Weighted sum of the resources
+    pub fn units(&self, budget: &BlockBudget) -> u64 {
+        budget.units(self.encoded_size, self.enclave_calls, self.staking_writes)
+    }
+}
+
+/// Units spent by the transactions delivered in the current block (reset at each begin block)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockResources {
+    used: u64,
+}
+
+impl BlockResources {
+    /// Units spent so far
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+
+    /// Charges a transaction, or fails (without charging) if it doesn't fit into the rest
+    /// of the block budget
+    pub fn charge(&mut self, units: u64, budget: &BlockBudget) -> Result<(), TxError> {
+        match self.used.checked_add(units) {
+            Some(used) if used <= budget.max_block_units => {
+                self.used = used;
+                Ok(())
+            }
+            _ => Err(TxError::BlockBudgetExceeded {
+                used: self.used,
+                units,
+                max: budget.max_block_units,
+            }),
+        }
+    }
+
+    /// Checks (without charging) that a transaction fits into an empty block
+    pub fn check_single(units: u64, budget: &BlockBudget) -> Result<(), TxError> {
+        BlockResources::default().charge(units, budget)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tx_error::BLOCK_BUDGET_EXCEEDED_CODE;
+
+    const BUDGET: BlockBudget = BlockBudget {
+        max_block_units: 1_000,
+        units_per_byte: 1,
+        units_per_enclave_call: 100,
+        units_per_staking_write: 10,
+    };
+
+    #[test]
+    fn check_block_filled_to_budget() {
+        let tx = TxResources {
+            encoded_size: 140,
+            enclave_calls: 1,
+            staking_writes: 1,
+        };
+        assert_eq!(250, tx.units(&BUDGET));
+
+        let mut block = BlockResources::default();
+        for _ in 0..4 {
+            block.charge(tx.units(&BUDGET), &BUDGET).unwrap();
+        }
+        assert_eq!(1_000, block.used());
+
+        let err = block.charge(1, &BUDGET).unwrap_err();
+        assert_eq!(BLOCK_BUDGET_EXCEEDED_CODE, err.code());
+        // rejected transactions aren't charged
+        assert_eq!(1_000, block.used());
+        block.charge(0, &BUDGET).unwrap();
+    }
+
+    #[test]
+    fn check_overflow_rejected_deterministically() {
+        let units = [300, 450, 200, 100, 50];
+        let outcomes = |block: &mut BlockResources| {
+            units
+                .iter()
+                .map(|units| block.charge(*units, &BUDGET).is_ok())
+                .collect::<Vec<_>>()
+        };
+        let (mut node1, mut node2) = (BlockResources::default(), BlockResources::default());
+        // the 4th transaction doesn't fit, but the smaller one after it does
+        let expected = vec![true, true, true, false, true];
+        assert_eq!(expected, outcomes(&mut node1));
+        assert_eq!(expected, outcomes(&mut node2));
+        assert_eq!(node1, node2);
+
+        assert!(BlockResources::check_single(1_000, &BUDGET).is_ok());
+        assert!(BlockResources::check_single(1_001, &BUDGET).is_err());
+        assert!(BlockResources::check_single(u64::max_value(), &BUDGET).is_err());
+    }
+}
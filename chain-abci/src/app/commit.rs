@@ -1,8 +1,13 @@
+use std::convert::TryInto;
 use std::mem;
 
+use super::filter_range::roll_up_block_filter;
+use super::tx_result_index::retain_height;
 use super::ChainNodeApp;
 use crate::enclave_bridge::EnclaveProxy;
 use abci::*;
+#[cfg(feature = "consensus-codec-check")]
+use chain_core::common::ConsensusCodec;
 use chain_core::common::MerkleTree;
 use chain_core::compute_app_hash;
 use chain_core::tx::data::input::{TxoPointer, TxoSize};
@@ -12,6 +17,17 @@ use chain_storage::buffer::{flush_storage, StoreKV};
 use chain_storage::jellyfish::flush_stakings;
 use parity_scale_codec::Encode;
 
+/// Checks (in debug builds) that the state about to be persisted is re-encoded identically
+/// after decoding it, so that re-serializing it on any node gives the same app hash
+#[cfg(feature = "consensus-codec-check")]
+fn debug_check_reencoding<T: ConsensusCodec>(value: &T) {
+    debug_assert!(
+        T::decode_canonical(&value.encode()).is_ok(),
+        "{} isn't re-encoded identically",
+        std::any::type_name::<T>()
+    );
+}
+
 /// Given a db and a DB transaction, it will go through TX inputs and mark them as spent
 /// in the TX_META storage and it will create a new entry for TX in TX_META with all outputs marked as unspent.
 fn update_utxos_commit(
@@ -70,6 +86,11 @@ fn process_txs(delivered_txs: &[TxAux], db: &mut impl StoreKV) {
                 // staked state updated in deliver_tx
                 // validator state updated in end_block
             }
+            TxAux::PublicTx(TxPublicAux::UpdateNodeTx(tx, witness)) => {
+                chain_storage::store_tx_body(db, &txid, &tx.encode());
+                chain_storage::store_tx_witness(db, &txid, &witness.encode());
+                // staked state updated in deliver_tx
+            }
         }
     }
 }
@@ -96,6 +117,11 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
             self.rewards_pool_updated = false;
         }
 
+        #[cfg(feature = "consensus-codec-check")]
+        self.staking_buffer
+            .values()
+            .for_each(debug_check_reencoding);
+
         // flush staking storage
         if !self.staking_buffer.is_empty() {
             new_state.staking_version = new_state
@@ -117,8 +143,22 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
             &top_level.network_params,
         );
         new_state.last_apphash = app_hash;
+        #[cfg(feature = "consensus-codec-check")]
+        debug_check_reencoding(&*new_state);
 
         chain_storage::store_txs_merkle_tree(&mut kv_store!(self), &app_hash, &tree.encode());
+        let block_filter = self.block_filter.take();
+        roll_up_block_filter(
+            &mut kv_store!(self),
+            new_state.last_block_height,
+            block_filter.as_ref(),
+        );
+        let retain_height = retain_height(new_state.last_block_height, self.retain_blocks);
+        self.tx_result_index.commit(
+            &mut kv_store!(self),
+            new_state.last_block_height,
+            retain_height,
+        );
         chain_storage::store_chain_state(
             &mut kv_store!(self),
             &*new_state,
@@ -131,9 +171,15 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
             .expect("kv storage io error");
 
         resp.data = new_state.last_apphash.to_vec();
+        if let Some(retain_height) = retain_height {
+            resp.retain_height = retain_height.value().try_into().unwrap_or(i64::max_value());
+        }
 
+        self.query_cache.invalidate(new_state.last_block_height);
+        self.crash_context.commit(new_state);
         self.mempool_state = Some(new_state.clone());
         self.delivered_txs.clear();
+        self.delivered_staking_addresses.clear();
         self.mempool_kv_buffer.clear();
         self.mempool_staking_buffer.clear();
         resp
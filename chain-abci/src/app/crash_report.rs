@@ -0,0 +1,200 @@
+//! Crash reports for fatal errors (panics) in the consensus paths, so that operators get
+//! the context of an abort (chain id, heights, last app hash, delivered transactions)
+//! without reconstructing it from the logs.
+//! The report only contains public data: transaction ids, never their payloads or any keys.
+
+use std::fs;
+use std::io;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::error;
+use serde::{Deserialize, Serialize};
+
+use super::app_init::ChainNodeState;
+use chain_core::common::Timespec;
+use chain_core::tx::data::TxId;
+
+/// Prefix of the crash report file names (`crash-report-<unix time>.json`)
+pub const CRASH_REPORT_PREFIX: &str = "crash-report-";
+
+/// Summary of the last committed state
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommittedStateSummary {
+    /// height of the last committed block
+    pub block_height: u64,
+    /// hex-encoded app hash of the last committed block
+    pub app_hash: String,
+    /// time of the last committed block
+    pub block_time: Timespec,
+    /// version of the staking merkle trie
+    pub staking_version: u64,
+}
+
+impl CommittedStateSummary {
+    fn new(state: &ChainNodeState) -> Self {
+        CommittedStateSummary {
+            block_height: state.last_block_height.value(),
+            app_hash: hex::encode(&state.last_apphash),
+            block_time: state.block_time,
+            staking_version: state.staking_version,
+        }
+    }
+}
+
+/// Crash report written on a fatal error
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrashReport {
+    /// time of the crash (seconds since UNIX epoch)
+    pub time: u64,
+    /// chain id the node was started with
+    pub chain_id: String,
+    /// the panic message
+    pub error: String,
+    /// source location of the panic (if known)
+    pub location: Option<String>,
+    /// the last committed state (`None` before InitChain)
+    pub last_committed: Option<CommittedStateSummary>,
+    /// height of the block being processed (`None` between blocks)
+    pub in_flight_block_height: Option<u64>,
+    /// hex-encoded ids of the transactions delivered in the block being processed
+    pub delivered_txids: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct CrashState {
+    chain_id: String,
+    last_committed: Option<CommittedStateSummary>,
+    in_flight_block_height: Option<u64>,
+    delivered_txids: Vec<TxId>,
+    consensus_thread: Option<ThreadId>,
+}
+
+/// Context of the crash reports, kept up to date by the app and shared with the panic hook
+#[derive(Debug, Clone, Default)]
+pub struct CrashContext(Arc<Mutex<CrashState>>);
+
+impl CrashContext {
+    /// Creates the context for the given chain and the last committed state (if any)
+    pub fn new(chain_id: &str, last_state: Option<&ChainNodeState>) -> Self {
+        CrashContext(Arc::new(Mutex::new(CrashState {
+            chain_id: chain_id.to_owned(),
+            last_committed: last_state.map(CommittedStateSummary::new),
+            in_flight_block_height: None,
+            delivered_txids: Vec::new(),
+            consensus_thread: None,
+        })))
+    }
+
+    // the hook may run after a panic in another thread, so the poisoned lock is still used
+    fn lock(&self) -> MutexGuard<'_, CrashState> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Marks the current thread as the one processing a consensus connection request
+    /// until the returned guard is dropped (the panic hook runs before the unwinding drops it)
+    pub fn consensus_request(&self) -> ConsensusRequest {
+        self.lock().consensus_thread = Some(thread::current().id());
+        ConsensusRequest(self.clone())
+    }
+
+    /// Checks if the current thread is processing a consensus connection request
+    pub fn in_consensus_request(&self) -> bool {
+        self.lock().consensus_thread == Some(thread::current().id())
+    }
+
+    /// Records the start of processing of a block
+    pub fn begin_block(&self, block_height: u64) {
+        let mut state = self.lock();
+        state.in_flight_block_height = Some(block_height);
+        state.delivered_txids.clear();
+    }
+
+    /// Records a delivered transaction
+    pub fn deliver_tx(&self, txid: TxId) {
+        self.lock().delivered_txids.push(txid);
+    }
+
+    /// Records the committed (or genesis) state
+    pub fn commit(&self, state: &ChainNodeState) {
+        let mut crash_state = self.lock();
+        crash_state.last_committed = Some(CommittedStateSummary::new(state));
+        crash_state.in_flight_block_height = None;
+        crash_state.delivered_txids.clear();
+    }
+
+    /// Crash report of the current context
+    pub fn report(&self, error: String, location: Option<String>) -> CrashReport {
+        let state = self.lock();
+        CrashReport {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            chain_id: state.chain_id.clone(),
+            error,
+            location,
+            last_committed: state.last_committed.clone(),
+            in_flight_block_height: state.in_flight_block_height,
+            delivered_txids: state.delivered_txids.iter().map(hex::encode).collect(),
+        }
+    }
+}
+
+/// Guard of the processing of a consensus connection request (see `CrashContext::consensus_request`)
+#[derive(Debug)]
+pub struct ConsensusRequest(CrashContext);
+
+impl Drop for ConsensusRequest {
+    fn drop(&mut self) {
+        self.0.lock().consensus_thread = None;
+    }
+}
+
+/// Writes the report (as JSON) into the directory, returns the path of the report file
+pub fn write_report(dir: &Path, report: &CrashReport) -> io::Result<PathBuf> {
+    let path = dir.join(format!("{}{}.json", CRASH_REPORT_PREFIX, report.time));
+    let payload =
+        serde_json::to_vec_pretty(report).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    fs::create_dir_all(dir)?;
+    fs::write(&path, payload)?;
+    Ok(path)
+}
+
+fn panic_message(info: &panic::PanicInfo<'_>) -> String {
+    if let Some(message) = info.payload().downcast_ref::<&str>() {
+        (*message).to_owned()
+    } else if let Some(message) = info.payload().downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_owned()
+    }
+}
+
+/// Installs a panic hook which writes a crash report into `dir` (and then calls the previous hook).
+/// If `exit` is set, the process exits afterwards on a panic in the main thread or in
+/// a consensus connection request (which shouldn't leave the node running with a partially
+/// processed block); panics in the other threads (e.g. of the mempool or query connections)
+/// are left to their threads.
+pub fn install_panic_hook(dir: PathBuf, context: CrashContext, exit: bool) {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let report = context.report(
+            panic_message(info),
+            info.location().map(|location| location.to_string()),
+        );
+        match write_report(&dir, &report) {
+            Ok(path) => error!("crash report written to {}", path.display()),
+            Err(e) => error!("failed to write crash report: {}", e),
+        }
+        previous(info);
+        let main_thread = thread::current().name() == Some("main");
+        if exit && (main_thread || context.in_consensus_request()) {
+            process::exit(1);
+        }
+    }));
+}
@@ -1,9 +1,13 @@
 use std::convert::TryInto;
 
 use crate::app::app_init::ChainNodeApp;
+use crate::app::event_builder::EventBuilder;
 use crate::enclave_bridge::EnclaveProxy;
 use abci::{Event, Pair as KVPair, PubKey, RequestEndBlock, ResponseEndBlock, ValidatorUpdate};
-use chain_core::common::TendermintEventType;
+use chain_core::common::{TendermintEventKey, TendermintEventType};
+use chain_core::state::tendermint::{
+    TendermintValidatorPubKey, TendermintVotePower, ValidatorPowerChange,
+};
 use chain_tx_filter::BlockFilter;
 use enclave_protocol::{IntraEnclaveRequest, IntraEnclaveResponseOk};
 
@@ -15,34 +19,45 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
             let end_block_resp = self
                 .tx_validator
                 .process_request(IntraEnclaveRequest::EndBlock);
-            if let Ok(IntraEnclaveResponseOk::EndBlock(maybe_filter)) = end_block_resp {
-                if let Some(raw_filter) = maybe_filter {
-                    let filter = BlockFilter::from(&*raw_filter);
-
-                    let (key, value) = filter.get_tendermint_kv();
-                    let mut kvpair = KVPair::new();
-                    kvpair.key = key;
-                    kvpair.value = value;
-                    let mut event = Event::new();
-                    event.field_type = TendermintEventType::BlockFilter.to_string();
-                    event.attributes.push(kvpair);
-                    resp.events.push(event);
+            // view keys of the transactions with outputs are only known to the enclave
+            let mut filter = match end_block_resp {
+                Ok(IntraEnclaveResponseOk::EndBlock(Some(raw_filter))) => {
+                    BlockFilter::from(&*raw_filter)
                 }
-            } else {
-                panic!("end block request to obtain the block filter failed");
+                Ok(IntraEnclaveResponseOk::EndBlock(None)) => BlockFilter::default(),
+                _ => panic!("end block request to obtain the block filter failed"),
+            };
+            for tx in self.delivered_txs.iter() {
+                filter.add_tx_id(&tx.tx_id());
             }
+            filter.extend_staked_state_addresses(self.delivered_staking_addresses.iter());
+
+            #[cfg(feature = "versioned-block-filter")]
+            let (key, value) = filter.get_tendermint_kv_versioned();
+            #[cfg(not(feature = "versioned-block-filter"))]
+            let (key, value) = filter.get_tendermint_kv();
+            let mut kvpair = KVPair::new();
+            kvpair.key = key;
+            kvpair.value = value;
+            let mut event = EventBuilder::new(TendermintEventType::BlockFilter);
+            event.push(kvpair);
+            resp.events.push(event.build());
+            self.block_filter = Some(filter);
         }
         // TODO: skipchain-based validator changes?
         let state = self.last_state.as_mut().expect("executing end block, but no app state stored (i.e. no initchain or recovery was executed)");
-        let val_updates = state.staking_table.end_block(
+        let val_updates = state.staking_table.end_block_with_old_power(
             &staking_getter!(self, state.staking_version),
             state.top_level.network_params.get_max_validators(),
         );
 
+        if !val_updates.is_empty() {
+            resp.events.push(validator_set_update_event(&val_updates));
+        }
         resp.set_validator_updates(
             val_updates
                 .into_iter()
-                .map(|(pubkey, power)| {
+                .map(|(pubkey, _, power)| {
                     let mut validator = ValidatorUpdate::default();
                     validator.set_power(power.into());
 
@@ -60,3 +75,29 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
         resp
     }
 }
+
+/// Event listing the voting power changes included in the validator updates
+/// (one `validator_update` attribute per changed validator, in the order of the updates)
+fn validator_set_update_event(
+    val_updates: &[(
+        TendermintValidatorPubKey,
+        TendermintVotePower,
+        TendermintVotePower,
+    )],
+) -> Event {
+    // one attribute per update, bounded by the maximum number of validators
+    let mut event = EventBuilder::new(TendermintEventType::ValidatorSetUpdate)
+        .with_max_attributes(val_updates.len());
+    for (pubkey, old_power, new_power) in val_updates.iter() {
+        let change = ValidatorPowerChange {
+            pubkey: pubkey.to_base64(),
+            old_power: *old_power,
+            new_power: *new_power,
+        };
+        event.attribute(
+            TendermintEventKey::ValidatorUpdate,
+            serde_json::to_vec(&change).expect("serialize validator power change"),
+        );
+    }
+    event.build()
+}
@@ -0,0 +1,136 @@
+//! Construction of the events returned to Tendermint.
+//! The attributes of an event are put in the canonical order (sorted by their key bytes, the
+//! attributes with the same key keep the order in which they were added), so that the events
+//! of the same logical change are byte-identical regardless of how they were assembled.
+
+use abci::{Event, Pair as KVPair};
+use chain_core::common::{TendermintEventKey, TendermintEventType};
+
+/// Default upper bound of the number of attributes of a single event (checked in debug builds)
+pub(crate) const DEFAULT_MAX_EVENT_ATTRIBUTES: usize = 64;
+
+pub(crate) struct EventBuilder {
+    event_type: TendermintEventType,
+    attributes: Vec<KVPair>,
+    max_attributes: usize,
+}
+
+impl EventBuilder {
+    pub fn new(event_type: TendermintEventType) -> Self {
+        EventBuilder {
+            event_type,
+            attributes: Vec::new(),
+            max_attributes: DEFAULT_MAX_EVENT_ATTRIBUTES,
+        }
+    }
+
+    /// Overrides the maximum number of attributes of the event
+    pub fn with_max_attributes(mut self, max_attributes: usize) -> Self {
+        self.max_attributes = max_attributes;
+        self
+    }
+
+    /// Adds an attribute under a well-known key
+    pub fn attribute(&mut self, key: TendermintEventKey, value: impl Into<Vec<u8>>) -> &mut Self {
+        let mut kv_pair = KVPair::new();
+        kv_pair.key = key.into();
+        kv_pair.value = value.into();
+        self.push(kv_pair)
+    }
+
+    /// Adds an already constructed attribute
+    pub fn push(&mut self, kv_pair: KVPair) -> &mut Self {
+        self.attributes.push(kv_pair);
+        self
+    }
+
+    /// Builds the event with its attributes in the canonical order
+    pub fn build(mut self) -> Event {
+        debug_assert!(
+            self.attributes.len() <= self.max_attributes,
+            "{} event has {} attributes (at most {} expected)",
+            self.event_type,
+            self.attributes.len(),
+            self.max_attributes
+        );
+        // stable sort: attributes with the same key stay in the insertion order
+        self.attributes.sort_by(|a, b| a.key.cmp(&b.key));
+
+        let mut event = Event::new();
+        event.field_type = self.event_type.to_string();
+        event.attributes = self.attributes.into();
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attribute_keys(event: &Event) -> Vec<Vec<u8>> {
+        event.attributes.iter().map(|kv| kv.key.clone()).collect()
+    }
+
+    #[test]
+    fn shuffled_attributes_should_build_identical_events() {
+        let attributes = vec![
+            (TendermintEventKey::StakingAddress, "0x01"),
+            (TendermintEventKey::StakingOpType, "unbond"),
+            (TendermintEventKey::BondedDelta, "-10"),
+            (TendermintEventKey::UnbondedDelta, "10"),
+            (TendermintEventKey::Fee, "1"),
+        ];
+        let shuffled = [3, 0, 4, 2, 1]
+            .iter()
+            .map(|&i| attributes[i])
+            .collect::<Vec<_>>();
+
+        let build = |attributes: &[(TendermintEventKey, &str)]| {
+            let mut builder = EventBuilder::new(TendermintEventType::StakingChange);
+            for (key, value) in attributes {
+                builder.attribute(*key, value.as_bytes());
+            }
+            builder.build()
+        };
+        let event = build(&attributes);
+        assert_eq!(event, build(&shuffled));
+        assert_eq!(
+            attribute_keys(&event),
+            vec![
+                b"bonded_delta".to_vec(),
+                b"fee".to_vec(),
+                b"staking_address".to_vec(),
+                b"staking_optype".to_vec(),
+                b"unbonded_delta".to_vec(),
+            ]
+        );
+    }
+
+    #[test]
+    fn attributes_with_same_key_should_keep_insertion_order() {
+        let mut builder = EventBuilder::new(TendermintEventType::ValidatorSetUpdate);
+        builder
+            .attribute(TendermintEventKey::ValidatorUpdate, "b")
+            .attribute(TendermintEventKey::Fee, "fee")
+            .attribute(TendermintEventKey::ValidatorUpdate, "a");
+        let event = builder.build();
+
+        let values = event
+            .attributes
+            .iter()
+            .map(|kv| String::from_utf8(kv.value.clone()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec!["fee", "b", "a"]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "attributes")]
+    fn too_many_attributes_should_panic_in_debug_builds() {
+        let mut builder = EventBuilder::new(TendermintEventType::Reward).with_max_attributes(1);
+        builder
+            .attribute(TendermintEventKey::MintedAmount, "1")
+            .attribute(TendermintEventKey::CoinMinted, "1");
+        builder.build();
+    }
+}
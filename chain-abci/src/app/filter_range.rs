@@ -0,0 +1,122 @@
+//! Node-local filters rolled up from the block filters of the ranges of `FILTER_RANGE_BLOCKS`
+//! blocks, so that the syncing wallets can skip the ranges without any of their view keys.
+//! The range of a block is updated at its commit (also if the block has no filter, so that
+//! the range tells which blocks it covers) and served by the `filterrange` query.
+
+use chain_core::state::tendermint::BlockHeight;
+use chain_storage::buffer::{GetKV, StoreKV};
+use chain_tx_filter::{BlockFilter, FilterRange};
+use parity_scale_codec::{Decode, Encode};
+
+/// Rolls up the filter of the block at `height` (`None` if the block has no filter) into the
+/// stored filter range of the block; a new range is started from the block if it doesn't follow
+/// the last rolled up one (e.g. the first block after a node upgrade)
+pub fn roll_up_block_filter(
+    db: &mut impl StoreKV,
+    height: BlockHeight,
+    filter: Option<&BlockFilter>,
+) {
+    // no block is committed at the genesis height (except in tests)
+    if height == BlockHeight::genesis() {
+        return;
+    }
+    let index = FilterRange::index_of(height);
+    let mut range = match load_filter_range(&*db, index) {
+        Some(range) if range.end_height.checked_add(1) == Some(height) => range,
+        _ => FilterRange::empty(height),
+    };
+    if let Err(e) = range.push_block(height, filter) {
+        // the block filters are produced with the default dimensions
+        log::error!("block filter of height {} not rolled up: {}", height, e);
+        range = FilterRange::empty(height);
+        range
+            .push_block(height, None)
+            .expect("block at the start of an empty range");
+    }
+    chain_storage::store_filter_range(db, index, range.encode());
+}
+
+/// The stored filter range which covers the block at `height` (if any)
+pub fn get_filter_range(db: &impl GetKV, height: BlockHeight) -> Option<FilterRange> {
+    load_filter_range(db, FilterRange::index_of(height)).filter(|range| range.contains(height))
+}
+
+fn load_filter_range(db: &impl GetKV, index: u64) -> Option<FilterRange> {
+    chain_storage::get_filter_range(db, index)
+        .map(|range| FilterRange::decode(&mut range.as_slice()).expect("stored filter range"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use chain_storage::buffer::{flush_storage, BufferStore, KVBuffer};
+    use chain_storage::{Storage, NUM_COLUMNS};
+    use chain_tx_filter::FILTER_RANGE_BLOCKS;
+    use secp256k1::key::{PublicKey, SecretKey};
+
+    fn view_key(seed: u8) -> PublicKey {
+        let secret = SecretKey::from_slice(&[seed; 32]).unwrap();
+        PublicKey::from_secret_key(secp256k1::SECP256K1, &secret)
+    }
+
+    fn commit_blocks(storage: &mut Storage, heights: impl Iterator<Item = u64>, seed: u8) {
+        for height in heights {
+            let mut filter = BlockFilter::default();
+            filter.add_view_key(&view_key(seed));
+            let mut buffer = KVBuffer::new();
+            roll_up_block_filter(
+                &mut BufferStore::new(&*storage, &mut buffer),
+                BlockHeight::new(height),
+                if height % 10 == 7 {
+                    Some(&filter)
+                } else {
+                    None
+                },
+            );
+            flush_storage(storage, buffer).unwrap();
+        }
+    }
+
+    #[test]
+    fn check_block_filters_are_rolled_up_per_range() {
+        let mut storage = Storage::new_db(Arc::new(kvdb_memorydb::create(NUM_COLUMNS)));
+        commit_blocks(&mut storage, 1..=FILTER_RANGE_BLOCKS, 1);
+        commit_blocks(&mut storage, FILTER_RANGE_BLOCKS + 1..=1500, 2);
+
+        let first = get_filter_range(&storage, BlockHeight::new(1)).unwrap();
+        assert!(first.is_complete());
+        assert!(first.matches_any(&[view_key(1)]));
+        assert!(!first.matches_any(&[view_key(2)]));
+
+        let second = get_filter_range(&storage, BlockHeight::new(1500)).unwrap();
+        assert_eq!(
+            (BlockHeight::new(1001), BlockHeight::new(1500)),
+            (second.start_height, second.end_height)
+        );
+        assert!(!second.is_complete());
+        assert!(second.matches_any(&[view_key(2)]));
+        assert!(!second.matches_any(&[view_key(1)]));
+
+        // not committed yet
+        assert!(get_filter_range(&storage, BlockHeight::new(1501)).is_none());
+    }
+
+    #[test]
+    fn check_range_restarts_after_missing_blocks() {
+        let mut storage = Storage::new_db(Arc::new(kvdb_memorydb::create(NUM_COLUMNS)));
+        commit_blocks(&mut storage, 1..=10, 1);
+        // blocks 11 to 20 weren't rolled up
+        commit_blocks(&mut storage, 21..=30, 2);
+
+        assert!(get_filter_range(&storage, BlockHeight::new(10)).is_none());
+        let range = get_filter_range(&storage, BlockHeight::new(21)).unwrap();
+        assert_eq!(
+            (BlockHeight::new(21), BlockHeight::new(30)),
+            (range.start_height, range.end_height)
+        );
+        assert!(range.matches_any(&[view_key(2)]));
+        assert!(!range.matches_any(&[view_key(1)]));
+    }
+}
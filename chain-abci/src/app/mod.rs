@@ -2,14 +2,24 @@
 mod macros;
 
 mod app_init;
+pub mod block_budget;
 mod commit;
+pub mod crash_report;
 mod end_block;
+mod event_builder;
+pub mod filter_range;
+pub mod proposer_stats;
 mod query;
+pub mod query_cache;
+pub mod query_stats;
 mod rewards;
 mod staking_event;
+pub mod startup_report;
+pub mod storage_check;
+pub mod tx_result_index;
+pub mod tx_trace;
 pub mod validate_tx;
 
-use abci::Pair as KVPair;
 use abci::*;
 use log::info;
 use std::convert::{TryFrom, TryInto};
@@ -20,15 +30,16 @@ pub use self::app_init::check_validators;
 pub use self::app_init::{
     get_validator_key, init_app_hash, BufferType, ChainNodeApp, ChainNodeState,
 };
-use crate::app::staking_event::StakingEvent;
+use crate::app::block_budget::BlockResources;
+use crate::app::event_builder::EventBuilder;
+use crate::app::staking_event::{StakingEvent, LEGACY_EVENT_KEYS};
 use crate::app::validate_tx::ResponseWithCodeAndLog;
 use crate::enclave_bridge::EnclaveProxy;
 use crate::staking::RewardsDistribution;
 use crate::storage::{TxAction, TxEnclaveAction, TxPublicAction};
-use chain_core::common::{TendermintEventKey, TendermintEventType, Timespec};
+use chain_core::common::{decode_tx_in_block, TendermintEventKey, TendermintEventType, Timespec};
 use chain_core::init::coin::Coin;
 use chain_core::init::config::NetworkParameters;
-use chain_core::state::account::PunishmentKind;
 use chain_core::state::tendermint::{BlockHeight, TendermintValidatorAddress, TendermintVotePower};
 use chain_core::tx::TxAux;
 use parity_scale_codec::Decode;
@@ -95,11 +106,12 @@ impl<T: EnclaveProxy + 'static> abci::Application for ChainNodeApp<T> {
         info!("received checktx request");
         let mut resp = ResponseCheckTx::new();
         match self.process_tx(req, BufferType::Mempool) {
-            Ok(_) => {
+            Ok((_, _, units)) => {
                 resp.set_code(0);
+                resp.gas_wanted = units.try_into().unwrap_or(i64::max_value());
             }
             Err(msg) => {
-                resp.set_code(1);
+                resp.set_code(msg.code());
                 resp.add_log(&msg.to_string());
                 log::warn!("check tx failed: {}", msg);
             }
@@ -111,6 +123,7 @@ impl<T: EnclaveProxy + 'static> abci::Application for ChainNodeApp<T> {
     /// state.
     fn init_chain(&mut self, _req: &RequestInitChain) -> ResponseInitChain {
         info!("received initchain request");
+        let _consensus_request = self.crash_context.consensus_request();
         ChainNodeApp::init_chain_handler(self, _req)
     }
 
@@ -122,6 +135,7 @@ impl<T: EnclaveProxy + 'static> abci::Application for ChainNodeApp<T> {
     /// commit()
     fn begin_block(&mut self, req: &RequestBeginBlock) -> ResponseBeginBlock {
         info!("received beginblock request");
+        let _consensus_request = self.crash_context.consensus_request();
         // TODO: Check security implications once https://github.com/tendermint/tendermint/issues/2653 is closed
         let header = req
             .header
@@ -129,6 +143,8 @@ impl<T: EnclaveProxy + 'static> abci::Application for ChainNodeApp<T> {
             .expect("No block header in begin block request from tendermint");
         let block_height = abci_block_height(header.height).expect("invalid block height");
         let block_time = abci_timespec(&header.time).expect("invalid block time");
+        self.crash_context.begin_block(block_height.value());
+        self.block_resources = BlockResources::default();
 
         let voters = if let Some(last_commit_info) = req.last_commit_info.as_ref() {
             // ignore the invalid items (logged)
@@ -207,10 +223,8 @@ impl<T: EnclaveProxy + 'static> abci::Application for ChainNodeApp<T> {
             );
             response.events.push(event.into());
 
-            if punishment_outcome.punishment_kind == PunishmentKind::ByzantineFault {
-                let jailed_until = punishment_outcome
-                    .jailed_until
-                    .expect("jailed until should exist when being jailed");
+            // only the byzantine faults are jailed
+            if let Some(jailed_until) = punishment_outcome.jailed_until {
                 let event = StakingEvent::Jail(
                     &punishment_outcome.staking_address,
                     jailed_until,
@@ -236,7 +250,17 @@ impl<T: EnclaveProxy + 'static> abci::Application for ChainNodeApp<T> {
             }
         }
 
+        let proposer = TendermintValidatorAddress::try_from(header.proposer_address.as_slice())
+            .map_err(|e| log::error!("invalid block proposer address: {}", e))
+            .ok()
+            .and_then(|address| last_state.staking_table.lookup_address(&address));
+        self.proposer_stats.record_block(
+            proposer,
+            last_state.staking_table.get_chosen_validators().iter(),
+        );
+
         if let Some((distributed, minted)) = self.rewards_try_distribute() {
+            self.proposer_stats.reset();
             let events = generate_reward_events(distributed, minted);
             for event in events.iter() {
                 response.events.push(event.to_owned());
@@ -250,20 +274,27 @@ impl<T: EnclaveProxy + 'static> abci::Application for ChainNodeApp<T> {
     /// state transistion.
     fn deliver_tx(&mut self, req: &RequestDeliverTx) -> ResponseDeliverTx {
         info!("received delivertx request");
+        let _consensus_request = self.crash_context.consensus_request();
         let mut resp = ResponseDeliverTx::new();
         let result = self.process_tx(req, BufferType::Consensus);
         match result {
-            Ok((txaux, tx_action)) => {
+            Ok((txaux, tx_action, units)) => {
                 let fee_amount = tx_action.fee().to_coin();
+                let staking_address = tx_action.staking_address();
+                self.tx_result_index
+                    .record(Some(txaux.tx_id()), 0, fee_amount, staking_address);
                 let tx_events = generate_tx_events(&txaux, tx_action);
 
                 resp.set_code(0);
+                resp.gas_used = units.try_into().unwrap_or(i64::max_value());
 
                 for event in tx_events.iter() {
                     resp.events.push(event.to_owned());
                 }
 
+                self.crash_context.deliver_tx(txaux.tx_id());
                 self.delivered_txs.push(txaux);
+                self.delivered_staking_addresses.extend(staking_address);
 
                 if fee_amount > Coin::zero() {
                     let rewards_pool =
@@ -274,7 +305,14 @@ impl<T: EnclaveProxy + 'static> abci::Application for ChainNodeApp<T> {
                 }
             }
             Err(msg) => {
-                resp.set_code(1);
+                if self.tx_result_index.is_enabled() {
+                    let txid = decode_tx_in_block::<TxAux>(&req.tx)
+                        .ok()
+                        .map(|txaux| txaux.tx_id());
+                    self.tx_result_index
+                        .record(txid, msg.code(), Coin::zero(), None);
+                }
+                resp.set_code(msg.code());
                 resp.add_log(&msg.to_string());
                 log::error!("deliver tx failed: {}", msg);
             }
@@ -285,12 +323,14 @@ impl<T: EnclaveProxy + 'static> abci::Application for ChainNodeApp<T> {
     /// Consensus Connection: Called at the end of the block. used to update the validator set.
     fn end_block(&mut self, req: &RequestEndBlock) -> ResponseEndBlock {
         info!("received endblock request");
+        let _consensus_request = self.crash_context.consensus_request();
         ChainNodeApp::end_block_handler(self, req)
     }
 
     /// Consensus Connection: Commit the block with the latest state from the application.
     fn commit(&mut self, _req: &RequestCommit) -> ResponseCommit {
         info!("received commit request");
+        let _consensus_request = self.crash_context.consensus_request();
         let resp = ChainNodeApp::commit_handler(self, _req);
 
         if sanity_check_enabled() {
@@ -346,18 +386,21 @@ fn generate_reward_events(distribution: RewardsDistribution, minted: Coin) -> Ve
         events.push(event);
     }
 
-    let mut reward_event = Event::new();
-    reward_event.field_type = TendermintEventType::Reward.to_string();
+    let mut reward_event = EventBuilder::new(TendermintEventType::Reward);
+
+    if LEGACY_EVENT_KEYS {
+        reward_event.attribute(
+            TendermintEventKey::CoinMinted,
+            serde_json::to_string(&minted).expect("encode coin minted failed"),
+        );
+    }
 
-    let mut minted_kvpair = KVPair::new();
-    minted_kvpair.key = TendermintEventKey::CoinMinted.into();
-    minted_kvpair.value = serde_json::to_string(&minted)
-        .expect("encode coin minted failed")
-        .as_bytes()
-        .to_owned();
-    reward_event.attributes.push(minted_kvpair);
+    reward_event.attribute(
+        TendermintEventKey::MintedAmount,
+        u64::from(minted).to_string(),
+    );
 
-    events.push(reward_event);
+    events.push(reward_event.build());
 
     events
 }
@@ -365,21 +408,14 @@ fn generate_reward_events(distribution: RewardsDistribution, minted: Coin) -> Ve
 fn generate_tx_events(txaux: &TxAux, tx_action: TxAction) -> Vec<abci::Event> {
     let mut events = Vec::new();
 
-    let mut valid_txs_event = Event::new();
-    valid_txs_event.field_type = TendermintEventType::ValidTransactions.to_string();
+    let mut valid_txs_event = EventBuilder::new(TendermintEventType::ValidTransactions);
 
-    let mut fee_kvpair = KVPair::new();
     let fee = tx_action.fee();
-    fee_kvpair.key = TendermintEventKey::Fee.into();
-    fee_kvpair.value = Vec::from(format!("{}", fee.to_coin()));
-    valid_txs_event.attributes.push(fee_kvpair);
+    valid_txs_event
+        .attribute(TendermintEventKey::Fee, format!("{}", fee.to_coin()))
+        .attribute(TendermintEventKey::TxId, hex::encode(txaux.tx_id()));
 
-    let mut txid_kvpair = KVPair::new();
-    txid_kvpair.key = TendermintEventKey::TxId.into();
-    txid_kvpair.value = Vec::from(hex::encode(txaux.tx_id()).as_bytes());
-    valid_txs_event.attributes.push(txid_kvpair);
-
-    events.push(valid_txs_event);
+    events.push(valid_txs_event.build());
 
     let maybe_tx_staking_event = generate_tx_staking_change_event(tx_action);
     if let Some(tx_staking_event) = maybe_tx_staking_event {
@@ -415,6 +451,9 @@ fn generate_tx_staking_change_event(tx_action: TxAction) -> Option<abci::Event>
             TxPublicAction::Unjail(staking_address) => {
                 Some(StakingEvent::Unjail(&staking_address).into())
             }
+            TxPublicAction::UpdateNode { fee, address } => {
+                Some(StakingEvent::UpdateNode(&address, fee).into())
+            }
         },
     }
 }
@@ -0,0 +1,215 @@
+//! Per-validator block proposer statistics (actual vs. expected number of proposed blocks),
+//! collected for analysing the fairness of the vote-weighted rewards.
+//! The statistics are node-local (neither a part of the consensus state nor persisted):
+//! they are reset at each rewards distribution, so that the numbers align with the reward
+//! periods, and when the node is (re)started, in which case the current period only covers
+//! the blocks since the start (see `ProposerPeriodStats::from_period_start`).
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use chain_core::state::account::{NodeEndpoint, StakedStateAddress};
+use chain_core::state::tendermint::TendermintVotePower;
+
+/// Query path which returns the collected proposer statistics and the endpoints announced by
+/// the active council nodes (as JSON, see `ValidatorsResponse`)
+pub const VALIDATORS_PATH: &str = "validators";
+
+/// Response of the `VALIDATORS_PATH` query, which combines the data of two sources in one JSON
+/// object: the fields of the (node-local) `ProposerStats` and the endpoints announced in the
+/// council node metadata of the committed state:
+///
+/// ```json
+/// {"current_period": {..}, "last_period": {..} or null,
+///  "announced_endpoints": {"<staking address>": ["<host:port>", ..]}}
+/// ```
+#[derive(Debug, Serialize)]
+pub struct ValidatorsResponse<'a> {
+    #[serde(flatten)]
+    pub proposer_stats: &'a ProposerStats,
+    /// informational only (not used in consensus)
+    pub announced_endpoints: BTreeMap<StakedStateAddress, Vec<NodeEndpoint>>,
+}
+
+/// Proposer statistics of a single validator
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValidatorProposerStats {
+    /// number of the proposed blocks
+    pub proposed: u64,
+    /// expected number of the proposed blocks: sum of the validator's shares of the total voting
+    /// power in the blocks it was in the active set
+    pub expected: f64,
+    /// `proposed / expected` (`None` until a proposal is expected)
+    pub proposal_ratio: Option<f64>,
+}
+
+impl ValidatorProposerStats {
+    fn update_ratio(&mut self) {
+        self.proposal_ratio = if self.expected > 0.0 {
+            Some(self.proposed as f64 / self.expected)
+        } else {
+            None
+        };
+    }
+}
+
+/// Proposer statistics of a rewards period
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProposerPeriodStats {
+    /// whether the statistics started at a rewards distribution (`false` if they started when
+    /// the node was (re)started, so the earlier blocks of the period aren't accounted)
+    pub from_period_start: bool,
+    /// number of the accounted blocks
+    pub blocks: u64,
+    /// number of the blocks whose proposer wasn't in the active validator set
+    pub skipped: u64,
+    /// statistics of the validators which were in the active set
+    pub validators: BTreeMap<StakedStateAddress, ValidatorProposerStats>,
+}
+
+/// Proposer statistics of the current and of the last finished rewards period
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProposerStats {
+    /// since the last rewards distribution
+    pub current_period: ProposerPeriodStats,
+    /// between the last two rewards distributions
+    pub last_period: Option<ProposerPeriodStats>,
+}
+
+impl ProposerStats {
+    /// Accounts a block
+    ///
+    /// # Arguments
+    ///
+    /// - `proposer`: staking address of the block proposer (`None` if it isn't known)
+    /// - `active_validators`: voting powers of the validators in the active set
+    pub fn record_block<'a>(
+        &mut self,
+        proposer: Option<&StakedStateAddress>,
+        active_validators: impl Iterator<Item = (&'a StakedStateAddress, &'a TendermintVotePower)>
+            + Clone,
+    ) {
+        let period = &mut self.current_period;
+        period.blocks += 1;
+
+        let total_power = active_validators
+            .clone()
+            .map(|(_, power)| i64::from(*power) as f64)
+            .sum::<f64>();
+        let in_active_set = proposer.map_or(false, |proposer| {
+            active_validators
+                .clone()
+                .any(|(address, _)| address == proposer)
+        });
+        if !in_active_set || total_power <= 0.0 {
+            log::warn!(
+                "block proposer ({:?}) is not in the active validator set, skipped in proposer stats",
+                proposer
+            );
+            period.skipped += 1;
+            return;
+        }
+
+        for (address, power) in active_validators {
+            let state = period.validators.entry(*address).or_default();
+            state.expected += i64::from(*power) as f64 / total_power;
+            if Some(address) == proposer {
+                state.proposed += 1;
+            }
+            state.update_ratio();
+        }
+    }
+
+    /// Finishes the current period (called at rewards distribution)
+    pub fn reset(&mut self) {
+        let next_period = ProposerPeriodStats {
+            from_period_start: true,
+            ..Default::default()
+        };
+        self.last_period = Some(std::mem::replace(&mut self.current_period, next_period));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain_core::init::address::RedeemAddress;
+
+    fn address(byte: u8) -> StakedStateAddress {
+        StakedStateAddress::BasicRedeem(RedeemAddress::from([byte; 20]))
+    }
+
+    fn power(value: i64) -> TendermintVotePower {
+        TendermintVotePower::new(value).unwrap()
+    }
+
+    #[test]
+    fn check_fixed_proposer_rotation() {
+        let (a, b, c) = (address(1), address(2), address(3));
+        let active: BTreeMap<StakedStateAddress, TendermintVotePower> =
+            vec![(a, power(10)), (b, power(10)), (c, power(20))]
+                .into_iter()
+                .collect();
+
+        // weighted round robin: a, c, b, c, ...; except that `b` misses its proposals in the
+        // second half (`c` proposes instead)
+        let mut stats = ProposerStats::default();
+        for i in 0..40 {
+            let proposer = match i % 4 {
+                0 => &a,
+                2 if i < 20 => &b,
+                _ => &c,
+            };
+            stats.record_block(Some(proposer), active.iter());
+        }
+
+        let period = &stats.current_period;
+        assert_eq!(period.blocks, 40);
+        assert_eq!(period.skipped, 0);
+        assert_eq!(period.validators[&a].proposed, 10);
+        assert_eq!(period.validators[&b].proposed, 5);
+        assert_eq!(period.validators[&c].proposed, 25);
+        assert!((period.validators[&a].expected - 10.0).abs() < 1e-9);
+        assert!((period.validators[&c].expected - 20.0).abs() < 1e-9);
+        assert!((period.validators[&a].proposal_ratio.unwrap() - 1.0).abs() < 1e-9);
+        assert!((period.validators[&b].proposal_ratio.unwrap() - 0.5).abs() < 1e-9);
+        assert!((period.validators[&c].proposal_ratio.unwrap() - 1.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn check_unknown_proposer_and_reset() {
+        let (a, b) = (address(1), address(2));
+        let active: BTreeMap<StakedStateAddress, TendermintVotePower> =
+            vec![(a, power(10))].into_iter().collect();
+
+        let mut stats = ProposerStats::default();
+        stats.record_block(Some(&a), active.iter());
+        stats.record_block(Some(&b), active.iter());
+        stats.record_block(None, active.iter());
+        assert_eq!(stats.current_period.blocks, 3);
+        assert_eq!(stats.current_period.skipped, 2);
+        assert!(!stats.current_period.validators.contains_key(&b));
+        assert_eq!(stats.current_period.validators[&a].proposed, 1);
+        assert!((stats.current_period.validators[&a].expected - 1.0).abs() < 1e-9);
+
+        // the node started in the middle of the period
+        assert!(!stats.current_period.from_period_start);
+        stats.reset();
+        assert_eq!(
+            stats.current_period,
+            ProposerPeriodStats {
+                from_period_start: true,
+                ..Default::default()
+            }
+        );
+        let last_period = stats.last_period.as_ref().unwrap();
+        assert!(!last_period.from_period_start);
+        assert_eq!(last_period.blocks, 3);
+        assert_eq!(last_period.validators[&a].proposed, 1);
+
+        stats.record_block(Some(&a), active.iter());
+        assert_eq!(stats.current_period.validators[&a].proposed, 1);
+        assert_eq!(stats.last_period.unwrap().blocks, 3);
+    }
+}
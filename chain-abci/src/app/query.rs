@@ -1,15 +1,28 @@
 use std::convert::{TryFrom, TryInto};
+use std::time::Instant;
 
+use super::filter_range::get_filter_range;
+use super::proposer_stats::{ValidatorsResponse, VALIDATORS_PATH};
+use super::query_cache::QUERY_CACHE_PATH;
+use super::query_stats::{QueryPath, QUERY_STATS_PATH, QUERY_STATS_RESET_PATH};
+use super::tx_trace::{
+    get_tx_traces, TX_TRACE_ARM_PATH, TX_TRACE_DISARM_PATH, TX_TRACE_PATH_PREFIX,
+};
 use super::ChainNodeApp;
 use crate::enclave_bridge::EnclaveProxy;
 use abci::*;
 use chain_core::common::{MerkleTree, Proof as MerkleProof, H256, HASH_SIZE_256};
 use chain_core::state::account::StakedStateAddress;
-use chain_core::state::tendermint::BlockHeight;
+use chain_core::state::tendermint::{BlockHeight, TendermintValidatorAddress};
 use chain_core::state::ChainState;
 use chain_core::tx::data::TXID_HASH_ID;
+use chain_core::tx::result::{
+    TX_RESULT_DISABLED_CODE, TX_RESULT_NOT_FOUND_CODE, TX_RESULT_NOT_FOUND_LOG,
+    TX_RESULT_PATH_PREFIX,
+};
 use chain_storage::jellyfish::get_with_proof;
 use chain_storage::LookupItem;
+use chain_tx_filter::FILTER_RANGE_PATH;
 use parity_scale_codec::{Decode, Encode};
 
 /// Generate generic ABCI ProofOp for the witness
@@ -73,7 +86,19 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
 
     /// Responds to query requests -- note that path is hex-encoded in the original request on the client side
     /// e.g. "store" == 0x73746f7265.
+    /// Every served query is accounted in `query_stats` under its path.
+    /// Queries of the committed state are served from `query_cache` if possible.
     pub fn query_handler(&self, _req: &RequestQuery) -> ResponseQuery {
+        let start = Instant::now();
+        let resp = self
+            .query_cache
+            .get_or_compute(_req, || self.handle_query(_req));
+        self.query_stats
+            .record(QueryPath::from_path(&_req.path), resp.code, start.elapsed());
+        resp
+    }
+
+    fn handle_query(&self, _req: &RequestQuery) -> ResponseQuery {
         let mut resp = ResponseQuery::new();
 
         // "When Tendermint connects to a peer, it sends two queries to the ABCI application using the following paths, with no additional data:
@@ -123,14 +148,24 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
                             } else {
                                 req_height
                             };
-                        // note this should not crash if Tendermint delivers all blocks with height in order
+                        // note this should be found if Tendermint delivers all blocks with height in order
                         // TODO: invariant / sanity check in rust-abci?
-                        let app_hash = self.storage.get_historical_app_hash(height).unwrap();
-                        let data = self
-                            .storage
-                            .lookup_item(LookupItem::TxsMerkle, &app_hash)
-                            .unwrap();
-                        let tree = MerkleTree::decode(&mut data.as_slice()).expect("merkle tree");
+                        let data =
+                            self.storage
+                                .get_historical_app_hash(height)
+                                .and_then(|app_hash| {
+                                    self.storage.lookup_item(LookupItem::TxsMerkle, &app_hash)
+                                });
+                        let tree = match data {
+                            Some(data) => {
+                                MerkleTree::decode(&mut data.as_slice()).expect("merkle tree")
+                            }
+                            None => {
+                                resp.log += "proof error: merkle tree not found";
+                                resp.code = 2;
+                                return resp;
+                            }
+                        };
 
                         // TODO: Change this in future to include individual ops?
                         let proof_ops = match tree.generate_proof(txid) {
@@ -225,11 +260,8 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
                 if self.tx_query_address.is_none() {
                     resp.code = 1;
                     resp.log += "tx query address not set / state is not persisted";
-                } else {
-                    let value = self.storage.get_historical_state(
-                        _req.height.try_into().expect("Invalid block height"),
-                    );
-                    match value {
+                } else if let Ok(height) = _req.height.try_into() {
+                    match self.storage.get_historical_state(height) {
                         Some(value) => {
                             if let Ok(state) = ChainState::decode(&mut value.to_vec().as_slice()) {
                                 resp.value = serde_json::to_string(&state).unwrap().into_bytes();
@@ -243,19 +275,47 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
                             resp.code = 2;
                         }
                     }
+                } else {
+                    resp.log += "invalid block height";
+                    resp.code = 3;
                 }
             }
             "council-nodes" => {
-                let council_nodes = &self
-                    .last_state
-                    .as_ref()
-                    .expect("Missing last_state: init chain was not called")
-                    .staking_table
-                    .list_council_nodes(&self.staking_getter_committed());
+                if let Some(state) = &self.last_state {
+                    let council_nodes = &state
+                        .staking_table
+                        .list_council_nodes(&self.staking_getter_committed());
 
-                resp.value = serde_json::to_string(&council_nodes)
-                    .expect("Unable to serialize validator metadata into json")
-                    .into_bytes();
+                    resp.value = serde_json::to_string(&council_nodes)
+                        .expect("Unable to serialize validator metadata into json")
+                        .into_bytes();
+                } else {
+                    resp.log += "node not correctly restored / initialized";
+                    resp.code = 1;
+                }
+            }
+            "validator_owner" => {
+                // staking address which (currently or previously) used the validator address
+                match (
+                    &self.last_state,
+                    TendermintValidatorAddress::try_from(_req.data.as_slice()),
+                ) {
+                    (Some(state), Ok(validator_address)) => {
+                        let owner = state
+                            .staking_table
+                            .lookup_address(&validator_address)
+                            .copied();
+                        resp.value = owner.encode();
+                    }
+                    (None, _) => {
+                        resp.log += "node not correctly restored / initialized";
+                        resp.code = 1;
+                    }
+                    (_, Err(_)) => {
+                        resp.log += "invalid validator address";
+                        resp.code = 3;
+                    }
+                }
             }
             "sealed" => {
                 self.lookup(
@@ -265,6 +325,76 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
                     "sealed log not found",
                 );
             }
+            VALIDATORS_PATH => {
+                let announced_endpoints = self
+                    .last_state
+                    .as_ref()
+                    .map(|state| {
+                        state
+                            .staking_table
+                            .list_council_nodes(&self.staking_getter_committed())
+                            .into_iter()
+                            .map(|node| (node.staking_address, node.announced_endpoints))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let validators = ValidatorsResponse {
+                    proposer_stats: &self.proposer_stats,
+                    announced_endpoints,
+                };
+                resp.value = serde_json::to_string(&validators)
+                    .expect("Unable to serialize proposer stats into json")
+                    .into_bytes();
+            }
+            QUERY_STATS_PATH => {
+                resp.value = serde_json::to_string(&self.query_stats.snapshot())
+                    .expect("Unable to serialize query stats into json")
+                    .into_bytes();
+            }
+            QUERY_CACHE_PATH => {
+                resp.value = serde_json::to_string(&self.query_cache.stats())
+                    .expect("Unable to serialize query cache stats into json")
+                    .into_bytes();
+            }
+            QUERY_STATS_RESET_PATH => {
+                if self.query_stats_admin {
+                    self.query_stats.reset();
+                } else {
+                    resp.log += "query stats admin path not enabled";
+                    resp.code = 1;
+                }
+            }
+            FILTER_RANGE_PATH => {
+                // the range of the latest committed block by default
+                let height = match _req.height.try_into() {
+                    Ok(height) if height == BlockHeight::genesis() => self
+                        .last_state
+                        .as_ref()
+                        .map(|state| state.last_block_height),
+                    Ok(height) => Some(height),
+                    Err(_) => None,
+                };
+                match height.and_then(|height| get_filter_range(&self.storage, height)) {
+                    Some(range) => {
+                        resp.value = range.encode();
+                    }
+                    None => {
+                        resp.log += "filter range not found";
+                        resp.code = 1;
+                    }
+                }
+            }
+            path if path.starts_with(TX_RESULT_PATH_PREFIX) => {
+                self.handle_tx_result_query(&mut resp, &path[TX_RESULT_PATH_PREFIX.len()..]);
+            }
+            path if path.starts_with(TX_TRACE_PATH_PREFIX) => {
+                if self.query_stats_admin {
+                    self.handle_trace_query(&mut resp, path, &_req.data[..]);
+                } else {
+                    resp.log += "tx trace admin path not enabled";
+                    resp.code = 1;
+                }
+            }
             _ => {
                 resp.log += "invalid path";
                 resp.code = 1;
@@ -272,6 +402,65 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
         }
         resp
     }
+
+    /// `txresult/<hex txid>`: the indexed result of the delivered transaction (SCALE-encoded `TxResult`)
+    fn handle_tx_result_query(&self, resp: &mut ResponseQuery, hex_txid: &str) {
+        if !self.tx_result_index.is_enabled() {
+            resp.log += "tx results are not indexed";
+            resp.code = TX_RESULT_DISABLED_CODE;
+            return;
+        }
+        let txid = hex::decode(hex_txid).unwrap_or_default();
+        if let Some(txid) = get_key(resp, &txid) {
+            match chain_storage::get_tx_result(&self.storage, &txid) {
+                Some(result) => {
+                    resp.value = result.encode();
+                }
+                None => {
+                    resp.log += TX_RESULT_NOT_FOUND_LOG;
+                    resp.code = TX_RESULT_NOT_FOUND_CODE;
+                }
+            }
+        }
+    }
+
+    /// Admin (local-only) paths of the transaction tracing:
+    /// `trace/arm` (data: txid), `trace/disarm` and `trace/<hex txid>`
+    fn handle_trace_query(&self, resp: &mut ResponseQuery, path: &str, data: &[u8]) {
+        match path {
+            TX_TRACE_ARM_PATH => {
+                if let Some(txid) = get_key(resp, data) {
+                    self.tx_tracer.arm(txid);
+                }
+            }
+            TX_TRACE_DISARM_PATH => {
+                self.tx_tracer.disarm();
+            }
+            _ => {
+                let txid = hex::decode(&path[TX_TRACE_PATH_PREFIX.len()..]).unwrap_or_default();
+                if let Some(txid) = get_key(resp, &txid) {
+                    let traces = get_tx_traces(&self.storage, &txid).and_then(|traces| {
+                        traces
+                            .map(|t| serde_json::to_vec(&t).map_err(std::io::Error::from))
+                            .transpose()
+                    });
+                    match traces {
+                        Ok(Some(value)) => {
+                            resp.value = value;
+                        }
+                        Ok(None) => {
+                            resp.log += "tx trace not found";
+                            resp.code = 1;
+                        }
+                        Err(e) => {
+                            resp.log += &format!("failed to read tx trace: {}", e);
+                            resp.code = 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
 }
 
 fn into_proof_op<T: Encode>(root_hash: H256, proof: MerkleProof<T>) -> ProofOp {
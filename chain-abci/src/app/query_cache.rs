@@ -0,0 +1,305 @@
+//! Node-local cache of ABCI query responses, so that repeated identical queries
+//! (e.g. clients polling the same account) don't hit the storage every time.
+//! Entries are keyed by the query (path, data, height, prove) and the last committed block height;
+//! the whole cache is invalidated on each commit, so only the committed state is ever served.
+//! Queries which may depend on the uncommitted (mempool or in-block) state bypass the cache.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use abci::{RequestQuery, ResponseQuery};
+use protobuf::Message;
+use serde::{Deserialize, Serialize};
+
+use chain_core::state::tendermint::BlockHeight;
+use chain_tx_filter::FILTER_RANGE_PATH;
+
+/// Query path which returns the cache statistics (as JSON)
+pub const QUERY_CACHE_PATH: &str = "query_cache";
+
+/// Default maximal number of the cached responses
+pub const DEFAULT_QUERY_CACHE_ENTRIES: usize = 1024;
+/// Default maximal total size (in bytes) of the cached responses
+pub const DEFAULT_QUERY_CACHE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Paths which only read the committed state (anything else bypasses the cache)
+const CACHEABLE_PATHS: [&str; 9] = [
+    "store",
+    "meta",
+    "witness",
+    "merkle",
+    "account",
+    "staking",
+    "state",
+    "sealed",
+    FILTER_RANGE_PATH,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct QueryCacheKey {
+    path: String,
+    data: Vec<u8>,
+    height: i64,
+    prove: bool,
+}
+
+impl QueryCacheKey {
+    fn new(req: &RequestQuery) -> Self {
+        QueryCacheKey {
+            path: req.path.clone(),
+            data: req.data.clone(),
+            height: req.height,
+            prove: req.prove,
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.path.len() + self.data.len()
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    response: ResponseQuery,
+    size: usize,
+    last_used: u64,
+}
+
+/// LRU map of the responses served at one committed block height
+#[derive(Debug, Default)]
+struct CacheInner {
+    committed_height: u64,
+    entries: HashMap<QueryCacheKey, CacheEntry>,
+    /// `last_used` tick -> key, the least recently used entry is the first one
+    lru: BTreeMap<u64, QueryCacheKey>,
+    tick: u64,
+    bytes: usize,
+}
+
+impl CacheInner {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn get(&mut self, key: &QueryCacheKey) -> Option<ResponseQuery> {
+        let tick = self.next_tick();
+        let entry = self.entries.get_mut(key)?;
+        self.lru.remove(&entry.last_used);
+        entry.last_used = tick;
+        self.lru.insert(tick, key.clone());
+        Some(entry.response.clone())
+    }
+
+    fn remove(&mut self, key: &QueryCacheKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.lru.remove(&entry.last_used);
+            self.bytes -= entry.size;
+        }
+    }
+
+    fn insert(
+        &mut self,
+        key: QueryCacheKey,
+        response: ResponseQuery,
+        max_entries: usize,
+        max_bytes: usize,
+    ) {
+        let size = key.size() + response.compute_size() as usize;
+        if size > max_bytes || max_entries == 0 {
+            return;
+        }
+        self.remove(&key);
+        while self.entries.len() >= max_entries || self.bytes + size > max_bytes {
+            let oldest = match self.lru.values().next() {
+                Some(key) => key.clone(),
+                None => break,
+            };
+            self.remove(&oldest);
+        }
+        let last_used = self.next_tick();
+        self.lru.insert(last_used, key.clone());
+        self.bytes += size;
+        self.entries.insert(
+            key,
+            CacheEntry {
+                response,
+                size,
+                last_used,
+            },
+        );
+    }
+
+    fn clear(&mut self, committed_height: BlockHeight) {
+        self.committed_height = committed_height.value();
+        self.entries.clear();
+        self.lru.clear();
+        self.bytes = 0;
+    }
+}
+
+/// Statistics of the query cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryCacheStats {
+    /// number of queries served from the cache
+    pub hits: u64,
+    /// number of cacheable queries which had to be served from the storage
+    pub misses: u64,
+    /// number of the cached responses
+    pub entries: usize,
+    /// total size of the cached responses (in bytes)
+    pub bytes: usize,
+    /// block height the cached responses were computed at
+    pub committed_height: u64,
+}
+
+/// Cache of the query responses, bounded by the number of entries and their total size
+#[derive(Debug)]
+pub struct QueryCache {
+    inner: Mutex<CacheInner>,
+    max_entries: usize,
+    max_bytes: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl Default for QueryCache {
+    fn default() -> Self {
+        QueryCache::new(DEFAULT_QUERY_CACHE_ENTRIES, DEFAULT_QUERY_CACHE_BYTES)
+    }
+}
+
+impl QueryCache {
+    /// Creates an empty cache with the given bounds
+    pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+        QueryCache {
+            inner: Mutex::new(CacheInner::default()),
+            max_entries,
+            max_bytes,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Whether the responses to queries of the given path can be cached
+    pub fn is_cacheable(path: &str) -> bool {
+        CACHEABLE_PATHS.contains(&path)
+    }
+
+    /// Returns the cached response to the query, or computes it with `compute` (and caches it).
+    /// The lock isn't held while computing the response; if a block was committed in the meantime,
+    /// the computed response is returned but not cached.
+    pub fn get_or_compute<F>(&self, req: &RequestQuery, compute: F) -> ResponseQuery
+    where
+        F: FnOnce() -> ResponseQuery,
+    {
+        if !Self::is_cacheable(&req.path) {
+            return compute();
+        }
+        let key = QueryCacheKey::new(req);
+        let committed_height = {
+            let mut inner = self.inner.lock().expect("query cache lock poisoned");
+            if let Some(response) = inner.get(&key) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return response;
+            }
+            inner.committed_height
+        };
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let response = compute();
+
+        let mut inner = self.inner.lock().expect("query cache lock poisoned");
+        if inner.committed_height == committed_height {
+            inner.insert(key, response.clone(), self.max_entries, self.max_bytes);
+        }
+        response
+    }
+
+    /// Drops all the cached responses (called on commit of the block at `committed_height`)
+    pub fn invalidate(&self, committed_height: BlockHeight) {
+        self.inner
+            .lock()
+            .expect("query cache lock poisoned")
+            .clear(committed_height);
+    }
+
+    /// Current statistics
+    pub fn stats(&self) -> QueryCacheStats {
+        let inner = self.inner.lock().expect("query cache lock poisoned");
+        QueryCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: inner.entries.len(),
+            bytes: inner.bytes,
+            committed_height: inner.committed_height,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(data: u8) -> RequestQuery {
+        let mut req = RequestQuery::new();
+        req.path = "account".to_owned();
+        req.data = vec![data; 20];
+        req
+    }
+
+    fn response(size: usize) -> ResponseQuery {
+        let mut resp = ResponseQuery::new();
+        resp.value = vec![0; size];
+        resp
+    }
+
+    #[test]
+    fn cache_should_evict_least_recently_used() {
+        let cache = QueryCache::new(2, DEFAULT_QUERY_CACHE_BYTES);
+        cache.get_or_compute(&request(1), || response(1));
+        cache.get_or_compute(&request(2), || response(1));
+        // touch 1, so that 2 is evicted
+        cache.get_or_compute(&request(1), || unreachable!());
+        cache.get_or_compute(&request(3), || response(1));
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 3, 2));
+        cache.get_or_compute(&request(1), || unreachable!());
+        cache.get_or_compute(&request(3), || unreachable!());
+        let resp = cache.get_or_compute(&request(2), || response(5));
+        assert_eq!(resp.value.len(), 5);
+    }
+
+    #[test]
+    fn cache_should_respect_byte_bound() {
+        let cache = QueryCache::new(DEFAULT_QUERY_CACHE_ENTRIES, 200);
+        cache.get_or_compute(&request(1), || response(100));
+        cache.get_or_compute(&request(2), || response(100));
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 1);
+        assert!(stats.bytes <= 200);
+
+        // larger than the whole cache: not cached
+        cache.get_or_compute(&request(3), || response(300));
+        assert_eq!(cache.stats().entries, 1);
+    }
+
+    #[test]
+    fn cache_should_be_invalidated_and_bypassed() {
+        let cache = QueryCache::default();
+        cache.get_or_compute(&request(1), || response(1));
+        cache.invalidate(BlockHeight::new(1));
+        assert_eq!(cache.stats().entries, 0);
+        let resp = cache.get_or_compute(&request(1), || response(2));
+        assert_eq!(resp.value.len(), 2);
+
+        let mut req = request(1);
+        req.path = "council-nodes".to_owned();
+        cache.get_or_compute(&req, || response(1));
+        cache.get_or_compute(&req, || response(1));
+        assert_eq!(cache.stats().entries, 1);
+        assert_eq!(cache.stats().misses, 2);
+    }
+}
@@ -0,0 +1,259 @@
+//! Per-path accounting of the served ABCI queries, so that hot or abusive query paths
+//! can be spotted on public (sentry) nodes.
+//! Counters are plain atomics indexed by a fixed set of paths, recording a query doesn't allocate.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::proposer_stats::VALIDATORS_PATH;
+use super::query_cache::QUERY_CACHE_PATH;
+use super::tx_trace::TX_TRACE_PATH_PREFIX;
+use chain_core::tx::result::TX_RESULT_PATH_PREFIX;
+use chain_tx_filter::FILTER_RANGE_PATH;
+
+/// Query path which returns the collected statistics (as JSON)
+pub const QUERY_STATS_PATH: &str = "query_stats";
+/// Admin query path which resets the collected statistics
+pub const QUERY_STATS_RESET_PATH: &str = "query_stats/reset";
+
+/// Query paths accounted separately (anything unknown is accounted as `other`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryPath {
+    /// peer filtering queries from Tendermint (`p2p/...`)
+    P2p,
+    /// `txquery`
+    TxQuery,
+    /// `tdbe`
+    Tdbe,
+    /// `store`
+    Store,
+    /// `meta`
+    Meta,
+    /// `witness`
+    Witness,
+    /// `merkle`
+    Merkle,
+    /// `account`
+    Account,
+    /// `staking`
+    Staking,
+    /// `state`
+    State,
+    /// `council-nodes`
+    CouncilNodes,
+    /// `validator_owner`
+    ValidatorOwner,
+    /// `sealed`
+    Sealed,
+    /// `validators`
+    Validators,
+    /// `query_stats`, `query_stats/reset` and `query_cache`
+    QueryStats,
+    /// `trace/...`
+    TxTrace,
+    /// `txresult/...`
+    TxResult,
+    /// `filterrange`
+    FilterRange,
+    /// any other (invalid) path
+    Other,
+}
+
+const QUERY_PATH_COUNT: usize = 19;
+
+impl QueryPath {
+    /// All the accounted paths
+    pub const ALL: [QueryPath; QUERY_PATH_COUNT] = [
+        QueryPath::P2p,
+        QueryPath::TxQuery,
+        QueryPath::Tdbe,
+        QueryPath::Store,
+        QueryPath::Meta,
+        QueryPath::Witness,
+        QueryPath::Merkle,
+        QueryPath::Account,
+        QueryPath::Staking,
+        QueryPath::State,
+        QueryPath::CouncilNodes,
+        QueryPath::ValidatorOwner,
+        QueryPath::Sealed,
+        QueryPath::Validators,
+        QueryPath::QueryStats,
+        QueryPath::TxTrace,
+        QueryPath::TxResult,
+        QueryPath::FilterRange,
+        QueryPath::Other,
+    ];
+
+    /// Classifies the path of a query request
+    pub fn from_path(path: &str) -> Self {
+        if path.starts_with("/p2p") || path.starts_with("p2p") {
+            return QueryPath::P2p;
+        }
+        if path.starts_with(TX_TRACE_PATH_PREFIX) {
+            return QueryPath::TxTrace;
+        }
+        if path.starts_with(TX_RESULT_PATH_PREFIX) {
+            return QueryPath::TxResult;
+        }
+        match path {
+            "txquery" => QueryPath::TxQuery,
+            "tdbe" => QueryPath::Tdbe,
+            "store" => QueryPath::Store,
+            "meta" => QueryPath::Meta,
+            "witness" => QueryPath::Witness,
+            "merkle" => QueryPath::Merkle,
+            "account" => QueryPath::Account,
+            "staking" => QueryPath::Staking,
+            "state" => QueryPath::State,
+            "council-nodes" => QueryPath::CouncilNodes,
+            "validator_owner" => QueryPath::ValidatorOwner,
+            "sealed" => QueryPath::Sealed,
+            VALIDATORS_PATH => QueryPath::Validators,
+            QUERY_STATS_PATH | QUERY_STATS_RESET_PATH | QUERY_CACHE_PATH => QueryPath::QueryStats,
+            FILTER_RANGE_PATH => QueryPath::FilterRange,
+            _ => QueryPath::Other,
+        }
+    }
+
+    /// Name used in the statistics
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QueryPath::P2p => "p2p",
+            QueryPath::TxQuery => "txquery",
+            QueryPath::Tdbe => "tdbe",
+            QueryPath::Store => "store",
+            QueryPath::Meta => "meta",
+            QueryPath::Witness => "witness",
+            QueryPath::Merkle => "merkle",
+            QueryPath::Account => "account",
+            QueryPath::Staking => "staking",
+            QueryPath::State => "state",
+            QueryPath::CouncilNodes => "council-nodes",
+            QueryPath::ValidatorOwner => "validator_owner",
+            QueryPath::Sealed => "sealed",
+            QueryPath::Validators => VALIDATORS_PATH,
+            QueryPath::QueryStats => "query_stats",
+            QueryPath::TxTrace => TX_TRACE_PATH_PREFIX,
+            QueryPath::TxResult => TX_RESULT_PATH_PREFIX,
+            QueryPath::FilterRange => FILTER_RANGE_PATH,
+            QueryPath::Other => "other",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, Default)]
+struct PathCounters {
+    count: AtomicU64,
+    errors: AtomicU64,
+    latency_micros: AtomicU64,
+}
+
+/// Statistics of a single query path
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryPathStats {
+    /// number of served queries
+    pub count: u64,
+    /// number of queries responded with a non-zero code
+    pub errors: u64,
+    /// accumulated time spent in serving the queries (in microseconds)
+    pub total_latency_us: u64,
+}
+
+/// Counters of the served queries per path
+#[derive(Debug, Default)]
+pub struct QueryStats {
+    paths: [PathCounters; QUERY_PATH_COUNT],
+}
+
+impl QueryStats {
+    /// Accounts a served query
+    pub fn record(&self, path: QueryPath, code: u32, elapsed: Duration) {
+        let counters = &self.paths[path.index()];
+        counters.count.fetch_add(1, Ordering::Relaxed);
+        if code != 0 {
+            counters.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        counters.latency_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    /// Statistics of the given path
+    pub fn get(&self, path: QueryPath) -> QueryPathStats {
+        let counters = &self.paths[path.index()];
+        QueryPathStats {
+            count: counters.count.load(Ordering::Relaxed),
+            errors: counters.errors.load(Ordering::Relaxed),
+            total_latency_us: counters.latency_micros.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Statistics of all the paths which were queried at least once
+    pub fn snapshot(&self) -> BTreeMap<&'static str, QueryPathStats> {
+        QueryPath::ALL
+            .iter()
+            .map(|path| (path.as_str(), self.get(*path)))
+            .filter(|(_, stats)| stats.count > 0)
+            .collect()
+    }
+
+    /// Resets all the counters
+    pub fn reset(&self) {
+        for counters in self.paths.iter() {
+            counters.count.store(0, Ordering::Relaxed);
+            counters.errors.store(0, Ordering::Relaxed);
+            counters.latency_micros.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_indices_should_match_the_order() {
+        for (i, path) in QueryPath::ALL.iter().enumerate() {
+            assert_eq!(path.index(), i);
+            if *path != QueryPath::P2p && *path != QueryPath::Other {
+                assert_eq!(QueryPath::from_path(path.as_str()), *path);
+            }
+        }
+        assert_eq!(QueryPath::from_path("/p2p/filter/id/abc"), QueryPath::P2p);
+        assert_eq!(QueryPath::from_path("validators"), QueryPath::Validators);
+        assert_eq!(QueryPath::from_path("trace/arm"), QueryPath::TxTrace);
+        assert_eq!(QueryPath::from_path("txresult/00"), QueryPath::TxResult);
+        assert_eq!(QueryPath::from_path("unknown"), QueryPath::Other);
+    }
+
+    #[test]
+    fn stats_should_accumulate_and_reset() {
+        let stats = QueryStats::default();
+        stats.record(QueryPath::Account, 0, Duration::from_micros(10));
+        stats.record(QueryPath::Account, 3, Duration::from_micros(5));
+        stats.record(QueryPath::Store, 0, Duration::from_micros(1));
+
+        assert_eq!(
+            stats.get(QueryPath::Account),
+            QueryPathStats {
+                count: 2,
+                errors: 1,
+                total_latency_us: 15,
+            }
+        );
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot["store"].count, 1);
+
+        stats.reset();
+        assert!(stats.snapshot().is_empty());
+    }
+}
@@ -9,6 +9,13 @@ use chain_core::init::coin::Coin;
 use chain_core::state::account::{CouncilNodeMeta, PunishmentKind, StakedStateAddress};
 use chain_core::tx::fee::Fee;
 
+use crate::app::event_builder::EventBuilder;
+
+/// Whether balance changes are still emitted under the legacy keys (balance entries of
+/// `staking_diff` and `minted`) alongside the typed amount keys.
+/// Indexers should migrate to the typed keys before this is turned off by default.
+pub(crate) const LEGACY_EVENT_KEYS: bool = cfg!(feature = "legacy-event-keys");
+
 pub(crate) enum StakingEvent<'a> {
     Deposit(&'a StakedStateAddress, Coin),
     Unbond(&'a StakedStateAddress, Coin, Timespec, Fee),
@@ -18,11 +25,12 @@ pub(crate) enum StakingEvent<'a> {
     Jail(&'a StakedStateAddress, Timespec, PunishmentKind),
     Slash(&'a StakedStateAddress, Coin, Coin, PunishmentKind),
     Unjail(&'a StakedStateAddress),
+    UpdateNode(&'a StakedStateAddress, Fee),
 }
 
 impl<'a> From<StakingEvent<'a>> for Event {
     fn from(event: StakingEvent) -> Self {
-        let mut builder = StakingEventBuilder::default();
+        let mut builder = StakingEventBuilder::new();
 
         match event {
             StakingEvent::Deposit(staking_address, deposit_amount) => {
@@ -55,29 +63,38 @@ impl<'a> From<StakingEvent<'a>> for Event {
                 punishment_kind,
             ),
             StakingEvent::Unjail(staking_address) => builder.unjail(staking_address),
+            StakingEvent::UpdateNode(staking_address, fee) => {
+                builder.update_node(staking_address, fee)
+            }
         }
 
-        builder.to_event()
+        builder.event.build()
     }
 }
 
-#[derive(Default)]
 struct StakingEventBuilder {
-    attributes: Vec<KVPair>,
+    event: EventBuilder,
 }
 
 impl StakingEventBuilder {
+    fn new() -> Self {
+        StakingEventBuilder {
+            event: EventBuilder::new(TendermintEventType::StakingChange),
+        }
+    }
+
     fn deposit(&mut self, staking_address: &StakedStateAddress, deposit_amount: Coin) {
-        self.attributes
-            .push(staking_address_attribute(staking_address));
-        self.attributes.push(StakingEventOpType::Deposit.into());
-
-        self.attributes.push(
-            StakingDiffField(vec![StakingDiff::Bonded(
-                StakingCoinChange::Increase,
-                deposit_amount,
-            )])
-            .into(),
+        self.event.push(staking_address_attribute(staking_address));
+        self.event.push(StakingEventOpType::Deposit.into());
+
+        self.staking_diff(vec![StakingDiff::Bonded(
+            StakingCoinChange::Increase,
+            deposit_amount,
+        )]);
+        self.delta(
+            TendermintEventKey::BondedDelta,
+            StakingCoinChange::Increase,
+            deposit_amount,
         );
     }
 
@@ -88,55 +105,60 @@ impl StakingEventBuilder {
         unbonded_from: Timespec,
         fee: Fee,
     ) {
-        self.attributes
-            .push(staking_address_attribute(staking_address));
-        self.attributes.push(StakingEventOpType::Unbond.into());
-
-        self.attributes.push(
-            StakingDiffField(vec![
-                StakingDiff::Bonded(
-                    StakingCoinChange::Decrease,
-                    (unbond_amount + fee.to_coin()).unwrap(),
-                ),
-                StakingDiff::Unbonded(StakingCoinChange::Increase, unbond_amount),
-                StakingDiff::UnbondedFrom(unbonded_from),
-            ])
-            .into(),
+        self.event.push(staking_address_attribute(staking_address));
+        self.event.push(StakingEventOpType::Unbond.into());
+
+        let bonded_decrease = (unbond_amount + fee.to_coin()).unwrap();
+        self.staking_diff(vec![
+            StakingDiff::Bonded(StakingCoinChange::Decrease, bonded_decrease),
+            StakingDiff::Unbonded(StakingCoinChange::Increase, unbond_amount),
+            StakingDiff::UnbondedFrom(unbonded_from),
+        ]);
+        self.delta(
+            TendermintEventKey::BondedDelta,
+            StakingCoinChange::Decrease,
+            bonded_decrease,
+        );
+        self.delta(
+            TendermintEventKey::UnbondedDelta,
+            StakingCoinChange::Increase,
+            unbond_amount,
         );
     }
 
     fn withdraw(&mut self, staking_address: &StakedStateAddress, withdraw_amount: Coin) {
-        self.attributes
-            .push(staking_address_attribute(staking_address));
-        self.attributes.push(StakingEventOpType::Withdraw.into());
-
-        self.attributes.push(
-            StakingDiffField(vec![StakingDiff::Unbonded(
-                StakingCoinChange::Decrease,
-                withdraw_amount,
-            )])
-            .into(),
+        self.event.push(staking_address_attribute(staking_address));
+        self.event.push(StakingEventOpType::Withdraw.into());
+
+        self.staking_diff(vec![StakingDiff::Unbonded(
+            StakingCoinChange::Decrease,
+            withdraw_amount,
+        )]);
+        self.delta(
+            TendermintEventKey::UnbondedDelta,
+            StakingCoinChange::Decrease,
+            withdraw_amount,
         );
     }
 
     fn node_join(&mut self, staking_address: &StakedStateAddress, node: CouncilNodeMeta) {
-        self.attributes
-            .push(staking_address_attribute(staking_address));
-        self.attributes.push(StakingEventOpType::NodeJoin.into());
-        self.attributes
-            .push(StakingDiffField(vec![StakingDiff::NodeJoin(node)]).into());
+        self.event.push(staking_address_attribute(staking_address));
+        self.event.push(StakingEventOpType::NodeJoin.into());
+        self.staking_diff(vec![StakingDiff::NodeJoin(node)]);
     }
 
     fn reward(&mut self, staking_address: &StakedStateAddress, reward_amount: Coin) {
-        self.attributes
-            .push(staking_address_attribute(staking_address));
-        self.attributes.push(StakingEventOpType::Reward.into());
-        self.attributes.push(
-            StakingDiffField(vec![StakingDiff::Bonded(
-                StakingCoinChange::Increase,
-                reward_amount,
-            )])
-            .into(),
+        self.event.push(staking_address_attribute(staking_address));
+        self.event.push(StakingEventOpType::Reward.into());
+        self.staking_diff(vec![StakingDiff::Bonded(
+            StakingCoinChange::Increase,
+            reward_amount,
+        )]);
+        self.amount(TendermintEventKey::RewardAmount, reward_amount);
+        self.delta(
+            TendermintEventKey::BondedDelta,
+            StakingCoinChange::Increase,
+            reward_amount,
         );
     }
 
@@ -146,16 +168,14 @@ impl StakingEventBuilder {
         jailed_until: Timespec,
         punishment_kind: PunishmentKind,
     ) {
-        self.attributes
-            .push(staking_address_attribute(staking_address));
-        self.attributes.push(StakingEventOpType::Jail.into());
-        self.attributes
-            .push(StakingDiffField(vec![StakingDiff::JailedUntil(jailed_until)]).into());
-
-        let mut reason_kv_pair = KVPair::new();
-        reason_kv_pair.key = TendermintEventKey::StakingOpReason.into();
-        reason_kv_pair.value = punishment_reason(punishment_kind).into_bytes();
-        self.attributes.push(reason_kv_pair)
+        self.event.push(staking_address_attribute(staking_address));
+        self.event.push(StakingEventOpType::Jail.into());
+        self.staking_diff(vec![StakingDiff::JailedUntil(jailed_until)]);
+
+        self.event.attribute(
+            TendermintEventKey::StakingOpReason,
+            punishment_reason(punishment_kind),
+        );
     }
 
     fn slash(
@@ -165,37 +185,73 @@ impl StakingEventBuilder {
         unbonded_slash_amount: Coin,
         punishment_kind: PunishmentKind,
     ) {
-        self.attributes
-            .push(staking_address_attribute(staking_address));
-        self.attributes.push(StakingEventOpType::Slash.into());
-        self.attributes.push(
-            StakingDiffField(vec![
-                StakingDiff::Bonded(StakingCoinChange::Decrease, bonded_slash_amount),
-                StakingDiff::Unbonded(StakingCoinChange::Decrease, unbonded_slash_amount),
-            ])
-            .into(),
+        self.event.push(staking_address_attribute(staking_address));
+        self.event.push(StakingEventOpType::Slash.into());
+        self.staking_diff(vec![
+            StakingDiff::Bonded(StakingCoinChange::Decrease, bonded_slash_amount),
+            StakingDiff::Unbonded(StakingCoinChange::Decrease, unbonded_slash_amount),
+        ]);
+        self.amount(
+            TendermintEventKey::SlashedAmount,
+            (bonded_slash_amount + unbonded_slash_amount)
+                .expect("sum of bonded and unbonded slash amount exceed maximum coin"),
+        );
+        self.delta(
+            TendermintEventKey::BondedDelta,
+            StakingCoinChange::Decrease,
+            bonded_slash_amount,
+        );
+        self.delta(
+            TendermintEventKey::UnbondedDelta,
+            StakingCoinChange::Decrease,
+            unbonded_slash_amount,
         );
 
-        let mut reason_kv_pair = KVPair::new();
-        reason_kv_pair.key = TendermintEventKey::StakingOpReason.into();
-        reason_kv_pair.value = punishment_reason(punishment_kind).into_bytes();
-        self.attributes.push(reason_kv_pair)
+        self.event.attribute(
+            TendermintEventKey::StakingOpReason,
+            punishment_reason(punishment_kind),
+        );
     }
 
     fn unjail(&mut self, staking_address: &StakedStateAddress) {
-        self.attributes
-            .push(staking_address_attribute(staking_address));
-        self.attributes.push(StakingEventOpType::Unjail.into());
+        self.event.push(staking_address_attribute(staking_address));
+        self.event.push(StakingEventOpType::Unjail.into());
     }
 
-    fn to_event(&self) -> Event {
-        let mut event = Event::new();
-        event.field_type = TendermintEventType::StakingChange.to_string();
-        for attribute in self.attributes.iter() {
-            event.attributes.push(attribute.clone())
+    fn update_node(&mut self, staking_address: &StakedStateAddress, fee: Fee) {
+        self.event.push(staking_address_attribute(staking_address));
+        self.event.push(StakingEventOpType::UpdateNode.into());
+
+        self.staking_diff(vec![StakingDiff::Bonded(
+            StakingCoinChange::Decrease,
+            fee.to_coin(),
+        )]);
+        self.delta(
+            TendermintEventKey::BondedDelta,
+            StakingCoinChange::Decrease,
+            fee.to_coin(),
+        );
+    }
+
+    /// Balance changes are only kept in `staking_diff` when legacy event keys are enabled, they
+    /// are emitted under the typed keys instead
+    fn staking_diff(&mut self, diffs: Vec<StakingDiff>) {
+        let diffs = diffs
+            .into_iter()
+            .filter(|diff| LEGACY_EVENT_KEYS || !diff.is_balance_change())
+            .collect::<Vec<_>>();
+        if !diffs.is_empty() {
+            self.event.push(StakingDiffField(diffs).into());
         }
+    }
 
-        event
+    fn amount(&mut self, key: TendermintEventKey, amount: Coin) {
+        self.event.attribute(key, u64::from(amount).to_string());
+    }
+
+    fn delta(&mut self, key: TendermintEventKey, change: StakingCoinChange, amount: Coin) {
+        self.event
+            .attribute(key, format!("{}{}", change, u64::from(amount)));
     }
 }
 
@@ -225,6 +281,7 @@ enum StakingEventOpType {
     Jail,
     Slash,
     Unjail,
+    UpdateNode,
 }
 
 impl fmt::Display for StakingEventOpType {
@@ -238,6 +295,7 @@ impl fmt::Display for StakingEventOpType {
             StakingEventOpType::Jail => write!(f, "jail"),
             StakingEventOpType::Slash => write!(f, "slash"),
             StakingEventOpType::Unjail => write!(f, "unjail"),
+            StakingEventOpType::UpdateNode => write!(f, "updatenode"),
         }
     }
 }
@@ -330,6 +388,17 @@ impl Serialize for StakingDiff {
     }
 }
 
+impl StakingDiff {
+    fn is_balance_change(&self) -> bool {
+        match self {
+            StakingDiff::Bonded(..) | StakingDiff::Unbonded(..) => true,
+            StakingDiff::UnbondedFrom(_)
+            | StakingDiff::NodeJoin(_)
+            | StakingDiff::JailedUntil(_) => false,
+        }
+    }
+}
+
 impl fmt::Display for StakingDiff {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -629,41 +698,56 @@ mod tests {
             }
         }
 
+        mod update_node {
+            use super::*;
+
+            #[test]
+            fn should_create_update_node_event() {
+                let any_staking_address = any_staking_address();
+
+                let fee = Fee::new(Coin::new(10).unwrap());
+
+                let event: Event = StakingEvent::UpdateNode(&any_staking_address, fee).into();
+
+                assert_staking_event_header(
+                    &event,
+                    &any_staking_address,
+                    StakingEventOpType::UpdateNode,
+                );
+                assert_eq!(event.attributes.len(), 3 + legacy_attributes(1));
+                assert_legacy_staking_diff(
+                    &event,
+                    "[{\"key\":\"Bonded\",\"value\":\"-10\"}]".to_owned(),
+                );
+                assert_attribute(
+                    &event,
+                    TendermintEventKey::BondedDelta,
+                    Some("-10".to_owned()),
+                );
+            }
+        }
+
         fn assert_deposit_event(
             event: Event,
             staking_address: StakedStateAddress,
             deposit_amount: Coin,
         ) {
-            assert_eq!(
-                event.field_type,
-                TendermintEventType::StakingChange.to_string()
-            );
-            assert_eq!(event.attributes.len(), 3);
-
-            let staking_address_attribute = event.attributes.first().unwrap();
-            assert_kv_pair(
-                staking_address_attribute,
-                TendermintEventKey::StakingAddress.to_string(),
-                staking_address.to_string(),
-            );
-
-            let staking_optype_attribute = event.attributes.get(1).unwrap();
-            assert_kv_pair(
-                staking_optype_attribute,
-                TendermintEventKey::StakingOpType.to_string(),
-                StakingEventOpType::Deposit.to_string(),
-            );
-
-            let staking_diff_attribute = event.attributes.get(2).unwrap();
-            let expected_value = format!(
-                "[{{\"key\":\"Bonded\",\"value\":\"{}\"}}]",
-                u64::from(deposit_amount)
+            assert_staking_event_header(&event, &staking_address, StakingEventOpType::Deposit);
+            assert_eq!(event.attributes.len(), 3 + legacy_attributes(1));
+
+            assert_legacy_staking_diff(
+                &event,
+                format!(
+                    "[{{\"key\":\"Bonded\",\"value\":\"{}\"}}]",
+                    u64::from(deposit_amount)
+                ),
             );
-            assert_kv_pair(
-                staking_diff_attribute,
-                TendermintEventKey::StakingDiff.to_string(),
-                expected_value,
+            assert_attribute(
+                &event,
+                TendermintEventKey::BondedDelta,
+                Some(u64::from(deposit_amount).to_string()),
             );
+            assert_attribute(&event, TendermintEventKey::UnbondedDelta, None);
         }
 
         fn assert_unbonded_event(
@@ -672,38 +756,35 @@ mod tests {
             unbond_amount: Coin,
             unbonded_from: Timespec,
         ) {
-            assert_eq!(
-                event.field_type,
-                TendermintEventType::StakingChange.to_string()
-            );
-            assert_eq!(event.attributes.len(), 3);
-
-            let staking_address_attribute = event.attributes.first().unwrap();
-            assert_kv_pair(
-                staking_address_attribute,
-                TendermintEventKey::StakingAddress.to_string(),
-                staking_address.to_string(),
-            );
-
-            let staking_optype_attribute = event.attributes.get(1).unwrap();
-            assert_kv_pair(
-                staking_optype_attribute,
-                TendermintEventKey::StakingOpType.to_string(),
-                StakingEventOpType::Unbond.to_string(),
-            );
-
-            let staking_diff_attribute = event.attributes.get(2).unwrap();
-            let expected_value = format!(
-                "[{{\"key\":\"Bonded\",\"value\":\"-{}\"}},{{\"key\":\"Unbonded\",\"value\":\"{}\"}},{{\"key\":\"UnbondedFrom\",\"value\":{}}}]",
-                u64::from(unbond_amount),
-                u64::from(unbond_amount),
-                unbonded_from,
-            );
-            assert_kv_pair(
-                staking_diff_attribute,
-                TendermintEventKey::StakingDiff.to_string(),
-                expected_value,
-            );
+            assert_staking_event_header(&event, staking_address, StakingEventOpType::Unbond);
+            assert_eq!(event.attributes.len(), 5);
+
+            let expected_value = if LEGACY_EVENT_KEYS {
+                format!(
+                    "[{{\"key\":\"Bonded\",\"value\":\"-{}\"}},{{\"key\":\"Unbonded\",\"value\":\"{}\"}},{{\"key\":\"UnbondedFrom\",\"value\":{}}}]",
+                    u64::from(unbond_amount),
+                    u64::from(unbond_amount),
+                    unbonded_from,
+                )
+            } else {
+                format!("[{{\"key\":\"UnbondedFrom\",\"value\":{}}}]", unbonded_from,)
+            };
+            assert_attribute(
+                &event,
+                TendermintEventKey::StakingDiff,
+                Some(expected_value),
+            );
+            assert_attribute(
+                &event,
+                TendermintEventKey::BondedDelta,
+                Some(format!("-{}", u64::from(unbond_amount))),
+            );
+            assert_attribute(
+                &event,
+                TendermintEventKey::UnbondedDelta,
+                Some(u64::from(unbond_amount).to_string()),
+            );
+            assert_attribute(&event, TendermintEventKey::Fee, None);
         }
 
         fn assert_withdraw_event(
@@ -711,36 +792,22 @@ mod tests {
             staking_address: &StakedStateAddress,
             withdraw_amount: Coin,
         ) {
-            assert_eq!(
-                event.field_type,
-                TendermintEventType::StakingChange.to_string()
-            );
-            assert_eq!(event.attributes.len(), 3);
-
-            let staking_address_attribute = event.attributes.first().unwrap();
-            assert_kv_pair(
-                staking_address_attribute,
-                TendermintEventKey::StakingAddress.to_string(),
-                staking_address.to_string(),
-            );
-
-            let staking_optype_attribute = event.attributes.get(1).unwrap();
-            assert_kv_pair(
-                staking_optype_attribute,
-                TendermintEventKey::StakingOpType.to_string(),
-                StakingEventOpType::Withdraw.to_string(),
-            );
-
-            let staking_diff_attribute = event.attributes.get(2).unwrap();
-            let expected_value = format!(
-                "[{{\"key\":\"Unbonded\",\"value\":\"-{}\"}}]",
-                u64::from(withdraw_amount)
+            assert_staking_event_header(&event, staking_address, StakingEventOpType::Withdraw);
+            assert_eq!(event.attributes.len(), 3 + legacy_attributes(1));
+
+            assert_legacy_staking_diff(
+                &event,
+                format!(
+                    "[{{\"key\":\"Unbonded\",\"value\":\"-{}\"}}]",
+                    u64::from(withdraw_amount)
+                ),
             );
-            assert_kv_pair(
-                staking_diff_attribute,
-                TendermintEventKey::StakingDiff.to_string(),
-                expected_value,
+            assert_attribute(
+                &event,
+                TendermintEventKey::UnbondedDelta,
+                Some(format!("-{}", u64::from(withdraw_amount))),
             );
+            assert_attribute(&event, TendermintEventKey::BondedDelta, None);
         }
 
         fn assert_node_join_event(
@@ -748,75 +815,49 @@ mod tests {
             staking_address: &StakedStateAddress,
             council_node: CouncilNodeMeta,
         ) {
-            assert_eq!(
-                event.field_type,
-                TendermintEventType::StakingChange.to_string()
-            );
+            assert_staking_event_header(&event, staking_address, StakingEventOpType::NodeJoin);
             assert_eq!(event.attributes.len(), 3);
 
-            let staking_address_attribute = event.attributes.first().unwrap();
-            assert_kv_pair(
-                staking_address_attribute,
-                TendermintEventKey::StakingAddress.to_string(),
-                staking_address.to_string(),
-            );
-
-            let staking_optype_attribute = event.attributes.get(1).unwrap();
-            assert_kv_pair(
-                staking_optype_attribute,
-                TendermintEventKey::StakingOpType.to_string(),
-                StakingEventOpType::NodeJoin.to_string(),
-            );
-
-            let staking_diff_attribute = event.attributes.get(2).unwrap();
             let expected_council_node = serde_json::to_string(&council_node)
                 .expect("Error when serializing council node info");
             let expected_value = format!(
                 "[{{\"key\":\"CouncilNode\",\"value\":{}}}]",
                 expected_council_node
             );
-            assert_kv_pair(
-                staking_diff_attribute,
-                TendermintEventKey::StakingDiff.to_string(),
-                expected_value,
+            assert_attribute(
+                &event,
+                TendermintEventKey::StakingDiff,
+                Some(expected_value),
             );
         }
 
         fn assert_reward_event(
             event: Event,
             staking_address: StakedStateAddress,
-            deposit_amount: Coin,
+            reward_amount: Coin,
         ) {
-            assert_eq!(
-                event.field_type,
-                TendermintEventType::StakingChange.to_string()
-            );
-            assert_eq!(event.attributes.len(), 3);
-
-            let staking_address_attribute = event.attributes.first().unwrap();
-            assert_kv_pair(
-                staking_address_attribute,
-                TendermintEventKey::StakingAddress.to_string(),
-                staking_address.to_string(),
-            );
-
-            let staking_optype_attribute = event.attributes.get(1).unwrap();
-            assert_kv_pair(
-                staking_optype_attribute,
-                TendermintEventKey::StakingOpType.to_string(),
-                StakingEventOpType::Reward.to_string(),
+            assert_staking_event_header(&event, &staking_address, StakingEventOpType::Reward);
+            assert_eq!(event.attributes.len(), 4 + legacy_attributes(1));
+
+            assert_legacy_staking_diff(
+                &event,
+                format!(
+                    "[{{\"key\":\"Bonded\",\"value\":\"{}\"}}]",
+                    u64::from(reward_amount)
+                ),
             );
-
-            let staking_diff_attribute = event.attributes.get(2).unwrap();
-            let expected_value = format!(
-                "[{{\"key\":\"Bonded\",\"value\":\"{}\"}}]",
-                u64::from(deposit_amount)
+            assert_attribute(
+                &event,
+                TendermintEventKey::RewardAmount,
+                Some(u64::from(reward_amount).to_string()),
             );
-            assert_kv_pair(
-                staking_diff_attribute,
-                TendermintEventKey::StakingDiff.to_string(),
-                expected_value,
+            assert_attribute(
+                &event,
+                TendermintEventKey::BondedDelta,
+                Some(u64::from(reward_amount).to_string()),
             );
+            assert_attribute(&event, TendermintEventKey::Fee, None);
+            assert_attribute(&event, TendermintEventKey::SlashedAmount, None);
         }
 
         fn assert_jail_event(
@@ -825,44 +866,21 @@ mod tests {
             timespec: Timespec,
             punishment_kind: PunishmentKind,
         ) {
-            assert_eq!(
-                event.field_type,
-                TendermintEventType::StakingChange.to_string()
-            );
+            assert_staking_event_header(&event, &staking_address, StakingEventOpType::Jail);
             assert_eq!(event.attributes.len(), 4);
 
-            let staking_address_attribute = event.attributes.first().unwrap();
-            assert_kv_pair(
-                staking_address_attribute,
-                TendermintEventKey::StakingAddress.to_string(),
-                staking_address.to_string(),
+            let expected_value = format!("[{{\"key\":\"JailedUntil\",\"value\":{}}}]", timespec);
+            assert_attribute(
+                &event,
+                TendermintEventKey::StakingDiff,
+                Some(expected_value),
             );
-
-            let staking_optype_attribute = event.attributes.get(1).unwrap();
-            assert_kv_pair(
-                staking_optype_attribute,
-                TendermintEventKey::StakingOpType.to_string(),
-                StakingEventOpType::Jail.to_string(),
-            );
-
-            let staking_diff_attribute = event.attributes.get(2).unwrap();
-            let expected_timespec = timespec.to_string();
-            let expected_value = format!(
-                "[{{\"key\":\"JailedUntil\",\"value\":{}}}]",
-                expected_timespec
-            );
-            assert_kv_pair(
-                staking_diff_attribute,
-                TendermintEventKey::StakingDiff.to_string(),
-                expected_value,
-            );
-
-            let staking_opreason_attribute = event.attributes.get(3).unwrap();
-            assert_kv_pair(
-                staking_opreason_attribute,
-                TendermintEventKey::StakingOpReason.to_string(),
-                punishment_reason(punishment_kind),
+            assert_attribute(
+                &event,
+                TendermintEventKey::StakingOpReason,
+                Some(punishment_reason(punishment_kind)),
             );
+            assert_attribute(&event, TendermintEventKey::SlashedAmount, None);
         }
 
         fn assert_slash_event(
@@ -872,71 +890,108 @@ mod tests {
             unbonded_slash_amount: Coin,
             punishment_kind: PunishmentKind,
         ) {
-            assert_eq!(
-                event.field_type,
-                TendermintEventType::StakingChange.to_string()
+            assert_staking_event_header(&event, &staking_address, StakingEventOpType::Slash);
+            assert_eq!(event.attributes.len(), 6 + legacy_attributes(1));
+
+            assert_legacy_staking_diff(
+                &event,
+                format!(
+                    "[{{\"key\":\"Bonded\",\"value\":\"-{}\"}},{{\"key\":\"Unbonded\",\"value\":\"-{}\"}}]",
+                    u64::from(bonded_slash_amount),
+                    u64::from(unbonded_slash_amount)
+                ),
             );
-            assert_eq!(event.attributes.len(), 4);
-
-            let staking_address_attribute = event.attributes.first().unwrap();
-            assert_kv_pair(
-                staking_address_attribute,
-                TendermintEventKey::StakingAddress.to_string(),
-                staking_address.to_string(),
+            assert_attribute(
+                &event,
+                TendermintEventKey::SlashedAmount,
+                Some(
+                    (u64::from(bonded_slash_amount) + u64::from(unbonded_slash_amount)).to_string(),
+                ),
             );
-
-            let staking_optype_attribute = event.attributes.get(1).unwrap();
-            assert_kv_pair(
-                staking_optype_attribute,
-                TendermintEventKey::StakingOpType.to_string(),
-                StakingEventOpType::Slash.to_string(),
+            assert_attribute(
+                &event,
+                TendermintEventKey::BondedDelta,
+                Some(format!("-{}", u64::from(bonded_slash_amount))),
             );
-
-            let staking_diff_bonded_attribute = event.attributes.get(2).unwrap();
-            let expected_value = format!(
-                "[{{\"key\":\"Bonded\",\"value\":\"-{}\"}},{{\"key\":\"Unbonded\",\"value\":\"-{}\"}}]",
-                u64::from(bonded_slash_amount),
-                u64::from(unbonded_slash_amount)
+            assert_attribute(
+                &event,
+                TendermintEventKey::UnbondedDelta,
+                Some(format!("-{}", u64::from(unbonded_slash_amount))),
             );
-            assert_kv_pair(
-                staking_diff_bonded_attribute,
-                TendermintEventKey::StakingDiff.to_string(),
-                expected_value,
-            );
-
-            let staking_opreason_attribute = event.attributes.get(3).unwrap();
-            assert_kv_pair(
-                staking_opreason_attribute,
-                TendermintEventKey::StakingOpReason.to_string(),
-                punishment_reason(punishment_kind),
+            assert_attribute(
+                &event,
+                TendermintEventKey::StakingOpReason,
+                Some(punishment_reason(punishment_kind)),
             );
+            // slashed amount must never be reported as a fee
+            assert_attribute(&event, TendermintEventKey::Fee, None);
         }
 
         fn assert_unjail_event(event: Event, staking_address: StakedStateAddress) {
+            assert_staking_event_header(&event, &staking_address, StakingEventOpType::Unjail);
+            assert_eq!(event.attributes.len(), 2);
+        }
+
+        fn assert_staking_event_header(
+            event: &Event,
+            staking_address: &StakedStateAddress,
+            op_type: StakingEventOpType,
+        ) {
             assert_eq!(
                 event.field_type,
                 TendermintEventType::StakingChange.to_string()
             );
-            assert_eq!(event.attributes.len(), 2);
 
-            let staking_address_attribute = event.attributes.first().unwrap();
-            assert_kv_pair(
-                staking_address_attribute,
-                TendermintEventKey::StakingAddress.to_string(),
-                staking_address.to_string(),
+            assert_attribute(
+                event,
+                TendermintEventKey::StakingAddress,
+                Some(staking_address.to_string()),
             );
-
-            let staking_optype_attribute = event.attributes.get(1).unwrap();
-            assert_kv_pair(
-                staking_optype_attribute,
-                TendermintEventKey::StakingOpType.to_string(),
-                StakingEventOpType::Unjail.to_string(),
+            assert_attribute(
+                event,
+                TendermintEventKey::StakingOpType,
+                Some(op_type.to_string()),
             );
+
+            // attributes are in the canonical order
+            let keys = event
+                .attributes
+                .iter()
+                .map(|kv_pair| kv_pair.key.clone())
+                .collect::<Vec<_>>();
+            let mut sorted_keys = keys.clone();
+            sorted_keys.sort();
+            assert_eq!(keys, sorted_keys);
+        }
+
+        fn legacy_attributes(count: usize) -> usize {
+            if LEGACY_EVENT_KEYS {
+                count
+            } else {
+                0
+            }
+        }
+
+        fn assert_legacy_staking_diff(event: &Event, expected_value: String) {
+            let expected_value = if LEGACY_EVENT_KEYS {
+                Some(expected_value)
+            } else {
+                None
+            };
+            assert_attribute(event, TendermintEventKey::StakingDiff, expected_value);
         }
 
-        fn assert_kv_pair(kv_pair: &KVPair, expected_key: String, expected_value: String) {
-            assert_eq!(String::from_utf8_lossy(&kv_pair.key), expected_key);
-            assert_eq!(String::from_utf8_lossy(&kv_pair.value), expected_value);
+        fn assert_attribute(
+            event: &Event,
+            key: TendermintEventKey,
+            expected_value: Option<String>,
+        ) {
+            let value = event
+                .attributes
+                .iter()
+                .find(|kv_pair| kv_pair.key == key.to_vec())
+                .map(|kv_pair| String::from_utf8_lossy(&kv_pair.value).to_string());
+            assert_eq!(value, expected_value, "value of event key {}", key);
         }
     }
 
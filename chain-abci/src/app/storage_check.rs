@@ -0,0 +1,411 @@
+//! Consistency checks of the persisted node state, run before the app is restored from storage.
+//! Crashes in the middle of writing may leave the last state, the staking merkle trie
+//! and the historical snapshots out of sync, which would otherwise only surface later
+//! as an app hash mismatch.
+
+use std::fmt;
+
+use parity_scale_codec::Decode;
+
+use super::ChainNodeState;
+use chain_core::common::{MerkleTree, H256};
+use chain_core::compute_app_hash;
+use chain_core::state::tendermint::BlockHeight;
+use chain_storage::jellyfish::{compute_staking_root, get_root_hash, iter_stakings, StakingGetter};
+use chain_storage::{LookupItem, Storage};
+
+/// How thorough the storage verification should be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageCheckLevel {
+    /// Checks which only look up a few keys, always run on start up
+    Quick,
+    /// Also walks through all historical snapshots and stakings (`--verify-storage`)
+    Full,
+}
+
+/// An inconsistency found in the persisted node state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageInconsistency {
+    /// Genesis app hash is not stored
+    MissingGenesisAppHash,
+    /// Stored genesis app hash is different from the configured one
+    GenesisAppHashMismatch { stored: H256, expected: H256 },
+    /// Chain id is not stored
+    MissingChainId,
+    /// Stored chain id is different from the configured one
+    ChainIdMismatch { stored: Vec<u8>, expected: String },
+    /// Last state can't be decoded
+    InvalidLastState(String),
+    /// Root node of the staking trie at the last state's version is not stored
+    MissingAccountRoot { version: u64 },
+    /// Root of the staking trie is different from the last state's account root
+    AccountRootMismatch {
+        version: u64,
+        stored: H256,
+        expected: H256,
+    },
+    /// Stakings stored in the trie don't hash to the account root
+    AccountRootRecomputeMismatch { computed: H256, expected: H256 },
+    /// Merkle tree of the last block's transactions is not stored
+    MissingTxsMerkleTree { app_hash: H256 },
+    /// App hash recomputed from the persisted components doesn't match the last state
+    AppHashMismatch { computed: H256, stored: H256 },
+    /// No app hash snapshot stored for the height
+    MissingHistoricalAppHash { height: BlockHeight },
+    /// App hash snapshot of the height is different from the last state
+    HistoricalAppHashMismatch {
+        height: BlockHeight,
+        stored: H256,
+        expected: H256,
+    },
+    /// Snapshot exists for a height after the last state's height
+    SnapshotAfterLastState { height: BlockHeight },
+    /// Staking version snapshot of the height is not stored
+    MissingHistoricalStakingVersion { height: BlockHeight },
+    /// Staking version snapshot of the height is different from the last state
+    HistoricalStakingVersionMismatch {
+        height: BlockHeight,
+        stored: u64,
+        expected: u64,
+    },
+    /// Staking version snapshots are not monotonic
+    StakingVersionNotMonotonic {
+        height: BlockHeight,
+        version: u64,
+        previous: u64,
+    },
+    /// Staking table is not consistent with the staking trie
+    StakingTable(String),
+}
+
+impl fmt::Display for StorageInconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageInconsistency::MissingGenesisAppHash => {
+                write!(f, "genesis app hash is not stored")
+            }
+            StorageInconsistency::GenesisAppHashMismatch { stored, expected } => write!(
+                f,
+                "stored genesis app hash: {} does not match the provided genesis app hash: {}",
+                hex::encode(stored),
+                hex::encode(expected)
+            ),
+            StorageInconsistency::MissingChainId => write!(f, "chain id is not stored"),
+            StorageInconsistency::ChainIdMismatch { stored, expected } => write!(
+                f,
+                "stored chain id: {:?} does not match the provided chain id: {:?}",
+                String::from_utf8_lossy(stored),
+                expected
+            ),
+            StorageInconsistency::InvalidLastState(err) => {
+                write!(f, "last app state can't be decoded: {}", err)
+            }
+            StorageInconsistency::MissingAccountRoot { version } => write!(
+                f,
+                "account root of staking version {} not found in trie storage",
+                version
+            ),
+            StorageInconsistency::AccountRootMismatch {
+                version,
+                stored,
+                expected,
+            } => write!(
+                f,
+                "account root of staking version {} in trie storage: {} does not match the last state's account root: {}",
+                version,
+                hex::encode(stored),
+                hex::encode(expected)
+            ),
+            StorageInconsistency::AccountRootRecomputeMismatch { computed, expected } => write!(
+                f,
+                "account root recomputed from stakings: {} does not match the last state's account root: {}",
+                hex::encode(computed),
+                hex::encode(expected)
+            ),
+            StorageInconsistency::MissingTxsMerkleTree { app_hash } => write!(
+                f,
+                "transactions merkle tree of app hash {} not found",
+                hex::encode(app_hash)
+            ),
+            StorageInconsistency::AppHashMismatch { computed, stored } => write!(
+                f,
+                "app hash recomputed from persisted components: {} does not match the last state's app hash: {}",
+                hex::encode(computed),
+                hex::encode(stored)
+            ),
+            StorageInconsistency::MissingHistoricalAppHash { height } => {
+                write!(f, "app hash of block height {} is not stored", height)
+            }
+            StorageInconsistency::HistoricalAppHashMismatch {
+                height,
+                stored,
+                expected,
+            } => write!(
+                f,
+                "stored app hash of block height {}: {} does not match the last state's app hash: {}",
+                height,
+                hex::encode(stored),
+                hex::encode(expected)
+            ),
+            StorageInconsistency::SnapshotAfterLastState { height } => write!(
+                f,
+                "snapshot of block height {} is stored, but it's after the last state's height",
+                height
+            ),
+            StorageInconsistency::MissingHistoricalStakingVersion { height } => {
+                write!(f, "staking version of block height {} is not stored", height)
+            }
+            StorageInconsistency::HistoricalStakingVersionMismatch {
+                height,
+                stored,
+                expected,
+            } => write!(
+                f,
+                "stored staking version of block height {}: {} does not match the last state's staking version: {}",
+                height, stored, expected
+            ),
+            StorageInconsistency::StakingVersionNotMonotonic {
+                height,
+                version,
+                previous,
+            } => write!(
+                f,
+                "staking version of block height {}: {} is lower than the previous one: {}",
+                height, version, previous
+            ),
+            StorageInconsistency::StakingTable(err) => {
+                write!(f, "staking table is inconsistent: {}", err)
+            }
+        }
+    }
+}
+
+/// Formats the found inconsistencies as a report (one per line)
+pub fn format_report(inconsistencies: &[StorageInconsistency]) -> String {
+    inconsistencies
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Verifies the persisted node state is consistent with itself and the provided genesis app hash
+/// and chain id. Storage without the last state (i.e. before init chain) is always consistent.
+pub fn verify_storage(
+    storage: &Storage,
+    genesis_app_hash: &H256,
+    chain_id: &str,
+    level: StorageCheckLevel,
+) -> Result<(), Vec<StorageInconsistency>> {
+    let data = match storage.get_last_app_state() {
+        Some(data) => data,
+        None => return Ok(()),
+    };
+
+    let mut inconsistencies = check_genesis(storage, genesis_app_hash, chain_id);
+    let last_state = match ChainNodeState::decode(&mut data.as_slice()) {
+        Ok(last_state) => last_state,
+        Err(err) => {
+            inconsistencies.push(StorageInconsistency::InvalidLastState(err.to_string()));
+            return Err(inconsistencies);
+        }
+    };
+
+    let account_root_found = match check_account_root(storage, &last_state) {
+        Ok(()) => true,
+        Err(inconsistency) => {
+            inconsistencies.push(inconsistency);
+            false
+        }
+    };
+    inconsistencies.extend(check_app_hash(storage, &last_state).err());
+    inconsistencies.extend(check_last_snapshot(storage, &last_state));
+
+    if level == StorageCheckLevel::Full {
+        inconsistencies.extend(check_snapshot_history(storage, &last_state));
+        // walking through the trie is only possible when its root is there
+        if account_root_found {
+            inconsistencies.extend(check_stakings(storage, &last_state));
+        }
+    }
+
+    if inconsistencies.is_empty() {
+        Ok(())
+    } else {
+        Err(inconsistencies)
+    }
+}
+
+fn check_genesis(
+    storage: &Storage,
+    genesis_app_hash: &H256,
+    chain_id: &str,
+) -> Vec<StorageInconsistency> {
+    let mut inconsistencies = vec![];
+    match chain_storage::get_genesis_app_hash(storage) {
+        None => inconsistencies.push(StorageInconsistency::MissingGenesisAppHash),
+        Some(stored) if &stored != genesis_app_hash => {
+            inconsistencies.push(StorageInconsistency::GenesisAppHashMismatch {
+                stored,
+                expected: *genesis_app_hash,
+            })
+        }
+        _ => {}
+    }
+    match chain_storage::get_stored_chain_id(storage) {
+        None => inconsistencies.push(StorageInconsistency::MissingChainId),
+        Some(stored) if stored != chain_id.as_bytes() => {
+            inconsistencies.push(StorageInconsistency::ChainIdMismatch {
+                stored,
+                expected: chain_id.to_owned(),
+            })
+        }
+        _ => {}
+    }
+    inconsistencies
+}
+
+fn check_account_root(
+    storage: &Storage,
+    last_state: &ChainNodeState,
+) -> Result<(), StorageInconsistency> {
+    let version = last_state.staking_version;
+    let expected = last_state.top_level.account_root;
+    match get_root_hash(storage, version) {
+        Ok(Some(stored)) if stored == expected => Ok(()),
+        Ok(Some(stored)) => Err(StorageInconsistency::AccountRootMismatch {
+            version,
+            stored,
+            expected,
+        }),
+        Ok(None) | Err(_) => Err(StorageInconsistency::MissingAccountRoot { version }),
+    }
+}
+
+fn check_app_hash(
+    storage: &Storage,
+    last_state: &ChainNodeState,
+) -> Result<(), StorageInconsistency> {
+    // transactions merkle tree is stored in commit, but not for genesis
+    let tree = if last_state.last_block_height == BlockHeight::genesis() {
+        MerkleTree::empty()
+    } else {
+        storage
+            .lookup_item(LookupItem::TxsMerkle, &last_state.last_apphash)
+            .and_then(|data| MerkleTree::<H256>::decode(&mut data.as_slice()).ok())
+            .ok_or(StorageInconsistency::MissingTxsMerkleTree {
+                app_hash: last_state.last_apphash,
+            })?
+    };
+    let top_level = &last_state.top_level;
+    let computed = compute_app_hash(
+        &tree,
+        &top_level.account_root,
+        &top_level.rewards_pool,
+        &top_level.network_params,
+    );
+    if computed == last_state.last_apphash {
+        Ok(())
+    } else {
+        Err(StorageInconsistency::AppHashMismatch {
+            computed,
+            stored: last_state.last_apphash,
+        })
+    }
+}
+
+/// Snapshots of the last state's height should match it, and there should be none after it
+fn check_last_snapshot(
+    storage: &Storage,
+    last_state: &ChainNodeState,
+) -> Vec<StorageInconsistency> {
+    let mut inconsistencies = vec![];
+    let height = last_state.last_block_height;
+    match storage.get_historical_app_hash(height) {
+        None => inconsistencies.push(StorageInconsistency::MissingHistoricalAppHash { height }),
+        Some(stored) if stored != last_state.last_apphash => {
+            inconsistencies.push(StorageInconsistency::HistoricalAppHashMismatch {
+                height,
+                stored,
+                expected: last_state.last_apphash,
+            })
+        }
+        _ => {}
+    }
+    match storage.get_historical_staking_version(height) {
+        None => {
+            inconsistencies.push(StorageInconsistency::MissingHistoricalStakingVersion { height })
+        }
+        Some(stored) if stored != last_state.staking_version => {
+            inconsistencies.push(StorageInconsistency::HistoricalStakingVersionMismatch {
+                height,
+                stored,
+                expected: last_state.staking_version,
+            })
+        }
+        _ => {}
+    }
+    if let Some(next_height) = height.checked_add(1) {
+        if storage.get_historical_app_hash(next_height).is_some()
+            || storage
+                .get_historical_staking_version(next_height)
+                .is_some()
+        {
+            inconsistencies.push(StorageInconsistency::SnapshotAfterLastState {
+                height: next_height,
+            });
+        }
+    }
+    inconsistencies
+}
+
+/// All the snapshots up to the last state's height should be stored, with monotonic staking versions
+fn check_snapshot_history(
+    storage: &Storage,
+    last_state: &ChainNodeState,
+) -> Vec<StorageInconsistency> {
+    let mut inconsistencies = vec![];
+    let mut previous = 0;
+    for height in 0..=last_state.last_block_height.value() {
+        let height = BlockHeight::new(height);
+        if storage.get_historical_app_hash(height).is_none() {
+            inconsistencies.push(StorageInconsistency::MissingHistoricalAppHash { height });
+        }
+        match storage.get_historical_staking_version(height) {
+            None => inconsistencies
+                .push(StorageInconsistency::MissingHistoricalStakingVersion { height }),
+            Some(version) if version < previous => {
+                inconsistencies.push(StorageInconsistency::StakingVersionNotMonotonic {
+                    height,
+                    version,
+                    previous,
+                })
+            }
+            Some(version) => previous = version,
+        }
+    }
+    inconsistencies
+}
+
+/// Recomputes the account root from all the stakings and audits the staking table against them
+fn check_stakings(storage: &Storage, last_state: &ChainNodeState) -> Vec<StorageInconsistency> {
+    let mut inconsistencies = vec![];
+    let version = last_state.staking_version;
+    let stakings = iter_stakings(storage, version).collect::<Vec<_>>();
+    if !stakings.is_empty() {
+        let computed = compute_staking_root(&stakings);
+        if computed != last_state.top_level.account_root {
+            inconsistencies.push(StorageInconsistency::AccountRootRecomputeMismatch {
+                computed,
+                expected: last_state.top_level.account_root,
+            });
+        }
+    }
+    if let Err(err) = last_state
+        .staking_table
+        .audit(&StakingGetter::new(storage, version))
+    {
+        inconsistencies.push(StorageInconsistency::StakingTable(err));
+    }
+    inconsistencies
+}
@@ -0,0 +1,186 @@
+//! Optional (`--index-tx-results`) node-local index of the delivered transaction results,
+//! so that clients can find out whether (and in which block, with what result) a transaction
+//! was included without an external indexer.
+//! The results of a block are written at its commit (with the rest of the block data) and
+//! served by the `txresult/<hex txid>` query; they are pruned with the same retention window
+//! as the block data (`retain_blocks`).
+
+use chain_core::init::coin::Coin;
+use chain_core::state::account::StakedStateAddress;
+use chain_core::state::tendermint::BlockHeight;
+use chain_core::tx::data::TxId;
+use chain_core::tx::result::TxResult;
+use chain_storage::buffer::StoreKV;
+
+/// Result of a transaction delivered in the current block
+#[derive(Debug, Clone)]
+struct DeliveredTx {
+    txid: TxId,
+    index: u32,
+    code: u32,
+    fee: Coin,
+    staking_address: Option<StakedStateAddress>,
+}
+
+/// Collects the results of the transactions delivered in the current block
+#[derive(Debug, Default)]
+pub struct TxResultIndex {
+    enabled: bool,
+    /// number of the transactions delivered in the current block (including the undecodable ones)
+    block_tx_count: u32,
+    block_results: Vec<DeliveredTx>,
+}
+
+impl TxResultIndex {
+    /// Creates the index, which only records anything if `enabled`
+    pub fn new(enabled: bool) -> Self {
+        TxResultIndex {
+            enabled,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Records the result of the next transaction delivered in the current block
+    /// (`txid` is `None` if the transaction couldn't be decoded)
+    pub fn record(
+        &mut self,
+        txid: Option<TxId>,
+        code: u32,
+        fee: Coin,
+        staking_address: Option<StakedStateAddress>,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        let index = self.block_tx_count;
+        self.block_tx_count += 1;
+        if let Some(txid) = txid {
+            self.block_results.push(DeliveredTx {
+                txid,
+                index,
+                code,
+                fee,
+                staking_address,
+            });
+        }
+    }
+
+    /// Writes the results of the block at `height` and prunes the ones below `retain_height`
+    pub fn commit(
+        &mut self,
+        db: &mut impl StoreKV,
+        height: BlockHeight,
+        retain_height: Option<BlockHeight>,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        let results = self
+            .block_results
+            .drain(..)
+            .map(|tx| {
+                (
+                    tx.txid,
+                    TxResult {
+                        height,
+                        index: tx.index,
+                        code: tx.code,
+                        fee: tx.fee,
+                        staking_address: tx.staking_address,
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+        self.block_tx_count = 0;
+        chain_storage::store_tx_results(db, height, &results);
+        if let Some(retain_height) = retain_height {
+            chain_storage::prune_tx_results(db, retain_height);
+        }
+    }
+}
+
+/// The lowest block height kept with the retention window of `retain_blocks` (if any)
+pub fn retain_height(height: BlockHeight, retain_blocks: Option<u64>) -> Option<BlockHeight> {
+    match retain_blocks {
+        Some(retain_blocks) if height.value() > retain_blocks => {
+            Some(BlockHeight::new(height.value() - retain_blocks + 1))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use chain_storage::buffer::{BufferStore, KVBuffer};
+    use chain_storage::{get_tx_result, Storage, NUM_COLUMNS};
+
+    fn commit_block(
+        index: &mut TxResultIndex,
+        storage: &Storage,
+        height: u64,
+        txs: &[(TxId, u32)],
+        retain_blocks: Option<u64>,
+    ) -> KVBuffer {
+        for (txid, code) in txs.iter() {
+            index.record(Some(*txid), *code, Coin::zero(), None);
+        }
+        let mut buffer = KVBuffer::new();
+        let height = BlockHeight::new(height);
+        index.commit(
+            &mut BufferStore::new(storage, &mut buffer),
+            height,
+            retain_height(height, retain_blocks),
+        );
+        buffer
+    }
+
+    #[test]
+    fn check_results_are_indexed_and_pruned() {
+        let mut storage = Storage::new_db(Arc::new(kvdb_memorydb::create(NUM_COLUMNS)));
+        let mut index = TxResultIndex::new(true);
+        let retain_blocks = Some(2);
+
+        // a rejected transaction is delivered again (and accepted) in a later block
+        let blocks: [&[(TxId, u32)]; 3] = [
+            &[([1; 32], 0), ([2; 32], 1)],
+            &[([2; 32], 0), ([3; 32], 1)],
+            &[],
+        ];
+        for (i, txs) in blocks.iter().enumerate() {
+            let buffer = commit_block(&mut index, &storage, i as u64 + 1, txs, retain_blocks);
+            chain_storage::buffer::flush_storage(&mut storage, buffer).unwrap();
+        }
+
+        // the block 1 is out of the window
+        assert_eq!(None, get_tx_result(&storage, &[1; 32]));
+        let result = get_tx_result(&storage, &[2; 32]).unwrap();
+        assert_eq!(
+            (BlockHeight::new(2), 0, 0),
+            (result.height, result.index, result.code)
+        );
+        let result = get_tx_result(&storage, &[3; 32]).unwrap();
+        assert_eq!((1, 1), (result.index, result.code));
+
+        // a rejected replay doesn't overwrite the accepted result
+        let buffer = commit_block(&mut index, &storage, 4, &[([2; 32], 1)], None);
+        chain_storage::buffer::flush_storage(&mut storage, buffer).unwrap();
+        assert!(get_tx_result(&storage, &[2; 32]).unwrap().is_accepted());
+    }
+
+    #[test]
+    fn check_retain_height() {
+        assert_eq!(None, retain_height(BlockHeight::new(10), None));
+        assert_eq!(None, retain_height(BlockHeight::new(10), Some(10)));
+        assert_eq!(
+            Some(BlockHeight::new(2)),
+            retain_height(BlockHeight::new(11), Some(10))
+        );
+    }
+}
@@ -0,0 +1,296 @@
+//! Diagnostic tracing of a single ("armed") transaction: when the armed transaction is processed
+//! in CheckTx or DeliverTx, every validation step is recorded and the trace is stored
+//! in a dedicated (local-only) storage column.
+//! When no transaction is armed, processing a transaction only pays for one atomic load.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use serde::{Deserialize, Serialize};
+
+use super::app_init::BufferType;
+use chain_core::common::Timespec;
+use chain_core::init::coin::Coin;
+use chain_core::state::account::{Nonce, StakedState, StakedStateAddress};
+use chain_core::state::tendermint::BlockHeight;
+use chain_core::tx::data::TxId;
+use chain_core::tx::{TxEnclaveAux, TxPublicAux};
+use chain_storage::Storage;
+use chain_tx_validation::ChainInfo;
+
+/// Prefix of the admin query paths: `trace/<hex txid>` returns the stored traces (as JSON)
+pub const TX_TRACE_PATH_PREFIX: &str = "trace/";
+/// Admin query path which arms tracing of the transaction (data: txid)
+pub const TX_TRACE_ARM_PATH: &str = "trace/arm";
+/// Admin query path which disarms tracing
+pub const TX_TRACE_DISARM_PATH: &str = "trace/disarm";
+/// Maximum number of traces stored per transaction (the oldest ones are dropped,
+/// e.g. when the armed transaction is rechecked in the mempool after every block)
+pub const MAX_TX_TRACES: usize = 32;
+
+/// ABCI request in which the transaction was processed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TraceRequest {
+    CheckTx,
+    DeliverTx,
+}
+
+impl From<BufferType> for TraceRequest {
+    fn from(buffer_type: BufferType) -> Self {
+        match buffer_type {
+            BufferType::Consensus => TraceRequest::DeliverTx,
+            BufferType::Mempool => TraceRequest::CheckTx,
+        }
+    }
+}
+
+/// A single recorded validation step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "step")]
+pub enum TraceStep {
+    /// values of the `ChainInfo` the transaction was validated with
+    ExtraInfo {
+        min_fee_computed: Coin,
+        chain_hex_id: u8,
+        block_time: Timespec,
+        block_height: BlockHeight,
+        unbonding_period: Timespec,
+    },
+    /// staked state read during the validation (`None` if it doesn't exist)
+    StakingRead {
+        address: StakedStateAddress,
+        nonce: Option<Nonce>,
+        bonded: Option<Coin>,
+        unbonded: Option<Coin>,
+        jailed: Option<bool>,
+    },
+    /// comparison of the transaction nonce with the staked state one
+    /// (a missing staked state has the default nonce)
+    NonceCheck {
+        tx_nonce: Nonce,
+        staked_state_nonce: Nonce,
+        matches: bool,
+    },
+    /// summary of the request passed to the enclave
+    EnclaveRequest {
+        tx_type: String,
+        inputs: usize,
+        outputs: Option<u16>,
+    },
+    /// summary of the enclave response (or of the validation error)
+    EnclaveResponse {
+        paid_fee: Option<Coin>,
+        error: Option<String>,
+    },
+    /// fee computation
+    Fee {
+        min_fee_computed: Coin,
+        paid_fee: Coin,
+    },
+    /// final result of the processing
+    Outcome { error: Option<String> },
+}
+
+impl TraceStep {
+    pub fn extra_info(info: &ChainInfo) -> Self {
+        TraceStep::ExtraInfo {
+            min_fee_computed: info.min_fee_computed.to_coin(),
+            chain_hex_id: info.chain_hex_id,
+            block_time: info.block_time,
+            block_height: info.block_height,
+            unbonding_period: info.get_unbonding_period(),
+        }
+    }
+
+    pub fn staking_read(address: StakedStateAddress, staking: Option<&StakedState>) -> Self {
+        TraceStep::StakingRead {
+            address,
+            nonce: staking.map(|s| s.nonce),
+            bonded: staking.map(|s| s.bonded),
+            unbonded: staking.map(|s| s.unbonded),
+            jailed: staking.map(StakedState::is_jailed),
+        }
+    }
+
+    pub fn nonce_check(tx_nonce: Nonce, staking: Option<&StakedState>) -> Self {
+        let staked_state_nonce = staking.map_or(0, |s| s.nonce);
+        TraceStep::NonceCheck {
+            tx_nonce,
+            staked_state_nonce,
+            matches: tx_nonce == staked_state_nonce,
+        }
+    }
+
+    /// summary of the enclave request which is built from `tx`
+    pub fn enclave_request(tx: &TxEnclaveAux) -> Self {
+        match tx {
+            TxEnclaveAux::TransferTx {
+                inputs,
+                no_of_outputs,
+                ..
+            } => TraceStep::EnclaveRequest {
+                tx_type: "transfer".to_owned(),
+                inputs: inputs.len(),
+                outputs: Some(*no_of_outputs),
+            },
+            TxEnclaveAux::DepositStakeTx { tx, .. } => TraceStep::EnclaveRequest {
+                tx_type: "deposit".to_owned(),
+                inputs: tx.inputs.len(),
+                outputs: None,
+            },
+            TxEnclaveAux::WithdrawUnbondedStakeTx { no_of_outputs, .. } => {
+                TraceStep::EnclaveRequest {
+                    tx_type: "withdraw".to_owned(),
+                    inputs: 0,
+                    outputs: Some(*no_of_outputs),
+                }
+            }
+        }
+    }
+}
+
+/// (address, nonce) of the staked state which a public transaction operates on
+pub fn public_tx_nonce(tx: &TxPublicAux) -> (StakedStateAddress, Nonce) {
+    match tx {
+        TxPublicAux::UnbondStakeTx(tx, _) => (tx.from_staked_account, tx.nonce),
+        TxPublicAux::UnjailTx(tx, _) => (tx.address, tx.nonce),
+        TxPublicAux::NodeJoinTx(tx, _) => (tx.address, tx.nonce),
+        TxPublicAux::UpdateNodeTx(tx, _) => (tx.address, tx.nonce),
+    }
+}
+
+/// Recorded steps of one processing of the traced transaction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TxTrace {
+    pub request: TraceRequest,
+    pub steps: Vec<TraceStep>,
+}
+
+impl TxTrace {
+    pub fn record(&mut self, step: TraceStep) {
+        self.steps.push(step);
+    }
+}
+
+/// Holds the armed transaction id
+#[derive(Debug, Default)]
+pub struct TxTracer {
+    armed: AtomicBool,
+    txid: Mutex<Option<TxId>>,
+}
+
+impl TxTracer {
+    /// the armed transaction id is only ever replaced as a whole, so it's still valid
+    /// if a thread panicked while holding the lock
+    fn lock_txid(&self) -> MutexGuard<'_, Option<TxId>> {
+        self.txid.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Arms tracing of the transaction (replacing the previously armed one)
+    pub fn arm(&self, txid: TxId) {
+        *self.lock_txid() = Some(txid);
+        self.armed.store(true, Ordering::Release);
+    }
+
+    pub fn disarm(&self) {
+        self.armed.store(false, Ordering::Release);
+        *self.lock_txid() = None;
+    }
+
+    pub fn armed_txid(&self) -> Option<TxId> {
+        if !self.armed.load(Ordering::Acquire) {
+            return None;
+        }
+        *self.lock_txid()
+    }
+
+    /// Starts a trace if `txid` is the armed one
+    pub fn start(&self, txid: &TxId, buffer_type: BufferType) -> Option<TxTrace> {
+        if self.armed_txid().as_ref() != Some(txid) {
+            return None;
+        }
+        Some(TxTrace {
+            request: buffer_type.into(),
+            steps: Vec::new(),
+        })
+    }
+}
+
+/// Appends the trace to the ones already stored for the transaction
+/// (keeping the latest `MAX_TX_TRACES` ones)
+pub fn store_tx_trace(storage: &Storage, txid: &TxId, trace: TxTrace) -> io::Result<()> {
+    let mut traces = get_tx_traces(storage, txid)?.unwrap_or_default();
+    traces.push(trace);
+    let excess = traces.len().saturating_sub(MAX_TX_TRACES);
+    traces.drain(..excess);
+    let value = serde_json::to_vec(&traces)?;
+    storage.store_tx_trace(txid, &value)
+}
+
+/// Traces stored for the transaction
+pub fn get_tx_traces(storage: &Storage, txid: &TxId) -> io::Result<Option<Vec<TxTrace>>> {
+    storage
+        .get_tx_trace(txid)
+        .map(|value| serde_json::from_slice(&value).map_err(io::Error::from))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain_storage::NUM_COLUMNS;
+    use std::sync::Arc;
+
+    #[test]
+    fn check_arming() {
+        let tracer = TxTracer::default();
+        let (txid, other) = ([1u8; 32], [2u8; 32]);
+        assert!(tracer.start(&txid, BufferType::Mempool).is_none());
+
+        tracer.arm(txid);
+        assert!(tracer.start(&other, BufferType::Mempool).is_none());
+        let trace = tracer.start(&txid, BufferType::Consensus).unwrap();
+        assert_eq!(trace.request, TraceRequest::DeliverTx);
+        assert!(trace.steps.is_empty());
+
+        tracer.disarm();
+        assert_eq!(tracer.armed_txid(), None);
+        assert!(tracer.start(&txid, BufferType::Mempool).is_none());
+    }
+
+    #[test]
+    fn check_stored_traces_are_capped() {
+        let storage = Storage::new_db(Arc::new(kvdb_memorydb::create(NUM_COLUMNS)));
+        let txid = [1u8; 32];
+        assert_eq!(get_tx_traces(&storage, &txid).unwrap(), None);
+
+        for i in 0..MAX_TX_TRACES + 2 {
+            let trace = TxTrace {
+                request: TraceRequest::CheckTx,
+                steps: vec![TraceStep::NonceCheck {
+                    tx_nonce: i as Nonce,
+                    staked_state_nonce: 0,
+                    matches: i == 0,
+                }],
+            };
+            store_tx_trace(&storage, &txid, trace).unwrap();
+        }
+        let traces = get_tx_traces(&storage, &txid).unwrap().unwrap();
+        assert_eq!(traces.len(), MAX_TX_TRACES);
+        // the oldest traces are dropped
+        assert_eq!(traces[0].steps[0], TraceStep::nonce_check(2, None));
+    }
+
+    #[test]
+    fn check_nonce_step() {
+        assert_eq!(
+            TraceStep::nonce_check(1, None),
+            TraceStep::NonceCheck {
+                tx_nonce: 1,
+                staked_state_nonce: 0,
+                matches: false,
+            }
+        );
+    }
+}
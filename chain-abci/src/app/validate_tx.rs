@@ -1,14 +1,18 @@
+use super::block_budget::{BlockResources, TxResources};
+use super::tx_trace::{public_tx_nonce, store_tx_trace, TraceStep, TxTrace};
 use super::{BufferType, ChainNodeApp, ChainNodeState};
-use crate::enclave_bridge::EnclaveProxy;
+use crate::enclave_bridge::{EnclaveProxy, MempoolCheck};
 use crate::storage::{
     process_public_tx, verify_enclave_tx, TxAction, TxEnclaveAction, TxPublicAction,
 };
 use crate::tx_error::TxError;
 use abci::*;
+use chain_core::common::{decode_all_strict, decode_tx_in_block};
+use chain_core::init::config::block_budget_enforced;
 use chain_core::tx::data::TxId;
-use chain_core::tx::TxAux;
-use chain_storage::buffer::{StoreKV, StoreStaking};
-use parity_scale_codec::Decode;
+use chain_core::tx::{TxAux, TxEnclaveAux, TxObfuscated};
+use chain_storage::buffer::{Get, StoreKV, StoreStaking};
+use chain_tx_validation::{witness::verify_tx_recover_address, ChainInfo};
 
 /// Wrapper to abstract over CheckTx and DeliverTx requests
 pub trait RequestWithTx {
@@ -61,34 +65,124 @@ impl ResponseWithCodeAndLog for ResponseDeliverTx {
 }
 
 impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
+    /// Processes a transaction, returning it along with its action and the charged resource units
     pub fn process_tx(
         &mut self,
         req: &impl RequestWithTx,
         buffer_type: BufferType,
-    ) -> Result<(TxAux, TxAction), TxError> {
+    ) -> Result<(TxAux, TxAction, u64), TxError> {
         let extra_info = self.tx_extra_info(req.tx().len());
+        // trailing bytes are rejected from the mempool, but only from their app version in blocks
+        let txaux = match buffer_type {
+            BufferType::Mempool => decode_all_strict::<TxAux>(req.tx())?,
+            BufferType::Consensus => decode_tx_in_block::<TxAux>(req.tx())?,
+        };
+        let units = self.charge_block_budget(&txaux, req.tx().len(), buffer_type)?;
+        let txid = txaux.tx_id();
+        let mut trace = self.tx_tracer.start(&txid, buffer_type);
+        if let Some(trace) = trace.as_mut() {
+            trace.record(TraceStep::extra_info(&extra_info));
+        }
+        let result = self.execute_tx(&txaux, &txid, &extra_info, buffer_type, &mut trace);
+        if let Some(mut trace) = trace {
+            trace.record(TraceStep::Outcome {
+                error: result.as_ref().err().map(ToString::to_string),
+            });
+            if let Err(e) = store_tx_trace(&self.storage, &txid, trace) {
+                log::error!("failed to store trace of tx {}: {}", hex::encode(&txid), e);
+            }
+        }
+        Ok((txaux, result?, units))
+    }
+
+    /// Charges the resource units of a transaction to the block budget (consensus connection,
+    /// from `BLOCK_BUDGET_APP_VERSION`), or only checks that it fits into a block (mempool connection: the estimate is reported
+    /// as `gas_wanted`, so that the proposer can pick the transactions which fit)
+    fn charge_block_budget(
+        &mut self,
+        txaux: &TxAux,
+        encoded_size: usize,
+        buffer_type: BufferType,
+    ) -> Result<u64, TxError> {
+        let state = match buffer_type {
+            BufferType::Consensus => self.last_state.as_ref().expect("expect last_state"),
+            BufferType::Mempool => self.mempool_state.as_ref().expect("expect mempool_state"),
+        };
+        let budget = state.top_level.network_params.get_block_budget();
+        let units = TxResources::of_tx(txaux, encoded_size).units(&budget);
+        match buffer_type {
+            BufferType::Consensus if block_budget_enforced() => {
+                self.block_resources.charge(units, &budget)?
+            }
+            BufferType::Consensus => {}
+            BufferType::Mempool => BlockResources::check_single(units, &budget)?,
+        }
+        Ok(units)
+    }
+
+    fn execute_tx(
+        &mut self,
+        txaux: &TxAux,
+        txid: &TxId,
+        extra_info: &ChainInfo,
+        buffer_type: BufferType,
+        trace: &mut Option<TxTrace>,
+    ) -> Result<TxAction, TxError> {
         let state = match buffer_type {
             BufferType::Consensus => self.last_state.as_mut().expect("expect last_state"),
             BufferType::Mempool => self.mempool_state.as_mut().expect("expect mempool_state"),
         };
-        let txaux = TxAux::decode(&mut req.tx())?;
-        let txid = txaux.tx_id();
-        let tx_action = match &txaux {
+        let tx_action = match txaux {
             TxAux::MLSHandshake(_) => return Err(TxError::WIPMLSData),
             TxAux::EnclaveTx(tx) => {
-                let action = verify_enclave_tx(
-                    &mut self.tx_validator,
-                    &tx,
-                    &extra_info,
-                    &staking_getter!(self, state.staking_version, buffer_type),
-                    &kv_store!(self, buffer_type),
-                )?;
+                if let Some(trace) = trace.as_mut() {
+                    let staking = staking_getter!(self, state.staking_version, buffer_type);
+                    let address = match tx {
+                        TxEnclaveAux::DepositStakeTx { tx, .. } => Some(tx.to_staked_account),
+                        TxEnclaveAux::WithdrawUnbondedStakeTx {
+                            payload: TxObfuscated { txid, .. },
+                            witness,
+                            ..
+                        } => verify_tx_recover_address(&witness, &txid).ok(),
+                        TxEnclaveAux::TransferTx { .. } => None,
+                    };
+                    if let Some(address) = address {
+                        trace.record(TraceStep::staking_read(
+                            address,
+                            staking.get(&address).as_ref(),
+                        ));
+                    }
+                    trace.record(TraceStep::enclave_request(tx));
+                }
+                let result = match buffer_type {
+                    BufferType::Consensus => verify_enclave_tx(
+                        &mut self.tx_validator,
+                        &tx,
+                        extra_info,
+                        &staking_getter!(self, state.staking_version, buffer_type),
+                        &kv_store!(self, buffer_type),
+                    ),
+                    BufferType::Mempool => verify_enclave_tx(
+                        &mut MempoolCheck(&mut self.tx_validator),
+                        &tx,
+                        extra_info,
+                        &staking_getter!(self, state.staking_version, buffer_type),
+                        &kv_store!(self, buffer_type),
+                    ),
+                };
+                if let Some(trace) = trace.as_mut() {
+                    trace.record(TraceStep::EnclaveResponse {
+                        paid_fee: result.as_ref().ok().map(|action| action.fee().to_coin()),
+                        error: result.as_ref().err().map(ToString::to_string),
+                    });
+                }
+                let action = result?;
                 // execute the action
                 execute_enclave_tx(
                     &mut staking_store!(self, state.staking_version, buffer_type),
                     &mut kv_store!(self, buffer_type),
                     state,
-                    &txid,
+                    txid,
                     &action,
                 );
 
@@ -118,11 +212,18 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
                 TxAction::Enclave(action)
             }
             TxAux::PublicTx(tx) => {
+                if let Some(trace) = trace.as_mut() {
+                    let (address, nonce) = public_tx_nonce(tx);
+                    let staking =
+                        staking_getter!(self, state.staking_version, buffer_type).get(&address);
+                    trace.record(TraceStep::staking_read(address, staking.as_ref()));
+                    trace.record(TraceStep::nonce_check(nonce, staking.as_ref()));
+                }
                 let action = process_public_tx(
                     &mut staking_store!(self, state.staking_version, buffer_type),
                     &mut state.staking_table,
                     state.enclave_isv_svn,
-                    &extra_info,
+                    extra_info,
                     &tx,
                 )?;
 
@@ -133,7 +234,13 @@ impl<T: EnclaveProxy + 'static> ChainNodeApp<T> {
                 TxAction::Public(action)
             }
         };
-        Ok((txaux, tx_action))
+        if let Some(trace) = trace.as_mut() {
+            trace.record(TraceStep::Fee {
+                min_fee_computed: extra_info.min_fee_computed.to_coin(),
+                paid_fee: tx_action.fee().to_coin(),
+            });
+        }
+        Ok(tx_action)
     }
 }
 
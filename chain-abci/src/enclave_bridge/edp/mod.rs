@@ -3,6 +3,7 @@ pub mod tdbe;
 
 use crate::enclave_bridge::EnclaveProxy;
 use aesm_client::AesmClient;
+use chain_core::common::decode_all_strict;
 use chain_core::tx::TX_AUX_SIZE;
 use chain_storage::ReadOnlyStorage;
 use enclave_protocol::{IntraEnclaveRequest, IntraEnclaveResponse};
@@ -10,7 +11,7 @@ use enclave_runner::{
     usercalls::{AsyncListener, AsyncStream, UsercallExtension},
     EnclaveBuilder,
 };
-use parity_scale_codec::{Decode, Encode};
+use parity_scale_codec::Encode;
 use sgxs_loaders::isgx::Device;
 use std::io::{Read, Write};
 use std::sync::{mpsc::channel, Arc, Mutex};
@@ -130,7 +131,7 @@ impl EnclaveProxy for TxValidationApp {
             .expect("write enclave request");
         let mut request_buf = vec![0u8; 2 * TX_AUX_SIZE];
         match stream.read(&mut request_buf) {
-            Ok(c) => match IntraEnclaveResponse::decode(&mut request_buf[..c].as_ref()) {
+            Ok(c) => match decode_all_strict::<IntraEnclaveResponse>(&request_buf[..c]) {
                 Ok(response) => response,
                 Err(e) => {
                     log::error!("enclave response decode error {:?}", e);
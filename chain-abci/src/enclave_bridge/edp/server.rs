@@ -1,5 +1,6 @@
 use crate::app::ChainNodeState;
 use crate::enclave_bridge::EnclaveProxy;
+use chain_core::common::decode_all_strict;
 use chain_core::state::account::StakedState;
 use chain_core::state::account::StakedStateOpWitness;
 use chain_core::tx::data::TxId;
@@ -77,7 +78,7 @@ impl<T: EnclaveProxy> TxValidationServer<T> {
         loop {
             if let Ok(r_len) = self.socket_to_enclave.read(&mut request) {
                 log::debug!("received a message");
-                let mcmd = EnclaveRequest::decode(&mut request[..r_len].as_ref());
+                let mcmd = decode_all_strict::<EnclaveRequest>(&request[..r_len]);
                 let resp = match mcmd {
                     Ok(EnclaveRequest::GetSealedTxData { txids }) => {
                         EnclaveResponse::GetSealedTxData(self.lookup_txids(txids.iter().copied()))
@@ -0,0 +1,336 @@
+//! Latencies and transient failures of the mock enclave (`MockClient`), so that the integration
+//! tests and the tx spammer can emulate a real enclave. All the random choices are drawn from
+//! a generator seeded by the profile, so a run can be reproduced.
+//!
+//! The profile is read from `MOCK_ENCLAVE_PROFILE` (inline YAML / JSON) or from the file in
+//! `MOCK_ENCLAVE_PROFILE_FILE`; without them (or with the default profile), the mock doesn't
+//! wait and doesn't fail.
+//!
+//! Only the transaction validation requests of the mempool checks (CheckTx) may fail: the
+//! failure of the other ones (init chain check, end block) isn't a transient condition for
+//! chain-abci, and the results of the delivered transactions must be the same on all the nodes.
+use std::env;
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+use enclave_protocol::IntraEnclaveRequest;
+use serde::{Deserialize, Serialize};
+
+use chain_core::tx::TxEnclaveAux;
+
+/// inline YAML / JSON profile
+pub const PROFILE_ENV: &str = "MOCK_ENCLAVE_PROFILE";
+/// path to a YAML / JSON profile
+pub const PROFILE_FILE_ENV: &str = "MOCK_ENCLAVE_PROFILE_FILE";
+
+/// Distribution of the latency added to the base one
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "distribution", rename_all = "snake_case")]
+pub enum Jitter {
+    /// no jitter
+    None,
+    /// uniform from 0 to `max_ms`
+    Uniform { max_ms: u64 },
+    /// exponential with the mean of `mean_ms`
+    Exponential { mean_ms: u64 },
+}
+
+impl Default for Jitter {
+    fn default() -> Self {
+        Jitter::None
+    }
+}
+
+/// Probability (from 0 to 1) that a mempool validation request of the transaction type fails
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FailureProbability {
+    pub transfer: f64,
+    pub deposit: f64,
+    pub withdraw: f64,
+}
+
+/// All the mempool validation requests from the `start`-th one (counted from 0) to the
+/// `start + length - 1`-th one fail
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FailureBurst {
+    pub start: u64,
+    pub length: u64,
+}
+
+/// Behaviour of the mock enclave
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FaultProfile {
+    /// seed of the random choices
+    pub seed: u64,
+    /// latency of every request
+    pub base_latency_ms: u64,
+    /// latency added to the base one
+    pub jitter: Jitter,
+    /// probability of a failure per transaction type
+    pub failure_probability: FailureProbability,
+    /// failures regardless of the probabilities
+    pub bursts: Vec<FailureBurst>,
+}
+
+impl FaultProfile {
+    /// Reads the profile from the environment (see the module documentation);
+    /// the default (inert) one if none is set
+    pub fn from_env() -> Result<Self, String> {
+        if let Ok(profile) = env::var(PROFILE_ENV) {
+            return Self::parse(&profile);
+        }
+        match env::var(PROFILE_FILE_ENV) {
+            Ok(path) => {
+                let profile = fs::read_to_string(&path)
+                    .map_err(|e| format!("failed to read {}: {}", path, e))?;
+                Self::parse(&profile)
+            }
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Parses a YAML / JSON profile
+    pub fn parse(profile: &str) -> Result<Self, String> {
+        let profile: FaultProfile = serde_yaml::from_str(profile)
+            .map_err(|e| format!("invalid mock enclave profile: {}", e))?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let FailureProbability {
+            transfer,
+            deposit,
+            withdraw,
+        } = self.failure_probability;
+        if [transfer, deposit, withdraw]
+            .iter()
+            .any(|p| !(0.0..=1.0).contains(p))
+        {
+            return Err("failure probabilities should be from 0 to 1".to_owned());
+        }
+        Ok(())
+    }
+
+    /// whether the requests are neither delayed nor failed
+    pub fn is_inert(&self) -> bool {
+        self.base_latency_ms == 0
+            && match self.jitter {
+                Jitter::None => true,
+                Jitter::Uniform { max_ms } => max_ms == 0,
+                Jitter::Exponential { mean_ms } => mean_ms == 0,
+            }
+            && self.failure_probability == FailureProbability::default()
+            && self.bursts.iter().all(|burst| burst.length == 0)
+    }
+}
+
+/// splitmix64 -- the sequence only depends on the seed (not on a version of a crate)
+#[derive(Debug, Clone)]
+struct SeededRng(u64);
+
+impl SeededRng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// uniform from [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// What happens to a request
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fault {
+    pub latency: Duration,
+    pub fail: bool,
+}
+
+impl Fault {
+    /// waits for the latency of the request
+    pub fn wait(&self) {
+        if self.latency > Duration::from_millis(0) {
+            thread::sleep(self.latency);
+        }
+    }
+}
+
+/// Draws the faults of the requests according to the profile
+#[derive(Debug, Clone)]
+pub struct FaultInjector {
+    profile: FaultProfile,
+    rng: SeededRng,
+    /// number of the mempool validation requests so far
+    validations: u64,
+}
+
+impl FaultInjector {
+    /// Starts drawing the faults from the seed of the profile
+    pub fn new(profile: FaultProfile) -> Self {
+        let rng = SeededRng(profile.seed);
+        FaultInjector {
+            profile,
+            rng,
+            validations: 0,
+        }
+    }
+
+    /// profile of the faults
+    pub fn profile(&self) -> &FaultProfile {
+        &self.profile
+    }
+
+    /// the fault of the next request made when delivering a block: only the latency, as its
+    /// failure would make the nodes disagree on the block results
+    pub fn next_consensus_fault(&mut self) -> Fault {
+        Fault {
+            latency: self.latency(),
+            fail: false,
+        }
+    }
+
+    /// the fault of the next request made when checking a transaction for the mempool
+    pub fn next_mempool_fault(&mut self, request: &IntraEnclaveRequest) -> Fault {
+        let latency = self.latency();
+        let probability = match request {
+            IntraEnclaveRequest::ValidateTx { request, .. } => match request.tx {
+                TxEnclaveAux::TransferTx { .. } => self.profile.failure_probability.transfer,
+                TxEnclaveAux::DepositStakeTx { .. } => self.profile.failure_probability.deposit,
+                TxEnclaveAux::WithdrawUnbondedStakeTx { .. } => {
+                    self.profile.failure_probability.withdraw
+                }
+            },
+            _ => {
+                return Fault {
+                    latency,
+                    fail: false,
+                }
+            }
+        };
+        Fault {
+            latency,
+            fail: self.validation_fails(probability),
+        }
+    }
+
+    fn latency(&mut self) -> Duration {
+        let jitter_ms = match self.profile.jitter {
+            Jitter::None => 0.0,
+            Jitter::Uniform { max_ms } => self.rng.next_f64() * max_ms as f64,
+            Jitter::Exponential { mean_ms } => -(mean_ms as f64) * (1.0 - self.rng.next_f64()).ln(),
+        };
+        Duration::from_millis(self.profile.base_latency_ms)
+            + Duration::from_secs_f64(jitter_ms / 1000.0)
+    }
+
+    fn validation_fails(&mut self, probability: f64) -> bool {
+        let index = self.validations;
+        self.validations += 1;
+        // always drawn, so that the bursts don't shift the rest of the sequence
+        let random_failure = self.rng.next_f64() < probability;
+        let in_burst = self
+            .profile
+            .bursts
+            .iter()
+            .any(|burst| index >= burst.start && index - burst.start < burst.length);
+        random_failure || in_burst
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failures(profile: &FaultProfile, count: usize) -> Vec<bool> {
+        let mut injector = FaultInjector::new(profile.clone());
+        (0..count)
+            .map(|_| injector.validation_fails(profile.failure_probability.transfer))
+            .collect()
+    }
+
+    fn transient_profile(seed: u64) -> FaultProfile {
+        FaultProfile {
+            seed,
+            failure_probability: FailureProbability {
+                transfer: 0.3,
+                ..Default::default()
+            },
+            bursts: vec![FailureBurst {
+                start: 100,
+                length: 10,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn seeded_profile_should_produce_same_failures() {
+        let profile = transient_profile(42);
+        let first = failures(&profile, 1000);
+        assert_eq!(first, failures(&profile, 1000));
+        assert_ne!(first, failures(&transient_profile(43), 1000));
+        assert!(first[100..110].iter().all(|fail| *fail));
+        let count = first.iter().filter(|fail| **fail).count();
+        assert!(count > 200 && count < 400, "{} failures", count);
+    }
+
+    #[test]
+    fn default_profile_should_be_inert() {
+        let profile = FaultProfile::default();
+        assert!(profile.is_inert());
+        let mut injector = FaultInjector::new(profile);
+        for _ in 0..100 {
+            assert!(!injector.validation_fails(0.0));
+            assert_eq!(Duration::from_millis(0), injector.latency());
+        }
+        assert!(!transient_profile(0).is_inert());
+    }
+
+    #[test]
+    fn latency_should_be_within_jitter() {
+        let mut injector = FaultInjector::new(FaultProfile {
+            base_latency_ms: 2,
+            jitter: Jitter::Uniform { max_ms: 8 },
+            ..Default::default()
+        });
+        for _ in 0..100 {
+            let latency = injector.latency();
+            assert!(latency >= Duration::from_millis(2) && latency <= Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn profile_should_parse() {
+        let profile = FaultProfile::parse(
+            "seed: 7\n\
+             base_latency_ms: 2\n\
+             jitter:\n  distribution: exponential\n  mean_ms: 3\n\
+             failure_probability:\n  transfer: 0.01\n\
+             bursts:\n  - start: 50\n    length: 5\n",
+        )
+        .unwrap();
+        assert_eq!(7, profile.seed);
+        assert_eq!(Jitter::Exponential { mean_ms: 3 }, profile.jitter);
+        assert_eq!(0.01, profile.failure_probability.transfer);
+        assert_eq!(0.0, profile.failure_probability.withdraw);
+        assert_eq!(
+            vec![FailureBurst {
+                start: 50,
+                length: 5
+            }],
+            profile.bursts
+        );
+        assert!(FaultProfile::parse("{}").unwrap().is_inert());
+        assert!(FaultProfile::parse("failure_probability: {transfer: 1.5}").is_err());
+        assert!(FaultProfile::parse("latency: 1").is_err());
+    }
+}
@@ -7,11 +7,13 @@ use chain_tx_validation::{
 use enclave_protocol::IntraEnclaveResponseOk;
 use mock_utils::{decrypt, seal, unseal};
 
+use super::fault_profile::{FaultInjector, FaultProfile};
 use super::*;
 
 pub struct MockClient {
     chain_hex_id: u8,
     filter: BlockFilter,
+    faults: Option<FaultInjector>,
 }
 
 impl Clone for MockClient {
@@ -20,6 +22,7 @@ impl Clone for MockClient {
             chain_hex_id: self.chain_hex_id,
             // incorrect, but this is a mock -- shouldn't matter
             filter: BlockFilter::default(),
+            faults: self.faults.clone(),
         }
     }
 }
@@ -33,35 +36,35 @@ impl MockClient {
         MockClient {
             chain_hex_id,
             filter: BlockFilter::default(),
+            faults: None,
         }
     }
 
-    fn add_view_keys(&mut self, plain_tx: &TxWithOutputs) {
-        match plain_tx {
-            TxWithOutputs::StakeWithdraw(tx) => {
-                for view in tx.attributes.allowed_view.iter() {
-                    self.filter.add_view_key(&view.view_key);
-                }
-            }
-            TxWithOutputs::Transfer(tx) => {
-                for view in tx.attributes.allowed_view.iter() {
-                    self.filter.add_view_key(&view.view_key);
-                }
-            }
+    /// mock which delays / fails the requests according to the profile
+    pub fn with_profile(chain_hex_id: u8, profile: FaultProfile) -> Self {
+        MockClient {
+            chain_hex_id,
+            filter: BlockFilter::default(),
+            faults: if profile.is_inert() {
+                None
+            } else {
+                Some(FaultInjector::new(profile))
+            },
         }
     }
-}
 
-impl EnclaveProxy for MockClient {
-    fn check_chain(&mut self, network_id: u8) -> Result<(), ()> {
-        if self.chain_hex_id == network_id {
-            Ok(())
-        } else {
-            Err(())
+    fn add_view_keys(&mut self, plain_tx: &TxWithOutputs) {
+        match plain_tx {
+            TxWithOutputs::StakeWithdraw(tx) => self
+                .filter
+                .extend_view_keys(tx.attributes.allowed_view.iter().map(|view| &view.view_key)),
+            TxWithOutputs::Transfer(tx) => self
+                .filter
+                .extend_view_keys(tx.attributes.allowed_view.iter().map(|view| &view.view_key)),
         }
     }
 
-    fn process_request(&mut self, request: IntraEnclaveRequest) -> IntraEnclaveResponse {
+    fn process(&mut self, request: IntraEnclaveRequest) -> IntraEnclaveResponse {
         match &request {
             IntraEnclaveRequest::InitChainCheck(network_id) => self
                 .check_chain(*network_id)
@@ -69,12 +72,14 @@ impl EnclaveProxy for MockClient {
                 .map_err(|_| Error::WrongChainHexId),
             IntraEnclaveRequest::EndBlock => {
                 let maybe_filter = if self.filter.is_modified() {
-                    Some(Box::new(self.filter.get_raw()))
+                    self.filter.get_raw().map(|raw| Some(Box::new(raw)))
                 } else {
-                    None
+                    Ok(None)
                 };
                 self.filter.reset();
-                Ok(IntraEnclaveResponseOk::EndBlock(maybe_filter))
+                maybe_filter
+                    .map(IntraEnclaveResponseOk::EndBlock)
+                    .map_err(|_| Error::EnclaveRejected)
             }
             IntraEnclaveRequest::Encrypt(_) => {
                 // In mock mode, client will do the encryption on their own.
@@ -162,3 +167,31 @@ impl EnclaveProxy for MockClient {
         }
     }
 }
+
+impl EnclaveProxy for MockClient {
+    fn check_chain(&mut self, network_id: u8) -> Result<(), ()> {
+        if self.chain_hex_id == network_id {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    fn process_request(&mut self, request: IntraEnclaveRequest) -> IntraEnclaveResponse {
+        if let Some(faults) = self.faults.as_mut() {
+            faults.next_consensus_fault().wait();
+        }
+        self.process(request)
+    }
+
+    fn process_mempool_request(&mut self, request: IntraEnclaveRequest) -> IntraEnclaveResponse {
+        if let Some(faults) = self.faults.as_mut() {
+            let fault = faults.next_mempool_fault(&request);
+            fault.wait();
+            if fault.fail {
+                return Err(chain_tx_validation::Error::EnclaveRejected);
+            }
+        }
+        self.process(request)
+    }
+}
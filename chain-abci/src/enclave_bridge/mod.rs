@@ -5,6 +5,8 @@ use enclave_protocol::{IntraEnclaveRequest, IntraEnclaveResponse};
 /// TODO: feature-guard when workspaces can be built with --features flag: https://github.com/rust-lang/cargo/issues/5015
 pub mod mock;
 
+pub mod fault_profile;
+
 #[cfg(all(not(feature = "mock-enclave"), feature = "edp", target_os = "linux"))]
 pub mod edp;
 
@@ -24,4 +26,22 @@ pub trait EnclaveProxy: Sync + Send + Sized {
     // sanity check for checking enclave initialization
     fn check_chain(&mut self, network_id: u8) -> Result<(), ()>;
     fn process_request(&mut self, request: IntraEnclaveRequest) -> IntraEnclaveResponse;
+    /// request made when checking a transaction for the mempool (CheckTx): unlike the ones
+    /// made when delivering a block, its response doesn't need to be the same on all the nodes
+    fn process_mempool_request(&mut self, request: IntraEnclaveRequest) -> IntraEnclaveResponse {
+        self.process_request(request)
+    }
+}
+
+/// Proxy which sends all the requests as mempool requests
+pub struct MempoolCheck<'a, T: EnclaveProxy>(pub &'a mut T);
+
+impl<'a, T: EnclaveProxy> EnclaveProxy for MempoolCheck<'a, T> {
+    fn check_chain(&mut self, network_id: u8) -> Result<(), ()> {
+        self.0.check_chain(network_id)
+    }
+
+    fn process_request(&mut self, request: IntraEnclaveRequest) -> IntraEnclaveResponse {
+        self.0.process_mempool_request(request)
+    }
 }
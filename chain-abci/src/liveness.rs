@@ -2,6 +2,7 @@ use bit_vec::BitVec;
 use parity_scale_codec::{Decode, Encode, Error, Input, Output};
 use serde::{Deserialize, Serialize};
 
+use chain_core::common::ConsensusCodec;
 use chain_core::state::tendermint::BlockHeight;
 
 /// Liveness tracker for a validator
@@ -72,10 +73,16 @@ impl Decode for LivenessTracker {
         let mut liveness = BitVec::from_bytes(&bytes);
         liveness.truncate(length as usize);
 
+        // reject what wouldn't be re-encoded identically (missing/extra bytes, non-zero padding)
+        if liveness.len() != length as usize || liveness.to_bytes() != bytes {
+            return Err(Error::from("Non-canonical liveness tracker encoding"));
+        }
         Ok(LivenessTracker { liveness })
     }
 }
 
+impl ConsensusCodec for LivenessTracker {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -92,6 +99,24 @@ mod tests {
         assert_eq!(initial, decoded);
     }
 
+    #[test]
+    fn check_liveness_tracker_non_canonical_decode() {
+        // 10 bits in 2 bytes
+        let canonical = (10u16, vec![0xffu8, 0xc0]).encode();
+        let decoded = LivenessTracker::decode(&mut canonical.as_ref()).unwrap();
+        assert_eq!(decoded.encode(), canonical);
+
+        // non-zero padding bits
+        let padding = (10u16, vec![0xffu8, 0xc1]).encode();
+        assert!(LivenessTracker::decode(&mut padding.as_ref()).is_err());
+        // extra byte
+        let extra = (10u16, vec![0xffu8, 0xc0, 0]).encode();
+        assert!(LivenessTracker::decode(&mut extra.as_ref()).is_err());
+        // missing byte
+        let missing = (10u16, vec![0xffu8]).encode();
+        assert!(LivenessTracker::decode(&mut missing.as_ref()).is_err());
+    }
+
     #[test]
     fn check_liveness_tracker() {
         let mut tracker = LivenessTracker::new();
@@ -1,3 +1,7 @@
+use chain_abci::app::crash_report::install_panic_hook;
+use chain_abci::app::startup_report::{ConfigSummary, StartupReport};
+use chain_abci::app::storage_check::{format_report, verify_storage, StorageCheckLevel};
+use chain_abci::app::tx_result_index::TxResultIndex;
 use chain_abci::app::{sanity_check_enabled, ChainNodeApp};
 #[cfg(all(not(feature = "mock-enclave"), feature = "edp", target_os = "linux"))]
 use chain_abci::enclave_bridge::edp::{
@@ -5,8 +9,11 @@ use chain_abci::enclave_bridge::edp::{
     TxValidationApp,
 };
 #[cfg(any(feature = "mock-enclave", not(target_os = "linux")))]
+use chain_abci::enclave_bridge::fault_profile::FaultProfile;
+#[cfg(any(feature = "mock-enclave", not(target_os = "linux")))]
 use chain_abci::enclave_bridge::mock::MockClient;
 use chain_abci::enclave_bridge::{EnclaveProxy, TdbeConfig};
+use chain_core::common::{H256, HASH_SIZE_256};
 use chain_core::init::network::{get_network, get_network_id, init_chain_id};
 use chain_storage::ReadOnlyStorage;
 use chain_storage::{Storage, StorageConfig, StorageType};
@@ -38,6 +45,14 @@ pub struct Config {
     launch_ra_proxy: bool,
     remote_attestation: SpRaConfig,
     data_bootstrap: TdbeConfig,
+    // enables the admin query paths (`query_stats/reset`, `trace/...`),
+    // only for nodes which don't expose Tendermint RPC publicly
+    #[serde(default)]
+    query_stats_admin: bool,
+    // number of the most recent blocks Tendermint keeps (and the retention of the tx result index),
+    // all blocks are kept if not set
+    #[serde(default)]
+    retain_blocks: Option<u64>,
 }
 
 impl Default for Config {
@@ -68,6 +83,8 @@ impl Default for Config {
                 ias_report_path: "/attestation/v4/report".into(),
             },
             data_bootstrap: TdbeConfig::default(),
+            query_stats_admin: false,
+            retain_blocks: None,
         }
     }
 }
@@ -176,6 +193,21 @@ pub struct AbciOpt {
         help = "Optional transaction query support for clients (tx query enclave listening address, e.g. mydomain.com:4444)"
     )]
     tx_query: Option<String>,
+    #[structopt(
+        long = "verify-storage",
+        help = "Verify consistency of the whole stored state (including all historical snapshots and stakings) before starting up"
+    )]
+    verify_storage: bool,
+    #[structopt(
+        long = "trace-tx",
+        help = "Records the validation steps of the transaction (hex-encoded txid) when it's processed in CheckTx or DeliverTx (retrievable via the local `trace/<txid>` query)"
+    )]
+    trace_tx: Option<String>,
+    #[structopt(
+        long = "index-tx-results",
+        help = "Indexes the results of the delivered transactions by txid (retrievable via the `txresult/<txid>` query)"
+    )]
+    index_tx_results: bool,
 }
 
 /// edp
@@ -201,7 +233,11 @@ fn get_enclave_proxy(config: &Config, storage: Arc<dyn KeyValueDB>) -> TxValidat
 #[cfg(any(feature = "mock-enclave", not(target_os = "linux")))]
 fn get_enclave_proxy(_config: &Config, _storage: Arc<dyn KeyValueDB>) -> MockClient {
     warn!("Using mock (non-enclave) infrastructure");
-    MockClient::new(get_network_id())
+    let profile = FaultProfile::from_env().expect("mock enclave profile");
+    if !profile.is_inert() {
+        warn!("Mock enclave profile: {:?}", profile);
+    }
+    MockClient::with_profile(get_network_id(), profile)
 }
 
 /// edp
@@ -297,7 +333,66 @@ fn main() {
 
             let host = config.host.parse().expect("invalid host");
             let addr = SocketAddr::new(host, config.port);
+            let trace_txid = match opt.trace_tx.as_ref().map(hex::decode) {
+                Some(Ok(txid)) if txid.len() == 32 => {
+                    let mut trace_txid = [0u8; 32];
+                    trace_txid.copy_from_slice(&txid);
+                    Some(trace_txid)
+                }
+                Some(_) => {
+                    error!("trace-tx should be a hex-encoded 32-byte txid");
+                    return;
+                }
+                None => None,
+            };
+            if config.retain_blocks == Some(0) {
+                error!("retain_blocks should be positive");
+                return;
+            }
+            StartupReport::new(ConfigSummary {
+                chain_id: config.chain_id.clone(),
+                network_id: format!("{:02X}", get_network_id()),
+                data_dir: opt.data.clone(),
+                listen_address: addr.to_string(),
+                tx_query: config.tx_query.clone(),
+                tx_query_listen: config.tx_query_listen.clone(),
+                remote_attestation_address: config.remote_attestation.address.clone(),
+                launch_ra_proxy: config.launch_ra_proxy,
+                query_stats_admin: config.query_stats_admin,
+                retain_blocks: config.retain_blocks,
+                index_tx_results: opt.index_tx_results,
+                verify_storage: opt.verify_storage,
+                sanity_checks: sanity_check_enabled(),
+            })
+            .log();
             let storage = Storage::new(&StorageConfig::new(&opt.data, StorageType::Node));
+            if opt.verify_storage {
+                info!("verifying storage");
+                let genesis_app_hash = match hex::decode(&config.genesis_app_hash) {
+                    Ok(decoded) if decoded.len() == HASH_SIZE_256 => {
+                        let mut genesis_app_hash = H256::default();
+                        genesis_app_hash.copy_from_slice(&decoded);
+                        genesis_app_hash
+                    }
+                    _ => {
+                        error!("genesis_app_hash should be a hex-encoded 32-byte hash");
+                        return;
+                    }
+                };
+                if let Err(inconsistencies) = verify_storage(
+                    &storage,
+                    &genesis_app_hash,
+                    &config.chain_id,
+                    StorageCheckLevel::Full,
+                ) {
+                    error!(
+                        "inconsistent storage, refusing to start:\n{}",
+                        format_report(&inconsistencies)
+                    );
+                    return;
+                }
+                info!("storage verified");
+            }
 
             let tx_validator = get_enclave_proxy(&config, storage.temp_hack_for_tdbe());
             if sanity_check_enabled() {
@@ -310,17 +405,27 @@ fn main() {
                 storage.get_read_only(),
             );
             info!("starting up");
-            abci::run(
-                addr,
-                ChainNodeApp::new_with_storage(
-                    tx_validator,
-                    &config.genesis_app_hash,
-                    &config.chain_id,
-                    storage,
-                    config.tx_query,
-                    config.data_bootstrap.external_listen_address,
-                ),
+            let mut app = ChainNodeApp::new_with_storage(
+                tx_validator,
+                &config.genesis_app_hash,
+                &config.chain_id,
+                storage,
+                config.tx_query,
+                config.data_bootstrap.external_listen_address,
             );
+            app.query_stats_admin = config.query_stats_admin;
+            app.retain_blocks = config.retain_blocks;
+            if opt.index_tx_results {
+                info!("indexing tx results");
+                app.tx_result_index = TxResultIndex::new(true);
+            }
+            if let Some(txid) = trace_txid {
+                info!("tracing tx {}", hex::encode(&txid));
+                app.tx_tracer.arm(txid);
+            }
+            // fatal errors (panics) leave a crash report in the data directory
+            install_panic_hook(PathBuf::from(&opt.data), app.crash_context.clone(), true);
+            abci::run(addr, app);
         }
     }
 }
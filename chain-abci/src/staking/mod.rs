@@ -8,6 +8,7 @@ mod tests {
     use secp256k1::key::{PublicKey, SecretKey};
     use std::str::FromStr;
 
+    use chain_core::common::ConsensusCodec;
     use chain_core::init::address::RedeemAddress;
     use chain_core::init::coin::Coin;
     use chain_core::init::config::SlashRatio;
@@ -15,10 +16,13 @@ mod tests {
     use chain_core::state::account::{
         NodeState, PunishmentKind, StakedState, StakedStateAddress, UnbondTx, UnjailTx, Validator,
     };
-    use chain_core::state::tendermint::{BlockHeight, TendermintValidatorPubKey};
+    use chain_core::state::tendermint::{
+        BlockHeight, TendermintValidatorPubKey, TendermintVotePower,
+    };
     use chain_core::state::validator::NodeJoinRequestTx;
     use chain_core::tx::fee::Fee;
     use chain_storage::buffer::{Get, GetStaking, MemStore, StoreStaking};
+    use parity_scale_codec::{Decode, Encode};
     use test_common::chain_env::{
         get_init_network_params, mock_council_node_join, mock_council_node_meta,
         DEFAULT_GENESIS_TIME,
@@ -26,6 +30,7 @@ mod tests {
 
     use super::*;
     use crate::app::BeginBlockInfo;
+    use crate::liveness::LivenessTracker;
     use crate::staking::table::{PunishmentOutcome, SlashedCoin};
     use crate::tx_error::{
         DepositError, NodeJoinError, PublicTxError, UnbondError, UnjailError, WithdrawError,
@@ -99,6 +104,25 @@ mod tests {
         )
     }
 
+    #[test]
+    fn check_audit() {
+        let (table, mut store) = init_staking_table();
+        assert!(table.audit(&store).is_ok());
+
+        let addr = staking_address(&[0xcc; 32]);
+        let mut staking = store.get(&addr).unwrap();
+        staking.bonded = Coin::new(1).unwrap();
+        store.set_staking(staking.clone());
+        assert!(table.audit(&store).unwrap_err().contains("bonded amount"));
+
+        staking.node_meta = None;
+        store.set_staking(staking);
+        assert!(table
+            .audit(&store)
+            .unwrap_err()
+            .contains("no council node record"));
+    }
+
     #[test]
     fn check_choose_validators() {
         let (mut table, mut store) = init_staking_table();
@@ -297,6 +321,52 @@ mod tests {
         assert!(!staking.is_jailed());
     }
 
+    #[test]
+    fn check_unbond_minimum_remainder() {
+        let (mut table, mut store) = init_staking_table();
+        let minimum = Coin::new(1_0000_0000).unwrap();
+        table.set_minimum_bond_remainder(minimum);
+        let addr = staking_address(&[0xcc; 32]);
+        let fee = Fee::new(Coin::new(10).unwrap());
+        let unbond = |store: &StakingMemStore, remaining: Coin| {
+            let staking = store.get(&addr).unwrap();
+            UnbondTx {
+                from_staked_account: addr,
+                nonce: staking.nonce,
+                value: ((staking.bonded - remaining).unwrap() - fee.to_coin()).unwrap(),
+                attributes: Default::default(),
+            }
+        };
+
+        // just below the minimum (the fee is unbonded too)
+        let remaining = (minimum - Coin::unit()).unwrap();
+        let tx = unbond(&store, remaining);
+        let result = table.unbond(&mut store, 10, 2, 3.into(), &tx, fee);
+        assert!(matches!(
+            result,
+            Err(PublicTxError::Unbond(UnbondError::RemainderTooSmall { remaining: r, minimum: m }))
+                if r == remaining && m == minimum
+        ));
+
+        // exactly the minimum
+        let tx = unbond(&store, minimum);
+        table.unbond(&mut store, 10, 2, 3.into(), &tx, fee).unwrap();
+        assert_eq!(minimum, store.get(&addr).unwrap().bonded);
+
+        // dust left
+        let tx = unbond(&store, Coin::unit());
+        assert!(matches!(
+            table.unbond(&mut store, 10, 2, 3.into(), &tx, fee),
+            Err(PublicTxError::Unbond(UnbondError::RemainderTooSmall { .. }))
+        ));
+
+        // unbond everything
+        let tx = unbond(&store, Coin::zero());
+        table.unbond(&mut store, 10, 2, 3.into(), &tx, fee).unwrap();
+        assert_eq!(Coin::zero(), store.get(&addr).unwrap().bonded);
+        assert!(table.audit(&store).is_ok());
+    }
+
     fn unbond_deposit_rejoin(
         table: &mut StakingTable,
         store: &mut impl StoreStaking,
@@ -839,4 +909,26 @@ mod tests {
         );
         assert!(staking.is_jailed());
     }
+
+    #[test]
+    fn check_staking_table_reencoding() {
+        let (table, _) = init_staking_table();
+        let encoded = table.encode();
+        assert_eq!(
+            StakingTable::decode_canonical(&encoded).unwrap().encode(),
+            encoded
+        );
+
+        // the same maps, with validator addresses in reversed order
+        type Maps = (
+            Vec<(StakedStateAddress, TendermintVotePower)>,
+            Vec<(StakedStateAddress, LivenessTracker)>,
+            Vec<(StakedStateAddress, u64)>,
+        );
+        let (mut validators, liveness, stats) = Maps::decode(&mut encoded.as_slice()).unwrap();
+        assert!(validators.len() > 1);
+        validators.reverse();
+        let unsorted = (validators, liveness, stats).encode();
+        assert!(StakingTable::decode(&mut unsorted.as_slice()).is_err());
+    }
 }
@@ -6,15 +6,15 @@ use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use core::cmp::Ordering;
 use itertools::Itertools;
-use parity_scale_codec::{Decode, Encode};
+use parity_scale_codec::{Decode, Encode, Error, Input};
 use serde::{Deserialize, Serialize};
 
-use chain_core::common::Timespec;
+use chain_core::common::{decode_sorted_map, ConsensusCodec, Timespec};
 use chain_core::init::coin::{sum_coins, Coin, CoinError, CoinResult};
 use chain_core::init::config::SlashRatio;
 use chain_core::state::account::{
-    NodeName, NodeSecurityContact, NodeState, PunishmentKind, SlashRecord, StakedState,
-    StakedStateAddress,
+    NodeEndpoint, NodeName, NodeSecurityContact, NodeState, PunishmentKind, SlashRecord,
+    StakedState, StakedStateAddress,
 };
 use chain_core::state::tendermint::{
     BlockHeight, TendermintValidatorAddress, TendermintValidatorPubKey, TendermintVotePower,
@@ -39,6 +39,8 @@ pub struct CouncilNodeMetadata {
     pub security_contact: NodeSecurityContact,
     /// Tendermint consensus validator-associated public key
     pub tendermint_pubkey: TendermintValidatorPubKey,
+    /// Announced "host:port" of the (sentry) nodes of validator
+    pub announced_endpoints: Vec<NodeEndpoint>,
 }
 
 /// order by bonded desc, staking_address
@@ -121,7 +123,7 @@ impl SlashedCoin {
 /// Invariant 2.4:
 ///   idx_* only contains CouncilNode not CommunityNode
 ///   Proof: checked during insertion
-#[derive(Clone, Debug, Default, Encode, Decode)]
+#[derive(Clone, Debug, Default, Encode)]
 pub struct StakingTable {
     // Selected validator voting powers of last executed end block
     chosen_validators: BTreeMap<StakedStateAddress, TendermintVotePower>,
@@ -132,12 +134,29 @@ pub struct StakingTable {
     // Keep the recent value of minimal_required_staking to do sanity check on validator states.
     #[codec(skip)]
     pub(crate) minimal_required_staking: Coin,
+    // Minimal bonded amount left by a partial unbonding (network parameter, zero: no minimum)
+    #[codec(skip)]
+    pub(crate) minimum_bond_remainder: Coin,
     #[codec(skip)]
     pub(crate) idx_validator_address: BTreeMap<TendermintValidatorAddress, StakedStateAddress>,
     #[codec(skip)]
     idx_sort: BTreeSet<ValidatorSortKey>,
 }
 
+// maps are decoded strictly sorted, so a decoded table is always re-encoded identically
+impl Decode for StakingTable {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        Ok(StakingTable {
+            chosen_validators: decode_sorted_map(input)?,
+            liveness: decode_sorted_map(input)?,
+            participator_stats: decode_sorted_map(input)?,
+            ..Default::default()
+        })
+    }
+}
+
+impl ConsensusCodec for StakingTable {}
+
 /// Returned if the caller did not do the necessary validations
 /// before inserting the validator record
 #[derive(Debug)]
@@ -173,6 +192,11 @@ impl StakingTable {
         tbl
     }
 
+    /// Sets the minimal bonded amount left by a partial unbonding (from the network parameters)
+    pub fn set_minimum_bond_remainder(&mut self, minimum_bond_remainder: Coin) {
+        self.minimum_bond_remainder = minimum_bond_remainder;
+    }
+
     /// After restored from storage, call initialize to populate the indexes
     pub fn initialize(&mut self, heap: &impl GetStaking, minimal_required_staking: Coin) {
         assert!(self.idx_sort.is_empty());
@@ -225,6 +249,23 @@ impl StakingTable {
         heap: &impl GetStaking,
         max_validators: usize,
     ) -> Vec<(TendermintValidatorPubKey, TendermintVotePower)> {
+        self.end_block_with_old_power(heap, max_validators)
+            .into_iter()
+            .map(|(pubkey, _, power)| (pubkey, power))
+            .collect()
+    }
+
+    /// Same as `end_block`, but each validator update also includes the voting power before it
+    /// (zero for the newly chosen validators)
+    pub fn end_block_with_old_power(
+        &mut self,
+        heap: &impl GetStaking,
+        max_validators: usize,
+    ) -> Vec<(
+        TendermintValidatorPubKey,
+        TendermintVotePower,
+        TendermintVotePower,
+    )> {
         let updates = self.update_validators(heap, max_validators);
         #[cfg(debug_assertions)]
         self.check_invariants(heap);
@@ -315,6 +356,7 @@ impl StakingTable {
                             staking_address: key.address,
                             security_contact: val.council_node.node_info.security_contact.clone(),
                             tendermint_pubkey: val.council_node.consensus_pubkey.clone(),
+                            announced_endpoints: val.council_node.announced_endpoints.clone(),
                         })
                     } else {
                         None
@@ -659,16 +701,94 @@ impl StakingTable {
         &mut self,
         heap: &impl GetStaking,
         max_validators: usize,
-    ) -> Vec<(TendermintValidatorPubKey, TendermintVotePower)> {
+    ) -> Vec<(
+        TendermintValidatorPubKey,
+        TendermintVotePower,
+        TendermintVotePower,
+    )> {
         let new = self.choose_validators(heap, max_validators);
         let updates = diff_validators(&self.chosen_validators, &new);
-        self.chosen_validators = new;
+        let old = std::mem::replace(&mut self.chosen_validators, new);
         updates
             .into_iter()
-            .map(|(addr, power)| (self.get_validator_pubkey(heap, &addr), power))
+            .map(|(addr, power)| {
+                let old_power = old
+                    .get(&addr)
+                    .copied()
+                    .unwrap_or_else(TendermintVotePower::zero);
+                (self.get_validator_pubkey(heap, &addr), old_power, power)
+            })
             .collect()
     }
 
+    /// Checks the table against the staking records on heap, returns description of the first
+    /// inconsistency found.
+    ///
+    /// Unlike `check_invariants`, it doesn't panic, so it can be used to diagnose persisted state,
+    /// it also works before the indexes are populated by `initialize`.
+    pub fn audit(&self, heap: &impl GetStaking) -> Result<(), String> {
+        // Invariant 2.3 + 2.2 + 2.4
+        for addr in self.liveness.keys() {
+            let staking = heap
+                .get(addr)
+                .ok_or_else(|| format!("tracked validator {} not found in staking trie", addr))?;
+            match staking.node_meta {
+                Some(NodeState::CouncilNode(_)) => {}
+                _ => {
+                    return Err(format!(
+                        "tracked validator {} has no council node record",
+                        addr
+                    ))
+                }
+            }
+        }
+        if self.idx_sort.is_empty() && self.idx_validator_address.is_empty() {
+            return Ok(());
+        }
+
+        // Invariant 2.1
+        for key in self.idx_sort.iter() {
+            let staking = heap.get(&key.address).ok_or_else(|| {
+                format!(
+                    "indexed validator {} not found in staking trie",
+                    key.address
+                )
+            })?;
+            if staking.bonded != key.bonded {
+                return Err(format!(
+                    "indexed validator {} has bonded amount {}, but {} in staking trie",
+                    key.address, key.bonded, staking.bonded
+                ));
+            }
+        }
+        for (val_addr, addr) in self.idx_validator_address.iter() {
+            match heap.get(addr).and_then(|staking| staking.node_meta) {
+                Some(NodeState::CouncilNode(val))
+                    if &val.validator_address() == val_addr
+                        || val
+                            .used_validator_addresses
+                            .iter()
+                            .any(|(addr, _)| addr == val_addr) => {}
+                _ => {
+                    return Err(format!(
+                        "validator address index doesn't match staking trie record of {}",
+                        addr
+                    ))
+                }
+            }
+        }
+        if self.liveness.keys().collect::<BTreeSet<_>>()
+            != self
+                .idx_sort
+                .iter()
+                .map(|key| &key.address)
+                .collect::<BTreeSet<_>>()
+        {
+            return Err("liveness tracking doesn't match validator index".to_owned());
+        }
+        Ok(())
+    }
+
     #[cfg(debug_assertions)]
     pub(crate) fn check_invariants(&mut self, heap: &impl GetStaking) {
         self.check_invariant2_1(heap);
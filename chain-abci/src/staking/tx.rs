@@ -6,14 +6,15 @@ use chain_core::state::account::{
     NodeMetadata, NodeState, StakedStateAddress, UnbondTx, UnjailTx, Validator,
 };
 use chain_core::state::tendermint::{BlockHeight, TendermintValidatorAddress};
-use chain_core::state::validator::NodeJoinRequestTx;
+use chain_core::state::validator::{NodeJoinRequestTx, UpdateNodeTx};
 use chain_core::tx::fee::Fee;
 use chain_storage::buffer::StoreStaking;
 use mls::{extras::check_nodejoin, DefaultCipherSuite};
 
 use super::table::{set_staking, StakingTable};
 use crate::tx_error::{
-    DepositError, NodeJoinError, PublicTxError, UnbondError, UnjailError, WithdrawError,
+    DepositError, NodeJoinError, PublicTxError, UnbondError, UnjailError, UpdateNodeError,
+    WithdrawError,
 };
 
 const MAX_USED_VALIDATOR_ADDR: usize = 10;
@@ -162,6 +163,39 @@ impl StakingTable {
         }
     }
 
+    /// Handle `UpdateNodeTx`, the fee is paid from the bonded amount
+    /// (the announced endpoints are informational, so jailed or inactive council nodes can update them)
+    pub fn update_node(
+        &mut self,
+        heap: &mut impl StoreStaking,
+        block_time: Timespec,
+        block_height: BlockHeight,
+        tx: &UpdateNodeTx,
+        fee: Fee,
+    ) -> Result<(), PublicTxError> {
+        let mut staking = self.get_or_default(heap, &tx.address);
+        if tx.nonce != staking.nonce {
+            return Err(PublicTxError::IncorrectNonce);
+        }
+        if !staking.has_council_node_meta() {
+            return Err(UpdateNodeError::NotCouncilNode.into());
+        }
+        self.sub_bonded(block_time, block_height, fee.to_coin(), &mut staking)
+            .map_err(UpdateNodeError::CoinError)?;
+
+        if let Some(NodeState::CouncilNode(val)) = staking.node_meta.as_mut() {
+            val.council_node.announced_endpoints = tx.announced_endpoints.clone();
+            staking.inc_nonce();
+            set_staking(heap, staking, self.minimal_required_staking);
+
+            #[cfg(debug_assertions)]
+            self.check_invariants(heap);
+            Ok(())
+        } else {
+            Err(UpdateNodeError::NotCouncilNode.into())
+        }
+    }
+
     /// Handle deposit tx
     /// Enclave validation is done in enclave, only incomplete check here.
     pub fn deposit(
@@ -205,13 +239,17 @@ impl StakingTable {
             return Err(UnbondError::ZeroValue.into());
         }
         let unbonded = (staking.unbonded + tx.value).map_err(UnbondError::CoinError)?;
-        self.sub_bonded(
-            block_time,
-            block_height,
-            (tx.value + fee_amount).map_err(UnbondError::CoinError)?,
-            &mut staking,
-        )
-        .map_err(UnbondError::CoinError)?;
+        let amount = (tx.value + fee_amount).map_err(UnbondError::CoinError)?;
+        let remaining = (staking.bonded - amount).map_err(UnbondError::CoinError)?;
+        if remaining != Coin::zero() && remaining < self.minimum_bond_remainder {
+            return Err(UnbondError::RemainderTooSmall {
+                remaining,
+                minimum: self.minimum_bond_remainder,
+            }
+            .into());
+        }
+        self.sub_bonded(block_time, block_height, amount, &mut staking)
+            .map_err(UnbondError::CoinError)?;
         staking.unbonded = unbonded;
 
         let unbonded_from = block_time.saturating_add(unbonding_period);
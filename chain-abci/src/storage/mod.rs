@@ -4,7 +4,8 @@ use crate::tx_error::PublicTxError;
 use chain_core::common::Timespec;
 use chain_core::init::coin::Coin;
 use chain_core::state::account::{
-    CouncilNodeMeta, NodeMetadata, StakedStateAddress, StakedStateOpAttributes,
+    announced_endpoints_supported, CouncilNodeMeta, NodeMetadata, StakedStateAddress,
+    StakedStateOpAttributes,
 };
 use chain_core::tx::data::input::{TxoPointer, TxoSize};
 use chain_core::tx::fee::Fee;
@@ -125,6 +126,10 @@ pub enum TxPublicAction {
         isv_svn: u16,
     },
     Unjail(StakedStateAddress),
+    UpdateNode {
+        fee: Fee,
+        address: StakedStateAddress,
+    },
 }
 
 impl TxPublicAction {
@@ -145,12 +150,16 @@ impl TxPublicAction {
     fn unjail(staking_address: StakedStateAddress) -> Self {
         Self::Unjail(staking_address)
     }
+    fn update_node(fee: Fee, address: StakedStateAddress) -> Self {
+        Self::UpdateNode { fee, address }
+    }
 
     pub fn fee(&self) -> Fee {
         match self {
             Self::Unbond { fee, .. } => *fee,
             Self::NodeJoin { .. } => Fee::new(Coin::zero()),
             Self::Unjail(_) => Fee::new(Coin::zero()),
+            Self::UpdateNode { fee, .. } => *fee,
         }
     }
 
@@ -159,6 +168,7 @@ impl TxPublicAction {
             Self::Unbond { unbond, .. } => Some(unbond.0),
             Self::NodeJoin { address, .. } => Some(*address),
             Self::Unjail(staking_address) => Some(*staking_address),
+            Self::UpdateNode { address, .. } => Some(*address),
         }
     }
 }
@@ -358,5 +368,28 @@ pub fn process_public_tx(
                 isv_svn,
             ))
         }
+        // TODO: delay checking witness, as address is contained in Tx?
+        TxPublicAux::UpdateNodeTx(maintx, witness) => {
+            if !announced_endpoints_supported() {
+                return Err(PublicTxError::UnsupportedVersion);
+            }
+            let address = verify_tx_recover_address(&witness, &maintx.id())?;
+            if address != maintx.address {
+                return Err(PublicTxError::StakingWitnessNotMatch);
+            }
+
+            staking_table.update_node(
+                staking_store,
+                chain_info.block_time,
+                chain_info.block_height,
+                maintx,
+                chain_info.min_fee_computed,
+            )?;
+
+            Ok(TxPublicAction::update_node(
+                chain_info.min_fee_computed,
+                address,
+            ))
+        }
     }
 }
@@ -11,6 +11,26 @@ pub enum TxError {
     Public(#[from] PublicTxError),
     #[error("FIXME/WIP payload for MLS handshake (not yet supported)")]
     WIPMLSData,
+    #[error(
+        "block budget exceeded: {used} units used in block, tx needs {units}, maximum is {max}"
+    )]
+    BlockBudgetExceeded { used: u64, units: u64, max: u64 },
+}
+
+/// response code of rejected transactions
+pub const TX_ERROR_CODE: u32 = 1;
+/// response code of transactions which don't fit into the block budget
+/// (they may fit into a later block)
+pub const BLOCK_BUDGET_EXCEEDED_CODE: u32 = 2;
+
+impl TxError {
+    /// code of CheckTx / DeliverTx response
+    pub fn code(&self) -> u32 {
+        match self {
+            TxError::BlockBudgetExceeded { .. } => BLOCK_BUDGET_EXCEEDED_CODE,
+            _ => TX_ERROR_CODE,
+        }
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -31,6 +51,8 @@ pub enum PublicTxError {
     NodeJoin(#[from] NodeJoinError),
     #[error("unbond tx process failed: {0}")]
     Unbond(#[from] UnbondError),
+    #[error("update node tx process failed: {0}")]
+    UpdateNode(#[from] UpdateNodeError),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -61,6 +83,14 @@ pub enum NodeJoinError {
     WIPNotValidator,
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum UpdateNodeError {
+    #[error("the staking address is not a council node")]
+    NotCouncilNode,
+    #[error("coin error in update node tx: {0}")]
+    CoinError(#[from] CoinError),
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum WithdrawError {
     #[error("unbonded amount {0} not equal to desired amount: {0}")]
@@ -81,6 +111,8 @@ pub enum UnbondError {
     IsJailed,
     #[error("the value of tx is zero")]
     ZeroValue,
+    #[error("the remaining bonded amount {remaining} is below the minimum {minimum} (unbond everything instead)")]
+    RemainderTooSmall { remaining: Coin, minimum: Coin },
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -1,9 +1,17 @@
 use abci::*;
 use bit_vec::BitVec;
+use chain_abci::app::filter_range::get_filter_range;
+use chain_abci::app::query_stats::{QueryPath, QueryPathStats};
+use chain_abci::app::tx_result_index::TxResultIndex;
+use chain_abci::app::tx_trace::{TraceRequest, TraceStep, TxTrace};
 use chain_abci::app::*;
+use chain_abci::enclave_bridge::fault_profile::{FailureBurst, FailureProbability, FaultProfile};
 use chain_abci::enclave_bridge::mock::MockClient;
 use chain_abci::staking::StakingTable;
-use chain_core::common::{MerkleTree, Proof, H256, HASH_SIZE_256};
+use chain_core::common::{
+    MerkleTree, Proof, TendermintEventKey, TendermintEventType, H256, HASH_SIZE_256,
+    STRICT_TX_DECODING_APP_VERSION,
+};
 use chain_core::compute_app_hash;
 use chain_core::init::address::RedeemAddress;
 use chain_core::init::coin::Coin;
@@ -14,8 +22,9 @@ use chain_core::init::config::{
     JailingParameters, RewardsParameters, SlashRatio, SlashingParameters,
 };
 use chain_core::state::account::{
-    DepositBondTx, NodeState, StakedState, StakedStateAddress, StakedStateDestination,
-    StakedStateOpAttributes, StakedStateOpWitness, UnbondTx, WithdrawUnbondedTx,
+    announced_endpoints_supported, DepositBondTx, NodeState, StakedState, StakedStateAddress,
+    StakedStateDestination, StakedStateOpAttributes, StakedStateOpWitness, UnbondTx,
+    WithdrawUnbondedTx,
 };
 use chain_core::state::tendermint::{
     BlockHeight, TendermintValidatorAddress, TendermintValidatorPubKey, TendermintVotePower,
@@ -23,6 +32,7 @@ use chain_core::state::tendermint::{
 use chain_core::state::validator::NodeJoinRequestTx;
 use chain_core::state::{ChainState, RewardsPoolState};
 use chain_core::tx::fee::{LinearFee, Milli};
+use chain_core::tx::result::{TxResult, TX_RESULT_DISABLED_CODE, TX_RESULT_NOT_FOUND_CODE};
 use chain_core::tx::witness::tree::RawXOnlyPubkey;
 use chain_core::tx::witness::EcdsaSignature;
 use chain_core::tx::{
@@ -37,17 +47,14 @@ use chain_core::tx::{
     witness::{TxInWitness, TxWitness},
     PlainTxAux, TransactionId, TxAux, TxEnclaveAux, TxPublicAux,
 };
-use chain_storage::buffer::Get;
+use chain_storage::buffer::{flush_storage, BufferStore, Get, KVBuffer};
 use chain_storage::jellyfish::SparseMerkleProof;
-use chain_storage::{
-    LookupItem, Storage, CHAIN_ID_KEY, COL_EXTRA, COL_NODE_INFO, GENESIS_APP_HASH_KEY,
-    LAST_STATE_KEY, NUM_COLUMNS,
-};
-use chain_tx_filter::BlockFilter;
+use chain_storage::{store_genesis_state, LookupItem, Storage, NUM_COLUMNS};
+use chain_tx_filter::{BlockFilter, FilterRange, FILTER_RANGE_PATH};
 use hex::decode;
 use kvdb::KeyValueDB;
 use kvdb_memorydb::create;
-use mock_utils::encrypt;
+use mock_utils::{encrypt, encrypt_payload};
 use parity_scale_codec::{Decode, Encode};
 use secp256k1::schnorrsig::schnorr_sign;
 use secp256k1::{key::PublicKey, key::SecretKey, key::XOnlyPublicKey, Message, Secp256k1, Signing};
@@ -85,16 +92,22 @@ fn create_db_with_state_history() -> Arc<dyn KeyValueDB> {
     let decoded_gah = decode(EXAMPLE_HASH).unwrap();
     let mut genesis_app_hash = [0u8; HASH_SIZE_256];
     genesis_app_hash.copy_from_slice(&decoded_gah[..]);
-    let mut inittx = db.transaction();
-    inittx.put(COL_NODE_INFO, GENESIS_APP_HASH_KEY, &genesis_app_hash);
-    inittx.put(
-        COL_NODE_INFO,
-        LAST_STATE_KEY,
-        &get_dummy_app_state(genesis_app_hash).encode(),
-    );
-    inittx.put(COL_EXTRA, CHAIN_ID_KEY, TEST_CHAIN_ID.as_bytes());
+    let mut storage = Storage::new_db(db.clone());
+    storage.write_genesis_chain_id(&genesis_app_hash, TEST_CHAIN_ID);
 
-    db.write(inittx).unwrap();
+    let account_root = storage.put_stakings(
+        0,
+        &[StakedState::default(StakedStateAddress::BasicRedeem(
+            RedeemAddress([0u8; 20]),
+        ))],
+    );
+    let mut kv_buffer = KVBuffer::new();
+    store_genesis_state(
+        &mut BufferStore::new(&storage, &mut kv_buffer),
+        &get_dummy_app_state(account_root),
+        false,
+    );
+    flush_storage(&mut storage, kv_buffer).unwrap();
     db
 }
 
@@ -182,8 +195,8 @@ fn nonhex_hash_should_panic() {
     );
 }
 
-fn get_dummy_network_params() -> NetworkParameters {
-    NetworkParameters::Genesis(InitNetworkParameters {
+fn get_dummy_init_network_params() -> InitNetworkParameters {
+    InitNetworkParameters {
         initial_fee_policy: LinearFee::new(
             Milli::try_new(1, 1).unwrap(),
             Milli::try_new(1, 1).unwrap(),
@@ -207,14 +220,19 @@ fn get_dummy_network_params() -> NetworkParameters {
             monetary_expansion_decay: 999_860,
         },
         max_validators: 2,
-    })
+    }
+}
+
+fn get_dummy_network_params() -> NetworkParameters {
+    NetworkParameters::Genesis(get_dummy_init_network_params())
 }
 
-fn get_dummy_app_state(app_hash: H256) -> ChainNodeState {
+fn get_dummy_app_state(account_root: H256) -> ChainNodeState {
     let params = get_dummy_network_params();
+    let rewards_pool = RewardsPoolState::new(0, params.get_rewards_monetary_expansion_tau());
     ChainNodeState {
         last_block_height: BlockHeight::genesis(),
-        last_apphash: app_hash,
+        last_apphash: compute_app_hash(&MerkleTree::empty(), &account_root, &rewards_pool, &params),
         block_time: 0,
         block_height: BlockHeight::genesis(),
         genesis_time: 0,
@@ -224,8 +242,8 @@ fn get_dummy_app_state(app_hash: H256) -> ChainNodeState {
         utxo_coins: Coin::zero(),
         enclave_isv_svn: 0,
         top_level: ChainState {
-            account_root: [0u8; 32],
-            rewards_pool: RewardsPoolState::new(0, params.get_rewards_monetary_expansion_tau()),
+            account_root,
+            rewards_pool,
             network_params: params,
         },
     }
@@ -266,7 +284,7 @@ fn init_chain_for(address: RedeemAddress) -> ChainNodeApp<MockClient> {
     .iter()
     .cloned()
     .collect();
-    let NetworkParameters::Genesis(params) = get_dummy_network_params();
+    let params = get_dummy_init_network_params();
     let mut nodes = BTreeMap::new();
     let pub_key =
         TendermintValidatorPubKey::from_base64(b"MDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDA=")
@@ -501,6 +519,135 @@ fn check_tx_should_accept_valid_tx() {
     assert_eq!(0, cresp.code, "{}", cresp.log);
 }
 
+#[test]
+fn check_tx_should_reject_trailing_bytes() {
+    let (mut app, txaux, _) = prepare_app_valid_tx();
+    let mut creq = RequestCheckTx::default();
+    let mut tx = txaux.encode();
+    tx.extend_from_slice(&[0xde, 0xad]);
+    creq.set_tx(tx);
+    let cresp = app.check_tx(&creq);
+    assert_ne!(0, cresp.code);
+}
+
+#[test]
+fn deliver_tx_should_ignore_trailing_bytes_before_their_app_version() {
+    let (mut app, txaux, _) = prepare_app_valid_tx();
+    begin_block(&mut app);
+    let mut creq = RequestDeliverTx::default();
+    let mut tx = txaux.encode();
+    tx.extend_from_slice(&[0xde, 0xad]);
+    creq.set_tx(tx);
+    let cresp = app.deliver_tx(&creq);
+    if chain_core::APP_VERSION >= STRICT_TX_DECODING_APP_VERSION {
+        assert_ne!(0, cresp.code);
+    } else {
+        assert_eq!(0, cresp.code, "{}", cresp.log);
+    }
+}
+
+#[test]
+fn check_tx_should_reject_trailing_bytes_in_enclave_payload() {
+    // the (decrypted) payloads are decoded the same way in the mempool and in blocks
+    if chain_core::APP_VERSION < STRICT_TX_DECODING_APP_VERSION {
+        return;
+    }
+    let (mut app, txaux, tx) = prepare_app_valid_tx();
+    let txaux = match txaux {
+        TxAux::EnclaveTx(TxEnclaveAux::WithdrawUnbondedStakeTx {
+            no_of_outputs,
+            witness,
+            mut payload,
+        }) => {
+            // the mock payload is followed by 16 bytes of padding
+            let mut txpayload = encrypt_payload(&PlainTxAux::WithdrawUnbondedStakeTx(tx));
+            let end = txpayload.len() - 16;
+            txpayload.splice(end..end, vec![0xde, 0xad]);
+            payload.txpayload = txpayload;
+            TxAux::EnclaveTx(TxEnclaveAux::WithdrawUnbondedStakeTx {
+                no_of_outputs,
+                witness,
+                payload,
+            })
+        }
+        _ => unreachable!(),
+    };
+    let mut creq = RequestCheckTx::default();
+    creq.set_tx(txaux.encode());
+    let cresp = app.check_tx(&creq);
+    assert_ne!(0, cresp.code);
+}
+
+#[test]
+fn transient_enclave_failures_should_only_reject_mempool_checks() {
+    let (mut app, txaux, tx) = prepare_app_valid_tx();
+    let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+    let addr = RedeemAddress::from(&PublicKey::from_secret_key(
+        secp256k1::SECP256K1,
+        &secret_key,
+    ));
+    let unbonded = get_account(&addr, &app).unwrap().unbonded;
+    // the first mempool validation fails (in a burst), the next ones with the probability of 1/2
+    let profile = FaultProfile {
+        seed: 1,
+        failure_probability: FailureProbability {
+            withdraw: 0.5,
+            ..Default::default()
+        },
+        bursts: vec![FailureBurst {
+            start: 0,
+            length: 1,
+        }],
+        ..Default::default()
+    };
+    app.tx_validator = MockClient::with_profile(0, profile.clone());
+
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        assert!(
+            attempts <= 20,
+            "the transaction should be accepted eventually"
+        );
+        let mut creq = RequestCheckTx::default();
+        creq.set_tx(txaux.encode());
+        if app.check_tx(&creq).code == 0 {
+            break;
+        }
+        // the rejected transaction left no trace in the committed state
+        let account = get_account(&addr, &app).unwrap();
+        assert_eq!(unbonded, account.unbonded);
+        assert_eq!(0, account.nonce);
+    }
+    assert!(attempts > 1);
+
+    // delivering a block is never failed, even when all the mempool checks would fail
+    app.tx_validator = MockClient::with_profile(
+        0,
+        FaultProfile {
+            failure_probability: FailureProbability {
+                withdraw: 1.0,
+                ..Default::default()
+            },
+            ..profile
+        },
+    );
+    begin_block(&mut app);
+    let mut dreq = RequestDeliverTx::default();
+    dreq.set_tx(txaux.encode());
+    assert_eq!(0, app.deliver_tx(&dreq).code);
+    let mut endreq = RequestEndBlock::default();
+    endreq.set_height(1);
+    app.end_block(&endreq);
+    app.commit(&RequestCommit::default());
+
+    assert_eq!(Coin::zero(), get_account(&addr, &app).unwrap().unbonded);
+    assert!(app
+        .storage
+        .lookup_item(LookupItem::TxMetaSpent, &tx.id())
+        .is_some());
+}
+
 #[test]
 #[should_panic]
 fn two_beginblocks_should_panic() {
@@ -638,18 +785,54 @@ fn deliver_tx_should_add_tx_events() {
 
     let staking_event = &cresp.events[1];
     assert_eq!(2, valid_tx_event.attributes.len());
+    // attributes are sorted by key
+    let keys = staking_event
+        .attributes
+        .iter()
+        .map(|kv_pair| kv_pair.key.clone())
+        .collect::<Vec<_>>();
+    let mut sorted_keys = keys.clone();
+    sorted_keys.sort();
+    assert_eq!(keys, sorted_keys);
+    let staking_address = staking_event
+        .attributes
+        .iter()
+        .find(|kv_pair| kv_pair.key == TendermintEventKey::StakingAddress.to_vec())
+        .expect("staking address of withdraw event");
     assert_eq!(
         "0x89aef553a06ab0c3173e79de1ce241a9ed3b992c",
-        String::from_utf8(staking_event.attributes[0].value.clone()).unwrap()
+        String::from_utf8(staking_address.value.clone()).unwrap()
     );
+    let staking_optype = staking_event
+        .attributes
+        .iter()
+        .find(|kv_pair| kv_pair.key == TendermintEventKey::StakingOpType.to_vec())
+        .expect("staking optype of withdraw event");
     assert_eq!(
         "withdraw",
-        String::from_utf8(staking_event.attributes[1].value.clone()).unwrap()
+        String::from_utf8(staking_optype.value.clone()).unwrap()
     );
+    let unbonded_delta = staking_event
+        .attributes
+        .iter()
+        .find(|kv_pair| kv_pair.key == TendermintEventKey::UnbondedDelta.to_vec())
+        .expect("unbonded delta of withdraw event");
     assert_eq!(
-        "[{\"key\":\"Unbonded\",\"value\":\"-9999999999999999999\"}]",
-        String::from_utf8(staking_event.attributes[2].value.clone()).unwrap()
+        "-9999999999999999999",
+        String::from_utf8(unbonded_delta.value.clone()).unwrap()
     );
+    let legacy_staking_diff = staking_event
+        .attributes
+        .iter()
+        .find(|kv_pair| kv_pair.key == TendermintEventKey::StakingDiff.to_vec());
+    if cfg!(feature = "legacy-event-keys") {
+        assert_eq!(
+            "[{\"key\":\"Unbonded\",\"value\":\"-9999999999999999999\"}]",
+            String::from_utf8(legacy_staking_diff.unwrap().value.clone()).unwrap()
+        );
+    } else {
+        assert!(legacy_staking_diff.is_none());
+    }
 }
 
 #[test]
@@ -737,6 +920,8 @@ fn valid_commit_should_persist() {
     ])
     .expect("sample pk");
     assert!(!filter.check_view_key(&sample));
+    assert!(filter.check_tx_id(&tx.id()));
+    assert!(!filter.check_tx_id(&[0; 32]));
 
     assert!(app
         .storage
@@ -781,6 +966,9 @@ fn valid_commit_should_persist() {
         &app.last_state.as_ref().unwrap().last_apphash[..],
         &cresp.data[..]
     );
+    let range = get_filter_range(&app.storage, BlockHeight::new(10)).unwrap();
+    assert!(range.matches_any(&[sample, tx.attributes.allowed_view[0].view_key]));
+    assert!(!range.matches_any(&[sample]));
     assert!(app
         .storage
         .lookup_item(
@@ -842,7 +1030,274 @@ fn staking_query_should_return_an_account() {
     );
 }
 
-fn block_commit_with_check(app: &mut ChainNodeApp<MockClient>, tx: TxAux, block_height: i64) {
+#[test]
+fn query_stats_should_account_queries_per_path() {
+    let addr = "fe7c045110b8dbf29765047380898919c5cb56f9";
+    let mut app = init_chain_for(addr.parse().unwrap());
+    let query = |app: &mut ChainNodeApp<MockClient>, path: &str, data: Vec<u8>| {
+        let mut qreq = RequestQuery::new();
+        qreq.path = path.into();
+        qreq.data = data;
+        app.query(&qreq)
+    };
+    for _ in 0..3 {
+        assert_eq!(
+            query(&mut app, "account", hex::decode(&addr).unwrap()).code,
+            0
+        );
+    }
+    assert_eq!(
+        query(&mut app, "staking", hex::decode(&addr).unwrap()).code,
+        0
+    );
+    assert_ne!(query(&mut app, "store", vec![0u8; 3]).code, 0);
+    assert_ne!(query(&mut app, "unknown", vec![]).code, 0);
+    assert_eq!(query(&mut app, "p2p/filter/id/abc", vec![]).code, 0);
+
+    assert_eq!(app.query_stats.get(QueryPath::Account).count, 3);
+    let qresp = query(&mut app, "query_stats", vec![]);
+    assert_eq!(qresp.code, 0);
+    let stats: BTreeMap<String, QueryPathStats> = serde_json::from_slice(&qresp.value).unwrap();
+    let counts = stats
+        .iter()
+        .map(|(path, stats)| (path.as_str(), (stats.count, stats.errors)))
+        .collect::<BTreeMap<_, _>>();
+    assert_eq!(
+        counts,
+        vec![
+            ("account", (3, 0)),
+            ("other", (1, 1)),
+            ("p2p", (1, 0)),
+            ("staking", (1, 0)),
+            ("store", (1, 1)),
+        ]
+        .into_iter()
+        .collect()
+    );
+
+    // reset is only allowed when the admin path is enabled
+    assert_ne!(query(&mut app, "query_stats/reset", vec![]).code, 0);
+    assert_eq!(app.query_stats.get(QueryPath::Account).count, 3);
+    app.query_stats_admin = true;
+    assert_eq!(query(&mut app, "query_stats/reset", vec![]).code, 0);
+    assert_eq!(app.query_stats.get(QueryPath::Account).count, 0);
+    assert_eq!(app.query_stats.get(QueryPath::QueryStats).count, 1);
+}
+
+#[test]
+fn queries_should_not_panic_on_invalid_inputs() {
+    let (env, storage) = ChainEnv::new(Coin::max(), Coin::zero(), 1);
+    let mut app = env.chain_node(storage);
+    let query = |app: &mut ChainNodeApp<MockClient>, path: &str, height: i64| {
+        let mut qreq = RequestQuery::new();
+        qreq.path = path.into();
+        qreq.data = vec![0xff; 20];
+        qreq.height = height;
+        app.query(&qreq)
+    };
+
+    // before init chain
+    assert_ne!(query(&mut app, "council-nodes", 0).code, 0);
+    assert_ne!(query(&mut app, "validator_owner", 0).code, 0);
+
+    let _rsp = app.init_chain(&env.req_init_chain());
+    app.tx_query_address = Some("127.0.0.1:3443".to_owned());
+    assert_ne!(query(&mut app, "state", -1).code, 0);
+    assert_eq!(query(&mut app, "council-nodes", 0).code, 0);
+}
+
+#[test]
+fn filter_range_query_should_return_rolled_up_blocks() {
+    let (env, storage) = ChainEnv::new(Coin::max(), Coin::zero(), 1);
+    let mut app = env.chain_node(storage);
+    let _rsp = app.init_chain(&env.req_init_chain());
+    let query = |app: &mut ChainNodeApp<MockClient>, height: i64| {
+        let mut qreq = RequestQuery::new();
+        qreq.path = FILTER_RANGE_PATH.into();
+        qreq.height = height;
+        app.query(&qreq)
+    };
+    assert_ne!(query(&mut app, 0).code, 0);
+
+    for height in 1..=2 {
+        app.begin_block(&env.req_begin_block(height, 0));
+        app.end_block(&RequestEndBlock {
+            height,
+            ..Default::default()
+        });
+        app.commit(&RequestCommit::new());
+    }
+    let qresp = query(&mut app, 0);
+    assert_eq!(qresp.code, 0);
+    let range = FilterRange::decode(&mut qresp.value.as_slice()).unwrap();
+    assert_eq!(
+        (BlockHeight::new(1), BlockHeight::new(2)),
+        (range.start_height, range.end_height)
+    );
+    assert!(range.filter.is_empty());
+    assert_eq!(query(&mut app, 1).value, qresp.value);
+    assert_ne!(query(&mut app, 3).code, 0);
+    assert_ne!(query(&mut app, -1).code, 0);
+    assert_eq!(app.query_stats.get(QueryPath::FilterRange).count, 5);
+}
+
+#[test]
+fn validator_owner_query_should_return_staking_address() {
+    let (env, storage) = ChainEnv::new(Coin::max(), Coin::zero(), 1);
+    let mut app = env.chain_node(storage);
+    let _rsp = app.init_chain(&env.req_init_chain());
+    let query = |app: &mut ChainNodeApp<MockClient>, data: Vec<u8>| {
+        let mut qreq = RequestQuery::new();
+        qreq.path = "validator_owner".into();
+        qreq.data = data;
+        app.query(&qreq)
+    };
+
+    let validator_address: [u8; 20] = (&env.validator_address(0)).into();
+    let qresp = query(&mut app, validator_address.to_vec());
+    assert_eq!(qresp.code, 0);
+    let owner = <Option<StakedStateAddress>>::decode(&mut qresp.value.as_slice()).unwrap();
+    assert_eq!(owner, Some(env.accounts[0].staking_address()));
+
+    let qresp = query(&mut app, vec![0xff; 20]);
+    assert_eq!(qresp.code, 0);
+    let owner = <Option<StakedStateAddress>>::decode(&mut qresp.value.as_slice()).unwrap();
+    assert_eq!(owner, None);
+
+    assert_ne!(query(&mut app, vec![0xff; 3]).code, 0);
+}
+
+#[test]
+fn armed_tx_trace_should_record_failing_unbond() {
+    let (env, storage) = ChainEnv::new(Coin::max(), Coin::zero(), 1);
+    let mut app = env.chain_node(storage);
+    let _rsp = app.init_chain(&env.req_init_chain());
+    let query = |app: &mut ChainNodeApp<MockClient>, path: &str, data: Vec<u8>| {
+        let mut qreq = RequestQuery::new();
+        qreq.path = path.into();
+        qreq.data = data;
+        app.query(&qreq)
+    };
+
+    // wrong nonce: the staked state's one is 0
+    let tx_aux = env.unbond_tx(Coin::unit(), 1, 0);
+    let txid = tx_aux.tx_id();
+    let trace_path = format!("trace/{}", hex::encode(&txid));
+
+    // tracing is only available via the admin paths
+    assert_ne!(query(&mut app, "trace/arm", txid.to_vec()).code, 0);
+    app.query_stats_admin = true;
+    assert_ne!(query(&mut app, &trace_path, vec![]).code, 0);
+    assert_eq!(query(&mut app, "trace/arm", txid.to_vec()).code, 0);
+
+    // not armed transactions aren't traced
+    let other = env.unbond_tx(Coin::unit(), 2, 0);
+    let rsp = app.check_tx(&RequestCheckTx {
+        tx: other.encode(),
+        ..Default::default()
+    });
+    assert_ne!(rsp.code, 0);
+    let other_path = format!("trace/{}", hex::encode(&other.tx_id()));
+    assert_ne!(query(&mut app, &other_path, vec![]).code, 0);
+
+    let rsp = app.check_tx(&RequestCheckTx {
+        tx: tx_aux.encode(),
+        ..Default::default()
+    });
+    assert_ne!(rsp.code, 0);
+
+    let qresp = query(&mut app, &trace_path, vec![]);
+    assert_eq!(qresp.code, 0);
+    let traces: Vec<TxTrace> = serde_json::from_slice(&qresp.value).unwrap();
+    assert_eq!(traces.len(), 1);
+    let trace = &traces[0];
+    assert_eq!(trace.request, TraceRequest::CheckTx);
+    assert!(matches!(trace.steps[0], TraceStep::ExtraInfo { .. }));
+    assert!(trace.steps.contains(&TraceStep::NonceCheck {
+        tx_nonce: 1,
+        staked_state_nonce: 0,
+        matches: false,
+    }));
+    match trace.steps.last() {
+        Some(TraceStep::Outcome { error: Some(_) }) => {}
+        step => panic!("unexpected last step: {:?}", step),
+    }
+
+    // disarmed: the trace isn't extended any more
+    assert_eq!(query(&mut app, "trace/disarm", vec![]).code, 0);
+    app.check_tx(&RequestCheckTx {
+        tx: tx_aux.encode(),
+        ..Default::default()
+    });
+    let qresp = query(&mut app, &trace_path, vec![]);
+    let traces: Vec<TxTrace> = serde_json::from_slice(&qresp.value).unwrap();
+    assert_eq!(traces.len(), 1);
+}
+
+#[test]
+fn indexed_tx_results_should_be_queried_by_txid() {
+    let (env, storage) =
+        ChainEnv::new_with_customizer(Coin::max(), Coin::zero(), 2, |parameters| {
+            parameters.required_council_node_stake = (Coin::max() / 10).unwrap();
+        });
+    let mut app = env.chain_node(storage);
+    let _rsp = app.init_chain(&env.req_init_chain());
+    let query = |app: &mut ChainNodeApp<MockClient>, txid: &TxId| {
+        let mut qreq = RequestQuery::new();
+        qreq.path = format!("txresult/{}", hex::encode(txid));
+        app.query(&qreq)
+    };
+
+    let accepted = env.unbond_tx(Coin::new(5000000000000000000).unwrap(), 0, 0);
+    // wrong nonce
+    let rejected = env.unbond_tx(Coin::unit(), 5, 1);
+    assert_eq!(
+        query(&mut app, &accepted.tx_id()).code,
+        TX_RESULT_DISABLED_CODE
+    );
+    app.tx_result_index = TxResultIndex::new(true);
+
+    app.begin_block(&env.req_begin_block(1, 0));
+    for tx in [&accepted, &rejected].iter() {
+        app.deliver_tx(&RequestDeliverTx {
+            tx: tx.encode(),
+            ..Default::default()
+        });
+    }
+    app.end_block(&RequestEndBlock {
+        height: 1,
+        ..Default::default()
+    });
+    app.commit(&RequestCommit::default());
+
+    let qresp = query(&mut app, &accepted.tx_id());
+    assert_eq!(qresp.code, 0);
+    let result = TxResult::decode(&mut qresp.value.as_slice()).unwrap();
+    assert_eq!(result.height, BlockHeight::new(1));
+    assert_eq!(result.index, 0);
+    assert!(result.is_accepted());
+    assert_eq!(
+        result.staking_address,
+        Some(env.accounts[0].staking_address())
+    );
+
+    let qresp = query(&mut app, &rejected.tx_id());
+    assert_eq!(qresp.code, 0);
+    let result = TxResult::decode(&mut qresp.value.as_slice()).unwrap();
+    assert_eq!(result.index, 1);
+    assert!(!result.is_accepted());
+    assert_eq!(result.fee, Coin::zero());
+
+    assert_eq!(query(&mut app, &[0xff; 32]).code, TX_RESULT_NOT_FOUND_CODE);
+    assert_eq!(app.query_stats.get(QueryPath::TxResult).count, 4);
+}
+
+/// delivers and commits the transaction in a block, returns the emitted block filter
+fn block_commit_with_check(
+    app: &mut ChainNodeApp<MockClient>,
+    tx: TxAux,
+    block_height: i64,
+) -> BlockFilter {
     let r = RequestInfo::default();
     let info_1 = app.info(&r);
     let app_last_state_1 = app.last_state.clone().unwrap();
@@ -860,7 +1315,17 @@ fn block_commit_with_check(app: &mut ChainNodeApp<MockClient>, tx: TxAux, block_
     println!("delivertx: {:?}", app.deliver_tx(&dreq));
     let mut breq = RequestEndBlock::default();
     breq.set_height(block_height);
-    println!("endblock: {:?}", app.end_block(&breq));
+    let end_block_resp = app.end_block(&breq);
+    println!("endblock: {:?}", end_block_resp);
+    let filter_event = end_block_resp
+        .events
+        .iter()
+        .find(|event| event.field_type == TendermintEventType::BlockFilter.to_string())
+        .expect("block filter should be emitted");
+    let filter = BlockFilter::try_from(filter_event.attributes[0].value.as_slice())
+        .expect("there should be a block filter");
+    // every delivered transaction is in the filter (whatever its type)
+    assert!(filter.check_tx_id(&tx.tx_id()));
     let info_2 = app.info(&r);
     let app_last_state_2 = app.last_state.clone().unwrap();
     let app_last_block_height_2 = app_last_state_2.last_block_height.value();
@@ -885,6 +1350,7 @@ fn block_commit_with_check(app: &mut ChainNodeApp<MockClient>, tx: TxAux, block_
     // app hash changed
     assert_ne!(info_2.last_block_app_hash, info_3.last_block_app_hash);
     assert_eq!(info_3.last_block_height as u64, app_last_block_height_3);
+    filter
 }
 pub fn get_account(
     account_address: &RedeemAddress,
@@ -952,7 +1418,8 @@ fn all_valid_tx_types_should_commit() {
         assert!(account.unbonded > Coin::zero());
         assert_eq!(account.nonce, 0);
     }
-    block_commit_with_check(&mut app, withdrawtx, 1);
+    let filter = block_commit_with_check(&mut app, withdrawtx, 1);
+    assert!(filter.check_staked_state_address(&StakedStateAddress::BasicRedeem(addr)));
 
     {
         let account = get_account(&addr, &app).expect("acount not exist");
@@ -1020,7 +1487,8 @@ fn all_valid_tx_types_should_commit() {
         assert_eq!(account.bonded, Coin::zero());
         assert_eq!(account.nonce, 1);
     }
-    block_commit_with_check(&mut app, depositx, 3);
+    let filter = block_commit_with_check(&mut app, depositx, 3);
+    assert!(filter.check_staked_state_address(&StakedStateAddress::BasicRedeem(addr)));
     {
         let spent_utxos0 = get_tx_meta(&txid, &app);
         assert!(spent_utxos0[0] && spent_utxos0[1]);
@@ -1054,7 +1522,8 @@ fn all_valid_tx_types_should_commit() {
         let account = get_account(&addr2, &app);
         assert!(account.is_none());
     }
-    block_commit_with_check(&mut app, depositx, 4);
+    let filter = block_commit_with_check(&mut app, depositx, 4);
+    assert!(filter.check_staked_state_address(&StakedStateAddress::BasicRedeem(addr2)));
     {
         let spent_utxos0 = get_tx_meta(txid, &app);
         assert!(spent_utxos0[0] && spent_utxos0[1] && spent_utxos0[2]);
@@ -1087,7 +1556,8 @@ fn all_valid_tx_types_should_commit() {
         );
         assert_eq!(account.nonce, 1);
     }
-    block_commit_with_check(&mut app, nodejointx, 5);
+    let filter = block_commit_with_check(&mut app, nodejointx, 5);
+    assert!(filter.check_staked_state_address(&StakedStateAddress::BasicRedeem(addr)));
     {
         let account = get_account(&addr, &app).expect("account not exist");
         assert!(account.node_meta.is_some());
@@ -1117,7 +1587,16 @@ fn all_valid_tx_types_should_commit() {
         assert_eq!(account.nonce, 2);
         account.bonded
     };
-    block_commit_with_check(&mut app, unbondtx, 6);
+    let filter = block_commit_with_check(&mut app, unbondtx, 6);
+    let staking_addresses = [
+        StakedStateAddress::BasicRedeem(addr),
+        StakedStateAddress::BasicRedeem(addr2),
+    ];
+    let checked = filter.check_staked_state_addresses(&staking_addresses);
+    assert!(checked[0]);
+    for (i, address) in staking_addresses.iter().enumerate() {
+        assert_eq!(checked[i], filter.check_staked_state_address(address));
+    }
     {
         let account = get_account(&addr, &app).expect("account not exist");
         assert_eq!(account.unbonded, Coin::unit());
@@ -1130,6 +1609,63 @@ fn all_valid_tx_types_should_commit() {
     }
 }
 
+#[test]
+fn validators_query_should_return_updated_endpoints() {
+    // node updates are only accepted from their app version
+    if !announced_endpoints_supported() {
+        return;
+    }
+    let (env, storage) = ChainEnv::new(Coin::max(), Coin::zero(), 2);
+    let mut app = env.chain_node(storage);
+    let _rsp = app.init_chain(&env.req_init_chain());
+
+    app.begin_block(&env.req_begin_block(1, 0));
+    let endpoints = vec!["sentry.example.com:26656".to_owned()];
+    let tx_aux = env.update_node_tx(endpoints.clone(), 0, 0);
+    let rsp_tx = app.deliver_tx(&RequestDeliverTx {
+        tx: tx_aux.encode(),
+        ..Default::default()
+    });
+    assert_eq!(0, rsp_tx.code);
+    let _response_end_block = app.end_block(&RequestEndBlock {
+        height: 1,
+        ..Default::default()
+    });
+    app.commit(&RequestCommit::default());
+
+    let mut qreq = RequestQuery::new();
+    qreq.path = "validators".into();
+    let qresp = app.query(&qreq);
+    assert_eq!(0, qresp.code);
+    let validators: serde_json::Value = serde_json::from_slice(&qresp.value).unwrap();
+    let announced = &validators["announced_endpoints"];
+    assert_eq!(
+        announced[env.accounts[0].staking_address().to_string()],
+        serde_json::json!(endpoints)
+    );
+    assert_eq!(
+        announced[env.accounts[1].staking_address().to_string()],
+        serde_json::json!([])
+    );
+    // the proposer statistics are returned next to the endpoints
+    let mut fields = validators
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect::<Vec<_>>();
+    fields.sort();
+    assert_eq!(
+        fields,
+        vec!["announced_endpoints", "current_period", "last_period"]
+    );
+    // the node didn't start at a rewards distribution
+    assert_eq!(
+        validators["current_period"]["from_period_start"],
+        serde_json::json!(false)
+    );
+}
+
 #[test]
 fn query_should_return_proof_for_committed_tx() {
     let (env, storage) =
@@ -0,0 +1,100 @@
+use abci::*;
+use chain_abci::app::block_budget::TxResources;
+use chain_abci::app::ChainNodeApp;
+use chain_abci::enclave_bridge::mock::MockClient;
+use chain_abci::tx_error::BLOCK_BUDGET_EXCEEDED_CODE;
+use chain_core::init::coin::Coin;
+use chain_core::init::config::{block_budget_enforced, DEFAULT_BLOCK_BUDGET};
+use parity_scale_codec::Encode;
+use test_common::chain_env::ChainEnv;
+
+fn deliver(app: &mut ChainNodeApp<MockClient>, tx: Vec<u8>) -> ResponseDeliverTx {
+    app.deliver_tx(&RequestDeliverTx {
+        tx,
+        ..Default::default()
+    })
+}
+
+/// unbond txs (more than fit into a block), their units and the number of them fitting
+fn unbond_txs(env: &ChainEnv) -> (Vec<Vec<u8>>, u64, usize) {
+    let unbond_txs = (0..)
+        .map(|nonce| env.unbond_tx(Coin::unit(), nonce, 0).encode())
+        .take(3000)
+        .collect::<Vec<_>>();
+    let units = TxResources::of_tx(&env.unbond_tx(Coin::unit(), 0, 0), unbond_txs[0].len())
+        .units(&DEFAULT_BLOCK_BUDGET);
+    let fitting = (DEFAULT_BLOCK_BUDGET.max_block_units / units) as usize;
+    assert!(fitting < unbond_txs.len());
+    (unbond_txs, units, fitting)
+}
+
+#[test]
+fn block_budget_should_not_be_enforced_before_its_app_version() {
+    if block_budget_enforced() {
+        return;
+    }
+    let (env, storage) = ChainEnv::new(Coin::max(), Coin::zero(), 1);
+    let (unbond_txs, _, fitting) = unbond_txs(&env);
+    let mut app = env.chain_node(storage);
+    let _rsp = app.init_chain(&env.req_init_chain());
+
+    app.begin_block(&env.req_begin_block(1, 0));
+    for tx in unbond_txs.iter().take(fitting + 1) {
+        let rsp = deliver(&mut app, tx.clone());
+        assert_eq!(0, rsp.code, "{}", rsp.log);
+    }
+    assert_eq!(fitting + 1, app.delivered_txs.len());
+}
+
+#[test]
+fn block_filled_to_budget_should_reject_overflow_tx_on_all_nodes() {
+    if !block_budget_enforced() {
+        return;
+    }
+    let (unbond_txs, units, fitting) = unbond_txs(&ChainEnv::new(Coin::max(), Coin::zero(), 1).0);
+
+    let mut app_hashes = Vec::new();
+    for _node in 0..2 {
+        let (env, storage) = ChainEnv::new(Coin::max(), Coin::zero(), 1);
+        let mut app = env.chain_node(storage);
+        let _rsp = app.init_chain(&env.req_init_chain());
+
+        // check tx estimates, but the cumulative budget isn't enforced in the mempool
+        for tx in unbond_txs.iter().take(fitting + 1) {
+            let rsp = app.check_tx(&RequestCheckTx {
+                tx: tx.clone(),
+                ..Default::default()
+            });
+            assert_eq!(units as i64, rsp.gas_wanted);
+        }
+
+        app.begin_block(&env.req_begin_block(1, 0));
+        for tx in unbond_txs.iter().take(fitting) {
+            let rsp = deliver(&mut app, tx.clone());
+            assert_eq!(0, rsp.code, "{}", rsp.log);
+            assert_eq!(units as i64, rsp.gas_used);
+        }
+        assert_eq!(fitting as u64 * units, app.block_resources.used());
+
+        let rsp = deliver(&mut app, unbond_txs[fitting].clone());
+        assert_eq!(BLOCK_BUDGET_EXCEEDED_CODE, rsp.code);
+        assert!(rsp.log.contains("block budget exceeded"));
+        assert_eq!(fitting, app.delivered_txs.len());
+        app.end_block(&RequestEndBlock {
+            height: 1,
+            ..Default::default()
+        });
+        app.commit(&RequestCommit::default());
+
+        // the budget is reset in the next block
+        app.begin_block(&env.req_begin_block(2, 0));
+        let rsp = deliver(&mut app, unbond_txs[fitting].clone());
+        assert_eq!(0, rsp.code, "{}", rsp.log);
+        app.end_block(&RequestEndBlock {
+            height: 2,
+            ..Default::default()
+        });
+        app_hashes.push(app.commit(&RequestCommit::default()).data);
+    }
+    assert_eq!(app_hashes[0], app_hashes[1]);
+}
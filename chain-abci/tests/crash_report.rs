@@ -0,0 +1,85 @@
+use std::fs;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::thread;
+
+use abci::*;
+use chain_abci::app::crash_report::{
+    install_panic_hook, CrashContext, CrashReport, CRASH_REPORT_PREFIX,
+};
+use chain_core::init::coin::Coin;
+use parity_scale_codec::Encode;
+use test_common::chain_env::ChainEnv;
+
+#[test]
+fn abort_in_block_should_write_crash_report() {
+    let dir = std::env::temp_dir().join(format!("chain-abci-crash-report-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    let (env, storage) =
+        ChainEnv::new_with_customizer(Coin::max(), Coin::zero(), 2, |parameters| {
+            parameters.required_council_node_stake = (Coin::max() / 10).unwrap();
+        });
+    let mut app = env.chain_node(storage);
+    let _rsp = app.init_chain(&env.req_init_chain());
+    install_panic_hook(dir.clone(), app.crash_context.clone(), false);
+
+    app.begin_block(&env.req_begin_block(1, 0));
+    let tx_aux = env.unbond_tx((Coin::max() / 10).unwrap(), 0, 0);
+    let rsp_tx = app.deliver_tx(&RequestDeliverTx {
+        tx: tx_aux.encode(),
+        ..Default::default()
+    });
+    assert_eq!(0, rsp_tx.code);
+
+    // deterministic abort: begin block request without a header
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        app.begin_block(&RequestBeginBlock::default())
+    }));
+    assert!(result.is_err());
+    // the aborted consensus request isn't in progress anymore
+    assert!(!app.crash_context.in_consensus_request());
+
+    let report_files = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with(CRASH_REPORT_PREFIX)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(1, report_files.len());
+    let payload = fs::read_to_string(&report_files[0]).unwrap();
+    let report: CrashReport = serde_json::from_str(&payload).unwrap();
+
+    assert_eq!(report.chain_id, env.req_init_chain().chain_id);
+    assert!(report.error.contains("No block header"));
+    assert!(report.location.is_some());
+    assert_eq!(report.in_flight_block_height, Some(1));
+    assert_eq!(report.delivered_txids, vec![hex::encode(tx_aux.tx_id())]);
+    let last_committed = report.last_committed.unwrap();
+    assert_eq!(last_committed.block_height, 0);
+    assert_eq!(last_committed.app_hash, hex::encode(env.genesis_app_hash));
+
+    // no transaction payloads
+    assert!(!payload.contains(&hex::encode(tx_aux.encode())));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn only_consensus_requests_should_be_fatal() {
+    let context = CrashContext::new("test-chain", None);
+    assert!(!context.in_consensus_request());
+    {
+        let _consensus_request = context.consensus_request();
+        assert!(context.in_consensus_request());
+        // e.g. the mempool connection
+        let other = context.clone();
+        assert!(!thread::spawn(move || other.in_consensus_request())
+            .join()
+            .unwrap());
+    }
+    assert!(!context.in_consensus_request());
+}
@@ -1,4 +1,5 @@
 use abci::*;
+use chain_core::common::{TendermintEventKey, TendermintEventType};
 use chain_core::init::coin::Coin;
 use chain_core::state::account::NodeState;
 use parity_scale_codec::Encode;
@@ -165,6 +166,70 @@ fn begin_block_should_slash_byzantine_validators() {
     assert_eq!(0, response_end_block.validator_updates.to_vec()[0].power);
 }
 
+fn attribute_value(event: &Event, key: TendermintEventKey) -> Option<String> {
+    event
+        .attributes
+        .iter()
+        .find(|kv_pair| kv_pair.key == key.to_vec())
+        .map(|kv_pair| String::from_utf8(kv_pair.value.clone()).unwrap())
+}
+
+#[test]
+fn begin_block_should_emit_typed_slash_amounts() {
+    // Init Chain
+    let (env, storage) = ChainEnv::new(Coin::max(), Coin::zero(), 1);
+    let mut app = env.chain_node(storage);
+    let _rsp_init_chain = app.init_chain(&env.req_init_chain());
+
+    // Begin Block
+    let response_begin_block = app.begin_block(&RequestBeginBlock {
+        byzantine_validators: vec![env.byzantine_evidence(0)].into(),
+        ..env.req_begin_block(1, 0)
+    });
+
+    let staking_events = response_begin_block
+        .events
+        .iter()
+        .filter(|event| event.field_type == TendermintEventType::StakingChange.to_string())
+        .collect::<Vec<_>>();
+    let slash_event = staking_events
+        .iter()
+        .find(|event| {
+            attribute_value(event, TendermintEventKey::StakingOpType).as_deref() == Some("slash")
+        })
+        .expect("slash event");
+    let jail_event = staking_events
+        .iter()
+        .find(|event| {
+            attribute_value(event, TendermintEventKey::StakingOpType).as_deref() == Some("jail")
+        })
+        .expect("jail event");
+
+    let account = get_account(&env.accounts[0].staking_address(), &app);
+    let slash_amount = account.last_slash.unwrap().amount;
+    assert_eq!(
+        Some(u64::from(slash_amount).to_string()),
+        attribute_value(slash_event, TendermintEventKey::SlashedAmount)
+    );
+    let bonded_delta = attribute_value(slash_event, TendermintEventKey::BondedDelta).unwrap();
+    let unbonded_delta = attribute_value(slash_event, TendermintEventKey::UnbondedDelta).unwrap();
+    assert!(bonded_delta.starts_with('-') && unbonded_delta.starts_with('-'));
+    assert_eq!(
+        u64::from(slash_amount),
+        bonded_delta[1..].parse::<u64>().unwrap() + unbonded_delta[1..].parse::<u64>().unwrap()
+    );
+    // slashed amount is never reported as a fee or a reward
+    assert_eq!(None, attribute_value(slash_event, TendermintEventKey::Fee));
+    assert_eq!(
+        None,
+        attribute_value(slash_event, TendermintEventKey::RewardAmount)
+    );
+    assert_eq!(
+        None,
+        attribute_value(jail_event, TendermintEventKey::SlashedAmount)
+    );
+}
+
 #[test]
 fn begin_block_should_slash_non_live_validators() {
     // Init Chain
@@ -0,0 +1,108 @@
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use abci::*;
+use chain_abci::app::query_cache::QUERY_CACHE_PATH;
+use chain_core::init::coin::Coin;
+use chain_storage::Storage;
+use kvdb::{DBTransaction, DBValue, KeyValueDB};
+use parity_util_mem::{MallocSizeOf, MallocSizeOfOps};
+use test_common::chain_env::ChainEnv;
+
+/// Key-value store which counts the reads of the underlying store
+struct CountingDb {
+    inner: Arc<dyn KeyValueDB>,
+    reads: Arc<AtomicUsize>,
+}
+
+impl MallocSizeOf for CountingDb {
+    fn size_of(&self, ops: &mut MallocSizeOfOps) -> usize {
+        self.inner.size_of(ops)
+    }
+}
+
+impl KeyValueDB for CountingDb {
+    fn get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        self.inner.get(col, key)
+    }
+
+    fn get_by_prefix(&self, col: u32, prefix: &[u8]) -> Option<Box<[u8]>> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        self.inner.get_by_prefix(col, prefix)
+    }
+
+    fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+        self.inner.write(transaction)
+    }
+
+    fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        self.inner.iter(col)
+    }
+
+    fn iter_with_prefix<'a>(
+        &'a self,
+        col: u32,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        self.inner.iter_with_prefix(col, prefix)
+    }
+
+    fn restore(&self, new_db: &str) -> io::Result<()> {
+        self.inner.restore(new_db)
+    }
+}
+
+fn account_query(data: Vec<u8>) -> RequestQuery {
+    RequestQuery {
+        path: "account".to_owned(),
+        data,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn repeated_queries_should_read_store_once_per_block() {
+    let (env, storage) = ChainEnv::new(Coin::max(), Coin::zero(), 1);
+    let reads = Arc::new(AtomicUsize::new(0));
+    let storage = Storage::new_db(Arc::new(CountingDb {
+        inner: storage.temp_hack_for_tdbe(),
+        reads: reads.clone(),
+    }));
+    let mut app = env.chain_node(storage);
+    let _rsp = app.init_chain(&env.req_init_chain());
+
+    let req = account_query(env.accounts[0].address.0.to_vec());
+    let before = reads.load(Ordering::SeqCst);
+    let first = app.query(&req);
+    assert_eq!(0, first.code);
+    let after_first = reads.load(Ordering::SeqCst);
+    assert!(after_first > before);
+    for _ in 0..5 {
+        assert_eq!(first, app.query(&req));
+    }
+    assert_eq!(after_first, reads.load(Ordering::SeqCst));
+
+    // the cache is dropped on commit
+    app.begin_block(&env.req_begin_block(1, 0));
+    app.end_block(&RequestEndBlock {
+        height: 1,
+        ..Default::default()
+    });
+    app.commit(&RequestCommit::default());
+    let after_commit = reads.load(Ordering::SeqCst);
+    assert_eq!(first.value, app.query(&req).value);
+    assert!(reads.load(Ordering::SeqCst) > after_commit);
+
+    let stats = app.query(&RequestQuery {
+        path: QUERY_CACHE_PATH.to_owned(),
+        ..Default::default()
+    });
+    let stats: serde_json::Value = serde_json::from_slice(&stats.value).unwrap();
+    assert_eq!(stats["hits"], 5);
+    assert_eq!(stats["misses"], 2);
+    assert_eq!(stats["committed_height"], 1);
+}
@@ -0,0 +1,312 @@
+use abci::*;
+use chain_abci::app::storage_check::{
+    format_report, verify_storage, StorageCheckLevel, StorageInconsistency,
+};
+use chain_abci::app::{ChainNodeApp, ChainNodeState};
+use chain_abci::enclave_bridge::mock::MockClient;
+use chain_core::common::{MerkleTree, H256};
+use chain_core::compute_app_hash;
+use chain_core::init::coin::Coin;
+use chain_core::state::tendermint::BlockHeight;
+use chain_storage::buffer::{flush_storage, BufferStore, Get, KVBuffer};
+use chain_storage::{
+    LookupItem, Storage, COL_APP_HASHS, COL_NODE_INFO, COL_STAKING_VERSIONS, LAST_STATE_KEY,
+};
+use parity_scale_codec::{Decode, Encode};
+use test_common::chain_env::{get_enclave_bridge_mock, ChainEnv};
+
+const TEST_CHAIN_ID: &str = "test-00";
+
+/// Node with the genesis and one block (with an unbond transaction) committed
+fn committed_app() -> (ChainEnv, ChainNodeApp<MockClient>) {
+    let (env, storage) = ChainEnv::new(Coin::max(), Coin::zero(), 2);
+    let mut app = env.chain_node(storage);
+    let _rsp = app.init_chain(&env.req_init_chain());
+
+    app.begin_block(&env.req_begin_block(1, 0));
+    let rsp_tx = app.deliver_tx(&RequestDeliverTx {
+        tx: env.unbond_tx(Coin::unit(), 0, 0).encode(),
+        ..Default::default()
+    });
+    assert_eq!(0, rsp_tx.code);
+    app.end_block(&RequestEndBlock {
+        height: 1,
+        ..Default::default()
+    });
+    app.commit(&RequestCommit::new());
+    (env, app)
+}
+
+fn last_state(app: &ChainNodeApp<MockClient>) -> ChainNodeState {
+    app.last_state.clone().unwrap()
+}
+
+fn write_raw(app: &ChainNodeApp<MockClient>, col: u32, key: &[u8], value: Option<&[u8]>) {
+    let db = app.storage.temp_hack_for_tdbe();
+    let mut tx = db.transaction();
+    match value {
+        Some(value) => tx.put(col, key, value),
+        None => tx.delete(col, key),
+    }
+    db.write(tx).unwrap();
+}
+
+/// Overwrites only the last state (as if the other writes were lost in a crash)
+fn store_last_state(app: &ChainNodeApp<MockClient>, state: &ChainNodeState) {
+    write_raw(app, COL_NODE_INFO, LAST_STATE_KEY, Some(&state.encode()));
+}
+
+/// Stores the last state with app hash recomputed, together with its snapshots (as in commit)
+fn recommit_last_state(app: &mut ChainNodeApp<MockClient>, mut state: ChainNodeState) {
+    let old_app_hash = last_state(app).last_apphash;
+    let tree = app
+        .storage
+        .lookup_item(LookupItem::TxsMerkle, &old_app_hash)
+        .map(|data| MerkleTree::<H256>::decode(&mut data.as_slice()).unwrap())
+        .unwrap();
+    state.last_apphash = compute_app_hash(
+        &tree,
+        &state.top_level.account_root,
+        &state.top_level.rewards_pool,
+        &state.top_level.network_params,
+    );
+
+    let mut kv_buffer = KVBuffer::new();
+    let mut store = BufferStore::new(&app.storage, &mut kv_buffer);
+    chain_storage::store_txs_merkle_tree(&mut store, &state.last_apphash, &tree.encode());
+    chain_storage::store_chain_state(&mut store, &state, state.last_block_height, false);
+    flush_storage(&mut app.storage, kv_buffer).unwrap();
+    app.last_state = Some(state);
+}
+
+fn verify(
+    env: &ChainEnv,
+    app: &ChainNodeApp<MockClient>,
+    level: StorageCheckLevel,
+) -> Result<(), Vec<StorageInconsistency>> {
+    verify_storage(&app.storage, &env.genesis_app_hash, TEST_CHAIN_ID, level)
+}
+
+fn assert_report_contains(result: Result<(), Vec<StorageInconsistency>>, message: &str) {
+    let report = format_report(&result.unwrap_err());
+    assert!(
+        report.contains(message),
+        "report doesn't contain {:?}:\n{}",
+        message,
+        report
+    );
+}
+
+#[test]
+fn check_consistent_storage() {
+    let (env, app) = committed_app();
+    assert!(verify(&env, &app, StorageCheckLevel::Quick).is_ok());
+    assert!(verify(&env, &app, StorageCheckLevel::Full).is_ok());
+}
+
+#[test]
+fn check_storage_without_last_state() {
+    let (env, storage) = ChainEnv::new(Coin::max(), Coin::zero(), 2);
+    assert!(verify_storage(
+        &storage,
+        &env.genesis_app_hash,
+        TEST_CHAIN_ID,
+        StorageCheckLevel::Full
+    )
+    .is_ok());
+}
+
+#[test]
+fn check_genesis_app_hash_mismatch() {
+    let (_env, app) = committed_app();
+    let result = verify_storage(
+        &app.storage,
+        &[0u8; 32],
+        TEST_CHAIN_ID,
+        StorageCheckLevel::Quick,
+    );
+    assert_report_contains(
+        result,
+        "does not match the provided genesis app hash: 0000000000000000000000000000000000000000000000000000000000000000",
+    );
+}
+
+#[test]
+fn check_chain_id_mismatch() {
+    let (env, app) = committed_app();
+    let result = verify_storage(
+        &app.storage,
+        &env.genesis_app_hash,
+        "other-01",
+        StorageCheckLevel::Quick,
+    );
+    assert_report_contains(
+        result,
+        "stored chain id: \"test-00\" does not match the provided chain id: \"other-01\"",
+    );
+}
+
+#[test]
+fn check_undecodable_last_state() {
+    let (env, app) = committed_app();
+    write_raw(&app, COL_NODE_INFO, LAST_STATE_KEY, Some(&[0xff]));
+    assert_report_contains(
+        verify(&env, &app, StorageCheckLevel::Quick),
+        "last app state can't be decoded",
+    );
+}
+
+#[test]
+fn check_missing_account_root() {
+    let (env, app) = committed_app();
+    let mut state = last_state(&app);
+    state.staking_version += 10;
+    store_last_state(&app, &state);
+    assert_report_contains(
+        verify(&env, &app, StorageCheckLevel::Quick),
+        &format!(
+            "account root of staking version {} not found in trie storage",
+            state.staking_version
+        ),
+    );
+}
+
+#[test]
+fn check_account_root_mismatch() {
+    let (env, app) = committed_app();
+    let mut state = last_state(&app);
+    state.top_level.account_root = [1u8; 32];
+    store_last_state(&app, &state);
+    assert_report_contains(
+        verify(&env, &app, StorageCheckLevel::Quick),
+        "does not match the last state's account root: 0101010101010101010101010101010101010101010101010101010101010101",
+    );
+}
+
+#[test]
+fn check_app_hash_mismatch() {
+    let (env, app) = committed_app();
+    let mut state = last_state(&app);
+    state.top_level.rewards_pool.period_bonus = Coin::unit();
+    store_last_state(&app, &state);
+    assert_report_contains(
+        verify(&env, &app, StorageCheckLevel::Quick),
+        &format!(
+            "does not match the last state's app hash: {}",
+            hex::encode(state.last_apphash)
+        ),
+    );
+}
+
+#[test]
+fn check_missing_txs_merkle_tree() {
+    let (env, app) = committed_app();
+    let state = last_state(&app);
+    write_raw(
+        &app,
+        LookupItem::TxsMerkle as u32,
+        &state.last_apphash,
+        None,
+    );
+    assert_report_contains(
+        verify(&env, &app, StorageCheckLevel::Quick),
+        &format!(
+            "transactions merkle tree of app hash {} not found",
+            hex::encode(state.last_apphash)
+        ),
+    );
+}
+
+#[test]
+fn check_historical_app_hash_mismatch() {
+    let (env, app) = committed_app();
+    write_raw(
+        &app,
+        COL_APP_HASHS,
+        &BlockHeight::new(1).encode(),
+        Some(&[2u8; 32]),
+    );
+    assert_report_contains(
+        verify(&env, &app, StorageCheckLevel::Quick),
+        "stored app hash of block height 1: 0202020202020202020202020202020202020202020202020202020202020202 does not match",
+    );
+}
+
+#[test]
+fn check_snapshot_after_last_state() {
+    let (env, app) = committed_app();
+    write_raw(
+        &app,
+        COL_APP_HASHS,
+        &BlockHeight::new(2).encode(),
+        Some(&[2u8; 32]),
+    );
+    assert_report_contains(
+        verify(&env, &app, StorageCheckLevel::Quick),
+        "snapshot of block height 2 is stored, but it's after the last state's height",
+    );
+}
+
+#[test]
+fn check_staking_versions_not_monotonic() {
+    let (env, app) = committed_app();
+    let state = last_state(&app);
+    write_raw(
+        &app,
+        COL_STAKING_VERSIONS,
+        &BlockHeight::genesis().encode(),
+        Some(&(state.staking_version + 1).encode()),
+    );
+    // only the full verification walks through all the snapshots
+    assert!(verify(&env, &app, StorageCheckLevel::Quick).is_ok());
+    assert_report_contains(
+        verify(&env, &app, StorageCheckLevel::Full),
+        &format!(
+            "staking version of block height 1: {} is lower than the previous one: {}",
+            state.staking_version,
+            state.staking_version + 1
+        ),
+    );
+}
+
+#[test]
+fn check_staking_table_audit() {
+    let (env, mut app) = committed_app();
+    let mut state = last_state(&app);
+    let address = env.accounts[1].staking_address();
+    let mut staking = app.staking_getter_committed().get(&address).unwrap();
+    staking.node_meta = None;
+
+    state.staking_version += 1;
+    state.top_level.account_root = app.storage.put_stakings(state.staking_version, &[staking]);
+    recommit_last_state(&mut app, state);
+
+    // the trie and the app hash are consistent, but the staking table isn't
+    assert!(verify(&env, &app, StorageCheckLevel::Quick).is_ok());
+    assert_report_contains(
+        verify(&env, &app, StorageCheckLevel::Full),
+        &format!(
+            "staking table is inconsistent: tracked validator {} has no council node record",
+            address
+        ),
+    );
+}
+
+#[test]
+#[should_panic(expected = "inconsistent storage, refusing to start")]
+fn check_inconsistent_storage_refuses_to_start() {
+    let (env, app) = committed_app();
+    let mut state = last_state(&app);
+    state.top_level.account_root = [1u8; 32];
+    store_last_state(&app, &state);
+
+    let storage = Storage::new_db(app.storage.temp_hack_for_tdbe());
+    let _app = ChainNodeApp::new_with_storage(
+        get_enclave_bridge_mock(),
+        &hex::encode_upper(env.genesis_app_hash),
+        TEST_CHAIN_ID,
+        storage,
+        None,
+        "".to_string(),
+    );
+}
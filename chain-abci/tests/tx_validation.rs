@@ -8,7 +8,9 @@ use chain_abci::staking::StakingTable;
 use chain_abci::storage::{
     process_public_tx, verify_enclave_tx as verify_enclave_tx_inner, TxEnclaveAction,
 };
-use chain_abci::tx_error::{NodeJoinError, PublicTxError, TxError, UnbondError, UnjailError};
+use chain_abci::tx_error::{
+    NodeJoinError, PublicTxError, TxError, UnbondError, UnjailError, UpdateNodeError,
+};
 use chain_core::common::{MerkleTree, Timespec};
 use chain_core::init::address::RedeemAddress;
 use chain_core::init::coin::{Coin, CoinError};
@@ -16,12 +18,12 @@ use chain_core::state::account::StakedState;
 use chain_core::state::account::StakedStateAddress;
 use chain_core::state::account::StakedStateOpAttributes;
 use chain_core::state::account::{
-    DepositBondTx, NodeMetadata, StakedStateOpWitness, UnbondTx, UnjailTx, Validator,
-    WithdrawUnbondedTx,
+    announced_endpoints_supported, DepositBondTx, NodeMetadata, NodeState, StakedStateOpWitness,
+    UnbondTx, UnjailTx, Validator, WithdrawUnbondedTx,
 };
 use chain_core::state::tendermint::BlockHeight;
 use chain_core::state::tendermint::TendermintValidatorPubKey;
-use chain_core::state::validator::NodeJoinRequestTx;
+use chain_core::state::validator::{NodeJoinRequestTx, UpdateNodeTx};
 use chain_core::tx::data::{
     address::ExtendedAddr,
     attribute::TxAttributes,
@@ -47,6 +49,7 @@ use chain_tx_validation::{
 use kvdb::KeyValueDB;
 use kvdb_memorydb::create;
 use mock_utils::{encrypt, encrypt_payload, seal};
+use parity_scale_codec::{Decode, Encode};
 use secp256k1::schnorrsig::schnorr_sign;
 use secp256k1::{key::PublicKey, key::SecretKey, key::XOnlyPublicKey, Message, Secp256k1, Signing};
 use std::fmt::Debug;
@@ -655,6 +658,15 @@ fn expect_error_joinnode<T>(res: &Result<T, TxError>, expected: NodeJoinError) {
     }
 }
 
+fn expect_error_update_node<T>(res: &Result<T, TxError>, expected: UpdateNodeError) {
+    match res {
+        Err(TxError::Public(PublicTxError::UpdateNode(err)))
+            if mem::discriminant(&expected) == mem::discriminant(err) => {}
+        Err(err) => panic!("Expected error {:?} but got {:?}", expected, err),
+        Ok(_) => panic!("Expected error {:?} but succeeded", expected),
+    }
+}
+
 fn expect_error_unjail<T>(res: &Result<T, TxError>, expected: UnjailError) {
     match res {
         Err(TxError::Public(PublicTxError::Unjail(err)))
@@ -1471,3 +1483,122 @@ fn test_nodejoin_fail() {
         expect_error_joinnode(&result, NodeJoinError::AlreadyJoined);
     }
 }
+
+fn prepare_update_node_transaction(
+    secret_key: &SecretKey,
+    address: StakedStateAddress,
+    nonce: u64,
+    announced_endpoints: Vec<String>,
+) -> TxPublicAux {
+    let tx = UpdateNodeTx {
+        nonce,
+        address,
+        attributes: StakedStateOpAttributes::new(DEFAULT_CHAIN_ID),
+        announced_endpoints,
+    };
+    let witness = get_account_op_witness(secp256k1::SECP256K1, &tx.id(), &secret_key);
+
+    TxPublicAux::UpdateNodeTx(tx, witness)
+}
+
+#[test]
+fn test_update_node_unsupported_version() {
+    if announced_endpoints_supported() {
+        return;
+    }
+    let (_, _, addr, secret_key, storage) = prepare_valid_nodejoin_tx(true);
+    let txaux = prepare_update_node_transaction(&secret_key, addr, 1, Vec::new());
+    let extra_info = get_chain_info_pub(&txaux);
+
+    let wrap = NodeInfoWrap::custom(Coin::one(), vec![addr]);
+    let result = verify_public_tx(&txaux, &extra_info, wrap, 0, &storage);
+    expect_error_public(&result, PublicTxError::UnsupportedVersion);
+    let encoded = TxAux::PublicTx(txaux).encode();
+    assert!(TxAux::decode(&mut encoded.as_slice()).is_err());
+}
+
+#[test]
+fn test_update_node_success() {
+    // node updates are only accepted from their app version
+    if !announced_endpoints_supported() {
+        return;
+    }
+    let (_, _, addr, secret_key, storage) = prepare_valid_nodejoin_tx(true);
+    let endpoints = vec![
+        "sentry-1.example.com:26656".to_owned(),
+        "[2001:db8::1]:26656".to_owned(),
+    ];
+    let txaux = prepare_update_node_transaction(&secret_key, addr, 1, endpoints.clone());
+    let extra_info = get_chain_info_pub(&txaux);
+
+    let wrap = NodeInfoWrap::custom(Coin::one(), vec![addr]);
+    let (fee, new_account) = verify_public_tx(&txaux, &extra_info, wrap, 0, &storage)
+        .expect("Verification of update node transaction failed");
+
+    assert_eq!(extra_info.min_fee_computed, fee);
+    let new_account = new_account.unwrap();
+    assert_eq!(new_account.nonce, 2);
+    assert_eq!(
+        (Coin::one() - fee.to_coin()).unwrap(),
+        new_account.bonded,
+        "the fee is paid from the bonded amount"
+    );
+    match new_account.node_meta {
+        Some(NodeState::CouncilNode(validator)) => {
+            assert_eq!(validator.council_node.announced_endpoints, endpoints)
+        }
+        _ => panic!("council node metadata expected"),
+    }
+}
+
+#[test]
+fn test_update_node_fail() {
+    if !announced_endpoints_supported() {
+        return;
+    }
+    let (_, _, addr, secret_key, storage) = prepare_valid_nodejoin_tx(true);
+    let endpoints = vec!["10.0.0.1:26656".to_owned()];
+    let wrap = || NodeInfoWrap::custom(Coin::one(), vec![addr]);
+    // IncorrectNonce
+    {
+        let txaux = prepare_update_node_transaction(&secret_key, addr, 0, endpoints.clone());
+        let extra_info = get_chain_info_pub(&txaux);
+        let result = verify_public_tx(&txaux, &extra_info, wrap(), 0, &storage);
+        expect_error_public(&result, PublicTxError::IncorrectNonce);
+    }
+    // MismatchAccountAddress
+    {
+        let other = StakedStateAddress::from(RedeemAddress::from([1u8; 20]));
+        let txaux = prepare_update_node_transaction(&secret_key, other, 1, endpoints.clone());
+        let extra_info = get_chain_info_pub(&txaux);
+        let result = verify_public_tx(&txaux, &extra_info, wrap(), 0, &storage);
+        expect_error_public(&result, PublicTxError::StakingWitnessNotMatch);
+    }
+    // NotCouncilNode
+    {
+        let (_, _, addr, secret_key, storage) = prepare_valid_nodejoin_tx(false);
+        let txaux = prepare_update_node_transaction(&secret_key, addr, 1, endpoints);
+        let extra_info = get_chain_info_pub(&txaux);
+        let result = verify_public_tx(&txaux, &extra_info, NodeInfoWrap::default(), 0, &storage);
+        expect_error_update_node(&result, UpdateNodeError::NotCouncilNode);
+    }
+    // invalid endpoints: too many, too long, bad syntax
+    for invalid in vec![
+        vec!["10.0.0.1:26656".to_owned(); 5],
+        vec![format!("{}.example.com:26656", "a".repeat(64))],
+        vec!["10.0.0.1".to_owned()],
+        vec!["sentry.example.com:99999".to_owned()],
+        vec!["sentry example.com:26656".to_owned()],
+    ] {
+        assert!(UpdateNodeTx::new(
+            1,
+            addr,
+            StakedStateOpAttributes::new(DEFAULT_CHAIN_ID),
+            invalid.clone()
+        )
+        .is_err());
+        let txaux = prepare_update_node_transaction(&secret_key, addr, 1, invalid);
+        let encoded = TxAux::PublicTx(txaux).encode();
+        assert!(TxAux::decode(&mut encoded.as_slice()).is_err());
+    }
+}
@@ -1,8 +1,9 @@
 use abci::*;
 use chain_abci::app::BufferType;
+use chain_core::common::{TendermintEventKey, TendermintEventType};
 use chain_core::init::coin::Coin;
 use chain_core::state::account::{NodeState, StakedState, Validator as ChainValidator};
-use chain_core::state::tendermint::TendermintVotePower;
+use chain_core::state::tendermint::{TendermintVotePower, ValidatorPowerChange};
 use chain_core::tx::fee::Milli;
 use chain_storage::buffer::Get;
 use parity_scale_codec::Encode;
@@ -204,7 +205,7 @@ fn check_rejoin() {
     assert!(!validator_unwrap(&get_account(&staking_address, &app)).is_active());
 
     // Begin block -- there should be a reward after this one
-    app.begin_block(&RequestBeginBlock {
+    let response_begin_block = app.begin_block(&RequestBeginBlock {
         last_commit_info: Some(env.last_commit_info(1, true)).into(),
         ..env.req_begin_block_with_time(2, 1, DEFAULT_GENESIS_TIME as i64 + 365 * 24 * 3600 + 1)
     });
@@ -212,6 +213,38 @@ fn check_rejoin() {
     let acct = get_account(&staking_address, &app);
     assert!(acct.bonded > required_stake);
 
+    // reward and minted amounts are reported under their own keys
+    let attribute_value = |event: &Event, key: TendermintEventKey| {
+        event
+            .attributes
+            .iter()
+            .find(|kv_pair| kv_pair.key == key.to_vec())
+            .map(|kv_pair| String::from_utf8(kv_pair.value.clone()).unwrap())
+    };
+    let reward_event = response_begin_block
+        .events
+        .iter()
+        .find(|event| {
+            attribute_value(event, TendermintEventKey::StakingAddress)
+                == Some(staking_address.to_string())
+                && attribute_value(event, TendermintEventKey::StakingOpType).as_deref()
+                    == Some("reward")
+        })
+        .expect("reward event");
+    let reward_amount = attribute_value(reward_event, TendermintEventKey::RewardAmount).unwrap();
+    assert_ne!("0", reward_amount);
+    assert_eq!(
+        Some(reward_amount),
+        attribute_value(reward_event, TendermintEventKey::BondedDelta)
+    );
+    assert_eq!(None, attribute_value(reward_event, TendermintEventKey::Fee));
+    let minted_event = response_begin_block
+        .events
+        .iter()
+        .find(|event| event.field_type == TendermintEventType::Reward.to_string())
+        .expect("minted event");
+    assert!(attribute_value(minted_event, TendermintEventKey::MintedAmount).is_some());
+
     // node join should be ok
     assert!(!validator_unwrap(&get_account(&staking_address, &app)).is_active());
 
@@ -240,3 +273,103 @@ fn check_rejoin() {
     assert_ne!(0, response_end_block.validator_updates.to_vec()[0].power);
     assert_ne!(0, response_end_block.validator_updates.to_vec()[1].power);
 }
+
+fn validator_set_update_changes(
+    response_end_block: &ResponseEndBlock,
+) -> Vec<ValidatorPowerChange> {
+    response_end_block
+        .events
+        .iter()
+        .filter(|event| event.field_type == TendermintEventType::ValidatorSetUpdate.to_string())
+        .flat_map(|event| event.attributes.iter())
+        .filter(|kv_pair| kv_pair.key == TendermintEventKey::ValidatorUpdate.to_vec())
+        .map(|kv_pair| serde_json::from_slice(&kv_pair.value).unwrap())
+        .collect()
+}
+
+/// Scenario 5: With a single validator slot, unbond stake from the chosen validator so that
+/// the other council node replaces it. The validator set update event should list both changes.
+#[test]
+fn check_validator_set_update_event_on_swap() {
+    // Init Chain
+    let (env, storage) =
+        ChainEnv::new_with_customizer(Coin::max(), Coin::zero(), 2, |parameters| {
+            parameters.required_council_node_stake = (Coin::max() / 10).unwrap();
+            parameters.max_validators = 1;
+        });
+    let mut app = env.chain_node(storage);
+    let _rsp = app.init_chain(&env.req_init_chain());
+    let state = app.last_state.as_ref().unwrap();
+    let chosen = (0..2)
+        .find(|&i| {
+            state
+                .staking_table
+                .get_chosen_validators()
+                .contains_key(&env.accounts[i].staking_address())
+        })
+        .expect("one chosen validator");
+    let standby = 1 - chosen;
+    let share = TendermintVotePower::from(env.share());
+
+    // Block without staking changes: no event
+    app.begin_block(&env.req_begin_block(1, chosen));
+    let response_end_block = app.end_block(&RequestEndBlock {
+        height: 1,
+        ..Default::default()
+    });
+    assert_eq!(0, response_end_block.validator_updates.to_vec().len());
+    assert!(!response_end_block
+        .events
+        .iter()
+        .any(|event| event.field_type == TendermintEventType::ValidatorSetUpdate.to_string()));
+    app.commit(&RequestCommit::new());
+
+    // Unbond from the chosen validator, so the standby one has more voting power
+    app.begin_block(&RequestBeginBlock {
+        last_commit_info: Some(env.last_commit_info(chosen, true)).into(),
+        ..env.req_begin_block(2, chosen)
+    });
+    let amount = (Coin::max() / 10).unwrap();
+    let tx_aux = env.unbond_tx(amount, 0, chosen);
+    let rsp_tx = app.deliver_tx(&RequestDeliverTx {
+        tx: tx_aux.encode(),
+        ..Default::default()
+    });
+    assert_eq!(0, rsp_tx.code, "{}", rsp_tx.log);
+
+    let response_end_block = app.end_block(&RequestEndBlock {
+        height: 2,
+        ..Default::default()
+    });
+    let validator_updates = response_end_block.validator_updates.to_vec();
+    assert_eq!(2, validator_updates.len());
+
+    let mut changes = validator_set_update_changes(&response_end_block);
+    changes.sort_by_key(|change| change.old_power);
+    assert_eq!(
+        vec![
+            ValidatorPowerChange {
+                pubkey: env.accounts[standby].validator_pub_key.to_base64(),
+                old_power: TendermintVotePower::zero(),
+                new_power: share,
+            },
+            ValidatorPowerChange {
+                pubkey: env.accounts[chosen].validator_pub_key.to_base64(),
+                old_power: share,
+                new_power: TendermintVotePower::zero(),
+            },
+        ],
+        changes
+    );
+    // events are in the order of the validator updates
+    for (change, update) in validator_set_update_changes(&response_end_block)
+        .iter()
+        .zip(validator_updates.iter())
+    {
+        assert_eq!(i64::from(change.new_power), update.power);
+        assert_eq!(
+            change.pubkey,
+            base64::encode(&update.pub_key.as_ref().unwrap().data)
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use std::prelude::v1::Vec;
+
+use parity_scale_codec::{Compact, Decode, Encode, Error, Input};
+
+/// Structure persisted as a part of the consensus state: decoding and re-encoding it has to give
+/// identical bytes, otherwise nodes which re-serialize the state may compute different app hashes.
+///
+/// `Decode` implementations of these types are expected to reject any non-canonical input
+/// (trailing bytes in nested blobs, unsorted or duplicated map keys, non-zero padding etc.).
+pub trait ConsensusCodec: Encode + Decode {
+    /// Decodes `bytes` (which have to be fully consumed) and checks that re-encoding the decoded
+    /// value gives the same bytes
+    fn decode_canonical(bytes: &[u8]) -> Result<Self, Error> {
+        let mut input = bytes;
+        let decoded = Self::decode(&mut input)?;
+        if !input.is_empty() {
+            return Err(Error::from("Trailing bytes after consensus structure"));
+        }
+        if decoded.encode() != bytes {
+            return Err(Error::from("Non-canonical encoding of consensus structure"));
+        }
+        Ok(decoded)
+    }
+}
+
+/// Decodes `BTreeMap` (encoded the same way as by its `Encode` implementation), rejecting
+/// unsorted or duplicated keys which would be silently reordered / dropped by the default `Decode`
+pub fn decode_sorted_map<K, V, I>(input: &mut I) -> Result<BTreeMap<K, V>, Error>
+where
+    K: Decode + Ord,
+    V: Decode,
+    I: Input,
+{
+    let len = <Compact<u32>>::decode(input)?.0;
+    let mut map = BTreeMap::new();
+    for _ in 0..len {
+        let key = K::decode(input)?;
+        let value = V::decode(input)?;
+        if let Some(last_key) = map.keys().next_back() {
+            if *last_key >= key {
+                return Err(Error::from("Map keys are not strictly sorted"));
+            }
+        }
+        map.insert(key, value);
+    }
+    Ok(map)
+}
+
+/// Decodes a value from `blob` (a nested encoding, e.g. `Vec<u8>` holding encoded value),
+/// rejecting trailing bytes
+pub fn decode_blob<T: Decode>(blob: &[u8]) -> Result<T, Error> {
+    let mut input = blob;
+    let value = T::decode(&mut input)?;
+    if !input.is_empty() {
+        return Err(Error::from("Trailing bytes in nested encoding"));
+    }
+    Ok(value)
+}
+
+/// Decodes a value crossing the enclave / host boundary (e.g. enclave protocol messages and
+/// transaction payloads) from `bytes`, rejecting trailing bytes: a lenient decoding could parse
+/// the same bytes differently in the enclave and host builds.
+///
+/// With the `lenient-boundary-decoding` feature, the trailing bytes are accepted (a temporary
+/// fallback for the already-deployed messages which carry padding).
+pub fn decode_all_strict<T: Decode>(bytes: &[u8]) -> Result<T, Error> {
+    let mut input = bytes;
+    let value = T::decode(&mut input)?;
+    if !input.is_empty() && cfg!(not(feature = "lenient-boundary-decoding")) {
+        return Err(Error::from("Trailing bytes after boundary structure"));
+    }
+    Ok(value)
+}
+
+/// app version from which the transactions executed in a block (and their decrypted payloads)
+/// are decoded with `decode_all_strict`
+pub const STRICT_TX_DECODING_APP_VERSION: u64 = 2;
+
+/// Decodes a transaction executed in a block (or its decrypted payload) from `bytes`: strictly
+/// (see `decode_all_strict`) from `STRICT_TX_DECODING_APP_VERSION`, before that the trailing bytes
+/// are ignored as they were by the previous releases (otherwise blocks could be replayed with
+/// different results).
+pub fn decode_tx_in_block<T: Decode>(bytes: &[u8]) -> Result<T, Error> {
+    if crate::APP_VERSION >= STRICT_TX_DECODING_APP_VERSION {
+        decode_all_strict(bytes)
+    } else {
+        T::decode(&mut &bytes[..])
+    }
+}
+
+/// Decodes a nested blob of a transaction executed in a block (e.g. the node keypackage):
+/// rejecting trailing bytes (see `decode_blob`) from `STRICT_TX_DECODING_APP_VERSION`, before
+/// that they are ignored as they were by the previous releases. The persisted structures are
+/// still checked with `ConsensusCodec::decode_canonical`, which rejects them in either case.
+pub fn decode_blob_in_block<T: Decode>(blob: &[u8]) -> Result<T, Error> {
+    if crate::APP_VERSION >= STRICT_TX_DECODING_APP_VERSION {
+        decode_blob(blob)
+    } else {
+        T::decode(&mut &blob[..])
+    }
+}
+
+/// Generates tests checking that sample values of consensus structures are decoded (with
+/// `ConsensusCodec::decode_canonical`) and re-encoded to identical bytes:
+///
+/// ```ignore
+/// consensus_codec_tests! {
+///     staked_state_reencoding: StakedState = StakedState::default(address);
+/// }
+/// ```
+#[macro_export]
+macro_rules! consensus_codec_tests {
+    ($($name:ident: $ty:ty = $value:expr;)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let value: $ty = $value;
+                let encoded = ::parity_scale_codec::Encode::encode(&value);
+                let decoded = <$ty as $crate::common::ConsensusCodec>::decode_canonical(&encoded)
+                    .expect("consensus structure isn't re-encoded identically");
+                assert_eq!(::parity_scale_codec::Encode::encode(&decoded), encoded);
+            }
+        )*
+    };
+}
+
+/// Decodes a fixture (hex) of a consensus structure and checks it's re-encoded identically
+#[cfg(test)]
+pub(crate) fn check_fixture<T: ConsensusCodec>(fixture: &str) -> T {
+    let bytes = hex::decode(fixture).expect("fixture hex");
+    T::decode_canonical(&bytes).expect("fixture isn't re-encoded identically")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorted_map_should_reject_unsorted_keys() {
+        let map: BTreeMap<u8, u8> = vec![(1, 10), (2, 20)].into_iter().collect();
+        let encoded = map.encode();
+        assert_eq!(
+            decode_sorted_map::<u8, u8, _>(&mut encoded.as_slice()).unwrap(),
+            map
+        );
+
+        // [(2, 20), (1, 10)]
+        let unsorted = vec![(2u8, 20u8), (1, 10)].encode();
+        assert!(BTreeMap::<u8, u8>::decode(&mut unsorted.as_slice()).is_ok());
+        assert!(decode_sorted_map::<u8, u8, _>(&mut unsorted.as_slice()).is_err());
+
+        // [(1, 10), (1, 11)]
+        let duplicated = vec![(1u8, 10u8), (1, 11)].encode();
+        assert!(decode_sorted_map::<u8, u8, _>(&mut duplicated.as_slice()).is_err());
+    }
+
+    #[test]
+    fn blob_should_reject_trailing_bytes() {
+        assert_eq!(decode_blob::<u16>(&[1, 0]).unwrap(), 1);
+        assert!(decode_blob::<u16>(&[1, 0, 0]).is_err());
+    }
+
+    #[test]
+    #[cfg(not(feature = "lenient-boundary-decoding"))]
+    fn strict_decoding_should_reject_trailing_bytes() {
+        assert_eq!(decode_all_strict::<u16>(&[1, 0]).unwrap(), 1);
+        assert!(decode_all_strict::<u16>(&[1, 0, 0xff]).is_err());
+        assert!(decode_all_strict::<u16>(&[1]).is_err());
+    }
+
+    #[test]
+    #[cfg(not(feature = "lenient-boundary-decoding"))]
+    fn tx_in_block_should_be_decoded_strictly_from_its_app_version() {
+        assert_eq!(decode_tx_in_block::<u16>(&[1, 0]).unwrap(), 1);
+        assert_eq!(
+            decode_tx_in_block::<u16>(&[1, 0, 0xff]).is_err(),
+            crate::APP_VERSION >= STRICT_TX_DECODING_APP_VERSION
+        );
+    }
+
+    #[test]
+    fn blob_in_block_should_be_decoded_strictly_from_its_app_version() {
+        assert_eq!(decode_blob_in_block::<u16>(&[1, 0]).unwrap(), 1);
+        assert_eq!(
+            decode_blob_in_block::<u16>(&[1, 0, 0]).is_err(),
+            crate::APP_VERSION >= STRICT_TX_DECODING_APP_VERSION
+        );
+    }
+}
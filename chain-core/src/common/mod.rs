@@ -5,9 +5,17 @@ use std::string::ToString;
 
 use digest::Digest;
 
+/// Byte-identity of decoded and re-encoded consensus structures
+mod consensus_codec;
 /// Generic merkle tree
 mod merkle_tree;
 
+#[cfg(test)]
+pub(crate) use consensus_codec::check_fixture;
+pub use consensus_codec::{
+    decode_all_strict, decode_blob, decode_blob_in_block, decode_sorted_map, decode_tx_in_block,
+    ConsensusCodec, STRICT_TX_DECODING_APP_VERSION,
+};
 pub use merkle_tree::{MerkleTree, Proof};
 
 /// Size in bytes of a 256-bit hash
@@ -43,6 +51,8 @@ pub enum TendermintEventType {
     StakingChange,
     /// when reward was distributed
     Reward,
+    /// validator voting power changes included in the end block validator updates
+    ValidatorSetUpdate,
 }
 
 impl fmt::Display for TendermintEventType {
@@ -52,6 +62,7 @@ impl fmt::Display for TendermintEventType {
             TendermintEventType::BlockFilter => write!(f, "block_filter"),
             TendermintEventType::StakingChange => write!(f, "staking_change"),
             TendermintEventType::Reward => write!(f, "reward"),
+            TendermintEventType::ValidatorSetUpdate => write!(f, "validator_set_update"),
         }
     }
 }
@@ -65,6 +76,8 @@ pub enum TendermintEventKey {
     TxId,
     /// bloom filter of view keys
     EthBloom,
+    /// Golomb-coded set filter of view keys
+    GcsFilter,
     /// affected staking address
     StakingAddress,
     /// staking operation type
@@ -73,10 +86,22 @@ pub enum TendermintEventKey {
     StakingDiff,
     /// staking operation reason
     StakingOpReason,
-    /// new coins minted from rewards pool
+    /// new coins minted from rewards pool (legacy JSON-encoded value, superseded by `MintedAmount`)
     CoinMinted,
-    /// when state was slashed
+    /// when state was slashed (legacy, superseded by `SlashedAmount`)
     Slash,
+    /// total amount slashed from bonded and unbonded balances (in base units)
+    SlashedAmount,
+    /// amount of reward distributed to staking address (in base units)
+    RewardAmount,
+    /// new coins minted from rewards pool (in base units)
+    MintedAmount,
+    /// signed change of bonded balance (in base units, e.g. "-100")
+    BondedDelta,
+    /// signed change of unbonded balance (in base units, e.g. "-100")
+    UnbondedDelta,
+    /// JSON-encoded `ValidatorPowerChange` of a validator
+    ValidatorUpdate,
 }
 
 impl From<TendermintEventKey> for Vec<u8> {
@@ -103,12 +128,19 @@ impl fmt::Display for TendermintEventKey {
             TendermintEventKey::Fee => write!(f, "fee"),
             TendermintEventKey::TxId => write!(f, "txid"),
             TendermintEventKey::EthBloom => write!(f, "ethbloom"),
+            TendermintEventKey::GcsFilter => write!(f, "gcsfilter"),
             TendermintEventKey::StakingAddress => write!(f, "staking_address"),
             TendermintEventKey::StakingOpType => write!(f, "staking_optype"),
             TendermintEventKey::StakingDiff => write!(f, "staking_diff"),
             TendermintEventKey::StakingOpReason => write!(f, "staking_opreason"),
             TendermintEventKey::CoinMinted => write!(f, "minted"),
             TendermintEventKey::Slash => write!(f, "slash"),
+            TendermintEventKey::SlashedAmount => write!(f, "slashed_amount"),
+            TendermintEventKey::RewardAmount => write!(f, "reward_amount"),
+            TendermintEventKey::MintedAmount => write!(f, "minted_amount"),
+            TendermintEventKey::BondedDelta => write!(f, "bonded_delta"),
+            TendermintEventKey::UnbondedDelta => write!(f, "unbonded_delta"),
+            TendermintEventKey::ValidatorUpdate => write!(f, "validator_update"),
         }
     }
 }
@@ -127,12 +159,19 @@ impl TendermintEventKey {
             TendermintEventKey::Fee => String::from("ZmVl"),
             TendermintEventKey::TxId => String::from("dHhpZA=="),
             TendermintEventKey::EthBloom => String::from("ZXRoYmxvb20="),
+            TendermintEventKey::GcsFilter => String::from("Z2NzZmlsdGVy"),
             TendermintEventKey::StakingAddress => String::from("c3Rha2luZ19hZGRyZXNz"),
             TendermintEventKey::StakingOpType => String::from("c3Rha2luZ19vcHR5cGU="),
             TendermintEventKey::StakingDiff => String::from("c3Rha2luZ19kaWZm"),
             TendermintEventKey::StakingOpReason => String::from("c3Rha2luZ19vcHJlYXNvbg=="),
             TendermintEventKey::CoinMinted => String::from("bWludGVk"),
             TendermintEventKey::Slash => String::from("c2xhc2g="),
+            TendermintEventKey::SlashedAmount => String::from("c2xhc2hlZF9hbW91bnQ="),
+            TendermintEventKey::RewardAmount => String::from("cmV3YXJkX2Ftb3VudA=="),
+            TendermintEventKey::MintedAmount => String::from("bWludGVkX2Ftb3VudA=="),
+            TendermintEventKey::BondedDelta => String::from("Ym9uZGVkX2RlbHRh"),
+            TendermintEventKey::UnbondedDelta => String::from("dW5ib25kZWRfZGVsdGE="),
+            TendermintEventKey::ValidatorUpdate => String::from("dmFsaWRhdG9yX3VwZGF0ZQ=="),
         }
     }
 }
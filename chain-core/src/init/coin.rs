@@ -157,44 +157,83 @@ impl ::std::str::FromStr for Coin {
     }
 }
 
+impl Coin {
+    /// parses an amount in CRO (with up to 8 decimals, e.g. `"25000000.5"`);
+    /// `FromStr` parses base units instead
+    pub fn from_cro_str(s: &str) -> CoinResult {
+        let (units, decimals) = match s.find('.') {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => (s, ""),
+        };
+        let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if (units.is_empty() && decimals.is_empty())
+            || !is_digits(units)
+            || !is_digits(decimals)
+            || decimals.len() > 8
+        {
+            return Err(CoinError::ParseIntError);
+        }
+        let units = if units.is_empty() {
+            0
+        } else {
+            units.parse::<u64>().map_err(|_| CoinError::Overflow)?
+        };
+        let decimals = if decimals.is_empty() {
+            0
+        } else {
+            // right-pad to 8 decimals
+            decimals
+                .parse::<u64>()
+                .map_err(|_| CoinError::ParseIntError)?
+                * 10u64.pow(8 - decimals.len() as u32)
+        };
+        units
+            .checked_mul(MAX_COIN_DECIMALS)
+            .and_then(|v| v.checked_add(decimals))
+            .ok_or(CoinError::Overflow)
+            .and_then(Coin::new)
+    }
+
+    /// checked addition: `Overflow` if the sum doesn't fit into `u64`,
+    /// `OutOfBound` if it's above `MAX_COIN`
+    fn checked_add(self, other: Coin) -> CoinResult {
+        self.0
+            .checked_add(other.0)
+            .ok_or(CoinError::Overflow)
+            .and_then(Coin::new)
+    }
+
+    /// checked subtraction: `Negative` if `other` is larger
+    fn checked_sub(self, other: Coin) -> CoinResult {
+        self.0
+            .checked_sub(other.0)
+            .ok_or(CoinError::Negative)
+            .map(Coin)
+    }
+}
+
 impl ops::Add for Coin {
     type Output = CoinResult;
     fn add(self, other: Coin) -> Self::Output {
-        let sum = self.0.checked_add(other.0);
-        match sum {
-            None => Err(CoinError::OutOfBound(0)),
-            Some(v) => Coin::new(v),
-        }
+        self.checked_add(other)
     }
 }
 impl<'a> ops::Add<&'a Coin> for Coin {
     type Output = CoinResult;
     fn add(self, other: &'a Coin) -> Self::Output {
-        let sum = self.0.checked_add(other.0);
-        match sum {
-            None => Err(CoinError::OutOfBound(0)),
-            Some(v) => Coin::new(v),
-        }
+        self.checked_add(*other)
     }
 }
 impl ops::Sub for Coin {
     type Output = CoinResult;
     fn sub(self, other: Coin) -> Self::Output {
-        let sub = self.0.checked_sub(other.0);
-        match sub {
-            None => Err(CoinError::Negative),
-            Some(v) => Coin::new(v),
-        }
+        self.checked_sub(other)
     }
 }
 impl<'a> ops::Sub<&'a Coin> for Coin {
     type Output = CoinResult;
     fn sub(self, other: &'a Coin) -> Self::Output {
-        let sub = self.0.checked_sub(other.0);
-        match sub {
-            None => Err(CoinError::Negative),
-            Some(v) => Coin::new(v),
-        }
+        self.checked_sub(*other)
     }
 }
 // this instance is necessary to chain the substraction operations
@@ -203,11 +242,7 @@ impl<'a> ops::Sub<&'a Coin> for Coin {
 impl ops::Sub<Coin> for CoinResult {
     type Output = CoinResult;
     fn sub(self, other: Coin) -> Self::Output {
-        if other.0 > self?.0 {
-            Err(CoinError::Negative)
-        } else {
-            Ok(Coin(self?.0 - other.0))
-        }
+        self?.checked_sub(other)
     }
 }
 
@@ -273,6 +308,14 @@ impl From<Coin> for TendermintVotePower {
     }
 }
 
+impl TryFrom<u64> for Coin {
+    type Error = CoinError;
+
+    fn try_from(v: u64) -> CoinResult {
+        Coin::new(v)
+    }
+}
+
 impl From<u32> for Coin {
     fn from(c: u32) -> Coin {
         Coin(u64::from(c))
@@ -281,13 +324,9 @@ impl From<u32> for Coin {
 
 impl Decode for Coin {
     fn decode<I: Input>(input: &mut I) -> Result<Self, ScaleError> {
-        let num = u64::decode(input)?;
-
-        if num > MAX_COIN {
-            Err(ScaleError::from("Value greater than maximum allowed"))
-        } else {
-            Ok(Coin(num))
-        }
+        // the bound is checked here, so an out-of-range value never constructs a `Coin`
+        Coin::try_from(u64::decode(input)?)
+            .map_err(|_| ScaleError::from("Value greater than maximum allowed"))
     }
 }
 
@@ -315,9 +354,29 @@ pub fn sum_coins(mut coins: impl Iterator<Item = Coin>) -> Result<Coin, CoinErro
 #[cfg(test)]
 mod test {
     use super::*;
-    use quickcheck::quickcheck;
+    use quickcheck::{quickcheck, Arbitrary, Gen};
     use std::str::FromStr;
 
+    /// any `u64` (default `Arbitrary` of integers only covers small values)
+    #[derive(Debug, Clone, Copy)]
+    struct AnyU64(u64);
+
+    impl Arbitrary for AnyU64 {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            AnyU64(g.next_u64())
+        }
+    }
+
+    /// any valid coin (in the full `[0, MAX_COIN]` range)
+    #[derive(Debug, Clone, Copy)]
+    struct AnyCoin(Coin);
+
+    impl Arbitrary for AnyCoin {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            AnyCoin(Coin(g.next_u64() % (MAX_COIN + 1)))
+        }
+    }
+
     #[test]
     // test whether oveflow error occur
     fn coin_overflow_add_should_produce_error() {
@@ -354,6 +413,58 @@ mod test {
         assert!(sub == a);
     }
 
+    #[test]
+    fn coin_add_error_should_match_mul() {
+        assert_eq!(Coin::max() + Coin::max(), Err(CoinError::Overflow));
+        assert_eq!(Coin::max() * 2, Err(CoinError::Overflow));
+        assert_eq!(
+            Coin::max() + Coin::unit(),
+            Err(CoinError::OutOfBound(MAX_COIN + 1))
+        );
+        assert_eq!(Coin::unit() - Coin::one(), Err(CoinError::Negative));
+        assert_eq!(
+            Coin::unit() - Coin::unit() - Coin::unit(),
+            Err(CoinError::Negative)
+        );
+    }
+
+    #[test]
+    fn coin_decode_should_reject_out_of_bound() {
+        assert_eq!(
+            Coin::decode(&mut MAX_COIN.encode().as_slice()).unwrap(),
+            Coin::max()
+        );
+        assert!(Coin::decode(&mut (MAX_COIN + 1).encode().as_slice()).is_err());
+        assert!(Coin::decode(&mut std::u64::MAX.encode().as_slice()).is_err());
+        assert_eq!(
+            Coin::try_from(MAX_COIN + 1),
+            Err(CoinError::OutOfBound(MAX_COIN + 1))
+        );
+    }
+
+    #[test]
+    fn coin_from_cro_str() {
+        assert_eq!(Coin::from_cro_str("1"), Ok(Coin::one()));
+        assert_eq!(Coin::from_cro_str("0.00000001"), Ok(Coin::unit()));
+        assert_eq!(
+            Coin::from_cro_str("25000000.5"),
+            Coin::new(2_500_000_050_000_000)
+        );
+        assert_eq!(Coin::from_cro_str(".5"), Coin::new(50_000_000));
+        assert_eq!(Coin::from_cro_str("100000000000"), Ok(Coin::max()));
+        assert_eq!(
+            Coin::from_cro_str("100000000000.00000001"),
+            Err(CoinError::OutOfBound(MAX_COIN + 1))
+        );
+        assert_eq!(
+            Coin::from_cro_str("100000000000000000000"),
+            Err(CoinError::Overflow)
+        );
+        for invalid in &["", ".", "1.000000001", "1.2.3", "-1", "+1", "1e5", "1 "] {
+            assert_eq!(Coin::from_cro_str(invalid), Err(CoinError::ParseIntError));
+        }
+    }
+
     #[test]
     fn coin_slash_ratio_mul() {
         let max = Coin::max();
@@ -364,6 +475,37 @@ mod test {
 
     quickcheck! {
 
+        // test the checked sums are associative (and fail together)
+        fn coin_sum_associative(a: AnyCoin, b: AnyCoin, c: AnyCoin) -> bool {
+            let (a, b, c) = (a.0, b.0, c.0);
+            let left = (a + b).and_then(|ab| ab + c).ok();
+            let right = (b + c).and_then(|bc| a + bc).ok();
+            let expected = u128::from(a.0) + u128::from(b.0) + u128::from(c.0);
+            let expected = Some(expected).filter(|v| *v <= u128::from(MAX_COIN));
+            left == right && left.map(|sum| u128::from(sum.0)) == expected
+        }
+
+        // test the subtraction is the inverse of the addition
+        fn coin_sub_inverse_of_add(a: AnyCoin, b: AnyCoin) -> bool {
+            let (a, b) = (a.0, b.0);
+            match a + b {
+                Ok(sum) => (sum - b) == Ok(a) && (sum - a) == Ok(b),
+                Err(_) => true,
+            }
+        }
+
+        // test the decoding of an encoded coin gives the same coin
+        fn coin_decode_encode_roundtrip(a: AnyCoin) -> bool {
+            Coin::decode(&mut a.0.encode().as_slice()).ok() == Some(a.0)
+        }
+
+        // test the decoding rejects any value above `MAX_COIN`
+        fn coin_decode_rejects_out_of_bound(v: AnyU64) -> bool {
+            let decoded = Coin::decode(&mut v.0.encode().as_slice());
+            decoded.is_ok() == (v.0 <= MAX_COIN)
+                && decoded.ok().map(u64::from).unwrap_or(v.0) == v.0
+        }
+
         // test a given u32 is always a valid value for a `Coin`
         fn coin_from_u32_always_valid(v: u32) -> bool {
             Coin::new(v as u64).is_ok()
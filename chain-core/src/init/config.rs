@@ -50,6 +50,12 @@ pub enum DistributionError {
     /// Invalid punishment configuration parameter
     #[error("Invalid punishment parameters")]
     InvalidPunishmentParamter,
+    /// validators couldn't partially unbond down to the required council node stake
+    #[error("Minimum bond remainder ({0}) exceeds the required council node stake")]
+    InvalidMinimumBondRemainder(Coin),
+    /// the parameters added in app version 2 are specified for an older app version
+    #[error("Minimum bond remainder is not supported before app version {0}")]
+    MinimumBondRemainderNotSupported(u64),
     /// keypackage decode error
     #[error("key package decode failed")]
     KeyPackageDecodeError,
@@ -66,6 +72,10 @@ pub struct InitConfig {
     pub distribution: BTreeMap<RedeemAddress, (StakedStateDestination, Coin)>,
     /// initial network parameters
     pub network_params: InitNetworkParameters,
+    /// minimal bonded amount left by a partial unbonding
+    /// (from app version 2, not specified: no minimum)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minimum_bond_remainder: Option<Coin>,
     /// initial validators
     pub council_nodes: BTreeMap<
         RedeemAddress,
@@ -108,10 +118,23 @@ impl InitConfig {
         InitConfig {
             distribution: owners,
             network_params,
+            minimum_bond_remainder: None,
             council_nodes,
         }
     }
 
+    /// the network parameters at genesis
+    /// (the versioned ones are only used if their parameters are specified)
+    pub fn get_network_parameters(&self) -> NetworkParameters {
+        match self.minimum_bond_remainder {
+            None => NetworkParameters::Genesis(self.network_params.clone()),
+            Some(minimum_bond_remainder) => NetworkParameters::GenesisV2(InitNetworkParametersV2 {
+                init_params: self.network_params.clone(),
+                minimum_bond_remainder,
+            }),
+        }
+    }
+
     fn check_validator_address(&self, address: &RedeemAddress) -> Result<(), DistributionError> {
         let expected = self.network_params.required_council_node_stake;
         match self.distribution.get(address) {
@@ -173,6 +196,18 @@ impl InitConfig {
             .rewards_config
             .validate()
             .map_err(DistributionError::InvalidRewardsParamter)?;
+        if let Some(minimum_bond_remainder) = self.minimum_bond_remainder {
+            if !network_parameters_v2_supported() {
+                return Err(DistributionError::MinimumBondRemainderNotSupported(
+                    NETWORK_PARAMETERS_V2_APP_VERSION,
+                ));
+            }
+            if minimum_bond_remainder > self.network_params.required_council_node_stake {
+                return Err(DistributionError::InvalidMinimumBondRemainder(
+                    minimum_bond_remainder,
+                ));
+            }
+        }
         if self.council_nodes.is_empty() {
             return Err(DistributionError::NoValidators);
         }
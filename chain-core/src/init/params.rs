@@ -1,4 +1,4 @@
-use crate::common::H256;
+use crate::common::{ConsensusCodec, H256};
 use crate::init::coin::{Coin, CoinError};
 use crate::tx::fee::{Fee, FeeAlgorithm};
 use crate::tx::fee::{LinearFee, Milli, MilliError};
@@ -33,11 +33,34 @@ pub struct InitNetworkParameters {
     pub max_validators: u16,
 }
 
-/// so far only one specified at genesis
+/// network parameters specified at genesis (in genesis.json) by the networks starting
+/// from app version 2
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode, Serialize, Deserialize)]
+pub struct InitNetworkParametersV2 {
+    /// the parameters of the first version
+    pub init_params: InitNetworkParameters,
+    /// minimal bonded amount left by a partial unbonding (zero: no minimum)
+    pub minimum_bond_remainder: Coin,
+}
+
+/// the variants are appended, so that the state of the existing networks keeps its encoding
 #[derive(Debug, PartialEq, Eq, Clone, Encode, Decode, Serialize, Deserialize)]
 pub enum NetworkParameters {
     /// parameters specified at genesis time
     Genesis(InitNetworkParameters),
+    /// parameters specified at genesis time, with the ones added in app version 2
+    GenesisV2(InitNetworkParametersV2),
+}
+
+impl ConsensusCodec for NetworkParameters {}
+
+/// app version from which the genesis can specify the parameters of `NetworkParameters::GenesisV2`
+/// (the previous versions can't decode them)
+pub const NETWORK_PARAMETERS_V2_APP_VERSION: u64 = 2;
+
+/// whether the current app version supports the parameters of `NetworkParameters::GenesisV2`
+pub fn network_parameters_v2_supported() -> bool {
+    crate::APP_VERSION >= NETWORK_PARAMETERS_V2_APP_VERSION
 }
 
 /// TODO: extract these to a trait?
@@ -47,100 +70,137 @@ impl NetworkParameters {
         blake3::hash(&self.encode()).into()
     }
 
-    /// cap on validators in tendermint
-    pub fn get_max_validators(&self) -> usize {
+    /// the parameters shared by all the versions
+    fn init_params(&self) -> &InitNetworkParameters {
         match self {
-            NetworkParameters::Genesis(params) => params.max_validators as usize,
+            NetworkParameters::Genesis(params) => params,
+            NetworkParameters::GenesisV2(params) => &params.init_params,
         }
     }
 
+    /// cap on validators in tendermint
+    pub fn get_max_validators(&self) -> usize {
+        self.init_params().max_validators as usize
+    }
+
     /// minimal stake required for node joining (to be a validator)
     pub fn get_required_council_node_stake(&self) -> Coin {
+        self.init_params().required_council_node_stake
+    }
+
+    /// minimal bonded amount left by a partial unbonding
+    /// (no minimum in the networks started before app version 2)
+    pub fn get_minimum_bond_remainder(&self) -> Coin {
         match self {
-            NetworkParameters::Genesis(params) => params.required_council_node_stake,
+            NetworkParameters::Genesis(_) => Coin::zero(),
+            NetworkParameters::GenesisV2(params) => params.minimum_bond_remainder,
         }
     }
 
     /// infraction configuration for byzantine fault
     pub fn get_byzantine_slash_percent(&self) -> SlashRatio {
-        match self {
-            NetworkParameters::Genesis(params) => params.slashing_config.byzantine_slash_percent,
-        }
+        self.init_params().slashing_config.byzantine_slash_percent
     }
 
     /// infraction configuration for liveness fault
     pub fn get_liveness_slash_percent(&self) -> SlashRatio {
-        match self {
-            NetworkParameters::Genesis(params) => params.slashing_config.liveness_slash_percent,
-        }
+        self.init_params().slashing_config.liveness_slash_percent
     }
 
     /// infraction configuration for liveness fault
     pub fn get_missed_block_threshold(&self) -> u16 {
-        match self {
-            NetworkParameters::Genesis(params) => params.jailing_config.missed_block_threshold,
-        }
+        self.init_params().jailing_config.missed_block_threshold
     }
 
     /// infraction configuration for liveness fault
     pub fn get_block_signing_window(&self) -> u16 {
-        match self {
-            NetworkParameters::Genesis(params) => params.jailing_config.block_signing_window,
-        }
+        self.init_params().jailing_config.block_signing_window
     }
 
     /// The period of reward being distributed
     pub fn get_rewards_reward_period_seconds(&self) -> u64 {
-        match self {
-            NetworkParameters::Genesis(params) => params.rewards_config.reward_period_seconds,
-        }
+        self.init_params().rewards_config.reward_period_seconds
     }
 
     /// The upper bound for the reward rate per annum
     pub fn get_rewards_monetary_expansion_r0(&self) -> Milli {
-        match self {
-            NetworkParameters::Genesis(params) => params.rewards_config.monetary_expansion_r0,
-        }
+        self.init_params().rewards_config.monetary_expansion_r0
     }
 
     /// Initial value of tau in the reward function
     pub fn get_rewards_monetary_expansion_tau(&self) -> u64 {
-        match self {
-            NetworkParameters::Genesis(params) => params.rewards_config.monetary_expansion_tau,
-        }
+        self.init_params().rewards_config.monetary_expansion_tau
     }
 
     /// The decay rate of tau.
     pub fn get_rewards_monetary_expansion_decay(&self) -> u64 {
-        match self {
-            NetworkParameters::Genesis(params) => params.rewards_config.monetary_expansion_decay,
-        }
+        self.init_params().rewards_config.monetary_expansion_decay
     }
 
     /// The total amount of tokens reserved for validator's reward in the basic unit
     pub fn get_rewards_monetary_expansion_cap(&self) -> Coin {
-        match self {
-            NetworkParameters::Genesis(params) => params.rewards_config.monetary_expansion_cap,
-        }
+        self.init_params().rewards_config.monetary_expansion_cap
     }
 
     /// constant fee -- TODO: will it be necessary? (used in the tx-query fee?)
     pub fn get_min_const_fee(&self) -> Result<Fee, CoinError> {
-        match self {
-            NetworkParameters::Genesis(params) => {
-                let coin = Coin::new(params.initial_fee_policy.coefficient.to_integral())?;
-                Ok(Fee::new(coin))
-            }
-        }
+        let fee_policy = &self.init_params().initial_fee_policy;
+        let coin = Coin::new(fee_policy.coefficient.to_integral())?;
+        Ok(Fee::new(coin))
     }
 
     /// calculates the fee based on the specified policy
     pub fn calculate_fee(&self, num_bytes: usize) -> Result<Fee, CoinError> {
-        match self {
-            NetworkParameters::Genesis(params) => {
-                params.initial_fee_policy.calculate_fee(num_bytes)
-            }
-        }
+        let fee_policy = &self.init_params().initial_fee_policy;
+        fee_policy.calculate_fee(num_bytes)
+    }
+
+    /// bound on the work done by transactions in a block
+    /// (not specified at genesis, so all the nodes use the same constants)
+    pub fn get_block_budget(&self) -> BlockBudget {
+        DEFAULT_BLOCK_BUDGET
+    }
+}
+
+/// per-block budget of "resource units" (akin to block gas limit):
+/// each transaction costs the weighted sum of its encoded size, enclave calls and staking writes
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BlockBudget {
+    /// maximum number of units spent by all the transactions in a block
+    pub max_block_units: u64,
+    /// units per byte of the encoded transaction
+    pub units_per_byte: u64,
+    /// units per transaction validation call to the enclave
+    pub units_per_enclave_call: u64,
+    /// units per write to the staking state
+    pub units_per_staking_write: u64,
+}
+
+/// app version from which the block budget is enforced in blocks
+/// (rejecting the transactions which don't fit changes the block execution rules)
+pub const BLOCK_BUDGET_APP_VERSION: u64 = 2;
+
+/// whether the current app version rejects the delivered transactions which don't fit into
+/// the rest of the block budget
+pub fn block_budget_enforced() -> bool {
+    crate::APP_VERSION >= BLOCK_BUDGET_APP_VERSION
+}
+
+/// block budget used by all the networks
+pub const DEFAULT_BLOCK_BUDGET: BlockBudget = BlockBudget {
+    max_block_units: 50_000_000,
+    units_per_byte: 10,
+    units_per_enclave_call: 100_000,
+    units_per_staking_write: 20_000,
+};
+
+impl BlockBudget {
+    /// weighted sum of the resources used by a transaction (saturating)
+    pub fn units(&self, encoded_size: usize, enclave_calls: u64, staking_writes: u64) -> u64 {
+        (encoded_size as u64)
+            .saturating_mul(self.units_per_byte)
+            .saturating_add(enclave_calls.saturating_mul(self.units_per_enclave_call))
+            .saturating_add(staking_writes.saturating_mul(self.units_per_staking_write))
     }
 }
 
@@ -46,12 +46,27 @@ pub fn compute_app_hash(
     reward_pool: &RewardsPoolState,
     params: &NetworkParameters,
 ) -> H256 {
-    let valid_tx_part = valid_tx_id_tree.root_hash();
+    compute_app_hash_with_tx_root(
+        &valid_tx_id_tree.root_hash(),
+        account_state_root,
+        reward_pool,
+        params,
+    )
+}
+
+/// computes the "global" application hash (see `compute_app_hash`) when only the root of valid TX merkle tree
+/// is known (e.g. from an inclusion proof)
+pub fn compute_app_hash_with_tx_root(
+    valid_tx_part: &H256,
+    account_state_root: &H256,
+    reward_pool: &RewardsPoolState,
+    params: &NetworkParameters,
+) -> H256 {
     let rewards_pool_part = reward_pool.hash();
     let network_params_part = params.hash();
     let mut hasher = blake3::Hasher::new();
     hasher.update(b"app_hash");
-    hasher.update(&valid_tx_part);
+    hasher.update(&valid_tx_part[..]);
     hasher.update(&account_state_root[..]);
     hasher.update(&rewards_pool_part);
     hasher.update(&network_params_part);
@@ -1,6 +1,6 @@
 mod address;
 mod op;
-use crate::common::{Timespec, HASH_SIZE_256};
+use crate::common::{decode_blob_in_block, ConsensusCodec, Timespec, HASH_SIZE_256};
 use crate::init::coin::Coin;
 use crate::state::tendermint::{
     BlockHeight, TendermintValidatorAddress, TendermintValidatorPubKey,
@@ -30,6 +30,64 @@ pub type Nonce = u64;
 pub type NodeName = String;
 /// optional security@... email
 pub type NodeSecurityContact = Option<String>;
+/// "host:port" of a (sentry) node, announced for the network bootstrapping
+/// (informational, never used in consensus)
+pub type NodeEndpoint = String;
+
+/// maximum number of the endpoints announced by a council node
+pub const MAX_ANNOUNCED_ENDPOINTS: usize = 4;
+/// maximum length (in bytes) of an announced endpoint
+pub const MAX_ENDPOINT_LEN: usize = 64;
+/// app version from which the announced endpoints are a part of the council node metadata
+/// (and can be updated with `UpdateNodeTx`)
+pub const ANNOUNCED_ENDPOINTS_APP_VERSION: u64 = 2;
+
+/// whether the current app version encodes the announced endpoints and accepts node updates
+/// (before, the council node metadata keeps the 0.5 format)
+pub fn announced_endpoints_supported() -> bool {
+    crate::APP_VERSION >= ANNOUNCED_ENDPOINTS_APP_VERSION
+}
+
+fn is_valid_host(host: &str) -> bool {
+    if host.starts_with('[') && host.ends_with(']') {
+        let ip = &host[1..host.len() - 1];
+        ip.contains(':')
+            && ip
+                .chars()
+                .all(|c| c.is_ascii_hexdigit() || c == ':' || c == '.')
+    } else {
+        !host.is_empty()
+            && host.split('.').all(|label| {
+                !label.is_empty()
+                    && !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            })
+    }
+}
+
+/// checks the number, the lengths and the "host:port" syntax of the announced endpoints
+/// (the host is a hostname, an IPv4 address or a bracketed IPv6 address; the port isn't 0)
+pub fn check_announced_endpoints(endpoints: &[NodeEndpoint]) -> Result<(), &'static str> {
+    if endpoints.len() > MAX_ANNOUNCED_ENDPOINTS {
+        return Err("More than 4 announced endpoints");
+    }
+    for endpoint in endpoints {
+        if endpoint.len() > MAX_ENDPOINT_LEN {
+            return Err("Announced endpoint longer than 64 bytes");
+        }
+        let (host, port) = match endpoint.rfind(':') {
+            Some(i) => (&endpoint[..i], &endpoint[i + 1..]),
+            None => return Err("Announced endpoint without port"),
+        };
+        let valid_port = port.chars().all(|c| c.is_ascii_digit())
+            && port.parse::<u16>().map(|port| port > 0).unwrap_or(false);
+        if !valid_port || !is_valid_host(host) {
+            return Err("Invalid announced endpoint (expected host:port)");
+        }
+    }
+    Ok(())
+}
 
 /// FIXME: Encode, Decode implementations when MLS payloads are stabilized
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Encode, Decode)]
@@ -141,7 +199,7 @@ impl Decode for NodeCommonInfo {
         // 0.5 test vectors specified it as Vec<u8> blob
         // FIXME: ok to break when stabilized in 0.6? will it break HW wallet parser?
         let temp: Vec<u8> = Vec::decode(input)?;
-        let init_payload = MLSInit::decode(&mut temp.as_ref())?;
+        let init_payload = decode_blob_in_block::<MLSInit>(&temp)?;
         Ok(NodeCommonInfo {
             name,
             security_contact,
@@ -158,6 +216,9 @@ pub struct CouncilNodeMeta {
     pub node_info: NodeCommonInfo,
     /// Tendermint consensus validator-associated public key
     pub consensus_pubkey: TendermintValidatorPubKey,
+    /// "host:port" of the (sentry) nodes the other council nodes can connect to
+    #[serde(default)]
+    pub announced_endpoints: Vec<NodeEndpoint>,
 }
 
 impl Encode for CouncilNodeMeta {
@@ -178,9 +239,19 @@ impl Encode for CouncilNodeMeta {
         // FIXME: ok to break when stabilized in 0.6? will it break HW wallet parser?
         let temp: Vec<u8> = self.node_info.confidential_init.init_payload.encode();
         temp.encode_to(dest);
+        if announced_endpoints_supported() {
+            self.announced_endpoints.encode_to(dest);
+        }
     }
 }
 
+/// decodes the announced endpoints and checks them with `check_announced_endpoints`
+pub fn decode_announced_endpoints<I: Input>(input: &mut I) -> Result<Vec<NodeEndpoint>, Error> {
+    let endpoints: Vec<NodeEndpoint> = Vec::decode(input)?;
+    check_announced_endpoints(&endpoints)?;
+    Ok(endpoints)
+}
+
 impl Decode for CouncilNodeMeta {
     fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
         // NOTE/WARN: the order of node_info + consensus pubkey
@@ -191,13 +262,20 @@ impl Decode for CouncilNodeMeta {
         // 0.5 test vectors specified it as Vec<u8> blob
         // FIXME: ok to break when stabilized in 0.6? will it break HW wallet parser?
         let temp: Vec<u8> = Vec::decode(input)?;
-        let init_payload = MLSInit::decode(&mut temp.as_ref())?;
-        Ok(CouncilNodeMeta::new_with_details(
+        let init_payload = decode_blob_in_block::<MLSInit>(&temp)?;
+        let announced_endpoints = if announced_endpoints_supported() {
+            decode_announced_endpoints(input)?
+        } else {
+            Vec::new()
+        };
+        let mut council_node = CouncilNodeMeta::new_with_details(
             name,
             security_contact,
             consensus_pubkey,
             ConfidentialInit { init_payload },
-        ))
+        );
+        council_node.announced_endpoints = announced_endpoints;
+        Ok(council_node)
     }
 }
 
@@ -302,6 +380,7 @@ impl CouncilNodeMeta {
                 confidential_init,
             },
             consensus_pubkey,
+            announced_endpoints: Vec::new(),
         }
     }
 
@@ -319,6 +398,7 @@ impl CouncilNodeMeta {
                 confidential_init,
             },
             consensus_pubkey,
+            announced_endpoints: Vec::new(),
         }
     }
 }
@@ -613,6 +693,13 @@ impl StakedState {
     }
 }
 
+impl ConsensusCodec for NodeCommonInfo {}
+impl ConsensusCodec for CouncilNodeMeta {}
+impl ConsensusCodec for Validator {}
+impl ConsensusCodec for NodeState {}
+impl ConsensusCodec for SlashRecord {}
+impl ConsensusCodec for StakedState {}
+
 /// bond status for StakedState initialize
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub enum StakedStateDestination {
@@ -628,6 +715,8 @@ pub enum StakedStateDestination {
 mod test {
 
     use super::*;
+    use crate::common::check_fixture;
+    use crate::init::address::RedeemAddress;
     use quickcheck::quickcheck;
     use quickcheck::Arbitrary;
     use quickcheck::Gen;
@@ -645,14 +734,25 @@ mod test {
             };
             // TODO: generate well-formed keypackage
             let keypackage: Vec<u8> = Vec::arbitrary(g);
-            CouncilNodeMeta::new_with_details(
+            let mut council_node = CouncilNodeMeta::new_with_details(
                 name,
                 security_contact,
                 TendermintValidatorPubKey::Ed25519(raw_pubkey),
                 ConfidentialInit {
                     init_payload: MLSInit::Genesis(keypackage),
                 },
-            )
+            );
+            let num_endpoints = usize::arbitrary(g) % (MAX_ANNOUNCED_ENDPOINTS + 1);
+            council_node.announced_endpoints = (0..num_endpoints)
+                .map(|_| {
+                    format!(
+                        "sentry{}.example.com:{}",
+                        u8::arbitrary(g),
+                        u16::arbitrary(g).max(1)
+                    )
+                })
+                .collect();
+            council_node
         }
     }
 
@@ -679,4 +779,158 @@ mod test {
             }
         }
     }
+
+    fn sample_validator() -> Validator {
+        let mut validator = Validator::new(CouncilNodeMeta::new_with_details(
+            "node".to_owned(),
+            Some("security@example.com".to_owned()),
+            TendermintValidatorPubKey::Ed25519([0xcd; 32]),
+            ConfidentialInit {
+                init_payload: MLSInit::Genesis(vec![1, 2, 3]),
+            },
+        ));
+        validator.jail(10, BlockHeight::genesis(), 100);
+        validator
+            .used_validator_addresses
+            .push((validator.validator_address(), 10));
+        validator
+    }
+
+    crate::consensus_codec_tests! {
+        validator_should_be_reencoded_identically: Validator = sample_validator();
+        node_state_should_be_reencoded_identically: NodeState =
+            NodeState::CouncilNode(sample_validator());
+        slash_record_should_be_reencoded_identically: SlashRecord = SlashRecord {
+            kind: PunishmentKind::ByzantineFault,
+            time: 10,
+            amount: Coin::one(),
+        };
+        staked_state_should_be_reencoded_identically: StakedState = StakedState::new(
+            1,
+            Coin::one(),
+            Coin::unit(),
+            10,
+            StakedStateAddress::BasicRedeem(RedeemAddress([0x11; 20])),
+            Some(sample_validator()),
+        );
+    }
+
+    #[test]
+    fn staked_state_fixture_should_be_reencoded_identically() {
+        let staked_state: StakedState = check_fixture(
+            "010000000000000064000000000000000000000000000000000000000000000000111111111111111111111111111111111111111100010002000000000000000300000000000000",
+        );
+        assert_eq!(staked_state.nonce, 1);
+        assert_eq!(
+            staked_state.last_slash,
+            Some(SlashRecord {
+                kind: PunishmentKind::NonLive,
+                time: 2,
+                amount: Coin::new(3).unwrap(),
+            })
+        );
+    }
+
+    #[test]
+    fn council_node_with_trailing_keypackage_bytes_should_be_rejected() {
+        let strict = crate::APP_VERSION >= crate::common::STRICT_TX_DECODING_APP_VERSION;
+        let mut blob = MLSInit::Genesis(vec![1, 2, 3]).encode();
+        blob.push(0);
+
+        let mut encoded = "node".encode();
+        encoded.push(0);
+        TendermintValidatorPubKey::Ed25519([0xcd; 32]).encode_to(&mut encoded);
+        blob.encode_to(&mut encoded);
+        if announced_endpoints_supported() {
+            Vec::<NodeEndpoint>::new().encode_to(&mut encoded);
+        }
+
+        assert_eq!(
+            CouncilNodeMeta::decode(&mut encoded.as_slice()).is_err(),
+            strict
+        );
+        assert!(CouncilNodeMeta::decode_canonical(&encoded).is_err());
+
+        let mut encoded = "node".encode();
+        encoded.push(0);
+        blob.encode_to(&mut encoded);
+        assert_eq!(
+            NodeCommonInfo::decode(&mut encoded.as_slice()).is_err(),
+            strict
+        );
+        assert!(NodeCommonInfo::decode_canonical(&encoded).is_err());
+    }
+
+    #[test]
+    fn announced_endpoints_should_be_validated() {
+        let endpoints = |endpoints: &[&str]| -> Vec<NodeEndpoint> {
+            endpoints.iter().map(|e| e.to_string()).collect()
+        };
+        assert!(check_announced_endpoints(&endpoints(&[
+            "sentry-1.example.com:26656",
+            "10.0.0.1:26656",
+            "[2001:db8::1]:26656",
+            "localhost:1",
+        ]))
+        .is_ok());
+
+        // too many
+        let five = endpoints(&["10.0.0.1:26656"; MAX_ANNOUNCED_ENDPOINTS + 1]);
+        assert!(check_announced_endpoints(&five).is_err());
+        // too long
+        let long = format!("{}.example.com:26656", "a".repeat(MAX_ENDPOINT_LEN));
+        assert!(check_announced_endpoints(&[long]).is_err());
+        // bad syntax
+        for invalid in [
+            "sentry.example.com",
+            "sentry.example.com:",
+            "sentry.example.com:0",
+            "sentry.example.com:65536",
+            "sentry.example.com:+80",
+            ":26656",
+            "sentry..example.com:26656",
+            "-sentry.example.com:26656",
+            "sentry example.com:26656",
+            "2001:db8::1:26656",
+            "[sentry]:26656",
+        ]
+        .iter()
+        {
+            assert!(
+                check_announced_endpoints(&endpoints(&[*invalid])).is_err(),
+                "{} should be rejected",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "new-txid")]
+    fn council_node_with_invalid_endpoints_should_be_rejected() {
+        let mut council_node = sample_validator().council_node;
+        council_node.announced_endpoints = vec!["10.0.0.1:26656".to_owned()];
+        assert_eq!(
+            CouncilNodeMeta::decode(&mut council_node.encode().as_slice()).unwrap(),
+            council_node
+        );
+        council_node.announced_endpoints = vec!["10.0.0.1".to_owned()];
+        assert!(CouncilNodeMeta::decode(&mut council_node.encode().as_slice()).is_err());
+    }
+
+    #[test]
+    #[cfg(not(feature = "new-txid"))]
+    fn council_node_should_keep_the_previous_encoding() {
+        let mut council_node = sample_validator().council_node;
+        let encoded = council_node.encode();
+        council_node.announced_endpoints = vec!["10.0.0.1:26656".to_owned()];
+        assert_eq!(encoded, council_node.encode());
+    }
+
+    quickcheck! {
+        // tests if encode(decode(encode(x))) == encode(x)
+        fn prop_reencode_council_node(council_node: CouncilNodeMeta) -> bool {
+            !has_valid_len(&council_node)
+                || CouncilNodeMeta::decode_canonical(&council_node.encode()).is_ok()
+        }
+    }
 }
@@ -10,11 +10,11 @@ use serde::{Deserialize, Serialize};
 use std::prelude::v1::Vec;
 
 use self::tendermint::BlockHeight;
-use crate::common::{MerkleTree, Timespec, H256};
-use crate::compute_app_hash;
+use crate::common::{ConsensusCodec, MerkleTree, Timespec, H256};
 use crate::init::coin::Coin;
 use crate::init::params::NetworkParameters;
 use crate::tx::data::TxId;
+use crate::{compute_app_hash, compute_app_hash_with_tx_root};
 
 /// ABCI chain state
 #[derive(PartialEq, Debug, Clone, Encode, Decode, Serialize, Deserialize)]
@@ -38,6 +38,17 @@ impl ChainState {
             &self.network_params,
         )
     }
+
+    /// computes the app hash based on the internal parameters
+    /// and root of the valid transactions merkle tree in a given block
+    pub fn compute_app_hash_with_tx_root(&self, valid_tx_root: &H256) -> H256 {
+        compute_app_hash_with_tx_root(
+            valid_tx_root,
+            &self.account_root,
+            &self.rewards_pool,
+            &self.network_params,
+        )
+    }
 }
 
 /// State from which periodic rewards are distributed and calculated
@@ -73,3 +84,34 @@ impl RewardsPoolState {
         }
     }
 }
+
+impl ConsensusCodec for ChainState {}
+impl ConsensusCodec for RewardsPoolState {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::check_fixture;
+
+    crate::consensus_codec_tests! {
+        rewards_pool_should_be_reencoded_identically: RewardsPoolState = RewardsPoolState {
+            period_bonus: Coin::one(),
+            last_block_height: 10.into(),
+            last_distribution_time: 20,
+            minted: Coin::max(),
+            tau: 30,
+        };
+    }
+
+    #[test]
+    fn rewards_pool_fixture_should_be_reencoded_identically() {
+        let rewards_pool: RewardsPoolState = check_fixture(
+            "0500000000000000070000000000000009000000000000000b000000000000000d00000000000000",
+        );
+        assert_eq!(rewards_pool.period_bonus, Coin::new(5).unwrap());
+        assert_eq!(rewards_pool.last_block_height, 7.into());
+        assert_eq!(rewards_pool.last_distribution_time, 9);
+        assert_eq!(rewards_pool.minted, Coin::new(11).unwrap());
+        assert_eq!(rewards_pool.tau, 13);
+    }
+}
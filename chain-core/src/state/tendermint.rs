@@ -262,6 +262,23 @@ impl TendermintValidatorPubKey {
             Self::Ed25519(ref bytes) => bytes,
         }
     }
+
+    /// encode the raw bytes as base64 (the inverse of `from_base64`)
+    pub fn to_base64(&self) -> String {
+        base64::encode(self.as_bytes())
+    }
+}
+
+/// Voting power change of a validator included in the end block validator updates
+/// (value of the `validator_update` attribute of `validator_set_update` events, JSON-encoded)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorPowerChange {
+    /// base64-encoded consensus public key
+    pub pubkey: String,
+    /// voting power before the update (zero if the validator wasn't in the set)
+    pub old_power: TendermintVotePower,
+    /// voting power after the update (zero if the validator was removed from the set)
+    pub new_power: TendermintVotePower,
 }
 
 /// tendermint validator address (the first 20 bytes of sha256 of the pubkey??)
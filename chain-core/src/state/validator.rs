@@ -1,5 +1,7 @@
 mod nodejoin;
 mod unjail;
+mod update;
 
 pub use nodejoin::NodeJoinRequestTx;
 pub use unjail::UnjailTx;
+pub use update::UpdateNodeTx;
@@ -0,0 +1,109 @@
+use crate::state::account::{
+    announced_endpoints_supported, check_announced_endpoints, decode_announced_endpoints,
+    NodeEndpoint, Nonce, StakedStateAddress, StakedStateOpAttributes,
+};
+#[cfg(feature = "new-txid")]
+use crate::tx::TaggedTransaction;
+#[cfg(not(feature = "new-txid"))]
+use crate::tx::TransactionId;
+use parity_scale_codec::{Decode, Encode, Error, Input, Output};
+
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+
+/// Updates the informational metadata of a council node:
+/// the announced endpoints replace the previous ones (empty to remove them)
+///
+/// (only accepted from `ANNOUNCED_ENDPOINTS_APP_VERSION`; it pays the fee from the bonded amount)
+///
+/// tx-validation should check that:
+/// - the staked state has council node metadata
+/// - the announced endpoints are valid (`check_announced_endpoints`)
+/// - the witness is correct
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct UpdateNodeTx {
+    /// the expected nonce on the corresponding state
+    pub nonce: Nonce,
+    /// the expected address on the corresponding state
+    pub address: StakedStateAddress,
+    /// the versioning and network identifier
+    pub attributes: StakedStateOpAttributes,
+    /// "host:port" of the (sentry) nodes the other council nodes can connect to
+    pub announced_endpoints: Vec<NodeEndpoint>,
+}
+
+impl Decode for UpdateNodeTx {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let nonce = Nonce::decode(input)?;
+        let address = StakedStateAddress::decode(input)?;
+        let attributes = StakedStateOpAttributes::decode(input)?;
+        let announced_endpoints = decode_announced_endpoints(input)?;
+
+        Ok(UpdateNodeTx {
+            nonce,
+            address,
+            attributes,
+            announced_endpoints,
+        })
+    }
+}
+
+impl Encode for UpdateNodeTx {
+    fn encode_to<EncOut: Output>(&self, dest: &mut EncOut) {
+        dest.push(&self.nonce);
+        dest.push(&self.address);
+        dest.push(&self.attributes);
+        dest.push(&self.announced_endpoints);
+    }
+
+    fn size_hint(&self) -> usize {
+        self.nonce.size_hint()
+            + self.address.size_hint()
+            + self.attributes.size_hint()
+            + self.announced_endpoints.size_hint()
+    }
+}
+
+#[cfg(not(feature = "new-txid"))]
+impl TransactionId for UpdateNodeTx {}
+
+#[cfg(feature = "new-txid")]
+impl From<UpdateNodeTx> for TaggedTransaction {
+    fn from(tx: UpdateNodeTx) -> TaggedTransaction {
+        TaggedTransaction::UpdateNodeTx(tx)
+    }
+}
+
+impl UpdateNodeTx {
+    /// constructs a new node update transaction from the provided components
+    /// (the endpoints are checked, as they would be when decoded)
+    pub fn new(
+        nonce: Nonce,
+        address: StakedStateAddress,
+        attributes: StakedStateOpAttributes,
+        announced_endpoints: Vec<NodeEndpoint>,
+    ) -> Result<Self, &'static str> {
+        if !announced_endpoints_supported() {
+            return Err("Node updates are not supported in the current app version");
+        }
+        check_announced_endpoints(&announced_endpoints)?;
+        Ok(Self {
+            nonce,
+            address,
+            attributes,
+            announced_endpoints,
+        })
+    }
+}
+
+impl fmt::Display for UpdateNodeTx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "updated node: {} (nonce: {})", self.address, self.nonce)?;
+        writeln!(
+            f,
+            "announced endpoints: {}",
+            self.announced_endpoints.join(", ")
+        )
+    }
+}
@@ -4,10 +4,18 @@ use serde::de;
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use std::collections::BTreeSet;
 use std::fmt;
 use std::prelude::v1::Vec;
 
-use crate::tx::data::access::TxAccessPolicy;
+use secp256k1::key::PublicKey;
+use thiserror::Error as ThisError;
+
+use crate::tx::data::access::{TxAccess, TxAccessPolicy};
+
+/// Maximal number of view keys in the transaction attributes
+/// (assumed by the maximal transaction size)
+pub const MAX_ALLOWED_VIEW_KEYS: usize = 64;
 
 /// Tx extra metadata, e.g. network ID
 #[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
@@ -118,4 +126,136 @@ impl TxAttributes {
             app_version: crate::APP_VERSION,
         }
     }
+
+    /// starts building tx attributes (validated when built)
+    pub fn builder(chain_hex_id: u8) -> TxAttributesBuilder {
+        TxAttributesBuilder {
+            chain_hex_id,
+            allowed_view: BTreeSet::new(),
+            app_version: crate::APP_VERSION,
+        }
+    }
+}
+
+/// Invalid tx attributes
+#[derive(ThisError, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AttributeError {
+    /// more than `MAX_ALLOWED_VIEW_KEYS` (distinct) view keys
+    #[error("too many view keys: {0} (at most {} allowed)", MAX_ALLOWED_VIEW_KEYS)]
+    TooManyViewKeys(usize),
+    /// app version which isn't known to this chain-core version
+    #[error("unknown app version: {0}")]
+    UnknownAppVersion(u64),
+}
+
+/// Builder of tx attributes: view keys are deduplicated (and sorted),
+/// the limits are checked in `build`
+#[derive(Debug, Clone)]
+pub struct TxAttributesBuilder {
+    chain_hex_id: u8,
+    allowed_view: BTreeSet<TxAccessPolicy>,
+    app_version: u64,
+}
+
+impl TxAttributesBuilder {
+    /// allows the view key to access all the transaction data
+    pub fn allow_view(mut self, view_key: PublicKey) -> Self {
+        self.allowed_view
+            .insert(TxAccessPolicy::new(view_key, TxAccess::AllData));
+        self
+    }
+
+    /// allows the view keys to access all the transaction data
+    pub fn allow_view_many<I>(self, view_keys: I) -> Self
+    where
+        I: IntoIterator<Item = PublicKey>,
+    {
+        view_keys
+            .into_iter()
+            .fold(self, |builder, view_key| builder.allow_view(view_key))
+    }
+
+    /// sets the app version (the current `APP_VERSION` by default)
+    pub fn app_version(mut self, app_version: u64) -> Self {
+        self.app_version = app_version;
+        self
+    }
+
+    /// validates and returns the tx attributes
+    pub fn build(self) -> Result<TxAttributes, AttributeError> {
+        if self.allowed_view.len() > MAX_ALLOWED_VIEW_KEYS {
+            return Err(AttributeError::TooManyViewKeys(self.allowed_view.len()));
+        }
+        if self.app_version == 0 || self.app_version > crate::APP_VERSION {
+            return Err(AttributeError::UnknownAppVersion(self.app_version));
+        }
+        Ok(TxAttributes {
+            chain_hex_id: self.chain_hex_id,
+            allowed_view: self.allowed_view.into_iter().collect(),
+            app_version: self.app_version,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::SecretKey;
+
+    fn view_key(seed: u8) -> PublicKey {
+        let secp = secp256k1::SECP256K1;
+        let secret_key = SecretKey::from_slice(&[seed; 32]).expect("secret key");
+        PublicKey::from_secret_key(&secp, &secret_key)
+    }
+
+    #[test]
+    fn builder_should_dedup_view_keys_and_encode_as_hand_built() {
+        let attributes = TxAttributes::builder(0xab)
+            .allow_view(view_key(2))
+            .allow_view_many(vec![view_key(1), view_key(2)])
+            .build()
+            .unwrap();
+
+        let mut policies = vec![
+            TxAccessPolicy::new(view_key(1), TxAccess::AllData),
+            TxAccessPolicy::new(view_key(2), TxAccess::AllData),
+        ];
+        policies.sort();
+        let hand_built = TxAttributes::new_with_access(0xab, policies);
+        assert_eq!(attributes, hand_built);
+        assert_eq!(attributes.encode(), hand_built.encode());
+        assert_eq!(
+            TxAttributes::decode(&mut attributes.encode().as_slice()).unwrap(),
+            hand_built
+        );
+        assert_eq!(
+            TxAttributes::builder(0xab).build().unwrap().encode(),
+            TxAttributes::new(0xab).encode()
+        );
+    }
+
+    #[test]
+    fn builder_should_check_limits() {
+        let result = TxAttributes::builder(0)
+            .allow_view_many((1..=(MAX_ALLOWED_VIEW_KEYS as u8 + 1)).map(view_key))
+            .build();
+        assert_eq!(
+            result,
+            Err(AttributeError::TooManyViewKeys(MAX_ALLOWED_VIEW_KEYS + 1))
+        );
+        assert!(TxAttributes::builder(0)
+            .allow_view_many((1..=(MAX_ALLOWED_VIEW_KEYS as u8)).map(view_key))
+            .build()
+            .is_ok());
+        assert_eq!(
+            TxAttributes::builder(0).app_version(0).build(),
+            Err(AttributeError::UnknownAppVersion(0))
+        );
+        assert_eq!(
+            TxAttributes::builder(0)
+                .app_version(crate::APP_VERSION + 1)
+                .build(),
+            Err(AttributeError::UnknownAppVersion(crate::APP_VERSION + 1))
+        );
+    }
 }
@@ -8,6 +8,7 @@ use crate::tx::TxAux;
 use parity_scale_codec::{Decode, Encode};
 
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::num::ParseIntError;
 use std::ops::{Add, Div, Mul};
 use std::prelude::v1::Vec;
@@ -252,10 +253,136 @@ impl FeeAlgorithm for LinearFee {
     }
 }
 
+/// Upper bound of the bisection steps of `solve_output_minus_fee` (the searched range of amounts
+/// is at most `2^64` wide and is halved by each step)
+const MAX_SOLVER_STEPS: usize = 64;
+
+/// Errors from solving the output amount of a transaction which has to pay its own fee
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FeeSolverError {
+    /// The fee could not be calculated
+    Coin(CoinError),
+    /// The total is less than the fee of the transaction
+    InsufficientTotal,
+    /// The fee grows past the total when the amount changes by a single base unit, so no amount
+    /// adds up to the total with its fee
+    NoExactAmount,
+}
+
+impl fmt::Display for FeeSolverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            FeeSolverError::Coin(ref err) => write!(f, "Fee calculation error: {}", err),
+            FeeSolverError::InsufficientTotal => {
+                write!(f, "Calculated fee is more than the total amount")
+            }
+            FeeSolverError::NoExactAmount => {
+                write!(
+                    f,
+                    "No output amount adds up to the total amount with its fee"
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for FeeSolverError {}
+
+impl From<CoinError> for FeeSolverError {
+    fn from(err: CoinError) -> Self {
+        FeeSolverError::Coin(err)
+    }
+}
+
+/// Solves `amount + fee(tx_template(amount)) == total`, i.e. finds the amount of the single output
+/// of a transaction which spends exactly `total` (such as a withdrawal of all the unbonded coins
+/// of a staked state) when the output has to absorb the fee. Returns the amount and the fee.
+///
+/// The fee of the transaction may depend on the amount (e.g. through its encoded size), so
+/// iterating `amount = total - fee(amount)` can oscillate around a size boundary. Instead, for a
+/// fee which is non-decreasing in the amount, `amount + fee(amount)` is strictly increasing, so
+/// there is at most one solution:
+/// - `total - fee(0)` is an upper bound of it and is the solution when the fee doesn't depend on
+///   the amount;
+/// - `total - fee(upper bound)` is a lower bound of it;
+/// - the range between the bounds is bisected, which takes at most `MAX_SOLVER_STEPS` steps.
+///
+/// With a fee which decreases with the amount, `NoExactAmount` may be returned even though there
+/// is a solution.
+pub fn solve_output_minus_fee<F, T>(
+    total: Coin,
+    fee_algo: &F,
+    tx_template: T,
+) -> Result<(Coin, Fee), FeeSolverError>
+where
+    F: FeeAlgorithm,
+    T: Fn(Coin) -> TxAux,
+{
+    let fee_of = |amount: Coin| fee_algo.calculate_for_txaux(&tx_template(amount));
+    // the sign of `amount + fee(amount) - total`
+    let compare = |amount: Coin| -> Result<(Ordering, Fee), FeeSolverError> {
+        let fee = fee_of(amount)?;
+        // `amount <= total` for all the evaluated amounts
+        let rest = (total - amount)?;
+        Ok((fee.to_coin().cmp(&rest), fee))
+    };
+
+    let upper =
+        (total - fee_of(Coin::zero())?.to_coin()).map_err(|_| FeeSolverError::InsufficientTotal)?;
+    let (ordering, fee) = compare(upper)?;
+    match ordering {
+        Ordering::Equal => return Ok((upper, fee)),
+        // only with a fee which decreases with the amount
+        Ordering::Less => return Err(FeeSolverError::NoExactAmount),
+        Ordering::Greater => {}
+    }
+
+    let lower = match total - fee.to_coin() {
+        Ok(lower) => lower,
+        // even the fee of the upper bound is more than the total: the lower bound is zero
+        Err(_) => Coin::zero(),
+    };
+    let (ordering, fee) = compare(lower)?;
+    match ordering {
+        Ordering::Equal => return Ok((lower, fee)),
+        // only with a fee which decreases with the amount
+        Ordering::Greater => return Err(FeeSolverError::NoExactAmount),
+        Ordering::Less => {}
+    }
+
+    // invariant: `lower` is below the solution and `upper` above it
+    let (mut lower, mut upper) = (u64::from(lower), u64::from(upper));
+    for _ in 0..MAX_SOLVER_STEPS {
+        if upper - lower <= 1 {
+            break;
+        }
+        let middle = lower + (upper - lower) / 2;
+        let amount = Coin::new(middle)?;
+        let (ordering, fee) = compare(amount)?;
+        match ordering {
+            Ordering::Equal => return Ok((amount, fee)),
+            Ordering::Less => lower = middle,
+            Ordering::Greater => upper = middle,
+        }
+    }
+    assert!(
+        upper - lower <= 1,
+        "bisection of the output amount did not converge"
+    );
+    Err(FeeSolverError::NoExactAmount)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::init::address::RedeemAddress;
+    use crate::state::account::{
+        StakedStateAddress, StakedStateOpAttributes, StakedStateOpWitness, UnbondTx,
+    };
+    use crate::tx::TxPublicAux;
+    use parity_scale_codec::Compact;
     use quickcheck::quickcheck;
+    use secp256k1::recovery::{RecoverableSignature, RecoveryId};
 
     fn test_milli_add_eq(v1: u64, v2: u64) -> bool {
         let v = v1 + v2;
@@ -338,6 +465,101 @@ mod test {
         assert!(t.is_err());
     }
 
+    fn unbond_template(amount: Coin) -> TxAux {
+        let signature =
+            RecoverableSignature::from_compact(&[0; 64], RecoveryId::from_i32(1).unwrap()).unwrap();
+        TxAux::PublicTx(TxPublicAux::UnbondStakeTx(
+            UnbondTx::new(
+                StakedStateAddress::BasicRedeem(RedeemAddress::default()),
+                0,
+                amount,
+                StakedStateOpAttributes::new(0),
+            ),
+            StakedStateOpWitness::new(signature),
+        ))
+    }
+
+    /// 100 base units per byte of the (compact) encoded amount of an unbonding transaction, so
+    /// that the fee jumps where the encoded size of the amount changes
+    #[derive(Clone)]
+    struct CompactAmountFee;
+
+    fn compact_amount_fee(amount: u64) -> u64 {
+        100 * Compact(amount).encode().len() as u64
+    }
+
+    impl FeeAlgorithm for CompactAmountFee {
+        fn calculate_fee(&self, num_bytes: usize) -> Result<Fee, CoinError> {
+            Coin::new(100 * num_bytes as u64).map(Fee::new)
+        }
+
+        fn calculate_for_txaux(&self, txaux: &TxAux) -> Result<Fee, CoinError> {
+            match txaux {
+                TxAux::PublicTx(TxPublicAux::UnbondStakeTx(tx, _)) => {
+                    Coin::new(compact_amount_fee(tx.value.into())).map(Fee::new)
+                }
+                _ => unreachable!("only unbonding transactions are estimated"),
+            }
+        }
+    }
+
+    #[test]
+    fn check_solve_output_minus_fee_with_linear_fee() {
+        let fee_algo = LinearFee::new(Milli::new(1, 1), Milli::new(1, 1));
+        let total = Coin::new(10_000).unwrap();
+        let (amount, fee) = solve_output_minus_fee(total, &fee_algo, unbond_template).unwrap();
+        assert_eq!(total, (amount + fee.to_coin()).unwrap());
+        assert_eq!(
+            fee,
+            fee_algo
+                .calculate_for_txaux(&unbond_template(amount))
+                .unwrap()
+        );
+
+        assert_eq!(
+            Err(FeeSolverError::InsufficientTotal),
+            solve_output_minus_fee(Coin::unit(), &fee_algo, unbond_template)
+        );
+    }
+
+    #[test]
+    fn check_solve_output_minus_fee_at_size_boundaries() {
+        let solve = |total: u64| {
+            solve_output_minus_fee(
+                Coin::new(total).unwrap(),
+                &CompactAmountFee,
+                unbond_template,
+            )
+            .map(|(amount, fee)| (u64::from(amount), u64::from(fee.to_coin())))
+        };
+
+        // `total - fee(amount)` alternates between both sides of the 2-to-4 bytes boundary
+        assert_eq!(Err(FeeSolverError::NoExactAmount), solve(16384 + 300));
+        assert_eq!(Ok((16384, 400)), solve(16384 + 400));
+        assert_eq!(Ok((16383, 200)), solve(16383 + 200));
+        assert_eq!(Err(FeeSolverError::InsufficientTotal), solve(99));
+
+        // the encoded size of the amount changes at each of these values
+        for boundary in &[1u64 << 6, 1 << 14, 1 << 30, 1 << 32] {
+            for total in boundary.saturating_sub(1000)..boundary + 1000 {
+                let solutions = (total.saturating_sub(1000)..=total)
+                    .filter(|amount| amount + compact_amount_fee(*amount) == total)
+                    .collect::<Vec<_>>();
+                match solve(total) {
+                    Ok((amount, fee)) => {
+                        assert_eq!(vec![amount], solutions);
+                        assert_eq!(compact_amount_fee(amount), fee);
+                    }
+                    Err(FeeSolverError::NoExactAmount) => assert!(solutions.is_empty()),
+                    Err(err) => {
+                        assert_eq!(FeeSolverError::InsufficientTotal, err);
+                        assert!(total < compact_amount_fee(0));
+                    }
+                }
+            }
+        }
+    }
+
     quickcheck! {
         fn prop_milli_add(n1: u64, n2: u64) -> bool {
             test_milli_add_eq(n1, n2)
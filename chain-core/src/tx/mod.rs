@@ -4,6 +4,8 @@ use std::prelude::v1::Vec;
 pub mod data;
 /// Transaction fee calculation
 pub mod fee;
+/// Indexed results of the delivered transactions
+pub mod result;
 /// Witness structures (e.g. signatures) for transactions
 pub mod witness;
 
@@ -15,11 +17,11 @@ use self::data::Tx;
 use self::witness::TxWitness;
 use crate::mls::MLSHandshakeAux;
 use crate::state::account::{
-    DepositBondTx, StakedStateOpAttributes, StakedStateOpWitness, UnbondTx, UnjailTx,
-    WithdrawUnbondedTx,
+    announced_endpoints_supported, DepositBondTx, StakedStateOpAttributes, StakedStateOpWitness,
+    UnbondTx, UnjailTx, WithdrawUnbondedTx,
 };
 use crate::state::tendermint::BlockHeight;
-use crate::state::validator::NodeJoinRequestTx;
+use crate::state::validator::{NodeJoinRequestTx, UpdateNodeTx};
 use crate::tx::data::TxId;
 use aead::Payload;
 use data::input::{TxoPointer, TxoSize};
@@ -380,7 +382,7 @@ impl TxEnclaveAux {
 }
 
 /// Transactions that are directly processed in non-enclave execution environment (chain-abci)
-/// TODO/NOTE: other TX types expected -- update of council node metadata, bonus donation, ...
+/// TODO/NOTE: other TX types expected -- bonus donation, ...
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum TxPublicAux {
     /// Tx that modifies staked state -- moves some bonded stake into unbonded (witness for staked state)
@@ -389,6 +391,8 @@ pub enum TxPublicAux {
     UnjailTx(UnjailTx, StakedStateOpWitness),
     /// Tx that updates a staked state with node (community or council node) details
     NodeJoinTx(NodeJoinRequestTx, StakedStateOpWitness),
+    /// Tx that updates the (informational) council node metadata of a staked state
+    UpdateNodeTx(UpdateNodeTx, StakedStateOpWitness),
 }
 
 impl Encode for TxPublicAux {
@@ -409,6 +413,11 @@ impl Encode for TxPublicAux {
                 dest.push(tx);
                 dest.push(witness);
             }
+            TxPublicAux::UpdateNodeTx(ref tx, ref witness) => {
+                dest.push_byte(3);
+                dest.push(tx);
+                dest.push(witness);
+            }
         }
     }
 
@@ -417,6 +426,7 @@ impl Encode for TxPublicAux {
             TxPublicAux::UnbondStakeTx(tx, witness) => tx.size_hint() + witness.size_hint(),
             TxPublicAux::UnjailTx(tx, witness) => tx.size_hint() + witness.size_hint(),
             TxPublicAux::NodeJoinTx(tx, witness) => tx.size_hint() + witness.size_hint(),
+            TxPublicAux::UpdateNodeTx(tx, witness) => tx.size_hint() + witness.size_hint(),
         }
     }
 }
@@ -424,7 +434,7 @@ impl Encode for TxPublicAux {
 impl Decode for TxPublicAux {
     fn decode<DecIn: Input>(input: &mut DecIn) -> Result<Self, Error> {
         let tag = input.read_byte()?;
-        // note: 3.. tags reserved for other tx types (node metadata update etc.)
+        // note: 4.. tags reserved for other tx types
         match tag {
             0 => {
                 let tx = UnbondTx::decode(input)?;
@@ -441,6 +451,12 @@ impl Decode for TxPublicAux {
                 let witness = StakedStateOpWitness::decode(input)?;
                 Ok(TxPublicAux::NodeJoinTx(tx, witness))
             }
+            // node updates are rejected (as unknown) before their app version
+            3 if announced_endpoints_supported() => {
+                let tx = UpdateNodeTx::decode(input)?;
+                let witness = StakedStateOpWitness::decode(input)?;
+                Ok(TxPublicAux::UpdateNodeTx(tx, witness))
+            }
             _ => Err("No such variant in enum TxPublicAux".into()),
         }
     }
@@ -453,6 +469,7 @@ impl TxPublicAux {
             TxPublicAux::UnbondStakeTx(tx, _) => tx.id(),
             TxPublicAux::UnjailTx(tx, _) => tx.id(),
             TxPublicAux::NodeJoinTx(tx, _) => tx.id(),
+            TxPublicAux::UpdateNodeTx(tx, _) => tx.id(),
         }
     }
 
@@ -462,6 +479,7 @@ impl TxPublicAux {
             TxPublicAux::UnbondStakeTx(tx, _) => &tx.attributes,
             TxPublicAux::UnjailTx(tx, _) => &tx.attributes,
             TxPublicAux::NodeJoinTx(tx, _) => &tx.attributes,
+            TxPublicAux::UpdateNodeTx(tx, _) => &tx.attributes,
         }
     }
 
@@ -590,6 +608,8 @@ pub enum TaggedTransaction {
     MLSSelfUpdateProposal(crate::mls::SelfUpdateProposalTx),
     /// NACK
     MLSMsgNack(crate::mls::NackMsgTx),
+    /// council node metadata update
+    UpdateNodeTx(UpdateNodeTx),
 }
 
 #[cfg(feature = "new-txid")]
@@ -652,6 +672,9 @@ impl fmt::Display for TxAux {
             TxAux::PublicTx(TxPublicAux::NodeJoinTx(tx, witness)) => {
                 display_tx_witness(f, tx, witness)
             }
+            TxAux::PublicTx(TxPublicAux::UpdateNodeTx(tx, witness)) => {
+                display_tx_witness(f, tx, witness)
+            }
             TxAux::MLSHandshake(_) => {
                 // FIXME
                 writeln!(f, "mls handshake")
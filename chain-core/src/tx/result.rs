@@ -0,0 +1,63 @@
+//! Results of the delivered transactions, as recorded by the nodes which index them
+//! (`txresult/<hex txid>` query)
+
+use crate::init::coin::Coin;
+use crate::state::account::StakedStateAddress;
+use crate::state::tendermint::BlockHeight;
+use parity_scale_codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+/// Prefix of the query path of the indexed transaction results: `txresult/<hex txid>`
+pub const TX_RESULT_PATH_PREFIX: &str = "txresult/";
+/// Response code of the `txresult/...` query when the transaction isn't indexed
+/// (unknown, pruned or delivered before the indexing was enabled)
+pub const TX_RESULT_NOT_FOUND_CODE: u32 = 1;
+/// Response code of the `txresult/...` query when the node doesn't index the results
+pub const TX_RESULT_DISABLED_CODE: u32 = 2;
+/// Log of the `txresult/...` query response when the transaction isn't indexed
+/// (as the clients may only get the log of a failed query)
+pub const TX_RESULT_NOT_FOUND_LOG: &str = "tx result not found";
+
+/// Result of a transaction delivered in a block
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+pub struct TxResult {
+    /// height of the block the transaction was delivered in
+    pub height: BlockHeight,
+    /// position of the transaction in the block
+    pub index: u32,
+    /// DeliverTx response code (0 if the transaction was accepted)
+    pub code: u32,
+    /// fee paid by the transaction (zero if it was rejected)
+    pub fee: Coin,
+    /// staking address involved in the transaction (if any)
+    pub staking_address: Option<StakedStateAddress>,
+}
+
+impl TxResult {
+    /// whether the transaction was accepted (and its effects applied)
+    pub fn is_accepted(&self) -> bool {
+        self.code == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::init::address::RedeemAddress;
+
+    #[test]
+    fn tx_result_should_roundtrip() {
+        let result = TxResult {
+            height: BlockHeight::new(10),
+            index: 2,
+            code: 0,
+            fee: Coin::new(1000).unwrap(),
+            staking_address: Some(StakedStateAddress::BasicRedeem(RedeemAddress::default())),
+        };
+        assert_eq!(
+            result,
+            TxResult::decode(&mut result.encode().as_slice()).unwrap()
+        );
+        assert!(result.is_accepted());
+    }
+}
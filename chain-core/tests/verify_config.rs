@@ -1,8 +1,9 @@
 use chain_core::init::address::RedeemAddress;
 use chain_core::init::coin::Coin;
 use chain_core::init::config::{
-    InitConfig, InitNetworkParameters, JailingParameters, RewardsParameters, SlashRatio,
-    SlashingParameters,
+    network_parameters_v2_supported, DistributionError, InitConfig, InitNetworkParameters,
+    JailingParameters, NetworkParameters, RewardsParameters, SlashRatio, SlashingParameters,
+    NETWORK_PARAMETERS_V2_APP_VERSION,
 };
 use chain_core::state::account::StakedStateDestination;
 use chain_core::state::tendermint::TendermintValidatorPubKey;
@@ -87,6 +88,47 @@ fn test_verify_test_example_snapshot() {
     config
         .validate_config_get_genesis(DEFAULT_GENESIS_TIME)
         .unwrap();
+    // the parameters of the existing networks keep their encoding
+    assert_eq!(
+        NetworkParameters::Genesis(params.clone()),
+        config.get_network_parameters()
+    );
+    assert_eq!(
+        Coin::zero(),
+        config.get_network_parameters().get_minimum_bond_remainder()
+    );
+
+    // validators can still unbond down to the required stake
+    let mut remainder_config = config.clone();
+    remainder_config.minimum_bond_remainder = Some(params.required_council_node_stake);
+    let result = remainder_config.validate_config_get_genesis(DEFAULT_GENESIS_TIME);
+    if network_parameters_v2_supported() {
+        result.unwrap();
+        assert_eq!(
+            params.required_council_node_stake,
+            remainder_config
+                .get_network_parameters()
+                .get_minimum_bond_remainder()
+        );
+    } else {
+        assert!(matches!(
+            result,
+            Err(DistributionError::MinimumBondRemainderNotSupported(
+                NETWORK_PARAMETERS_V2_APP_VERSION
+            ))
+        ));
+    }
+    remainder_config.minimum_bond_remainder =
+        Some((params.required_council_node_stake + Coin::unit()).unwrap());
+    let result = remainder_config.validate_config_get_genesis(DEFAULT_GENESIS_TIME);
+    if network_parameters_v2_supported() {
+        assert!(matches!(
+            result,
+            Err(DistributionError::InvalidMinimumBondRemainder(_))
+        ));
+    } else {
+        assert!(result.is_err());
+    }
 
     // add 1 into rewards_pool
     params.rewards_config.monetary_expansion_cap = Coin::new(951_6484_5705_9733_7035).unwrap();
@@ -10,12 +10,13 @@ use chain_core::tx::data::{
     input::{TxoPointer, TxoSize},
     TxId,
 };
+use chain_core::tx::result::TxResult;
 
 use super::buffer::{GetKV, StoreKV};
 use super::{
     LookupItem, StoredChainState, CHAIN_ID_KEY, COL_APP_HASHS, COL_APP_STATES, COL_EXTRA,
-    COL_NODE_INFO, COL_STAKING_VERSIONS, GENESIS_APP_HASH_KEY, LAST_FETCHED_BLOCK_KEY,
-    LAST_STATE_KEY,
+    COL_FILTER_RANGES, COL_NODE_INFO, COL_STAKING_VERSIONS, COL_TX_RESULTS, GENESIS_APP_HASH_KEY,
+    LAST_FETCHED_BLOCK_KEY, LAST_STATE_KEY, TX_RESULTS_EARLIEST_KEY,
 };
 
 pub fn get_last_app_state(db: &impl GetKV) -> Option<Vec<u8>> {
@@ -173,6 +174,68 @@ pub fn store_txs_merkle_tree(db: &mut impl StoreKV, app_hash: &H256, tree_payloa
     insert_item(db, LookupItem::TxsMerkle, *app_hash, tree_payload.to_vec());
 }
 
+/// Stores the results of the transactions delivered in the block at `height` in the tx result index
+/// (an accepted result isn't overwritten by a rejected replay of the same transaction)
+pub fn store_tx_results(db: &mut impl StoreKV, height: BlockHeight, results: &[(TxId, TxResult)]) {
+    let mut txids = Vec::with_capacity(results.len());
+    for (txid, result) in results.iter() {
+        if get_tx_result(&*db, txid).map_or(false, |stored| stored.is_accepted()) {
+            continue;
+        }
+        db.set((COL_TX_RESULTS, txid.to_vec()), result.encode());
+        txids.push(*txid);
+    }
+    if !txids.is_empty() {
+        db.set((COL_TX_RESULTS, height.encode()), txids.encode());
+    }
+    let earliest_key = (COL_TX_RESULTS, TX_RESULTS_EARLIEST_KEY.to_vec());
+    if db.get(&earliest_key).is_none() {
+        db.set(earliest_key, height.encode());
+    }
+}
+
+pub fn get_tx_result(db: &impl GetKV, txid: &TxId) -> Option<TxResult> {
+    db.get(&(COL_TX_RESULTS, txid.to_vec()))
+        .map(|result| TxResult::decode(&mut result.as_slice()).expect("stored tx result"))
+}
+
+/// Removes the results of the transactions delivered below `retain_height` from the tx result index
+pub fn prune_tx_results(db: &mut impl StoreKV, retain_height: BlockHeight) {
+    let earliest_key = (COL_TX_RESULTS, TX_RESULTS_EARLIEST_KEY.to_vec());
+    let earliest = match db.get(&earliest_key) {
+        Some(height) => BlockHeight::decode(&mut height.as_slice()).expect("stored height"),
+        None => return,
+    };
+    if earliest >= retain_height {
+        return;
+    }
+    for height in earliest.value()..retain_height.value() {
+        let block_key = (COL_TX_RESULTS, BlockHeight::new(height).encode());
+        if let Some(txids) = db.get(&block_key) {
+            let txids = Vec::<TxId>::decode(&mut txids.as_slice()).expect("stored txids");
+            for txid in txids.iter() {
+                // a rejected transaction may be delivered again in a later block
+                let stored = get_tx_result(&*db, txid);
+                if stored.map_or(false, |result| result.height.value() == height) {
+                    db.delete((COL_TX_RESULTS, txid.to_vec()));
+                }
+            }
+            db.delete(block_key);
+        }
+    }
+    db.set(earliest_key, retain_height.encode());
+}
+
+/// Stores the (encoded) filter rolled up from the block filters of the range at `index`
+pub fn store_filter_range(db: &mut impl StoreKV, index: u64, filter_range: Vec<u8>) {
+    db.set((COL_FILTER_RANGES, index.encode()), filter_range);
+}
+
+/// The (encoded) filter rolled up from the block filters of the range at `index`
+pub fn get_filter_range(db: &impl GetKV, index: u64) -> Option<Vec<u8>> {
+    db.get(&(COL_FILTER_RANGES, index.encode()))
+}
+
 pub fn lookup_input(db: &impl GetKV, txin: &TxoPointer) -> Option<bool> {
     lookup_item(db, LookupItem::TxMetaSpent, &txin.id)
         .and_then(|v| BitVec::from_bytes(&v).get(txin.index as usize))
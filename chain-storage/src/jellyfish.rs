@@ -116,6 +116,13 @@ pub fn compute_staking_root(stakings: &[StakedState]) -> H256 {
     put_stakings(&mut store, 0, stakings.iter()).expect("jellyfish error with in memory storage")
 }
 
+/// Get root hash of the merkle trie at given version, returns `None` if the root node is not stored
+pub fn get_root_hash<S: GetKV>(storage: &S, version: Version) -> Result<Option<H256>> {
+    KVReader::new(storage)
+        .get_node_option(&NodeKey::new_empty_path(version))
+        .map(|node| node.map(|node| *node.hash().as_ref()))
+}
+
 /// Wrap `SparseMerkleProof` to support SCALE encoding
 #[derive(Debug, Clone)]
 pub struct SparseMerkleProof(jellyfish_merkle::SparseMerkleProof);
@@ -39,13 +39,21 @@ pub const COL_TRIE_NODE: u32 = 9;
 pub const COL_TRIE_STALED: u32 = 10;
 /// Column to store block height -> staking version
 pub const COL_STAKING_VERSIONS: u32 = 11;
+/// Column for diagnostic traces of armed transactions (local only, not a part of the app state): TxId => JSON-serialized traces
+pub const COL_TX_TRACE: u32 = 12;
+/// Column for the (optional, local only) index of the delivered transaction results: TxId => TxResult, height => TxIds of the block
+pub const COL_TX_RESULTS: u32 = 13;
+/// Column for the filters rolled up from the block filters of the ranges of blocks (local only, not a part of the app state): index of the range => encoded FilterRange
+pub const COL_FILTER_RANGES: u32 = 14;
 /// Number of columns in DB
-pub const NUM_COLUMNS: u32 = 12;
+pub const NUM_COLUMNS: u32 = 15;
 
 pub const CHAIN_ID_KEY: &[u8] = b"chain_id";
 pub const GENESIS_APP_HASH_KEY: &[u8] = b"genesis_app_hash";
 pub const LAST_STATE_KEY: &[u8] = b"last_state";
 pub const LAST_FETCHED_BLOCK_KEY: &[u8] = b"last_fetched_block";
+/// the lowest height in the tx result index (in `COL_TX_RESULTS`)
+pub const TX_RESULTS_EARLIEST_KEY: &[u8] = b"earliest_height";
 
 pub enum StorageType {
     Node,
@@ -265,6 +273,18 @@ impl Storage {
             .expect("genesis app hash should be stored");
     }
 
+    /// stores the diagnostic traces of a transaction -- written right away
+    /// (not in the current DB transaction), as they are also recorded in CheckTx
+    pub fn store_tx_trace(&self, txid: &TxId, traces: &[u8]) -> std::io::Result<()> {
+        let mut dbtx = self.db.transaction();
+        dbtx.put(COL_TX_TRACE, txid, traces);
+        self.db.write(dbtx)
+    }
+
+    pub fn get_tx_trace(&self, txid: &TxId) -> Option<Vec<u8>> {
+        self.get(&(COL_TX_TRACE, txid.to_vec()))
+    }
+
     pub fn persist_write(&mut self) -> std::io::Result<()> {
         if let Some(dbtx) = self.current_tx.take() {
             self.db.write(dbtx)
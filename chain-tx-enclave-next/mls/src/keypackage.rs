@@ -29,6 +29,8 @@ use core::cmp::Ordering;
 pub type ProtocolVersion = u8;
 pub type Timespec = u64;
 
+/// Wire format of the messages is pinned by the vectors in `tests/test_vectors/wire/v1`,
+/// changing the encoding requires a new version and a new vector set.
 pub const PROTOCOL_VERSION_MLS10: ProtocolVersion = 1;
 pub const DEFAULT_LIFE_TIME: Timespec = 90 * 24 * 3600; // certificate has 90 days valid duration
 pub const CREDENTIAL_TYPE_X509: u8 = 1;
@@ -410,6 +410,7 @@ impl<CS: CipherSuite> Codec for MLSPlaintextCommitAuthData<CS> {
 }
 
 /// spec: draft-ietf-mls-protocol.md#Welcoming-New-Members
+#[derive(Debug, Clone)]
 pub struct Welcome<CS: CipherSuite> {
     pub version: ProtocolVersion,
     pub cipher_suite: CipherSuiteTag,
@@ -419,6 +420,31 @@ pub struct Welcome<CS: CipherSuite> {
     pub encrypted_group_info: Vec<u8>,
 }
 
+impl<CS: CipherSuite> Codec for Welcome<CS> {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.version.encode(bytes);
+        self.cipher_suite.encode(bytes);
+        encode_vec_u32(bytes, &self.secrets);
+        (self.encrypted_group_info.len() as u32).encode(bytes);
+        bytes.extend_from_slice(&self.encrypted_group_info);
+    }
+
+    fn read(r: &mut Reader) -> Option<Self> {
+        let version = ProtocolVersion::read(r)?;
+        let cipher_suite = CipherSuiteTag::read(r)?;
+        let secrets = read_vec_u32(r)?;
+        let len = u32::read(r)? as usize;
+        let encrypted_group_info = r.take(len)?.to_vec();
+
+        Some(Welcome {
+            version,
+            cipher_suite,
+            secrets,
+            encrypted_group_info,
+        })
+    }
+}
+
 /// spec: draft-ietf-mls-protocol.md#Welcoming-New-Members
 pub struct PathSecret<CS: CipherSuite> {
     /// 1..255
@@ -476,11 +502,29 @@ impl<CS: CipherSuite> Codec for GroupSecret<CS> {
 }
 
 /// spec: draft-ietf-mls-protocol.md#Welcoming-New-Members
+#[derive(Debug, Clone)]
 pub struct EncryptedGroupSecrets<CS: CipherSuite> {
     pub encrypted_group_secrets: HPKECiphertext<CS>,
     pub key_package_hash: HashValue<CS>,
 }
 
+impl<CS: CipherSuite> Codec for EncryptedGroupSecrets<CS> {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.key_package_hash.encode(bytes);
+        self.encrypted_group_secrets.encode(bytes);
+    }
+
+    fn read(r: &mut Reader) -> Option<Self> {
+        let key_package_hash = HashValue::read(r)?;
+        let encrypted_group_secrets = HPKECiphertext::read(r)?;
+
+        Some(EncryptedGroupSecrets {
+            encrypted_group_secrets,
+            key_package_hash,
+        })
+    }
+}
+
 /// spec: draft-ietf-mls-protocol.md#Direct-Paths
 #[derive(Debug, Clone)]
 pub struct HPKECiphertext<CS: CipherSuite> {
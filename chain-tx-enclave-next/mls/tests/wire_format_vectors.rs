@@ -0,0 +1,464 @@
+//! Golden vectors pinning the MLS wire format.
+//!
+//! `test_vectors/wire/v{PROTOCOL_VERSION_MLS10}` holds the vectors for the format implemented by
+//! the current code, they must decode and re-encode byte-exact. They are built by
+//! `generate_vectors` from fixed keys and seeds (`MLS_WIRE_VECTORS_WRITE=1 cargo test` rewrites
+//! them), so that any encoding change shows up as a difference to the checked-in files.
+//! `test_vectors/wire/compat/<release>` holds the sets shipped with the released versions, they
+//! must still be understood by the current code.
+//!
+//! An intentional format change must come with a new vector set and a protocol version bump
+//! (or a new negotiated extension), the released sets are kept in `compat` as they are.
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use elliptic_curve::{point::Generator, FromBytes};
+use mls::ciphersuite::{CipherSuite, DefaultCipherSuite as CS};
+use mls::credential::Credential;
+use mls::extensions::{ExtensionEntry, LifeTimeExt, MLSExtension, ParentHashExt};
+use mls::group::{GroupInfo, GroupInfoPayload};
+use mls::key::{HPKEPrivateKey, HPKEPublicKey, IdentityPublicKey};
+use mls::keypackage::{KeyPackagePayload, DEFAULT_CAPABILITIES_EXT, PROTOCOL_VERSION_MLS10};
+use mls::message::{
+    Add, Commit, ContentType, EncryptedGroupSecrets, GroupContext, HPKECiphertext, MLSPlaintext,
+    MLSPlaintextCommon, MLSPlaintextTBS, Proposal, ProposalId, Remove, Sender, SenderType, Update,
+    UpdatePath, UpdatePathNode, Welcome,
+};
+use mls::tree::{Node, ParentNode, RatchetTreeExt};
+use mls::tree_math::LeafSize;
+use mls::{Codec, KeyPackage};
+use p256::{AffinePoint, ElementBytes, ProjectivePoint, Scalar};
+use sha2::{Digest, Sha256};
+
+const TDBE_GROUP_ID: &[u8] =
+    b"Thaler Experimental Network Council Node Transaction Data Bootstrap Enclave";
+
+fn vectors_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_vectors/wire")
+}
+
+fn current_vector(name: &str) -> Vec<u8> {
+    let path = vectors_dir()
+        .join(format!("v{}", PROTOCOL_VERSION_MLS10))
+        .join(format!("{}.bin", name));
+    fs::read(&path).unwrap_or_else(|e| panic!("read {}: {}", path.display(), e))
+}
+
+/// decode the vector and check that encoding it again gives back the same bytes
+fn roundtrip<T: Codec>(name: &str, vector: &[u8]) -> T {
+    let decoded = T::read_bytes(vector).unwrap_or_else(|| panic!("decode {}", name));
+    assert_eq!(
+        decoded.get_encoding(),
+        vector,
+        "{} is not re-encoded byte-exact",
+        name
+    );
+    decoded
+}
+
+fn check_keypackage(name: &str, vector: &[u8], version_ok: impl Fn(u8) -> bool) {
+    let kp = roundtrip::<KeyPackage<CS>>(name, vector);
+    assert!(version_ok(kp.payload.version));
+}
+
+fn check_proposal(name: &str, vector: &[u8]) -> MLSPlaintext<CS> {
+    let plain = roundtrip::<MLSPlaintext<CS>>(name, vector);
+    let matches_name = match &plain.content.content {
+        ContentType::Proposal(Proposal::Add(_)) => name == "add_proposal",
+        ContentType::Proposal(Proposal::Update(_)) => name == "update_proposal",
+        ContentType::Proposal(Proposal::Remove(_)) => name == "remove_proposal",
+        _ => false,
+    };
+    assert!(matches_name, "unexpected content in {}", name);
+    plain
+}
+
+fn check_commit(name: &str, vector: &[u8]) {
+    let plain = roundtrip::<MLSPlaintext<CS>>(name, vector);
+    let commit = plain.get_commit().expect("commit content");
+    assert!(!commit.proposals.is_empty());
+    assert!(commit.path.is_some());
+}
+
+fn check_welcome(name: &str, vector: &[u8], version_ok: impl Fn(u8) -> bool) {
+    let welcome = roundtrip::<Welcome<CS>>(name, vector);
+    assert!(version_ok(welcome.version));
+    assert!(!welcome.secrets.is_empty());
+}
+
+fn check_vector(name: &str, vector: &[u8], version_ok: impl Fn(u8) -> bool) {
+    match name {
+        "keypackage" => check_keypackage(name, vector, version_ok),
+        "add_proposal" | "update_proposal" | "remove_proposal" => {
+            check_proposal(name, vector);
+        }
+        "commit" => check_commit(name, vector),
+        "welcome" => check_welcome(name, vector, version_ok),
+        "group_info" => {
+            roundtrip::<GroupInfo<CS>>(name, vector);
+        }
+        _ => panic!("unknown vector: {}", name),
+    }
+}
+
+#[test]
+fn verify_current_wire_format_vectors() {
+    for name in &[
+        "keypackage",
+        "add_proposal",
+        "update_proposal",
+        "remove_proposal",
+        "commit",
+        "welcome",
+        "group_info",
+    ] {
+        check_vector(name, &current_vector(name), |v| v == PROTOCOL_VERSION_MLS10);
+    }
+}
+
+#[test]
+fn encode_remove_proposal_content() {
+    let vector = current_vector("remove_proposal");
+    let content = MLSPlaintextCommon::<CS> {
+        group_id: TDBE_GROUP_ID.to_vec(),
+        epoch: 1,
+        sender: Sender {
+            sender_type: SenderType::Member,
+            sender: LeafSize(0),
+        },
+        authenticated_data: vec![],
+        content: ContentType::Proposal(Proposal::Remove(Remove {
+            removed: LeafSize(1),
+        })),
+    };
+    let encoded = content.get_encoding();
+    // the signature follows the content
+    assert_eq!(&vector[..encoded.len()], &encoded[..]);
+
+    let plain = check_proposal("remove_proposal", &vector);
+    assert_eq!(plain.get_remove().expect("remove").removed, LeafSize(1));
+    assert_eq!(plain.content.get_encoding(), encoded);
+}
+
+#[test]
+fn encode_commit_proposal_ids() {
+    let update = current_vector("update_proposal");
+    let add = current_vector("add_proposal");
+    let plain = roundtrip::<MLSPlaintext<CS>>("commit", &current_vector("commit"));
+    let commit = plain.get_commit().expect("commit content");
+    // proposal ids are the hashes of the proposal messages
+    let expected = [&update, &add]
+        .iter()
+        .map(|p| CS::hash(&p[..]))
+        .collect::<Vec<_>>();
+    let ids = commit
+        .proposals
+        .iter()
+        .map(|id| id.0.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, expected);
+}
+
+/// vectors from the released versions must still be understood by the current code
+#[test]
+fn verify_compat_wire_format_vectors() {
+    let compat = vectors_dir().join("compat");
+    for release in fs::read_dir(&compat).expect("compat vectors directory") {
+        let release = release.expect("compat release").path();
+        if !release.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(&release).expect("compat release directory") {
+            let path = entry.expect("compat vector").path();
+            if path.extension().map_or(true, |ext| ext != "bin") {
+                continue;
+            }
+            let name = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .expect("vector name");
+            let vector = fs::read(&path).expect("read compat vector");
+            check_vector(name, &vector, |v| {
+                DEFAULT_CAPABILITIES_EXT.versions.contains(&v)
+            });
+        }
+    }
+}
+
+/// AES-128-GCM tag appended to the HPKE / welcome ciphertexts
+const AEAD_TAG_LEN: usize = 16;
+/// encoded `PathSecret`
+const PATH_SECRET_LEN: usize = 1 + 32;
+/// encoded `GroupSecret` with a path secret
+const GROUP_SECRETS_LEN: usize = 1 + 32 + 1 + PATH_SECRET_LEN;
+
+/// deterministic bytes for the fixed keys, nonces and ciphertexts labelled by `label`
+fn seeded(label: &str, len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(len);
+    let mut counter = 0u32;
+    while out.len() < len {
+        let mut hasher = Sha256::new();
+        hasher.update(b"mls wire vectors/");
+        hasher.update(label.as_bytes());
+        hasher.update(&counter.to_be_bytes());
+        out.extend_from_slice(&hasher.finalize());
+        counter += 1;
+    }
+    out.truncate(len);
+    out
+}
+
+fn secret_scalar(label: &str) -> Scalar {
+    Scalar::from_bytes(&ElementBytes::clone_from_slice(&seeded(label, 32))).unwrap()
+}
+
+/// uncompressed P-256 point of `secret`
+fn public_point(secret: &Scalar) -> Vec<u8> {
+    (ProjectivePoint::from(AffinePoint::generator()) * secret)
+        .to_affine()
+        .unwrap()
+        .to_pubkey(false)
+        .as_bytes()
+        .to_vec()
+}
+
+fn hpke_public_key(label: &str) -> HPKEPublicKey<CS> {
+    HPKEPrivateKey::<CS>::unmarshal(&seeded(label, 32))
+        .expect("seeded HPKE key")
+        .public_key()
+}
+
+fn der_signature(r: &[u8], s: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for int in &[r, s] {
+        let start = int.iter().position(|b| *b != 0).unwrap_or(int.len() - 1);
+        let int = &int[start..];
+        body.push(0x02);
+        if int[0] & 0x80 != 0 {
+            body.push(int.len() as u8 + 1);
+            body.push(0);
+        } else {
+            body.push(int.len() as u8);
+        }
+        body.extend_from_slice(int);
+    }
+    let mut signature = vec![0x30, body.len() as u8];
+    signature.append(&mut body);
+    signature
+}
+
+/// ECDSA P-256 SHA-256 signature by the fixed identity key `identity`
+/// (ring only signs with random nonces, so the nonce is derived from the key and the message)
+fn sign(identity: &str, msg: &[u8]) -> Vec<u8> {
+    let secret = secret_scalar(identity);
+    let digest = Sha256::digest(msg);
+    let z = Scalar::from_bytes(&digest).unwrap();
+    let mut hasher = Sha256::new();
+    hasher.update(b"ecdsa nonce");
+    hasher.update(ElementBytes::from(secret));
+    hasher.update(&digest);
+    let k = Scalar::from_bytes(&hasher.finalize()).unwrap();
+    let r = Scalar::from_bytes(ElementBytes::from_slice(&public_point(&k)[1..33])).unwrap();
+    let s = k.invert().unwrap() * &(z + &(r * &secret));
+    let signature = der_signature(&ElementBytes::from(r), &ElementBytes::from(s));
+    IdentityPublicKey::new_unsafe(public_point(&secret))
+        .verify_signature(msg, &signature)
+        .expect("valid signature");
+    signature
+}
+
+fn keypackage(identity: &str, init: &str, extra: Vec<ExtensionEntry>) -> KeyPackage<CS> {
+    let mut extensions = vec![
+        DEFAULT_CAPABILITIES_EXT.entry(),
+        LifeTimeExt::new(0, 100).entry(),
+    ];
+    extensions.extend(extra);
+    let payload = KeyPackagePayload {
+        version: PROTOCOL_VERSION_MLS10,
+        cipher_suite: CS::tag(),
+        init_key: hpke_public_key(init),
+        credential: Credential::X509(public_point(&secret_scalar(identity))),
+        extensions,
+    };
+    let signature = sign(identity, &payload.get_encoding());
+    KeyPackage { payload, signature }
+}
+
+/// group context of the epoch the proposals and the commit are sent in
+fn group_context() -> GroupContext<CS> {
+    GroupContext {
+        group_id: TDBE_GROUP_ID.to_vec(),
+        epoch: 1,
+        tree_hash: CS::hash(b"tree hash"),
+        confirmed_transcript_hash: CS::hash(b"confirmed transcript hash"),
+        extensions: vec![],
+    }
+}
+
+fn plaintext(identity: &str, sender: u32, content: ContentType<CS>) -> MLSPlaintext<CS> {
+    let content = MLSPlaintextCommon {
+        group_id: TDBE_GROUP_ID.to_vec(),
+        epoch: 1,
+        sender: Sender {
+            sender_type: SenderType::Member,
+            sender: LeafSize(sender),
+        },
+        authenticated_data: vec![],
+        content,
+    };
+    let to_be_signed = MLSPlaintextTBS {
+        context: group_context(),
+        content: content.clone(),
+    }
+    .get_encoding();
+    MLSPlaintext {
+        content,
+        signature: sign(identity, &to_be_signed),
+    }
+}
+
+/// HPKE ciphertext with a fixed ephemeral key; the content is opaque in the wire format,
+/// so it's filled with seeded bytes of the length of the real ciphertext
+fn ciphertext(label: &str, len: usize) -> HPKECiphertext<CS> {
+    HPKECiphertext {
+        kem_output: hpke_public_key(&format!("{} kem output", label)).marshal(),
+        ciphertext: seeded(&format!("{} ciphertext", label), len),
+    }
+}
+
+fn path_node(label: &str) -> UpdatePathNode<CS> {
+    UpdatePathNode {
+        public_key: hpke_public_key(label),
+        encrypted_path_secret: vec![ciphertext(
+            &format!("{} path secret", label),
+            PATH_SECRET_LEN + AEAD_TAG_LEN,
+        )],
+    }
+}
+
+/// builds the current vector set: the creator (leaf 0) adds the joiner to a group with
+/// the updater (leaf 1), and commits the add and update proposals with a new direct path
+fn generate_vectors() -> Vec<(&'static str, Vec<u8>)> {
+    let joiner = keypackage("joiner identity", "joiner init", vec![]);
+    let updater = keypackage("updater identity", "updater init", vec![]);
+    let add = plaintext(
+        "creator identity",
+        0,
+        ContentType::Proposal(Proposal::Add(Add {
+            key_package: joiner.clone(),
+        })),
+    );
+    let update = plaintext(
+        "updater identity",
+        1,
+        ContentType::Proposal(Proposal::Update(Update {
+            key_package: updater.clone(),
+        })),
+    );
+    let remove = plaintext(
+        "creator identity",
+        0,
+        ContentType::Proposal(Proposal::Remove(Remove {
+            removed: LeafSize(1),
+        })),
+    );
+
+    // direct path of leaf 0 in the three member tree: node 1 and the root (node 3)
+    let root = ParentNode {
+        public_key: hpke_public_key("root node"),
+        unmerged_leaves: vec![],
+        parent_hash: None,
+    };
+    let parent = ParentNode {
+        public_key: hpke_public_key("parent node"),
+        unmerged_leaves: vec![],
+        parent_hash: Some(CS::hash(&root.get_encoding())),
+    };
+    let leaf = keypackage(
+        "creator identity",
+        "creator path init",
+        vec![ParentHashExt::<CS>(Some(CS::hash(&parent.get_encoding()))).entry()],
+    );
+    let commit = Commit {
+        proposals: vec![
+            ProposalId(CS::hash(&update.get_encoding())),
+            ProposalId(CS::hash(&add.get_encoding())),
+        ],
+        path: Some(UpdatePath {
+            leaf_key_package: leaf.clone(),
+            nodes: vec![path_node("parent node"), path_node("root node")],
+        }),
+    };
+    let confirmation_tag = CS::hash(b"confirmation tag");
+    let commit = plaintext(
+        "creator identity",
+        0,
+        ContentType::Commit {
+            commit,
+            confirmation_tag: confirmation_tag.clone(),
+        },
+    );
+
+    let tree = RatchetTreeExt::<CS>::new(vec![
+        Some(Node::Leaf(leaf)),
+        Some(Node::Parent(parent)),
+        Some(Node::Leaf(updater)),
+        Some(Node::Parent(root)),
+        Some(Node::Leaf(joiner.clone())),
+    ]);
+    let payload = GroupInfoPayload {
+        group_id: TDBE_GROUP_ID.to_vec(),
+        epoch: 2,
+        tree_hash: CS::hash(b"tree hash 2"),
+        confirmed_transcript_hash: CS::hash(b"confirmed transcript hash 2"),
+        interim_transcript_hash: CS::hash(b"interim transcript hash 2"),
+        extensions: vec![tree.entry()],
+        confirmation_tag,
+        signer_index: LeafSize(0),
+    };
+    let signature = sign("creator identity", &payload.get_encoding());
+    let group_info = GroupInfo { payload, signature }.get_encoding();
+
+    let welcome = Welcome::<CS> {
+        version: PROTOCOL_VERSION_MLS10,
+        cipher_suite: CS::tag(),
+        secrets: vec![EncryptedGroupSecrets {
+            encrypted_group_secrets: ciphertext(
+                "welcome group secrets",
+                GROUP_SECRETS_LEN + AEAD_TAG_LEN,
+            ),
+            key_package_hash: CS::hash(&joiner.get_encoding()),
+        }],
+        encrypted_group_info: seeded("welcome group info", group_info.len() + AEAD_TAG_LEN),
+    };
+
+    vec![
+        ("keypackage", joiner.get_encoding()),
+        ("add_proposal", add.get_encoding()),
+        ("update_proposal", update.get_encoding()),
+        ("remove_proposal", remove.get_encoding()),
+        ("commit", commit.get_encoding()),
+        ("welcome", welcome.get_encoding()),
+        ("group_info", group_info),
+    ]
+}
+
+#[test]
+fn generated_vectors_should_match_current_vectors() {
+    let write = env::var_os("MLS_WIRE_VECTORS_WRITE").is_some();
+    for (name, generated) in generate_vectors() {
+        if write {
+            let path = vectors_dir()
+                .join(format!("v{}", PROTOCOL_VERSION_MLS10))
+                .join(format!("{}.bin", name));
+            fs::write(&path, &generated).expect("write vector");
+        }
+        assert_eq!(
+            generated,
+            current_vector(name),
+            "{} differs from the generated vector",
+            name
+        );
+    }
+}
@@ -8,10 +8,11 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use parity_scale_codec::{Decode, Encode};
+use parity_scale_codec::Encode;
 use rustls::{NoClientAuth, ServerConfig, ServerSession, StreamOwned};
 use thread_pool::ThreadPool;
 
+use chain_core::common::decode_all_strict;
 use enclave_protocol::{
     DecryptionRequest, TxQueryInitRequest, TxQueryInitResponse, ENCRYPTION_REQUEST_SIZE,
 };
@@ -90,7 +91,7 @@ fn handle_connection<T: Read + Write>(mut stream: T, chain_data_stream: Arc<Mute
 
     match stream.read(&mut bytes) {
         Ok(len) => {
-            match TxQueryInitRequest::decode(&mut &bytes.as_slice()[0..len]) {
+            match decode_all_strict::<TxQueryInitRequest>(&bytes[0..len]) {
                 Ok(TxQueryInitRequest::Encrypt(request)) => {
                     let response = handle_encryption_request(request, len, chain_data_stream);
 
@@ -120,36 +121,34 @@ fn handle_connection<T: Read + Write>(mut stream: T, chain_data_stream: Arc<Mute
                     }
 
                     match stream.read(&mut bytes) {
-                        Ok(len) => {
-                            match DecryptionRequest::decode(&mut &bytes.as_slice()[0..len]) {
-                                Ok(decryption_request) => {
-                                    if !verify_decryption_request(&decryption_request, challenge) {
-                                        log::error!("Decryption request is invalid");
-                                        return;
-                                    }
+                        Ok(len) => match decode_all_strict::<DecryptionRequest>(&bytes[0..len]) {
+                            Ok(decryption_request) => {
+                                if !verify_decryption_request(&decryption_request, challenge) {
+                                    log::error!("Decryption request is invalid");
+                                    return;
+                                }
 
-                                    match handle_decryption_request(
-                                        &decryption_request,
-                                        chain_data_stream,
-                                    ) {
-                                        Ok(decryption_response) => {
-                                            if let Err(err) =
-                                                stream.write_all(&decryption_response.encode())
-                                            {
-                                                log::error!("Error while writing decryption response back to TLS stream: {}", err);
-                                            }
+                                match handle_decryption_request(
+                                    &decryption_request,
+                                    chain_data_stream,
+                                ) {
+                                    Ok(decryption_response) => {
+                                        if let Err(err) =
+                                            stream.write_all(&decryption_response.encode())
+                                        {
+                                            log::error!("Error while writing decryption response back to TLS stream: {}", err);
                                         }
-                                        Err(err) => log::error!(
-                                            "Error while handling decryption request: {}",
-                                            err
-                                        ),
                                     }
-                                }
-                                Err(err) => {
-                                    log::error!("Unable to decode decryption request: {}", err)
+                                    Err(err) => log::error!(
+                                        "Error while handling decryption request: {}",
+                                        err
+                                    ),
                                 }
                             }
-                        }
+                            Err(err) => {
+                                log::error!("Unable to decode decryption request: {}", err)
+                            }
+                        },
                         Err(err) => {
                             log::error!(
                                 "Unable to read challenge response from TLS stream: {}",
@@ -7,13 +7,14 @@ use rs_libc::alloc::*;
 
 use aead::{generic_array::GenericArray, NewAead};
 use aes_gcm_siv::Aes128GcmSiv;
+use chain_core::common::decode_all_strict;
 use chain_core::tx::TX_AUX_SIZE;
 use chain_tx_filter::BlockFilter;
 use chain_tx_validation::Error;
 use enclave_macro::get_network_id;
 use enclave_protocol::{IntraEnclaveRequest, IntraEnclaveResponse, IntraEnclaveResponseOk};
 use enclave_utils::tls::{create_ra_context, create_tls_client_stream};
-use parity_scale_codec::{Decode, Encode};
+use parity_scale_codec::Encode;
 use ra_client::{EnclaveCertVerifier, EnclaveCertVerifierConfig, EnclaveInfo};
 use sgx_isa::Report;
 use std::io::{Read, Write};
@@ -76,8 +77,7 @@ fn handling_loop<I: Read + Write, J: Read + Write>(
         }
         log::trace!("waiting for chain-abci request");
         match chain_abci.read(&mut request_buf) {
-            Ok(n) if n > 0 => match IntraEnclaveRequest::decode(&mut &request_buf.as_slice()[0..n])
-            {
+            Ok(n) if n > 0 => match decode_all_strict::<IntraEnclaveRequest>(&request_buf[0..n]) {
                 Ok(IntraEnclaveRequest::InitChainCheck(network_id)) => {
                     let response: IntraEnclaveResponse = if network_id == NETWORK_HEX_ID {
                         Ok(IntraEnclaveResponseOk::InitChainCheck)
@@ -106,13 +106,18 @@ fn handling_loop<I: Read + Write, J: Read + Write>(
                     log::debug!("end block request");
 
                     let maybe_filter = if filter.is_modified() {
-                        Some(Box::new(filter.get_raw()))
+                        filter.get_raw().map(|raw| Some(Box::new(raw)))
                     } else {
-                        None
+                        Ok(None)
                     };
                     filter.reset();
-                    let response: IntraEnclaveResponse =
-                        Ok(IntraEnclaveResponseOk::EndBlock(maybe_filter));
+                    let response: IntraEnclaveResponse = match maybe_filter {
+                        Ok(maybe_filter) => Ok(IntraEnclaveResponseOk::EndBlock(maybe_filter)),
+                        Err(e) => {
+                            log::error!("failed to get the block filter: {}", e);
+                            Err(Error::EnclaveRejected)
+                        }
+                    };
                     write_response(response, &mut chain_abci);
                     if let Some((_, ref s)) = process_signal {
                         let _ = s.send(());
@@ -2,6 +2,7 @@
 use crate::sgx_module::write_response;
 use aead::{generic_array::GenericArray, Aead};
 use aes_gcm_siv::Aes128GcmSiv;
+use chain_core::common::decode_tx_in_block;
 use chain_core::state::tendermint::BlockHeight;
 use chain_core::tx::data::TxId;
 use chain_core::tx::TransactionId;
@@ -35,7 +36,7 @@ pub(crate) fn encrypt(alg: &Aes128GcmSiv, tx: TxToObfuscate) -> TxObfuscated {
 pub(crate) fn decrypt(alg: &Aes128GcmSiv, tx: &TxObfuscated) -> Result<PlainTxAux, ()> {
     let nonce = GenericArray::from_slice(&tx.init_vector);
     let plaintext = alg.decrypt(nonce, tx).map_err(|_| ())?;
-    let result = PlainTxAux::decode(&mut plaintext.as_slice());
+    let result = decode_tx_in_block::<PlainTxAux>(&plaintext);
     result.map_err(|_| ())
 }
 
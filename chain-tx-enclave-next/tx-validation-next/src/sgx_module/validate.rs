@@ -24,14 +24,10 @@ use std::prelude::v1::{Box, Vec};
 fn add_view_keys(wraptx: &TxWithOutputs, filter: &mut BlockFilter) {
     match wraptx {
         TxWithOutputs::Transfer(tx) => {
-            for view in tx.attributes.allowed_view.iter() {
-                filter.add_view_key(&view.view_key);
-            }
+            filter.extend_view_keys(tx.attributes.allowed_view.iter().map(|view| &view.view_key))
         }
         TxWithOutputs::StakeWithdraw(tx) => {
-            for view in tx.attributes.allowed_view.iter() {
-                filter.add_view_key(&view.view_key);
-            }
+            filter.extend_view_keys(tx.attributes.allowed_view.iter().map(|view| &view.view_key))
         }
     }
 }
@@ -1,5 +1,6 @@
-use parity_scale_codec::{Decode, Encode};
+use parity_scale_codec::Encode;
 
+use chain_core::common::{decode_all_strict, decode_tx_in_block};
 use chain_core::state::tendermint::BlockHeight;
 use chain_core::tx::{data::TxId, PlainTxAux, TxObfuscated, TxWithOutputs};
 use chain_tx_validation::Error;
@@ -9,7 +10,7 @@ const SEAL_KEY: u8 = 0xf0;
 pub fn decrypt(payload: &TxObfuscated) -> Result<PlainTxAux, Error> {
     let unpad = unpad_payload(&payload.txpayload)?;
     let bs = unpad.iter().map(|b| b ^ ENCRYPTION_KEY).collect::<Vec<_>>();
-    PlainTxAux::decode(&mut bs.as_slice()).map_err(|_| Error::EnclaveRejected)
+    decode_tx_in_block::<PlainTxAux>(&bs).map_err(|_| Error::EnclaveRejected)
 }
 
 fn unpad_payload(payload: &[u8]) -> Result<&[u8], Error> {
@@ -28,7 +29,7 @@ pub fn seal(tx: &TxWithOutputs) -> Vec<u8> {
 }
 pub fn unseal(payload: &[u8]) -> Result<TxWithOutputs, Error> {
     let bytes = payload.iter().map(|b| b ^ SEAL_KEY).collect::<Vec<_>>();
-    TxWithOutputs::decode(&mut bytes.as_slice()).map_err(|_| Error::EnclaveRejected)
+    decode_all_strict::<TxWithOutputs>(&bytes).map_err(|_| Error::EnclaveRejected)
 }
 fn pad_payload(payload: &[u8]) -> Vec<u8> {
     let mut result = Vec::with_capacity(payload.len() + 16);
@@ -0,0 +1,7 @@
+extern crate cbindgen;
+
+fn main() {
+    cbindgen::generate(".")
+        .expect("Unable to generate bindings")
+        .write_to_file("./chain-tx-filter.h");
+}
@@ -0,0 +1,63 @@
+#ifndef CHAIN_TX_FILTER_H
+#define CHAIN_TX_FILTER_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The view key may be in the filter
+ */
+#define FILTER_MAYBE_PRESENT 1
+
+/**
+ * The view key isn't in the filter
+ */
+#define FILTER_NOT_PRESENT 0
+
+/**
+ * A null pointer was passed
+ */
+#define FILTER_ERROR_NULL_POINTER -1
+
+/**
+ * The bytes aren't a valid block filter (e.g. of a wrong length or of an unknown version)
+ */
+#define FILTER_ERROR_INVALID_FILTER -2
+
+/**
+ * The bytes aren't a valid compressed public key
+ */
+#define FILTER_ERROR_INVALID_VIEW_KEY -3
+
+/**
+ * Unexpected failure of the library
+ */
+#define FILTER_ERROR_INTERNAL -4
+
+/**
+ * Tests if the view key may be in the block filter
+ *
+ * Returns `FILTER_MAYBE_PRESENT` or `FILTER_NOT_PRESENT`, or a negative error code
+ *
+ * # Safety
+ * `filter_ptr` should point to `filter_len` readable bytes and `pubkey33_ptr` to 33 readable
+ * bytes (or be null)
+ */
+int32_t filter_check_view_key(const uint8_t *filter_ptr,
+                              uintptr_t filter_len,
+                              const uint8_t *pubkey33_ptr);
+
+/**
+ * Fraction (from 0 to 1) of the set bits of the block filter, e.g. to tell how reliable its
+ * `FILTER_MAYBE_PRESENT` answers are
+ *
+ * Returns a negative error code (as a double) if the filter is invalid
+ *
+ * # Safety
+ * `filter_ptr` should point to `filter_len` readable bytes (or be null)
+ */
+double filter_fill_ratio(const uint8_t *filter_ptr, uintptr_t filter_len);
+
+#endif /* CHAIN_TX_FILTER_H */
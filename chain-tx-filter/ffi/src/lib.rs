@@ -0,0 +1,100 @@
+//! C ABI of the block filter checks, so that the wallets which aren't written in Rust can tell
+//! whether a block may concern them from the payload of its `ethbloom` event, without going
+//! through a Rust client.
+//!
+//! The filter is passed as the bytes of the event payload (the raw 256 bytes of a default filter,
+//! or a sized / versioned one) and the view key as its 33-byte compressed serialization. No memory
+//! allocated by the library is handed to the caller, and the invalid inputs are reported with the
+//! negative error codes below (nothing panics across the boundary).
+use std::convert::TryFrom;
+use std::panic::{catch_unwind, UnwindSafe};
+use std::slice;
+
+use chain_tx_filter::{BlockFilter, MAX_BLOOM_SIZE};
+use secp256k1::key::PublicKey;
+
+/// The view key may be in the filter
+pub const FILTER_MAYBE_PRESENT: i32 = 1;
+/// The view key isn't in the filter
+pub const FILTER_NOT_PRESENT: i32 = 0;
+/// A null pointer was passed
+pub const FILTER_ERROR_NULL_POINTER: i32 = -1;
+/// The bytes aren't a valid block filter (e.g. of a wrong length or of an unknown version)
+pub const FILTER_ERROR_INVALID_FILTER: i32 = -2;
+/// The bytes aren't a valid compressed public key
+pub const FILTER_ERROR_INVALID_VIEW_KEY: i32 = -3;
+/// Unexpected failure of the library
+pub const FILTER_ERROR_INTERNAL: i32 = -4;
+
+/// Length of a compressed view key
+const VIEW_KEY_LEN: usize = 33;
+/// Longest valid payload: the largest filter with its header and version prefix
+const MAX_FILTER_LEN: usize = MAX_BLOOM_SIZE + 3;
+
+/// runs `f`, with `internal_error` returned if it panics
+fn guarded<T>(internal_error: T, f: impl FnOnce() -> Result<T, T> + UnwindSafe) -> T {
+    match catch_unwind(f) {
+        Ok(Ok(value)) | Ok(Err(value)) => value,
+        Err(_) => internal_error,
+    }
+}
+
+unsafe fn read_filter(filter_ptr: *const u8, filter_len: usize) -> Result<BlockFilter, i32> {
+    if filter_ptr.is_null() {
+        return Err(FILTER_ERROR_NULL_POINTER);
+    }
+    // also keeps the slice within `isize::MAX`
+    if filter_len == 0 || filter_len > MAX_FILTER_LEN {
+        return Err(FILTER_ERROR_INVALID_FILTER);
+    }
+    let payload = slice::from_raw_parts(filter_ptr, filter_len);
+    BlockFilter::try_from(payload).map_err(|_| FILTER_ERROR_INVALID_FILTER)
+}
+
+unsafe fn read_view_key(pubkey33_ptr: *const u8) -> Result<PublicKey, i32> {
+    if pubkey33_ptr.is_null() {
+        return Err(FILTER_ERROR_NULL_POINTER);
+    }
+    let serialized = slice::from_raw_parts(pubkey33_ptr, VIEW_KEY_LEN);
+    PublicKey::from_slice(serialized).map_err(|_| FILTER_ERROR_INVALID_VIEW_KEY)
+}
+
+/// Tests if the view key may be in the block filter
+///
+/// Returns `FILTER_MAYBE_PRESENT` or `FILTER_NOT_PRESENT`, or a negative error code
+///
+/// # Safety
+/// `filter_ptr` should point to `filter_len` readable bytes and `pubkey33_ptr` to 33 readable
+/// bytes (or be null)
+#[no_mangle]
+pub unsafe extern "C" fn filter_check_view_key(
+    filter_ptr: *const u8,
+    filter_len: usize,
+    pubkey33_ptr: *const u8,
+) -> i32 {
+    guarded(FILTER_ERROR_INTERNAL, || {
+        let filter = read_filter(filter_ptr, filter_len)?;
+        let view_key = read_view_key(pubkey33_ptr)?;
+        if filter.check_view_key(&view_key) {
+            Ok(FILTER_MAYBE_PRESENT)
+        } else {
+            Ok(FILTER_NOT_PRESENT)
+        }
+    })
+}
+
+/// Fraction (from 0 to 1) of the set bits of the block filter, e.g. to tell how reliable its
+/// `FILTER_MAYBE_PRESENT` answers are
+///
+/// Returns a negative error code (as a double) if the filter is invalid
+///
+/// # Safety
+/// `filter_ptr` should point to `filter_len` readable bytes (or be null)
+#[no_mangle]
+pub unsafe extern "C" fn filter_fill_ratio(filter_ptr: *const u8, filter_len: usize) -> f64 {
+    guarded(f64::from(FILTER_ERROR_INTERNAL), || {
+        read_filter(filter_ptr, filter_len)
+            .map(|filter| filter.saturation())
+            .map_err(f64::from)
+    })
+}
@@ -0,0 +1,122 @@
+//! Calls the exported functions through the dynamic library, as a foreign caller would
+use std::env;
+use std::ptr;
+
+use chain_tx_filter::BlockFilter;
+use chain_tx_filter_ffi::*;
+use libloading::{Library, Symbol};
+use secp256k1::key::{PublicKey, SecretKey};
+
+type CheckViewKey = unsafe extern "C" fn(*const u8, usize, *const u8) -> i32;
+type FillRatio = unsafe extern "C" fn(*const u8, usize) -> f64;
+
+fn library() -> Library {
+    // the cdylib is built next to the test executable (`target/<profile>/deps`)
+    let deps = env::current_exe().unwrap().parent().unwrap().to_owned();
+    let name = libloading::library_filename("chain_tx_filter_ffi");
+    let path = [deps.join(&name), deps.parent().unwrap().join(&name)]
+        .iter()
+        .find(|path| path.exists())
+        .cloned()
+        .expect("chain-tx-filter-ffi dynamic library");
+    unsafe { Library::new(path).unwrap() }
+}
+
+fn view_key(seed: u8) -> [u8; 33] {
+    let secret = SecretKey::from_slice(&[seed; 32]).unwrap();
+    PublicKey::from_secret_key(secp256k1::SECP256K1, &secret).serialize()
+}
+
+fn filter_payloads() -> (BlockFilter, Vec<Vec<u8>>) {
+    let mut filter = BlockFilter::default();
+    for seed in 1..=2 {
+        let view_key = PublicKey::from_slice(&view_key(seed)).unwrap();
+        filter.add_view_key(&view_key);
+    }
+    let payloads = vec![
+        filter.get_tendermint_kv().1,
+        filter.get_tendermint_kv_versioned().1,
+    ];
+    (filter, payloads)
+}
+
+#[test]
+fn view_keys_should_be_checked_through_c_abi() {
+    let library = library();
+    let check: Symbol<CheckViewKey> = unsafe { library.get(b"filter_check_view_key").unwrap() };
+    let (_, payloads) = filter_payloads();
+
+    for payload in payloads.iter() {
+        let result =
+            |seed| unsafe { check(payload.as_ptr(), payload.len(), view_key(seed).as_ptr()) };
+        assert_eq!(FILTER_MAYBE_PRESENT, result(1));
+        assert_eq!(FILTER_MAYBE_PRESENT, result(2));
+        assert_eq!(FILTER_NOT_PRESENT, result(3));
+    }
+}
+
+#[test]
+fn invalid_inputs_should_return_error_codes() {
+    let library = library();
+    let check: Symbol<CheckViewKey> = unsafe { library.get(b"filter_check_view_key").unwrap() };
+    let (_, payloads) = filter_payloads();
+    let payload = &payloads[0];
+    let key = view_key(1);
+
+    let mut unknown_version = payloads[1].clone();
+    unknown_version[0] = 0x82;
+    unsafe {
+        assert_eq!(
+            FILTER_ERROR_NULL_POINTER,
+            check(ptr::null(), payload.len(), key.as_ptr())
+        );
+        assert_eq!(
+            FILTER_ERROR_NULL_POINTER,
+            check(payload.as_ptr(), payload.len(), ptr::null())
+        );
+        for len in [0, 1, payload.len() - 1, usize::MAX].iter() {
+            assert_eq!(
+                FILTER_ERROR_INVALID_FILTER,
+                check(payload.as_ptr(), *len, key.as_ptr())
+            );
+        }
+        assert_eq!(
+            FILTER_ERROR_INVALID_FILTER,
+            check(
+                unknown_version.as_ptr(),
+                unknown_version.len(),
+                key.as_ptr()
+            )
+        );
+        assert_eq!(
+            FILTER_ERROR_INVALID_VIEW_KEY,
+            check(payload.as_ptr(), payload.len(), [0u8; 33].as_ptr())
+        );
+    }
+}
+
+#[test]
+fn fill_ratio_should_match_filter_saturation() {
+    let library = library();
+    let fill_ratio: Symbol<FillRatio> = unsafe { library.get(b"filter_fill_ratio").unwrap() };
+    let (filter, payloads) = filter_payloads();
+
+    for payload in payloads.iter() {
+        let ratio = unsafe { fill_ratio(payload.as_ptr(), payload.len()) };
+        assert!(ratio > 0.0);
+        assert_eq!(filter.saturation(), ratio);
+    }
+    let empty = BlockFilter::default().get_tendermint_kv().1;
+    assert_eq!(0.0, unsafe { fill_ratio(empty.as_ptr(), empty.len()) });
+
+    unsafe {
+        assert_eq!(
+            f64::from(FILTER_ERROR_NULL_POINTER),
+            fill_ratio(ptr::null(), 256)
+        );
+        assert_eq!(
+            f64::from(FILTER_ERROR_INVALID_FILTER),
+            fill_ratio(empty.as_ptr(), 100)
+        );
+    }
+}
@@ -0,0 +1,1054 @@
+use crate::filter::Bloom;
+use crate::filter::H2048;
+use crate::format::{strip_version, BLOCK_FILTER_VERSION, VERSION_FLAG};
+use crate::gcs::GolombSet;
+use chain_core::common::TendermintEventKey;
+use chain_core::init::address::RedeemAddress;
+use chain_core::state::account::StakedStateAddress;
+use chain_core::tx::data::TxId;
+use secp256k1::key::PublicKey;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+use std::prelude::v1::{String, Vec};
+use std::str::FromStr;
+
+pub use bit_vec::BitVec;
+
+/// Block-level filter of the view keys (and IDs) of transactions
+///
+/// Transaction IDs are added as their raw 32 bytes, so they can't be mistaken for
+/// (33-byte serialized) view keys.
+pub trait TxFilter {
+    /// resets the filter
+    fn reset(&mut self);
+
+    /// adds a view key to the filter
+    fn add_view_key(&mut self, view_key: &PublicKey);
+
+    /// tests if a view key is in the filter
+    /// true = maybe present
+    /// false = not present
+    fn check_view_key(&self, view_key: &PublicKey) -> bool;
+
+    /// adds a transaction ID to the filter
+    fn add_tx_id(&mut self, tx_id: &TxId);
+
+    /// tests if a transaction ID is in the filter
+    /// true = maybe present
+    /// false = not present
+    fn check_tx_id(&self, tx_id: &TxId) -> bool;
+
+    /// gets a Key-Value payload for tendermint events
+    fn get_tendermint_kv(&self) -> (Vec<u8>, Vec<u8>);
+
+    /// check if view keys were added since its creation
+    fn is_modified(&self) -> bool;
+}
+
+/// Probabilistic fixed-size filter wrapper
+/// (256 bytes with 3 bits per view key by default, as ethbloom)
+#[derive(Default, Debug)]
+pub struct BlockFilter {
+    bloom: Bloom,
+    modified: bool,
+    /// number of the added items (estimated from the set bits for a decoded filter)
+    num_inserted: usize,
+}
+
+impl BlockFilter {
+    /// creates an empty filter of `size_bytes` (a power of two from 256 to 8192) which sets
+    /// `num_hashes` (from 1 to 16) bits per view key; the dimensions are encoded in the
+    /// tendermint event payload (unless they are the default ones)
+    pub fn with_params(size_bytes: usize, num_hashes: u8) -> Result<Self, &'static str> {
+        Ok(BlockFilter {
+            bloom: Bloom::with_params(size_bytes, num_hashes)?,
+            modified: false,
+            num_inserted: 0,
+        })
+    }
+
+    /// size of the filter in bytes
+    pub fn size_bytes(&self) -> usize {
+        self.bloom.size_bytes()
+    }
+
+    /// number of bits set per view key
+    pub fn num_hashes(&self) -> u8 {
+        self.bloom.num_hashes()
+    }
+
+    /// number of the set bits (e.g. for metrics)
+    pub fn bit_count(&self) -> usize {
+        self.bloom.count_ones()
+    }
+
+    /// length of the tendermint event payload (see `get_tendermint_kv`) in bytes, which includes
+    /// the dimensions of a filter of a non-default size
+    pub fn byte_len(&self) -> usize {
+        self.bloom.data_len()
+    }
+
+    /// resets the filter, so that it can be reused for the next block: it's then the same as a
+    /// new filter of its dimensions (not modified, without any set bit)
+    pub fn reset(&mut self) {
+        self.modified = false;
+        self.num_inserted = 0;
+        self.bloom.reset();
+    }
+
+    /// joins with another filter (of the same dimensions), e.g. to build a filter of a range of
+    /// blocks: a view key which is in either filter is in the merged one
+    pub fn merge(&mut self, other: &BlockFilter) -> Result<(), &'static str> {
+        self.bloom.add(&other.bloom)?;
+        self.modified |= !other.is_empty();
+        self.num_inserted = self.num_inserted.saturating_add(other.num_inserted);
+        Ok(())
+    }
+
+    /// whether no view key was added to the filter
+    pub fn is_empty(&self) -> bool {
+        self.bloom.is_empty()
+    }
+
+    /// adds a view key to the filter
+    pub fn add_view_key(&mut self, view_key: &PublicKey) {
+        self.modified = true;
+        self.num_inserted = self.num_inserted.saturating_add(1);
+        self.bloom.set(&view_key.serialize()[..]);
+    }
+
+    /// gets a Key-Value payload for tendermint events
+    pub fn get_tendermint_kv(&self) -> (Vec<u8>, Vec<u8>) {
+        (TendermintEventKey::EthBloom.into(), self.bloom.data())
+    }
+
+    /// gets a Key-Value payload for tendermint events, prefixed with the version of its format
+    /// (`0x80 | BLOCK_FILTER_VERSION`); the decoding accepts both payloads
+    pub fn get_tendermint_kv_versioned(&self) -> (Vec<u8>, Vec<u8>) {
+        let mut value = Vec::with_capacity(self.byte_len() + 1);
+        value.push(VERSION_FLAG | BLOCK_FILTER_VERSION);
+        value.extend(self.bloom.data());
+        (TendermintEventKey::EthBloom.into(), value)
+    }
+
+    /// tests if a view key is in the filter
+    /// true = maybe present
+    /// false = not present
+    pub fn check_view_key(&self, view_key: &PublicKey) -> bool {
+        self.bloom.check(&view_key.serialize())
+    }
+
+    /// adds several view keys to the filter (it's only modified if there's any)
+    pub fn extend_view_keys<'a>(&mut self, view_keys: impl Iterator<Item = &'a PublicKey>) {
+        let mut added = 0usize;
+        for view_key in view_keys {
+            self.bloom.set(&view_key.serialize()[..]);
+            added += 1;
+        }
+        self.inserted(added);
+    }
+
+    fn inserted(&mut self, count: usize) {
+        if count > 0 {
+            self.modified = true;
+            self.num_inserted = self.num_inserted.saturating_add(count);
+        }
+    }
+
+    /// adds a transaction ID to the filter
+    pub fn add_tx_id(&mut self, tx_id: &TxId) {
+        self.modified = true;
+        self.num_inserted = self.num_inserted.saturating_add(1);
+        self.bloom.set(&tx_id[..]);
+    }
+
+    /// tests if a transaction ID is in the filter
+    /// true = maybe present
+    /// false = not present
+    pub fn check_tx_id(&self, tx_id: &TxId) -> bool {
+        self.bloom.check(&tx_id[..])
+    }
+
+    /// tests several view keys at once: the i-th bit is set if the i-th key may be present
+    /// (none of the keys is hashed if the filter is empty)
+    pub fn check_view_keys(&self, view_keys: &[PublicKey]) -> BitVec {
+        if self.bloom.is_empty() {
+            return BitVec::from_elem(view_keys.len(), false);
+        }
+        view_keys
+            .iter()
+            .map(|view_key| self.bloom.check(&view_key.serialize()))
+            .collect()
+    }
+
+    /// adds a staked state address to the filter
+    /// (chain-abci adds the ones of the block's delivered transactions in EndBlock)
+    pub fn add_staked_state_address(&mut self, address: &StakedStateAddress) {
+        self.modified = true;
+        self.num_inserted = self.num_inserted.saturating_add(1);
+        self.bloom.set(address_bytes(address));
+    }
+
+    /// adds several staked state addresses to the filter (it's only modified if there's any)
+    pub fn extend_staked_state_addresses<'a>(
+        &mut self,
+        addresses: impl Iterator<Item = &'a StakedStateAddress>,
+    ) {
+        let mut added = 0usize;
+        for address in addresses {
+            self.bloom.set(address_bytes(address));
+            added += 1;
+        }
+        self.inserted(added);
+    }
+
+    /// tests if a staked state address is in the filter
+    /// true = maybe present
+    /// false = not present
+    pub fn check_staked_state_address(&self, address: &StakedStateAddress) -> bool {
+        self.bloom.check(address_bytes(address))
+    }
+
+    /// tests several staked state addresses at once: the i-th bit is set if the i-th address
+    /// may be present
+    pub fn check_staked_state_addresses(&self, addresses: &[StakedStateAddress]) -> BitVec {
+        if self.bloom.is_empty() {
+            return BitVec::from_elem(addresses.len(), false);
+        }
+        addresses
+            .iter()
+            .map(|address| self.bloom.check(address_bytes(address)))
+            .collect()
+    }
+
+    /// check if view keys were added since its creation
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    /// gets raw filter data (fails if the filter isn't of the default size)
+    pub fn get_raw(&self) -> Result<H2048, &'static str> {
+        self.bloom.raw_data()
+    }
+
+    /// number of the view keys and transaction IDs added to the filter (for a decoded filter,
+    /// it's estimated from the set bits)
+    pub fn num_inserted(&self) -> usize {
+        self.num_inserted
+    }
+
+    /// fraction of the set bits of the filter
+    pub fn saturation(&self) -> f64 {
+        self.bloom.saturation()
+    }
+
+    /// expected false positive rate of the filter with `num_items` items
+    pub fn estimated_false_positive_rate_for(&self, num_items: usize) -> f64 {
+        self.bloom.false_positive_rate(num_items)
+    }
+
+    /// expected false positive rate of the filter with its items (see `num_inserted`)
+    pub fn estimated_false_positive_rate(&self) -> f64 {
+        self.estimated_false_positive_rate_for(self.num_inserted)
+    }
+
+    /// hex string of the Key-Value payload (the 256-byte bloom for the default dimensions)
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.bloom.data())
+    }
+}
+
+fn address_bytes(address: &StakedStateAddress) -> &[u8] {
+    match address {
+        StakedStateAddress::BasicRedeem(RedeemAddress(raw)) => &raw[..],
+    }
+}
+
+impl TryFrom<&[u8]> for BlockFilter {
+    type Error = &'static str;
+
+    /// Accepts the legacy payloads (the raw 256 bytes or a filter with a header) and the
+    /// versioned ones
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        let bloom = Bloom::try_from(strip_version(value)?)?;
+        Ok(BlockFilter {
+            num_inserted: bloom.estimated_items(),
+            bloom,
+            modified: false,
+        })
+    }
+}
+
+/// Error of parsing a block filter from its hex string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseFilterError {
+    /// the string isn't hex-encoded (e.g. it has an odd length or a non-hex digit)
+    InvalidHex(hex::FromHexError),
+    /// the decoded bytes aren't a valid filter (e.g. of a wrong length)
+    InvalidFilter(&'static str),
+}
+
+impl fmt::Display for ParseFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseFilterError::InvalidHex(e) => write!(f, "invalid hex of block filter: {}", e),
+            ParseFilterError::InvalidFilter(e) => write!(f, "invalid block filter: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ParseFilterError {}
+
+impl fmt::Display for BlockFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl FromStr for BlockFilter {
+    type Err = ParseFilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let data = hex::decode(s).map_err(ParseFilterError::InvalidHex)?;
+        BlockFilter::try_from(data.as_slice()).map_err(ParseFilterError::InvalidFilter)
+    }
+}
+
+impl Serialize for BlockFilter {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockFilter {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        BlockFilter::from_str(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+/// Filter (of the default dimensions) of the view keys
+impl From<Vec<PublicKey>> for BlockFilter {
+    fn from(view_keys: Vec<PublicKey>) -> BlockFilter {
+        let mut filter = BlockFilter::default();
+        filter.extend_view_keys(view_keys.iter());
+        filter
+    }
+}
+
+impl From<&H2048> for BlockFilter {
+    fn from(val: &H2048) -> BlockFilter {
+        let bloom = Bloom::from(val);
+        BlockFilter {
+            num_inserted: bloom.estimated_items(),
+            bloom,
+            modified: false,
+        }
+    }
+}
+
+impl TxFilter for BlockFilter {
+    fn reset(&mut self) {
+        BlockFilter::reset(self)
+    }
+
+    fn add_view_key(&mut self, view_key: &PublicKey) {
+        BlockFilter::add_view_key(self, view_key)
+    }
+
+    fn check_view_key(&self, view_key: &PublicKey) -> bool {
+        BlockFilter::check_view_key(self, view_key)
+    }
+
+    fn add_tx_id(&mut self, tx_id: &TxId) {
+        BlockFilter::add_tx_id(self, tx_id)
+    }
+
+    fn check_tx_id(&self, tx_id: &TxId) -> bool {
+        BlockFilter::check_tx_id(self, tx_id)
+    }
+
+    fn get_tendermint_kv(&self) -> (Vec<u8>, Vec<u8>) {
+        BlockFilter::get_tendermint_kv(self)
+    }
+
+    fn is_modified(&self) -> bool {
+        BlockFilter::is_modified(self)
+    }
+}
+
+/// Probabilistic filter wrapper based on a Golomb-coded set
+/// (variable-size, with a lower false positive rate than `BlockFilter` for the same size)
+#[derive(Default, Debug)]
+pub struct GcsBlockFilter {
+    set: GolombSet,
+    modified: bool,
+}
+
+impl GcsBlockFilter {
+    /// joins with another filter: a view key which is in either filter is in the merged one
+    pub fn merge(&mut self, other: &GcsBlockFilter) {
+        self.modified |= !other.is_empty();
+        self.set.add(&other.set);
+    }
+
+    /// number of the (distinct) view key hashes in the filter
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    /// whether the filter is empty
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+}
+
+impl TxFilter for GcsBlockFilter {
+    fn reset(&mut self) {
+        self.modified = false;
+        self.set.reset();
+    }
+
+    fn add_view_key(&mut self, view_key: &PublicKey) {
+        self.modified = true;
+        self.set.insert(&view_key.serialize()[..]);
+    }
+
+    fn check_view_key(&self, view_key: &PublicKey) -> bool {
+        self.set.check(&view_key.serialize())
+    }
+
+    fn add_tx_id(&mut self, tx_id: &TxId) {
+        self.modified = true;
+        self.set.insert(&tx_id[..]);
+    }
+
+    fn check_tx_id(&self, tx_id: &TxId) -> bool {
+        self.set.check(&tx_id[..])
+    }
+
+    fn get_tendermint_kv(&self) -> (Vec<u8>, Vec<u8>) {
+        (TendermintEventKey::GcsFilter.into(), self.set.data())
+    }
+
+    fn is_modified(&self) -> bool {
+        self.modified
+    }
+}
+
+impl TryFrom<&[u8]> for GcsBlockFilter {
+    type Error = &'static str;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        let set = GolombSet::try_from(value)?;
+        Ok(GcsBlockFilter {
+            set,
+            modified: false,
+        })
+    }
+}
+
+/// Block filter of either kind, e.g. as decoded from the tendermint event
+#[derive(Debug)]
+pub enum FilterKind {
+    /// fixed-size Bloom filter (`ethbloom` event key)
+    EthBloom(BlockFilter),
+    /// Golomb-coded set (`gcsfilter` event key)
+    Gcs(GcsBlockFilter),
+}
+
+impl Default for FilterKind {
+    fn default() -> Self {
+        FilterKind::EthBloom(BlockFilter::default())
+    }
+}
+
+impl FilterKind {
+    /// decodes the filter from the Key-Value payload of the tendermint event
+    pub fn from_tendermint_kv(key: &[u8], value: &[u8]) -> Result<Self, &'static str> {
+        if key == TendermintEventKey::EthBloom.to_vec().as_slice() {
+            Ok(FilterKind::EthBloom(BlockFilter::try_from(value)?))
+        } else if key == TendermintEventKey::GcsFilter.to_vec().as_slice() {
+            Ok(FilterKind::Gcs(GcsBlockFilter::try_from(value)?))
+        } else {
+            Err("Unknown block filter kind")
+        }
+    }
+
+    fn filter(&self) -> &dyn TxFilter {
+        match self {
+            FilterKind::EthBloom(filter) => filter,
+            FilterKind::Gcs(filter) => filter,
+        }
+    }
+
+    fn filter_mut(&mut self) -> &mut dyn TxFilter {
+        match self {
+            FilterKind::EthBloom(filter) => filter,
+            FilterKind::Gcs(filter) => filter,
+        }
+    }
+}
+
+impl TxFilter for FilterKind {
+    fn reset(&mut self) {
+        self.filter_mut().reset()
+    }
+
+    fn add_view_key(&mut self, view_key: &PublicKey) {
+        self.filter_mut().add_view_key(view_key)
+    }
+
+    fn check_view_key(&self, view_key: &PublicKey) -> bool {
+        self.filter().check_view_key(view_key)
+    }
+
+    fn add_tx_id(&mut self, tx_id: &TxId) {
+        self.filter_mut().add_tx_id(tx_id)
+    }
+
+    fn check_tx_id(&self, tx_id: &TxId) -> bool {
+        self.filter().check_tx_id(tx_id)
+    }
+
+    fn get_tendermint_kv(&self) -> (Vec<u8>, Vec<u8>) {
+        self.filter().get_tendermint_kv()
+    }
+
+    fn is_modified(&self) -> bool {
+        self.filter().is_modified()
+    }
+}
+
+impl From<BlockFilter> for FilterKind {
+    fn from(filter: BlockFilter) -> FilterKind {
+        FilterKind::EthBloom(filter)
+    }
+}
+
+impl From<GcsBlockFilter> for FilterKind {
+    fn from(filter: GcsBlockFilter) -> FilterKind {
+        FilterKind::Gcs(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{DEFAULT_BLOOM_HASHES, DEFAULT_BLOOM_SIZE, MAX_BLOOM_SIZE};
+    use quickcheck::quickcheck;
+    use secp256k1::key::SecretKey;
+
+    fn view_key(seed: u8) -> PublicKey {
+        let secret = SecretKey::from_slice(&[seed; 32]).unwrap();
+        PublicKey::from_secret_key(secp256k1::SECP256K1, &secret)
+    }
+
+    fn check_roundtrip<F: TxFilter + Default>(filter: F) {
+        let (key, value) = filter.get_tendermint_kv();
+        let decoded = FilterKind::from_tendermint_kv(&key, &value).unwrap();
+        assert!(!decoded.is_modified());
+        assert!(decoded.check_view_key(&view_key(1)));
+        assert!(decoded.check_view_key(&view_key(2)));
+        assert!(!decoded.check_view_key(&view_key(3)));
+        assert_eq!(decoded.get_tendermint_kv(), (key, value));
+    }
+
+    fn with_view_keys<F: TxFilter + Default>() -> F {
+        let mut filter = F::default();
+        assert!(!filter.is_modified());
+        filter.add_view_key(&view_key(1));
+        filter.add_view_key(&view_key(2));
+        assert!(filter.is_modified());
+        filter
+    }
+
+    #[test]
+    fn bloom_filter_should_roundtrip() {
+        let filter = with_view_keys::<BlockFilter>();
+        assert_eq!(filter.get_tendermint_kv().0, b"ethbloom".to_vec());
+        check_roundtrip(filter);
+    }
+
+    #[test]
+    fn sized_bloom_filters_should_roundtrip() {
+        for size in [256, 1024, 4096].iter() {
+            let mut filter = BlockFilter::with_params(*size, DEFAULT_BLOOM_HASHES + 1).unwrap();
+            filter.add_view_key(&view_key(1));
+            filter.add_view_key(&view_key(2));
+            let (_, value) = filter.get_tendermint_kv();
+            let decoded = BlockFilter::try_from(value.as_slice()).unwrap();
+            assert_eq!(decoded.size_bytes(), *size);
+            assert_eq!(decoded.num_hashes(), DEFAULT_BLOOM_HASHES + 1);
+            check_roundtrip(filter);
+        }
+        // the default filter keeps the (headerless) ethbloom payload
+        let (_, value) = with_view_keys::<BlockFilter>().get_tendermint_kv();
+        assert_eq!(value.len(), DEFAULT_BLOOM_SIZE);
+    }
+
+    #[test]
+    fn only_default_bloom_filters_should_have_raw_data() {
+        let filter = with_view_keys::<BlockFilter>();
+        let decoded = BlockFilter::from(&filter.get_raw().unwrap());
+        assert!(decoded.check_view_key(&view_key(1)));
+        assert!(decoded.check_view_key(&view_key(2)));
+        let sized = BlockFilter::with_params(1024, DEFAULT_BLOOM_HASHES).unwrap();
+        assert!(sized.get_raw().is_err());
+    }
+
+    #[test]
+    fn bloom_filters_of_different_sizes_should_not_mix() {
+        let small = BlockFilter::with_params(1024, DEFAULT_BLOOM_HASHES).unwrap();
+        let large = BlockFilter::with_params(4096, DEFAULT_BLOOM_HASHES).unwrap();
+        let (_, large_value) = large.get_tendermint_kv();
+        let (_, small_value) = small.get_tendermint_kv();
+        assert!(BlockFilter::try_from(&large_value[..small_value.len()]).is_err());
+        assert!(BlockFilter::try_from(&large_value[2..]).is_err());
+
+        let mut filter = BlockFilter::default();
+        assert!(filter.merge(&small).is_err());
+        assert!(filter.merge(&BlockFilter::default()).is_ok());
+        // nothing was added
+        assert!(!filter.is_modified());
+        assert!(filter.is_empty());
+    }
+
+    fn filter_with(seeds: &[u8], size: usize) -> BlockFilter {
+        let mut filter = BlockFilter::with_params(size, DEFAULT_BLOOM_HASHES).unwrap();
+        for seed in seeds {
+            filter.add_view_key(&view_key(*seed));
+        }
+        filter
+    }
+
+    #[test]
+    fn merged_bloom_filter_should_contain_keys_of_both() {
+        let mut merged = filter_with(&[1, 2], DEFAULT_BLOOM_SIZE);
+        assert!(!merged.is_empty());
+        merged
+            .merge(&filter_with(&[3], DEFAULT_BLOOM_SIZE))
+            .unwrap();
+        assert!(merged.is_modified());
+        for seed in 1..=3 {
+            assert!(merged.check_view_key(&view_key(seed)));
+        }
+        assert!(!merged.check_view_key(&view_key(4)));
+
+        // a decoded filter isn't modified, until a non-empty one is merged into it
+        let (_, value) = merged.get_tendermint_kv();
+        let mut decoded = BlockFilter::try_from(value.as_slice()).unwrap();
+        decoded.merge(&BlockFilter::default()).unwrap();
+        assert!(!decoded.is_modified());
+        decoded
+            .merge(&filter_with(&[4], DEFAULT_BLOOM_SIZE))
+            .unwrap();
+        assert!(decoded.is_modified());
+        assert!(decoded.check_view_key(&view_key(4)));
+    }
+
+    #[test]
+    fn decoded_bloom_filter_should_contain_tx_ids() {
+        let mut filter = with_view_keys::<BlockFilter>();
+        filter.add_tx_id(&[1; 32]);
+        filter.add_tx_id(&[2; 32]);
+        let (key, value) = filter.get_tendermint_kv();
+        // same payload as with view keys only
+        assert_eq!(key, b"ethbloom".to_vec());
+        assert_eq!(value.len(), DEFAULT_BLOOM_SIZE);
+
+        let decoded = BlockFilter::try_from(value.as_slice()).unwrap();
+        assert!(decoded.check_tx_id(&[1; 32]));
+        assert!(decoded.check_tx_id(&[2; 32]));
+        assert!(!decoded.check_tx_id(&[3; 32]));
+        assert!(decoded.check_view_key(&view_key(1)));
+        assert!(!decoded.check_view_key(&view_key(3)));
+
+        let mut only_tx_ids = BlockFilter::default();
+        only_tx_ids.add_tx_id(&[1; 32]);
+        assert!(only_tx_ids.is_modified());
+        let (_, value) = only_tx_ids.get_tendermint_kv();
+        let decoded = BlockFilter::try_from(value.as_slice()).unwrap();
+        assert!(decoded.check_tx_id(&[1; 32]));
+        assert!(!decoded.check_tx_id(&[2; 32]));
+        assert!(!decoded.check_view_key(&view_key(1)));
+    }
+
+    #[test]
+    fn bloom_filter_should_roundtrip_through_hex() {
+        let filter = with_view_keys::<BlockFilter>();
+        let hex = filter.to_hex();
+        assert_eq!(hex.len(), DEFAULT_BLOOM_SIZE * 2);
+        assert_eq!(hex, filter.to_string());
+        let decoded = BlockFilter::from_str(&hex).unwrap();
+        assert!(decoded.check_view_key(&view_key(1)));
+        assert!(!decoded.check_view_key(&view_key(3)));
+        assert_eq!(decoded.to_hex(), hex);
+
+        let json = serde_json::to_string(&filter).unwrap();
+        assert_eq!(json, format!("\"{}\"", hex));
+        let decoded: BlockFilter = serde_json::from_str(&json).unwrap();
+        assert!(decoded.check_view_key(&view_key(2)));
+        assert_eq!(decoded.get_tendermint_kv(), filter.get_tendermint_kv());
+
+        // the dimensions of a sized filter are kept
+        let mut sized = BlockFilter::with_params(1024, DEFAULT_BLOOM_HASHES).unwrap();
+        sized.add_view_key(&view_key(1));
+        let decoded: BlockFilter =
+            serde_json::from_str(&serde_json::to_string(&sized).unwrap()).unwrap();
+        assert_eq!(decoded.size_bytes(), 1024);
+        assert!(decoded.check_view_key(&view_key(1)));
+    }
+
+    #[test]
+    fn invalid_hex_filter_should_be_rejected() {
+        let hex = BlockFilter::default().to_hex();
+        assert!(matches!(
+            BlockFilter::from_str(&hex[1..]),
+            Err(ParseFilterError::InvalidHex(hex::FromHexError::OddLength))
+        ));
+        let non_hex = format!("zz{}", &hex[2..]);
+        assert!(matches!(
+            BlockFilter::from_str(&non_hex),
+            Err(ParseFilterError::InvalidHex(
+                hex::FromHexError::InvalidHexCharacter { .. }
+            ))
+        ));
+        assert!(matches!(
+            BlockFilter::from_str(&hex[2..]),
+            Err(ParseFilterError::InvalidFilter(_))
+        ));
+        assert!(matches!(
+            BlockFilter::from_str(""),
+            Err(ParseFilterError::InvalidFilter(_))
+        ));
+
+        let error = serde_json::from_str::<BlockFilter>(&format!("\"{}\"", &hex[1..]))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Odd number of digits"), "{}", error);
+        assert!(serde_json::from_str::<BlockFilter>(&format!("\"{}\"", non_hex)).is_err());
+        assert!(serde_json::from_str::<BlockFilter>("256").is_err());
+    }
+
+    #[test]
+    fn versioned_bloom_filters_should_roundtrip() {
+        for size in [DEFAULT_BLOOM_SIZE, 1024].iter() {
+            let mut filter = filter_with(&[1, 2], *size);
+            filter.add_tx_id(&[1; 32]);
+            let (legacy_key, legacy_value) = filter.get_tendermint_kv();
+            let (key, value) = filter.get_tendermint_kv_versioned();
+            assert_eq!(key, legacy_key);
+            assert_eq!(value[0], 0x81);
+            assert_eq!(&value[1..], &legacy_value[..]);
+
+            for value in [legacy_value, value].iter() {
+                let decoded = match FilterKind::from_tendermint_kv(&key, value).unwrap() {
+                    FilterKind::EthBloom(decoded) => decoded,
+                    _ => panic!("not a bloom filter"),
+                };
+                assert_eq!(decoded.size_bytes(), *size);
+                assert!(decoded.check_view_key(&view_key(1)));
+                assert!(decoded.check_tx_id(&[1; 32]));
+                assert!(!decoded.check_view_key(&view_key(3)));
+                // re-encoded in the legacy form
+                assert_eq!(decoded.get_tendermint_kv(), filter.get_tendermint_kv());
+            }
+        }
+
+        // a legacy payload whose first byte looks like a version prefix
+        let mut raw = vec![0u8; DEFAULT_BLOOM_SIZE];
+        raw[0] = 0xff;
+        let decoded = BlockFilter::try_from(raw.as_slice()).unwrap();
+        assert_eq!(decoded.get_tendermint_kv().1, raw);
+    }
+
+    #[test]
+    fn unknown_bloom_filter_versions_should_be_rejected() {
+        let (_, mut value) = with_view_keys::<BlockFilter>().get_tendermint_kv_versioned();
+        for prefix in [0x80, 0x82, 0xff].iter() {
+            value[0] = *prefix;
+            assert_eq!(
+                BlockFilter::try_from(value.as_slice()).unwrap_err(),
+                "Unknown block filter version"
+            );
+        }
+        // a known version with an invalid filter
+        value[0] = VERSION_FLAG | BLOCK_FILTER_VERSION;
+        assert!(BlockFilter::try_from(&value[..DEFAULT_BLOOM_SIZE - 1]).is_err());
+        assert!(BlockFilter::try_from(&[VERSION_FLAG | BLOCK_FILTER_VERSION][..]).is_err());
+    }
+
+    #[test]
+    fn reset_filter_should_behave_as_new_one() {
+        for size in [DEFAULT_BLOOM_SIZE, 1024].iter() {
+            let mut reused = filter_with(&[1, 2], *size);
+            reused.add_tx_id(&[1; 32]);
+            assert!(reused.bit_count() > 0);
+            reused.reset();
+            let fresh = BlockFilter::with_params(*size, DEFAULT_BLOOM_HASHES).unwrap();
+            // the enclaves only return a filter for a block if it was modified
+            assert!(!reused.is_modified());
+            assert!(reused.is_empty());
+            assert_eq!(reused.bit_count(), 0);
+            assert_eq!(reused.num_inserted(), 0);
+            assert_eq!(reused.get_tendermint_kv(), fresh.get_tendermint_kv());
+
+            let mut fresh = fresh;
+            for filter in [&mut reused, &mut fresh].iter_mut() {
+                filter.add_view_key(&view_key(3));
+                filter.add_tx_id(&[2; 32]);
+            }
+            assert!(reused.is_modified());
+            assert_eq!(reused.bit_count(), fresh.bit_count());
+            assert_eq!(reused.num_inserted(), 2);
+            assert_eq!(reused.get_tendermint_kv(), fresh.get_tendermint_kv());
+            assert!(!reused.check_view_key(&view_key(1)));
+            assert!(!reused.check_tx_id(&[1; 32]));
+            assert!(reused.check_view_key(&view_key(3)));
+        }
+    }
+
+    #[test]
+    fn bloom_filter_sizes_should_match_payload() {
+        let mut filter = BlockFilter::default();
+        assert_eq!(filter.byte_len(), DEFAULT_BLOOM_SIZE);
+        filter.add_view_key(&view_key(1));
+        assert!(filter.bit_count() > 0 && filter.bit_count() <= DEFAULT_BLOOM_HASHES as usize);
+        assert_eq!(
+            filter.bit_count() as f64 / (DEFAULT_BLOOM_SIZE * 8) as f64,
+            filter.saturation()
+        );
+
+        let sized = BlockFilter::with_params(1024, DEFAULT_BLOOM_HASHES).unwrap();
+        assert_eq!(sized.size_bytes(), 1024);
+        assert_eq!(sized.byte_len(), sized.get_tendermint_kv().1.len());
+        assert!(sized.byte_len() > 1024);
+    }
+
+    #[test]
+    fn bulk_insertion_should_match_single_insertions() {
+        let view_keys = (0..10_000u32)
+            .map(|i| {
+                let mut seed = [1u8; 32];
+                seed[..4].copy_from_slice(&i.to_be_bytes());
+                PublicKey::from_secret_key(
+                    secp256k1::SECP256K1,
+                    &SecretKey::from_slice(&seed).unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut extended = BlockFilter::with_params(MAX_BLOOM_SIZE, DEFAULT_BLOOM_HASHES).unwrap();
+        extended.extend_view_keys(view_keys.iter());
+        let mut single = BlockFilter::with_params(MAX_BLOOM_SIZE, DEFAULT_BLOOM_HASHES).unwrap();
+        for view_key in view_keys.iter() {
+            single.add_view_key(view_key);
+        }
+        assert!(extended.is_modified());
+        assert_eq!(extended.num_inserted(), 10_000);
+        assert_eq!(extended.get_tendermint_kv(), single.get_tendermint_kv());
+        assert!(view_keys.iter().all(|key| extended.check_view_key(key)));
+
+        let from_keys = BlockFilter::from(view_keys[..2].to_vec());
+        assert!(from_keys.check_view_key(&view_keys[0]));
+        assert!(from_keys.check_view_key(&view_keys[1]));
+        assert_eq!(from_keys.num_inserted(), 2);
+
+        let addresses = [
+            StakedStateAddress::BasicRedeem(RedeemAddress([1; 20])),
+            StakedStateAddress::BasicRedeem(RedeemAddress([2; 20])),
+        ];
+        let mut filter = BlockFilter::default();
+        filter.extend_staked_state_addresses(addresses.iter());
+        assert!(filter.is_modified());
+        assert!(filter.check_staked_state_address(&addresses[1]));
+
+        // nothing added
+        let mut filter = BlockFilter::default();
+        filter.extend_view_keys([].iter());
+        filter.extend_staked_state_addresses([].iter());
+        assert!(!filter.is_modified());
+        assert!(!BlockFilter::from(Vec::new()).is_modified());
+    }
+
+    fn tx_id(marker: u8, i: u32) -> TxId {
+        let mut tx_id = [marker; 32];
+        tx_id[..4].copy_from_slice(&i.to_be_bytes());
+        tx_id
+    }
+
+    #[test]
+    fn estimated_false_positive_rate_should_match_measured() {
+        let empty = BlockFilter::default();
+        assert_eq!(empty.saturation(), 0.0);
+        assert_eq!(empty.estimated_false_positive_rate(), 0.0);
+
+        for (size, count) in [
+            (DEFAULT_BLOOM_SIZE, 100),
+            (DEFAULT_BLOOM_SIZE, 300),
+            (1024, 500),
+        ]
+        .iter()
+        {
+            let mut filter = BlockFilter::with_params(*size, DEFAULT_BLOOM_HASHES).unwrap();
+            for i in 0..*count {
+                filter.add_tx_id(&tx_id(0, i));
+            }
+            assert_eq!(filter.num_inserted(), *count as usize);
+
+            let trials = 100_000;
+            let measured = (0..trials)
+                .filter(|i| filter.check_tx_id(&tx_id(1, *i)))
+                .count() as f64
+                / f64::from(trials);
+            let estimated = filter.estimated_false_positive_rate();
+            assert!(
+                (estimated - measured).abs() <= 0.2 * estimated,
+                "estimated {}, measured {}",
+                estimated,
+                measured
+            );
+            // the fraction of set bits gives about the same estimate
+            let from_saturation = filter.saturation().powi(i32::from(DEFAULT_BLOOM_HASHES));
+            assert!((from_saturation - measured).abs() <= 0.2 * from_saturation);
+
+            // the number of items of a decoded filter is estimated from its bits
+            let (_, value) = filter.get_tendermint_kv();
+            let decoded = BlockFilter::try_from(value.as_slice()).unwrap();
+            let error = decoded.num_inserted() as f64 - f64::from(*count);
+            assert!(
+                error.abs() <= 0.05 * f64::from(*count),
+                "{}",
+                decoded.num_inserted()
+            );
+        }
+    }
+
+    #[test]
+    fn saturated_filter_should_always_match() {
+        let mut filter = BlockFilter::default();
+        let mut i = 0;
+        while filter.saturation() < 1.0 {
+            filter.add_tx_id(&tx_id(0, i));
+            i += 1;
+        }
+        assert!(filter.estimated_false_positive_rate() > 0.99);
+        let (_, value) = filter.get_tendermint_kv();
+        let decoded = BlockFilter::try_from(value.as_slice()).unwrap();
+        assert_eq!(decoded.estimated_false_positive_rate(), 1.0);
+        filter.reset();
+        assert_eq!(filter.num_inserted(), 0);
+    }
+
+    #[test]
+    fn batch_checks_should_match_single_checks() {
+        let view_keys = (0..1000u32)
+            .map(|i| {
+                let mut secret = [1; 32];
+                secret[..4].copy_from_slice(&i.to_be_bytes());
+                PublicKey::from_secret_key(
+                    secp256k1::SECP256K1,
+                    &SecretKey::from_slice(&secret).unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let addresses = (0..1000u32)
+            .map(|i| {
+                let mut raw = [2; 20];
+                raw[..4].copy_from_slice(&i.to_be_bytes());
+                StakedStateAddress::BasicRedeem(RedeemAddress(raw))
+            })
+            .collect::<Vec<_>>();
+
+        let mut filters = vec![BlockFilter::default()];
+        for count in [10, 100].iter() {
+            let mut filter = BlockFilter::default();
+            for i in (0..*count).map(|i| i * 7) {
+                filter.add_view_key(&view_keys[i]);
+                filter.add_staked_state_address(&addresses[i]);
+            }
+            filters.push(filter);
+        }
+
+        for filter in filters.iter() {
+            let single = view_keys
+                .iter()
+                .map(|key| filter.check_view_key(key))
+                .collect::<BitVec>();
+            assert_eq!(single, filter.check_view_keys(&view_keys));
+
+            let single = addresses
+                .iter()
+                .map(|address| filter.check_staked_state_address(address))
+                .collect::<BitVec>();
+            assert_eq!(single, filter.check_staked_state_addresses(&addresses));
+        }
+
+        // no false negatives
+        let batch = filters[2].check_view_keys(&view_keys);
+        assert!((0..100).all(|i| batch[i * 7]));
+        assert!(filters[0].check_view_keys(&view_keys).none());
+        assert_eq!(
+            view_keys.len(),
+            filters[1].check_staked_state_addresses(&addresses).len()
+        );
+    }
+
+    quickcheck! {
+
+        // no false negatives after merging (of filters of any size)
+        fn merged_filter_contains_keys_of_both(left: Vec<u8>, right: Vec<u8>, large: bool) -> bool {
+            // valid secret keys
+            let left = left.iter().take(8).map(|seed| seed % 200 + 1).collect::<Vec<_>>();
+            let right = right.iter().take(8).map(|seed| seed % 200 + 1).collect::<Vec<_>>();
+            let size = if large { 4096 } else { DEFAULT_BLOOM_SIZE };
+            let mut merged = filter_with(&left, size);
+            merged.merge(&filter_with(&right, size)).is_ok()
+                && merged.is_empty() == (left.is_empty() && right.is_empty())
+                && left
+                    .iter()
+                    .chain(right.iter())
+                    .all(|seed| merged.check_view_key(&view_key(*seed)))
+        }
+
+    }
+
+    #[test]
+    fn gcs_filter_should_roundtrip() {
+        let filter = with_view_keys::<GcsBlockFilter>();
+        assert_eq!(filter.get_tendermint_kv().0, b"gcsfilter".to_vec());
+        assert_eq!(filter.len(), 2);
+        check_roundtrip(filter);
+    }
+
+    #[test]
+    fn gcs_filters_should_join() {
+        let mut filter = GcsBlockFilter::default();
+        filter.add_view_key(&view_key(1));
+        let mut other = GcsBlockFilter::default();
+        other.add_view_key(&view_key(2));
+        filter.merge(&GcsBlockFilter::try_from(other.get_tendermint_kv().1.as_slice()).unwrap());
+        assert!(filter.check_view_key(&view_key(1)));
+        assert!(filter.check_view_key(&view_key(2)));
+
+        filter.reset();
+        assert!(filter.is_empty());
+        assert!(!filter.is_modified());
+    }
+
+    #[test]
+    fn unknown_filter_kind_should_be_rejected() {
+        let (_, value) = BlockFilter::default().get_tendermint_kv();
+        assert!(FilterKind::from_tendermint_kv(b"fee", &value).is_err());
+        // the payload must match the key
+        assert!(FilterKind::from_tendermint_kv(b"gcsfilter", &value).is_err());
+    }
+}
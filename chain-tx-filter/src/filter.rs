@@ -7,8 +7,14 @@
 //! "a specialised Bloom filter that sets three bits out of 2048,
 //! given an arbitrary byte sequence. It does this through taking the low-order 11 bits of each of
 //! the first three pairs of bytes in a Keccak-256 hash of the byte sequence."
+//!
+//! Larger filters (and more bits per item) can be used for blocks with many view keys;
+//! the default dimensions are the ethbloom ones.
+use crate::format::{
+    bit_positions, check_dimensions, split_filter, DEFAULT_BLOOM_HASHES, DEFAULT_BLOOM_SIZE,
+    HEADER_LEN,
+};
 use bit_vec::BitVec;
-use chain_core::init::address::keccak256;
 use std::convert::TryFrom;
 use std::prelude::v1::Vec;
 
@@ -16,96 +22,184 @@ pub type H2048 = [u8; 256];
 
 /// A Bloom filter
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Bloom(BitVec);
+pub struct Bloom {
+    bits: BitVec,
+    num_hashes: u8,
+}
 
 impl Default for Bloom {
     fn default() -> Self {
-        Bloom(BitVec::from_elem(2048, false))
+        Bloom {
+            bits: BitVec::from_elem(DEFAULT_BLOOM_SIZE * 8, false),
+            num_hashes: DEFAULT_BLOOM_HASHES,
+        }
     }
 }
 
 impl Into<BitVec> for Bloom {
     fn into(self) -> BitVec {
-        self.0
+        self.bits
     }
 }
 
+/// The default filters are encoded as their raw 256 bytes (as ethbloom), the others are prefixed
+/// with their dimensions (so their encoded length is never 256 bytes)
 impl TryFrom<&[u8]> for Bloom {
     type Error = &'static str;
 
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        if value.len() != 256 {
-            Err("Invalid length, ethbloom is expected to be 256-bytes")
-        } else {
-            Ok(Bloom(BitVec::from_bytes(value)))
-        }
+        let (num_hashes, bits) = split_filter(value)?;
+        Ok(Bloom {
+            bits: BitVec::from_bytes(bits),
+            num_hashes,
+        })
     }
 }
 
 impl From<&H2048> for Bloom {
     fn from(val: &H2048) -> Bloom {
-        Bloom(BitVec::from_bytes(&val[..]))
+        Bloom {
+            bits: BitVec::from_bytes(&val[..]),
+            num_hashes: DEFAULT_BLOOM_HASHES,
+        }
     }
 }
 
-impl Into<H2048> for Bloom {
-    fn into(self) -> H2048 {
-        self.raw_data()
+impl Bloom {
+    /// Creates an empty filter of `size_bytes` (a power of two from 256 to 8192) which sets
+    /// `num_hashes` (from 1 to 16) bits per item
+    pub fn with_params(size_bytes: usize, num_hashes: u8) -> Result<Self, &'static str> {
+        check_dimensions(size_bytes, num_hashes)?;
+        Ok(Bloom {
+            bits: BitVec::from_elem(size_bytes * 8, false),
+            num_hashes,
+        })
+    }
+
+    /// Size of the filter in bytes
+    pub fn size_bytes(&self) -> usize {
+        self.bits.len() / 8
     }
-}
 
-fn single_set(arr: &[u8]) -> BitVec {
-    let mut r = [0u8; 256];
-    let h = keccak256(arr);
-    for i in [0usize, 2usize, 4usize].iter() {
-        let m = (((h[*i] as usize) << 8) + (h[*i + 1] as usize)) % 2048;
-        r[m / 8] |= 1 << (m % 8);
+    /// Number of bits set per item
+    pub fn num_hashes(&self) -> u8 {
+        self.num_hashes
+    }
+
+    /// Whether the filter has the default (ethbloom) dimensions
+    pub fn is_default(&self) -> bool {
+        self.size_bytes() == DEFAULT_BLOOM_SIZE && self.num_hashes == DEFAULT_BLOOM_HASHES
+    }
+
+    /// Indices (in the underlying bitvector, which numbers the bits of each byte from the most
+    /// significant one) of the bits of the array (see `format::bit_positions`)
+    fn indices(&self, arr: &[u8]) -> impl Iterator<Item = usize> {
+        bit_positions(arr, self.bits.len(), self.num_hashes).map(|m| (m / 8) * 8 + 7 - (m % 8))
+    }
+
+    /// Whether no bit is set
+    pub fn is_empty(&self) -> bool {
+        self.bits.none()
+    }
+
+    /// Number of the set bits
+    pub fn count_ones(&self) -> usize {
+        self.bits
+            .blocks()
+            .map(|block| block.count_ones() as usize)
+            .sum()
+    }
+
+    /// Fraction of the set bits
+    pub fn saturation(&self) -> f64 {
+        self.count_ones() as f64 / self.bits.len() as f64
+    }
+
+    /// Expected false positive rate after `num_items` distinct items were added:
+    /// `(1 - e^(-k * n / m))^k` for `k` hashes and `m` bits
+    pub fn false_positive_rate(&self, num_items: usize) -> f64 {
+        let k = f64::from(self.num_hashes);
+        let m = self.bits.len() as f64;
+        (1.0 - (-k * num_items as f64 / m).exp()).powf(k)
+    }
+
+    /// Estimated number of the distinct items in the filter (from the fraction `x` of the set
+    /// bits): `-m / k * ln(1 - x)`
+    pub fn estimated_items(&self) -> usize {
+        let saturation = self.saturation();
+        if saturation >= 1.0 {
+            // not distinguishable from any larger number
+            return usize::MAX;
+        }
+        let k = f64::from(self.num_hashes);
+        let m = self.bits.len() as f64;
+        (-m / k * (1.0 - saturation).ln()).round() as usize
     }
-    BitVec::from_bytes(&r[..])
-}
 
-impl Bloom {
     /// Starts a fresh filter
     pub fn reset(&mut self) {
-        self.0.clear();
+        self.bits.clear();
     }
 
-    /// Adds the other bloom filter to the current one
-    pub fn add(&mut self, other: &Bloom) {
-        self.0.or(&other.0);
+    /// Adds the other bloom filter to the current one (they need to have the same dimensions)
+    pub fn add(&mut self, other: &Bloom) -> Result<(), &'static str> {
+        if self.bits.len() != other.bits.len() || self.num_hashes != other.num_hashes {
+            return Err("Bloom filters with different dimensions can't be joined");
+        }
+        self.bits.or(&other.bits);
+        Ok(())
     }
 
     /// Set respective bits in the bloom with the array
     pub fn set(&mut self, arr: &[u8]) {
-        self.0.or(&single_set(arr));
+        for i in self.indices(arr) {
+            self.bits.set(i, true);
+        }
     }
 
     /// Check that an array is in the bloom
     pub fn check(&self, arr: &[u8]) -> bool {
-        let s1 = single_set(arr);
-        let mut s2 = s1.clone();
-        s2.and(&self.0);
-
-        s2 == s1
+        self.indices(arr).all(|i| self.bits[i])
     }
 
     /// Gets the bytes from the underlying bitvector as fixed size byte array
-    pub fn raw_data(&self) -> H2048 {
+    /// (only the filters of the default size fit into it)
+    pub fn raw_data(&self) -> Result<H2048, &'static str> {
+        if self.size_bytes() != DEFAULT_BLOOM_SIZE {
+            return Err("Invalid size, only the default size bloom filter fits H2048");
+        }
         let mut result = [0u8; 256];
-        let bytes = self.0.to_bytes();
-        result.copy_from_slice(&bytes);
-        result
+        result.copy_from_slice(&self.bits.to_bytes());
+        Ok(result)
+    }
+
+    /// Length of the encoded filter (see `data`)
+    pub fn data_len(&self) -> usize {
+        if self.is_default() {
+            self.size_bytes()
+        } else {
+            HEADER_LEN + self.size_bytes()
+        }
     }
 
-    /// Gets the bytes from the underlying bitvector
+    /// Gets the encoded filter: the bytes from the underlying bitvector, prefixed with the
+    /// dimensions if they aren't the default ones
     pub fn data(&self) -> Vec<u8> {
-        self.0.to_bytes()
+        if self.is_default() {
+            return self.bits.to_bytes();
+        }
+        let mut result = Vec::with_capacity(self.data_len());
+        result.push(self.num_hashes);
+        result.push(self.size_bytes().trailing_zeros() as u8);
+        result.extend_from_slice(&self.bits.to_bytes());
+        result
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::format::{MAX_BLOOM_HASHES, MAX_BLOOM_SIZE};
     use quickcheck::quickcheck;
 
     #[test]
@@ -116,13 +210,92 @@ mod tests {
         bloom.set(&arr);
         assert!(bloom.check(&hex::decode(test_vec).unwrap()));
 
-        let h: H2048 = bloom.into();
+        let h = bloom.raw_data().expect("default size filter");
         for i in [1323usize, 431usize, 1319usize].iter() {
             let v = 1 << (i % 8);
             assert!(h[i / 8] & v == v);
         }
     }
 
+    fn false_positives(bloom: &Bloom, probes: u32) -> usize {
+        (0..probes)
+            .filter(|i| bloom.check(&(u32::MAX - i).to_be_bytes()))
+            .count()
+    }
+
+    #[test]
+    fn false_positive_rate_should_drop_with_size() {
+        // (size, upper bound of false positives out of 100k probes with 100 items): about
+        // 2x of the expected (1 - e^(-3 * 100 / bits))^3 * 100k, i.e. 253, 4.6 and 0.08
+        let mut previous = usize::MAX;
+        for (size, max_false_positives) in [(256, 500), (1024, 20), (4096, 3)].iter() {
+            let mut bloom = Bloom::with_params(*size, DEFAULT_BLOOM_HASHES).unwrap();
+            for i in 0..100u32 {
+                bloom.set(&i.to_be_bytes());
+            }
+            assert!((0..100u32).all(|i| bloom.check(&i.to_be_bytes())));
+            let count = false_positives(&bloom, 100_000);
+            assert!(
+                count <= *max_false_positives,
+                "{} false positives with {} bytes",
+                count,
+                size
+            );
+            assert!(count <= previous);
+            previous = count;
+        }
+    }
+
+    #[test]
+    fn filters_of_any_size_should_roundtrip() {
+        for size in [256, 512, 1024, 4096, MAX_BLOOM_SIZE].iter() {
+            for num_hashes in [1, DEFAULT_BLOOM_HASHES, MAX_BLOOM_HASHES].iter() {
+                let mut bloom = Bloom::with_params(*size, *num_hashes).unwrap();
+                bloom.set(b"item");
+                let data = bloom.data();
+                assert_eq!(bloom.is_default(), data.len() == DEFAULT_BLOOM_SIZE);
+                let decoded = Bloom::try_from(data.as_slice()).unwrap();
+                assert_eq!(decoded, bloom);
+                assert!(decoded.check(b"item"));
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_dimensions_should_be_rejected() {
+        assert!(Bloom::with_params(128, 3).is_err());
+        assert!(Bloom::with_params(1000, 3).is_err());
+        assert!(Bloom::with_params(2 * MAX_BLOOM_SIZE, 3).is_err());
+        assert!(Bloom::with_params(1024, 0).is_err());
+        assert!(Bloom::with_params(1024, MAX_BLOOM_HASHES + 1).is_err());
+    }
+
+    #[test]
+    fn mismatched_sizes_should_fail_to_decode() {
+        let small = Bloom::with_params(1024, 3).unwrap().data();
+        let large = Bloom::with_params(4096, 3).unwrap().data();
+
+        // the header claims a different size than the data
+        let mut data = small.clone();
+        data[1] = large[1];
+        assert!(Bloom::try_from(data.as_slice()).is_err());
+        assert!(Bloom::try_from(&large[..small.len()]).is_err());
+        // raw data of a non-default size without the header
+        assert!(Bloom::try_from(&small[HEADER_LEN..]).is_err());
+        // the default dimensions with a header
+        let mut data = vec![DEFAULT_BLOOM_HASHES, 8];
+        data.extend_from_slice(&[0; DEFAULT_BLOOM_SIZE]);
+        assert!(Bloom::try_from(data.as_slice()).is_err());
+        assert!(Bloom::try_from(&[3, 64][..]).is_err());
+
+        let mut bloom = Bloom::try_from(small.as_slice()).unwrap();
+        assert!(bloom
+            .add(&Bloom::try_from(large.as_slice()).unwrap())
+            .is_err());
+        assert!(bloom.add(&Bloom::with_params(1024, 4).unwrap()).is_err());
+        assert!(bloom.add(&Bloom::with_params(1024, 3).unwrap()).is_ok());
+    }
+
     quickcheck! {
 
         // test this implementation matches ethbloom
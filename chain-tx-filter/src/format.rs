@@ -0,0 +1,92 @@
+//! Layout of the `ethbloom` event payload and positions of the bits of an item, shared by the
+//! decoded filters (`BlockFilter`) and the borrowed ones (`RawFilter`), so that they agree
+//! bit-for-bit. It only depends on `core` and a Keccak-256 implementation.
+use tiny_keccak::{Hasher, Keccak};
+
+/// Size (in bytes) of the default (ethbloom) filter
+pub const DEFAULT_BLOOM_SIZE: usize = 256;
+/// Number of hash functions of the default (ethbloom) filter
+pub const DEFAULT_BLOOM_HASHES: u8 = 3;
+/// Maximum size (in bytes) of a filter (bit indices are taken from 16-bit pairs of the hash)
+pub const MAX_BLOOM_SIZE: usize = 8192;
+/// Maximum number of hash functions (the pairs of bytes of a Keccak-256 hash)
+pub const MAX_BLOOM_HASHES: u8 = 16;
+
+/// Current format version of the versioned block filter payload
+pub const BLOCK_FILTER_VERSION: u8 = 1;
+
+/// Set in the version prefix, so that it can't be mistaken for the first byte (the number of
+/// hashes, at most 16) of a legacy filter with a header
+pub(crate) const VERSION_FLAG: u8 = 0x80;
+
+/// Length of the header of the encoded filters with non-default dimensions:
+/// the number of hash functions and log2 of the size in bytes
+pub(crate) const HEADER_LEN: usize = 2;
+
+/// Checks that the size (in bytes) is a power of two from 256 to 8192 and that the number of
+/// hashes is from 1 to 16
+pub(crate) fn check_dimensions(size_bytes: usize, num_hashes: u8) -> Result<(), &'static str> {
+    if !size_bytes.is_power_of_two() || !(DEFAULT_BLOOM_SIZE..=MAX_BLOOM_SIZE).contains(&size_bytes)
+    {
+        return Err(
+            "Invalid size, bloom filter is expected to be a power of two from 256 to 8192 bytes",
+        );
+    }
+    if !(1..=MAX_BLOOM_HASHES).contains(&num_hashes) {
+        return Err("Invalid number of bloom filter hashes, expected from 1 to 16");
+    }
+    Ok(())
+}
+
+/// Strips the version prefix of a versioned payload (a legacy payload is returned as it is)
+pub(crate) fn strip_version(value: &[u8]) -> Result<&[u8], &'static str> {
+    match value.first() {
+        Some(prefix) if value.len() != DEFAULT_BLOOM_SIZE && prefix & VERSION_FLAG != 0 => {
+            if prefix & !VERSION_FLAG != BLOCK_FILTER_VERSION {
+                return Err("Unknown block filter version");
+            }
+            Ok(&value[1..])
+        }
+        _ => Ok(value),
+    }
+}
+
+/// Splits an encoded filter (without the version prefix) into its number of hashes and its bits:
+/// the default filters are encoded as their raw 256 bytes (as ethbloom), the others are prefixed
+/// with their dimensions (so their encoded length is never 256 bytes)
+pub(crate) fn split_filter(value: &[u8]) -> Result<(u8, &[u8]), &'static str> {
+    if value.len() == DEFAULT_BLOOM_SIZE {
+        return Ok((DEFAULT_BLOOM_HASHES, value));
+    }
+    if value.len() < HEADER_LEN {
+        return Err("Invalid length, bloom filter is expected to be 256-bytes or to have a header");
+    }
+    let num_hashes = value[0];
+    let size = 1usize
+        .checked_shl(u32::from(value[1]))
+        .ok_or("Invalid bloom filter size")?;
+    check_dimensions(size, num_hashes)?;
+    if size == DEFAULT_BLOOM_SIZE && num_hashes == DEFAULT_BLOOM_HASHES {
+        return Err("Invalid bloom filter, the default dimensions are encoded without header");
+    }
+    if value.len() - HEADER_LEN != size {
+        return Err("Invalid length, bloom filter data doesn't match its size");
+    }
+    Ok((num_hashes, &value[HEADER_LEN..]))
+}
+
+/// Positions of the bits of an item in a filter of `bits` bits: the low-order bits of the first
+/// `num_hashes` pairs of bytes of its Keccak-256 hash, numbered from the least significant bit
+/// of each byte (as in ethbloom)
+pub(crate) fn bit_positions(
+    item: &[u8],
+    bits: usize,
+    num_hashes: u8,
+) -> impl Iterator<Item = usize> {
+    let mut h = [0u8; 32];
+    let mut hasher = Keccak::v256();
+    hasher.update(item);
+    hasher.finalize(&mut h);
+    (0..usize::from(num_hashes))
+        .map(move |i| (((h[2 * i] as usize) << 8) + (h[2 * i + 1] as usize)) % bits)
+}
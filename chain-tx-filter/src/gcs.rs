@@ -0,0 +1,317 @@
+//! # Golomb-coded set
+//! A compact probabilistic set (as in BIP-158 compact block filters): the items are hashed into
+//! `[0, N * 2^P)`, sorted, and the differences between the successive hashes are Golomb-Rice coded
+//! with the parameter `P`. For the same number of bytes, it has a much lower false positive rate
+//! than a fixed-size Bloom filter.
+//!
+//! The hashes are kept as 64-bit values with only their `precision` high bits set, so that a decoded
+//! set (whose hashes were truncated when it was encoded) can still be joined with other sets.
+//!
+//! Encoding: `P` (1 byte), the number of hash bits (1 byte), the number of items (u32, big-endian)
+//! and the Golomb-Rice coded differences (MSB first, zero-padded to a full byte).
+use bit_vec::BitVec;
+use chain_core::init::address::keccak256;
+use std::cmp;
+use std::convert::TryFrom;
+use std::prelude::v1::Vec;
+
+/// Golomb-Rice parameter (the false positive rate is about 2^-P)
+pub const GCS_P: u8 = 19;
+
+const HEADER_LEN: usize = 6;
+
+/// A Golomb-coded set
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GolombSet {
+    /// hashes of the items (masked to `precision`), sorted and without duplicates
+    hashes: Vec<u64>,
+    /// number of significant (high) bits of the hashes
+    precision: u8,
+}
+
+impl Default for GolombSet {
+    fn default() -> Self {
+        GolombSet {
+            hashes: Vec::new(),
+            precision: 64,
+        }
+    }
+}
+
+fn item_hash(item: &[u8]) -> u64 {
+    let hash = keccak256(item);
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+/// the `bits` high bits of the hash, as a number
+#[inline]
+fn truncate(hash: u64, bits: u8) -> u64 {
+    if bits == 0 {
+        0
+    } else {
+        hash >> (64 - u32::from(bits))
+    }
+}
+
+/// the hash with only its `bits` high bits kept
+#[inline]
+fn mask(hash: u64, bits: u8) -> u64 {
+    if bits == 0 {
+        0
+    } else {
+        truncate(hash, bits) << (64 - u32::from(bits))
+    }
+}
+
+/// number of bits needed to represent `n` distinct values
+#[inline]
+fn ceil_log2(n: usize) -> u8 {
+    if n <= 1 {
+        0
+    } else {
+        (64 - ((n - 1) as u64).leading_zeros()) as u8
+    }
+}
+
+impl GolombSet {
+    /// Starts a fresh set
+    pub fn reset(&mut self) {
+        *self = GolombSet::default();
+    }
+
+    /// Adds the items of the other set to the current one
+    pub fn add(&mut self, other: &GolombSet) {
+        let precision = cmp::min(self.precision, other.precision);
+        let mut hashes = self
+            .hashes
+            .iter()
+            .chain(other.hashes.iter())
+            .map(|hash| mask(*hash, precision))
+            .collect::<Vec<_>>();
+        hashes.sort_unstable();
+        hashes.dedup();
+        self.hashes = hashes;
+        self.precision = precision;
+    }
+
+    /// Adds an item to the set
+    pub fn insert(&mut self, item: &[u8]) {
+        let hash = mask(item_hash(item), self.precision);
+        if let Err(index) = self.hashes.binary_search(&hash) {
+            self.hashes.insert(index, hash);
+        }
+    }
+
+    /// Check that an item is in the set
+    /// (false positives are possible, false negatives are not)
+    pub fn check(&self, item: &[u8]) -> bool {
+        let hash = mask(item_hash(item), self.precision);
+        self.hashes.binary_search(&hash).is_ok()
+    }
+
+    /// Number of the (distinct) hashes in the set
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    /// Whether the set is empty
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// Gets the encoded set
+    pub fn data(&self) -> Vec<u8> {
+        // hashes are truncated to `log2(N) + P` bits; as truncating may merge some of them, it's
+        // repeated until N doesn't change, so that a decoded set is encoded to the same bytes
+        let mut bits = cmp::min(self.precision, ceil_log2(self.hashes.len()) + GCS_P);
+        let values = loop {
+            let mut values = self
+                .hashes
+                .iter()
+                .map(|hash| truncate(*hash, bits))
+                .collect::<Vec<_>>();
+            values.dedup();
+            let needed = cmp::min(self.precision, ceil_log2(values.len()) + GCS_P);
+            if needed == bits {
+                break values;
+            }
+            bits = needed;
+        };
+
+        let mut coded = BitVec::new();
+        let mut previous = 0u64;
+        for value in values.iter() {
+            let delta = value - previous;
+            for _ in 0..(delta >> GCS_P) {
+                coded.push(true);
+            }
+            coded.push(false);
+            for i in (0..GCS_P).rev() {
+                coded.push((delta >> i) & 1 == 1);
+            }
+            previous = *value;
+        }
+
+        let mut result = Vec::with_capacity(HEADER_LEN + coded.len() / 8 + 1);
+        result.push(GCS_P);
+        result.push(bits);
+        result.extend_from_slice(&(values.len() as u32).to_be_bytes());
+        result.extend_from_slice(&coded.to_bytes());
+        result
+    }
+}
+
+impl TryFrom<&[u8]> for GolombSet {
+    type Error = &'static str;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        const TRUNCATED: &str = "Invalid GCS filter, the coded values are truncated";
+
+        if value.len() < HEADER_LEN {
+            return Err("Invalid length, GCS filter header is expected to be 6-bytes");
+        }
+        let p = value[0];
+        let bits = value[1];
+        if p >= 64 || bits > 64 {
+            return Err("Invalid GCS filter parameters");
+        }
+        let mut count = [0u8; 4];
+        count.copy_from_slice(&value[2..HEADER_LEN]);
+        let count = u32::from_be_bytes(count) as usize;
+        let coded = BitVec::from_bytes(&value[HEADER_LEN..]);
+        // every value takes at least `p + 1` bits
+        if count > coded.len() / (usize::from(p) + 1) {
+            return Err(TRUNCATED);
+        }
+
+        let mut hashes = Vec::with_capacity(count);
+        let mut position = 0;
+        let mut next_bit = || {
+            let bit = coded.get(position).ok_or(TRUNCATED);
+            position += 1;
+            bit
+        };
+        let mut previous = 0u64;
+        for i in 0..count {
+            let mut quotient = 0u64;
+            while next_bit()? {
+                quotient += 1;
+            }
+            let mut delta = quotient
+                .checked_mul(1 << p)
+                .ok_or("Invalid GCS filter, value out of range")?;
+            for j in (0..p).rev() {
+                if next_bit()? {
+                    delta |= 1 << j;
+                }
+            }
+            if i > 0 && delta == 0 {
+                return Err("Invalid GCS filter, values are expected to be sorted and unique");
+            }
+            let value = previous
+                .checked_add(delta)
+                .filter(|value| bits == 64 || *value >> bits == 0)
+                .ok_or("Invalid GCS filter, value out of range")?;
+            hashes.push(if bits == 0 {
+                0
+            } else {
+                value << (64 - u32::from(bits))
+            });
+            previous = value;
+        }
+        // only the zero padding of the last byte may follow
+        if (position..coded.len()).any(|i| coded[i]) || coded.len() - position >= 8 {
+            return Err("Invalid GCS filter, unexpected data after the coded values");
+        }
+
+        Ok(GolombSet {
+            hashes,
+            precision: bits,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck::quickcheck;
+
+    fn items(count: u32) -> Vec<Vec<u8>> {
+        (0..count).map(|i| i.to_be_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_gcs() {
+        let mut set = GolombSet::default();
+        for item in items(100).iter() {
+            set.insert(item);
+        }
+        assert_eq!(set.len(), 100);
+
+        let decoded = GolombSet::try_from(set.data().as_slice()).unwrap();
+        for item in items(100).iter() {
+            assert!(set.check(item));
+            assert!(decoded.check(item));
+        }
+        // the false positive rate is about 100 * 2^-P
+        let false_positives = (100..100_100u32)
+            .filter(|i| decoded.check(&i.to_be_bytes()))
+            .count();
+        assert!(false_positives < 10, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn empty_set_should_roundtrip() {
+        let set = GolombSet::default();
+        let data = set.data();
+        assert_eq!(data.len(), HEADER_LEN);
+        let decoded = GolombSet::try_from(data.as_slice()).unwrap();
+        assert!(decoded.is_empty());
+        assert_eq!(decoded.data(), data);
+    }
+
+    #[test]
+    fn joined_sets_should_contain_items_of_both() {
+        let mut set1 = GolombSet::default();
+        set1.insert(b"a");
+        let mut set2 = GolombSet::default();
+        set2.insert(b"b");
+        let mut decoded = GolombSet::try_from(set2.data().as_slice()).unwrap();
+        decoded.add(&set1);
+        assert!(decoded.check(b"a"));
+        assert!(decoded.check(b"b"));
+        assert_eq!(decoded.len(), 2);
+    }
+
+    #[test]
+    fn invalid_data_should_be_rejected() {
+        assert!(GolombSet::try_from(&[GCS_P, 20][..]).is_err());
+        // more values than the data can hold
+        assert!(GolombSet::try_from(&[GCS_P, 20, 0, 0, 0, 2, 0, 0][..]).is_err());
+        // trailing data
+        let mut data = GolombSet::default().data();
+        data.push(0);
+        assert!(GolombSet::try_from(data.as_slice()).is_err());
+    }
+
+    quickcheck! {
+
+        // encoding is canonical and keeps all the items
+        fn gcs_roundtrip(items: Vec<Vec<u8>>) -> bool {
+            let mut set = GolombSet::default();
+            for item in items.iter() {
+                set.insert(item);
+            }
+            let data = set.data();
+            match GolombSet::try_from(data.as_slice()) {
+                Ok(decoded) => {
+                    decoded.data() == data && items.iter().all(|item| decoded.check(item))
+                }
+                Err(_) => false,
+            }
+        }
+
+    }
+}
@@ -1,79 +1,34 @@
+//! Block-level filters of the view keys (and IDs) of transactions, which are published in the
+//! `ethbloom` / `gcsfilter` tendermint events of each block.
+//!
+//! The filters are built and decoded with the default `full` feature. With the `check-only`
+//! feature alone (`default-features = false`), only `RawFilter` is built: the checks of the
+//! `ethbloom` payload with the serialized view keys, without std and secp256k1 (e.g. for wasm).
+#![cfg_attr(not(feature = "full"), no_std)]
+
+#[cfg(feature = "full")]
+mod block_filter;
+#[cfg(feature = "full")]
 mod filter;
-use chain_core::common::TendermintEventKey;
-use filter::Bloom;
-use filter::H2048;
-use secp256k1::key::PublicKey;
-use std::convert::TryFrom;
-use std::prelude::v1::Vec;
-
-/// Probabilistic fixed-size filter wrapper
-#[derive(Default, Debug)]
-pub struct BlockFilter {
-    // may be replaced with GCS, e.g. https://github.com/dac-gmbh/golomb-set
-    bloom: Bloom,
-    modified: bool,
-}
-
-impl BlockFilter {
-    /// resets the filter
-    pub fn reset(&mut self) {
-        self.modified = false;
-        self.bloom.reset();
-    }
-
-    /// joins with another filter
-    pub fn add_filter(&mut self, other: &BlockFilter) {
-        self.modified = true;
-        self.bloom.add(&other.bloom);
-    }
-
-    /// adds a view key to the filter
-    pub fn add_view_key(&mut self, view_key: &PublicKey) {
-        self.modified = true;
-        self.bloom.set(&view_key.serialize()[..]);
-    }
-
-    /// gets a Key-Value payload for tendermint events
-    pub fn get_tendermint_kv(&self) -> (Vec<u8>, Vec<u8>) {
-        (TendermintEventKey::EthBloom.into(), self.bloom.data())
-    }
-
-    /// tests if a view key is in the filter
-    /// true = maybe present
-    /// false = not present
-    pub fn check_view_key(&self, view_key: &PublicKey) -> bool {
-        self.bloom.check(&view_key.serialize())
-    }
-
-    /// check if view keys were added since its creation
-    pub fn is_modified(&self) -> bool {
-        self.modified
-    }
-
-    /// gets raw filter data
-    pub fn get_raw(&self) -> H2048 {
-        self.bloom.raw_data()
-    }
-}
-
-impl TryFrom<&[u8]> for BlockFilter {
-    type Error = &'static str;
-
-    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        let bloom = Bloom::try_from(value)?;
-        Ok(BlockFilter {
-            bloom,
-            modified: false,
-        })
-    }
-}
-
-impl From<&H2048> for BlockFilter {
-    fn from(val: &H2048) -> BlockFilter {
-        let bloom = Bloom::from(val);
-        BlockFilter {
-            bloom,
-            modified: false,
-        }
-    }
-}
+#[cfg(feature = "check-only")]
+mod format;
+#[cfg(feature = "full")]
+mod gcs;
+#[cfg(feature = "full")]
+mod range;
+#[cfg(feature = "check-only")]
+mod raw;
+
+#[cfg(feature = "full")]
+pub use block_filter::*;
+#[cfg(feature = "check-only")]
+pub use format::{
+    BLOCK_FILTER_VERSION, DEFAULT_BLOOM_HASHES, DEFAULT_BLOOM_SIZE, MAX_BLOOM_HASHES,
+    MAX_BLOOM_SIZE,
+};
+#[cfg(feature = "full")]
+pub use gcs::GCS_P;
+#[cfg(feature = "full")]
+pub use range::{FilterRange, FILTER_RANGE_BLOCKS, FILTER_RANGE_PATH};
+#[cfg(feature = "check-only")]
+pub use raw::RawFilter;
@@ -0,0 +1,220 @@
+//! Filters rolled up from the block filters of the ranges of `FILTER_RANGE_BLOCKS` blocks,
+//! so that the syncing wallets can skip the whole ranges which don't concern any of their
+//! view keys instead of checking the filter of each block.
+use std::convert::TryFrom;
+
+use chain_core::state::tendermint::BlockHeight;
+use parity_scale_codec::{Decode, Encode, Error, Input, Output};
+use secp256k1::key::PublicKey;
+
+use crate::BlockFilter;
+
+/// Number of blocks in a filter range: the range `i` is from the block `i * FILTER_RANGE_BLOCKS + 1`
+/// to the block `(i + 1) * FILTER_RANGE_BLOCKS`
+pub const FILTER_RANGE_BLOCKS: u64 = 1000;
+
+/// ABCI query path of the filter range of a block (the block height is passed as the query height,
+/// 0 for the latest committed block)
+pub const FILTER_RANGE_PATH: &str = "filterrange";
+
+/// Union of the block filters from `start_height` to `end_height` (inclusive), which are in the same
+/// filter range; as the block filters are merged, it has their dimensions (256 bytes, 3 hashes)
+#[derive(Debug)]
+pub struct FilterRange {
+    pub start_height: BlockHeight,
+    pub end_height: BlockHeight,
+    pub filter: BlockFilter,
+}
+
+impl FilterRange {
+    /// index of the filter range of the block at `height`
+    pub fn index_of(height: BlockHeight) -> u64 {
+        height.value().saturating_sub(1) / FILTER_RANGE_BLOCKS
+    }
+
+    /// filter range without any block yet, which starts at `start_height` (the first block of
+    /// its range, or a later one if the previous blocks weren't rolled up)
+    pub fn empty(start_height: BlockHeight) -> Self {
+        FilterRange {
+            start_height,
+            end_height: start_height.saturating_sub(1),
+            filter: BlockFilter::default(),
+        }
+    }
+
+    /// index of the filter range
+    pub fn index(&self) -> u64 {
+        Self::index_of(self.start_height)
+    }
+
+    /// whether no block was rolled up
+    pub fn is_empty(&self) -> bool {
+        self.end_height < self.start_height
+    }
+
+    /// whether the block at `height` was rolled up
+    pub fn contains(&self, height: BlockHeight) -> bool {
+        self.start_height <= height && height <= self.end_height
+    }
+
+    /// whether all the blocks of the range were rolled up
+    pub fn is_complete(&self) -> bool {
+        self.start_height.value() == self.index() * FILTER_RANGE_BLOCKS + 1
+            && self.end_height.value() == (self.index() + 1) * FILTER_RANGE_BLOCKS
+    }
+
+    /// rolls up the next block (`filter` is `None` if the block has no filter); it fails if the block
+    /// doesn't follow the last one or is in another range, or if its filter has other dimensions
+    pub fn push_block(
+        &mut self,
+        height: BlockHeight,
+        filter: Option<&BlockFilter>,
+    ) -> Result<(), &'static str> {
+        if self.end_height.checked_add(1) != Some(height) {
+            return Err("the block doesn't follow the last block of the filter range");
+        }
+        if Self::index_of(height) != self.index() {
+            return Err("the block is outside of the filter range");
+        }
+        if let Some(filter) = filter {
+            self.filter.merge(filter)?;
+        }
+        self.end_height = height;
+        Ok(())
+    }
+
+    /// tests if any of the view keys may be in the filters of the blocks
+    /// true = maybe present
+    /// false = not present in any block of the range
+    pub fn matches_any(&self, keys: &[PublicKey]) -> bool {
+        keys.iter().any(|key| self.filter.check_view_key(key))
+    }
+}
+
+/// the block filter is encoded as its versioned tendermint event payload
+impl Encode for FilterRange {
+    fn encode_to<W: Output>(&self, dest: &mut W) {
+        self.start_height.encode_to(dest);
+        self.end_height.encode_to(dest);
+        self.filter.get_tendermint_kv_versioned().1.encode_to(dest);
+    }
+}
+
+impl Decode for FilterRange {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let start_height = BlockHeight::decode(input)?;
+        let end_height = BlockHeight::decode(input)?;
+        let filter = BlockFilter::try_from(Vec::<u8>::decode(input)?.as_slice())?;
+        let range = FilterRange {
+            start_height,
+            end_height,
+            filter,
+        };
+        if start_height == BlockHeight::genesis()
+            || (!range.is_empty() && FilterRange::index_of(end_height) != range.index())
+        {
+            return Err("Invalid filter range heights".into());
+        }
+        Ok(range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::key::SecretKey;
+
+    fn view_key(seed: u8) -> PublicKey {
+        let secret = SecretKey::from_slice(&[seed; 32]).unwrap();
+        PublicKey::from_secret_key(secp256k1::SECP256K1, &secret)
+    }
+
+    fn block_filter(seed: u8) -> BlockFilter {
+        let mut filter = BlockFilter::default();
+        filter.add_view_key(&view_key(seed));
+        filter
+    }
+
+    #[test]
+    fn blocks_should_be_rolled_up_in_their_range() {
+        let mut range = FilterRange::empty(BlockHeight::new(1));
+        assert!(range.is_empty());
+        assert!(!range.matches_any(&[view_key(1)]));
+        for height in 1..=FILTER_RANGE_BLOCKS {
+            let filter = match height {
+                10 => Some(block_filter(1)),
+                500 => Some(block_filter(2)),
+                _ => None,
+            };
+            range
+                .push_block(BlockHeight::new(height), filter.as_ref())
+                .unwrap();
+        }
+        assert!(range.is_complete());
+        assert!(range.contains(BlockHeight::new(FILTER_RANGE_BLOCKS)));
+        assert!(range.matches_any(&[view_key(3), view_key(1)]));
+        assert!(range.matches_any(&[view_key(2)]));
+        assert!(!range.matches_any(&[view_key(3), view_key(4)]));
+        assert!(!range.matches_any(&[]));
+
+        // the next block is in the next range
+        let next = BlockHeight::new(FILTER_RANGE_BLOCKS + 1);
+        assert!(range.push_block(next, None).is_err());
+        assert_eq!(1, FilterRange::index_of(next));
+        assert_eq!(
+            0,
+            FilterRange::index_of(BlockHeight::new(FILTER_RANGE_BLOCKS))
+        );
+    }
+
+    #[test]
+    fn only_consecutive_blocks_should_be_rolled_up() {
+        // a range rolled up from a block in the middle isn't complete
+        let mut range = FilterRange::empty(BlockHeight::new(1500));
+        assert_eq!(1, range.index());
+        assert!(range.push_block(BlockHeight::new(1501), None).is_err());
+        range.push_block(BlockHeight::new(1500), None).unwrap();
+        assert!(range.push_block(BlockHeight::new(1500), None).is_err());
+        assert!(range.push_block(BlockHeight::new(1502), None).is_err());
+        for height in 1501..=2000 {
+            range.push_block(BlockHeight::new(height), None).unwrap();
+        }
+        assert!(!range.is_complete());
+        assert!(!range.contains(BlockHeight::new(1499)));
+
+        let mut sized = BlockFilter::with_params(1024, 3).unwrap();
+        sized.add_view_key(&view_key(1));
+        let mut range = FilterRange::empty(BlockHeight::new(1));
+        assert!(range.push_block(BlockHeight::new(1), Some(&sized)).is_err());
+        assert!(range.is_empty());
+    }
+
+    #[test]
+    fn filter_range_should_roundtrip() {
+        let mut range = FilterRange::empty(BlockHeight::new(2001));
+        range
+            .push_block(BlockHeight::new(2001), Some(&block_filter(1)))
+            .unwrap();
+        range.push_block(BlockHeight::new(2002), None).unwrap();
+
+        let decoded = FilterRange::decode(&mut range.encode().as_slice()).unwrap();
+        assert_eq!(
+            (range.start_height, range.end_height),
+            (decoded.start_height, decoded.end_height)
+        );
+        assert!(decoded.matches_any(&[view_key(1)]));
+        assert!(!decoded.matches_any(&[view_key(2)]));
+        assert_eq!(range.encode(), decoded.encode());
+
+        let empty = FilterRange::empty(BlockHeight::new(1));
+        let decoded = FilterRange::decode(&mut empty.encode().as_slice()).unwrap();
+        assert!(decoded.is_empty());
+
+        // the heights should be in the same range
+        let invalid = FilterRange {
+            end_height: BlockHeight::new(3001),
+            ..FilterRange::empty(BlockHeight::new(2001))
+        };
+        assert!(FilterRange::decode(&mut invalid.encode().as_slice()).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+//! Checks of a block filter borrowed from the payload of its `ethbloom` event, without decoding it
+//! into a `BlockFilter`: they don't allocate and the view keys are passed serialized (no
+//! secp256k1), so they are available without the default features (`no_std`), e.g. for the
+//! light clients compiled to wasm.
+use core::convert::TryFrom;
+
+use crate::format::{bit_positions, split_filter, strip_version};
+
+/// Block filter borrowed from the (legacy or versioned) payload of the `ethbloom` event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawFilter<'a> {
+    bits: &'a [u8],
+    num_hashes: u8,
+}
+
+impl<'a> RawFilter<'a> {
+    /// size of the filter in bytes
+    pub fn size_bytes(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// number of bits set per view key
+    pub fn num_hashes(&self) -> u8 {
+        self.num_hashes
+    }
+
+    /// tests if a view key (its 33-byte compressed serialization) is in the filter, as
+    /// `BlockFilter::check_view_key` does; the raw bytes of the other items (32-byte transaction
+    /// IDs, 20-byte staked state addresses) can be tested as well
+    /// true = maybe present
+    /// false = not present
+    pub fn check_raw(&self, bytes: &[u8]) -> bool {
+        bit_positions(bytes, self.bits.len() * 8, self.num_hashes)
+            .all(|m| self.bits[m / 8] & (1 << (m % 8)) != 0)
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for RawFilter<'a> {
+    type Error = &'static str;
+
+    /// Accepts the same payloads as `BlockFilter::try_from`
+    fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
+        let (num_hashes, bits) = split_filter(strip_version(value)?)?;
+        Ok(RawFilter { bits, num_hashes })
+    }
+}
+
+#[cfg(all(test, feature = "full"))]
+mod tests {
+    use super::*;
+    use crate::BlockFilter;
+    use quickcheck::quickcheck;
+    use secp256k1::key::{PublicKey, SecretKey};
+
+    fn view_key(seed: u8) -> PublicKey {
+        let secret = SecretKey::from_slice(&[seed; 32]).unwrap();
+        PublicKey::from_secret_key(secp256k1::SECP256K1, &secret)
+    }
+
+    #[test]
+    fn raw_checks_should_match_view_key_checks() {
+        for (size, num_hashes) in [(256, 3), (256, 1), (1024, 5), (8192, 16)].iter() {
+            let mut filter = BlockFilter::with_params(*size, *num_hashes).unwrap();
+            for seed in 1..=40 {
+                filter.add_view_key(&view_key(seed));
+            }
+            let payloads = [
+                filter.get_tendermint_kv().1,
+                filter.get_tendermint_kv_versioned().1,
+            ];
+            for payload in payloads.iter() {
+                let raw = RawFilter::try_from(payload.as_slice()).unwrap();
+                assert_eq!((*size, *num_hashes), (raw.size_bytes(), raw.num_hashes()));
+                let mut absent = 0;
+                for seed in 1..=255 {
+                    let view_key = view_key(seed);
+                    let present = filter.check_view_key(&view_key);
+                    assert_eq!(present, raw.check_raw(&view_key.serialize()));
+                    absent += usize::from(!present);
+                }
+                assert!(absent > 0);
+            }
+        }
+    }
+
+    quickcheck! {
+        // the same payloads are accepted (with the same dimensions)
+        fn raw_filter_should_decode_as_block_filter(payload: Vec<u8>) -> bool {
+            let raw = RawFilter::try_from(payload.as_slice());
+            match (raw, BlockFilter::try_from(payload.as_slice())) {
+                (Ok(raw), Ok(filter)) => {
+                    (raw.size_bytes(), raw.num_hashes()) == (filter.size_bytes(), filter.num_hashes())
+                }
+                (Err(raw_err), Err(err)) => raw_err == err,
+                _ => false,
+            }
+        }
+
+        // the same bits are checked, whichever bits are set
+        fn raw_checks_should_match_on_any_bits(bits: Vec<u8>, tx_id: Vec<u8>) -> bool {
+            // 1024 bytes, 5 hashes
+            let mut payload = vec![5, 10];
+            payload.extend(bits.iter().cycle().take(1024));
+            payload.resize(1026, 0xff);
+            let mut id = [0u8; 32];
+            id.iter_mut().zip(tx_id.iter()).for_each(|(d, s)| *d = *s);
+
+            let raw = RawFilter::try_from(payload.as_slice()).unwrap();
+            let filter = BlockFilter::try_from(payload.as_slice()).unwrap();
+            raw.check_raw(&id) == filter.check_tx_id(&id)
+        }
+    }
+}
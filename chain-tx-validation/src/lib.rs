@@ -73,6 +73,8 @@ pub enum Error {
     AccountIncorrectNonce,
     /// Account is jailed
     AccountJailed,
+    /// unbonding would leave less than the minimum bond remainder bonded (without unbonding everything)
+    UnbondRemainderTooSmall,
 }
 
 impl fmt::Display for Error {
@@ -122,6 +124,10 @@ impl fmt::Display for Error {
             AccountIncorrectNonce => write!(f, "incorrect transaction count for account operation"),
             MismatchAccountAddress => write!(f, "mismatch account address"),
             AccountJailed => write!(f, "account is jailed"),
+            UnbondRemainderTooSmall => write!(
+                f,
+                "remaining bonded amount is below the minimum bond remainder"
+            ),
         }
     }
 }
@@ -367,3 +373,18 @@ pub fn verify_unjailed(account: &StakedState) -> Result<(), Error> {
         Ok(())
     }
 }
+
+/// Verifies that unbonding `amount` (value and fee) leaves either nothing or at least
+/// `minimum_bond_remainder` bonded in the account
+pub fn verify_unbond_remainder(
+    account: &StakedState,
+    amount: Coin,
+    minimum_bond_remainder: Coin,
+) -> Result<(), Error> {
+    let remaining = (account.bonded - amount).map_err(|_| Error::InvalidSum)?;
+    if remaining != Coin::zero() && remaining < minimum_bond_remainder {
+        Err(Error::UnbondRemainderTooSmall)
+    } else {
+        Ok(())
+    }
+}
@@ -7,7 +7,14 @@ cargo install cargo-fuzz
 export NETWORK_ID="ab"
 export MRSIGNER="0000000000000000000000000000000000000000000000000000000000000000"
 export TQE_MRENCLAVE="0000000000000000000000000000000000000000000000000000000000000000"
+# seed corpora from the valid inputs of the seeded chain
+(cd fuzz && cargo run --example generate_corpus corpus)
 cargo fuzz run abci-cycle -- -runs=0
+for target in tx-process abci-query begin-block; do
+  cargo fuzz run $target fuzz/corpus/$target -- -runs=0
+done
 wget -q -O fuzzit https://github.com/fuzzitdev/fuzzit/releases/download/v2.4.77/fuzzit_Linux_x86_64
 chmod a+x fuzzit
-./fuzzit create job --type fuzzing abci-cycle ./fuzz/target/x86_64-unknown-linux-gnu/release/abci-cycle
\ No newline at end of file
+for target in abci-cycle tx-process abci-query begin-block; do
+  ./fuzzit create job --type fuzzing $target ./fuzz/target/x86_64-unknown-linux-gnu/release/$target
+done
@@ -22,9 +22,10 @@ use client_common::storage::SledStorage;
 #[cfg(not(feature = "mock-enclave"))]
 use client_common::tendermint::types::AbciQueryExt;
 use client_common::tendermint::types::GenesisExt;
-use client_common::tendermint::{Client, WebsocketRpcClient};
+use client_common::tendermint::{Client, FailoverClient, WebsocketRpcClient};
 use client_common::TransactionObfuscation;
 use client_common::{ErrorKind, Result, ResultExt, SecKey, Storage};
+use client_core::coin_selection::BranchAndBound;
 use client_core::signer::WalletSignerManager;
 use client_core::transaction_builder::DefaultWalletTransactionBuilder;
 use client_core::types::BalanceChange;
@@ -32,7 +33,7 @@ use client_core::wallet::syncer::{
     spawn_light_client_supervisor, Handle, ObfuscationSyncerConfig, ProgressReport, SyncerOptions,
     WalletSyncer,
 };
-use client_core::wallet::{DefaultWalletClient, WalletClient};
+use client_core::wallet::{DefaultWalletClient, WalletClient, WalletLockMap};
 use client_network::network_ops::{DefaultNetworkOpsClient, NetworkOpsClient};
 
 use self::address_command::AddressCommand;
@@ -40,7 +41,7 @@ use self::multisig_command::MultiSigCommand;
 use self::transaction_command::TransactionCommand;
 use self::wallet_command::WalletCommand;
 use crate::logo::{get_jok, get_logo};
-use crate::{ask_seckey, storage_path, tendermint_url};
+use crate::{ask_seckey, storage_path, tendermint_archive_url, tendermint_url};
 use chain_core::tx::fee::LinearFee;
 use client_core::hd_wallet::HardwareKind;
 #[cfg(feature = "mock-hardware-wallet")]
@@ -89,6 +90,7 @@ ENVIRONMENT VARIABLES:
     CRYPTO_CHAIN_ID                 Chain ID of Thaler Experimental Network
     CRYPTO_CLIENT_STORAGE           Storage directory (Default: `.storage`)
     CRYPTO_CLIENT_TENDERMINT        Websocket endpoint for tendermint (Default: `ws://localhost:26657/websocket`)
+    CRYPTO_CLIENT_TENDERMINT_ARCHIVE    Websocket endpoint of a tendermint archive node, from which the blocks pruned by the node are synced (Optional)
     CRYPTO_GENESIS_FINGERPRINT             Set the genesis fingerprint(Optional)
 "#
 )]
@@ -356,6 +358,7 @@ impl Command {
                     signer_manager.clone(),
                     fee_algorithm,
                     transaction_obfuscation.clone(),
+                    BranchAndBound::default(),
                 );
 
                 let wallet_client = DefaultWalletClient::new(
@@ -404,6 +407,7 @@ impl Command {
                     signer_manager.clone(),
                     fee_algorithm,
                     transaction_obfuscation.clone(),
+                    BranchAndBound::default(),
                 );
                 let wallet_client = DefaultWalletClient::new(
                     storage,
@@ -437,6 +441,9 @@ impl Command {
             } => {
                 let rpc_url = tendermint_url();
                 let tendermint_client = WebsocketRpcClient::new(&rpc_url)?;
+                let archive_client = tendermint_archive_url()
+                    .map(|url| WebsocketRpcClient::new(&url))
+                    .transpose()?;
                 let tx_obfuscation = get_tx_query(tendermint_client.clone())?;
                 let db_path = storage_path();
                 let storage = SledStorage::new(&db_path)?;
@@ -566,7 +573,8 @@ impl Command {
 
                 let config = ObfuscationSyncerConfig::new(
                     storage.clone(),
-                    tendermint_client,
+                    // the blocks pruned by the node are synced from the archive node (if any)
+                    FailoverClient::with_archive(tendermint_client, archive_client),
                     tx_obfuscation,
                     SyncerOptions {
                         enable_fast_forward: *enable_fast_forward,
@@ -737,6 +745,10 @@ impl Command {
                     Default::default(),
                 ),
             ]),
+            Row::new(vec![
+                Cell::new("Locked", Default::default()),
+                Cell::new(format!("{}", balance.locked).as_str(), Default::default()),
+            ]),
         ];
 
         let table = Table::new(rows, Default::default())
@@ -831,7 +843,7 @@ impl Command {
         force: bool,
         storage: SledStorage,
     ) -> Result<()> {
-        let wallet_client = get_wallet_client(storage)?;
+        let wallet_client = get_wallet_client(storage, config.wallet_locks.clone())?;
 
         let mut init_block_height = 0;
         let mut final_block_height = 0;
@@ -862,6 +874,37 @@ impl Command {
                         }
                     }
                 }
+                ProgressReport::ReorgDetected {
+                    block_height,
+                    common_block_height,
+                    ..
+                } => {
+                    println!(
+                        "\nBlock {} is no longer on the chain, rolled back to block {}",
+                        block_height, common_block_height
+                    );
+                    init_block_height = common_block_height;
+                    if let Some(ref mut pb) = progress_bar {
+                        pb.total = final_block_height - common_block_height;
+                        pb.set(0);
+                    }
+                }
+                ProgressReport::BlocksPruned {
+                    from_block_height,
+                    earliest_available,
+                    ..
+                } => {
+                    println!(
+                        "\nWarning! Blocks {} to {} were pruned by the node, transactions in them are skipped (set CRYPTO_CLIENT_TENDERMINT_ARCHIVE to an archive node to sync them)",
+                        from_block_height,
+                        earliest_available - 1
+                    );
+                    init_block_height = earliest_available - 1;
+                    if let Some(ref mut pb) = progress_bar {
+                        pb.total = final_block_height - init_block_height;
+                        pb.set(0);
+                    }
+                }
             };
             true
         };
@@ -880,18 +923,20 @@ fn print_sync_warning() {
     println!();
 }
 
-fn get_wallet_client(storage: SledStorage) -> Result<AppWalletClient> {
+fn get_wallet_client(storage: SledStorage, wallet_locks: WalletLockMap) -> Result<AppWalletClient> {
     let tendermint_client = WebsocketRpcClient::new(&tendermint_url())?;
 
     let hw_key_service = HwKeyService::default();
 
-    let signer_manager = WalletSignerManager::new(storage.clone(), hw_key_service.clone());
+    let signer_manager = WalletSignerManager::new(storage.clone(), hw_key_service.clone())
+        .with_wallet_locks(wallet_locks.clone());
     let fee_algorithm = tendermint_client.genesis()?.fee_policy();
     let transaction_obfuscation = get_tx_query(tendermint_client.clone())?;
     let transaction_builder = DefaultWalletTransactionBuilder::new(
         signer_manager,
         fee_algorithm,
         transaction_obfuscation,
+        BranchAndBound::default(),
     );
 
     let wallet_client = DefaultWalletClient::new(
@@ -900,6 +945,7 @@ fn get_wallet_client(storage: SledStorage) -> Result<AppWalletClient> {
         transaction_builder,
         None,
         hw_key_service,
-    );
+    )
+    .with_wallet_locks(wallet_locks);
     Ok(wallet_client)
 }
@@ -13,14 +13,13 @@ use chain_core::state::account::{
     ConfidentialInit, CouncilNodeMeta, MLSInit, StakedStateAddress, StakedStateOpAttributes,
 };
 use chain_core::state::tendermint::TendermintValidatorPubKey;
-use chain_core::tx::data::access::{TxAccess, TxAccessPolicy};
 use chain_core::tx::data::address::ExtendedAddr;
 use chain_core::tx::data::attribute::TxAttributes;
 use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::data::output::TxOut;
 use chain_core::tx::TxAux;
 use client_common::{Error, ErrorKind, PublicKey, Result, ResultExt, SecKey, Transaction};
-use client_core::transaction_builder::SignedTransferTransaction;
+use client_core::transaction_builder::{FeeTip, SignedTransferTransaction};
 use client_core::types::{BalanceChange, TransactionPending};
 use client_core::WalletClient;
 use client_network::NetworkOpsClient;
@@ -335,8 +334,8 @@ impl TransactionCommand {
                 let tx_signed = std::fs::read_to_string(file)
                     .chain(|| (ErrorKind::IoError, "Unable to read from file"))?;
                 let signed = SignedTransferTransaction::from_str(&tx_signed)?;
-                let tx_id = wallet_client.broadcast_signed_transfer_tx(name, &enckey, signed)?;
-                success(hex::encode(tx_id).as_str());
+                let result = wallet_client.broadcast_signed_transfer_tx(name, &enckey, signed)?;
+                success(hex::encode(result.tx_id).as_str());
                 Ok(())
             }
         }
@@ -554,19 +553,14 @@ fn new_transaction<T: WalletClient, N: NetworkOpsClient>(
         TransactionType::Transfer => {
             let (tx_aux, tx_pending) = new_transfer_transaction(wallet_client, name, &enckey)?;
             wallet_client.broadcast_transaction(&tx_aux)?;
-            wallet_client.update_tx_pending_state(&name, &enckey, tx_aux.tx_id(), tx_pending)?;
+            wallet_client.update_tx_pending_state(&name, &enckey, &tx_aux, tx_pending)?;
         }
         TransactionType::Deposit => {
             if advanced {
                 let (tx_aux, tx_pending) =
                     new_deposit_transaction(wallet_client, network_ops_client, name, &enckey)?;
                 wallet_client.broadcast_transaction(&tx_aux)?;
-                wallet_client.update_tx_pending_state(
-                    &name,
-                    &enckey,
-                    tx_aux.tx_id(),
-                    tx_pending,
-                )?;
+                wallet_client.update_tx_pending_state(&name, &enckey, &tx_aux, tx_pending)?;
             } else {
                 new_deposit_amount_transaction(wallet_client, network_ops_client, name, &enckey)?;
             }
@@ -579,7 +573,7 @@ fn new_transaction<T: WalletClient, N: NetworkOpsClient>(
             let (tx_aux, tx_pending) =
                 new_withdraw_transaction(wallet_client, network_ops_client, name, &enckey)?;
             wallet_client.broadcast_transaction(&tx_aux)?;
-            wallet_client.update_tx_pending_state(&name, &enckey, tx_aux.tx_id(), tx_pending)?;
+            wallet_client.update_tx_pending_state(&name, &enckey, &tx_aux, tx_pending)?;
         }
         TransactionType::Unjail => {
             let tx_aux = new_unjail_transaction(network_ops_client, name, &enckey)?;
@@ -608,16 +602,10 @@ fn new_withdraw_transaction<T: WalletClient, N: NetworkOpsClient>(
     let self_view_key = wallet_client.view_key(name, enckey)?;
     view_keys.push(self_view_key);
 
-    let access_policies: BTreeSet<_> = view_keys
-        .iter()
-        .map(|key| TxAccessPolicy {
-            view_key: key.into(),
-            access: TxAccess::AllData,
-        })
-        .collect();
-
-    let attributes =
-        TxAttributes::new_with_access(get_network_id(), access_policies.into_iter().collect());
+    let attributes = TxAttributes::builder(get_network_id())
+        .allow_view_many(view_keys.iter().map(Into::into))
+        .build()
+        .chain(|| (ErrorKind::InvalidInput, "Invalid transaction attributes"))?;
 
     network_ops_client.create_withdraw_all_unbonded_stake_transaction(
         name,
@@ -753,14 +741,16 @@ fn new_deposit_amount_transaction<T: WalletClient, N: NetworkOpsClient>(
         total_amount, fee
     ));
     let to_transfer_address = wallet_client.new_transfer_address(name, enckey)?;
-    let tx_id = wallet_client.send_to_address_commit(
-        name,
-        enckey,
-        total_amount,
-        to_transfer_address,
-        &mut BTreeSet::new(),
-        get_network_id(),
-    )?;
+    let tx_id = wallet_client
+        .send_to_address_commit(
+            name,
+            enckey,
+            total_amount,
+            to_transfer_address,
+            &mut BTreeSet::new(),
+            get_network_id(),
+        )?
+        .tx_id;
 
     success("broadcast transfer transaction");
     success("create deposit transaction");
@@ -799,7 +789,7 @@ fn new_deposit_amount_transaction<T: WalletClient, N: NetworkOpsClient>(
         hex::encode(tx_id)
     ));
     wallet_client.broadcast_transaction(&transaction)?;
-    wallet_client.update_tx_pending_state(&name, &enckey, transaction.tx_id(), tx_pending)?;
+    wallet_client.update_tx_pending_state(&name, &enckey, &transaction, tx_pending)?;
     Ok(())
 }
 
@@ -812,16 +802,10 @@ fn new_transfer_transaction<T: WalletClient>(
     let mut view_keys = ask_view_keys()?;
     let self_view_key = wallet_client.view_key(name, enckey)?;
     view_keys.push(self_view_key);
-    let access_policies: BTreeSet<_> = view_keys
-        .iter()
-        .map(|key| TxAccessPolicy {
-            view_key: key.into(),
-            access: TxAccess::AllData,
-        })
-        .collect();
-
-    let attributes =
-        TxAttributes::new_with_access(get_network_id(), access_policies.into_iter().collect());
+    let attributes = TxAttributes::builder(get_network_id())
+        .allow_view_many(view_keys.iter().map(Into::into))
+        .build()
+        .chain(|| (ErrorKind::InvalidInput, "Invalid transaction attributes"))?;
 
     let return_address = wallet_client.new_transfer_address(name, &enckey)?;
 
@@ -832,6 +816,8 @@ fn new_transfer_transaction<T: WalletClient>(
         attributes,
         None,
         return_address,
+        false,
+        FeeTip::default(),
     )?;
     let tx_pending = TransactionPending {
         block_height: wallet_client.get_current_block_height()?,
@@ -9,7 +9,7 @@ use secstr::SecUtf8;
 use structopt::StructOpt;
 
 use chain_core::init::{coin::Coin, network::init_chain_id};
-use client_common::{seckey::parse_hex_enckey, Error, ErrorKind, Result, ResultExt, SecKey};
+use client_common::{seckey::parse_hex_enckey, ErrorKind, Result, ResultExt, SecKey};
 
 use crate::command::Command;
 use client_core::hd_wallet::HardwareKind;
@@ -56,6 +56,11 @@ pub(crate) fn tendermint_url() -> String {
         .unwrap_or_else(|_| "ws://localhost:26657/websocket".to_owned())
 }
 
+#[inline]
+pub(crate) fn tendermint_archive_url() -> Option<String> {
+    std::env::var("CRYPTO_CLIENT_TENDERMINT_ARCHIVE").ok()
+}
+
 #[inline]
 pub(crate) fn chain_id() -> Option<String> {
     std::env::var("CRYPTO_CHAIN_ID").map(Some).unwrap_or(None)
@@ -88,42 +93,12 @@ pub(crate) fn ask_hardware_kind(message: Option<&str>) -> Result<HardwareKind> {
 }
 
 pub(crate) fn coin_from_str(coin_str: &str) -> Result<Coin> {
-    if !coin_str.contains('.') {
-        Coin::from_str(&format!("{}00000000", coin_str)).chain(|| {
-            (
-                ErrorKind::DeserializationError,
-                format!("Unable to deserialize coin from value: {}", coin_str),
-            )
-        })
-    } else {
-        let coin_parts = coin_str.split('.').collect::<Vec<&str>>();
-
-        if 2 != coin_parts.len() {
-            return Err(Error::new(
-                ErrorKind::DeserializationError,
-                format!("Too many decimal points in coin: {}", coin_str),
-            ));
-        }
-
-        let mut iter = coin_parts.iter();
-
-        let before_decimal = iter.next().unwrap();
-        let after_decimal = iter.next().unwrap();
-
-        if after_decimal.len() > 8 {
-            return Err(Error::new(
-                ErrorKind::DeserializationError,
-                format!("Too many digits after decimal in coin: {}", coin_str),
-            ));
-        }
-
-        Coin::from_str(&format!("{}{:0<8}", before_decimal, after_decimal)).chain(|| {
-            (
-                ErrorKind::DeserializationError,
-                format!("Unable to deserialize coin from value: {}", coin_str),
-            )
-        })
-    }
+    Coin::from_cro_str(coin_str).chain(|| {
+        (
+            ErrorKind::DeserializationError,
+            format!("Unable to deserialize coin from value: {}", coin_str),
+        )
+    })
 }
 
 #[cfg(test)]
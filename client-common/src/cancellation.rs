@@ -0,0 +1,56 @@
+//! Cooperative cancellation of long-running operations (e.g. wallet synchronization)
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::{Error, ErrorKind, Result};
+
+/// Token which cancels the operations it was passed to. Clones share the cancellation state, so
+/// that the caller can keep a clone and cancel the operation from another thread. Operations
+/// check the token at the boundaries of their steps (e.g. blocks), so cancellation takes effect
+/// after the current step and only completed steps are persisted.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a new (not cancelled) token
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Cancels the operations using this token (or any of its clones)
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns `true` if the token was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Returns an error of kind `ErrorKind::Cancelled` if the token was cancelled
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(Error::new(ErrorKind::Cancelled, "Cancelled by user"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_cancellation_is_shared_by_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+        assert!(clone.check().is_ok());
+
+        token.cancel();
+        assert!(clone.is_cancelled());
+        assert_eq!(ErrorKind::Cancelled, clone.check().unwrap_err().kind());
+        assert!(!CancellationToken::new().is_cancelled());
+    }
+}
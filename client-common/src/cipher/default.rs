@@ -4,13 +4,14 @@ use std::{
     sync::Arc,
 };
 
-use parity_scale_codec::{Decode, Encode};
+use parity_scale_codec::Encode;
 
 use crate::TransactionObfuscation;
 use crate::{
     tendermint::{types::AbciQueryExt, Client},
     Error, ErrorKind, PrivateKey, Result, ResultExt, SignedTransaction, Transaction,
 };
+use chain_core::common::decode_all_strict;
 use chain_core::tx::{data::TxId, TxAux, TxWithOutputs};
 use enclave_macro::{get_mrsigner, get_network_id, get_tqe_mrenclave};
 use enclave_protocol::{
@@ -148,7 +149,7 @@ impl TransactionObfuscation for DefaultTransactionObfuscation {
                     "Unable to read from TQE connection stream",
                 )
             })?;
-            let resp = TxQueryInitResponse::decode(&mut challenge.as_ref());
+            let resp = decode_all_strict::<TxQueryInitResponse>(&challenge);
             let ch = match resp {
                 Ok(TxQueryInitResponse::DecryptChallenge(challenge)) => challenge,
                 _ => {
@@ -180,7 +181,7 @@ impl TransactionObfuscation for DefaultTransactionObfuscation {
             let mut plaintext = Vec::new();
             let result = match tls.read_to_end(&mut plaintext) {
                 Ok(_) => {
-                    let mresp = DecryptionResponse::decode(&mut plaintext.as_slice());
+                    let mresp = decode_all_strict::<DecryptionResponse>(&plaintext);
                     if let Ok(resp) = mresp {
                         let txs = resp.txs;
 
@@ -257,7 +258,7 @@ impl TransactionObfuscation for DefaultTransactionObfuscation {
         let mut plaintext = Vec::new();
         match tls.read_to_end(&mut plaintext) {
             Ok(_) => {
-                let tx = EncryptionResponse::decode(&mut plaintext.as_slice())
+                let tx = decode_all_strict::<EncryptionResponse>(&plaintext)
                     .chain(|| {
                         (
                             ErrorKind::DeserializationError,
@@ -125,6 +125,27 @@ pub enum ErrorKind {
     RunEnclaveError,
     /// Ledger error
     LedgerError,
+    /// Operation cancelled (by user)
+    Cancelled,
+    /// Requested block (or its results) was pruned by the tendermint node
+    BlockPruned {
+        /// Lowest block height still available on the node
+        earliest_available: u64,
+    },
+    /// A connected tendermint node is on another chain than the configured one
+    ChainIdMismatch,
+    /// Transfer between the addresses of a wallet, rejected by its compliance policy
+    SelfTransfer,
+    /// Operation which needs private keys, requested on a watch-only wallet
+    OnlyWatchOnly,
+    /// Entry (e.g. a label) already exists
+    AlreadyExists,
+    /// Multi-sig session does not exist (or has expired)
+    SessionNotFound,
+    /// Synced history of a wallet doesn't match its tamper-evident records (or the node)
+    HistoryMismatch,
+    /// Spendable outputs of a wallet don't cover the amount (and the fee) of a transfer
+    InsufficientBalance,
 }
 
 impl fmt::Display for ErrorKind {
@@ -149,6 +170,19 @@ impl fmt::Display for ErrorKind {
             ErrorKind::VerifyError => write!(f, "Verify error"),
             ErrorKind::RunEnclaveError => write!(f, "Run enclave error"),
             ErrorKind::LedgerError => write!(f, "ledger error"),
+            ErrorKind::Cancelled => write!(f, "Cancelled"),
+            ErrorKind::BlockPruned { earliest_available } => write!(
+                f,
+                "Block pruned (earliest available height: {})",
+                earliest_available
+            ),
+            ErrorKind::ChainIdMismatch => write!(f, "Chain id mismatch"),
+            ErrorKind::SelfTransfer => write!(f, "Self transfer"),
+            ErrorKind::OnlyWatchOnly => write!(f, "Watch-only wallet"),
+            ErrorKind::AlreadyExists => write!(f, "Already exists"),
+            ErrorKind::SessionNotFound => write!(f, "Session not found"),
+            ErrorKind::HistoryMismatch => write!(f, "History mismatch"),
+            ErrorKind::InsufficientBalance => write!(f, "Insufficient balance"),
         }
     }
 }
@@ -3,8 +3,10 @@ use parity_scale_codec::{Decode, Encode, Error, Input, Output};
 use rand::rngs::OsRng;
 use secp256k1::schnorrsig::{schnorr_sign, schnorr_sign_aux, AuxRandNonce, SchnorrSignature};
 use secp256k1::{recovery::RecoverableSignature, Message, PublicKey as SecpPublicKey, SecretKey};
+use secstr::SecUtf8;
 use std::convert::TryInto;
-use zeroize::Zeroize;
+use std::fmt;
+use zeroize::{Zeroize, Zeroizing};
 
 use crate::{ErrorKind, PublicKey, Result, ResultExt};
 
@@ -24,10 +26,17 @@ pub trait PrivateKeyAction: Sync + Send {
     fn public_key(&self) -> Result<PublicKey>;
 }
 
-/// Private key used in Thaler Experimental Network
-#[derive(Debug, PartialEq, Clone)]
+/// Private key used in Thaler Experimental Network. Its bytes are zeroized when it's dropped (so
+/// it should be borrowed rather than cloned) and they're never written by its `Debug`.
+#[derive(PartialEq, Clone)]
 pub struct PrivateKey(SecretKey);
 
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PrivateKey(***SECRET***)")
+    }
+}
+
 impl PrivateKeyAction for PrivateKey {
     fn sign(&self, tx: &Transaction) -> Result<RecoverableSignature> {
         let tx_id = tx.id();
@@ -92,6 +101,11 @@ impl PrivateKey {
         Ok(PrivateKey(secret_key))
     }
 
+    /// Borrows the secret key, e.g. to sign with it without copying it
+    pub fn as_secret_key(&self) -> &SecretKey {
+        &self.0
+    }
+
     /// Serializes current private key
     pub fn serialize(&self) -> Vec<u8> {
         self.0[..].to_vec()
@@ -108,11 +122,28 @@ impl PrivateKey {
 
         Ok(PrivateKey(secret_key))
     }
+
+    /// Deserializes private key from its hex encoding
+    pub fn from_hex(hex_key: &str) -> Result<PrivateKey> {
+        let bytes = Zeroizing::new(hex::decode(hex_key).chain(|| {
+            (
+                ErrorKind::DeserializationError,
+                "Unable to decode hex of secret key",
+            )
+        })?);
+        PrivateKey::deserialize_from(&bytes)
+    }
+
+    /// Serializes current private key to hex
+    pub fn to_hex(&self) -> SecUtf8 {
+        let bytes = Zeroizing::new(self.serialize());
+        SecUtf8::from(hex::encode(&*bytes))
+    }
 }
 
 impl Encode for PrivateKey {
     fn encode_to<W: Output>(&self, dest: &mut W) {
-        self.serialize().encode_to(dest)
+        self.0[..].encode_to(dest)
     }
 
     fn size_hint(&self) -> usize {
@@ -122,7 +153,7 @@ impl Encode for PrivateKey {
 
 impl Decode for PrivateKey {
     fn decode<I: Input>(input: &mut I) -> std::result::Result<Self, Error> {
-        let serialized = <Vec<u8>>::decode(input)?;
+        let serialized = Zeroizing::new(<Vec<u8>>::decode(input)?);
         PrivateKey::deserialize_from(&serialized)
             .map_err(|_| Error::from("Unable to decode private key"))
     }
@@ -158,6 +189,14 @@ impl Drop for PrivateKey {
     }
 }
 
+#[cfg(test)]
+impl PrivateKey {
+    /// Secret bytes held in place, to check that they're wiped
+    fn secret_bytes(&self) -> &[u8] {
+        &self.0[..]
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -196,14 +235,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn check_hex_serialization() {
+        let hex_key = "c553a03604235df8fcd14fc6d1e5b18a219fbcc6e93effcfcf768e2977a74ec2";
+        let private_key = PrivateKey::from_hex(hex_key).unwrap();
+        assert_eq!(hex_key, private_key.to_hex().unsecure());
+        assert_eq!(
+            private_key,
+            PrivateKey::from_hex(&hex_key.to_uppercase()).unwrap()
+        );
+
+        for invalid in &[
+            "not hex",
+            "c553a036",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+        ] {
+            assert_eq!(
+                ErrorKind::DeserializationError,
+                PrivateKey::from_hex(invalid).unwrap_err().kind()
+            );
+        }
+    }
+
     #[test]
     fn check_encoding() {
         let private_key = PrivateKey::new().unwrap();
         let new_private_key = PrivateKey::decode(&mut private_key.encode().as_slice()).unwrap();
+        assert_eq!(private_key.serialize().encode(), private_key.encode());
 
         assert_eq!(
             private_key, new_private_key,
             "Encoding / Decoding is implemented incorrectly"
         );
     }
+
+    #[test]
+    fn check_zeroize() {
+        let mut private_key = PrivateKey::new().unwrap();
+        assert_ne!(&[0u8; 32][..], private_key.secret_bytes());
+
+        // the destructor wipes the bytes the same way
+        private_key.zeroize();
+        assert_eq!(&[0u8; 32][..], private_key.secret_bytes());
+    }
+
+    #[test]
+    fn check_debug_is_redacted() {
+        let hex_key = "c553a03604235df8fcd14fc6d1e5b18a219fbcc6e93effcfcf768e2977a74ec2";
+        let private_key = PrivateKey::from_hex(hex_key).unwrap();
+        assert!(!format!("{:?}", private_key).contains(hex_key));
+        assert!(!format!("{:?}", Some(private_key)).contains("c553a036"));
+    }
 }
@@ -7,14 +7,18 @@
 //! This crate contains all the common types and utilities used by other `client-*` crates.
 mod transaction;
 
+pub mod cancellation;
 pub mod cipher;
 pub mod error;
 pub mod key;
 pub mod multi_sig_address;
+pub mod proof_bundle;
 pub mod seckey;
 pub mod storage;
 pub mod tendermint;
 
+#[doc(inline)]
+pub use crate::cancellation::CancellationToken;
 #[doc(inline)]
 pub use crate::cipher::TransactionObfuscation;
 #[doc(inline)]
@@ -24,8 +28,12 @@ pub use key::{PrivateKey, PrivateKeyAction, PublicKey};
 #[doc(inline)]
 pub use multi_sig_address::MultiSigAddress;
 #[doc(inline)]
+pub use proof_bundle::{verify_proof_bundle, ProofBundle};
+#[doc(inline)]
 pub use seckey::SecKey;
 #[doc(inline)]
-pub use storage::{SecureStorage, Storage};
+pub use storage::{SecureStorage, Storage, StorageBatch};
 #[doc(inline)]
-pub use transaction::{temporary_mls_init, SignedTransaction, Transaction, TransactionInfo};
+pub use transaction::{
+    temporary_mls_init, SignedTransaction, Transaction, TransactionFee, TransactionInfo,
+};
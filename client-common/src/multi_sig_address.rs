@@ -90,7 +90,9 @@ impl From<MultiSigAddress> for ExtendedAddr {
 }
 
 impl MultiSigAddress {
-    /// Create MultiSig address from list of public keys
+    /// Create MultiSig address from list of public keys. The address doesn't depend on the order
+    /// of `public_keys` (the keys of each combination and the combinations are sorted), so that
+    /// co-signers passing them in any order agree on it.
     pub fn new(
         public_keys: Vec<PublicKey>,
         self_public_key: PublicKey,
@@ -352,6 +354,24 @@ mod multi_sig_tests {
             assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
         }
 
+        #[test]
+        fn should_not_depend_on_public_key_order() {
+            let public_keys = (0..3)
+                .map(|_| PublicKey::from(&PrivateKey::new().unwrap()))
+                .collect::<Vec<_>>();
+            let mut reversed = public_keys.clone();
+            reversed.reverse();
+
+            let address = MultiSigAddress::new(public_keys.clone(), public_keys[0].clone(), 1)
+                .unwrap()
+                .to_extended_addr();
+            let reversed_address = MultiSigAddress::new(reversed, public_keys[1].clone(), 1)
+                .unwrap()
+                .to_extended_addr();
+
+            assert_eq!(address, reversed_address);
+        }
+
         #[test]
         #[cfg(feature = "experimental")]
         fn should_work() {
@@ -0,0 +1,476 @@
+//! Self-contained proofs of transactions seen by a wallet
+//!
+//! A proof bundle can be verified without access to any wallet or node, it contains only public data:
+//! the trust anchor, headers of blocks committing to the transactions (signed by their validators),
+//! inclusion proofs and the decoded transactions themselves. The verifier provides the validator set
+//! it trusts (e.g. the genesis validators), which must have signed the headers.
+use parity_scale_codec::{Decode, Encode, Error as CodecError, Input, Output};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tendermint::block::signed_header::SignedHeader;
+use tendermint::lite::{Commit as _, ValidatorSet as _};
+use tendermint::{merkle, validator};
+
+use chain_core::common::{Proof, H256};
+use chain_core::state::ChainState;
+use chain_core::tx::data::TxId;
+use chain_core::tx::TxAux;
+
+use crate::tendermint::types::Header;
+use crate::{Error, ErrorKind, Result, ResultExt, Transaction};
+
+/// Current version of proof bundle encoding
+pub const PROOF_BUNDLE_VERSION: u8 = 2;
+
+/// Anchor of trust of a proof bundle (taken from genesis of the chain)
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct TrustAnchor {
+    /// Chain ID
+    pub chain_id: String,
+    /// App hash after init chain
+    pub genesis_app_hash: H256,
+}
+
+/// Tendermint type encoded in a proof bundle (as json)
+#[derive(Debug, Clone)]
+pub struct JsonEncoded<T>(pub T);
+
+impl<T: Serialize> Encode for JsonEncoded<T> {
+    fn encode_to<O: Output>(&self, dest: &mut O) {
+        serde_json::to_string(&self.0).unwrap().encode_to(dest)
+    }
+}
+
+impl<T: DeserializeOwned> Decode for JsonEncoded<T> {
+    fn decode<I: Input>(value: &mut I) -> std::result::Result<Self, CodecError> {
+        serde_json::from_str(&String::decode(value)?)
+            .map_err(|_| "fail to decode tendermint type from json".into())
+            .map(JsonEncoded)
+    }
+}
+
+/// Proof of a transaction included in a block
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct TransactionProof {
+    /// Height of block which includes the transaction
+    pub block_height: u64,
+    /// Decoded transaction
+    pub transaction: Transaction,
+    /// Raw transactions of the block (committed by data hash of its header)
+    pub block_transactions: Vec<Vec<u8>>,
+    /// Inclusion proof of transaction ID in merkle tree of valid transactions of the block
+    pub inclusion_proof: Proof<TxId>,
+    /// Chain state after the block (combined with root of inclusion proof, it gives app hash of the block)
+    pub chain_state: ChainState,
+    /// Header of the block (committed by the next header)
+    pub header: JsonEncoded<Header>,
+    /// Header of the next block (which contains app hash of the block) with the commit of its validators
+    pub next_header: JsonEncoded<SignedHeader>,
+    /// Validators of the next block
+    pub next_validators: JsonEncoded<validator::Set>,
+}
+
+/// Bundle of transaction proofs
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct ProofBundle {
+    /// Trust anchor
+    pub anchor: TrustAnchor,
+    /// Transaction proofs
+    pub proofs: Vec<TransactionProof>,
+}
+
+impl ProofBundle {
+    /// Encodes proof bundle prefixed with current version
+    pub fn encode_versioned(&self) -> Vec<u8> {
+        (PROOF_BUNDLE_VERSION, self).encode()
+    }
+
+    /// Decodes versioned proof bundle
+    pub fn decode_versioned(bytes: &[u8]) -> Result<Self> {
+        let mut input = bytes;
+        let version = u8::decode(&mut input).chain(|| {
+            (
+                ErrorKind::DeserializationError,
+                "Unable to decode proof bundle version",
+            )
+        })?;
+
+        if version != PROOF_BUNDLE_VERSION {
+            return Err(Error::new(
+                ErrorKind::DeserializationError,
+                format!("Unsupported proof bundle version: {}", version),
+            ));
+        }
+
+        ProofBundle::decode(&mut input).chain(|| {
+            (
+                ErrorKind::DeserializationError,
+                "Unable to decode proof bundle",
+            )
+        })
+    }
+}
+
+/// Verifies given versioned proof bundle against expected genesis app hash and returns decoded bundle.
+/// The header committing to each transaction must be signed by more than 2/3 of its validators, which
+/// must include more than 1/3 of the voting power of `trusted_validators` (as in tendermint light
+/// client).
+pub fn verify_proof_bundle(
+    bundle: &[u8],
+    expected_genesis_hash: &H256,
+    trusted_validators: &validator::Set,
+) -> Result<ProofBundle> {
+    let bundle = ProofBundle::decode_versioned(bundle)?;
+
+    if &bundle.anchor.genesis_app_hash != expected_genesis_hash {
+        return Err(Error::new(
+            ErrorKind::VerifyError,
+            format!(
+                "Genesis app hash of proof bundle {} does not match expected genesis app hash {}",
+                hex::encode(bundle.anchor.genesis_app_hash),
+                hex::encode(expected_genesis_hash)
+            ),
+        ));
+    }
+
+    for proof in bundle.proofs.iter() {
+        verify_transaction_proof(&bundle.anchor, trusted_validators, proof)?;
+    }
+
+    Ok(bundle)
+}
+
+fn verify_transaction_proof(
+    anchor: &TrustAnchor,
+    trusted_validators: &validator::Set,
+    proof: &TransactionProof,
+) -> Result<()> {
+    let next_header = &proof.next_header.0;
+    verify_signed_header(
+        anchor,
+        trusted_validators,
+        next_header,
+        &proof.next_validators.0,
+    )?;
+
+    if next_header.header.height.value() != proof.block_height + 1 {
+        return Err(Error::new(
+            ErrorKind::VerifyError,
+            format!(
+                "Header at height {} can not commit to block at height {}",
+                next_header.header.height.value(),
+                proof.block_height
+            ),
+        ));
+    }
+
+    let header = &proof.header.0;
+    let header_hash = header.hash();
+    if header.height.value() != proof.block_height
+        || next_header.header.last_block_id.as_ref().map(|id| id.hash) != Some(header_hash)
+    {
+        return Err(Error::new(
+            ErrorKind::VerifyError,
+            format!(
+                "Header of block at height {} is not committed by the next header",
+                proof.block_height
+            ),
+        ));
+    }
+
+    // same as `Txs.Hash()` of tendermint: merkle root of the hashes of the transactions
+    let data_hash = merkle::simple_hash_from_byte_vectors(
+        proof
+            .block_transactions
+            .iter()
+            .map(|raw| Sha256::digest(raw).to_vec())
+            .collect(),
+    );
+    if header.data_hash.map(|hash| hash.as_bytes().to_vec()) != Some(data_hash.to_vec()) {
+        return Err(Error::new(
+            ErrorKind::VerifyError,
+            format!(
+                "Transactions of block at height {} do not match data hash of its header",
+                proof.block_height
+            ),
+        ));
+    }
+
+    let transaction_id = proof.transaction.id();
+    let in_block = proof.block_transactions.iter().any(|raw| {
+        TxAux::decode(&mut raw.as_slice())
+            .map(|tx_aux| tx_aux.tx_id() == transaction_id)
+            .unwrap_or(false)
+    });
+    if !in_block || &transaction_id != proof.inclusion_proof.value() {
+        return Err(Error::new(
+            ErrorKind::VerifyError,
+            format!(
+                "Transaction {} is not included in block at height {}",
+                hex::encode(transaction_id),
+                proof.block_height
+            ),
+        ));
+    }
+
+    // the root of valid transactions is only known from the inclusion proof, it's authenticated by
+    // the app hash it gives (with the chain state) in the next header
+    let valid_tx_root = proof.inclusion_proof.root_hash();
+    let app_hash = proof
+        .chain_state
+        .compute_app_hash_with_tx_root(&valid_tx_root);
+    if hex::encode(&next_header.header.app_hash) != hex::encode(app_hash) {
+        return Err(Error::new(
+            ErrorKind::VerifyError,
+            format!(
+                "App hash of block at height {} does not match the one in header",
+                proof.block_height
+            ),
+        ));
+    }
+
+    if !proof.inclusion_proof.verify(&valid_tx_root) {
+        return Err(Error::new(
+            ErrorKind::VerifyError,
+            format!(
+                "Transaction {} is not a valid transaction of block at height {}",
+                hex::encode(transaction_id),
+                proof.block_height
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Verifies that the signed header is signed by more than 2/3 of its validators, and more than 1/3 of
+/// the trusted validators
+fn verify_signed_header(
+    anchor: &TrustAnchor,
+    trusted_validators: &validator::Set,
+    signed_header: &SignedHeader,
+    validators: &validator::Set,
+) -> Result<()> {
+    let header = &signed_header.header;
+    let invalid = |reason: String| {
+        Error::new(
+            ErrorKind::VerifyError,
+            format!("Invalid header at height {}: {}", header.height, reason),
+        )
+    };
+
+    if header.chain_id.to_string() != anchor.chain_id {
+        return Err(invalid(format!(
+            "chain ID {} does not match chain ID of trust anchor {}",
+            header.chain_id, anchor.chain_id
+        )));
+    }
+    if validators.hash() != header.validators_hash {
+        return Err(invalid(
+            "validators do not match its validators hash".to_owned(),
+        ));
+    }
+    if signed_header.commit.block_id.hash != header.hash() {
+        return Err(invalid("commit is not for this header".to_owned()));
+    }
+    signed_header
+        .validate(validators)
+        .map_err(|err| invalid(format!("invalid commit: {}", err)))?;
+
+    let signed_power = signed_header
+        .voting_power_in(validators)
+        .map_err(|err| invalid(format!("invalid commit: {}", err)))?;
+    if signed_power * 3 <= validators.total_power() * 2 {
+        return Err(invalid(
+            "not signed by more than 2/3 of its validators".to_owned(),
+        ));
+    }
+
+    let trusted_power = signed_header
+        .voting_power_in(trusted_validators)
+        .map_err(|err| invalid(format!("invalid commit: {}", err)))?;
+    if trusted_power * 3 <= trusted_validators.total_power() {
+        return Err(invalid(
+            "not signed by more than 1/3 of the trusted validators".to_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use chain_core::common::MerkleTree;
+    use chain_core::init::address::RedeemAddress;
+    use chain_core::init::coin::Coin;
+    use chain_core::init::config::{
+        JailingParameters, RewardsParameters, SlashRatio, SlashingParameters,
+    };
+    use chain_core::init::params::{InitNetworkParameters, NetworkParameters};
+    use chain_core::state::account::{StakedStateAddress, StakedStateOpAttributes, UnbondTx};
+    use chain_core::state::RewardsPoolState;
+    use chain_core::tx::fee::{LinearFee, Milli};
+    use tendermint::block::{self, Commit, CommitSigs};
+
+    const CHAIN_ID: &str = "test-chain-y3m1e6-AB";
+
+    fn chain_state() -> ChainState {
+        let network_params = InitNetworkParameters {
+            initial_fee_policy: LinearFee::new(
+                Milli::try_new(1, 1).unwrap(),
+                Milli::try_new(1, 1).unwrap(),
+            ),
+            required_council_node_stake: Coin::unit(),
+            required_community_node_stake: Coin::unit(),
+            jailing_config: JailingParameters {
+                block_signing_window: 5,
+                missed_block_threshold: 1,
+            },
+            slashing_config: SlashingParameters {
+                liveness_slash_percent: SlashRatio::from_str("0.1").unwrap(),
+                byzantine_slash_percent: SlashRatio::from_str("0.2").unwrap(),
+                invalid_commit_slash_percent: SlashRatio::from_str("0.3").unwrap(),
+            },
+            rewards_config: RewardsParameters {
+                monetary_expansion_cap: Coin::zero(),
+                reward_period_seconds: 24 * 60 * 60,
+                monetary_expansion_r0: "0.5".parse().unwrap(),
+                monetary_expansion_tau: 1_4500_0000_0000_0000,
+                monetary_expansion_decay: 999_860,
+            },
+            max_validators: 50,
+        };
+
+        ChainState {
+            account_root: [1; 32],
+            rewards_pool: RewardsPoolState::new(
+                0,
+                network_params.rewards_config.monetary_expansion_tau,
+            ),
+            network_params: NetworkParameters::Genesis(network_params),
+        }
+    }
+
+    fn transaction(nonce: u64) -> Transaction {
+        Transaction::UnbondStakeTransaction(UnbondTx::new(
+            StakedStateAddress::BasicRedeem(RedeemAddress([1; 20])),
+            nonce,
+            Coin::unit(),
+            StakedStateOpAttributes::new(0),
+        ))
+    }
+
+    fn header(height: u64, app_hash: &H256) -> Header {
+        serde_json::from_value(serde_json::json!({
+            "version": { "block": "10", "app": "0" },
+            "chain_id": CHAIN_ID,
+            "height": height.to_string(),
+            "time": "2020-04-14T16:05:58.649057Z",
+            "last_block_id": null,
+            "last_commit_hash": null,
+            "data_hash": null,
+            "validators_hash": "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855",
+            "next_validators_hash": "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855",
+            "consensus_hash": "048091BC7DDC283F77BFBF91D73C44DA58C3DF8A9CBC867405D8B7F3DAADA22F",
+            "app_hash": hex::encode(app_hash),
+            "last_results_hash": null,
+            "evidence_hash": null,
+            "proposer_address": "11D6FD7549C5673EFCE92625FB9D550EC80F40B9"
+        }))
+        .unwrap()
+    }
+
+    /// Header without any signature of the empty validator set
+    fn unsigned_header(height: u64, app_hash: &H256) -> SignedHeader {
+        let header = header(height, app_hash);
+        let commit = Commit {
+            height: header.height,
+            round: 0,
+            block_id: block::Id {
+                hash: header.hash(),
+                parts: None,
+            },
+            signatures: CommitSigs::new(vec![]),
+        };
+        SignedHeader { header, commit }
+    }
+
+    fn proof_bundle() -> ProofBundle {
+        let transactions = vec![transaction(0), transaction(1), transaction(2)];
+        let tree = MerkleTree::new(transactions.iter().map(Transaction::id).collect());
+        let chain_state = chain_state();
+        let app_hash =
+            chain_state.compute_app_hash(transactions.iter().map(Transaction::id).collect());
+
+        ProofBundle {
+            anchor: TrustAnchor {
+                chain_id: CHAIN_ID.to_owned(),
+                genesis_app_hash: [2; 32],
+            },
+            proofs: vec![TransactionProof {
+                block_height: 10,
+                transaction: transactions[1].clone(),
+                block_transactions: vec![],
+                inclusion_proof: tree.generate_proof(transactions[1].id()).unwrap(),
+                chain_state,
+                header: JsonEncoded(header(10, &[0; 32])),
+                next_header: JsonEncoded(unsigned_header(11, &app_hash)),
+                next_validators: JsonEncoded(validator::Set::new(vec![])),
+            }],
+        }
+    }
+
+    #[test]
+    fn check_proof_bundle_encoding() {
+        let bundle = proof_bundle();
+        let decoded = ProofBundle::decode_versioned(&bundle.encode_versioned()).unwrap();
+
+        assert_eq!(bundle.anchor, decoded.anchor);
+        assert_eq!(1, decoded.proofs.len());
+        assert_eq!(transaction(1), decoded.proofs[0].transaction);
+        assert_eq!(
+            bundle.proofs[0].next_header.0.header.hash(),
+            decoded.proofs[0].next_header.0.header.hash()
+        );
+    }
+
+    #[test]
+    fn check_proof_bundle_with_wrong_genesis() {
+        let encoded = proof_bundle().encode_versioned();
+        let trusted = validator::Set::new(vec![]);
+        assert_eq!(
+            ErrorKind::VerifyError,
+            verify_proof_bundle(&encoded, &[3; 32], &trusted)
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn check_unsigned_proof_bundle() {
+        // the app hash matches, but nobody signed the header
+        let encoded = proof_bundle().encode_versioned();
+        let trusted = validator::Set::new(vec![]);
+        let err = verify_proof_bundle(&encoded, &[2; 32], &trusted).unwrap_err();
+        assert_eq!(ErrorKind::VerifyError, err.kind());
+        assert!(err.message().starts_with("Invalid header at height 11"));
+    }
+
+    #[test]
+    fn check_unsupported_proof_bundle_version() {
+        let mut encoded = proof_bundle().encode_versioned();
+        encoded[0] = PROOF_BUNDLE_VERSION + 1;
+        let trusted = validator::Set::new(vec![]);
+        assert_eq!(
+            ErrorKind::DeserializationError,
+            verify_proof_bundle(&encoded, &[2; 32], &trusted)
+                .unwrap_err()
+                .kind()
+        );
+    }
+}
@@ -3,6 +3,7 @@ use std::str::FromStr;
 
 use aes::{Aes256, NewBlockCipher};
 use aes_gcm_siv::aead::generic_array::GenericArray;
+use parity_scale_codec::{Decode, Encode};
 use secstr::{SecBox, SecUtf8};
 use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
 use zeroize::Zeroize;
@@ -49,13 +50,56 @@ const GLOBAL_DATA_CONTEXT: &str =
 const SALT_CONTEXT: &str =
     "Thaler Experimental Network Wallet 2020-10-19 16:59:10 salt from wallet name";
 
+/// Parameters of the (argon2) derivation of encryption keys from passphrases. The parameters a
+/// wallet's records were encrypted under are recorded, so that they can be upgraded when the
+/// current ones change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct KdfParams {
+    /// Version of the parameters
+    pub version: u32,
+    /// Memory cost (in KiB)
+    pub mem_cost: u32,
+    /// Number of passes
+    pub time_cost: u32,
+    /// Degree of parallelism
+    pub lanes: u32,
+}
+
+impl KdfParams {
+    /// Parameters of the records written before the parameters were recorded (argon2 defaults)
+    pub const V1: KdfParams = KdfParams {
+        version: 1,
+        mem_cost: 4096,
+        time_cost: 3,
+        lanes: 1,
+    };
+
+    /// Parameters of the newly written records
+    pub const CURRENT: KdfParams = KdfParams::V1;
+}
+
 /// derive encryption key from passphrase
 /// FIXME: derivation should derive multiple keys, e.g. for view/sync and spending operations
+#[inline]
 pub fn derive_enckey(passphrase: &SecUtf8, name: &str) -> argon2::Result<SecKey> {
+    derive_enckey_with(passphrase, name, &KdfParams::CURRENT)
+}
+
+/// derive encryption key from passphrase with given KDF parameters
+pub fn derive_enckey_with(
+    passphrase: &SecUtf8,
+    name: &str,
+    params: &KdfParams,
+) -> argon2::Result<SecKey> {
     let mut salt = [0; 32];
     blake3::derive_key(SALT_CONTEXT, name.as_bytes(), &mut salt);
-    let mut extended =
-        argon2::hash_raw(passphrase.unsecure().as_bytes(), &salt, &Default::default())?;
+    let config = argon2::Config {
+        mem_cost: params.mem_cost,
+        time_cost: params.time_cost,
+        lanes: params.lanes,
+        ..Default::default()
+    };
+    let mut extended = argon2::hash_raw(passphrase.unsecure().as_bytes(), &salt, &config)?;
     let mut arr = GenericArray::clone_from_slice(&[0; 32]);
     blake3::derive_key(GLOBAL_DATA_CONTEXT, &extended, &mut arr);
     extended.zeroize();
@@ -97,6 +141,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn check_enckey_different_for_kdf_params() {
+        let passphrase = SecUtf8::from("passphrase");
+        let params = KdfParams {
+            version: 2,
+            mem_cost: 8192,
+            ..KdfParams::V1
+        };
+        assert_eq!(
+            derive_enckey(&passphrase, "Wallet"),
+            derive_enckey_with(&passphrase, "Wallet", &KdfParams::V1)
+        );
+        assert_ne!(
+            derive_enckey_with(&passphrase, "Wallet", &KdfParams::V1),
+            derive_enckey_with(&passphrase, "Wallet", &params)
+        );
+    }
+
     quickcheck! {
         fn check_serialization(passphrase: String, name: String) -> bool {
             let key = derive_enckey(&SecUtf8::from(passphrase), &name).unwrap();
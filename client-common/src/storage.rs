@@ -1,10 +1,15 @@
 //! Data storage layer
+mod cached_storage;
 mod memory_storage;
 #[cfg(feature = "sled")]
 mod sled_storage;
 mod unauthorized_storage;
 use parity_scale_codec::{Decode, Encode};
 
+pub use cached_storage::{
+    CachedStorage, DecryptedCache, DecryptedCacheStats, DEFAULT_DECRYPTED_CACHE_BYTES,
+    DEFAULT_DECRYPTED_CACHE_ENTRIES,
+};
 pub use memory_storage::MemoryStorage;
 #[cfg(feature = "sled")]
 pub use sled_storage::SledStorage;
@@ -16,6 +21,7 @@ use aes_gcm_siv::aead::{Aead, NewAead, Payload};
 use aes_gcm_siv::Aes256GcmSiv;
 use rand::rngs::OsRng;
 use rand::Rng;
+use zeroize::Zeroizing;
 
 use crate::{Error, ErrorKind, Result, ResultExt};
 
@@ -61,6 +67,85 @@ pub trait Storage: Send + Sync + Clone {
     /// Returns all the keyspaces currently available.
     fn keyspaces(&self) -> Result<Vec<Vec<u8>>>;
 
+    /// Returns the keys (with their values) of a keyspace from `start` (inclusive) to `end`
+    /// (exclusive, unbounded if `None`), in ascending order of the key bytes.
+    ///
+    /// The default implementation scans all the keys of the keyspace.
+    fn range<S: AsRef<[u8]>>(
+        &self,
+        keyspace: S,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut keys = self
+            .keys(&keyspace)?
+            .into_iter()
+            .filter(|key| key.as_slice() >= start && end.map_or(true, |end| key.as_slice() < end))
+            .collect::<Vec<_>>();
+        keys.sort();
+
+        let mut entries = Vec::with_capacity(keys.len());
+        for key in keys {
+            if let Some(value) = self.get(&keyspace, &key)? {
+                entries.push((key, value));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Returns a page of the keys (with their values) of a keyspace from `start` (inclusive) to
+    /// `end` (exclusive, unbounded if `None`): `limit` entries after skipping `offset` ones, in
+    /// ascending order of the key bytes (descending if `reversed`).
+    ///
+    /// The default implementation scans all the keys of the keyspace, but only reads the values
+    /// of the page.
+    fn range_page<S: AsRef<[u8]>>(
+        &self,
+        keyspace: S,
+        start: &[u8],
+        end: Option<&[u8]>,
+        offset: usize,
+        limit: usize,
+        reversed: bool,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut keys = self
+            .keys(&keyspace)?
+            .into_iter()
+            .filter(|key| key.as_slice() >= start && end.map_or(true, |end| key.as_slice() < end))
+            .collect::<Vec<_>>();
+        keys.sort();
+        if reversed {
+            keys.reverse();
+        }
+
+        let mut entries = Vec::with_capacity(limit.min(keys.len()));
+        for key in keys.into_iter().skip(offset).take(limit) {
+            if let Some(value) = self.get(&keyspace, &key)? {
+                entries.push((key, value));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Returns the keys (with their values) of a keyspace which start with `prefix`, in ascending
+    /// order of the key bytes.
+    fn iter_prefix<S: AsRef<[u8]>>(
+        &self,
+        keyspace: S,
+        prefix: &[u8],
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let end = prefix_end(prefix);
+        self.range(keyspace, prefix, end.as_deref())
+    }
+
+    /// Adds the deletion of all the keys of given keyspace to the batch
+    fn batch_clear<S: AsRef<[u8]>>(&self, batch: &mut StorageBatch, keyspace: S) -> Result<()> {
+        for key in self.keys(&keyspace)? {
+            batch.delete(&keyspace, key);
+        }
+        Ok(())
+    }
+
     /// load and deserialize object
     fn load<T: Decode>(&self, keyspace: &str, key: &str) -> Result<Option<T>> {
         if let Some(bytes) = self.get(keyspace, key)? {
@@ -80,8 +165,105 @@ pub trait Storage: Send + Sync + Clone {
         self.set(keyspace, key, value.encode()).map(|_| ())
     }
 
+    /// Applies all the operations of batch atomically (either all of them are written or none)
+    fn write_batch(&self, batch: StorageBatch) -> Result<()>;
+
     /// flush db
     fn flush(&self) -> Result<()>;
+
+    /// Returns the cache of records decrypted by `SecureStorage` (if the storage has one)
+    fn decrypted_cache(&self) -> Option<&DecryptedCache> {
+        None
+    }
+}
+
+/// The smallest key greater than all the keys starting with `prefix` (`None` if there is no such
+/// key, i.e. the prefix is empty or consists of `0xff` bytes only)
+fn prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < u8::max_value() {
+            end.push(last + 1);
+            return Some(end);
+        }
+    }
+    None
+}
+
+/// Operation of storage batch
+#[derive(Debug)]
+pub(crate) struct BatchOperation {
+    pub keyspace: Vec<u8>,
+    pub key: Vec<u8>,
+    /// `None` deletes the key
+    pub value: Option<Vec<u8>>,
+}
+
+/// Set of writes to be applied atomically with `Storage::write_batch`
+#[derive(Debug, Default)]
+pub struct StorageBatch {
+    pub(crate) operations: Vec<BatchOperation>,
+}
+
+impl StorageBatch {
+    /// Sets a key to a new value in given keyspace
+    pub fn set<S: AsRef<[u8]>, K: AsRef<[u8]>>(&mut self, keyspace: S, key: K, value: Vec<u8>) {
+        self.operations.push(BatchOperation {
+            keyspace: keyspace.as_ref().to_vec(),
+            key: key.as_ref().to_vec(),
+            value: Some(value),
+        })
+    }
+
+    /// Deletes a key from keyspace
+    pub fn delete<S: AsRef<[u8]>, K: AsRef<[u8]>>(&mut self, keyspace: S, key: K) {
+        self.operations.push(BatchOperation {
+            keyspace: keyspace.as_ref().to_vec(),
+            key: key.as_ref().to_vec(),
+            value: None,
+        })
+    }
+
+    /// Sets a key to a new value (after encryption) in given keyspace
+    pub fn set_secure<S: AsRef<[u8]>, K: AsRef<[u8]>>(
+        &mut self,
+        keyspace: S,
+        key: K,
+        value: &[u8],
+        enckey: &SecKey,
+    ) -> Result<()> {
+        let cipher = encrypt_bytes(&key, enckey, value)?;
+        self.set(keyspace, key, cipher);
+        Ok(())
+    }
+
+    /// Serialize and save object
+    pub fn save<T: Encode>(&mut self, keyspace: &str, key: &str, value: &T) {
+        self.set(keyspace, key, value.encode())
+    }
+
+    /// Serialize and save object (after encryption)
+    pub fn save_secure<T: Encode>(
+        &mut self,
+        keyspace: &str,
+        key: &str,
+        enckey: &SecKey,
+        value: &T,
+    ) -> Result<()> {
+        self.set_secure(keyspace, key, &value.encode(), enckey)
+    }
+
+    /// Returns number of operations in batch
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    /// Returns `true` if there is no operation in batch
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
 }
 
 /// Interface for a generic key-value storage (with encryption)
@@ -146,6 +328,40 @@ pub trait SecureStorage: Storage {
         self.set_secure(keyspace, key, value.encode(), enckey)
             .map(|_| ())
     }
+
+    /// Adds the write which re-encrypts the value of key (if it exists) with `new_enckey` to the
+    /// batch. Returns `false` if there is no such key.
+    fn batch_reencrypt<S: AsRef<[u8]>, K: AsRef<[u8]>>(
+        &self,
+        batch: &mut StorageBatch,
+        keyspace: S,
+        key: K,
+        enckey: &SecKey,
+        new_enckey: &SecKey,
+    ) -> Result<bool> {
+        match self.get_secure(&keyspace, &key, enckey)? {
+            Some(value) => {
+                batch.set_secure(keyspace, key, &value, new_enckey)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Adds the writes which re-encrypt all the values of given keyspace with `new_enckey` to the
+    /// batch
+    fn batch_reencrypt_keyspace<S: AsRef<[u8]>>(
+        &self,
+        batch: &mut StorageBatch,
+        keyspace: S,
+        enckey: &SecKey,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        for key in self.keys(&keyspace)? {
+            self.batch_reencrypt(batch, &keyspace, key, enckey, new_enckey)?;
+        }
+        Ok(())
+    }
 }
 
 impl<T> SecureStorage for T
@@ -158,9 +374,15 @@ where
         key: K,
         enckey: &SecKey,
     ) -> Result<Option<Vec<u8>>> {
-        self.get(keyspace, &key)?
-            .map(|value| decrypt_bytes(&key, enckey, &value))
-            .transpose()
+        let read = || {
+            self.get(&keyspace, &key)?
+                .map(|value| decrypt_bytes(&key, enckey, &value))
+                .transpose()
+        };
+        match self.decrypted_cache() {
+            Some(cache) => cache.get_or_read(keyspace.as_ref(), key.as_ref(), enckey, read),
+            None => read(),
+        }
     }
 
     fn set_secure<S: AsRef<[u8]>, K: AsRef<[u8]>>(
@@ -172,6 +394,7 @@ where
     ) -> Result<Option<Vec<u8>>> {
         let old_value = self.get_secure(&keyspace, &key, enckey)?;
 
+        let value = Zeroizing::new(value);
         let cipher = encrypt_bytes(&key, enckey, &value)?;
         self.set(keyspace, &key, cipher)?;
 
@@ -192,7 +415,7 @@ where
     {
         self.fetch_and_update(keyspace, &key, |current| {
             let opened = current
-                .map(|current| decrypt_bytes(&key, enckey, current))
+                .map(|current| decrypt_bytes(&key, enckey, current).map(Zeroizing::new))
                 .transpose()
                 .chain(|| {
                     (
@@ -201,7 +424,7 @@ where
                     )
                 })?;
 
-            let next = f(opened.as_ref().map(AsRef::as_ref))?;
+            let next = f(opened.as_ref().map(|opened| &opened[..]))?.map(Zeroizing::new);
 
             next.as_ref()
                 .map(|next| encrypt_bytes(&key, enckey, next))
@@ -237,6 +460,12 @@ pub fn encrypt_bytes<K: AsRef<[u8]>>(key: K, enckey: &SecKey, bytes: &[u8]) -> R
 
 /// Decrypts bytes with given enckey
 pub fn decrypt_bytes<K: AsRef<[u8]>>(key: K, enckey: &SecKey, bytes: &[u8]) -> Result<Vec<u8>> {
+    if bytes.len() < NONCE_SIZE {
+        return Err(Error::new(
+            ErrorKind::DecryptionError,
+            "Unable to decrypt bytes: missing nonce",
+        ));
+    }
     let algo = get_algo(enckey);
 
     let payload = Payload {
@@ -0,0 +1,608 @@
+//! Storage wrapper with an in-memory cache of decrypted records, so that the records which are
+//! read on every request (e.g. public keys and addresses of a wallet) are not decrypted each time.
+//! Entries are keyed by (keyspace, key, fingerprint of enckey): a record is never served for an
+//! enckey which did not decrypt it. Any write to a key (through this wrapper) invalidates its
+//! entries, and no entry is inserted while a write is in progress, so reads after a write always
+//! return the written value. Decrypted values are zeroized when they are evicted.
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use zeroize::Zeroizing;
+
+use crate::storage::{Storage, StorageBatch};
+use crate::{Result, SecKey};
+
+/// Default maximal number of cached decrypted records
+pub const DEFAULT_DECRYPTED_CACHE_ENTRIES: usize = 4096;
+/// Default maximal total size (in bytes) of cached decrypted records
+pub const DEFAULT_DECRYPTED_CACHE_BYTES: usize = 8 * 1024 * 1024;
+
+type Fingerprint = [u8; 32];
+type RecordKey = (Vec<u8>, Vec<u8>);
+
+fn fingerprint(enckey: &SecKey) -> Fingerprint {
+    *blake3::hash(enckey.unsecure().as_slice()).as_bytes()
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    value: Zeroizing<Vec<u8>>,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheInner {
+    /// (keyspace, key) -> enckey fingerprint -> decrypted value
+    entries: HashMap<RecordKey, HashMap<Fingerprint, CacheEntry>>,
+    /// `last_used` tick -> entry, the least recently used entry is the first one
+    lru: BTreeMap<u64, (RecordKey, Fingerprint)>,
+    tick: u64,
+    bytes: usize,
+    len: usize,
+    /// incremented on start and end of every write
+    generation: u64,
+    /// number of writes in progress
+    pending_writes: usize,
+}
+
+impl CacheInner {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn get(&mut self, record: &RecordKey, fingerprint: &Fingerprint) -> Option<Vec<u8>> {
+        let tick = self.next_tick();
+        let entry = self.entries.get_mut(record)?.get_mut(fingerprint)?;
+        let last_used = std::mem::replace(&mut entry.last_used, tick);
+        let value = entry.value.to_vec();
+        let lru_key = self
+            .lru
+            .remove(&last_used)
+            .expect("lru entry of cached record");
+        self.lru.insert(tick, lru_key);
+        Some(value)
+    }
+
+    fn remove(&mut self, record: &RecordKey, fingerprint: &Fingerprint) {
+        if let Some(fingerprints) = self.entries.get_mut(record) {
+            if let Some(entry) = fingerprints.remove(fingerprint) {
+                self.lru.remove(&entry.last_used);
+                self.bytes -= entry.value.len();
+                self.len -= 1;
+            }
+            if fingerprints.is_empty() {
+                self.entries.remove(record);
+            }
+        }
+    }
+
+    fn remove_record(&mut self, record: &RecordKey) {
+        if let Some(fingerprints) = self.entries.remove(record) {
+            for entry in fingerprints.values() {
+                self.lru.remove(&entry.last_used);
+                self.bytes -= entry.value.len();
+                self.len -= 1;
+            }
+        }
+    }
+
+    fn remove_where<F>(&mut self, f: F)
+    where
+        F: Fn(&RecordKey, &Fingerprint) -> bool,
+    {
+        let removed = self
+            .lru
+            .values()
+            .filter(|(record, fingerprint)| f(record, fingerprint))
+            .cloned()
+            .collect::<Vec<_>>();
+        for (record, fingerprint) in removed {
+            self.remove(&record, &fingerprint);
+        }
+    }
+
+    fn insert(
+        &mut self,
+        record: RecordKey,
+        fingerprint: Fingerprint,
+        value: Vec<u8>,
+        max_entries: usize,
+        max_bytes: usize,
+    ) {
+        let value = Zeroizing::new(value);
+        if value.len() > max_bytes || max_entries == 0 {
+            return;
+        }
+        self.remove(&record, &fingerprint);
+        while self.len >= max_entries || self.bytes + value.len() > max_bytes {
+            let oldest = match self.lru.values().next() {
+                Some(oldest) => oldest.clone(),
+                None => break,
+            };
+            self.remove(&oldest.0, &oldest.1);
+        }
+        let last_used = self.next_tick();
+        self.lru.insert(last_used, (record.clone(), fingerprint));
+        self.bytes += value.len();
+        self.len += 1;
+        self.entries
+            .entry(record)
+            .or_default()
+            .insert(fingerprint, CacheEntry { value, last_used });
+    }
+}
+
+/// Statistics of the decrypted-record cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecryptedCacheStats {
+    /// number of reads served from the cache
+    pub hits: u64,
+    /// number of reads which had to decrypt the stored record
+    pub misses: u64,
+    /// number of cached records
+    pub entries: usize,
+    /// total size of cached records (in bytes)
+    pub bytes: usize,
+}
+
+/// Cache of decrypted records, bounded by the number of entries and their total size
+#[derive(Debug)]
+pub struct DecryptedCache {
+    inner: Mutex<CacheInner>,
+    max_entries: usize,
+    max_bytes: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl Default for DecryptedCache {
+    fn default() -> Self {
+        DecryptedCache::new(
+            DEFAULT_DECRYPTED_CACHE_ENTRIES,
+            DEFAULT_DECRYPTED_CACHE_BYTES,
+        )
+    }
+}
+
+impl DecryptedCache {
+    /// Creates an empty cache with the given bounds
+    pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+        DecryptedCache {
+            inner: Mutex::new(CacheInner::default()),
+            max_entries,
+            max_bytes,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    // decrypted values are only cloned out of the cache, so the poisoned lock is still consistent
+    fn lock(&self) -> MutexGuard<'_, CacheInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the cached decrypted record, or reads (and decrypts) it with `read` and caches it.
+    /// The lock isn't held while reading; if a write started or finished in the meantime, the
+    /// record is returned but not cached.
+    pub(crate) fn get_or_read<F>(
+        &self,
+        keyspace: &[u8],
+        key: &[u8],
+        enckey: &SecKey,
+        read: F,
+    ) -> Result<Option<Vec<u8>>>
+    where
+        F: FnOnce() -> Result<Option<Vec<u8>>>,
+    {
+        let record = (keyspace.to_vec(), key.to_vec());
+        let fingerprint = fingerprint(enckey);
+        let generation = {
+            let mut inner = self.lock();
+            if let Some(value) = inner.get(&record, &fingerprint) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(Some(value));
+            }
+            if inner.pending_writes > 0 {
+                None
+            } else {
+                Some(inner.generation)
+            }
+        };
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let value = read()?;
+
+        if let (Some(value), Some(generation)) = (&value, generation) {
+            let mut inner = self.lock();
+            if inner.generation == generation {
+                inner.insert(
+                    record,
+                    fingerprint,
+                    value.clone(),
+                    self.max_entries,
+                    self.max_bytes,
+                );
+            }
+        }
+        Ok(value)
+    }
+
+    fn begin_write(&self, records: &[RecordKey]) {
+        let mut inner = self.lock();
+        inner.generation += 1;
+        inner.pending_writes += 1;
+        for record in records {
+            inner.remove_record(record);
+        }
+    }
+
+    fn end_write(&self, records: &[RecordKey]) {
+        let mut inner = self.lock();
+        inner.generation += 1;
+        inner.pending_writes -= 1;
+        for record in records {
+            inner.remove_record(record);
+        }
+    }
+
+    fn write<T, F>(&self, records: &[RecordKey], write: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        self.begin_write(records);
+        let result = write();
+        self.end_write(records);
+        result
+    }
+
+    fn remove_keyspace(&self, keyspace: &[u8]) {
+        let mut inner = self.lock();
+        inner.generation += 1;
+        inner.remove_where(|record, _| record.0 == keyspace);
+    }
+
+    /// Drops all the records decrypted with given enckey (e.g. when its wallet is locked, or its
+    /// passphrase is changed)
+    pub fn remove_enckey(&self, enckey: &SecKey) {
+        let fingerprint = fingerprint(enckey);
+        let mut inner = self.lock();
+        inner.generation += 1;
+        inner.remove_where(|_, entry_fingerprint| *entry_fingerprint == fingerprint);
+    }
+
+    /// Drops all the cached records
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        inner.generation += 1;
+        inner.remove_where(|_, _| true);
+    }
+
+    /// Current statistics
+    pub fn stats(&self) -> DecryptedCacheStats {
+        let inner = self.lock();
+        DecryptedCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: inner.len,
+            bytes: inner.bytes,
+        }
+    }
+}
+
+/// Storage which caches the records decrypted by `SecureStorage`
+#[derive(Debug, Clone)]
+pub struct CachedStorage<S: Storage> {
+    storage: S,
+    cache: Arc<DecryptedCache>,
+}
+
+impl<S: Storage> CachedStorage<S> {
+    /// Wraps the storage with a cache of default bounds
+    pub fn new(storage: S) -> Self {
+        Self::with_cache(storage, DecryptedCache::default())
+    }
+
+    /// Wraps the storage with given cache
+    pub fn with_cache(storage: S, cache: DecryptedCache) -> Self {
+        CachedStorage {
+            storage,
+            cache: Arc::new(cache),
+        }
+    }
+
+    /// Returns the cache of decrypted records
+    pub fn cache(&self) -> &DecryptedCache {
+        &self.cache
+    }
+}
+
+fn record_key<S: AsRef<[u8]>, K: AsRef<[u8]>>(keyspace: S, key: K) -> RecordKey {
+    (keyspace.as_ref().to_vec(), key.as_ref().to_vec())
+}
+
+impl<T: Storage> Storage for CachedStorage<T> {
+    fn clear<S: AsRef<[u8]>>(&self, keyspace: S) -> Result<()> {
+        let result = self.storage.clear(&keyspace);
+        self.cache.remove_keyspace(keyspace.as_ref());
+        result
+    }
+
+    fn get<S: AsRef<[u8]>, K: AsRef<[u8]>>(&self, keyspace: S, key: K) -> Result<Option<Vec<u8>>> {
+        self.storage.get(keyspace, key)
+    }
+
+    fn set<S: AsRef<[u8]>, K: AsRef<[u8]>>(
+        &self,
+        keyspace: S,
+        key: K,
+        value: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>> {
+        self.cache.write(&[record_key(&keyspace, &key)], || {
+            self.storage.set(keyspace, key, value)
+        })
+    }
+
+    fn delete<S: AsRef<[u8]>, K: AsRef<[u8]>>(
+        &self,
+        keyspace: S,
+        key: K,
+    ) -> Result<Option<Vec<u8>>> {
+        self.cache.write(&[record_key(&keyspace, &key)], || {
+            self.storage.delete(keyspace, key)
+        })
+    }
+
+    fn fetch_and_update<S, K, F>(&self, keyspace: S, key: K, f: F) -> Result<Option<Vec<u8>>>
+    where
+        S: AsRef<[u8]>,
+        K: AsRef<[u8]>,
+        F: Fn(Option<&[u8]>) -> Result<Option<Vec<u8>>>,
+    {
+        self.cache.write(&[record_key(&keyspace, &key)], || {
+            self.storage.fetch_and_update(keyspace, key, f)
+        })
+    }
+
+    fn keys<S: AsRef<[u8]>>(&self, keyspace: S) -> Result<Vec<Vec<u8>>> {
+        self.storage.keys(keyspace)
+    }
+
+    fn contains_key<S: AsRef<[u8]>, K: AsRef<[u8]>>(&self, keyspace: S, key: K) -> Result<bool> {
+        self.storage.contains_key(keyspace, key)
+    }
+
+    fn keyspaces(&self) -> Result<Vec<Vec<u8>>> {
+        self.storage.keyspaces()
+    }
+
+    fn range<S: AsRef<[u8]>>(
+        &self,
+        keyspace: S,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.storage.range(keyspace, start, end)
+    }
+
+    fn range_page<S: AsRef<[u8]>>(
+        &self,
+        keyspace: S,
+        start: &[u8],
+        end: Option<&[u8]>,
+        offset: usize,
+        limit: usize,
+        reversed: bool,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.storage
+            .range_page(keyspace, start, end, offset, limit, reversed)
+    }
+
+    fn write_batch(&self, batch: StorageBatch) -> Result<()> {
+        let records = batch
+            .operations
+            .iter()
+            .map(|operation| (operation.keyspace.clone(), operation.key.clone()))
+            .collect::<Vec<_>>();
+        self.cache
+            .write(&records, || self.storage.write_batch(batch))
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.storage.flush()
+    }
+
+    fn decrypted_cache(&self) -> Option<&DecryptedCache> {
+        Some(&self.cache)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    use secstr::SecUtf8;
+
+    use crate::seckey::derive_enckey;
+    use crate::storage::{MemoryStorage, SecureStorage};
+    use crate::ErrorKind;
+
+    fn enckey(passphrase: &str) -> SecKey {
+        derive_enckey(&SecUtf8::from(passphrase), "").unwrap()
+    }
+
+    #[test]
+    fn check_read_after_write() {
+        let storage = CachedStorage::new(MemoryStorage::default());
+        let enckey = enckey("passphrase");
+
+        storage
+            .set_secure("keyspace", "key", b"one".to_vec(), &enckey)
+            .unwrap();
+        for _ in 0..3 {
+            assert_eq!(
+                Some(b"one".to_vec()),
+                storage.get_secure("keyspace", "key", &enckey).unwrap()
+            );
+        }
+        let stats = storage.cache().stats();
+        // `set_secure` reads the old value (not found) first
+        assert_eq!((stats.hits, stats.misses, stats.entries), (2, 2, 1));
+
+        storage
+            .set_secure("keyspace", "key", b"two".to_vec(), &enckey)
+            .unwrap();
+        assert_eq!(
+            Some(b"two".to_vec()),
+            storage.get_secure("keyspace", "key", &enckey).unwrap()
+        );
+
+        storage
+            .fetch_and_update_secure("keyspace", "key", &enckey, |_| Ok(Some(b"three".to_vec())))
+            .unwrap();
+        assert_eq!(
+            Some(b"three".to_vec()),
+            storage.get_secure("keyspace", "key", &enckey).unwrap()
+        );
+
+        let mut batch = StorageBatch::default();
+        batch
+            .set_secure("keyspace", "key", b"four", &enckey)
+            .unwrap();
+        storage.write_batch(batch).unwrap();
+        assert_eq!(
+            Some(b"four".to_vec()),
+            storage.get_secure("keyspace", "key", &enckey).unwrap()
+        );
+
+        storage.delete("keyspace", "key").unwrap();
+        assert_eq!(
+            None,
+            storage.get_secure("keyspace", "key", &enckey).unwrap()
+        );
+
+        storage
+            .set_secure("keyspace", "key", b"five".to_vec(), &enckey)
+            .unwrap();
+        storage.get_secure("keyspace", "key", &enckey).unwrap();
+        storage.clear("keyspace").unwrap();
+        assert_eq!(
+            None,
+            storage.get_secure("keyspace", "key", &enckey).unwrap()
+        );
+    }
+
+    #[test]
+    fn check_cached_record_needs_enckey() {
+        let storage = CachedStorage::new(MemoryStorage::default());
+        let enckey = enckey("passphrase");
+
+        storage
+            .set_secure("keyspace", "key", b"secret".to_vec(), &enckey)
+            .unwrap();
+        storage.get_secure("keyspace", "key", &enckey).unwrap();
+        assert_eq!(1, storage.cache().stats().entries);
+
+        let error = storage
+            .get_secure("keyspace", "key", &self::enckey("wrong passphrase"))
+            .unwrap_err();
+        assert_eq!(ErrorKind::DecryptionError, error.kind());
+
+        storage.cache().remove_enckey(&enckey);
+        assert_eq!(0, storage.cache().stats().entries);
+        assert_eq!(
+            Some(b"secret".to_vec()),
+            storage.get_secure("keyspace", "key", &enckey).unwrap()
+        );
+    }
+
+    #[test]
+    fn check_cache_bounds() {
+        let storage =
+            CachedStorage::with_cache(MemoryStorage::default(), DecryptedCache::new(2, 10));
+        let enckey = enckey("passphrase");
+
+        for key in 0..3u8 {
+            storage
+                .set_secure("keyspace", [key], vec![key; 4], &enckey)
+                .unwrap();
+            storage.get_secure("keyspace", [key], &enckey).unwrap();
+        }
+        let stats = storage.cache().stats();
+        assert_eq!((stats.entries, stats.bytes), (2, 8));
+
+        // key 0 was evicted
+        storage.get_secure("keyspace", [2], &enckey).unwrap();
+        let hits = storage.cache().stats().hits;
+        storage.get_secure("keyspace", [0], &enckey).unwrap();
+        assert_eq!(hits, storage.cache().stats().hits);
+
+        // larger than the whole cache: not cached
+        storage
+            .set_secure("keyspace", "large", vec![0; 11], &enckey)
+            .unwrap();
+        storage.get_secure("keyspace", "large", &enckey).unwrap();
+        assert!(storage.cache().stats().bytes <= 10);
+    }
+
+    #[test]
+    fn check_concurrent_read_after_write() {
+        let storage = CachedStorage::new(MemoryStorage::default());
+        let enckey = enckey("passphrase");
+        storage
+            .set_secure("wallet", "addresses", 0u64.to_le_bytes().to_vec(), &enckey)
+            .unwrap();
+
+        let read = |storage: &CachedStorage<MemoryStorage>, enckey: &SecKey| {
+            let value = storage
+                .get_secure("wallet", "addresses", enckey)
+                .unwrap()
+                .unwrap();
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(&value);
+            u64::from_le_bytes(bytes)
+        };
+
+        let writer = {
+            let storage = storage.clone();
+            let enckey = self::enckey("passphrase");
+            thread::spawn(move || {
+                for i in 1..=500u64 {
+                    if i % 2 == 0 {
+                        storage
+                            .set_secure("wallet", "addresses", i.to_le_bytes().to_vec(), &enckey)
+                            .unwrap();
+                    } else {
+                        storage
+                            .fetch_and_update_secure("wallet", "addresses", &enckey, |_| {
+                                Ok(Some(i.to_le_bytes().to_vec()))
+                            })
+                            .unwrap();
+                    }
+                    assert_eq!(i, read(&storage, &enckey));
+                }
+            })
+        };
+        let reader = {
+            let storage = storage.clone();
+            let enckey = self::enckey("passphrase");
+            thread::spawn(move || {
+                let mut last = 0;
+                while last < 500 {
+                    let current = read(&storage, &enckey);
+                    assert!(current >= last, "read {} after {}", current, last);
+                    last = current;
+                }
+            })
+        };
+
+        writer.join().unwrap();
+        reader.join().unwrap();
+        assert_eq!(500, read(&storage, &enckey));
+        assert_eq!(500, read(&storage, &enckey));
+        assert!(storage.cache().stats().hits > 0);
+    }
+}
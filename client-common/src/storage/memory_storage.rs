@@ -1,12 +1,13 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
 use std::sync::{Arc, RwLock};
 
-use crate::{Error, ErrorKind, Result, Storage};
+use crate::{Error, ErrorKind, Result, Storage, StorageBatch};
 
-/// Storage backed by `HashMap`
+/// Storage backed by `HashMap` (the keys of a keyspace are kept ordered in a `BTreeMap`)
 #[allow(clippy::type_complexity)]
 #[derive(Debug, Default, Clone)]
-pub struct MemoryStorage(Arc<RwLock<HashMap<Vec<u8>, HashMap<Vec<u8>, Vec<u8>>>>>);
+pub struct MemoryStorage(Arc<RwLock<HashMap<Vec<u8>, BTreeMap<Vec<u8>, Vec<u8>>>>>);
 
 impl Storage for MemoryStorage {
     fn flush(&self) -> Result<()> {
@@ -21,7 +22,7 @@ impl Storage for MemoryStorage {
         })?;
 
         if let Some(ref mut space) = memory.get_mut(keyspace.as_ref()) {
-            space.drain();
+            space.clear();
         }
 
         Ok(())
@@ -153,4 +154,209 @@ impl Storage for MemoryStorage {
 
         Ok(keyspaces)
     }
+
+    fn range<S: AsRef<[u8]>>(
+        &self,
+        keyspace: S,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let memory = self.0.read().map_err(|_| {
+            Error::new(
+                ErrorKind::StorageError,
+                "Unable to acquire read lock on memory storage",
+            )
+        })?;
+
+        let end = end.map_or(Bound::Unbounded, Bound::Excluded);
+        if let Bound::Excluded(end) = end {
+            if end <= start {
+                return Ok(Vec::new());
+            }
+        }
+        let entries = memory
+            .get(keyspace.as_ref())
+            .map(|space| {
+                space
+                    .range::<[u8], _>((Bound::Included(start), end))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        Ok(entries)
+    }
+
+    fn range_page<S: AsRef<[u8]>>(
+        &self,
+        keyspace: S,
+        start: &[u8],
+        end: Option<&[u8]>,
+        offset: usize,
+        limit: usize,
+        reversed: bool,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let memory = self.0.read().map_err(|_| {
+            Error::new(
+                ErrorKind::StorageError,
+                "Unable to acquire read lock on memory storage",
+            )
+        })?;
+
+        let end = end.map_or(Bound::Unbounded, Bound::Excluded);
+        if let Bound::Excluded(end) = end {
+            if end <= start {
+                return Ok(Vec::new());
+            }
+        }
+        let space = match memory.get(keyspace.as_ref()) {
+            Some(space) => space,
+            None => return Ok(Vec::new()),
+        };
+        let range = space.range::<[u8], _>((Bound::Included(start), end));
+        let page: Box<dyn Iterator<Item = _>> = if reversed {
+            Box::new(range.rev().skip(offset).take(limit))
+        } else {
+            Box::new(range.skip(offset).take(limit))
+        };
+
+        Ok(page
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+
+    fn write_batch(&self, batch: StorageBatch) -> Result<()> {
+        let mut memory = self.0.write().map_err(|_| {
+            Error::new(
+                ErrorKind::StorageError,
+                "Unable to acquire write lock on memory storage",
+            )
+        })?;
+
+        for operation in batch.operations {
+            let space = memory.entry(operation.keyspace).or_default();
+            match operation.value {
+                Some(value) => space.insert(operation.key, value),
+                None => space.remove(&operation.key),
+            };
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seckey::derive_enckey;
+    use crate::storage::SecureStorage;
+    use secstr::SecUtf8;
+
+    #[test]
+    fn check_range_and_prefix() {
+        let storage = MemoryStorage::default();
+        for key in &[&[1, 2][..], &[1, 0xff], &[1, 0xff, 0], &[2], &[0xff, 0xff]] {
+            storage.set("keyspace", key, key.to_vec()).unwrap();
+        }
+        let keys = |entries: Vec<(Vec<u8>, Vec<u8>)>| {
+            entries
+                .into_iter()
+                .map(|(key, value)| {
+                    assert_eq!(key, value);
+                    key
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            vec![vec![1, 0xff], vec![1, 0xff, 0]],
+            keys(storage.range("keyspace", &[1, 3], Some(&[2])).unwrap())
+        );
+        assert_eq!(
+            vec![vec![2], vec![0xff, 0xff]],
+            keys(storage.range("keyspace", &[2], None).unwrap())
+        );
+        assert!(storage
+            .range("keyspace", &[2], Some(&[1]))
+            .unwrap()
+            .is_empty());
+        assert!(storage.range("other", &[], None).unwrap().is_empty());
+
+        assert_eq!(
+            vec![vec![1, 2], vec![1, 0xff], vec![1, 0xff, 0]],
+            keys(storage.iter_prefix("keyspace", &[1]).unwrap())
+        );
+        assert_eq!(
+            vec![vec![0xff, 0xff]],
+            keys(storage.iter_prefix("keyspace", &[0xff]).unwrap())
+        );
+        assert_eq!(5, storage.iter_prefix("keyspace", &[]).unwrap().len());
+
+        assert_eq!(
+            vec![vec![1, 0xff, 0], vec![2]],
+            keys(
+                storage
+                    .range_page("keyspace", &[1], None, 2, 2, false)
+                    .unwrap()
+            )
+        );
+        assert_eq!(
+            vec![vec![1, 0xff], vec![1, 2]],
+            keys(
+                storage
+                    .range_page("keyspace", &[1], Some(&[2]), 1, 5, true)
+                    .unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn check_batch_reencrypt() {
+        let storage = MemoryStorage::default();
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let new_enckey = derive_enckey(&SecUtf8::from("new passphrase"), "name").unwrap();
+        for key in &["key1", "key2"] {
+            storage
+                .set_secure("keyspace", key, key.as_bytes().to_vec(), &enckey)
+                .unwrap();
+        }
+
+        let mut batch = StorageBatch::default();
+        assert!(!storage
+            .batch_reencrypt(&mut batch, "keyspace", "key3", &enckey, &new_enckey)
+            .unwrap());
+        storage
+            .batch_reencrypt_keyspace(&mut batch, "keyspace", &enckey, &new_enckey)
+            .unwrap();
+        assert_eq!(2, batch.len());
+        // nothing is written before the batch is
+        assert!(storage.get_secure("keyspace", "key1", &enckey).is_ok());
+
+        storage.write_batch(batch).unwrap();
+        for key in &["key1", "key2"] {
+            assert_eq!(
+                ErrorKind::DecryptionError,
+                storage
+                    .get_secure("keyspace", key, &enckey)
+                    .unwrap_err()
+                    .kind()
+            );
+            assert_eq!(
+                key.as_bytes(),
+                &storage
+                    .get_secure("keyspace", key, &new_enckey)
+                    .unwrap()
+                    .unwrap()[..]
+            );
+        }
+
+        let mut batch = StorageBatch::default();
+        assert_eq!(
+            ErrorKind::DecryptionError,
+            storage
+                .batch_reencrypt_keyspace(&mut batch, "keyspace", &enckey, &new_enckey)
+                .unwrap_err()
+                .kind()
+        );
+    }
 }
@@ -2,10 +2,11 @@
 use std::path::Path;
 use std::sync::Arc;
 
-use sled::{Config, Db};
+use sled::transaction::{ConflictableTransactionError, TransactionError};
+use sled::{Config, Db, Transactional, Tree};
 
-use crate::storage::Storage;
-use crate::{ErrorKind, Result, ResultExt};
+use crate::storage::{Storage, StorageBatch};
+use crate::{Error, ErrorKind, Result, ResultExt};
 
 /// Storage backed by Sled
 #[derive(Clone)]
@@ -248,6 +249,86 @@ impl Storage for SledStorage {
         })?)
     }
 
+    fn range<S: AsRef<[u8]>>(
+        &self,
+        keyspace: S,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let tree = self.0.open_tree(keyspace.as_ref().to_vec()).chain(|| {
+            (
+                ErrorKind::StorageError,
+                format!(
+                    "Unable to open sled storage tree for keyspace: {}",
+                    String::from_utf8_lossy(keyspace.as_ref())
+                ),
+            )
+        })?;
+
+        let iter = match end {
+            Some(end) if end <= start => return Ok(Vec::new()),
+            Some(end) => tree.range(start..end),
+            None => tree.range(start..),
+        };
+        iter.map(|entry| {
+            let (key, value) = entry.chain(|| {
+                (
+                    ErrorKind::StorageError,
+                    format!(
+                        "Unable to retrieve range of keyspace: {}",
+                        String::from_utf8_lossy(keyspace.as_ref())
+                    ),
+                )
+            })?;
+            Ok((key.as_ref().to_vec(), value.as_ref().to_vec()))
+        })
+        .collect()
+    }
+
+    fn range_page<S: AsRef<[u8]>>(
+        &self,
+        keyspace: S,
+        start: &[u8],
+        end: Option<&[u8]>,
+        offset: usize,
+        limit: usize,
+        reversed: bool,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let tree = self.0.open_tree(keyspace.as_ref().to_vec()).chain(|| {
+            (
+                ErrorKind::StorageError,
+                format!(
+                    "Unable to open sled storage tree for keyspace: {}",
+                    String::from_utf8_lossy(keyspace.as_ref())
+                ),
+            )
+        })?;
+
+        let iter = match end {
+            Some(end) if end <= start => return Ok(Vec::new()),
+            Some(end) => tree.range(start..end),
+            None => tree.range(start..),
+        };
+        let page: Box<dyn Iterator<Item = _>> = if reversed {
+            Box::new(iter.rev().skip(offset).take(limit))
+        } else {
+            Box::new(iter.skip(offset).take(limit))
+        };
+        page.map(|entry| {
+            let (key, value) = entry.chain(|| {
+                (
+                    ErrorKind::StorageError,
+                    format!(
+                        "Unable to retrieve range of keyspace: {}",
+                        String::from_utf8_lossy(keyspace.as_ref())
+                    ),
+                )
+            })?;
+            Ok((key.as_ref().to_vec(), value.as_ref().to_vec()))
+        })
+        .collect()
+    }
+
     fn keyspaces(&self) -> Result<Vec<Vec<u8>>> {
         let mut result = Vec::with_capacity(self.0.tree_names().len());
         for name in self.0.tree_names().iter() {
@@ -256,12 +337,62 @@ impl Storage for SledStorage {
         }
         Ok(result)
     }
+
+    fn write_batch(&self, batch: StorageBatch) -> Result<()> {
+        let mut keyspaces = batch
+            .operations
+            .iter()
+            .map(|operation| operation.keyspace.clone())
+            .collect::<Vec<_>>();
+        keyspaces.sort();
+        keyspaces.dedup();
+
+        let trees = keyspaces
+            .iter()
+            .map(|keyspace| {
+                self.0.open_tree(keyspace).chain(|| {
+                    (
+                        ErrorKind::StorageError,
+                        format!(
+                            "Unable to open sled storage tree for keyspace: {}",
+                            String::from_utf8_lossy(keyspace)
+                        ),
+                    )
+                })
+            })
+            .collect::<Result<Vec<Tree>>>()?;
+
+        trees
+            .as_slice()
+            .transaction(|trees| {
+                for operation in batch.operations.iter() {
+                    let index = keyspaces
+                        .binary_search(&operation.keyspace)
+                        .expect("keyspace of batch operation");
+                    match operation.value {
+                        Some(ref value) => {
+                            trees[index].insert(operation.key.as_slice(), value.as_slice())?;
+                        }
+                        None => {
+                            trees[index].remove(operation.key.as_slice())?;
+                        }
+                    }
+                }
+                Ok::<_, ConflictableTransactionError<()>>(())
+            })
+            .map_err(|err: TransactionError<()>| {
+                Error::new(
+                    ErrorKind::StorageError,
+                    format!("Unable to write batch: {:?}", err),
+                )
+            })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::SledStorage;
-    use crate::Storage;
+    use crate::{Storage, StorageBatch};
 
     #[test]
     fn check_flow() {
@@ -328,4 +459,63 @@ mod tests {
             "More than two keyspaces present"
         );
     }
+
+    #[test]
+    fn check_write_batch() {
+        let dir = tempfile::tempdir().expect("Unable to create temporary directory");
+        let storage = SledStorage::new(dir.path()).expect("Unable to start sled storage");
+        storage
+            .set("keyspace1", "key1", b"value1".to_vec())
+            .expect("Unable to set value");
+
+        let mut batch = StorageBatch::default();
+        batch.delete("keyspace1", "key1");
+        batch.set("keyspace1", "key2", b"value2".to_vec());
+        batch.set("keyspace2", "key3", b"value3".to_vec());
+        assert_eq!(3, batch.len());
+        storage.write_batch(batch).expect("Unable to write batch");
+
+        assert_eq!(None, storage.get("keyspace1", "key1").unwrap());
+        assert_eq!(
+            Some(b"value2".to_vec()),
+            storage.get("keyspace1", "key2").unwrap()
+        );
+        assert_eq!(
+            Some(b"value3".to_vec()),
+            storage.get("keyspace2", "key3").unwrap()
+        );
+    }
+
+    #[test]
+    fn check_range() {
+        let dir = tempfile::tempdir().expect("Unable to create temporary directory");
+        let storage = SledStorage::new(dir.path()).expect("Unable to start sled storage");
+        for key in &[&[1, 2][..], &[1, 0xff], &[2], &[3]] {
+            storage
+                .set("keyspace", key, key.to_vec())
+                .expect("Unable to set value");
+        }
+
+        let entries = storage
+            .range("keyspace", &[1, 3], Some(&[3]))
+            .expect("Unable to get range");
+        assert_eq!(
+            vec![(vec![1, 0xff], vec![1, 0xff]), (vec![2], vec![2])],
+            entries
+        );
+        assert_eq!(2, storage.range("keyspace", &[2], None).unwrap().len());
+        assert!(storage
+            .range("keyspace", &[3], Some(&[2]))
+            .unwrap()
+            .is_empty());
+        assert_eq!(2, storage.iter_prefix("keyspace", &[1]).unwrap().len());
+
+        let page = storage
+            .range_page("keyspace", &[1], None, 1, 2, true)
+            .expect("Unable to get range page");
+        assert_eq!(
+            vec![(vec![2], vec![2]), (vec![1, 0xff], vec![1, 0xff])],
+            page
+        );
+    }
 }
@@ -1,4 +1,4 @@
-use crate::{ErrorKind, Result, Storage};
+use crate::{ErrorKind, Result, Storage, StorageBatch};
 
 /// `Storage` which returns `PermissionDenied` error for each function call.
 #[derive(Debug, Default, Clone, Copy)]
@@ -57,4 +57,29 @@ impl Storage for UnauthorizedStorage {
     fn keyspaces(&self) -> Result<Vec<Vec<u8>>> {
         Err(ErrorKind::PermissionDenied.into())
     }
+
+    fn range<S: AsRef<[u8]>>(
+        &self,
+        _keyspace: S,
+        _start: &[u8],
+        _end: Option<&[u8]>,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn range_page<S: AsRef<[u8]>>(
+        &self,
+        _keyspace: S,
+        _start: &[u8],
+        _end: Option<&[u8]>,
+        _offset: usize,
+        _limit: usize,
+        _reversed: bool,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn write_batch(&self, _batch: StorageBatch) -> Result<()> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
 }
@@ -1,5 +1,7 @@
 //! Tendermint client operations
+mod chain_id_guard;
 mod client;
+mod failover_client;
 #[cfg(feature = "websocket-rpc")]
 mod rpc_client;
 mod unauthorized_client;
@@ -8,7 +10,9 @@ pub mod lite;
 pub mod mock;
 pub mod types;
 
+pub use chain_id_guard::{ChainIdCheck, ChainIdGuard};
 pub use client::Client;
+pub use failover_client::FailoverClient;
 #[cfg(feature = "websocket-rpc")]
 pub use rpc_client::WebsocketRpcClient;
 pub use unauthorized_client::UnauthorizedClient;
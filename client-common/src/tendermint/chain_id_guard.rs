@@ -0,0 +1,376 @@
+//! Checks that the tendermint nodes of a client are on the chain it's configured for, so that a
+//! wallet pointed at the wrong network (e.g. a testnet node instead of a mainnet one) doesn't
+//! build and broadcast transactions for it.
+//!
+//! The client of each node is wrapped in a `ChainIdGuard`, which compares the chain hex id of the
+//! node's genesis with the configured one before its first call: a node which is only used after
+//! a failover is checked when the client switches to it. The guards of a client share a
+//! `ChainIdCheck`; once a node is found on another chain, the broadcasts are refused (unless the
+//! mismatches are allowed, for development), the reads are still served, and `chain_status`
+//! reports the mismatch.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::tendermint::types::*;
+use crate::tendermint::Client;
+use crate::{Error, ErrorKind, Result};
+use chain_core::state::ChainState;
+
+/// Chain id checks of the nodes of a client (the clones share the results)
+#[derive(Debug, Clone)]
+pub struct ChainIdCheck {
+    expected_chain_hex_id: u8,
+    allow_mismatch: bool,
+    mismatch: Arc<Mutex<Option<ChainIdMismatch>>>,
+}
+
+impl ChainIdCheck {
+    /// Checks the nodes against `expected_chain_hex_id`; with `allow_mismatch`, a mismatch is
+    /// only reported
+    pub fn new(expected_chain_hex_id: u8, allow_mismatch: bool) -> Self {
+        ChainIdCheck {
+            expected_chain_hex_id,
+            allow_mismatch,
+            mismatch: Default::default(),
+        }
+    }
+
+    /// Wraps the client of a node, which is then checked before its first call
+    pub fn guard<C: Client>(&self, client: C) -> ChainIdGuard<C> {
+        ChainIdGuard {
+            client,
+            check: self.clone(),
+            checked: Default::default(),
+        }
+    }
+
+    /// The first mismatch found; it isn't cleared by the later checks, as the wallets may have
+    /// been synced from the other chain in the meantime
+    pub fn mismatch(&self) -> Option<ChainIdMismatch> {
+        self.mismatch
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Fails if a node was found on another chain (unless the mismatches are allowed)
+    pub fn ensure_matching(&self) -> Result<()> {
+        match self.mismatch() {
+            Some(mismatch) if !self.allow_mismatch => Err(Error::new(
+                ErrorKind::ChainIdMismatch,
+                format!(
+                    "Connected to a node of chain {}, but configured for chain hex id {}, transactions won't be broadcast",
+                    mismatch.node_chain_id, mismatch.expected_chain_hex_id
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn record(&self, node_chain_id: &str) {
+        if chain_hex_id(node_chain_id) == Some(self.expected_chain_hex_id) {
+            return;
+        }
+        log::error!(
+            "node is on chain {}, but the client is configured for chain hex id {:02X}",
+            node_chain_id,
+            self.expected_chain_hex_id
+        );
+        let mut mismatch = self.mismatch.lock().unwrap_or_else(PoisonError::into_inner);
+        if mismatch.is_none() {
+            *mismatch = Some(ChainIdMismatch {
+                expected_chain_hex_id: format!("{:02X}", self.expected_chain_hex_id),
+                node_chain_id: node_chain_id.to_owned(),
+            });
+        }
+    }
+}
+
+/// `Client` of a node which is checked to be on the configured chain (see the module
+/// documentation)
+#[derive(Debug, Clone)]
+pub struct ChainIdGuard<C: Client> {
+    client: C,
+    check: ChainIdCheck,
+    checked: Arc<AtomicBool>,
+}
+
+impl<C: Client> ChainIdGuard<C> {
+    /// Compares the chain id of the node with the configured one (e.g. on startup); fails if a
+    /// node of the client is on another chain (unless the mismatches are allowed)
+    pub fn check(&self) -> Result<()> {
+        let genesis = self.client.genesis()?;
+        self.check_genesis(&genesis);
+        self.check.ensure_matching()
+    }
+
+    fn check_genesis(&self, genesis: &Genesis) {
+        self.check.record(genesis.chain_id.as_str());
+        self.checked.store(true, Ordering::SeqCst);
+    }
+
+    /// checks the node before its first call; a failed `genesis` call is retried on the next one
+    fn ensure_checked(&self) {
+        if self.checked.load(Ordering::SeqCst) {
+            return;
+        }
+        match self.client.genesis() {
+            Ok(genesis) => self.check_genesis(&genesis),
+            Err(err) => log::warn!("unable to check the chain id of the node: {}", err),
+        }
+    }
+}
+
+impl<C: Client> Client for ChainIdGuard<C> {
+    fn genesis(&self) -> Result<Genesis> {
+        let genesis = self.client.genesis()?;
+        if !self.checked.load(Ordering::SeqCst) {
+            self.check_genesis(&genesis);
+        }
+        Ok(genesis)
+    }
+
+    fn status(&self) -> Result<StatusResponse> {
+        self.ensure_checked();
+        self.client.status()
+    }
+
+    fn chain_status(&self) -> Result<ChainStatus> {
+        self.ensure_checked();
+        let mut status = self.client.chain_status()?;
+        status.chain_id_mismatch = status.chain_id_mismatch.or_else(|| self.check.mismatch());
+        Ok(status)
+    }
+
+    fn block(&self, height: u64) -> Result<Block> {
+        self.ensure_checked();
+        self.client.block(height)
+    }
+
+    fn block_batch<'a, T: Iterator<Item = &'a u64>>(&self, heights: T) -> Result<Vec<Block>> {
+        self.ensure_checked();
+        self.client.block_batch(heights)
+    }
+
+    fn signed_header(&self, height: u64) -> Result<SignedHeader> {
+        self.ensure_checked();
+        self.client.signed_header(height)
+    }
+
+    fn validators(&self, height: u64) -> Result<ValidatorSet> {
+        self.ensure_checked();
+        self.client.validators(height)
+    }
+
+    fn block_results(&self, height: u64) -> Result<BlockResultsResponse> {
+        self.ensure_checked();
+        self.client.block_results(height)
+    }
+
+    fn block_results_batch<'a, T: Iterator<Item = &'a u64>>(
+        &self,
+        heights: T,
+    ) -> Result<Vec<BlockResultsResponse>> {
+        self.ensure_checked();
+        self.client.block_results_batch(heights)
+    }
+
+    /// Refused if a node of the client is on another chain (or if the node can't be checked)
+    fn broadcast_transaction(&self, transaction: &[u8]) -> Result<BroadcastTxResponse> {
+        if !self.checked.load(Ordering::SeqCst) {
+            let genesis = self.client.genesis()?;
+            self.check_genesis(&genesis);
+        }
+        self.check.ensure_matching()?;
+        self.client.broadcast_transaction(transaction)
+    }
+
+    fn query(
+        &self,
+        path: &str,
+        data: &[u8],
+        height: Option<Height>,
+        prove: bool,
+    ) -> Result<AbciQuery> {
+        self.ensure_checked();
+        self.client.query(path, data, height, prove)
+    }
+
+    fn query_state_batch<T: Iterator<Item = u64>>(&self, heights: T) -> Result<Vec<ChainState>> {
+        self.ensure_checked();
+        self.client.query_state_batch(heights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    use crate::tendermint::{mock, FailoverClient};
+
+    /// Node of the chain `chain_id`, which pruned the blocks below `earliest`
+    #[derive(Clone)]
+    struct MockNode {
+        chain_id: &'static str,
+        earliest: u64,
+        broadcast: Arc<AtomicUsize>,
+    }
+
+    impl MockNode {
+        fn new(chain_id: &'static str, earliest: u64) -> Self {
+            MockNode {
+                chain_id,
+                earliest,
+                broadcast: Default::default(),
+            }
+        }
+    }
+
+    impl Client for MockNode {
+        fn genesis(&self) -> Result<Genesis> {
+            let mut genesis = mock::genesis();
+            genesis.chain_id = self.chain_id.parse().unwrap();
+            Ok(genesis)
+        }
+
+        fn status(&self) -> Result<StatusResponse> {
+            unreachable!()
+        }
+
+        fn chain_status(&self) -> Result<ChainStatus> {
+            Ok(ChainStatus {
+                latest_block_height: 200,
+                earliest_block_height: self.earliest,
+                catching_up: false,
+                chain_id_mismatch: None,
+                history_mismatch: Vec::new(),
+            })
+        }
+
+        fn block(&self, height: u64) -> Result<Block> {
+            if height < self.earliest {
+                return Err(Error::new(
+                    ErrorKind::BlockPruned {
+                        earliest_available: self.earliest,
+                    },
+                    "pruned",
+                ));
+            }
+            Ok(mock::block())
+        }
+
+        fn block_batch<'a, T: Iterator<Item = &'a u64>>(&self, heights: T) -> Result<Vec<Block>> {
+            heights.map(|height| self.block(*height)).collect()
+        }
+
+        fn block_results(&self, _height: u64) -> Result<BlockResultsResponse> {
+            unreachable!()
+        }
+
+        fn block_results_batch<'a, T: Iterator<Item = &'a u64>>(
+            &self,
+            _heights: T,
+        ) -> Result<Vec<BlockResultsResponse>> {
+            unreachable!()
+        }
+
+        fn broadcast_transaction(&self, _transaction: &[u8]) -> Result<BroadcastTxResponse> {
+            self.broadcast.fetch_add(1, Ordering::SeqCst);
+            Err(Error::new(
+                ErrorKind::TendermintRpcError,
+                "not a mock response",
+            ))
+        }
+
+        fn query(
+            &self,
+            _path: &str,
+            _data: &[u8],
+            _height: Option<Height>,
+            _prove: bool,
+        ) -> Result<AbciQuery> {
+            unreachable!()
+        }
+
+        fn query_state_batch<T: Iterator<Item = u64>>(
+            &self,
+            _heights: T,
+        ) -> Result<Vec<ChainState>> {
+            unreachable!()
+        }
+    }
+
+    fn is_refused<T>(result: Result<T>) -> bool {
+        match result {
+            Err(err) => err.kind() == ErrorKind::ChainIdMismatch,
+            Ok(_) => false,
+        }
+    }
+
+    #[test]
+    fn check_failover_to_node_of_other_chain_blocks_broadcasts() {
+        let check = ChainIdCheck::new(0xab, false);
+        let primary = MockNode::new("test-chain-y3m1e6-AB", 100);
+        let archive = MockNode::new("test-chain-y3m1e6-CD", 1);
+        let client = FailoverClient::new(check.guard(primary.clone()), check.guard(archive));
+
+        assert!(!is_refused(client.broadcast_transaction(&[])));
+        assert_eq!(1, primary.broadcast.load(Ordering::SeqCst));
+        assert_eq!(None, check.mismatch());
+
+        // the client switches to the archive node for a pruned block
+        client.block(150).unwrap();
+        client.block(5).unwrap();
+
+        assert!(is_refused(client.broadcast_transaction(&[])));
+        assert_eq!(1, primary.broadcast.load(Ordering::SeqCst));
+        // the reads are still served
+        client.block(150).unwrap();
+        client.block(5).unwrap();
+        assert_eq!(
+            Some(ChainIdMismatch {
+                expected_chain_hex_id: "AB".to_owned(),
+                node_chain_id: "test-chain-y3m1e6-CD".to_owned(),
+            }),
+            client.chain_status().unwrap().chain_id_mismatch
+        );
+    }
+
+    #[test]
+    fn check_mismatch_is_found_on_startup() {
+        let check = ChainIdCheck::new(0xab, false);
+        let client = check.guard(MockNode::new("mainnet-crypto-com-chain-2A", 1));
+        assert!(is_refused(client.check()));
+        assert!(is_refused(client.broadcast_transaction(&[])));
+        assert!(client.block(1).is_ok());
+
+        assert!(check
+            .guard(MockNode::new("test-chain-y3m1e6-AB", 1))
+            .check()
+            .is_err());
+        assert!(ChainIdCheck::new(0xab, false)
+            .guard(MockNode::new("test-chain-y3m1e6-AB", 1))
+            .check()
+            .is_ok());
+    }
+
+    #[test]
+    fn check_allowed_mismatch_is_only_reported() {
+        let check = ChainIdCheck::new(0xab, true);
+        let node = MockNode::new("chain-without-hex-id", 1);
+        let client = check.guard(node.clone());
+        assert!(client.check().is_ok());
+        assert!(!is_refused(client.broadcast_transaction(&[])));
+        assert_eq!(1, node.broadcast.load(Ordering::SeqCst));
+        assert_eq!(
+            "chain-without-hex-id",
+            client
+                .chain_status()
+                .unwrap()
+                .chain_id_mismatch
+                .unwrap()
+                .node_chain_id
+        );
+    }
+}
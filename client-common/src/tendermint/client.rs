@@ -1,6 +1,10 @@
+use parity_scale_codec::Decode;
+
 use crate::tendermint::types::*;
-use crate::Result;
+use crate::{Error, ErrorKind, Result, ResultExt};
 use chain_core::state::ChainState;
+use chain_core::tx::data::TxId;
+use chain_core::tx::result::{TxResult, TX_RESULT_NOT_FOUND_LOG, TX_RESULT_PATH_PREFIX};
 
 /// Makes remote calls to tendermint (backend agnostic)
 pub trait Client: Send + Sync + Clone {
@@ -10,12 +14,46 @@ pub trait Client: Send + Sync + Clone {
     /// Makes `status` call to tendermint
     fn status(&self) -> Result<StatusResponse>;
 
+    /// Heights of the blocks available on the node (from the `status` call); nodes which don't
+    /// report their earliest block are assumed to keep all the blocks
+    fn chain_status(&self) -> Result<ChainStatus> {
+        let status = self.status()?;
+        Ok(ChainStatus {
+            latest_block_height: status.sync_info.latest_block_height.value(),
+            earliest_block_height: 1,
+            catching_up: status.sync_info.catching_up,
+            chain_id_mismatch: None,
+            history_mismatch: Vec::new(),
+        })
+    }
+
     /// Makes `block` call to tendermint
     fn block(&self, height: u64) -> Result<Block>;
 
     /// Makes batched `block` call to tendermint
     fn block_batch<'a, T: Iterator<Item = &'a u64>>(&self, heights: T) -> Result<Vec<Block>>;
 
+    /// Header of the block at given height with the commit of its validators (by default, from the
+    /// next block, which includes the commit)
+    fn signed_header(&self, height: u64) -> Result<SignedHeader> {
+        let header = self.block(height)?.header;
+        let commit = self.block(height + 1)?.last_commit.chain(|| {
+            (
+                ErrorKind::TendermintRpcError,
+                format!("Commit of block {} not found", height),
+            )
+        })?;
+        Ok(SignedHeader { header, commit })
+    }
+
+    /// Makes `validators` call to tendermint (not supported by default)
+    fn validators(&self, height: u64) -> Result<ValidatorSet> {
+        Err(Error::new(
+            ErrorKind::TendermintRpcError,
+            format!("Validators of block {} are not available", height),
+        ))
+    }
+
     /// Makes `block_results` call to tendermint
     fn block_results(&self, height: u64) -> Result<BlockResultsResponse>;
 
@@ -42,4 +80,23 @@ pub trait Client: Send + Sync + Clone {
 
     /// Match batch state `abci_query` call to tendermint
     fn query_state_batch<T: Iterator<Item = u64>>(&self, heights: T) -> Result<Vec<ChainState>>;
+
+    /// Result of the delivered transaction from the tx result index of the node (`txresult/<txid>`
+    /// query); `None` if the node didn't index it (unknown or pruned transaction), an error if the
+    /// node doesn't index the results
+    fn tx_result(&self, txid: &TxId) -> Result<Option<TxResult>> {
+        let path = format!("{}{}", TX_RESULT_PATH_PREFIX, hex::encode(txid));
+        match self.query(&path, &[], None, false) {
+            Ok(response) => TxResult::decode(&mut response.bytes().as_slice())
+                .map(Some)
+                .chain(|| {
+                    (
+                        ErrorKind::DeserializationError,
+                        "Unable to decode tx result",
+                    )
+                }),
+            Err(err) if err.message().contains(TX_RESULT_NOT_FOUND_LOG) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
 }
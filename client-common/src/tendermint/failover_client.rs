@@ -0,0 +1,336 @@
+use crate::{
+    tendermint::{types::*, Client},
+    Error, ErrorKind, Result,
+};
+use chain_core::state::ChainState;
+
+/// `Client` which makes the calls to a (pruning) primary node, and repeats the calls for the
+/// heights pruned by it (`ErrorKind::BlockPruned`) on an archive node (if one is configured).
+#[derive(Debug, Clone)]
+pub struct FailoverClient<P, A>
+where
+    P: Client,
+    A: Client,
+{
+    primary: P,
+    archive: Option<A>,
+}
+
+impl<P, A> FailoverClient<P, A>
+where
+    P: Client,
+    A: Client,
+{
+    /// Creates a new instance of `FailoverClient`
+    #[inline]
+    pub fn new(primary: P, archive: A) -> Self {
+        Self::with_archive(primary, Some(archive))
+    }
+
+    /// Creates a new instance of `FailoverClient` with an optional archive node (without one, the
+    /// pruned heights are reported as by the primary node)
+    #[inline]
+    pub fn with_archive(primary: P, archive: Option<A>) -> Self {
+        Self { primary, archive }
+    }
+
+    fn with_failover<T>(
+        &self,
+        primary: impl FnOnce(&P) -> Result<T>,
+        archive: impl FnOnce(&A) -> Result<T>,
+    ) -> Result<T> {
+        match (primary(&self.primary), &self.archive) {
+            (Err(err), Some(node)) if is_pruned(&err) => {
+                log::info!("{}, retrying on the archive node", err);
+                archive(node)
+            }
+            (result, _) => result,
+        }
+    }
+}
+
+fn is_pruned(err: &Error) -> bool {
+    match err.kind() {
+        ErrorKind::BlockPruned { .. } => true,
+        _ => false,
+    }
+}
+
+impl<P, A> Client for FailoverClient<P, A>
+where
+    P: Client,
+    A: Client,
+{
+    fn genesis(&self) -> Result<Genesis> {
+        self.primary.genesis()
+    }
+
+    fn status(&self) -> Result<StatusResponse> {
+        self.primary.status()
+    }
+
+    /// Heights of the primary node; the earliest one is the one of the archive node (if it
+    /// keeps more blocks)
+    fn chain_status(&self) -> Result<ChainStatus> {
+        let mut status = self.primary.chain_status()?;
+        let archive = match self.archive {
+            Some(ref archive) => archive.chain_status()?,
+            None => return Ok(status),
+        };
+        status.earliest_block_height = status
+            .earliest_block_height
+            .min(archive.earliest_block_height);
+        status.chain_id_mismatch = status.chain_id_mismatch.or(archive.chain_id_mismatch);
+        Ok(status)
+    }
+
+    fn block(&self, height: u64) -> Result<Block> {
+        self.with_failover(|node| node.block(height), |node| node.block(height))
+    }
+
+    fn block_batch<'a, T: Iterator<Item = &'a u64>>(&self, heights: T) -> Result<Vec<Block>> {
+        let heights = heights.copied().collect::<Vec<_>>();
+        self.with_failover(
+            |node| node.block_batch(heights.iter()),
+            |node| node.block_batch(heights.iter()),
+        )
+    }
+
+    fn signed_header(&self, height: u64) -> Result<SignedHeader> {
+        self.with_failover(
+            |node| node.signed_header(height),
+            |node| node.signed_header(height),
+        )
+    }
+
+    fn validators(&self, height: u64) -> Result<ValidatorSet> {
+        self.with_failover(
+            |node| node.validators(height),
+            |node| node.validators(height),
+        )
+    }
+
+    fn block_results(&self, height: u64) -> Result<BlockResultsResponse> {
+        self.with_failover(
+            |node| node.block_results(height),
+            |node| node.block_results(height),
+        )
+    }
+
+    fn block_results_batch<'a, T: Iterator<Item = &'a u64>>(
+        &self,
+        heights: T,
+    ) -> Result<Vec<BlockResultsResponse>> {
+        let heights = heights.copied().collect::<Vec<_>>();
+        self.with_failover(
+            |node| node.block_results_batch(heights.iter()),
+            |node| node.block_results_batch(heights.iter()),
+        )
+    }
+
+    fn broadcast_transaction(&self, transaction: &[u8]) -> Result<BroadcastTxResponse> {
+        self.primary.broadcast_transaction(transaction)
+    }
+
+    fn query(
+        &self,
+        path: &str,
+        data: &[u8],
+        height: Option<Height>,
+        prove: bool,
+    ) -> Result<AbciQuery> {
+        self.with_failover(
+            |node| node.query(path, data, height, prove),
+            |node| node.query(path, data, height, prove),
+        )
+    }
+
+    fn query_state_batch<T: Iterator<Item = u64>>(&self, heights: T) -> Result<Vec<ChainState>> {
+        let heights = heights.collect::<Vec<_>>();
+        self.with_failover(
+            |node| node.query_state_batch(heights.iter().copied()),
+            |node| node.query_state_batch(heights.iter().copied()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use crate::tendermint::mock;
+
+    /// Node which pruned the blocks below `earliest`
+    #[derive(Clone)]
+    struct MockNode {
+        earliest: u64,
+        latest: u64,
+        served: Arc<AtomicUsize>,
+    }
+
+    impl MockNode {
+        fn new(earliest: u64, latest: u64) -> Self {
+            MockNode {
+                earliest,
+                latest,
+                served: Default::default(),
+            }
+        }
+
+        fn served(&self) -> usize {
+            self.served.load(Ordering::SeqCst)
+        }
+
+        fn check(&self, height: u64) -> Result<()> {
+            if height < self.earliest {
+                return Err(Error::new(
+                    ErrorKind::BlockPruned {
+                        earliest_available: self.earliest,
+                    },
+                    format!(
+                        "height {} is not available, lowest height is {}",
+                        height, self.earliest
+                    ),
+                ));
+            }
+            self.served.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    impl Client for MockNode {
+        fn genesis(&self) -> Result<Genesis> {
+            unreachable!()
+        }
+
+        fn status(&self) -> Result<StatusResponse> {
+            unreachable!()
+        }
+
+        fn chain_status(&self) -> Result<ChainStatus> {
+            Ok(ChainStatus {
+                latest_block_height: self.latest,
+                earliest_block_height: self.earliest,
+                catching_up: false,
+                chain_id_mismatch: None,
+                history_mismatch: Vec::new(),
+            })
+        }
+
+        fn block(&self, height: u64) -> Result<Block> {
+            self.check(height)?;
+            Ok(mock::block())
+        }
+
+        fn block_batch<'a, T: Iterator<Item = &'a u64>>(&self, heights: T) -> Result<Vec<Block>> {
+            heights.map(|height| self.block(*height)).collect()
+        }
+
+        fn block_results(&self, _height: u64) -> Result<BlockResultsResponse> {
+            unreachable!()
+        }
+
+        fn block_results_batch<'a, T: Iterator<Item = &'a u64>>(
+            &self,
+            _heights: T,
+        ) -> Result<Vec<BlockResultsResponse>> {
+            unreachable!()
+        }
+
+        fn broadcast_transaction(&self, _transaction: &[u8]) -> Result<BroadcastTxResponse> {
+            unreachable!()
+        }
+
+        fn query(
+            &self,
+            _path: &str,
+            _data: &[u8],
+            _height: Option<Height>,
+            _prove: bool,
+        ) -> Result<AbciQuery> {
+            unreachable!()
+        }
+
+        fn query_state_batch<T: Iterator<Item = u64>>(
+            &self,
+            _heights: T,
+        ) -> Result<Vec<ChainState>> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn check_pruned_height_error_parsing() {
+        assert_eq!(
+            Some(100),
+            parse_pruned_height_error("height 5 is not available, lowest height is 100")
+        );
+        assert_eq!(
+            Some(100),
+            parse_pruned_height_error(
+                "Internal error: height 5 is not available, lowest height is 100 (code: -32603)"
+            )
+        );
+        assert_eq!(
+            None,
+            parse_pruned_height_error(
+                "height must be less than or equal to the current blockchain height"
+            )
+        );
+        assert_eq!(None, parse_pruned_height_error("lowest height is 100"));
+    }
+
+    #[test]
+    fn check_pruned_blocks_are_fetched_from_archive() {
+        let primary = MockNode::new(100, 200);
+        let archive = MockNode::new(1, 200);
+        let client = FailoverClient::new(primary.clone(), archive.clone());
+
+        client.block(150).unwrap();
+        assert_eq!((1, 0), (primary.served(), archive.served()));
+
+        client.block(5).unwrap();
+        assert_eq!((1, 1), (primary.served(), archive.served()));
+
+        // a batch crossing the earliest height is fetched from the archive node as a whole
+        assert_eq!(3, client.block_batch([98, 99, 100].iter()).unwrap().len());
+        assert_eq!((1, 4), (primary.served(), archive.served()));
+
+        let status = client.chain_status().unwrap();
+        assert_eq!(1, status.earliest_block_height);
+        assert_eq!(200, status.latest_block_height);
+    }
+
+    #[test]
+    fn check_blocks_pruned_by_both_nodes_are_reported() {
+        let client = FailoverClient::new(MockNode::new(100, 200), MockNode::new(50, 200));
+
+        let err = client.block(5).unwrap_err();
+        assert_eq!(
+            ErrorKind::BlockPruned {
+                earliest_available: 50
+            },
+            err.kind()
+        );
+        assert_eq!(50, client.chain_status().unwrap().earliest_block_height);
+    }
+
+    #[test]
+    fn check_pruned_blocks_without_archive_are_reported() {
+        let primary = MockNode::new(100, 200);
+        let client = FailoverClient::<_, MockNode>::with_archive(primary.clone(), None);
+
+        client.block(150).unwrap();
+        let err = client.block(5).unwrap_err();
+        assert_eq!(
+            ErrorKind::BlockPruned {
+                earliest_available: 100
+            },
+            err.kind()
+        );
+        assert_eq!(1, primary.served());
+        assert_eq!(100, client.chain_status().unwrap().earliest_block_height);
+    }
+}
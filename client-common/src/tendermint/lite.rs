@@ -4,7 +4,8 @@ use serde::{Deserialize, Serialize};
 use tendermint::{block::signed_header::SignedHeader, block::Header, lite, validator};
 
 use crate::tendermint::client::Client;
-use crate::Result as CommonResult;
+use crate::tendermint::types::{BlockResults, BlockResultsResponse};
+use crate::{Error as CommonError, ErrorKind, Result as CommonResult};
 
 ///
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -45,3 +46,37 @@ where
 {
     Ok(validator::Set::new(client.genesis()?.validators))
 }
+
+/// check that validator set update events of a block are consistent with the validator hashes
+/// of the next block header (updates at height H change the validator set at height H+2,
+/// so the validators hash and the next validators hash of H+1 differ iff there are updates)
+///
+/// Blocks without a validator set update event are not checked, as the event is only emitted
+/// for non-empty updates and older nodes don't emit it at all.
+pub fn check_validator_set_transition(
+    block_results: &BlockResultsResponse,
+    next_header: &Header,
+) -> CommonResult<()> {
+    let changes = match block_results.validator_set_updates()? {
+        Some(changes) => changes,
+        None => return Ok(()),
+    };
+    let hash_changed = next_header.validators_hash != next_header.next_validators_hash;
+    if hash_changed == changes.is_empty() {
+        return Err(CommonError::new(
+            ErrorKind::VerifyError,
+            format!(
+                "{} validator set updates at height {}, but next validators hash {} at height {}",
+                changes.len(),
+                block_results.height,
+                if hash_changed {
+                    "changed"
+                } else {
+                    "didn't change"
+                },
+                next_header.height
+            ),
+        ));
+    }
+    Ok(())
+}
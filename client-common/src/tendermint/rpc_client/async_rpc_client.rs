@@ -123,10 +123,11 @@ impl AsyncRpcClient {
             let method = batch_params[i].0;
             let params = &batch_params[i].1;
 
-            if let Ok(response) = self.receive_response(method, params, &id, receiver).await {
-                responses.push(response);
-            } else {
-                break;
+            match self.receive_response(method, params, &id, receiver).await {
+                Ok(response) => responses.push(response),
+                // nothing to return, e.g. the first block of the batch was pruned
+                Err(err) if responses.is_empty() => return Err(err),
+                Err(_) => break,
             }
         }
 
@@ -225,12 +226,11 @@ impl AsyncRpcClient {
         };
 
         match response.error {
-            Some(err) => bail!(
-                "Error response from tendermint RPC for request method ({}) and params ({:?}): {}",
-                method,
-                params,
-                err
-            ),
+            // the error response is kept as the source, so that it can be inspected by the caller
+            Some(err) => Err(anyhow::Error::new(err).context(format!(
+                "Error response from tendermint RPC for request method ({}) and params ({:?})",
+                method, params
+            ))),
             None => Ok(response.result.unwrap_or_default()),
         }
     }
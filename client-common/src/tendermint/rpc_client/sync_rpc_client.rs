@@ -13,6 +13,7 @@ use chain_core::state::ChainState;
 use std::sync::Mutex;
 
 use super::async_rpc_client::AsyncRpcClient;
+use super::types::JsonRpcError;
 use crate::{
     tendermint::{types::*, Client},
     Error, ErrorKind, PrivateKey, Result, ResultExt, SignedTransaction, Transaction,
@@ -126,7 +127,7 @@ impl SyncRpcClient {
         let mut runtime = self.runtime.lock().unwrap();
         let async_rpc_client = runtime.block_on(AsyncRpcClient::new(&self.url)).chain(|| {
             (
-                ErrorKind::InitializationError,
+                ErrorKind::ConnectionError,
                 format!(
                     "Unable to connect to tendermint RPC websocket at: {}",
                     self.url
@@ -156,15 +157,10 @@ impl SyncRpcClient {
             }
         });
 
-        receiver
+        let response = receiver
             .recv_timeout(RESPONSE_TIMEOUT)
-            .chain(|| (ErrorKind::TendermintRpcError, "Request timed out"))?
-            .chain(|| {
-                (
-                    ErrorKind::TendermintRpcError,
-                    "Error while calling tendermint RPC call",
-                )
-            })
+            .chain(|| (ErrorKind::ConnectionError, "Request timed out"))?;
+        into_result(response)
     }
 
     /// Makes RPC call in batch and deserializes responses
@@ -186,16 +182,33 @@ impl SyncRpcClient {
             }
         });
 
-        receiver
+        let response = receiver
             .recv_timeout(RESPONSE_TIMEOUT)
-            .chain(|| (ErrorKind::TendermintRpcError, "Request timed out"))?
-            .chain(|| {
-                (
-                    ErrorKind::TendermintRpcError,
-                    "Error while calling tendermint RPC call",
-                )
-            })
+            .chain(|| (ErrorKind::ConnectionError, "Request timed out"))?;
+        into_result(response)
+    }
+}
+
+/// Converts the response of the async client; the error response of tendermint for a pruned
+/// height is converted to `ErrorKind::BlockPruned`
+fn into_result<T>(response: anyhow::Result<T>) -> Result<T> {
+    if let Err(err) = &response {
+        if let Some(earliest_available) = err
+            .downcast_ref::<JsonRpcError>()
+            .and_then(JsonRpcError::pruned_height)
+        {
+            return Err(Error::new(
+                ErrorKind::BlockPruned { earliest_available },
+                format!("{:#}", err),
+            ));
+        }
     }
+    response.chain(|| {
+        (
+            ErrorKind::TendermintRpcError,
+            "Error while calling tendermint RPC call",
+        )
+    })
 }
 
 impl Client for SyncRpcClient {
@@ -211,6 +224,27 @@ impl Client for SyncRpcClient {
         self.call("status", Default::default())
     }
 
+    /// Reads the earliest available height from the `status` response (tendermint 0.33+)
+    fn chain_status(&self) -> Result<ChainStatus> {
+        let status = self.call::<Value>("status", Default::default())?;
+        let sync_info = &status["sync_info"];
+        let height = |key: &str| sync_info[key].as_str().and_then(|h| h.parse::<u64>().ok());
+        let latest_block_height = height("latest_block_height").chain(|| {
+            (
+                ErrorKind::DeserializationError,
+                "Invalid latest block height in tendermint status",
+            )
+        })?;
+        Ok(ChainStatus {
+            latest_block_height,
+            // not reported by older nodes, 0 before the first block
+            earliest_block_height: height("earliest_block_height").unwrap_or(1).max(1),
+            catching_up: sync_info["catching_up"].as_bool().unwrap_or_default(),
+            chain_id_mismatch: None,
+            history_mismatch: Vec::new(),
+        })
+    }
+
     /// Makes `block` call to tendermint
     fn block(&self, height: u64) -> Result<Block> {
         let params = vec![json!(height.to_string())];
@@ -226,6 +260,21 @@ impl Client for SyncRpcClient {
         Ok(rsps.into_iter().map(|rsp| rsp.block).collect())
     }
 
+    /// Makes `commit` call to tendermint (which also serves the commit of the latest block)
+    fn signed_header(&self, height: u64) -> Result<SignedHeader> {
+        let params = vec![json!(height.to_string())];
+        Ok(self.call::<CommitResponse>("commit", params)?.signed_header)
+    }
+
+    /// Makes `validators` call to tendermint (first page of 100 validators, the maximum page size)
+    fn validators(&self, height: u64) -> Result<ValidatorSet> {
+        let params = vec![json!(height.to_string()), json!(1), json!(100)];
+        let validators = self
+            .call::<ValidatorsResponse>("validators", params)?
+            .validators;
+        Ok(ValidatorSet::new(validators))
+    }
+
     /// Makes `block_results` call to tendermint
     fn block_results(&self, height: u64) -> Result<BlockResultsResponse> {
         let params = vec![json!(height.to_string())];
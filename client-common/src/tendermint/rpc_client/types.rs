@@ -4,6 +4,8 @@ use std::fmt;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::tendermint::types::parse_pruned_height_error;
+
 /// Websocket connection state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConnectionState {
@@ -36,6 +38,18 @@ pub struct JsonRpcError {
     pub data: Option<Value>,
 }
 
+impl JsonRpcError {
+    /// Earliest available height, if the error is for a height pruned by the node
+    pub fn pruned_height(&self) -> Option<u64> {
+        parse_pruned_height_error(&self.message).or_else(|| {
+            self.data
+                .as_ref()
+                .and_then(Value::as_str)
+                .and_then(parse_pruned_height_error)
+        })
+    }
+}
+
 impl std::error::Error for JsonRpcError {}
 
 impl fmt::Display for JsonRpcError {
@@ -11,10 +11,10 @@ use chain_core::tx::data::TxId;
 use chain_core::tx::fee::LinearFee;
 use chain_core::tx::{TxAux, TxEnclaveAux, TxPublicAux};
 
-pub use self::block_results::BlockResults;
+pub use self::block_results::{BlockResults, StakingCoinDelta, StakingEventAmounts};
 pub use tendermint::{
-    abci, abci::transaction::Data, abci::Code, block::Header, block::Height, Block,
-    Genesis as GenericGenesis, Hash, Time,
+    abci, abci::transaction::Data, abci::Code, block::signed_header::SignedHeader, block::Header,
+    block::Height, validator::Set as ValidatorSet, Block, Genesis as GenericGenesis, Hash, Time,
 };
 pub use tendermint_rpc::endpoint::{
     abci_query::AbciQuery, abci_query::Response as AbciQueryResponse,
@@ -33,6 +33,59 @@ pub struct GenesisResponse {
     pub genesis: Genesis,
 }
 
+/// Heights of the blocks available on a tendermint node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainStatus {
+    /// Height of the latest block
+    pub latest_block_height: u64,
+    /// Height of the earliest block which wasn't pruned (1 on an archive node)
+    pub earliest_block_height: u64,
+    /// Whether the node is catching up with the network
+    pub catching_up: bool,
+    /// Set if a connected node is on another chain than the configured one (see `ChainIdGuard`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id_mismatch: Option<ChainIdMismatch>,
+    /// Names of the wallets whose synced history doesn't match their tamper-evident records (or
+    /// the node), their sends are blocked until the mismatch is acknowledged
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history_mismatch: Vec<String>,
+}
+
+/// Chain of a tendermint node which doesn't match the network the client is configured for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainIdMismatch {
+    /// Chain hex id (network id) the client is configured for, e.g. `AB`
+    pub expected_chain_hex_id: String,
+    /// Chain id of the node (in its genesis)
+    pub node_chain_id: String,
+}
+
+/// Returns the network id encoded in the last two (hex) characters of a chain id
+pub fn chain_hex_id(chain_id: &str) -> Option<u8> {
+    if chain_id.len() < 2 || !chain_id.is_char_boundary(chain_id.len() - 2) {
+        return None;
+    }
+    let bytes = hex::decode(&chain_id[chain_id.len() - 2..]).ok()?;
+    bytes.first().copied()
+}
+
+/// Parses the error message of a tendermint node for a height below its earliest available
+/// (not pruned) one, e.g. `height 5 is not available, lowest height is 100`, and returns the
+/// earliest available height
+pub fn parse_pruned_height_error(message: &str) -> Option<u64> {
+    const LOWEST_HEIGHT: &str = "lowest height is ";
+
+    if !message.contains("is not available") {
+        return None;
+    }
+    let start = message.find(LOWEST_HEIGHT)? + LOWEST_HEIGHT.len();
+    let digits = message[start..]
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect::<String>();
+    digits.parse().ok()
+}
+
 /// crypto-chain specific methods.
 pub trait BlockExt {
     /// Returns un-encrypted staking(deposit/unbound) transactions in a block
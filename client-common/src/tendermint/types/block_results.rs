@@ -1,14 +1,16 @@
 #![allow(missing_docs)]
 use indexmap::IndexMap;
-use std::convert::TryFrom;
 use std::str::{from_utf8, FromStr};
 
 use chain_core::common::{TendermintEventKey, TendermintEventType};
 use chain_core::init::{coin::Coin, MAX_COIN_DECIMALS};
 use chain_core::state::account::StakedStateAddress;
+use chain_core::state::tendermint::ValidatorPowerChange;
 use chain_core::tx::data::TxId;
 use chain_core::tx::fee::Fee;
-use chain_tx_filter::BlockFilter;
+use chain_tx_filter::FilterKind;
+
+use serde::Deserialize;
 
 use crate::tendermint::types::BlockResultsResponse;
 use crate::{Error, ErrorKind, Result, ResultExt};
@@ -29,8 +31,44 @@ pub trait BlockResults {
     /// Returns true when contains a staking transaction
     fn contains_staking(&self) -> bool;
 
-    /// Returns block filter in block results
-    fn block_filter(&self) -> Result<BlockFilter>;
+    /// Returns block filter in block results (of the kind given by the key of its event attribute)
+    fn block_filter(&self) -> Result<FilterKind>;
+
+    /// Returns amounts of staking change events (of transactions, begin block and end block) of
+    /// given staking address, in the order they were emitted
+    fn staking_amounts(
+        &self,
+        staking_address: &StakedStateAddress,
+    ) -> Result<Vec<StakingEventAmounts>>;
+
+    /// Returns validator voting power changes of the `validator_set_update` end block event,
+    /// after checking them against the validator updates in block results, or `None` if there is
+    /// no such event (blocks without updates, or from nodes which don't emit it)
+    fn validator_set_updates(&self) -> Result<Option<Vec<ValidatorPowerChange>>>;
+}
+
+/// Signed change of a staking balance
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StakingCoinDelta {
+    /// balance increased by amount
+    Increase(Coin),
+    /// balance decreased by amount
+    Decrease(Coin),
+}
+
+/// Amounts reported by a staking change event
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct StakingEventAmounts {
+    /// Staking operation type (e.g. `slash`, `reward`)
+    pub op_type: String,
+    /// Total amount slashed from bonded and unbonded balances
+    pub slashed_amount: Option<Coin>,
+    /// Amount of reward distributed
+    pub reward_amount: Option<Coin>,
+    /// Change of bonded balance
+    pub bonded_delta: Option<StakingCoinDelta>,
+    /// Change of unbonded balance
+    pub unbonded_delta: Option<StakingCoinDelta>,
 }
 
 impl BlockResults for BlockResultsResponse {
@@ -99,13 +137,19 @@ impl BlockResults for BlockResultsResponse {
         }
     }
 
-    fn block_filter(&self) -> Result<BlockFilter> {
+    fn block_filter(&self) -> Result<FilterKind> {
         match &self.end_block_events {
-            None => Ok(BlockFilter::default()),
+            None => Ok(FilterKind::default()),
             Some(events) => {
                 for event in events.iter() {
                     if event.type_str == TendermintEventType::BlockFilter.to_string() {
                         let attribute = &event.attributes[0];
+                        let key = base64::decode(attribute.key.as_ref()).chain(|| {
+                            (
+                                ErrorKind::DeserializationError,
+                                "Unable to decode base64 bytes of block filter key in block results",
+                            )
+                        })?;
                         let decoded = base64::decode(attribute.value.as_ref()).chain(|| {
                             (
                                 ErrorKind::DeserializationError,
@@ -113,17 +157,239 @@ impl BlockResults for BlockResultsResponse {
                             )
                         })?;
 
-                        return Ok(BlockFilter::try_from(decoded.as_slice()).map_err(
+                        return Ok(FilterKind::from_tendermint_kv(&key, &decoded).map_err(
                             |message| Error::new(ErrorKind::DeserializationError, message),
                         )?);
                     }
                 }
-                Ok(BlockFilter::default())
+                Ok(FilterKind::default())
+            }
+        }
+    }
+
+    fn staking_amounts(
+        &self,
+        staking_address: &StakedStateAddress,
+    ) -> Result<Vec<StakingEventAmounts>> {
+        let tx_events = self
+            .txs_results
+            .iter()
+            .flatten()
+            .flat_map(|deliver_tx| deliver_tx.events.iter());
+        let events = self
+            .begin_block_events
+            .iter()
+            .flatten()
+            .chain(tx_events)
+            .chain(self.end_block_events.iter().flatten());
+
+        let mut amounts = Vec::new();
+        for event in events {
+            if event.type_str != TendermintEventType::StakingChange.to_string() {
+                continue;
+            }
+            if find_staking_address_from_event_attributes(&event.attributes)?.as_ref()
+                != Some(staking_address)
+            {
+                continue;
+            }
+            amounts.push(parse_staking_event_amounts(&event.attributes)?);
+        }
+        Ok(amounts)
+    }
+
+    fn validator_set_updates(&self) -> Result<Option<Vec<ValidatorPowerChange>>> {
+        let mut events = self
+            .end_block_events
+            .iter()
+            .flatten()
+            .filter(|event| event.type_str == TendermintEventType::ValidatorSetUpdate.to_string())
+            .peekable();
+        if events.peek().is_none() {
+            return Ok(None);
+        }
+
+        let mut changes = Vec::new();
+        for event in events {
+            for attribute in event.attributes.iter() {
+                let key = base64::decode(attribute.key.as_ref()).chain(|| {
+                    (
+                        ErrorKind::DeserializationError,
+                        "Unable to decode base64 bytes of attribute key in block results",
+                    )
+                })?;
+                if key != TendermintEventKey::ValidatorUpdate {
+                    continue;
+                }
+                let value = base64::decode(attribute.value.as_ref()).chain(|| {
+                    (
+                        ErrorKind::DeserializationError,
+                        "Unable to decode base64 bytes of validator update in block results",
+                    )
+                })?;
+                changes.push(serde_json::from_slice(&value).chain(|| {
+                    (
+                        ErrorKind::DeserializationError,
+                        "Invalid validator update in block results",
+                    )
+                })?);
             }
         }
+
+        if changes.len() != self.validator_updates.len() {
+            return Err(Error::new(
+                ErrorKind::VerifyError,
+                format!(
+                    "{} validator set update events, but {} validator updates in block results",
+                    changes.len(),
+                    self.validator_updates.len()
+                ),
+            ));
+        }
+        for (change, update) in changes.iter().zip(self.validator_updates.iter()) {
+            let pubkey = serde_json::to_value(&update.pub_key)
+                .ok()
+                .and_then(|pubkey| pubkey["value"].as_str().map(ToOwned::to_owned));
+            if pubkey.as_ref() != Some(&change.pubkey)
+                || update.power.value() != u64::from(change.new_power)
+            {
+                return Err(Error::new(
+                    ErrorKind::VerifyError,
+                    format!(
+                        "Validator set update event of {} doesn't match validator updates in block results",
+                        change.pubkey
+                    ),
+                ));
+            }
+        }
+        Ok(Some(changes))
     }
 }
 
+fn decode_event_attribute_value(
+    attributes: &[Attribute],
+    key: TendermintEventKey,
+) -> Result<Option<String>> {
+    match find_event_attribute_by_key(attributes, key)? {
+        None => Ok(None),
+        Some(attribute) => {
+            let value = base64::decode(attribute.value.as_ref()).chain(|| {
+                (
+                    ErrorKind::DeserializationError,
+                    format!("Unable to decode base64 bytes of {} in block results", key),
+                )
+            })?;
+            let value = String::from_utf8(value).chain(|| {
+                (
+                    ErrorKind::DeserializationError,
+                    format!("Invalid {} text encoding", key),
+                )
+            })?;
+            Ok(Some(value))
+        }
+    }
+}
+
+fn parse_event_coin(text: &str) -> Result<Coin> {
+    let amount = text.parse::<u64>().chain(|| {
+        (
+            ErrorKind::DeserializationError,
+            format!("Invalid amount in staking event: {}", text),
+        )
+    })?;
+    Coin::new(amount).chain(|| {
+        (
+            ErrorKind::DeserializationError,
+            format!("Invalid amount in staking event: {}", text),
+        )
+    })
+}
+
+fn parse_event_coin_delta(text: &str) -> Result<StakingCoinDelta> {
+    if let Some(amount) = text.strip_prefix('-') {
+        Ok(StakingCoinDelta::Decrease(parse_event_coin(amount)?))
+    } else {
+        Ok(StakingCoinDelta::Increase(parse_event_coin(text)?))
+    }
+}
+
+/// Entry of the legacy `staking_diff` attribute
+#[derive(Deserialize)]
+struct LegacyStakingDiff {
+    key: String,
+    value: serde_json::Value,
+}
+
+fn parse_staking_event_amounts(attributes: &[Attribute]) -> Result<StakingEventAmounts> {
+    let decode = |key| decode_event_attribute_value(attributes, key);
+    let parse_coin =
+        |key| -> Result<Option<Coin>> { decode(key)?.as_deref().map(parse_event_coin).transpose() };
+    let parse_delta = |key| -> Result<Option<StakingCoinDelta>> {
+        decode(key)?
+            .as_deref()
+            .map(parse_event_coin_delta)
+            .transpose()
+    };
+
+    let mut amounts = StakingEventAmounts {
+        op_type: decode(TendermintEventKey::StakingOpType)?.unwrap_or_default(),
+        slashed_amount: parse_coin(TendermintEventKey::SlashedAmount)?,
+        reward_amount: parse_coin(TendermintEventKey::RewardAmount)?,
+        bonded_delta: parse_delta(TendermintEventKey::BondedDelta)?,
+        unbonded_delta: parse_delta(TendermintEventKey::UnbondedDelta)?,
+    };
+
+    if amounts.bonded_delta.is_none() && amounts.unbonded_delta.is_none() {
+        // events emitted before the typed amount keys only have balance changes in `staking_diff`
+        if let Some(staking_diff) = decode(TendermintEventKey::StakingDiff)? {
+            let diffs: Vec<LegacyStakingDiff> =
+                serde_json::from_str(&staking_diff).chain(|| {
+                    (
+                        ErrorKind::DeserializationError,
+                        "Invalid staking diff in block results",
+                    )
+                })?;
+            for diff in diffs.iter() {
+                let delta = match diff.value.as_str() {
+                    Some(value) => parse_event_coin_delta(value)?,
+                    None => continue,
+                };
+                match diff.key.as_str() {
+                    "Bonded" => amounts.bonded_delta = Some(delta),
+                    "Unbonded" => amounts.unbonded_delta = Some(delta),
+                    _ => {}
+                }
+            }
+            let decrease = |delta: Option<StakingCoinDelta>| match delta {
+                Some(StakingCoinDelta::Decrease(amount)) => amount,
+                _ => Coin::zero(),
+            };
+            match amounts.op_type.as_str() {
+                "slash" if amounts.slashed_amount.is_none() => {
+                    amounts.slashed_amount = Some(
+                        (decrease(amounts.bonded_delta) + decrease(amounts.unbonded_delta)).chain(
+                            || {
+                                (
+                                    ErrorKind::DeserializationError,
+                                    "Invalid slashed amount in staking diff",
+                                )
+                            },
+                        )?,
+                    );
+                }
+                "reward" if amounts.reward_amount.is_none() => {
+                    if let Some(StakingCoinDelta::Increase(amount)) = amounts.bonded_delta {
+                        amounts.reward_amount = Some(amount);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(amounts)
+}
+
 fn find_event_attribute_by_key(
     attributes: &[Attribute],
     target_key: TendermintEventKey,
@@ -336,6 +602,28 @@ mod tests {
         assert!(block_results.block_filter().is_ok());
     }
 
+    #[test]
+    fn check_gcs_block_filter() {
+        use crate::{PrivateKey, PublicKey};
+        use chain_tx_filter::{GcsBlockFilter, TxFilter};
+
+        let view_key = PublicKey::from(&PrivateKey::new().unwrap());
+        let mut filter = GcsBlockFilter::default();
+        filter.add_view_key(&view_key.clone().into());
+        let (key, value) = filter.get_tendermint_kv();
+        let response_str = format!(
+            r#"{{"height": "37", "txs_results": null, "begin_block_events": null, "end_block_events": [{{"type": "block_filter", "attributes": [{{"key": "{}", "value": "{}"}}]}}], "validator_updates": null, "consensus_param_updates": null}}"#,
+            base64::encode(&key),
+            base64::encode(&value)
+        );
+        let block_results: BlockResultsResponse =
+            serde_json::from_str(&response_str).expect("invalid response str");
+
+        let block_filter = block_results.block_filter().unwrap();
+        assert!(matches!(block_filter, FilterKind::Gcs(_)));
+        assert!(block_filter.check_view_key(&view_key.into()));
+    }
+
     #[test]
     fn check_wrong_id() {
         let response_str = r#"{"height": "38", "txs_results": [{"code": 0, "data": null, "log": "", "info": "", "gasWanted": "0", "gasUsed": "0", "events": [{"type": "valid_txs", "attributes": [{"key": "dHhpZA==", "value": "kOzcmhZgAAaw5riwRjjKNe+foJEiDAOObTDQ="}]}], "codespace": ""}], "begin_block_events": null, "end_block_events": [{"type": "block_filter", "attributes": [{"key": "ZXRoYmxvb20=", "value": "AAAAAAAAAAAAAAAAAgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAEAAAAAAAAAAAAAAAAAAAAAAAAAAAAACAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABAAAAAA=="}]}], "validator_updates": null, "consensus_param_updates": null}"#;
@@ -357,6 +645,213 @@ mod tests {
         assert_eq!(0, block_results.fees().unwrap().len());
     }
 
+    mod block_results_validator_set_updates {
+        use super::*;
+        use chain_core::state::tendermint::TendermintVotePower;
+        use tendermint::{validator, vote, PublicKey};
+
+        fn power(value: i64) -> TendermintVotePower {
+            TendermintVotePower::new(value).unwrap()
+        }
+
+        fn change(seed: u8, old_power: i64, new_power: i64) -> ValidatorPowerChange {
+            ValidatorPowerChange {
+                pubkey: base64::encode(&[seed; 32]),
+                old_power: power(old_power),
+                new_power: power(new_power),
+            }
+        }
+
+        fn update(seed: u8, new_power: u64) -> validator::Update {
+            validator::Update {
+                pub_key: PublicKey::from_raw_ed25519(&[seed; 32]).unwrap(),
+                power: vote::Power::new(new_power),
+            }
+        }
+
+        fn block_results(
+            changes: &[ValidatorPowerChange],
+            validator_updates: Vec<validator::Update>,
+        ) -> BlockResultsResponse {
+            let attributes = changes
+                .iter()
+                .map(|change| {
+                    format!(
+                        r#"{{"key": "{}", "value": "{}"}}"#,
+                        TendermintEventKey::ValidatorUpdate.to_base64_string(),
+                        base64::encode(&serde_json::to_string(change).unwrap())
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let response_str = format!(
+                r#"{{"height": "3", "txs_results": null, "begin_block_events": null, "end_block_events": [{{"type": "validator_set_update", "attributes": [{}]}}], "validator_updates": null, "consensus_param_updates": null}}"#,
+                attributes
+            );
+            let mut block_results: BlockResultsResponse =
+                serde_json::from_str(&response_str).expect("invalid response str");
+            block_results.validator_updates = validator_updates;
+            block_results
+        }
+
+        #[test]
+        fn should_parse_matching_updates() {
+            let changes = vec![change(1, 10, 0), change(2, 0, 20)];
+            let block_results = block_results(&changes, vec![update(1, 0), update(2, 20)]);
+
+            assert_eq!(
+                Some(changes),
+                block_results.validator_set_updates().unwrap()
+            );
+        }
+
+        #[test]
+        fn should_return_empty_without_updates() {
+            let block_results = block_results(&[], vec![]);
+
+            assert_eq!(Some(vec![]), block_results.validator_set_updates().unwrap());
+        }
+
+        #[test]
+        fn should_return_none_without_event() {
+            let mut block_results = block_results(&[], vec![update(1, 0)]);
+            block_results.end_block_events = None;
+
+            assert_eq!(None, block_results.validator_set_updates().unwrap());
+        }
+
+        #[test]
+        fn should_return_err_when_updates_do_not_match() {
+            let changes = vec![change(1, 10, 0)];
+
+            let block_results_with_power = block_results(&changes, vec![update(1, 5)]);
+            assert!(block_results_with_power.validator_set_updates().is_err());
+
+            let block_results_with_key = block_results(&changes, vec![update(2, 0)]);
+            assert!(block_results_with_key.validator_set_updates().is_err());
+
+            let block_results_missing = block_results(&changes, vec![]);
+            assert!(block_results_missing.validator_set_updates().is_err());
+
+            let block_results_extra = block_results(&[], vec![update(1, 0)]);
+            assert!(block_results_extra.validator_set_updates().is_err());
+        }
+    }
+
+    mod block_results_staking_amounts {
+        use super::*;
+
+        const ADDRESS: &str = "0x83fe11feb0887183eb62c30994bdd9e303497e3d";
+
+        fn staking_event_json(attributes: &[(TendermintEventKey, &str)]) -> String {
+            let attributes = attributes
+                .iter()
+                .map(|(key, value)| {
+                    format!(
+                        r#"{{"key": "{}", "value": "{}"}}"#,
+                        key.to_base64_string(),
+                        base64::encode(value)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!(
+                r#"{{"type": "staking_change", "attributes": [{}]}}"#,
+                attributes
+            )
+        }
+
+        fn block_results(begin_block_events: &[String]) -> BlockResultsResponse {
+            let response_str = format!(
+                r#"{{"height": "3", "txs_results": null, "begin_block_events": [{}], "end_block_events": null, "validator_updates": null, "consensus_param_updates": null}}"#,
+                begin_block_events.join(", ")
+            );
+            serde_json::from_str(&response_str).expect("invalid response str")
+        }
+
+        fn staking_address() -> StakedStateAddress {
+            StakedStateAddress::from_str(ADDRESS).unwrap()
+        }
+
+        #[test]
+        fn should_parse_typed_amounts() {
+            let block_results = block_results(&[
+                staking_event_json(&[
+                    (TendermintEventKey::StakingAddress, ADDRESS),
+                    (TendermintEventKey::StakingOpType, "slash"),
+                    (TendermintEventKey::SlashedAmount, "300"),
+                    (TendermintEventKey::BondedDelta, "-100"),
+                    (TendermintEventKey::UnbondedDelta, "-200"),
+                ]),
+                staking_event_json(&[
+                    (
+                        TendermintEventKey::StakingAddress,
+                        "0x0e7c045110b8dbf29765047380898919c5cb56f4",
+                    ),
+                    (TendermintEventKey::StakingOpType, "reward"),
+                    (TendermintEventKey::RewardAmount, "50"),
+                    (TendermintEventKey::BondedDelta, "50"),
+                ]),
+            ]);
+
+            assert_eq!(
+                vec![StakingEventAmounts {
+                    op_type: "slash".to_owned(),
+                    slashed_amount: Some(Coin::new(300).unwrap()),
+                    reward_amount: None,
+                    bonded_delta: Some(StakingCoinDelta::Decrease(Coin::new(100).unwrap())),
+                    unbonded_delta: Some(StakingCoinDelta::Decrease(Coin::new(200).unwrap())),
+                }],
+                block_results.staking_amounts(&staking_address()).unwrap()
+            );
+        }
+
+        #[test]
+        fn should_fall_back_to_legacy_staking_diff() {
+            let block_results = block_results(&[
+                staking_event_json(&[
+                    (TendermintEventKey::StakingAddress, ADDRESS),
+                    (TendermintEventKey::StakingOpType, "slash"),
+                    (
+                        TendermintEventKey::StakingDiff,
+                        r#"[{"key":"Bonded","value":"-100"},{"key":"Unbonded","value":"-200"}]"#,
+                    ),
+                ]),
+                staking_event_json(&[
+                    (TendermintEventKey::StakingAddress, ADDRESS),
+                    (TendermintEventKey::StakingOpType, "reward"),
+                    (
+                        TendermintEventKey::StakingDiff,
+                        r#"[{"key":"Bonded","value":"50"}]"#,
+                    ),
+                ]),
+            ]);
+
+            let amounts = block_results.staking_amounts(&staking_address()).unwrap();
+            assert_eq!(2, amounts.len());
+            assert_eq!(Some(Coin::new(300).unwrap()), amounts[0].slashed_amount);
+            assert_eq!(None, amounts[0].reward_amount);
+            assert_eq!(None, amounts[1].slashed_amount);
+            assert_eq!(Some(Coin::new(50).unwrap()), amounts[1].reward_amount);
+            assert_eq!(
+                Some(StakingCoinDelta::Increase(Coin::new(50).unwrap())),
+                amounts[1].bonded_delta
+            );
+        }
+
+        #[test]
+        fn should_return_err_when_amount_is_invalid() {
+            let block_results = block_results(&[staking_event_json(&[
+                (TendermintEventKey::StakingAddress, ADDRESS),
+                (TendermintEventKey::StakingOpType, "slash"),
+                (TendermintEventKey::SlashedAmount, "-1"),
+            ])]);
+
+            let result = block_results.staking_amounts(&staking_address());
+            assert_eq!(ErrorKind::DeserializationError, result.unwrap_err().kind());
+        }
+    }
+
     mod find_event_attribute_by_key {
         use super::*;
 
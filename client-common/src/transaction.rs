@@ -1,14 +1,17 @@
 use parity_scale_codec::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 
-use super::{ErrorKind, Result, ResultExt};
+use super::tendermint::types::{StakingCoinDelta, StakingEventAmounts};
+use super::{Error, ErrorKind, Result, ResultExt};
+use chain_core::init::coin::{sum_coins, Coin};
 use chain_core::state::account::{
     DepositBondTx, StakedStateOpWitness, UnbondTx, UnjailTx, WithdrawUnbondedTx,
 };
-use chain_core::state::validator::NodeJoinRequestTx;
+use chain_core::state::validator::{NodeJoinRequestTx, UpdateNodeTx};
 use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::data::output::TxOut;
 use chain_core::tx::data::{Tx, TxId};
+use chain_core::tx::fee::Fee;
 use chain_core::tx::witness::TxWitness;
 use chain_core::tx::{PlainTxAux, TransactionId, TxWithOutputs};
 use mls::{
@@ -75,6 +78,8 @@ pub enum Transaction {
     UnjailTransaction(UnjailTx),
     /// Node join transaction
     NodejoinTransaction(NodeJoinRequestTx),
+    /// Council node metadata update transaction
+    UpdateNodeTransaction(UpdateNodeTx),
 }
 
 impl Transaction {
@@ -86,7 +91,8 @@ impl Transaction {
             Transaction::UnbondStakeTransaction(_)
             | Transaction::WithdrawUnbondedStakeTransaction(_)
             | Transaction::UnjailTransaction(_)
-            | Transaction::NodejoinTransaction(_) => &[],
+            | Transaction::NodejoinTransaction(_)
+            | Transaction::UpdateNodeTransaction(_) => &[],
         }
     }
 
@@ -98,7 +104,8 @@ impl Transaction {
             Transaction::UnbondStakeTransaction(_)
             | Transaction::DepositStakeTransaction(_)
             | Transaction::UnjailTransaction(_)
-            | Transaction::NodejoinTransaction(_) => &[],
+            | Transaction::NodejoinTransaction(_)
+            | Transaction::UpdateNodeTransaction(_) => &[],
         }
     }
 }
@@ -113,6 +120,7 @@ impl Transaction {
             Transaction::WithdrawUnbondedStakeTransaction(ref transaction) => transaction.id(),
             Transaction::UnjailTransaction(ref transaction) => transaction.id(),
             Transaction::NodejoinTransaction(ref transaction) => transaction.id(),
+            Transaction::UpdateNodeTransaction(ref transaction) => transaction.id(),
         }
     }
 }
@@ -126,6 +134,140 @@ impl From<TxWithOutputs> for Transaction {
     }
 }
 
+/// Extraction of the fee paid by a transaction.
+///
+/// The fee of a transfer transaction is implicit (the input amount minus the output amount). The
+/// fees of the staking transactions are paid from staking balances, so they're derived from the
+/// balance changes reported by their staking event:
+/// - deposit: the input amount minus the increase of the bonded balance
+/// - unbond: the decrease of the bonded balance minus the unbonded amount
+/// - withdraw: the decrease of the unbonded balance minus the output amount
+/// - node update: the decrease of the bonded balance
+///
+/// Unjail and node join transactions don't pay any fee.
+pub struct TransactionFee;
+
+impl TransactionFee {
+    /// Computes the fee paid by a transaction which doesn't need its staking event (i.e. all but
+    /// deposit, unbond, withdraw and node update transactions), `resolve_input` returns the amount of an input.
+    pub fn compute(
+        tx: &Transaction,
+        resolve_input: impl Fn(&TxoPointer) -> Result<Coin>,
+    ) -> Result<Fee> {
+        Self::compute_with_staking_event(tx, resolve_input, None)
+    }
+
+    /// Computes the fee paid by a transaction, `resolve_input` returns the amount of an input and
+    /// `staking_event` are the amounts of the staking change event of the transaction (if any).
+    pub fn compute_with_staking_event(
+        tx: &Transaction,
+        resolve_input: impl Fn(&TxoPointer) -> Result<Coin>,
+        staking_event: Option<&StakingEventAmounts>,
+    ) -> Result<Fee> {
+        let fee = match tx {
+            Transaction::TransferTransaction(transfer) => subtract_fee(
+                input_amount(tx, resolve_input)?,
+                output_amount(&transfer.outputs)?,
+            )?,
+            Transaction::DepositStakeTransaction(_) => {
+                let deposited = match staking_delta(staking_event, |event| event.bonded_delta)? {
+                    StakingCoinDelta::Increase(deposited) => deposited,
+                    StakingCoinDelta::Decrease(_) => return Err(unexpected_delta("deposit")),
+                };
+                subtract_fee(input_amount(tx, resolve_input)?, deposited)?
+            }
+            Transaction::UnbondStakeTransaction(unbond) => {
+                let bonded = match staking_delta(staking_event, |event| event.bonded_delta)? {
+                    StakingCoinDelta::Decrease(bonded) => bonded,
+                    StakingCoinDelta::Increase(_) => return Err(unexpected_delta("unbond")),
+                };
+                subtract_fee(bonded, unbond.value)?
+            }
+            Transaction::WithdrawUnbondedStakeTransaction(withdraw) => {
+                let unbonded = match staking_delta(staking_event, |event| event.unbonded_delta)? {
+                    StakingCoinDelta::Decrease(unbonded) => unbonded,
+                    StakingCoinDelta::Increase(_) => return Err(unexpected_delta("withdraw")),
+                };
+                subtract_fee(unbonded, output_amount(&withdraw.outputs)?)?
+            }
+            Transaction::UpdateNodeTransaction(_) => {
+                match staking_delta(staking_event, |event| event.bonded_delta)? {
+                    StakingCoinDelta::Decrease(fee) => fee,
+                    StakingCoinDelta::Increase(_) => return Err(unexpected_delta("update node")),
+                }
+            }
+            Transaction::UnjailTransaction(_) | Transaction::NodejoinTransaction(_) => Coin::zero(),
+        };
+        Ok(Fee::new(fee))
+    }
+}
+
+fn input_amount(
+    tx: &Transaction,
+    resolve_input: impl Fn(&TxoPointer) -> Result<Coin>,
+) -> Result<Coin> {
+    let amounts = tx
+        .inputs()
+        .iter()
+        .map(|input| {
+            resolve_input(input).chain(|| {
+                (
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Unable to resolve input {}:{} of transaction {}",
+                        hex::encode(&input.id),
+                        input.index,
+                        hex::encode(&tx.id())
+                    ),
+                )
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    sum_coins(amounts.into_iter()).chain(|| {
+        (
+            ErrorKind::IllegalInput,
+            "Sum of input values exceeds maximum allowed amount",
+        )
+    })
+}
+
+fn output_amount(outputs: &[TxOut]) -> Result<Coin> {
+    sum_coins(outputs.iter().map(|output| output.value)).chain(|| {
+        (
+            ErrorKind::IllegalInput,
+            "Sum of output values exceeds maximum allowed amount",
+        )
+    })
+}
+
+fn subtract_fee(paid: Coin, received: Coin) -> Result<Coin> {
+    (paid - received).chain(|| {
+        (
+            ErrorKind::IllegalInput,
+            "Amount received by transaction exceeds the amount it spent",
+        )
+    })
+}
+
+fn staking_delta(
+    staking_event: Option<&StakingEventAmounts>,
+    delta: impl Fn(&StakingEventAmounts) -> Option<StakingCoinDelta>,
+) -> Result<StakingCoinDelta> {
+    staking_event.and_then(delta).chain(|| {
+        (
+            ErrorKind::InvalidInput,
+            "Fee of staking transaction needs the balance change of its staking event",
+        )
+    })
+}
+
+fn unexpected_delta(kind: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("Unexpected staking balance change of {} transaction", kind),
+    )
+}
+
 /// Enum representing a signed transaction
 #[derive(Debug, Clone, PartialEq, Encode, Decode)]
 pub enum SignedTransaction {
@@ -185,3 +327,173 @@ pub fn temporary_mls_init(kp: Vec<u8>) -> Vec<u8> {
     }
     .get_encoding()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain_core::init::address::RedeemAddress;
+    use chain_core::state::account::{StakedStateAddress, StakedStateOpAttributes};
+    use chain_core::tx::data::address::ExtendedAddr;
+    use chain_core::tx::data::attribute::TxAttributes;
+
+    fn coin(value: u64) -> Coin {
+        Coin::new(value).unwrap()
+    }
+
+    fn output(value: u64) -> TxOut {
+        TxOut::new(ExtendedAddr::OrTree([0; 32]), coin(value))
+    }
+
+    fn address() -> StakedStateAddress {
+        StakedStateAddress::BasicRedeem(RedeemAddress([1; 20]))
+    }
+
+    /// inputs `[n; 32]:0` are worth `n * 100`
+    fn resolve_input(input: &TxoPointer) -> Result<Coin> {
+        Ok(coin(u64::from(input.id[0]) * 100))
+    }
+
+    fn staking_event(
+        bonded_delta: Option<StakingCoinDelta>,
+        unbonded_delta: Option<StakingCoinDelta>,
+    ) -> StakingEventAmounts {
+        StakingEventAmounts {
+            bonded_delta,
+            unbonded_delta,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn check_transfer_fee() {
+        let tx = Transaction::TransferTransaction(Tx {
+            inputs: vec![TxoPointer::new([1; 32], 0), TxoPointer::new([2; 32], 0)],
+            outputs: vec![output(150), output(140)],
+            attributes: TxAttributes::new(0),
+        });
+        assert_eq!(
+            Fee::new(coin(10)),
+            TransactionFee::compute(&tx, resolve_input).unwrap()
+        );
+
+        // outputs exceeding inputs
+        let tx = Transaction::TransferTransaction(Tx {
+            inputs: vec![TxoPointer::new([1; 32], 0)],
+            outputs: vec![output(101)],
+            attributes: TxAttributes::new(0),
+        });
+        assert_eq!(
+            ErrorKind::IllegalInput,
+            TransactionFee::compute(&tx, resolve_input)
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn check_transfer_with_unresolvable_input() {
+        let tx = Transaction::TransferTransaction(Tx {
+            inputs: vec![TxoPointer::new([1; 32], 0), TxoPointer::new([2; 32], 1)],
+            outputs: vec![output(100)],
+            attributes: TxAttributes::new(0),
+        });
+        let error = TransactionFee::compute(&tx, |input| {
+            if input.index == 0 {
+                resolve_input(input)
+            } else {
+                Err(Error::new(ErrorKind::StorageError, "unknown transaction"))
+            }
+        })
+        .unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        assert!(error.to_string().contains(&hex::encode(&[2; 32])));
+    }
+
+    #[test]
+    fn check_deposit_fee() {
+        let tx = Transaction::DepositStakeTransaction(DepositBondTx {
+            inputs: vec![TxoPointer::new([3; 32], 0)],
+            to_staked_account: address(),
+            attributes: StakedStateOpAttributes::new(0),
+        });
+        let event = staking_event(Some(StakingCoinDelta::Increase(coin(290))), None);
+        assert_eq!(
+            Fee::new(coin(10)),
+            TransactionFee::compute_with_staking_event(&tx, resolve_input, Some(&event)).unwrap()
+        );
+        // the fee is reported by the staking event only
+        assert!(TransactionFee::compute(&tx, resolve_input).is_err());
+    }
+
+    #[test]
+    fn check_unbond_fee() {
+        let tx = Transaction::UnbondStakeTransaction(UnbondTx {
+            from_staked_account: address(),
+            nonce: 0,
+            value: coin(100),
+            attributes: StakedStateOpAttributes::new(0),
+        });
+        // paid from the bonded balance
+        let event = staking_event(
+            Some(StakingCoinDelta::Decrease(coin(110))),
+            Some(StakingCoinDelta::Increase(coin(100))),
+        );
+        assert_eq!(
+            Fee::new(coin(10)),
+            TransactionFee::compute_with_staking_event(&tx, resolve_input, Some(&event)).unwrap()
+        );
+        let event = staking_event(Some(StakingCoinDelta::Increase(coin(110))), None);
+        assert!(
+            TransactionFee::compute_with_staking_event(&tx, resolve_input, Some(&event)).is_err()
+        );
+    }
+
+    #[test]
+    fn check_withdraw_fee() {
+        let tx = Transaction::WithdrawUnbondedStakeTransaction(WithdrawUnbondedTx {
+            nonce: 1,
+            outputs: vec![output(60), output(30)],
+            attributes: TxAttributes::new(0),
+        });
+        // paid from the unbonded balance
+        let event = staking_event(None, Some(StakingCoinDelta::Decrease(coin(100))));
+        assert_eq!(
+            Fee::new(coin(10)),
+            TransactionFee::compute_with_staking_event(&tx, resolve_input, Some(&event)).unwrap()
+        );
+        let event = staking_event(Some(StakingCoinDelta::Decrease(coin(100))), None);
+        assert!(
+            TransactionFee::compute_with_staking_event(&tx, resolve_input, Some(&event)).is_err()
+        );
+    }
+
+    #[test]
+    fn check_update_node_fee() {
+        let tx = Transaction::UpdateNodeTransaction(UpdateNodeTx {
+            nonce: 0,
+            address: address(),
+            attributes: StakedStateOpAttributes::new(0),
+            announced_endpoints: vec!["10.0.0.1:26656".to_owned()],
+        });
+        // paid from the bonded balance
+        let event = staking_event(Some(StakingCoinDelta::Decrease(coin(10))), None);
+        assert_eq!(
+            Fee::new(coin(10)),
+            TransactionFee::compute_with_staking_event(&tx, resolve_input, Some(&event)).unwrap()
+        );
+        assert!(TransactionFee::compute(&tx, resolve_input).is_err());
+    }
+
+    #[test]
+    fn check_unjail_fee() {
+        let tx = Transaction::UnjailTransaction(UnjailTx {
+            nonce: 0,
+            address: address(),
+            attributes: StakedStateOpAttributes::new(0),
+        });
+        assert_eq!(
+            Fee::new(Coin::zero()),
+            TransactionFee::compute(&tx, resolve_input).unwrap()
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use secstr::SecUtf8;
+
+use chain_core::init::coin::Coin;
+use chain_core::tx::fee::Fee;
+use client_common::seckey::derive_enckey;
+use client_common::storage::MemoryStorage;
+use client_common::tendermint::types::Time;
+use client_common::SecKey;
+use client_core::service::{HistoryIndexService, WalletStateMemento, WalletStateService};
+use client_core::types::{BalanceChange, TransactionChange, TransactionType};
+
+const NAME: &str = "bench";
+const CHANGES: u64 = 100_000;
+
+fn wallet_with_history() -> (MemoryStorage, SecKey) {
+    let storage = MemoryStorage::default();
+    let enckey = derive_enckey(&SecUtf8::from("Correct horse battery staple, 2020!"), NAME)
+        .expect("derive enckey");
+    let mut memento = WalletStateMemento::default();
+    for i in 0..CHANGES {
+        let mut transaction_id = [0; 32];
+        transaction_id[..8].copy_from_slice(&i.to_be_bytes());
+        memento.add_transaction_change(TransactionChange {
+            transaction_id,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            fee_paid: Fee::new(Coin::zero()),
+            balance_change: BalanceChange::Incoming { value: Coin::one() },
+            transaction_type: TransactionType::Transfer,
+            block_height: i,
+            block_time: Time::now(),
+            memo: None,
+        });
+    }
+    WalletStateService::new(storage.clone())
+        .apply_memento(NAME, &enckey, &memento)
+        .expect("apply memento");
+    (storage, enckey)
+}
+
+/// Changes of 10 blocks out of a history of 100k changes
+fn bench_history_range(c: &mut Criterion) {
+    let mut group = c.benchmark_group("history_range_100k");
+    group.sample_size(10);
+
+    let (storage, enckey) = wallet_with_history();
+    let index = HistoryIndexService::new(storage.clone());
+    index.ensure_indexed(NAME, &enckey).expect("index history");
+    group.bench_function("indexed", |b| {
+        b.iter(|| index.history_range(NAME, &enckey, 50_000, 50_009))
+    });
+
+    let state = WalletStateService::new(storage);
+    group.bench_function("full_scan", |b| {
+        b.iter(|| {
+            state
+                .get_transaction_history(NAME, &enckey, false)
+                .expect("history")
+                .filter(|change| (50_000..=50_009).contains(&change.block_height))
+                .collect::<Vec<_>>()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_history_range);
+criterion_main!(benches);
@@ -0,0 +1,52 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use secstr::SecUtf8;
+
+use client_common::storage::{CachedStorage, MemoryStorage};
+use client_common::{SecKey, Storage};
+use client_core::hd_wallet::HardwareKind;
+use client_core::types::WalletKind;
+use client_core::wallet::{DefaultWalletClient, WalletClient};
+
+const NAME: &str = "bench";
+const ADDRESSES: usize = 500;
+
+fn wallet_with_addresses<S: Storage + 'static>(storage: S) -> (impl WalletClient, SecKey) {
+    let client = DefaultWalletClient::new_read_only(storage);
+    let passphrase = SecUtf8::from("Correct horse battery staple, 2020!");
+    let (enckey, _) = client
+        .new_wallet(
+            NAME,
+            &passphrase,
+            WalletKind::Basic,
+            HardwareKind::LocalOnly,
+            None,
+        )
+        .expect("new wallet");
+    for _ in 0..ADDRESSES {
+        client
+            .new_transfer_address(NAME, &enckey)
+            .expect("new transfer address");
+    }
+    (client, enckey)
+}
+
+/// `wallet_listTransferAddresses` request on a wallet with `ADDRESSES` addresses, with and without
+/// the cache of decrypted records
+fn bench_transfer_addresses(c: &mut Criterion) {
+    let mut group = c.benchmark_group("transfer_addresses");
+
+    let (client, enckey) = wallet_with_addresses(MemoryStorage::default());
+    group.bench_function("uncached", |b| {
+        b.iter(|| client.transfer_addresses(NAME, &enckey, 0, 0, false))
+    });
+
+    let (client, enckey) = wallet_with_addresses(CachedStorage::new(MemoryStorage::default()));
+    group.bench_function("cached", |b| {
+        b.iter(|| client.transfer_addresses(NAME, &enckey, 0, 0, false))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_transfer_addresses);
+criterion_main!(benches);
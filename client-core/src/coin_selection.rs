@@ -0,0 +1,351 @@
+//! Coin selection: choice of the unspent outputs which fund a transaction
+use std::cmp::Reverse;
+use std::fmt::Debug;
+use std::slice;
+
+use chain_core::init::coin::{sum_coins, Coin};
+use chain_core::tx::data::input::TxoPointer;
+use client_common::{Error, ErrorKind, Result, ResultExt};
+
+/// Default maximum number of steps of the branch and bound search
+const DEFAULT_MAX_TRIES: usize = 100_000;
+
+/// Estimates the fee of the transaction projected to spend given inputs (with or without an extra
+/// change output)
+pub trait FeeEstimator {
+    /// Returns the estimated fee of the projected transaction
+    fn estimate_fee(&self, inputs: &[TxoPointer], with_change: bool) -> Result<Coin>;
+}
+
+impl<T> FeeEstimator for T
+where
+    T: Fn(&[TxoPointer], bool) -> Result<Coin>,
+{
+    #[inline]
+    fn estimate_fee(&self, inputs: &[TxoPointer], with_change: bool) -> Result<Coin> {
+        self(inputs, with_change)
+    }
+}
+
+/// Unspent outputs selected to fund a transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    /// Selected unspent outputs
+    pub inputs: Vec<TxoPointer>,
+    /// Fee of the projected transaction
+    pub fee: Coin,
+    /// Amount of the change output (zero when the transaction has no change output)
+    pub change: Coin,
+}
+
+impl Selection {
+    /// Completes the selection of given inputs (worth `total`) for `target`, returning `None` if
+    /// they don't cover the target and the fee of the projected transaction, or if their excess is
+    /// too small to pay for a change output (the chain requires the fee to be exactly the minimum
+    /// fee, so the excess can't go to the fee).
+    fn complete(
+        inputs: Vec<TxoPointer>,
+        total: Coin,
+        target: Coin,
+        fee_estimator: &dyn FeeEstimator,
+    ) -> Result<Option<Selection>> {
+        let fee = fee_estimator.estimate_fee(&inputs, false)?;
+        let excess = match total - target - fee {
+            Ok(excess) => excess,
+            Err(_) => return Ok(None),
+        };
+
+        if excess == Coin::zero() {
+            return Ok(Some(Selection {
+                inputs,
+                fee,
+                change: Coin::zero(),
+            }));
+        }
+
+        let fee_with_change = fee_estimator.estimate_fee(&inputs, true)?;
+        match total - target - fee_with_change {
+            Ok(change) if change != Coin::zero() => Ok(Some(Selection {
+                inputs,
+                fee: fee_with_change,
+                change,
+            })),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Strategy selecting the unspent outputs which fund a transaction
+pub trait CoinSelector: Debug + Send + Sync {
+    /// Selects unspent outputs covering `target` and the fee of the projected transaction (as
+    /// estimated by `fee_estimator`)
+    fn select(
+        &self,
+        utxos: &[(TxoPointer, Coin)],
+        target: Coin,
+        fee_estimator: &dyn FeeEstimator,
+    ) -> Result<Selection>;
+}
+
+/// Selects the unspent outputs with the highest value first, until they cover the target, the fee
+/// and a change output (if any)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LargestFirst;
+
+impl CoinSelector for LargestFirst {
+    fn select(
+        &self,
+        utxos: &[(TxoPointer, Coin)],
+        target: Coin,
+        fee_estimator: &dyn FeeEstimator,
+    ) -> Result<Selection> {
+        let mut sorted = utxos.iter().collect::<Vec<_>>();
+        sorted.sort_by_key(|(_, value)| Reverse(*value));
+
+        let mut inputs = Vec::new();
+        let mut total = Coin::zero();
+        let mut covered = false;
+
+        for (pointer, value) in sorted {
+            inputs.push(pointer.clone());
+            total = (total + value).chain(|| {
+                (
+                    ErrorKind::IllegalInput,
+                    "Total amount of selected UTXOs exceeds maximum allowed value",
+                )
+            })?;
+
+            if total < target {
+                continue;
+            }
+            covered =
+                covered || (total - target - fee_estimator.estimate_fee(&inputs, false)?).is_ok();
+            if let Some(selection) =
+                Selection::complete(inputs.clone(), total, target, fee_estimator)?
+            {
+                return Ok(selection);
+            }
+        }
+
+        if covered {
+            Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Unable to select unspent outputs without overpaying the fee (the excess is too small for a change output)",
+            ))
+        } else {
+            Err(Error::new(ErrorKind::InvalidInput, "Insufficient balance"))
+        }
+    }
+}
+
+/// Searches (with branch and bound) for unspent outputs which exactly match the target and the fee
+/// without a change output, so the transaction neither creates change nor spends more outputs than
+/// needed. Falls back to largest-first selection when the search finds no match within `max_tries`
+/// steps.
+#[derive(Debug, Clone, Copy)]
+pub struct BranchAndBound {
+    /// Maximum number of steps of the search
+    pub max_tries: usize,
+}
+
+impl Default for BranchAndBound {
+    #[inline]
+    fn default() -> Self {
+        BranchAndBound {
+            max_tries: DEFAULT_MAX_TRIES,
+        }
+    }
+}
+
+impl CoinSelector for BranchAndBound {
+    fn select(
+        &self,
+        utxos: &[(TxoPointer, Coin)],
+        target: Coin,
+        fee_estimator: &dyn FeeEstimator,
+    ) -> Result<Selection> {
+        // Effective value of an output: its value minus the fee of spending it
+        let base_fee = u64::from(fee_estimator.estimate_fee(&[], false)?);
+
+        let mut candidates = Vec::with_capacity(utxos.len());
+        for (pointer, value) in utxos {
+            let input_fee = u64::from(fee_estimator.estimate_fee(slice::from_ref(pointer), false)?)
+                .saturating_sub(base_fee);
+            let effective_value = u64::from(*value).saturating_sub(input_fee);
+            if effective_value > 0 {
+                candidates.push((pointer, *value, effective_value));
+            }
+        }
+        candidates.sort_by_key(|(_, _, effective_value)| Reverse(*effective_value));
+
+        let effective_values = candidates
+            .iter()
+            .map(|(_, _, effective_value)| *effective_value)
+            .collect::<Vec<_>>();
+        // Without a change output, any excess would go to the fee, so only an exact match will do
+        let exact = u64::from(target).saturating_add(base_fee);
+
+        if let Some(chosen) = search(&effective_values, exact, exact, self.max_tries) {
+            let inputs = chosen
+                .iter()
+                .map(|&index| candidates[index].0.clone())
+                .collect();
+            let total = sum_coins(chosen.iter().map(|&index| candidates[index].1)).chain(|| {
+                (
+                    ErrorKind::IllegalInput,
+                    "Total amount of selected UTXOs exceeds maximum allowed value",
+                )
+            })?;
+
+            if let Some(selection) = Selection::complete(inputs, total, target, fee_estimator)? {
+                return Ok(selection);
+            }
+        }
+
+        LargestFirst.select(utxos, target, fee_estimator)
+    }
+}
+
+/// Depth-first search for a subset of `values` (sorted in decreasing order) whose sum is within
+/// `lower..=upper`, returning the indices of its values
+fn search(values: &[u64], lower: u64, upper: u64, max_tries: usize) -> Option<Vec<usize>> {
+    // `included[i]` tells whether the value `i` is in the current branch
+    let mut included: Vec<bool> = Vec::with_capacity(values.len());
+    let mut current = 0u64;
+    // Sum of the values not decided yet in the current branch
+    let mut available = values
+        .iter()
+        .fold(0u64, |sum, value| sum.saturating_add(*value));
+
+    for _ in 0..max_tries {
+        if current.saturating_add(available) < lower || current > upper {
+            // Backtrack to the latest included value, and exclude it
+            while let Some(&false) = included.last() {
+                included.pop();
+                available += values[included.len()];
+            }
+            match included.last_mut() {
+                Some(last) => *last = false,
+                None => return None,
+            }
+            current -= values[included.len() - 1];
+        } else if current >= lower {
+            return Some(
+                included
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, included)| **included)
+                    .map(|(index, _)| index)
+                    .collect(),
+            );
+        } else {
+            let value = values[included.len()];
+            available -= value;
+            current += value;
+            included.push(true);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utxos(values: &[u64]) -> Vec<(TxoPointer, Coin)> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                (
+                    TxoPointer::new([index as u8; 32], 0),
+                    Coin::new(*value).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    /// Fee of 10, plus 5 per input and 3 for the change output
+    fn estimate_fee(inputs: &[TxoPointer], with_change: bool) -> Result<Coin> {
+        let change = if with_change { 3 } else { 0 };
+        Ok(Coin::new(10 + 5 * inputs.len() as u64 + change).unwrap())
+    }
+
+    #[test]
+    fn check_exact_match_selection() {
+        let utxos = utxos(&[70, 35, 25]);
+
+        let selection = BranchAndBound::default()
+            .select(&utxos, Coin::new(40).unwrap(), &estimate_fee)
+            .unwrap();
+        assert_eq!(
+            vec![utxos[1].0.clone(), utxos[2].0.clone()],
+            selection.inputs
+        );
+        assert_eq!(Coin::new(20).unwrap(), selection.fee);
+        assert_eq!(Coin::zero(), selection.change);
+    }
+
+    #[test]
+    fn check_change_creation() {
+        let utxos = utxos(&[35, 70, 25]);
+
+        let selection = LargestFirst
+            .select(&utxos, Coin::new(40).unwrap(), &estimate_fee)
+            .unwrap();
+        assert_eq!(vec![utxos[1].0.clone()], selection.inputs);
+        assert_eq!(Coin::new(18).unwrap(), selection.fee);
+        assert_eq!(Coin::new(12).unwrap(), selection.change);
+
+        // No changeless match: falls back to largest-first
+        let selection = BranchAndBound::default()
+            .select(&utxos[1..2], Coin::new(40).unwrap(), &estimate_fee)
+            .unwrap();
+        assert_eq!(Coin::new(12).unwrap(), selection.change);
+
+        // Excess too small to pay for a change output: the fee is never overpaid
+        let utxos = self::utxos(&[57]);
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            LargestFirst
+                .select(&utxos, Coin::new(40).unwrap(), &estimate_fee)
+                .unwrap_err()
+                .kind()
+        );
+
+        // ... so another output is spent to make the change output affordable
+        let utxos = self::utxos(&[57, 20]);
+        let selection = LargestFirst
+            .select(&utxos, Coin::new(40).unwrap(), &estimate_fee)
+            .unwrap();
+        assert_eq!(
+            vec![utxos[0].0.clone(), utxos[1].0.clone()],
+            selection.inputs
+        );
+        assert_eq!(Coin::new(23).unwrap(), selection.fee);
+        assert_eq!(Coin::new(14).unwrap(), selection.change);
+    }
+
+    #[test]
+    fn check_insufficient_balance_with_fees() {
+        // Enough to cover the target, but not the fee of spending both outputs
+        let utxos = utxos(&[30, 25]);
+        let target = Coin::new(40).unwrap();
+
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            LargestFirst
+                .select(&utxos, target, &estimate_fee)
+                .unwrap_err()
+                .kind()
+        );
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            BranchAndBound::default()
+                .select(&utxos, target, &estimate_fee)
+                .unwrap_err()
+                .kind()
+        );
+    }
+}
@@ -6,9 +6,11 @@
 
 use crate::hd_wallet::KeyIndex;
 use chain_core::init::network::{get_bip44_coin_type_from_network, Network};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 const MASTER_SYMBOL: &str = "m";
+const BIP44_PURPOSE: u32 = 44;
 const HARDENED_SYMBOLS: [&str; 2] = ["H", "'"];
 const SEPARATOR: char = '/';
 
@@ -60,8 +62,76 @@ impl ChainPath {
     pub fn create_bip44(network: Network, account_index: u32, index: u32) -> Self {
         let coin_type = get_bip44_coin_type_from_network(network);
 
-        let chain_path_string = format!("m/44'/{}'/{}'/0/{}", coin_type, account_index, index);
-        Self::from(chain_path_string)
+        DerivationPath {
+            coin_type,
+            account: account_index,
+            change: 0,
+            index,
+        }
+        .to_chain_path()
+    }
+}
+
+/// Levels of a bip44 chain path below the purpose:
+/// `m / 44' / coin_type' / account' / change / address_index`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DerivationPath {
+    /// coin type (hardened)
+    pub coin_type: u32,
+    /// account (hardened), see `HDAccountType`
+    pub account: u32,
+    /// change
+    pub change: u32,
+    /// address index
+    pub index: u32,
+}
+
+impl DerivationPath {
+    /// Parses a bip44 chain path, `None` if the path has other levels
+    pub fn from_chain_path(chain_path: &ChainPath) -> Option<Self> {
+        let mut levels = chain_path.iter();
+        if levels.next()?.ok()? != SubPath::Root {
+            return None;
+        }
+        let mut child = || match levels.next() {
+            Some(Ok(SubPath::Child(key_index))) => Some(key_index),
+            _ => None,
+        };
+        let (purpose, coin_type, account, change, index) =
+            (child()?, child()?, child()?, child()?, child()?);
+        if levels.next().is_some() {
+            return None;
+        }
+        match (purpose, coin_type, account, change, index) {
+            (
+                KeyIndex::Hardened(_),
+                KeyIndex::Hardened(_),
+                KeyIndex::Hardened(_),
+                KeyIndex::Normal(change),
+                KeyIndex::Normal(index),
+            ) if purpose.normalize_index() == BIP44_PURPOSE => Some(DerivationPath {
+                coin_type: coin_type.normalize_index(),
+                account: account.normalize_index(),
+                change,
+                index,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The chain path in the format of `ChainPath::create_bip44`
+    pub fn to_chain_path(&self) -> ChainPath {
+        ChainPath::from(self.to_string())
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "m/{}'/{}'/{}'/{}/{}",
+            BIP44_PURPOSE, self.coin_type, self.account, self.change, self.index
+        )
     }
 }
 
@@ -189,4 +259,30 @@ mod tests {
             .collect::<Result<Vec<_>, _>>()
             .is_err());
     }
+
+    #[test]
+    fn test_derivation_path() {
+        let chain_path = ChainPath::create_bip44(Network::Mainnet, 1, 7);
+        assert_eq!("m/44'/394'/1'/0/7", chain_path.to_string());
+        let path = DerivationPath::from_chain_path(&chain_path).unwrap();
+        assert_eq!(
+            DerivationPath {
+                coin_type: 394,
+                account: 1,
+                change: 0,
+                index: 7
+            },
+            path
+        );
+        assert_eq!(chain_path, path.to_chain_path());
+
+        assert_eq!(
+            Some(path),
+            DerivationPath::from_chain_path(&ChainPath::from("m/44H/394H/1H/0/7"))
+        );
+        // not hardened, other purpose, missing level
+        assert!(DerivationPath::from_chain_path(&ChainPath::from("m/44'/394'/1/0/7")).is_none());
+        assert!(DerivationPath::from_chain_path(&ChainPath::from("m/49'/394'/1'/0/7")).is_none());
+        assert!(DerivationPath::from_chain_path(&ChainPath::from("m/44'/394'/1'/0")).is_none());
+    }
 }
@@ -20,6 +20,6 @@ pub use crate::hd_wallet::extended_key::{
     key_index::KeyIndex, ExtendedPrivKey, ExtendedPubKey, KeySeed,
 };
 pub use crate::hd_wallet::key_chain::{
-    chain_path::{ChainPath, Error as ChainPathError, SubPath},
+    chain_path::{ChainPath, DerivationPath, Error as ChainPathError, SubPath},
     DefaultKeyChain, Derivation, KeyChain,
 };
@@ -10,6 +10,7 @@
 //! - Transaction history
 //! - Transaction creation and signing (with automatic unspent transaction selection)
 
+pub mod coin_selection;
 pub mod hd_seed;
 pub mod hd_wallet;
 pub mod input_selection;
@@ -24,12 +25,14 @@ pub mod types;
 pub mod unspent_transactions;
 pub mod wallet;
 
+#[doc(inline)]
+pub use crate::coin_selection::CoinSelector;
 #[doc(inline)]
 pub use crate::hd_seed::HDSeed;
 #[doc(inline)]
 pub use crate::input_selection::InputSelectionStrategy;
 #[doc(inline)]
-pub use crate::mnemonic::Mnemonic;
+pub use crate::mnemonic::{Mnemonic, MnemonicLanguage};
 #[doc(inline)]
 pub use crate::service::WalletStateMemento;
 #[doc(inline)]
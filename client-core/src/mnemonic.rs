@@ -7,9 +7,50 @@ use secstr::SecUtf8;
 use serde::de::{self, Deserializer};
 use serde::{Deserialize, Serialize, Serializer};
 
-use client_common::{ErrorKind, Result, ResultExt};
+use client_common::{Error, ErrorKind, Result, ResultExt};
+
+/// Language of the words of a mnemonic (one of the standard BIP39 wordlists)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MnemonicLanguage {
+    /// English wordlist
+    English,
+    /// Japanese wordlist
+    Japanese,
+    /// Korean wordlist
+    Korean,
+    /// Simplified Chinese wordlist
+    ChineseSimplified,
+    /// Spanish wordlist
+    Spanish,
+}
+
+impl MnemonicLanguage {
+    /// All the supported languages
+    pub const ALL: [MnemonicLanguage; 5] = [
+        MnemonicLanguage::English,
+        MnemonicLanguage::Japanese,
+        MnemonicLanguage::Korean,
+        MnemonicLanguage::ChineseSimplified,
+        MnemonicLanguage::Spanish,
+    ];
+
+    fn wordlist(self) -> Language {
+        match self {
+            MnemonicLanguage::English => Language::English,
+            MnemonicLanguage::Japanese => Language::Japanese,
+            MnemonicLanguage::Korean => Language::Korean,
+            MnemonicLanguage::ChineseSimplified => Language::ChineseSimplified,
+            MnemonicLanguage::Spanish => Language::Spanish,
+        }
+    }
+}
 
-const MNEMONIC_LANGUAGE: Language = Language::English;
+impl Default for MnemonicLanguage {
+    fn default() -> Self {
+        MnemonicLanguage::English
+    }
+}
 
 /// Mnemonic wrapped in secures string
 pub struct Mnemonic(bip39::Mnemonic);
@@ -34,10 +75,15 @@ impl<'de> Deserialize<'de> for Mnemonic {
 }
 
 impl Mnemonic {
-    /// Generate and returns mnemonic words
+    /// Generate and returns mnemonic words (in English)
     #[allow(clippy::new_without_default)]
     #[inline]
     pub fn new(mnemonics_word_count: u32) -> Result<Self> {
+        Mnemonic::new_in(mnemonics_word_count, MnemonicLanguage::English)
+    }
+
+    /// Generate and returns mnemonic words in given language
+    pub fn new_in(mnemonics_word_count: u32, language: MnemonicLanguage) -> Result<Self> {
         let flag = match mnemonics_word_count {
             12 => MnemonicType::Words12,
             15 => MnemonicType::Words15,
@@ -51,26 +97,62 @@ impl Mnemonic {
                 ))
             }
         };
-        let mnemonic = bip39::Mnemonic::new(flag, MNEMONIC_LANGUAGE);
+        let mnemonic = bip39::Mnemonic::new(flag, language.wordlist());
 
         Ok(Mnemonic(mnemonic))
     }
 
-    /// Create Mnemonic from words in secure string
+    /// Create Mnemonic from words in secure string (the language is the one whose wordlist
+    /// contains all the words)
     #[inline]
     pub fn from_secstr(words: &SecUtf8) -> Result<Self> {
         Mnemonic::from_str(words.unsecure())
     }
 
-    /// Create Mnemonic from words in string literal
+    /// Create Mnemonic from words in secure string in given language
     #[inline]
+    pub fn from_secstr_in(words: &SecUtf8, language: MnemonicLanguage) -> Result<Self> {
+        Mnemonic::from_str_in(words.unsecure(), language)
+    }
+
+    /// Create Mnemonic from words in string literal, detecting their language
     fn from_str(words: &str) -> Result<Self> {
-        let mnemonic = bip39::Mnemonic::from_phrase(words, MNEMONIC_LANGUAGE)
+        let mut candidates = MnemonicLanguage::ALL
+            .iter()
+            .filter_map(|language| Mnemonic::from_str_in(words, *language).ok());
+        match (candidates.next(), candidates.next()) {
+            (Some(mnemonic), None) => Ok(mnemonic),
+            (Some(_), Some(_)) => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Mnemonic phrase is valid in several languages, its language has to be given",
+            )),
+            (None, _) => Err(Error::new(
+                ErrorKind::DeserializationError,
+                "Invalid mnemonic phrase",
+            )),
+        }
+    }
+
+    /// Create Mnemonic from words in string literal in given language (the words are NFKD
+    /// normalized, as required by BIP39)
+    fn from_str_in(words: &str, language: MnemonicLanguage) -> Result<Self> {
+        let mnemonic = bip39::Mnemonic::from_phrase(words, language.wordlist())
             .chain(|| (ErrorKind::DeserializationError, "Invalid mnemonic phrase"))?;
 
         Ok(Mnemonic(mnemonic))
     }
 
+    /// Returns the language of the mnemonic words
+    pub fn language(&self) -> MnemonicLanguage {
+        match self.0.language() {
+            Language::Japanese => MnemonicLanguage::Japanese,
+            Language::Korean => MnemonicLanguage::Korean,
+            Language::ChineseSimplified => MnemonicLanguage::ChineseSimplified,
+            Language::Spanish => MnemonicLanguage::Spanish,
+            _ => MnemonicLanguage::English,
+        }
+    }
+
     /// Returns mnemonic phrase as secure string
     #[inline]
     pub fn phrase(&self) -> SecUtf8 {
@@ -86,9 +168,13 @@ impl Mnemonic {
     /// Returns the seed from the mnemonic words as byte slice
     #[inline]
     pub fn seed(&self) -> Vec<u8> {
-        // TODO: advanced/optional recovery" seeding option
-        // give salt as another argument, make default as ""
-        Seed::new(&self.0, "").as_bytes().to_vec()
+        self.seed_with_password("")
+    }
+
+    /// Returns the seed from the mnemonic words and an (optional BIP39) password as byte slice
+    #[inline]
+    pub fn seed_with_password(&self, password: &str) -> Vec<u8> {
+        Seed::new(&self.0, password).as_bytes().to_vec()
     }
 
     // TODO: Implement zeroize for bip39::Mnemonic phrase and entropy
@@ -130,7 +216,7 @@ mod mnemonic_tests {
             let mnemonic = Mnemonic::new(24).expect("get 24 words mnemonics");
 
             assert!(
-                bip39::Mnemonic::validate(mnemonic.unsecure_phrase(), MNEMONIC_LANGUAGE).is_ok()
+                bip39::Mnemonic::validate(mnemonic.unsecure_phrase(), Language::English).is_ok()
             );
         }
 
@@ -177,6 +263,101 @@ mod mnemonic_tests {
         }
     }
 
+    mod language {
+        use super::*;
+
+        const JAPANESE_PASSWORD: &str = "㍍ガバヴァぱばぐゞちぢ十人十色";
+
+        fn assert_seed(phrase: &str, password: &str, language: MnemonicLanguage, seed: &str) {
+            let words = SecUtf8::from(phrase);
+            let detected = Mnemonic::from_secstr(&words).expect("detect mnemonic language");
+            assert_eq!(language, detected.language());
+            assert_eq!(seed, hex::encode(detected.seed_with_password(password)));
+
+            let given = Mnemonic::from_secstr_in(&words, language).expect("mnemonic in language");
+            assert_eq!(seed, hex::encode(given.seed_with_password(password)));
+        }
+
+        // https://github.com/trezor/python-mnemonic/blob/master/vectors.json
+        #[test]
+        fn should_match_english_vector() {
+            assert_seed(
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+                "TREZOR",
+                MnemonicLanguage::English,
+                "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+            );
+        }
+
+        // https://github.com/bip32JP/bip32JP.github.io/blob/master/test_JP_BIP39.json (the
+        // words are separated with ideographic spaces and not NFKD normalized)
+        #[test]
+        fn should_match_japanese_vectors() {
+            assert_seed(
+                "あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あおぞら",
+                JAPANESE_PASSWORD,
+                MnemonicLanguage::Japanese,
+                "a262d6fb6122ecf45be09c50492b31f92e9beb7d9a845987a02cefda57a15f9c467a17872029a9e92299b5cbdf306e3a0ee620245cbd508959b6cb7ca637bd55",
+            );
+            assert_seed(
+                "うちゅう　ふそく　ひしょ　がちょう　うけもつ　めいそう　みかん　そざい　いばる　うけとる　さんま　さこつ　おうさま　ぱんつ　しひょう　めした　たはつ　いちぶ　つうじょう　てさぎょう　きつね　みすえる　いりぐち　かめれおん",
+                JAPANESE_PASSWORD,
+                MnemonicLanguage::Japanese,
+                "346b7321d8c04f6f37b49fdf062a2fddc8e1bf8f1d33171b65074531ec546d1d3469974beccb1a09263440fc92e1042580a557fdce314e27ee4eabb25fa5e5fe",
+            );
+        }
+
+        // https://github.com/infincia/bip39-rs/issues/26#issuecomment-586476647
+        #[test]
+        fn should_match_spanish_vector() {
+            assert_seed(
+                "camello pomelo toque oponer urgente lástima merengue cutis tirón pudor pomo barco",
+                "el español se habla en muchos países",
+                MnemonicLanguage::Spanish,
+                "67a2cf87b9d110dd5210275fd4d7a107a0a0dd9446e02f3822f177365786ae440b8873693c88f732834af90785753d989a367f7094230901b204c567718ce6be",
+            );
+        }
+
+        #[test]
+        fn should_detect_generated_language() {
+            for language in MnemonicLanguage::ALL.iter() {
+                let mnemonic = Mnemonic::new_in(12, *language).expect("get 12 words mnemonics");
+                assert_eq!(*language, mnemonic.language());
+
+                let recovered = Mnemonic::from_secstr(&mnemonic.phrase()).unwrap();
+                assert_eq!(*language, recovered.language());
+                assert_eq!(mnemonic.seed(), recovered.seed());
+            }
+        }
+
+        #[test]
+        fn should_reject_words_of_other_language() {
+            let words = Mnemonic::new_in(12, MnemonicLanguage::Korean)
+                .unwrap()
+                .phrase();
+            assert_eq!(
+                ErrorKind::DeserializationError,
+                Mnemonic::from_secstr_in(&words, MnemonicLanguage::ChineseSimplified)
+                    .unwrap_err()
+                    .kind()
+            );
+        }
+
+        #[test]
+        fn should_keep_english_seed() {
+            let words = SecUtf8::from("point shiver hurt flight fun online hub antenna engine pave chef fantasy front interest poem accident catch load frequent praise elite pet remove used");
+            let mnemonic = Mnemonic::from_secstr(&words).unwrap();
+            assert_eq!(
+                Seed::new(
+                    &bip39::Mnemonic::from_phrase(words.unsecure(), Language::English).unwrap(),
+                    ""
+                )
+                .as_bytes(),
+                &mnemonic.seed()[..]
+            );
+        }
+    }
+
     #[test]
     fn test_deserialize_error() {
         let invalid_mnemonic_json = "\"hello from rust\"";
@@ -1,8 +1,10 @@
 //! MultiSig operations support
 mod builder;
+mod message;
 mod session;
 mod signer;
 
 pub use builder::MultiSigBuilder;
-pub use session::MultiSigSession;
+pub use message::{NonceCommitmentMsg, NonceMsg, PartialSignatureMsg};
+pub use session::{MultiSigSession, MultiSigSessionState};
 use signer::Signer;
@@ -1,10 +1,11 @@
 use parity_scale_codec::{Decode, Encode};
 use secp256k1::schnorrsig::SchnorrSignature;
+use zeroize::Zeroizing;
 
 use chain_core::common::H256;
 use client_common::{ErrorKind, PrivateKey, PublicKey, Result, ResultExt};
 
-use super::MultiSigSession;
+use super::{MultiSigSession, MultiSigSessionState};
 
 /// MultiSig session builder tailored for Crypto.com chain flow
 ///
@@ -132,11 +133,21 @@ impl MultiSigBuilder {
         self.session.signature()
     }
 
+    /// Returns the progress of this session
+    pub fn state(&self) -> MultiSigSessionState {
+        self.session.state()
+    }
+
     /// Returns public keys of all signers in this session
     pub fn public_keys(&self) -> Vec<PublicKey> {
         self.session.public_keys()
     }
 
+    /// Returns public key of current signer
+    pub fn public_key(&self) -> PublicKey {
+        self.session.public_key.clone()
+    }
+
     /// Returns true if nonce commitment for given public key is already set,
     /// false otherwise.
     pub fn has_nonce_commitment(&self, public_key: &PublicKey) -> Result<bool> {
@@ -155,7 +166,8 @@ impl MultiSigBuilder {
         self.session.has_partial_signature(public_key)
     }
 
-    /// Returns incompleted MultiSig session in bytes
+    /// Returns incompleted MultiSig session in bytes (which include the private key of current
+    /// signer, so they should be zeroized after use)
     pub fn to_incomplete(&self) -> Vec<u8> {
         self.session.encode()
     }
@@ -168,6 +180,7 @@ impl MultiSigBuilder {
     /// If you are in doubt, always create a new session builder and restart
     /// the whole process from scratch.
     pub fn from_incomplete_insecure(bytes: Vec<u8>) -> Result<Self> {
+        let bytes = Zeroizing::new(bytes);
         let session = MultiSigSession::decode(&mut bytes.as_slice()).chain(|| {
             (
                 ErrorKind::DeserializationError,
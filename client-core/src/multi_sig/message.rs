@@ -0,0 +1,62 @@
+//! Messages which the co-signers of a multi-sig transaction exchange between their sessions
+//! (each co-signer keeps its own session in its own storage)
+use parity_scale_codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use chain_core::common::H256;
+use client_common::PublicKey;
+
+/// Nonce commitment of a co-signer, to be passed to the other co-signers of its session (e.g. by
+/// email or RPC)
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+pub struct NonceCommitmentMsg {
+    /// Session id of the sender (each co-signer has its own session)
+    pub session_id: H256,
+    /// Public key of the sender
+    pub public_key: PublicKey,
+    /// Nonce commitment of the sender
+    pub nonce_commitment: H256,
+}
+
+/// Nonce of a co-signer, to be passed to the other co-signers of its session
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+pub struct NonceMsg {
+    /// Session id of the sender (each co-signer has its own session)
+    pub session_id: H256,
+    /// Public key of the sender
+    pub public_key: PublicKey,
+    /// Nonce of the sender
+    pub nonce: H256,
+}
+
+/// Partial signature of a co-signer, to be passed to the other co-signers of its session
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+pub struct PartialSignatureMsg {
+    /// Session id of the sender (each co-signer has its own session)
+    pub session_id: H256,
+    /// Public key of the sender
+    pub public_key: PublicKey,
+    /// Partial signature of the sender
+    pub partial_signature: H256,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client_common::PrivateKey;
+
+    #[test]
+    fn check_encoding() {
+        let message = NonceMsg {
+            session_id: [1; 32],
+            public_key: PublicKey::from(&PrivateKey::new().unwrap()),
+            nonce: [2; 32],
+        };
+
+        let encoded = message.encode();
+        assert_eq!(message, NonceMsg::decode(&mut encoded.as_slice()).unwrap());
+
+        let json = serde_json::to_string(&message).unwrap();
+        assert_eq!(message, serde_json::from_str(&json).unwrap());
+    }
+}
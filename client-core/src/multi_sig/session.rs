@@ -9,8 +9,9 @@ use secp256k1experimental::musig::{
     MuSigNonce, MuSigNonceCommitment, MuSigPartialSignature, MuSigSession, MuSigSessionID,
 };
 use secp256k1experimental::schnorrsig::SchnorrSignature;
-use secp256k1experimental::{key::XOnlyPublicKey, Message, SecretKey};
+use secp256k1experimental::{key::XOnlyPublicKey, Message};
 use secp256k1experimental::{All, Secp256k1};
+use zeroize::Zeroize;
 
 thread_local! {
     /// Thread local static Secp object
@@ -22,7 +23,21 @@ use client_common::{Error, ErrorKind, PrivateKey, PublicKey, Result, ResultExt};
 
 use super::Signer;
 
-/// A MultiSig session as a basic building block
+/// Progress of a MultiSig session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultiSigSessionState {
+    /// Nonce commitments of some co-signers are not received yet
+    AwaitingNonceCommitments,
+    /// Nonces of some co-signers are not received yet
+    AwaitingNonces,
+    /// Partial signatures of some co-signers are not received yet
+    AwaitingPartialSignatures,
+    /// Partial signatures from all co-signers are received, i.e. final signature is available
+    Complete,
+}
+
+/// A MultiSig session as a basic building block. Its session id (from which the nonce of the current
+/// signer is derived), nonces and private key are zeroized when it's dropped.
 #[derive(Debug, Encode, Decode)]
 pub struct MultiSigSession {
     /// Session id
@@ -37,6 +52,18 @@ pub struct MultiSigSession {
     pub private_key: PrivateKey,
 }
 
+impl Drop for MultiSigSession {
+    fn drop(&mut self) {
+        self.id.zeroize();
+        for signer in self.signers.iter_mut() {
+            signer.nonce_commitment.zeroize();
+            signer.nonce.zeroize();
+            signer.partial_signature.zeroize();
+        }
+        // the private key is zeroized by its own destructor
+    }
+}
+
 impl MultiSigSession {
     /// for some reason, the old implementation exposed all internals + kept reconstructing the session?
     /// this is a minimal change to get it working
@@ -130,7 +157,7 @@ impl MultiSigSession {
                 &pre_init,
                 self.signers.len(),
                 self.signer_index(&self.public_key)?,
-                &SecretKey::from(&self.private_key),
+                self.private_key.as_secret_key(),
             )
             .chain(|| (ErrorKind::MultiSigError, "Unable to create session"))?;
 
@@ -173,7 +200,7 @@ impl MultiSigSession {
                 &pre_init,
                 self.signers.len(),
                 self.signer_index(&self.public_key)?,
-                &SecretKey::from(&self.private_key),
+                self.private_key.as_secret_key(),
             )
             .chain(|| (ErrorKind::MultiSigError, "Unable to create session"))?;
 
@@ -231,7 +258,7 @@ impl MultiSigSession {
                 &pre_init,
                 self.signers.len(),
                 self.signer_index(&self.public_key)?,
-                &SecretKey::from(&self.private_key),
+                self.private_key.as_secret_key(),
             )
             .chain(|| (ErrorKind::MultiSigError, "Unable to create session"))?;
 
@@ -322,7 +349,7 @@ impl MultiSigSession {
                 &pre_init,
                 self.signers.len(),
                 self.signer_index(&self.public_key)?,
-                &SecretKey::from(&self.private_key),
+                self.private_key.as_secret_key(),
             )
             .chain(|| (ErrorKind::MultiSigError, "Unable to create session"))?;
 
@@ -396,6 +423,27 @@ impl MultiSigSession {
         })
     }
 
+    /// Returns the progress of this session
+    pub fn state(&self) -> MultiSigSessionState {
+        if self
+            .signers
+            .iter()
+            .any(|signer| signer.nonce_commitment.is_none())
+        {
+            MultiSigSessionState::AwaitingNonceCommitments
+        } else if self.signers.iter().any(|signer| signer.nonce.is_none()) {
+            MultiSigSessionState::AwaitingNonces
+        } else if self
+            .signers
+            .iter()
+            .any(|signer| signer.partial_signature.is_none())
+        {
+            MultiSigSessionState::AwaitingPartialSignatures
+        } else {
+            MultiSigSessionState::Complete
+        }
+    }
+
     /// Returns public keys of all signers in this session
     pub fn public_keys(&self) -> Vec<PublicKey> {
         self.signers
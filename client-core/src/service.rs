@@ -1,32 +1,73 @@
 //! Management services
+mod address_book_service;
+mod address_label_service;
+mod backup_service;
+mod chain_tip_service;
+mod fee_estimate_service;
 mod hd_key_service;
+mod history_index_service;
 mod hw_key_service;
 mod key_service;
 mod ledger_service;
 #[cfg(feature = "experimental")]
 mod multi_sig_session_service;
+mod output_lock_service;
+mod pending_transaction_service;
 mod root_hash_service;
+mod signing_session_service;
 mod sync_state_service;
+mod transaction_metadata_service;
 mod wallet_service;
 mod wallet_state_service;
 
 #[doc(hidden)]
 pub use self::wallet_state_service::WalletStateMemento;
 
+pub use self::address_book_service::{AddressBookService, CONTACT_PREFIX, MAX_CONTACT_NAME_LENGTH};
+pub use self::address_label_service::{AddressLabelService, MAX_ADDRESS_LABEL_LENGTH};
+pub use self::backup_service::{
+    restore_records, seal_records, ArchiveBlob, BackupBlob, BackupRecords, BackupService,
+    BACKUP_VERSION, MAX_BACKUP_SNAPSHOTS,
+};
+pub use self::chain_tip_service::{
+    ChainTipRecord, ChainTipService, CHAIN_TIP_RECORD_INTERVAL, MAX_CHAIN_TIP_RECORDS,
+};
+pub use self::fee_estimate_service::FeeEstimateService;
 pub use self::hd_key_service::{HDAccountType, HdKey, HdKeyService};
+pub use self::history_index_service::{
+    batch_delete_history_index, batch_index_memento, batch_rollback_history_index,
+    delete_history_index, HistoryIndexService, HISTORY_INDEX_VERSION,
+};
 pub use self::hw_key_service::{HwKeyService, UnauthorizedHwKeyService};
 pub use self::key_service::KeyService;
 pub use self::ledger_service::{
     LedgerServiceHID, LedgerServiceZemu, LedgerSignKeyHID, LedgerSignKeyZemu,
 };
 #[cfg(feature = "experimental")]
-pub use self::multi_sig_session_service::MultiSigSessionService;
+pub use self::multi_sig_session_service::{MultiSigSessionInfo, MultiSigSessionService};
+pub use self::output_lock_service::{
+    unlock_spent_outputs, OutputLockService, DEFAULT_OUTPUT_LOCK_TIMEOUT,
+};
+pub use self::pending_transaction_service::{
+    prune_pending_transactions, reload_pending_transactions, PendingTransaction,
+    PendingTransactionService, DEFAULT_PENDING_TRANSACTIONS_MAX_SIZE,
+    DEFAULT_PENDING_TRANSACTION_TIMEOUT,
+};
 pub use self::root_hash_service::RootHashService;
+pub use self::signing_session_service::{
+    SigningSessionService, DEFAULT_SIGNING_SESSION_TTL, MAX_SIGNING_SESSION_TTL,
+};
 pub use self::sync_state_service::{
-    delete_sync_state, load_sync_state, save_sync_state, SyncState, SyncStateService,
+    add_sync_checkpoints, batch_add_sync_checkpoints, batch_delete_sync_state,
+    batch_save_sync_checkpoints, batch_save_sync_state, delete_sync_state, load_sync_checkpoints,
+    load_sync_state, save_sync_state, SyncCheckpoint, SyncState, SyncStateService,
+    MAX_SYNC_CHECKPOINTS,
+};
+pub use self::transaction_metadata_service::{
+    TransactionMetadataService, MAX_TRANSACTION_MEMO_LENGTH,
 };
 pub use self::wallet_service::{load_wallet, Wallet, WalletInfo, WalletService, WalletStorageImpl};
 pub use self::wallet_state_service::{
-    delete_wallet_state, load_wallet_state, modify_wallet_state, save_wallet_state, WalletState,
-    WalletStateService,
+    batch_apply_memento, batch_delete_wallet_state, batch_save_wallet_state, delete_wallet_state,
+    load_wallet_state, modify_wallet_state, save_wallet_state, WalletState, WalletStateService,
 };
@@ -0,0 +1,200 @@
+//! Address book of a wallet: the addresses of the counterparties it sends to, by contact name
+use std::collections::BTreeMap;
+
+use chain_core::tx::data::address::ExtendedAddr;
+use client_common::{Error, ErrorKind, Result, SecKey, SecureStorage, Storage, StorageBatch};
+
+const KEYSPACE: &str = "core_address_book";
+
+/// Maximum length (in bytes) of a contact name
+pub const MAX_CONTACT_NAME_LENGTH: usize = 64;
+
+/// Prefix of the contact names given in place of an address (e.g. `contact:alice`)
+pub const CONTACT_PREFIX: &str = "contact:";
+
+/// Maintains mapping `wallet-name -> (contact-name -> address)` (encrypted with the wallet's
+/// enckey). Contact names are unique within a wallet.
+#[derive(Debug, Clone)]
+pub struct AddressBookService<S>
+where
+    S: Storage,
+{
+    storage: S,
+}
+
+impl<S> AddressBookService<S>
+where
+    S: Storage,
+{
+    /// Creates a new instance of address book service
+    #[inline]
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// Adds a contact to the address book of the wallet. Fails with `ErrorKind::AlreadyExists` if
+    /// the wallet already has a contact with the same name.
+    pub fn add_contact(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        contact: &str,
+        address: &ExtendedAddr,
+    ) -> Result<()> {
+        if contact.is_empty() || contact.len() > MAX_CONTACT_NAME_LENGTH {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Contact name must be 1 to {} bytes long",
+                    MAX_CONTACT_NAME_LENGTH
+                ),
+            ));
+        }
+
+        let mut contacts = self.contacts(name, enckey)?;
+        if contacts.contains_key(contact) {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("Contact {} already exists", contact),
+            ));
+        }
+        contacts.insert(contact.to_owned(), address.clone());
+        self.storage.save_secure(KEYSPACE, name, enckey, &contacts)
+    }
+
+    /// Removes a contact from the address book of the wallet
+    pub fn remove_contact(&self, name: &str, enckey: &SecKey, contact: &str) -> Result<()> {
+        let mut contacts = self.contacts(name, enckey)?;
+        if contacts.remove(contact).is_none() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Contact {} not found", contact),
+            ));
+        }
+        self.storage.save_secure(KEYSPACE, name, enckey, &contacts)
+    }
+
+    /// Returns the address of a contact
+    pub fn find_contact(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        contact: &str,
+    ) -> Result<Option<ExtendedAddr>> {
+        Ok(self.contacts(name, enckey)?.remove(contact))
+    }
+
+    /// Returns all the contacts of the wallet
+    pub fn contacts(&self, name: &str, enckey: &SecKey) -> Result<BTreeMap<String, ExtendedAddr>> {
+        Ok(self
+            .storage
+            .load_secure(KEYSPACE, name, enckey)?
+            .unwrap_or_default())
+    }
+
+    /// Adds the write which re-encrypts the address book of the wallet with a new enckey to the
+    /// batch
+    pub fn batch_change_enckey(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        self.storage
+            .batch_reencrypt(batch, KEYSPACE, name, enckey, new_enckey)
+            .map(|_| ())
+    }
+
+    /// Adds the writes which move the address book of the wallet to a new name (and enckey) to
+    /// the batch
+    pub fn batch_rename(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_name: &str,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        let contacts = self.contacts(name, enckey)?;
+        batch.save_secure(KEYSPACE, new_name, new_enckey, &contacts)?;
+        batch.delete(KEYSPACE, name);
+        Ok(())
+    }
+
+    /// Adds the deletion of the address book of the wallet to the batch
+    #[inline]
+    pub fn batch_delete(&self, batch: &mut StorageBatch, name: &str) {
+        batch.delete(KEYSPACE, name);
+    }
+
+    /// Clears all storage
+    #[inline]
+    pub fn clear(&self) -> Result<()> {
+        self.storage.clear(KEYSPACE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client_common::seckey::derive_enckey;
+    use client_common::storage::MemoryStorage;
+    use secstr::SecUtf8;
+
+    #[test]
+    fn check_flow() {
+        let storage = MemoryStorage::default();
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let service = AddressBookService::new(storage.clone());
+        let alice = ExtendedAddr::OrTree([1; 32]);
+        let bob = ExtendedAddr::OrTree([2; 32]);
+
+        service
+            .add_contact("name", &enckey, "alice", &alice)
+            .unwrap();
+        assert_eq!(
+            ErrorKind::AlreadyExists,
+            service
+                .add_contact("name", &enckey, "alice", &bob)
+                .unwrap_err()
+                .kind()
+        );
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            service
+                .add_contact("name", &enckey, "", &bob)
+                .unwrap_err()
+                .kind()
+        );
+        service.add_contact("name", &enckey, "bob", &bob).unwrap();
+
+        let service = AddressBookService::new(storage);
+        assert_eq!(
+            Some(alice),
+            service.find_contact("name", &enckey, "alice").unwrap()
+        );
+        assert_eq!(2, service.contacts("name", &enckey).unwrap().len());
+        // the address books of the wallets are distinct
+        assert!(service.contacts("other", &enckey).unwrap().is_empty());
+
+        service.remove_contact("name", &enckey, "alice").unwrap();
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            service
+                .remove_contact("name", &enckey, "alice")
+                .unwrap_err()
+                .kind()
+        );
+        assert_eq!(
+            None,
+            service.find_contact("name", &enckey, "alice").unwrap()
+        );
+
+        let wrong_enckey = derive_enckey(&SecUtf8::from("wrong"), "name").unwrap();
+        assert!(service.contacts("name", &wrong_enckey).is_err());
+        assert!(service
+            .add_contact("name", &wrong_enckey, "carol", &bob)
+            .is_err());
+    }
+}
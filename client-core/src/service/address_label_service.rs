@@ -0,0 +1,228 @@
+//! Labels given by the user to the addresses of a wallet
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use client_common::{Error, ErrorKind, Result, SecKey, SecureStorage, Storage, StorageBatch};
+
+use crate::types::WalletAddress;
+
+const KEYSPACE: &str = "core_address_label";
+
+/// Maximum length (in bytes) of an address label
+pub const MAX_ADDRESS_LABEL_LENGTH: usize = 64;
+
+/// Maintains mapping `wallet-name -> (address -> label)` (encrypted with the wallet's enckey).
+/// Labels are unique within a wallet.
+#[derive(Debug, Clone)]
+pub struct AddressLabelService<S>
+where
+    S: Storage,
+{
+    storage: S,
+}
+
+impl<S> AddressLabelService<S>
+where
+    S: Storage,
+{
+    /// Creates a new instance of address label service
+    #[inline]
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// Sets the label of an address (an empty label removes it). Fails with
+    /// `ErrorKind::AlreadyExists` if another address of the wallet has the same label.
+    pub fn set_label(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        address: &WalletAddress,
+        label: &str,
+    ) -> Result<()> {
+        let mut labels = self.get_labels(name, enckey)?;
+        let address = address.to_string();
+
+        if label.is_empty() {
+            if labels.remove(&address).is_none() {
+                return Ok(());
+            }
+        } else {
+            if label.len() > MAX_ADDRESS_LABEL_LENGTH {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Address label cannot be longer than {} bytes",
+                        MAX_ADDRESS_LABEL_LENGTH
+                    ),
+                ));
+            }
+            if let Some((labelled, _)) = labels
+                .iter()
+                .find(|(labelled, existing)| existing.as_str() == label && **labelled != address)
+            {
+                return Err(Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!("Label {} is already given to address {}", label, labelled),
+                ));
+            }
+            labels.insert(address, label.to_owned());
+        }
+        self.storage.save_secure(KEYSPACE, name, enckey, &labels)
+    }
+
+    /// Returns the label of an address
+    pub fn get_label(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        address: &WalletAddress,
+    ) -> Result<Option<String>> {
+        Ok(self.get_labels(name, enckey)?.remove(&address.to_string()))
+    }
+
+    /// Returns the address with given label
+    pub fn find_address(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        label: &str,
+    ) -> Result<Option<WalletAddress>> {
+        self.get_labels(name, enckey)?
+            .into_iter()
+            .find(|(_, existing)| existing == label)
+            .map(|(address, _)| WalletAddress::from_str(&address))
+            .transpose()
+    }
+
+    /// Returns the labels of all the labelled addresses of the wallet (indexed by the string
+    /// representation of the addresses)
+    pub fn get_labels(&self, name: &str, enckey: &SecKey) -> Result<BTreeMap<String, String>> {
+        Ok(self
+            .storage
+            .load_secure(KEYSPACE, name, enckey)?
+            .unwrap_or_default())
+    }
+
+    /// Adds the write which re-encrypts the labels of the wallet with a new enckey to the batch
+    pub fn batch_change_enckey(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        self.storage
+            .batch_reencrypt(batch, KEYSPACE, name, enckey, new_enckey)
+            .map(|_| ())
+    }
+
+    /// Adds the writes which move the labels of the wallet to a new name (and enckey) to the batch
+    pub fn batch_rename(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_name: &str,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        let labels = self.get_labels(name, enckey)?;
+        batch.save_secure(KEYSPACE, new_name, new_enckey, &labels)?;
+        batch.delete(KEYSPACE, name);
+        Ok(())
+    }
+
+    /// Delete all the labels of the wallet
+    #[inline]
+    pub fn delete(&self, name: &str) -> Result<()> {
+        self.storage.delete(KEYSPACE, name)?;
+        Ok(())
+    }
+
+    /// Adds the deletion of the address labels of the wallet to the batch
+    #[inline]
+    pub fn batch_delete(&self, batch: &mut StorageBatch, name: &str) {
+        batch.delete(KEYSPACE, name);
+    }
+
+    /// Clears all storage
+    #[inline]
+    pub fn clear(&self) -> Result<()> {
+        self.storage.clear(KEYSPACE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain_core::tx::data::address::ExtendedAddr;
+    use client_common::seckey::derive_enckey;
+    use client_common::storage::MemoryStorage;
+    use secstr::SecUtf8;
+
+    #[test]
+    fn check_flow() {
+        let storage = MemoryStorage::default();
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let service = AddressLabelService::new(storage.clone());
+        let savings = WalletAddress::Transfer(ExtendedAddr::OrTree([1; 32]));
+        let payroll = WalletAddress::Transfer(ExtendedAddr::OrTree([2; 32]));
+
+        service
+            .set_label("name", &enckey, &savings, "savings")
+            .unwrap();
+        // setting the same label again is fine
+        service
+            .set_label("name", &enckey, &savings, "savings")
+            .unwrap();
+        assert_eq!(
+            ErrorKind::AlreadyExists,
+            service
+                .set_label("name", &enckey, &payroll, "savings")
+                .unwrap_err()
+                .kind()
+        );
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            service
+                .set_label(
+                    "name",
+                    &enckey,
+                    &payroll,
+                    &"x".repeat(MAX_ADDRESS_LABEL_LENGTH + 1)
+                )
+                .unwrap_err()
+                .kind()
+        );
+
+        let service = AddressLabelService::new(storage);
+        assert_eq!(
+            Some(savings.clone()),
+            service.find_address("name", &enckey, "savings").unwrap()
+        );
+        assert_eq!(
+            Some("savings".to_owned()),
+            service.get_label("name", &enckey, &savings).unwrap()
+        );
+
+        // removing the label frees it for another address
+        service.set_label("name", &enckey, &savings, "").unwrap();
+        assert_eq!(
+            None,
+            service.find_address("name", &enckey, "savings").unwrap()
+        );
+        service
+            .set_label("name", &enckey, &payroll, "savings")
+            .unwrap();
+        assert_eq!(
+            Some(payroll),
+            service.find_address("name", &enckey, "savings").unwrap()
+        );
+
+        let wrong_enckey = derive_enckey(&SecUtf8::from("wrong"), "name").unwrap();
+        assert!(service.get_labels("name", &wrong_enckey).is_err());
+
+        service.delete("name").unwrap();
+        assert!(service.get_labels("name", &enckey).unwrap().is_empty());
+    }
+}
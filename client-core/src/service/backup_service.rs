@@ -0,0 +1,470 @@
+//! Encrypted wallet backups. A wallet is split into name-independent records (wallet metadata,
+//! private keys, multi-sig addresses, sync and wallet state); a full snapshot contains all of
+//! them and an incremental snapshot only the ones modified since a prior snapshot.
+//!
+//! Snapshot payloads are compressed and encrypted (AES-GCM-SIV) with a key derived from the
+//! backup passphrase, the blob header (including snapshot ids) is authenticated as associated
+//! data, so a chain of snapshots can't be reordered or spliced.
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read, Write};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use parity_scale_codec::{Decode, Encode};
+use secstr::SecUtf8;
+
+use chain_core::common::H256;
+use client_common::seckey::derive_enckey;
+use client_common::storage::{decrypt_bytes, encrypt_bytes};
+use client_common::{
+    Error, ErrorKind, Result, ResultExt, SecKey, SecureStorage, Storage, StorageBatch,
+};
+
+const KEYSPACE: &str = "core_wallet_backup";
+
+/// Version of the backup blob format
+pub const BACKUP_VERSION: u8 = 1;
+/// Maximum number of snapshots (per wallet) which incremental snapshots can be based on
+pub const MAX_BACKUP_SNAPSHOTS: usize = 100;
+
+/// Backup records: `record id -> encoded record`
+pub type BackupRecords = BTreeMap<String, Vec<u8>>;
+
+/// Encrypted snapshot of wallet records
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct BackupBlob {
+    /// format version
+    pub version: u8,
+    /// id of this snapshot
+    pub snapshot_id: H256,
+    /// id of the snapshot this (incremental) snapshot is based on, `None` for a full snapshot
+    pub parent_snapshot_id: Option<H256>,
+    /// salt of the backup key derivation
+    pub salt: [u8; 16],
+    /// encrypted and compressed payload
+    pub ciphertext: Vec<u8>,
+}
+
+/// Encrypted archive of wallet records moved out of the live storage (a standalone full snapshot)
+pub type ArchiveBlob = BackupBlob;
+
+impl BackupBlob {
+    /// Authenticated (but not encrypted) part of the blob
+    fn header(&self) -> Vec<u8> {
+        (
+            self.version,
+            self.snapshot_id,
+            self.parent_snapshot_id,
+            self.salt,
+        )
+            .encode()
+    }
+}
+
+#[derive(Debug, Default, Encode, Decode)]
+struct BackupPayload {
+    /// records added or modified since the parent snapshot (all the records in a full snapshot)
+    records: Vec<(String, Vec<u8>)>,
+    /// ids of the records removed since the parent snapshot
+    deleted: Vec<String>,
+}
+
+/// Modification counters of the wallet records, and the counters at the time of each snapshot
+#[derive(Debug, Default, Encode, Decode)]
+struct BackupIndex {
+    /// `record id -> (modification counter, hash of the record)`
+    records: BTreeMap<String, (u64, H256)>,
+    /// `snapshot id -> (record id -> modification counter)`, oldest first
+    snapshots: Vec<(H256, BTreeMap<String, u64>)>,
+}
+
+/// Takes snapshots of wallet records, keeping track of the record modifications
+///
+/// Stores `wallet-name -> backup index` (encrypted with the wallet's enckey)
+#[derive(Debug, Default, Clone)]
+pub struct BackupService<S>
+where
+    S: Storage,
+{
+    storage: S,
+}
+
+impl<S> BackupService<S>
+where
+    S: Storage,
+{
+    /// Creates a new instance of backup service
+    #[inline]
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// Takes a snapshot of given (current) records, encrypted with `backup_key`
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: all the current records of the wallet
+    /// - `since`: id of a prior snapshot, only the records modified after it are included if given
+    pub fn snapshot(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        records: BackupRecords,
+        since: Option<&H256>,
+        backup_key: &SecUtf8,
+    ) -> Result<BackupBlob> {
+        let mut index: BackupIndex = self
+            .storage
+            .load_secure(KEYSPACE, name, enckey)?
+            .unwrap_or_default();
+
+        index.records.retain(|id, _| records.contains_key(id));
+        for (id, record) in records.iter() {
+            let hash = *blake3::hash(record).as_bytes();
+            let entry = index.records.entry(id.clone()).or_insert((0, hash));
+            if entry.1 != hash {
+                *entry = (entry.0 + 1, hash);
+            }
+        }
+        let counters = index
+            .records
+            .iter()
+            .map(|(id, (counter, _))| (id.clone(), *counter))
+            .collect::<BTreeMap<_, _>>();
+
+        let payload = match since {
+            None => BackupPayload {
+                records: records.into_iter().collect(),
+                deleted: vec![],
+            },
+            Some(since) => {
+                let (_, base) = index
+                    .snapshots
+                    .iter()
+                    .find(|(snapshot_id, _)| snapshot_id == since)
+                    .chain(|| {
+                        (
+                            ErrorKind::InvalidInput,
+                            format!("Backup snapshot {} not found", hex::encode(since)),
+                        )
+                    })?;
+                BackupPayload {
+                    records: records
+                        .into_iter()
+                        .filter(|(id, _)| base.get(id) != counters.get(id))
+                        .collect(),
+                    deleted: base
+                        .keys()
+                        .filter(|id| !counters.contains_key(*id))
+                        .cloned()
+                        .collect(),
+                }
+            }
+        };
+
+        let blob = seal(&payload, since.copied(), backup_key)?;
+
+        index.snapshots.push((blob.snapshot_id, counters));
+        if index.snapshots.len() > MAX_BACKUP_SNAPSHOTS {
+            index
+                .snapshots
+                .drain(..index.snapshots.len() - MAX_BACKUP_SNAPSHOTS);
+        }
+        self.storage.save_secure(KEYSPACE, name, enckey, &index)?;
+
+        Ok(blob)
+    }
+
+    /// Adds the write which re-encrypts the backup index of the wallet with a new enckey to the batch
+    pub fn batch_change_enckey(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        self.storage
+            .batch_reencrypt(batch, KEYSPACE, name, enckey, new_enckey)
+            .map(|_| ())
+    }
+
+    /// Adds the writes which move the backup index of the wallet to a new name (and enckey) to
+    /// the batch, so that the incremental snapshots can still be based on the prior ones
+    pub fn batch_rename(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_name: &str,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        let index: Option<BackupIndex> = self.storage.load_secure(KEYSPACE, name, enckey)?;
+        if let Some(index) = index {
+            batch.save_secure(KEYSPACE, new_name, new_enckey, &index)?;
+            batch.delete(KEYSPACE, name);
+        }
+        Ok(())
+    }
+
+    /// Deletes backup index of given wallet
+    #[inline]
+    pub fn delete(&self, name: &str) -> Result<()> {
+        self.storage.delete(KEYSPACE, name).map(|_| ())
+    }
+
+    /// Adds the deletion of the backups of the wallet to the batch
+    #[inline]
+    pub fn batch_delete(&self, batch: &mut StorageBatch, name: &str) {
+        batch.delete(KEYSPACE, name);
+    }
+
+    /// Clears all storage
+    #[inline]
+    pub fn clear(&self) -> Result<()> {
+        self.storage.clear(KEYSPACE)
+    }
+}
+
+/// Seals given records into a full snapshot which isn't recorded in the backup index of the
+/// wallet (e.g. an archive), encrypted with `backup_key`
+pub fn seal_records(records: BackupRecords, backup_key: &SecUtf8) -> Result<BackupBlob> {
+    let payload = BackupPayload {
+        records: records.into_iter().collect(),
+        deleted: vec![],
+    };
+    seal(&payload, None, backup_key)
+}
+
+/// Decrypts a chain of snapshots (a full snapshot followed by incremental ones, each based on the
+/// previous one) and returns the restored records
+pub fn restore_records(blobs: &[BackupBlob], backup_key: &SecUtf8) -> Result<BackupRecords> {
+    let (first, _) = blobs
+        .split_first()
+        .chain(|| (ErrorKind::InvalidInput, "No backup snapshot given"))?;
+    if first.parent_snapshot_id.is_some() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Backup chain has to start with a full snapshot",
+        ));
+    }
+    for (previous, blob) in blobs.iter().zip(blobs.iter().skip(1)) {
+        if blob.parent_snapshot_id != Some(previous.snapshot_id) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Broken backup chain: snapshot {} is not based on snapshot {}",
+                    hex::encode(&blob.snapshot_id),
+                    hex::encode(&previous.snapshot_id)
+                ),
+            ));
+        }
+    }
+
+    let mut records = BackupRecords::new();
+    for blob in blobs.iter() {
+        let payload = open(blob, backup_key)?;
+        for id in payload.deleted.iter() {
+            records.remove(id);
+        }
+        records.extend(payload.records);
+    }
+    Ok(records)
+}
+
+fn backup_enckey(backup_key: &SecUtf8, salt: &[u8]) -> Result<SecKey> {
+    derive_enckey(backup_key, &hex::encode(salt))
+        .err_kind(ErrorKind::InvalidInput, || "unable to derive backup key")
+}
+
+fn seal(
+    payload: &BackupPayload,
+    parent_snapshot_id: Option<H256>,
+    backup_key: &SecUtf8,
+) -> Result<BackupBlob> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(&payload.encode())
+        .chain(|| (ErrorKind::IoError, "Unable to compress backup"))?;
+    let compressed = encoder
+        .finish()
+        .chain(|| (ErrorKind::IoError, "Unable to compress backup"))?;
+
+    let salt: [u8; 16] = rand::random();
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&parent_snapshot_id.encode());
+    hasher.update(&salt);
+    hasher.update(&compressed);
+
+    let mut blob = BackupBlob {
+        version: BACKUP_VERSION,
+        snapshot_id: *hasher.finalize().as_bytes(),
+        parent_snapshot_id,
+        salt,
+        ciphertext: vec![],
+    };
+    let key = backup_enckey(backup_key, &salt)?;
+    blob.ciphertext = encrypt_bytes(blob.header(), &key, &compressed)?;
+    Ok(blob)
+}
+
+fn open(blob: &BackupBlob, backup_key: &SecUtf8) -> Result<BackupPayload> {
+    if blob.version != BACKUP_VERSION {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Unsupported backup version: {}", blob.version),
+        ));
+    }
+    let key = backup_enckey(backup_key, &blob.salt)?;
+    let compressed = decrypt_bytes(blob.header(), &key, &blob.ciphertext).chain(|| {
+        (
+            ErrorKind::DecryptionError,
+            "Unable to decrypt backup: incorrect backup key or corrupted snapshot",
+        )
+    })?;
+
+    let mut encoded = Vec::new();
+    DeflateDecoder::new(compressed.as_slice())
+        .read_to_end(&mut encoded)
+        .chain(|| {
+            (
+                ErrorKind::DeserializationError,
+                "Unable to decompress backup",
+            )
+        })?;
+    let payload = BackupPayload::decode(&mut encoded.as_slice())
+        .chain(|| (ErrorKind::DeserializationError, "Unable to decode backup"))?;
+
+    let ids = payload
+        .records
+        .iter()
+        .map(|(id, _)| id)
+        .collect::<BTreeSet<_>>();
+    if ids.len() != payload.records.len() {
+        return Err(Error::new(
+            ErrorKind::DeserializationError,
+            "Duplicated records in backup",
+        ));
+    }
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use client_common::storage::MemoryStorage;
+
+    fn records(items: &[(&str, &[u8])]) -> BackupRecords {
+        items
+            .iter()
+            .map(|(id, record)| (id.to_string(), record.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn check_incremental_chain() {
+        let service = BackupService::new(MemoryStorage::default());
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let backup_key = SecUtf8::from("backup key");
+
+        let full = service
+            .snapshot(
+                "name",
+                &enckey,
+                records(&[("a", b"1"), ("b", b"2"), ("c", b"3")]),
+                None,
+                &backup_key,
+            )
+            .unwrap();
+        assert!(full.parent_snapshot_id.is_none());
+
+        let second = service
+            .snapshot(
+                "name",
+                &enckey,
+                records(&[("a", b"1"), ("b", b"20"), ("d", b"4")]),
+                Some(&full.snapshot_id),
+                &backup_key,
+            )
+            .unwrap();
+        let payload = open(&second, &backup_key).unwrap();
+        assert_eq!(
+            payload.records,
+            vec![
+                ("b".to_owned(), b"20".to_vec()),
+                ("d".to_owned(), b"4".to_vec())
+            ]
+        );
+        assert_eq!(payload.deleted, vec!["c".to_owned()]);
+
+        let third = service
+            .snapshot(
+                "name",
+                &enckey,
+                records(&[("a", b"10"), ("b", b"20"), ("d", b"4")]),
+                Some(&second.snapshot_id),
+                &backup_key,
+            )
+            .unwrap();
+        assert_eq!(open(&third, &backup_key).unwrap().records.len(), 1);
+
+        assert_eq!(
+            restore_records(&[full.clone(), second.clone(), third.clone()], &backup_key).unwrap(),
+            records(&[("a", b"10"), ("b", b"20"), ("d", b"4")])
+        );
+
+        // broken chains
+        for chain in vec![
+            vec![second.clone(), third.clone()],
+            vec![full.clone(), third.clone()],
+            vec![full.clone(), third, second],
+        ] {
+            assert_eq!(
+                ErrorKind::InvalidInput,
+                restore_records(&chain, &backup_key).unwrap_err().kind()
+            );
+        }
+
+        // tampered header
+        let mut tampered = full;
+        tampered.snapshot_id[0] ^= 1;
+        assert_eq!(
+            ErrorKind::DecryptionError,
+            restore_records(&[tampered], &backup_key)
+                .unwrap_err()
+                .kind()
+        );
+
+        // unknown snapshot
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            service
+                .snapshot("name", &enckey, records(&[]), Some(&[0; 32]), &backup_key)
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn check_wrong_backup_key() {
+        let service = BackupService::new(MemoryStorage::default());
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+
+        let blob = service
+            .snapshot(
+                "name",
+                &enckey,
+                records(&[("a", b"1")]),
+                None,
+                &SecUtf8::from("backup key"),
+            )
+            .unwrap();
+        assert_eq!(
+            ErrorKind::DecryptionError,
+            restore_records(&[blob], &SecUtf8::from("other key"))
+                .unwrap_err()
+                .kind()
+        );
+    }
+}
@@ -0,0 +1,493 @@
+//! Tamper-evident records of the chain tip known by a wallet: every `CHAIN_TIP_RECORD_INTERVAL`
+//! synced blocks, the wallet records the height and the app hash of the last synced block,
+//! authenticated with a key derived from its enckey and numbered by a counter. They detect a
+//! storage restored from an older copy (the records and the sync state don't agree, or records
+//! are missing) and a node which switched to another history (its header at the height of the
+//! latest record has another app hash).
+use parity_scale_codec::{Decode, Encode};
+
+use chain_core::common::H256;
+use client_common::{Error, ErrorKind, Result, ResultExt, SecKey, Storage, StorageBatch};
+
+const KEYSPACE: &str = "core_chain_tip";
+/// key space of the wallets whose history mismatched, until it's acknowledged
+const MISMATCH_KEYSPACE: &str = "core_chain_tip_mismatch";
+
+const MAC_CONTEXT: &str =
+    "Thaler Experimental Network Wallet 2020-11-02 10:21:42 chain tip record authentication";
+
+/// Number of synced blocks between two records
+pub const CHAIN_TIP_RECORD_INTERVAL: u64 = 100;
+/// Maximum number of records kept per wallet (the oldest ones are dropped)
+pub const MAX_CHAIN_TIP_RECORDS: usize = 16;
+
+/// Synced block recorded by a wallet
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct ChainTipRecord {
+    /// counter of the record, incremented by one for each record of the wallet
+    pub counter: u64,
+    /// block height
+    pub block_height: u64,
+    /// app hash in the header of the block (hex encoded)
+    pub app_hash: String,
+    /// authentication code of the record
+    pub mac: H256,
+}
+
+fn mac_key(enckey: &SecKey) -> [u8; 32] {
+    let mut key = [0; 32];
+    blake3::derive_key(MAC_CONTEXT, enckey.unsecure(), &mut key);
+    key
+}
+
+fn compute_mac(
+    key: &[u8; 32],
+    name: &str,
+    counter: u64,
+    block_height: u64,
+    app_hash: &str,
+) -> H256 {
+    blake3::keyed_hash(key, &(name, counter, block_height, app_hash).encode()).into()
+}
+
+impl ChainTipRecord {
+    fn new(key: &[u8; 32], name: &str, counter: u64, block_height: u64, app_hash: &str) -> Self {
+        ChainTipRecord {
+            counter,
+            block_height,
+            app_hash: app_hash.to_owned(),
+            mac: compute_mac(key, name, counter, block_height, app_hash),
+        }
+    }
+
+    fn is_authentic(&self, key: &[u8; 32], name: &str) -> bool {
+        compute_mac(key, name, self.counter, self.block_height, &self.app_hash) == self.mac
+    }
+}
+
+fn history_mismatch(name: &str, reason: impl std::fmt::Display) -> Error {
+    Error::new(
+        ErrorKind::HistoryMismatch,
+        format!("History of wallet {} doesn't match: {}", name, reason),
+    )
+}
+
+/// Maintains mapping `wallet-name -> chain tip records` (authenticated, not encrypted) and
+/// `wallet-name -> reason of history mismatch`
+#[derive(Debug, Default, Clone)]
+pub struct ChainTipService<S>
+where
+    S: Storage,
+{
+    storage: S,
+}
+
+impl<S> ChainTipService<S>
+where
+    S: Storage,
+{
+    /// Creates a new instance of chain tip service
+    #[inline]
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// Returns the records of the wallet (ordered by counter), without verifying them
+    pub fn records(&self, name: &str) -> Result<Vec<ChainTipRecord>> {
+        Ok(self.storage.load(KEYSPACE, name)?.unwrap_or_default())
+    }
+
+    /// Adds the write of a record for the block at `block_height` (with `app_hash` in its header)
+    /// to the batch, if `CHAIN_TIP_RECORD_INTERVAL` blocks were synced since the latest record
+    /// (or there is none yet), or if it's `forced` (e.g. the wallet was rolled back below the
+    /// latest record)
+    pub fn batch_append(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        block_height: u64,
+        app_hash: &str,
+        forced: bool,
+    ) -> Result<()> {
+        let mut records = self.records(name)?;
+        let counter = match records.last() {
+            Some(latest)
+                if !forced && block_height < latest.block_height + CHAIN_TIP_RECORD_INTERVAL =>
+            {
+                return Ok(())
+            }
+            Some(latest) => latest.counter + 1,
+            None => 0,
+        };
+        records.push(ChainTipRecord::new(
+            &mac_key(enckey),
+            name,
+            counter,
+            block_height,
+            app_hash,
+        ));
+        if records.len() > MAX_CHAIN_TIP_RECORDS {
+            records.drain(..records.len() - MAX_CHAIN_TIP_RECORDS);
+        }
+        batch.save(KEYSPACE, name, &records);
+        Ok(())
+    }
+
+    /// Verifies the records of the wallet against the height of its sync state (`None` if it
+    /// has none) and returns the latest one. Fails with `ErrorKind::HistoryMismatch` if a record
+    /// isn't authentic, if records are missing (gap in their counters, or more than
+    /// `CHAIN_TIP_RECORD_INTERVAL` blocks synced since the latest one), or if the sync state is
+    /// older than the latest record, i.e. a part of the storage was restored from an older copy.
+    ///
+    /// A wallet without records (e.g. synced before they were introduced) starts a new sequence.
+    pub fn verify(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        sync_height: Option<u64>,
+    ) -> Result<Option<ChainTipRecord>> {
+        let records = self.records(name)?;
+        let latest = match records.last() {
+            Some(latest) => latest.clone(),
+            None => return Ok(None),
+        };
+
+        let key = mac_key(enckey);
+        if let Some(record) = records
+            .iter()
+            .find(|record| !record.is_authentic(&key, name))
+        {
+            return Err(history_mismatch(
+                name,
+                format!("record {} was tampered with", record.counter),
+            ));
+        }
+        if let Some(pair) = records
+            .windows(2)
+            .find(|pair| pair[1].counter != pair[0].counter + 1)
+        {
+            return Err(history_mismatch(
+                name,
+                format!(
+                    "records {} to {} are missing",
+                    pair[0].counter + 1,
+                    pair[1].counter.saturating_sub(1)
+                ),
+            ));
+        }
+
+        match sync_height {
+            None => Err(history_mismatch(name, "sync state is missing")),
+            Some(height) if height < latest.block_height => Err(history_mismatch(
+                name,
+                format!(
+                    "sync state (block {}) is older than the latest record {} (block {})",
+                    height, latest.counter, latest.block_height
+                ),
+            )),
+            Some(height) if height >= latest.block_height + CHAIN_TIP_RECORD_INTERVAL => {
+                Err(history_mismatch(
+                    name,
+                    format!(
+                        "records after record {} (block {}) are missing, sync state is at block {}",
+                        latest.counter, latest.block_height, height
+                    ),
+                ))
+            }
+            Some(_) => Ok(Some(latest)),
+        }
+    }
+
+    /// Flags the wallet with a history mismatch, its sends are blocked until it's acknowledged
+    pub fn set_mismatch(&self, name: &str, reason: &str) -> Result<()> {
+        self.storage
+            .save(MISMATCH_KEYSPACE, name, &reason.to_owned())
+    }
+
+    /// Returns the reason of the history mismatch of the wallet, if it's flagged
+    pub fn mismatch(&self, name: &str) -> Result<Option<String>> {
+        self.storage.load(MISMATCH_KEYSPACE, name)
+    }
+
+    /// Fails with `ErrorKind::HistoryMismatch` if the wallet is flagged with a history mismatch
+    pub fn ensure_no_mismatch(&self, name: &str) -> Result<()> {
+        match self.mismatch(name)? {
+            Some(reason) => Err(Error::new(
+                ErrorKind::HistoryMismatch,
+                format!("{} (acknowledge it to use the wallet again)", reason),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the names of the wallets flagged with a history mismatch
+    pub fn mismatched_wallets(&self) -> Result<Vec<String>> {
+        self.storage
+            .keys(MISMATCH_KEYSPACE)?
+            .into_iter()
+            .map(|key| {
+                String::from_utf8(key).chain(|| {
+                    (
+                        ErrorKind::DeserializationError,
+                        "Unable to deserialize wallet name",
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Clears the history mismatch of the wallet and its records, a new sequence of records is
+    /// started from the current sync state
+    pub fn acknowledge(&self, name: &str) -> Result<()> {
+        let mut batch = StorageBatch::default();
+        batch.delete(MISMATCH_KEYSPACE, name);
+        batch.delete(KEYSPACE, name);
+        self.storage.write_batch(batch)
+    }
+
+    fn reauthenticate(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        new_name: &str,
+        new_enckey: &SecKey,
+    ) -> Result<Vec<ChainTipRecord>> {
+        let (key, new_key) = (mac_key(enckey), mac_key(new_enckey));
+        // the records which aren't authentic are kept as they are, so that they still fail the
+        // verification
+        Ok(self
+            .records(name)?
+            .into_iter()
+            .map(|record| {
+                if record.is_authentic(&key, name) {
+                    ChainTipRecord::new(
+                        &new_key,
+                        new_name,
+                        record.counter,
+                        record.block_height,
+                        &record.app_hash,
+                    )
+                } else {
+                    record
+                }
+            })
+            .collect())
+    }
+
+    /// Adds the write which authenticates the records of the wallet with a new enckey to the
+    /// batch
+    pub fn batch_change_enckey(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        let records = self.reauthenticate(name, enckey, name, new_enckey)?;
+        if !records.is_empty() {
+            batch.save(KEYSPACE, name, &records);
+        }
+        Ok(())
+    }
+
+    /// Adds the writes which move the records (and the history mismatch) of the wallet to a new
+    /// name (and enckey) to the batch
+    pub fn batch_rename(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_name: &str,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        let records = self.reauthenticate(name, enckey, new_name, new_enckey)?;
+        if !records.is_empty() {
+            batch.save(KEYSPACE, new_name, &records);
+        }
+        if let Some(reason) = self.mismatch(name)? {
+            batch.save(MISMATCH_KEYSPACE, new_name, &reason);
+        }
+        batch.delete(KEYSPACE, name);
+        batch.delete(MISMATCH_KEYSPACE, name);
+        Ok(())
+    }
+
+    /// Deletes the records of the wallet (e.g. when its sync state is reset), its history
+    /// mismatch is kept
+    #[inline]
+    pub fn delete_records(&self, name: &str) -> Result<()> {
+        self.storage.delete(KEYSPACE, name)?;
+        Ok(())
+    }
+
+    /// Deletes the records and the history mismatch of the wallet
+    pub fn delete(&self, name: &str) -> Result<()> {
+        self.storage.delete(KEYSPACE, name)?;
+        self.storage.delete(MISMATCH_KEYSPACE, name)?;
+        Ok(())
+    }
+
+    /// Adds the deletion of the records and the history mismatch of the wallet to the batch
+    pub fn batch_delete(&self, batch: &mut StorageBatch, name: &str) {
+        batch.delete(KEYSPACE, name);
+        batch.delete(MISMATCH_KEYSPACE, name);
+    }
+
+    /// Clears all storage
+    #[inline]
+    pub fn clear(&self) -> Result<()> {
+        self.storage.clear(KEYSPACE)?;
+        self.storage.clear(MISMATCH_KEYSPACE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client_common::seckey::derive_enckey;
+    use client_common::storage::MemoryStorage;
+    use secstr::SecUtf8;
+
+    fn append(service: &ChainTipService<MemoryStorage>, enckey: &SecKey, heights: &[u64]) {
+        for height in heights {
+            let mut batch = StorageBatch::default();
+            service
+                .batch_append(
+                    &mut batch,
+                    "name",
+                    enckey,
+                    *height,
+                    &format!("{:064X}", height),
+                    false,
+                )
+                .unwrap();
+            service.storage.write_batch(batch).unwrap();
+        }
+    }
+
+    fn verify_err(
+        service: &ChainTipService<MemoryStorage>,
+        enckey: &SecKey,
+        sync_height: Option<u64>,
+    ) -> ErrorKind {
+        service
+            .verify("name", enckey, sync_height)
+            .unwrap_err()
+            .kind()
+    }
+
+    #[test]
+    fn check_records_flow() {
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let service = ChainTipService::new(MemoryStorage::default());
+        assert_eq!(None, service.verify("name", &enckey, None).unwrap());
+
+        append(&service, &enckey, &[1, 50, 101, 150, 250]);
+        let records = service.records("name").unwrap();
+        assert_eq!(
+            vec![(0, 1), (1, 101), (2, 250)],
+            records
+                .iter()
+                .map(|record| (record.counter, record.block_height))
+                .collect::<Vec<_>>()
+        );
+        let latest = service.verify("name", &enckey, Some(300)).unwrap().unwrap();
+        assert_eq!(2, latest.counter);
+
+        // sync state not saved with the records
+        assert_eq!(
+            ErrorKind::HistoryMismatch,
+            verify_err(&service, &enckey, Some(350))
+        );
+        assert_eq!(
+            ErrorKind::HistoryMismatch,
+            verify_err(&service, &enckey, None)
+        );
+
+        // records are authenticated with the enckey
+        let other_enckey = derive_enckey(&SecUtf8::from("other passphrase"), "name").unwrap();
+        assert_eq!(
+            ErrorKind::HistoryMismatch,
+            verify_err(&service, &other_enckey, Some(300))
+        );
+        let mut tampered = records.clone();
+        tampered[1].block_height = 102;
+        service.storage.save(KEYSPACE, "name", &tampered).unwrap();
+        assert_eq!(
+            ErrorKind::HistoryMismatch,
+            verify_err(&service, &enckey, Some(300))
+        );
+
+        // gap in the counters
+        let mut gap = records;
+        gap.remove(1);
+        service.storage.save(KEYSPACE, "name", &gap).unwrap();
+        assert_eq!(
+            ErrorKind::HistoryMismatch,
+            verify_err(&service, &enckey, Some(300))
+        );
+    }
+
+    #[test]
+    fn check_restored_older_copy() {
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let service = ChainTipService::new(MemoryStorage::default());
+
+        append(&service, &enckey, &[1, 101]);
+        let backup = service.records("name").unwrap();
+        append(&service, &enckey, &[201, 301]);
+        service.verify("name", &enckey, Some(310)).unwrap();
+
+        // the records are restored from the backup, but not the sync state
+        service.storage.save(KEYSPACE, "name", &backup).unwrap();
+        assert_eq!(
+            ErrorKind::HistoryMismatch,
+            verify_err(&service, &enckey, Some(310))
+        );
+
+        // the sync state is restored from the backup, but not the records
+        append(&service, &enckey, &[201, 301]);
+        assert_eq!(
+            ErrorKind::HistoryMismatch,
+            verify_err(&service, &enckey, Some(110))
+        );
+    }
+
+    #[test]
+    fn check_mismatch_flow() {
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let new_enckey = derive_enckey(&SecUtf8::from("passphrase"), "new_name").unwrap();
+        let service = ChainTipService::new(MemoryStorage::default());
+        append(&service, &enckey, &[1]);
+
+        service.ensure_no_mismatch("name").unwrap();
+        service.set_mismatch("name", "reason").unwrap();
+        assert_eq!(
+            ErrorKind::HistoryMismatch,
+            service.ensure_no_mismatch("name").unwrap_err().kind()
+        );
+        assert_eq!(
+            vec!["name".to_owned()],
+            service.mismatched_wallets().unwrap()
+        );
+
+        let mut batch = StorageBatch::default();
+        service
+            .batch_rename(&mut batch, "name", &enckey, "new_name", &new_enckey)
+            .unwrap();
+        service.storage.write_batch(batch).unwrap();
+        assert_eq!(
+            vec!["new_name".to_owned()],
+            service.mismatched_wallets().unwrap()
+        );
+        assert!(service
+            .verify("new_name", &new_enckey, Some(1))
+            .unwrap()
+            .is_some());
+
+        service.acknowledge("new_name").unwrap();
+        service.ensure_no_mismatch("new_name").unwrap();
+        assert!(service.records("new_name").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,139 @@
+//! Fees of the outgoing transactions as estimated when they were built. They are kept after the
+//! transactions are confirmed, so they can be reconciled against the fees reported by the chain.
+use std::collections::{BTreeMap, BTreeSet};
+
+use chain_core::init::coin::Coin;
+use chain_core::tx::data::TxId;
+use client_common::{Result, SecKey, SecureStorage, Storage, StorageBatch};
+
+const KEYSPACE: &str = "core_fee_estimate";
+
+/// Maintains mapping `wallet-name -> (txid -> estimated fee)` (encrypted with the wallet's enckey)
+#[derive(Debug, Clone)]
+pub struct FeeEstimateService<S>
+where
+    S: Storage,
+{
+    storage: S,
+}
+
+impl<S> FeeEstimateService<S>
+where
+    S: Storage,
+{
+    /// Creates a new instance of fee estimate service
+    #[inline]
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// Records the estimated fee of a transaction
+    pub fn set(&self, name: &str, enckey: &SecKey, tx_id: TxId, fee: Coin) -> Result<()> {
+        let mut estimates = self.get_all(name, enckey)?;
+        estimates.insert(tx_id, fee);
+        self.storage.save_secure(KEYSPACE, name, enckey, &estimates)
+    }
+
+    /// Returns the estimated fees of all the recorded transactions of the wallet
+    pub fn get_all(&self, name: &str, enckey: &SecKey) -> Result<BTreeMap<TxId, Coin>> {
+        Ok(self
+            .storage
+            .load_secure(KEYSPACE, name, enckey)?
+            .unwrap_or_default())
+    }
+
+    /// Adds the write which re-encrypts the estimated fees of the wallet with a new enckey to the batch
+    pub fn batch_change_enckey(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        self.storage
+            .batch_reencrypt(batch, KEYSPACE, name, enckey, new_enckey)
+            .map(|_| ())
+    }
+
+    /// Adds the removal of the estimated fees of given transactions to the batch, returning the
+    /// removed ones
+    pub fn batch_remove(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        tx_ids: &BTreeSet<TxId>,
+    ) -> Result<BTreeMap<TxId, Coin>> {
+        let (removed, kept) = self
+            .get_all(name, enckey)?
+            .into_iter()
+            .partition::<BTreeMap<_, _>, _>(|(tx_id, _)| tx_ids.contains(tx_id));
+        if !removed.is_empty() {
+            batch.save_secure(KEYSPACE, name, enckey, &kept)?;
+        }
+        Ok(removed)
+    }
+
+    /// Adds the writes which move the estimated fees of the wallet to a new name (and enckey) to
+    /// the batch
+    pub fn batch_rename(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_name: &str,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        let estimates = self.get_all(name, enckey)?;
+        batch.save_secure(KEYSPACE, new_name, new_enckey, &estimates)?;
+        batch.delete(KEYSPACE, name);
+        Ok(())
+    }
+
+    /// Delete all the estimated fees of the wallet
+    #[inline]
+    pub fn delete(&self, name: &str) -> Result<()> {
+        self.storage.delete(KEYSPACE, name)?;
+        Ok(())
+    }
+
+    /// Adds the deletion of the fee estimates of the wallet to the batch
+    #[inline]
+    pub fn batch_delete(&self, batch: &mut StorageBatch, name: &str) {
+        batch.delete(KEYSPACE, name);
+    }
+
+    /// Clears all storage
+    #[inline]
+    pub fn clear(&self) -> Result<()> {
+        self.storage.clear(KEYSPACE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client_common::seckey::derive_enckey;
+    use client_common::storage::MemoryStorage;
+    use secstr::SecUtf8;
+
+    #[test]
+    fn check_flow() {
+        let storage = MemoryStorage::default();
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let service = FeeEstimateService::new(storage.clone());
+        assert!(service.get_all("name", &enckey).unwrap().is_empty());
+
+        service.set("name", &enckey, [1; 32], Coin::one()).unwrap();
+        service.set("name", &enckey, [2; 32], Coin::unit()).unwrap();
+
+        let estimates = FeeEstimateService::new(storage)
+            .get_all("name", &enckey)
+            .unwrap();
+        assert_eq!(2, estimates.len());
+        assert_eq!(Some(&Coin::one()), estimates.get(&[1; 32]));
+
+        service.delete("name").unwrap();
+        assert!(service.get_all("name", &enckey).unwrap().is_empty());
+    }
+}
@@ -4,6 +4,7 @@ use chain_core::init::network::get_network;
 use client_common::storage::decrypt_bytes;
 use client_common::{
     Error, ErrorKind, PrivateKey, PublicKey, Result, ResultExt, SecKey, SecureStorage, Storage,
+    StorageBatch,
 };
 
 use crate::types::AddressType;
@@ -14,6 +15,9 @@ use std::convert::From;
 
 const KEYSPACE: &str = "core_hd_key";
 
+/// Largest index of a non-hardened child key (in BIP32)
+const MAX_ADDRESS_INDEX: u32 = 0x7fff_ffff;
+
 /// HD key
 #[derive(Debug, Clone, PartialEq, Default, Encode, Decode)]
 pub struct HdKey {
@@ -99,6 +103,20 @@ where
         self.storage.contains_key(KEYSPACE, name)
     }
 
+    /// Adds the write which re-encrypts the HD key of the wallet (if any) with a new enckey to the
+    /// batch
+    pub fn batch_change_enckey(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        self.storage
+            .batch_reencrypt(batch, KEYSPACE, name, enckey, new_enckey)
+            .map(|_| ())
+    }
+
     /// Delete wallet
     pub fn delete_wallet(&self, name: &str, enckey: &SecKey) -> Result<()> {
         self.storage
@@ -110,6 +128,12 @@ where
         Ok(())
     }
 
+    /// Adds the deletion of the HD seed (and indexes) of the wallet to the batch
+    #[inline]
+    pub fn batch_delete(&self, batch: &mut StorageBatch, name: &str) {
+        batch.delete(KEYSPACE, name);
+    }
+
     /// Adds a new mnemonic in storage and sets its index to zero
     pub fn add_mnemonic(
         &self,
@@ -245,6 +269,18 @@ where
         enckey: &SecKey,
         account_type: HDAccountType,
     ) -> Result<(PublicKey, PrivateKey)> {
+        let (public_key, private_key, _) =
+            self.generate_keypair_with_path(name, enckey, account_type)?;
+        Ok((public_key, private_key))
+    }
+
+    /// Same as `generate_keypair`, also returns the chain path the keypair is derived with
+    pub fn generate_keypair_with_path(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        account_type: HDAccountType,
+    ) -> Result<(PublicKey, PrivateKey, ChainPath)> {
         let hd_key = self.update_hd_key(name, enckey, account_type)?;
         let index = match account_type {
             HDAccountType::Transfer => hd_key.transfer_index,
@@ -252,9 +288,94 @@ where
             HDAccountType::Viewkey => hd_key.viewkey_index,
         };
 
-        hd_key
-            .seed
-            .derive_key_pair(get_network(), account_type.index(), index)
+        let (public_key, private_key) =
+            hd_key
+                .seed
+                .derive_key_pair(get_network(), account_type.index(), index)?;
+        let chain_path = ChainPath::create_bip44(get_network(), account_type.index(), index);
+        Ok((public_key, private_key, chain_path))
+    }
+
+    /// Reserves the next `count` indexes of given account type (all of them or none): adds the
+    /// write of the updated HD key to the batch and returns it with the reserved indexes
+    fn batch_reserve_indexes(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        account_type: HDAccountType,
+        count: usize,
+    ) -> Result<(HdKey, Vec<u32>)> {
+        let mut hd_key = self.get_hdkey(name, enckey)?.chain(|| {
+            (
+                ErrorKind::InvalidInput,
+                format!("HD Key with name ({}) not found", name),
+            )
+        })?;
+        let last_index = match account_type {
+            HDAccountType::Staking => &mut hd_key.staking_index,
+            HDAccountType::Transfer => &mut hd_key.transfer_index,
+            HDAccountType::Viewkey => &mut hd_key.viewkey_index,
+        };
+        let mut indexes = Vec::with_capacity(count);
+        for _ in 0..count {
+            *last_index = last_index
+                .checked_add(1)
+                .filter(|index| *index <= MAX_ADDRESS_INDEX)
+                .chain(|| {
+                    (
+                        ErrorKind::InvalidInput,
+                        format!("HD Key with name ({}) has no more address indexes", name),
+                    )
+                })?;
+            indexes.push(*last_index);
+        }
+
+        batch.set_secure(KEYSPACE, name, &hd_key.encode(), enckey)?;
+        Ok((hd_key, indexes))
+    }
+
+    /// Same as `generate_keypair_with_path`, for `count` keypairs at once: the update of the
+    /// HdKey is added to the batch
+    pub fn batch_generate_keypairs_with_path(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        account_type: HDAccountType,
+        count: usize,
+    ) -> Result<Vec<(PublicKey, PrivateKey, ChainPath)>> {
+        let (hd_key, indexes) =
+            self.batch_reserve_indexes(batch, name, enckey, account_type, count)?;
+        indexes
+            .into_iter()
+            .map(|index| {
+                let (public_key, private_key) =
+                    hd_key
+                        .seed
+                        .derive_key_pair(get_network(), account_type.index(), index)?;
+                let chain_path =
+                    ChainPath::create_bip44(get_network(), account_type.index(), index);
+                Ok((public_key, private_key, chain_path))
+            })
+            .collect()
+    }
+
+    /// Same as `generate_chain_path`, for `count` chain paths at once: the update of the HdKey is
+    /// added to the batch
+    pub fn batch_generate_chain_paths(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        account_type: HDAccountType,
+        count: usize,
+    ) -> Result<Vec<ChainPath>> {
+        let (_, indexes) = self.batch_reserve_indexes(batch, name, enckey, account_type, count)?;
+        Ok(indexes
+            .into_iter()
+            .map(|index| ChainPath::create_bip44(get_network(), account_type.index(), index))
+            .collect())
     }
 
     /// Generate ChainPath for given wallet and address type
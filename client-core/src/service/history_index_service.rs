@@ -0,0 +1,678 @@
+//! Index of the transaction history of the wallets by block height: the transaction changes
+//! (except the ones which don't change the balance of the wallet) are stored (encrypted) in a
+//! keyspace of the wallet under the key `block height (big-endian) ++ transaction id`, so that the
+//! changes of a range of heights are read with a range scan of the storage.
+//!
+//! The index is derived from the wallet state: it's updated with the mementos applied to the
+//! state, and the existing wallets are (re)indexed once, when their index is first used. The
+//! version of the indexed schema of a wallet is recorded in a marker.
+use std::collections::BTreeSet;
+
+use parity_scale_codec::{Decode, Encode};
+
+use chain_core::tx::data::TxId;
+use client_common::storage::decrypt_bytes;
+use client_common::{
+    Error, ErrorKind, Result, ResultExt, SecKey, SecureStorage, Storage, StorageBatch,
+};
+
+use super::wallet_state_service::{load_wallet_state, WalletState, WalletStateMemento};
+use crate::types::{BalanceChange, HistoryFilter, HistoryPage, TransactionChange};
+
+/// key space of the markers of indexed wallets (`wallet-name -> schema version`)
+const KEYSPACE: &str = "core_history_index";
+
+/// Version of the schema of the history index (a wallet indexed with an older one is reindexed)
+pub const HISTORY_INDEX_VERSION: u32 = 1;
+
+fn get_index_keyspace(name: &str) -> String {
+    format!("{}_{}", KEYSPACE, name)
+}
+
+fn index_key(block_height: u64, transaction_id: &TxId) -> Vec<u8> {
+    let mut key = Vec::with_capacity(8 + transaction_id.len());
+    key.extend_from_slice(&block_height.to_be_bytes());
+    key.extend_from_slice(transaction_id);
+    key
+}
+
+/// only the changes listed in the history of the wallet are indexed
+fn is_indexed(change: &TransactionChange) -> bool {
+    BalanceChange::NoChange != change.balance_change
+}
+
+fn batch_index_transaction_change(
+    batch: &mut StorageBatch,
+    name: &str,
+    enckey: &SecKey,
+    change: &TransactionChange,
+) -> Result<()> {
+    batch.set_secure(
+        get_index_keyspace(name),
+        index_key(change.block_height, &change.transaction_id),
+        &change.encode(),
+        enckey,
+    )
+}
+
+/// Add the transaction changes of a memento to the history index of the wallet to storage batch
+/// (in the batch which saves the wallet state with the memento applied, so that the index stays in
+/// sync with the state)
+pub fn batch_index_memento(
+    batch: &mut StorageBatch,
+    name: &str,
+    enckey: &SecKey,
+    memento: &WalletStateMemento,
+) -> Result<()> {
+    for change in memento
+        .transaction_changes()
+        .filter(|change| is_indexed(change))
+    {
+        batch_index_transaction_change(batch, name, enckey, change)?;
+    }
+    Ok(())
+}
+
+/// Add the removal of the transaction changes above `block_height` from the history index of the
+/// wallet to storage batch
+pub fn batch_rollback_history_index<S: Storage>(
+    storage: &S,
+    batch: &mut StorageBatch,
+    name: &str,
+    block_height: u64,
+) -> Result<()> {
+    let start = match block_height.checked_add(1) {
+        Some(start) => start.to_be_bytes(),
+        None => return Ok(()),
+    };
+    let keyspace = get_index_keyspace(name);
+    for (key, _) in storage.range(&keyspace, &start, None)? {
+        batch.delete(&keyspace, key);
+    }
+    Ok(())
+}
+
+/// Delete the history index of the wallet (and its marker) from storage
+pub fn delete_history_index<S: Storage>(storage: &S, name: &str) -> Result<()> {
+    storage.delete(KEYSPACE, name)?;
+    storage.clear(get_index_keyspace(name))
+}
+
+/// Add deletion of the history index of the wallet (and its marker) to storage batch
+pub fn batch_delete_history_index<S: Storage>(
+    storage: &S,
+    batch: &mut StorageBatch,
+    name: &str,
+) -> Result<()> {
+    batch.delete(KEYSPACE, name);
+    storage.batch_clear(batch, get_index_keyspace(name))
+}
+
+/// Maintains the index `wallet-name -> (block height, txid) -> transaction change` (encrypted with
+/// the wallet's enckey)
+#[derive(Debug, Clone)]
+pub struct HistoryIndexService<S>
+where
+    S: Storage,
+{
+    storage: S,
+}
+
+impl<S> HistoryIndexService<S>
+where
+    S: Storage,
+{
+    /// Creates a new instance of history index service
+    #[inline]
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// Returns `true` if the wallet is indexed with the current schema version
+    pub fn is_indexed(&self, name: &str) -> Result<bool> {
+        let version: Option<u32> = self.storage.load(KEYSPACE, name)?;
+        Ok(version == Some(HISTORY_INDEX_VERSION))
+    }
+
+    /// Indexes the transaction history of the wallet (from its stored state) if it isn't indexed
+    /// with the current schema version yet
+    pub fn ensure_indexed(&self, name: &str, enckey: &SecKey) -> Result<()> {
+        if self.is_indexed(name)? {
+            return Ok(());
+        }
+        let state = load_wallet_state(&self.storage, name, enckey)?.unwrap_or_default();
+        self.reindex(name, enckey, &state)
+    }
+
+    /// Rebuilds the history index of the wallet from given wallet state.
+    ///
+    /// The existing entries are overwritten rather than cleared first, so that the index stays
+    /// readable while it's rebuilt.
+    pub fn reindex(&self, name: &str, enckey: &SecKey, state: &WalletState) -> Result<()> {
+        let keyspace = get_index_keyspace(name);
+        let mut batch = StorageBatch::default();
+        let mut keys = BTreeSet::new();
+        for change in state.transaction_history.values().filter(|c| is_indexed(c)) {
+            keys.insert(index_key(change.block_height, &change.transaction_id));
+            batch_index_transaction_change(&mut batch, name, enckey, change)?;
+        }
+        for key in self.storage.keys(&keyspace)? {
+            if !keys.contains(&key) {
+                batch.delete(&keyspace, key);
+            }
+        }
+        batch.save(KEYSPACE, name, &HISTORY_INDEX_VERSION);
+        self.storage.write_batch(batch)
+    }
+
+    /// Returns the (indexed) transaction changes of the wallet from `from_height` to `to_height`
+    /// (inclusive), ordered by block height (and transaction id)
+    pub fn history_range(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        from_height: u64,
+        to_height: u64,
+    ) -> Result<Vec<TransactionChange>> {
+        if from_height > to_height {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Start height of the history range is above its end height",
+            ));
+        }
+        self.ensure_indexed(name, enckey)?;
+
+        let end = to_height.checked_add(1).map(u64::to_be_bytes);
+        self.storage
+            .range(
+                get_index_keyspace(name),
+                &from_height.to_be_bytes(),
+                end.as_ref().map(|end| &end[..]),
+            )?
+            .into_iter()
+            .map(|(key, value)| decode_transaction_change(name, &key, &value, enckey))
+            .collect()
+    }
+
+    /// Returns a page of the (indexed) transaction changes of the wallet, ordered by block height
+    /// (and transaction id); only the entries of the page are read and decrypted
+    pub fn history_page(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        offset: usize,
+        limit: usize,
+        reversed: bool,
+    ) -> Result<Vec<TransactionChange>> {
+        self.ensure_indexed(name, enckey)?;
+
+        self.storage
+            .range_page(get_index_keyspace(name), &[], None, offset, limit, reversed)?
+            .into_iter()
+            .map(|(key, value)| decode_transaction_change(name, &key, &value, enckey))
+            .collect()
+    }
+
+    /// Returns a page of the (indexed) transaction changes of the wallet matching the filter,
+    /// newest first, with the number of all the matching changes. The height range of the filter
+    /// is a range scan of the index; without a direction or an amount restriction, the page is
+    /// read from the index (only its entries are read and decrypted), otherwise all the changes of
+    /// the range are decrypted to be matched.
+    pub fn history_filtered(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        offset: usize,
+        limit: usize,
+        filter: &HistoryFilter,
+    ) -> Result<HistoryPage> {
+        let decode = |(key, value): (Vec<u8>, Vec<u8>)| {
+            decode_transaction_change(name, &key, &value, enckey)
+        };
+
+        if filter.direction.is_none() && filter.min_amount.is_none() {
+            let (start, end) = self.filter_bounds(name, enckey, filter)?;
+            let end = end.as_ref().map(|end| &end[..]);
+            let keyspace = get_index_keyspace(name);
+            let total = self
+                .storage
+                .keys(&keyspace)?
+                .iter()
+                .filter(|key| {
+                    key.as_slice() >= &start[..] && end.map_or(true, |end| key.as_slice() < end)
+                })
+                .count();
+            let changes = self
+                .storage
+                .range_page(&keyspace, &start, end, offset, limit, true)?
+                .into_iter()
+                .map(decode)
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(HistoryPage { total, changes });
+        }
+
+        let entries = self.filtered_entries(name, enckey, filter)?;
+        let mut total = 0;
+        let mut changes = Vec::new();
+        for entry in entries.into_iter().rev() {
+            let change = decode(entry)?;
+            if !filter.matches(&change) {
+                continue;
+            }
+            if total >= offset && changes.len() < limit {
+                changes.push(change);
+            }
+            total += 1;
+        }
+        Ok(HistoryPage { total, changes })
+    }
+
+    /// Returns all the (indexed) transaction changes of the wallet matching the filter, ordered
+    /// by block height (and transaction id), e.g. for an export. The filter is applied while the
+    /// height range of the index is scanned.
+    pub fn history_matching(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        filter: &HistoryFilter,
+    ) -> Result<Vec<TransactionChange>> {
+        let mut changes = Vec::new();
+        for (key, value) in self.filtered_entries(name, enckey, filter)? {
+            let change = decode_transaction_change(name, &key, &value, enckey)?;
+            if filter.matches(&change) {
+                changes.push(change);
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Returns the (encrypted) index entries in the height range of the filter
+    fn filtered_entries(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        filter: &HistoryFilter,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let (start, end) = self.filter_bounds(name, enckey, filter)?;
+        self.storage.range(
+            get_index_keyspace(name),
+            &start,
+            end.as_ref().map(|end| &end[..]),
+        )
+    }
+
+    /// Returns the index keys bounding the height range of the filter (start inclusive, end
+    /// exclusive or unbounded), once the wallet is indexed
+    fn filter_bounds(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        filter: &HistoryFilter,
+    ) -> Result<([u8; 8], Option<[u8; 8]>)> {
+        let from_height = filter.from_height.unwrap_or(0);
+        let to_height = filter.to_height.unwrap_or_else(u64::max_value);
+        if from_height > to_height {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Start height of the history filter is above its end height",
+            ));
+        }
+        self.ensure_indexed(name, enckey)?;
+
+        Ok((
+            from_height.to_be_bytes(),
+            to_height.checked_add(1).map(u64::to_be_bytes),
+        ))
+    }
+
+    /// Adds the writes which re-encrypt the history index of the wallet with a new enckey to the
+    /// batch
+    pub fn batch_change_enckey(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        self.storage
+            .batch_reencrypt_keyspace(batch, get_index_keyspace(name), enckey, new_enckey)
+    }
+
+    /// Adds the removal of given transaction changes from the history index of the wallet to the
+    /// batch
+    pub fn batch_remove(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        changes: &[TransactionChange],
+    ) {
+        let keyspace = get_index_keyspace(name);
+        for change in changes {
+            batch.delete(
+                &keyspace,
+                index_key(change.block_height, &change.transaction_id),
+            );
+        }
+    }
+
+    /// Clears all storage
+    pub fn clear(&self) -> Result<()> {
+        let prefix = get_index_keyspace("");
+        for keyspace in self.storage.keyspaces()? {
+            if keyspace.starts_with(prefix.as_bytes()) {
+                self.storage.clear(keyspace)?;
+            }
+        }
+        self.storage.clear(KEYSPACE)
+    }
+}
+
+fn decode_transaction_change(
+    name: &str,
+    key: &[u8],
+    value: &[u8],
+    enckey: &SecKey,
+) -> Result<TransactionChange> {
+    let bytes = decrypt_bytes(key, enckey, value)?;
+    TransactionChange::decode(&mut bytes.as_slice()).chain(|| {
+        (
+            ErrorKind::DeserializationError,
+            format!(
+                "Unable to deserialize indexed transaction change of wallet {}",
+                name
+            ),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secstr::SecUtf8;
+
+    use chain_core::init::coin::Coin;
+    use chain_core::tx::fee::Fee;
+    use client_common::seckey::derive_enckey;
+    use client_common::storage::MemoryStorage;
+    use client_common::tendermint::types::Time;
+
+    use crate::service::{save_wallet_state, WalletStateService};
+    use crate::types::{HistoryDirection, TransactionType};
+
+    fn change(n: u8, block_height: u64, balance_change: BalanceChange) -> TransactionChange {
+        TransactionChange {
+            transaction_id: [n; 32],
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            fee_paid: Fee::new(Coin::zero()),
+            balance_change,
+            transaction_type: TransactionType::Transfer,
+            block_height,
+            block_time: Time::now(),
+            memo: None,
+        }
+    }
+
+    fn incoming() -> BalanceChange {
+        BalanceChange::Incoming { value: Coin::one() }
+    }
+
+    fn ids(changes: Vec<TransactionChange>) -> Vec<u8> {
+        changes
+            .into_iter()
+            .map(|change| change.transaction_id[0])
+            .collect()
+    }
+
+    #[test]
+    fn check_range_queries() {
+        let name = "name";
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), name).unwrap();
+        let storage = MemoryStorage::default();
+        let wallet_state_service = WalletStateService::new(storage.clone());
+        let service = HistoryIndexService::new(storage.clone());
+
+        let mut memento = WalletStateMemento::default();
+        memento.add_transaction_change(change(3, 300, incoming()));
+        memento.add_transaction_change(change(1, 1, incoming()));
+        memento.add_transaction_change(change(2, 256, incoming()));
+        memento.add_transaction_change(change(4, 256, BalanceChange::NoChange));
+        memento.add_transaction_change(change(5, u64::max_value(), incoming()));
+        wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+
+        let range = |from, to| ids(service.history_range(name, &enckey, from, to).unwrap());
+        assert_eq!(vec![1, 2, 3, 5], range(0, u64::max_value()));
+        assert_eq!(vec![2], range(2, 256));
+        assert_eq!(vec![2, 3], range(256, 300));
+        assert!(range(301, 1000).is_empty());
+        assert!(service.history_range(name, &enckey, 2, 1).is_err());
+
+        let page = |offset, limit, reversed| {
+            ids(service
+                .history_page(name, &enckey, offset, limit, reversed)
+                .unwrap())
+        };
+        assert_eq!(vec![2, 3], page(1, 2, false));
+        assert_eq!(vec![3, 2], page(1, 2, true));
+
+        // rolled back above block 256
+        let mut batch = StorageBatch::default();
+        batch_rollback_history_index(&storage, &mut batch, name, 256).unwrap();
+        storage.write_batch(batch).unwrap();
+        assert_eq!(vec![1, 2], range(0, u64::max_value()));
+
+        let wrong_enckey = derive_enckey(&SecUtf8::from("wrong"), name).unwrap();
+        assert!(service.history_range(name, &wrong_enckey, 0, 1).is_err());
+
+        delete_history_index(&storage, name).unwrap();
+        assert!(!service.is_indexed(name).unwrap());
+    }
+
+    #[test]
+    fn check_filtered_pages() {
+        let name = "name";
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), name).unwrap();
+        let storage = MemoryStorage::default();
+        let wallet_state_service = WalletStateService::new(storage.clone());
+        let service = HistoryIndexService::new(storage);
+
+        let outgoing = |value| BalanceChange::Outgoing {
+            value: Coin::new(value).unwrap(),
+        };
+        let mut memento = WalletStateMemento::default();
+        memento.add_transaction_change(change(1, 10, incoming()));
+        memento.add_transaction_change(change(2, 20, outgoing(500)));
+        memento.add_transaction_change(change(3, 20, incoming()));
+        memento.add_transaction_change(change(4, 30, outgoing(50)));
+        memento.add_transaction_change(change(5, 40, outgoing(1000)));
+        memento.add_transaction_change(change(6, 40, BalanceChange::NoChange));
+        wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+
+        let page = |offset, limit, filter: HistoryFilter| {
+            let page = service
+                .history_filtered(name, &enckey, offset, limit, &filter)
+                .unwrap();
+            (page.total, ids(page.changes))
+        };
+
+        // newest first
+        assert_eq!(
+            (5, vec![5, 4, 3, 2, 1]),
+            page(0, 10, HistoryFilter::default())
+        );
+        // boundary offsets
+        assert_eq!((5, vec![2, 1]), page(3, 2, HistoryFilter::default()));
+        assert_eq!((5, vec![1]), page(4, 2, HistoryFilter::default()));
+        assert_eq!((5, vec![]), page(5, 2, HistoryFilter::default()));
+        assert_eq!((5, vec![]), page(6, 2, HistoryFilter::default()));
+        assert_eq!((5, vec![]), page(0, 0, HistoryFilter::default()));
+
+        let heights = HistoryFilter {
+            from_height: Some(20),
+            to_height: Some(30),
+            ..Default::default()
+        };
+        assert_eq!((3, vec![4, 3, 2]), page(0, 10, heights.clone()));
+        assert_eq!((3, vec![3]), page(1, 1, heights.clone()));
+
+        // filters combined
+        let outgoing_from_100 = HistoryFilter {
+            direction: Some(HistoryDirection::Outgoing),
+            min_amount: Some(Coin::new(100).unwrap()),
+            ..Default::default()
+        };
+        assert_eq!((2, vec![5, 2]), page(0, 10, outgoing_from_100.clone()));
+        assert_eq!((2, vec![2]), page(1, 10, outgoing_from_100.clone()));
+        assert_eq!(
+            (1, vec![2]),
+            page(
+                0,
+                10,
+                HistoryFilter {
+                    to_height: Some(30),
+                    ..outgoing_from_100
+                }
+            )
+        );
+        assert_eq!(
+            (1, vec![3]),
+            page(
+                0,
+                10,
+                HistoryFilter {
+                    direction: Some(HistoryDirection::Incoming),
+                    ..heights
+                }
+            )
+        );
+
+        assert!(service
+            .history_filtered(
+                name,
+                &enckey,
+                0,
+                10,
+                &HistoryFilter {
+                    from_height: Some(2),
+                    to_height: Some(1),
+                    ..Default::default()
+                }
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn check_matching_history_is_ordered_by_height() {
+        let name = "name";
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), name).unwrap();
+        let storage = MemoryStorage::default();
+        let wallet_state_service = WalletStateService::new(storage.clone());
+        let service = HistoryIndexService::new(storage);
+
+        let outgoing = |value| BalanceChange::Outgoing {
+            value: Coin::new(value).unwrap(),
+        };
+        let mut memento = WalletStateMemento::default();
+        memento.add_transaction_change(change(5, 20_000, outgoing(1000)));
+        memento.add_transaction_change(change(1, 9_999, outgoing(500)));
+        memento.add_transaction_change(change(3, 10_000, incoming()));
+        memento.add_transaction_change(change(4, 15_000, outgoing(50)));
+        memento.add_transaction_change(change(2, 10_001, outgoing(200)));
+        memento.add_transaction_change(change(6, 20_000, BalanceChange::NoChange));
+        wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+
+        let matching =
+            |filter: HistoryFilter| ids(service.history_matching(name, &enckey, &filter).unwrap());
+
+        // oldest first
+        assert_eq!(vec![1, 3, 2, 4, 5], matching(HistoryFilter::default()));
+
+        let outgoing_above_10_000 = HistoryFilter {
+            direction: Some(HistoryDirection::Outgoing),
+            from_height: Some(10_001),
+            ..Default::default()
+        };
+        assert_eq!(vec![2, 4, 5], matching(outgoing_above_10_000.clone()));
+        assert_eq!(
+            vec![2, 5],
+            matching(HistoryFilter {
+                min_amount: Some(Coin::new(100).unwrap()),
+                ..outgoing_above_10_000.clone()
+            })
+        );
+        assert_eq!(
+            vec![2, 4],
+            matching(HistoryFilter {
+                to_height: Some(19_999),
+                ..outgoing_above_10_000
+            })
+        );
+        assert_eq!(
+            vec![3],
+            matching(HistoryFilter {
+                direction: Some(HistoryDirection::Incoming),
+                ..Default::default()
+            })
+        );
+
+        assert!(service
+            .history_matching(
+                name,
+                &enckey,
+                &HistoryFilter {
+                    from_height: Some(2),
+                    to_height: Some(1),
+                    ..Default::default()
+                }
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn check_existing_history_is_indexed_once() {
+        let name = "name";
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), name).unwrap();
+        let storage = MemoryStorage::default();
+        let service = HistoryIndexService::new(storage.clone());
+
+        // stored before the index existed
+        let mut state = WalletState::default();
+        state.add_transaction_change([1; 32], change(1, 10, incoming()));
+        state.add_transaction_change([2; 32], change(2, 20, incoming()));
+        save_wallet_state(&storage, name, &enckey, &state).unwrap();
+        assert!(!service.is_indexed(name).unwrap());
+
+        assert_eq!(
+            vec![2],
+            ids(service.history_range(name, &enckey, 11, 20).unwrap())
+        );
+        assert!(service.is_indexed(name).unwrap());
+
+        // not reindexed from the (unchanged) wallet state again
+        state.add_transaction_change([3; 32], change(3, 15, incoming()));
+        save_wallet_state(&storage, name, &enckey, &state).unwrap();
+        assert_eq!(
+            vec![2],
+            ids(service.history_range(name, &enckey, 11, 20).unwrap())
+        );
+
+        // an explicit reindex drops the stale entries
+        let mut state = WalletState::default();
+        state.add_transaction_change([3; 32], change(3, 15, incoming()));
+        service.reindex(name, &enckey, &state).unwrap();
+        assert_eq!(
+            vec![3],
+            ids(service.history_range(name, &enckey, 0, 20).unwrap())
+        );
+
+        service.clear().unwrap();
+        assert!(!service.is_indexed(name).unwrap());
+        assert!(storage.keys(get_index_keyspace(name)).unwrap().is_empty());
+    }
+}
@@ -1,14 +1,27 @@
+use parity_scale_codec::{Decode, Encode};
+use secstr::SecUtf8;
 use zeroize::Zeroize;
 
-use client_common::Result;
-use client_common::{PrivateKey, SecKey, SecureStorage, Storage};
+use client_common::seckey::{derive_enckey_with, KdfParams};
+use client_common::{Error, ErrorKind, Result, ResultExt};
+use client_common::{PrivateKey, SecKey, SecureStorage, Storage, StorageBatch};
+
+use crate::wallet::WalletLockMap;
 
 const KEYSPACE: &str = "core_key";
+/// Keyspace of the KDF parameters the records of the wallets were encrypted under (in plain, as
+/// they're needed to derive the enckey)
+const KDF_PARAMS_KEYSPACE: &str = "core_key_kdf_params";
 
 /// Maintains mapping `wallet-name -> private-key`
+///
+/// The re-encryptions of a wallet's private key are serialized per wallet (between the threads
+/// sharing the service or its clones). The callers of `batch_reencrypt_all` hold the exclusive
+/// guard of the wallet (on the same locks, see `with_wallet_locks`) until the batch is written.
 #[derive(Debug, Default, Clone)]
 pub struct KeyService<T: SecureStorage> {
     storage: T,
+    locks: WalletLockMap,
 }
 
 impl<T> KeyService<T>
@@ -18,7 +31,16 @@ where
     /// Creates a new instance of key service
     #[inline]
     pub fn new(storage: T) -> Self {
-        KeyService { storage }
+        KeyService {
+            storage,
+            locks: WalletLockMap::new(),
+        }
+    }
+
+    /// Shares the wallet locks with the wallet client (and the other services on the storage)
+    pub fn with_wallet_locks(mut self, locks: WalletLockMap) -> Self {
+        self.locks = locks;
+        self
     }
 
     /// Adds a new wallet_name-private keypair to storage
@@ -57,6 +79,109 @@ where
             .transpose()
     }
 
+    /// Adds the write which re-encrypts the private key of the wallet with a new enckey to the
+    /// batch
+    pub fn batch_change_enckey(
+        &self,
+        batch: &mut StorageBatch,
+        wallet_name: &str,
+        enckey: &SecKey,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        self.storage
+            .batch_reencrypt(batch, KEYSPACE, wallet_name.as_bytes(), enckey, new_enckey)
+            .map(|_| ())
+    }
+
+    /// Returns the KDF parameters the records of the wallet were encrypted under (the records
+    /// written before they were recorded are under `KdfParams::V1`)
+    pub fn kdf_params(&self, wallet_name: &str) -> Result<KdfParams> {
+        self.storage
+            .get(KDF_PARAMS_KEYSPACE, wallet_name.as_bytes())?
+            .map(|bytes| KdfParams::decode(&mut bytes.as_slice()))
+            .transpose()
+            .chain(|| {
+                (
+                    ErrorKind::DeserializationError,
+                    "Unable to decode KDF parameters",
+                )
+            })
+            .map(|params| params.unwrap_or(KdfParams::V1))
+    }
+
+    /// Records the KDF parameters the records of the wallet are encrypted under
+    pub fn set_kdf_params(&self, wallet_name: &str, params: &KdfParams) -> Result<()> {
+        self.storage
+            .set(KDF_PARAMS_KEYSPACE, wallet_name.as_bytes(), params.encode())
+            .map(|_| ())
+    }
+
+    /// Derives the enckey of the wallet with the KDF parameters its records were encrypted under
+    pub fn derive_enckey(&self, wallet_name: &str, passphrase: &SecUtf8) -> Result<SecKey> {
+        derive_enckey_with(passphrase, wallet_name, &self.kdf_params(wallet_name)?)
+            .err_kind(ErrorKind::InvalidInput, || {
+                "unable to derive encryption key from passphrase"
+            })
+    }
+
+    /// Adds the writes which re-encrypt the private key of the wallet under the enckey derived
+    /// with `new_params` (and record them) to the batch, after checking that the passphrase
+    /// decrypts it (if the wallet has one). Returns the enckeys under the recorded parameters and under the new ones,
+    /// so that the other records of the wallet can be re-encrypted in the same batch.
+    pub fn batch_reencrypt_all(
+        &self,
+        batch: &mut StorageBatch,
+        wallet_name: &str,
+        passphrase: &SecUtf8,
+        new_params: &KdfParams,
+    ) -> Result<(SecKey, SecKey)> {
+        let enckey = self.derive_enckey(wallet_name, passphrase)?;
+        let new_enckey = derive_enckey_with(passphrase, wallet_name, new_params)
+            .err_kind(ErrorKind::InvalidInput, || {
+                "unable to derive encryption key from passphrase"
+            })?;
+
+        self.storage.batch_reencrypt(
+            batch,
+            KEYSPACE,
+            wallet_name.as_bytes(),
+            &enckey,
+            &new_enckey,
+        )?;
+        batch.set(
+            KDF_PARAMS_KEYSPACE,
+            wallet_name.as_bytes(),
+            new_params.encode(),
+        );
+        Ok((enckey, new_enckey))
+    }
+
+    /// Re-encrypts the private key of the wallet under the enckey derived with `new_params`, which
+    /// are recorded in the same write (nothing is changed if the passphrase is incorrect), and
+    /// returns the new enckey
+    pub fn reencrypt_all(
+        &self,
+        wallet_name: &str,
+        passphrase: &SecUtf8,
+        new_params: &KdfParams,
+    ) -> Result<SecKey> {
+        let _guard = self.locks.exclusive(wallet_name);
+        if !self
+            .storage
+            .contains_key(KEYSPACE, wallet_name.as_bytes())?
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Private key of wallet ({}) not found", wallet_name),
+            ));
+        }
+        let mut batch = StorageBatch::default();
+        let (_, new_enckey) =
+            self.batch_reencrypt_all(&mut batch, wallet_name, passphrase, new_params)?;
+        self.storage.write_batch(batch)?;
+        Ok(new_enckey)
+    }
+
     /// Delete private key
     pub fn delete_wallet_private_key(&self, wallet_name: &str, enckey: &SecKey) -> Result<()> {
         self.storage.delete(KEYSPACE, wallet_name.as_bytes())?;
@@ -65,20 +190,27 @@ where
         Ok(())
     }
 
+    /// Adds the deletion of the private key of the wallet to the batch
+    #[inline]
+    pub fn batch_delete(&self, batch: &mut StorageBatch, wallet_name: &str) {
+        batch.delete(KEYSPACE, wallet_name.as_bytes());
+        batch.delete(KDF_PARAMS_KEYSPACE, wallet_name.as_bytes());
+    }
+
     /// Clears all storage
     #[inline]
     pub fn clear(&self) -> Result<()> {
-        self.storage.clear(KEYSPACE)
+        self.storage.clear(KEYSPACE)?;
+        self.storage.clear(KDF_PARAMS_KEYSPACE)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use secstr::SecUtf8;
 
+    use client_common::seckey::derive_enckey;
     use client_common::storage::MemoryStorage;
-    use client_common::{seckey::derive_enckey, ErrorKind};
 
     #[test]
     fn check_flow() {
@@ -112,4 +244,73 @@ mod tests {
 
         assert!(key_service.clear().is_ok());
     }
+
+    #[test]
+    fn check_reencrypt_all_upgrades_records() {
+        let key_service = KeyService::new(MemoryStorage::default());
+        let passphrase = SecUtf8::from("passphrase");
+        let name = "Default";
+        let v2 = KdfParams {
+            version: 2,
+            mem_cost: 8192,
+            time_cost: 2,
+            lanes: 1,
+        };
+
+        // written before the parameters were recorded
+        let v1_enckey = derive_enckey_with(&passphrase, name, &KdfParams::V1).unwrap();
+        let private_key = PrivateKey::new().unwrap();
+        key_service
+            .add_wallet_private_key(name, &private_key, &v1_enckey)
+            .unwrap();
+        assert_eq!(KdfParams::V1, key_service.kdf_params(name).unwrap());
+        assert_eq!(
+            v1_enckey,
+            key_service.derive_enckey(name, &passphrase).unwrap()
+        );
+
+        // the record is left intact if the passphrase is incorrect
+        let error = key_service
+            .reencrypt_all(name, &SecUtf8::from("passphrase1"), &v2)
+            .unwrap_err();
+        assert_eq!(ErrorKind::DecryptionError, error.kind());
+        assert_eq!(KdfParams::V1, key_service.kdf_params(name).unwrap());
+        assert_eq!(
+            private_key,
+            key_service
+                .wallet_private_key(name, &v1_enckey)
+                .unwrap()
+                .unwrap()
+        );
+
+        let v2_enckey = key_service.reencrypt_all(name, &passphrase, &v2).unwrap();
+        assert_eq!(
+            v2_enckey,
+            derive_enckey_with(&passphrase, name, &v2).unwrap()
+        );
+        assert_eq!(v2, key_service.kdf_params(name).unwrap());
+        assert_eq!(
+            v2_enckey,
+            key_service.derive_enckey(name, &passphrase).unwrap()
+        );
+        assert_eq!(
+            private_key,
+            key_service
+                .wallet_private_key(name, &v2_enckey)
+                .unwrap()
+                .unwrap()
+        );
+        assert_eq!(
+            ErrorKind::DecryptionError,
+            key_service
+                .wallet_private_key(name, &v1_enckey)
+                .unwrap_err()
+                .kind()
+        );
+
+        let mut batch = StorageBatch::default();
+        key_service.batch_delete(&mut batch, name);
+        key_service.storage.write_batch(batch).unwrap();
+        assert_eq!(KdfParams::V1, key_service.kdf_params(name).unwrap());
+    }
 }
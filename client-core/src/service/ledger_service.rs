@@ -24,6 +24,7 @@ const CRO_TX_AUX_ENUM_PUBLIC_TX: u8 = 1;
 const CRO_TX_AUX_PUBLIC_AUX_UNBOND_STAKE: u8 = 0;
 const CRO_TX_AUX_PUBLIC_AUX_UNJAIL: u8 = 1;
 const CRO_TX_AUX_PUBLIC_AUX_NODE_JOIN: u8 = 2;
+const CRO_TX_AUX_PUBLIC_AUX_UPDATE_NODE: u8 = 3;
 
 const CRO_TX_AUX_ENCLAVE_TRANSFER_TX: u8 = 0;
 const CRO_TX_AUX_ENCLAVE_DEPOSIT_STAKE: u8 = 1;
@@ -52,6 +53,12 @@ fn get_blob(tx: &Transaction) -> Vec<u8> {
             blob.append(&mut encoded);
             blob
         }
+        Transaction::UpdateNodeTransaction(tx) => {
+            let mut encoded = tx.encode();
+            let mut blob = vec![CRO_TX_AUX_ENUM_PUBLIC_TX, CRO_TX_AUX_PUBLIC_AUX_UPDATE_NODE];
+            blob.append(&mut encoded);
+            blob
+        }
         Transaction::TransferTransaction(tx) => {
             let mut encoded = tx.encode();
             let mut blob = vec![CRO_TX_AUX_ENUM_ENCLAVE_TX, CRO_TX_AUX_ENCLAVE_TRANSFER_TX];
@@ -1,15 +1,64 @@
+//! Multi-sig signing sessions of the wallets. A session holds the nonce of its signer until the
+//! final signature is produced, so the sessions which are abandoned (or complete) are expected to
+//! be expired.
+use std::mem;
+use std::time::Duration;
+
+use parity_scale_codec::{Decode, Encode};
 use secp256k1::schnorrsig::SchnorrSignature;
+use zeroize::{Zeroize, Zeroizing};
 
-use chain_core::common::H256;
+use chain_core::common::{Timespec, H256};
+use client_common::storage::decrypt_bytes;
 use client_common::{
-    ErrorKind, PrivateKey, PublicKey, Result, ResultExt, SecKey, SecureStorage, Storage,
+    Error, ErrorKind, PrivateKey, PublicKey, Result, ResultExt, SecKey, SecureStorage, Storage,
+    StorageBatch,
 };
 
-use crate::multi_sig::MultiSigBuilder;
+use crate::multi_sig::{
+    MultiSigBuilder, MultiSigSessionState, NonceCommitmentMsg, NonceMsg, PartialSignatureMsg,
+};
 
 const KEYSPACE: &str = "core_multi_sig_address";
 
-/// Maintains mapping `multi-sig session-id -> multi-sig session`
+/// Session as persisted in storage
+#[derive(Debug, Encode, Decode)]
+struct SessionRecord {
+    /// Time (unix timestamp) at which the session was created
+    created_at: Timespec,
+    /// Incomplete session (see `MultiSigBuilder::to_incomplete`)
+    session: Vec<u8>,
+}
+
+impl Drop for SessionRecord {
+    fn drop(&mut self) {
+        self.session.zeroize();
+    }
+}
+
+impl SessionRecord {
+    fn decode_bytes(mut bytes: &[u8]) -> Result<Self> {
+        Self::decode(&mut bytes).chain(|| {
+            (
+                ErrorKind::DeserializationError,
+                "Unable to deserialize multi-sig session record",
+            )
+        })
+    }
+}
+
+/// Summary of a multi-sig session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiSigSessionInfo {
+    /// Session id
+    pub session_id: H256,
+    /// Time (unix timestamp) at which the session was created
+    pub created_at: Timespec,
+    /// Progress of the session
+    pub state: MultiSigSessionState,
+}
+
+/// Maintains mapping `multi-sig session-id -> (creation time, multi-sig session)`
 #[derive(Debug, Default, Clone)]
 pub struct MultiSigSessionService<T: Storage> {
     storage: T,
@@ -33,6 +82,7 @@ where
     /// - `self_public_key`: Public key of current signer
     /// - `self_private_key`: Private key of current signer
     /// - `enckey`: Passphrase for encryption
+    /// - `now`: Current time (unix timestamp), recorded as the creation time of the session
     pub fn new_session(
         &self,
         message: H256,
@@ -40,6 +90,7 @@ where
         self_public_key: PublicKey,
         self_private_key: PrivateKey,
         enckey: &SecKey,
+        now: Timespec,
     ) -> Result<H256> {
         let session = MultiSigBuilder::new(
             message,
@@ -49,19 +100,33 @@ where
         )?;
 
         let session_id = session.id();
-        self.set_session(&session_id, session, enckey)?;
+        self.set_session(&session_id, &session, now, enckey)?;
 
         Ok(session_id)
     }
 
     /// Returns nonce commitment of self
     pub fn nonce_commitment(&self, session_id: &H256, enckey: &SecKey) -> Result<H256> {
-        let mut session = self.get_session(session_id, enckey)?;
+        self.export_nonce_commitment(session_id, enckey)
+            .map(|message| message.nonce_commitment)
+    }
+
+    /// Returns nonce commitment of self as a message to be passed to the other co-signers
+    pub fn export_nonce_commitment(
+        &self,
+        session_id: &H256,
+        enckey: &SecKey,
+    ) -> Result<NonceCommitmentMsg> {
+        let (mut session, created_at) = self.get_session(session_id, enckey)?;
         let nonce_commitment = session.nonce_commitment()?;
 
-        self.set_session(session_id, session, enckey)?;
+        self.set_session(session_id, &session, created_at, enckey)?;
 
-        Ok(nonce_commitment)
+        Ok(NonceCommitmentMsg {
+            session_id: *session_id,
+            public_key: session.public_key(),
+            nonce_commitment,
+        })
     }
 
     /// Adds a nonce commitment from a public key to session with given id
@@ -72,30 +137,47 @@ where
         public_key: &PublicKey,
         enckey: &SecKey,
     ) -> Result<()> {
-        self.storage
-            .fetch_and_update_secure(KEYSPACE, session_id, enckey, |value| {
-                let session_bytes = value.chain(|| {
-                    (
-                        ErrorKind::InvalidInput,
-                        format!("Session with ID ({}) not found", hex::encode(session_id)),
-                    )
-                })?;
-                let mut session =
-                    MultiSigBuilder::from_incomplete_insecure(session_bytes.to_vec())?;
-                session.add_nonce_commitment(public_key, nonce_commitment)?;
+        self.update_session(session_id, enckey, |session| {
+            session.add_nonce_commitment(public_key, nonce_commitment)
+        })
+    }
 
-                Ok(Some(session.to_incomplete()))
-            })
-            .map(|_| ())
+    /// Adds the nonce commitment of a co-signer (received in a message) to session with given id.
+    /// Fails with `ErrorKind::InvalidInput` if the sender isn't a co-signer of the session, and with
+    /// `ErrorKind::AlreadyExists` if its nonce commitment is already added.
+    pub fn import_nonce_commitment(
+        &self,
+        session_id: &H256,
+        message: &NonceCommitmentMsg,
+        enckey: &SecKey,
+    ) -> Result<()> {
+        self.update_session(session_id, enckey, |session| {
+            check_sender(session, &message.public_key)?;
+            if session.has_nonce_commitment(&message.public_key)? {
+                return Err(already_received(&message.public_key, "nonce commitment"));
+            }
+            session.add_nonce_commitment(&message.public_key, message.nonce_commitment)
+        })
     }
 
     /// Returns nonce of self. This function will fail if nonce commitments from all co-signers are not received.
     pub fn nonce(&self, session_id: &H256, enckey: &SecKey) -> Result<H256> {
-        let mut session = self.get_session(session_id, enckey)?;
+        self.export_nonce(session_id, enckey)
+            .map(|message| message.nonce)
+    }
+
+    /// Returns nonce of self as a message to be passed to the other co-signers. This function will
+    /// fail if nonce commitments from all co-signers are not received.
+    pub fn export_nonce(&self, session_id: &H256, enckey: &SecKey) -> Result<NonceMsg> {
+        let (mut session, created_at) = self.get_session(session_id, enckey)?;
         let nonce = session.nonce()?;
 
-        self.set_session(session_id, session, enckey)?;
-        Ok(nonce)
+        self.set_session(session_id, &session, created_at, enckey)?;
+        Ok(NonceMsg {
+            session_id: *session_id,
+            public_key: session.public_key(),
+            nonce,
+        })
     }
 
     /// Adds a nonce from a public key to session with given id
@@ -106,30 +188,51 @@ where
         public_key: &PublicKey,
         enckey: &SecKey,
     ) -> Result<()> {
-        self.storage
-            .fetch_and_update_secure(KEYSPACE, session_id, enckey, |value| {
-                let session_bytes = value.chain(|| {
-                    (
-                        ErrorKind::InvalidInput,
-                        format!("Session with ID ({}) not found", hex::encode(session_id)),
-                    )
-                })?;
-                let mut session =
-                    MultiSigBuilder::from_incomplete_insecure(session_bytes.to_vec())?;
-                session.add_nonce(public_key, nonce)?;
+        self.update_session(session_id, enckey, |session| {
+            session.add_nonce(public_key, nonce)
+        })
+    }
 
-                Ok(Some(session.to_incomplete()))
-            })
-            .map(|_| ())
+    /// Adds the nonce of a co-signer (received in a message) to session with given id. Fails with
+    /// `ErrorKind::InvalidInput` if the sender isn't a co-signer of the session, and with
+    /// `ErrorKind::AlreadyExists` if its nonce is already added.
+    pub fn import_nonce(
+        &self,
+        session_id: &H256,
+        message: &NonceMsg,
+        enckey: &SecKey,
+    ) -> Result<()> {
+        self.update_session(session_id, enckey, |session| {
+            check_sender(session, &message.public_key)?;
+            if session.has_nonce(&message.public_key)? {
+                return Err(already_received(&message.public_key, "nonce"));
+            }
+            session.add_nonce(&message.public_key, &message.nonce)
+        })
     }
 
     /// Returns partial signature of self. This function will fail if nonces from all co-signers are not received.
     pub fn partial_signature(&self, session_id: &H256, enckey: &SecKey) -> Result<H256> {
-        let mut session = self.get_session(session_id, enckey)?;
+        self.export_partial_signature(session_id, enckey)
+            .map(|message| message.partial_signature)
+    }
+
+    /// Returns partial signature of self as a message to be passed to the other co-signers. This
+    /// function will fail if nonces from all co-signers are not received.
+    pub fn export_partial_signature(
+        &self,
+        session_id: &H256,
+        enckey: &SecKey,
+    ) -> Result<PartialSignatureMsg> {
+        let (mut session, created_at) = self.get_session(session_id, enckey)?;
         let partial_signature = session.partial_signature()?;
 
-        self.set_session(session_id, session, enckey)?;
-        Ok(partial_signature)
+        self.set_session(session_id, &session, created_at, enckey)?;
+        Ok(PartialSignatureMsg {
+            session_id: *session_id,
+            public_key: session.public_key(),
+            partial_signature,
+        })
     }
 
     /// Adds a partial signature from a public key to session with given id
@@ -140,62 +243,205 @@ where
         public_key: &PublicKey,
         enckey: &SecKey,
     ) -> Result<()> {
-        self.storage
-            .fetch_and_update_secure(KEYSPACE, session_id, enckey, |value| {
-                let session_bytes = value.chain(|| {
-                    (
-                        ErrorKind::InvalidInput,
-                        format!("Session with ID ({}) not found", hex::encode(session_id)),
-                    )
-                })?;
-                let mut session =
-                    MultiSigBuilder::from_incomplete_insecure(session_bytes.to_vec())?;
-                session.add_partial_signature(public_key, partial_signature)?;
+        self.update_session(session_id, enckey, |session| {
+            session.add_partial_signature(public_key, partial_signature)
+        })
+    }
 
-                Ok(Some(session.to_incomplete()))
-            })
-            .map(|_| ())
+    /// Adds the partial signature of a co-signer (received in a message) to session with given id.
+    /// Fails with `ErrorKind::InvalidInput` if the sender isn't a co-signer of the session, and with
+    /// `ErrorKind::AlreadyExists` if its partial signature is already added.
+    pub fn import_partial_signature(
+        &self,
+        session_id: &H256,
+        message: &PartialSignatureMsg,
+        enckey: &SecKey,
+    ) -> Result<()> {
+        self.update_session(session_id, enckey, |session| {
+            check_sender(session, &message.public_key)?;
+            if session.has_partial_signature(&message.public_key)? {
+                return Err(already_received(&message.public_key, "partial signature"));
+            }
+            session.add_partial_signature(&message.public_key, message.partial_signature)
+        })
     }
 
     /// Returns final signature. This function will fail if partial signatures from all co-signers are not received.
     pub fn signature(&self, session_id: &H256, enckey: &SecKey) -> Result<SchnorrSignature> {
-        let session = self.get_session(session_id, enckey)?;
+        let (session, _) = self.get_session(session_id, enckey)?;
         session.signature()
     }
 
     /// Returns public keys of all signers in this session
     pub fn public_keys(&self, session_id: &H256, enckey: &SecKey) -> Result<Vec<PublicKey>> {
-        let session = self.get_session(session_id, enckey)?;
+        let (session, _) = self.get_session(session_id, enckey)?;
         Ok(session.public_keys())
     }
 
-    /// Retrieves a session from storage
-    fn get_session(&self, session_id: &H256, enckey: &SecKey) -> Result<MultiSigBuilder> {
-        let session_bytes = self
-            .storage
-            .get_secure(KEYSPACE, session_id, enckey)?
-            .chain(|| {
-                (
-                    ErrorKind::InvalidInput,
-                    format!("Session with ID ({}) not found", hex::encode(session_id)),
-                )
-            })?;
-        MultiSigBuilder::from_incomplete_insecure(session_bytes)
+    /// Returns the sessions created with given enckey (i.e. the ones of its wallet), oldest first
+    pub fn list_sessions(&self, enckey: &SecKey) -> Result<Vec<MultiSigSessionInfo>> {
+        let mut sessions = Vec::new();
+        for (session_id, value) in self.storage.iter_prefix(KEYSPACE, &[])? {
+            if let Ok(bytes) = decrypt_bytes(&session_id, enckey, &value) {
+                let bytes = Zeroizing::new(bytes);
+                let mut record = SessionRecord::decode_bytes(&bytes)?;
+                let session =
+                    MultiSigBuilder::from_incomplete_insecure(mem::take(&mut record.session))?;
+
+                sessions.push(MultiSigSessionInfo {
+                    session_id: session.id(),
+                    created_at: record.created_at,
+                    state: session.state(),
+                });
+            }
+        }
+        sessions.sort_by_key(|session| session.created_at);
+        Ok(sessions)
+    }
+
+    /// Deletes the sessions created with given enckey which are at least `older_than` old at
+    /// `now`, and returns the number of deleted sessions. Using a deleted session fails with
+    /// `ErrorKind::SessionNotFound`.
+    pub fn expire_sessions(
+        &self,
+        enckey: &SecKey,
+        older_than: Duration,
+        now: Timespec,
+    ) -> Result<usize> {
+        let mut batch = StorageBatch::default();
+        for (session_id, value) in self.storage.iter_prefix(KEYSPACE, &[])? {
+            if let Ok(bytes) = decrypt_bytes(&session_id, enckey, &value) {
+                let bytes = Zeroizing::new(bytes);
+                let record = SessionRecord::decode_bytes(&bytes)?;
+
+                if now.saturating_sub(record.created_at) >= older_than.as_secs() {
+                    batch.delete(KEYSPACE, &session_id);
+                }
+            }
+        }
+
+        let expired = batch.len();
+        self.storage.write_batch(batch)?;
+        Ok(expired)
+    }
+
+    /// Adds the deletion of the sessions created with given enckey (i.e. the ones of its wallet)
+    /// to the batch
+    pub fn batch_delete_sessions(&self, batch: &mut StorageBatch, enckey: &SecKey) -> Result<()> {
+        for (session_id, value) in self.storage.iter_prefix(KEYSPACE, &[])? {
+            if decrypt_bytes(&session_id, enckey, &value).is_ok() {
+                batch.delete(KEYSPACE, &session_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the writes which re-encrypt the sessions created with given enckey (i.e. the ones of
+    /// its wallet) with a new enckey to the batch
+    pub fn batch_change_enckey(
+        &self,
+        batch: &mut StorageBatch,
+        enckey: &SecKey,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        for (session_id, value) in self.storage.iter_prefix(KEYSPACE, &[])? {
+            // the sessions are not keyed by wallet, the ones of the other wallets are encrypted
+            // with other enckeys
+            if let Ok(session) = decrypt_bytes(&session_id, enckey, &value) {
+                let session = Zeroizing::new(session);
+                batch.set_secure(KEYSPACE, &session_id, &session, new_enckey)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Retrieves a session (and its creation time) from storage
+    fn get_session(
+        &self,
+        session_id: &H256,
+        enckey: &SecKey,
+    ) -> Result<(MultiSigBuilder, Timespec)> {
+        let bytes = Zeroizing::new(
+            self.storage
+                .get_secure(KEYSPACE, session_id, enckey)?
+                .ok_or_else(|| session_not_found(session_id))?,
+        );
+        let mut record = SessionRecord::decode_bytes(&bytes)?;
+        let session = MultiSigBuilder::from_incomplete_insecure(mem::take(&mut record.session))?;
+        Ok((session, record.created_at))
     }
 
     /// Persists a session in storage
     fn set_session(
         &self,
         session_id: &H256,
-        session: MultiSigBuilder,
+        session: &MultiSigBuilder,
+        created_at: Timespec,
         enckey: &SecKey,
     ) -> Result<()> {
+        let record = SessionRecord {
+            created_at,
+            session: session.to_incomplete(),
+        };
+        // the previous record holds the private key as well
+        let _previous = self
+            .storage
+            .set_secure(KEYSPACE, session_id, record.encode(), enckey)?
+            .map(Zeroizing::new);
+        Ok(())
+    }
+
+    /// Applies `f` to a session in storage, atomically
+    fn update_session<F>(&self, session_id: &H256, enckey: &SecKey, f: F) -> Result<()>
+    where
+        F: Fn(&mut MultiSigBuilder) -> Result<()>,
+    {
         self.storage
-            .set_secure(KEYSPACE, session_id, session.to_incomplete(), enckey)
+            .fetch_and_update_secure(KEYSPACE, session_id, enckey, |value| {
+                let bytes = value.ok_or_else(|| session_not_found(session_id))?;
+                let mut record = SessionRecord::decode_bytes(bytes)?;
+                let mut session =
+                    MultiSigBuilder::from_incomplete_insecure(mem::take(&mut record.session))?;
+                f(&mut session)?;
+
+                record.session = session.to_incomplete();
+                Ok(Some(record.encode()))
+            })
             .map(|_| ())
     }
 }
 
+/// Checks that the sender of a message is one of the other co-signers of the session
+fn check_sender(session: &MultiSigBuilder, sender: &PublicKey) -> Result<()> {
+    if *sender == session.public_key() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Cannot import a message of the current signer",
+        ));
+    }
+    if !session.public_keys().contains(sender) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Sender ({}) is not a co-signer of the session", sender),
+        ));
+    }
+    Ok(())
+}
+
+fn already_received(sender: &PublicKey, payload: &str) -> Error {
+    Error::new(
+        ErrorKind::AlreadyExists,
+        format!("The {} of {} is already received", payload, sender),
+    )
+}
+
+fn session_not_found(session_id: &H256) -> Error {
+    Error::new(
+        ErrorKind::SessionNotFound,
+        format!("Session with ID ({}) not found", hex::encode(session_id)),
+    )
+}
+
 #[cfg(test)]
 mod multi_sig_session_service_tests {
     use super::*;
@@ -226,6 +472,7 @@ mod multi_sig_session_service_tests {
                 public_key_1.clone(),
                 private_key_1.clone(),
                 &enckey,
+                1000,
             )
             .unwrap();
         let session_id_2 = multi_sig_service
@@ -235,6 +482,7 @@ mod multi_sig_session_service_tests {
                 public_key_2.clone(),
                 private_key_2.clone(),
                 &enckey,
+                1000,
             )
             .unwrap();
 
@@ -317,4 +565,232 @@ mod multi_sig_session_service_tests {
         )
         .expect("Invalid signature");
     }
+
+    #[test]
+    fn check_change_enckey() {
+        let storage = MemoryStorage::default();
+        let multi_sig_service = MultiSigSessionService::new(storage.clone());
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "").unwrap();
+        let new_enckey = derive_enckey(&SecUtf8::from("new passphrase"), "").unwrap();
+        let other_enckey = derive_enckey(&SecUtf8::from("passphrase"), "other").unwrap();
+
+        let private_key = PrivateKey::new().unwrap();
+        let public_key = PublicKey::from(&private_key);
+        let new_session = |message, enckey| {
+            multi_sig_service
+                .new_session(
+                    message,
+                    vec![public_key.clone()],
+                    public_key.clone(),
+                    private_key.clone(),
+                    enckey,
+                    1000,
+                )
+                .unwrap()
+        };
+        let session_id = new_session([1u8; 32], &enckey);
+        let other_session_id = new_session([2u8; 32], &other_enckey);
+
+        let mut batch = StorageBatch::default();
+        multi_sig_service
+            .batch_change_enckey(&mut batch, &enckey, &new_enckey)
+            .unwrap();
+        assert_eq!(1, batch.len());
+        storage.write_batch(batch).unwrap();
+
+        assert!(multi_sig_service.public_keys(&session_id, &enckey).is_err());
+        assert_eq!(
+            vec![public_key.clone()],
+            multi_sig_service
+                .public_keys(&session_id, &new_enckey)
+                .unwrap()
+        );
+        assert_eq!(
+            vec![public_key],
+            multi_sig_service
+                .public_keys(&other_session_id, &other_enckey)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn check_list_and_expire_sessions() {
+        let multi_sig_service = MultiSigSessionService::new(MemoryStorage::default());
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "").unwrap();
+        let other_enckey = derive_enckey(&SecUtf8::from("passphrase"), "other").unwrap();
+
+        let private_key = PrivateKey::new().unwrap();
+        let public_key = PublicKey::from(&private_key);
+        let new_session = |message, enckey, now| {
+            multi_sig_service
+                .new_session(
+                    message,
+                    vec![public_key.clone()],
+                    public_key.clone(),
+                    private_key.clone(),
+                    enckey,
+                    now,
+                )
+                .unwrap()
+        };
+        let old_session_id = new_session([2u8; 32], &enckey, 1000);
+        let session_id = new_session([1u8; 32], &enckey, 1100);
+        let other_session_id = new_session([3u8; 32], &other_enckey, 1000);
+
+        assert_eq!(
+            vec![
+                MultiSigSessionInfo {
+                    session_id: old_session_id,
+                    created_at: 1000,
+                    state: MultiSigSessionState::AwaitingNonceCommitments,
+                },
+                MultiSigSessionInfo {
+                    session_id,
+                    created_at: 1100,
+                    state: MultiSigSessionState::AwaitingNonceCommitments,
+                },
+            ],
+            multi_sig_service.list_sessions(&enckey).unwrap()
+        );
+
+        let older_than = Duration::from_secs(100);
+        assert_eq!(
+            0,
+            multi_sig_service
+                .expire_sessions(&enckey, older_than, 1099)
+                .unwrap()
+        );
+        assert_eq!(
+            1,
+            multi_sig_service
+                .expire_sessions(&enckey, older_than, 1100)
+                .unwrap()
+        );
+
+        assert_eq!(
+            ErrorKind::SessionNotFound,
+            multi_sig_service
+                .nonce_commitment(&old_session_id, &enckey)
+                .unwrap_err()
+                .kind()
+        );
+        assert_eq!(
+            ErrorKind::SessionNotFound,
+            multi_sig_service
+                .add_nonce_commitment(&old_session_id, [0u8; 32], &public_key, &enckey)
+                .unwrap_err()
+                .kind()
+        );
+        assert!(multi_sig_service
+            .nonce_commitment(&session_id, &enckey)
+            .is_ok());
+
+        // the sessions of other wallets are untouched
+        assert_eq!(
+            1,
+            multi_sig_service
+                .list_sessions(&other_enckey)
+                .unwrap()
+                .len()
+        );
+        assert!(multi_sig_service
+            .public_keys(&other_session_id, &other_enckey)
+            .is_ok());
+    }
+
+    #[test]
+    fn check_complete_session_pruned_immediately() {
+        let multi_sig_service = MultiSigSessionService::new(MemoryStorage::default());
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "").unwrap();
+
+        let private_key_1 = PrivateKey::new().unwrap();
+        let private_key_2 = PrivateKey::new().unwrap();
+        let public_key_1 = PublicKey::from(&private_key_1);
+        let public_key_2 = PublicKey::from(&private_key_2);
+
+        let session_ids = [
+            (public_key_1.clone(), private_key_1),
+            (public_key_2.clone(), private_key_2),
+        ]
+        .iter()
+        .map(|(public_key, private_key)| {
+            multi_sig_service
+                .new_session(
+                    [1u8; 32],
+                    vec![public_key_1.clone(), public_key_2.clone()],
+                    public_key.clone(),
+                    private_key.clone(),
+                    &enckey,
+                    1000,
+                )
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+        let (session_id_1, session_id_2) = (&session_ids[0], &session_ids[1]);
+        let states = || {
+            multi_sig_service
+                .list_sessions(&enckey)
+                .unwrap()
+                .into_iter()
+                .map(|session| session.state)
+                .collect::<Vec<_>>()
+        };
+
+        let nonce_commitment_1 = multi_sig_service
+            .nonce_commitment(session_id_1, &enckey)
+            .unwrap();
+        let nonce_commitment_2 = multi_sig_service
+            .nonce_commitment(session_id_2, &enckey)
+            .unwrap();
+        multi_sig_service
+            .add_nonce_commitment(session_id_1, nonce_commitment_2, &public_key_2, &enckey)
+            .unwrap();
+        multi_sig_service
+            .add_nonce_commitment(session_id_2, nonce_commitment_1, &public_key_1, &enckey)
+            .unwrap();
+        assert_eq!(vec![MultiSigSessionState::AwaitingNonces; 2], states());
+
+        let nonce_1 = multi_sig_service.nonce(session_id_1, &enckey).unwrap();
+        let nonce_2 = multi_sig_service.nonce(session_id_2, &enckey).unwrap();
+        multi_sig_service
+            .add_nonce(session_id_1, &nonce_2, &public_key_2, &enckey)
+            .unwrap();
+        multi_sig_service
+            .add_nonce(session_id_2, &nonce_1, &public_key_1, &enckey)
+            .unwrap();
+        assert_eq!(
+            vec![MultiSigSessionState::AwaitingPartialSignatures; 2],
+            states()
+        );
+
+        let partial_signature_1 = multi_sig_service
+            .partial_signature(session_id_1, &enckey)
+            .unwrap();
+        let partial_signature_2 = multi_sig_service
+            .partial_signature(session_id_2, &enckey)
+            .unwrap();
+        multi_sig_service
+            .add_partial_signature(session_id_1, partial_signature_2, &public_key_2, &enckey)
+            .unwrap();
+        multi_sig_service
+            .add_partial_signature(session_id_2, partial_signature_1, &public_key_1, &enckey)
+            .unwrap();
+        assert_eq!(vec![MultiSigSessionState::Complete; 2], states());
+        assert!(multi_sig_service.signature(session_id_1, &enckey).is_ok());
+
+        assert_eq!(
+            2,
+            multi_sig_service
+                .expire_sessions(&enckey, Duration::from_secs(0), 1000)
+                .unwrap()
+        );
+        assert!(multi_sig_service.list_sessions(&enckey).unwrap().is_empty());
+        assert_eq!(
+            ErrorKind::SessionNotFound,
+            multi_sig_service
+                .signature(session_id_1, &enckey)
+                .unwrap_err()
+                .kind()
+        );
+    }
 }
@@ -0,0 +1,267 @@
+//! Unspent outputs reserved by transactions being built, so concurrent builds of the same wallet
+//! never select the same inputs. A lock expires after its timeout, so an output is never reserved
+//! forever by a transaction which is neither broadcast nor explicitly unlocked.
+use std::collections::{BTreeMap, BTreeSet};
+
+use parity_scale_codec::{Decode, Encode};
+
+use chain_core::common::Timespec;
+use chain_core::tx::data::input::TxoPointer;
+use client_common::{
+    Error, ErrorKind, Result, ResultExt, SecKey, SecureStorage, Storage, StorageBatch,
+};
+
+use crate::service::WalletStateMemento;
+
+const KEYSPACE: &str = "core_output_lock";
+
+/// Time (in seconds) after which an output locked by a transaction build is released
+pub const DEFAULT_OUTPUT_LOCK_TIMEOUT: Timespec = 600;
+
+/// Maintains mapping `wallet-name -> (locked output -> expiry time)` (encrypted with the wallet's
+/// enckey)
+#[derive(Debug, Clone)]
+pub struct OutputLockService<S>
+where
+    S: Storage,
+{
+    storage: S,
+}
+
+impl<S> OutputLockService<S>
+where
+    S: Storage,
+{
+    /// Creates a new instance of output lock service
+    #[inline]
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+
+    fn load(&self, name: &str, enckey: &SecKey) -> Result<BTreeMap<TxoPointer, Timespec>> {
+        Ok(self
+            .storage
+            .load_secure(KEYSPACE, name, enckey)?
+            .unwrap_or_default())
+    }
+
+    /// Applies `f` to the locks of the wallet, atomically
+    fn update<F>(&self, name: &str, enckey: &SecKey, f: F) -> Result<()>
+    where
+        F: Fn(&mut BTreeMap<TxoPointer, Timespec>) -> Result<()>,
+    {
+        self.storage
+            .fetch_and_update_secure(KEYSPACE, name, enckey, |bytes| {
+                let mut locks = match bytes {
+                    Some(mut bytes) => {
+                        BTreeMap::<TxoPointer, Timespec>::decode(&mut bytes).chain(|| {
+                            (
+                                ErrorKind::DeserializationError,
+                                "Unable to deserialize output locks from bytes",
+                            )
+                        })?
+                    }
+                    None => BTreeMap::new(),
+                };
+                f(&mut locks)?;
+
+                if locks.is_empty() {
+                    Ok(None)
+                } else {
+                    Ok(Some(locks.encode()))
+                }
+            })
+            .map(|_| ())
+    }
+
+    /// Returns the outputs of the wallet which are locked at `now`
+    pub fn get_locked(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        now: Timespec,
+    ) -> Result<BTreeSet<TxoPointer>> {
+        Ok(self
+            .load(name, enckey)?
+            .into_iter()
+            .filter(|(_, expiry)| *expiry > now)
+            .map(|(output, _)| output)
+            .collect())
+    }
+
+    /// Locks all the given outputs until `now + timeout`, or none of them: fails with
+    /// `ErrorKind::AlreadyExists` if one of them is already locked (the expired locks are
+    /// dropped)
+    pub fn lock(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        outputs: &[TxoPointer],
+        now: Timespec,
+        timeout: Timespec,
+    ) -> Result<()> {
+        let expires_at = now.saturating_add(timeout);
+        self.update(name, enckey, |locks| {
+            locks.retain(|_, expiry| *expiry > now);
+            if let Some(locked) = outputs.iter().find(|output| locks.contains_key(*output)) {
+                return Err(Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!(
+                        "Output {}:{} is already locked",
+                        hex::encode(locked.id),
+                        locked.index
+                    ),
+                ));
+            }
+            for output in outputs {
+                locks.insert(output.clone(), expires_at);
+            }
+            Ok(())
+        })
+    }
+
+    /// Releases given outputs (the ones which are not locked are ignored)
+    pub fn unlock(&self, name: &str, enckey: &SecKey, outputs: &[TxoPointer]) -> Result<()> {
+        self.update(name, enckey, |locks| {
+            for output in outputs {
+                locks.remove(output);
+            }
+            Ok(())
+        })
+    }
+
+    /// Adds the write which re-encrypts the output locks of the wallet with a new enckey to the batch
+    pub fn batch_change_enckey(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        self.storage
+            .batch_reencrypt(batch, KEYSPACE, name, enckey, new_enckey)
+            .map(|_| ())
+    }
+
+    /// Adds the writes which move the output locks of the wallet to a new name (and enckey) to
+    /// the batch
+    pub fn batch_rename(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_name: &str,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        let locks = self.load(name, enckey)?;
+        batch.save_secure(KEYSPACE, new_name, new_enckey, &locks)?;
+        batch.delete(KEYSPACE, name);
+        Ok(())
+    }
+
+    /// Delete the output locks of the wallet
+    #[inline]
+    pub fn delete(&self, name: &str) -> Result<()> {
+        self.storage.delete(KEYSPACE, name)?;
+        Ok(())
+    }
+
+    /// Adds the deletion of the output locks of the wallet to the batch
+    #[inline]
+    pub fn batch_delete(&self, batch: &mut StorageBatch, name: &str) {
+        batch.delete(KEYSPACE, name);
+    }
+
+    /// Clears all storage
+    #[inline]
+    pub fn clear(&self) -> Result<()> {
+        self.storage.clear(KEYSPACE)
+    }
+}
+
+/// Unlocks the outputs spent by an (applied) memento
+pub fn unlock_spent_outputs<S: Storage>(
+    storage: &S,
+    name: &str,
+    enckey: &SecKey,
+    memento: &WalletStateMemento,
+) -> Result<()> {
+    let spent_outputs = memento
+        .removed_unspent_transactions()
+        .cloned()
+        .collect::<Vec<_>>();
+    if spent_outputs.is_empty() {
+        return Ok(());
+    }
+    OutputLockService::new(storage.clone()).unlock(name, enckey, &spent_outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client_common::seckey::derive_enckey;
+    use client_common::storage::MemoryStorage;
+    use secstr::SecUtf8;
+
+    fn output(index: usize) -> TxoPointer {
+        TxoPointer::new([1; 32], index)
+    }
+
+    #[test]
+    fn check_lock_flow() {
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let service = OutputLockService::new(MemoryStorage::default());
+
+        service
+            .lock("name", &enckey, &[output(0), output(1)], 1000, 60)
+            .unwrap();
+        assert_eq!(
+            vec![output(0), output(1)],
+            service
+                .get_locked("name", &enckey, 1000)
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+
+        // all or nothing
+        assert_eq!(
+            ErrorKind::AlreadyExists,
+            service
+                .lock("name", &enckey, &[output(2), output(1)], 1010, 60)
+                .unwrap_err()
+                .kind()
+        );
+        assert!(!service
+            .get_locked("name", &enckey, 1010)
+            .unwrap()
+            .contains(&output(2)));
+
+        service.unlock("name", &enckey, &[output(1)]).unwrap();
+        service
+            .lock("name", &enckey, &[output(2), output(1)], 1010, 60)
+            .unwrap();
+        assert_eq!(3, service.get_locked("name", &enckey, 1010).unwrap().len());
+    }
+
+    #[test]
+    fn check_locks_expire() {
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let service = OutputLockService::new(MemoryStorage::default());
+
+        service
+            .lock("name", &enckey, &[output(0)], 1000, 60)
+            .unwrap();
+        assert_eq!(1, service.get_locked("name", &enckey, 1059).unwrap().len());
+        assert!(service
+            .get_locked("name", &enckey, 1060)
+            .unwrap()
+            .is_empty());
+
+        // an expired lock doesn't prevent locking again
+        service
+            .lock("name", &enckey, &[output(0)], 1060, 60)
+            .unwrap();
+        assert_eq!(1, service.get_locked("name", &enckey, 1119).unwrap().len());
+    }
+}
@@ -0,0 +1,736 @@
+//! Broadcast transactions which are not confirmed yet. The transaction bytes are kept in storage,
+//! so the tracker survives restarts and a transaction can be broadcast again (including the ones
+//! whose broadcast failed because the node was unreachable). Nothing is re-broadcast or expired
+//! automatically: a transaction which is still unknown after the timeout is only reported as
+//! stale, and it's up to the caller to re-broadcast or expire it. The wallet sync reloads the
+//! tracked transactions into the wallet state when it starts (e.g. after a restart), and stops
+//! tracking the confirmed transactions and the ones whose inputs were spent by other transactions.
+//! A transaction sent with an idempotency key is found by it as long as it's tracked.
+//! The total size of the kept transactions is capped: the oldest confirmed transactions are
+//! evicted first, the unconfirmed ones are never evicted (they can only be expired).
+use std::collections::BTreeMap;
+
+use parity_scale_codec::{Decode, Encode};
+
+use chain_core::common::Timespec;
+use chain_core::tx::data::TxId;
+use client_common::{Error, ErrorKind, Result, SecKey, SecureStorage, Storage, StorageBatch};
+
+use super::wallet_state_service::{WalletState, WalletStateMemento};
+use crate::types::{PendingTransactionInfo, PendingTransactionStatus, TransactionPending};
+
+const KEYSPACE: &str = "core_pending_transaction";
+
+/// Time (in seconds) after broadcast since which an unconfirmed transaction is stale
+pub const DEFAULT_PENDING_TRANSACTION_TIMEOUT: Timespec = 600;
+
+/// Maximum total size (in bytes) of the (confirmed) transactions kept for a wallet
+pub const DEFAULT_PENDING_TRANSACTIONS_MAX_SIZE: usize = 4 * 1024 * 1024;
+
+/// Tracked (broadcast but unconfirmed) transaction
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct PendingTransaction {
+    /// id of the transaction
+    pub tx_id: TxId,
+    /// encoded `TxAux`, as broadcast
+    pub tx: Vec<u8>,
+    /// inputs and return amount of the transaction (as recorded in wallet state)
+    pub pending: TransactionPending,
+    /// time of the last broadcast
+    pub broadcast_time: Timespec,
+    /// number of broadcast attempts (more than one after explicit re-broadcasts)
+    pub broadcast_count: u32,
+    /// key given by the sender, so that sending again with the same key doesn't send another
+    /// transaction
+    pub idempotency_key: Option<String>,
+}
+
+impl PendingTransaction {
+    /// Whether one of the inputs of the (unconfirmed) transaction was spent by another transaction
+    /// in the synced wallet state, so that it can't be confirmed anymore
+    pub fn is_conflicting(&self, wallet_state: &WalletState) -> bool {
+        !wallet_state.transaction_history.contains_key(&self.tx_id)
+            && self
+                .pending
+                .used_inputs
+                .iter()
+                .any(|input| !wallet_state.unspent_transactions.contains_key(input))
+    }
+
+    /// Status of the transaction at `now`, given whether it's in the transaction history
+    pub fn status(
+        &self,
+        confirmed: bool,
+        now: Timespec,
+        timeout: Timespec,
+    ) -> PendingTransactionStatus {
+        if confirmed {
+            PendingTransactionStatus::Confirmed
+        } else if now.saturating_sub(self.broadcast_time) >= timeout {
+            PendingTransactionStatus::Stale
+        } else {
+            PendingTransactionStatus::Pending
+        }
+    }
+
+    /// Summary of the transaction with its status
+    pub fn info(&self, status: PendingTransactionStatus) -> PendingTransactionInfo {
+        PendingTransactionInfo {
+            transaction_id: self.tx_id,
+            status,
+            broadcast_time: self.broadcast_time,
+            broadcast_count: self.broadcast_count,
+            block_height: self.pending.block_height,
+            raw_tx_hex: hex::encode(&self.tx),
+            idempotency_key: self.idempotency_key.clone(),
+        }
+    }
+}
+
+/// Maintains mapping `wallet-name -> (txid -> pending transaction)` (encrypted with the wallet's enckey)
+#[derive(Debug, Clone)]
+pub struct PendingTransactionService<S>
+where
+    S: Storage,
+{
+    storage: S,
+    max_size: usize,
+}
+
+impl<S> PendingTransactionService<S>
+where
+    S: Storage,
+{
+    /// Creates a new instance of pending transaction service
+    #[inline]
+    pub fn new(storage: S) -> Self {
+        Self::with_max_size(storage, DEFAULT_PENDING_TRANSACTIONS_MAX_SIZE)
+    }
+
+    /// Creates a new instance of pending transaction service which keeps at most `max_size`
+    /// bytes of transactions for a wallet
+    #[inline]
+    pub fn with_max_size(storage: S, max_size: usize) -> Self {
+        Self { storage, max_size }
+    }
+
+    fn load(&self, name: &str, enckey: &SecKey) -> Result<BTreeMap<TxId, PendingTransaction>> {
+        Ok(self
+            .storage
+            .load_secure(KEYSPACE, name, enckey)?
+            .unwrap_or_default())
+    }
+
+    fn save(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        transactions: &BTreeMap<TxId, PendingTransaction>,
+    ) -> Result<()> {
+        self.storage
+            .save_secure(KEYSPACE, name, enckey, transactions)
+    }
+
+    /// Evicts confirmed transactions, oldest first, until the kept ones fit into `max_size`. The
+    /// unconfirmed ones (which couldn't be re-broadcast afterwards) and `keep` are never evicted,
+    /// even if they don't fit.
+    fn evict(
+        &self,
+        transactions: &mut BTreeMap<TxId, PendingTransaction>,
+        keep: &TxId,
+        is_confirmed: impl Fn(&TxId) -> bool,
+    ) {
+        let mut size = transactions
+            .values()
+            .map(|transaction| transaction.tx.len())
+            .sum::<usize>();
+        if size <= self.max_size {
+            return;
+        }
+
+        let mut candidates = transactions
+            .values()
+            .filter(|transaction| &transaction.tx_id != keep && is_confirmed(&transaction.tx_id))
+            .map(|transaction| (transaction.broadcast_time, transaction.tx_id))
+            .collect::<Vec<_>>();
+        candidates.sort();
+
+        for (_, tx_id) in candidates {
+            if size <= self.max_size {
+                break;
+            }
+            if let Some(transaction) = transactions.remove(&tx_id) {
+                size -= transaction.tx.len();
+            }
+        }
+        if size > self.max_size {
+            log::warn!(
+                "Unconfirmed transactions exceed the size limit of the pending transactions ({} > {} bytes)",
+                size,
+                self.max_size
+            );
+        }
+    }
+
+    /// Starts tracking a broadcast transaction, evicting the oldest confirmed tracked transactions
+    /// if the size limit is exceeded
+    #[allow(clippy::too_many_arguments)]
+    pub fn add(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        tx_id: TxId,
+        tx: Vec<u8>,
+        pending: TransactionPending,
+        broadcast_time: Timespec,
+        idempotency_key: Option<String>,
+        is_confirmed: impl Fn(&TxId) -> bool,
+    ) -> Result<()> {
+        let mut transactions = self.load(name, enckey)?;
+        transactions.insert(
+            tx_id,
+            PendingTransaction {
+                tx_id,
+                tx,
+                pending,
+                broadcast_time,
+                broadcast_count: 1,
+                idempotency_key,
+            },
+        );
+        self.evict(&mut transactions, &tx_id, is_confirmed);
+        self.save(name, enckey, &transactions)
+    }
+
+    /// Returns all the tracked transactions of the wallet
+    pub fn get_all(&self, name: &str, enckey: &SecKey) -> Result<Vec<PendingTransaction>> {
+        Ok(self
+            .load(name, enckey)?
+            .into_iter()
+            .map(|(_, transaction)| transaction)
+            .collect())
+    }
+
+    /// Returns a tracked transaction
+    pub fn get(&self, name: &str, enckey: &SecKey, tx_id: &TxId) -> Result<PendingTransaction> {
+        self.load(name, enckey)?.remove(tx_id).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("Transaction {} is not pending", hex::encode(tx_id)),
+            )
+        })
+    }
+
+    /// Returns the tracked transaction sent with given idempotency key (if any)
+    pub fn find_by_idempotency_key(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        idempotency_key: &str,
+    ) -> Result<Option<PendingTransaction>> {
+        Ok(self
+            .load(name, enckey)?
+            .into_iter()
+            .map(|(_, transaction)| transaction)
+            .find(|transaction| {
+                transaction.idempotency_key.as_ref().map(String::as_str) == Some(idempotency_key)
+            }))
+    }
+
+    /// Records an (explicit) re-broadcast of a tracked transaction
+    pub fn set_rebroadcast(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        tx_id: &TxId,
+        broadcast_time: Timespec,
+    ) -> Result<()> {
+        let mut transactions = self.load(name, enckey)?;
+        let transaction = transactions.get_mut(tx_id).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("Transaction {} is not pending", hex::encode(tx_id)),
+            )
+        })?;
+        transaction.broadcast_time = broadcast_time;
+        transaction.broadcast_count += 1;
+        self.save(name, enckey, &transactions)
+    }
+
+    /// Stops tracking transactions
+    pub fn remove(&self, name: &str, enckey: &SecKey, tx_ids: &[TxId]) -> Result<()> {
+        let mut transactions = self.load(name, enckey)?;
+        for tx_id in tx_ids {
+            transactions.remove(tx_id);
+        }
+        self.save(name, enckey, &transactions)
+    }
+
+    /// Adds the write which re-encrypts the tracked transactions of the wallet with a new enckey to the batch
+    pub fn batch_change_enckey(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        self.storage
+            .batch_reencrypt(batch, KEYSPACE, name, enckey, new_enckey)
+            .map(|_| ())
+    }
+
+    /// Adds the writes which move the tracked transactions of the wallet to a new name (and
+    /// enckey) to the batch
+    pub fn batch_rename(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_name: &str,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        let transactions = self.load(name, enckey)?;
+        batch.save_secure(KEYSPACE, new_name, new_enckey, &transactions)?;
+        batch.delete(KEYSPACE, name);
+        Ok(())
+    }
+
+    /// Delete all the tracked transactions of the wallet
+    #[inline]
+    pub fn delete(&self, name: &str) -> Result<()> {
+        self.storage.delete(KEYSPACE, name)?;
+        Ok(())
+    }
+
+    /// Adds the deletion of the tracked transactions of the wallet to the batch
+    #[inline]
+    pub fn batch_delete(&self, batch: &mut StorageBatch, name: &str) {
+        batch.delete(KEYSPACE, name);
+    }
+
+    /// Clears all storage
+    #[inline]
+    pub fn clear(&self) -> Result<()> {
+        self.storage.clear(KEYSPACE)
+    }
+}
+
+/// Stops tracking the transactions of the wallet which are confirmed or conflicting in the
+/// wallet state synced up to `synced_height`, returning the conflicting ones (the inputs of the
+/// transactions broadcast above `synced_height` may not be synced yet, so they aren't conflicting)
+pub fn prune_pending_transactions<S: Storage>(
+    storage: &S,
+    name: &str,
+    enckey: &SecKey,
+    wallet_state: &WalletState,
+    synced_height: u64,
+) -> Result<Vec<TxId>> {
+    let service = PendingTransactionService::new(storage.clone());
+    let mut transactions = service.load(name, enckey)?;
+    let (confirmed, conflicting): (Vec<_>, Vec<_>) = transactions
+        .values()
+        .filter(|transaction| {
+            wallet_state
+                .transaction_history
+                .contains_key(&transaction.tx_id)
+                || (transaction.pending.block_height <= synced_height
+                    && transaction.is_conflicting(wallet_state))
+        })
+        .map(|transaction| transaction.tx_id)
+        .partition(|tx_id| wallet_state.transaction_history.contains_key(tx_id));
+    if confirmed.is_empty() && conflicting.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    for tx_id in confirmed.iter().chain(conflicting.iter()) {
+        transactions.remove(tx_id);
+    }
+    for tx_id in conflicting.iter() {
+        log::warn!(
+            "Pending transaction {} of wallet {} conflicts with a synced transaction, it's no longer tracked",
+            hex::encode(tx_id),
+            name
+        );
+    }
+    service.save(name, enckey, &transactions)?;
+    Ok(conflicting)
+}
+
+/// Adds the tracked transactions of the wallet which are neither confirmed nor pending in the
+/// (loaded) wallet state to its pending transactions, e.g. after a restart which reset the wallet
+/// state, so that their inputs stay spent until they're confirmed or expired. Returns their ids.
+pub fn reload_pending_transactions<S: Storage>(
+    storage: &S,
+    name: &str,
+    enckey: &SecKey,
+    wallet_state: &mut WalletState,
+) -> Result<Vec<TxId>> {
+    let transactions = PendingTransactionService::new(storage.clone()).load(name, enckey)?;
+    let mut memento = WalletStateMemento::default();
+    let mut reloaded = Vec::new();
+    for transaction in transactions.values() {
+        if !wallet_state
+            .transaction_history
+            .contains_key(&transaction.tx_id)
+            && !wallet_state
+                .pending_transactions
+                .contains_key(&transaction.tx_id)
+        {
+            memento.add_pending_transaction(transaction.tx_id, transaction.pending.clone());
+            reloaded.push(transaction.tx_id);
+        }
+    }
+    wallet_state.apply_memento(&memento)?;
+    Ok(reloaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain_core::init::coin::Coin;
+    use chain_core::tx::data::address::ExtendedAddr;
+    use chain_core::tx::data::input::TxoPointer;
+    use chain_core::tx::data::output::TxOut;
+    use chain_core::tx::fee::Fee;
+    use client_common::seckey::derive_enckey;
+    use client_common::storage::MemoryStorage;
+    use client_common::tendermint::types::Time;
+    use secstr::SecUtf8;
+    use std::str::FromStr;
+
+    use crate::types::{BalanceChange, TransactionChange, TransactionType};
+
+    fn pending() -> TransactionPending {
+        TransactionPending {
+            used_inputs: vec![TxoPointer::new([1; 32], 0)],
+            block_height: 10,
+            return_amount: Coin::one(),
+        }
+    }
+
+    #[test]
+    fn check_tracker_survives_restart() {
+        let storage = MemoryStorage::default();
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let service = PendingTransactionService::new(storage.clone());
+        service
+            .add(
+                "name",
+                &enckey,
+                [2; 32],
+                vec![1, 2, 3],
+                pending(),
+                1000,
+                None,
+                |_| false,
+            )
+            .unwrap();
+        drop(service);
+
+        // restart: a new service on the same storage
+        let service = PendingTransactionService::new(storage);
+        let transactions = service.get_all("name", &enckey).unwrap();
+        assert_eq!(1, transactions.len());
+        let transaction = &transactions[0];
+        assert_eq!(vec![1, 2, 3], transaction.tx);
+        assert_eq!(
+            "010203",
+            transaction
+                .info(PendingTransactionStatus::Pending)
+                .raw_tx_hex
+        );
+        assert_eq!(pending().used_inputs, transaction.pending.used_inputs);
+        assert_eq!(
+            PendingTransactionStatus::Pending,
+            transaction.status(
+                false,
+                1000 + DEFAULT_PENDING_TRANSACTION_TIMEOUT - 1,
+                DEFAULT_PENDING_TRANSACTION_TIMEOUT
+            )
+        );
+        assert_eq!(
+            PendingTransactionStatus::Stale,
+            transaction.status(
+                false,
+                1000 + DEFAULT_PENDING_TRANSACTION_TIMEOUT,
+                DEFAULT_PENDING_TRANSACTION_TIMEOUT
+            )
+        );
+        assert_eq!(
+            PendingTransactionStatus::Confirmed,
+            transaction.status(true, 1000, DEFAULT_PENDING_TRANSACTION_TIMEOUT)
+        );
+
+        service
+            .set_rebroadcast("name", &enckey, &[2; 32], 2000)
+            .unwrap();
+        let transaction = service.get("name", &enckey, &[2; 32]).unwrap();
+        assert_eq!(
+            (2000, 2),
+            (transaction.broadcast_time, transaction.broadcast_count)
+        );
+        assert!(service
+            .set_rebroadcast("name", &enckey, &[3; 32], 2000)
+            .is_err());
+
+        service.remove("name", &enckey, &[[2; 32]]).unwrap();
+        assert!(service.get_all("name", &enckey).unwrap().is_empty());
+        assert!(service.get("name", &enckey, &[2; 32]).is_err());
+    }
+
+    #[test]
+    fn check_only_confirmed_transactions_are_evicted() {
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let service = PendingTransactionService::with_max_size(MemoryStorage::default(), 10);
+        let confirmed = [[1; 32], [2; 32]];
+        let is_confirmed = |tx_id: &TxId| confirmed.contains(tx_id);
+        for (i, tx_id) in [[1; 32], [2; 32], [3; 32]].iter().enumerate() {
+            service
+                .add(
+                    "name",
+                    &enckey,
+                    *tx_id,
+                    vec![0; 3],
+                    pending(),
+                    1000 + i as Timespec,
+                    None,
+                    is_confirmed,
+                )
+                .unwrap();
+        }
+        assert_eq!(3, service.get_all("name", &enckey).unwrap().len());
+
+        // the oldest confirmed transaction goes first
+        service
+            .add(
+                "name",
+                &enckey,
+                [4; 32],
+                vec![0; 3],
+                pending(),
+                1003,
+                None,
+                is_confirmed,
+            )
+            .unwrap();
+        let tx_ids = service
+            .get_all("name", &enckey)
+            .unwrap()
+            .into_iter()
+            .map(|transaction| transaction.tx_id)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![[2; 32], [3; 32], [4; 32]], tx_ids);
+
+        // then the other confirmed one before the older unconfirmed one
+        service
+            .add(
+                "name",
+                &enckey,
+                [5; 32],
+                vec![0; 3],
+                pending(),
+                1004,
+                None,
+                is_confirmed,
+            )
+            .unwrap();
+        let tx_ids = service
+            .get_all("name", &enckey)
+            .unwrap()
+            .into_iter()
+            .map(|transaction| transaction.tx_id)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![[3; 32], [4; 32], [5; 32]], tx_ids);
+
+        // but never an unconfirmed one, even above the size limit
+        service
+            .add(
+                "name",
+                &enckey,
+                [6; 32],
+                vec![0; 9],
+                pending(),
+                1005,
+                None,
+                is_confirmed,
+            )
+            .unwrap();
+        let tx_ids = service
+            .get_all("name", &enckey)
+            .unwrap()
+            .into_iter()
+            .map(|transaction| transaction.tx_id)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![[3; 32], [4; 32], [5; 32], [6; 32]], tx_ids);
+    }
+
+    #[test]
+    fn check_confirmed_and_conflicting_transactions_are_pruned() {
+        let storage = MemoryStorage::default();
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let service = PendingTransactionService::new(storage.clone());
+        for (i, tx_id) in [[2; 32], [3; 32], [4; 32]].iter().enumerate() {
+            let mut pending = pending();
+            pending.used_inputs = vec![TxoPointer::new([1; 32], i)];
+            service
+                .add(
+                    "name",
+                    &enckey,
+                    *tx_id,
+                    vec![0; 3],
+                    pending,
+                    1000,
+                    None,
+                    |_| false,
+                )
+                .unwrap();
+        }
+
+        // [2; 32] is confirmed, the input of [3; 32] was spent by another transaction and the
+        // input of [4; 32] is still unspent
+        let mut wallet_state = WalletState::default();
+        wallet_state.transaction_history.insert(
+            [2; 32],
+            TransactionChange {
+                transaction_id: [2; 32],
+                inputs: Vec::new(),
+                outputs: Vec::new(),
+                fee_paid: Fee::new(Coin::zero()),
+                balance_change: BalanceChange::NoChange,
+                transaction_type: TransactionType::Transfer,
+                block_height: 11,
+                block_time: Time::from_str("2019-04-09T09:38:41.735577Z").unwrap(),
+                memo: None,
+            },
+        );
+        wallet_state.unspent_transactions.insert(
+            TxoPointer::new([1; 32], 2),
+            TxOut::new(ExtendedAddr::OrTree([0; 32]), Coin::one()),
+        );
+
+        assert_eq!(
+            vec![[3; 32]],
+            prune_pending_transactions(&storage, "name", &enckey, &wallet_state, 11).unwrap()
+        );
+        let tx_ids = service
+            .get_all("name", &enckey)
+            .unwrap()
+            .into_iter()
+            .map(|transaction| transaction.tx_id)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![[4; 32]], tx_ids);
+
+        // nothing left to prune
+        assert!(
+            prune_pending_transactions(&storage, "name", &enckey, &wallet_state, 11)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(1, service.get_all("name", &enckey).unwrap().len());
+    }
+
+    #[test]
+    fn check_transactions_broadcast_above_synced_height_are_not_conflicting() {
+        let storage = MemoryStorage::default();
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let service = PendingTransactionService::new(storage.clone());
+        service
+            .add(
+                "name",
+                &enckey,
+                [2; 32],
+                vec![0; 3],
+                pending(),
+                1000,
+                None,
+                |_| false,
+            )
+            .unwrap();
+
+        // the input isn't synced yet (e.g. during a resync from scratch)
+        let wallet_state = WalletState::default();
+        assert!(
+            prune_pending_transactions(&storage, "name", &enckey, &wallet_state, 9)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(1, service.get_all("name", &enckey).unwrap().len());
+    }
+
+    #[test]
+    fn check_tracked_transactions_are_reloaded_into_wallet_state() {
+        let storage = MemoryStorage::default();
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let service = PendingTransactionService::new(storage.clone());
+        for tx_id in [[2; 32], [3; 32]].iter() {
+            service
+                .add(
+                    "name",
+                    &enckey,
+                    *tx_id,
+                    vec![0; 3],
+                    pending(),
+                    1000,
+                    None,
+                    |_| false,
+                )
+                .unwrap();
+        }
+
+        // [2; 32] is still pending in the wallet state, [3; 32] was lost (e.g. after a restart)
+        let mut wallet_state = WalletState::default();
+        wallet_state.pending_transactions.insert([2; 32], pending());
+
+        assert_eq!(
+            vec![[3; 32]],
+            reload_pending_transactions(&storage, "name", &enckey, &mut wallet_state).unwrap()
+        );
+        assert_eq!(
+            vec![[2; 32], [3; 32]],
+            wallet_state
+                .pending_transactions
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+        );
+        assert!(
+            reload_pending_transactions(&storage, "name", &enckey, &mut wallet_state)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn check_transaction_is_found_by_idempotency_key() {
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let service = PendingTransactionService::new(MemoryStorage::default());
+        service
+            .add(
+                "name",
+                &enckey,
+                [2; 32],
+                vec![0; 3],
+                pending(),
+                1000,
+                Some("payment-1".to_owned()),
+                |_| false,
+            )
+            .unwrap();
+
+        let transaction = service
+            .find_by_idempotency_key("name", &enckey, "payment-1")
+            .unwrap()
+            .unwrap();
+        assert_eq!([2; 32], transaction.tx_id);
+        assert_eq!(
+            Some("payment-1".to_owned()),
+            transaction
+                .info(PendingTransactionStatus::Pending)
+                .idempotency_key
+        );
+        assert!(service
+            .find_by_idempotency_key("name", &enckey, "payment-2")
+            .unwrap()
+            .is_none());
+    }
+}
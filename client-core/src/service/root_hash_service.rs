@@ -1,13 +1,29 @@
+use std::collections::BTreeMap;
+
 use parity_scale_codec::{Decode, Encode};
 
-use super::wallet_service::get_multisig_keyspace;
+use super::wallet_service::{get_multisig_keyspace, get_multisig_proof_keyspace};
 use chain_core::common::{Proof, H256};
 use chain_core::tx::witness::tree::RawXOnlyPubkey;
 use client_common::MultiSigAddress;
-use client_common::{ErrorKind, PublicKey, Result, ResultExt, SecKey, SecureStorage, Storage};
+use client_common::{
+    ErrorKind, PublicKey, Result, ResultExt, SecKey, SecureStorage, Storage, StorageBatch,
+};
 const KEYSPACE: &str = "core_root_hash";
 
-/// Maintains mapping `multi-sig-public-key -> multi-sig address`
+/// Inclusion proofs generated for the signer sets (sorted public keys) of a multi-sig address
+type ProofCache = BTreeMap<Vec<PublicKey>, Proof<RawXOnlyPubkey>>;
+
+#[cfg(test)]
+thread_local! {
+    /// Number of proofs generated from the merkle tree of a multi-sig address (i.e. not found in
+    /// the cache) on current thread
+    static GENERATED_PROOFS: std::cell::Cell<usize> = std::cell::Cell::new(0);
+}
+
+/// Maintains mapping `multi-sig-public-key -> multi-sig address`, and the inclusion proofs
+/// generated for the signer sets of each address (invalidated when its record is written or
+/// deleted)
 #[derive(Debug, Default, Clone)]
 pub struct RootHashService<T: Storage> {
     storage: T,
@@ -57,27 +73,68 @@ where
         Ok((root_hash, multi_sig_address))
     }
 
+    /// Same as `new_root_hash`, the multi-sig address is written by the batch
+    pub fn batch_new_root_hash(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        public_keys: Vec<PublicKey>,
+        self_public_key: PublicKey,
+        required_signers: usize,
+        enckey: &SecKey,
+    ) -> Result<(H256, MultiSigAddress)> {
+        let (root_hash, multi_sig_address) =
+            Self::peek_new_root_hash(public_keys, self_public_key, required_signers)?;
+        batch.set_secure(
+            get_multisig_keyspace(name),
+            hex::encode(&root_hash),
+            &multi_sig_address.encode(),
+            enckey,
+        )?;
+        batch.delete(get_multisig_proof_keyspace(name), hex::encode(&root_hash));
+        Ok((root_hash, multi_sig_address))
+    }
+
     /// delete root hash
     pub fn delete_root_hash(&self, name: &str, root_hash: &H256, _enckey: &SecKey) -> Result<()> {
-        let multisigaddress_keyspace = get_multisig_keyspace(name);
-        self.storage
-            .delete(multisigaddress_keyspace, hex::encode(&root_hash))?;
-        Ok(())
+        let mut batch = StorageBatch::default();
+        batch.delete(get_multisig_keyspace(name), hex::encode(&root_hash));
+        batch.delete(get_multisig_proof_keyspace(name), hex::encode(&root_hash));
+        self.storage.write_batch(batch)
     }
 
-    /// Generates inclusion proof for set of public keys in merkle root hash
+    /// Generates inclusion proof for set of public keys in merkle root hash. The proofs are cached
+    /// (encrypted with enckey), so that signing again with the same set of public keys doesn't go
+    /// through the merkle tree of the address.
     pub fn generate_proof(
         &self,
         name: &str,
         root_hash: &H256,
-        public_keys: Vec<PublicKey>,
+        mut public_keys: Vec<PublicKey>,
         enckey: &SecKey,
     ) -> Result<Proof<RawXOnlyPubkey>> {
+        public_keys.sort();
+
+        let proof_keyspace = get_multisig_proof_keyspace(name);
+        let mut proofs: ProofCache = self
+            .storage
+            .load_secure(&proof_keyspace, &hex::encode(root_hash), enckey)?
+            .unwrap_or_default();
+        if let Some(proof) = proofs.get(&public_keys) {
+            return Ok(proof.clone());
+        }
+
         let address = self.get_multi_sig_address_from_root_hash(name, root_hash, enckey)?;
+        #[cfg(test)]
+        GENERATED_PROOFS.with(|count| count.set(count.get() + 1));
+        let proof = address
+            .generate_proof(public_keys.clone())?
+            .chain(|| (ErrorKind::InvalidInput, "Unable to generate merkle proof"))?;
 
-        address
-            .generate_proof(public_keys)?
-            .chain(|| (ErrorKind::InvalidInput, "Unable to generate merkle proof"))
+        proofs.insert(public_keys, proof.clone());
+        self.storage
+            .save_secure(&proof_keyspace, &hex::encode(root_hash), enckey, &proofs)?;
+        Ok(proof)
     }
 
     /// Returns the number of required cosigners for given root_hash
@@ -102,14 +159,15 @@ where
         root_hash: &H256,
         multi_sig_address: &MultiSigAddress,
     ) -> Result<()> {
-        let multisigaddress_keyspace = get_multisig_keyspace(name);
-        self.storage.set_secure(
-            multisigaddress_keyspace,
+        let mut batch = StorageBatch::default();
+        batch.set_secure(
+            get_multisig_keyspace(name),
             hex::encode(&root_hash),
-            multi_sig_address.encode(),
+            &multi_sig_address.encode(),
             enckey,
         )?;
-        Ok(())
+        batch.delete(get_multisig_proof_keyspace(name), hex::encode(&root_hash));
+        self.storage.write_batch(batch)
     }
 
     /// Returns MultiSig address from storage with the given root_hash
@@ -137,21 +195,170 @@ where
         })
     }
 
+    /// Adds the writes which re-encrypt the multi-sig addresses of the wallet (and their cached
+    /// proofs) with a new enckey to the batch
+    pub fn batch_change_enckey(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        self.storage.batch_reencrypt_keyspace(
+            batch,
+            get_multisig_keyspace(name),
+            enckey,
+            new_enckey,
+        )?;
+        self.storage.batch_reencrypt_keyspace(
+            batch,
+            get_multisig_proof_keyspace(name),
+            enckey,
+            new_enckey,
+        )
+    }
+
     /// Clears all storage
     pub fn clear(&self) -> Result<()> {
         self.storage.clear(KEYSPACE)
     }
 }
 
-#[cfg(all(test, feature = "experimental"))]
+#[cfg(test)]
 mod tests {
     use super::*;
     use secstr::SecUtf8;
 
+    use chain_core::tx::data::address::ExtendedAddr;
+    use chain_core::tx::data::Tx;
+    use chain_core::tx::witness::TxInWitness;
+    use chain_tx_validation::witness::verify_tx_address;
     use client_common::storage::MemoryStorage;
-    use client_common::{seckey::derive_enckey, PrivateKey};
+    use client_common::{seckey::derive_enckey, PrivateKey, PrivateKeyAction, Transaction};
+
+    #[test]
+    fn check_address_is_independent_of_public_key_order() {
+        let root_hash_service = RootHashService::new(MemoryStorage::default());
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "").unwrap();
+        let name = "name";
+
+        let private_keys = (0..3)
+            .map(|_| PrivateKey::new().unwrap())
+            .collect::<Vec<_>>();
+        let public_keys = private_keys.iter().map(PublicKey::from).collect::<Vec<_>>();
+        let permutations = vec![[0, 1, 2], [0, 2, 1], [1, 2, 0], [2, 1, 0]];
+        let permuted = |permutation: &[usize; 3]| {
+            permutation
+                .iter()
+                .map(|i| public_keys[*i].clone())
+                .collect::<Vec<_>>()
+        };
+
+        let transaction = Transaction::TransferTransaction(Tx::new());
+        let mut addresses = Vec::new();
+        for permutation in permutations.iter() {
+            let (root_hash, multi_sig_address) = root_hash_service
+                .new_root_hash(
+                    name,
+                    permuted(permutation),
+                    public_keys[permutation[0]].clone(),
+                    1,
+                    &enckey,
+                )
+                .unwrap();
+            let address = ExtendedAddr::from(multi_sig_address);
+
+            // every signer's proof verifies as a tree signature of the address
+            for (private_key, public_key) in private_keys.iter().zip(public_keys.iter()) {
+                let proof = root_hash_service
+                    .generate_proof(name, &root_hash, vec![public_key.clone()], &enckey)
+                    .unwrap();
+                let witness =
+                    TxInWitness::TreeSig(private_key.schnorr_sign(&transaction).unwrap(), proof);
+                assert!(verify_tx_address(&witness, &transaction.id(), &address).is_ok());
+            }
+            addresses.push(address);
+        }
+        assert!(addresses.iter().all(|address| address == &addresses[0]));
+    }
+
+    fn generated_proofs() -> usize {
+        GENERATED_PROOFS.with(|count| count.get())
+    }
+
+    #[test]
+    fn check_proofs_are_cached() {
+        let storage = MemoryStorage::default();
+        let root_hash_service = RootHashService::new(storage.clone());
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "").unwrap();
+        let name = "name";
+
+        let public_keys = (0..3)
+            .map(|_| PublicKey::from(&PrivateKey::new().unwrap()))
+            .collect::<Vec<_>>();
+        let (root_hash, _) = root_hash_service
+            .new_root_hash(
+                name,
+                public_keys.clone(),
+                public_keys[0].clone(),
+                1,
+                &enckey,
+            )
+            .unwrap();
+        let generate_proof = |public_key: &PublicKey, enckey: &SecKey| {
+            root_hash_service
+                .generate_proof(name, &root_hash, vec![public_key.clone()], enckey)
+                .unwrap()
+        };
+
+        let generated = generated_proofs();
+        let proof = generate_proof(&public_keys[1], &enckey);
+        assert_eq!(generated + 1, generated_proofs());
+        let cached_proof = generate_proof(&public_keys[1], &enckey);
+        assert_eq!(generated + 1, generated_proofs());
+        assert_eq!(proof, cached_proof);
+        assert!(cached_proof.verify(&root_hash));
+
+        // each signer set has its own proof
+        let other_proof = generate_proof(&public_keys[2], &enckey);
+        assert_eq!(generated + 2, generated_proofs());
+        assert_ne!(proof, other_proof);
+
+        // the cached proofs are re-encrypted with the address
+        let new_enckey = derive_enckey(&SecUtf8::from("new passphrase"), "").unwrap();
+        let mut batch = StorageBatch::default();
+        root_hash_service
+            .batch_change_enckey(&mut batch, name, &enckey, &new_enckey)
+            .unwrap();
+        storage.write_batch(batch).unwrap();
+        assert_eq!(proof, generate_proof(&public_keys[1], &new_enckey));
+        assert_eq!(generated + 2, generated_proofs());
+
+        // writing the address record invalidates its cached proofs
+        let address = root_hash_service
+            .get_multi_sig_address_from_root_hash(name, &root_hash, &new_enckey)
+            .unwrap();
+        root_hash_service
+            .set_multi_sig_address_from_root_hash(name, &new_enckey, &root_hash, &address)
+            .unwrap();
+        assert_eq!(proof, generate_proof(&public_keys[1], &new_enckey));
+        assert_eq!(generated + 3, generated_proofs());
+
+        // so does deleting it
+        root_hash_service
+            .delete_root_hash(name, &root_hash, &new_enckey)
+            .unwrap();
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            root_hash_service
+                .generate_proof(name, &root_hash, vec![public_keys[1].clone()], &new_enckey)
+                .unwrap_err()
+                .kind()
+        );
+    }
 
     #[test]
+    #[cfg(feature = "experimental")]
     fn check_root_hash_flow() {
         let root_hash_service = RootHashService::new(MemoryStorage::default());
         let enckey = derive_enckey(&SecUtf8::from("passphrase"), "").unwrap();
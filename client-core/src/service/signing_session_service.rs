@@ -0,0 +1,371 @@
+//! In-memory signing sessions: private keys of a declared list of addresses are held for a short
+//! time, so multi-step flows can reference a session id instead of the wallet's enckey.
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+#[cfg(test)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use rand::random;
+use zeroize::Zeroize;
+
+use chain_core::common::H256;
+use client_common::{Error, ErrorKind, PrivateKey, PublicKey, Result, SecKey};
+
+/// Time-to-live of a signing session when none is requested
+pub const DEFAULT_SIGNING_SESSION_TTL: Duration = Duration::from_secs(60);
+/// Maximum time-to-live of a signing session
+pub const MAX_SIGNING_SESSION_TTL: Duration = Duration::from_secs(300);
+
+struct SigningSession {
+    /// name of the wallet
+    name: String,
+    /// enckey of the wallet (only used to access the multi-sig sessions started in this session,
+    /// which are stored encrypted with it)
+    enckey: SecKey,
+    /// private keys by (declared) address
+    keys: BTreeMap<String, PrivateKey>,
+    /// ids of the multi-sig sessions started in this session
+    multi_sig_sessions: BTreeSet<H256>,
+    expires_at: Instant,
+    #[cfg(test)]
+    dropped: Arc<AtomicUsize>,
+}
+
+impl Drop for SigningSession {
+    fn drop(&mut self) {
+        for key in self.keys.values_mut() {
+            key.zeroize();
+        }
+        #[cfg(test)]
+        self.dropped.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Maintains mapping `signing session-id -> private keys of declared addresses` (in memory only)
+#[derive(Clone, Default)]
+pub struct SigningSessionService {
+    sessions: Arc<Mutex<HashMap<H256, SigningSession>>>,
+    #[cfg(test)]
+    dropped: Arc<AtomicUsize>,
+}
+
+impl SigningSessionService {
+    /// Creates a new instance of signing session service
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Starts a new session holding `keys` (by address) and returns session-id
+    ///
+    /// # Arguments
+    ///
+    /// - `name`: Name of the wallet
+    /// - `enckey`: enckey of the wallet
+    /// - `keys`: Private keys of the declared addresses (the only ones usable in the session)
+    /// - `ttl`: Time after which the session expires (`DEFAULT_SIGNING_SESSION_TTL` if `None`)
+    pub fn begin(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        keys: BTreeMap<String, PrivateKey>,
+        ttl: Option<Duration>,
+    ) -> Result<H256> {
+        if keys.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Signing session requires at least one address",
+            ));
+        }
+        let ttl = ttl.unwrap_or(DEFAULT_SIGNING_SESSION_TTL);
+        if ttl == Duration::from_secs(0) || ttl > MAX_SIGNING_SESSION_TTL {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Signing session TTL has to be between 1 and {} seconds",
+                    MAX_SIGNING_SESSION_TTL.as_secs()
+                ),
+            ));
+        }
+
+        let session_id: H256 = random();
+        let session = SigningSession {
+            name: name.to_owned(),
+            enckey: enckey.clone(),
+            keys,
+            multi_sig_sessions: BTreeSet::new(),
+            expires_at: Instant::now() + ttl,
+            #[cfg(test)]
+            dropped: self.dropped.clone(),
+        };
+
+        let mut sessions = self.sessions.lock().expect("lock signing sessions");
+        remove_expired(&mut sessions);
+        sessions.insert(session_id, session);
+        Ok(session_id)
+    }
+
+    /// Calls `f` with private key of `address` held in given session. Fails if the session
+    /// doesn't exist (anymore) or if `address` wasn't declared when the session was started.
+    pub fn with_key<R, F>(&self, session_id: &H256, address: &str, f: F) -> Result<R>
+    where
+        F: FnOnce(&PrivateKey) -> Result<R>,
+    {
+        let mut sessions = self.sessions.lock().expect("lock signing sessions");
+        let session = get_session(&mut sessions, session_id)?;
+
+        let private_key = session.keys.get(address).ok_or_else(|| {
+            Error::new(
+                ErrorKind::PermissionDenied,
+                format!("Address {} is not declared in signing session", address),
+            )
+        })?;
+        f(private_key)
+    }
+
+    /// Starts a multi-sig session with `f` (called with wallet's name and enckey) and returns its
+    /// id. Fails if `self_public_key` isn't the public key of one of the declared addresses.
+    pub fn new_multi_sig_session<F>(
+        &self,
+        session_id: &H256,
+        self_public_key: &PublicKey,
+        f: F,
+    ) -> Result<H256>
+    where
+        F: FnOnce(&str, &SecKey) -> Result<H256>,
+    {
+        let mut sessions = self.sessions.lock().expect("lock signing sessions");
+        let session = get_session(&mut sessions, session_id)?;
+
+        if !session
+            .keys
+            .values()
+            .any(|private_key| PublicKey::from(private_key) == *self_public_key)
+        {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "Self public key is not the key of an address declared in signing session",
+            ));
+        }
+
+        let multi_sig_session_id = f(&session.name, &session.enckey)?;
+        session.multi_sig_sessions.insert(multi_sig_session_id);
+        Ok(multi_sig_session_id)
+    }
+
+    /// Calls `f` with wallet's name and enckey to access given multi-sig session. Fails if the
+    /// multi-sig session wasn't started in this session.
+    pub fn with_multi_sig_session<R, F>(
+        &self,
+        session_id: &H256,
+        multi_sig_session_id: &H256,
+        f: F,
+    ) -> Result<R>
+    where
+        F: FnOnce(&str, &SecKey) -> Result<R>,
+    {
+        let mut sessions = self.sessions.lock().expect("lock signing sessions");
+        let session = get_session(&mut sessions, session_id)?;
+
+        if !session.multi_sig_sessions.contains(multi_sig_session_id) {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "Multi-sig session was not started in signing session",
+            ));
+        }
+        f(&session.name, &session.enckey)
+    }
+
+    /// Ends given session (held private keys are zeroized)
+    pub fn end(&self, session_id: &H256) -> Result<()> {
+        let mut sessions = self.sessions.lock().expect("lock signing sessions");
+        remove_expired(&mut sessions);
+
+        match sessions.remove(session_id) {
+            Some(_) => Ok(()),
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Signing session not found (it may have expired)",
+            )),
+        }
+    }
+
+    /// Removes all the expired sessions
+    pub fn remove_expired(&self) {
+        remove_expired(&mut self.sessions.lock().expect("lock signing sessions"));
+    }
+}
+
+fn remove_expired(sessions: &mut HashMap<H256, SigningSession>) {
+    let now = Instant::now();
+    sessions.retain(|_, session| session.expires_at > now);
+}
+
+fn get_session<'a>(
+    sessions: &'a mut HashMap<H256, SigningSession>,
+    session_id: &H256,
+) -> Result<&'a mut SigningSession> {
+    remove_expired(sessions);
+    sessions.get_mut(session_id).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            "Signing session not found (it may have expired)",
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread::sleep;
+
+    use client_common::seckey::parse_hex_enckey;
+
+    fn enckey() -> SecKey {
+        parse_hex_enckey(&"11".repeat(32)).unwrap()
+    }
+
+    fn keys(addresses: &[&str]) -> BTreeMap<String, PrivateKey> {
+        addresses
+            .iter()
+            .map(|address| (address.to_string(), PrivateKey::new().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn check_key_scoping() {
+        let service = SigningSessionService::new();
+        let session_id = service
+            .begin("Default", &enckey(), keys(&["a", "b"]), None)
+            .unwrap();
+
+        assert!(service.with_key(&session_id, "a", |_| Ok(())).is_ok());
+        assert!(service.with_key(&session_id, "b", |_| Ok(())).is_ok());
+        assert_eq!(
+            ErrorKind::PermissionDenied,
+            service
+                .with_key(&session_id, "c", |_| Ok(()))
+                .unwrap_err()
+                .kind()
+        );
+
+        let other_session_id = service
+            .begin("Default", &enckey(), keys(&["c"]), None)
+            .unwrap();
+        assert!(service.with_key(&other_session_id, "c", |_| Ok(())).is_ok());
+        assert_eq!(
+            ErrorKind::PermissionDenied,
+            service
+                .with_key(&other_session_id, "a", |_| Ok(()))
+                .unwrap_err()
+                .kind()
+        );
+
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            service
+                .begin("Default", &enckey(), BTreeMap::new(), None)
+                .unwrap_err()
+                .kind()
+        );
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            service
+                .begin(
+                    "Default",
+                    &enckey(),
+                    keys(&["a"]),
+                    Some(MAX_SIGNING_SESSION_TTL * 2)
+                )
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn check_expiry() {
+        let service = SigningSessionService::new();
+        let session_id = service
+            .begin(
+                "Default",
+                &enckey(),
+                keys(&["a"]),
+                Some(Duration::from_millis(50)),
+            )
+            .unwrap();
+        assert!(service.with_key(&session_id, "a", |_| Ok(())).is_ok());
+
+        sleep(Duration::from_millis(100));
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            service
+                .with_key(&session_id, "a", |_| Ok(()))
+                .unwrap_err()
+                .kind()
+        );
+        assert_eq!(1, service.dropped.load(Ordering::SeqCst));
+        assert!(service.end(&session_id).is_err());
+    }
+
+    #[test]
+    fn check_end_drops_keys() {
+        let service = SigningSessionService::new();
+        let session_id = service
+            .begin("Default", &enckey(), keys(&["a", "b"]), None)
+            .unwrap();
+        let other_session_id = service
+            .begin("Default", &enckey(), keys(&["a"]), None)
+            .unwrap();
+        assert_eq!(0, service.dropped.load(Ordering::SeqCst));
+
+        service.end(&session_id).unwrap();
+        assert_eq!(1, service.dropped.load(Ordering::SeqCst));
+        assert!(service.with_key(&session_id, "a", |_| Ok(())).is_err());
+        assert!(service.with_key(&other_session_id, "a", |_| Ok(())).is_ok());
+    }
+
+    #[test]
+    fn check_multi_sig_session_scoping() {
+        let service = SigningSessionService::new();
+        let declared = PrivateKey::new().unwrap();
+        let declared_public_key = PublicKey::from(&declared);
+        let mut session_keys = BTreeMap::new();
+        session_keys.insert("a".to_owned(), declared);
+        let session_id = service
+            .begin("Default", &enckey(), session_keys, None)
+            .unwrap();
+
+        let undeclared_public_key = PublicKey::from(&PrivateKey::new().unwrap());
+        assert_eq!(
+            ErrorKind::PermissionDenied,
+            service
+                .new_multi_sig_session(&session_id, &undeclared_public_key, |_, _| Ok([1; 32]))
+                .unwrap_err()
+                .kind()
+        );
+
+        let multi_sig_session_id = service
+            .new_multi_sig_session(&session_id, &declared_public_key, |name, enckey| {
+                assert_eq!("Default", name);
+                assert_eq!(self::enckey(), *enckey);
+                Ok([2; 32])
+            })
+            .unwrap();
+        assert!(service
+            .with_multi_sig_session(&session_id, &multi_sig_session_id, |_, _| Ok(()))
+            .is_ok());
+        assert_eq!(
+            ErrorKind::PermissionDenied,
+            service
+                .with_multi_sig_session(&session_id, &[1; 32], |_, _| Ok(()))
+                .unwrap_err()
+                .kind()
+        );
+
+        service.end(&session_id).unwrap();
+        assert!(service
+            .with_multi_sig_session(&session_id, &multi_sig_session_id, |_, _| Ok(()))
+            .is_err());
+    }
+}
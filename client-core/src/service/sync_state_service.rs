@@ -1,8 +1,14 @@
 use chain_core::common::H256;
-use client_common::{ErrorKind, Result, ResultExt, Storage};
+use client_common::{ErrorKind, Result, ResultExt, Storage, StorageBatch};
 use parity_scale_codec::{Decode, Encode};
 /// key space of wallet sync state
 const KEYSPACE: &str = "core_wallet_sync";
+/// key space of recently synced blocks of wallet
+const CHECKPOINT_KEYSPACE: &str = "core_wallet_sync_checkpoints";
+
+/// Maximum number of recently synced blocks kept as checkpoints, it bounds the depth of fork the
+/// wallet can roll back from
+pub const MAX_SYNC_CHECKPOINTS: usize = 100;
 
 /// Sync state for wallet
 #[derive(Debug, Encode, Decode)]
@@ -32,6 +38,32 @@ impl SyncState {
     }
 }
 
+impl SyncState {
+    /// Sync state rolled back to given checkpoint (not trusted until it's synced again)
+    pub fn from_checkpoint(checkpoint: &SyncCheckpoint) -> SyncState {
+        SyncState {
+            last_block_height: checkpoint.block_height,
+            last_app_hash: checkpoint.app_hash.clone(),
+            last_block_hash: checkpoint.block_hash.clone(),
+            staking_root: checkpoint.staking_root,
+            trusted: false,
+        }
+    }
+}
+
+/// Synced block which the wallet can roll back to
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct SyncCheckpoint {
+    /// block height
+    pub block_height: u64,
+    /// app hash after the block
+    pub app_hash: String,
+    /// block hash
+    pub block_hash: String,
+    /// staking root after the block
+    pub staking_root: H256,
+}
+
 /// Load sync state from storage
 pub fn load_sync_state<S: Storage>(storage: &S, name: &str) -> Result<Option<SyncState>> {
     storage.load(KEYSPACE, name)
@@ -42,12 +74,67 @@ pub fn save_sync_state<S: Storage>(storage: &S, name: &str, state: &SyncState) -
     storage.save(KEYSPACE, name, state)
 }
 
-/// Delete sync state from storage
+/// Add saving of sync state to storage batch
+pub fn batch_save_sync_state(batch: &mut StorageBatch, name: &str, state: &SyncState) {
+    batch.save(KEYSPACE, name, state)
+}
+
+/// Delete sync state (and its checkpoints) from storage
 pub fn delete_sync_state<S: Storage>(storage: &S, name: &str) -> Result<()> {
     storage.delete(KEYSPACE, name)?;
+    storage.delete(CHECKPOINT_KEYSPACE, name)?;
+    Ok(())
+}
+
+/// Add deletion of sync state (and its checkpoints) to storage batch
+pub fn batch_delete_sync_state(batch: &mut StorageBatch, name: &str) {
+    batch.delete(KEYSPACE, name);
+    batch.delete(CHECKPOINT_KEYSPACE, name);
+}
+
+/// Load checkpoints of recently synced blocks (ordered by block height) from storage
+pub fn load_sync_checkpoints<S: Storage>(storage: &S, name: &str) -> Result<Vec<SyncCheckpoint>> {
+    Ok(storage.load(CHECKPOINT_KEYSPACE, name)?.unwrap_or_default())
+}
+
+/// Append checkpoints of newly synced blocks to storage, only the latest `MAX_SYNC_CHECKPOINTS`
+/// ones are kept
+pub fn add_sync_checkpoints<S: Storage>(
+    storage: &S,
+    name: &str,
+    checkpoints: impl IntoIterator<Item = SyncCheckpoint>,
+) -> Result<()> {
+    let mut batch = StorageBatch::default();
+    batch_add_sync_checkpoints(storage, &mut batch, name, checkpoints)?;
+    storage.write_batch(batch)
+}
+
+/// Add appending of checkpoints of newly synced blocks to storage batch (see
+/// `add_sync_checkpoints`)
+pub fn batch_add_sync_checkpoints<S: Storage>(
+    storage: &S,
+    batch: &mut StorageBatch,
+    name: &str,
+    checkpoints: impl IntoIterator<Item = SyncCheckpoint>,
+) -> Result<()> {
+    let mut all_checkpoints = load_sync_checkpoints(storage, name)?;
+    all_checkpoints.extend(checkpoints);
+    if all_checkpoints.len() > MAX_SYNC_CHECKPOINTS {
+        all_checkpoints.drain(..all_checkpoints.len() - MAX_SYNC_CHECKPOINTS);
+    }
+    batch_save_sync_checkpoints(batch, name, &all_checkpoints);
     Ok(())
 }
 
+/// Add saving of sync checkpoints to storage batch
+pub fn batch_save_sync_checkpoints(
+    batch: &mut StorageBatch,
+    name: &str,
+    checkpoints: &[SyncCheckpoint],
+) {
+    batch.save(CHECKPOINT_KEYSPACE, name, &checkpoints.to_vec())
+}
+
 /// Exposes functionalities for managing client's global state (for synchronization)
 ///
 /// Stores `wallet-name -> global-state`
@@ -77,13 +164,14 @@ where
     /// Deletes global state data for given wallet
     #[inline]
     pub fn delete_global_state(&self, name: &str) -> Result<()> {
-        self.storage.delete(KEYSPACE, name).map(|_| ())
+        delete_sync_state(&self.storage, name)
     }
 
     /// Clears all storage
     #[inline]
     pub fn clear(&self) -> Result<()> {
-        self.storage.clear(KEYSPACE)
+        self.storage.clear(KEYSPACE)?;
+        self.storage.clear(CHECKPOINT_KEYSPACE)
     }
 
     /// Get wallet global state
@@ -110,7 +198,10 @@ where
 mod tests {
     use parity_scale_codec::{Decode, Encode};
 
-    use super::{SyncState, SyncStateService};
+    use super::{
+        add_sync_checkpoints, load_sync_checkpoints, SyncCheckpoint, SyncState, SyncStateService,
+        MAX_SYNC_CHECKPOINTS,
+    };
     use client_common::storage::MemoryStorage;
 
     #[test]
@@ -174,4 +265,30 @@ mod tests {
         let state2 = SyncState::decode(&mut bytes.as_slice()).unwrap();
         assert_eq!(bytes, state2.encode());
     }
+
+    #[test]
+    fn check_sync_checkpoints_are_bounded() {
+        let storage = MemoryStorage::default();
+        let name = "name";
+        let checkpoint = |block_height: u64| SyncCheckpoint {
+            block_height,
+            app_hash: "".to_owned(),
+            block_hash: format!("{}", block_height),
+            staking_root: [0u8; 32],
+        };
+
+        add_sync_checkpoints(&storage, name, (1..=10).map(checkpoint)).unwrap();
+        assert_eq!(10, load_sync_checkpoints(&storage, name).unwrap().len());
+
+        add_sync_checkpoints(&storage, name, (11..=150).map(checkpoint)).unwrap();
+        let checkpoints = load_sync_checkpoints(&storage, name).unwrap();
+        assert_eq!(MAX_SYNC_CHECKPOINTS, checkpoints.len());
+        assert_eq!(checkpoint(51), checkpoints[0]);
+        assert_eq!(checkpoint(150), checkpoints[MAX_SYNC_CHECKPOINTS - 1]);
+
+        SyncStateService::new(storage.clone())
+            .delete_global_state(name)
+            .unwrap();
+        assert!(load_sync_checkpoints(&storage, name).unwrap().is_empty());
+    }
 }
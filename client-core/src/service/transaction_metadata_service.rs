@@ -0,0 +1,185 @@
+//! Metadata attached by the user to the transactions of a wallet (e.g. memos). It's stored apart
+//! from the transaction history, so it's kept when the history is re-synced or re-indexed.
+use std::collections::BTreeMap;
+
+use chain_core::tx::data::TxId;
+use client_common::{Error, ErrorKind, Result, SecKey, SecureStorage, Storage, StorageBatch};
+
+use crate::types::TransactionChange;
+
+const KEYSPACE: &str = "core_transaction_metadata";
+
+/// Maximum length (in bytes) of a transaction memo
+pub const MAX_TRANSACTION_MEMO_LENGTH: usize = 256;
+
+/// Maintains mapping `wallet-name -> (txid -> memo)` (encrypted with the wallet's enckey)
+#[derive(Debug, Clone)]
+pub struct TransactionMetadataService<S>
+where
+    S: Storage,
+{
+    storage: S,
+}
+
+impl<S> TransactionMetadataService<S>
+where
+    S: Storage,
+{
+    /// Creates a new instance of transaction metadata service
+    #[inline]
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// Sets the memo of a transaction (`None` removes it)
+    pub fn set_memo(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        tx_id: TxId,
+        memo: Option<String>,
+    ) -> Result<()> {
+        let mut memos = self.get_memos(name, enckey)?;
+        match memo {
+            Some(memo) => {
+                if memo.len() > MAX_TRANSACTION_MEMO_LENGTH {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!(
+                            "Transaction memo cannot be longer than {} bytes",
+                            MAX_TRANSACTION_MEMO_LENGTH
+                        ),
+                    ));
+                }
+                memos.insert(tx_id, memo);
+            }
+            None => {
+                if memos.remove(&tx_id).is_none() {
+                    return Ok(());
+                }
+            }
+        }
+        self.storage.save_secure(KEYSPACE, name, enckey, &memos)
+    }
+
+    /// Returns the memo of a transaction
+    pub fn get_memo(&self, name: &str, enckey: &SecKey, tx_id: &TxId) -> Result<Option<String>> {
+        Ok(self.get_memos(name, enckey)?.remove(tx_id))
+    }
+
+    /// Returns the memos of all the transactions of the wallet
+    pub fn get_memos(&self, name: &str, enckey: &SecKey) -> Result<BTreeMap<TxId, String>> {
+        Ok(self
+            .storage
+            .load_secure(KEYSPACE, name, enckey)?
+            .unwrap_or_default())
+    }
+
+    /// Fills the memos of given transaction changes
+    pub fn attach_memos<'a, I>(&self, name: &str, enckey: &SecKey, changes: I) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a mut TransactionChange>,
+    {
+        let mut memos = self.get_memos(name, enckey)?;
+        if memos.is_empty() {
+            return Ok(());
+        }
+        for change in changes {
+            change.memo = memos.remove(&change.transaction_id);
+        }
+        Ok(())
+    }
+
+    /// Adds the write which re-encrypts the memos of the wallet with a new enckey to the batch
+    pub fn batch_change_enckey(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        self.storage
+            .batch_reencrypt(batch, KEYSPACE, name, enckey, new_enckey)
+            .map(|_| ())
+    }
+
+    /// Adds the writes which move the memos of the wallet to a new name (and enckey) to the batch
+    pub fn batch_rename(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_name: &str,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        let memos = self.get_memos(name, enckey)?;
+        batch.save_secure(KEYSPACE, new_name, new_enckey, &memos)?;
+        batch.delete(KEYSPACE, name);
+        Ok(())
+    }
+
+    /// Delete all the memos of the wallet
+    #[inline]
+    pub fn delete(&self, name: &str) -> Result<()> {
+        self.storage.delete(KEYSPACE, name)?;
+        Ok(())
+    }
+
+    /// Adds the deletion of the transaction memos of the wallet to the batch
+    #[inline]
+    pub fn batch_delete(&self, batch: &mut StorageBatch, name: &str) {
+        batch.delete(KEYSPACE, name);
+    }
+
+    /// Clears all storage
+    #[inline]
+    pub fn clear(&self) -> Result<()> {
+        self.storage.clear(KEYSPACE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client_common::seckey::derive_enckey;
+    use client_common::storage::MemoryStorage;
+    use secstr::SecUtf8;
+
+    #[test]
+    fn check_flow() {
+        let storage = MemoryStorage::default();
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let service = TransactionMetadataService::new(storage.clone());
+        assert_eq!(None, service.get_memo("name", &enckey, &[1; 32]).unwrap());
+
+        service
+            .set_memo("name", &enckey, [1; 32], Some("payroll march".to_owned()))
+            .unwrap();
+        service
+            .set_memo("name", &enckey, [2; 32], Some("rent".to_owned()))
+            .unwrap();
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            service
+                .set_memo(
+                    "name",
+                    &enckey,
+                    [3; 32],
+                    Some("x".repeat(MAX_TRANSACTION_MEMO_LENGTH + 1))
+                )
+                .unwrap_err()
+                .kind()
+        );
+
+        let service = TransactionMetadataService::new(storage);
+        assert_eq!(
+            Some("payroll march".to_owned()),
+            service.get_memo("name", &enckey, &[1; 32]).unwrap()
+        );
+        service.set_memo("name", &enckey, [2; 32], None).unwrap();
+        assert_eq!(1, service.get_memos("name", &enckey).unwrap().len());
+
+        service.delete("name").unwrap();
+        assert!(service.get_memos("name", &enckey).unwrap().is_empty());
+    }
+}
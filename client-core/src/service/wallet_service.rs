@@ -3,14 +3,17 @@ use parity_scale_codec::{Decode, Encode, Input, Output};
 
 use crate::hd_wallet::{ChainPath, HardwareKind};
 use crate::service::{load_wallet_state, HdKey, WalletState};
-use crate::types::WalletKind;
+use crate::types::{
+    AddressType, ArchiveMark, CompliancePolicy, RecordClass, RetentionPolicy, WalletKind,
+};
+use crate::wallet::WalletLockMap;
 use chain_core::common::H256;
 use chain_core::init::address::RedeemAddress;
 use chain_core::state::account::StakedStateAddress;
 use chain_core::tx::data::address::ExtendedAddr;
 use client_common::{
     Error, ErrorKind, MultiSigAddress, PrivateKey, PublicKey, Result, ResultExt, SecKey,
-    SecureStorage, Storage,
+    SecureStorage, Storage, StorageBatch,
 };
 use secstr::SecUtf8;
 use serde::de::{self, Visitor};
@@ -19,9 +22,12 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::BTreeMap;
 use std::fmt;
 use std::str;
+use uuid::Uuid;
 
 /// Key space of wallet
 const KEYSPACE: &str = "core_wallet";
+/// Provenance tag stored for keys which were not derived by the wallet itself
+const IMPORTED_KEY_TAG: &str = "imported";
 
 fn get_public_keyspace(name: &str) -> String {
     format!("{}_{}_publickey", KEYSPACE, name)
@@ -39,6 +45,14 @@ fn get_private_keyspace(name: &str) -> String {
     format!("{}_{}_privatekey", KEYSPACE, name)
 }
 
+fn get_importedkey_keyspace(name: &str) -> String {
+    format!("{}_{}_importedkey", KEYSPACE, name)
+}
+
+fn get_keykind_keyspace(name: &str) -> String {
+    format!("{}_{}_keykind", KEYSPACE, name)
+}
+
 fn get_hdpath_keyspace(name: &str) -> String {
     format!("{}_{}_hdpath", KEYSPACE, name)
 }
@@ -55,6 +69,10 @@ pub fn get_multisig_keyspace(name: &str) -> String {
     format!("{}_{}_multisigaddress", KEYSPACE, name)
 }
 
+pub fn get_multisig_proof_keyspace(name: &str) -> String {
+    format!("{}_{}_multisigproof", KEYSPACE, name)
+}
+
 fn get_info_keyspace(name: &str) -> String {
     format!("{}_{}_info", KEYSPACE, name)
 }
@@ -63,6 +81,10 @@ fn get_wallet_keyspace() -> String {
     format!("{}_walletname", KEYSPACE)
 }
 
+fn get_walletid_keyspace() -> String {
+    format!("{}_walletid", KEYSPACE)
+}
+
 fn serde_to_str<T, S>(value: &T, serializer: S) -> std::result::Result<S::Ok, S::Error>
 where
     T: Encode,
@@ -106,6 +128,9 @@ where
 pub struct WalletInfo {
     /// name of the the wallet
     pub name: String,
+    /// stable identifier of the wallet (`None` in the exports made before wallets had one)
+    #[serde(default)]
+    pub wallet_id: Option<String>,
     /// wallet meta data
     #[serde(deserialize_with = "deserde_from_str", serialize_with = "serde_to_str")]
     pub wallet: Wallet,
@@ -117,7 +142,7 @@ pub struct WalletInfo {
     ///public_key -> encoded private_key pairs, private key is None for hardware wallet
     #[serde(deserialize_with = "deserde_from_str", serialize_with = "serde_to_str")]
     pub key_pairs: BTreeMap<PublicKey, PrivateKey>,
-    /// public_key -> hd path pairs for hardware wallet
+    /// public_key -> hd path pairs for hd wallet and hardware wallet
     #[serde(deserialize_with = "deserde_from_str", serialize_with = "serde_to_str")]
     pub key_chainpath: BTreeMap<PublicKey, String>,
     /// hdkey for hd wallet and hw wallet
@@ -130,6 +155,14 @@ pub struct WalletInfo {
     /// staking keys
     #[serde(deserialize_with = "deserde_from_str", serialize_with = "serde_to_str")]
     pub staking_keys: Vec<PublicKey>,
+    /// public keys of externally generated private keys, which can not be recovered from the
+    /// wallet seed (their private keys are included in `key_pairs`)
+    #[serde(
+        default,
+        deserialize_with = "deserde_from_str",
+        serialize_with = "serde_to_str"
+    )]
+    pub imported_keys: Vec<PublicKey>,
 }
 
 use std::sync::{Arc, Mutex};
@@ -562,9 +595,15 @@ fn generate_page(all_items_count: u64, offset: u64, limit: u64, reversed: bool)
 }
 
 /// Maintains mapping `wallet-name -> wallet-details`
+///
+/// The read-modify-write updates of a wallet (appending keys and addresses to its indexed
+/// records) are serialized per wallet, so that the threads sharing the service (or its clones)
+/// can't lose one of them. The callers of the `batch_*` methods hold the exclusive guard of the
+/// wallet (on the same locks, see `with_wallet_locks`) until the batch is written.
 #[derive(Debug, Default, Clone)]
 pub struct WalletService<T: Storage> {
     storage: T,
+    locks: WalletLockMap,
 }
 
 impl<T> WalletService<T>
@@ -573,7 +612,17 @@ where
 {
     /// Creates a new instance of wallet service
     pub fn new(storage: T) -> Self {
-        WalletService { storage }
+        WalletService {
+            storage,
+            locks: WalletLockMap::new(),
+        }
+    }
+
+    /// Shares the wallet locks with the wallet client (and the other services on the storage), so
+    /// that the updates of a wallet are serialized with the ones of the client
+    pub fn with_wallet_locks(mut self, locks: WalletLockMap) -> Self {
+        self.locks = locks;
+        self
     }
 
     /// Get the wallet info from storage
@@ -741,6 +790,7 @@ where
         wallet_kind: WalletKind,
         hardware_kind: HardwareKind,
     ) -> Result<()> {
+        let _guard = self.locks.exclusive(name);
         if self.storage.contains_key(KEYSPACE, name)? {
             return Err(Error::new(
                 ErrorKind::InvalidInput,
@@ -758,10 +808,68 @@ where
         );
         newone.wallet_storage = Some(Arc::new(Mutex::new(WalletStorageImpl::new(newstorage))));
         self.set_wallet(name, enckey, newone)?;
+        self.assign_wallet_id(name, None)?;
 
         Ok(())
     }
 
+    /// Returns the stable identifier of the wallet, which (unlike its name) is never reused by
+    /// another wallet. The wallets created before they had one are assigned one here.
+    pub fn wallet_id(&self, name: &str) -> Result<String> {
+        if !self.storage.contains_key(get_wallet_keyspace(), name)? {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Wallet with name ({}) not found", name),
+            ));
+        }
+        match read_string(&self.storage, &get_info_keyspace(name), "walletid") {
+            Ok(wallet_id) => Ok(wallet_id),
+            Err(_) => self.assign_wallet_id(name, None),
+        }
+    }
+
+    /// Returns the name of the wallet with given identifier
+    pub fn name_by_id(&self, wallet_id: &str) -> Result<Option<String>> {
+        let walletid_keyspace = get_walletid_keyspace();
+        if !self.storage.contains_key(&walletid_keyspace, wallet_id)? {
+            return Ok(None);
+        }
+        read_string(&self.storage, &walletid_keyspace, wallet_id).map(Some)
+    }
+
+    /// Assigns an identifier to the wallet: `wallet_id` (e.g. the one of an imported backup) if
+    /// no other wallet has it, a new one otherwise
+    pub fn assign_wallet_id(&self, name: &str, wallet_id: Option<&str>) -> Result<String> {
+        let wallet_id = match wallet_id {
+            Some(wallet_id) if self.name_by_id(wallet_id)?.is_none() => wallet_id.to_owned(),
+            _ => Uuid::new_v4().to_hyphenated().to_string(),
+        };
+        let mut batch = StorageBatch::default();
+        self.batch_set_wallet_id(&mut batch, name, &wallet_id)?;
+        self.storage.write_batch(batch)?;
+        Ok(wallet_id)
+    }
+
+    /// Adds the writes which set the identifier of the wallet (moving it from another wallet, if
+    /// any) to the batch
+    pub fn batch_set_wallet_id(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        wallet_id: &str,
+    ) -> Result<()> {
+        let info_keyspace = get_info_keyspace(name);
+        let walletid_keyspace = get_walletid_keyspace();
+        if let Ok(previous_id) = read_string(&self.storage, &info_keyspace, "walletid") {
+            if previous_id != wallet_id {
+                batch.delete(&walletid_keyspace, previous_id);
+            }
+        }
+        batch.set(&info_keyspace, "walletid", wallet_id.as_bytes().to_vec());
+        batch.set(&walletid_keyspace, wallet_id, name.as_bytes().to_vec());
+        Ok(())
+    }
+
     /// Returns view key of wallet
     pub fn view_key(&self, name: &str, enckey: &SecKey) -> Result<PublicKey> {
         let _wallet_found = self.get_wallet_info(name, enckey)?;
@@ -769,6 +877,133 @@ where
         read_pubkey_enc(&self.storage, &info_keyspace, "viewkey", enckey)
     }
 
+    /// Returns number of confirmations required before synced outputs can be spent (0 by default)
+    pub fn min_confirmations(&self, name: &str, enckey: &SecKey) -> Result<u32> {
+        let _wallet_found = self.get_wallet_info(name, enckey)?;
+        let info_keyspace = get_info_keyspace(name);
+        let min_confirmations =
+            read_number(&self.storage, &info_keyspace, "minconfirmations", Some(0))?;
+        Ok(min_confirmations as u32)
+    }
+
+    /// Sets number of confirmations required before synced outputs can be spent
+    pub fn set_min_confirmations(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        min_confirmations: u32,
+    ) -> Result<()> {
+        let _wallet_found = self.get_wallet_info(name, enckey)?;
+        let info_keyspace = get_info_keyspace(name);
+        write_number(
+            &self.storage,
+            &info_keyspace,
+            "minconfirmations",
+            u64::from(min_confirmations),
+        )
+    }
+
+    /// Returns compliance policy of wallet (disabled by default)
+    pub fn compliance_policy(&self, name: &str, enckey: &SecKey) -> Result<CompliancePolicy> {
+        let _wallet_found = self.get_wallet_info(name, enckey)?;
+        let info_keyspace = get_info_keyspace(name);
+        match self.storage.get(info_keyspace, "compliancepolicy")? {
+            Some(value) => CompliancePolicy::decode(&mut value.as_slice()).chain(|| {
+                (
+                    ErrorKind::DeserializationError,
+                    "Unable to deserialize compliance policy",
+                )
+            }),
+            None => Ok(CompliancePolicy::default()),
+        }
+    }
+
+    /// Sets compliance policy of wallet
+    pub fn set_compliance_policy(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        policy: CompliancePolicy,
+    ) -> Result<()> {
+        let _wallet_found = self.get_wallet_info(name, enckey)?;
+        let info_keyspace = get_info_keyspace(name);
+        self.storage
+            .set(info_keyspace, "compliancepolicy", policy.encode())?;
+        Ok(())
+    }
+
+    /// Returns retention policy of given record class of wallet (no limit by default)
+    pub fn retention_policy(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        class: RecordClass,
+    ) -> Result<RetentionPolicy> {
+        let _wallet_found = self.get_wallet_info(name, enckey)?;
+        let info_keyspace = get_info_keyspace(name);
+        let key = format!("retentionpolicy_{}", class.key());
+        match self.storage.get(info_keyspace, key)? {
+            Some(value) => RetentionPolicy::decode(&mut value.as_slice()).chain(|| {
+                (
+                    ErrorKind::DeserializationError,
+                    "Unable to deserialize retention policy",
+                )
+            }),
+            None => Ok(RetentionPolicy::default()),
+        }
+    }
+
+    /// Sets retention policy of given record class of wallet
+    pub fn set_retention_policy(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        class: RecordClass,
+        policy: RetentionPolicy,
+    ) -> Result<()> {
+        let _wallet_found = self.get_wallet_info(name, enckey)?;
+        let info_keyspace = get_info_keyspace(name);
+        let key = format!("retentionpolicy_{}", class.key());
+        self.storage.set(info_keyspace, key, policy.encode())?;
+        Ok(())
+    }
+
+    /// Returns the latest record of given class archived from the live storage of wallet (if any)
+    pub fn archive_mark(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        class: RecordClass,
+    ) -> Result<Option<ArchiveMark>> {
+        let _wallet_found = self.get_wallet_info(name, enckey)?;
+        let info_keyspace = get_info_keyspace(name);
+        let key = format!("archivemark_{}", class.key());
+        match self.storage.get(info_keyspace, key)? {
+            Some(value) => ArchiveMark::decode(&mut value.as_slice())
+                .map(Some)
+                .chain(|| {
+                    (
+                        ErrorKind::DeserializationError,
+                        "Unable to deserialize archive mark",
+                    )
+                }),
+            None => Ok(None),
+        }
+    }
+
+    /// Adds the write of the latest record of given class archived from the live storage of
+    /// wallet to the batch
+    pub fn batch_set_archive_mark(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        class: RecordClass,
+        mark: ArchiveMark,
+    ) {
+        let key = format!("archivemark_{}", class.key());
+        batch.set(get_info_keyspace(name), key, mark.encode());
+    }
+
     /// Returns all public keys stored in a wallet
     pub fn public_keys(&self, name: &str, enckey: &SecKey) -> Result<IndexSet<PublicKey>> {
         if !self.storage.contains_key(KEYSPACE, name)? {
@@ -916,6 +1151,106 @@ where
         Ok(())
     }
 
+    /// Adds the write of a (public_key, private_key) pair of given wallet to the batch
+    pub fn batch_add_key_pairs(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        public_key: &PublicKey,
+        private_key: &PrivateKey,
+    ) -> Result<()> {
+        batch.set_secure(
+            get_private_keyspace(name),
+            public_key.serialize(),
+            &private_key.serialize(),
+            enckey,
+        )
+    }
+
+    /// Tags a public key as imported, i.e. its private key was generated outside of the wallet
+    /// and can not be regenerated from the wallet seed
+    pub fn add_imported_key(&self, name: &str, public_key: &PublicKey) -> Result<()> {
+        let mut batch = StorageBatch::default();
+        self.batch_add_imported_key(&mut batch, name, public_key);
+        self.storage.write_batch(batch)
+    }
+
+    /// Adds the tag of a public key as imported to the batch
+    pub fn batch_add_imported_key(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        public_key: &PublicKey,
+    ) {
+        // key: public_key
+        // value: provenance tag
+        batch.set(
+            get_importedkey_keyspace(name),
+            public_key.serialize(),
+            IMPORTED_KEY_TAG.as_bytes().to_vec(),
+        );
+    }
+
+    /// Checks if given public key belongs to an imported private key
+    pub fn is_imported_key(&self, name: &str, public_key: &PublicKey) -> Result<bool> {
+        self.storage
+            .contains_key(get_importedkey_keyspace(name), public_key.serialize())
+    }
+
+    /// Returns public keys of all the imported private keys in given wallet
+    pub fn imported_keys(&self, name: &str) -> Result<IndexSet<PublicKey>> {
+        let importedkey_keyspace = get_importedkey_keyspace(name);
+        let mut ret = IndexSet::new();
+        for key in self.storage.keys(importedkey_keyspace)? {
+            ret.insert(PublicKey::deserialize_from(&key)?);
+        }
+        Ok(ret)
+    }
+
+    /// Adds the tag of a public key with the kind of address it's generated for (so that it can't
+    /// be used for an address of the other kind) to the batch, which also adds the key
+    pub fn batch_set_key_kind(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        public_key: &PublicKey,
+        kind: AddressType,
+    ) {
+        batch.set(
+            get_keykind_keyspace(name),
+            public_key.serialize(),
+            kind.to_string().into_bytes(),
+        );
+    }
+
+    /// Returns the kind of address a public key is generated for (`None` for the keys generated
+    /// before they were tagged, which are usable for both kinds)
+    pub fn key_kind(&self, name: &str, public_key: &PublicKey) -> Result<Option<AddressType>> {
+        self.storage
+            .get(get_keykind_keyspace(name), public_key.serialize())?
+            .map(|tag| {
+                str::from_utf8(&tag)
+                    .chain(|| (ErrorKind::DeserializationError, "Invalid key kind tag"))?
+                    .parse()
+            })
+            .transpose()
+    }
+
+    /// Fails if the public key is tagged with another kind of address than `kind`
+    fn ensure_key_kind(&self, name: &str, public_key: &PublicKey, kind: AddressType) -> Result<()> {
+        match self.key_kind(name, public_key)? {
+            Some(key_kind) if key_kind != kind => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Public key is generated for {} addresses, it can't be used for a {} address",
+                    key_kind, kind
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+
     /// Adds a (public_key, hd_path) pair to given wallet
     pub fn add_key_path(
         &self,
@@ -934,7 +1269,24 @@ where
         Ok(())
     }
 
-    /// Adds a public key to given wallet
+    /// Adds the write of a (public_key, hd_path) pair of given wallet to the batch
+    pub fn batch_add_key_path(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        public_key: &PublicKey,
+        hd_path: &ChainPath,
+    ) -> Result<()> {
+        batch.set_secure(
+            get_hdpath_keyspace(name),
+            public_key.serialize(),
+            &hd_path.clone().encode(),
+            enckey,
+        )
+    }
+
+    /// Adds a public key to given wallet (unless it's generated for staking addresses)
     // TODO: change api not to use _enckey
     pub fn add_public_key(
         &self,
@@ -942,6 +1294,8 @@ where
         _enckey: &SecKey,
         public_key: &PublicKey,
     ) -> Result<()> {
+        let _guard = self.locks.exclusive(name);
+        self.ensure_key_kind(name, public_key, AddressType::Transfer)?;
         let public_keyspace = get_public_keyspace(name);
         let info_keyspace = get_info_keyspace(name);
 
@@ -963,7 +1317,37 @@ where
         Ok(())
     }
 
-    /// Adds a public key corresponding to a staking address to given wallet
+    /// Adds the writes which append public keys to given wallet to the batch (the caller
+    /// serializes the updates of the wallet until it's written)
+    pub fn batch_add_public_keys(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        public_keys: &[PublicKey],
+    ) -> Result<()> {
+        let public_keyspace = get_public_keyspace(name);
+        let info_keyspace = get_info_keyspace(name);
+
+        let mut index_value: u64 =
+            read_number(&self.storage, &info_keyspace, "publicindex", Some(0))?;
+        for public_key in public_keys {
+            batch.set(
+                &public_keyspace,
+                format!("{}", index_value),
+                public_key.serialize(),
+            );
+            index_value += 1;
+        }
+        batch.set(
+            &info_keyspace,
+            "publicindex",
+            index_value.to_le_bytes().to_vec(),
+        );
+        Ok(())
+    }
+
+    /// Adds a public key corresponding to a staking address to given wallet (unless it's
+    /// generated for transfer addresses)
     // TODO: change api not to use _enckey
     pub fn add_staking_key(
         &self,
@@ -971,6 +1355,8 @@ where
         _enckey: &SecKey,
         staking_key: &PublicKey,
     ) -> Result<()> {
+        let _guard = self.locks.exclusive(name);
+        self.ensure_key_kind(name, staking_key, AddressType::Staking)?;
         // stakingkey set
         // key: redeem address (20 bytes)
         // value: staking key (<-publickey)
@@ -1008,9 +1394,45 @@ where
         Ok(())
     }
 
+    /// Adds the writes which append staking keys to given wallet to the batch (the caller
+    /// serializes the updates of the wallet until it's written)
+    pub fn batch_add_staking_keys(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        staking_keys: &[PublicKey],
+    ) -> Result<()> {
+        let stakingkey_keyspace = get_stakingkey_keyspace(name);
+        let stakingkeyset_keyspace = get_stakingkeyset_keyspace(name);
+        let info_keyspace = get_info_keyspace(name);
+
+        let mut index_value: u64 =
+            read_number(&self.storage, &info_keyspace, "stakingkeyindex", Some(0))?;
+        for staking_key in staking_keys {
+            batch.set(
+                &stakingkeyset_keyspace,
+                RedeemAddress::from(staking_key).to_string(),
+                staking_key.serialize(),
+            );
+            batch.set(
+                &stakingkey_keyspace,
+                format!("{}", index_value),
+                staking_key.serialize(),
+            );
+            index_value += 1;
+        }
+        batch.set(
+            &info_keyspace,
+            "stakingkeyindex",
+            index_value.to_le_bytes().to_vec(),
+        );
+        Ok(())
+    }
+
     /// Adds a multi-sig address to given wallet
     // TODO: change api not to use _enckey
     pub fn add_root_hash(&self, name: &str, _enckey: &SecKey, root_hash: H256) -> Result<()> {
+        let _guard = self.locks.exclusive(name);
         // roothashset
         let roothash_keyspace = get_roothash_keyspace(name);
         let roothashset_keyspace = get_roothashset_keyspace(name);
@@ -1041,6 +1463,41 @@ where
         Ok(())
     }
 
+    /// Adds the writes which append multi-sig addresses to given wallet to the batch (the caller
+    /// serializes the updates of the wallet until it's written)
+    pub fn batch_add_root_hashes(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        root_hashes: &[H256],
+    ) -> Result<()> {
+        let roothash_keyspace = get_roothash_keyspace(name);
+        let roothashset_keyspace = get_roothashset_keyspace(name);
+        let info_keyspace = get_info_keyspace(name);
+
+        let mut index_value: u64 =
+            read_number(&self.storage, &info_keyspace, "roothashindex", Some(0))?;
+        for root_hash in root_hashes {
+            batch.set(
+                &roothash_keyspace,
+                format!("{}", index_value),
+                root_hash.to_vec(),
+            );
+            batch.set(
+                &roothashset_keyspace,
+                hex::encode(&root_hash),
+                root_hash.to_vec(),
+            );
+            index_value += 1;
+        }
+        batch.set(
+            &info_keyspace,
+            "roothashindex",
+            index_value.to_le_bytes().to_vec(),
+        );
+        Ok(())
+    }
+
     /// Retrieves names of all the stored wallets
     pub fn names(&self) -> Result<Vec<String>> {
         let wallet_keyspace = get_wallet_keyspace();
@@ -1072,44 +1529,102 @@ where
             })?;
             let name_found = read_string(&self.storage, &wallet_keyspace, &string_key)?;
 
-            self.delete_wallet_keyspace(&name_found)?;
+            let mut batch = StorageBatch::default();
+            self.batch_delete_wallet_keyspace(&mut batch, &name_found)?;
+            self.storage.write_batch(batch)?;
         }
         self.storage.clear(wallet_keyspace)?;
+        self.storage.clear(get_walletid_keyspace())?;
         self.storage.clear(KEYSPACE)?;
 
         Ok(())
     }
 
-    fn delete_wallet_keyspace(&self, name: &str) -> Result<()> {
-        self.storage.delete(KEYSPACE, name)?;
-        assert!(self.storage.get(KEYSPACE, name)?.is_none());
+    /// Adds the deletion of all the records of the wallet (wallet info, keys, HD paths and
+    /// multi-sig addresses and their cached proofs) to the batch
+    fn batch_delete_wallet_keyspace(&self, batch: &mut StorageBatch, name: &str) -> Result<()> {
+        batch.delete(KEYSPACE, name);
         let info_keyspace = get_info_keyspace(name);
+        // the identifier may have been moved to another wallet (renamed from this one)
+        if let Ok(wallet_id) = read_string(&self.storage, &info_keyspace, "walletid") {
+            if self.name_by_id(&wallet_id)?.as_deref() == Some(name) {
+                batch.delete(get_walletid_keyspace(), wallet_id);
+            }
+        }
 
-        let stakingkey_keyspace = get_stakingkey_keyspace(name);
-        let stakingkeyset_keyspace = get_stakingkeyset_keyspace(name);
-        let public_keyspace = get_public_keyspace(name);
-        let private_keyspace = get_private_keyspace(name);
-        let roothash_keyspace = get_roothash_keyspace(name);
-        let roothashset_keyspace = get_roothashset_keyspace(name);
-        let multisigaddress_keyspace = get_multisig_keyspace(name);
-        let wallet_keyspace = get_wallet_keyspace();
-        self.storage.delete(wallet_keyspace, name)?;
-        self.storage.clear(info_keyspace)?;
-        self.storage.clear(roothash_keyspace)?;
-        self.storage.clear(roothashset_keyspace)?;
-        self.storage.clear(stakingkey_keyspace)?;
-        self.storage.clear(stakingkeyset_keyspace)?;
-        self.storage.clear(public_keyspace)?;
-        self.storage.clear(private_keyspace)?;
-        self.storage.clear(multisigaddress_keyspace)?;
+        batch.delete(get_wallet_keyspace(), name);
+        for keyspace in &[
+            info_keyspace,
+            get_roothash_keyspace(name),
+            get_roothashset_keyspace(name),
+            get_stakingkey_keyspace(name),
+            get_stakingkeyset_keyspace(name),
+            get_public_keyspace(name),
+            get_private_keyspace(name),
+            get_importedkey_keyspace(name),
+            get_keykind_keyspace(name),
+            get_hdpath_keyspace(name),
+            get_multisig_keyspace(name),
+            get_multisig_proof_keyspace(name),
+        ] {
+            self.storage.batch_clear(batch, keyspace)?;
+        }
         Ok(())
     }
+
+    /// Adds the writes which re-encrypt the records of the wallet (wallet info, view key, private
+    /// keys and HD paths) with a new enckey to the batch
+    pub fn batch_change_enckey(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        if !self
+            .storage
+            .batch_reencrypt(batch, KEYSPACE, name, enckey, new_enckey)?
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Wallet with name ({}) not found", name),
+            ));
+        }
+        self.storage.batch_reencrypt(
+            batch,
+            get_info_keyspace(name),
+            "viewkey",
+            enckey,
+            new_enckey,
+        )?;
+        self.storage.batch_reencrypt_keyspace(
+            batch,
+            get_private_keyspace(name),
+            enckey,
+            new_enckey,
+        )?;
+        self.storage
+            .batch_reencrypt_keyspace(batch, get_hdpath_keyspace(name), enckey, new_enckey)
+    }
+
     /// Delete the key
-    // TODO: change api not to use _enckey
     pub fn delete(&self, name: &str, enckey: &SecKey) -> Result<Wallet> {
+        let mut batch = StorageBatch::default();
+        let wallet_found = self.batch_delete(&mut batch, name, enckey)?;
+        self.storage.write_batch(batch)?;
+        Ok(wallet_found)
+    }
+
+    /// Adds the deletion of the wallet and all its records to the batch, after verifying the
+    /// enckey (nothing is added if it's wrong)
+    pub fn batch_delete(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+    ) -> Result<Wallet> {
         let wallet_found = self.get_wallet_info(name, enckey)?;
-        self.storage.delete(KEYSPACE, name)?;
-        self.delete_wallet_keyspace(name)?;
+        self.batch_delete_wallet_keyspace(batch, name)?;
         Ok(wallet_found)
     }
 }
@@ -1198,6 +1713,72 @@ mod tests {
 
         assert_eq!(error.kind(), ErrorKind::InvalidInput);
     }
+
+    #[test]
+    fn check_concurrent_key_additions_are_all_recorded() {
+        let wallet_service = WalletService::new(MemoryStorage::default());
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let view_key = PublicKey::from(&PrivateKey::new().unwrap());
+        wallet_service
+            .create(
+                "name",
+                &enckey,
+                view_key,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+            )
+            .unwrap();
+
+        const THREADS: usize = 16;
+        const KEYS_PER_THREAD: usize = 8;
+        let handles = (0..THREADS)
+            .map(|_| {
+                let wallet_service = wallet_service.clone();
+                let enckey = enckey.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..KEYS_PER_THREAD {
+                        let public_key = PublicKey::from(&PrivateKey::new().unwrap());
+                        wallet_service
+                            .add_public_key("name", &enckey, &public_key)
+                            .unwrap();
+                        wallet_service
+                            .add_staking_key("name", &enckey, &public_key)
+                            .unwrap();
+                        wallet_service
+                            .add_root_hash("name", &enckey, [0; 32])
+                            .unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let keys = THREADS * KEYS_PER_THREAD;
+        assert_eq!(
+            keys,
+            wallet_service.public_keys("name", &enckey).unwrap().len()
+        );
+        assert_eq!(
+            keys,
+            wallet_service
+                .staking_keys("name", &enckey, 0, 0, false)
+                .unwrap()
+                .len()
+        );
+        let info_keyspace = get_info_keyspace("name");
+        assert_eq!(
+            keys as u64,
+            read_number(
+                &wallet_service.storage,
+                &info_keyspace,
+                "roothashindex",
+                None
+            )
+            .unwrap()
+        );
+    }
 }
 
 #[cfg(test)]
@@ -1235,6 +1816,7 @@ mod test {
 
         let info = WalletInfo {
             name: "test".into(),
+            wallet_id: Some("6d0b6ad4-02b5-4c1b-9f5e-8ad6d1c5a1f4".into()),
             wallet,
             private_key: PrivateKey::new().unwrap(),
             passphrase: Some("abc".into()),
@@ -1243,8 +1825,17 @@ mod test {
             hdkey: Some(HdKey::default()),
             multisig_address_pair,
             staking_keys: vec![],
+            imported_keys: vec![PublicKey::from(&PrivateKey::new().unwrap())],
         };
         let s = serde_json::to_string(&info);
         assert!(s.is_ok());
+
+        // backups exported before imported keys were tracked can still be loaded
+        let mut value: serde_json::Value = serde_json::from_str(&s.unwrap()).unwrap();
+        value.as_object_mut().unwrap().remove("imported_keys");
+        value.as_object_mut().unwrap().remove("wallet_id");
+        let info_2: WalletInfo = serde_json::from_value(value).unwrap();
+        assert!(info_2.imported_keys.is_empty());
+        assert!(info_2.wallet_id.is_none());
     }
 }
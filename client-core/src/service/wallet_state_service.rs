@@ -1,12 +1,18 @@
 use parity_scale_codec::{Decode, Encode};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use chain_core::{
+    common::Timespec,
     init::coin::{sum_coins, CoinError},
-    tx::data::{input::TxoPointer, output::TxOut, TxId},
+    tx::data::{address::ExtendedAddr, input::TxoPointer, output::TxOut, TxId},
+};
+use client_common::{
+    Error, ErrorKind, Result, ResultExt, SecKey, SecureStorage, Storage, StorageBatch,
 };
-use client_common::{Error, ErrorKind, Result, ResultExt, SecKey, SecureStorage, Storage};
 
+use super::history_index_service::{
+    batch_delete_history_index, batch_index_memento, delete_history_index, HistoryIndexService,
+};
 use crate::types::{TransactionChange, TransactionPending, WalletBalance};
 
 /// key space of wallet state
@@ -34,6 +40,7 @@ where
     /// Clears all storage
     #[inline]
     pub fn clear(&self) -> Result<()> {
+        HistoryIndexService::new(self.storage.clone()).clear()?;
         self.storage.clear(KEYSPACE)
     }
 
@@ -129,28 +136,73 @@ where
         self.get_wallet_state(name, enckey)?.get_output(input)
     }
 
-    /// Returns currently stored balance for given wallet
-    pub fn get_balance(&self, name: &str, enckey: &SecKey) -> Result<WalletBalance> {
+    /// Returns currently stored available transactions which have at least `min_confirmations`
+    /// confirmations at `current_block_height`
+    pub fn get_spendable_transactions(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        current_block_height: u64,
+        min_confirmations: u32,
+    ) -> Result<BTreeMap<TxoPointer, TxOut>> {
+        Ok(self
+            .get_wallet_state(name, enckey)?
+            .get_spendable_transactions(current_block_height, min_confirmations))
+    }
+
+    /// Returns the inputs (out of given ones) which have less than `min_confirmations` confirmations
+    /// at `current_block_height`
+    pub fn get_immature_inputs(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        inputs: &[TxoPointer],
+        current_block_height: u64,
+        min_confirmations: u32,
+    ) -> Result<Vec<TxoPointer>> {
+        let wallet_state = self.get_wallet_state(name, enckey)?;
+        Ok(inputs
+            .iter()
+            .filter(|input| {
+                wallet_state.is_immature(input, current_block_height, min_confirmations)
+            })
+            .cloned()
+            .collect())
+    }
+
+    /// Returns currently stored balance for given wallet, the outputs which are not valid yet at
+    /// `current_block_time` (time of the latest block) being counted as locked
+    pub fn get_balance(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        current_block_time: Timespec,
+    ) -> Result<WalletBalance> {
+        self.get_balance_with_confirmations(name, enckey, 0, 0, current_block_time)
+    }
+
+    /// Returns currently stored balance for given wallet, outputs with less than `min_confirmations`
+    /// confirmations at `current_block_height` are counted as immature and the ones which are not
+    /// valid yet at `current_block_time` (time of the latest block) as locked
+    pub fn get_balance_with_confirmations(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        current_block_height: u64,
+        min_confirmations: u32,
+        current_block_time: Timespec,
+    ) -> Result<WalletBalance> {
         let wallet_state = self.get_wallet_state(name, enckey)?;
         let balance = wallet_state
-            .get_balance()
+            .get_balance_with_confirmations(
+                current_block_height,
+                min_confirmations,
+                current_block_time,
+            )
             .chain(|| (ErrorKind::StorageError, "Calculate balance error"))?;
         Ok(balance)
     }
 
-    fn modify_state<F>(&self, name: &str, enckey: &SecKey, f: F) -> Result<()>
-    where
-        F: Fn(&mut WalletState) -> Result<()>,
-    {
-        self.storage
-            .fetch_and_update_secure(KEYSPACE, name, enckey, |bytes_optional| {
-                let mut wallet_state = parse_wallet_state(name, bytes_optional)?;
-                f(&mut wallet_state)?;
-                Ok(Some(wallet_state.encode()))
-            })
-            .map(|_| ())
-    }
-
     /// Applies and commits wallet state memento
     pub fn apply_memento(
         &self,
@@ -158,7 +210,24 @@ where
         enckey: &SecKey,
         memento: &WalletStateMemento,
     ) -> Result<()> {
-        self.modify_state(name, enckey, |state| state.apply_memento(memento))
+        let mut batch = StorageBatch::default();
+        batch_apply_memento(&self.storage, &mut batch, name, enckey, memento)?;
+        self.storage.write_batch(batch)
+    }
+
+    /// Adds the writes which re-encrypt the state (and the history index) of the wallet with a new
+    /// enckey to the batch
+    pub fn batch_change_enckey(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        self.storage
+            .batch_reencrypt(batch, KEYSPACE, name, enckey, new_enckey)?;
+        HistoryIndexService::new(self.storage.clone())
+            .batch_change_enckey(batch, name, enckey, new_enckey)
     }
 
     /// Deletes all the state data corresponding to a wallet
@@ -166,6 +235,7 @@ where
     pub fn delete_wallet_state(&self, name: &str, enckey: &SecKey) -> Result<()> {
         // Check if the enckey is correct
         let _ = self.get_wallet_state(name, enckey)?;
+        delete_history_index(&self.storage, name)?;
         self.storage.delete(KEYSPACE, name).map(|_| ())
     }
 
@@ -214,6 +284,32 @@ pub fn save_wallet_state<S: SecureStorage>(
     storage.save_secure(KEYSPACE, name, enckey, state)
 }
 
+/// Add saving of wallet state to storage batch
+pub fn batch_save_wallet_state(
+    batch: &mut StorageBatch,
+    name: &str,
+    enckey: &SecKey,
+    state: &WalletState,
+) -> Result<()> {
+    batch.save_secure(KEYSPACE, name, enckey, state)
+}
+
+/// Add saving of wallet state with the memento applied (and the indexing of its transaction
+/// changes) to storage batch, and returns the new state
+pub fn batch_apply_memento<S: Storage>(
+    storage: &S,
+    batch: &mut StorageBatch,
+    name: &str,
+    enckey: &SecKey,
+    memento: &WalletStateMemento,
+) -> Result<WalletState> {
+    let mut state = load_wallet_state(storage, name, enckey)?.unwrap_or_default();
+    state.apply_memento(memento)?;
+    batch_save_wallet_state(batch, name, enckey, &state)?;
+    batch_index_memento(batch, name, enckey, memento)?;
+    Ok(state)
+}
+
 /// Modify wallet state atomically, and returns the new one.
 pub fn modify_wallet_state<S, F>(
     storage: &S,
@@ -234,12 +330,24 @@ where
     Ok(load_wallet_state(storage, name, enckey)?.unwrap())
 }
 
-/// Delete wallet state from storage
+/// Delete wallet state (and its history index) from storage
 pub fn delete_wallet_state<S: Storage>(storage: &S, name: &str) -> Result<()> {
+    delete_history_index(storage, name)?;
     storage.delete(KEYSPACE, name)?;
     Ok(())
 }
 
+/// Add deletion of wallet state (and its history index) to storage batch
+pub fn batch_delete_wallet_state<S: Storage>(
+    storage: &S,
+    batch: &mut StorageBatch,
+    name: &str,
+) -> Result<()> {
+    batch_delete_history_index(storage, batch, name)?;
+    batch.delete(KEYSPACE, name);
+    Ok(())
+}
+
 /// Wallet state
 #[derive(Debug, Encode, Decode)]
 pub struct WalletState {
@@ -304,8 +412,65 @@ impl WalletState {
             .collect::<Vec<_>>();
         result
     }
-    /// get the balance info
-    pub fn get_balance(&self) -> std::result::Result<WalletBalance, CoinError> {
+    /// Returns height of block in which given output appeared (if its transaction was synced)
+    pub fn get_output_height(&self, input: &TxoPointer) -> Option<u64> {
+        self.transaction_history
+            .get(&input.id)
+            .map(|change| change.block_height)
+    }
+
+    /// Returns `true` if given output has less than `min_confirmations` confirmations at
+    /// `current_block_height` (output included in block at `current_block_height` has one confirmation)
+    pub fn is_immature(
+        &self,
+        input: &TxoPointer,
+        current_block_height: u64,
+        min_confirmations: u32,
+    ) -> bool {
+        if min_confirmations == 0 {
+            return false;
+        }
+
+        match self.get_output_height(input) {
+            Some(block_height) => {
+                current_block_height + 1 < block_height + u64::from(min_confirmations)
+            }
+            // height is unknown (e.g. state synced by older version), don't lock the funds
+            None => false,
+        }
+    }
+
+    /// get available transactions which have at least `min_confirmations` confirmations at
+    /// `current_block_height`
+    pub fn get_spendable_transactions(
+        &self,
+        current_block_height: u64,
+        min_confirmations: u32,
+    ) -> BTreeMap<TxoPointer, TxOut> {
+        let mut available = self.get_available_transactions();
+        available
+            .retain(|key, _value| !self.is_immature(key, current_block_height, min_confirmations));
+        available
+    }
+
+    /// get the balance info, the outputs which are not valid yet at `current_block_time` (time of
+    /// the latest block) being counted as locked
+    pub fn get_balance(
+        &self,
+        current_block_time: Timespec,
+    ) -> std::result::Result<WalletBalance, CoinError> {
+        self.get_balance_with_confirmations(0, 0, current_block_time)
+    }
+
+    /// get the balance info, outputs with less than `min_confirmations` confirmations at
+    /// `current_block_height` are counted as immature and the ones which are not valid yet at
+    /// `current_block_time` (`valid_from` later than the time of the latest block) as locked
+    pub fn get_balance_with_confirmations(
+        &self,
+        current_block_height: u64,
+        min_confirmations: u32,
+        current_block_time: Timespec,
+    ) -> std::result::Result<WalletBalance, CoinError> {
         // pending amount
         let pending_coins = self
             .pending_transactions
@@ -315,20 +480,34 @@ impl WalletState {
 
         // unavailable amount
         let pending_inputs = self.get_pending_inputs();
-        let available_coins = self
+        let (locked_coins, unlocked_coins): (Vec<_>, Vec<_>) = self
             .unspent_transactions
             .iter()
             .filter(|(key, _value)| !pending_inputs.contains(key))
-            .map(|(_key, value)| value.value);
-        let amount_available = sum_coins(available_coins)?;
+            .partition(|(_key, value)| {
+                value
+                    .valid_from
+                    .map_or(false, |valid_from| valid_from > current_block_time)
+            });
+        let (immature_coins, available_coins): (Vec<_>, Vec<_>) =
+            unlocked_coins.into_iter().partition(|(key, _value)| {
+                self.is_immature(key, current_block_height, min_confirmations)
+            });
+        let amount_available =
+            sum_coins(available_coins.into_iter().map(|(_, value)| value.value))?;
+        let amount_immature = sum_coins(immature_coins.into_iter().map(|(_, value)| value.value))?;
+        let amount_locked = sum_coins(locked_coins.into_iter().map(|(_, value)| value.value))?;
 
         // total amount
-        let amount_total = (amount_pending + amount_available)?;
+        let amount_total =
+            (((amount_pending + amount_available)? + amount_immature)? + amount_locked)?;
 
         let wallet_balances = WalletBalance {
             total: amount_total,
             available: amount_available,
             pending: amount_pending,
+            immature: amount_immature,
+            locked: amount_locked,
         };
         Ok(wallet_balances)
     }
@@ -346,6 +525,72 @@ impl WalletState {
         self.transaction_log.push(txid);
     }
 
+    /// Returns `true` if the history record of given transaction can be archived without
+    /// affecting the balance: none of its outputs is unspent and it isn't pending
+    pub fn is_archivable(&self, txid: &TxId) -> bool {
+        !self.pending_transactions.contains_key(txid)
+            && !self
+                .unspent_transactions
+                .keys()
+                .any(|pointer| &pointer.id == txid)
+    }
+
+    /// Removes the history records of given transactions, returning the removed ones
+    pub fn remove_transaction_changes(&mut self, txids: &BTreeSet<TxId>) -> Vec<TransactionChange> {
+        let removed = txids
+            .iter()
+            .filter_map(|txid| self.transaction_history.remove(txid))
+            .collect::<Vec<_>>();
+        self.transaction_log.retain(|txid| !txids.contains(txid));
+        removed
+    }
+
+    /// Rolls back all the transactions in blocks above `block_height`: their history records and
+    /// outputs are removed, and our outputs they spent become unspent again. The state is left
+    /// unchanged if `is_our_address` fails.
+    pub fn rollback<F>(&mut self, block_height: u64, is_our_address: F) -> Result<()>
+    where
+        F: Fn(&ExtendedAddr) -> Result<bool>,
+    {
+        let removed = self
+            .transaction_history
+            .iter()
+            .filter(|(_, change)| change.block_height > block_height)
+            .map(|(txid, _)| *txid)
+            .collect::<BTreeSet<_>>();
+        if removed.is_empty() {
+            return Ok(());
+        }
+
+        let mut restored = Vec::new();
+        for change in removed
+            .iter()
+            .filter_map(|txid| self.transaction_history.get(txid))
+        {
+            for input in change.inputs.iter() {
+                if let Some(output) = &input.output {
+                    if !removed.contains(&input.pointer.id)
+                        && self.transaction_history.contains_key(&input.pointer.id)
+                        && is_our_address(&output.address)?
+                    {
+                        restored.push((input.pointer.clone(), output.clone()));
+                    }
+                }
+            }
+        }
+
+        for txid in removed.iter() {
+            self.transaction_history.remove(txid);
+        }
+        let history = &self.transaction_history;
+        self.transaction_log
+            .retain(|txid| history.contains_key(txid));
+        self.unspent_transactions
+            .retain(|pointer, _| history.contains_key(&pointer.id));
+        self.unspent_transactions.extend(restored);
+        Ok(())
+    }
+
     /// Applies a memento operation to wallet state
     fn apply_memento_operation(&mut self, memento_operation: &MementoOperation) -> Result<()> {
         match memento_operation {
@@ -432,6 +677,22 @@ impl WalletStateMemento {
         ))
     }
 
+    /// Returns the transaction changes added to memento
+    pub(crate) fn transaction_changes(&self) -> impl Iterator<Item = &TransactionChange> {
+        self.0.iter().filter_map(|operation| match operation {
+            MementoOperation::AddTransactionChange(_, change) => Some(change),
+            _ => None,
+        })
+    }
+
+    /// Returns the unspent transactions removed (i.e. spent) by memento
+    pub(crate) fn removed_unspent_transactions(&self) -> impl Iterator<Item = &TxoPointer> {
+        self.0.iter().filter_map(|operation| match operation {
+            MementoOperation::RemoveUnspentTransaction(input) => Some(input),
+            _ => None,
+        })
+    }
+
     /// Adds transaction pending info to memento
     #[inline]
     pub fn add_pending_transaction(&mut self, tx_id: TxId, tx_pending: TransactionPending) {
@@ -472,7 +733,7 @@ mod tests {
     use client_common::tendermint::types::Time;
     use client_common::{seckey::derive_enckey, storage::MemoryStorage};
 
-    use crate::types::{BalanceChange, TransactionType};
+    use crate::types::{BalanceChange, TransactionInput, TransactionType};
     use chain_core::init::coin::Coin;
 
     #[test]
@@ -508,7 +769,7 @@ mod tests {
 
         assert_eq!(
             WalletBalance::default(),
-            wallet_state_service.get_balance(name, enckey).unwrap()
+            wallet_state_service.get_balance(name, enckey, 0).unwrap()
         );
 
         // Add an unspent transaction and check if it is added
@@ -584,6 +845,7 @@ mod tests {
             block_height: 0,
             fee_paid: Fee::new(Coin::new(10).unwrap()),
             block_time: Time::from_str("2019-04-09T09:38:41.735577Z").unwrap(),
+            memo: None,
         });
 
         assert!(wallet_state_service
@@ -623,6 +885,7 @@ mod tests {
             block_height: 0,
             fee_paid: Fee::new(Coin::new(10).unwrap()),
             block_time: Time::from_str("2019-04-09T09:38:41.735577Z").unwrap(),
+            memo: None,
         });
 
         assert!(wallet_state_service
@@ -658,11 +921,13 @@ mod tests {
             .apply_memento(name, enckey, &memento)
             .unwrap();
         assert_eq!(
-            wallet_state_service.get_balance(name, enckey).unwrap(),
+            wallet_state_service.get_balance(name, enckey, 0).unwrap(),
             WalletBalance {
                 total: Coin::new(140).unwrap(),
                 available: Coin::new(140).unwrap(),
                 pending: Coin::zero(),
+                immature: Coin::zero(),
+                locked: Coin::zero(),
             }
         );
 
@@ -681,11 +946,13 @@ mod tests {
             .unwrap();
 
         assert_eq!(
-            wallet_state_service.get_balance(name, enckey).unwrap(),
+            wallet_state_service.get_balance(name, enckey, 0).unwrap(),
             WalletBalance {
                 total: Coin::new(90).unwrap(),
                 available: Coin::new(40).unwrap(),
                 pending: Coin::new(50).unwrap(),
+                immature: Coin::zero(),
+                locked: Coin::zero(),
             }
         );
 
@@ -719,11 +986,13 @@ mod tests {
             .unwrap();
         // now, we can get the balance
         assert_eq!(
-            wallet_state_service.get_balance(name, enckey).unwrap(),
+            wallet_state_service.get_balance(name, enckey, 0).unwrap(),
             WalletBalance {
                 total: Coin::new(90).unwrap(),
                 available: Coin::new(90).unwrap(),
                 pending: Coin::zero(),
+                immature: Coin::zero(),
+                locked: Coin::zero(),
             }
         );
         let unspent_tx = wallet_state_service
@@ -753,12 +1022,236 @@ mod tests {
             .apply_memento(name, enckey, &memento)
             .unwrap();
         assert_eq!(
-            wallet_state_service.get_balance(name, enckey).unwrap(),
+            wallet_state_service.get_balance(name, enckey, 0).unwrap(),
             WalletBalance {
                 total: Coin::new(140).unwrap(),
                 available: Coin::new(140).unwrap(),
                 pending: Coin::new(0).unwrap(),
+                immature: Coin::zero(),
+                locked: Coin::zero(),
             }
         );
     }
+
+    #[test]
+    fn test_immature_outputs_become_spendable() {
+        let name = "name";
+        let enckey = &derive_enckey(&SecUtf8::from("passphrase"), name).unwrap();
+        let wallet_state_service = WalletStateService::new(MemoryStorage::default());
+        let tx_pointer = |n: u8, i: usize| TxoPointer::new([n; 32], i);
+        let output =
+            |n: u8, m: u64| TxOut::new(ExtendedAddr::OrTree([n; 32]), Coin::new(m).unwrap());
+
+        // an output synced in block 10
+        let mut memento = WalletStateMemento::default();
+        memento.add_unspent_transaction(tx_pointer(0, 0), output(0, 100));
+        memento.add_transaction_change(TransactionChange {
+            transaction_id: [0; 32],
+            inputs: Vec::new(),
+            outputs: vec![output(0, 100)],
+            balance_change: BalanceChange::Incoming {
+                value: Coin::new(100).unwrap(),
+            },
+            transaction_type: TransactionType::Transfer,
+            block_height: 10,
+            fee_paid: Fee::new(Coin::zero()),
+            block_time: Time::from_str("2019-04-09T09:38:41.735577Z").unwrap(),
+            memo: None,
+        });
+        wallet_state_service
+            .apply_memento(name, enckey, &memento)
+            .unwrap();
+
+        let immature = WalletBalance {
+            total: Coin::new(100).unwrap(),
+            available: Coin::zero(),
+            pending: Coin::zero(),
+            immature: Coin::new(100).unwrap(),
+            locked: Coin::zero(),
+        };
+        let mature = WalletBalance {
+            total: Coin::new(100).unwrap(),
+            available: Coin::new(100).unwrap(),
+            pending: Coin::zero(),
+            immature: Coin::zero(),
+            locked: Coin::zero(),
+        };
+
+        // without confirmation depth, the output is spendable right away
+        assert_eq!(
+            mature,
+            wallet_state_service.get_balance(name, enckey, 0).unwrap()
+        );
+
+        // chain advances from block 10 (1 confirmation) to block 12 (3 confirmations)
+        for (current_block_height, expected) in
+            vec![(10, &immature), (11, &immature), (12, &mature)]
+        {
+            assert_eq!(
+                expected,
+                &wallet_state_service
+                    .get_balance_with_confirmations(name, enckey, current_block_height, 3, 0)
+                    .unwrap()
+            );
+            let spendable = wallet_state_service
+                .get_spendable_transactions(name, enckey, current_block_height, 3)
+                .unwrap();
+            assert_eq!(
+                expected.available,
+                sum_coins(spendable.values().map(|output| output.value)).unwrap()
+            );
+            assert_eq!(
+                expected.immature != Coin::zero(),
+                !wallet_state_service
+                    .get_immature_inputs(name, enckey, &[tx_pointer(0, 0)], current_block_height, 3)
+                    .unwrap()
+                    .is_empty()
+            );
+        }
+    }
+
+    #[test]
+    fn test_balance_breakdown() {
+        let name = "name";
+        let enckey = &derive_enckey(&SecUtf8::from("passphrase"), name).unwrap();
+        let wallet_state_service = WalletStateService::new(MemoryStorage::default());
+        let tx_pointer = |n: u8, i: usize| TxoPointer::new([n; 32], i);
+        let output =
+            |n: u8, m: u64| TxOut::new(ExtendedAddr::OrTree([n; 32]), Coin::new(m).unwrap());
+
+        // available output, output time-locked until 1000, output spent by a pending transaction
+        // (returning 30) and immature output synced in block 10
+        let mut memento = WalletStateMemento::default();
+        memento.add_unspent_transaction(tx_pointer(0, 0), output(0, 100));
+        memento.add_unspent_transaction(
+            tx_pointer(1, 0),
+            TxOut::new_with_timelock(ExtendedAddr::OrTree([1; 32]), Coin::new(200).unwrap(), 1000),
+        );
+        memento.add_unspent_transaction(tx_pointer(2, 0), output(2, 50));
+        memento.add_pending_transaction(
+            [3; 32],
+            TransactionPending {
+                used_inputs: vec![tx_pointer(2, 0)],
+                block_height: 10,
+                return_amount: Coin::new(30).unwrap(),
+            },
+        );
+        memento.add_unspent_transaction(tx_pointer(4, 0), output(4, 400));
+        memento.add_transaction_change(TransactionChange {
+            transaction_id: [4; 32],
+            inputs: Vec::new(),
+            outputs: vec![output(4, 400)],
+            balance_change: BalanceChange::Incoming {
+                value: Coin::new(400).unwrap(),
+            },
+            transaction_type: TransactionType::Transfer,
+            block_height: 10,
+            fee_paid: Fee::new(Coin::zero()),
+            block_time: Time::from_str("2019-04-09T09:38:41.735577Z").unwrap(),
+            memo: None,
+        });
+        wallet_state_service
+            .apply_memento(name, enckey, &memento)
+            .unwrap();
+
+        assert_eq!(
+            WalletBalance {
+                total: Coin::new(730).unwrap(),
+                available: Coin::new(100).unwrap(),
+                pending: Coin::new(30).unwrap(),
+                immature: Coin::new(400).unwrap(),
+                locked: Coin::new(200).unwrap(),
+            },
+            wallet_state_service
+                .get_balance_with_confirmations(name, enckey, 10, 3, 999)
+                .unwrap()
+        );
+        // the time-locked output is unlocked once its `valid_from` is reached
+        assert_eq!(
+            WalletBalance {
+                total: Coin::new(730).unwrap(),
+                available: Coin::new(300).unwrap(),
+                pending: Coin::new(30).unwrap(),
+                immature: Coin::new(400).unwrap(),
+                locked: Coin::zero(),
+            },
+            wallet_state_service
+                .get_balance_with_confirmations(name, enckey, 10, 3, 1000)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rollback_wallet_state() {
+        let tx_pointer = |n: u8, i: usize| TxoPointer::new([n; 32], i);
+        let output =
+            |n: u8, m: u64| TxOut::new(ExtendedAddr::OrTree([n; 32]), Coin::new(m).unwrap());
+        let change = |n: u8, inputs: Vec<TransactionInput>, block_height: u64| TransactionChange {
+            transaction_id: [n; 32],
+            inputs,
+            outputs: vec![output(0, 100), output(1, 100)],
+            balance_change: BalanceChange::Incoming {
+                value: Coin::new(100).unwrap(),
+            },
+            transaction_type: TransactionType::Transfer,
+            block_height,
+            fee_paid: Fee::new(Coin::zero()),
+            block_time: Time::from_str("2019-04-09T09:38:41.735577Z").unwrap(),
+            memo: None,
+        };
+        // only address `[0; 32]` belongs to the wallet
+        let is_our_address = |addr: &ExtendedAddr| Ok(addr == &ExtendedAddr::OrTree([0; 32]));
+
+        // tx 1 in block 5, tx 2 in block 7 spends both outputs of tx 1
+        let mut state = WalletState::default();
+        let mut memento = WalletStateMemento::default();
+        memento.add_transaction_change(change(1, vec![], 5));
+        memento.add_unspent_transaction(tx_pointer(1, 0), output(0, 100));
+        memento.add_transaction_change(change(
+            2,
+            vec![
+                TransactionInput {
+                    pointer: tx_pointer(1, 0),
+                    output: Some(output(0, 100)),
+                },
+                TransactionInput {
+                    pointer: tx_pointer(1, 1),
+                    output: Some(output(1, 100)),
+                },
+            ],
+            7,
+        ));
+        memento.remove_unspent_transaction(tx_pointer(1, 0));
+        memento.add_unspent_transaction(tx_pointer(2, 0), output(0, 100));
+        state.apply_memento(&memento).unwrap();
+
+        // nothing above block 7
+        state.rollback(7, is_our_address).unwrap();
+        assert_eq!(2, state.transaction_history.len());
+        assert_eq!(
+            vec![tx_pointer(2, 0)],
+            state
+                .unspent_transactions
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+        );
+
+        state.rollback(6, is_our_address).unwrap();
+        assert_eq!(vec![[1; 32]], state.transaction_log);
+        assert!(state.get_transaction_change(&[2; 32]).is_none());
+        assert_eq!(
+            vec![tx_pointer(1, 0)],
+            state
+                .unspent_transactions
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+        );
+
+        state.rollback(0, is_our_address).unwrap();
+        assert!(state.transaction_history.is_empty());
+        assert!(state.transaction_log.is_empty());
+        assert!(state.unspent_transactions.is_empty());
+    }
 }
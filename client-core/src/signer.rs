@@ -1,10 +1,12 @@
 //! Transaction signing
 mod dummy_signer;
+mod external_signer;
 mod key_pair_signer;
 mod unauthorized_signer;
 mod wallet_signer;
 
 pub use dummy_signer::DummySigner;
+pub use external_signer::{ExternalSignFn, ExternalSigner};
 pub use key_pair_signer::KeyPairSigner;
 pub use unauthorized_signer::UnauthorizedSigner;
 pub use wallet_signer::{WalletSigner, WalletSignerManager};
@@ -7,7 +7,7 @@ use chain_core::state::account::{
     WithdrawUnbondedTx,
 };
 use chain_core::state::tendermint::BlockHeight;
-use chain_core::tx::data::input::{TxoPointer, TxoSize};
+use chain_core::tx::data::input::TxoSize;
 use chain_core::tx::data::{Tx, TxId};
 use chain_core::tx::witness::tree::RawXOnlyPubkey;
 use chain_core::tx::witness::{TxInWitness, TxWitness};
@@ -86,10 +86,10 @@ impl DummySigner {
         let plain_payload = PlainTxAux::DepositStakeTx(witness);
         let padded_payload = self.pad_payload(plain_payload);
         let deposit_bond_tx = DepositBondTx {
-            inputs: vec![TxoPointer {
-                id: TxId::default(),
-                index: TxoSize::default(),
-            }],
+            inputs: inputs
+                .iter()
+                .map(|input| input.prev_txo_pointer.clone())
+                .collect(),
             to_staked_account: StakedStateAddress::BasicRedeem(RedeemAddress::default()),
             attributes: StakedStateOpAttributes::default(),
         };
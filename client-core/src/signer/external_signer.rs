@@ -0,0 +1,275 @@
+//! A signer forwarding signing requests to an external device (e.g. a Ledger or an HSM), so that
+//! the private keys never enter the process
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::Arc;
+
+use chain_core::common::H256;
+use chain_core::tx::data::address::ExtendedAddr;
+use chain_core::tx::witness::{TxInWitness, TxWitness};
+use chain_tx_validation::witness::verify_tx_address;
+use client_common::{Error, ErrorKind, Result, Transaction};
+
+use crate::{SelectedUnspentTransactions, SignCondition, Signer};
+
+/// Callback signing a transaction id with the keys of given addresses (one witness per address,
+/// in the same order)
+pub type ExternalSignFn = dyn Fn(H256, Vec<ExtendedAddr>) -> Result<TxWitness> + Send + Sync;
+
+/// Signer forwarding signing requests (the transaction id and the addresses of the spent outputs)
+/// to a callback, which can sign outputs of the given addresses only
+#[derive(Clone)]
+pub struct ExternalSigner {
+    addresses: BTreeSet<ExtendedAddr>,
+    sign: Arc<ExternalSignFn>,
+}
+
+impl fmt::Debug for ExternalSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExternalSigner")
+            .field("addresses", &self.addresses)
+            .finish()
+    }
+}
+
+impl ExternalSigner {
+    /// Creates a new signer of the outputs of `addresses` with the `sign` callback
+    pub fn new<F>(addresses: impl IntoIterator<Item = ExtendedAddr>, sign: F) -> Self
+    where
+        F: Fn(H256, Vec<ExtendedAddr>) -> Result<TxWitness> + Send + Sync + 'static,
+    {
+        Self {
+            addresses: addresses.into_iter().collect(),
+            sign: Arc::new(sign),
+        }
+    }
+
+    /// Forwards the signing request to the callback and checks that the witnesses it returns
+    /// verify against the transaction id
+    fn forward(&self, tx: &Transaction, addresses: Vec<ExtendedAddr>) -> Result<TxWitness> {
+        if let Some(address) = addresses
+            .iter()
+            .find(|address| !self.addresses.contains(address))
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Output's address ({}) can't be signed by the external signer",
+                    address
+                ),
+            ));
+        }
+
+        let tx_id = tx.id();
+        let witness = (self.sign)(tx_id, addresses.clone())?;
+        if witness.len() != addresses.len() {
+            return Err(Error::new(
+                ErrorKind::VerifyError,
+                format!(
+                    "External signer returned {} witnesses for {} inputs",
+                    witness.len(),
+                    addresses.len()
+                ),
+            ));
+        }
+        for (witness, address) in witness.iter().zip(addresses.iter()) {
+            verify_tx_address(witness, &tx_id, address).map_err(|err| {
+                Error::new(
+                    ErrorKind::VerifyError,
+                    format!("Incorrect signature of the external signer: {}", err),
+                )
+            })?;
+        }
+
+        Ok(witness)
+    }
+}
+
+impl Signer for ExternalSigner {
+    fn schnorr_sign_transaction(
+        &self,
+        tx: &Transaction,
+        selected_unspent_transactions: &SelectedUnspentTransactions<'_>,
+    ) -> Result<TxWitness> {
+        let addresses = selected_unspent_transactions
+            .iter()
+            .map(|(_, output)| output.address.clone())
+            .collect();
+        self.forward(tx, addresses)
+    }
+
+    fn schnorr_sign_condition(&self, signing_addr: &ExtendedAddr) -> Result<SignCondition> {
+        if self.addresses.contains(signing_addr) {
+            Ok(SignCondition::SingleSignUnlock)
+        } else {
+            Ok(SignCondition::Impossible)
+        }
+    }
+
+    fn schnorr_sign(&self, tx: &Transaction, signing_addr: &ExtendedAddr) -> Result<TxInWitness> {
+        let mut witness = self.forward(tx, vec![signing_addr.clone()])?;
+        Ok(witness.remove(0))
+    }
+}
+
+#[cfg(test)]
+mod external_signer_tests {
+    use super::*;
+    use rand::rngs::OsRng;
+    use secp256k1::schnorrsig::schnorr_sign;
+    use secp256k1::{Message, SecretKey};
+
+    use chain_core::init::coin::Coin;
+    use chain_core::tx::data::attribute::TxAttributes;
+    use chain_core::tx::data::input::TxoPointer;
+    use chain_core::tx::data::output::TxOut;
+    use chain_core::tx::data::Tx;
+    use chain_core::tx::fee::{LinearFee, Milli};
+    use client_common::seckey::derive_enckey;
+    use client_common::storage::MemoryStorage;
+    use client_common::{MultiSigAddress, PrivateKey, PublicKey};
+    use secstr::SecUtf8;
+
+    use crate::service::HwKeyService;
+    use crate::signer::WalletSignerManager;
+    use crate::transaction_builder::RawTransferTransactionBuilder;
+    use crate::UnspentTransactions;
+
+    /// Device holding its private key in memory, which signs through the callback
+    fn device() -> (ExtendedAddr, ExternalSigner) {
+        let private_key = PrivateKey::new().unwrap();
+        let public_key = PublicKey::from(&private_key);
+        let multi_sig_address =
+            MultiSigAddress::new(vec![public_key.clone()], public_key.clone(), 1).unwrap();
+        let proof = multi_sig_address
+            .generate_proof(vec![public_key])
+            .unwrap()
+            .unwrap();
+        let address = ExtendedAddr::from(multi_sig_address);
+        let secret_key = SecretKey::from_slice(&private_key.serialize()).unwrap();
+
+        let device_address = address.clone();
+        let signer = ExternalSigner::new(vec![address.clone()], move |tx_id, addresses| {
+            let message = Message::from_slice(&tx_id).unwrap();
+            addresses
+                .iter()
+                .map(|address| {
+                    assert_eq!(&device_address, address);
+                    Ok(TxInWitness::TreeSig(
+                        schnorr_sign(secp256k1::SECP256K1, &message, &secret_key, &mut OsRng),
+                        proof.clone(),
+                    ))
+                })
+                .collect()
+        });
+        (address, signer)
+    }
+
+    fn unspent_transactions(address: &ExtendedAddr) -> UnspentTransactions {
+        UnspentTransactions::new(vec![
+            (
+                TxoPointer::new([1; 32], 0),
+                TxOut::new(address.clone(), Coin::new(100).unwrap()),
+            ),
+            (
+                TxoPointer::new([2; 32], 1),
+                TxOut::new(address.clone(), Coin::new(200).unwrap()),
+            ),
+        ])
+    }
+
+    #[test]
+    fn check_external_signing_flow() {
+        let (address, signer) = device();
+        let tx = Transaction::TransferTransaction(Tx::new());
+
+        let witness = signer.schnorr_sign(&tx, &address).unwrap();
+        assert!(verify_tx_address(&witness, &tx.id(), &address).is_ok());
+
+        let unspent_transactions = unspent_transactions(&address);
+        let witness = signer
+            .schnorr_sign_transaction(&tx, &unspent_transactions.select_all())
+            .unwrap();
+        assert_eq!(2, witness.len());
+        for witness in witness.iter() {
+            assert!(verify_tx_address(witness, &tx.id(), &address).is_ok());
+        }
+
+        // the callback isn't called for the addresses of other keys
+        let foreign = ExtendedAddr::OrTree([0; 32]);
+        assert!(SignCondition::Impossible == signer.schnorr_sign_condition(&foreign).unwrap());
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            signer.schnorr_sign(&tx, &foreign).unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn check_incorrect_external_witnesses_are_rejected() {
+        let (address, honest) = device();
+        let other_tx = Transaction::TransferTransaction(Tx {
+            attributes: TxAttributes::new(171),
+            ..Tx::new()
+        });
+
+        // witnesses of another transaction
+        let signer = ExternalSigner::new(vec![address.clone()], move |_, addresses| {
+            let witness = honest.schnorr_sign(&other_tx, &addresses[0])?;
+            Ok(vec![witness; addresses.len()].into())
+        });
+        let tx = Transaction::TransferTransaction(Tx::new());
+        assert_eq!(
+            ErrorKind::VerifyError,
+            signer.schnorr_sign(&tx, &address).unwrap_err().kind()
+        );
+
+        // missing witnesses
+        let signer = ExternalSigner::new(vec![address.clone()], |_, _| Ok(TxWitness::new()));
+        assert_eq!(
+            ErrorKind::VerifyError,
+            signer
+                .schnorr_sign_transaction(&tx, &unspent_transactions(&address).select_all())
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn check_wallet_signers_forward_to_external_signer() {
+        let (address, signer) = device();
+        let signer_manager = WalletSignerManager::with_external_signer(
+            MemoryStorage::default(),
+            HwKeyService::default(),
+            signer,
+        );
+        // the wallet doesn't hold any key
+        let enckey = derive_enckey(&SecUtf8::from("passphrase"), "name").unwrap();
+        let create_signer =
+            || signer_manager.create_signer("name", &enckey, &signer_manager.hw_key_service);
+
+        // as the transaction builder does
+        let fee_algorithm =
+            LinearFee::new(Milli::try_new(1, 1).unwrap(), Milli::try_new(1, 1).unwrap());
+        let mut raw_builder =
+            RawTransferTransactionBuilder::new(TxAttributes::new(171), fee_algorithm);
+        for unspent_transaction in unspent_transactions(&address).iter() {
+            raw_builder.add_input(unspent_transaction.clone(), 1);
+        }
+        raw_builder.add_output(TxOut::new(
+            ExtendedAddr::OrTree([0; 32]),
+            Coin::new(250).unwrap(),
+        ));
+        raw_builder.sign_all(create_signer()).unwrap();
+        assert!(raw_builder.is_completed());
+
+        // as the network operations client does
+        let tx = raw_builder.to_transaction();
+        let unspent_transactions = unspent_transactions(&address);
+        let witness = create_signer()
+            .schnorr_sign_transaction(&tx, &unspent_transactions.select_all())
+            .unwrap();
+        for witness in witness.iter() {
+            assert!(verify_tx_address(witness, &tx.id(), &address).is_ok());
+        }
+    }
+}
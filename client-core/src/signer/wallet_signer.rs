@@ -6,7 +6,9 @@ use client_common::Transaction;
 use client_common::{Error, ErrorKind, Result, ResultExt, SecKey, Storage};
 
 use crate::service::{HwKeyService, KeyService, RootHashService, WalletService};
+use crate::signer::ExternalSigner;
 use crate::types::WalletKind;
+use crate::wallet::WalletLockMap;
 use crate::{SelectedUnspentTransactions, SignCondition, Signer};
 
 /// Wallet signer manager responsible for creating wallet signers
@@ -20,6 +22,19 @@ where
     key_service: KeyService<S>,
     root_hash_service: RootHashService<S>,
     wallet_service: WalletService<S>,
+    storage: S,
+    external_signer: Option<ExternalSigner>,
+}
+
+impl<S> WalletSignerManager<S>
+where
+    S: Storage,
+{
+    /// Storage of the signed wallets
+    #[inline]
+    pub(crate) fn storage(&self) -> &S {
+        &self.storage
+    }
 }
 
 impl<S> WalletSignerManager<S>
@@ -32,7 +47,29 @@ where
             hw_key_service,
             key_service: KeyService::new(storage.clone()),
             root_hash_service: RootHashService::new(storage.clone()),
-            wallet_service: WalletService::new(storage),
+            wallet_service: WalletService::new(storage.clone()),
+            storage,
+            external_signer: None,
+        }
+    }
+
+    /// Shares the wallet locks of the wallet client with the services of the signers
+    pub fn with_wallet_locks(mut self, wallet_locks: WalletLockMap) -> Self {
+        self.key_service = self.key_service.with_wallet_locks(wallet_locks.clone());
+        self.wallet_service = self.wallet_service.with_wallet_locks(wallet_locks);
+        self
+    }
+
+    /// Create an instance of wallet signer manager whose signers forward all the signing requests
+    /// to `external_signer` (the private keys of the wallets aren't used)
+    pub fn with_external_signer(
+        storage: S,
+        hw_key_service: HwKeyService,
+        external_signer: ExternalSigner,
+    ) -> Self {
+        Self {
+            external_signer: Some(external_signer),
+            ..Self::new(storage, hw_key_service)
         }
     }
 
@@ -43,13 +80,16 @@ where
         enckey: &'a SecKey,
         hw_key_service: &'a HwKeyService,
     ) -> WalletSigner<'a, S> {
-        WalletSigner::new(
-            name,
-            enckey,
-            &self.root_hash_service,
-            &self.wallet_service,
-            hw_key_service,
-        )
+        WalletSigner {
+            external_signer: self.external_signer.as_ref(),
+            ..WalletSigner::new(
+                name,
+                enckey,
+                &self.root_hash_service,
+                &self.wallet_service,
+                hw_key_service,
+            )
+        }
     }
 }
 
@@ -63,6 +103,7 @@ where
     root_hash_service: &'a RootHashService<S>,
     wallet_service: &'a WalletService<S>,
     hw_key_service: &'a HwKeyService,
+    external_signer: Option<&'a ExternalSigner>,
 }
 
 impl<'a, S> WalletSigner<'a, S>
@@ -83,6 +124,7 @@ where
             root_hash_service,
             wallet_service,
             hw_key_service,
+            external_signer: None,
         }
     }
 }
@@ -96,6 +138,9 @@ where
         tx: &Transaction,
         selected_unspent_transactions: &SelectedUnspentTransactions<'_>,
     ) -> Result<TxWitness> {
+        if let Some(external_signer) = self.external_signer {
+            return external_signer.schnorr_sign_transaction(tx, selected_unspent_transactions);
+        }
         selected_unspent_transactions
             .iter()
             .map(|(_, output)| self.schnorr_sign(tx, &output.address))
@@ -104,6 +149,9 @@ where
     }
 
     fn schnorr_sign_condition(&self, signing_addr: &ExtendedAddr) -> Result<SignCondition> {
+        if let Some(external_signer) = self.external_signer {
+            return external_signer.schnorr_sign_condition(signing_addr);
+        }
         let maybe_root_hash =
             self.wallet_service
                 .find_root_hash(self.name, self.enckey, signing_addr)?;
@@ -115,6 +163,9 @@ where
     }
 
     fn schnorr_sign(&self, tx: &Transaction, signing_addr: &ExtendedAddr) -> Result<TxInWitness> {
+        if let Some(external_signer) = self.external_signer {
+            return external_signer.schnorr_sign(tx, signing_addr);
+        }
         let root_hash = self
             .wallet_service
             .find_root_hash(self.name, self.enckey, signing_addr)?
@@ -167,6 +218,12 @@ where
                     .chain(|| (ErrorKind::PermissionDenied, "can not find chain path"))?;
                 self.hw_key_service.get_sign_key(&chain_path)?
             }
+            WalletKind::Watch => {
+                return Err(Error::new(
+                    ErrorKind::OnlyWatchOnly,
+                    "Watch-only wallet can not sign transactions",
+                ))
+            }
             WalletKind::Basic | WalletKind::HD => {
                 let private_key = self
                     .wallet_service
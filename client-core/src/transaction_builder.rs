@@ -1,13 +1,20 @@
 //! Transaction builder
 mod default_wallet_transaction_builder;
+mod fee_tip;
 mod raw_transfer_transaction_builder;
+mod size_estimate;
 mod unauthorized_wallet_transaction_builder;
 
-pub use default_wallet_transaction_builder::DefaultWalletTransactionBuilder;
+pub(crate) use default_wallet_transaction_builder::select_and_build_raw_transaction;
+pub use default_wallet_transaction_builder::{
+    default_dust_threshold, DefaultWalletTransactionBuilder,
+};
+pub use fee_tip::{FeeTip, MAX_FEE_TIP_MULTIPLIER};
 pub use raw_transfer_transaction_builder::{
     RawTransferTransaction, RawTransferTransactionBuilder, SignedTransferTransaction,
     UnsignedTransferTransaction, WitnessedUTxO,
 };
+pub use size_estimate::{estimate_encoded_size, TxKind, TxTemplate};
 pub use unauthorized_wallet_transaction_builder::UnauthorizedWalletTransactionBuilder;
 
 use chain_core::init::coin::Coin;
@@ -34,11 +41,14 @@ pub trait WalletTransactionBuilder: Send + Sync + Clone {
     /// - `outputs`: Transaction outputs
     /// - `return_address`: Address to which change amount will get returned
     /// - `attributes`: Transaction attributes,
+    /// - `fee_tip`: Amount paid on top of the fee computed by the fee algorithm (only zero is
+    ///   accepted for now)
     ///
     /// # return
     /// - `TxAux`: obfuscated transaction
     /// - `Vec<TxoPointer>`: the selected inputs
     /// - `Coin`: the return amount of Coin
+    #[allow(clippy::too_many_arguments)]
     fn build_transfer_tx(
         &self,
         name: &str,
@@ -47,6 +57,50 @@ pub trait WalletTransactionBuilder: Send + Sync + Clone {
         outputs: Vec<TxOut>,
         return_address: ExtendedAddr,
         attributes: TxAttributes,
+        fee_tip: FeeTip,
+    ) -> Result<(TxAux, Vec<TxoPointer>, Coin)>;
+
+    /// Estimates the fee of a transfer transaction: selects unspent transactions the same way as
+    /// `build_transfer_tx` and returns the difference between the values of the selected inputs
+    /// and the outputs (including change), without signing the transaction
+    ///
+    /// # Attributes
+    ///
+    /// - `unspent_transactions`: Unspent transactions
+    /// - `outputs`: Transaction outputs
+    /// - `return_address`: Address to which change amount would get returned
+    /// - `attributes`: Transaction attributes,
+    fn estimate_fee(
+        &self,
+        unspent_transactions: UnspentTransactions,
+        outputs: Vec<TxOut>,
+        return_address: ExtendedAddr,
+        attributes: TxAttributes,
+    ) -> Result<Coin>;
+
+    /// Builds a transfer transaction spending all the given unspent transactions (but the ones
+    /// locked by other builds) to a single output of `to_address`, whose value is their total
+    /// value minus the fee
+    ///
+    /// # Attributes
+    ///
+    /// - `name`: Name of wallet
+    /// - `enckey`: Encryption key of wallet
+    /// - `unspent_transactions`: Unspent transactions
+    /// - `to_address`: Address to which the whole balance is sent
+    /// - `attributes`: Transaction attributes,
+    ///
+    /// # return
+    /// - `TxAux`: obfuscated transaction
+    /// - `Vec<TxoPointer>`: the spent inputs
+    /// - `Coin`: the amount sent to `to_address`
+    fn build_sweep_tx(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        unspent_transactions: UnspentTransactions,
+        to_address: ExtendedAddr,
+        attributes: TxAttributes,
     ) -> Result<(TxAux, Vec<TxoPointer>, Coin)>;
 
     /// Obfuscates given signed transaction
@@ -1,3 +1,6 @@
+use std::slice;
+use std::sync::Arc;
+
 use chain_core::init::coin::{sum_coins, Coin};
 use chain_core::tx::data::address::ExtendedAddr;
 use chain_core::tx::data::attribute::TxAttributes;
@@ -6,13 +9,18 @@ use chain_core::tx::data::output::TxOut;
 use chain_core::tx::fee::FeeAlgorithm;
 use chain_core::tx::TxAux;
 use client_common::{
-    ErrorKind, PrivateKey, Result, ResultExt, SecKey, SignedTransaction, Storage, Transaction,
-    TransactionObfuscation,
+    Error, ErrorKind, PrivateKey, Result, ResultExt, SecKey, SignedTransaction, Storage,
+    Transaction, TransactionObfuscation,
 };
 
+use crate::coin_selection::{BranchAndBound, CoinSelector};
+use crate::service::{OutputLockService, DEFAULT_OUTPUT_LOCK_TIMEOUT};
 use crate::signer::WalletSignerManager;
-use crate::transaction_builder::RawTransferTransactionBuilder;
-use crate::{SelectedUnspentTransactions, UnspentTransactions, WalletTransactionBuilder};
+use crate::transaction_builder::{
+    estimate_encoded_size, FeeTip, RawTransferTransactionBuilder, TxKind, TxTemplate,
+};
+use crate::wallet::unix_timestamp;
+use crate::{UnspentTransactions, WalletTransactionBuilder};
 use chain_core::tx::data::TxId;
 
 /// Default implementation of `TransactionBuilder`
@@ -20,13 +28,18 @@ use chain_core::tx::data::TxId;
 /// # Algorithm
 ///
 /// 1. Calculate `output_value`: Sum of all the output values.
-/// 2. Initialize `fees = 0`.
-/// 3. Select unspent transactions with `fees + output_value`.
-/// 4. Build transaction with selected unspent transactions (also add an extra output for change amount).
-/// 5. Sign transaction with dummy signer.
-/// 6. Wrap up transaction.
-/// 7. Calculate `new_fees`.
-/// 8. If `new_fees > fees`, then change `fees = new_fees` and goto step 3, otherwise return signed transaction.
+/// 2. Select unspent transactions for `output_value` with the coin selector, which accounts for the
+///    fees of the projected transaction (signed with dummy signer), with or without a change output.
+///    Unspent transactions locked by other builds are skipped, and the selected ones are locked (a
+///    build racing for the same outputs selects again). Unspent transactions worth less than the
+///    fee they add to the transaction are skipped as well.
+/// 3. Build transaction with selected unspent transactions (also add an extra output for change
+///    amount, if any). If the change is below the dust threshold (by default, the fee which
+///    spending an output adds to a transaction, see `default_dust_threshold`), unspent
+///    transactions which exactly match the outputs and the fee are selected instead, if any (the
+///    chain requires the fee to be exactly the minimum fee, so the dust can't go to the fee).
+/// 4. Sign transaction (the selected unspent transactions are unlocked if it fails).
+/// 5. Wrap up transaction.
 ///
 #[derive(Debug, Clone)]
 pub struct DefaultWalletTransactionBuilder<S, F, O>
@@ -38,6 +51,9 @@ where
     signer_manager: WalletSignerManager<S>,
     fee_algorithm: F,
     transaction_obfuscation: O,
+    coin_selector: Arc<dyn CoinSelector>,
+    output_lock_service: OutputLockService<S>,
+    dust_threshold: Coin,
 }
 
 impl<F, S, O> DefaultWalletTransactionBuilder<S, F, O>
@@ -58,34 +74,81 @@ where
         attributes: TxAttributes,
         // FIXME: this should be per unspent_transactions
         threshold: u16,
+        fee_tip: FeeTip,
     ) -> Result<(TxAux, Vec<TxoPointer>, Coin)> {
-        let mut raw_builder = self.select_and_build(
-            &unspent_transactions,
-            outputs,
-            return_address.clone(),
-            attributes,
-            threshold,
-        )?;
+        let (tx_aux, selected_inputs, raw_builder) =
+            self.build_locked(name, enckey, unspent_transactions, |unspent_transactions| {
+                self.select_and_build(
+                    unspent_transactions,
+                    outputs.clone(),
+                    return_address.clone(),
+                    attributes.clone(),
+                    threshold,
+                    fee_tip,
+                )
+            })?;
 
-        let selected_inputs: Vec<TxoPointer> = raw_builder
-            .iter_inputs()
-            .map(|witness_utxo| witness_utxo.prev_txo_pointer.clone())
-            .collect();
         let return_amount = raw_builder
             .iter_outputs()
             .find(|&m| m.address == return_address)
             .map(|output| output.value)
             .unwrap_or_default();
 
+        Ok((tx_aux, selected_inputs, return_amount))
+    }
+
+    /// Builds a transaction (with `build`) from the unspent transactions which aren't locked by
+    /// other builds, locks its inputs and signs it (its inputs are unlocked if signing fails)
+    fn build_locked<B>(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        mut unspent_transactions: UnspentTransactions,
+        build: B,
+    ) -> Result<(TxAux, Vec<TxoPointer>, RawTransferTransactionBuilder<F>)>
+    where
+        B: Fn(&UnspentTransactions) -> Result<RawTransferTransactionBuilder<F>>,
+    {
+        let now = unix_timestamp()?;
+        let (mut raw_builder, selected_inputs) = loop {
+            let locked_outputs = self.output_lock_service.get_locked(name, enckey, now)?;
+            unspent_transactions.retain(|(pointer, _)| !locked_outputs.contains(pointer));
+
+            let raw_builder = build(&unspent_transactions)?;
+            let selected_inputs: Vec<TxoPointer> = raw_builder
+                .iter_inputs()
+                .map(|witness_utxo| witness_utxo.prev_txo_pointer.clone())
+                .collect();
+
+            match self.output_lock_service.lock(
+                name,
+                enckey,
+                &selected_inputs,
+                now,
+                DEFAULT_OUTPUT_LOCK_TIMEOUT,
+            ) {
+                // another build locked some of the selected outputs in the meantime
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+                result => result?,
+            }
+            break (raw_builder, selected_inputs);
+        };
+
         let signer =
             self.signer_manager
                 .create_signer(name, enckey, &self.signer_manager.hw_key_service);
 
-        raw_builder.sign_all(signer)?;
-
-        let tx_aux = raw_builder.to_tx_aux(self.transaction_obfuscation.clone())?;
+        let tx_aux = raw_builder
+            .sign_all(signer)
+            .and_then(|_| raw_builder.to_tx_aux(self.transaction_obfuscation.clone()))
+            .map_err(|err| {
+                let _ = self
+                    .output_lock_service
+                    .unlock(name, enckey, &selected_inputs);
+                err
+            })?;
 
-        Ok((tx_aux, selected_inputs, return_amount))
+        Ok((tx_aux, selected_inputs, raw_builder))
     }
 }
 
@@ -103,6 +166,7 @@ where
         outputs: Vec<TxOut>,
         return_address: ExtendedAddr,
         attributes: TxAttributes,
+        fee_tip: FeeTip,
     ) -> Result<(TxAux, Vec<TxoPointer>, Coin)> {
         self.build_transfer_tx_ex(
             name,
@@ -112,9 +176,75 @@ where
             return_address,
             attributes,
             1,
+            fee_tip,
         )
     }
 
+    fn estimate_fee(
+        &self,
+        unspent_transactions: UnspentTransactions,
+        outputs: Vec<TxOut>,
+        return_address: ExtendedAddr,
+        attributes: TxAttributes,
+    ) -> Result<Coin> {
+        let raw_builder = self.select_and_build(
+            &unspent_transactions,
+            outputs,
+            return_address,
+            attributes,
+            1,
+            FeeTip::default(),
+        )?;
+
+        let input_value = sum_coins(
+            raw_builder
+                .iter_inputs()
+                .map(|witness_utxo| witness_utxo.prev_tx_out.value),
+        )
+        .chain(|| {
+            (
+                ErrorKind::IllegalInput,
+                "Sum of input values exceeds maximum allowed amount",
+            )
+        })?;
+        let output_value =
+            sum_coins(raw_builder.iter_outputs().map(|output| output.value)).chain(|| {
+                (
+                    ErrorKind::IllegalInput,
+                    "Sum of output values exceeds maximum allowed amount",
+                )
+            })?;
+        (input_value - output_value).chain(|| {
+            (
+                ErrorKind::IllegalInput,
+                "Sum of output values exceeds sum of input values",
+            )
+        })
+    }
+
+    fn build_sweep_tx(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        unspent_transactions: UnspentTransactions,
+        to_address: ExtendedAddr,
+        attributes: TxAttributes,
+    ) -> Result<(TxAux, Vec<TxoPointer>, Coin)> {
+        let (tx_aux, inputs, raw_builder) =
+            self.build_locked(name, enckey, unspent_transactions, |unspent_transactions| {
+                build_sweep_raw_transaction(
+                    &self.fee_algorithm,
+                    unspent_transactions,
+                    to_address.clone(),
+                    attributes.clone(),
+                    1,
+                )
+            })?;
+        let amount = raw_builder.output_at_index(0)?.value;
+
+        Ok((tx_aux, inputs, amount))
+    }
+
     #[inline]
     fn obfuscate(&self, signed_transaction: SignedTransaction) -> Result<TxAux> {
         self.transaction_obfuscation.encrypt(signed_transaction)
@@ -140,18 +270,30 @@ where
 {
     /// Creates a new instance of transaction builder
     #[inline]
-    pub fn new(
+    pub fn new<C: CoinSelector + 'static>(
         signer_manager: WalletSignerManager<S>,
         fee_algorithm: F,
         transaction_obfuscation: O,
+        coin_selector: C,
     ) -> Self {
         Self {
+            output_lock_service: OutputLockService::new(signer_manager.storage().clone()),
             signer_manager,
+            dust_threshold: default_dust_threshold(&fee_algorithm),
             fee_algorithm,
             transaction_obfuscation,
+            coin_selector: Arc::new(coin_selector),
         }
     }
 
+    /// Sets the threshold under which the builder looks for unspent transactions which need no
+    /// change output (`default_dust_threshold` of the fee algorithm by default)
+    #[inline]
+    pub fn with_dust_threshold(mut self, dust_threshold: Coin) -> Self {
+        self.dust_threshold = dust_threshold;
+        self
+    }
+
     /// Create a `DummySigner` which signs a transaction with dummy values for fees calculation.
     /// Returns a result of unsigned raw transfer transaction builder
     pub fn select_and_build<'a>(
@@ -162,66 +304,227 @@ where
         attributes: TxAttributes,
         // FIXME: this should be per UnspentTransactions
         threshold: u16,
+        fee_tip: FeeTip,
     ) -> Result<RawTransferTransactionBuilder<F>> {
-        let output_value = sum_coins(outputs.iter().map(|output| output.value)).chain(|| {
-            (
-                ErrorKind::IllegalInput,
-                "Sum of output values exceeds maximum allowed amount",
-            )
-        })?;
-        let mut fees = Coin::zero();
-        let raw_tx_builder = loop {
-            let (selected_unspent_txs, change_amount) =
-                unspent_transactions.select((output_value + fees).chain(|| {
-                    (
-                        ErrorKind::IllegalInput,
-                        "Sum of output values and fee exceeds maximum allowed amount",
-                    )
-                })?)?;
-            let raw_tx_builder = self.build_raw_transaction(
-                &selected_unspent_txs,
-                &outputs,
-                return_address.clone(),
-                change_amount,
-                attributes.clone(),
-                threshold,
-            );
+        select_and_build_raw_transaction(
+            &self.fee_algorithm,
+            self.coin_selector.as_ref(),
+            unspent_transactions,
+            outputs,
+            return_address,
+            attributes,
+            threshold,
+            fee_tip,
+            self.dust_threshold,
+        )
+    }
+}
 
-            let new_fees = raw_tx_builder.estimate_fee()?;
-            if new_fees > fees {
-                fees = new_fees;
-            } else {
-                break raw_tx_builder;
-            }
+/// Returns the fee which spending an output (of a single-signature address) adds to a transfer
+/// transaction with given fee algorithm: an output worth less costs more to spend than it's worth,
+/// so it's the default dust threshold of the builders. With a linear fee, it's the coefficient
+/// times the encoded size of an input and its witness.
+pub fn default_dust_threshold<F: FeeAlgorithm>(fee_algorithm: &F) -> Coin {
+    let fee = |inputs| {
+        let template = TxTemplate {
+            kind: TxKind::Transfer,
+            inputs,
+            outputs: 1,
+            view_keys: 0,
         };
-
-        Ok(raw_tx_builder)
+        estimate_encoded_size(&template)
+            .ok()
+            .and_then(|size| fee_algorithm.calculate_fee(size).ok())
+            .map(|fee| fee.to_coin())
+    };
+    match (fee(1), fee(0)) {
+        (Some(with_input), Some(without_input)) => (with_input - without_input).unwrap_or_default(),
+        _ => Coin::zero(),
     }
+}
 
-    fn build_raw_transaction(
-        &self,
-        selected_unspent_transactions: &SelectedUnspentTransactions<'_>,
-        outputs: &[TxOut],
-        return_address: ExtendedAddr,
-        change_amount: Coin,
-        attributes: TxAttributes,
-        // FIXME: this should be per SelectedUnspentTransactions
-        threshold: u16,
-    ) -> RawTransferTransactionBuilder<F> {
-        let mut raw_tx_builder =
-            RawTransferTransactionBuilder::new(attributes, self.fee_algorithm.clone());
-        for input in selected_unspent_transactions.iter() {
-            raw_tx_builder.add_input(input.clone(), threshold);
-        }
-        for output in outputs.iter() {
-            raw_tx_builder.add_output(output.clone());
+/// Selects unspent transactions for given outputs and fees (with the tip on top of them) with
+/// given coin selector (see algorithm of `DefaultWalletTransactionBuilder`) and returns unsigned
+/// raw transfer transaction builder. A change below `dust_threshold` is avoided if some unspent
+/// transactions need no change output.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn select_and_build_raw_transaction<F>(
+    fee_algorithm: &F,
+    coin_selector: &dyn CoinSelector,
+    unspent_transactions: &UnspentTransactions,
+    outputs: Vec<TxOut>,
+    return_address: ExtendedAddr,
+    attributes: TxAttributes,
+    threshold: u16,
+    fee_tip: FeeTip,
+    dust_threshold: Coin,
+) -> Result<RawTransferTransactionBuilder<F>>
+where
+    F: FeeAlgorithm + Clone,
+{
+    fee_tip.check_accepted()?;
+    let output_value = sum_coins(outputs.iter().map(|output| output.value)).chain(|| {
+        (
+            ErrorKind::IllegalInput,
+            "Sum of output values exceeds maximum allowed amount",
+        )
+    })?;
+
+    let find_inputs = |pointers: &[TxoPointer]| {
+        pointers
+            .iter()
+            .map(|pointer| {
+                unspent_transactions
+                    .iter()
+                    .find(|(unspent_pointer, _)| unspent_pointer == pointer)
+                    .cloned()
+                    .chain(|| (ErrorKind::InvalidInput, "Selected UTXO is not unspent"))
+            })
+            .collect::<Result<Vec<_>>>()
+    };
+    let fee_estimator = |pointers: &[TxoPointer], with_change: bool| {
+        // The value of the change output doesn't change the size of the transaction
+        let change_amount = if with_change {
+            Coin::max()
+        } else {
+            Coin::zero()
+        };
+        build_raw_transaction(
+            fee_algorithm,
+            &find_inputs(pointers)?,
+            &outputs,
+            return_address.clone(),
+            change_amount,
+            attributes.clone(),
+            threshold,
+        )
+        .estimate_fee()
+        .and_then(|fee| fee_tip.add_to(fee))
+    };
+
+    // The unspent transactions worth less than the fee they add are not worth spending
+    let base_fee = fee_estimator(&[], false)?;
+    let mut utxos = Vec::with_capacity(unspent_transactions.len());
+    for (pointer, output) in unspent_transactions.iter() {
+        let input_fee =
+            (fee_estimator(slice::from_ref(pointer), false)? - base_fee).unwrap_or_default();
+        if output.value >= input_fee {
+            utxos.push((pointer.clone(), output.value));
         }
-        if change_amount != Coin::zero() {
-            raw_tx_builder.add_output(TxOut::new(return_address, change_amount));
+    }
+    let mut selection = coin_selector.select(&utxos, output_value, &fee_estimator)?;
+
+    // The change below the dust threshold can't go to the fee, so look for a selection without it
+    if selection.change != Coin::zero() && selection.change < dust_threshold {
+        if let Ok(changeless) =
+            BranchAndBound::default().select(&utxos, output_value, &fee_estimator)
+        {
+            if changeless.change == Coin::zero() {
+                selection = changeless;
+            }
         }
+    }
+    let mut raw_builder = build_raw_transaction(
+        fee_algorithm,
+        &find_inputs(&selection.inputs)?,
+        &outputs,
+        return_address,
+        selection.change,
+        attributes,
+        threshold,
+    );
+    fee_tip.check(raw_builder.estimate_fee()?)?;
+    raw_builder.set_fee_tip(fee_tip.amount);
+    Ok(raw_builder)
+}
 
-        raw_tx_builder
+/// Builds an unsigned raw transfer transaction spending all the given unspent transactions to a
+/// single output of `to_address`, whose value is their total value minus the fee of the projected
+/// transaction. Fails with `ErrorKind::InsufficientBalance` if nothing would be left after the fee.
+fn build_sweep_raw_transaction<F>(
+    fee_algorithm: &F,
+    unspent_transactions: &UnspentTransactions,
+    to_address: ExtendedAddr,
+    attributes: TxAttributes,
+    threshold: u16,
+) -> Result<RawTransferTransactionBuilder<F>>
+where
+    F: FeeAlgorithm + Clone,
+{
+    let inputs = unspent_transactions.iter().cloned().collect::<Vec<_>>();
+    if inputs.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InsufficientBalance,
+            "No spendable output to sweep",
+        ));
     }
+    let total_value = sum_coins(inputs.iter().map(|(_, output)| output.value)).chain(|| {
+        (
+            ErrorKind::IllegalInput,
+            "Sum of input values exceeds maximum allowed amount",
+        )
+    })?;
+
+    // The value of the output doesn't change the size of the transaction
+    let fee = build_raw_transaction(
+        fee_algorithm,
+        &inputs,
+        &[],
+        to_address.clone(),
+        Coin::max(),
+        attributes.clone(),
+        threshold,
+    )
+    .estimate_fee()?;
+    let amount = (total_value - fee)
+        .ok()
+        .filter(|amount| *amount != Coin::zero())
+        .chain(|| {
+            (
+                ErrorKind::InsufficientBalance,
+                format!(
+                    "Fee of the sweep ({}) exceeds the spendable balance ({})",
+                    fee, total_value
+                ),
+            )
+        })?;
+
+    Ok(build_raw_transaction(
+        fee_algorithm,
+        &inputs,
+        &[],
+        to_address,
+        amount,
+        attributes,
+        threshold,
+    ))
+}
+
+fn build_raw_transaction<F>(
+    fee_algorithm: &F,
+    selected_unspent_transactions: &[(TxoPointer, TxOut)],
+    outputs: &[TxOut],
+    return_address: ExtendedAddr,
+    change_amount: Coin,
+    attributes: TxAttributes,
+    // FIXME: this should be per selected unspent transaction
+    threshold: u16,
+) -> RawTransferTransactionBuilder<F>
+where
+    F: FeeAlgorithm + Clone,
+{
+    let mut raw_tx_builder = RawTransferTransactionBuilder::new(attributes, fee_algorithm.clone());
+    for input in selected_unspent_transactions.iter() {
+        raw_tx_builder.add_input(input.clone(), threshold);
+    }
+    for output in outputs.iter() {
+        raw_tx_builder.add_output(output.clone());
+    }
+    if change_amount != Coin::zero() {
+        raw_tx_builder.add_output(TxOut::new(return_address, change_amount));
+    }
+
+    raw_tx_builder
 }
 
 #[cfg(test)]
@@ -239,9 +542,11 @@ mod default_wallet_transaction_builder_tests {
     use client_common::storage::MemoryStorage;
     use client_common::Transaction;
 
+    use crate::coin_selection::{FeeEstimator, LargestFirst, Selection};
     use crate::hd_wallet::HardwareKind;
     use crate::service::HwKeyService;
     use crate::signer::WalletSignerManager;
+    use crate::transaction_builder::MAX_FEE_TIP_MULTIPLIER;
     use crate::types::WalletKind;
     use crate::unspent_transactions::{Operation, Sorter};
     use crate::wallet::{DefaultWalletClient, WalletClient};
@@ -348,6 +653,7 @@ mod default_wallet_transaction_builder_tests {
             signer_manager,
             fee_algorithm,
             MockTransactionCipher,
+            LargestFirst,
         );
 
         let outputs = vec![TxOut::new(
@@ -364,6 +670,7 @@ mod default_wallet_transaction_builder_tests {
                 return_address,
                 attributes,
                 2,
+                FeeTip::default(),
             )
             .unwrap();
 
@@ -478,6 +785,7 @@ mod default_wallet_transaction_builder_tests {
             signer_manager,
             fee_algorithm,
             MockTransactionCipher,
+            LargestFirst,
         );
 
         let outputs = vec![TxOut::new(
@@ -495,9 +803,403 @@ mod default_wallet_transaction_builder_tests {
                     outputs,
                     return_address,
                     attributes,
+                    FeeTip::default(),
+                )
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn check_concurrent_builds_select_disjoint_inputs() {
+        let name = "name";
+        let passphrase = SecUtf8::from("passphrase");
+
+        let storage = MemoryStorage::default();
+        let wallet_client = DefaultWalletClient::new_read_only(storage.clone());
+        let (enckey, _) = wallet_client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        let unspent_transactions = UnspentTransactions::new(
+            (0..8)
+                .map(|i| {
+                    (
+                        TxoPointer::new([i; 32], 0),
+                        TxOut::new(
+                            wallet_client.new_transfer_address(name, &enckey).unwrap(),
+                            Coin::new(1000).unwrap(),
+                        ),
+                    )
+                })
+                .collect(),
+        );
+        let return_address = wallet_client.new_transfer_address(name, &enckey).unwrap();
+
+        // two builders on the same storage, each building until no unlocked output is left
+        let barrier = Arc::new(std::sync::Barrier::new(2));
+        let handles = (0..2)
+            .map(|_| {
+                let transaction_builder = DefaultWalletTransactionBuilder::new(
+                    WalletSignerManager::new(storage.clone(), HwKeyService::default()),
+                    LinearFee::new(Milli::try_new(1, 1).unwrap(), Milli::try_new(1, 1).unwrap()),
+                    MockTransactionCipher,
+                    LargestFirst,
+                );
+                let enckey = enckey.clone();
+                let unspent_transactions = unspent_transactions.clone();
+                let return_address = return_address.clone();
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    let mut selected_inputs = Vec::new();
+                    while let Ok((_, inputs, _)) = transaction_builder.build_transfer_tx(
+                        name,
+                        &enckey,
+                        unspent_transactions.clone(),
+                        vec![TxOut::new(ExtendedAddr::OrTree([9; 32]), Coin::unit())],
+                        return_address.clone(),
+                        TxAttributes::new(171),
+                        FeeTip::default(),
+                    ) {
+                        selected_inputs.extend(inputs);
+                    }
+                    selected_inputs
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let selected_inputs = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>();
+        let distinct_inputs = selected_inputs
+            .iter()
+            .collect::<std::collections::BTreeSet<_>>();
+        assert_eq!(selected_inputs.len(), distinct_inputs.len());
+        assert_eq!(unspent_transactions.len(), distinct_inputs.len());
+    }
+
+    #[test]
+    fn check_fee_tip_flow() {
+        let fee_algorithm =
+            LinearFee::new(Milli::try_new(1, 1).unwrap(), Milli::try_new(1, 1).unwrap());
+        let unspent_transactions = UnspentTransactions::new(vec![(
+            TxoPointer::new([0; 32], 0),
+            TxOut::new(ExtendedAddr::OrTree([0; 32]), Coin::new(100_000).unwrap()),
+        )]);
+        let outputs = vec![TxOut::new(
+            ExtendedAddr::OrTree([1; 32]),
+            Coin::new(60_000).unwrap(),
+        )];
+        let return_address = ExtendedAddr::OrTree([2; 32]);
+        let build = |fee_tip| {
+            select_and_build_raw_transaction(
+                &fee_algorithm,
+                &LargestFirst,
+                &unspent_transactions,
+                outputs.clone(),
+                return_address.clone(),
+                TxAttributes::new(171),
+                1,
+                fee_tip,
+                default_dust_threshold(&fee_algorithm),
+            )
+        };
+        let change = |raw_builder: &RawTransferTransactionBuilder<LinearFee>| {
+            raw_builder.output_at_index(1).unwrap().value
+        };
+
+        let untipped = build(FeeTip::default()).unwrap();
+        let base_fee = untipped.estimate_fee().unwrap();
+        assert_eq!(
+            Coin::new(40_000 - u64::from(base_fee)).unwrap(),
+            change(&untipped)
+        );
+
+        // the chain requires the exact fee, so any tip is rejected before selecting the inputs
+        let tip = Coin::new(500).unwrap();
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            build(FeeTip::new(tip)).unwrap_err().kind()
+        );
+        let excessive_tip = Coin::new(u64::from(base_fee) * MAX_FEE_TIP_MULTIPLIER + 1).unwrap();
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            build(FeeTip::forced(excessive_tip)).unwrap_err().kind()
+        );
+    }
+
+    /// Selects all the offered unspent transactions
+    #[derive(Debug)]
+    struct SelectAll;
+
+    impl CoinSelector for SelectAll {
+        fn select(
+            &self,
+            utxos: &[(TxoPointer, Coin)],
+            target: Coin,
+            fee_estimator: &dyn FeeEstimator,
+        ) -> Result<Selection> {
+            let inputs = utxos
+                .iter()
+                .map(|(pointer, _)| pointer.clone())
+                .collect::<Vec<_>>();
+            let total = sum_coins(utxos.iter().map(|(_, value)| *value)).unwrap();
+            let fee = fee_estimator.estimate_fee(&inputs, true)?;
+            Ok(Selection {
+                inputs,
+                fee,
+                change: (total - target - fee).unwrap(),
+            })
+        }
+    }
+
+    #[test]
+    fn check_dust_policy() {
+        let fee_algorithm =
+            LinearFee::new(Milli::try_new(1, 1).unwrap(), Milli::try_new(1, 1).unwrap());
+        let dust_threshold = default_dust_threshold(&fee_algorithm);
+        assert!(dust_threshold > Coin::one());
+        let return_address = ExtendedAddr::OrTree([2; 32]);
+        let build = |coin_selector: &dyn CoinSelector,
+                     values: &[u64],
+                     amount: u64,
+                     dust_threshold: Coin| {
+            let unspent_transactions = UnspentTransactions::new(
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| {
+                        (
+                            TxoPointer::new([i as u8; 32], 0),
+                            TxOut::new(ExtendedAddr::OrTree([0; 32]), Coin::new(*value).unwrap()),
+                        )
+                    })
+                    .collect(),
+            );
+            select_and_build_raw_transaction(
+                &fee_algorithm,
+                coin_selector,
+                &unspent_transactions,
+                vec![TxOut::new(
+                    ExtendedAddr::OrTree([1; 32]),
+                    Coin::new(amount).unwrap(),
+                )],
+                return_address.clone(),
+                TxAttributes::new(171),
+                1,
+                FeeTip::default(),
+                dust_threshold,
+            )
+            .unwrap()
+        };
+
+        // change above the threshold
+        let raw_builder = build(&LargestFirst, &[100_000], 60_000, dust_threshold);
+        assert_eq!(2, raw_builder.outputs_len());
+        let change = raw_builder.output_at_index(1).unwrap();
+        assert_eq!(return_address, change.address);
+        assert!(change.value >= dust_threshold);
+        let fee_with_change = 40_000 - u64::from(change.value);
+
+        let fee = |raw_builder: &RawTransferTransactionBuilder<LinearFee>| {
+            (raw_builder.total_input_amount().unwrap() - raw_builder.total_output_amount().unwrap())
+                .unwrap()
+        };
+        let fee_without_change = build_raw_transaction(
+            &fee_algorithm,
+            &[(
+                TxoPointer::new([0; 32], 0),
+                TxOut::new(ExtendedAddr::OrTree([0; 32]), Coin::new(100_000).unwrap()),
+            )],
+            &[TxOut::new(ExtendedAddr::OrTree([1; 32]), Coin::zero())],
+            return_address.clone(),
+            Coin::zero(),
+            TxAttributes::new(171),
+            1,
+        )
+        .estimate_fee()
+        .unwrap();
+
+        // change below the threshold is never folded into the fee...
+        let amount = 100_000 - fee_with_change - 1;
+        let raw_builder = build(&LargestFirst, &[100_000], amount, dust_threshold);
+        assert_eq!(2, raw_builder.outputs_len());
+        assert_eq!(Coin::one(), raw_builder.output_at_index(1).unwrap().value);
+        assert_eq!(raw_builder.estimate_fee().unwrap(), fee(&raw_builder));
+
+        // ... but avoided with unspent transactions which need no change output
+        let exact = amount + u64::from(fee_without_change);
+        let raw_builder = build(&LargestFirst, &[100_000, exact], amount, dust_threshold);
+        assert_eq!(1, raw_builder.outputs_len());
+        assert_eq!(
+            Coin::new(exact).unwrap(),
+            raw_builder.total_input_amount().unwrap()
+        );
+        assert_eq!(raw_builder.estimate_fee().unwrap(), fee(&raw_builder));
+        // unless the threshold is lower
+        let raw_builder = build(&LargestFirst, &[100_000, exact], amount, Coin::zero());
+        assert_eq!(2, raw_builder.outputs_len());
+
+        // an input worth less than the fee it adds isn't offered to the selector
+        let raw_builder = build(&SelectAll, &[100_000, 1, 20_000], 60_000, dust_threshold);
+        let inputs = raw_builder
+            .iter_inputs()
+            .map(|input| input.prev_tx_out.value)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![Coin::new(100_000).unwrap(), Coin::new(20_000).unwrap()],
+            inputs
+        );
+    }
+
+    #[test]
+    fn check_sweep_flow() {
+        let name = "name";
+        let passphrase = SecUtf8::from("passphrase");
+
+        let storage = MemoryStorage::default();
+        let wallet_client = DefaultWalletClient::new_read_only(storage.clone());
+        let (enckey, _) = wallet_client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        let addresses = vec![
+            wallet_client.new_transfer_address(name, &enckey).unwrap(),
+            wallet_client.new_transfer_address(name, &enckey).unwrap(),
+        ];
+        let unspent_transactions = UnspentTransactions::new(vec![
+            (
+                TxoPointer::new([0; 32], 0),
+                TxOut::new(addresses[0].clone(), Coin::new(500).unwrap()),
+            ),
+            (
+                TxoPointer::new([1; 32], 0),
+                TxOut::new(addresses[1].clone(), Coin::new(1000).unwrap()),
+            ),
+        ]);
+
+        let signer_manager = WalletSignerManager::new(storage, HwKeyService::default());
+        let fee_algorithm =
+            LinearFee::new(Milli::try_new(1, 1).unwrap(), Milli::try_new(1, 1).unwrap());
+        let transaction_builder = DefaultWalletTransactionBuilder::new(
+            signer_manager,
+            fee_algorithm,
+            MockTransactionCipher,
+            LargestFirst,
+        );
+
+        let to_address = ExtendedAddr::OrTree([9; 32]);
+        let (tx_aux, inputs, amount) = transaction_builder
+            .build_sweep_tx(
+                name,
+                &enckey,
+                unspent_transactions.clone(),
+                to_address.clone(),
+                TxAttributes::new(171),
+            )
+            .unwrap();
+        assert_eq!(2, inputs.len());
+        let fee = fee_algorithm
+            .calculate_for_txaux(&tx_aux)
+            .unwrap()
+            .to_coin();
+        assert!((amount + fee).unwrap() <= Coin::new(1500).unwrap());
+
+        match tx_aux {
+            TxAux::EnclaveTx(TxEnclaveAux::TransferTx {
+                payload: TxObfuscated { txpayload, .. },
+                ..
+            }) => match PlainTxAux::decode(&mut txpayload.as_slice()).unwrap() {
+                PlainTxAux::TransferTx(transaction, witness) => {
+                    assert_eq!(
+                        vec![TxOut::new(to_address.clone(), amount)],
+                        transaction.outputs
+                    );
+                    for (i, input) in transaction.inputs.iter().enumerate() {
+                        let (_, output) = unspent_transactions
+                            .iter()
+                            .find(|(pointer, _)| pointer == input)
+                            .unwrap();
+                        assert!(
+                            verify_tx_address(&witness[i], &transaction.id(), &output.address)
+                                .is_ok()
+                        );
+                    }
+                }
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        }
+
+        // the swept outputs are locked
+        assert_eq!(
+            ErrorKind::InsufficientBalance,
+            transaction_builder
+                .build_sweep_tx(
+                    name,
+                    &enckey,
+                    unspent_transactions,
+                    to_address,
+                    TxAttributes::new(171),
                 )
                 .unwrap_err()
                 .kind()
         );
     }
+
+    #[test]
+    fn check_sweep_fee_exceeding_balance() {
+        let fee_algorithm =
+            LinearFee::new(Milli::try_new(1, 1).unwrap(), Milli::try_new(1, 1).unwrap());
+        let sweep = |values: &[u64]| {
+            let unspent_transactions = UnspentTransactions::new(
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| {
+                        (
+                            TxoPointer::new([i as u8; 32], 0),
+                            TxOut::new(ExtendedAddr::OrTree([0; 32]), Coin::new(*value).unwrap()),
+                        )
+                    })
+                    .collect(),
+            );
+            build_sweep_raw_transaction(
+                &fee_algorithm,
+                &unspent_transactions,
+                ExtendedAddr::OrTree([9; 32]),
+                TxAttributes::new(171),
+                1,
+            )
+        };
+
+        let swept = sweep(&[10_000]).unwrap();
+        assert_eq!(1, swept.outputs_len());
+        let fee = (Coin::new(10_000).unwrap() - swept.output_at_index(0).unwrap().value).unwrap();
+        assert_eq!(fee, swept.estimate_fee().unwrap());
+
+        // nothing to sweep, dust only or the fee equal to the balance
+        for values in &[vec![], vec![1, 1, 1], vec![u64::from(fee)]] {
+            assert_eq!(
+                ErrorKind::InsufficientBalance,
+                sweep(values).unwrap_err().kind()
+            );
+        }
+        // a single base unit left after the fee
+        let swept = sweep(&[u64::from(fee) + 1]).unwrap();
+        assert_eq!(Coin::one(), swept.output_at_index(0).unwrap().value);
+    }
 }
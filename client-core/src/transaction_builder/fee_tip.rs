@@ -0,0 +1,119 @@
+//! Voluntary overpayment of the fee of a transaction
+use chain_core::init::coin::Coin;
+use client_common::{Error, ErrorKind, Result, ResultExt};
+
+/// Maximum tip, as a multiple of the fee computed by the fee algorithm (unless it's forced)
+pub const MAX_FEE_TIP_MULTIPLIER: u64 = 10;
+
+/// Amount paid on top of the fee computed by the fee algorithm, so that the nodes prioritizing
+/// their mempool by fee include the transaction faster
+///
+/// Only the zero tip is accepted for now (see `check_accepted`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FeeTip {
+    /// Amount of the tip
+    pub amount: Coin,
+    /// Skips the sanity cap of the tip (`MAX_FEE_TIP_MULTIPLIER` times the base fee)
+    pub force: bool,
+}
+
+impl FeeTip {
+    /// Creates a tip of given amount, bounded by the sanity cap
+    #[inline]
+    pub fn new(amount: Coin) -> Self {
+        FeeTip {
+            amount,
+            force: false,
+        }
+    }
+
+    /// Creates a tip of given amount, not bounded by the sanity cap
+    #[inline]
+    pub fn forced(amount: Coin) -> Self {
+        FeeTip {
+            amount,
+            force: true,
+        }
+    }
+
+    /// Adds the tip to the fee computed by the fee algorithm
+    pub fn add_to(&self, base_fee: Coin) -> Result<Coin> {
+        (base_fee + self.amount).chain(|| {
+            (
+                ErrorKind::IllegalInput,
+                "Fee with tip exceeds maximum allowed amount",
+            )
+        })
+    }
+
+    /// Fails if the tip isn't zero: the chain only accepts the transfer transactions whose inputs
+    /// are exactly the outputs plus the computed fee, so a tipped transaction would be rejected
+    pub fn check_accepted(&self) -> Result<()> {
+        if self.amount == Coin::zero() {
+            return Ok(());
+        }
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Fee tip {} is not accepted: the chain requires the transaction to pay the exact fee",
+                self.amount
+            ),
+        ))
+    }
+
+    /// Checks that the tip doesn't exceed the sanity cap for given base fee (unless it's forced)
+    pub fn check(&self, base_fee: Coin) -> Result<()> {
+        if self.force || self.amount == Coin::zero() {
+            return Ok(());
+        }
+        let cap = u64::from(base_fee).saturating_mul(MAX_FEE_TIP_MULTIPLIER);
+        if u64::from(self.amount) > cap {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Fee tip {} exceeds {} times the fee of the transaction ({}), it has to be forced",
+                    self.amount, MAX_FEE_TIP_MULTIPLIER, base_fee
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_fee_tip_cap() {
+        let base_fee = Coin::new(100).unwrap();
+        assert!(FeeTip::default().check_accepted().is_ok());
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            FeeTip::forced(Coin::unit())
+                .check_accepted()
+                .unwrap_err()
+                .kind()
+        );
+        assert!(FeeTip::default().check(Coin::zero()).is_ok());
+        assert!(FeeTip::new(Coin::new(1000).unwrap())
+            .check(base_fee)
+            .is_ok());
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            FeeTip::new(Coin::new(1001).unwrap())
+                .check(base_fee)
+                .unwrap_err()
+                .kind()
+        );
+        assert!(FeeTip::forced(Coin::new(1001).unwrap())
+            .check(base_fee)
+            .is_ok());
+        assert_eq!(
+            Coin::new(1100).unwrap(),
+            FeeTip::new(Coin::new(1000).unwrap())
+                .add_to(base_fee)
+                .unwrap()
+        );
+    }
+}
@@ -1,5 +1,4 @@
 //! Builder for building raw transfer transaction
-use std::ops::Sub;
 use std::slice::Iter;
 use std::str::FromStr;
 use std::string::ToString;
@@ -17,11 +16,12 @@ use chain_core::tx::{TransactionId, TxAux};
 use chain_tx_validation::witness::verify_tx_address;
 use chain_tx_validation::{check_inputs_basic, check_outputs_basic};
 use client_common::{
-    Error, ErrorKind, PublicKey, Result, ResultExt, SignedTransaction, Transaction,
+    Error, ErrorKind, PublicKey, Result, ResultExt, SignedTransaction, Transaction, TransactionFee,
     TransactionObfuscation,
 };
 
-use crate::signer::{DummySigner, SignCondition, Signer};
+use super::size_estimate::transfer_encoded_size;
+use crate::signer::{SignCondition, Signer};
 use crate::UnspentTransactions;
 use chain_core::tx::data::address::ExtendedAddr;
 
@@ -140,6 +140,7 @@ where
 {
     raw_transaction: RawTransferTransaction,
     fee_algorithm: F,
+    fee_tip: Coin,
 }
 
 impl<F> RawTransferTransactionBuilder<F>
@@ -157,9 +158,21 @@ where
         RawTransferTransactionBuilder {
             raw_transaction,
             fee_algorithm,
+            fee_tip: Coin::zero(),
         }
     }
 
+    /// Sets the amount the transaction pays on top of the fee computed by the fee algorithm (the
+    /// fee is verified to cover both)
+    pub fn set_fee_tip(&mut self, fee_tip: Coin) {
+        self.fee_tip = fee_tip;
+    }
+
+    /// Returns the amount the transaction pays on top of the fee computed by the fee algorithm
+    pub fn fee_tip(&self) -> Coin {
+        self.fee_tip
+    }
+
     /// Create an iterator over inputs
     pub fn iter_inputs(&self) -> Iter<WitnessedUTxO> {
         self.raw_transaction.inputs.iter()
@@ -312,10 +325,13 @@ where
 
         self.verify_output_does_not_exceed_input_amount()?;
 
-        let input_value = self.total_input_amount()?;
-        let output_value = self.total_output_amount()?;
-
-        Ok(input_value.sub(output_value).unwrap())
+        let fee = TransactionFee::compute(&self.to_transaction(), |pointer| {
+            self.iter_inputs()
+                .find(|input| &input.prev_txo_pointer == pointer)
+                .map(|input| input.prev_tx_out.value)
+                .chain(|| (ErrorKind::InvalidInput, "Unknown transaction input"))
+        })?;
+        Ok(fee.to_coin())
     }
 
     /// Returns required fee of completed transfer transaction according to fee
@@ -348,12 +364,10 @@ where
 
     /// Estimate transaction fee with dummy signatures
     pub fn estimate_fee(&self) -> Result<Coin> {
-        let dummy_signer = DummySigner();
-        let witness = dummy_signer.schnorr_sign_inputs_len(&self.raw_transaction.inputs)?;
-        let tx_aux = dummy_signer.mock_txaux_for_tx(self.to_tx(), witness);
+        let size = transfer_encoded_size(self.to_tx(), &self.raw_transaction.inputs)?;
         let estimated_fee = self
             .fee_algorithm
-            .calculate_for_txaux(&tx_aux)
+            .calculate_fee(size)
             .chain(|| {
                 (
                     ErrorKind::IllegalInput,
@@ -375,6 +389,13 @@ where
     where
         O: TransactionObfuscation,
     {
+        transaction_obfuscation.encrypt(self.to_signed_transaction()?)
+    }
+
+    /// Convert raw transaction to (not obfuscated) signed transaction
+    /// # Error
+    /// Returns VerifyError when the transaction is invalid
+    pub fn to_signed_transaction(&self) -> Result<SignedTransaction> {
         self.verify()?;
 
         let tx = self.to_tx();
@@ -383,9 +404,8 @@ where
             .map(|input| input.witness.clone().unwrap())
             .collect();
         let witness = TxWitness::from(witness_vec);
-        let signed_transaction = SignedTransaction::TransferTransaction(tx, witness);
 
-        transaction_obfuscation.encrypt(signed_transaction)
+        Ok(SignedTransaction::TransferTransaction(tx, witness))
     }
 
     /// Verify the raw transaction is valid
@@ -450,9 +470,14 @@ where
     }
 
     fn verify_fee(&self) -> Result<()> {
-        let fee_expected = self.estimate_fee()?;
+        let fee_expected = (self.estimate_fee()? + self.fee_tip).chain(|| {
+            (
+                ErrorKind::VerifyError,
+                "Fee with tip exceeds maximum allowed amount",
+            )
+        })?;
         let fee_in_tx = self.fee()?;
-        // FIXME: this should be !=, but unit tests don't have proper mocks
+        // the chain requires the sum of inputs to be exactly the sum of outputs plus the fee
         if fee_in_tx < fee_expected {
             let fee_gap = (fee_expected - fee_in_tx).unwrap();
             return Err(Error::new(
@@ -460,6 +485,13 @@ where
                 format!("Insufficient fee, need more {:?}", fee_gap),
             ));
         }
+        if fee_in_tx > fee_expected {
+            let fee_gap = (fee_in_tx - fee_expected).unwrap();
+            return Err(Error::new(
+                ErrorKind::VerifyError,
+                format!("Overpaid fee, need less {:?}", fee_gap),
+            ));
+        }
         Ok(())
     }
 
@@ -542,6 +574,7 @@ where
         Ok(RawTransferTransactionBuilder {
             raw_transaction,
             fee_algorithm,
+            fee_tip: Coin::zero(),
         })
     }
 }
@@ -713,6 +746,23 @@ mod raw_transfer_transaction_builder_tests {
 
             assert!(builder.verify().is_ok());
         }
+
+        #[test]
+        fn should_return_error_when_fee_is_overpaid() {
+            let (private_key, public_key, transfer_addr) = create_key_pair_and_transfer_addr();
+            let mut builder = create_2in2out_testing_raw_transaction_builder(transfer_addr);
+            builder.raw_transaction.outputs[1].value =
+                (builder.raw_transaction.outputs[1].value - Coin::unit()).unwrap();
+
+            let witness =
+                create_public_key_witness(private_key, public_key, &builder.to_transaction());
+            let _ = builder.add_witness(0, witness.clone());
+            let _ = builder.add_witness(1, witness);
+
+            let err = builder.verify().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::VerifyError);
+            assert!(err.message().starts_with("Overpaid fee"));
+        }
     }
 
     mod add_input {
@@ -1197,6 +1247,8 @@ mod raw_transfer_transaction_builder_tests {
         assert_eq!(restored_raw_transaction_builder.is_completed(), false);
     }
 
+    /// Builds a transaction spending 10001 units, whose second output is the change (i.e. it pays
+    /// exactly the estimated fee)
     fn create_2in2out_testing_raw_transaction_builder(
         transfer_addr: ExtendedAddr,
     ) -> RawTransferTransactionBuilder<LinearFee> {
@@ -1207,14 +1259,14 @@ mod raw_transfer_transaction_builder_tests {
         builder.add_input(
             (
                 TxoPointer::new(random(), 0),
-                TxOut::new(transfer_addr.clone(), Coin::new(501).unwrap()),
+                TxOut::new(transfer_addr.clone(), Coin::new(5001).unwrap()),
             ),
             1,
         );
         builder.add_input(
             (
                 TxoPointer::new(random(), 0),
-                TxOut::new(transfer_addr, Coin::new(500).unwrap()),
+                TxOut::new(transfer_addr, Coin::new(5000).unwrap()),
             ),
             1,
         );
@@ -1223,10 +1275,10 @@ mod raw_transfer_transaction_builder_tests {
             ExtendedAddr::OrTree(random()),
             Coin::new(50).unwrap(),
         ));
-        builder.add_output(TxOut::new(
-            ExtendedAddr::OrTree(random()),
-            Coin::new(250).unwrap(),
-        ));
+        builder.add_output(TxOut::new(ExtendedAddr::OrTree(random()), Coin::zero()));
+        // the encoded size (so the fee) doesn't depend on the output values
+        let fee = u64::from(builder.estimate_fee().unwrap());
+        builder.raw_transaction.outputs[1].value = Coin::new(10001 - 50 - fee).unwrap();
 
         builder
     }
@@ -0,0 +1,241 @@
+//! Encoded size of transactions, estimated from their shape (as the builders do, with dummy
+//! signatures) so that fees can be computed before the transactions are built
+use parity_scale_codec::Encode;
+use secp256k1::{PublicKey, SecretKey, SECP256K1};
+use serde::{Deserialize, Serialize};
+
+use chain_core::init::coin::Coin;
+use chain_core::state::account::WithdrawUnbondedTx;
+use chain_core::tx::data::access::{TxAccess, TxAccessPolicy};
+use chain_core::tx::data::address::ExtendedAddr;
+use chain_core::tx::data::attribute::TxAttributes;
+use chain_core::tx::data::input::{TxoPointer, TxoSize};
+use chain_core::tx::data::output::TxOut;
+use chain_core::tx::data::Tx;
+use client_common::{Error, ErrorKind, Result};
+
+use super::WitnessedUTxO;
+use crate::signer::DummySigner;
+
+/// Kind of a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TxKind {
+    /// Transfer of UTXOs
+    Transfer,
+    /// Deposit of UTXOs to a staked state
+    Deposit,
+    /// Withdrawal of the unbonded coins of a staked state to UTXOs
+    Withdraw,
+}
+
+/// Shape of a transaction, enough to know its encoded size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxTemplate {
+    /// Kind of the transaction
+    pub kind: TxKind,
+    /// Number of inputs (spending single-signature addresses; none for withdrawals)
+    pub inputs: usize,
+    /// Number of outputs (without timelock; none for deposits)
+    pub outputs: usize,
+    /// Number of view keys allowed to view the transaction (none for deposits)
+    #[serde(default)]
+    pub view_keys: usize,
+}
+
+impl TxTemplate {
+    fn check(&self) -> Result<()> {
+        let max = usize::from(TxoSize::max_value());
+        if self.inputs > max || self.outputs > max || self.view_keys > max {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Transaction template exceeds {} inputs, outputs or view keys",
+                    max
+                ),
+            ));
+        }
+
+        let consistent = match self.kind {
+            TxKind::Transfer => true,
+            TxKind::Deposit => self.outputs == 0 && self.view_keys == 0,
+            TxKind::Withdraw => self.inputs == 0,
+        };
+        if consistent {
+            Ok(())
+        } else {
+            Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Deposit transactions have no outputs (nor view keys) and withdrawal transactions have no inputs",
+            ))
+        }
+    }
+
+    fn attributes(&self) -> TxAttributes {
+        let secret_key = SecretKey::from_slice(&[1; 32]).expect("valid secret key");
+        let view_key = PublicKey::from_secret_key(SECP256K1, &secret_key);
+        TxAttributes::new_with_access(
+            0,
+            vec![TxAccessPolicy::new(view_key, TxAccess::AllData); self.view_keys],
+        )
+    }
+
+    fn outputs(&self) -> Vec<TxOut> {
+        vec![TxOut::new(ExtendedAddr::OrTree([0; 32]), Coin::zero()); self.outputs]
+    }
+}
+
+/// Returns the encoded size of the (obfuscated) transfer transaction `tx` spending `inputs`,
+/// signed with dummy signatures (i.e. the size used by the builders to estimate the fee)
+pub(crate) fn transfer_encoded_size(tx: Tx, inputs: &[WitnessedUTxO]) -> Result<usize> {
+    let dummy_signer = DummySigner();
+    let witness = dummy_signer.schnorr_sign_inputs_len(inputs)?;
+    Ok(dummy_signer.mock_txaux_for_tx(tx, witness).encode().len())
+}
+
+/// Returns the encoded size of the (obfuscated) transactions of given shape, i.e. the size from
+/// which their fee is computed
+pub fn estimate_encoded_size(template: &TxTemplate) -> Result<usize> {
+    template.check()?;
+
+    let inputs = vec![WitnessedUTxO::dummy(); template.inputs];
+    match template.kind {
+        TxKind::Transfer => {
+            let tx = Tx::new_with(
+                inputs
+                    .iter()
+                    .map(|input| input.prev_txo_pointer.clone())
+                    .collect::<Vec<TxoPointer>>(),
+                template.outputs(),
+                template.attributes(),
+            );
+            transfer_encoded_size(tx, &inputs)
+        }
+        TxKind::Deposit => Ok(DummySigner()
+            .mock_txaux_for_deposit(&inputs)?
+            .encode()
+            .len()),
+        TxKind::Withdraw => {
+            let tx = WithdrawUnbondedTx::new(0, template.outputs(), template.attributes());
+            Ok(DummySigner().mock_txaux_for_withdraw(tx).encode().len())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chain_core::tx::witness::TxWitness;
+    use chain_core::tx::{PlainTxAux, TransactionId, TxAux, TxEnclaveAux};
+    use client_common::{MultiSigAddress, PrivateKey, PublicKey, Transaction};
+    use mock_utils::encrypt;
+
+    use crate::signer::{KeyPairSigner, Signer};
+
+    /// Builds and signs a transfer transaction of given shape, and obfuscates it as the enclave
+    /// does
+    fn build_transfer_tx(inputs: usize, outputs: usize, view_keys: usize) -> TxAux {
+        let private_key = PrivateKey::new().unwrap();
+        let public_key = PublicKey::from(&private_key);
+        let address = ExtendedAddr::from(
+            MultiSigAddress::new(vec![public_key.clone()], public_key.clone(), 1).unwrap(),
+        );
+        let signer = KeyPairSigner::new(private_key, public_key).unwrap();
+
+        let allowed_view = (0..view_keys)
+            .map(|_| {
+                let view_key = PublicKey::from(&PrivateKey::new().unwrap());
+                TxAccessPolicy::new(view_key.into(), TxAccess::AllData)
+            })
+            .collect();
+        let tx = Tx::new_with(
+            (0..inputs)
+                .map(|index| TxoPointer::new([index as u8; 32], index))
+                .collect(),
+            (0..outputs)
+                .map(|index| {
+                    TxOut::new(
+                        ExtendedAddr::OrTree([index as u8; 32]),
+                        Coin::new(1000 * index as u64 + 1).unwrap(),
+                    )
+                })
+                .collect(),
+            TxAttributes::new_with_access(0xab, allowed_view),
+        );
+
+        let transaction = Transaction::TransferTransaction(tx.clone());
+        let witness = (0..inputs)
+            .map(|_| signer.schnorr_sign(&transaction, &address).unwrap())
+            .collect::<Vec<_>>();
+        let payload = encrypt(
+            &PlainTxAux::TransferTx(tx.clone(), TxWitness::from(witness)),
+            tx.id(),
+        );
+        TxAux::EnclaveTx(TxEnclaveAux::TransferTx {
+            inputs: tx.inputs.clone(),
+            no_of_outputs: tx.outputs.len() as TxoSize,
+            payload,
+        })
+    }
+
+    #[test]
+    fn check_transfer_size_matches_built_transactions() {
+        for &(inputs, outputs, view_keys) in &[(1, 1, 0), (2, 2, 1), (3, 1, 3), (5, 4, 2)] {
+            let template = TxTemplate {
+                kind: TxKind::Transfer,
+                inputs,
+                outputs,
+                view_keys,
+            };
+            assert_eq!(
+                build_transfer_tx(inputs, outputs, view_keys).encode().len(),
+                estimate_encoded_size(&template).unwrap(),
+                "{:?}",
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn check_size_grows_with_shape() {
+        let size = |kind, inputs, outputs, view_keys| {
+            estimate_encoded_size(&TxTemplate {
+                kind,
+                inputs,
+                outputs,
+                view_keys,
+            })
+            .unwrap()
+        };
+
+        assert!(size(TxKind::Deposit, 2, 0, 0) > size(TxKind::Deposit, 1, 0, 0));
+        assert!(size(TxKind::Withdraw, 0, 2, 0) > size(TxKind::Withdraw, 0, 1, 0));
+        assert!(size(TxKind::Withdraw, 0, 1, 1) > size(TxKind::Withdraw, 0, 1, 0));
+    }
+
+    #[test]
+    fn check_inconsistent_template() {
+        let template = TxTemplate {
+            kind: TxKind::Withdraw,
+            inputs: 1,
+            outputs: 1,
+            view_keys: 0,
+        };
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            estimate_encoded_size(&template).unwrap_err().kind()
+        );
+
+        let template = TxTemplate {
+            kind: TxKind::Transfer,
+            inputs: 1,
+            outputs: usize::from(TxoSize::max_value()) + 1,
+            view_keys: 0,
+        };
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            estimate_encoded_size(&template).unwrap_err().kind()
+        );
+    }
+}
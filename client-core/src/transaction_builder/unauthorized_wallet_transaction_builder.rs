@@ -6,6 +6,7 @@ use chain_core::tx::data::output::TxOut;
 use chain_core::tx::TxAux;
 use client_common::{ErrorKind, PrivateKey, Result, SecKey, SignedTransaction, Transaction};
 
+use crate::transaction_builder::FeeTip;
 use crate::{UnspentTransactions, WalletTransactionBuilder};
 use chain_core::tx::data::TxId;
 
@@ -23,6 +24,28 @@ impl WalletTransactionBuilder for UnauthorizedWalletTransactionBuilder {
         _: Vec<TxOut>,
         _: ExtendedAddr,
         _: TxAttributes,
+        _: FeeTip,
+    ) -> Result<(TxAux, Vec<TxoPointer>, Coin)> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn estimate_fee(
+        &self,
+        _: UnspentTransactions,
+        _: Vec<TxOut>,
+        _: ExtendedAddr,
+        _: TxAttributes,
+    ) -> Result<Coin> {
+        Err(ErrorKind::PermissionDenied.into())
+    }
+
+    fn build_sweep_tx(
+        &self,
+        _: &str,
+        _: &SecKey,
+        _: UnspentTransactions,
+        _: ExtendedAddr,
+        _: TxAttributes,
     ) -> Result<(TxAux, Vec<TxoPointer>, Coin)> {
         Err(ErrorKind::PermissionDenied.into())
     }
@@ -2,12 +2,35 @@
 mod address_type;
 mod wallet_type;
 
+pub mod compliance_policy;
+pub mod fee_summary;
+pub mod history_filter;
+pub mod payment_request;
+pub mod qr_payload;
+pub mod retention_policy;
 pub mod transaction_change;
 
-pub use self::address_type::AddressType;
+pub use self::address_type::{AddressType, ImportedAddress, WalletAddress};
+#[doc(inline)]
+pub use self::compliance_policy::CompliancePolicy;
+#[doc(inline)]
+pub use self::fee_summary::{FeeDiscrepancy, FeeSummary};
+#[doc(inline)]
+pub use self::history_filter::{HistoryDirection, HistoryFilter, HistoryPage};
+#[doc(inline)]
+pub use self::payment_request::{parse_payment_request, PaymentRequest};
+#[doc(inline)]
+pub use self::qr_payload::{
+    parse_qr_binary_payload, parse_qr_payload, qr_binary_payload_for_address,
+    qr_binary_payload_for_payment_request, qr_payload_for_address, qr_payload_for_payment_request,
+    QrPayload,
+};
+#[doc(inline)]
+pub use self::retention_policy::{ArchiveMark, RecordClass, RetentionPolicy, WalletArchive};
 #[doc(inline)]
 pub use self::transaction_change::{
-    BalanceChange, TransactionChange, TransactionInput, TransactionPending, TransactionType,
+    BalanceChange, BroadcastResult, PendingTransactionInfo, PendingTransactionStatus,
+    TransactionChange, TransactionDetails, TransactionInput, TransactionPending, TransactionType,
     WalletBalance,
 };
 pub use self::wallet_type::WalletKind;
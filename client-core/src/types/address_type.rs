@@ -1,11 +1,14 @@
+use std::fmt;
 use std::str::FromStr;
 
 use unicase::eq_ascii;
 
+use chain_core::state::account::StakedStateAddress;
+use chain_core::tx::data::address::ExtendedAddr;
 use client_common::{Error, ErrorKind, Result};
 
 /// Enum for specifying different types of addresses
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AddressType {
     /// Transfer address
     Transfer,
@@ -30,8 +33,56 @@ impl FromStr for AddressType {
     }
 }
 
+impl fmt::Display for AddressType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AddressType::Transfer => write!(f, "transfer"),
+            AddressType::Staking => write!(f, "staking"),
+        }
+    }
+}
+
 impl Default for AddressType {
     fn default() -> Self {
         Self::Transfer
     }
 }
+
+/// Transfer or staking address of a wallet
+#[derive(Debug, Clone, PartialEq)]
+pub enum WalletAddress {
+    /// 1-of-1 transfer address
+    Transfer(ExtendedAddr),
+    /// Staking address
+    Staking(StakedStateAddress),
+}
+
+/// Address registered for an imported private key
+pub type ImportedAddress = WalletAddress;
+
+impl fmt::Display for WalletAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WalletAddress::Transfer(address) => write!(f, "{}", address),
+            WalletAddress::Staking(address) => write!(f, "{}", address),
+        }
+    }
+}
+
+impl FromStr for WalletAddress {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Ok(address) = StakedStateAddress::from_str(s) {
+            return Ok(WalletAddress::Staking(address));
+        }
+        ExtendedAddr::from_str(s)
+            .map(WalletAddress::Transfer)
+            .map_err(|_| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    "Address is neither a transfer address nor a staking address",
+                )
+            })
+    }
+}
@@ -0,0 +1,116 @@
+//! Restrictions on the transfers built by a wallet in regulated deployments
+use parity_scale_codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use chain_core::init::coin::Coin;
+use chain_core::tx::data::address::ExtendedAddr;
+use chain_core::tx::data::output::TxOut;
+use client_common::{Error, ErrorKind, Result};
+
+/// Compliance policy of a wallet (disabled by default)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct CompliancePolicy {
+    /// Whether the policy is applied to the transfers built by the wallet
+    pub enabled: bool,
+    /// Minimum value of each output (the change output excluded)
+    pub min_output_value: Coin,
+}
+
+impl CompliancePolicy {
+    /// Checks the outputs of a transfer (without its change output) against the policy:
+    /// transfers whose outputs all go to addresses of the wallet (`is_own_address`) are rejected
+    /// with `ErrorKind::SelfTransfer`, unless `allow_self_transfer` is set
+    pub fn check_outputs<F>(
+        &self,
+        outputs: &[TxOut],
+        allow_self_transfer: bool,
+        is_own_address: F,
+    ) -> Result<()>
+    where
+        F: Fn(&ExtendedAddr) -> Result<bool>,
+    {
+        if !self.enabled {
+            return Ok(());
+        }
+        if let Some(output) = outputs
+            .iter()
+            .find(|output| output.value < self.min_output_value)
+        {
+            return Err(Error::new(
+                ErrorKind::IllegalInput,
+                format!(
+                    "Output value {} is below the minimum output value {} of the compliance policy",
+                    output.value, self.min_output_value
+                ),
+            ));
+        }
+        if allow_self_transfer || outputs.is_empty() {
+            return Ok(());
+        }
+        for output in outputs {
+            if !is_own_address(&output.address)? {
+                return Ok(());
+            }
+        }
+        Err(Error::new(
+            ErrorKind::SelfTransfer,
+            "All the outputs go to addresses of the wallet, which the compliance policy doesn't allow",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(address: u8, value: u64) -> TxOut {
+        TxOut::new(
+            ExtendedAddr::OrTree([address; 32]),
+            Coin::new(value).unwrap(),
+        )
+    }
+
+    fn check(policy: &CompliancePolicy, outputs: &[TxOut], allow: bool) -> Result<()> {
+        // addresses 1 and 2 belong to the wallet
+        policy.check_outputs(outputs, allow, |address| {
+            let ExtendedAddr::OrTree(root_hash) = address;
+            Ok(root_hash[0] < 3)
+        })
+    }
+
+    #[test]
+    fn check_self_transfers_are_rejected() {
+        let policy = CompliancePolicy {
+            enabled: true,
+            min_output_value: Coin::zero(),
+        };
+        let own = [output(1, 10), output(2, 10)];
+        let err = check(&policy, &own, false).unwrap_err();
+        assert_eq!(ErrorKind::SelfTransfer, err.kind());
+        assert!(check(&policy, &own, true).is_ok());
+        assert!(check(&policy, &[output(1, 10), output(3, 10)], false).is_ok());
+
+        let disabled = CompliancePolicy::default();
+        assert!(check(&disabled, &own, false).is_ok());
+    }
+
+    #[test]
+    fn check_min_output_value_is_enforced() {
+        let policy = CompliancePolicy {
+            enabled: true,
+            min_output_value: Coin::new(100).unwrap(),
+        };
+        assert!(check(&policy, &[output(3, 100)], false).is_ok());
+        let err = check(&policy, &[output(3, 100), output(4, 99)], false).unwrap_err();
+        assert_eq!(ErrorKind::IllegalInput, err.kind());
+        // the override only allows the self transfers
+        let err = check(&policy, &[output(1, 99)], true).unwrap_err();
+        assert_eq!(ErrorKind::IllegalInput, err.kind());
+
+        let disabled = CompliancePolicy {
+            enabled: false,
+            ..policy
+        };
+        assert!(check(&disabled, &[output(3, 1)], false).is_ok());
+    }
+}
@@ -0,0 +1,219 @@
+//! Fees paid by a wallet per day
+use std::collections::BTreeMap;
+
+pub use chrono::NaiveDate;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use chain_core::init::coin::{sum_coins, Coin};
+use chain_core::tx::data::TxId;
+use client_common::{Error, ErrorKind, Result, ResultExt};
+
+use super::transaction_change::{
+    deserialize_transaction_id, serialize_transaction_id, TransactionChange,
+};
+
+/// Transaction whose fee reported by the chain differs from the one estimated when it was built
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeeDiscrepancy {
+    /// Transaction ID
+    #[serde(serialize_with = "serialize_transaction_id")]
+    #[serde(deserialize_with = "deserialize_transaction_id")]
+    pub transaction_id: TxId,
+    /// Fee estimated by the wallet
+    pub estimated: Coin,
+    /// Fee reported by the chain
+    pub reported: Coin,
+}
+
+/// Fees paid by a wallet in the transactions confirmed in a date range (UTC)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeeSummary {
+    /// Total of the fees
+    pub total: Coin,
+    /// Total of the fees per day (only the days with transactions, in ascending order)
+    pub per_day: Vec<(NaiveDate, Coin)>,
+    /// Number of transactions
+    pub tx_count: usize,
+    /// Transactions whose estimated fee differs from the reported one
+    pub discrepancies: Vec<FeeDiscrepancy>,
+    /// Date of the latest record archived out of the live storage of the wallet, if the range
+    /// starts before it: the summary is truncated, the archived transactions are not included
+    #[serde(default)]
+    pub archived_until: Option<NaiveDate>,
+}
+
+impl FeeSummary {
+    /// Sums the (chain reported) fees paid by the wallet in the transactions of `history` confirmed
+    /// from `from` to `to` (inclusive) and reconciles them against the locally estimated ones
+    /// (`estimates`)
+    pub fn new(
+        history: impl IntoIterator<Item = TransactionChange>,
+        estimates: &BTreeMap<TxId, Coin>,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Self> {
+        if from > to {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Start date of the fee summary is after its end date",
+            ));
+        }
+
+        let mut per_day = BTreeMap::<NaiveDate, Coin>::new();
+        let mut tx_count = 0;
+        let mut discrepancies = Vec::new();
+        for change in history
+            .into_iter()
+            .filter(TransactionChange::is_fee_paid_by_wallet)
+        {
+            let date = DateTime::<Utc>::from(change.block_time).date().naive_utc();
+            if date < from || date > to {
+                continue;
+            }
+
+            let fee = change.fee_paid.to_coin();
+            let day_total = per_day.entry(date).or_insert_with(Coin::zero);
+            *day_total = (*day_total + fee).chain(|| {
+                (
+                    ErrorKind::IllegalInput,
+                    "Sum of fees exceeds maximum allowed amount",
+                )
+            })?;
+            tx_count += 1;
+
+            match estimates.get(&change.transaction_id) {
+                Some(estimated) if *estimated != fee => discrepancies.push(FeeDiscrepancy {
+                    transaction_id: change.transaction_id,
+                    estimated: *estimated,
+                    reported: fee,
+                }),
+                _ => {}
+            }
+        }
+
+        let total = sum_coins(per_day.values().copied()).chain(|| {
+            (
+                ErrorKind::IllegalInput,
+                "Sum of fees exceeds maximum allowed amount",
+            )
+        })?;
+
+        Ok(FeeSummary {
+            total,
+            per_day: per_day.into_iter().collect(),
+            tx_count,
+            discrepancies,
+            archived_until: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    use chain_core::tx::fee::Fee;
+    use client_common::tendermint::types::Time;
+
+    use crate::types::{BalanceChange, TransactionType};
+
+    fn change(
+        transaction_id: TxId,
+        fee: u64,
+        balance_change: BalanceChange,
+        block_time: &str,
+    ) -> TransactionChange {
+        TransactionChange {
+            transaction_id,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            fee_paid: Fee::new(Coin::new(fee).unwrap()),
+            balance_change,
+            transaction_type: TransactionType::Transfer,
+            block_height: 1,
+            block_time: Time::from_str(block_time).unwrap(),
+            memo: None,
+        }
+    }
+
+    fn outgoing() -> BalanceChange {
+        BalanceChange::Outgoing { value: Coin::one() }
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn check_fees_per_day() {
+        let history = vec![
+            change([1; 32], 10, outgoing(), "2020-01-01T10:00:00Z"),
+            change([2; 32], 20, outgoing(), "2020-01-01T23:59:59Z"),
+            // paid by the sender
+            change(
+                [3; 32],
+                40,
+                BalanceChange::Incoming { value: Coin::one() },
+                "2020-01-02T10:00:00Z",
+            ),
+            change([4; 32], 80, outgoing(), "2020-01-03T00:00:00Z"),
+            // out of the range
+            change([5; 32], 160, outgoing(), "2020-01-04T00:00:00Z"),
+        ];
+
+        let summary = FeeSummary::new(
+            history,
+            &BTreeMap::new(),
+            date("2020-01-01"),
+            date("2020-01-03"),
+        )
+        .unwrap();
+        assert_eq!(Coin::new(110).unwrap(), summary.total);
+        assert_eq!(
+            vec![
+                (date("2020-01-01"), Coin::new(30).unwrap()),
+                (date("2020-01-03"), Coin::new(80).unwrap()),
+            ],
+            summary.per_day
+        );
+        assert_eq!(3, summary.tx_count);
+        assert!(summary.discrepancies.is_empty());
+
+        assert!(FeeSummary::new(
+            Vec::new(),
+            &BTreeMap::new(),
+            date("2020-01-03"),
+            date("2020-01-01"),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn check_reported_fee_differing_from_estimate_is_flagged() {
+        let history = vec![
+            change([1; 32], 10, outgoing(), "2020-01-01T10:00:00Z"),
+            change([2; 32], 25, outgoing(), "2020-01-01T11:00:00Z"),
+            // not estimated (e.g. built elsewhere and broadcast through the wallet)
+            change([3; 32], 40, outgoing(), "2020-01-01T12:00:00Z"),
+        ];
+        let mut estimates = BTreeMap::new();
+        estimates.insert([1; 32], Coin::new(10).unwrap());
+        estimates.insert([2; 32], Coin::new(20).unwrap());
+
+        let summary =
+            FeeSummary::new(history, &estimates, date("2020-01-01"), date("2020-01-01")).unwrap();
+        // the total is the one reported by the chain
+        assert_eq!(Coin::new(75).unwrap(), summary.total);
+        assert_eq!(
+            vec![FeeDiscrepancy {
+                transaction_id: [2; 32],
+                estimated: Coin::new(20).unwrap(),
+                reported: Coin::new(25).unwrap(),
+            }],
+            summary.discrepancies
+        );
+    }
+}
@@ -0,0 +1,62 @@
+//! Filtered pages of the transaction history of a wallet
+use serde::{Deserialize, Serialize};
+
+use chain_core::init::coin::Coin;
+
+use super::transaction_change::{BalanceChange, TransactionChange};
+
+/// Direction of the balance change of a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryDirection {
+    /// Transactions adding to the balance of the wallet
+    Incoming,
+    /// Transactions reducing the balance of the wallet
+    Outgoing,
+}
+
+/// Restricts the transaction changes returned in a page of history (all the set criteria have to
+/// match)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryFilter {
+    /// Direction of the balance change
+    pub direction: Option<HistoryDirection>,
+    /// Lowest block height (inclusive)
+    pub from_height: Option<u64>,
+    /// Highest block height (inclusive)
+    pub to_height: Option<u64>,
+    /// Minimum value of the balance change
+    pub min_amount: Option<Coin>,
+}
+
+impl HistoryFilter {
+    /// Returns `true` if the transaction change matches the filter
+    pub fn matches(&self, change: &TransactionChange) -> bool {
+        let (direction, value) = match change.balance_change {
+            BalanceChange::Incoming { value } => (HistoryDirection::Incoming, value),
+            BalanceChange::Outgoing { value } => (HistoryDirection::Outgoing, value),
+            BalanceChange::NoChange => return false,
+        };
+        self.direction
+            .map_or(true, |expected| expected == direction)
+            && self
+                .from_height
+                .map_or(true, |from_height| change.block_height >= from_height)
+            && self
+                .to_height
+                .map_or(true, |to_height| change.block_height <= to_height)
+            && self
+                .min_amount
+                .map_or(true, |min_amount| value >= min_amount)
+    }
+}
+
+/// Page of the transaction history of a wallet (newest first)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryPage {
+    /// Number of the transaction changes matching the filter (in all the pages)
+    pub total: usize,
+    /// Transaction changes of the page
+    pub changes: Vec<TransactionChange>,
+}
@@ -0,0 +1,405 @@
+//! Payment request URIs (`cro:<address>?amount=1.5&label=...&exp=...`)
+use std::fmt;
+use std::str::FromStr;
+
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use chain_core::common::Timespec;
+use chain_core::init::coin::Coin;
+use chain_core::init::MAX_COIN_DECIMALS;
+use chain_core::tx::data::address::ExtendedAddr;
+use client_common::{Error, ErrorKind, Result, ResultExt};
+
+/// URI scheme of payment requests
+pub const PAYMENT_REQUEST_SCHEME: &str = "cro";
+
+/// Number of decimal digits of one CRO
+const COIN_DECIMAL_DIGITS: usize = 8;
+
+/// Characters percent-encoded in the query components: all but the unreserved ones of RFC 3986
+/// (alphanumerics and `-._~`)
+const QUERY_COMPONENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'!')
+    .add(b'"')
+    .add(b'#')
+    .add(b'$')
+    .add(b'%')
+    .add(b'&')
+    .add(b'\'')
+    .add(b'(')
+    .add(b')')
+    .add(b'*')
+    .add(b'+')
+    .add(b',')
+    .add(b'/')
+    .add(b':')
+    .add(b';')
+    .add(b'<')
+    .add(b'=')
+    .add(b'>')
+    .add(b'?')
+    .add(b'@')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// A request for payment bound to a transfer address of the receiver
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaymentRequest {
+    /// Transfer address to which the payment should be sent
+    #[serde(serialize_with = "serialize_address")]
+    #[serde(deserialize_with = "deserialize_address")]
+    pub address: ExtendedAddr,
+    /// Requested amount (if any)
+    pub amount: Option<Coin>,
+    /// Human readable label of the request
+    pub label: Option<String>,
+    /// Unix timestamp (in seconds) after which the request is no longer valid
+    pub expires: Option<Timespec>,
+    /// Query parameters not understood by this client, in order of appearance
+    pub extra: Vec<(String, String)>,
+}
+
+impl PaymentRequest {
+    /// Creates a new payment request for given address
+    pub fn new(
+        address: ExtendedAddr,
+        amount: Option<Coin>,
+        label: Option<String>,
+        expires: Option<Timespec>,
+    ) -> Self {
+        Self {
+            address,
+            amount,
+            label,
+            expires,
+            extra: Vec::new(),
+        }
+    }
+
+    /// Returns `true` if the payment request is expired at given time, `false` otherwise
+    #[inline]
+    pub fn is_expired(&self, now: Timespec) -> bool {
+        self.expires.map(|expires| now > expires).unwrap_or(false)
+    }
+
+    /// Returns an error if the payment request is expired at given time
+    pub fn ensure_not_expired(&self, now: Timespec) -> Result<()> {
+        if self.is_expired(now) {
+            Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Payment request expired at {}",
+                    self.expires.expect("expiry checked above")
+                ),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns `true` if given string looks like a payment request URI
+    #[inline]
+    pub fn is_payment_request(s: &str) -> bool {
+        s.starts_with(&format!("{}:", PAYMENT_REQUEST_SCHEME))
+    }
+}
+
+/// Parses a payment request URI
+#[inline]
+pub fn parse_payment_request(uri: &str) -> Result<PaymentRequest> {
+    uri.parse()
+}
+
+impl FromStr for PaymentRequest {
+    type Err = Error;
+
+    fn from_str(uri: &str) -> Result<Self> {
+        let rest = uri
+            .strip_prefix(PAYMENT_REQUEST_SCHEME)
+            .and_then(|rest| rest.strip_prefix(':'))
+            .chain(|| {
+                (
+                    ErrorKind::DeserializationError,
+                    format!(
+                        "Payment request must start with `{}:`",
+                        PAYMENT_REQUEST_SCHEME
+                    ),
+                )
+            })?;
+
+        let mut parts = rest.splitn(2, '?');
+        let address_str = parts.next().unwrap_or_default();
+        let address = ExtendedAddr::from_str(address_str).chain(|| {
+            (
+                ErrorKind::DeserializationError,
+                format!("Invalid address in payment request: {}", address_str),
+            )
+        })?;
+
+        let mut request = PaymentRequest::new(address, None, None, None);
+
+        for pair in parts.next().unwrap_or_default().split('&') {
+            if pair.is_empty() {
+                continue;
+            }
+
+            let mut pair = pair.splitn(2, '=');
+            let key = decode_component(pair.next().unwrap_or_default())?;
+            let value = decode_component(pair.next().unwrap_or_default())?;
+
+            match key.as_str() {
+                "amount" => {
+                    if request.amount.is_some() {
+                        return Err(duplicate_parameter(&key));
+                    }
+                    request.amount = Some(Coin::from_cro_str(&value).chain(|| {
+                        (
+                            ErrorKind::DeserializationError,
+                            format!("Invalid amount in payment request: {}", value),
+                        )
+                    })?);
+                }
+                "label" => {
+                    if request.label.is_some() {
+                        return Err(duplicate_parameter(&key));
+                    }
+                    request.label = Some(value);
+                }
+                "exp" => {
+                    if request.expires.is_some() {
+                        return Err(duplicate_parameter(&key));
+                    }
+                    request.expires = Some(value.parse().chain(|| {
+                        (
+                            ErrorKind::DeserializationError,
+                            format!("Invalid expiry in payment request: {}", value),
+                        )
+                    })?);
+                }
+                _ => request.extra.push((key, value)),
+            }
+        }
+
+        Ok(request)
+    }
+}
+
+impl fmt::Display for PaymentRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", PAYMENT_REQUEST_SCHEME, self.address)?;
+
+        let mut params = Vec::new();
+
+        if let Some(amount) = self.amount {
+            params.push(("amount".to_owned(), coin_to_decimal_string(amount)));
+        }
+        if let Some(ref label) = self.label {
+            params.push(("label".to_owned(), label.clone()));
+        }
+        if let Some(expires) = self.expires {
+            params.push(("exp".to_owned(), expires.to_string()));
+        }
+        params.extend(self.extra.iter().cloned());
+
+        for (i, (key, value)) in params.iter().enumerate() {
+            write!(
+                f,
+                "{}{}={}",
+                if i == 0 { '?' } else { '&' },
+                utf8_percent_encode(key, QUERY_COMPONENT),
+                utf8_percent_encode(value, QUERY_COMPONENT)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Formats a coin as a decimal CRO amount without trailing zeros (e.g. `1.5`)
+pub fn coin_to_decimal_string(coin: Coin) -> String {
+    let value = u64::from(coin);
+    let integral = value / MAX_COIN_DECIMALS;
+    let fractional = value % MAX_COIN_DECIMALS;
+
+    if fractional == 0 {
+        integral.to_string()
+    } else {
+        let fractional = format!("{:0width$}", fractional, width = COIN_DECIMAL_DIGITS);
+        format!("{}.{}", integral, fractional.trim_end_matches('0'))
+    }
+}
+
+fn decode_component(s: &str) -> Result<String> {
+    percent_decode_str(s)
+        .decode_utf8()
+        .map(|decoded| decoded.into_owned())
+        .chain(|| {
+            (
+                ErrorKind::DeserializationError,
+                format!("Invalid percent-encoding in payment request: {}", s),
+            )
+        })
+}
+
+fn duplicate_parameter(key: &str) -> Error {
+    Error::new(
+        ErrorKind::DeserializationError,
+        format!("Duplicate `{}` parameter in payment request", key),
+    )
+}
+
+fn serialize_address<S>(
+    address: &ExtendedAddr,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&address.to_string())
+}
+
+fn deserialize_address<'de, D>(deserializer: D) -> std::result::Result<ExtendedAddr, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let address: String = Deserialize::deserialize(deserializer)?;
+    ExtendedAddr::from_str(&address).map_err(|e| de::Error::custom(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address() -> ExtendedAddr {
+        ExtendedAddr::OrTree([1; 32])
+    }
+
+    #[test]
+    fn check_payment_request_round_trip() {
+        let mut request = PaymentRequest::new(
+            address(),
+            Some(Coin::new(150_000_000).unwrap()),
+            Some("coffee & cake".to_owned()),
+            Some(1_600_000_000),
+        );
+        request
+            .extra
+            .push(("memo".to_owned(), "table 4".to_owned()));
+
+        let uri = request.to_string();
+        assert!(PaymentRequest::is_payment_request(&uri));
+        assert_eq!(
+            format!(
+                "cro:{}?amount=1.5&label=coffee%20%26%20cake&exp=1600000000&memo=table%204",
+                address()
+            ),
+            uri
+        );
+
+        let parsed = parse_payment_request(&uri).unwrap();
+        assert_eq!(request, parsed);
+        assert_eq!(uri, parsed.to_string());
+    }
+
+    #[test]
+    fn check_unreserved_characters_are_not_encoded() {
+        let mut request =
+            PaymentRequest::new(address(), None, Some("a-b.c_d~e/f?g=h#i".to_owned()), None);
+        request
+            .extra
+            .push(("x-y.z".to_owned(), "caf\u{e9} 100%".to_owned()));
+
+        let uri = request.to_string();
+        assert_eq!(
+            format!(
+                "cro:{}?label=a-b.c_d~e%2Ff%3Fg%3Dh%23i&x-y.z=caf%C3%A9%20100%25",
+                address()
+            ),
+            uri
+        );
+        assert_eq!(request, parse_payment_request(&uri).unwrap());
+    }
+
+    #[test]
+    fn check_payment_request_without_parameters() {
+        let request = PaymentRequest::new(address(), None, None, None);
+        let uri = request.to_string();
+        assert!(!uri.contains('?'));
+        assert_eq!(request, parse_payment_request(&uri).unwrap());
+    }
+
+    #[test]
+    fn check_unknown_parameters_are_preserved() {
+        let uri = format!("cro:{}?foo=bar&amount=2&baz=", address());
+        let request = parse_payment_request(&uri).unwrap();
+
+        assert_eq!(Some(Coin::new(200_000_000).unwrap()), request.amount);
+        assert_eq!(
+            vec![
+                ("foo".to_owned(), "bar".to_owned()),
+                ("baz".to_owned(), "".to_owned())
+            ],
+            request.extra
+        );
+    }
+
+    #[test]
+    fn check_malformed_payment_requests() {
+        let address = address();
+        let malformed = vec![
+            format!("bitcoin:{}", address),
+            format!("cro{}", address),
+            "cro:not-an-address".to_owned(),
+            format!("cro:{}?amount=1.123456789", address),
+            format!("cro:{}?amount=abc", address),
+            format!("cro:{}?amount=.", address),
+            format!("cro:{}?amount=-1", address),
+            format!("cro:{}?amount=100000000000.00000001", address),
+            format!("cro:{}?amount=1&amount=2", address),
+            format!("cro:{}?exp=tomorrow", address),
+            format!("cro:{}?label=%FF", address),
+        ];
+
+        for uri in malformed {
+            assert!(parse_payment_request(&uri).is_err(), "{}", uri);
+        }
+    }
+
+    #[test]
+    fn check_payment_request_expiry() {
+        let request = PaymentRequest::new(address(), None, None, Some(100));
+        assert!(!request.is_expired(100));
+        assert!(request.ensure_not_expired(100).is_ok());
+        assert!(request.is_expired(101));
+        assert!(request.ensure_not_expired(101).is_err());
+
+        let request = PaymentRequest::new(address(), None, None, None);
+        assert!(!request.is_expired(Timespec::max_value()));
+    }
+
+    #[test]
+    fn check_decimal_coin_conversion() {
+        for coin in &[Coin::new(150_000_000).unwrap(), Coin::unit(), Coin::max()] {
+            assert_eq!(
+                *coin,
+                Coin::from_cro_str(&coin_to_decimal_string(*coin)).unwrap()
+            );
+        }
+
+        assert_eq!(
+            "1.5",
+            coin_to_decimal_string(Coin::new(150_000_000).unwrap())
+        );
+        assert_eq!("1", coin_to_decimal_string(Coin::one()));
+        assert_eq!("0.00000001", coin_to_decimal_string(Coin::unit()));
+        assert_eq!("0", coin_to_decimal_string(Coin::zero()));
+    }
+}
@@ -0,0 +1,248 @@
+//! QR payloads of transfer addresses and payment requests
+//!
+//! The text form is the canonical payment request URI (`cro:<address>?...`), the compact binary
+//! form is a version byte followed by a CBOR map (address is SCALE-encoded).
+//! A payload without any request parameters is parsed as an address payload.
+use parity_scale_codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use chain_core::common::Timespec;
+use chain_core::init::coin::Coin;
+use chain_core::tx::data::address::ExtendedAddr;
+use client_common::{Error, ErrorKind, Result, ResultExt};
+
+use super::payment_request::{parse_payment_request, PaymentRequest};
+
+/// Current version of the binary QR payloads
+pub const QR_PAYLOAD_VERSION: u8 = 1;
+
+/// Parsed QR payload
+#[derive(Debug, Clone, PartialEq)]
+pub enum QrPayload {
+    /// Plain transfer address
+    Address(ExtendedAddr),
+    /// Payment request with at least one parameter
+    PaymentRequest(PaymentRequest),
+}
+
+impl QrPayload {
+    fn from_request(request: PaymentRequest) -> Self {
+        if request.amount.is_none()
+            && request.label.is_none()
+            && request.expires.is_none()
+            && request.extra.is_empty()
+        {
+            QrPayload::Address(request.address)
+        } else {
+            QrPayload::PaymentRequest(request)
+        }
+    }
+
+    /// Transfer address of the payload
+    pub fn address(&self) -> &ExtendedAddr {
+        match self {
+            QrPayload::Address(address) => address,
+            QrPayload::PaymentRequest(request) => &request.address,
+        }
+    }
+}
+
+/// Binary (CBOR) form of a payment request
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CompactPayload {
+    #[serde(rename = "a", with = "serde_bytes")]
+    address: Vec<u8>,
+    #[serde(rename = "m", default, skip_serializing_if = "Option::is_none")]
+    amount: Option<u64>,
+    #[serde(rename = "l", default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(rename = "e", default, skip_serializing_if = "Option::is_none")]
+    expires: Option<Timespec>,
+    #[serde(rename = "x", default, skip_serializing_if = "Vec::is_empty")]
+    extra: Vec<(String, String)>,
+}
+
+/// Returns text QR payload of a transfer address
+pub fn qr_payload_for_address(address: &ExtendedAddr) -> String {
+    qr_payload_for_payment_request(&PaymentRequest::new(address.clone(), None, None, None))
+}
+
+/// Returns text QR payload of a payment request
+pub fn qr_payload_for_payment_request(request: &PaymentRequest) -> String {
+    request.to_string()
+}
+
+/// Returns compact binary QR payload of a transfer address
+pub fn qr_binary_payload_for_address(address: &ExtendedAddr) -> Vec<u8> {
+    qr_binary_payload_for_payment_request(&PaymentRequest::new(address.clone(), None, None, None))
+}
+
+/// Returns compact binary QR payload of a payment request
+pub fn qr_binary_payload_for_payment_request(request: &PaymentRequest) -> Vec<u8> {
+    let compact = CompactPayload {
+        address: request.address.encode(),
+        amount: request.amount.map(u64::from),
+        label: request.label.clone(),
+        expires: request.expires,
+        extra: request.extra.clone(),
+    };
+
+    let mut payload = vec![QR_PAYLOAD_VERSION];
+    payload.append(
+        &mut serde_cbor::to_vec(&compact).expect("Unable to serialize QR payload into CBOR"),
+    );
+    payload
+}
+
+/// Parses text QR payload
+pub fn parse_qr_payload(payload: &str) -> Result<QrPayload> {
+    parse_payment_request(payload).map(QrPayload::from_request)
+}
+
+/// Parses compact binary QR payload
+pub fn parse_qr_binary_payload(payload: &[u8]) -> Result<QrPayload> {
+    let (version, cbor) = payload.split_first().chain(|| {
+        (
+            ErrorKind::DeserializationError,
+            "QR payload is empty".to_owned(),
+        )
+    })?;
+
+    match *version {
+        QR_PAYLOAD_VERSION => {}
+        version if version > QR_PAYLOAD_VERSION => {
+            return Err(Error::new(
+                ErrorKind::DeserializationError,
+                format!(
+                    "Unsupported QR payload version {} (newer than {})",
+                    version, QR_PAYLOAD_VERSION
+                ),
+            ))
+        }
+        version => {
+            return Err(Error::new(
+                ErrorKind::DeserializationError,
+                format!("Invalid QR payload version {}", version),
+            ))
+        }
+    }
+
+    let compact: CompactPayload = serde_cbor::from_slice(cbor).chain(|| {
+        (
+            ErrorKind::DeserializationError,
+            "Unable to deserialize QR payload from CBOR",
+        )
+    })?;
+
+    let mut address_bytes = compact.address.as_slice();
+    let address = ExtendedAddr::decode(&mut address_bytes)
+        .ok()
+        .filter(|_| address_bytes.is_empty())
+        .chain(|| {
+            (
+                ErrorKind::DeserializationError,
+                "Invalid address in QR payload",
+            )
+        })?;
+    let amount = compact.amount.map(Coin::new).transpose().chain(|| {
+        (
+            ErrorKind::DeserializationError,
+            "Invalid amount in QR payload",
+        )
+    })?;
+
+    let mut request = PaymentRequest::new(address, amount, compact.label, compact.expires);
+    request.extra = compact.extra;
+
+    Ok(QrPayload::from_request(request))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address() -> ExtendedAddr {
+        ExtendedAddr::OrTree([7; 32])
+    }
+
+    fn request() -> PaymentRequest {
+        let mut request = PaymentRequest::new(
+            address(),
+            Some(Coin::new(150_000_000).unwrap()),
+            Some("coffee".to_owned()),
+            Some(1_600_000_000),
+        );
+        request
+            .extra
+            .push(("memo".to_owned(), "table 4".to_owned()));
+        request
+    }
+
+    #[test]
+    fn check_text_payload_round_trip() {
+        let payload = qr_payload_for_address(&address());
+        assert_eq!(payload, format!("cro:{}", address()));
+        assert_eq!(
+            QrPayload::Address(address()),
+            parse_qr_payload(&payload).unwrap()
+        );
+
+        let payload = qr_payload_for_payment_request(&request());
+        assert_eq!(
+            QrPayload::PaymentRequest(request()),
+            parse_qr_payload(&payload).unwrap()
+        );
+
+        assert!(parse_qr_payload(&address().to_string()).is_err());
+        assert!(parse_qr_payload("cro:not-an-address").is_err());
+    }
+
+    #[test]
+    fn check_binary_payload_round_trip() {
+        let payload = qr_binary_payload_for_address(&address());
+        assert_eq!(QR_PAYLOAD_VERSION, payload[0]);
+        assert_eq!(
+            QrPayload::Address(address()),
+            parse_qr_binary_payload(&payload).unwrap()
+        );
+
+        let payload = qr_binary_payload_for_payment_request(&request());
+        assert!(payload.len() < qr_payload_for_payment_request(&request()).len());
+        let parsed = parse_qr_binary_payload(&payload).unwrap();
+        assert_eq!(QrPayload::PaymentRequest(request()), parsed);
+        assert_eq!(&address(), parsed.address());
+    }
+
+    #[test]
+    fn check_malformed_binary_payloads() {
+        let payload = qr_binary_payload_for_payment_request(&request());
+
+        let mut future = payload.clone();
+        future[0] = QR_PAYLOAD_VERSION + 1;
+        let error = parse_qr_binary_payload(&future).unwrap_err();
+        assert!(error.to_string().contains("Unsupported QR payload version"));
+
+        let mut zero = payload.clone();
+        zero[0] = 0;
+        assert!(parse_qr_binary_payload(&zero).is_err());
+
+        let mut trailing = payload.clone();
+        trailing.push(0);
+        assert!(parse_qr_binary_payload(&trailing).is_err());
+
+        assert!(parse_qr_binary_payload(&[]).is_err());
+        assert!(parse_qr_binary_payload(&payload[..payload.len() - 1]).is_err());
+
+        let invalid_amount = CompactPayload {
+            address: address().encode(),
+            amount: Some(u64::max_value()),
+            label: None,
+            expires: None,
+            extra: Vec::new(),
+        };
+        let mut payload = vec![QR_PAYLOAD_VERSION];
+        payload.append(&mut serde_cbor::to_vec(&invalid_amount).unwrap());
+        assert!(parse_qr_binary_payload(&payload).is_err());
+    }
+}
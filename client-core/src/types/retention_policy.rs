@@ -0,0 +1,138 @@
+//! Retention of the wallet records which grow with the activity of the wallet
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use parity_scale_codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use chain_core::common::Timespec;
+use chain_core::init::coin::Coin;
+use chain_core::tx::data::TxId;
+
+use super::transaction_change::TransactionChange;
+
+const SECONDS_PER_DAY: Timespec = 24 * 60 * 60;
+
+/// Class of the wallet records whose retention is limited by a policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordClass {
+    /// Transaction changes of the history of the wallet
+    TransactionHistory,
+    /// Fees estimated when the outgoing transactions were built
+    FeeEstimates,
+}
+
+impl RecordClass {
+    /// All the record classes
+    pub const ALL: [RecordClass; 2] = [RecordClass::TransactionHistory, RecordClass::FeeEstimates];
+
+    /// Name of the class in the storage keys of the wallet settings
+    pub(crate) fn key(self) -> &'static str {
+        match self {
+            RecordClass::TransactionHistory => "history",
+            RecordClass::FeeEstimates => "feeestimates",
+        }
+    }
+}
+
+/// Retention policy of a record class (no limit by default): the records beyond any of the limits
+/// are expired, and moved out of the live storage when the old records of the wallet are archived
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct RetentionPolicy {
+    /// Maximum age of the records (in days, by block time)
+    pub max_age_days: Option<u32>,
+    /// Maximum number of records (the most recent ones are kept)
+    pub max_entries: Option<u64>,
+}
+
+impl RetentionPolicy {
+    /// Returns `true` if the record confirmed at `block_time` is expired at `now`, `newer` being
+    /// the number of records of its class confirmed after it
+    pub fn is_expired(&self, block_time: Timespec, newer: u64, now: Timespec) -> bool {
+        let too_old = self.max_age_days.map_or(false, |days| {
+            block_time < now.saturating_sub(Timespec::from(days) * SECONDS_PER_DAY)
+        });
+        let too_many = self.max_entries.map_or(false, |entries| newer >= entries);
+        too_old || too_many
+    }
+}
+
+/// Latest record archived from the live storage of a wallet: the live queries covering it (or the
+/// records before it) are truncated
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Encode, Decode,
+)]
+pub struct ArchiveMark {
+    /// Block height of the record
+    pub block_height: u64,
+    /// Block time of the record
+    pub block_time: Timespec,
+}
+
+impl ArchiveMark {
+    /// Returns the mark of given transaction change
+    pub fn of(change: &TransactionChange) -> ArchiveMark {
+        ArchiveMark {
+            block_height: change.block_height,
+            block_time: DateTime::<Utc>::from(change.block_time).timestamp() as Timespec,
+        }
+    }
+
+    /// Returns the (UTC) date of the record
+    pub fn date(&self) -> NaiveDate {
+        NaiveDateTime::from_timestamp(self.block_time as i64, 0).date()
+    }
+}
+
+/// Records of a wallet archived out of its live storage (mounted read-only)
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WalletArchive {
+    /// Archived transaction changes, ordered by block height (and transaction id)
+    pub transaction_history: Vec<TransactionChange>,
+    /// Archived fee estimates
+    pub fee_estimates: BTreeMap<TxId, Coin>,
+}
+
+impl WalletArchive {
+    /// Returns the archived transaction changes from `from_height` to `to_height` (inclusive)
+    pub fn history_range(&self, from_height: u64, to_height: u64) -> Vec<TransactionChange> {
+        self.transaction_history
+            .iter()
+            .filter(|change| change.block_height >= from_height && change.block_height <= to_height)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the archived transaction change of given transaction
+    pub fn get_transaction_change(&self, transaction_id: &TxId) -> Option<&TransactionChange> {
+        self.transaction_history
+            .iter()
+            .find(|change| &change.transaction_id == transaction_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_expiry() {
+        let now = 100 * SECONDS_PER_DAY;
+        assert!(!RetentionPolicy::default().is_expired(0, 1000, now));
+
+        let by_age = RetentionPolicy {
+            max_age_days: Some(30),
+            max_entries: None,
+        };
+        assert!(by_age.is_expired(now - 31 * SECONDS_PER_DAY, 0, now));
+        assert!(!by_age.is_expired(now - 30 * SECONDS_PER_DAY, 1000, now));
+
+        let by_entries = RetentionPolicy {
+            max_age_days: None,
+            max_entries: Some(2),
+        };
+        assert!(!by_entries.is_expired(0, 1, now));
+        assert!(by_entries.is_expired(now, 2, now));
+    }
+}
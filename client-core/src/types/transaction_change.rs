@@ -7,9 +7,11 @@ use parity_scale_codec::{Decode, Encode, Error, Input, Output};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 use chain_core::{
+    common::Timespec,
     init::coin::{Coin, CoinError},
     tx::data::{input::TxoPointer, output::TxOut, TxId},
     tx::fee::Fee,
+    tx::TxAux,
 };
 use client_common::tendermint::types::Time;
 use client_common::{ErrorKind, Result, ResultExt, Transaction};
@@ -19,9 +21,11 @@ use client_common::{ErrorKind, Result, ResultExt, Transaction};
 /// The semantic of `WalletBalance` is like this:
 ///
 /// ```plain
-/// total = available + pending
+/// total = available + pending + immature + locked
 /// pending = sum(incoming coins of pending tx)
-/// available = sum(synced utxo - spent by pending tx)
+/// locked = sum(synced utxo with `valid_from` later than the latest block time - spent by pending tx)
+/// immature = sum(synced utxo with less than `min_confirmations` confirmations - spent by pending tx - locked)
+/// available = sum(synced utxo - spent by pending tx - locked - immature)
 /// ```
 ///
 /// For pending tx with n incoming coins (transfer from other wallet to our wallet or withdraw):
@@ -51,6 +55,13 @@ pub struct WalletBalance {
     pub available: Coin,
     /// The pending amount balance
     pub pending: Coin,
+    /// The synced amount balance which doesn't have enough confirmations to be spent yet
+    #[serde(default)]
+    pub immature: Coin,
+    /// The synced amount balance which is time-locked (its `valid_from` is later than the time of
+    /// the latest block)
+    #[serde(default)]
+    pub locked: Coin,
 }
 
 /// Transaction pending infomation
@@ -64,6 +75,88 @@ pub struct TransactionPending {
     pub return_amount: Coin,
 }
 
+/// Status of a broadcast transaction which is tracked until it's confirmed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PendingTransactionStatus {
+    /// Broadcast and waiting for confirmation
+    Pending,
+    /// Found in the transaction history (no longer tracked after the next sync)
+    Confirmed,
+    /// Not confirmed within the timeout, to be explicitly re-broadcast or expired
+    Stale,
+}
+
+/// Tracked broadcast transaction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingTransactionInfo {
+    /// Transaction ID
+    #[serde(serialize_with = "serialize_transaction_id")]
+    #[serde(deserialize_with = "deserialize_transaction_id")]
+    pub transaction_id: TxId,
+    /// Tracking status
+    pub status: PendingTransactionStatus,
+    /// Time of the last broadcast
+    pub broadcast_time: Timespec,
+    /// Number of broadcasts
+    pub broadcast_count: u32,
+    /// The block height when the transaction was (last) broadcast
+    pub block_height: u64,
+    /// Hex encoded `TxAux`, as broadcast (so it can be re-broadcast through any node)
+    pub raw_tx_hex: String,
+    /// Idempotency key the transaction was sent with (if any)
+    pub idempotency_key: Option<String>,
+}
+
+/// Outcome of broadcasting a transaction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BroadcastResult {
+    /// Transaction ID
+    #[serde(serialize_with = "serialize_transaction_id")]
+    #[serde(deserialize_with = "deserialize_transaction_id")]
+    pub tx_id: TxId,
+    /// Hex encoded `TxAux`, exactly as broadcast
+    pub raw_tx_hex: String,
+    /// Fee paid by the transaction (when it's known to the wallet)
+    pub fee: Option<Coin>,
+    /// The block height when the transaction was broadcast
+    pub broadcast_height_hint: u64,
+}
+
+impl BroadcastResult {
+    /// Creates the result of broadcasting `tx_aux`
+    pub fn new(tx_aux: &TxAux, fee: Option<Coin>, broadcast_height_hint: u64) -> Self {
+        Self {
+            tx_id: tx_aux.tx_id(),
+            raw_tx_hex: hex::encode(tx_aux.encode()),
+            fee,
+            broadcast_height_hint,
+        }
+    }
+}
+
+/// Details of a transaction of the wallet: its contents when the wallet can decrypt it, otherwise
+/// the metadata recorded when it was synced
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionDetails {
+    /// Transaction ID
+    #[serde(serialize_with = "serialize_transaction_id")]
+    #[serde(deserialize_with = "deserialize_transaction_id")]
+    pub transaction_id: TxId,
+    /// Transaction inputs (with the details of the spent outputs, when they're known)
+    pub inputs: Vec<TransactionInput>,
+    /// Transaction outputs
+    pub outputs: Vec<TxOut>,
+    /// Fee paid by the transaction (when it's synced)
+    pub fee: Option<Coin>,
+    /// Height of block which has this transaction (when it's synced)
+    pub block_height: Option<u64>,
+    /// Number of blocks synced since (and including) the block which has this transaction
+    pub confirmations: Option<u64>,
+    /// Whether the contents of the transaction were decrypted (or only its metadata is known)
+    pub decrypted: bool,
+}
+
 /// Transaction data with attached metadata
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TransactionChange {
@@ -87,6 +180,10 @@ pub struct TransactionChange {
     pub block_height: u64,
     /// Time of block which has this transaction
     pub block_time: Time,
+    /// Memo attached to the transaction by the user (stored locally, apart from the history; not
+    /// part of the encoding)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
 }
 
 /// Transaction input
@@ -99,6 +196,23 @@ pub struct TransactionInput {
     pub output: Option<TxOut>,
 }
 
+impl TransactionChange {
+    /// Whether the fee of the transaction was paid by the wallet (the fees of incoming transfers
+    /// and deposits are paid by their senders)
+    pub fn is_fee_paid_by_wallet(&self) -> bool {
+        match self.transaction_type {
+            TransactionType::Transfer | TransactionType::Deposit => {
+                matches!(self.balance_change, BalanceChange::Outgoing { .. })
+            }
+            TransactionType::Withdraw
+            | TransactionType::Unbond
+            | TransactionType::Unjail
+            | TransactionType::Nodejoin
+            | TransactionType::UpdateNode => true,
+        }
+    }
+}
+
 /// Type of transaction
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Encode, Decode)]
 pub enum TransactionType {
@@ -114,6 +228,8 @@ pub enum TransactionType {
     Unjail,
     /// Nodejoin transaction
     Nodejoin,
+    /// Council node metadata update transaction
+    UpdateNode,
 }
 
 impl fmt::Display for TransactionType {
@@ -125,6 +241,7 @@ impl fmt::Display for TransactionType {
             TransactionType::Deposit => write!(f, "Deposit"),
             TransactionType::Unjail => write!(f, "Unfail"),
             TransactionType::Nodejoin => write!(f, "Nodejoin"),
+            TransactionType::UpdateNode => write!(f, "UpdateNode"),
         }
     }
 }
@@ -147,7 +264,7 @@ pub enum BalanceChange {
     NoChange,
 }
 
-fn serialize_transaction_id<S>(
+pub(crate) fn serialize_transaction_id<S>(
     transaction_id: &TxId,
     serializer: S,
 ) -> std::result::Result<S::Ok, S::Error>
@@ -157,7 +274,9 @@ where
     serializer.serialize_str(&hex::encode(transaction_id))
 }
 
-fn deserialize_transaction_id<'de, D>(deserializer: D) -> std::result::Result<TxId, D::Error>
+pub(crate) fn deserialize_transaction_id<'de, D>(
+    deserializer: D,
+) -> std::result::Result<TxId, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -217,6 +336,7 @@ impl Decode for TransactionChange {
             transaction_type,
             block_height,
             block_time,
+            memo: None,
         })
     }
 }
@@ -260,6 +380,7 @@ impl From<&Transaction> for TransactionType {
             Transaction::DepositStakeTransaction(_) => TransactionType::Deposit,
             Transaction::UnjailTransaction(_) => TransactionType::Unjail,
             Transaction::NodejoinTransaction(_) => TransactionType::Nodejoin,
+            Transaction::UpdateNodeTransaction(_) => TransactionType::UpdateNode,
         }
     }
 }
@@ -267,7 +388,11 @@ impl From<&Transaction> for TransactionType {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chain_core::init::address::RedeemAddress;
     use chain_core::init::coin::Coin;
+    use chain_core::state::account::{DepositBondTx, StakedStateAddress, StakedStateOpAttributes};
+    use chain_core::state::tendermint::BlockHeight;
+    use chain_core::tx::{TransactionId, TxEnclaveAux, TxObfuscated};
 
     #[test]
     fn check_transaction_change_encode_decode() {
@@ -282,6 +407,7 @@ mod tests {
             fee_paid: Fee::new(Coin::one()),
             block_height: 0,
             block_time: Time::now(),
+            memo: None,
         };
 
         let encoded = transaction_change.encode();
@@ -290,6 +416,32 @@ mod tests {
         assert_eq!(transaction_change, decoded);
     }
 
+    #[test]
+    fn check_broadcast_result_raw_tx_decodes() {
+        let tx = DepositBondTx::new(
+            vec![TxoPointer::new([1; 32], 0)],
+            StakedStateAddress::BasicRedeem(RedeemAddress::default()),
+            StakedStateOpAttributes::new(171),
+        );
+        let tx_aux = TxAux::EnclaveTx(TxEnclaveAux::DepositStakeTx {
+            tx: tx.clone(),
+            payload: TxObfuscated {
+                txid: tx.id(),
+                key_from: BlockHeight::genesis(),
+                init_vector: [0u8; 12],
+                txpayload: vec![1, 2, 3],
+            },
+        });
+
+        let result = BroadcastResult::new(&tx_aux, Some(Coin::one()), 10);
+        assert_eq!(tx_aux.tx_id(), result.tx_id);
+        assert_eq!(10, result.broadcast_height_hint);
+
+        let raw_tx = hex::decode(&result.raw_tx_hex).unwrap();
+        let decoded = TxAux::decode(&mut raw_tx.as_slice()).unwrap();
+        assert_eq!(tx_aux, decoded);
+    }
+
     #[test]
     fn balance_change_add_incoming() {
         let coin = Coin::zero()
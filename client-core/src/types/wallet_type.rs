@@ -16,6 +16,8 @@ pub enum WalletKind {
     HD,
     /// HW Wallet
     HW,
+    /// Watch-only wallet (public keys only, its transactions are signed elsewhere)
+    Watch,
 }
 
 impl From<u64> for WalletKind {
@@ -23,6 +25,7 @@ impl From<u64> for WalletKind {
         match code {
             0 => WalletKind::Basic,
             1 => WalletKind::HD,
+            3 => WalletKind::Watch,
             _ => WalletKind::HW,
         }
     }
@@ -1,18 +1,25 @@
 //! Wallet management
+mod async_wallet_client;
 mod default_wallet_client;
 /// Wallet synchronizer
 pub mod syncer;
 mod syncer_logic;
+mod wallet_lock;
 
+pub use async_wallet_client::AsyncWalletClient;
 pub use default_wallet_client::DefaultWalletClient;
+pub use wallet_lock::{WalletGuard, WalletLockMap};
 
 use indexmap::IndexSet;
 #[cfg(feature = "experimental")]
 use secp256k1::schnorrsig::SchnorrSignature;
-use secstr::SecUtf8;
-use std::collections::BTreeSet;
+use secstr::{SecStr, SecUtf8};
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(feature = "experimental")]
+use std::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use chain_core::common::{Proof, H256};
+use chain_core::common::{Proof, Timespec, H256};
 use chain_core::init::address::RedeemAddress;
 use chain_core::init::coin::Coin;
 use chain_core::state::account::StakedStateAddress;
@@ -23,20 +30,32 @@ use chain_core::tx::data::output::TxOut;
 #[cfg(feature = "experimental")]
 use chain_core::tx::data::Tx;
 use chain_core::tx::data::TxId;
+#[cfg(feature = "experimental")]
+use chain_core::tx::fee::LinearFee;
 use chain_core::tx::witness::tree::RawXOnlyPubkey;
 use chain_core::tx::TxAux;
 use client_common::tendermint::types::BroadcastTxResponse;
 use client_common::{
-    MultiSigAddress, PrivateKey, PrivateKeyAction, PublicKey, Result, SecKey, Transaction,
-    TransactionInfo,
+    CancellationToken, ErrorKind, MultiSigAddress, PrivateKey, PrivateKeyAction, PublicKey, Result,
+    ResultExt, SecKey, Transaction, TransactionInfo,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::hd_wallet::HardwareKind;
-use crate::service::{SyncState, WalletInfo};
-use crate::transaction_builder::{SignedTransferTransaction, UnsignedTransferTransaction};
-use crate::types::{AddressType, TransactionChange, TransactionPending, WalletBalance, WalletKind};
-use crate::{InputSelectionStrategy, Mnemonic, UnspentTransactions};
+use crate::hd_wallet::{DerivationPath, HardwareKind};
+#[cfg(feature = "experimental")]
+use crate::multi_sig::{NonceCommitmentMsg, NonceMsg, PartialSignatureMsg};
+#[cfg(feature = "experimental")]
+use crate::service::MultiSigSessionInfo;
+use crate::service::{ArchiveBlob, BackupBlob, SyncState, WalletInfo};
+use crate::transaction_builder::{FeeTip, SignedTransferTransaction, UnsignedTransferTransaction};
+use crate::types::fee_summary::NaiveDate;
+use crate::types::{
+    AddressType, ArchiveMark, BroadcastResult, CompliancePolicy, FeeSummary, HistoryFilter,
+    HistoryPage, ImportedAddress, PendingTransactionInfo, RecordClass, RetentionPolicy,
+    TransactionChange, TransactionDetails, TransactionPending, WalletAddress, WalletArchive,
+    WalletBalance, WalletKind,
+};
+use crate::{InputSelectionStrategy, Mnemonic, MnemonicLanguage, UnspentTransactions};
 
 /// information needed when create/delete a wallet
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -45,6 +64,9 @@ pub struct CreateWalletRequest {
     pub name: String,
     /// the passphares of the wallet
     pub passphrase: SecUtf8,
+    /// the passphrase typed again (if present, wallet creation requires it to match `passphrase`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passphrase_confirmation: Option<SecUtf8>,
 }
 
 /// information needed when operate the a wallet
@@ -57,6 +79,17 @@ pub struct WalletRequest {
     pub enckey: SecKey,
 }
 
+/// Wallet client whose type is selected at runtime
+pub type DynWalletClient = Box<dyn WalletClient + Send + Sync>;
+
+/// Current unix timestamp (in seconds)
+pub(crate) fn unix_timestamp() -> Result<Timespec> {
+    Ok(SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .chain(|| (ErrorKind::InternalError, "System time is before unix epoch"))?
+        .as_secs())
+}
+
 /// Interface for a generic wallet
 pub trait WalletClient: Send + Sync {
     /// if the view key included in the transaction, return the Transaction
@@ -71,18 +104,66 @@ pub trait WalletClient: Send + Sync {
     /// get hardware kind
     fn get_hardware_kind(&self, name: &str, enckey: &SecKey) -> Result<HardwareKind>;
 
-    /// Send balance to a transfer address, return the transaction id directly
+    /// Send balance to a transfer address, return the broadcast transaction directly
+    /// (the output can't be spent before `valid_from` if it's set, `allow_self_transfer`
+    /// overrides the self transfer check of the compliance policy, a non-zero `fee_tip` is
+    /// rejected, see `FeeTip::check_accepted`). If a tracked transaction was sent with
+    /// the same `idempotency_key`, it's returned instead of sending another one.
+    #[allow(clippy::too_many_arguments)]
     fn send_to_address(
         &self,
         name: &str,
         enckey: &SecKey,
         amount: Coin,
         address: ExtendedAddr,
+        valid_from: Option<Timespec>,
         view_keys: &mut BTreeSet<PublicKey>,
         network_id: u8,
+        allow_self_transfer: bool,
+        fee_tip: FeeTip,
+        idempotency_key: Option<&str>,
+    ) -> Result<BroadcastResult>;
+
+    /// Builds and signs the transaction of `send_to_address` (fee included) without broadcasting
+    /// it, returns it with its fee. Its inputs stay locked until it's broadcast (e.g. by
+    /// `broadcast`) or the locks expire.
+    #[allow(clippy::too_many_arguments)]
+    fn build_transaction(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        amount: Coin,
+        address: ExtendedAddr,
+        valid_from: Option<Timespec>,
+        view_keys: &mut BTreeSet<PublicKey>,
+        network_id: u8,
+        allow_self_transfer: bool,
+        fee_tip: FeeTip,
+    ) -> Result<(SignedTransferTransaction, Coin)>;
+
+    /// Broadcasts a transfer transaction built by the wallet and records it as pending (with its
+    /// fee, when it's known)
+    fn broadcast(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        signed_tx: SignedTransferTransaction,
+        fee: Option<Coin>,
+    ) -> Result<BroadcastResult>;
+
+    /// Sends the whole spendable balance of the wallet (all the confirmed outputs which are
+    /// neither locked by another build nor time-locked) to `to_address` in a single output, the
+    /// fee being deducted from it. Fails with `ErrorKind::InsufficientBalance` if the fee isn't
+    /// less than the balance.
+    fn sweep(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        to_address: ExtendedAddr,
+        attributes: TxAttributes,
     ) -> Result<TxId>;
 
-    /// send balance to a transfer address, waiting it transaction confirmed then return transaction id
+    /// send balance to a transfer address, waiting it transaction confirmed then return the broadcast transaction
     fn send_to_address_commit(
         &self,
         name: &str,
@@ -91,11 +172,18 @@ pub trait WalletClient: Send + Sync {
         address: ExtendedAddr,
         view_keys: &mut BTreeSet<PublicKey>,
         network_id: u8,
-    ) -> Result<TxId>;
+    ) -> Result<BroadcastResult>;
 
     /// Retrieves names of all wallets stored
     fn wallets(&self) -> Result<Vec<String>>;
 
+    /// Retrieves the stable identifier of a wallet, which is kept when the wallet is renamed and
+    /// never reused by another wallet
+    fn wallet_id(&self, name: &str) -> Result<String>;
+
+    /// Retrieves the name of the wallet with given identifier
+    fn wallet_by_id(&self, wallet_id: &str) -> Result<Option<String>>;
+
     /// Creates a new wallet with given name, enckey and kind. Returns mnemonics if `wallet_kind` was `HD`.
     /// TODO: separate two apis
     /// new_wallet_basic(name, passphrase)
@@ -109,6 +197,16 @@ pub trait WalletClient: Send + Sync {
         mnemonics_word_count: Option<u32>,
     ) -> Result<(SecKey, Option<Mnemonic>)>;
 
+    /// Creates a new HD wallet with given name and enckey, whose mnemonic (returned) is made of
+    /// the words of the BIP39 wordlist of given language
+    fn new_hd_wallet(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        mnemonics_word_count: Option<u32>,
+        language: MnemonicLanguage,
+    ) -> Result<(SecKey, Mnemonic)>;
+
     /// export wallet info including private key, transfer address, staking address and so on
     fn export_wallet(&self, name: &str, enckey: &SecKey) -> Result<WalletInfo>;
 
@@ -120,7 +218,7 @@ pub trait WalletClient: Send + Sync {
         wallet_info: &mut WalletInfo,
     ) -> Result<SecKey>;
 
-    /// Restores a HD wallet from given mnemonic
+    /// Restores a HD wallet from given mnemonic (in any of the supported languages)
     fn restore_wallet(
         &self,
         name: &str,
@@ -136,9 +234,82 @@ pub trait WalletClient: Send + Sync {
         view_key: &PrivateKey,
     ) -> Result<SecKey>;
 
-    /// Remove a wallet
+    /// Creates a watch-only wallet tracking the staking and transfer addresses of given public
+    /// keys (the same addresses as the ones of a wallet holding their private keys). Its balance
+    /// and history are synced as usual, but the operations which need private keys (e.g. signing
+    /// transactions or deriving new addresses) fail with `ErrorKind::OnlyWatchOnly`.
+    fn new_watch_wallet(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        public_keys: Vec<PublicKey>,
+    ) -> Result<SecKey>;
+
+    /// Removes a wallet: after verifying the passphrase (nothing is removed if it's wrong), all
+    /// its records (keys, multi-sig addresses and sessions, sync state, wallet state...) are
+    /// deleted in a single storage batch
     fn delete_wallet(&self, name: &str, passphrase: &SecUtf8) -> Result<()>;
 
+    /// Renames a wallet, keeping its identifier, keys, history and pending transactions. Returns
+    /// the enckey of the wallet under its new name.
+    fn rename_wallet(&self, name: &str, new_name: &str, passphrase: &SecUtf8) -> Result<SecKey>;
+
+    /// Changes the passphrase of a wallet: all its records (keys, wallet info, multi-sig
+    /// addresses, state and sessions) are re-encrypted at once, and nothing is changed if the old
+    /// passphrase is incorrect. Returns the new enckey of the wallet.
+    fn change_passphrase(
+        &self,
+        name: &str,
+        old_passphrase: &SecUtf8,
+        new_passphrase: &SecUtf8,
+    ) -> Result<SecKey>;
+
+    /// Locks a wallet: the records decrypted with its enckey are dropped from the cache of the
+    /// storage (if it has one), so that they aren't served again without decrypting them
+    fn lock_wallet(&self, name: &str, enckey: &SecKey) -> Result<()>;
+
+    /// Takes a full encrypted backup of the wallet records (keys, metadata, sync state and
+    /// wallet state)
+    fn backup(&self, name: &str, passphrase: &SecUtf8, backup_key: &SecUtf8) -> Result<BackupBlob>;
+
+    /// Takes an encrypted backup of the wallet records modified since given prior snapshot
+    fn backup_incremental(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        backup_key: &SecUtf8,
+        since_snapshot_id: &H256,
+    ) -> Result<BackupBlob>;
+
+    /// Restores a wallet with a new name from a chain of backup snapshots (a full snapshot
+    /// followed by incremental ones, each based on the previous one)
+    fn restore_backup(
+        &self,
+        blobs: &[BackupBlob],
+        backup_key: &SecUtf8,
+        new_name: &str,
+        passphrase: &SecUtf8,
+    ) -> Result<SecKey>;
+
+    /// Moves the records of the wallet confirmed before `before` (block time), and the ones expired
+    /// by the retention policies of their classes, out of the live storage into an archive
+    /// encrypted with the passphrase. The records which the balance depends on (transactions with
+    /// unspent outputs or pending ones) are kept.
+    fn archive_old_records(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        before: Timespec,
+    ) -> Result<ArchiveBlob>;
+
+    /// Mounts an archive of the wallet (read-only, the live storage isn't modified)
+    fn import_archive(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        archive: &ArchiveBlob,
+    ) -> Result<WalletArchive>;
+
     /// get auth token client
     fn auth_token(&self, name: &str, passphrase: &SecUtf8) -> Result<SecKey>;
 
@@ -193,6 +364,69 @@ pub trait WalletClient: Send + Sync {
         address: &ExtendedAddr,
     ) -> Result<Option<H256>>;
 
+    /// Returns the derivation path of the key of given address (`None` for the imported keys and
+    /// the keys derived before the paths were recorded)
+    fn derivation_path(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        address: &WalletAddress,
+    ) -> Result<Option<DerivationPath>>;
+
+    /// Returns all the transfer and staking addresses of the wallet with the derivation paths of
+    /// their keys (see `derivation_path`) and their labels
+    fn addresses_detailed(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+    ) -> Result<Vec<(WalletAddress, Option<DerivationPath>, Option<String>)>>;
+
+    /// Gives a label to an address of the wallet (an empty label removes it). Labels are unique
+    /// within a wallet: fails with `ErrorKind::AlreadyExists` if another address has the label.
+    fn set_address_label(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        address: &WalletAddress,
+        label: &str,
+    ) -> Result<()>;
+
+    /// Finds the address of the wallet with given label
+    fn address_by_label(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        label: &str,
+    ) -> Result<Option<WalletAddress>>;
+
+    /// Adds a contact (a counterparty's address) to the address book of the wallet. Contact names
+    /// are unique within a wallet: fails with `ErrorKind::AlreadyExists` if the contact exists.
+    fn add_contact(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        contact: &str,
+        address: &ExtendedAddr,
+    ) -> Result<()>;
+
+    /// Removes a contact from the address book of the wallet
+    fn remove_contact(&self, name: &str, enckey: &SecKey, contact: &str) -> Result<()>;
+
+    /// Returns all the contacts of the address book of the wallet
+    fn contacts(&self, name: &str, enckey: &SecKey) -> Result<BTreeMap<String, ExtendedAddr>>;
+
+    /// Returns the address of a contact of the wallet
+    fn find_contact(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        contact: &str,
+    ) -> Result<Option<ExtendedAddr>>;
+
+    /// Re-derives the keys of a HD wallet from its seed with their recorded derivation paths and
+    /// returns the public keys which don't match (or whose paths can't be derived)
+    fn verify_derivations(&self, name: &str, enckey: &SecKey) -> Result<Vec<PublicKey>>;
+
     /// Retrieves private key corresponding to given wallet name
     fn wallet_private_key(
         &self,
@@ -231,6 +465,37 @@ pub trait WalletClient: Send + Sync {
     /// Generates a new 1-of-1 transfer address
     fn new_transfer_address(&self, name: &str, enckey: &SecKey) -> Result<ExtendedAddr>;
 
+    /// Generates `count` new 1-of-1 transfer addresses, all of them written in one storage batch
+    /// (none of them is added if one fails)
+    fn new_addresses(&self, name: &str, enckey: &SecKey, count: usize)
+        -> Result<Vec<ExtendedAddr>>;
+
+    /// Imports an externally generated (32 bytes) private key and registers an address of given
+    /// type for it. Imported keys can not be regenerated from the mnemonic of a HD wallet, so
+    /// they're tagged as imported and always included in wallet exports. Importing a key again
+    /// with the same address type returns its address (it fails with another address type).
+    fn import_private_key(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        private_key: &SecStr,
+        address_type: AddressType,
+    ) -> Result<ImportedAddress>;
+
+    /// Retrieves public keys of all the imported private keys in given wallet
+    fn imported_keys(&self, name: &str, enckey: &SecKey) -> Result<IndexSet<PublicKey>>;
+
+    /// Creates a payment request URI (`cro:<address>?amount=..&label=..&exp=..`) bound to a fresh
+    /// transfer address
+    fn create_payment_request(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        amount: Option<Coin>,
+        label: Option<String>,
+        expires: Option<Timespec>,
+    ) -> Result<String>;
+
     /// Add watch only staking address
     fn new_watch_staking_address(
         &self,
@@ -280,10 +545,61 @@ pub trait WalletClient: Send + Sync {
     /// Returns number of cosigners required to sign the transaction
     fn required_cosigners(&self, name: &str, enckey: &SecKey, root_hash: &H256) -> Result<usize>;
 
-    /// Retrieves current balance of wallet
+    /// Retrieves current balance of wallet (outputs with less than `min_confirmations` confirmations
+    /// relative to the latest block height of node are counted as immature, and the ones whose
+    /// `valid_from` is in the future as locked)
     fn balance(&self, name: &str, enckey: &SecKey) -> Result<WalletBalance>;
 
-    /// Retrieves transaction history of wallet
+    /// Returns number of confirmations required before synced outputs of wallet are spendable
+    fn min_confirmations(&self, name: &str, enckey: &SecKey) -> Result<u32>;
+
+    /// Sets number of confirmations required before synced outputs of wallet are spendable
+    fn set_min_confirmations(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        min_confirmations: u32,
+    ) -> Result<()>;
+
+    /// Returns compliance policy applied to the transfers built by wallet
+    fn compliance_policy(&self, name: &str, enckey: &SecKey) -> Result<CompliancePolicy>;
+
+    /// Sets compliance policy applied to the transfers built by wallet
+    fn set_compliance_policy(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        policy: CompliancePolicy,
+    ) -> Result<()>;
+
+    /// Returns retention policy of given record class of wallet
+    fn retention_policy(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        class: RecordClass,
+    ) -> Result<RetentionPolicy>;
+
+    /// Sets retention policy of given record class of wallet
+    fn set_retention_policy(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        class: RecordClass,
+        policy: RetentionPolicy,
+    ) -> Result<()>;
+
+    /// Returns the latest record of given class archived out of the live storage of wallet: the
+    /// history (and the other queries of the class) up to it is truncated
+    fn archive_mark(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        class: RecordClass,
+    ) -> Result<Option<ArchiveMark>>;
+
+    /// Retrieves transaction history of wallet (ordered by block height), without the archived
+    /// records (see `archive_mark`)
     fn history(
         &self,
         name: &str,
@@ -293,6 +609,38 @@ pub trait WalletClient: Send + Sync {
         reversed: bool,
     ) -> Result<Vec<TransactionChange>>;
 
+    /// Retrieves a page of the transaction history of wallet matching the filter (newest first),
+    /// with the number of all the matching transaction changes, without the archived records (see
+    /// `archive_mark`)
+    fn history_paged(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        offset: usize,
+        limit: usize,
+        filter: &HistoryFilter,
+    ) -> Result<HistoryPage>;
+
+    /// Retrieves all the transaction changes of wallet matching the filter (e.g. the outgoing
+    /// ones above a block height, for an export), ordered by block height, without the archived
+    /// records (see `archive_mark`)
+    fn history_filtered(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        filter: &HistoryFilter,
+    ) -> Result<Vec<TransactionChange>>;
+
+    /// Retrieves transaction history of wallet from `from_height` to `to_height` (inclusive),
+    /// ordered by block height, without the archived records (see `archive_mark`)
+    fn history_range(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        from_height: u64,
+        to_height: u64,
+    ) -> Result<Vec<TransactionChange>>;
+
     /// Retrieves transaction change corresponding to given transaction ID
     fn get_transaction_change(
         &self,
@@ -301,6 +649,59 @@ pub trait WalletClient: Send + Sync {
         transaction_id: &TxId,
     ) -> Result<Option<TransactionChange>>;
 
+    /// Retrieves the details of given transaction: its inputs and outputs (when the wallet can
+    /// decrypt it, otherwise the ones recorded in its transaction change), fee, block height and
+    /// confirmations (when it's synced). Returns `None` if the transaction is neither in the
+    /// history nor decryptable by the wallet.
+    fn transaction_details(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        transaction_id: &TxId,
+    ) -> Result<Option<TransactionDetails>>;
+
+    /// Attaches a memo to given transaction (`None` removes it). Memos are stored locally (apart
+    /// from the transaction history, so they're kept when it's re-synced) and returned with the
+    /// transaction changes of the history.
+    fn set_transaction_memo(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        transaction_id: TxId,
+        memo: Option<String>,
+    ) -> Result<()>;
+
+    /// Retrieves the memo attached to given transaction
+    fn transaction_memo(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        transaction_id: &TxId,
+    ) -> Result<Option<String>>;
+
+    /// Sums the fees paid by the wallet in the transactions confirmed from `from` to `to`
+    /// (inclusive, UTC dates), flagging the ones which differ from the fees estimated by the wallet
+    fn fee_summary(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<FeeSummary>;
+
+    /// Exports versioned proof bundle of given transactions of wallet (see
+    /// `client_common::verify_proof_bundle`). The bundle doesn't contain any private or view keys.
+    /// A transaction of the latest block can only be proven once the next block is committed, which
+    /// is awaited. Fails with `ErrorKind::Cancelled` (between the transactions, or while waiting)
+    /// when `cancellation` is cancelled.
+    fn export_proof_bundle(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        tx_ids: Vec<TxId>,
+        cancellation: &CancellationToken,
+    ) -> Result<Vec<u8>>;
+
     /// Retrieves all unspent transactions of wallet
     fn unspent_transactions(&self, name: &str, enckey: &SecKey) -> Result<UnspentTransactions>;
 
@@ -321,19 +722,33 @@ pub trait WalletClient: Send + Sync {
         inputs: Vec<TxoPointer>,
     ) -> Result<Vec<(TxoPointer, bool)>>;
 
+    /// Returns the inputs (out of given ones) which don't have `min_confirmations` confirmations yet.
+    /// Automatic input selection never uses them, explicitly selected inputs can still be spent.
+    fn immature_inputs(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        inputs: &[TxoPointer],
+    ) -> Result<Vec<TxoPointer>>;
+
     /// Returns output of transaction with given input details
     fn output(&self, name: &str, enckey: &SecKey, input: &TxoPointer) -> Result<TxOut>;
 
-    /// Builds a transaction
+    /// Builds a transaction. The unspent transactions which are time-locked at the time of the
+    /// latest block (their `valid_from` is later) are not selected.
     ///
     /// # Attributes
     ///
     /// - `name`: Name of wallet
     /// - `enckey`: Passphrase of wallet
-    /// - `outputs`: Transaction outputs
+    /// - `outputs`: Transaction outputs (time-locked until their `valid_from`, if it's set)
     /// - `attributes`: Transaction attributes,
     /// - `input_selection_strategy`: Strategy to use while selecting unspent transactions
     /// - `return_address`: Address to which change amount will get returned
+    /// - `allow_self_transfer`: Allows the outputs to all go to addresses of the wallet when its
+    ///   compliance policy is enabled (which fails with `ErrorKind::SelfTransfer` otherwise)
+    /// - `fee_tip`: Amount paid on top of the fee (only zero is accepted for now)
+    #[allow(clippy::too_many_arguments)]
     fn create_transaction(
         &self,
         name: &str,
@@ -342,8 +757,21 @@ pub trait WalletClient: Send + Sync {
         attributes: TxAttributes,
         input_selection_strategy: Option<InputSelectionStrategy>,
         return_address: ExtendedAddr,
+        allow_self_transfer: bool,
+        fee_tip: FeeTip,
     ) -> Result<(TxAux, Vec<TxoPointer>, Coin)>;
 
+    /// Estimates the fee of a transaction with given outputs: the unspent transactions are selected
+    /// the same way as by `create_transaction` (with the default input selection strategy), but
+    /// the transaction is neither signed nor broadcast
+    fn estimate_fee(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        outputs: Vec<TxOut>,
+        attributes: TxAttributes,
+    ) -> Result<Coin>;
+
     /// Broadcasts a transaction to Thaler Experimental Network
     fn broadcast_transaction(&self, tx_aux: &TxAux) -> Result<BroadcastTxResponse>;
 
@@ -366,15 +794,54 @@ pub trait WalletClient: Send + Sync {
     /// Get the current block height
     fn get_current_block_height(&self) -> Result<u64>;
 
-    /// Update the wallet state
+    /// Update the wallet state with a broadcast transaction, and track it until it's confirmed
     fn update_tx_pending_state(
         &self,
         name: &str,
         enckey: &SecKey,
-        tx_id: TxId,
+        tx_aux: &TxAux,
         tx_pending: TransactionPending,
     ) -> Result<()>;
 
+    /// Returns the tracked broadcast transactions with their status
+    /// (confirmed transactions are reported until the next sync stops tracking them)
+    fn pending_transactions(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+    ) -> Result<Vec<PendingTransactionInfo>>;
+
+    /// Broadcasts a stale tracked transaction again
+    fn rebroadcast_pending_transaction(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        tx_id: &TxId,
+    ) -> Result<()>;
+
+    /// Broadcasts again all the tracked transactions which are not confirmed yet (whether they're
+    /// stale or not, e.g. the ones whose broadcast failed because the node was unreachable) and
+    /// returns their ids. Fails if the node is (still) unreachable; the transactions rejected by
+    /// the node are skipped.
+    fn rebroadcast_pending(&self, name: &str, enckey: &SecKey) -> Result<Vec<TxId>>;
+
+    /// Stops tracking a stale transaction and releases its inputs in the wallet state
+    fn expire_pending_transaction(&self, name: &str, enckey: &SecKey, tx_id: &TxId) -> Result<()>;
+
+    /// Locks an unspent output of the wallet for `timeout` seconds (`DEFAULT_OUTPUT_LOCK_TIMEOUT`
+    /// if `None`), so that transaction builds don't select it. Fails with
+    /// `ErrorKind::AlreadyExists` if it's already locked (by a build or manually).
+    fn lock_output(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        input: &TxoPointer,
+        timeout: Option<Timespec>,
+    ) -> Result<()>;
+
+    /// Unlocks an output of the wallet (nothing is done if it isn't locked)
+    fn unlock_output(&self, name: &str, enckey: &SecKey, input: &TxoPointer) -> Result<()>;
+
     /// build raw transfer tx
     ///
     fn build_raw_transfer_tx(
@@ -403,11 +870,20 @@ pub trait WalletClient: Send + Sync {
         name: &str,
         enckey: &SecKey,
         signed_tx: SignedTransferTransaction,
-    ) -> Result<TxId>;
+    ) -> Result<BroadcastResult>;
 
     /// Get current sync state of wallet, return genesis one if not exists.
     fn get_sync_state(&self, name: &str) -> Result<SyncState>;
 
+    /// Names of the wallets whose synced history doesn't match their chain tip records (or the
+    /// node), their syncs and sends are blocked until the mismatch is acknowledged
+    fn history_mismatches(&self) -> Result<Vec<String>>;
+
+    /// Acknowledges the history mismatch of the wallet: its chain tip records are dropped (a new
+    /// sequence starts from its current sync state) and its syncs and sends are unblocked. Fails
+    /// with `ErrorKind::InvalidInput` if the wallet has no mismatch.
+    fn acknowledge_history_mismatch(&self, name: &str, enckey: &SecKey) -> Result<()>;
+
     ///Flush databaase
     fn flush_database(&self) -> Result<()>;
 }
@@ -480,9 +956,63 @@ pub trait MultiSigWalletClient: WalletClient {
         public_key: &PublicKey,
     ) -> Result<()>;
 
+    /// Returns nonce commitment of current signer as a message to be passed to the other co-signers
+    /// (whose sessions don't share the storage of current wallet)
+    fn export_nonce_commitment(
+        &self,
+        session_id: &H256,
+        enckey: &SecKey,
+    ) -> Result<NonceCommitmentMsg>;
+
+    /// Adds the nonce commitment of a co-signer received in a message to session with given id.
+    /// Fails with `ErrorKind::InvalidInput` if the sender isn't one of the other co-signers of the
+    /// session, and with `ErrorKind::AlreadyExists` if its nonce commitment is already added.
+    fn import_nonce_commitment(
+        &self,
+        session_id: &H256,
+        enckey: &SecKey,
+        message: &NonceCommitmentMsg,
+    ) -> Result<()>;
+
+    /// Returns nonce of current signer as a message to be passed to the other co-signers. This
+    /// function will fail if nonce commitments from all co-signers are not received.
+    fn export_nonce(&self, session_id: &H256, enckey: &SecKey) -> Result<NonceMsg>;
+
+    /// Adds the nonce of a co-signer received in a message to session with given id. Fails with
+    /// `ErrorKind::InvalidInput` if the sender isn't one of the other co-signers of the session,
+    /// and with `ErrorKind::AlreadyExists` if its nonce is already added.
+    fn import_nonce(&self, session_id: &H256, enckey: &SecKey, message: &NonceMsg) -> Result<()>;
+
+    /// Returns partial signature of current signer as a message to be passed to the other
+    /// co-signers. This function will fail if nonces from all co-signers are not received.
+    fn export_partial_signature(
+        &self,
+        session_id: &H256,
+        enckey: &SecKey,
+    ) -> Result<PartialSignatureMsg>;
+
+    /// Adds the partial signature of a co-signer received in a message to session with given id.
+    /// Fails with `ErrorKind::InvalidInput` if the sender isn't one of the other co-signers of the
+    /// session, and with `ErrorKind::AlreadyExists` if its partial signature is already added.
+    fn import_partial_signature(
+        &self,
+        session_id: &H256,
+        enckey: &SecKey,
+        message: &PartialSignatureMsg,
+    ) -> Result<()>;
+
     /// Returns final signature. This function will fail if partial signatures from all co-signers are not received.
     fn signature(&self, session_id: &H256, enckey: &SecKey) -> Result<SchnorrSignature>;
 
+    /// Returns the multi-sig sessions of the wallet (their ids, creation times and progress),
+    /// oldest first
+    fn list_sessions(&self, name: &str, enckey: &SecKey) -> Result<Vec<MultiSigSessionInfo>>;
+
+    /// Deletes the multi-sig sessions of the wallet created at least `older_than` ago (whatever
+    /// their progress) and returns the number of deleted sessions. Using a deleted session fails
+    /// with `ErrorKind::SessionNotFound`.
+    fn expire_sessions(&self, name: &str, enckey: &SecKey, older_than: Duration) -> Result<usize>;
+
     /// Returns obfuscated transaction by signing given transaction with signature produced by current session id.
     fn transaction(
         &self,
@@ -491,4 +1021,62 @@ pub trait MultiSigWalletClient: WalletClient {
         enckey: &SecKey,
         unsigned_transaction: Tx,
     ) -> Result<TxAux>;
+
+    /// Stores a m-of-n multi-sig address without any private key of its co-signers (e.g. on an
+    /// online watch-only wallet of offline co-signers) and returns the address
+    ///
+    /// # Arguments
+    ///
+    /// `name`: Name of wallet
+    /// `enckey`: enckey of wallet
+    /// `participants`: Public keys of all co-signers
+    /// `m`: Number of required co-signers
+    /// `n`: Total number of co-signers
+    fn import_multi_sig_address(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        participants: Vec<PublicKey>,
+        m: usize,
+        n: usize,
+    ) -> Result<ExtendedAddr>;
+
+    /// Builds an unsigned transaction bundle (encoded raw transfer transaction) spending outputs of
+    /// a multi-sig address of current wallet. The change is returned to the multi-sig address.
+    /// No private key is required.
+    #[allow(clippy::too_many_arguments)]
+    fn new_multi_sig_transfer_bundle(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        from_address: &ExtendedAddr,
+        to_address: ExtendedAddr,
+        amount: Coin,
+        view_keys: Vec<PublicKey>,
+        network_id: u8,
+        fee_algorithm: LinearFee,
+    ) -> Result<Vec<u8>>;
+
+    /// Adds a signature produced by co-signers (e.g. final signature of their multi-sig session on
+    /// transaction id of the bundle) to all inputs of the bundle and returns the updated bundle.
+    /// Inclusion proof of `signer_public_keys` is generated from the stored multi-sig address and the
+    /// resulting witness is verified against the spent address. No private key is required.
+    fn add_multi_sig_bundle_signature(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        bundle: Vec<u8>,
+        signer_public_keys: Vec<PublicKey>,
+        signature: SchnorrSignature,
+        fee_algorithm: LinearFee,
+    ) -> Result<Vec<u8>>;
+
+    /// Verifies a fully signed transaction bundle, broadcasts it and returns its transaction id
+    fn broadcast_multi_sig_bundle(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        bundle: Vec<u8>,
+        fee_algorithm: LinearFee,
+    ) -> Result<TxId>;
 }
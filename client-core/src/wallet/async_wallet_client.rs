@@ -0,0 +1,394 @@
+//! Asynchronous facade of a wallet client
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use tokio::sync::oneshot;
+
+use chain_core::common::Timespec;
+use chain_core::init::coin::Coin;
+use chain_core::tx::data::address::ExtendedAddr;
+use client_common::{Error, ErrorKind, PublicKey, Result, ResultExt, SecKey};
+
+use crate::transaction_builder::FeeTip;
+use crate::types::{BroadcastResult, WalletBalance};
+use crate::wallet::{DynWalletClient, WalletClient};
+
+/// Call of the wallet client, run on a thread of the pool
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Asynchronous wrapper of a `WalletClient`: each call is queued (on a bounded queue) and run on a
+/// dedicated pool of threads, so that the blocking wallet operations (storage, tendermint requests)
+/// don't stall the async executor
+pub struct AsyncWalletClient {
+    client: Arc<DynWalletClient>,
+    jobs: Mutex<SyncSender<Job>>,
+}
+
+impl AsyncWalletClient {
+    /// Creates a new instance of `AsyncWalletClient` running the calls of `client` on `threads`
+    /// threads, with at most `queue_size` calls waiting for a thread
+    pub fn new(client: DynWalletClient, threads: usize, queue_size: usize) -> Result<Self> {
+        if threads == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Number of wallet client threads cannot be zero",
+            ));
+        }
+
+        let (sender, receiver) = sync_channel::<Job>(queue_size);
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for index in 0..threads {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("wallet-client-{}", index))
+                .spawn(move || run_jobs(&receiver))
+                .chain(|| {
+                    (
+                        ErrorKind::InitializationError,
+                        "Unable to spawn wallet client thread",
+                    )
+                })?;
+        }
+
+        Ok(Self {
+            client: Arc::new(client),
+            jobs: Mutex::new(sender),
+        })
+    }
+
+    /// Queues `call` to be run on the wrapped client (on a thread of the pool) and returns a
+    /// future of its result. The future fails if the queue of the pool is full.
+    pub fn call<F, R>(&self, call: F) -> impl Future<Output = Result<R>>
+    where
+        F: FnOnce(&dyn WalletClient) -> Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let (result_sender, result_receiver) = oneshot::channel();
+        let client = self.client.clone();
+        let job: Job = Box::new(move || {
+            // the caller may have given up waiting for the result
+            let _ = result_sender.send(call(&**client));
+        });
+
+        let queued = self
+            .jobs
+            .lock()
+            .expect("Unable to acquire lock on wallet client queue")
+            .try_send(job)
+            .map_err(|error| match error {
+                TrySendError::Full(_) => {
+                    Error::new(ErrorKind::InternalError, "Wallet client queue is full")
+                }
+                TrySendError::Disconnected(_) => Error::new(
+                    ErrorKind::InternalError,
+                    "Wallet client threads are stopped",
+                ),
+            });
+
+        async move {
+            queued?;
+            result_receiver.await.chain(|| {
+                (
+                    ErrorKind::InternalError,
+                    "Wallet client call was aborted before returning a result",
+                )
+            })?
+        }
+    }
+
+    /// Returns the balance of the wallet (see `WalletClient::balance`)
+    pub fn balance(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+    ) -> impl Future<Output = Result<WalletBalance>> {
+        let name = name.to_owned();
+        let enckey = enckey.clone();
+        self.call(move |client| client.balance(&name, &enckey))
+    }
+
+    /// Sends `amount` to `address` (see `WalletClient::send_to_address`)
+    #[allow(clippy::too_many_arguments)]
+    pub fn send_to_address(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        amount: Coin,
+        address: ExtendedAddr,
+        valid_from: Option<Timespec>,
+        mut view_keys: BTreeSet<PublicKey>,
+        network_id: u8,
+        allow_self_transfer: bool,
+        fee_tip: FeeTip,
+        idempotency_key: Option<String>,
+    ) -> impl Future<Output = Result<BroadcastResult>> {
+        let name = name.to_owned();
+        let enckey = enckey.clone();
+        self.call(move |client| {
+            client.send_to_address(
+                &name,
+                &enckey,
+                amount,
+                address,
+                valid_from,
+                &mut view_keys,
+                network_id,
+                allow_self_transfer,
+                fee_tip,
+                idempotency_key.as_ref().map(String::as_str),
+            )
+        })
+    }
+}
+
+/// Runs the queued jobs until the queue is dropped
+fn run_jobs(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        let job = receiver
+            .lock()
+            .expect("Unable to acquire lock on wallet client queue")
+            .recv();
+        match job {
+            Ok(job) => job(),
+            Err(_) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc::channel;
+
+    use secstr::SecUtf8;
+    use serde_json::{json, Value};
+
+    use chain_core::state::ChainState;
+    use chain_core::tx::data::input::TxoPointer;
+    use chain_core::tx::data::output::TxOut;
+    use chain_core::tx::fee::{LinearFee, Milli};
+    use client_common::cipher::MockAbciTransactionObfuscation;
+    use client_common::storage::MemoryStorage;
+    use client_common::tendermint::mock;
+    use client_common::tendermint::types::*;
+    use client_common::tendermint::Client;
+
+    use crate::coin_selection::LargestFirst;
+    use crate::hd_wallet::HardwareKind;
+    use crate::service::{HwKeyService, WalletStateService};
+    use crate::signer::WalletSignerManager;
+    use crate::transaction_builder::DefaultWalletTransactionBuilder;
+    use crate::types::WalletKind;
+    use crate::wallet::DefaultWalletClient;
+    use crate::WalletStateMemento;
+
+    #[derive(Clone)]
+    struct MockClient;
+
+    impl Client for MockClient {
+        fn genesis(&self) -> Result<Genesis> {
+            Ok(mock::genesis())
+        }
+
+        fn status(&self) -> Result<StatusResponse> {
+            Ok(mock::status_response())
+        }
+
+        fn block(&self, _: u64) -> Result<Block> {
+            unreachable!()
+        }
+
+        fn block_batch<'a, T: Iterator<Item = &'a u64>>(&self, _heights: T) -> Result<Vec<Block>> {
+            unreachable!()
+        }
+
+        fn block_results(&self, _height: u64) -> Result<BlockResultsResponse> {
+            unreachable!()
+        }
+
+        fn block_results_batch<'a, T: Iterator<Item = &'a u64>>(
+            &self,
+            _heights: T,
+        ) -> Result<Vec<BlockResultsResponse>> {
+            unreachable!()
+        }
+
+        fn broadcast_transaction(&self, _: &[u8]) -> Result<BroadcastTxResponse> {
+            Ok(BroadcastTxResponse {
+                code: tendermint::abci::Code::Ok,
+                data: tendermint::abci::Data::from(vec![]),
+                log: tendermint::abci::Log::from(""),
+                hash: tendermint::abci::transaction::Hash::new([0; 32]),
+            })
+        }
+
+        fn query(
+            &self,
+            _path: &str,
+            _data: &[u8],
+            _height: Option<Height>,
+            _prove: bool,
+        ) -> Result<AbciQuery> {
+            unreachable!()
+        }
+
+        fn query_state_batch<T: Iterator<Item = u64>>(
+            &self,
+            _heights: T,
+        ) -> Result<Vec<ChainState>> {
+            unreachable!()
+        }
+    }
+
+    /// Example handler of an async HTTP server: `POST /balance` and `POST /send` with a JSON body
+    async fn handle_request(wallet: &AsyncWalletClient, path: &str, body: Value) -> (u16, Value) {
+        let name = body["name"].as_str().unwrap_or_default();
+        let enckey = match serde_json::from_value::<SecKey>(body["enckey"].clone()) {
+            Ok(enckey) => enckey,
+            Err(_) => return (400, json!({ "error": "Invalid enckey" })),
+        };
+
+        let result = match path {
+            "/balance" => wallet
+                .balance(name, &enckey)
+                .await
+                .map(|balance| json!(balance)),
+            "/send" => {
+                let amount = Coin::new(body["amount"].as_u64().unwrap_or_default());
+                let address = body["to_address"].as_str().unwrap_or_default().parse();
+                match (amount, address) {
+                    (Ok(amount), Ok(address)) => wallet
+                        .send_to_address(
+                            name,
+                            &enckey,
+                            amount,
+                            address,
+                            None,
+                            BTreeSet::new(),
+                            0xab,
+                            false,
+                            FeeTip::default(),
+                            None,
+                        )
+                        .await
+                        .map(|result| json!(result)),
+                    _ => return (400, json!({ "error": "Invalid amount or address" })),
+                }
+            }
+            _ => return (404, json!({ "error": "Not found" })),
+        };
+
+        match result {
+            Ok(value) => (200, value),
+            Err(error) => (500, json!({ "error": error.to_string() })),
+        }
+    }
+
+    #[test]
+    fn check_async_http_handler() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let storage = MemoryStorage::default();
+        let hw_key_service = HwKeyService::default();
+        let client = DefaultWalletClient::new(
+            storage.clone(),
+            MockClient,
+            DefaultWalletTransactionBuilder::new(
+                WalletSignerManager::new(storage.clone(), hw_key_service.clone()),
+                LinearFee::new(Milli::try_new(1, 1).unwrap(), Milli::try_new(1, 1).unwrap()),
+                MockAbciTransactionObfuscation::new(MockClient),
+                LargestFirst,
+            ),
+            None,
+            hw_key_service,
+        );
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        let transfer_address = client.new_transfer_address(name, &enckey).unwrap();
+
+        let mut memento = WalletStateMemento::default();
+        memento.add_unspent_transaction(
+            TxoPointer::new([1; 32], 0),
+            TxOut::new(transfer_address, Coin::new(10_000).unwrap()),
+        );
+        WalletStateService::new(storage)
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+
+        let wallet = AsyncWalletClient::new(client.into_dyn(), 2, 4).unwrap();
+        let wallet_request = json!({ "name": name, "enckey": enckey });
+
+        let mut runtime = tokio::runtime::Builder::new()
+            .basic_scheduler()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (status, balance) =
+                handle_request(&wallet, "/balance", wallet_request.clone()).await;
+            assert_eq!(200, status);
+            assert_eq!(json!(Coin::new(10_000).unwrap()), balance["available"]);
+
+            let mut send_request = wallet_request.clone();
+            send_request["amount"] = json!(1000);
+            send_request["to_address"] = json!(ExtendedAddr::OrTree([9; 32]).to_string());
+            let (status, result) = handle_request(&wallet, "/send", send_request).await;
+            assert_eq!(200, status);
+            assert!(result["tx_id"].is_string());
+
+            let (status, balance) =
+                handle_request(&wallet, "/balance", wallet_request.clone()).await;
+            assert_eq!(200, status);
+            assert_eq!(json!(Coin::zero()), balance["available"]);
+
+            let (status, _) = handle_request(&wallet, "/unknown", wallet_request).await;
+            assert_eq!(404, status);
+        });
+    }
+
+    #[test]
+    fn check_bounded_queue() {
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default()).into_dyn();
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            AsyncWalletClient::new(client, 0, 1).err().unwrap().kind()
+        );
+
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default()).into_dyn();
+        let wallet = AsyncWalletClient::new(client, 1, 1).unwrap();
+
+        // keep the only thread busy, fill the queue, then overflow it
+        let (started_sender, started_receiver) = channel();
+        let (release_sender, release_receiver) = channel::<()>();
+        let running = wallet.call(move |client| {
+            started_sender.send(()).unwrap();
+            release_receiver.recv().unwrap();
+            client.wallets()
+        });
+        started_receiver.recv().unwrap();
+        let queued = wallet.call(|client| client.wallets());
+        let rejected = wallet.call(|client| client.wallets());
+        release_sender.send(()).unwrap();
+
+        let mut runtime = tokio::runtime::Builder::new()
+            .basic_scheduler()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            assert_eq!(ErrorKind::InternalError, rejected.await.unwrap_err().kind());
+            assert!(running.await.unwrap().is_empty());
+            assert!(queued.await.unwrap().is_empty());
+        });
+    }
+}
@@ -1,23 +1,37 @@
-use crate::hd_wallet::{ChainPath, HardwareKind};
+#[cfg(feature = "experimental")]
+use crate::coin_selection::LargestFirst;
+use crate::hd_wallet::{ChainPath, DerivationPath, HardwareKind};
+#[cfg(feature = "experimental")]
+use crate::multi_sig::{NonceCommitmentMsg, NonceMsg, PartialSignatureMsg};
 use crate::service::*;
 use crate::transaction_builder::UnauthorizedWalletTransactionBuilder;
+#[cfg(feature = "experimental")]
+use crate::transaction_builder::{
+    default_dust_threshold, select_and_build_raw_transaction, FeeTip, RawTransferTransactionBuilder,
+};
 use crate::transaction_builder::{SignedTransferTransaction, UnsignedTransferTransaction};
+use crate::types::fee_summary::NaiveDate;
 use crate::types::{
-    AddressType, BalanceChange, TransactionChange, TransactionPending, WalletBalance, WalletKind,
+    AddressType, ArchiveMark, BalanceChange, BroadcastResult, CompliancePolicy, FeeSummary,
+    HistoryFilter, HistoryPage, ImportedAddress, PaymentRequest, PendingTransactionInfo,
+    PendingTransactionStatus, RecordClass, RetentionPolicy, TransactionChange, TransactionDetails,
+    TransactionInput, TransactionPending, WalletAddress, WalletArchive, WalletBalance, WalletKind,
 };
 use crate::wallet::syncer::{get_genesis_sync_state, AddressRecovery};
 use crate::wallet::syncer_logic::create_transaction_change;
+use crate::wallet::{unix_timestamp, DynWalletClient, WalletLockMap};
 #[cfg(feature = "experimental")]
 use crate::MultiSigWalletClient;
 use crate::{
-    InputSelectionStrategy, Mnemonic, UnspentTransactions, WalletClient, WalletTransactionBuilder,
+    InputSelectionStrategy, Mnemonic, MnemonicLanguage, UnspentTransactions, WalletClient,
+    WalletTransactionBuilder,
 };
 use bit_vec::BitVec;
-use chain_core::common::{Proof, H256};
+use chain_core::common::{MerkleTree, Proof, Timespec, H256};
 use chain_core::init::address::RedeemAddress;
-use chain_core::init::coin::Coin;
+use chain_core::init::coin::{sum_coins, Coin};
+use chain_core::init::network::{get_bip44_coin_type_from_network, get_network};
 use chain_core::state::account::StakedStateAddress;
-use chain_core::tx::data::access::{TxAccess, TxAccessPolicy};
 use chain_core::tx::data::address::ExtendedAddr;
 use chain_core::tx::data::attribute::TxAttributes;
 use chain_core::tx::data::input::{str2txid, TxoPointer};
@@ -26,30 +40,51 @@ use chain_core::tx::data::output::TxOut;
 use chain_core::tx::data::Tx;
 use chain_core::tx::data::TxId;
 use chain_core::tx::fee::Fee;
+#[cfg(feature = "experimental")]
+use chain_core::tx::fee::LinearFee;
 use chain_core::tx::witness::tree::RawXOnlyPubkey;
 #[cfg(feature = "experimental")]
 use chain_core::tx::witness::{TxInWitness, TxWitness};
-use chain_core::tx::{TxAux, TxEnclaveAux, TxObfuscated};
+use chain_core::tx::{TxAux, TxEnclaveAux};
+use chrono::{DateTime, Utc};
+use client_common::proof_bundle::{JsonEncoded, TransactionProof, TrustAnchor};
 use client_common::tendermint::types::Time;
-use client_common::tendermint::types::{AbciQueryExt, BlockResults, BroadcastTxResponse};
+use client_common::tendermint::types::{AbciQueryExt, BlockExt, BlockResults, BroadcastTxResponse};
 use client_common::tendermint::{Client, UnauthorizedClient};
 #[cfg(feature = "experimental")]
 use client_common::SignedTransaction;
 use client_common::{
-    seckey::derive_enckey, Error, ErrorKind, MultiSigAddress, PrivateKey, PrivateKeyAction,
-    PublicKey, Result, ResultExt, SecKey, Storage, Transaction, TransactionInfo,
+    seckey::{derive_enckey, KdfParams},
+    CancellationToken, Error, ErrorKind, MultiSigAddress, PrivateKey, PrivateKeyAction,
+    ProofBundle, PublicKey, Result, ResultExt, SecKey, Storage, StorageBatch, Transaction,
+    TransactionFee, TransactionInfo,
 };
 use indexmap::IndexSet;
-use parity_scale_codec::Encode;
+use parity_scale_codec::{Decode, Encode};
 #[cfg(feature = "experimental")]
 use secp256k1::schnorrsig::SchnorrSignature;
-use secstr::SecUtf8;
+use secstr::{SecStr, SecUtf8};
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::convert::TryInto;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use zxcvbn::{feedback::Feedback, zxcvbn as estimate_password_strength};
+/// Record ids of the wallet backups
+const BACKUP_INFO: &str = "info";
+const BACKUP_SYNC_STATE: &str = "sync_state";
+const BACKUP_SYNC_CHECKPOINTS: &str = "sync_checkpoints";
+const BACKUP_WALLET_STATE: &str = "wallet_state";
+const BACKUP_KEY_PREFIX: &str = "key/";
+const BACKUP_MULTISIG_PREFIX: &str = "multisig/";
+/// Record ids of the wallet archives
+const ARCHIVE_WALLET_ID: &str = "wallet_id";
+const ARCHIVE_HISTORY_PREFIX: &str = "history/";
+const ARCHIVE_FEE_ESTIMATE_PREFIX: &str = "fee_estimate/";
+
+/// Maximum time to wait for the next block when exporting a proof of a transaction in the latest one
+const NEXT_BLOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// Default implementation of `WalletClient` based on `Storage` and `Index`
 #[derive(Debug, Default, Clone)]
 pub struct DefaultWalletClient<S, C, T>
@@ -65,6 +100,16 @@ where
     wallet_state_service: WalletStateService<S>,
     sync_state_service: SyncStateService<S>,
     root_hash_service: RootHashService<S>,
+    backup_service: BackupService<S>,
+    pending_transaction_service: PendingTransactionService<S>,
+    output_lock_service: OutputLockService<S>,
+    fee_estimate_service: FeeEstimateService<S>,
+    transaction_metadata_service: TransactionMetadataService<S>,
+    address_label_service: AddressLabelService<S>,
+    address_book_service: AddressBookService<S>,
+    history_index_service: HistoryIndexService<S>,
+    chain_tip_service: ChainTipService<S>,
+    wallet_locks: WalletLockMap,
     #[cfg(feature = "experimental")]
     multi_sig_session_service: MultiSigSessionService<S>,
 
@@ -88,16 +133,29 @@ where
         block_height_ensure: Option<u64>,
         hw_key_service: HwKeyService,
     ) -> Self {
+        // the services which serialize their own updates of a wallet share the locks of the client
+        let wallet_locks = WalletLockMap::new();
         Self {
-            key_service: KeyService::new(storage.clone()),
+            key_service: KeyService::new(storage.clone()).with_wallet_locks(wallet_locks.clone()),
             hd_key_service: HdKeyService::new(storage.clone()),
             hw_key_service,
-            wallet_service: WalletService::new(storage.clone()),
+            wallet_service: WalletService::new(storage.clone())
+                .with_wallet_locks(wallet_locks.clone()),
             wallet_state_service: WalletStateService::new(storage.clone()),
             sync_state_service: SyncStateService::new(storage.clone()),
             #[cfg(feature = "experimental")]
             multi_sig_session_service: MultiSigSessionService::new(storage.clone()),
             root_hash_service: RootHashService::new(storage.clone()),
+            backup_service: BackupService::new(storage.clone()),
+            pending_transaction_service: PendingTransactionService::new(storage.clone()),
+            output_lock_service: OutputLockService::new(storage.clone()),
+            fee_estimate_service: FeeEstimateService::new(storage.clone()),
+            transaction_metadata_service: TransactionMetadataService::new(storage.clone()),
+            address_label_service: AddressLabelService::new(storage.clone()),
+            address_book_service: AddressBookService::new(storage.clone()),
+            history_index_service: HistoryIndexService::new(storage.clone()),
+            chain_tip_service: ChainTipService::new(storage.clone()),
+            wallet_locks,
             tendermint_client,
             transaction_builder,
             block_height_ensure,
@@ -105,1654 +163,7593 @@ where
         }
     }
 
-    fn is_tx_exist(&self, name: &str, enckey: &SecKey, txid: TxId) -> Result<bool> {
-        let tx_change = self
-            .wallet_state_service
-            .get_transaction_history(name, enckey, false)?
-            .filter(|change| BalanceChange::NoChange != change.balance_change)
-            .find(|tx_change| tx_change.transaction_id == txid)
-            .chain(|| {
-                (
-                    ErrorKind::InvalidInput,
-                    "no transaction find by transaction id",
-                )
-            });
-        Ok(tx_change.is_ok())
+    /// Shares the wallet locks with other clients (e.g. the ones of the other RPC services on the
+    /// same storage), so that their methods are also serialized per wallet
+    pub fn with_wallet_locks(mut self, wallet_locks: WalletLockMap) -> Self {
+        self.key_service = self.key_service.with_wallet_locks(wallet_locks.clone());
+        self.wallet_service = self.wallet_service.with_wallet_locks(wallet_locks.clone());
+        self.wallet_locks = wallet_locks;
+        self
     }
-}
 
-impl<S> DefaultWalletClient<S, UnauthorizedClient, UnauthorizedWalletTransactionBuilder>
-where
-    S: Storage + 'static,
-{
-    /// Creates a new read-only instance of `DefaultWalletClient`
-    pub fn new_read_only(storage: S) -> Self {
-        let hw_key_service = HwKeyService::default();
-        Self::new(
-            storage,
-            UnauthorizedClient,
-            UnauthorizedWalletTransactionBuilder,
-            None,
-            hw_key_service,
-        )
+    /// Boxes the client into a `DynWalletClient`
+    pub fn into_dyn(self) -> DynWalletClient
+    where
+        C: 'static,
+        T: 'static,
+    {
+        Box::new(self)
     }
-}
 
-impl<S, C, T> AddressRecovery for DefaultWalletClient<S, C, T>
-where
-    S: Storage + 'static,
-    C: Client,
-    T: WalletTransactionBuilder,
-{
-    // new_address: transfer address in TxOut, it will check whether it belongs with 20 window, then it will create 20 new addresses
-    // return: true means new addresses are generated, so need to refresh current wallet state to bring new addresses
-    // return: false mean no new addresses, don't need to refresh wallet state
-    fn recover_addresses(
-        &mut self,
-        extended_addr: &ExtendedAddr,
+    /// Releases the inputs of a transaction which failed to be broadcast, returning the failure
+    fn unlock_inputs(
+        &self,
         name: &str,
         enckey: &SecKey,
-        _wallet: &mut Wallet,
-    ) -> Result<bool> {
-        let is_exist = self
-            .wallet_service
-            .find_root_hash(name, enckey, &extended_addr)?
-            .is_some();
-        if is_exist {
-            // no need
-            return Ok(false);
-        }
-
-        let index = self
-            .hd_key_service
-            .get_latest_transfer_index(name, enckey)?;
-        let mut found = false;
-        let count = 20;
-        for i in index..(index + count) {
-            let publickey = self.hd_key_service.peek_pubkey(name, enckey, i)?;
-            let (h256, _multisigaddr) = RootHashService::<S>::peek_new_root_hash(
-                vec![publickey.clone()],
-                publickey.clone(),
-                1,
-            )?;
-            assert!(32 == h256.len());
-            let extended_addr_from_hash = ExtendedAddr::OrTree(h256);
-            if extended_addr == &extended_addr_from_hash {
-                found = true;
-                break;
-            }
+        inputs: &[TxoPointer],
+        err: Error,
+    ) -> Error {
+        if let Err(unlock_err) = self.output_lock_service.unlock(name, enckey, inputs) {
+            log::warn!("unable to unlock inputs of wallet {}: {}", name, unlock_err);
         }
+        err
+    }
 
-        if !found {
-            return Ok(false);
+    /// Fails with `ErrorKind::OnlyWatchOnly` if the wallet is watch-only (it can't sign)
+    fn ensure_signing_wallet(&self, name: &str, enckey: &SecKey) -> Result<()> {
+        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
+        if wallet.wallet_kind == WalletKind::Watch {
+            Err(watch_only_error(name))
+        } else {
+            Ok(())
         }
+    }
 
-        let count = count;
-        for _i in 0..count {
-            let _newaddress: ExtendedAddr = self
-                .new_transfer_address(name, enckey)
-                .expect("get new transfer address");
+    /// Drops the records decrypted with the enckey from the cache of the storage (if it has one)
+    fn forget_enckey(&self, enckey: &SecKey) {
+        if let Some(cache) = self.storage.decrypted_cache() {
+            cache.remove_enckey(enckey);
         }
+    }
 
-        Ok(true)
+    /// Builds the history index of the wallet if it isn't indexed yet. This writes to the storage,
+    /// so it's done under the exclusive guard of the wallet, before the reads take the shared one.
+    fn ensure_history_indexed(&self, name: &str, enckey: &SecKey) -> Result<()> {
+        let _guard = self.wallet_locks.exclusive(name);
+        // Check if wallet exists
+        self.wallet_service.view_key(name, enckey)?;
+        self.history_index_service.ensure_indexed(name, enckey)
     }
-}
 
-impl<S, C, T> WalletClient for DefaultWalletClient<S, C, T>
-where
-    S: Storage + 'static,
-    C: Client,
-    T: WalletTransactionBuilder,
-{
-    fn get_transaction(&self, name: &str, enckey: &SecKey, txid: TxId) -> Result<Transaction> {
-        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
-        let private_key = self
-            .wallet_private_key(name, enckey, wallet.wallet_kind)?
-            .chain(|| (ErrorKind::StorageError, "can not find private key"))?;
-        let tx = self.transaction_builder.decrypt_tx(txid, &private_key)?;
-        Ok(tx)
+    /// Fee paid by a transfer transaction spending outputs of the wallet
+    fn transfer_fee(&self, name: &str, enckey: &SecKey, transaction: Tx) -> Result<Coin> {
+        TransactionFee::compute(&Transaction::TransferTransaction(transaction), |input| {
+            self.output(name, enckey, input).map(|output| output.value)
+        })
+        .map(|fee| fee.to_coin())
     }
 
-    fn update_hw_service(&mut self, hw_wallet_kind: HardwareKind) -> Result<()> {
-        let hw_key_service = match hw_wallet_kind {
-            HardwareKind::Trezor => HwKeyService::Unauthorized(UnauthorizedHwKeyService),
-            HardwareKind::LocalOnly => HwKeyService::Unauthorized(UnauthorizedHwKeyService),
-            #[cfg(feature = "mock-hardware-wallet")]
-            HardwareKind::Mock => {
-                let mock = LedgerServiceZemu::new(false)?;
-                HwKeyService::Mock(mock)
+    /// Address of given type registered for the key when it was imported (if any)
+    fn imported_address(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        public_key: &PublicKey,
+        address_type: AddressType,
+    ) -> Result<Option<ImportedAddress>> {
+        match address_type {
+            AddressType::Transfer => {
+                let address: ExtendedAddr =
+                    MultiSigAddress::new(vec![public_key.clone()], public_key.clone(), 1)?.into();
+                Ok(self
+                    .wallet_service
+                    .find_root_hash(name, enckey, &address)?
+                    .map(|_| ImportedAddress::Transfer(address)))
             }
-            HardwareKind::Ledger => {
-                let ledger_service = LedgerServiceHID::new(true)?;
-                HwKeyService::Ledger(ledger_service)
+            AddressType::Staking => {
+                let address = RedeemAddress::from(public_key);
+                Ok(self
+                    .wallet_service
+                    .find_staking_key(name, enckey, &address)?
+                    .map(|_| ImportedAddress::Staking(StakedStateAddress::BasicRedeem(address))))
             }
-        };
-        self.hw_key_service = hw_key_service;
+        }
+    }
+
+    /// Adds the writes which re-encrypt all the records of the wallet (except its private key) with
+    /// a new enckey to the batch
+    fn batch_change_enckey(
+        &self,
+        batch: &mut StorageBatch,
+        name: &str,
+        enckey: &SecKey,
+        new_enckey: &SecKey,
+    ) -> Result<()> {
+        self.wallet_service
+            .batch_change_enckey(batch, name, enckey, new_enckey)?;
+        self.hd_key_service
+            .batch_change_enckey(batch, name, enckey, new_enckey)?;
+        self.root_hash_service
+            .batch_change_enckey(batch, name, enckey, new_enckey)?;
+        self.wallet_state_service
+            .batch_change_enckey(batch, name, enckey, new_enckey)?;
+        self.backup_service
+            .batch_change_enckey(batch, name, enckey, new_enckey)?;
+        self.pending_transaction_service
+            .batch_change_enckey(batch, name, enckey, new_enckey)?;
+        self.output_lock_service
+            .batch_change_enckey(batch, name, enckey, new_enckey)?;
+        self.fee_estimate_service
+            .batch_change_enckey(batch, name, enckey, new_enckey)?;
+        self.transaction_metadata_service
+            .batch_change_enckey(batch, name, enckey, new_enckey)?;
+        self.address_label_service
+            .batch_change_enckey(batch, name, enckey, new_enckey)?;
+        self.address_book_service
+            .batch_change_enckey(batch, name, enckey, new_enckey)?;
+        self.chain_tip_service
+            .batch_change_enckey(batch, name, enckey, new_enckey)?;
+        #[cfg(feature = "experimental")]
+        self.multi_sig_session_service
+            .batch_change_enckey(batch, enckey, new_enckey)?;
         Ok(())
     }
 
-    fn get_wallet_kind(&self, name: &str, enckey: &SecKey) -> Result<WalletKind> {
-        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
-        Ok(wallet.wallet_kind)
+    /// Derives the enckey of a new wallet with the current KDF parameters, which are recorded
+    fn new_wallet_enckey(&self, name: &str, passphrase: &SecUtf8) -> Result<SecKey> {
+        if self.wallet_service.names()?.iter().any(|n| n == name) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Wallet with name ({}) already exists", name),
+            ));
+        }
+        let enckey = derive_enckey(passphrase, name).err_kind(ErrorKind::InvalidInput, || {
+            "unable to derive encryption key from passphrase"
+        })?;
+        self.key_service.set_kdf_params(name, &KdfParams::CURRENT)?;
+        Ok(enckey)
     }
 
-    fn get_hardware_kind(&self, name: &str, enckey: &SecKey) -> Result<HardwareKind> {
-        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
-        Ok(wallet.hardware_kind)
+    /// Re-encrypts all the records of a wallet written under older KDF parameters with the enckey
+    /// derived with the current ones, in one batch (nothing is changed if the passphrase is
+    /// incorrect)
+    fn upgrade_kdf_params(&self, name: &str, passphrase: &SecUtf8) -> Result<SecKey> {
+        let _guard = self.wallet_locks.exclusive(name);
+        let mut batch = StorageBatch::default();
+        let (enckey, new_enckey) = self.key_service.batch_reencrypt_all(
+            &mut batch,
+            name,
+            passphrase,
+            &KdfParams::CURRENT,
+        )?;
+        // test validity of enckey
+        self.wallet_service.view_key(name, &enckey)?;
+        self.batch_change_enckey(&mut batch, name, &enckey, &new_enckey)?;
+        self.storage.write_batch(batch)?;
+        self.forget_enckey(&enckey);
+        Ok(new_enckey)
     }
 
-    fn send_to_address(
+    /// Stores the seed of the mnemonic and the view key derived from it, and creates the HD wallet
+    fn create_hd_wallet(
         &self,
         name: &str,
         enckey: &SecKey,
-        amount: Coin,
-        address: ExtendedAddr,
-        view_keys: &mut BTreeSet<PublicKey>,
-        network_id: u8,
-    ) -> Result<TxId> {
-        let current_block_height = self.get_current_block_height()?;
-        let tx_out = TxOut::new(address, amount);
-
-        let view_key = self.view_key(name, enckey)?;
-
-        view_keys.insert(view_key);
-
-        let access_policies: BTreeSet<_> = view_keys
-            .iter()
-            .map(|key| TxAccessPolicy {
-                view_key: key.into(),
-                access: TxAccess::AllData,
-            })
-            .collect();
-
-        let attributes =
-            TxAttributes::new_with_access(network_id, access_policies.into_iter().collect());
+        mnemonic: &Mnemonic,
+        hardware_kind: HardwareKind,
+    ) -> Result<()> {
+        self.hd_key_service
+            .add_mnemonic(name, Some(mnemonic), enckey)?;
 
-        let return_address = self.new_transfer_address(name, enckey)?;
-        let (transaction, selected_inputs, return_amount) =
-            self.create_transaction(name, enckey, vec![tx_out], attributes, None, return_address)?;
+        let (public_key, private_key) =
+            self.hd_key_service
+                .generate_keypair(name, enckey, HDAccountType::Viewkey)?;
 
-        self.broadcast_transaction(&transaction)?;
-        //update the wallet state
-        let tx_pending = TransactionPending {
-            used_inputs: selected_inputs,
-            block_height: current_block_height,
-            return_amount,
-        };
+        self.key_service
+            .add_wallet_private_key(name, &private_key, enckey)?;
 
-        self.update_tx_pending_state(name, enckey, transaction.tx_id(), tx_pending)?;
+        self.wallet_service
+            .create(name, enckey, public_key, WalletKind::HD, hardware_kind)
+    }
 
-        if let TxAux::EnclaveTx(TxEnclaveAux::TransferTx {
-            payload: TxObfuscated { txid, .. },
-            ..
-        }) = transaction
+    /// Splits the wallet into (name-independent) backup records
+    fn backup_records(&self, name: &str, enckey: &SecKey) -> Result<BackupRecords> {
+        let mut wallet_info = self.export_wallet(name, enckey)?;
+        let mut records = BackupRecords::new();
+
+        for (public_key, private_key) in std::mem::take(&mut wallet_info.key_pairs) {
+            records.insert(
+                format!(
+                    "{}{}",
+                    BACKUP_KEY_PREFIX,
+                    hex::encode(public_key.serialize())
+                ),
+                private_key.serialize(),
+            );
+        }
+        for (root_hash, multisig_address) in std::mem::take(&mut wallet_info.multisig_address_pair)
         {
-            Ok(txid)
-        } else {
-            Err(Error::new(
-                ErrorKind::IllegalInput,
-                "Transaction is not transfer transaction",
-            ))
+            records.insert(
+                format!("{}{}", BACKUP_MULTISIG_PREFIX, root_hash),
+                multisig_address.encode(),
+            );
         }
-    }
+        wallet_info.name = String::new();
+        records.insert(
+            BACKUP_INFO.to_owned(),
+            serde_json::to_vec(&wallet_info).chain(|| {
+                (
+                    ErrorKind::SerializationError,
+                    "Unable to serialize wallet info",
+                )
+            })?,
+        );
 
-    /// broadcast transaction and waiting it confiremed
-    fn send_to_address_commit(
-        &self,
-        name: &str,
-        enckey: &SecKey,
-        amount: Coin,
-        address: ExtendedAddr,
-        view_keys: &mut BTreeSet<PublicKey>,
-        network_id: u8,
-    ) -> Result<TxId> {
-        let tx_id = self.send_to_address(name, enckey, amount, address, view_keys, network_id)?;
-        let block_height = self.get_current_block_height()?;
-        loop {
-            // query tx_id from tendermint
-            let confirmed = self
-                .tendermint_client
-                .query("meta", &tx_id.to_vec(), None, false)
-                .is_ok();
-            if !confirmed {
-                std::thread::sleep(Duration::from_secs(1));
-                let current_block_height = self.get_current_block_height()?;
-                if current_block_height - block_height >= self.block_height_ensure.unwrap_or(50) {
-                    return Err(Error::new(
-                        ErrorKind::TendermintRpcError,
-                        "waiting for transaction confirmed timeout",
-                    ));
-                }
-                continue;
-            }
-            break;
+        if let Some(sync_state) = load_sync_state(&self.storage, name)? {
+            records.insert(BACKUP_SYNC_STATE.to_owned(), sync_state.encode());
+        }
+        let checkpoints = load_sync_checkpoints(&self.storage, name)?;
+        if !checkpoints.is_empty() {
+            records.insert(BACKUP_SYNC_CHECKPOINTS.to_owned(), checkpoints.encode());
+        }
+        if let Some(wallet_state) = load_wallet_state(&self.storage, name, enckey)? {
+            records.insert(BACKUP_WALLET_STATE.to_owned(), wallet_state.encode());
         }
-        Ok(tx_id)
-    }
 
-    #[inline]
-    fn wallets(&self) -> Result<Vec<String>> {
-        self.wallet_service.names()
+        Ok(records)
     }
 
-    fn export_wallet(&self, name: &str, enckey: &SecKey) -> Result<WalletInfo> {
-        let wallet = self.wallet_service.get_wallet(name, enckey)?;
-        let private_key = self
-            .key_service
-            .wallet_private_key(name, enckey)?
+    /// Imports the wallet from its backup records with given name
+    fn import_backup_records(
+        &self,
+        mut records: BackupRecords,
+        new_name: &str,
+        passphrase: &SecUtf8,
+    ) -> Result<SecKey> {
+        let info = records
+            .remove(BACKUP_INFO)
+            .chain(|| (ErrorKind::InvalidInput, "Wallet info not found in backup"))?;
+        let mut wallet_info: WalletInfo = serde_json::from_slice(&info).chain(|| {
+            (
+                ErrorKind::DeserializationError,
+                "Unable to deserialize wallet info from backup",
+            )
+        })?;
+        wallet_info.name = new_name.to_owned();
+
+        let sync_state = records
+            .remove(BACKUP_SYNC_STATE)
+            .map(|bytes| SyncState::decode(&mut bytes.as_slice()))
+            .transpose()
             .chain(|| {
                 (
-                    ErrorKind::InvalidInput,
-                    "Can not find private key in wallet",
+                    ErrorKind::DeserializationError,
+                    "Unable to decode sync state from backup",
+                )
+            })?;
+        let checkpoints = records
+            .remove(BACKUP_SYNC_CHECKPOINTS)
+            .map(|bytes| Vec::<SyncCheckpoint>::decode(&mut bytes.as_slice()))
+            .transpose()
+            .chain(|| {
+                (
+                    ErrorKind::DeserializationError,
+                    "Unable to decode sync checkpoints from backup",
+                )
+            })?;
+        let wallet_state = records
+            .remove(BACKUP_WALLET_STATE)
+            .map(|bytes| WalletState::decode(&mut bytes.as_slice()))
+            .transpose()
+            .chain(|| {
+                (
+                    ErrorKind::DeserializationError,
+                    "Unable to decode wallet state from backup",
                 )
             })?;
 
-        let mut key_pairs = BTreeMap::new();
-        let mut key_chainpath = BTreeMap::new();
-        let public_keys = self.public_keys(name, enckey)?;
-
-        // get public-private key pair and public-chainpath pair
-        for public_key in public_keys.into_iter() {
-            if let Some(private_key) =
-                self.wallet_service
-                    .find_private_key(name, enckey, &public_key)?
-            {
-                key_pairs.insert(public_key.clone(), private_key);
-            }
-            if let Some(chain_path) = self
-                .wallet_service
-                .find_chain_path(name, enckey, &public_key)?
-                .map(|p| p.into_string())
-            {
-                key_chainpath.insert(public_key, chain_path);
+        for (id, record) in records.into_iter() {
+            if id.starts_with(BACKUP_KEY_PREFIX) {
+                let public_key = hex::decode(&id[BACKUP_KEY_PREFIX.len()..])
+                    .chain(|| {
+                        (
+                            ErrorKind::DeserializationError,
+                            "Unable to decode public key from backup",
+                        )
+                    })
+                    .and_then(|bytes| PublicKey::deserialize_from(&bytes))?;
+                let private_key = PrivateKey::deserialize_from(&record)?;
+                wallet_info.key_pairs.insert(public_key, private_key);
+            } else if id.starts_with(BACKUP_MULTISIG_PREFIX) {
+                let multisig_address =
+                    MultiSigAddress::decode(&mut record.as_slice()).chain(|| {
+                        (
+                            ErrorKind::DeserializationError,
+                            "Unable to decode multi-sig address from backup",
+                        )
+                    })?;
+                wallet_info.multisig_address_pair.insert(
+                    id[BACKUP_MULTISIG_PREFIX.len()..].to_owned(),
+                    multisig_address,
+                );
+            } else {
+                log::warn!("unknown backup record skipped: {}", id);
             }
         }
 
-        // get multisig address
-        let mut multisig_address_pair = BTreeMap::new();
-        let roothashes = wallet.get_transfer_addresses_roothash()?;
-        for root_hash in roothashes.iter() {
-            let multisig_address = self
-                .root_hash_service
-                .get_multi_sig_address_from_root_hash(name, root_hash, enckey)?;
-            multisig_address_pair.insert(hex::encode(&root_hash), multisig_address);
+        let enckey = self.import_wallet(new_name, passphrase, &mut wallet_info)?;
+        if let Some(sync_state) = sync_state {
+            save_sync_state(&self.storage, new_name, &sync_state)?;
         }
-
-        let staking_keys2 = wallet.get_staking_addresses_publickey()?;
-        let mut staking_keys: Vec<PublicKey> = vec![];
-        for key in staking_keys2.iter() {
-            staking_keys.push(key.clone());
+        if let Some(checkpoints) = checkpoints {
+            add_sync_checkpoints(&self.storage, new_name, checkpoints)?;
+        }
+        if let Some(wallet_state) = wallet_state {
+            save_wallet_state(&self.storage, new_name, &enckey, &wallet_state)?;
+            self.history_index_service
+                .reindex(new_name, &enckey, &wallet_state)?;
         }
+        Ok(enckey)
+    }
 
-        // get hdkey
-        let hdkey = self.hd_key_service.get_hdkey(name, enckey)?;
+    fn backup_snapshot(
+        &self,
+        name: &str,
+        passphrase: &SecUtf8,
+        backup_key: &SecUtf8,
+        since_snapshot_id: Option<&H256>,
+    ) -> Result<BackupBlob> {
+        let enckey = self.auth_token(name, passphrase)?;
+        let records = self.backup_records(name, &enckey)?;
+        self.backup_service
+            .snapshot(name, &enckey, records, since_snapshot_id, backup_key)
+    }
 
-        let wallet_info = WalletInfo {
-            name: name.into(),
-            wallet,
-            private_key,
-            passphrase: None,
-            key_pairs,
-            key_chainpath,
-            hdkey,
-            multisig_address_pair,
-            staking_keys,
+    fn transaction_proof(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        tx_id: TxId,
+        cancellation: &CancellationToken,
+    ) -> Result<TransactionProof> {
+        let block_height = self
+            .get_transaction_change(name, enckey, &tx_id)?
+            .chain(|| {
+                (
+                    ErrorKind::InvalidInput,
+                    format!("Transaction {} not found in wallet", hex::encode(tx_id)),
+                )
+            })?
+            .block_height;
+
+        let block = self.tendermint_client.block(block_height)?;
+        let transaction = match block
+            .staking_transactions()?
+            .into_iter()
+            .find(|transaction| transaction.id() == tx_id)
+        {
+            Some(transaction) => transaction,
+            None => self.get_transaction(name, enckey, tx_id)?,
         };
-        Ok(wallet_info)
+
+        let valid_tx_ids = self
+            .tendermint_client
+            .block_results(block_height)?
+            .fees()?
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        let inclusion_proof = MerkleTree::new(valid_tx_ids)
+            .generate_proof(tx_id)
+            .chain(|| {
+                (
+                    ErrorKind::VerifyError,
+                    format!(
+                        "Transaction {} is not a valid transaction of block {}",
+                        hex::encode(tx_id),
+                        block_height
+                    ),
+                )
+            })?;
+
+        let chain_state = self
+            .tendermint_client
+            .query_state_batch(std::iter::once(block_height))?
+            .pop()
+            .chain(|| {
+                (
+                    ErrorKind::TendermintRpcError,
+                    format!("Chain state of block {} not found", block_height),
+                )
+            })?;
+
+        // app hash of the block is committed in header of the next block (so a transaction of the
+        // latest block can only be proven after the next one)
+        self.wait_for_block(block_height + 1, cancellation)?;
+        let next_header = self.tendermint_client.signed_header(block_height + 1)?;
+        let next_validators = self.tendermint_client.validators(block_height + 1)?;
+
+        Ok(TransactionProof {
+            block_height,
+            transaction,
+            block_transactions: block
+                .data
+                .iter()
+                .map(|raw| raw.clone().into_vec())
+                .collect(),
+            inclusion_proof,
+            chain_state,
+            header: JsonEncoded(block.header),
+            next_header: JsonEncoded(next_header),
+            next_validators: JsonEncoded(next_validators),
+        })
     }
 
-    fn import_wallet(
+    /// Waits (up to `NEXT_BLOCK_TIMEOUT`) until the block at given height is committed
+    fn wait_for_block(&self, height: u64, cancellation: &CancellationToken) -> Result<()> {
+        let started = Instant::now();
+        while self.get_current_block_height()? < height {
+            cancellation.check()?;
+            if started.elapsed() > NEXT_BLOCK_TIMEOUT {
+                return Err(Error::new(
+                    ErrorKind::TendermintRpcError,
+                    format!("Timeout while waiting for block {}", height),
+                ));
+            }
+            std::thread::sleep(Duration::from_secs(1));
+        }
+        Ok(())
+    }
+
+    /// Returns unspent transactions which can be used by automatic input selection (i.e. having at
+    /// least `min_confirmations` confirmations and not time-locked at the time of the latest block)
+    fn spendable_transactions(&self, name: &str, enckey: &SecKey) -> Result<UnspentTransactions> {
+        let min_confirmations = self.wallet_service.min_confirmations(name, enckey)?;
+        let mut spendable_transactions = if min_confirmations == 0 {
+            self.unspent_transactions(name, enckey)?
+        } else {
+            let current_block_height = self
+                .tendermint_client
+                .status()?
+                .sync_info
+                .latest_block_height
+                .value();
+            let spendable_transactions = self.wallet_state_service.get_spendable_transactions(
+                name,
+                enckey,
+                current_block_height,
+                min_confirmations,
+            )?;
+            UnspentTransactions::new(spendable_transactions.into_iter().collect())
+        };
+
+        // the node is only asked for the time of the latest block when it's needed
+        if spendable_transactions
+            .iter()
+            .any(|(_, output)| output.valid_from.is_some())
+        {
+            let current_block_time = self.get_current_block_time()?;
+            spendable_transactions.retain(|(_, output)| {
+                output
+                    .valid_from
+                    .map_or(true, |valid_from| valid_from <= current_block_time)
+            });
+        }
+        Ok(spendable_transactions)
+    }
+
+    /// Returns the time (unix timestamp) of the latest block, against which the time-locks of the
+    /// outputs are checked
+    fn get_current_block_time(&self) -> Result<Timespec> {
+        let latest_block_time = self.tendermint_client.status()?.sync_info.latest_block_time;
+        Ok(DateTime::<Utc>::from(latest_block_time).timestamp() as Timespec)
+    }
+
+    fn is_tx_exist(&self, name: &str, enckey: &SecKey, txid: TxId) -> Result<bool> {
+        let tx_change = self
+            .wallet_state_service
+            .get_transaction_history(name, enckey, false)?
+            .filter(|change| BalanceChange::NoChange != change.balance_change)
+            .find(|tx_change| tx_change.transaction_id == txid)
+            .chain(|| {
+                (
+                    ErrorKind::InvalidInput,
+                    "no transaction find by transaction id",
+                )
+            });
+        Ok(tx_change.is_ok())
+    }
+
+    /// Broadcasts a transfer transaction built by the wallet and tracks it (with the idempotency
+    /// key it was sent with, if any)
+    fn broadcast_tracked(
         &self,
         name: &str,
-        passphrase: &SecUtf8,
-        wallet_info: &mut WalletInfo,
-    ) -> Result<SecKey> {
-        let all_wallet = self.wallet_service.names()?;
-        if all_wallet.contains(&name.to_string()) {
+        enckey: &SecKey,
+        signed_tx: SignedTransferTransaction,
+        fee: Option<Coin>,
+        idempotency_key: Option<&str>,
+    ) -> Result<BroadcastResult> {
+        let _guard = self.wallet_locks.exclusive(name);
+        self.chain_tip_service.ensure_no_mismatch(name)?;
+        let transaction = signed_tx.signed_transaction;
+        if !matches!(
+            transaction,
+            TxAux::EnclaveTx(TxEnclaveAux::TransferTx { .. })
+        ) {
             return Err(Error::new(
-                ErrorKind::PermissionDenied,
-                format!("wallet {} already exist", name),
+                ErrorKind::IllegalInput,
+                "Transaction is not transfer transaction",
             ));
         }
-        check_passphrase_strength(name, passphrase)?;
-        let enckey = derive_enckey(passphrase, name).err_kind(ErrorKind::InvalidInput, || {
-            "unable to derive encryption key from passphrase"
-        })?;
-        let view_key = PublicKey::from(&wallet_info.private_key);
-        if view_key != wallet_info.wallet.view_key {
-            return Err(Error::new(ErrorKind::InvalidInput, "public key not match"));
-        }
-        self.key_service.add_wallet_private_key(
-            &wallet_info.name,
-            &wallet_info.private_key,
-            &enckey,
-        )?;
 
-        let newstorage = self.storage.clone();
-        // connect storage
-        wallet_info.wallet.wallet_storage =
-            Some(Arc::new(Mutex::new(WalletStorageImpl::new(newstorage))));
-        wallet_info.wallet.name = wallet_info.name.clone();
-        wallet_info.wallet.enckey = Some(enckey.clone());
+        let broadcast = self.get_current_block_height().and_then(|block_height| {
+            self.broadcast_transaction(&transaction)
+                .map(|_| block_height)
+        });
+        let (current_block_height, unreachable) = match broadcast {
+            Ok(block_height) => (block_height, None),
+            // the node is unreachable: the transaction is tracked as pending anyway (so that its
+            // inputs aren't spent by another one), to be sent by `rebroadcast_pending`
+            Err(err) if err.kind() == ErrorKind::ConnectionError => {
+                let block_height = self
+                    .sync_state_service
+                    .get_global_state(name)?
+                    .map_or(0, |sync_state| sync_state.last_block_height);
+                (block_height, Some(err))
+            }
+            Err(err) => return Err(self.unlock_inputs(name, enckey, &signed_tx.used_inputs, err)),
+        };
 
-        self.wallet_service
-            .set_wallet(name, &enckey, wallet_info.wallet.clone())?;
+        //update the wallet state
+        let tx_pending = TransactionPending {
+            used_inputs: signed_tx.used_inputs,
+            block_height: current_block_height,
+            return_amount: signed_tx.return_amount,
+        };
 
-        for (public_key, private_key) in wallet_info.key_pairs.iter() {
-            self.wallet_service
-                .add_key_pairs(name, &enckey, public_key, private_key)?;
-            self.wallet_service
-                .add_public_key(name, &enckey, public_key)?;
+        self.track_pending_transaction(name, enckey, &transaction, tx_pending, idempotency_key)?;
+        if let Some(fee) = fee {
+            self.fee_estimate_service
+                .set(name, enckey, transaction.tx_id(), fee)?;
         }
-
-        for (public_key, chain_path) in wallet_info.key_chainpath.iter() {
-            let path = ChainPath::from(chain_path.as_str());
-            self.wallet_service
-                .add_key_path(name, &enckey, public_key, &path)?;
-            self.wallet_service
-                .add_public_key(name, &enckey, public_key)?;
+        if let Some(err) = unreachable {
+            return Err(Error::new(
+                ErrorKind::ConnectionError,
+                format!(
+                    "Unable to broadcast transaction {} ({}), it's kept to be re-broadcast",
+                    hex::encode(transaction.tx_id()),
+                    err
+                ),
+            ));
         }
 
-        if let Some(hdkey) = wallet_info.hdkey.clone() {
-            self.hd_key_service.add_hdkey(name, &enckey, hdkey)?;
-        }
+        Ok(BroadcastResult::new(
+            &transaction,
+            fee,
+            current_block_height,
+        ))
+    }
 
-        // store multisig address
-        for (root_hash_str, multisig_addr) in wallet_info.multisig_address_pair.iter() {
-            let root_hash_raw = hex::decode(root_hash_str)
-                .chain(|| (ErrorKind::InvalidInput, "failed to parse root_hash"))?;
+    /// Updates the wallet state with a broadcast transaction, and tracks it (with the idempotency
+    /// key it was sent with, if any) until it's confirmed
+    fn track_pending_transaction(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        tx_aux: &TxAux,
+        tx_pending: TransactionPending,
+        idempotency_key: Option<&str>,
+    ) -> Result<()> {
+        let _guard = self.wallet_locks.exclusive(name);
+        let tx_id = tx_aux.tx_id();
+        let mut wallet_state_memento = WalletStateMemento::default();
+        wallet_state_memento.add_pending_transaction(tx_id, tx_pending.clone());
+        self.wallet_state_service
+            .apply_memento(name, enckey, &wallet_state_memento)?;
+        let wallet_state = self.wallet_service.get_wallet_state(name, enckey)?;
+        self.pending_transaction_service.add(
+            name,
+            enckey,
+            tx_id,
+            tx_aux.encode(),
+            tx_pending,
+            unix_timestamp()?,
+            idempotency_key.map(ToOwned::to_owned),
+            |tx_id| wallet_state.transaction_history.contains_key(tx_id),
+        )
+    }
 
-            let root_hash: H256 = root_hash_raw
-                .as_slice()
-                .try_into()
-                .chain(|| (ErrorKind::InvalidInput, "failed to parse root_hash"))?;
-            self.wallet_service
-                .add_root_hash(name, &enckey, root_hash)?;
-            self.root_hash_service
-                .set_multi_sig_address_from_root_hash(name, &enckey, &root_hash, multisig_addr)?;
-        }
+    /// Result of the broadcast of a tracked transaction (sent again with the same idempotency key)
+    fn tracked_broadcast_result(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        transaction: PendingTransaction,
+    ) -> Result<BroadcastResult> {
+        let tx_aux = TxAux::decode(&mut transaction.tx.as_slice()).chain(|| {
+            (
+                ErrorKind::DeserializationError,
+                "Unable to decode pending transaction",
+            )
+        })?;
+        let fee = self
+            .fee_estimate_service
+            .get_all(name, enckey)?
+            .get(&transaction.tx_id)
+            .cloned();
+        Ok(BroadcastResult::new(
+            &tx_aux,
+            fee,
+            transaction.pending.block_height,
+        ))
+    }
 
-        let public_keys = wallet_info.wallet.get_staking_addresses_publickey()?;
-        for public_key in public_keys.iter() {
-            self.wallet_service
-                .add_staking_key(name, &enckey, public_key)?;
+    /// Broadcasts a tracked transaction again, tracking it as pending in the wallet state again
+    /// if it was rolled back in the meantime
+    fn resubmit_pending_transaction(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        wallet_state: &WalletState,
+        transaction: PendingTransaction,
+        now: Timespec,
+    ) -> Result<()> {
+        let tx_id = transaction.tx_id;
+        let tx_aux = TxAux::decode(&mut transaction.tx.as_slice()).chain(|| {
+            (
+                ErrorKind::DeserializationError,
+                "Unable to decode pending transaction",
+            )
+        })?;
+        self.broadcast_transaction(&tx_aux)?;
+
+        if !wallet_state.pending_transactions.contains_key(&tx_id) {
+            let mut tx_pending = transaction.pending;
+            tx_pending.block_height = self.get_current_block_height()?;
+            let mut wallet_state_memento = WalletStateMemento::default();
+            wallet_state_memento.add_pending_transaction(tx_id, tx_pending);
+            self.wallet_state_service
+                .apply_memento(name, enckey, &wallet_state_memento)?;
         }
+        self.pending_transaction_service
+            .set_rebroadcast(name, enckey, &tx_id, now)
+    }
 
-        for staking_key in wallet_info.staking_keys.iter() {
-            self.wallet_service
-                .add_staking_key(name, &enckey, staking_key)?;
+    /// Checks with the tx result index of the node (if it keeps one) that a stale transaction
+    /// wasn't included in a block which the wallet hasn't synced yet
+    fn ensure_not_included(&self, tx_id: &TxId) -> Result<()> {
+        match self.tendermint_client.tx_result(tx_id) {
+            Ok(Some(result)) if result.is_accepted() => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Transaction {} was included in block {}, it'll be confirmed by the wallet sync",
+                    hex::encode(tx_id),
+                    result.height
+                ),
+            )),
+            Ok(_) => Ok(()),
+            Err(err) => {
+                log::debug!(
+                    "result of transaction {} not available: {}",
+                    hex::encode(tx_id),
+                    err
+                );
+                Ok(())
+            }
         }
-        Ok(enckey)
     }
 
-    fn new_wallet(
+    /// Generates a new key of given account type, and adds the writes of its private key and its
+    /// chain path (as the wallet kind has them), with the update of the HD key, to the batch
+    fn batch_new_key(
         &self,
+        batch: &mut StorageBatch,
         name: &str,
-        passphrase: &SecUtf8,
+        enckey: &SecKey,
         wallet_kind: WalletKind,
-        hardware_kind: HardwareKind,
-        mnemonics_word_count: Option<u32>,
-    ) -> Result<(SecKey, Option<Mnemonic>)> {
-        check_passphrase_strength(name, passphrase)?;
-
-        let enckey = derive_enckey(passphrase, name).err_kind(ErrorKind::InvalidInput, || {
-            "unable to derive encryption key from passphrase"
-        })?;
-
+        account_type: HDAccountType,
+    ) -> Result<PublicKey> {
         match wallet_kind {
             WalletKind::Basic => {
                 let private_key = PrivateKey::new()?;
-                let view_key = PublicKey::from(&private_key);
-
-                self.key_service
-                    .add_wallet_private_key(name, &private_key, &enckey)?;
-
-                self.wallet_service
-                    .create(name, &enckey, view_key, wallet_kind, hardware_kind)?;
-
-                Ok((enckey, None))
+                let public_key = PublicKey::from(&private_key);
+                self.wallet_service.batch_add_key_pairs(
+                    batch,
+                    name,
+                    enckey,
+                    &public_key,
+                    &private_key,
+                )?;
+                Ok(public_key)
             }
             WalletKind::HD => {
-                let mnemonic = Mnemonic::new(mnemonics_word_count.unwrap_or(24))?;
-
-                self.hd_key_service
-                    .add_mnemonic(name, Some(&mnemonic), &enckey)?;
-
-                let (public_key, private_key) =
-                    self.hd_key_service
-                        .generate_keypair(name, &enckey, HDAccountType::Viewkey)?;
-
-                self.key_service
-                    .add_wallet_private_key(name, &private_key, &enckey)?;
-
-                self.wallet_service.create(
+                let (public_key, private_key, hd_path) = self
+                    .hd_key_service
+                    .batch_generate_keypairs_with_path(batch, name, enckey, account_type, 1)?
+                    .pop()
+                    .chain(|| (ErrorKind::InternalError, "HD key not generated"))?;
+                self.wallet_service.batch_add_key_pairs(
+                    batch,
                     name,
-                    &enckey,
-                    public_key,
-                    wallet_kind,
-                    hardware_kind,
+                    enckey,
+                    &public_key,
+                    &private_key,
                 )?;
-
-                Ok((enckey, Some(mnemonic)))
+                self.wallet_service.batch_add_key_path(
+                    batch,
+                    name,
+                    enckey,
+                    &public_key,
+                    &hd_path,
+                )?;
+                Ok(public_key)
             }
             WalletKind::HW => {
-                // the view-key pair is the local key pair, not come from the hardware wallet.
-                let private_key = PrivateKey::new()?;
-                let view_key = PublicKey::from(&private_key);
-                self.hd_key_service.add_mnemonic(name, None, &enckey)?;
-                self.key_service
-                    .add_wallet_private_key(name, &private_key, &enckey)?;
-
-                self.wallet_service
-                    .create(name, &enckey, view_key, wallet_kind, hardware_kind)?;
-
-                Ok((enckey, None))
+                let hd_path = self
+                    .hd_key_service
+                    .batch_generate_chain_paths(batch, name, enckey, account_type, 1)?
+                    .pop()
+                    .chain(|| (ErrorKind::InternalError, "HD chain path not generated"))?;
+                let public_key = self.hw_key_service.get_public_key(hd_path.clone())?;
+                self.wallet_service.batch_add_key_path(
+                    batch,
+                    name,
+                    enckey,
+                    &public_key,
+                    &hd_path,
+                )?;
+                Ok(public_key)
             }
+            WalletKind::Watch => Err(watch_only_error(name)),
         }
     }
+}
 
-    fn restore_wallet(
-        &self,
-        name: &str,
-        passphrase: &SecUtf8,
-        mnemonic: &Mnemonic,
-    ) -> Result<SecKey> {
-        check_passphrase_strength(name, passphrase)?;
-
-        let enckey = derive_enckey(passphrase, name).err_kind(ErrorKind::InvalidInput, || {
-            "unable to derive encryption key from passphrase"
-        })?;
-
-        self.hd_key_service
-            .add_mnemonic(name, Some(mnemonic), &enckey)?;
-
-        let (public_key, private_key) =
-            self.hd_key_service
-                .generate_keypair(name, &enckey, HDAccountType::Viewkey)?;
-
-        self.key_service
-            .add_wallet_private_key(name, &private_key, &enckey)?;
-
-        self.wallet_service.create(
-            name,
-            &enckey,
-            public_key,
-            WalletKind::HD,
-            HardwareKind::LocalOnly,
-        )?;
-        Ok(enckey)
+impl<S> DefaultWalletClient<S, UnauthorizedClient, UnauthorizedWalletTransactionBuilder>
+where
+    S: Storage + 'static,
+{
+    /// Creates a new read-only instance of `DefaultWalletClient`
+    pub fn new_read_only(storage: S) -> Self {
+        let hw_key_service = HwKeyService::default();
+        Self::new(
+            storage,
+            UnauthorizedClient,
+            UnauthorizedWalletTransactionBuilder,
+            None,
+            hw_key_service,
+        )
     }
+}
 
-    fn restore_basic_wallet(
-        &self,
+impl<S, C, T> AddressRecovery for DefaultWalletClient<S, C, T>
+where
+    S: Storage + 'static,
+    C: Client,
+    T: WalletTransactionBuilder,
+{
+    // new_address: transfer address in TxOut, it will check whether it belongs with 20 window, then it will create 20 new addresses
+    // return: true means new addresses are generated, so need to refresh current wallet state to bring new addresses
+    // return: false mean no new addresses, don't need to refresh wallet state
+    fn recover_addresses(
+        &mut self,
+        extended_addr: &ExtendedAddr,
         name: &str,
-        passphrase: &SecUtf8,
-        view_key_priv: &PrivateKey,
-    ) -> Result<SecKey> {
-        check_passphrase_strength(name, passphrase)?;
-
-        let enckey = derive_enckey(passphrase, name).err_kind(ErrorKind::InvalidInput, || {
-            "unable to derive encryption key from passphrase"
-        })?;
-
-        let view_key = PublicKey::from(view_key_priv);
-        self.key_service
-            .add_wallet_private_key(name, &view_key_priv, &enckey)?;
-        self.wallet_service.create(
-            name,
-            &enckey,
-            view_key,
-            WalletKind::Basic,
-            HardwareKind::LocalOnly,
-        )?;
-        Ok(enckey)
-    }
-
-    fn delete_wallet(&self, name: &str, passphrase: &SecUtf8) -> Result<()> {
-        // remove from wallet/sync_state/wallet_state/key_service
-
-        let enckey = derive_enckey(passphrase, name).err_kind(ErrorKind::InvalidInput, || {
-            "unable to derive encryption key from passphrase"
-        })?;
-
-        // the passphrase is verified here.
-        self.wallet_service.delete(name, &enckey)?;
-        self.sync_state_service.delete_global_state(name)?;
-        self.wallet_state_service
-            .delete_wallet_state(name, &enckey)?;
-        if self.hd_key_service.has_wallet(name)? {
-            self.hd_key_service.delete_wallet(name, &enckey)?;
+        enckey: &SecKey,
+        _wallet: &mut Wallet,
+    ) -> Result<bool> {
+        let _guard = self.wallet_locks.exclusive(name);
+        let is_exist = self
+            .wallet_service
+            .find_root_hash(name, enckey, &extended_addr)?
+            .is_some();
+        if is_exist {
+            // no need
+            return Ok(false);
         }
-        self.key_service.delete_wallet_private_key(name, &enckey)?;
 
-        Ok(())
-    }
+        let index = self
+            .hd_key_service
+            .get_latest_transfer_index(name, enckey)?;
+        let mut found = false;
+        let count = 20;
+        for i in index..(index + count) {
+            let publickey = self.hd_key_service.peek_pubkey(name, enckey, i)?;
+            let (h256, _multisigaddr) = RootHashService::<S>::peek_new_root_hash(
+                vec![publickey.clone()],
+                publickey.clone(),
+                1,
+            )?;
+            assert!(32 == h256.len());
+            let extended_addr_from_hash = ExtendedAddr::OrTree(h256);
+            if extended_addr == &extended_addr_from_hash {
+                found = true;
+                break;
+            }
+        }
 
-    fn auth_token(&self, name: &str, passphrase: &SecUtf8) -> Result<SecKey> {
-        let enckey = derive_enckey(passphrase, name).err_kind(ErrorKind::InvalidInput, || {
-            "unable to derive encryption key from passphrase"
-        })?;
+        if !found {
+            return Ok(false);
+        }
 
-        // test validity of enckey
-        self.view_key(name, &enckey)?;
-        Ok(enckey)
-    }
+        let count = count;
+        for _i in 0..count {
+            let _newaddress: ExtendedAddr = self
+                .new_transfer_address(name, enckey)
+                .expect("get new transfer address");
+        }
 
-    #[inline]
-    fn view_key(&self, name: &str, enckey: &SecKey) -> Result<PublicKey> {
-        self.wallet_service.view_key(name, enckey)
+        Ok(true)
     }
+}
 
-    #[inline]
-    fn view_key_private(&self, name: &str, enckey: &SecKey) -> Result<PrivateKey> {
-        self.key_service
-            .wallet_private_key(name, enckey)?
-            .err_kind(ErrorKind::InvalidInput, || "private view key not found")
+impl<S, C, T> WalletClient for DefaultWalletClient<S, C, T>
+where
+    S: Storage + 'static,
+    C: Client,
+    T: WalletTransactionBuilder,
+{
+    fn get_transaction(&self, name: &str, enckey: &SecKey, txid: TxId) -> Result<Transaction> {
+        let _guard = self.wallet_locks.shared(name);
+        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
+        let private_key = self
+            .wallet_private_key(name, enckey, wallet.wallet_kind)?
+            .chain(|| (ErrorKind::StorageError, "can not find private key"))?;
+        let tx = self.transaction_builder.decrypt_tx(txid, &private_key)?;
+        Ok(tx)
     }
 
-    #[inline]
-    fn public_keys(&self, name: &str, enckey: &SecKey) -> Result<IndexSet<PublicKey>> {
-        self.wallet_service.public_keys(name, enckey)
+    fn update_hw_service(&mut self, hw_wallet_kind: HardwareKind) -> Result<()> {
+        let hw_key_service = match hw_wallet_kind {
+            HardwareKind::Trezor => HwKeyService::Unauthorized(UnauthorizedHwKeyService),
+            HardwareKind::LocalOnly => HwKeyService::Unauthorized(UnauthorizedHwKeyService),
+            #[cfg(feature = "mock-hardware-wallet")]
+            HardwareKind::Mock => {
+                let mock = LedgerServiceZemu::new(false)?;
+                HwKeyService::Mock(mock)
+            }
+            HardwareKind::Ledger => {
+                let ledger_service = LedgerServiceHID::new(true)?;
+                HwKeyService::Ledger(ledger_service)
+            }
+        };
+        self.hw_key_service = hw_key_service;
+        Ok(())
     }
 
-    #[inline]
-    fn staking_keys(&self, name: &str, enckey: &SecKey) -> Result<IndexSet<PublicKey>> {
-        self.wallet_service.staking_keys(name, enckey, 0, 0, false)
+    fn get_wallet_kind(&self, name: &str, enckey: &SecKey) -> Result<WalletKind> {
+        let _guard = self.wallet_locks.shared(name);
+        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
+        Ok(wallet.wallet_kind)
     }
 
-    #[inline]
-    fn root_hashes(&self, name: &str, enckey: &SecKey) -> Result<IndexSet<H256>> {
-        self.wallet_service.root_hashes(name, enckey, 0, 0, false)
+    fn get_hardware_kind(&self, name: &str, enckey: &SecKey) -> Result<HardwareKind> {
+        let _guard = self.wallet_locks.shared(name);
+        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
+        Ok(wallet.hardware_kind)
     }
 
-    #[inline]
-    fn staking_addresses(
+    fn send_to_address(
         &self,
         name: &str,
         enckey: &SecKey,
-        offset: u64,
-        limit: u64,
-        reversed: bool,
-    ) -> Result<IndexSet<StakedStateAddress>> {
-        self.wallet_service
-            .staking_addresses(name, enckey, offset, limit, reversed)
+        amount: Coin,
+        address: ExtendedAddr,
+        valid_from: Option<Timespec>,
+        view_keys: &mut BTreeSet<PublicKey>,
+        network_id: u8,
+        allow_self_transfer: bool,
+        fee_tip: FeeTip,
+        idempotency_key: Option<&str>,
+    ) -> Result<BroadcastResult> {
+        let _guard = self.wallet_locks.exclusive(name);
+        if let Some(idempotency_key) = idempotency_key {
+            if let Some(transaction) = self.pending_transaction_service.find_by_idempotency_key(
+                name,
+                enckey,
+                idempotency_key,
+            )? {
+                return self.tracked_broadcast_result(name, enckey, transaction);
+            }
+        }
+        let (signed_tx, fee) = self.build_transaction(
+            name,
+            enckey,
+            amount,
+            address,
+            valid_from,
+            view_keys,
+            network_id,
+            allow_self_transfer,
+            fee_tip,
+        )?;
+        self.broadcast_tracked(name, enckey, signed_tx, Some(fee), idempotency_key)
     }
 
-    #[inline]
-    fn transfer_addresses(
+    fn build_transaction(
         &self,
         name: &str,
         enckey: &SecKey,
-        offset: u64,
-        limit: u64,
-        reversed: bool,
-    ) -> Result<IndexSet<ExtendedAddr>> {
-        self.wallet_service
-            .transfer_addresses(name, enckey, offset, limit, reversed)
-    }
+        amount: Coin,
+        address: ExtendedAddr,
+        valid_from: Option<Timespec>,
+        view_keys: &mut BTreeSet<PublicKey>,
+        network_id: u8,
+        allow_self_transfer: bool,
+        fee_tip: FeeTip,
+    ) -> Result<(SignedTransferTransaction, Coin)> {
+        let _guard = self.wallet_locks.exclusive(name);
+        self.ensure_signing_wallet(name, enckey)?;
+        self.chain_tip_service.ensure_no_mismatch(name)?;
+        let mut tx_out = TxOut::new(address, amount);
+        tx_out.valid_from = valid_from;
 
-    #[inline]
-    fn find_staking_key(
-        &self,
-        name: &str,
-        enckey: &SecKey,
-        redeem_address: &RedeemAddress,
-    ) -> Result<Option<PublicKey>> {
-        self.wallet_service
-            .find_staking_key(name, enckey, redeem_address)
+        let view_key = self.view_key(name, enckey)?;
+
+        view_keys.insert(view_key);
+
+        let attributes = TxAttributes::builder(network_id)
+            .allow_view_many(view_keys.iter().map(Into::into))
+            .build()
+            .chain(|| (ErrorKind::InvalidInput, "Invalid transaction attributes"))?;
+
+        let return_address = self.new_transfer_address(name, enckey)?;
+        let (transaction, selected_inputs, return_amount) = self.create_transaction(
+            name,
+            enckey,
+            vec![tx_out.clone()],
+            attributes.clone(),
+            None,
+            return_address.clone(),
+            allow_self_transfer,
+            fee_tip,
+        )?;
+
+        let mut outputs = vec![tx_out];
+        if return_amount != Coin::zero() {
+            outputs.push(TxOut::new(return_address, return_amount));
+        }
+        let fee = self.transfer_fee(
+            name,
+            enckey,
+            Tx::new_with(selected_inputs.clone(), outputs, attributes),
+        )?;
+
+        let signed_tx = SignedTransferTransaction {
+            signed_transaction: transaction,
+            return_amount,
+            used_inputs: selected_inputs,
+        };
+        Ok((signed_tx, fee))
     }
 
-    #[inline]
-    fn find_root_hash(
+    fn broadcast(
         &self,
         name: &str,
         enckey: &SecKey,
-        address: &ExtendedAddr,
-    ) -> Result<Option<H256>> {
-        self.wallet_service.find_root_hash(name, enckey, address)
+        signed_tx: SignedTransferTransaction,
+        fee: Option<Coin>,
+    ) -> Result<BroadcastResult> {
+        self.broadcast_tracked(name, enckey, signed_tx, fee, None)
     }
 
-    #[inline]
-    fn wallet_private_key(
+    /// broadcast transaction and waiting it confiremed
+    fn sweep(
         &self,
         name: &str,
         enckey: &SecKey,
-        wallet_kind: WalletKind,
-    ) -> Result<Option<PrivateKey>> {
-        if wallet_kind != WalletKind::HW {
-            let k = self.key_service.wallet_private_key(name, enckey)?;
-            Ok(k)
-        } else {
-            unreachable!("can not get private key from HW key")
-        }
+        to_address: ExtendedAddr,
+        attributes: TxAttributes,
+    ) -> Result<TxId> {
+        let _guard = self.wallet_locks.exclusive(name);
+        self.ensure_signing_wallet(name, enckey)?;
+        self.chain_tip_service.ensure_no_mismatch(name)?;
+        let unspent_transactions = self.spendable_transactions(name, enckey)?;
+
+        let is_own_address = self
+            .wallet_service
+            .find_root_hash(name, enckey, &to_address)?
+            .is_some();
+        // the value of the output is checked against the policy before the fee is deducted
+        let balance = sum_coins(unspent_transactions.iter().map(|(_, output)| output.value))
+            .chain(|| {
+                (
+                    ErrorKind::IllegalInput,
+                    "Sum of input values exceeds maximum allowed amount",
+                )
+            })?;
+        self.wallet_service
+            .compliance_policy(name, enckey)?
+            .check_outputs(&[TxOut::new(to_address.clone(), balance)], false, |_| {
+                Ok(is_own_address)
+            })?;
+
+        let (transaction, used_inputs, amount) = self.transaction_builder.build_sweep_tx(
+            name,
+            enckey,
+            unspent_transactions,
+            to_address.clone(),
+            attributes.clone(),
+        )?;
+        let fee = self.transfer_fee(
+            name,
+            enckey,
+            Tx::new_with(
+                used_inputs.clone(),
+                vec![TxOut::new(to_address, amount)],
+                attributes,
+            ),
+        )?;
+        let return_amount = if is_own_address { amount } else { Coin::zero() };
+
+        let signed_tx = SignedTransferTransaction {
+            signed_transaction: transaction,
+            return_amount,
+            used_inputs,
+        };
+        Ok(self.broadcast(name, enckey, signed_tx, Some(fee))?.tx_id)
     }
 
-    fn sign_key(
+    fn send_to_address_commit(
         &self,
         name: &str,
         enckey: &SecKey,
-        public_key: &PublicKey,
-    ) -> Result<Box<dyn PrivateKeyAction>> {
-        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
-        match wallet.wallet_kind {
-            WalletKind::HW => {
-                let chain_path = self
-                    .wallet_service
-                    .find_chain_path(name, enckey, public_key)?
-                    .chain(|| {
-                        (
-                            ErrorKind::InvalidInput,
-                            "Not able to find chain path for given public_key in current wallet",
-                        )
-                    })?;
-                self.hw_key_service.get_sign_key(&chain_path)
-            }
-            _ => {
-                let private_key = self
-                    .wallet_service
-                    .find_private_key(name, enckey, public_key)?
-                    .chain(|| {
-                        (
-                            ErrorKind::InvalidInput,
-                            "Not able to find private key for given public_key in current wallet",
-                        )
-                    })?;
-                Ok(Box::new(private_key))
+        amount: Coin,
+        address: ExtendedAddr,
+        view_keys: &mut BTreeSet<PublicKey>,
+        network_id: u8,
+    ) -> Result<BroadcastResult> {
+        let result = self.send_to_address(
+            name,
+            enckey,
+            amount,
+            address,
+            None,
+            view_keys,
+            network_id,
+            false,
+            FeeTip::default(),
+            None,
+        )?;
+        let tx_id = result.tx_id;
+        let block_height = self.get_current_block_height()?;
+        loop {
+            // query tx_id from tendermint
+            let confirmed = self
+                .tendermint_client
+                .query("meta", &tx_id.to_vec(), None, false)
+                .is_ok();
+            if !confirmed {
+                std::thread::sleep(Duration::from_secs(1));
+                let current_block_height = self.get_current_block_height()?;
+                if current_block_height - block_height >= self.block_height_ensure.unwrap_or(50) {
+                    return Err(Error::new(
+                        ErrorKind::TendermintRpcError,
+                        "waiting for transaction confirmed timeout",
+                    ));
+                }
+                continue;
             }
+            break;
         }
+        Ok(result)
     }
 
     #[inline]
-    fn private_key(
-        &self,
-        name: &str,
-        enckey: &SecKey,
-        public_key: &PublicKey,
-    ) -> Result<Option<PrivateKey>> {
-        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
-        match wallet.wallet_kind {
-            WalletKind::HW => unreachable!("can not get private key in hw wallet"),
-            _ => self
-                .wallet_service
-                .find_private_key(name, enckey, public_key),
-        }
+    fn wallets(&self) -> Result<Vec<String>> {
+        self.wallet_service.names()
     }
 
-    fn new_public_key(
-        &self,
-        name: &str,
-        enckey: &SecKey,
-        address_type: Option<AddressType>,
-    ) -> Result<PublicKey> {
-        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
-        match wallet.wallet_kind {
-            WalletKind::Basic => {
-                let private_key = PrivateKey::new()?;
-                let public_key = PublicKey::from(&private_key);
-                self.wallet_service
-                    .add_public_key(name, enckey, &public_key)?;
-                self.wallet_service
-                    .add_key_pairs(name, enckey, &public_key, &private_key)?;
-                Ok(public_key)
-            }
-            WalletKind::HD => {
-                let account_type = address_type
-                    .chain(|| {
-                        (
-                            ErrorKind::InvalidInput,
-                            "Address type is needed when creating address for HD wallet",
-                        )
-                    })?
-                    .into();
-                let (public_key, private_key) =
-                    self.hd_key_service
-                        .generate_keypair(name, enckey, account_type)?;
-                self.wallet_service
-                    .add_public_key(name, enckey, &public_key)?;
-                self.wallet_service
-                    .add_key_pairs(name, enckey, &public_key, &private_key)?;
-                Ok(public_key)
-            }
-            WalletKind::HW => {
-                let account_type = address_type
-                    .chain(|| {
-                        (
-                            ErrorKind::InvalidInput,
-                            "Address type is needed when creating address for HW wallet",
-                        )
-                    })?
-                    .into();
-                let hd_path =
-                    self.hd_key_service
-                        .generate_chain_path(name, enckey, account_type)?;
-                let public_key = self.hw_key_service.get_public_key(hd_path.clone())?;
-                self.wallet_service
-                    .add_key_path(name, enckey, &public_key, &hd_path)?;
-                Ok(public_key)
-            }
-        }
+    #[inline]
+    fn wallet_id(&self, name: &str) -> Result<String> {
+        let _guard = self.wallet_locks.exclusive(name);
+        self.wallet_service.wallet_id(name)
     }
 
-    fn flush_database(&self) -> Result<()> {
-        self.storage
-            .flush()
-            .chain(|| (ErrorKind::IoError, "Unable to flush sled"))?;
-        Ok(())
+    #[inline]
+    fn wallet_by_id(&self, wallet_id: &str) -> Result<Option<String>> {
+        self.wallet_service.name_by_id(wallet_id)
     }
-    fn new_staking_address(&self, name: &str, enckey: &SecKey) -> Result<StakedStateAddress> {
-        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
-        let public_key = match wallet.wallet_kind {
-            WalletKind::Basic => {
-                let private_key = PrivateKey::new()?;
-                let public_key = PublicKey::from(&private_key);
-                self.wallet_service
-                    .add_key_pairs(name, enckey, &public_key, &private_key)?;
-                public_key
-            }
-            WalletKind::HD => {
-                let (public_key, private_key) =
-                    self.hd_key_service
-                        .generate_keypair(name, enckey, HDAccountType::Staking)?;
-                self.wallet_service
-                    .add_key_pairs(name, enckey, &public_key, &private_key)?;
-                public_key
-            }
-            WalletKind::HW => {
-                let hd_path = self.hd_key_service.generate_chain_path(
-                    name,
-                    enckey,
-                    HDAccountType::Staking,
-                )?;
-                let public_key = self.hw_key_service.get_public_key(hd_path.clone())?;
-                self.wallet_service
-                    .add_key_path(name, enckey, &public_key, &hd_path)?;
-                public_key
-            }
-        };
 
-        self.wallet_service
-            .add_staking_key(name, enckey, &public_key)?;
+    fn export_wallet(&self, name: &str, enckey: &SecKey) -> Result<WalletInfo> {
+        let _guard = self.wallet_locks.shared(name);
+        let wallet = self.wallet_service.get_wallet(name, enckey)?;
+        let private_key = self
+            .key_service
+            .wallet_private_key(name, enckey)?
+            .chain(|| {
+                (
+                    ErrorKind::InvalidInput,
+                    "Can not find private key in wallet",
+                )
+            })?;
 
-        Ok(StakedStateAddress::BasicRedeem(RedeemAddress::from(
-            &public_key,
-        )))
-    }
+        let mut key_pairs = BTreeMap::new();
+        let mut key_chainpath = BTreeMap::new();
+        let public_keys = self.public_keys(name, enckey)?;
 
-    fn new_transfer_address(&self, name: &str, enckey: &SecKey) -> Result<ExtendedAddr> {
-        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
-        let public_key = match wallet.wallet_kind {
-            WalletKind::Basic => {
-                let private_key = PrivateKey::new()?;
-                let public_key = PublicKey::from(&private_key);
+        // get public-private key pair and public-chainpath pair
+        for public_key in public_keys.into_iter() {
+            if let Some(private_key) =
                 self.wallet_service
-                    .add_key_pairs(name, enckey, &public_key, &private_key)?;
-                public_key
+                    .find_private_key(name, enckey, &public_key)?
+            {
+                key_pairs.insert(public_key.clone(), private_key);
             }
-            WalletKind::HD => {
-                let (public_key, private_key) =
-                    self.hd_key_service
-                        .generate_keypair(name, enckey, HDAccountType::Transfer)?;
-                self.wallet_service
-                    .add_key_pairs(name, enckey, &public_key, &private_key)?;
-                public_key
+            if let Some(chain_path) = self
+                .wallet_service
+                .find_chain_path(name, enckey, &public_key)?
+                .map(|p| p.into_string())
+            {
+                key_chainpath.insert(public_key, chain_path);
             }
-            WalletKind::HW => {
-                let hd_path = self.hd_key_service.generate_chain_path(
-                    name,
-                    enckey,
-                    HDAccountType::Transfer,
-                )?;
-                let public_key = self.hw_key_service.get_public_key(hd_path.clone())?;
-                self.wallet_service
-                    .add_key_path(name, enckey, &public_key, &hd_path)?;
-                public_key
+        }
+
+        // imported keys can not be regenerated from the seed, so their private keys are always
+        // exported, including the ones of imported staking keys
+        let imported_keys = self.wallet_service.imported_keys(name)?;
+        if wallet.wallet_kind == WalletKind::HD && !imported_keys.is_empty() {
+            log::warn!(
+                "{} key(s) imported into wallet {} are not covered by its mnemonic, keep this export as backup",
+                imported_keys.len(),
+                name
+            );
+        }
+        for public_key in imported_keys.iter() {
+            if let Some(private_key) = self
+                .wallet_service
+                .find_private_key(name, enckey, public_key)?
+            {
+                key_pairs.insert(public_key.clone(), private_key);
             }
-        };
-        self.wallet_service
-            .add_public_key(name, enckey, &public_key)?;
+        }
 
-        let ret = self.new_multisig_transfer_address(
-            name,
-            enckey,
-            vec![public_key.clone()],
-            public_key,
-            1,
-        );
+        // get multisig address
+        let mut multisig_address_pair = BTreeMap::new();
+        let roothashes = wallet.get_transfer_addresses_roothash()?;
+        for root_hash in roothashes.iter() {
+            let multisig_address = self
+                .root_hash_service
+                .get_multi_sig_address_from_root_hash(name, root_hash, enckey)?;
+            multisig_address_pair.insert(hex::encode(&root_hash), multisig_address);
+        }
 
-        self.storage
-            .flush()
-            .chain(|| (ErrorKind::IoError, "Unable to flush sled"))?;
+        let staking_keys2 = wallet.get_staking_addresses_publickey()?;
+        let mut staking_keys: Vec<PublicKey> = vec![];
+        for key in staking_keys2.iter() {
+            staking_keys.push(key.clone());
+            if let Some(chain_path) = self.wallet_service.find_chain_path(name, enckey, key)? {
+                key_chainpath.insert(key.clone(), chain_path.into_string());
+            }
+        }
+
+        // get hdkey
+        let hdkey = self.hd_key_service.get_hdkey(name, enckey)?;
 
-        ret
+        let wallet_info = WalletInfo {
+            name: name.into(),
+            wallet_id: Some(self.wallet_service.wallet_id(name)?),
+            wallet,
+            private_key,
+            passphrase: None,
+            key_pairs,
+            key_chainpath,
+            hdkey,
+            multisig_address_pair,
+            staking_keys,
+            imported_keys: imported_keys.into_iter().collect(),
+        };
+        Ok(wallet_info)
     }
 
-    fn new_watch_staking_address(
+    fn import_wallet(
         &self,
         name: &str,
-        enckey: &SecKey,
-        public_key: &PublicKey,
-    ) -> Result<StakedStateAddress> {
-        self.wallet_service
-            .add_staking_key(name, enckey, public_key)?;
+        passphrase: &SecUtf8,
+        wallet_info: &mut WalletInfo,
+    ) -> Result<SecKey> {
+        let _guard = self.wallet_locks.exclusive(name);
+        let all_wallet = self.wallet_service.names()?;
+        if all_wallet.contains(&name.to_string()) {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!("wallet {} already exist", name),
+            ));
+        }
+        check_passphrase_strength(name, passphrase)?;
+        let enckey = self.new_wallet_enckey(name, passphrase)?;
+        let view_key = PublicKey::from(&wallet_info.private_key);
+        if view_key != wallet_info.wallet.view_key {
+            return Err(Error::new(ErrorKind::InvalidInput, "public key not match"));
+        }
+        self.key_service.add_wallet_private_key(
+            &wallet_info.name,
+            &wallet_info.private_key,
+            &enckey,
+        )?;
 
-        self.storage
-            .flush()
-            .chain(|| (ErrorKind::IoError, "Unable to flush sled"))?;
+        let newstorage = self.storage.clone();
+        // connect storage
+        wallet_info.wallet.wallet_storage =
+            Some(Arc::new(Mutex::new(WalletStorageImpl::new(newstorage))));
+        wallet_info.wallet.name = wallet_info.name.clone();
+        wallet_info.wallet.enckey = Some(enckey.clone());
 
-        Ok(StakedStateAddress::BasicRedeem(RedeemAddress::from(
-            public_key,
-        )))
-    }
+        self.wallet_service
+            .set_wallet(name, &enckey, wallet_info.wallet.clone())?;
 
-    fn new_watch_transfer_address(
-        &self,
-        name: &str,
-        enckey: &SecKey,
-        public_key: &PublicKey,
-    ) -> Result<ExtendedAddr> {
-        self.new_multisig_transfer_address(
-            name,
-            enckey,
-            vec![public_key.clone()],
-            public_key.clone(),
-            1,
-        )
-    }
+        for (public_key, private_key) in wallet_info.key_pairs.iter() {
+            self.wallet_service
+                .add_key_pairs(name, &enckey, public_key, private_key)?;
+            // private keys of imported staking keys are exported as well
+            if !wallet_info.staking_keys.contains(public_key) {
+                self.wallet_service
+                    .add_public_key(name, &enckey, public_key)?;
+            }
+        }
 
-    fn new_multisig_transfer_address(
-        &self,
-        name: &str,
-        enckey: &SecKey,
-        public_keys: Vec<PublicKey>,
-        self_public_key: PublicKey,
-        m: usize,
-    ) -> Result<ExtendedAddr> {
-        if !public_keys.contains(&self_public_key) {
-            return Err(Error::new(
-                ErrorKind::InvalidInput,
-                "Signer public keys does not contain self public key",
-            ));
+        for public_key in wallet_info.imported_keys.iter() {
+            self.wallet_service.add_imported_key(name, public_key)?;
         }
 
-        let (root_hash, multi_sig_address) =
-            self.root_hash_service
-                .new_root_hash(name, public_keys, self_public_key, m, enckey)?;
+        for (public_key, chain_path) in wallet_info.key_chainpath.iter() {
+            let path = ChainPath::from(chain_path.as_str());
+            self.wallet_service
+                .add_key_path(name, &enckey, public_key, &path)?;
+            // the keys with private keys were added above (HD wallet)
+            if !wallet_info.key_pairs.contains_key(public_key)
+                && !wallet_info.staking_keys.contains(public_key)
+            {
+                self.wallet_service
+                    .add_public_key(name, &enckey, public_key)?;
+            }
+        }
 
-        self.wallet_service.add_root_hash(name, enckey, root_hash)?;
+        if let Some(hdkey) = wallet_info.hdkey.clone() {
+            self.hd_key_service.add_hdkey(name, &enckey, hdkey)?;
+        }
 
-        self.storage
-            .flush()
-            .chain(|| (ErrorKind::IoError, "Unable to flush sled"))?;
+        // store multisig address
+        for (root_hash_str, multisig_addr) in wallet_info.multisig_address_pair.iter() {
+            let root_hash_raw = hex::decode(root_hash_str)
+                .chain(|| (ErrorKind::InvalidInput, "failed to parse root_hash"))?;
 
-        Ok(multi_sig_address.into())
-    }
+            let root_hash: H256 = root_hash_raw
+                .as_slice()
+                .try_into()
+                .chain(|| (ErrorKind::InvalidInput, "failed to parse root_hash"))?;
+            self.wallet_service
+                .add_root_hash(name, &enckey, root_hash)?;
+            self.root_hash_service
+                .set_multi_sig_address_from_root_hash(name, &enckey, &root_hash, multisig_addr)?;
+        }
 
-    fn get_multisig_addresses(&self, name: &str, enckey: &SecKey) -> Result<Vec<MultiSigAddress>> {
-        let root_hashes = self.wallet_service.root_hashes(name, enckey, 0, 0, false)?;
-        root_hashes
-            .iter()
-            .map(|hash| {
-                self.root_hash_service
-                    .get_multi_sig_address_from_root_hash(name, hash, enckey)
-            })
-            .collect::<Result<Vec<MultiSigAddress>>>()
+        let public_keys = wallet_info.wallet.get_staking_addresses_publickey()?;
+        for public_key in public_keys.iter() {
+            self.wallet_service
+                .add_staking_key(name, &enckey, public_key)?;
+        }
+
+        for staking_key in wallet_info.staking_keys.iter() {
+            self.wallet_service
+                .add_staking_key(name, &enckey, staking_key)?;
+        }
+        self.wallet_service
+            .assign_wallet_id(name, wallet_info.wallet_id.as_deref())?;
+        Ok(enckey)
     }
 
-    fn generate_proof(
+    fn new_wallet(
         &self,
         name: &str,
-        enckey: &SecKey,
-        address: &ExtendedAddr,
-        public_keys: Vec<PublicKey>,
-    ) -> Result<Proof<RawXOnlyPubkey>> {
-        // To verify if the enckey is correct or not
-        self.wallet_service.view_key(name, enckey)?;
+        passphrase: &SecUtf8,
+        wallet_kind: WalletKind,
+        hardware_kind: HardwareKind,
+        mnemonics_word_count: Option<u32>,
+    ) -> Result<(SecKey, Option<Mnemonic>)> {
+        let _guard = self.wallet_locks.exclusive(name);
+        check_passphrase_strength(name, passphrase)?;
 
-        match address {
-            ExtendedAddr::OrTree(ref address) => {
-                self.root_hash_service
-                    .generate_proof(name, address, public_keys, enckey)
-            }
-        }
-    }
+        let enckey = self.new_wallet_enckey(name, passphrase)?;
 
-    fn required_cosigners(&self, name: &str, enckey: &SecKey, root_hash: &H256) -> Result<usize> {
-        // To verify if the enckey is correct or not
-        self.wallet_service.view_key(name, enckey)?;
+        match wallet_kind {
+            WalletKind::Basic => {
+                let private_key = PrivateKey::new()?;
+                let view_key = PublicKey::from(&private_key);
 
-        self.root_hash_service
-            .required_signers(name, root_hash, enckey)
-    }
+                self.key_service
+                    .add_wallet_private_key(name, &private_key, &enckey)?;
 
-    #[inline]
-    fn balance(&self, name: &str, enckey: &SecKey) -> Result<WalletBalance> {
-        // Check if wallet exists
-        self.wallet_service.view_key(name, enckey)?;
-        self.wallet_state_service.get_balance(name, enckey)
+                self.wallet_service
+                    .create(name, &enckey, view_key, wallet_kind, hardware_kind)?;
+
+                Ok((enckey, None))
+            }
+            WalletKind::HD => {
+                let mnemonic = Mnemonic::new(mnemonics_word_count.unwrap_or(24))?;
+                self.create_hd_wallet(name, &enckey, &mnemonic, hardware_kind)?;
+                Ok((enckey, Some(mnemonic)))
+            }
+            WalletKind::HW => {
+                // the view-key pair is the local key pair, not come from the hardware wallet.
+                let private_key = PrivateKey::new()?;
+                let view_key = PublicKey::from(&private_key);
+                self.hd_key_service.add_mnemonic(name, None, &enckey)?;
+                self.key_service
+                    .add_wallet_private_key(name, &private_key, &enckey)?;
+
+                self.wallet_service
+                    .create(name, &enckey, view_key, wallet_kind, hardware_kind)?;
+
+                Ok((enckey, None))
+            }
+            WalletKind::Watch => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Watch-only wallets are created from their public keys",
+            )),
+        }
     }
 
-    fn history(
+    fn new_hd_wallet(
         &self,
         name: &str,
-        enckey: &SecKey,
-        offset: usize,
-        limit: usize,
-        reversed: bool,
-    ) -> Result<Vec<TransactionChange>> {
-        // Check if wallet exists
-        self.wallet_service.view_key(name, enckey)?;
+        passphrase: &SecUtf8,
+        mnemonics_word_count: Option<u32>,
+        language: MnemonicLanguage,
+    ) -> Result<(SecKey, Mnemonic)> {
+        let _guard = self.wallet_locks.exclusive(name);
+        check_passphrase_strength(name, passphrase)?;
 
-        let history = self
-            .wallet_state_service
-            .get_transaction_history(name, enckey, reversed)?
-            .filter(|change| BalanceChange::NoChange != change.balance_change)
-            .skip(offset)
-            .take(limit)
-            .collect::<Vec<_>>();
+        let enckey = self.new_wallet_enckey(name, passphrase)?;
 
-        Ok(history)
+        let mnemonic = Mnemonic::new_in(mnemonics_word_count.unwrap_or(24), language)?;
+        self.create_hd_wallet(name, &enckey, &mnemonic, HardwareKind::LocalOnly)?;
+        Ok((enckey, mnemonic))
     }
 
-    #[inline]
-    fn get_transaction_change(
+    fn new_watch_wallet(
         &self,
         name: &str,
-        enckey: &SecKey,
-        transaction_id: &TxId,
-    ) -> Result<Option<TransactionChange>> {
-        self.wallet_state_service
-            .get_transaction_change(name, enckey, transaction_id)
-    }
+        passphrase: &SecUtf8,
+        public_keys: Vec<PublicKey>,
+    ) -> Result<SecKey> {
+        let _guard = self.wallet_locks.exclusive(name);
+        check_passphrase_strength(name, passphrase)?;
 
-    fn unspent_transactions(&self, name: &str, enckey: &SecKey) -> Result<UnspentTransactions> {
-        // Check if wallet exists
-        self.wallet_service.view_key(name, enckey)?;
-
-        let unspent_transactions = self
-            .wallet_state_service
-            .get_unspent_transactions(name, enckey, false)?;
-
-        Ok(UnspentTransactions::new(
-            unspent_transactions.into_iter().collect(),
-        ))
-    }
+        let enckey = self.new_wallet_enckey(name, passphrase)?;
 
-    fn has_unspent_transactions(
-        &self,
-        name: &str,
-        enckey: &SecKey,
-        inputs: &[TxoPointer],
-    ) -> Result<bool> {
-        // Check if wallet exists
-        self.wallet_service.view_key(name, enckey)?;
+        // the view-key pair is a local one (as in a hardware wallet), the offline signer has to
+        // add its view key to the transactions so that the wallet can decrypt them.
+        let private_key = PrivateKey::new()?;
+        let view_key = PublicKey::from(&private_key);
+        self.key_service
+            .add_wallet_private_key(name, &private_key, &enckey)?;
+        self.wallet_service.create(
+            name,
+            &enckey,
+            view_key,
+            WalletKind::Watch,
+            HardwareKind::LocalOnly,
+        )?;
 
-        self.wallet_state_service
-            .has_unspent_transactions(name, enckey, inputs)
+        // same addresses as the ones of a wallet holding the private keys
+        for public_key in public_keys {
+            self.wallet_service
+                .add_staking_key(name, &enckey, &public_key)?;
+            self.wallet_service
+                .add_public_key(name, &enckey, &public_key)?;
+            self.new_multisig_transfer_address(
+                name,
+                &enckey,
+                vec![public_key.clone()],
+                public_key,
+                1,
+            )?;
+        }
+        Ok(enckey)
     }
 
-    #[inline]
-    fn are_inputs_unspent(
+    fn restore_wallet(
         &self,
         name: &str,
-        enckey: &SecKey,
-        inputs: Vec<TxoPointer>,
-    ) -> Result<Vec<(TxoPointer, bool)>> {
-        self.wallet_state_service
-            .are_inputs_unspent(name, enckey, inputs)
-    }
+        passphrase: &SecUtf8,
+        mnemonic: &Mnemonic,
+    ) -> Result<SecKey> {
+        let _guard = self.wallet_locks.exclusive(name);
+        check_passphrase_strength(name, passphrase)?;
 
-    #[inline]
-    fn output(&self, name: &str, enckey: &SecKey, input: &TxoPointer) -> Result<TxOut> {
-        // Check if wallet exists
-        self.wallet_service.view_key(name, enckey)?;
+        let enckey = self.new_wallet_enckey(name, passphrase)?;
 
-        self.wallet_state_service
-            .get_output(name, enckey, input)
-            .and_then(|optional| {
-                optional.chain(|| {
-                    (
-                        ErrorKind::InvalidInput,
-                        "Output details not found for given transaction input",
-                    )
-                })
-            })
+        self.create_hd_wallet(name, &enckey, mnemonic, HardwareKind::LocalOnly)?;
+        Ok(enckey)
     }
 
-    fn create_transaction(
+    fn restore_basic_wallet(
         &self,
         name: &str,
-        enckey: &SecKey,
-        outputs: Vec<TxOut>,
-        attributes: TxAttributes,
-        input_selection_strategy: Option<InputSelectionStrategy>,
-        return_address: ExtendedAddr,
-    ) -> Result<(TxAux, Vec<TxoPointer>, Coin)> {
-        let mut unspent_transactions = self.unspent_transactions(name, enckey)?;
-        unspent_transactions.apply_all(input_selection_strategy.unwrap_or_default().as_ref());
+        passphrase: &SecUtf8,
+        view_key_priv: &PrivateKey,
+    ) -> Result<SecKey> {
+        let _guard = self.wallet_locks.exclusive(name);
+        check_passphrase_strength(name, passphrase)?;
 
-        self.transaction_builder.build_transfer_tx(
-            name,
-            enckey,
-            unspent_transactions,
-            outputs,
-            return_address,
-            attributes,
-        )
-    }
+        let enckey = self.new_wallet_enckey(name, passphrase)?;
 
-    #[inline]
-    fn broadcast_transaction(&self, tx_aux: &TxAux) -> Result<BroadcastTxResponse> {
-        self.tendermint_client
-            .broadcast_transaction(&tx_aux.encode())
+        let view_key = PublicKey::from(view_key_priv);
+        self.key_service
+            .add_wallet_private_key(name, &view_key_priv, &enckey)?;
+        self.wallet_service.create(
+            name,
+            &enckey,
+            view_key,
+            WalletKind::Basic,
+            HardwareKind::LocalOnly,
+        )?;
+        Ok(enckey)
     }
 
-    fn export_plain_tx(&self, name: &str, enckey: &SecKey, txid: &str) -> Result<TransactionInfo> {
-        let txid = str2txid(txid).chain(|| (ErrorKind::InvalidInput, "invalid transaction id"))?;
-        let tx = self.get_transaction(name, enckey, txid)?;
-        // get the block height
-        let tx_change = self
-            .wallet_state_service
-            .get_transaction_history(name, enckey, false)?
-            .filter(|change| BalanceChange::NoChange != change.balance_change)
-            .find(|tx_change| tx_change.transaction_id == tx.id())
-            .chain(|| {
-                (
-                    ErrorKind::InvalidInput,
-                    "no transaction find by transaction id",
-                )
-            })?;
+    fn delete_wallet(&self, name: &str, passphrase: &SecUtf8) -> Result<()> {
+        let _guard = self.wallet_locks.exclusive(name);
+        let enckey = self.key_service.derive_enckey(name, passphrase)?;
 
-        let tx_info = TransactionInfo {
-            tx,
-            block_height: tx_change.block_height,
-        };
-        Ok(tx_info)
+        // the passphrase is verified here (nothing is deleted if it's wrong), then all the
+        // records of the wallet are deleted in one batch
+        let mut batch = StorageBatch::default();
+        self.wallet_service
+            .batch_delete(&mut batch, name, &enckey)?;
+        batch_delete_sync_state(&mut batch, name);
+        batch_delete_wallet_state(&self.storage, &mut batch, name)?;
+        self.hd_key_service.batch_delete(&mut batch, name);
+        self.key_service.batch_delete(&mut batch, name);
+        self.backup_service.batch_delete(&mut batch, name);
+        self.pending_transaction_service
+            .batch_delete(&mut batch, name);
+        self.output_lock_service.batch_delete(&mut batch, name);
+        self.fee_estimate_service.batch_delete(&mut batch, name);
+        self.transaction_metadata_service
+            .batch_delete(&mut batch, name);
+        self.address_label_service.batch_delete(&mut batch, name);
+        self.address_book_service.batch_delete(&mut batch, name);
+        self.chain_tip_service.batch_delete(&mut batch, name);
+        #[cfg(feature = "experimental")]
+        self.multi_sig_session_service
+            .batch_delete_sessions(&mut batch, &enckey)?;
+        self.storage.write_batch(batch)
     }
 
-    /// import a plain base64 encoded plain transaction
-    fn import_plain_tx(&self, name: &str, enckey: &SecKey, tx_str: &str) -> Result<Coin> {
-        let tx_info = TransactionInfo::decode(tx_str)?;
-
-        let found_tx = self.is_tx_exist(name, enckey, tx_info.tx.id())?;
-        if found_tx {
+    fn rename_wallet(&self, name: &str, new_name: &str, passphrase: &SecUtf8) -> Result<SecKey> {
+        let _guards = self.wallet_locks.exclusive_many(&[name, new_name]);
+        if name == new_name {
             return Err(Error::new(
-                ErrorKind::ValidationError,
-                "This transaction is already imported/in your wallet",
+                ErrorKind::InvalidInput,
+                "New wallet name is the same as the current one",
             ));
         }
-
-        // check if the output is spent or not
-        let v = self
-            .tendermint_client
-            .query("meta", &tx_info.tx.id().to_vec(), None, false)?
-            .bytes();
-        let bit_flag = BitVec::from_bytes(&v);
-        let spent_flags: Result<Vec<bool>> = tx_info
-            .tx
-            .outputs()
-            .iter()
-            .enumerate()
-            .map(|(index, _output)| {
-                bit_flag
-                    .get(index)
-                    .chain(|| (ErrorKind::InvalidInput, "check failed in enclave"))
-            })
-            .collect();
-        let mut memento = WalletStateMemento::default();
-        // check if tx belongs to the block
-        let block = self.tendermint_client.block(tx_info.block_height)?;
-        let block_result = self.tendermint_client.block_results(tx_info.block_height)?;
-        let fees = block_result.fees()?;
-        let paid_fee = fees.get(&tx_info.tx.id());
-        if paid_fee.is_none() {
+        let enckey = self.auth_token(name, passphrase)?;
+        if self.wallet_service.names()?.iter().any(|n| n == new_name) {
             return Err(Error::new(
-                ErrorKind::InvalidInput,
-                "block height and transaction not match",
+                ErrorKind::PermissionDenied,
+                format!("wallet {} already exist", new_name),
             ));
         }
-        let wallet = self.wallet_service.get_wallet(name, enckey)?;
-
-        let wallet_state = self.wallet_service.get_wallet_state(name, enckey)?;
+        let wallet_id = self.wallet_service.wallet_id(name)?;
+
+        // the records are encrypted with a name-dependent enckey, so they are written again
+        // under the new name; the wallet only switches to it (with its identifier and the records
+        // which aren't backed up) in one batch at the end
+        let records = self.backup_records(name, &enckey)?;
+        let renamed = self
+            .import_backup_records(records, new_name, passphrase)
+            .and_then(|new_enckey| {
+                let mut batch = StorageBatch::default();
+                self.wallet_service
+                    .batch_set_wallet_id(&mut batch, new_name, &wallet_id)?;
+                self.pending_transaction_service.batch_rename(
+                    &mut batch,
+                    name,
+                    &enckey,
+                    new_name,
+                    &new_enckey,
+                )?;
+                self.output_lock_service.batch_rename(
+                    &mut batch,
+                    name,
+                    &enckey,
+                    new_name,
+                    &new_enckey,
+                )?;
+                self.fee_estimate_service.batch_rename(
+                    &mut batch,
+                    name,
+                    &enckey,
+                    new_name,
+                    &new_enckey,
+                )?;
+                self.transaction_metadata_service.batch_rename(
+                    &mut batch,
+                    name,
+                    &enckey,
+                    new_name,
+                    &new_enckey,
+                )?;
+                self.address_label_service.batch_rename(
+                    &mut batch,
+                    name,
+                    &enckey,
+                    new_name,
+                    &new_enckey,
+                )?;
+                self.address_book_service.batch_rename(
+                    &mut batch,
+                    name,
+                    &enckey,
+                    new_name,
+                    &new_enckey,
+                )?;
+                self.backup_service.batch_rename(
+                    &mut batch,
+                    name,
+                    &enckey,
+                    new_name,
+                    &new_enckey,
+                )?;
+                self.chain_tip_service.batch_rename(
+                    &mut batch,
+                    name,
+                    &enckey,
+                    new_name,
+                    &new_enckey,
+                )?;
+                self.storage.write_batch(batch)?;
+                Ok(new_enckey)
+            });
+        let new_enckey = match renamed {
+            Ok(new_enckey) => new_enckey,
+            Err(err) => {
+                if self.wallet_service.names()?.iter().any(|n| n == new_name) {
+                    if let Err(rollback_err) = self.delete_wallet(new_name, passphrase) {
+                        log::warn!(
+                            "unable to remove partially renamed wallet {}: {}",
+                            new_name,
+                            rollback_err
+                        );
+                    }
+                }
+                return Err(err);
+            }
+        };
 
-        let imported_value = import_transaction(
-            &wallet,
-            &wallet_state,
-            &mut memento,
-            &tx_info,
-            *paid_fee.expect("tx fee checked above"),
-            block.header.time,
-            spent_flags?,
-        )
-        .chain(|| (ErrorKind::InvalidInput, "import error"))?;
+        self.delete_wallet(name, passphrase)?;
+        Ok(new_enckey)
+    }
 
-        self.wallet_state_service
-            .apply_memento(name, enckey, &memento)?;
+    fn change_passphrase(
+        &self,
+        name: &str,
+        old_passphrase: &SecUtf8,
+        new_passphrase: &SecUtf8,
+    ) -> Result<SecKey> {
+        let _guard = self.wallet_locks.exclusive(name);
+        // the old passphrase is verified here.
+        let enckey = self.auth_token(name, old_passphrase)?;
+        check_passphrase_strength(name, new_passphrase)?;
+        let new_enckey = derive_enckey(new_passphrase, name)
+            .err_kind(ErrorKind::InvalidInput, || {
+                "unable to derive encryption key from passphrase"
+            })?;
 
-        self.storage
-            .flush()
-            .chain(|| (ErrorKind::IoError, "Unable to flush sled"))?;
+        // the records are all re-encrypted in one batch, so that the wallet is never left with a
+        // part of them under each passphrase
+        let mut batch = StorageBatch::default();
+        self.key_service
+            .batch_change_enckey(&mut batch, name, &enckey, &new_enckey)?;
+        self.batch_change_enckey(&mut batch, name, &enckey, &new_enckey)?;
+        self.storage.write_batch(batch)?;
+        self.forget_enckey(&enckey);
+        Ok(new_enckey)
+    }
 
-        Ok(imported_value)
+    fn lock_wallet(&self, name: &str, enckey: &SecKey) -> Result<()> {
+        let _guard = self.wallet_locks.shared(name);
+        // the enckey is verified here.
+        self.wallet_service.view_key(name, enckey)?;
+        self.forget_enckey(enckey);
+        Ok(())
     }
 
-    fn get_current_block_height(&self) -> Result<u64> {
-        let status = self.tendermint_client.status()?;
-        let current_block_height = status.sync_info.latest_block_height.value();
-        Ok(current_block_height)
+    #[inline]
+    fn backup(&self, name: &str, passphrase: &SecUtf8, backup_key: &SecUtf8) -> Result<BackupBlob> {
+        let _guard = self.wallet_locks.exclusive(name);
+        self.backup_snapshot(name, passphrase, backup_key, None)
     }
 
-    fn update_tx_pending_state(
+    #[inline]
+    fn backup_incremental(
         &self,
         name: &str,
-        enckey: &SecKey,
-        tx_id: TxId,
-        tx_pending: TransactionPending,
-    ) -> Result<()> {
-        let mut wallet_state_memento = WalletStateMemento::default();
-        wallet_state_memento.add_pending_transaction(tx_id, tx_pending);
-        self.wallet_state_service
-            .apply_memento(name, enckey, &wallet_state_memento)
+        passphrase: &SecUtf8,
+        backup_key: &SecUtf8,
+        since_snapshot_id: &H256,
+    ) -> Result<BackupBlob> {
+        let _guard = self.wallet_locks.exclusive(name);
+        self.backup_snapshot(name, passphrase, backup_key, Some(since_snapshot_id))
     }
 
-    fn build_raw_transfer_tx(
+    fn restore_backup(
         &self,
-        name: &str,
-        enckey: &SecKey,
-        to_address: ExtendedAddr,
-        amount: Coin,
-        view_keys: Vec<PublicKey>,
-        network_id: u8,
-    ) -> Result<UnsignedTransferTransaction> {
-        let unspent_transactions = self.unspent_transactions(name, enckey)?;
-        let return_address = self.new_transfer_address(name, enckey)?;
-        let unsigned = UnsignedTransferTransaction {
-            unspent_transactions,
-            view_keys,
-            network_id,
-            to_address,
-            return_address,
-            amount,
-        };
-        Ok(unsigned)
+        blobs: &[BackupBlob],
+        backup_key: &SecUtf8,
+        new_name: &str,
+        passphrase: &SecUtf8,
+    ) -> Result<SecKey> {
+        let _guard = self.wallet_locks.exclusive(new_name);
+        let records = restore_records(blobs, backup_key)?;
+        self.import_backup_records(records, new_name, passphrase)
     }
 
-    fn sign_raw_transfer_tx(
+    fn archive_old_records(
         &self,
         name: &str,
-        enckey: &SecKey,
-        unsigned_tx: UnsignedTransferTransaction,
-    ) -> Result<SignedTransferTransaction> {
-        let tx_out = TxOut::new(unsigned_tx.to_address, unsigned_tx.amount);
-        let view_key = self.view_key(name, enckey)?;
-        let mut view_keys = unsigned_tx.view_keys;
-        view_keys.push(view_key);
-        let access_policies: BTreeSet<_> = view_keys
+        passphrase: &SecUtf8,
+        before: Timespec,
+    ) -> Result<ArchiveBlob> {
+        let _guard = self.wallet_locks.exclusive(name);
+        let enckey = self.auth_token(name, passphrase)?;
+        let now = unix_timestamp()?;
+        let history_policy =
+            self.wallet_service
+                .retention_policy(name, &enckey, RecordClass::TransactionHistory)?;
+        let fee_policy =
+            self.wallet_service
+                .retention_policy(name, &enckey, RecordClass::FeeEstimates)?;
+        let is_expired = |policy: &RetentionPolicy, newer: usize, change: &TransactionChange| {
+            let mark = ArchiveMark::of(change);
+            mark.block_time < before || policy.is_expired(mark.block_time, newer as u64, now)
+        };
+
+        let mut state = load_wallet_state(&self.storage, name, &enckey)?.unwrap_or_default();
+        let estimates = self.fee_estimate_service.get_all(name, &enckey)?;
+        // most recent first
+        let mut changes = state.transaction_history.values().collect::<Vec<_>>();
+        changes
+            .sort_by_key(|change| std::cmp::Reverse((change.block_height, change.transaction_id)));
+        let archived_changes = changes
             .iter()
-            .map(|key| TxAccessPolicy {
-                view_key: key.into(),
-                access: TxAccess::AllData,
+            .enumerate()
+            .filter(|(newer, change)| {
+                is_expired(&history_policy, *newer, change)
+                    && state.is_archivable(&change.transaction_id)
             })
-            .collect();
-
-        let attributes = TxAttributes::new_with_access(
-            unsigned_tx.network_id,
-            access_policies.into_iter().collect(),
+            .map(|(_, change)| change.transaction_id)
+            .collect::<BTreeSet<_>>();
+        // the estimates are aged by the block time of their (confirmed) transactions, and archived
+        // along with them
+        let archived_estimates = changes
+            .iter()
+            .filter(|change| estimates.contains_key(&change.transaction_id))
+            .enumerate()
+            .filter(|(newer, change)| {
+                archived_changes.contains(&change.transaction_id)
+                    || is_expired(&fee_policy, *newer, change)
+            })
+            .map(|(_, change)| (change.transaction_id, ArchiveMark::of(change)))
+            .collect::<BTreeMap<_, _>>();
+        let history_mark = changes
+            .iter()
+            .filter(|change| archived_changes.contains(&change.transaction_id))
+            .map(|change| ArchiveMark::of(change))
+            .max();
+        let fee_mark = archived_estimates.values().max().copied();
+
+        let mut records = BackupRecords::new();
+        records.insert(
+            ARCHIVE_WALLET_ID.to_owned(),
+            self.wallet_service.wallet_id(name)?.into_bytes(),
         );
+        let mut batch = StorageBatch::default();
+        let removed_changes = state.remove_transaction_changes(&archived_changes);
+        self.history_index_service
+            .batch_remove(&mut batch, name, &removed_changes);
+        batch_save_wallet_state(&mut batch, name, &enckey, &state)?;
+        for change in removed_changes {
+            records.insert(
+                format!(
+                    "{}{}",
+                    ARCHIVE_HISTORY_PREFIX,
+                    hex::encode(&change.transaction_id)
+                ),
+                change.encode(),
+            );
+        }
+        let removed_estimates = self.fee_estimate_service.batch_remove(
+            &mut batch,
+            name,
+            &enckey,
+            &archived_estimates.keys().copied().collect::<BTreeSet<_>>(),
+        )?;
+        for estimate in removed_estimates {
+            records.insert(
+                format!(
+                    "{}{}",
+                    ARCHIVE_FEE_ESTIMATE_PREFIX,
+                    hex::encode(&estimate.0)
+                ),
+                estimate.encode(),
+            );
+        }
+        for (class, mark) in [
+            (RecordClass::TransactionHistory, history_mark),
+            (RecordClass::FeeEstimates, fee_mark),
+        ]
+        .iter()
+        {
+            if let Some(mark) = mark {
+                let previous = self.wallet_service.archive_mark(name, &enckey, *class)?;
+                let mark = previous.map_or(*mark, |previous| previous.max(*mark));
+                self.wallet_service
+                    .batch_set_archive_mark(&mut batch, name, *class, mark);
+            }
+        }
 
-        let return_address = unsigned_tx.return_address.clone();
-
-        let (transaction, selected_inputs, return_amount) =
-            self.transaction_builder.build_transfer_tx(
-                name,
-                enckey,
-                unsigned_tx.unspent_transactions,
-                vec![tx_out],
-                return_address,
-                attributes,
-            )?;
-        let signed_tx = SignedTransferTransaction {
-            signed_transaction: transaction,
-            used_inputs: selected_inputs,
-            return_amount,
-        };
-        Ok(signed_tx)
+        // the archive is sealed before the records are removed from the live storage
+        let archive = seal_records(records, passphrase)?;
+        self.storage.write_batch(batch)?;
+        Ok(archive)
     }
 
-    fn broadcast_signed_transfer_tx(
+    fn import_archive(
         &self,
         name: &str,
-        enckey: &SecKey,
-        signed_tx: SignedTransferTransaction,
-    ) -> Result<TxId> {
-        let current_block_height = self.get_current_block_height()?;
+        passphrase: &SecUtf8,
+        archive: &ArchiveBlob,
+    ) -> Result<WalletArchive> {
+        let _guard = self.wallet_locks.shared(name);
+        // the archive is encrypted with the passphrase of the wallet at the time it was taken, which
+        // may have been changed since
+        let records = restore_records(std::slice::from_ref(archive), passphrase)?;
+        let wallet_id = self.wallet_service.wallet_id(name)?;
+        if records.get(ARCHIVE_WALLET_ID).map(Vec::as_slice) != Some(wallet_id.as_bytes()) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Archive is not one of wallet {}", name),
+            ));
+        }
 
-        self.broadcast_transaction(&signed_tx.signed_transaction)?;
+        let mut wallet_archive = WalletArchive::default();
+        for (id, record) in records.iter() {
+            if id.starts_with(ARCHIVE_HISTORY_PREFIX) {
+                let change = TransactionChange::decode(&mut record.as_slice()).chain(|| {
+                    (
+                        ErrorKind::DeserializationError,
+                        "Unable to decode archived transaction change",
+                    )
+                })?;
+                wallet_archive.transaction_history.push(change);
+            } else if id.starts_with(ARCHIVE_FEE_ESTIMATE_PREFIX) {
+                let (tx_id, fee) = <(TxId, Coin)>::decode(&mut record.as_slice()).chain(|| {
+                    (
+                        ErrorKind::DeserializationError,
+                        "Unable to decode archived fee estimate",
+                    )
+                })?;
+                wallet_archive.fee_estimates.insert(tx_id, fee);
+            }
+        }
+        wallet_archive
+            .transaction_history
+            .sort_by_key(|change| (change.block_height, change.transaction_id));
+        Ok(wallet_archive)
+    }
 
-        //update the wallet state
-        let tx_pending = TransactionPending {
-            used_inputs: signed_tx.used_inputs.clone(),
-            block_height: current_block_height,
-            return_amount: signed_tx.return_amount,
-        };
+    fn auth_token(&self, name: &str, passphrase: &SecUtf8) -> Result<SecKey> {
+        // the records written under older KDF parameters are upgraded once the passphrase is
+        // verified
+        if self.key_service.kdf_params(name)? != KdfParams::CURRENT {
+            return self.upgrade_kdf_params(name, passphrase);
+        }
+        let _guard = self.wallet_locks.shared(name);
+        let enckey = derive_enckey(passphrase, name).err_kind(ErrorKind::InvalidInput, || {
+            "unable to derive encryption key from passphrase"
+        })?;
 
-        let transaction = signed_tx.signed_transaction;
+        // test validity of enckey
+        self.view_key(name, &enckey)?;
+        Ok(enckey)
+    }
 
-        self.update_tx_pending_state(name, enckey, transaction.tx_id(), tx_pending)?;
+    #[inline]
+    fn view_key(&self, name: &str, enckey: &SecKey) -> Result<PublicKey> {
+        let _guard = self.wallet_locks.shared(name);
+        self.wallet_service.view_key(name, enckey)
+    }
 
-        if let TxAux::EnclaveTx(TxEnclaveAux::TransferTx {
-            payload: TxObfuscated { txid, .. },
-            ..
-        }) = transaction
-        {
-            Ok(txid)
-        } else {
-            Err(Error::new(
-                ErrorKind::IllegalInput,
-                "Transaction is not transfer transaction",
-            ))
-        }
+    #[inline]
+    fn view_key_private(&self, name: &str, enckey: &SecKey) -> Result<PrivateKey> {
+        let _guard = self.wallet_locks.shared(name);
+        self.key_service
+            .wallet_private_key(name, enckey)?
+            .err_kind(ErrorKind::InvalidInput, || "private view key not found")
     }
 
-    fn get_sync_state(&self, name: &str) -> Result<SyncState> {
-        let mstate = self.sync_state_service.get_global_state(name)?;
-        let sync_state = if let Some(sync_state) = mstate {
-            sync_state
-        } else {
-            get_genesis_sync_state(&self.tendermint_client, true)?
-        };
-        Ok(sync_state)
+    #[inline]
+    fn public_keys(&self, name: &str, enckey: &SecKey) -> Result<IndexSet<PublicKey>> {
+        let _guard = self.wallet_locks.shared(name);
+        self.wallet_service.public_keys(name, enckey)
     }
-}
 
-#[cfg(feature = "experimental")]
-impl<S, C, T> MultiSigWalletClient for DefaultWalletClient<S, C, T>
-where
-    S: Storage + 'static,
-    C: Client,
-    T: WalletTransactionBuilder,
-{
-    fn schnorr_signature(
+    #[inline]
+    fn staking_keys(&self, name: &str, enckey: &SecKey) -> Result<IndexSet<PublicKey>> {
+        let _guard = self.wallet_locks.shared(name);
+        self.wallet_service.staking_keys(name, enckey, 0, 0, false)
+    }
+
+    #[inline]
+    fn root_hashes(&self, name: &str, enckey: &SecKey) -> Result<IndexSet<H256>> {
+        let _guard = self.wallet_locks.shared(name);
+        self.wallet_service.root_hashes(name, enckey, 0, 0, false)
+    }
+
+    #[inline]
+    fn staking_addresses(
         &self,
         name: &str,
         enckey: &SecKey,
-        tx: &Transaction,
-        public_key: &PublicKey,
-    ) -> Result<SchnorrSignature> {
-        // To verify if the enckey is correct or not
-        self.transfer_addresses(name, enckey)?;
-        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
-        let sign_key = match wallet.wallet_kind {
-            WalletKind::HW => {
-                let chain_path = self
-                    .wallet_service
-                    .find_chain_path(name, enckey, public_key)?
-                    .chain(|| (ErrorKind::InvalidInput, "can not find chain path"))?;
-                self.hw_key_service.get_sign_key(&chain_path)?
-            }
-            _ => {
-                let private_key = self
-                    .wallet_service
-                    .find_private_key(name, enckey, public_key)?
-                    .chain(|| (ErrorKind::InvalidInput, ""))?;
-                Box::new(private_key)
-            }
-        };
-        sign_key.schnorr_sign(tx)
+        offset: u64,
+        limit: u64,
+        reversed: bool,
+    ) -> Result<IndexSet<StakedStateAddress>> {
+        let _guard = self.wallet_locks.shared(name);
+        self.wallet_service
+            .staking_addresses(name, enckey, offset, limit, reversed)
     }
 
-    fn new_multi_sig_session(
+    #[inline]
+    fn transfer_addresses(
         &self,
         name: &str,
         enckey: &SecKey,
-        message: H256,
-        signer_public_keys: Vec<PublicKey>,
-        self_public_key: PublicKey,
-    ) -> Result<H256> {
-        // To verify if the enckey is correct or not
-        self.transfer_addresses(name, enckey)?;
-        //        let sign_key = self.sign_key(name, enckey, &self_public_key)?;
-
-        let self_private_key = self
-            .private_key(name, enckey, &self_public_key)?
-            .chain(|| {
-                (
-                    ErrorKind::InvalidInput,
-                    format!(
-                        "Self public key ({}) is not owned by current wallet",
-                        self_public_key
-                    ),
-                )
-            })?;
+        offset: u64,
+        limit: u64,
+        reversed: bool,
+    ) -> Result<IndexSet<ExtendedAddr>> {
+        let _guard = self.wallet_locks.shared(name);
+        self.wallet_service
+            .transfer_addresses(name, enckey, offset, limit, reversed)
+    }
 
-        self.multi_sig_session_service.new_session(
-            message,
-            signer_public_keys,
-            self_public_key,
-            self_private_key,
-            enckey,
-        )
+    #[inline]
+    fn find_staking_key(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        redeem_address: &RedeemAddress,
+    ) -> Result<Option<PublicKey>> {
+        let _guard = self.wallet_locks.shared(name);
+        self.wallet_service
+            .find_staking_key(name, enckey, redeem_address)
     }
 
-    fn nonce_commitment(&self, session_id: &H256, enckey: &SecKey) -> Result<H256> {
-        self.multi_sig_session_service
-            .nonce_commitment(session_id, enckey)
+    #[inline]
+    fn find_root_hash(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        address: &ExtendedAddr,
+    ) -> Result<Option<H256>> {
+        let _guard = self.wallet_locks.shared(name);
+        self.wallet_service.find_root_hash(name, enckey, address)
     }
 
-    fn add_nonce_commitment(
+    fn derivation_path(
         &self,
-        session_id: &H256,
+        name: &str,
         enckey: &SecKey,
-        nonce_commitment: H256,
-        public_key: &PublicKey,
-    ) -> Result<()> {
-        self.multi_sig_session_service.add_nonce_commitment(
-            session_id,
-            nonce_commitment,
-            public_key,
-            enckey,
-        )
+        address: &WalletAddress,
+    ) -> Result<Option<DerivationPath>> {
+        let _guard = self.wallet_locks.shared(name);
+        let public_key = match address {
+            WalletAddress::Staking(StakedStateAddress::BasicRedeem(redeem_address)) => self
+                .wallet_service
+                .find_staking_key(name, enckey, redeem_address)?,
+            WalletAddress::Transfer(transfer_address) => {
+                match self
+                    .wallet_service
+                    .find_root_hash(name, enckey, transfer_address)?
+                {
+                    Some(root_hash) => Some(
+                        self.root_hash_service
+                            .public_key(name, &root_hash, enckey)?,
+                    ),
+                    None => None,
+                }
+            }
+        }
+        .err_kind(ErrorKind::InvalidInput, || {
+            format!("Address {} not found in wallet", address)
+        })?;
+
+        Ok(self
+            .wallet_service
+            .find_chain_path(name, enckey, &public_key)?
+            .as_ref()
+            .and_then(DerivationPath::from_chain_path))
     }
 
-    fn nonce(&self, session_id: &H256, enckey: &SecKey) -> Result<H256> {
-        self.multi_sig_session_service.nonce(session_id, enckey)
+    fn addresses_detailed(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+    ) -> Result<Vec<(WalletAddress, Option<DerivationPath>, Option<String>)>> {
+        let _guard = self.wallet_locks.shared(name);
+        let transfer_addresses = self
+            .wallet_service
+            .transfer_addresses(name, enckey, 0, 0, false)?;
+        let staking_addresses = self
+            .wallet_service
+            .staking_addresses(name, enckey, 0, 0, false)?;
+        let mut labels = self.address_label_service.get_labels(name, enckey)?;
+
+        transfer_addresses
+            .into_iter()
+            .map(WalletAddress::Transfer)
+            .chain(staking_addresses.into_iter().map(WalletAddress::Staking))
+            .map(|address| {
+                let derivation_path = self.derivation_path(name, enckey, &address)?;
+                let label = labels.remove(&address.to_string());
+                Ok((address, derivation_path, label))
+            })
+            .collect()
     }
 
-    fn add_nonce(
+    fn set_address_label(
         &self,
-        session_id: &H256,
+        name: &str,
         enckey: &SecKey,
-        nonce: &H256,
-        public_key: &PublicKey,
+        address: &WalletAddress,
+        label: &str,
     ) -> Result<()> {
-        self.multi_sig_session_service
-            .add_nonce(session_id, &nonce, public_key, enckey)
-    }
+        let _guard = self.wallet_locks.exclusive(name);
+        let wallet = self.wallet_service.get_wallet(name, enckey)?;
+        let in_wallet = match address {
+            WalletAddress::Transfer(address) => wallet.transfer_addresses_contains(address)?,
+            WalletAddress::Staking(address) => wallet.staking_addresses_contains(address)?,
+        };
+        if !in_wallet {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Address {} not found in wallet", address),
+            ));
+        }
 
-    fn partial_signature(&self, session_id: &H256, enckey: &SecKey) -> Result<H256> {
-        self.multi_sig_session_service
-            .partial_signature(session_id, enckey)
+        self.address_label_service
+            .set_label(name, enckey, address, label)
     }
 
-    fn add_partial_signature(
+    fn address_by_label(
         &self,
-        session_id: &H256,
+        name: &str,
         enckey: &SecKey,
-        partial_signature: H256,
-        public_key: &PublicKey,
-    ) -> Result<()> {
-        self.multi_sig_session_service.add_partial_signature(
-            session_id,
-            partial_signature,
-            public_key,
-            enckey,
-        )
-    }
+        label: &str,
+    ) -> Result<Option<WalletAddress>> {
+        let _guard = self.wallet_locks.shared(name);
+        // Check if wallet exists
+        self.wallet_service.view_key(name, enckey)?;
 
-    fn signature(&self, session_id: &H256, enckey: &SecKey) -> Result<SchnorrSignature> {
-        self.multi_sig_session_service.signature(session_id, enckey)
+        self.address_label_service.find_address(name, enckey, label)
     }
 
-    fn transaction(
+    fn add_contact(
         &self,
         name: &str,
-        session_id: &H256,
         enckey: &SecKey,
-        unsigned_transaction: Tx,
-    ) -> Result<TxAux> {
-        if unsigned_transaction.inputs.len() != 1 {
-            return Err(Error::new(
-                ErrorKind::InvalidInput,
-                "Multi-Sig Signing is only supported for transactions with only one input",
-            ));
-        }
-
-        let output_to_spend = self.output(name, enckey, &unsigned_transaction.inputs[0])?;
-        let root_hash = self
-            .wallet_service
-            .find_root_hash(name, enckey, &output_to_spend.address)?
-            .chain(|| {
-                (
-                    ErrorKind::IllegalInput,
-                    "Output address is not owned by current wallet; cannot spend output in given transaction",
-                )
-            })?;
-        let public_keys = self
-            .multi_sig_session_service
-            .public_keys(session_id, enckey)?;
+        contact: &str,
+        address: &ExtendedAddr,
+    ) -> Result<()> {
+        let _guard = self.wallet_locks.exclusive(name);
+        // Check if wallet exists (the address book of a wallet is empty until a contact is added)
+        self.wallet_service.view_key(name, enckey)?;
 
-        let proof = self
-            .root_hash_service
-            .generate_proof(name, &root_hash, public_keys, enckey)?;
-        let signature = self.signature(session_id, enckey)?;
+        self.address_book_service
+            .add_contact(name, enckey, contact, address)
+    }
 
-        let witness = TxWitness::from(vec![TxInWitness::TreeSig(signature, proof)]);
-        let signed_transaction =
-            SignedTransaction::TransferTransaction(unsigned_transaction, witness);
+    fn remove_contact(&self, name: &str, enckey: &SecKey, contact: &str) -> Result<()> {
+        let _guard = self.wallet_locks.exclusive(name);
+        self.wallet_service.view_key(name, enckey)?;
 
-        self.transaction_builder.obfuscate(signed_transaction)
+        self.address_book_service
+            .remove_contact(name, enckey, contact)
     }
-}
-
-fn check_passphrase_strength(name: &str, passphrase: &SecUtf8) -> Result<()> {
-    // `estimate_password_strength` returns a score between `0-4`. Any score less than 3 should be considered too
-    // weak.
-    let password_entropy = estimate_password_strength(passphrase.unsecure(), &[name])
-        .chain(|| (ErrorKind::IllegalInput, "Blank passphrase"))?;
 
-    #[cfg(debug_assertions)]
-    let entropy_score = 0;
-    #[cfg(not(debug_assertions))]
-    let entropy_score = 3;
+    fn contacts(&self, name: &str, enckey: &SecKey) -> Result<BTreeMap<String, ExtendedAddr>> {
+        let _guard = self.wallet_locks.shared(name);
+        self.wallet_service.view_key(name, enckey)?;
 
-    if password_entropy.score() < entropy_score {
-        return Err(Error::new(
-            ErrorKind::IllegalInput,
-            format!(
-                "Weak passphrase: {}",
-                parse_feedback(password_entropy.feedback().as_ref())
-            ),
-        ));
+        self.address_book_service.contacts(name, enckey)
     }
 
-    Ok(())
-}
+    fn find_contact(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        contact: &str,
+    ) -> Result<Option<ExtendedAddr>> {
+        let _guard = self.wallet_locks.shared(name);
+        self.wallet_service.view_key(name, enckey)?;
 
-fn parse_feedback(feedback: Option<&Feedback>) -> String {
-    match feedback {
-        None => "No feedback available!".to_string(),
-        Some(feedback) => {
-            let mut feedbacks = Vec::new();
+        self.address_book_service
+            .find_contact(name, enckey, contact)
+    }
 
-            if let Some(warning) = feedback.warning() {
-                feedbacks.push(format!("Warning: {}", warning));
-            }
+    fn verify_derivations(&self, name: &str, enckey: &SecKey) -> Result<Vec<PublicKey>> {
+        let _guard = self.wallet_locks.shared(name);
+        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
+        if wallet.wallet_kind != WalletKind::HD {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Derivation paths can only be verified for HD wallets",
+            ));
+        }
+        let hd_key = self
+            .hd_key_service
+            .get_hdkey(name, enckey)?
+            .chain(|| (ErrorKind::InvalidInput, "HD key not found in wallet"))?;
+        let network = get_network();
+        let coin_type = get_bip44_coin_type_from_network(network);
 
-            for suggestion in feedback.suggestions() {
-                feedbacks.push(format!("Suggestion: {}", suggestion));
+        let public_keys = self.wallet_service.public_keys(name, enckey)?;
+        let staking_keys = self
+            .wallet_service
+            .staking_keys(name, enckey, 0, 0, false)?;
+        let mut mismatches = Vec::new();
+        for public_key in public_keys.union(&staking_keys) {
+            let chain_path = match self
+                .wallet_service
+                .find_chain_path(name, enckey, public_key)?
+            {
+                Some(chain_path) => chain_path,
+                None => continue,
+            };
+            // the seed only derives the keys of bip44 paths of the network (with change 0)
+            let derived = match DerivationPath::from_chain_path(&chain_path) {
+                Some(path) if path.coin_type == coin_type && path.change == 0 => Some(
+                    hd_key
+                        .seed
+                        .derive_key_pair(network, path.account, path.index)?
+                        .0,
+                ),
+                _ => None,
+            };
+            if derived.as_ref() != Some(public_key) {
+                log::warn!(
+                    "key {} of wallet {} is not derived with its recorded path {}",
+                    public_key,
+                    name,
+                    chain_path
+                );
+                mismatches.push(public_key.clone());
             }
+        }
+        Ok(mismatches)
+    }
 
-            if feedbacks.is_empty() {
-                feedbacks.push("No feedback available!".to_string());
+    #[inline]
+    fn wallet_private_key(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        wallet_kind: WalletKind,
+    ) -> Result<Option<PrivateKey>> {
+        let _guard = self.wallet_locks.shared(name);
+        if wallet_kind != WalletKind::HW {
+            let k = self.key_service.wallet_private_key(name, enckey)?;
+            Ok(k)
+        } else {
+            unreachable!("can not get private key from HW key")
+        }
+    }
+
+    fn sign_key(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        public_key: &PublicKey,
+    ) -> Result<Box<dyn PrivateKeyAction>> {
+        let _guard = self.wallet_locks.shared(name);
+        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
+        match wallet.wallet_kind {
+            WalletKind::HW => {
+                let chain_path = self
+                    .wallet_service
+                    .find_chain_path(name, enckey, public_key)?
+                    .chain(|| {
+                        (
+                            ErrorKind::InvalidInput,
+                            "Not able to find chain path for given public_key in current wallet",
+                        )
+                    })?;
+                self.hw_key_service.get_sign_key(&chain_path)
+            }
+            WalletKind::Watch => Err(watch_only_error(name)),
+            WalletKind::Basic | WalletKind::HD => {
+                let private_key = self
+                    .wallet_service
+                    .find_private_key(name, enckey, public_key)?
+                    .chain(|| {
+                        (
+                            ErrorKind::InvalidInput,
+                            "Not able to find private key for given public_key in current wallet",
+                        )
+                    })?;
+                Ok(Box::new(private_key))
             }
+        }
+    }
 
-            feedbacks.join(" | ")
+    #[inline]
+    fn private_key(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        public_key: &PublicKey,
+    ) -> Result<Option<PrivateKey>> {
+        let _guard = self.wallet_locks.shared(name);
+        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
+        match wallet.wallet_kind {
+            WalletKind::HW => unreachable!("can not get private key in hw wallet"),
+            WalletKind::Watch => Err(watch_only_error(name)),
+            WalletKind::Basic | WalletKind::HD => self
+                .wallet_service
+                .find_private_key(name, enckey, public_key),
         }
     }
-}
 
-fn import_transaction(
-    wallet: &Wallet,
-    wallet_state: &WalletState,
-    memento: &mut WalletStateMemento,
-    transaction_info: &TransactionInfo,
-    paid_fee: Fee,
-    block_time: Time,
-    spent_flag: Vec<bool>,
-) -> Result<Coin> {
-    let transaction_change = create_transaction_change(
-        wallet,
-        wallet_state,
-        &transaction_info.tx,
-        paid_fee,
-        transaction_info.block_height,
-        block_time,
-    )
-    .chain(|| (ErrorKind::InvalidInput, "create transaction change failed"))?;
-    let mut value = Coin::zero();
-    let transfer_addresses = wallet.get_transfer_addresses()?;
-    for (i, (output, spent)) in transaction_change
-        .outputs
-        .iter()
-        .zip(spent_flag)
-        .enumerate()
-    {
-        // Only add unspent transaction if output address belongs to current wallet
-        if transfer_addresses.contains(&output.address) && !spent {
-            memento.add_unspent_transaction(
-                TxoPointer::new(transaction_change.transaction_id, i),
-                output.clone(),
-            );
-            value = (value + output.value).chain(|| {
+    fn new_public_key(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        address_type: Option<AddressType>,
+    ) -> Result<PublicKey> {
+        let _guard = self.wallet_locks.exclusive(name);
+        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
+        let account_type = match wallet.wallet_kind {
+            // the keys of basic wallets aren't derived for an account
+            WalletKind::Basic => address_type.unwrap_or(AddressType::Transfer),
+            WalletKind::HD => address_type.chain(|| {
                 (
                     ErrorKind::InvalidInput,
-                    "invalid coin in outputs of transaction",
+                    "Address type is needed when creating address for HD wallet",
                 )
-            })?;
+            })?,
+            WalletKind::HW => address_type.chain(|| {
+                (
+                    ErrorKind::InvalidInput,
+                    "Address type is needed when creating address for HW wallet",
+                )
+            })?,
+            WalletKind::Watch => return Err(watch_only_error(name)),
+        }
+        .into();
+        let mut batch = StorageBatch::default();
+        let public_key =
+            self.batch_new_key(&mut batch, name, enckey, wallet.wallet_kind, account_type)?;
+        // the keys of hardware wallets are only recorded with their chain paths
+        if wallet.wallet_kind != WalletKind::HW {
+            self.wallet_service
+                .batch_add_public_keys(&mut batch, name, &[public_key.clone()])?;
         }
+        self.storage.write_batch(batch)?;
+        Ok(public_key)
     }
-    memento.add_transaction_change(transaction_change);
-    Ok(value)
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::Mnemonic;
-    use client_common::storage::MemoryStorage;
-    use client_common::PublicKey;
-    use std::str::FromStr;
+    fn flush_database(&self) -> Result<()> {
+        self.storage
+            .flush()
+            .chain(|| (ErrorKind::IoError, "Unable to flush sled"))?;
+        Ok(())
+    }
+    fn new_staking_address(&self, name: &str, enckey: &SecKey) -> Result<StakedStateAddress> {
+        let _guard = self.wallet_locks.exclusive(name);
+        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
+        let mut batch = StorageBatch::default();
+        let public_key = self.batch_new_key(
+            &mut batch,
+            name,
+            enckey,
+            wallet.wallet_kind,
+            HDAccountType::Staking,
+        )?;
+        self.wallet_service
+            .batch_set_key_kind(&mut batch, name, &public_key, AddressType::Staking);
+        self.wallet_service
+            .batch_add_staking_keys(&mut batch, name, &[public_key.clone()])?;
+        self.storage.write_batch(batch)?;
 
-    #[test]
-    fn check_delete_wallet() {
-        let words = Mnemonic::from_secstr(&SecUtf8::from("pony thank pluck sweet bless tuna couple eight stove fluid essay debate cinnamon elite only")).unwrap();
-        let passphrase = SecUtf8::from("123456");
-        let wrong_passphrase = SecUtf8::from("123457");
-        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
-        client
-            .restore_wallet("Default", &passphrase, &words)
-            .expect("restore wallet");
-        // FIXME this failure will leave storage in an inconsistant state
-        // assert!(client.restore_wallet("test", &passphrase, &words).is_err());
-        assert!(client.delete_wallet("Default", &wrong_passphrase).is_err());
-        assert!(client.delete_wallet("Default1", &passphrase).is_err());
-        client
-            .delete_wallet("Default", &passphrase)
-            .expect("delete wallet");
-        client
-            .restore_wallet("test", &passphrase, &words)
-            .expect("restore wallet");
+        Ok(StakedStateAddress::BasicRedeem(RedeemAddress::from(
+            &public_key,
+        )))
+    }
+
+    fn new_transfer_address(&self, name: &str, enckey: &SecKey) -> Result<ExtendedAddr> {
+        let _guard = self.wallet_locks.exclusive(name);
+        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
+        let mut batch = StorageBatch::default();
+        let public_key = self.batch_new_key(
+            &mut batch,
+            name,
+            enckey,
+            wallet.wallet_kind,
+            HDAccountType::Transfer,
+        )?;
+        self.wallet_service.batch_set_key_kind(
+            &mut batch,
+            name,
+            &public_key,
+            AddressType::Transfer,
+        );
+        self.wallet_service
+            .batch_add_public_keys(&mut batch, name, &[public_key.clone()])?;
+        let (root_hash, multi_sig_address) = self.root_hash_service.batch_new_root_hash(
+            &mut batch,
+            name,
+            vec![public_key.clone()],
+            public_key,
+            1,
+            enckey,
+        )?;
+        self.wallet_service
+            .batch_add_root_hashes(&mut batch, name, &[root_hash])?;
+        self.storage.write_batch(batch)?;
+
+        self.storage
+            .flush()
+            .chain(|| (ErrorKind::IoError, "Unable to flush sled"))?;
+
+        Ok(multi_sig_address.into())
+    }
+
+    fn new_addresses(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        count: usize,
+    ) -> Result<Vec<ExtendedAddr>> {
+        let _guard = self.wallet_locks.exclusive(name);
+        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
+        let mut batch = StorageBatch::default();
+        let public_keys = match wallet.wallet_kind {
+            WalletKind::Basic => (0..count)
+                .map(|_| {
+                    let private_key = PrivateKey::new()?;
+                    let public_key = PublicKey::from(&private_key);
+                    self.wallet_service.batch_add_key_pairs(
+                        &mut batch,
+                        name,
+                        enckey,
+                        &public_key,
+                        &private_key,
+                    )?;
+                    Ok(public_key)
+                })
+                .collect::<Result<Vec<_>>>()?,
+            WalletKind::HD => self
+                .hd_key_service
+                .batch_generate_keypairs_with_path(
+                    &mut batch,
+                    name,
+                    enckey,
+                    HDAccountType::Transfer,
+                    count,
+                )?
+                .into_iter()
+                .map(|(public_key, private_key, hd_path)| {
+                    self.wallet_service.batch_add_key_pairs(
+                        &mut batch,
+                        name,
+                        enckey,
+                        &public_key,
+                        &private_key,
+                    )?;
+                    self.wallet_service.batch_add_key_path(
+                        &mut batch,
+                        name,
+                        enckey,
+                        &public_key,
+                        &hd_path,
+                    )?;
+                    Ok(public_key)
+                })
+                .collect::<Result<Vec<_>>>()?,
+            WalletKind::HW => self
+                .hd_key_service
+                .batch_generate_chain_paths(
+                    &mut batch,
+                    name,
+                    enckey,
+                    HDAccountType::Transfer,
+                    count,
+                )?
+                .into_iter()
+                .map(|hd_path| {
+                    let public_key = self.hw_key_service.get_public_key(hd_path.clone())?;
+                    self.wallet_service.batch_add_key_path(
+                        &mut batch,
+                        name,
+                        enckey,
+                        &public_key,
+                        &hd_path,
+                    )?;
+                    Ok(public_key)
+                })
+                .collect::<Result<Vec<_>>>()?,
+            WalletKind::Watch => return Err(watch_only_error(name)),
+        };
+        self.wallet_service
+            .batch_add_public_keys(&mut batch, name, &public_keys)?;
+
+        let mut root_hashes = Vec::with_capacity(count);
+        let mut addresses = Vec::with_capacity(count);
+        for public_key in public_keys {
+            self.wallet_service.batch_set_key_kind(
+                &mut batch,
+                name,
+                &public_key,
+                AddressType::Transfer,
+            );
+            let (root_hash, multi_sig_address) = self.root_hash_service.batch_new_root_hash(
+                &mut batch,
+                name,
+                vec![public_key.clone()],
+                public_key,
+                1,
+                enckey,
+            )?;
+            root_hashes.push(root_hash);
+            addresses.push(multi_sig_address.into());
+        }
+        self.wallet_service
+            .batch_add_root_hashes(&mut batch, name, &root_hashes)?;
+
+        self.storage.write_batch(batch)?;
+        self.storage
+            .flush()
+            .chain(|| (ErrorKind::IoError, "Unable to flush sled"))?;
+        Ok(addresses)
+    }
+
+    fn import_private_key(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        private_key: &SecStr,
+        address_type: AddressType,
+    ) -> Result<ImportedAddress> {
+        let _guard = self.wallet_locks.exclusive(name);
+        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
+        if wallet.wallet_kind == WalletKind::HW {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "Private keys can not be imported into hardware wallet",
+            ));
+        }
+        if wallet.wallet_kind == WalletKind::Watch {
+            return Err(watch_only_error(name));
+        }
+
+        let private_key = private_key.unsecure();
+        if private_key.len() != 32 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Private key should be 32 bytes",
+            ));
+        }
+        let private_key = PrivateKey::deserialize_from(private_key).chain(|| {
+            (
+                ErrorKind::InvalidInput,
+                "Private key is not within secp256k1 curve order",
+            )
+        })?;
+        let public_key = PublicKey::from(&private_key);
+
+        if self
+            .wallet_service
+            .find_private_key(name, enckey, &public_key)?
+            .is_some()
+        {
+            // importing the key again (with the same address type) is a no-op
+            return match self.imported_address(name, enckey, &public_key, address_type)? {
+                Some(address) => Ok(address),
+                None => Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "Private key already exists in wallet with another address type",
+                )),
+            };
+        }
+
+        let mut batch = StorageBatch::default();
+        self.wallet_service.batch_add_key_pairs(
+            &mut batch,
+            name,
+            enckey,
+            &public_key,
+            &private_key,
+        )?;
+        self.wallet_service
+            .batch_add_imported_key(&mut batch, name, &public_key);
+        self.wallet_service
+            .batch_set_key_kind(&mut batch, name, &public_key, address_type);
+
+        let address = match address_type {
+            AddressType::Transfer => {
+                self.wallet_service.batch_add_public_keys(
+                    &mut batch,
+                    name,
+                    &[public_key.clone()],
+                )?;
+                let (root_hash, multi_sig_address) = self.root_hash_service.batch_new_root_hash(
+                    &mut batch,
+                    name,
+                    vec![public_key.clone()],
+                    public_key,
+                    1,
+                    enckey,
+                )?;
+                self.wallet_service
+                    .batch_add_root_hashes(&mut batch, name, &[root_hash])?;
+                ImportedAddress::Transfer(multi_sig_address.into())
+            }
+            AddressType::Staking => {
+                self.wallet_service.batch_add_staking_keys(
+                    &mut batch,
+                    name,
+                    &[public_key.clone()],
+                )?;
+                ImportedAddress::Staking(StakedStateAddress::BasicRedeem(RedeemAddress::from(
+                    &public_key,
+                )))
+            }
+        };
+
+        self.storage.write_batch(batch)?;
+        self.storage
+            .flush()
+            .chain(|| (ErrorKind::IoError, "Unable to flush sled"))?;
+
+        Ok(address)
+    }
+
+    #[inline]
+    fn imported_keys(&self, name: &str, enckey: &SecKey) -> Result<IndexSet<PublicKey>> {
+        let _guard = self.wallet_locks.shared(name);
+        // To verify if the enckey is correct or not
+        self.wallet_service.view_key(name, enckey)?;
+        self.wallet_service.imported_keys(name)
+    }
+
+    fn create_payment_request(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        amount: Option<Coin>,
+        label: Option<String>,
+        expires: Option<Timespec>,
+    ) -> Result<String> {
+        let _guard = self.wallet_locks.exclusive(name);
+        let address = self.new_transfer_address(name, enckey)?;
+        Ok(PaymentRequest::new(address, amount, label, expires).to_string())
+    }
+
+    fn new_watch_staking_address(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        public_key: &PublicKey,
+    ) -> Result<StakedStateAddress> {
+        let _guard = self.wallet_locks.exclusive(name);
+        self.wallet_service
+            .add_staking_key(name, enckey, public_key)?;
+
+        self.storage
+            .flush()
+            .chain(|| (ErrorKind::IoError, "Unable to flush sled"))?;
+
+        Ok(StakedStateAddress::BasicRedeem(RedeemAddress::from(
+            public_key,
+        )))
+    }
+
+    fn new_watch_transfer_address(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        public_key: &PublicKey,
+    ) -> Result<ExtendedAddr> {
+        let _guard = self.wallet_locks.exclusive(name);
+        if self.wallet_service.key_kind(name, public_key)? == Some(AddressType::Staking) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Public key is generated for staking addresses, it can't be used for a transfer address",
+            ));
+        }
+        self.new_multisig_transfer_address(
+            name,
+            enckey,
+            vec![public_key.clone()],
+            public_key.clone(),
+            1,
+        )
+    }
+
+    fn new_multisig_transfer_address(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        public_keys: Vec<PublicKey>,
+        self_public_key: PublicKey,
+        m: usize,
+    ) -> Result<ExtendedAddr> {
+        let _guard = self.wallet_locks.exclusive(name);
+        if !public_keys.contains(&self_public_key) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Signer public keys does not contain self public key",
+            ));
+        }
+
+        let (root_hash, multi_sig_address) =
+            self.root_hash_service
+                .new_root_hash(name, public_keys, self_public_key, m, enckey)?;
+
+        self.wallet_service.add_root_hash(name, enckey, root_hash)?;
+
+        self.storage
+            .flush()
+            .chain(|| (ErrorKind::IoError, "Unable to flush sled"))?;
+
+        Ok(multi_sig_address.into())
+    }
+
+    fn get_multisig_addresses(&self, name: &str, enckey: &SecKey) -> Result<Vec<MultiSigAddress>> {
+        let _guard = self.wallet_locks.shared(name);
+        let root_hashes = self.wallet_service.root_hashes(name, enckey, 0, 0, false)?;
+        root_hashes
+            .iter()
+            .map(|hash| {
+                self.root_hash_service
+                    .get_multi_sig_address_from_root_hash(name, hash, enckey)
+            })
+            .collect::<Result<Vec<MultiSigAddress>>>()
+    }
+
+    fn generate_proof(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        address: &ExtendedAddr,
+        public_keys: Vec<PublicKey>,
+    ) -> Result<Proof<RawXOnlyPubkey>> {
+        let _guard = self.wallet_locks.shared(name);
+        // To verify if the enckey is correct or not
+        self.wallet_service.view_key(name, enckey)?;
+
+        match address {
+            ExtendedAddr::OrTree(ref address) => {
+                self.root_hash_service
+                    .generate_proof(name, address, public_keys, enckey)
+            }
+        }
+    }
+
+    fn required_cosigners(&self, name: &str, enckey: &SecKey, root_hash: &H256) -> Result<usize> {
+        let _guard = self.wallet_locks.shared(name);
+        // To verify if the enckey is correct or not
+        self.wallet_service.view_key(name, enckey)?;
+
+        self.root_hash_service
+            .required_signers(name, root_hash, enckey)
+    }
+
+    fn balance(&self, name: &str, enckey: &SecKey) -> Result<WalletBalance> {
+        let _guard = self.wallet_locks.shared(name);
+        // Check if wallet exists
+        self.wallet_service.view_key(name, enckey)?;
+
+        let min_confirmations = self.wallet_service.min_confirmations(name, enckey)?;
+        // the current block height is only needed to count immature outputs
+        let current_block_height = if min_confirmations == 0 {
+            0
+        } else {
+            self.get_current_block_height()?
+        };
+        let wallet_state = self.wallet_service.get_wallet_state(name, enckey)?;
+        // and the time of the latest block to count the time-locked ones (as when they're spent)
+        let current_block_time = if wallet_state
+            .unspent_transactions
+            .values()
+            .any(|output| output.valid_from.is_some())
+        {
+            self.get_current_block_time()?
+        } else {
+            0
+        };
+        wallet_state
+            .get_balance_with_confirmations(
+                current_block_height,
+                min_confirmations,
+                current_block_time,
+            )
+            .chain(|| (ErrorKind::StorageError, "Calculate balance error"))
+    }
+
+    #[inline]
+    fn min_confirmations(&self, name: &str, enckey: &SecKey) -> Result<u32> {
+        let _guard = self.wallet_locks.shared(name);
+        self.wallet_service.min_confirmations(name, enckey)
+    }
+
+    #[inline]
+    fn set_min_confirmations(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        min_confirmations: u32,
+    ) -> Result<()> {
+        let _guard = self.wallet_locks.exclusive(name);
+        self.wallet_service
+            .set_min_confirmations(name, enckey, min_confirmations)
+    }
+
+    #[inline]
+    fn compliance_policy(&self, name: &str, enckey: &SecKey) -> Result<CompliancePolicy> {
+        let _guard = self.wallet_locks.shared(name);
+        self.wallet_service.compliance_policy(name, enckey)
+    }
+
+    #[inline]
+    fn set_compliance_policy(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        policy: CompliancePolicy,
+    ) -> Result<()> {
+        let _guard = self.wallet_locks.exclusive(name);
+        self.wallet_service
+            .set_compliance_policy(name, enckey, policy)
+    }
+
+    #[inline]
+    fn retention_policy(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        class: RecordClass,
+    ) -> Result<RetentionPolicy> {
+        let _guard = self.wallet_locks.shared(name);
+        self.wallet_service.retention_policy(name, enckey, class)
+    }
+
+    #[inline]
+    fn set_retention_policy(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        class: RecordClass,
+        policy: RetentionPolicy,
+    ) -> Result<()> {
+        let _guard = self.wallet_locks.exclusive(name);
+        self.wallet_service
+            .set_retention_policy(name, enckey, class, policy)
+    }
+
+    #[inline]
+    fn archive_mark(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        class: RecordClass,
+    ) -> Result<Option<ArchiveMark>> {
+        let _guard = self.wallet_locks.shared(name);
+        self.wallet_service.archive_mark(name, enckey, class)
+    }
+
+    fn history(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        offset: usize,
+        limit: usize,
+        reversed: bool,
+    ) -> Result<Vec<TransactionChange>> {
+        // also checks that the wallet exists
+        self.ensure_history_indexed(name, enckey)?;
+        let _guard = self.wallet_locks.shared(name);
+
+        let mut history = self
+            .history_index_service
+            .history_page(name, enckey, offset, limit, reversed)?;
+        self.transaction_metadata_service
+            .attach_memos(name, enckey, &mut history)?;
+        Ok(history)
+    }
+
+    fn history_paged(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        offset: usize,
+        limit: usize,
+        filter: &HistoryFilter,
+    ) -> Result<HistoryPage> {
+        // also checks that the wallet exists
+        self.ensure_history_indexed(name, enckey)?;
+        let _guard = self.wallet_locks.shared(name);
+
+        let mut page = self
+            .history_index_service
+            .history_filtered(name, enckey, offset, limit, filter)?;
+        self.transaction_metadata_service
+            .attach_memos(name, enckey, &mut page.changes)?;
+        Ok(page)
+    }
+
+    fn history_filtered(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        filter: &HistoryFilter,
+    ) -> Result<Vec<TransactionChange>> {
+        // also checks that the wallet exists
+        self.ensure_history_indexed(name, enckey)?;
+        let _guard = self.wallet_locks.shared(name);
+
+        let mut history = self
+            .history_index_service
+            .history_matching(name, enckey, filter)?;
+        self.transaction_metadata_service
+            .attach_memos(name, enckey, &mut history)?;
+        Ok(history)
+    }
+
+    fn history_range(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        from_height: u64,
+        to_height: u64,
+    ) -> Result<Vec<TransactionChange>> {
+        // also checks that the wallet exists
+        self.ensure_history_indexed(name, enckey)?;
+        let _guard = self.wallet_locks.shared(name);
+
+        let mut history =
+            self.history_index_service
+                .history_range(name, enckey, from_height, to_height)?;
+        self.transaction_metadata_service
+            .attach_memos(name, enckey, &mut history)?;
+        Ok(history)
+    }
+
+    fn get_transaction_change(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        transaction_id: &TxId,
+    ) -> Result<Option<TransactionChange>> {
+        let _guard = self.wallet_locks.shared(name);
+        let mut change =
+            self.wallet_state_service
+                .get_transaction_change(name, enckey, transaction_id)?;
+        if let Some(ref mut change) = change {
+            change.memo =
+                self.transaction_metadata_service
+                    .get_memo(name, enckey, transaction_id)?;
+        }
+        Ok(change)
+    }
+
+    fn transaction_details(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        transaction_id: &TxId,
+    ) -> Result<Option<TransactionDetails>> {
+        let _guard = self.wallet_locks.shared(name);
+        let change =
+            self.wallet_state_service
+                .get_transaction_change(name, enckey, transaction_id)?;
+        let transaction = match self.get_transaction(name, enckey, *transaction_id) {
+            Ok(transaction) => Some(transaction),
+            Err(e) if change.is_some() || e.kind() == ErrorKind::InvalidInput => {
+                // only the metadata recorded when the transaction was synced is known
+                log::debug!(
+                    "unable to decrypt transaction {}: {}",
+                    hex::encode(transaction_id),
+                    e
+                );
+                None
+            }
+            Err(e) => return Err(e),
+        };
+        let inputs = match (&change, &transaction) {
+            (Some(change), _) => change.inputs.clone(),
+            (None, Some(transaction)) => transaction
+                .inputs()
+                .iter()
+                .map(|pointer| TransactionInput {
+                    pointer: pointer.clone(),
+                    output: None,
+                })
+                .collect(),
+            (None, None) => return Ok(None),
+        };
+        let outputs = match (&transaction, &change) {
+            (Some(transaction), _) => transaction.outputs().to_vec(),
+            (None, Some(change)) => change.outputs.clone(),
+            (None, None) => return Ok(None),
+        };
+        let block_height = change.as_ref().map(|change| change.block_height);
+        let confirmations = match (
+            block_height,
+            self.sync_state_service.get_global_state(name)?,
+        ) {
+            (Some(block_height), Some(sync_state)) => sync_state
+                .last_block_height
+                .checked_sub(block_height)
+                .map(|blocks| blocks + 1),
+            _ => None,
+        };
+
+        Ok(Some(TransactionDetails {
+            transaction_id: *transaction_id,
+            inputs,
+            outputs,
+            fee: change.as_ref().map(|change| change.fee_paid.to_coin()),
+            block_height,
+            confirmations,
+            decrypted: transaction.is_some(),
+        }))
+    }
+
+    fn set_transaction_memo(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        transaction_id: TxId,
+        memo: Option<String>,
+    ) -> Result<()> {
+        let _guard = self.wallet_locks.exclusive(name);
+        // Check if wallet exists
+        self.wallet_service.view_key(name, enckey)?;
+
+        self.transaction_metadata_service
+            .set_memo(name, enckey, transaction_id, memo)
+    }
+
+    fn transaction_memo(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        transaction_id: &TxId,
+    ) -> Result<Option<String>> {
+        let _guard = self.wallet_locks.shared(name);
+        // Check if wallet exists
+        self.wallet_service.view_key(name, enckey)?;
+
+        self.transaction_metadata_service
+            .get_memo(name, enckey, transaction_id)
+    }
+
+    fn fee_summary(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<FeeSummary> {
+        let _guard = self.wallet_locks.shared(name);
+        // Check if wallet exists
+        self.wallet_service.view_key(name, enckey)?;
+
+        let history = self
+            .wallet_state_service
+            .get_transaction_history(name, enckey, false)?;
+        let estimates = self.fee_estimate_service.get_all(name, enckey)?;
+        let mut summary = FeeSummary::new(history, &estimates, from, to)?;
+        let mut archived_until = None;
+        for class in RecordClass::ALL.iter() {
+            archived_until =
+                archived_until.max(self.wallet_service.archive_mark(name, enckey, *class)?);
+        }
+        summary.archived_until = archived_until
+            .map(|mark| mark.date())
+            .filter(|date| from <= *date);
+        Ok(summary)
+    }
+
+    fn export_proof_bundle(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        tx_ids: Vec<TxId>,
+        cancellation: &CancellationToken,
+    ) -> Result<Vec<u8>> {
+        let _guard = self.wallet_locks.shared(name);
+        let genesis = self.tendermint_client.genesis()?;
+        let genesis_app_hash: &[u8] = genesis.app_hash.as_ref();
+        let anchor = TrustAnchor {
+            chain_id: genesis.chain_id.to_string(),
+            genesis_app_hash: genesis_app_hash
+                .try_into()
+                .chain(|| (ErrorKind::VerifyError, "Invalid genesis app hash"))?,
+        };
+
+        let proofs = tx_ids
+            .into_iter()
+            .map(|tx_id| {
+                cancellation.check()?;
+                self.transaction_proof(name, enckey, tx_id, cancellation)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(ProofBundle { anchor, proofs }.encode_versioned())
+    }
+
+    fn unspent_transactions(&self, name: &str, enckey: &SecKey) -> Result<UnspentTransactions> {
+        let _guard = self.wallet_locks.shared(name);
+        // Check if wallet exists
+        self.wallet_service.view_key(name, enckey)?;
+
+        let unspent_transactions = self
+            .wallet_state_service
+            .get_unspent_transactions(name, enckey, false)?;
+
+        Ok(UnspentTransactions::new(
+            unspent_transactions.into_iter().collect(),
+        ))
+    }
+
+    fn has_unspent_transactions(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        inputs: &[TxoPointer],
+    ) -> Result<bool> {
+        let _guard = self.wallet_locks.shared(name);
+        // Check if wallet exists
+        self.wallet_service.view_key(name, enckey)?;
+
+        self.wallet_state_service
+            .has_unspent_transactions(name, enckey, inputs)
+    }
+
+    #[inline]
+    fn are_inputs_unspent(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        inputs: Vec<TxoPointer>,
+    ) -> Result<Vec<(TxoPointer, bool)>> {
+        let _guard = self.wallet_locks.shared(name);
+        self.wallet_state_service
+            .are_inputs_unspent(name, enckey, inputs)
+    }
+
+    fn immature_inputs(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        inputs: &[TxoPointer],
+    ) -> Result<Vec<TxoPointer>> {
+        let _guard = self.wallet_locks.shared(name);
+        let min_confirmations = self.wallet_service.min_confirmations(name, enckey)?;
+        if min_confirmations == 0 {
+            return Ok(vec![]);
+        }
+
+        let current_block_height = self.get_current_block_height()?;
+        self.wallet_state_service.get_immature_inputs(
+            name,
+            enckey,
+            inputs,
+            current_block_height,
+            min_confirmations,
+        )
+    }
+
+    #[inline]
+    fn output(&self, name: &str, enckey: &SecKey, input: &TxoPointer) -> Result<TxOut> {
+        let _guard = self.wallet_locks.shared(name);
+        // Check if wallet exists
+        self.wallet_service.view_key(name, enckey)?;
+
+        self.wallet_state_service
+            .get_output(name, enckey, input)
+            .and_then(|optional| {
+                optional.chain(|| {
+                    (
+                        ErrorKind::InvalidInput,
+                        "Output details not found for given transaction input",
+                    )
+                })
+            })
+    }
+
+    fn create_transaction(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        outputs: Vec<TxOut>,
+        attributes: TxAttributes,
+        input_selection_strategy: Option<InputSelectionStrategy>,
+        return_address: ExtendedAddr,
+        allow_self_transfer: bool,
+        fee_tip: FeeTip,
+    ) -> Result<(TxAux, Vec<TxoPointer>, Coin)> {
+        let _guard = self.wallet_locks.shared(name);
+        self.ensure_signing_wallet(name, enckey)?;
+        // the multi-sig addresses are in the root hashes of the wallet as well
+        self.wallet_service
+            .compliance_policy(name, enckey)?
+            .check_outputs(&outputs, allow_self_transfer, |address| {
+                Ok(self
+                    .wallet_service
+                    .find_root_hash(name, enckey, address)?
+                    .is_some())
+            })?;
+        let mut unspent_transactions = self.spendable_transactions(name, enckey)?;
+        unspent_transactions.apply_all(input_selection_strategy.unwrap_or_default().as_ref());
+
+        self.transaction_builder.build_transfer_tx(
+            name,
+            enckey,
+            unspent_transactions,
+            outputs,
+            return_address,
+            attributes,
+            fee_tip,
+        )
+    }
+
+    fn estimate_fee(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        outputs: Vec<TxOut>,
+        attributes: TxAttributes,
+    ) -> Result<Coin> {
+        let _guard = self.wallet_locks.shared(name);
+        let mut unspent_transactions = self.spendable_transactions(name, enckey)?;
+        unspent_transactions.apply_all(InputSelectionStrategy::default().as_ref());
+
+        // the change would be returned to a new transfer address, which has the same size
+        let return_address = ExtendedAddr::OrTree([0; 32]);
+        self.transaction_builder.estimate_fee(
+            unspent_transactions,
+            outputs,
+            return_address,
+            attributes,
+        )
+    }
+
+    #[inline]
+    fn broadcast_transaction(&self, tx_aux: &TxAux) -> Result<BroadcastTxResponse> {
+        self.tendermint_client
+            .broadcast_transaction(&tx_aux.encode())
+    }
+
+    fn export_plain_tx(&self, name: &str, enckey: &SecKey, txid: &str) -> Result<TransactionInfo> {
+        let _guard = self.wallet_locks.shared(name);
+        let txid = str2txid(txid).chain(|| (ErrorKind::InvalidInput, "invalid transaction id"))?;
+        let tx = self.get_transaction(name, enckey, txid)?;
+        // get the block height
+        let tx_change = self
+            .wallet_state_service
+            .get_transaction_history(name, enckey, false)?
+            .filter(|change| BalanceChange::NoChange != change.balance_change)
+            .find(|tx_change| tx_change.transaction_id == tx.id())
+            .chain(|| {
+                (
+                    ErrorKind::InvalidInput,
+                    "no transaction find by transaction id",
+                )
+            })?;
+
+        let tx_info = TransactionInfo {
+            tx,
+            block_height: tx_change.block_height,
+        };
+        Ok(tx_info)
+    }
+
+    /// import a plain base64 encoded plain transaction
+    fn import_plain_tx(&self, name: &str, enckey: &SecKey, tx_str: &str) -> Result<Coin> {
+        let _guard = self.wallet_locks.exclusive(name);
+        let tx_info = TransactionInfo::decode(tx_str)?;
+
+        let found_tx = self.is_tx_exist(name, enckey, tx_info.tx.id())?;
+        if found_tx {
+            return Err(Error::new(
+                ErrorKind::ValidationError,
+                "This transaction is already imported/in your wallet",
+            ));
+        }
+
+        // check if the output is spent or not
+        let v = self
+            .tendermint_client
+            .query("meta", &tx_info.tx.id().to_vec(), None, false)?
+            .bytes();
+        let bit_flag = BitVec::from_bytes(&v);
+        let spent_flags: Result<Vec<bool>> = tx_info
+            .tx
+            .outputs()
+            .iter()
+            .enumerate()
+            .map(|(index, _output)| {
+                bit_flag
+                    .get(index)
+                    .chain(|| (ErrorKind::InvalidInput, "check failed in enclave"))
+            })
+            .collect();
+        let mut memento = WalletStateMemento::default();
+        // check if tx belongs to the block
+        let block = self.tendermint_client.block(tx_info.block_height)?;
+        let block_result = self.tendermint_client.block_results(tx_info.block_height)?;
+        let fees = block_result.fees()?;
+        let paid_fee = fees.get(&tx_info.tx.id());
+        if paid_fee.is_none() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "block height and transaction not match",
+            ));
+        }
+        let wallet = self.wallet_service.get_wallet(name, enckey)?;
+
+        let wallet_state = self.wallet_service.get_wallet_state(name, enckey)?;
+
+        let imported_value = import_transaction(
+            &wallet,
+            &wallet_state,
+            &mut memento,
+            &tx_info,
+            *paid_fee.expect("tx fee checked above"),
+            block.header.time,
+            spent_flags?,
+        )
+        .chain(|| (ErrorKind::InvalidInput, "import error"))?;
+
+        self.wallet_state_service
+            .apply_memento(name, enckey, &memento)?;
+
+        self.storage
+            .flush()
+            .chain(|| (ErrorKind::IoError, "Unable to flush sled"))?;
+
+        Ok(imported_value)
+    }
+
+    fn get_current_block_height(&self) -> Result<u64> {
+        let status = self.tendermint_client.status()?;
+        let current_block_height = status.sync_info.latest_block_height.value();
+        Ok(current_block_height)
+    }
+
+    fn update_tx_pending_state(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        tx_aux: &TxAux,
+        tx_pending: TransactionPending,
+    ) -> Result<()> {
+        self.track_pending_transaction(name, enckey, tx_aux, tx_pending, None)
+    }
+
+    fn pending_transactions(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+    ) -> Result<Vec<PendingTransactionInfo>> {
+        let _guard = self.wallet_locks.shared(name);
+        let now = unix_timestamp()?;
+        let wallet_state = self.wallet_service.get_wallet_state(name, enckey)?;
+        // confirmed transactions are reported until the wallet sync stops tracking them
+        let transactions = self
+            .pending_transaction_service
+            .get_all(name, enckey)?
+            .into_iter()
+            .map(|transaction| {
+                transaction.info(
+                    transaction.status(
+                        wallet_state
+                            .transaction_history
+                            .contains_key(&transaction.tx_id),
+                        now,
+                        DEFAULT_PENDING_TRANSACTION_TIMEOUT,
+                    ),
+                )
+            })
+            .collect::<Vec<_>>();
+        Ok(transactions)
+    }
+
+    fn rebroadcast_pending_transaction(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        tx_id: &TxId,
+    ) -> Result<()> {
+        let _guard = self.wallet_locks.exclusive(name);
+        self.chain_tip_service.ensure_no_mismatch(name)?;
+        let now = unix_timestamp()?;
+        let wallet_state = self.wallet_service.get_wallet_state(name, enckey)?;
+        let transaction = self.pending_transaction_service.get(name, enckey, tx_id)?;
+        ensure_stale_transaction(&transaction, &wallet_state, now)?;
+        self.ensure_not_included(tx_id)?;
+
+        self.resubmit_pending_transaction(name, enckey, &wallet_state, transaction, now)
+    }
+
+    fn rebroadcast_pending(&self, name: &str, enckey: &SecKey) -> Result<Vec<TxId>> {
+        let _guard = self.wallet_locks.exclusive(name);
+        self.chain_tip_service.ensure_no_mismatch(name)?;
+        let now = unix_timestamp()?;
+        let wallet_state = self.wallet_service.get_wallet_state(name, enckey)?;
+
+        let mut rebroadcast = Vec::new();
+        for transaction in self.pending_transaction_service.get_all(name, enckey)? {
+            let tx_id = transaction.tx_id;
+            if wallet_state.transaction_history.contains_key(&tx_id)
+                || transaction.is_conflicting(&wallet_state)
+                || self.ensure_not_included(&tx_id).is_err()
+            {
+                // it'll be (or was) cleaned up by the wallet sync
+                continue;
+            }
+            match self.resubmit_pending_transaction(name, enckey, &wallet_state, transaction, now) {
+                Ok(()) => rebroadcast.push(tx_id),
+                Err(err) if err.kind() == ErrorKind::ConnectionError => return Err(err),
+                Err(err) => log::warn!(
+                    "unable to re-broadcast transaction {} of wallet {}: {}",
+                    hex::encode(tx_id),
+                    name,
+                    err
+                ),
+            }
+        }
+        Ok(rebroadcast)
+    }
+
+    fn expire_pending_transaction(&self, name: &str, enckey: &SecKey, tx_id: &TxId) -> Result<()> {
+        let _guard = self.wallet_locks.exclusive(name);
+        let now = unix_timestamp()?;
+        let wallet_state = self.wallet_service.get_wallet_state(name, enckey)?;
+        let transaction = self.pending_transaction_service.get(name, enckey, tx_id)?;
+        ensure_stale_transaction(&transaction, &wallet_state, now)?;
+        self.ensure_not_included(tx_id)?;
+
+        if wallet_state.pending_transactions.contains_key(tx_id) {
+            let mut wallet_state_memento = WalletStateMemento::default();
+            wallet_state_memento.remove_pending_transaction(*tx_id);
+            self.wallet_state_service
+                .apply_memento(name, enckey, &wallet_state_memento)?;
+        }
+        self.output_lock_service
+            .unlock(name, enckey, &transaction.pending.used_inputs)?;
+        self.pending_transaction_service
+            .remove(name, enckey, &[*tx_id])
+    }
+
+    fn lock_output(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        input: &TxoPointer,
+        timeout: Option<Timespec>,
+    ) -> Result<()> {
+        let _guard = self.wallet_locks.exclusive(name);
+        // Check if wallet exists
+        self.wallet_service.view_key(name, enckey)?;
+
+        if !self.wallet_state_service.has_unspent_transactions(
+            name,
+            enckey,
+            std::slice::from_ref(input),
+        )? {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Output to lock is not an unspent output of the wallet",
+            ));
+        }
+        self.output_lock_service.lock(
+            name,
+            enckey,
+            std::slice::from_ref(input),
+            unix_timestamp()?,
+            timeout.unwrap_or(DEFAULT_OUTPUT_LOCK_TIMEOUT),
+        )
+    }
+
+    fn unlock_output(&self, name: &str, enckey: &SecKey, input: &TxoPointer) -> Result<()> {
+        let _guard = self.wallet_locks.exclusive(name);
+        // Check if wallet exists
+        self.wallet_service.view_key(name, enckey)?;
+
+        self.output_lock_service
+            .unlock(name, enckey, std::slice::from_ref(input))
+    }
+
+    fn build_raw_transfer_tx(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        to_address: ExtendedAddr,
+        amount: Coin,
+        view_keys: Vec<PublicKey>,
+        network_id: u8,
+    ) -> Result<UnsignedTransferTransaction> {
+        let _guard = self.wallet_locks.exclusive(name);
+        let unspent_transactions = self.spendable_transactions(name, enckey)?;
+        let return_address = self.new_transfer_address(name, enckey)?;
+        let unsigned = UnsignedTransferTransaction {
+            unspent_transactions,
+            view_keys,
+            network_id,
+            to_address,
+            return_address,
+            amount,
+        };
+        Ok(unsigned)
+    }
+
+    fn sign_raw_transfer_tx(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        unsigned_tx: UnsignedTransferTransaction,
+    ) -> Result<SignedTransferTransaction> {
+        let _guard = self.wallet_locks.shared(name);
+        self.ensure_signing_wallet(name, enckey)?;
+        let tx_out = TxOut::new(unsigned_tx.to_address, unsigned_tx.amount);
+        let view_key = self.view_key(name, enckey)?;
+        let mut view_keys = unsigned_tx.view_keys;
+        view_keys.push(view_key);
+        let attributes = TxAttributes::builder(unsigned_tx.network_id)
+            .allow_view_many(view_keys.iter().map(Into::into))
+            .build()
+            .chain(|| (ErrorKind::InvalidInput, "Invalid transaction attributes"))?;
+
+        let return_address = unsigned_tx.return_address.clone();
+
+        let (transaction, selected_inputs, return_amount) =
+            self.transaction_builder.build_transfer_tx(
+                name,
+                enckey,
+                unsigned_tx.unspent_transactions,
+                vec![tx_out],
+                return_address,
+                attributes,
+                FeeTip::default(),
+            )?;
+        let signed_tx = SignedTransferTransaction {
+            signed_transaction: transaction,
+            used_inputs: selected_inputs,
+            return_amount,
+        };
+        Ok(signed_tx)
+    }
+
+    #[inline]
+    fn broadcast_signed_transfer_tx(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        signed_tx: SignedTransferTransaction,
+    ) -> Result<BroadcastResult> {
+        // outputs of the signed transaction are not known here, so neither is the fee
+        self.broadcast(name, enckey, signed_tx, None)
+    }
+
+    fn get_sync_state(&self, name: &str) -> Result<SyncState> {
+        let _guard = self.wallet_locks.shared(name);
+        let mstate = self.sync_state_service.get_global_state(name)?;
+        let sync_state = if let Some(sync_state) = mstate {
+            sync_state
+        } else {
+            get_genesis_sync_state(&self.tendermint_client, true)?
+        };
+        Ok(sync_state)
+    }
+
+    #[inline]
+    fn history_mismatches(&self) -> Result<Vec<String>> {
+        self.chain_tip_service.mismatched_wallets()
+    }
+
+    fn acknowledge_history_mismatch(&self, name: &str, enckey: &SecKey) -> Result<()> {
+        let _guard = self.wallet_locks.exclusive(name);
+        // the enckey is verified here.
+        self.wallet_service.get_wallet(name, enckey)?;
+        let reason = self
+            .chain_tip_service
+            .mismatch(name)?
+            .err_kind(ErrorKind::InvalidInput, || {
+                format!("History of wallet {} has no mismatch", name)
+            })?;
+        log::warn!(
+            "history mismatch of wallet {} acknowledged: {}",
+            name,
+            reason
+        );
+        self.chain_tip_service.acknowledge(name)
+    }
+}
+
+#[cfg(feature = "experimental")]
+impl<S, C, T> MultiSigWalletClient for DefaultWalletClient<S, C, T>
+where
+    S: Storage + 'static,
+    C: Client,
+    T: WalletTransactionBuilder,
+{
+    fn schnorr_signature(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        tx: &Transaction,
+        public_key: &PublicKey,
+    ) -> Result<SchnorrSignature> {
+        let _guard = self.wallet_locks.shared(name);
+        // To verify if the enckey is correct or not
+        self.transfer_addresses(name, enckey)?;
+        let wallet = self.wallet_service.get_wallet_info(name, enckey)?;
+        let sign_key = match wallet.wallet_kind {
+            WalletKind::HW => {
+                let chain_path = self
+                    .wallet_service
+                    .find_chain_path(name, enckey, public_key)?
+                    .chain(|| (ErrorKind::InvalidInput, "can not find chain path"))?;
+                self.hw_key_service.get_sign_key(&chain_path)?
+            }
+            WalletKind::Watch => return Err(watch_only_error(name)),
+            WalletKind::Basic | WalletKind::HD => {
+                let private_key = self
+                    .wallet_service
+                    .find_private_key(name, enckey, public_key)?
+                    .chain(|| (ErrorKind::InvalidInput, ""))?;
+                Box::new(private_key)
+            }
+        };
+        sign_key.schnorr_sign(tx)
+    }
+
+    fn new_multi_sig_session(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        message: H256,
+        signer_public_keys: Vec<PublicKey>,
+        self_public_key: PublicKey,
+    ) -> Result<H256> {
+        let _guard = self.wallet_locks.exclusive(name);
+        // To verify if the enckey is correct or not
+        self.transfer_addresses(name, enckey)?;
+        //        let sign_key = self.sign_key(name, enckey, &self_public_key)?;
+
+        let self_private_key = self
+            .private_key(name, enckey, &self_public_key)?
+            .chain(|| {
+                (
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Self public key ({}) is not owned by current wallet",
+                        self_public_key
+                    ),
+                )
+            })?;
+
+        self.multi_sig_session_service.new_session(
+            message,
+            signer_public_keys,
+            self_public_key,
+            self_private_key,
+            enckey,
+            unix_timestamp()?,
+        )
+    }
+
+    fn nonce_commitment(&self, session_id: &H256, enckey: &SecKey) -> Result<H256> {
+        self.multi_sig_session_service
+            .nonce_commitment(session_id, enckey)
+    }
+
+    fn add_nonce_commitment(
+        &self,
+        session_id: &H256,
+        enckey: &SecKey,
+        nonce_commitment: H256,
+        public_key: &PublicKey,
+    ) -> Result<()> {
+        self.multi_sig_session_service.add_nonce_commitment(
+            session_id,
+            nonce_commitment,
+            public_key,
+            enckey,
+        )
+    }
+
+    fn nonce(&self, session_id: &H256, enckey: &SecKey) -> Result<H256> {
+        self.multi_sig_session_service.nonce(session_id, enckey)
+    }
+
+    fn add_nonce(
+        &self,
+        session_id: &H256,
+        enckey: &SecKey,
+        nonce: &H256,
+        public_key: &PublicKey,
+    ) -> Result<()> {
+        self.multi_sig_session_service
+            .add_nonce(session_id, &nonce, public_key, enckey)
+    }
+
+    fn partial_signature(&self, session_id: &H256, enckey: &SecKey) -> Result<H256> {
+        self.multi_sig_session_service
+            .partial_signature(session_id, enckey)
+    }
+
+    fn add_partial_signature(
+        &self,
+        session_id: &H256,
+        enckey: &SecKey,
+        partial_signature: H256,
+        public_key: &PublicKey,
+    ) -> Result<()> {
+        self.multi_sig_session_service.add_partial_signature(
+            session_id,
+            partial_signature,
+            public_key,
+            enckey,
+        )
+    }
+
+    fn export_nonce_commitment(
+        &self,
+        session_id: &H256,
+        enckey: &SecKey,
+    ) -> Result<NonceCommitmentMsg> {
+        self.multi_sig_session_service
+            .export_nonce_commitment(session_id, enckey)
+    }
+
+    fn import_nonce_commitment(
+        &self,
+        session_id: &H256,
+        enckey: &SecKey,
+        message: &NonceCommitmentMsg,
+    ) -> Result<()> {
+        self.multi_sig_session_service
+            .import_nonce_commitment(session_id, message, enckey)
+    }
+
+    fn export_nonce(&self, session_id: &H256, enckey: &SecKey) -> Result<NonceMsg> {
+        self.multi_sig_session_service
+            .export_nonce(session_id, enckey)
+    }
+
+    fn import_nonce(&self, session_id: &H256, enckey: &SecKey, message: &NonceMsg) -> Result<()> {
+        self.multi_sig_session_service
+            .import_nonce(session_id, message, enckey)
+    }
+
+    fn export_partial_signature(
+        &self,
+        session_id: &H256,
+        enckey: &SecKey,
+    ) -> Result<PartialSignatureMsg> {
+        self.multi_sig_session_service
+            .export_partial_signature(session_id, enckey)
+    }
+
+    fn import_partial_signature(
+        &self,
+        session_id: &H256,
+        enckey: &SecKey,
+        message: &PartialSignatureMsg,
+    ) -> Result<()> {
+        self.multi_sig_session_service
+            .import_partial_signature(session_id, message, enckey)
+    }
+
+    fn signature(&self, session_id: &H256, enckey: &SecKey) -> Result<SchnorrSignature> {
+        self.multi_sig_session_service.signature(session_id, enckey)
+    }
+
+    fn list_sessions(&self, name: &str, enckey: &SecKey) -> Result<Vec<MultiSigSessionInfo>> {
+        let _guard = self.wallet_locks.shared(name);
+        // To verify if the enckey is correct or not
+        self.transfer_addresses(name, enckey)?;
+        self.multi_sig_session_service.list_sessions(enckey)
+    }
+
+    fn expire_sessions(&self, name: &str, enckey: &SecKey, older_than: Duration) -> Result<usize> {
+        let _guard = self.wallet_locks.exclusive(name);
+        // To verify if the enckey is correct or not
+        self.transfer_addresses(name, enckey)?;
+        self.multi_sig_session_service
+            .expire_sessions(enckey, older_than, unix_timestamp()?)
+    }
+
+    fn transaction(
+        &self,
+        name: &str,
+        session_id: &H256,
+        enckey: &SecKey,
+        unsigned_transaction: Tx,
+    ) -> Result<TxAux> {
+        let _guard = self.wallet_locks.shared(name);
+        if unsigned_transaction.inputs.len() != 1 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Multi-Sig Signing is only supported for transactions with only one input",
+            ));
+        }
+
+        let output_to_spend = self.output(name, enckey, &unsigned_transaction.inputs[0])?;
+        let root_hash = self
+            .wallet_service
+            .find_root_hash(name, enckey, &output_to_spend.address)?
+            .chain(|| {
+                (
+                    ErrorKind::IllegalInput,
+                    "Output address is not owned by current wallet; cannot spend output in given transaction",
+                )
+            })?;
+        let public_keys = self
+            .multi_sig_session_service
+            .public_keys(session_id, enckey)?;
+
+        let proof = self
+            .root_hash_service
+            .generate_proof(name, &root_hash, public_keys, enckey)?;
+        let signature = self.signature(session_id, enckey)?;
+
+        let witness = TxWitness::from(vec![TxInWitness::TreeSig(signature, proof)]);
+        let signed_transaction =
+            SignedTransaction::TransferTransaction(unsigned_transaction, witness);
+
+        self.transaction_builder.obfuscate(signed_transaction)
+    }
+
+    fn import_multi_sig_address(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        participants: Vec<PublicKey>,
+        m: usize,
+        n: usize,
+    ) -> Result<ExtendedAddr> {
+        let _guard = self.wallet_locks.exclusive(name);
+        if participants.len() != n {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Expected {} participants of multi-sig address, found {}",
+                    n,
+                    participants.len()
+                ),
+            ));
+        }
+
+        // None of the participants is owned by current wallet, so the first one stands for self
+        // public key (which is only used when current wallet starts a signing session)
+        let self_public_key = participants
+            .first()
+            .cloned()
+            .chain(|| (ErrorKind::InvalidInput, "Participants cannot be empty"))?;
+
+        self.new_multisig_transfer_address(name, enckey, participants, self_public_key, m)
+    }
+
+    fn new_multi_sig_transfer_bundle(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        from_address: &ExtendedAddr,
+        to_address: ExtendedAddr,
+        amount: Coin,
+        mut view_keys: Vec<PublicKey>,
+        network_id: u8,
+        fee_algorithm: LinearFee,
+    ) -> Result<Vec<u8>> {
+        let _guard = self.wallet_locks.shared(name);
+        let root_hash = self
+            .wallet_service
+            .find_root_hash(name, enckey, from_address)?
+            .chain(|| {
+                (
+                    ErrorKind::InvalidInput,
+                    "Multi-sig address is not owned by current wallet",
+                )
+            })?;
+        let multi_sig_address = self
+            .root_hash_service
+            .get_multi_sig_address_from_root_hash(name, &root_hash, enckey)?;
+
+        let mut unspent_transactions = self.spendable_transactions(name, enckey)?;
+        unspent_transactions.retain(|(_, output)| &output.address == from_address);
+
+        view_keys.push(self.view_key(name, enckey)?);
+        let attributes = TxAttributes::builder(network_id)
+            .allow_view_many(view_keys.iter().map(Into::into))
+            .build()
+            .chain(|| (ErrorKind::InvalidInput, "Invalid transaction attributes"))?;
+
+        let raw_tx_builder = select_and_build_raw_transaction(
+            &fee_algorithm,
+            &LargestFirst,
+            &unspent_transactions,
+            vec![TxOut::new(to_address, amount)],
+            from_address.clone(),
+            attributes,
+            multi_sig_address.merkle_tree.len() as u16,
+            FeeTip::default(),
+            default_dust_threshold(&fee_algorithm),
+        )?;
+
+        Ok(raw_tx_builder.to_incomplete())
+    }
+
+    fn add_multi_sig_bundle_signature(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        bundle: Vec<u8>,
+        signer_public_keys: Vec<PublicKey>,
+        signature: SchnorrSignature,
+        fee_algorithm: LinearFee,
+    ) -> Result<Vec<u8>> {
+        let _guard = self.wallet_locks.exclusive(name);
+        let mut raw_tx_builder =
+            RawTransferTransactionBuilder::from_incomplete(bundle, fee_algorithm)?;
+        let input_addresses = raw_tx_builder
+            .iter_inputs()
+            .map(|input| input.prev_tx_out.address.clone())
+            .collect::<Vec<_>>();
+
+        for (index, address) in input_addresses.iter().enumerate() {
+            let root_hash = self
+                .wallet_service
+                .find_root_hash(name, enckey, address)?
+                .chain(|| {
+                    (
+                        ErrorKind::IllegalInput,
+                        "Input address is not owned by current wallet; cannot add signature",
+                    )
+                })?;
+            let proof = self.root_hash_service.generate_proof(
+                name,
+                &root_hash,
+                signer_public_keys.clone(),
+                enckey,
+            )?;
+            raw_tx_builder.add_witness(index, TxInWitness::TreeSig(signature, proof))?;
+        }
+
+        Ok(raw_tx_builder.to_incomplete())
+    }
+
+    fn broadcast_multi_sig_bundle(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        bundle: Vec<u8>,
+        fee_algorithm: LinearFee,
+    ) -> Result<TxId> {
+        let _guard = self.wallet_locks.exclusive(name);
+        self.chain_tip_service.ensure_no_mismatch(name)?;
+        let raw_tx_builder = RawTransferTransactionBuilder::from_incomplete(bundle, fee_algorithm)?;
+        let signed_transaction = raw_tx_builder.to_signed_transaction()?;
+
+        let used_inputs = raw_tx_builder
+            .iter_inputs()
+            .map(|input| input.prev_txo_pointer.clone())
+            .collect::<Vec<_>>();
+        let input_addresses = raw_tx_builder
+            .iter_inputs()
+            .map(|input| &input.prev_tx_out.address)
+            .collect::<BTreeSet<_>>();
+        let return_amount = sum_coins(
+            raw_tx_builder
+                .iter_outputs()
+                .filter(|output| input_addresses.contains(&output.address))
+                .map(|output| output.value),
+        )
+        .chain(|| {
+            (
+                ErrorKind::IllegalInput,
+                "Sum of output values exceeds maximum allowed amount",
+            )
+        })?;
+
+        let tx_aux = self.transaction_builder.obfuscate(signed_transaction)?;
+        let current_block_height = self.get_current_block_height()?;
+
+        self.broadcast_transaction(&tx_aux)?;
+
+        let tx_pending = TransactionPending {
+            used_inputs,
+            block_height: current_block_height,
+            return_amount,
+        };
+        self.update_tx_pending_state(name, enckey, &tx_aux, tx_pending)?;
+
+        Ok(tx_aux.tx_id())
+    }
+}
+
+fn check_passphrase_strength(name: &str, passphrase: &SecUtf8) -> Result<()> {
+    // `estimate_password_strength` returns a score between `0-4`. Any score less than 3 should be considered too
+    // weak.
+    let password_entropy = estimate_password_strength(passphrase.unsecure(), &[name])
+        .chain(|| (ErrorKind::IllegalInput, "Blank passphrase"))?;
+
+    #[cfg(debug_assertions)]
+    let entropy_score = 0;
+    #[cfg(not(debug_assertions))]
+    let entropy_score = 3;
+
+    if password_entropy.score() < entropy_score {
+        return Err(Error::new(
+            ErrorKind::IllegalInput,
+            format!(
+                "Weak passphrase: {}",
+                parse_feedback(password_entropy.feedback().as_ref())
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+fn parse_feedback(feedback: Option<&Feedback>) -> String {
+    match feedback {
+        None => "No feedback available!".to_string(),
+        Some(feedback) => {
+            let mut feedbacks = Vec::new();
+
+            if let Some(warning) = feedback.warning() {
+                feedbacks.push(format!("Warning: {}", warning));
+            }
+
+            for suggestion in feedback.suggestions() {
+                feedbacks.push(format!("Suggestion: {}", suggestion));
+            }
+
+            if feedbacks.is_empty() {
+                feedbacks.push("No feedback available!".to_string());
+            }
+
+            feedbacks.join(" | ")
+        }
+    }
+}
+
+fn import_transaction(
+    wallet: &Wallet,
+    wallet_state: &WalletState,
+    memento: &mut WalletStateMemento,
+    transaction_info: &TransactionInfo,
+    paid_fee: Fee,
+    block_time: Time,
+    spent_flag: Vec<bool>,
+) -> Result<Coin> {
+    let transaction_change = create_transaction_change(
+        wallet,
+        wallet_state,
+        &transaction_info.tx,
+        paid_fee,
+        transaction_info.block_height,
+        block_time,
+    )
+    .chain(|| (ErrorKind::InvalidInput, "create transaction change failed"))?;
+    let mut value = Coin::zero();
+    let transfer_addresses = wallet.get_transfer_addresses()?;
+    for (i, (output, spent)) in transaction_change
+        .outputs
+        .iter()
+        .zip(spent_flag)
+        .enumerate()
+    {
+        // Only add unspent transaction if output address belongs to current wallet
+        if transfer_addresses.contains(&output.address) && !spent {
+            memento.add_unspent_transaction(
+                TxoPointer::new(transaction_change.transaction_id, i),
+                output.clone(),
+            );
+            value = (value + output.value).chain(|| {
+                (
+                    ErrorKind::InvalidInput,
+                    "invalid coin in outputs of transaction",
+                )
+            })?;
+        }
+    }
+    memento.add_transaction_change(transaction_change);
+    Ok(value)
+}
+
+/// Error of the operations which need private keys, requested on a watch-only wallet
+fn watch_only_error(name: &str) -> Error {
+    Error::new(
+        ErrorKind::OnlyWatchOnly,
+        format!("Wallet {} is watch-only, it has no private keys", name),
+    )
+}
+
+/// Only stale transactions can be re-broadcast or expired: a pending one may still be confirmed,
+/// and a confirmed one is in the transaction history
+fn ensure_stale_transaction(
+    transaction: &PendingTransaction,
+    wallet_state: &WalletState,
+    now: Timespec,
+) -> Result<()> {
+    let status = transaction.status(
+        wallet_state
+            .transaction_history
+            .contains_key(&transaction.tx_id),
+        now,
+        DEFAULT_PENDING_TRANSACTION_TIMEOUT,
+    );
+    if status == PendingTransactionStatus::Stale {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Transaction {} is {:?}, only stale transactions can be re-broadcast or expired",
+                hex::encode(&transaction.tx_id),
+                status
+            ),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coin_selection::LargestFirst;
+    use crate::signer::WalletSignerManager;
+    use crate::transaction_builder::DefaultWalletTransactionBuilder;
+    use crate::types::TransactionType;
+    use crate::Mnemonic;
+    use chain_core::init::coin::sum_coins;
+    use chain_core::state::account::{StakedStateOpAttributes, StakedStateOpWitness, UnbondTx};
+    use chain_core::state::tendermint::BlockHeight;
+    use chain_core::state::ChainState;
+    use chain_core::tx::fee::{FeeAlgorithm, LinearFee, Milli};
+    use chain_core::tx::result::{TxResult, TX_RESULT_NOT_FOUND_LOG, TX_RESULT_PATH_PREFIX};
+    use chain_core::tx::{PlainTxAux, TransactionId, TxObfuscated, TxPublicAux};
+    use chain_tx_validation::witness::verify_tx_address;
+    use client_common::cipher::{MockAbciTransactionObfuscation, TransactionObfuscation};
+    use client_common::storage::{CachedStorage, MemoryStorage, SledStorage};
+    use client_common::tendermint::types::*;
+    use client_common::{verify_proof_bundle, PublicKey, SignedTransaction};
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use tendermint::block::CommitSigs;
+    use test_common::block_generator::{BlockGenerator, GeneratorClient};
+
+    #[test]
+    fn check_delete_wallet() {
+        let words = Mnemonic::from_secstr(&SecUtf8::from("pony thank pluck sweet bless tuna couple eight stove fluid essay debate cinnamon elite only")).unwrap();
+        let passphrase = SecUtf8::from("123456");
+        let wrong_passphrase = SecUtf8::from("123457");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        client
+            .restore_wallet("Default", &passphrase, &words)
+            .expect("restore wallet");
+        // FIXME this failure will leave storage in an inconsistant state
+        // assert!(client.restore_wallet("test", &passphrase, &words).is_err());
+        assert!(client.delete_wallet("Default", &wrong_passphrase).is_err());
+        assert!(client.delete_wallet("Default1", &passphrase).is_err());
+        client
+            .delete_wallet("Default", &passphrase)
+            .expect("delete wallet");
+        client
+            .restore_wallet("test", &passphrase, &words)
+            .expect("restore wallet");
+    }
+
+    #[test]
+    fn check_restore_wallet_twice() {
+        let words = Mnemonic::from_secstr(&SecUtf8::from("pony thank pluck sweet bless tuna couple eight stove fluid essay debate cinnamon elite only")).unwrap();
+        let name1 = "Default1";
+        let name2 = "Default2";
+        let passphrase = SecUtf8::from("123456");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let enckey1 = client
+            .restore_wallet(name1, &passphrase, &words)
+            .expect("restore wallet 1 failed");
+        let enckey2 = client
+            .restore_wallet(name2, &passphrase, &words)
+            .expect("restore wallet 2 failed");
+        let transfer_address_1 = client
+            .new_transfer_address(name1, &enckey1)
+            .expect("create transfer address 1 failed");
+        let transfer_address_2 = client
+            .new_transfer_address(name2, &enckey2)
+            .expect("create transfer address 2 failed");
+        assert_eq!(transfer_address_1, transfer_address_2);
+        let staking_address_1 = client
+            .new_staking_address(name1, &enckey1)
+            .expect("create staking address 1 failed");
+        let staking_address_2 = client
+            .new_staking_address(name2, &enckey2)
+            .expect("create staking address 2 failed");
+        assert_eq!(staking_address_1, staking_address_2);
+        let transfer_address_22 = client.new_transfer_address(name2, &enckey2).unwrap();
+        assert_ne!(transfer_address_2, transfer_address_22);
+        let transfer_addresses = client
+            .transfer_addresses(name2, &enckey2, 0, 0, false)
+            .unwrap();
+        assert_eq!(transfer_addresses.len(), 2);
+    }
+
+    #[test]
+    fn check_hd_wallet_in_language() {
+        let passphrase = SecUtf8::from("123456");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let (enckey, mnemonic) = client
+            .new_hd_wallet(
+                "Default1",
+                &passphrase,
+                Some(12),
+                MnemonicLanguage::Japanese,
+            )
+            .unwrap();
+        assert_eq!(MnemonicLanguage::Japanese, mnemonic.language());
+        assert_eq!(12, mnemonic.unsecure_phrase().split_whitespace().count());
+        assert_eq!(
+            WalletKind::HD,
+            client.get_wallet_kind("Default1", &enckey).unwrap()
+        );
+
+        // recovered with the language detected from the words
+        let recovered = Mnemonic::from_secstr(&mnemonic.phrase()).unwrap();
+        let recovered_enckey = client
+            .restore_wallet("Default2", &passphrase, &recovered)
+            .unwrap();
+        assert_eq!(
+            client.view_key("Default1", &enckey).unwrap(),
+            client.view_key("Default2", &recovered_enckey).unwrap()
+        );
+        assert_eq!(
+            client.new_staking_address("Default1", &enckey).unwrap(),
+            client
+                .new_staking_address("Default2", &recovered_enckey)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn check_hd_and_basic_wallets_in_one_storage() {
+        let passphrase = SecUtf8::from("123456");
+        let storage = MemoryStorage::default();
+        let client = DefaultWalletClient::new_read_only(storage.clone());
+        let (hd_enckey, mnemonic) = client
+            .new_wallet(
+                "HD",
+                &passphrase,
+                WalletKind::HD,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        let mnemonic = mnemonic.expect("HD wallet has a mnemonic");
+        let (basic_enckey, basic_mnemonic) = client
+            .new_wallet(
+                "Basic",
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        assert!(basic_mnemonic.is_none());
+
+        // interleaved, so that the wallets would share any misplaced index
+        let mut hd_addresses = Vec::new();
+        let mut basic_addresses = Vec::new();
+        for _ in 0..3 {
+            hd_addresses.push(client.new_transfer_address("HD", &hd_enckey).unwrap());
+            basic_addresses.push(client.new_transfer_address("Basic", &basic_enckey).unwrap());
+        }
+        assert!(hd_addresses
+            .iter()
+            .all(|address| !basic_addresses.contains(address)));
+
+        // the same mnemonic re-derives the same addresses, in a storage with a basic wallet too
+        let restored = DefaultWalletClient::new_read_only(storage);
+        let restored_enckey = restored
+            .restore_wallet("Restored", &passphrase, &mnemonic)
+            .unwrap();
+        for address in hd_addresses.iter() {
+            assert_eq!(
+                *address,
+                restored
+                    .new_transfer_address("Restored", &restored_enckey)
+                    .unwrap()
+            );
+        }
+        assert_eq!(
+            basic_addresses.into_iter().collect::<IndexSet<_>>(),
+            restored
+                .transfer_addresses("Basic", &basic_enckey, 0, 0, false)
+                .unwrap()
+        );
+        assert_eq!(
+            WalletKind::Basic,
+            restored.get_wallet_kind("Basic", &basic_enckey).unwrap()
+        );
+    }
+
+    #[test]
+    fn check_concurrent_address_creation() {
+        let words = Mnemonic::from_secstr(&SecUtf8::from("pony thank pluck sweet bless tuna couple eight stove fluid essay debate cinnamon elite only")).unwrap();
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let enckey = client.restore_wallet(name, &passphrase, &words).unwrap();
+
+        let threads = (0..4)
+            .map(|_| {
+                let client = client.clone();
+                let enckey = enckey.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..5 {
+                        client.new_transfer_address(name, &enckey).unwrap();
+                        client.new_staking_address(name, &enckey).unwrap();
+                        // reads interleaved with the writes of the other threads
+                        client
+                            .transfer_addresses(name, &enckey, 0, 0, false)
+                            .unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        // the same addresses as created one by one: no HD index skipped or used twice
+        let sequential = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let sequential_enckey = sequential
+            .restore_wallet(name, &passphrase, &words)
+            .unwrap();
+        for _ in 0..20 {
+            sequential
+                .new_transfer_address(name, &sequential_enckey)
+                .unwrap();
+            sequential
+                .new_staking_address(name, &sequential_enckey)
+                .unwrap();
+        }
+        let transfer_addresses = client
+            .transfer_addresses(name, &enckey, 0, 0, false)
+            .unwrap();
+        assert_eq!(20, transfer_addresses.len());
+        assert_eq!(
+            sequential
+                .transfer_addresses(name, &sequential_enckey, 0, 0, false)
+                .unwrap(),
+            transfer_addresses
+        );
+        let staking_addresses = client
+            .staking_addresses(name, &enckey, 0, 0, false)
+            .unwrap();
+        assert_eq!(20, staking_addresses.len());
+        assert_eq!(
+            sequential
+                .staking_addresses(name, &sequential_enckey, 0, 0, false)
+                .unwrap(),
+            staking_addresses
+        );
+    }
+
+    #[test]
+    fn check_address_recover() {
+        let words = Mnemonic::from_secstr(&SecUtf8::from("pony thank pluck sweet bless tuna couple eight stove fluid essay debate cinnamon elite only")).unwrap();
+        let name1 = "Default1";
+        let passphrase = SecUtf8::from("123456");
+        let mut client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let enckey1 = client
+            .restore_wallet(name1, &passphrase, &words)
+            .expect("restore wallet 1 failed");
+        let dummy_viewkey = PublicKey::from(
+            &PrivateKey::new().expect("Derive public key from private key should work"),
+        );
+        let mut dummy_wallet = Wallet::new(
+            dummy_viewkey,
+            WalletKind::HD,
+            HardwareKind::LocalOnly,
+            "",
+            None,
+        );
+
+        assert_eq!(
+            client
+                .recover_addresses(
+                    &ExtendedAddr::from_str(
+                        "dcro13jw3znc45e4s9rh0r8cj5j5ghx7snyxlhpvty8ura2qumcs3strqgwehc0"
+                    )
+                    .unwrap(),
+                    &name1,
+                    &enckey1,
+                    &mut dummy_wallet,
+                )
+                .unwrap(),
+            true
+        );
+        assert_eq!(
+            client
+                .recover_addresses(
+                    &ExtendedAddr::from_str(
+                        "dcro13jw3znc45e4s9rh0r8cj5j5ghx7snyxlhpvty8ura2qumcs3strqgwehc0"
+                    )
+                    .unwrap(),
+                    &name1,
+                    &enckey1,
+                    &mut dummy_wallet,
+                )
+                .unwrap(),
+            false
+        );
+    }
+
+    #[test]
+    fn check_restore_basic_wallet() {
+        let private_key =
+            PrivateKey::deserialize_from(&[0x01; 32]).expect("32 bytes, within curve order");
+
+        let name = "Default1";
+        let passphrase = SecUtf8::from("123456");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let seckey = client
+            .restore_basic_wallet(&name, &passphrase, &private_key)
+            .unwrap();
+        assert_eq!(
+            seckey.unsecure().to_vec(),
+            hex::decode("2683346bb890dc5497b050e0c4b93f843ca75503fd5bba41787583351f79931e")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn check_import_private_key() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        // zero and curve order are not valid secret keys
+        let curve_order =
+            hex::decode("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141")
+                .unwrap();
+        for invalid in &[vec![0u8; 32], curve_order, vec![0x01; 31]] {
+            let error = client
+                .import_private_key(
+                    name,
+                    &enckey,
+                    &SecStr::new(invalid.clone()),
+                    AddressType::Transfer,
+                )
+                .expect_err("imported invalid private key");
+            assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        }
+
+        let transfer_key = SecStr::new(vec![0x01; 32]);
+        let staking_key = SecStr::new(vec![0x02; 32]);
+        let transfer_address = client
+            .import_private_key(name, &enckey, &transfer_key, AddressType::Transfer)
+            .unwrap();
+        let staking_address = client
+            .import_private_key(name, &enckey, &staking_key, AddressType::Staking)
+            .unwrap();
+        match transfer_address {
+            ImportedAddress::Transfer(ref address) => assert!(client
+                .transfer_addresses(name, &enckey, 0, 0, false)
+                .unwrap()
+                .contains(address)),
+            _ => panic!("expected transfer address"),
+        }
+        match staking_address {
+            ImportedAddress::Staking(ref address) => assert!(client
+                .staking_addresses(name, &enckey, 0, 0, false)
+                .unwrap()
+                .contains(address)),
+            _ => panic!("expected staking address"),
+        }
+
+        // importing a key again is idempotent, unless it's with another address type
+        assert_eq!(
+            transfer_address,
+            client
+                .import_private_key(name, &enckey, &transfer_key, AddressType::Transfer)
+                .unwrap()
+        );
+        assert_eq!(
+            staking_address,
+            client
+                .import_private_key(name, &enckey, &staking_key, AddressType::Staking)
+                .unwrap()
+        );
+        let error = client
+            .import_private_key(name, &enckey, &transfer_key, AddressType::Staking)
+            .expect_err("imported private key with another address type");
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert_eq!(
+            1,
+            client
+                .transfer_addresses(name, &enckey, 0, 0, false)
+                .unwrap()
+                .iter()
+                .filter(|address| ImportedAddress::Transfer((*address).clone()) == transfer_address)
+                .count()
+        );
+
+        let imported_keys = client.imported_keys(name, &enckey).unwrap();
+        assert_eq!(imported_keys.len(), 2);
+
+        // both imported private keys survive export and import
+        let mut wallet_info = client.export_wallet(name, &enckey).unwrap();
+        assert_eq!(wallet_info.imported_keys.len(), 2);
+        for public_key in imported_keys.iter() {
+            assert!(wallet_info.key_pairs.contains_key(public_key));
+        }
+
+        let client2 = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let enckey2 = client2
+            .import_wallet(name, &passphrase, &mut wallet_info)
+            .unwrap();
+        assert_eq!(
+            client2.imported_keys(name, &enckey2).unwrap(),
+            imported_keys
+        );
+        assert_eq!(
+            client2.public_keys(name, &enckey2).unwrap(),
+            client.public_keys(name, &enckey).unwrap()
+        );
+        assert!(client2
+            .staking_addresses(name, &enckey2, 0, 0, false)
+            .unwrap()
+            .contains(&StakedStateAddress::BasicRedeem(RedeemAddress::from(
+                &PublicKey::from(&PrivateKey::deserialize_from(&[0x02; 32]).unwrap())
+            ))));
+    }
+
+    #[test]
+    fn check_imported_key_is_spendable() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let storage = MemoryStorage::default();
+        let hw_key_service = HwKeyService::default();
+        let client = DefaultWalletClient::new(
+            storage.clone(),
+            UnauthorizedClient,
+            DefaultWalletTransactionBuilder::new(
+                WalletSignerManager::new(storage, hw_key_service.clone()),
+                LinearFee::new(Milli::try_new(1, 1).unwrap(), Milli::try_new(1, 1).unwrap()),
+                MockAbciTransactionObfuscation::new(UnauthorizedClient),
+                LargestFirst,
+            ),
+            None,
+            hw_key_service,
+        );
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        let private_key = PrivateKey::from_hex(
+            "c553a03604235df8fcd14fc6d1e5b18a219fbcc6e93effcfcf768e2977a74ec2",
+        )
+        .unwrap();
+        let imported_address = match client
+            .import_private_key(
+                name,
+                &enckey,
+                &SecStr::new(private_key.serialize()),
+                AddressType::Transfer,
+            )
+            .unwrap()
+        {
+            ImportedAddress::Transfer(address) => address,
+            _ => panic!("expected transfer address"),
+        };
+
+        let mut memento = WalletStateMemento::default();
+        memento.add_unspent_transaction(
+            TxoPointer::new([1; 32], 0),
+            TxOut::new(imported_address.clone(), Coin::new(10000).unwrap()),
+        );
+        client
+            .wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+
+        // the output of the imported address is signed with the imported key
+        let (signed_tx, _) = client
+            .build_transaction(
+                name,
+                &enckey,
+                Coin::new(2500).unwrap(),
+                ExtendedAddr::OrTree([9; 32]),
+                None,
+                &mut BTreeSet::new(),
+                171,
+                false,
+                FeeTip::default(),
+            )
+            .unwrap();
+        match signed_tx.signed_transaction {
+            TxAux::EnclaveTx(TxEnclaveAux::TransferTx { payload, .. }) => {
+                match mock_utils::decrypt(&payload).unwrap() {
+                    PlainTxAux::TransferTx(tx, witness) => {
+                        assert_eq!(vec![TxoPointer::new([1; 32], 0)], tx.inputs);
+                        assert!(verify_tx_address(&witness[0], &tx.id(), &imported_address).is_ok());
+                    }
+                    _ => panic!("not a transfer transaction"),
+                }
+            }
+            _ => panic!("not a transfer transaction"),
+        }
+    }
+
+    #[test]
+    fn check_derivation_paths() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::HD,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        let transfer_addresses = (0..2)
+            .map(|_| client.new_transfer_address(name, &enckey).unwrap())
+            .collect::<Vec<_>>();
+        let staking_address = client.new_staking_address(name, &enckey).unwrap();
+        let imported_address = client
+            .import_private_key(
+                name,
+                &enckey,
+                &SecStr::new(vec![0x01; 32]),
+                AddressType::Transfer,
+            )
+            .unwrap();
+
+        let path =
+            |address: WalletAddress| client.derivation_path(name, &enckey, &address).unwrap();
+        let coin_type = get_bip44_coin_type_from_network(get_network());
+        // the indices start from 1
+        assert_eq!(
+            Some(DerivationPath {
+                coin_type,
+                account: HDAccountType::Transfer.index(),
+                change: 0,
+                index: 2
+            }),
+            path(WalletAddress::Transfer(transfer_addresses[1].clone()))
+        );
+        assert_eq!(
+            Some(DerivationPath {
+                coin_type,
+                account: HDAccountType::Staking.index(),
+                change: 0,
+                index: 1
+            }),
+            path(WalletAddress::Staking(staking_address))
+        );
+        assert_eq!(None, path(imported_address));
+        let foreign = WalletAddress::Transfer(ExtendedAddr::OrTree([1; 32]));
+        assert!(client.derivation_path(name, &enckey, &foreign).is_err());
+
+        assert!(client.verify_derivations(name, &enckey).unwrap().is_empty());
+
+        // the paths are exported as well
+        let wallet_info = client.export_wallet(name, &enckey).unwrap();
+        assert_eq!(3, wallet_info.key_chainpath.len());
+
+        // corrupt the path of the first transfer address
+        let ExtendedAddr::OrTree(root_hash) = transfer_addresses[0];
+        let public_key = client
+            .root_hash_service
+            .public_key(name, &root_hash, &enckey)
+            .unwrap();
+        client
+            .wallet_service
+            .add_key_path(
+                name,
+                &enckey,
+                &public_key,
+                &ChainPath::create_bip44(get_network(), HDAccountType::Transfer.index(), 2),
+            )
+            .unwrap();
+        assert_eq!(
+            vec![public_key],
+            client.verify_derivations(name, &enckey).unwrap()
+        );
+    }
+
+    /// Node which reports `result` from its tx result index
+    #[derive(Clone)]
+    struct TxIndexClient {
+        result: Option<TxResult>,
+    }
+
+    impl Client for TxIndexClient {
+        fn genesis(&self) -> Result<Genesis> {
+            unreachable!()
+        }
+
+        fn status(&self) -> Result<StatusResponse> {
+            unreachable!()
+        }
+
+        fn block(&self, _: u64) -> Result<Block> {
+            unreachable!()
+        }
+
+        fn block_batch<'a, T: Iterator<Item = &'a u64>>(&self, _heights: T) -> Result<Vec<Block>> {
+            unreachable!()
+        }
+
+        fn block_results(&self, _height: u64) -> Result<BlockResultsResponse> {
+            unreachable!()
+        }
+
+        fn block_results_batch<'a, T: Iterator<Item = &'a u64>>(
+            &self,
+            _heights: T,
+        ) -> Result<Vec<BlockResultsResponse>> {
+            unreachable!()
+        }
+
+        fn broadcast_transaction(&self, _: &[u8]) -> Result<BroadcastTxResponse> {
+            unreachable!()
+        }
+
+        fn query(
+            &self,
+            path: &str,
+            _data: &[u8],
+            _height: Option<Height>,
+            _prove: bool,
+        ) -> Result<AbciQuery> {
+            assert!(path.starts_with(TX_RESULT_PATH_PREFIX));
+            match &self.result {
+                Some(result) => Ok(AbciQuery {
+                    value: result.encode(),
+                    ..Default::default()
+                }),
+                None => Err(Error::new(
+                    ErrorKind::TendermintRpcError,
+                    TX_RESULT_NOT_FOUND_LOG,
+                )),
+            }
+        }
+
+        fn query_state_batch<T: Iterator<Item = u64>>(
+            &self,
+            _heights: T,
+        ) -> Result<Vec<ChainState>> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn check_stale_transaction_included_in_block_cant_be_expired() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let tx_id = [1; 32];
+        let tx_result = |code| TxResult {
+            height: BlockHeight::new(10),
+            index: 0,
+            code,
+            fee: Coin::zero(),
+            staking_address: None,
+        };
+
+        for (result, expirable) in vec![
+            (None, true),
+            (Some(tx_result(1)), true),
+            (Some(tx_result(0)), false),
+        ] {
+            let client = DefaultWalletClient::new(
+                MemoryStorage::default(),
+                TxIndexClient { result },
+                UnauthorizedWalletTransactionBuilder,
+                None,
+                HwKeyService::default(),
+            );
+            let (enckey, _) = client
+                .new_wallet(
+                    name,
+                    &passphrase,
+                    WalletKind::Basic,
+                    HardwareKind::LocalOnly,
+                    None,
+                )
+                .unwrap();
+            // broadcast long ago: stale
+            client
+                .pending_transaction_service
+                .add(
+                    name,
+                    &enckey,
+                    tx_id,
+                    Vec::new(),
+                    TransactionPending {
+                        used_inputs: Vec::new(),
+                        block_height: 0,
+                        return_amount: Coin::zero(),
+                    },
+                    0,
+                    None,
+                    |_| false,
+                )
+                .unwrap();
+
+            assert_eq!(
+                expirable,
+                client
+                    .expire_pending_transaction(name, &enckey, &tx_id)
+                    .is_ok()
+            );
+        }
+    }
+
+    /// Node which is unreachable until `reachable` is set
+    #[derive(Default, Clone)]
+    struct FlakyClient {
+        reachable: Arc<AtomicBool>,
+        broadcasted: Arc<AtomicUsize>,
+    }
+
+    impl FlakyClient {
+        fn ensure_reachable(&self) -> Result<()> {
+            if self.reachable.load(Ordering::SeqCst) {
+                Ok(())
+            } else {
+                Err(Error::new(
+                    ErrorKind::ConnectionError,
+                    "Unable to connect to tendermint RPC websocket",
+                ))
+            }
+        }
+    }
+
+    impl Client for FlakyClient {
+        fn genesis(&self) -> Result<Genesis> {
+            unreachable!()
+        }
+
+        fn status(&self) -> Result<StatusResponse> {
+            self.ensure_reachable()?;
+            Ok(client_common::tendermint::mock::status_response())
+        }
+
+        fn block(&self, _: u64) -> Result<Block> {
+            unreachable!()
+        }
+
+        fn block_batch<'a, T: Iterator<Item = &'a u64>>(&self, _heights: T) -> Result<Vec<Block>> {
+            unreachable!()
+        }
+
+        fn block_results(&self, _height: u64) -> Result<BlockResultsResponse> {
+            unreachable!()
+        }
+
+        fn block_results_batch<'a, T: Iterator<Item = &'a u64>>(
+            &self,
+            _heights: T,
+        ) -> Result<Vec<BlockResultsResponse>> {
+            unreachable!()
+        }
+
+        fn broadcast_transaction(&self, _: &[u8]) -> Result<BroadcastTxResponse> {
+            self.ensure_reachable()?;
+            self.broadcasted.fetch_add(1, Ordering::SeqCst);
+            Ok(BroadcastTxResponse {
+                code: tendermint::abci::Code::Ok,
+                data: tendermint::abci::Data::from(vec![]),
+                log: tendermint::abci::Log::from(""),
+                hash: tendermint::abci::transaction::Hash::new([0; 32]),
+            })
+        }
+
+        fn query(
+            &self,
+            _path: &str,
+            _data: &[u8],
+            _height: Option<Height>,
+            _prove: bool,
+        ) -> Result<AbciQuery> {
+            self.ensure_reachable()?;
+            Err(Error::new(
+                ErrorKind::TendermintRpcError,
+                TX_RESULT_NOT_FOUND_LOG,
+            ))
+        }
+
+        fn query_state_batch<T: Iterator<Item = u64>>(
+            &self,
+            _heights: T,
+        ) -> Result<Vec<ChainState>> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn check_transaction_is_kept_when_node_is_unreachable() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let tendermint_client = FlakyClient::default();
+        let client = DefaultWalletClient::new(
+            MemoryStorage::default(),
+            tendermint_client.clone(),
+            UnauthorizedWalletTransactionBuilder,
+            None,
+            HwKeyService::default(),
+        );
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        let input = TxoPointer::new([1; 32], 0);
+        let mut memento = WalletStateMemento::default();
+        memento.add_unspent_transaction(
+            input.clone(),
+            TxOut::new(ExtendedAddr::OrTree([0; 32]), Coin::new(100).unwrap()),
+        );
+        client
+            .wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+
+        let tx_id = [2; 32];
+        let signed_tx = SignedTransferTransaction {
+            signed_transaction: TxAux::EnclaveTx(TxEnclaveAux::TransferTx {
+                inputs: vec![input.clone()],
+                no_of_outputs: 1,
+                payload: TxObfuscated {
+                    key_from: BlockHeight::genesis(),
+                    init_vector: [0; 12],
+                    txpayload: vec![0; 32],
+                    txid: tx_id,
+                },
+            }),
+            return_amount: Coin::zero(),
+            used_inputs: vec![input],
+        };
+
+        // the broadcast fails, but the transaction is tracked and its inputs stay locked
+        assert_eq!(
+            ErrorKind::ConnectionError,
+            client
+                .broadcast(name, &enckey, signed_tx, None)
+                .unwrap_err()
+                .kind()
+        );
+        let wallet_state = client
+            .wallet_service
+            .get_wallet_state(name, &enckey)
+            .unwrap();
+        assert!(wallet_state.pending_transactions.contains_key(&tx_id));
+        let transaction = client
+            .pending_transaction_service
+            .get(name, &enckey, &tx_id)
+            .unwrap();
+        assert_eq!(1, transaction.broadcast_count);
+        assert_eq!(
+            ErrorKind::ConnectionError,
+            client
+                .rebroadcast_pending(name, &enckey)
+                .unwrap_err()
+                .kind()
+        );
+        assert_eq!(0, tendermint_client.broadcasted.load(Ordering::SeqCst));
+
+        // once the node is back, the transaction is re-broadcast
+        tendermint_client.reachable.store(true, Ordering::SeqCst);
+        assert_eq!(
+            vec![tx_id],
+            client.rebroadcast_pending(name, &enckey).unwrap()
+        );
+        assert_eq!(1, tendermint_client.broadcasted.load(Ordering::SeqCst));
+        let transaction = client
+            .pending_transaction_service
+            .get(name, &enckey, &tx_id)
+            .unwrap();
+        assert_eq!(2, transaction.broadcast_count);
+
+        // then the sync stops tracking it once it's confirmed
+        let mut wallet_state = client
+            .wallet_service
+            .get_wallet_state(name, &enckey)
+            .unwrap();
+        wallet_state.transaction_history.insert(
+            tx_id,
+            TransactionChange {
+                transaction_id: tx_id,
+                inputs: Vec::new(),
+                outputs: Vec::new(),
+                fee_paid: Fee::new(Coin::zero()),
+                balance_change: BalanceChange::NoChange,
+                transaction_type: TransactionType::Transfer,
+                block_height: 2,
+                block_time: Time::from_str("2019-04-09T09:38:41.735577Z").unwrap(),
+                memo: None,
+            },
+        );
+        assert!(
+            prune_pending_transactions(&client.storage, name, &enckey, &wallet_state, 2)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(client
+            .pending_transaction_service
+            .get_all(name, &enckey)
+            .unwrap()
+            .is_empty());
+        assert!(client
+            .rebroadcast_pending(name, &enckey)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn check_sweep_skips_time_locked_outputs() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let storage = MemoryStorage::default();
+        let hw_key_service = HwKeyService::default();
+        let tendermint_client = FlakyClient::default();
+        tendermint_client.reachable.store(true, Ordering::SeqCst);
+        let client = DefaultWalletClient::new(
+            storage.clone(),
+            tendermint_client.clone(),
+            DefaultWalletTransactionBuilder::new(
+                WalletSignerManager::new(storage, hw_key_service.clone()),
+                LinearFee::new(Milli::try_new(1, 1).unwrap(), Milli::try_new(1, 1).unwrap()),
+                MockAbciTransactionObfuscation::new(tendermint_client.clone()),
+                LargestFirst,
+            ),
+            None,
+            hw_key_service,
+        );
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        let address = client.new_transfer_address(name, &enckey).unwrap();
+
+        let mut time_locked = TxOut::new(address.clone(), Coin::new(20_000).unwrap());
+        time_locked.valid_from = Some(unix_timestamp().unwrap() + 3600);
+        let mut memento = WalletStateMemento::default();
+        memento.add_unspent_transaction(
+            TxoPointer::new([1; 32], 0),
+            TxOut::new(address, Coin::new(10_000).unwrap()),
+        );
+        memento.add_unspent_transaction(TxoPointer::new([2; 32], 0), time_locked);
+        client
+            .wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+
+        let tx_id = client
+            .sweep(
+                name,
+                &enckey,
+                ExtendedAddr::OrTree([9; 32]),
+                TxAttributes::new(171),
+            )
+            .unwrap();
+        assert_eq!(1, tendermint_client.broadcasted.load(Ordering::SeqCst));
+        let wallet_state = client
+            .wallet_service
+            .get_wallet_state(name, &enckey)
+            .unwrap();
+        assert_eq!(
+            vec![TxoPointer::new([1; 32], 0)],
+            wallet_state.pending_transactions[&tx_id].used_inputs
+        );
+        // the swept amount doesn't come back to the wallet
+        assert_eq!(
+            Coin::zero(),
+            wallet_state.pending_transactions[&tx_id].return_amount
+        );
+
+        // only the time-locked output is left, which can't be swept yet
+        assert_eq!(
+            ErrorKind::InsufficientBalance,
+            client
+                .sweep(
+                    name,
+                    &enckey,
+                    ExtendedAddr::OrTree([9; 32]),
+                    TxAttributes::new(171),
+                )
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn check_watch_wallet() {
+        let name = "Watch";
+        let passphrase = SecUtf8::from("123456");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+
+        // the keys of the offline signer
+        let (signer_enckey, _) = client
+            .new_wallet(
+                "Signer",
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        let staking_address = client
+            .new_staking_address("Signer", &signer_enckey)
+            .unwrap();
+        let transfer_address = client
+            .new_transfer_address("Signer", &signer_enckey)
+            .unwrap();
+        let mut public_keys = client
+            .staking_keys("Signer", &signer_enckey)
+            .unwrap()
+            .into_iter()
+            .collect::<Vec<_>>();
+        public_keys.extend(client.public_keys("Signer", &signer_enckey).unwrap());
+
+        let enckey = client
+            .new_watch_wallet(name, &passphrase, public_keys.clone())
+            .unwrap();
+        assert_eq!(
+            WalletKind::Watch,
+            client.get_wallet_kind(name, &enckey).unwrap()
+        );
+        assert!(client
+            .staking_addresses(name, &enckey, 0, 0, false)
+            .unwrap()
+            .contains(&staking_address));
+        assert!(client
+            .transfer_addresses(name, &enckey, 0, 0, false)
+            .unwrap()
+            .contains(&transfer_address));
+
+        // balance tracking
+        let mut memento = WalletStateMemento::default();
+        memento.add_transaction_change(TransactionChange {
+            transaction_id: [1; 32],
+            inputs: vec![],
+            outputs: vec![TxOut::new(transfer_address.clone(), Coin::one())],
+            fee_paid: Fee::new(Coin::zero()),
+            balance_change: BalanceChange::Incoming { value: Coin::one() },
+            transaction_type: TransactionType::Transfer,
+            block_height: 1,
+            block_time: Time::now(),
+            memo: None,
+        });
+        memento.add_unspent_transaction(
+            TxoPointer::new([1; 32], 0),
+            TxOut::new(transfer_address.clone(), Coin::one()),
+        );
+        client
+            .wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+        assert_eq!(Coin::one(), client.balance(name, &enckey).unwrap().total);
+        assert_eq!(
+            1,
+            client.history(name, &enckey, 0, 10, false).unwrap().len()
+        );
+        assert_eq!(1, client.unspent_transactions(name, &enckey).unwrap().len());
+
+        // no signing
+        let watch_only =
+            |result: Result<()>| result.unwrap_err().kind() == ErrorKind::OnlyWatchOnly;
+        assert!(watch_only(
+            client.sign_key(name, &enckey, &public_keys[0]).map(|_| ())
+        ));
+        assert!(watch_only(
+            client
+                .private_key(name, &enckey, &public_keys[0])
+                .map(|_| ())
+        ));
+        assert!(watch_only(
+            client
+                .create_transaction(
+                    name,
+                    &enckey,
+                    vec![TxOut::new(ExtendedAddr::OrTree([9; 32]), Coin::unit())],
+                    TxAttributes::new(171),
+                    None,
+                    transfer_address,
+                    false,
+                    FeeTip::default(),
+                )
+                .map(|_| ())
+        ));
+        assert!(watch_only(
+            client.new_transfer_address(name, &enckey).map(|_| ())
+        ));
+        assert!(watch_only(
+            client.new_staking_address(name, &enckey).map(|_| ())
+        ));
+        assert!(watch_only(
+            client
+                .import_private_key(
+                    name,
+                    &enckey,
+                    &SecStr::new(vec![0x03; 32]),
+                    AddressType::Transfer,
+                )
+                .map(|_| ())
+        ));
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            client
+                .new_wallet(
+                    "Other",
+                    &passphrase,
+                    WalletKind::Watch,
+                    HardwareKind::LocalOnly,
+                    None
+                )
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn check_history_range() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        let mut memento = WalletStateMemento::default();
+        for (n, block_height) in [(1u8, 30u64), (2, 10), (3, 20)].iter() {
+            memento.add_transaction_change(TransactionChange {
+                transaction_id: [*n; 32],
+                inputs: vec![],
+                outputs: vec![],
+                fee_paid: Fee::new(Coin::zero()),
+                balance_change: BalanceChange::Incoming { value: Coin::one() },
+                transaction_type: TransactionType::Transfer,
+                block_height: *block_height,
+                block_time: Time::now(),
+                memo: None,
+            });
+        }
+        client
+            .wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+
+        let heights = |changes: Vec<TransactionChange>| {
+            changes
+                .into_iter()
+                .map(|change| change.block_height)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![10, 20],
+            heights(client.history_range(name, &enckey, 0, 20).unwrap())
+        );
+        assert_eq!(
+            vec![30, 20],
+            heights(client.history(name, &enckey, 0, 2, true).unwrap())
+        );
+        let page = client
+            .history_paged(
+                name,
+                &enckey,
+                1,
+                1,
+                &HistoryFilter {
+                    from_height: Some(20),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(2, page.total);
+        assert_eq!(vec![20], heights(page.changes));
+        assert!(client.history_range("Default1", &enckey, 0, 20).is_err());
+    }
+
+    #[test]
+    fn check_transaction_memo() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        let mut memento = WalletStateMemento::default();
+        for (n, block_height) in [(1u8, 10u64), (2, 20)].iter() {
+            memento.add_transaction_change(TransactionChange {
+                transaction_id: [*n; 32],
+                inputs: vec![],
+                outputs: vec![],
+                fee_paid: Fee::new(Coin::zero()),
+                balance_change: BalanceChange::Outgoing { value: Coin::one() },
+                transaction_type: TransactionType::Transfer,
+                block_height: *block_height,
+                block_time: Time::now(),
+                memo: None,
+            });
+        }
+        client
+            .wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+
+        assert_eq!(
+            None,
+            client.transaction_memo(name, &enckey, &[1; 32]).unwrap()
+        );
+        client
+            .set_transaction_memo(name, &enckey, [1; 32], Some("payroll march".to_owned()))
+            .unwrap();
+        assert!(client
+            .set_transaction_memo("Default1", &enckey, [1; 32], Some("rent".to_owned()))
+            .is_err());
+        assert_eq!(
+            Some("payroll march".to_owned()),
+            client.transaction_memo(name, &enckey, &[1; 32]).unwrap()
+        );
+
+        let memos = |changes: Vec<TransactionChange>| {
+            changes
+                .into_iter()
+                .map(|change| change.memo)
+                .collect::<Vec<_>>()
+        };
+        let expected = vec![Some("payroll march".to_owned()), None];
+        assert_eq!(
+            expected,
+            memos(client.history(name, &enckey, 0, 10, false).unwrap())
+        );
+        assert_eq!(
+            expected,
+            memos(client.history_range(name, &enckey, 0, 20).unwrap())
+        );
+        assert_eq!(
+            vec![None, Some("payroll march".to_owned())],
+            memos(
+                client
+                    .history_paged(name, &enckey, 0, 10, &HistoryFilter::default())
+                    .unwrap()
+                    .changes
+            )
+        );
+        assert_eq!(
+            Some("payroll march".to_owned()),
+            client
+                .get_transaction_change(name, &enckey, &[1; 32])
+                .unwrap()
+                .unwrap()
+                .memo
+        );
+
+        // full rebuild of the history (e.g. re-sync from genesis)
+        delete_wallet_state(&client.storage, name).unwrap();
+        assert!(client
+            .history(name, &enckey, 0, 10, false)
+            .unwrap()
+            .is_empty());
+        client
+            .wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+        assert_eq!(
+            expected,
+            memos(client.history(name, &enckey, 0, 10, false).unwrap())
+        );
+
+        client
+            .set_transaction_memo(name, &enckey, [1; 32], None)
+            .unwrap();
+        assert_eq!(
+            vec![None, None],
+            memos(client.history(name, &enckey, 0, 10, false).unwrap())
+        );
+    }
+
+    #[test]
+    fn check_address_labels() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let new_passphrase = SecUtf8::from("654321");
+        let storage = MemoryStorage::default();
+        let client = DefaultWalletClient::new_read_only(storage.clone());
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::HD,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        let transfer_address =
+            WalletAddress::Transfer(client.new_transfer_address(name, &enckey).unwrap());
+        let staking_address =
+            WalletAddress::Staking(client.new_staking_address(name, &enckey).unwrap());
+
+        client
+            .set_address_label(name, &enckey, &transfer_address, "savings")
+            .unwrap();
+        assert_eq!(
+            ErrorKind::AlreadyExists,
+            client
+                .set_address_label(name, &enckey, &staking_address, "savings")
+                .unwrap_err()
+                .kind()
+        );
+        client
+            .set_address_label(name, &enckey, &staking_address, "validator")
+            .unwrap();
+
+        // only the addresses of the wallet can be labelled
+        let foreign_address = WalletAddress::Transfer(ExtendedAddr::OrTree([9; 32]));
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            client
+                .set_address_label(name, &enckey, &foreign_address, "foreign")
+                .unwrap_err()
+                .kind()
+        );
+
+        // the labels are persisted, and follow the passphrase of the wallet
+        let client = DefaultWalletClient::new_read_only(storage);
+        assert_eq!(
+            Some(transfer_address.clone()),
+            client.address_by_label(name, &enckey, "savings").unwrap()
+        );
+        let new_enckey = client
+            .change_passphrase(name, &passphrase, &new_passphrase)
+            .unwrap();
+        assert!(client.address_by_label(name, &enckey, "savings").is_err());
+        assert!(client
+            .set_address_label(name, &enckey, &transfer_address, "old")
+            .is_err());
+        assert_eq!(
+            vec![
+                (transfer_address.clone(), Some("savings".to_owned())),
+                (staking_address.clone(), Some("validator".to_owned())),
+            ],
+            client
+                .addresses_detailed(name, &new_enckey)
+                .unwrap()
+                .into_iter()
+                .map(|(address, derivation_path, label)| {
+                    assert!(derivation_path.is_some());
+                    (address, label)
+                })
+                .collect::<Vec<_>>()
+        );
+
+        // an empty label removes it
+        client
+            .set_address_label(name, &new_enckey, &transfer_address, "")
+            .unwrap();
+        assert_eq!(
+            None,
+            client
+                .address_by_label(name, &new_enckey, "savings")
+                .unwrap()
+        );
+        client
+            .set_address_label(name, &new_enckey, &staking_address, "savings")
+            .unwrap();
+        assert_eq!(
+            Some(staking_address),
+            client
+                .address_by_label(name, &new_enckey, "savings")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn check_address_book() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        let alice = ExtendedAddr::OrTree([1; 32]);
+        let bob = ExtendedAddr::OrTree([2; 32]);
+
+        client.add_contact(name, &enckey, "alice", &alice).unwrap();
+        assert_eq!(
+            ErrorKind::AlreadyExists,
+            client
+                .add_contact(name, &enckey, "alice", &bob)
+                .unwrap_err()
+                .kind()
+        );
+        client.add_contact(name, &enckey, "bob", &bob).unwrap();
+        assert_eq!(
+            Some(alice.clone()),
+            client.find_contact(name, &enckey, "alice").unwrap()
+        );
+
+        // the address book can't be accessed with an enckey of another passphrase, even when it's
+        // empty
+        let wrong_enckey = derive_enckey(&SecUtf8::from("wrong passphrase"), name).unwrap();
+        assert!(client.contacts(name, &wrong_enckey).is_err());
+        assert!(client.find_contact(name, &wrong_enckey, "alice").is_err());
+        assert!(client
+            .add_contact(name, &wrong_enckey, "carol", &bob)
+            .is_err());
+        assert!(client.remove_contact(name, &wrong_enckey, "bob").is_err());
+        let (other_enckey, _) = client
+            .new_wallet(
+                "Other",
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        assert!(client.contacts("Other", &other_enckey).unwrap().is_empty());
+        assert!(client.contacts("Other", &enckey).is_err());
+
+        client.remove_contact(name, &enckey, "bob").unwrap();
+        let contacts = client.contacts(name, &enckey).unwrap();
+        assert_eq!(1, contacts.len());
+        assert_eq!(Some(&alice), contacts.get("alice"));
+
+        // the contacts follow the wallet when it's renamed
+        let new_enckey = client.rename_wallet(name, "Renamed", &passphrase).unwrap();
+        assert_eq!(contacts, client.contacts("Renamed", &new_enckey).unwrap());
+    }
+
+    #[test]
+    fn check_archive_old_records() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        let change = |n: u8, block_height: u64, block_time: &str| TransactionChange {
+            transaction_id: [n; 32],
+            inputs: vec![],
+            outputs: vec![],
+            fee_paid: Fee::new(Coin::new(u64::from(n)).unwrap()),
+            balance_change: BalanceChange::Outgoing { value: Coin::one() },
+            transaction_type: TransactionType::Transfer,
+            block_height,
+            block_time: Time::from_str(block_time).unwrap(),
+            memo: None,
+        };
+        let mut memento = WalletStateMemento::default();
+        memento.add_transaction_change(change(1, 10, "2020-01-01T10:00:00Z"));
+        // its output is unspent
+        memento.add_transaction_change(TransactionChange {
+            balance_change: BalanceChange::Incoming { value: Coin::one() },
+            ..change(2, 20, "2020-01-02T10:00:00Z")
+        });
+        memento.add_unspent_transaction(
+            TxoPointer::new([2; 32], 0),
+            TxOut::new(ExtendedAddr::OrTree([0; 32]), Coin::one()),
+        );
+        memento.add_transaction_change(change(3, 30, "2020-01-03T10:00:00Z"));
+        memento.add_transaction_change(change(4, 40, "2020-07-01T10:00:00Z"));
+        client
+            .wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+        for n in [1u8, 3, 4].iter() {
+            client
+                .fee_estimate_service
+                .set(name, &enckey, [*n; 32], Coin::unit())
+                .unwrap();
+        }
+        let balance = client.balance(name, &enckey).unwrap();
+
+        let heights = |changes: Vec<TransactionChange>| {
+            changes
+                .into_iter()
+                .map(|change| change.block_height)
+                .collect::<Vec<_>>()
+        };
+        let date = |s: &str| NaiveDate::from_str(s).unwrap();
+        // 2020-01-02T12:00:00Z
+        let archive = client
+            .archive_old_records(name, &passphrase, 1_577_966_400)
+            .unwrap();
+        assert_eq!(
+            vec![20, 30, 40],
+            heights(client.history_range(name, &enckey, 0, 100).unwrap())
+        );
+        assert_eq!(
+            vec![20, 30, 40],
+            heights(client.history(name, &enckey, 0, 10, false).unwrap())
+        );
+        assert!(client
+            .get_transaction_change(name, &enckey, &[1; 32])
+            .unwrap()
+            .is_none());
+        let estimates = client.fee_estimate_service.get_all(name, &enckey).unwrap();
+        assert!(!estimates.contains_key(&[1; 32]));
+        assert_eq!(balance, client.balance(name, &enckey).unwrap());
+        assert_eq!(
+            Some(10),
+            client
+                .archive_mark(name, &enckey, RecordClass::TransactionHistory)
+                .unwrap()
+                .map(|mark| mark.block_height)
+        );
+
+        // the fee summary is truncated if its range starts before the archived records
+        let summary = client
+            .fee_summary(name, &enckey, date("2020-01-01"), date("2020-01-31"))
+            .unwrap();
+        assert_eq!(Some(date("2020-01-01")), summary.archived_until);
+        assert_eq!(1, summary.tx_count);
+        let summary = client
+            .fee_summary(name, &enckey, date("2020-01-02"), date("2020-01-31"))
+            .unwrap();
+        assert_eq!(None, summary.archived_until);
+
+        let mounted = client.import_archive(name, &passphrase, &archive).unwrap();
+        assert_eq!(vec![10], heights(mounted.history_range(0, 100)));
+        assert!(mounted.get_transaction_change(&[1; 32]).is_some());
+        assert_eq!(Some(&Coin::unit()), mounted.fee_estimates.get(&[1; 32]));
+        assert!(client
+            .import_archive(name, &SecUtf8::from("wrong passphrase"), &archive)
+            .is_err());
+        client
+            .new_wallet(
+                "Other",
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            client
+                .import_archive("Other", &passphrase, &archive)
+                .unwrap_err()
+                .kind()
+        );
+
+        // only the most recent record is retained (besides the ones the balance depends on)
+        client
+            .set_retention_policy(
+                name,
+                &enckey,
+                RecordClass::TransactionHistory,
+                RetentionPolicy {
+                    max_age_days: None,
+                    max_entries: Some(1),
+                },
+            )
+            .unwrap();
+        let archive = client.archive_old_records(name, &passphrase, 0).unwrap();
+        assert_eq!(
+            vec![20, 40],
+            heights(client.history_range(name, &enckey, 0, 100).unwrap())
+        );
+        assert_eq!(balance, client.balance(name, &enckey).unwrap());
+        let mounted = client.import_archive(name, &passphrase, &archive).unwrap();
+        assert_eq!(vec![30], heights(mounted.history_range(0, 100)));
+        // the estimate is archived along with its transaction
+        assert_eq!(
+            vec![[4; 32]],
+            client
+                .fee_estimate_service
+                .get_all(name, &enckey)
+                .unwrap()
+                .keys()
+                .copied()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn check_encoded_backup_restores_into_sled_storage() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let backup_key = SecUtf8::from("backup key");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        client.new_staking_address(name, &enckey).unwrap();
+        let public_key = client.new_public_key(name, &enckey, None).unwrap();
+        let other_key = PublicKey::from(&PrivateKey::new().unwrap());
+        client
+            .new_multisig_transfer_address(
+                name,
+                &enckey,
+                vec![public_key.clone(), other_key],
+                public_key,
+                1,
+            )
+            .unwrap();
+
+        // moved between machines as bytes
+        let blob = client
+            .backup(name, &passphrase, &backup_key)
+            .unwrap()
+            .encode();
+
+        let dir = tempfile::tempdir().unwrap();
+        let sled = DefaultWalletClient::new_read_only(SledStorage::new(dir.path()).unwrap());
+        let decoded = BackupBlob::decode(&mut blob.as_slice()).unwrap();
+        let sled_enckey = sled
+            .restore_backup(&[decoded.clone()], &backup_key, name, &passphrase)
+            .unwrap();
+        assert_eq!(
+            client.view_key(name, &enckey).unwrap(),
+            sled.view_key(name, &sled_enckey).unwrap()
+        );
+        assert_eq!(
+            client.root_hashes(name, &enckey).unwrap(),
+            sled.root_hashes(name, &sled_enckey).unwrap()
+        );
+        let multisig_addresses = |addresses: Vec<MultiSigAddress>| {
+            addresses
+                .into_iter()
+                .map(ExtendedAddr::from)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            multisig_addresses(client.get_multisig_addresses(name, &enckey).unwrap()),
+            multisig_addresses(sled.get_multisig_addresses(name, &sled_enckey).unwrap())
+        );
+        assert_eq!(
+            client.staking_keys(name, &enckey).unwrap(),
+            sled.staking_keys(name, &sled_enckey).unwrap()
+        );
+
+        // an existing wallet isn't overwritten
+        assert!(sled
+            .restore_backup(&[decoded.clone()], &backup_key, name, &passphrase)
+            .is_err());
+        // tampered blobs are rejected
+        let mut tampered = decoded;
+        let last = tampered.ciphertext.len() - 1;
+        tampered.ciphertext[last] ^= 1;
+        assert_eq!(
+            ErrorKind::DecryptionError,
+            sled.restore_backup(&[tampered], &backup_key, "Tampered", &passphrase)
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn check_backup_and_restore() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let backup_key = SecUtf8::from("backup key");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        client.new_staking_address(name, &enckey).unwrap();
+        client.new_transfer_address(name, &enckey).unwrap();
+        let mut sync_state = SyncState::genesis([1; 32]);
+        sync_state.last_block_height = 10;
+        save_sync_state(&client.storage, name, &sync_state).unwrap();
+
+        let full = client.backup(name, &passphrase, &backup_key).unwrap();
+        assert!(client
+            .backup(name, &SecUtf8::from("wrong passphrase"), &backup_key)
+            .is_err());
+
+        // full restore
+        let client2 = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let enckey2 = client2
+            .restore_backup(&[full.clone()], &backup_key, "Restored", &passphrase)
+            .unwrap();
+        assert_eq!(
+            client2.view_key("Restored", &enckey2).unwrap(),
+            client.view_key(name, &enckey).unwrap()
+        );
+        assert_eq!(
+            client2
+                .staking_addresses("Restored", &enckey2, 0, 0, false)
+                .unwrap(),
+            client
+                .staking_addresses(name, &enckey, 0, 0, false)
+                .unwrap()
+        );
+        assert_eq!(
+            client2
+                .transfer_addresses("Restored", &enckey2, 0, 0, false)
+                .unwrap(),
+            client
+                .transfer_addresses(name, &enckey, 0, 0, false)
+                .unwrap()
+        );
+        assert_eq!(
+            client2
+                .get_sync_state("Restored")
+                .unwrap()
+                .last_block_height,
+            10
+        );
+        // the identifier is kept, unless another wallet of the storage has it
+        assert_eq!(
+            client2.wallet_id("Restored").unwrap(),
+            client.wallet_id(name).unwrap()
+        );
+        client
+            .restore_backup(&[full.clone()], &backup_key, "Copy", &passphrase)
+            .unwrap();
+        assert_ne!(
+            client.wallet_id("Copy").unwrap(),
+            client.wallet_id(name).unwrap()
+        );
+
+        // incremental chain restore
+        client.new_staking_address(name, &enckey).unwrap();
+        let incremental = client
+            .backup_incremental(name, &passphrase, &backup_key, &full.snapshot_id)
+            .unwrap();
+        assert_eq!(incremental.parent_snapshot_id, Some(full.snapshot_id));
+
+        let client3 = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let enckey3 = client3
+            .restore_backup(
+                &[full.clone(), incremental.clone()],
+                &backup_key,
+                "Restored",
+                &passphrase,
+            )
+            .unwrap();
+        let staking_addresses = client3
+            .staking_addresses("Restored", &enckey3, 0, 0, false)
+            .unwrap();
+        assert_eq!(staking_addresses.len(), 2);
+        assert_eq!(
+            staking_addresses,
+            client
+                .staking_addresses(name, &enckey, 0, 0, false)
+                .unwrap()
+        );
+
+        // wrong backup key
+        let client4 = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        assert_eq!(
+            client4
+                .restore_backup(
+                    &[full.clone()],
+                    &SecUtf8::from("wrong key"),
+                    "Restored",
+                    &passphrase
+                )
+                .unwrap_err()
+                .kind(),
+            ErrorKind::DecryptionError
+        );
+
+        // broken chain
+        assert_eq!(
+            client4
+                .restore_backup(&[incremental], &backup_key, "Restored", &passphrase)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidInput
+        );
+        let unrelated = client.backup(name, &passphrase, &backup_key).unwrap();
+        let incremental = client
+            .backup_incremental(name, &passphrase, &backup_key, &unrelated.snapshot_id)
+            .unwrap();
+        assert_eq!(
+            client4
+                .restore_backup(&[full, incremental], &backup_key, "Restored", &passphrase)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidInput
+        );
+        assert!(client4.wallets().unwrap().is_empty());
+    }
+
+    #[test]
+    fn check_output_locks() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let storage = MemoryStorage::default();
+        let hw_key_service = HwKeyService::default();
+        let client = DefaultWalletClient::new(
+            storage.clone(),
+            UnauthorizedClient,
+            DefaultWalletTransactionBuilder::new(
+                WalletSignerManager::new(storage, hw_key_service.clone()),
+                LinearFee::new(Milli::try_new(1, 1).unwrap(), Milli::try_new(1, 1).unwrap()),
+                MockAbciTransactionObfuscation::new(UnauthorizedClient),
+                LargestFirst,
+            ),
+            None,
+            hw_key_service,
+        );
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        let transfer_address = client.new_transfer_address(name, &enckey).unwrap();
+        let mut memento = WalletStateMemento::default();
+        for i in 1..=2 {
+            memento.add_unspent_transaction(
+                TxoPointer::new([i; 32], 0),
+                TxOut::new(
+                    transfer_address.clone(),
+                    Coin::new(1000 * u64::from(i)).unwrap(),
+                ),
+            );
+        }
+        client
+            .wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+
+        let create = || {
+            client.create_transaction(
+                name,
+                &enckey,
+                vec![TxOut::new(ExtendedAddr::OrTree([9; 32]), Coin::unit())],
+                TxAttributes::new(171),
+                None,
+                transfer_address.clone(),
+                false,
+                FeeTip::default(),
+            )
+        };
+
+        // the largest output is locked manually, the other one by the first build
+        client
+            .lock_output(name, &enckey, &TxoPointer::new([2; 32], 0), None)
+            .unwrap();
+        let (_, selected_inputs, _) = create().unwrap();
+        assert_eq!(vec![TxoPointer::new([1; 32], 0)], selected_inputs);
+        assert_eq!(ErrorKind::InvalidInput, create().unwrap_err().kind());
+        assert_eq!(
+            ErrorKind::AlreadyExists,
+            client
+                .lock_output(name, &enckey, &TxoPointer::new([1; 32], 0), None)
+                .unwrap_err()
+                .kind()
+        );
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            client
+                .lock_output(name, &enckey, &TxoPointer::new([3; 32], 0), None)
+                .unwrap_err()
+                .kind()
+        );
+
+        client
+            .unlock_output(name, &enckey, &TxoPointer::new([2; 32], 0))
+            .unwrap();
+        let (_, selected_inputs, _) = create().unwrap();
+        assert_eq!(vec![TxoPointer::new([2; 32], 0)], selected_inputs);
+
+        // outputs observed spent by the sync are unlocked
+        let mut memento = WalletStateMemento::default();
+        memento.remove_unspent_transaction(TxoPointer::new([1; 32], 0));
+        unlock_spent_outputs(&client.storage, name, &enckey, &memento).unwrap();
+        assert_eq!(
+            vec![TxoPointer::new([2; 32], 0)],
+            client
+                .output_lock_service
+                .get_locked(name, &enckey, unix_timestamp().unwrap())
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn check_build_transaction() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let storage = MemoryStorage::default();
+        let hw_key_service = HwKeyService::default();
+        let client = DefaultWalletClient::new(
+            storage.clone(),
+            UnauthorizedClient,
+            DefaultWalletTransactionBuilder::new(
+                WalletSignerManager::new(storage, hw_key_service.clone()),
+                LinearFee::new(Milli::try_new(1, 1).unwrap(), Milli::try_new(1, 1).unwrap()),
+                MockAbciTransactionObfuscation::new(UnauthorizedClient),
+                LargestFirst,
+            ),
+            None,
+            hw_key_service,
+        );
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        let transfer_address = client.new_transfer_address(name, &enckey).unwrap();
+        let mut memento = WalletStateMemento::default();
+        memento.add_unspent_transaction(
+            TxoPointer::new([1; 32], 0),
+            TxOut::new(transfer_address.clone(), Coin::new(10000).unwrap()),
+        );
+        client
+            .wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+
+        let amount = Coin::new(2500).unwrap();
+        let (signed_tx, fee) = client
+            .build_transaction(
+                name,
+                &enckey,
+                amount,
+                ExtendedAddr::OrTree([9; 32]),
+                None,
+                &mut BTreeSet::new(),
+                171,
+                false,
+                FeeTip::default(),
+            )
+            .unwrap();
+        assert!(fee > Coin::zero());
+        assert_eq!(
+            Coin::new(10000).unwrap(),
+            ((amount + fee).unwrap() + signed_tx.return_amount).unwrap()
+        );
+
+        // round-trips through its encoding
+        let tx_aux = signed_tx.signed_transaction;
+        let raw_tx = hex::decode(hex::encode(tx_aux.encode())).unwrap();
+        assert_eq!(tx_aux, TxAux::decode(&mut raw_tx.as_slice()).unwrap());
+
+        // signed, but neither broadcast nor recorded as pending
+        match tx_aux {
+            TxAux::EnclaveTx(TxEnclaveAux::TransferTx { payload, .. }) => {
+                match mock_utils::decrypt(&payload).unwrap() {
+                    PlainTxAux::TransferTx(tx, witness) => {
+                        assert_eq!(signed_tx.used_inputs, tx.inputs);
+                        assert_eq!(1, witness.len());
+                        assert!(verify_tx_address(&witness[0], &tx.id(), &transfer_address).is_ok());
+                    }
+                    _ => panic!("not a transfer transaction"),
+                }
+            }
+            _ => panic!("not a transfer transaction"),
+        }
+        assert!(client
+            .pending_transactions(name, &enckey)
+            .unwrap()
+            .is_empty());
+
+        // its inputs are locked
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            client
+                .build_transaction(
+                    name,
+                    &enckey,
+                    amount,
+                    ExtendedAddr::OrTree([9; 32]),
+                    None,
+                    &mut BTreeSet::new(),
+                    171,
+                    false,
+                    FeeTip::default(),
+                )
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn check_time_locked_outputs() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let storage = MemoryStorage::default();
+        let hw_key_service = HwKeyService::default();
+        let tendermint_client = FlakyClient::default();
+        tendermint_client.reachable.store(true, Ordering::SeqCst);
+        let client = DefaultWalletClient::new(
+            storage.clone(),
+            tendermint_client.clone(),
+            DefaultWalletTransactionBuilder::new(
+                WalletSignerManager::new(storage, hw_key_service.clone()),
+                LinearFee::new(Milli::try_new(1, 1).unwrap(), Milli::try_new(1, 1).unwrap()),
+                MockAbciTransactionObfuscation::new(tendermint_client.clone()),
+                LargestFirst,
+            ),
+            None,
+            hw_key_service,
+        );
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        let address = client.new_transfer_address(name, &enckey).unwrap();
+
+        // the time of the latest block of the mock node is the current time
+        let now = unix_timestamp().unwrap();
+        let mut unlocked = TxOut::new(address.clone(), Coin::new(5_000).unwrap());
+        unlocked.valid_from = Some(now - 60);
+        let mut time_locked = TxOut::new(address.clone(), Coin::new(20_000).unwrap());
+        time_locked.valid_from = Some(now + 3600);
+        let mut memento = WalletStateMemento::default();
+        memento.add_unspent_transaction(
+            TxoPointer::new([1; 32], 0),
+            TxOut::new(address, Coin::new(10_000).unwrap()),
+        );
+        memento.add_unspent_transaction(TxoPointer::new([2; 32], 0), unlocked);
+        memento.add_unspent_transaction(TxoPointer::new([3; 32], 0), time_locked);
+        client
+            .wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+
+        let build = |amount: u64, valid_from| {
+            client.build_transaction(
+                name,
+                &enckey,
+                Coin::new(amount).unwrap(),
+                ExtendedAddr::OrTree([9; 32]),
+                valid_from,
+                &mut BTreeSet::new(),
+                171,
+                false,
+                FeeTip::default(),
+            )
+        };
+
+        // the time-locked output can't be spent yet
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            build(25_000, None).unwrap_err().kind()
+        );
+
+        // the output sent is time-locked, not the change
+        let valid_from = now + 7200;
+        let (signed_tx, _) = build(12_000, Some(valid_from)).unwrap();
+        let mut used_inputs = signed_tx.used_inputs.clone();
+        used_inputs.sort();
+        assert_eq!(
+            vec![TxoPointer::new([1; 32], 0), TxoPointer::new([2; 32], 0)],
+            used_inputs
+        );
+        match signed_tx.signed_transaction {
+            TxAux::EnclaveTx(TxEnclaveAux::TransferTx { payload, .. }) => {
+                match mock_utils::decrypt(&payload).unwrap() {
+                    PlainTxAux::TransferTx(tx, _) => {
+                        assert_eq!(2, tx.outputs.len());
+                        assert_eq!(ExtendedAddr::OrTree([9; 32]), tx.outputs[0].address);
+                        assert_eq!(Some(valid_from), tx.outputs[0].valid_from);
+                        assert_eq!(None, tx.outputs[1].valid_from);
+                    }
+                    _ => panic!("not a transfer transaction"),
+                }
+            }
+            _ => panic!("not a transfer transaction"),
+        }
+    }
+
+    #[test]
+    fn check_balance_counts_time_locked_outputs() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let storage = MemoryStorage::default();
+        let hw_key_service = HwKeyService::default();
+        let tendermint_client = FlakyClient::default();
+        tendermint_client.reachable.store(true, Ordering::SeqCst);
+        let client = DefaultWalletClient::new(
+            storage.clone(),
+            tendermint_client.clone(),
+            DefaultWalletTransactionBuilder::new(
+                WalletSignerManager::new(storage, hw_key_service.clone()),
+                LinearFee::new(Milli::try_new(1, 1).unwrap(), Milli::try_new(1, 1).unwrap()),
+                MockAbciTransactionObfuscation::new(tendermint_client),
+                LargestFirst,
+            ),
+            None,
+            hw_key_service,
+        );
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        let transfer_address = client.new_transfer_address(name, &enckey).unwrap();
+        // the time of the latest block of the mock node is the current time
+        let now = unix_timestamp().unwrap();
+
+        let mut memento = WalletStateMemento::default();
+        memento.add_unspent_transaction(
+            TxoPointer::new([1; 32], 0),
+            TxOut::new_with_timelock(transfer_address.clone(), Coin::new(100).unwrap(), now - 60),
+        );
+        memento.add_unspent_transaction(
+            TxoPointer::new([2; 32], 0),
+            TxOut::new_with_timelock(transfer_address, Coin::new(200).unwrap(), now + 3600),
+        );
+        client
+            .wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+
+        let balance = client.balance(name, &enckey).unwrap();
+        assert_eq!(Coin::new(300).unwrap(), balance.total);
+        assert_eq!(Coin::new(100).unwrap(), balance.available);
+        assert_eq!(Coin::new(200).unwrap(), balance.locked);
+    }
+
+    #[derive(Debug, Clone)]
+    struct MockDecryptableTransactions(Vec<Transaction>);
+
+    impl TransactionObfuscation for MockDecryptableTransactions {
+        fn decrypt(
+            &self,
+            transaction_ids: &[TxId],
+            _private_key: &PrivateKey,
+        ) -> Result<Vec<Transaction>> {
+            Ok(self
+                .0
+                .iter()
+                .filter(|transaction| transaction_ids.contains(&transaction.id()))
+                .cloned()
+                .collect())
+        }
+
+        fn encrypt(&self, _transaction: SignedTransaction) -> Result<TxAux> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn check_transaction_details() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let storage = MemoryStorage::default();
+        let hw_key_service = HwKeyService::default();
+        let output = TxOut::new(ExtendedAddr::OrTree([3; 32]), Coin::new(1000).unwrap());
+        let tx = Tx::new_with(
+            vec![TxoPointer::new([7; 32], 0)],
+            vec![output.clone()],
+            TxAttributes::new(171),
+        );
+        let client = DefaultWalletClient::new(
+            storage.clone(),
+            UnauthorizedClient,
+            DefaultWalletTransactionBuilder::new(
+                WalletSignerManager::new(storage, hw_key_service.clone()),
+                LinearFee::new(Milli::try_new(0, 0).unwrap(), Milli::try_new(0, 0).unwrap()),
+                MockDecryptableTransactions(vec![Transaction::TransferTransaction(tx.clone())]),
+                LargestFirst,
+            ),
+            None,
+            hw_key_service,
+        );
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        let spent_output = TxOut::new(ExtendedAddr::OrTree([4; 32]), Coin::new(1100).unwrap());
+        let mut memento = WalletStateMemento::default();
+        memento.add_transaction_change(TransactionChange {
+            transaction_id: tx.id(),
+            inputs: vec![TransactionInput {
+                pointer: TxoPointer::new([7; 32], 0),
+                output: Some(spent_output.clone()),
+            }],
+            outputs: vec![],
+            fee_paid: Fee::new(Coin::new(100).unwrap()),
+            balance_change: BalanceChange::Outgoing {
+                value: Coin::new(1100).unwrap(),
+            },
+            transaction_type: TransactionType::Transfer,
+            block_height: 5,
+            block_time: Time::now(),
+            memo: None,
+        });
+        memento.add_transaction_change(TransactionChange {
+            transaction_id: [5; 32],
+            inputs: vec![],
+            outputs: vec![output.clone()],
+            fee_paid: Fee::new(Coin::zero()),
+            balance_change: BalanceChange::Incoming {
+                value: Coin::new(1000).unwrap(),
+            },
+            transaction_type: TransactionType::Transfer,
+            block_height: 8,
+            block_time: Time::now(),
+            memo: None,
+        });
+        client
+            .wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+        let mut sync_state = SyncState::genesis([1; 32]);
+        sync_state.last_block_height = 10;
+        save_sync_state(&client.storage, name, &sync_state).unwrap();
+
+        // decrypted transaction, enriched with its synced metadata
+        assert_eq!(
+            Some(TransactionDetails {
+                transaction_id: tx.id(),
+                inputs: vec![TransactionInput {
+                    pointer: TxoPointer::new([7; 32], 0),
+                    output: Some(spent_output),
+                }],
+                outputs: vec![output.clone()],
+                fee: Some(Coin::new(100).unwrap()),
+                block_height: Some(5),
+                confirmations: Some(6),
+                decrypted: true,
+            }),
+            client.transaction_details(name, &enckey, &tx.id()).unwrap()
+        );
+
+        // obfuscated transaction: only its metadata is known
+        assert_eq!(
+            Some(TransactionDetails {
+                transaction_id: [5; 32],
+                inputs: vec![],
+                outputs: vec![output],
+                fee: Some(Coin::zero()),
+                block_height: Some(8),
+                confirmations: Some(3),
+                decrypted: false,
+            }),
+            client.transaction_details(name, &enckey, &[5; 32]).unwrap()
+        );
+
+        // unknown transaction
+        assert_eq!(
+            None,
+            client.transaction_details(name, &enckey, &[9; 32]).unwrap()
+        );
+    }
+
+    #[test]
+    fn check_estimate_fee() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let storage = MemoryStorage::default();
+        let hw_key_service = HwKeyService::default();
+        let fee_algorithm =
+            LinearFee::new(Milli::try_new(1, 1).unwrap(), Milli::try_new(1, 1).unwrap());
+        let client = DefaultWalletClient::new(
+            storage.clone(),
+            UnauthorizedClient,
+            DefaultWalletTransactionBuilder::new(
+                WalletSignerManager::new(storage, hw_key_service.clone()),
+                fee_algorithm,
+                MockAbciTransactionObfuscation::new(UnauthorizedClient),
+                LargestFirst,
+            ),
+            None,
+            hw_key_service,
+        );
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        let transfer_address = client.new_transfer_address(name, &enckey).unwrap();
+        let mut memento = WalletStateMemento::default();
+        for i in 1..=3 {
+            memento.add_unspent_transaction(
+                TxoPointer::new([i; 32], 0),
+                TxOut::new(
+                    transfer_address.clone(),
+                    Coin::new(1000 * u64::from(i)).unwrap(),
+                ),
+            );
+        }
+        client
+            .wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+
+        let amount = Coin::new(2500).unwrap();
+        let outputs = vec![TxOut::new(ExtendedAddr::OrTree([9; 32]), amount)];
+        let attributes = TxAttributes::new(171);
+        let estimated_fee = client
+            .estimate_fee(name, &enckey, outputs.clone(), attributes.clone())
+            .unwrap();
+        assert!(estimated_fee > Coin::zero());
+
+        // the fee of the transaction built afterwards
+        let return_address = client.new_transfer_address(name, &enckey).unwrap();
+        let (tx_aux, selected_inputs, return_amount) = client
+            .create_transaction(
+                name,
+                &enckey,
+                outputs,
+                attributes,
+                None,
+                return_address,
+                false,
+                FeeTip::default(),
+            )
+            .unwrap();
+        let input_amount = sum_coins(
+            selected_inputs
+                .iter()
+                .map(|input| client.output(name, &enckey, input).unwrap().value),
+        )
+        .unwrap();
+        let fee = ((input_amount - amount) - return_amount).unwrap();
+        let difference = if fee > estimated_fee {
+            fee - estimated_fee
+        } else {
+            estimated_fee - fee
+        };
+        assert!(difference.unwrap() <= Coin::unit());
+        assert!(
+            fee_algorithm
+                .calculate_for_txaux(&tx_aux)
+                .unwrap()
+                .to_coin()
+                <= fee
+        );
+
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            client
+                .estimate_fee(
+                    name,
+                    &enckey,
+                    vec![TxOut::new(
+                        ExtendedAddr::OrTree([9; 32]),
+                        Coin::new(6000).unwrap()
+                    )],
+                    TxAttributes::new(171),
+                )
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn check_compliance_policy_rejects_self_transfers() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        let transfer_address = client.new_transfer_address(name, &enckey).unwrap();
+        let public_key = client.new_public_key(name, &enckey, None).unwrap();
+        let other_key = PublicKey::from(&PrivateKey::new().unwrap());
+        let multi_sig_address = client
+            .new_multisig_transfer_address(
+                name,
+                &enckey,
+                vec![public_key.clone(), other_key],
+                public_key,
+                1,
+            )
+            .unwrap();
+        let external_address = ExtendedAddr::OrTree([9; 32]);
+
+        let create = |addresses: &[&ExtendedAddr], allow_self_transfer| {
+            let outputs = addresses
+                .iter()
+                .map(|address| TxOut::new((*address).clone(), Coin::unit()))
+                .collect();
+            // the read-only client can't sign, so building always fails
+            client
+                .create_transaction(
+                    name,
+                    &enckey,
+                    outputs,
+                    TxAttributes::new(171),
+                    None,
+                    transfer_address.clone(),
+                    allow_self_transfer,
+                    FeeTip::default(),
+                )
+                .unwrap_err()
+                .kind()
+        };
+        let own_addresses = [&transfer_address, &multi_sig_address];
+        assert_ne!(ErrorKind::SelfTransfer, create(&own_addresses, false));
+
+        client
+            .set_compliance_policy(
+                name,
+                &enckey,
+                CompliancePolicy {
+                    enabled: true,
+                    min_output_value: Coin::unit(),
+                },
+            )
+            .unwrap();
+        assert!(client.compliance_policy(name, &enckey).unwrap().enabled);
+        assert_eq!(ErrorKind::SelfTransfer, create(&own_addresses, false));
+        assert_eq!(
+            ErrorKind::SelfTransfer,
+            create(&[&multi_sig_address], false)
+        );
+        assert_ne!(ErrorKind::SelfTransfer, create(&own_addresses, true));
+        assert_ne!(
+            ErrorKind::SelfTransfer,
+            create(&[&multi_sig_address, &external_address], false)
+        );
+    }
+
+    #[test]
+    fn check_rename_wallet() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        let staking_address = client.new_staking_address(name, &enckey).unwrap();
+        let mut memento = WalletStateMemento::default();
+        memento.add_transaction_change(TransactionChange {
+            transaction_id: [1; 32],
+            inputs: vec![],
+            outputs: vec![],
+            fee_paid: Fee::new(Coin::zero()),
+            balance_change: BalanceChange::Incoming { value: Coin::one() },
+            transaction_type: TransactionType::Transfer,
+            block_height: 10,
+            block_time: Time::now(),
+            memo: None,
+        });
+        client
+            .wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+        client
+            .fee_estimate_service
+            .set(name, &enckey, [1; 32], Coin::one())
+            .unwrap();
+        let wallet_id = client.wallet_id(name).unwrap();
+
+        assert!(client
+            .rename_wallet(name, "Renamed", &SecUtf8::from("wrong passphrase"))
+            .is_err());
+        let new_enckey = client.rename_wallet(name, "Renamed", &passphrase).unwrap();
+
+        assert_eq!(vec!["Renamed".to_owned()], client.wallets().unwrap());
+        assert_eq!(wallet_id, client.wallet_id("Renamed").unwrap());
+        assert_eq!(
+            Some("Renamed".to_owned()),
+            client.wallet_by_id(&wallet_id).unwrap()
+        );
+        assert!(client.wallet_id(name).is_err());
+        assert!(client
+            .staking_addresses("Renamed", &new_enckey, 0, 0, false)
+            .unwrap()
+            .contains(&staking_address));
+        let history = client.history_range("Renamed", &new_enckey, 0, 20).unwrap();
+        assert_eq!(1, history.len());
+        assert_eq!([1; 32], history[0].transaction_id);
+        assert_eq!(
+            Some(&Coin::one()),
+            client
+                .fee_estimate_service
+                .get_all("Renamed", &new_enckey)
+                .unwrap()
+                .get(&[1; 32])
+        );
+
+        // the name of another wallet can't be taken
+        client
+            .new_wallet(
+                "Other",
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        assert_eq!(
+            ErrorKind::PermissionDenied,
+            client
+                .rename_wallet("Renamed", "Other", &passphrase)
+                .unwrap_err()
+                .kind()
+        );
+        assert_eq!(wallet_id, client.wallet_id("Renamed").unwrap());
+    }
+
+    #[test]
+    fn check_lock_wallet_drops_cached_records() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let storage = CachedStorage::new(MemoryStorage::default());
+        let client = DefaultWalletClient::new_read_only(storage.clone());
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        client.new_transfer_address(name, &enckey).unwrap();
+        client
+            .transfer_addresses(name, &enckey, 0, 0, false)
+            .unwrap();
+        assert_ne!(0, storage.cache().stats().entries);
+
+        let wrong_enckey = derive_enckey(&SecUtf8::from("wrong passphrase"), name).unwrap();
+        assert!(client.lock_wallet(name, &wrong_enckey).is_err());
+        assert_ne!(0, storage.cache().stats().entries);
+
+        client.lock_wallet(name, &enckey).unwrap();
+        assert_eq!(0, storage.cache().stats().entries);
+
+        // the records decrypted with the old enckey are dropped along the passphrase change
+        client
+            .transfer_addresses(name, &enckey, 0, 0, false)
+            .unwrap();
+        let new_enckey = client
+            .change_passphrase(name, &passphrase, &SecUtf8::from("654321"))
+            .unwrap();
+        assert_eq!(0, storage.cache().stats().entries);
+        assert_eq!(
+            1,
+            client
+                .transfer_addresses(name, &new_enckey, 0, 0, false)
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    fn check_change_passphrase() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let new_passphrase = SecUtf8::from("654321");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::HD,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        let staking_address = client.new_staking_address(name, &enckey).unwrap();
+        let transfer_address = client.new_transfer_address(name, &enckey).unwrap();
+        let mut memento = WalletStateMemento::default();
+        memento.add_transaction_change(TransactionChange {
+            transaction_id: [1; 32],
+            inputs: vec![],
+            outputs: vec![],
+            fee_paid: Fee::new(Coin::zero()),
+            balance_change: BalanceChange::Incoming { value: Coin::one() },
+            transaction_type: TransactionType::Transfer,
+            block_height: 10,
+            block_time: Time::now(),
+            memo: None,
+        });
+        client
+            .wallet_state_service
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+        client
+            .fee_estimate_service
+            .set(name, &enckey, [1; 32], Coin::one())
+            .unwrap();
+        let (other_enckey, _) = client
+            .new_wallet(
+                "Other",
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        let other_view_key = client.view_key("Other", &other_enckey).unwrap();
+
+        // nothing is changed with an incorrect passphrase
+        assert!(client
+            .change_passphrase(name, &SecUtf8::from("wrong passphrase"), &new_passphrase)
+            .is_err());
+        assert_eq!(enckey, client.auth_token(name, &passphrase).unwrap());
+
+        let new_enckey = client
+            .change_passphrase(name, &passphrase, &new_passphrase)
+            .unwrap();
+        assert!(client.auth_token(name, &passphrase).is_err());
+        assert!(client.view_key(name, &enckey).is_err());
+        assert_eq!(
+            new_enckey,
+            client.auth_token(name, &new_passphrase).unwrap()
+        );
+
+        let wallet_info = client.export_wallet(name, &new_enckey).unwrap();
+        assert!(wallet_info.hdkey.is_some());
+        assert!(!wallet_info.key_pairs.is_empty());
+        assert!(client
+            .staking_addresses(name, &new_enckey, 0, 0, false)
+            .unwrap()
+            .contains(&staking_address));
+        assert!(client
+            .transfer_addresses(name, &new_enckey, 0, 0, false)
+            .unwrap()
+            .contains(&transfer_address));
+        assert_eq!(
+            1,
+            client
+                .get_multisig_addresses(name, &new_enckey)
+                .unwrap()
+                .len()
+        );
+        let history = client.history_range(name, &new_enckey, 0, 20).unwrap();
+        assert_eq!(1, history.len());
+        assert_eq!([1; 32], history[0].transaction_id);
+        assert_eq!(
+            Some(&Coin::one()),
+            client
+                .fee_estimate_service
+                .get_all(name, &new_enckey)
+                .unwrap()
+                .get(&[1; 32])
+        );
+
+        // the other wallet is untouched
+        assert_eq!(
+            other_enckey,
+            client.auth_token("Other", &passphrase).unwrap()
+        );
+        assert_eq!(
+            other_view_key,
+            client.view_key("Other", &other_enckey).unwrap()
+        );
+    }
+
+    #[test]
+    fn check_records_are_upgraded_to_current_kdf_params() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::HD,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        let transfer_address = client.new_transfer_address(name, &enckey).unwrap();
+        client
+            .fee_estimate_service
+            .set(name, &enckey, [1; 32], Coin::one())
+            .unwrap();
+        assert_eq!(
+            KdfParams::CURRENT,
+            client.key_service.kdf_params(name).unwrap()
+        );
+
+        // simulates a wallet written under older parameters
+        let old_params = KdfParams {
+            version: 0,
+            mem_cost: 2048,
+            time_cost: 2,
+            lanes: 1,
+        };
+        let mut batch = StorageBatch::default();
+        let (_, old_enckey) = client
+            .key_service
+            .batch_reencrypt_all(&mut batch, name, &passphrase, &old_params)
+            .unwrap();
+        client
+            .batch_change_enckey(&mut batch, name, &enckey, &old_enckey)
+            .unwrap();
+        client.storage.write_batch(batch).unwrap();
+        assert!(client.view_key(name, &enckey).is_err());
+
+        // nothing is changed with an incorrect passphrase
+        assert!(client
+            .auth_token(name, &SecUtf8::from("wrong passphrase"))
+            .is_err());
+        assert_eq!(old_params, client.key_service.kdf_params(name).unwrap());
+        assert!(client.view_key(name, &old_enckey).is_ok());
+
+        // the records are upgraded when the wallet is opened
+        assert_eq!(enckey, client.auth_token(name, &passphrase).unwrap());
+        assert_eq!(
+            KdfParams::CURRENT,
+            client.key_service.kdf_params(name).unwrap()
+        );
+        assert!(client.view_key(name, &old_enckey).is_err());
+        assert!(client.view_key_private(name, &enckey).is_ok());
+        assert!(client
+            .transfer_addresses(name, &enckey, 0, 0, false)
+            .unwrap()
+            .contains(&transfer_address));
+        assert_eq!(
+            Some(&Coin::one()),
+            client
+                .fee_estimate_service
+                .get_all(name, &enckey)
+                .unwrap()
+                .get(&[1; 32])
+        );
+        assert_eq!(enckey, client.auth_token(name, &passphrase).unwrap());
+    }
+
+    #[test]
+    fn check_staking_and_transfer_keys_are_distinct() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::HD,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        client.new_staking_address(name, &enckey).unwrap();
+        client.new_transfer_address(name, &enckey).unwrap();
+        client.new_addresses(name, &enckey, 2).unwrap();
+        let staking_keys = client.staking_keys(name, &enckey).unwrap();
+        let transfer_keys = client.public_keys(name, &enckey).unwrap();
+        assert_eq!(1, staking_keys.len());
+        assert_eq!(3, transfer_keys.len());
+        assert!(staking_keys.iter().all(|key| !transfer_keys.contains(key)));
+
+        let staking_key = &staking_keys[0];
+        let transfer_key = &transfer_keys[0];
+        assert_eq!(
+            Some(AddressType::Staking),
+            client.wallet_service.key_kind(name, staking_key).unwrap()
+        );
+        assert!(transfer_keys.iter().all(|key| {
+            client.wallet_service.key_kind(name, key).unwrap() == Some(AddressType::Transfer)
+        }));
+
+        // a key can't be reused for an address of the other kind
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            client
+                .new_watch_staking_address(name, &enckey, transfer_key)
+                .unwrap_err()
+                .kind()
+        );
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            client
+                .new_watch_transfer_address(name, &enckey, staking_key)
+                .unwrap_err()
+                .kind()
+        );
+        assert_eq!(staking_keys, client.staking_keys(name, &enckey).unwrap());
+        assert_eq!(
+            3,
+            client
+                .transfer_addresses(name, &enckey, 0, 0, false)
+                .unwrap()
+                .len()
+        );
+
+        // the keys of a watch-only wallet (not tagged) are used for both kinds
+        let public_key = PublicKey::from(&PrivateKey::new().unwrap());
+        let watch_enckey = client
+            .new_watch_wallet("Watch", &passphrase, vec![public_key.clone()])
+            .unwrap();
+        assert_eq!(
+            None,
+            client
+                .wallet_service
+                .key_kind("Watch", &public_key)
+                .unwrap()
+        );
+        assert!(client
+            .staking_keys("Watch", &watch_enckey)
+            .unwrap()
+            .contains(&public_key));
+        assert!(client
+            .public_keys("Watch", &watch_enckey)
+            .unwrap()
+            .contains(&public_key));
     }
 
     #[test]
-    fn check_restore_wallet_twice() {
-        let words = Mnemonic::from_secstr(&SecUtf8::from("pony thank pluck sweet bless tuna couple eight stove fluid essay debate cinnamon elite only")).unwrap();
-        let name1 = "Default1";
-        let name2 = "Default2";
+    fn check_recreated_wallet_gets_new_id() {
+        let name = "Default";
         let passphrase = SecUtf8::from("123456");
         let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
-        let enckey1 = client
-            .restore_wallet(name1, &passphrase, &words)
-            .expect("restore wallet 1 failed");
-        let enckey2 = client
-            .restore_wallet(name2, &passphrase, &words)
-            .expect("restore wallet 2 failed");
-        let transfer_address_1 = client
-            .new_transfer_address(name1, &enckey1)
-            .expect("create transfer address 1 failed");
-        let transfer_address_2 = client
-            .new_transfer_address(name2, &enckey2)
-            .expect("create transfer address 2 failed");
-        assert_eq!(transfer_address_1, transfer_address_2);
-        let staking_address_1 = client
-            .new_staking_address(name1, &enckey1)
-            .expect("create staking address 1 failed");
-        let staking_address_2 = client
-            .new_staking_address(name2, &enckey2)
-            .expect("create staking address 2 failed");
-        assert_eq!(staking_address_1, staking_address_2);
-        let transfer_address_22 = client.new_transfer_address(name2, &enckey2).unwrap();
-        assert_ne!(transfer_address_2, transfer_address_22);
+        let create = || {
+            client
+                .new_wallet(
+                    name,
+                    &passphrase,
+                    WalletKind::Basic,
+                    HardwareKind::LocalOnly,
+                    None,
+                )
+                .unwrap();
+            client.wallet_id(name).unwrap()
+        };
+
+        let wallet_id = create();
+        client.delete_wallet(name, &passphrase).unwrap();
+        assert_eq!(None, client.wallet_by_id(&wallet_id).unwrap());
+
+        let new_wallet_id = create();
+        assert_ne!(wallet_id, new_wallet_id);
+        assert_eq!(
+            Some(name.to_owned()),
+            client.wallet_by_id(&new_wallet_id).unwrap()
+        );
+    }
+
+    #[test]
+    fn check_new_addresses() {
+        let name = "Default";
+        let passphrase = SecUtf8::from("123456");
+        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        let first_address = client.new_transfer_address(name, &enckey).unwrap();
+
+        let addresses = client.new_addresses(name, &enckey, 1000).unwrap();
+        assert_eq!(1000, addresses.len());
+        let distinct = addresses.iter().collect::<BTreeSet<_>>();
+        assert_eq!(1000, distinct.len());
+        assert!(!distinct.contains(&first_address));
+
         let transfer_addresses = client
-            .transfer_addresses(name2, &enckey2, 0, 0, false)
+            .transfer_addresses(name, &enckey, 0, 2000, false)
             .unwrap();
-        assert_eq!(transfer_addresses.len(), 2);
+        assert_eq!(1001, transfer_addresses.len());
+        assert_eq!(Some(&first_address), transfer_addresses.get_index(0));
+        assert!(addresses
+            .iter()
+            .all(|address| transfer_addresses.contains(address)));
+        assert_eq!(1001, client.public_keys(name, &enckey).unwrap().len());
+        for address in &addresses {
+            let root_hash = client
+                .wallet_service
+                .find_root_hash(name, &enckey, address)
+                .unwrap()
+                .unwrap();
+            let public_key = client
+                .root_hash_service
+                .public_key(name, &root_hash, &enckey)
+                .unwrap();
+            assert!(client
+                .wallet_service
+                .find_private_key(name, &enckey, &public_key)
+                .unwrap()
+                .is_some());
+        }
+
+        // the following addresses are added after them
+        let next_address = client.new_transfer_address(name, &enckey).unwrap();
+        assert_eq!(
+            Some(&next_address),
+            client
+                .transfer_addresses(name, &enckey, 0, 2000, false)
+                .unwrap()
+                .get_index(1001)
+        );
     }
 
     #[test]
-    fn check_address_recover() {
-        let words = Mnemonic::from_secstr(&SecUtf8::from("pony thank pluck sweet bless tuna couple eight stove fluid essay debate cinnamon elite only")).unwrap();
-        let name1 = "Default1";
+    fn check_new_addresses_failure_leaves_wallet_unchanged() {
+        let name = "Default";
         let passphrase = SecUtf8::from("123456");
-        let mut client = DefaultWalletClient::new_read_only(MemoryStorage::default());
-        let enckey1 = client
-            .restore_wallet(name1, &passphrase, &words)
-            .expect("restore wallet 1 failed");
-        let dummy_viewkey = PublicKey::from(
-            &PrivateKey::new().expect("Derive public key from private key should work"),
+        let storage = MemoryStorage::default();
+        let client = DefaultWalletClient::new_read_only(storage.clone());
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::HD,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        client.new_transfer_address(name, &enckey).unwrap();
+
+        // only 2 transfer addresses left to derive
+        let mut hd_key = client
+            .hd_key_service
+            .get_hdkey(name, &enckey)
+            .unwrap()
+            .unwrap();
+        hd_key.transfer_index = 0x7fff_fffd;
+        client
+            .hd_key_service
+            .add_hdkey(name, &enckey, hd_key)
+            .unwrap();
+
+        let dump = || {
+            storage
+                .keyspaces()
+                .unwrap()
+                .into_iter()
+                .map(|keyspace| {
+                    let values = storage
+                        .keys(&keyspace)
+                        .unwrap()
+                        .into_iter()
+                        .map(|key| {
+                            let value = storage.get(&keyspace, &key).unwrap();
+                            (key, value)
+                        })
+                        .collect::<Vec<_>>();
+                    (keyspace, values)
+                })
+                .collect::<BTreeMap<_, _>>()
+        };
+        let before = dump();
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            client.new_addresses(name, &enckey, 3).unwrap_err().kind()
         );
-        let mut dummy_wallet = Wallet::new(
-            dummy_viewkey,
-            WalletKind::HD,
-            HardwareKind::LocalOnly,
-            "",
-            None,
+        assert!(before == dump());
+        assert_eq!(
+            1,
+            client
+                .transfer_addresses(name, &enckey, 0, 100, false)
+                .unwrap()
+                .len()
         );
 
+        assert_eq!(2, client.new_addresses(name, &enckey, 2).unwrap().len());
         assert_eq!(
+            3,
             client
-                .recover_addresses(
-                    &ExtendedAddr::from_str(
-                        "dcro13jw3znc45e4s9rh0r8cj5j5ghx7snyxlhpvty8ura2qumcs3strqgwehc0"
-                    )
-                    .unwrap(),
-                    &name1,
-                    &enckey1,
-                    &mut dummy_wallet,
-                )
-                .unwrap(),
-            true
+                .transfer_addresses(name, &enckey, 0, 100, false)
+                .unwrap()
+                .len()
         );
+    }
+
+    #[test]
+    fn check_delete_wallet_wipes_records() {
+        let name = "Wiped";
+        let passphrase = SecUtf8::from("123456");
+        let storage = MemoryStorage::default();
+        let client = DefaultWalletClient::new_read_only(storage.clone());
+        let create = || {
+            client
+                .new_wallet(
+                    name,
+                    &passphrase,
+                    WalletKind::Basic,
+                    HardwareKind::LocalOnly,
+                    None,
+                )
+                .unwrap()
+                .0
+        };
+
+        let enckey = create();
+        client.new_transfer_address(name, &enckey).unwrap();
+        client.new_staking_address(name, &enckey).unwrap();
+        save_sync_state(&storage, name, &SyncState::genesis([0; 32])).unwrap();
+
+        // nothing is deleted with a wrong passphrase
+        assert!(client
+            .delete_wallet(name, &SecUtf8::from("wrong passphrase"))
+            .is_err());
+        assert_eq!(vec![name.to_owned()], client.wallets().unwrap());
         assert_eq!(
+            1,
             client
-                .recover_addresses(
-                    &ExtendedAddr::from_str(
-                        "dcro13jw3znc45e4s9rh0r8cj5j5ghx7snyxlhpvty8ura2qumcs3strqgwehc0"
+                .transfer_addresses(name, &enckey, 0, 100, false)
+                .unwrap()
+                .len()
+        );
+
+        client.delete_wallet(name, &passphrase).unwrap();
+        assert!(client.wallets().unwrap().is_empty());
+        for keyspace in storage.keyspaces().unwrap() {
+            let keys = storage.keys(&keyspace).unwrap();
+            if String::from_utf8_lossy(&keyspace).contains(name) {
+                assert!(keys.is_empty());
+            }
+            assert!(keys.iter().all(|key| key.as_slice() != name.as_bytes()));
+        }
+        assert!(load_sync_state(&storage, name).unwrap().is_none());
+
+        // a wallet re-created with the same name starts from scratch
+        let enckey = create();
+        assert!(client
+            .transfer_addresses(name, &enckey, 0, 100, false)
+            .unwrap()
+            .is_empty());
+        assert!(load_sync_state(&storage, name).unwrap().is_none());
+    }
+
+    #[cfg(feature = "experimental")]
+    mod multi_sig_bundle {
+        use super::*;
+        use crate::signer::WalletSignerManager;
+        use crate::transaction_builder::DefaultWalletTransactionBuilder;
+        use chain_core::state::ChainState;
+        use chain_core::tx::fee::{LinearFee, Milli};
+        use chain_tx_validation::witness::verify_tx_address;
+        use client_common::cipher::MockAbciTransactionObfuscation;
+        use client_common::tendermint::mock;
+        use client_common::tendermint::types::*;
+        use client_common::MultiSigAddress;
+        use serde::{de::DeserializeOwned, Serialize};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(Default, Clone)]
+        struct MockClient {
+            broadcasted: Arc<AtomicUsize>,
+        }
+
+        impl Client for MockClient {
+            fn genesis(&self) -> Result<Genesis> {
+                Ok(mock::genesis())
+            }
+
+            fn status(&self) -> Result<StatusResponse> {
+                Ok(mock::status_response())
+            }
+
+            fn block(&self, _: u64) -> Result<Block> {
+                unreachable!()
+            }
+
+            fn block_batch<'a, T: Iterator<Item = &'a u64>>(
+                &self,
+                _heights: T,
+            ) -> Result<Vec<Block>> {
+                unreachable!()
+            }
+
+            fn block_results(&self, _height: u64) -> Result<BlockResultsResponse> {
+                unreachable!()
+            }
+
+            fn block_results_batch<'a, T: Iterator<Item = &'a u64>>(
+                &self,
+                _heights: T,
+            ) -> Result<Vec<BlockResultsResponse>> {
+                unreachable!()
+            }
+
+            fn broadcast_transaction(&self, _: &[u8]) -> Result<BroadcastTxResponse> {
+                self.broadcasted.fetch_add(1, Ordering::SeqCst);
+                Ok(BroadcastTxResponse {
+                    code: tendermint::abci::Code::Ok,
+                    data: tendermint::abci::Data::from(vec![]),
+                    log: tendermint::abci::Log::from(""),
+                    hash: tendermint::abci::transaction::Hash::new([0; 32]),
+                })
+            }
+
+            fn query(
+                &self,
+                _path: &str,
+                _data: &[u8],
+                _height: Option<Height>,
+                _prove: bool,
+            ) -> Result<AbciQuery> {
+                unreachable!()
+            }
+
+            fn query_state_batch<T: Iterator<Item = u64>>(
+                &self,
+                _heights: T,
+            ) -> Result<Vec<ChainState>> {
+                unreachable!()
+            }
+        }
+
+        fn fee_algorithm() -> LinearFee {
+            LinearFee::new(Milli::try_new(1, 1).unwrap(), Milli::try_new(1, 1).unwrap())
+        }
+
+        #[test]
+        fn check_watch_only_multi_sig_bundle_flow() {
+            let passphrase = SecUtf8::from("passphrase");
+
+            // offline co-signers, each with its own storage
+            let signers = (0..2)
+                .map(|i| {
+                    let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+                    let name = format!("signer{}", i);
+                    let (enckey, _) = client
+                        .new_wallet(
+                            &name,
+                            &passphrase,
+                            WalletKind::Basic,
+                            HardwareKind::LocalOnly,
+                            None,
+                        )
+                        .unwrap();
+                    let public_key = client
+                        .new_public_key(&name, &enckey, Some(AddressType::Transfer))
+                        .unwrap();
+                    (client, name, enckey, public_key)
+                })
+                .collect::<Vec<_>>();
+            let participants = signers
+                .iter()
+                .map(|(_, _, _, public_key)| public_key.clone())
+                .collect::<Vec<_>>();
+
+            // online watch-only wallet
+            let tendermint_client = MockClient::default();
+            let storage = MemoryStorage::default();
+            let hw_key_service = HwKeyService::default();
+            let watch_only = DefaultWalletClient::new(
+                storage.clone(),
+                tendermint_client.clone(),
+                DefaultWalletTransactionBuilder::new(
+                    WalletSignerManager::new(storage, hw_key_service.clone()),
+                    fee_algorithm(),
+                    MockAbciTransactionObfuscation::new(tendermint_client.clone()),
+                    LargestFirst,
+                ),
+                None,
+                hw_key_service,
+            );
+            let name = "treasury";
+            let enckey = watch_only
+                .restore_basic_wallet(name, &passphrase, &PrivateKey::new().unwrap())
+                .unwrap();
+
+            assert_eq!(
+                ErrorKind::InvalidInput,
+                watch_only
+                    .import_multi_sig_address(name, &enckey, participants.clone(), 2, 3)
+                    .unwrap_err()
+                    .kind()
+            );
+            let treasury = watch_only
+                .import_multi_sig_address(name, &enckey, participants.clone(), 2, 2)
+                .unwrap();
+            assert!(watch_only.public_keys(name, &enckey).unwrap().is_empty());
+
+            let mut memento = WalletStateMemento::default();
+            memento.add_unspent_transaction(
+                TxoPointer::new([1; 32], 0),
+                TxOut::new(treasury.clone(), Coin::new(1_000_000).unwrap()),
+            );
+            watch_only
+                .wallet_state_service
+                .apply_memento(name, &enckey, &memento)
+                .unwrap();
+
+            let bundle = watch_only
+                .new_multi_sig_transfer_bundle(
+                    name,
+                    &enckey,
+                    &treasury,
+                    ExtendedAddr::OrTree([2; 32]),
+                    Coin::new(100).unwrap(),
+                    vec![],
+                    0xab,
+                    fee_algorithm(),
+                )
+                .unwrap();
+
+            // co-signers sign transaction id of the bundle offline
+            let tx_id =
+                RawTransferTransactionBuilder::from_incomplete(bundle.clone(), fee_algorithm())
+                    .unwrap()
+                    .tx_id();
+            let sessions = signers
+                .iter()
+                .map(|(client, name, enckey, public_key)| {
+                    client
+                        .new_multi_sig_session(
+                            name,
+                            enckey,
+                            tx_id,
+                            participants.clone(),
+                            public_key.clone(),
+                        )
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+            for &(i, j) in [(0, 1), (1, 0)].iter() {
+                let nonce_commitment = signers[j]
+                    .0
+                    .nonce_commitment(&sessions[j], &signers[j].2)
+                    .unwrap();
+                signers[i]
+                    .0
+                    .add_nonce_commitment(
+                        &sessions[i],
+                        &signers[i].2,
+                        nonce_commitment,
+                        &signers[j].3,
                     )
-                    .unwrap(),
-                    &name1,
-                    &enckey1,
-                    &mut dummy_wallet,
+                    .unwrap();
+            }
+            for &(i, j) in [(0, 1), (1, 0)].iter() {
+                let nonce = signers[j].0.nonce(&sessions[j], &signers[j].2).unwrap();
+                signers[i]
+                    .0
+                    .add_nonce(&sessions[i], &signers[i].2, &nonce, &signers[j].3)
+                    .unwrap();
+            }
+            for &(i, j) in [(0, 1), (1, 0)].iter() {
+                let partial_signature = signers[j]
+                    .0
+                    .partial_signature(&sessions[j], &signers[j].2)
+                    .unwrap();
+                signers[i]
+                    .0
+                    .add_partial_signature(
+                        &sessions[i],
+                        &signers[i].2,
+                        partial_signature,
+                        &signers[j].3,
+                    )
+                    .unwrap();
+            }
+            let signature = signers[0].0.signature(&sessions[0], &signers[0].2).unwrap();
+
+            // unsigned bundle cannot be broadcast
+            assert!(watch_only
+                .broadcast_multi_sig_bundle(name, &enckey, bundle.clone(), fee_algorithm())
+                .is_err());
+            // proof for a single co-signer does not exist in 2-of-2 address
+            assert!(watch_only
+                .add_multi_sig_bundle_signature(
+                    name,
+                    &enckey,
+                    bundle.clone(),
+                    participants[..1].to_vec(),
+                    signature,
+                    fee_algorithm(),
                 )
-                .unwrap(),
-            false
-        );
+                .is_err());
+
+            let signed_bundle = watch_only
+                .add_multi_sig_bundle_signature(
+                    name,
+                    &enckey,
+                    bundle,
+                    participants,
+                    signature,
+                    fee_algorithm(),
+                )
+                .unwrap();
+            let broadcast_tx_id = watch_only
+                .broadcast_multi_sig_bundle(name, &enckey, signed_bundle, fee_algorithm())
+                .unwrap();
+            assert_eq!(tx_id, broadcast_tx_id);
+            assert_eq!(1, tendermint_client.broadcasted.load(Ordering::SeqCst));
+            let balance = watch_only
+                .wallet_state_service
+                .get_balance(name, &enckey, 0)
+                .unwrap();
+            assert_eq!(Coin::zero(), balance.available);
+            assert!(balance.pending > Coin::zero());
+        }
+
+        /// Passes a message to another co-signer (SCALE and JSON encoded on the way)
+        fn send<M>(message: &M) -> M
+        where
+            M: Encode + Decode + Serialize + DeserializeOwned,
+        {
+            let message = M::decode(&mut message.encode().as_slice()).unwrap();
+            serde_json::from_str(&serde_json::to_string(&message).unwrap()).unwrap()
+        }
+
+        #[test]
+        fn check_multi_sig_ceremony_with_messages() {
+            let passphrase = SecUtf8::from("passphrase");
+
+            // co-signers of a 2-of-3 address, each with its own storage
+            let signers = (0..3)
+                .map(|i| {
+                    let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+                    let name = format!("signer{}", i);
+                    let (enckey, _) = client
+                        .new_wallet(
+                            &name,
+                            &passphrase,
+                            WalletKind::Basic,
+                            HardwareKind::LocalOnly,
+                            None,
+                        )
+                        .unwrap();
+                    let public_key = client
+                        .new_public_key(&name, &enckey, Some(AddressType::Transfer))
+                        .unwrap();
+                    (client, name, enckey, public_key)
+                })
+                .collect::<Vec<_>>();
+            let participants = signers
+                .iter()
+                .map(|(_, _, _, public_key)| public_key.clone())
+                .collect::<Vec<_>>();
+            let address = ExtendedAddr::from(
+                MultiSigAddress::new(participants.clone(), participants[0].clone(), 2).unwrap(),
+            );
+
+            // the first two co-signers sign, the third one starts a session with the second one
+            let tx_id = [7; 32];
+            let new_session = |i: usize, co_signers: Vec<PublicKey>| {
+                let (client, name, enckey, public_key) = &signers[i];
+                client
+                    .new_multi_sig_session(name, enckey, tx_id, co_signers, public_key.clone())
+                    .unwrap()
+            };
+            let co_signers = participants[..2].to_vec();
+            let sessions = vec![
+                new_session(0, co_signers.clone()),
+                new_session(1, co_signers.clone()),
+            ];
+            let other_session = new_session(2, participants[1..].to_vec());
+
+            let nonce_commitments = (0..2)
+                .map(|i| {
+                    signers[i]
+                        .0
+                        .export_nonce_commitment(&sessions[i], &signers[i].2)
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(sessions[0], nonce_commitments[0].session_id);
+            assert_eq!(participants[0], nonce_commitments[0].public_key);
+            for &(i, j) in [(0, 1), (1, 0)].iter() {
+                signers[i]
+                    .0
+                    .import_nonce_commitment(
+                        &sessions[i],
+                        &signers[i].2,
+                        &send(&nonce_commitments[j]),
+                    )
+                    .unwrap();
+            }
+
+            // duplicate submission
+            assert_eq!(
+                ErrorKind::AlreadyExists,
+                signers[0]
+                    .0
+                    .import_nonce_commitment(&sessions[0], &signers[0].2, &nonce_commitments[1])
+                    .unwrap_err()
+                    .kind()
+            );
+            // the sender is the current signer
+            assert_eq!(
+                ErrorKind::InvalidInput,
+                signers[0]
+                    .0
+                    .import_nonce_commitment(&sessions[0], &signers[0].2, &nonce_commitments[0])
+                    .unwrap_err()
+                    .kind()
+            );
+            // the sender isn't a co-signer of the session
+            let outsider = signers[2]
+                .0
+                .export_nonce_commitment(&other_session, &signers[2].2)
+                .unwrap();
+            assert_eq!(
+                ErrorKind::InvalidInput,
+                signers[0]
+                    .0
+                    .import_nonce_commitment(&sessions[0], &signers[0].2, &send(&outsider))
+                    .unwrap_err()
+                    .kind()
+            );
+
+            let nonces = (0..2)
+                .map(|i| {
+                    signers[i]
+                        .0
+                        .export_nonce(&sessions[i], &signers[i].2)
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+            for &(i, j) in [(0, 1), (1, 0)].iter() {
+                signers[i]
+                    .0
+                    .import_nonce(&sessions[i], &signers[i].2, &send(&nonces[j]))
+                    .unwrap();
+            }
+            assert_eq!(
+                ErrorKind::AlreadyExists,
+                signers[1]
+                    .0
+                    .import_nonce(&sessions[1], &signers[1].2, &nonces[0])
+                    .unwrap_err()
+                    .kind()
+            );
+
+            let partial_signatures = (0..2)
+                .map(|i| {
+                    signers[i]
+                        .0
+                        .export_partial_signature(&sessions[i], &signers[i].2)
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+            for &(i, j) in [(0, 1), (1, 0)].iter() {
+                signers[i]
+                    .0
+                    .import_partial_signature(
+                        &sessions[i],
+                        &signers[i].2,
+                        &send(&partial_signatures[j]),
+                    )
+                    .unwrap();
+            }
+            assert_eq!(
+                ErrorKind::AlreadyExists,
+                signers[0]
+                    .0
+                    .import_partial_signature(&sessions[0], &signers[0].2, &partial_signatures[1])
+                    .unwrap_err()
+                    .kind()
+            );
+
+            let signature = signers[0].0.signature(&sessions[0], &signers[0].2).unwrap();
+            assert_eq!(
+                signature,
+                signers[1].0.signature(&sessions[1], &signers[1].2).unwrap()
+            );
+
+            // the final signature unlocks the 2-of-3 address
+            let proof = MultiSigAddress::new(participants.clone(), participants[0].clone(), 2)
+                .unwrap()
+                .generate_proof(co_signers)
+                .unwrap()
+                .unwrap();
+            let witness = TxInWitness::TreeSig(signature, proof);
+            assert!(verify_tx_address(&witness, &tx_id, &address).is_ok());
+        }
     }
 
     #[test]
-    fn check_restore_basic_wallet() {
-        let private_key =
-            PrivateKey::deserialize_from(&[0x01; 32]).expect("32 bytes, within curve order");
+    fn check_export_and_verify_proof_bundle() {
+        let name = "name";
+        let passphrase = SecUtf8::from("passphrase");
+        let tendermint_client = GeneratorClient::new(BlockGenerator::one_node());
+        let client = DefaultWalletClient::new(
+            MemoryStorage::default(),
+            tendermint_client.clone(),
+            UnauthorizedWalletTransactionBuilder,
+            None,
+            HwKeyService::default(),
+        );
+        let (enckey, _) = client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
 
-        let name = "Default1";
-        let passphrase = SecUtf8::from("123456");
-        let client = DefaultWalletClient::new_read_only(MemoryStorage::default());
-        let seckey = client
-            .restore_basic_wallet(&name, &passphrase, &private_key)
+        // unbond transaction of the wallet in block 2
+        let unbond = UnbondTx::new(
+            StakedStateAddress::BasicRedeem(RedeemAddress([1; 20])),
+            0,
+            Coin::unit(),
+            StakedStateOpAttributes::new(0),
+        );
+        let transaction = Transaction::UnbondStakeTransaction(unbond.clone());
+        let witness =
+            StakedStateOpWitness::new(PrivateKey::new().unwrap().sign(&transaction).unwrap());
+        {
+            let mut gen = tendermint_client.gen.write().unwrap();
+            gen.gen_block(&[]);
+            gen.gen_block(&[TxAux::PublicTx(TxPublicAux::UnbondStakeTx(unbond, witness))]);
+        }
+        let mut memento = WalletStateMemento::default();
+        memento.add_transaction_change(TransactionChange {
+            transaction_id: transaction.id(),
+            inputs: vec![],
+            outputs: vec![],
+            fee_paid: Fee::new(Coin::zero()),
+            balance_change: BalanceChange::NoChange,
+            transaction_type: TransactionType::Unbond,
+            block_height: 2,
+            block_time: Time::now(),
+            memo: None,
+        });
+        client
+            .wallet_state_service
+            .apply_memento(name, &enckey, &memento)
             .unwrap();
-        assert_eq!(
-            seckey.unsecure().to_vec(),
-            hex::decode("2683346bb890dc5497b050e0c4b93f843ca75503fd5bba41787583351f79931e")
-                .unwrap()
+
+        // the next block commits the app hash of the block of the transaction
+        tendermint_client.gen.write().unwrap().gen_block(&[]);
+        let bundle = client
+            .export_proof_bundle(
+                name,
+                &enckey,
+                vec![transaction.id()],
+                &CancellationToken::default(),
+            )
+            .unwrap();
+
+        let genesis = tendermint_client.genesis().unwrap();
+        let genesis_app_hash: H256 = genesis.app_hash.as_slice().try_into().unwrap();
+        let trusted_validators = ValidatorSet::new(genesis.validators);
+        let verified = verify_proof_bundle(&bundle, &genesis_app_hash, &trusted_validators)
+            .expect("valid proof bundle");
+        assert_eq!(1, verified.proofs.len());
+        assert_eq!(transaction, verified.proofs[0].transaction);
+
+        // not signed by the trusted validators
+        assert!(
+            verify_proof_bundle(&bundle, &genesis_app_hash, &ValidatorSet::new(vec![])).is_err()
         );
+
+        let verify = |bundle: ProofBundle| {
+            verify_proof_bundle(
+                &bundle.encode_versioned(),
+                &genesis_app_hash,
+                &trusted_validators,
+            )
+        };
+        // chain state changed
+        let mut tampered = verified.clone();
+        tampered.proofs[0].chain_state.account_root = [4; 32];
+        assert!(verify(tampered).is_err());
+        // transaction missing from the block
+        let mut tampered = verified.clone();
+        tampered.proofs[0].block_transactions.clear();
+        assert!(verify(tampered).is_err());
+        // header of another block
+        let mut tampered = verified.clone();
+        tampered.proofs[0].block_height = 1;
+        assert!(verify(tampered).is_err());
+        // header not signed
+        let mut tampered = verified;
+        tampered.proofs[0].next_header.0.commit.signatures = CommitSigs::new(vec![]);
+        assert!(verify(tampered).is_err());
     }
 }
@@ -36,20 +36,25 @@ use chain_core::tx::data::address::ExtendedAddr;
 use chain_core::tx::data::TxId;
 use chain_core::tx::fee::Fee;
 use chain_storage::jellyfish::compute_staking_root;
-use chain_tx_filter::BlockFilter;
+use chain_tx_filter::{FilterKind, TxFilter};
 use chain_util::NonEmpty;
+use client_common::tendermint::lite::check_validator_set_transition;
 use client_common::tendermint::types::{
     Block, BlockExt, BlockResults, BlockResultsResponse, Genesis, Time,
 };
 use client_common::tendermint::Client;
 use client_common::{
-    Error, ErrorKind, PrivateKey, Result, ResultExt, SecKey, SecureStorage, Transaction,
-    TransactionObfuscation,
+    CancellationToken, Error, ErrorKind, PrivateKey, Result, ResultExt, SecKey, SecureStorage,
+    StorageBatch, Transaction, TransactionObfuscation,
 };
 
 use super::syncer_logic::handle_blocks;
+use super::WalletLockMap;
 use crate::service;
-use crate::service::{KeyService, SyncState, Wallet, WalletState, WalletStateMemento};
+use crate::service::{
+    ChainTipRecord, ChainTipService, KeyService, SyncCheckpoint, SyncState, Wallet, WalletState,
+    WalletStateMemento,
+};
 use std::sync::Mutex;
 type BlockConfirmFunc = Arc<Mutex<Box<dyn Fn(u64, String) -> bool>>>; // height, blockhash
 
@@ -133,6 +138,7 @@ pub struct ObfuscationSyncerConfig<
     pub client: C,
     pub obfuscation: O,
     pub light_client: Option<L>,
+    pub wallet_locks: WalletLockMap,
 
     // configs
     pub options: SyncerOptions,
@@ -155,8 +161,16 @@ impl<S: SecureStorage, C: Client, O: TransactionObfuscation, L: LightClientHandl
             obfuscation,
             options,
             light_client,
+            wallet_locks: WalletLockMap::new(),
         }
     }
+
+    /// Shares the wallet locks of the wallet client, so that the writes of the syncer and the
+    /// ones of the client don't interleave
+    pub fn with_wallet_locks(mut self, wallet_locks: WalletLockMap) -> Self {
+        self.wallet_locks = wallet_locks;
+        self
+    }
 }
 
 /// Common configs for wallet syncer
@@ -166,6 +180,7 @@ pub struct SyncerConfig<S: SecureStorage, C: Client, L: LightClientHandle> {
     storage: S,
     client: C,
     light_client: Option<L>,
+    wallet_locks: WalletLockMap,
 
     // configs
     options: SyncerOptions,
@@ -184,6 +199,7 @@ pub struct WalletSyncer<
     storage: S,
     client: C,
     recover_address: T,
+    wallet_locks: WalletLockMap,
     options: SyncerOptions,
 
     // wallet
@@ -217,25 +233,45 @@ where
             enckey,
             options: config.options,
             recover_address,
+            wallet_locks: config.wallet_locks,
             light_client: config.light_client,
         }
     }
 
-    /// Delete sync state and wallet state.
+    /// Delete sync state and wallet state (and the chain tip records, which were taken along the
+    /// sync state).
     pub fn reset_state(&self) -> Result<()> {
+        let _guard = self.wallet_locks.exclusive(&self.name);
         service::delete_sync_state(&self.storage, &self.name)?;
         service::delete_wallet_state(&self.storage, &self.name)?;
+        ChainTipService::new(self.storage.clone()).delete_records(&self.name)?;
         Ok(())
     }
 
     /// Load wallet state in memory, sync it to most recent latest, then drop the memory cache.
     pub fn sync<F: FnMut(ProgressReport) -> bool>(&mut self, callback: F) -> Result<()> {
-        WalletSyncerImpl::new(self, callback)?.sync()
+        self.sync_cancellable(&CancellationToken::new(), callback)
+    }
+
+    /// Same as `sync`, but stops (with `ErrorKind::Cancelled`) after the block being processed
+    /// when `cancellation` is cancelled; the sync state is saved for the processed blocks only.
+    pub fn sync_cancellable<F: FnMut(ProgressReport) -> bool>(
+        &mut self,
+        cancellation: &CancellationToken,
+        callback: F,
+    ) -> Result<()> {
+        WalletSyncerImpl::new(self, callback, cancellation.clone())?.sync()
     }
 }
 
-fn load_view_key<S: SecureStorage>(storage: &S, name: &str, enckey: &SecKey) -> Result<PrivateKey> {
+fn load_view_key<S: SecureStorage>(
+    storage: &S,
+    wallet_locks: &WalletLockMap,
+    name: &str,
+    enckey: &SecKey,
+) -> Result<PrivateKey> {
     KeyService::new(storage.clone())
+        .with_wallet_locks(wallet_locks.clone())
         .wallet_private_key(name, enckey)?
         .err_kind(ErrorKind::InvalidInput, || {
             format!("wallet private view key not found: {}", name)
@@ -260,7 +296,7 @@ where
     where
         O: TransactionObfuscation,
     {
-        let private_key = load_view_key(&config.storage, &name, &enckey)?;
+        let private_key = load_view_key(&config.storage, &config.wallet_locks, &name, &enckey)?;
         let decryptor = TxObfuscationDecryptor::new(config.obfuscation, private_key);
         Ok(Self::with_config(
             SyncerConfig {
@@ -268,6 +304,7 @@ where
                 client: config.client,
                 options: config.options,
                 light_client: config.light_client,
+                wallet_locks: config.wallet_locks,
             },
             decryptor,
             name,
@@ -289,6 +326,7 @@ struct WalletSyncerImpl<
 > {
     env: &'a mut WalletSyncer<S, C, D, T, L>,
     progress_callback: F,
+    cancellation: CancellationToken,
 
     // cached state
     wallet: Wallet,
@@ -306,7 +344,11 @@ impl<
         L: LightClientHandle,
     > WalletSyncerImpl<'a, S, C, D, F, T, L>
 {
-    fn new(env: &'a mut WalletSyncer<S, C, D, T, L>, progress_callback: F) -> Result<Self> {
+    fn new(
+        env: &'a mut WalletSyncer<S, C, D, T, L>,
+        progress_callback: F,
+        cancellation: CancellationToken,
+    ) -> Result<Self> {
         let wallet = service::load_wallet(&env.storage, &env.name, &env.enckey)?
             .err_kind(ErrorKind::InvalidInput, || {
                 format!("wallet not found: {}", env.name)
@@ -321,12 +363,20 @@ impl<
             get_genesis_sync_state(&env.client, enable_genesis_fingerprint_check)?
         };
 
-        let wallet_state =
+        let mut wallet_state =
             service::load_wallet_state(&env.storage, &env.name, &env.enckey)?.unwrap_or_default();
+        // the broadcast transactions which are still tracked stay pending (saved by `sync`)
+        service::reload_pending_transactions(
+            &env.storage,
+            &env.name,
+            &env.enckey,
+            &mut wallet_state,
+        )?;
 
         Ok(Self {
             env,
             progress_callback,
+            cancellation,
             wallet,
             sync_state,
             wallet_state,
@@ -348,22 +398,45 @@ impl<
         })
     }
 
-    fn update_state(&mut self, memento: &WalletStateMemento) -> Result<()> {
+    /// Writes the batch along with the wallet state updated by the memento (and its history index)
+    fn update_state(
+        &mut self,
+        mut batch: StorageBatch,
+        memento: &WalletStateMemento,
+    ) -> Result<()> {
+        let _guard = self.env.wallet_locks.exclusive(&self.env.name);
         // if there is a job, then fetch & update, if not skip
-        if !memento.is_empty() {
-            self.wallet_state = service::modify_wallet_state(
-                &self.env.storage,
-                &self.env.name,
-                &self.env.enckey,
-                |state| state.apply_memento(memento),
-            )?;
+        if memento.is_empty() {
+            return self.env.storage.write_batch(batch);
         }
+        self.wallet_state = service::batch_apply_memento(
+            &self.env.storage,
+            &mut batch,
+            &self.env.name,
+            &self.env.enckey,
+            memento,
+        )?;
+        self.env.storage.write_batch(batch)?;
+        service::unlock_spent_outputs(
+            &self.env.storage,
+            &self.env.name,
+            &self.env.enckey,
+            memento,
+        )?;
+        service::prune_pending_transactions(
+            &self.env.storage,
+            &self.env.name,
+            &self.env.enckey,
+            &self.wallet_state,
+            self.sync_state.last_block_height,
+        )?;
         Ok(())
     }
 
     fn save(&mut self, memento: &WalletStateMemento) -> Result<()> {
-        service::save_sync_state(&self.env.storage, &self.env.name, &self.sync_state)?;
-        self.update_state(memento)?;
+        let mut batch = StorageBatch::default();
+        service::batch_save_sync_state(&mut batch, &self.env.name, &self.sync_state);
+        self.update_state(batch, memento)?;
         self.env.storage.flush()?;
         Ok(())
     }
@@ -451,23 +524,54 @@ impl<
         self.sync_state.last_app_hash = block.app_hash.clone();
         self.sync_state.last_block_hash = block.block_hash.clone();
         self.sync_state.staking_root = block.staking_root;
-        self.save(&memento)?;
+
+        // the checkpoints, the chain tip record (if one is due) and the wallet state are saved
+        // along the sync state
+        let _guard = self.env.wallet_locks.exclusive(&self.env.name);
+        let mut batch = StorageBatch::default();
+        service::batch_add_sync_checkpoints(
+            &self.env.storage,
+            &mut batch,
+            &self.env.name,
+            blocks.iter().map(|block| SyncCheckpoint {
+                block_height: block.block_height,
+                app_hash: block.app_hash.clone(),
+                block_hash: block.block_hash.clone(),
+                staking_root: block.staking_root,
+            }),
+        )?;
+        service::batch_save_sync_state(&mut batch, &self.env.name, &self.sync_state);
+        ChainTipService::new(self.env.storage.clone()).batch_append(
+            &mut batch,
+            &self.env.name,
+            &self.env.enckey,
+            block.block_height,
+            &block.last_app_hash,
+            false,
+        )?;
+        self.update_state(batch, &memento)?;
+        self.env.storage.flush()?;
 
         if !self.update_progress(block.block_height) {
-            return Err(Error::new(ErrorKind::InvalidInput, "Cancelled by user"));
+            return Err(Error::new(ErrorKind::Cancelled, "Cancelled by user"));
         }
 
         Ok(())
     }
 
     fn sync(&mut self) -> Result<()> {
-        service::save_wallet_state(
-            &self.env.storage,
-            &self.env.name,
-            &self.env.enckey,
-            &self.wallet_state,
-        )?;
+        self.verify_history()?;
+        {
+            let _guard = self.env.wallet_locks.exclusive(&self.env.name);
+            service::save_wallet_state(
+                &self.env.storage,
+                &self.env.name,
+                &self.env.enckey,
+                &self.wallet_state,
+            )?;
+        }
 
+        self.cancellation.check()?;
         let status = self.env.client.status()?;
         if status.sync_info.catching_up {
             return Err(Error::new(
@@ -528,12 +632,61 @@ impl<
             };
 
         if !self.init_progress(target_height) {
-            return Err(Error::new(ErrorKind::InvalidInput, "Cancelled by user"));
+            return Err(Error::new(ErrorKind::Cancelled, "Cancelled by user"));
         }
 
         self.sync_to(target_height, &target_app_hash, &target_block_hash)
     }
 
+    /// Verifies the chain tip records of the wallet against its sync state and the node (see
+    /// `ChainTipService`). On a mismatch, the wallet is flagged (which blocks its sync and sends)
+    /// until the user acknowledges it.
+    fn verify_history(&self) -> Result<()> {
+        let chain_tip_service = ChainTipService::new(self.env.storage.clone());
+        chain_tip_service.ensure_no_mismatch(&self.env.name)?;
+
+        let sync_height = service::load_sync_state(&self.env.storage, &self.env.name)?
+            .map(|sync_state| sync_state.last_block_height);
+        let verified = chain_tip_service
+            .verify(&self.env.name, &self.env.enckey, sync_height)
+            .and_then(|latest| match latest {
+                Some(record) => self.check_node_app_hash(&record),
+                None => Ok(()),
+            });
+        if let Err(err) = &verified {
+            if err.kind() == ErrorKind::HistoryMismatch {
+                log::error!("{}", err.message());
+                let _guard = self.env.wallet_locks.exclusive(&self.env.name);
+                chain_tip_service.set_mismatch(&self.env.name, err.message())?;
+            }
+        }
+        verified
+    }
+
+    /// Checks that the header of the node at the height of the record has the recorded app hash
+    fn check_node_app_hash(&self, record: &ChainTipRecord) -> Result<()> {
+        match self.env.client.block(record.block_height) {
+            Ok(block) if hex::encode_upper(&block.header.app_hash) == record.app_hash => Ok(()),
+            Ok(_) => Err(Error::new(
+                ErrorKind::HistoryMismatch,
+                format!(
+                    "History of wallet {} doesn't match: app hash of block {} on the node differs from record {}",
+                    self.env.name, record.block_height, record.counter
+                ),
+            )),
+            Err(err) => {
+                if let ErrorKind::BlockPruned { .. } = err.kind() {
+                    log::warn!(
+                        "block {} was pruned by the node, it can't be checked against the chain tip record",
+                        record.block_height
+                    );
+                    return Ok(());
+                }
+                Err(err)
+            }
+        }
+    }
+
     fn get_block_data_tuple_for_sync(
         &mut self,
         range: &[u64],
@@ -553,12 +706,14 @@ impl<
     ) -> Result<()> {
         self.sync_state.trusted = false;
         log::debug!("sync_to block {} ", target_height);
+        self.detect_reorg(target_height)?;
 
         // Send batch RPC requests to tendermint in chunks of `batch_size` requests per batch call
         for chunk in ((self.sync_state.last_block_height + 1)..=target_height)
             .chunks(self.env.options.batch_size)
             .into_iter()
         {
+            self.cancellation.check()?;
             let mut batch = Vec::with_capacity(self.env.options.batch_size);
             if self.env.options.enable_fast_forward {
                 if let Some(block) = self.fast_forward_status(&target_app_hash, target_height)? {
@@ -588,6 +743,17 @@ impl<
             let mut succeed = false;
             for _ in 0..12 {
                 let block_data_tuple = self.get_block_data_tuple_for_sync(&range);
+                if let Err(err) = &block_data_tuple {
+                    if let ErrorKind::BlockPruned { earliest_available } = err.kind() {
+                        // retrying won't make the blocks available again
+                        return self.skip_pruned_blocks(
+                            earliest_available,
+                            target_height,
+                            target_app_hash,
+                            target_block_hash,
+                        );
+                    }
+                }
                 if let Ok((tmp_blocks, tmp_block_results, tmp_states)) = block_data_tuple.as_ref() {
                     blocks = tmp_blocks.to_vec();
                     block_results = tmp_block_results.to_vec();
@@ -612,6 +778,7 @@ impl<
                         );
                     }
                 }
+                self.cancellation.check()?;
                 log::info!("retry fetching block-data");
                 std::thread::sleep(std::time::Duration::from_secs(5));
             }
@@ -620,11 +787,20 @@ impl<
                 return Err(Error::new(ErrorKind::IoError, "sync fetch-block failed"));
             }
 
+            // validator set update events must agree with the next block's validator hashes
+            for (block_result, next_block) in block_results.iter().zip(blocks.iter().skip(1)) {
+                check_validator_set_transition(block_result, &next_block.header)?;
+            }
+
             for (block, block_result, state) in izip!(
                 blocks.into_iter(),
                 block_results.into_iter(),
                 states.into_iter()
             ) {
+                // the blocks before are saved below, so that the sync state is consistent
+                if self.cancellation.is_cancelled() {
+                    break;
+                }
                 let block = FilteredBlock::from_block(
                     &self.wallet,
                     &self.wallet_state,
@@ -661,6 +837,7 @@ impl<
             if let Some(non_empty_batch) = NonEmpty::new(batch) {
                 self.handle_batch(non_empty_batch)?;
             }
+            self.cancellation.check()?;
         }
 
         match self.sync_state.last_block_height.cmp(&target_height) {
@@ -675,6 +852,7 @@ impl<
                     ));
                 };
                 self.sync_state.trusted = true;
+                let _guard = self.env.wallet_locks.exclusive(&self.env.name);
                 service::save_sync_state(&self.env.storage, &self.env.name, &self.sync_state)
             }
             Ordering::Greater => {
@@ -692,6 +870,169 @@ impl<
         }
     }
 
+    /// Blocks from the next one to sync up to `earliest_available` were pruned by the node (and
+    /// by its archive node, if any): the scan continues from the earliest available block, so the
+    /// transactions in the pruned blocks are missed (reported to the callback and logged).
+    fn skip_pruned_blocks(
+        &mut self,
+        earliest_available: u64,
+        target_height: u64,
+        target_app_hash: &str,
+        target_block_hash: &str,
+    ) -> Result<()> {
+        let from_block_height = self.sync_state.last_block_height + 1;
+        if earliest_available <= from_block_height || earliest_available > target_height {
+            return Err(Error::new(
+                ErrorKind::BlockPruned { earliest_available },
+                format!(
+                    "block {} is not available on the node (earliest available height: {})",
+                    from_block_height, earliest_available
+                ),
+            ));
+        }
+
+        if !(self.progress_callback)(ProgressReport::BlocksPruned {
+            wallet_name: self.env.name.clone(),
+            from_block_height,
+            earliest_available,
+        }) {
+            return Err(Error::new(ErrorKind::Cancelled, "Cancelled by user"));
+        }
+        log::warn!(
+            "blocks {} to {} were pruned by the node, wallet {} skips them (transactions in them are not synced)",
+            from_block_height,
+            earliest_available - 1,
+            self.env.name
+        );
+
+        // the hash chains can't be verified across the gap
+        self.sync_state.last_block_height = earliest_available - 1;
+        self.sync_state.last_app_hash.clear();
+        self.sync_state.last_block_hash.clear();
+        self.sync_to(target_height, target_app_hash, target_block_hash)
+    }
+
+    /// Checks that the last synced block is still on the chain of the node, if not, rolls back
+    /// wallet state to the last common checkpoint.
+    fn detect_reorg(&mut self, target_height: u64) -> Result<()> {
+        let last_block_height = self.sync_state.last_block_height;
+        if last_block_height == 0
+            || last_block_height > target_height
+            || self.sync_state.last_block_hash.is_empty()
+        {
+            return Ok(());
+        }
+        match self.node_block_hash(last_block_height) {
+            Ok(hash) if hash == self.sync_state.last_block_hash => return Ok(()),
+            Ok(_) => {}
+            Err(err) => {
+                if let ErrorKind::BlockPruned { .. } = err.kind() {
+                    log::warn!(
+                        "block {} was pruned by the node, it can't be checked for reorg",
+                        last_block_height
+                    );
+                    return Ok(());
+                }
+                return Err(err);
+            }
+        }
+        log::warn!(
+            "block hash at height {} don't match the node, searching for common block",
+            last_block_height
+        );
+
+        let mut checkpoints = service::load_sync_checkpoints(&self.env.storage, &self.env.name)?;
+        checkpoints.retain(|checkpoint| checkpoint.block_height <= target_height);
+
+        // checkpoints still on the node's chain form a prefix, binary search the last one
+        let (mut low, mut high) = (0, checkpoints.len());
+        while low < high {
+            let mid = (low + high) / 2;
+            let checkpoint = &checkpoints[mid];
+            if self.node_block_hash(checkpoint.block_height)? == checkpoint.block_hash {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        if low == 0 {
+            return Err(Error::new(
+                ErrorKind::VerifyError,
+                format!(
+                    "no common block found within the last {} synced blocks, please reset the wallet state",
+                    service::MAX_SYNC_CHECKPOINTS
+                ),
+            ));
+        }
+        checkpoints.truncate(low);
+        let common = checkpoints.last().expect("non-empty checkpoints");
+
+        if !(self.progress_callback)(ProgressReport::ReorgDetected {
+            wallet_name: self.env.name.clone(),
+            block_height: last_block_height,
+            common_block_height: common.block_height,
+        }) {
+            return Err(Error::new(ErrorKind::Cancelled, "Cancelled by user"));
+        }
+
+        // the wallet state is loaded and rolled back under the guard, so that no write of the
+        // wallet client is lost in between
+        let _guard = self.env.wallet_locks.exclusive(&self.env.name);
+        let mut wallet_state =
+            service::load_wallet_state(&self.env.storage, &self.env.name, &self.env.enckey)?
+                .unwrap_or_default();
+        let wallet = &self.wallet;
+        wallet_state.rollback(common.block_height, |address| {
+            wallet.transfer_addresses_contains(address)
+        })?;
+        let sync_state = SyncState::from_checkpoint(common);
+        // the records above the common block would no longer agree with the sync state
+        let common_app_hash =
+            hex::encode_upper(&self.env.client.block(common.block_height)?.header.app_hash);
+
+        let mut batch = StorageBatch::default();
+        service::batch_save_wallet_state(
+            &mut batch,
+            &self.env.name,
+            &self.env.enckey,
+            &wallet_state,
+        )?;
+        service::batch_rollback_history_index(
+            &self.env.storage,
+            &mut batch,
+            &self.env.name,
+            common.block_height,
+        )?;
+        service::batch_save_sync_state(&mut batch, &self.env.name, &sync_state);
+        service::batch_save_sync_checkpoints(&mut batch, &self.env.name, &checkpoints);
+        ChainTipService::new(self.env.storage.clone()).batch_append(
+            &mut batch,
+            &self.env.name,
+            &self.env.enckey,
+            common.block_height,
+            &common_app_hash,
+            true,
+        )?;
+        self.env.storage.write_batch(batch)?;
+        self.env.storage.flush()?;
+
+        log::warn!(
+            "wallet {} rolled back from block {} to block {}",
+            self.env.name,
+            last_block_height,
+            common.block_height
+        );
+        self.wallet_state = wallet_state;
+        self.sync_state = sync_state;
+        Ok(())
+    }
+
+    /// Hash of block at given height on the chain of the node
+    fn node_block_hash(&self, block_height: u64) -> Result<String> {
+        let block = self.env.client.block(block_height)?;
+        Ok(ProdHasher {}.hash_header(&block.header).to_string())
+    }
+
     fn rollback_pending_tx(&mut self, current_block_height: u64) -> Result<()> {
         let mut memento = WalletStateMemento::default();
         let state =
@@ -838,6 +1179,26 @@ pub enum ProgressReport {
         /// Current synchronized block height
         current_block_height: u64,
     },
+    /// Report that synced blocks are no longer on the chain of the node, and wallet state has been
+    /// rolled back to the last common block
+    ReorgDetected {
+        /// Name of wallet
+        wallet_name: String,
+        /// Last synchronized block height before rollback
+        block_height: u64,
+        /// Height of last common block which wallet state is rolled back to
+        common_block_height: u64,
+    },
+    /// Report that the blocks to synchronize were pruned by the node, and synchronization skips
+    /// them (the transactions in them are missed)
+    BlocksPruned {
+        /// Name of wallet
+        wallet_name: String,
+        /// Height of first skipped block
+        from_block_height: u64,
+        /// Earliest block height available on the node, synchronization continues from it
+        earliest_available: u64,
+    },
 }
 
 /// Structure for representing a block header on Thaler Experimental Network,
@@ -858,8 +1219,8 @@ pub(crate) struct FilteredBlock {
     pub block_time: Time,
     /// List of successfully committed transaction ids in this block and their fees
     pub valid_transaction_fees: IndexMap<TxId, Fee>,
-    /// Bloom filter for view keys and staking addresses
-    pub block_filter: BlockFilter,
+    /// Probabilistic filter (Bloom filter or Golomb-coded set) for view keys and staking addresses
+    pub block_filter: FilterKind,
     /// List of successfully committed transaction of transactions that may need to be queried against
     pub enclave_transaction_ids: Vec<TxId>,
     /// List of un-encrypted transactions (only contains transactions of type `DepositStake` and `UnbondStake`)
@@ -1095,7 +1456,7 @@ mod tests {
     use chain_core::state::ChainState;
     use client_common::storage::MemoryStorage;
     use client_common::tendermint::types::*;
-    use client_common::tendermint::Client;
+    use client_common::tendermint::{Client, FailoverClient};
     use test_common::block_generator::{BlockGenerator, GeneratorClient};
 
     use crate::hd_wallet::HardwareKind;
@@ -1140,6 +1501,7 @@ mod tests {
                 storage,
                 client,
                 light_client,
+                wallet_locks: WalletLockMap::new(),
                 options: SyncerOptions {
                     enable_fast_forward,
                     disable_light_client: enable_fast_forward,
@@ -1169,6 +1531,465 @@ mod tests {
         check_wallet_syncer_impl(true);
     }
 
+    fn gen_client(num_blocks: usize, fork_height: Option<usize>) -> GeneratorClient {
+        let client = GeneratorClient::new(BlockGenerator::one_node());
+        {
+            let mut gen = client.gen.write().unwrap();
+            for height in 1..=num_blocks {
+                gen.gen_block(&[]);
+                if Some(height) == fork_height {
+                    // a different block at the same height, following blocks build on top of it
+                    gen.blocks[height - 1].block.header.time = std::time::UNIX_EPOCH.into();
+                }
+            }
+        }
+        client
+    }
+
+    #[test]
+    fn check_wallet_syncer_reorg() {
+        let storage = MemoryStorage::default();
+        let name = "name";
+        let wallet = DefaultWalletClient::new_read_only(storage.clone());
+        let (enckey, _) = wallet
+            .new_wallet(
+                name,
+                &SecUtf8::from("passphrase"),
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        // both chains share the first 5 blocks
+        let client = gen_client(10, None);
+        let fork_client = gen_client(12, Some(6));
+
+        let mut syncer = WalletSyncer::with_config(
+            SyncerConfig {
+                storage: storage.clone(),
+                client: client.clone(),
+                light_client: Some(client),
+                wallet_locks: WalletLockMap::new(),
+                options: SyncerOptions {
+                    enable_fast_forward: false,
+                    disable_light_client: true,
+                    enable_address_recovery: false,
+                    batch_size: 2,
+                    block_height_ensure: 50,
+                    light_client_peers: "".into(),
+                    light_client_trusting_period_seconds: 36000000,
+                    light_client_trusting_height: 1,
+                    light_client_trusting_blockhash: "".into(),
+                },
+            },
+            |_txids: &[TxId]| -> Result<Vec<Transaction>> { Ok(vec![]) },
+            name.to_owned(),
+            enckey,
+            wallet,
+        );
+        let genesis = syncer.client.genesis().unwrap();
+        let hash = compute_genesis_fingerprint(&genesis).unwrap();
+        std::env::set_var("CRYPTO_GENESIS_FINGERPRINT", hash);
+
+        let mut reorgs = vec![];
+        let mut callback = |report: ProgressReport| {
+            if let ProgressReport::ReorgDetected {
+                block_height,
+                common_block_height,
+                ..
+            } = report
+            {
+                reorgs.push((block_height, common_block_height));
+            }
+            true
+        };
+        syncer.sync(&mut callback).expect("Unable to synchronize");
+        let sync_state = service::load_sync_state(&storage, name).unwrap().unwrap();
+        assert_eq!(10, sync_state.last_block_height);
+        assert_eq!(
+            10,
+            service::load_sync_checkpoints(&storage, name)
+                .unwrap()
+                .len()
+        );
+
+        // node switches to the other chain
+        syncer.client = fork_client.clone();
+        syncer.light_client = Some(fork_client.clone());
+        syncer.sync(&mut callback).expect("Unable to synchronize");
+        assert_eq!(vec![(10, 5)], reorgs);
+
+        let sync_state = service::load_sync_state(&storage, name).unwrap().unwrap();
+        let fork_block = fork_client.block(12).unwrap();
+        assert_eq!(12, sync_state.last_block_height);
+        assert_eq!(
+            ProdHasher {}.hash_header(&fork_block.header).to_string(),
+            sync_state.last_block_hash
+        );
+        assert!(sync_state.trusted);
+
+        let checkpoints = service::load_sync_checkpoints(&storage, name).unwrap();
+        assert_eq!(
+            (1..=12).collect::<Vec<u64>>(),
+            checkpoints
+                .iter()
+                .map(|checkpoint| checkpoint.block_height)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn check_wallet_syncer_history_mismatch() {
+        let storage = MemoryStorage::default();
+        let name = "name";
+        let wallet = DefaultWalletClient::new_read_only(storage.clone());
+        let (enckey, _) = wallet
+            .new_wallet(
+                name,
+                &SecUtf8::from("passphrase"),
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        let chain_tip_service = ChainTipService::new(storage.clone());
+
+        let client = gen_client(10, None);
+        let mut syncer = WalletSyncer::with_config(
+            SyncerConfig {
+                storage: storage.clone(),
+                client: client.clone(),
+                light_client: Some(client.clone()),
+                wallet_locks: WalletLockMap::new(),
+                options: SyncerOptions {
+                    enable_fast_forward: false,
+                    disable_light_client: true,
+                    enable_address_recovery: false,
+                    batch_size: 2,
+                    block_height_ensure: 50,
+                    light_client_peers: "".into(),
+                    light_client_trusting_period_seconds: 36000000,
+                    light_client_trusting_height: 1,
+                    light_client_trusting_blockhash: "".into(),
+                },
+            },
+            |_txids: &[TxId]| -> Result<Vec<Transaction>> { Ok(vec![]) },
+            name.to_owned(),
+            enckey.clone(),
+            wallet.clone(),
+        );
+        let genesis = syncer.client.genesis().unwrap();
+        let hash = compute_genesis_fingerprint(&genesis).unwrap();
+        std::env::set_var("CRYPTO_GENESIS_FINGERPRINT", hash);
+
+        syncer.sync(|_| true).expect("Unable to synchronize");
+        // the first synced batch is recorded
+        let records = chain_tip_service.records(name).unwrap();
+        assert_eq!(
+            vec![(0, 2)],
+            records
+                .iter()
+                .map(|record| (record.counter, record.block_height))
+                .collect::<Vec<_>>()
+        );
+
+        // sync state restored from a backup older than the record
+        let checkpoints = service::load_sync_checkpoints(&storage, name).unwrap();
+        save_sync_state(&storage, name, &SyncState::from_checkpoint(&checkpoints[0])).unwrap();
+        assert_eq!(
+            ErrorKind::HistoryMismatch,
+            syncer.sync(|_| true).unwrap_err().kind()
+        );
+        assert_eq!(vec![name.to_owned()], wallet.history_mismatches().unwrap());
+
+        // blocked until acknowledged
+        save_sync_state(&storage, name, &SyncState::from_checkpoint(&checkpoints[9])).unwrap();
+        assert_eq!(
+            ErrorKind::HistoryMismatch,
+            syncer.sync(|_| true).unwrap_err().kind()
+        );
+        wallet.acknowledge_history_mismatch(name, &enckey).unwrap();
+        assert!(wallet.history_mismatches().unwrap().is_empty());
+        syncer.sync(|_| true).expect("Unable to synchronize");
+
+        // a new sequence of records starts with the next synced blocks
+        {
+            let mut gen = client.gen.write().unwrap();
+            gen.gen_block(&[]);
+            gen.gen_block(&[]);
+        }
+        syncer.sync(|_| true).expect("Unable to synchronize");
+        let records = chain_tip_service.records(name).unwrap();
+        assert_eq!(
+            vec![(0, 12)],
+            records
+                .iter()
+                .map(|record| (record.counter, record.block_height))
+                .collect::<Vec<_>>()
+        );
+
+        // node switches to a chain with another app hash at the recorded height
+        let fork_client = gen_client(12, None);
+        fork_client.gen.write().unwrap().blocks[11]
+            .block
+            .header
+            .app_hash = vec![0xff; 32];
+        syncer.client = fork_client.clone();
+        syncer.light_client = Some(fork_client);
+        assert_eq!(
+            ErrorKind::HistoryMismatch,
+            syncer.sync(|_| true).unwrap_err().kind()
+        );
+        assert!(chain_tip_service.mismatch(name).unwrap().is_some());
+        let sync_state = service::load_sync_state(&storage, name).unwrap().unwrap();
+        assert_eq!(12, sync_state.last_block_height);
+    }
+
+    /// Node which pruned the blocks below `earliest`
+    #[derive(Clone)]
+    struct PrunedClient {
+        inner: GeneratorClient,
+        earliest: u64,
+    }
+
+    impl PrunedClient {
+        fn check<'a>(&self, heights: impl IntoIterator<Item = &'a u64>) -> Result<()> {
+            match heights.into_iter().find(|height| **height < self.earliest) {
+                Some(height) => Err(Error::new(
+                    ErrorKind::BlockPruned {
+                        earliest_available: self.earliest,
+                    },
+                    format!(
+                        "height {} is not available, lowest height is {}",
+                        height, self.earliest
+                    ),
+                )),
+                None => Ok(()),
+            }
+        }
+    }
+
+    impl Client for PrunedClient {
+        fn genesis(&self) -> Result<Genesis> {
+            self.inner.genesis()
+        }
+        fn status(&self) -> Result<StatusResponse> {
+            self.inner.status()
+        }
+        fn block(&self, height: u64) -> Result<Block> {
+            self.check(&[height])?;
+            self.inner.block(height)
+        }
+        fn block_batch<'a, T: Iterator<Item = &'a u64>>(&self, heights: T) -> Result<Vec<Block>> {
+            let heights = heights.collect::<Vec<_>>();
+            self.check(heights.iter().copied())?;
+            self.inner.block_batch(heights.into_iter())
+        }
+        fn block_results(&self, height: u64) -> Result<BlockResultsResponse> {
+            self.check(&[height])?;
+            self.inner.block_results(height)
+        }
+        fn block_results_batch<'a, T: Iterator<Item = &'a u64>>(
+            &self,
+            heights: T,
+        ) -> Result<Vec<BlockResultsResponse>> {
+            let heights = heights.collect::<Vec<_>>();
+            self.check(heights.iter().copied())?;
+            self.inner.block_results_batch(heights.into_iter())
+        }
+        fn broadcast_transaction(&self, transaction: &[u8]) -> Result<BroadcastTxResponse> {
+            self.inner.broadcast_transaction(transaction)
+        }
+        fn query(
+            &self,
+            path: &str,
+            data: &[u8],
+            height: Option<Height>,
+            prove: bool,
+        ) -> Result<AbciQuery> {
+            self.inner.query(path, data, height, prove)
+        }
+        fn query_state_batch<T: Iterator<Item = u64>>(
+            &self,
+            heights: T,
+        ) -> Result<Vec<ChainState>> {
+            let heights = heights.collect::<Vec<_>>();
+            self.check(&heights)?;
+            self.inner.query_state_batch(heights.into_iter())
+        }
+    }
+
+    fn sync_pruned<C: Client>(client: C, light_client: GeneratorClient) -> (u64, Vec<(u64, u64)>) {
+        let storage = MemoryStorage::default();
+        let name = "name";
+        let wallet = DefaultWalletClient::new_read_only(storage.clone());
+        let (enckey, _) = wallet
+            .new_wallet(
+                name,
+                &SecUtf8::from("passphrase"),
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        let mut syncer = WalletSyncer::with_config(
+            SyncerConfig {
+                storage: storage.clone(),
+                client,
+                light_client: Some(light_client),
+                wallet_locks: WalletLockMap::new(),
+                options: SyncerOptions {
+                    enable_fast_forward: false,
+                    disable_light_client: true,
+                    enable_address_recovery: false,
+                    batch_size: 3,
+                    block_height_ensure: 50,
+                    light_client_peers: "".into(),
+                    light_client_trusting_period_seconds: 36000000,
+                    light_client_trusting_height: 1,
+                    light_client_trusting_blockhash: "".into(),
+                },
+            },
+            |_txids: &[TxId]| -> Result<Vec<Transaction>> { Ok(vec![]) },
+            name.to_owned(),
+            enckey,
+            wallet,
+        );
+        let genesis = syncer.client.genesis().unwrap();
+        let hash = compute_genesis_fingerprint(&genesis).unwrap();
+        std::env::set_var("CRYPTO_GENESIS_FINGERPRINT", hash);
+
+        let mut pruned = vec![];
+        syncer
+            .sync(|report| {
+                if let ProgressReport::BlocksPruned {
+                    from_block_height,
+                    earliest_available,
+                    ..
+                } = report
+                {
+                    pruned.push((from_block_height, earliest_available));
+                }
+                true
+            })
+            .expect("Unable to synchronize");
+
+        let sync_state = service::load_sync_state(&storage, name).unwrap().unwrap();
+        assert!(sync_state.trusted);
+        (sync_state.last_block_height, pruned)
+    }
+
+    #[test]
+    fn check_wallet_syncer_pruned_node() {
+        let client = gen_client(10, None);
+        let pruned_client = PrunedClient {
+            inner: client.clone(),
+            earliest: 6,
+        };
+
+        // the scan starts from the earliest available block
+        assert_eq!(
+            (10, vec![(1, 6)]),
+            sync_pruned(pruned_client.clone(), client.clone())
+        );
+
+        // the pruned blocks are fetched from the archive node
+        assert_eq!(
+            (10, vec![]),
+            sync_pruned(FailoverClient::new(pruned_client, client.clone()), client)
+        );
+    }
+
+    #[test]
+    fn check_wallet_syncer_cancellation() {
+        let storage = MemoryStorage::default();
+        let name = "name";
+        let wallet = DefaultWalletClient::new_read_only(storage.clone());
+        let (enckey, _) = wallet
+            .new_wallet(
+                name,
+                &SecUtf8::from("passphrase"),
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        let client = gen_client(10, None);
+        let mut syncer = WalletSyncer::with_config(
+            SyncerConfig {
+                storage: storage.clone(),
+                client: client.clone(),
+                light_client: Some(client),
+                wallet_locks: WalletLockMap::new(),
+                options: SyncerOptions {
+                    enable_fast_forward: false,
+                    disable_light_client: true,
+                    enable_address_recovery: false,
+                    batch_size: 3,
+                    block_height_ensure: 50,
+                    light_client_peers: "".into(),
+                    light_client_trusting_period_seconds: 36000000,
+                    light_client_trusting_height: 1,
+                    light_client_trusting_blockhash: "".into(),
+                },
+            },
+            |_txids: &[TxId]| -> Result<Vec<Transaction>> { Ok(vec![]) },
+            name.to_owned(),
+            enckey,
+            wallet,
+        );
+        let genesis = syncer.client.genesis().unwrap();
+        let hash = compute_genesis_fingerprint(&genesis).unwrap();
+        std::env::set_var("CRYPTO_GENESIS_FINGERPRINT", hash);
+
+        // cancelled (e.g. from another thread) while the block 5 is processed
+        let cancellation = CancellationToken::new();
+        let err = syncer
+            .sync_cancellable(&cancellation, |report| {
+                if let ProgressReport::Update {
+                    current_block_height: 5,
+                    ..
+                } = report
+                {
+                    cancellation.cancel();
+                }
+                true
+            })
+            .unwrap_err();
+        assert_eq!(ErrorKind::Cancelled, err.kind());
+
+        // the checkpoint reflects the fully processed blocks only
+        let sync_state = service::load_sync_state(&storage, name).unwrap().unwrap();
+        assert_eq!(6, sync_state.last_block_height);
+        assert!(!sync_state.trusted);
+        assert_eq!(
+            (1..=6).collect::<Vec<u64>>(),
+            service::load_sync_checkpoints(&storage, name)
+                .unwrap()
+                .iter()
+                .map(|checkpoint| checkpoint.block_height)
+                .collect::<Vec<_>>()
+        );
+
+        // not started at all with a cancelled token
+        assert_eq!(
+            ErrorKind::Cancelled,
+            syncer
+                .sync_cancellable(&cancellation, |_| true)
+                .unwrap_err()
+                .kind()
+        );
+
+        // resumed from the checkpoint
+        syncer.sync(|_| true).expect("Unable to synchronize");
+        let sync_state = service::load_sync_state(&storage, name).unwrap().unwrap();
+        assert_eq!(10, sync_state.last_block_height);
+        assert!(sync_state.trusted);
+    }
+
     #[test]
     #[ignore]
     fn check_wallet_syncer_app_hash_on_multiple_tx() {
@@ -1273,6 +2094,7 @@ mod tests {
                 storage,
                 client,
                 light_client,
+                wallet_locks: WalletLockMap::new(),
                 options: SyncerOptions {
                     enable_fast_forward,
                     disable_light_client: enable_fast_forward,
@@ -1330,6 +2152,7 @@ mod tests {
                 storage,
                 client,
                 light_client,
+                wallet_locks: WalletLockMap::new(),
                 options: SyncerOptions {
                     enable_fast_forward: false,
                     disable_light_client: false,
@@ -1394,6 +2217,7 @@ mod tests {
                 storage,
                 client,
                 light_client,
+                wallet_locks: WalletLockMap::new(),
                 options: SyncerOptions {
                     enable_fast_forward: false,
                     disable_light_client: false,
@@ -107,6 +107,7 @@ pub fn create_transaction_change(
         transaction_type,
         block_height,
         block_time,
+        memo: None,
     };
     Ok(transaction_change)
 }
@@ -368,7 +369,7 @@ mod tests {
             block_time: Time::from_str("2019-04-09T09:38:41.735577Z").unwrap(),
             valid_transaction_fees,
             enclave_transaction_ids: enclave_txs.iter().map(|tx| tx.id()).collect(),
-            block_filter,
+            block_filter: block_filter.into(),
             staking_transactions: other_txs.to_vec(),
             staking_root,
         }
@@ -514,14 +515,14 @@ mod tests {
         }
 
         assert_eq!(
-            states[0].get_balance().unwrap().total,
+            states[0].get_balance(0).unwrap().total,
             Coin::new(0).unwrap()
         );
         assert_eq!(states[0].transaction_history.len(), 2);
         assert_eq!(states[0].unspent_transactions.len(), 0);
 
         assert_eq!(
-            states[1].get_balance().unwrap().total,
+            states[1].get_balance(0).unwrap().total,
             Coin::new(100).unwrap()
         );
         assert_eq!(states[1].transaction_history.len(), 1);
@@ -0,0 +1,278 @@
+//! Per-wallet locks of `DefaultWalletClient`, so that the requests of a multi-threaded server
+//! can't interleave their (multi-record) writes of the same wallet: the methods which only read a
+//! wallet take a shared guard, the ones which modify it (new keys and addresses, pending
+//! transactions, ...) take an exclusive one.
+//!
+//! The guards are re-entrant within a thread (a method of the client may call the other ones),
+//! but a shared guard can't be upgraded to an exclusive one. The guards of several wallets are
+//! taken in the order of the wallet names (see `WalletLockMap::exclusive_many`).
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LockMode {
+    Shared,
+    Exclusive,
+}
+
+#[derive(Debug, Default)]
+struct LockState {
+    readers: usize,
+    writer: bool,
+    waiting_writers: usize,
+}
+
+#[derive(Debug, Default)]
+struct WalletLock {
+    state: Mutex<LockState>,
+    released: Condvar,
+}
+
+impl WalletLock {
+    // the state is consistent whenever its mutex is released, so a poisoned one is still usable
+    fn state(&self) -> MutexGuard<'_, LockState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait<'a>(&self, state: MutexGuard<'a, LockState>) -> MutexGuard<'a, LockState> {
+        self.released
+            .wait(state)
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// waiting writers go first, so that a stream of readers can't starve them
+    fn acquire(&self, mode: LockMode) {
+        let mut state = self.state();
+        match mode {
+            LockMode::Shared => {
+                while state.writer || state.waiting_writers > 0 {
+                    state = self.wait(state);
+                }
+                state.readers += 1;
+            }
+            LockMode::Exclusive => {
+                state.waiting_writers += 1;
+                while state.writer || state.readers > 0 {
+                    state = self.wait(state);
+                }
+                state.waiting_writers -= 1;
+                state.writer = true;
+            }
+        }
+    }
+
+    fn release(&self, mode: LockMode) {
+        let mut state = self.state();
+        match mode {
+            LockMode::Shared => state.readers -= 1,
+            LockMode::Exclusive => state.writer = false,
+        }
+        self.released.notify_all();
+    }
+}
+
+thread_local! {
+    /// wallet locks held by the current thread: address of the lock -> (mode, depth)
+    static HELD_LOCKS: RefCell<HashMap<usize, (LockMode, usize)>> = RefCell::new(HashMap::new());
+}
+
+/// Shared / exclusive locks of the wallets (by name); the clones share the locks
+#[derive(Debug, Clone, Default)]
+pub struct WalletLockMap {
+    locks: Arc<Mutex<HashMap<String, Arc<WalletLock>>>>,
+}
+
+impl WalletLockMap {
+    /// Creates a new set of wallet locks
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn lock(&self, name: &str) -> Arc<WalletLock> {
+        self.locks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(name.to_owned())
+            .or_default()
+            .clone()
+    }
+
+    /// Waits for a shared guard of the wallet (for reading it)
+    pub fn shared(&self, name: &str) -> WalletGuard {
+        WalletGuard::acquire(self.lock(name), LockMode::Shared)
+    }
+
+    /// Waits for an exclusive guard of the wallet (for modifying it)
+    ///
+    /// # Panics
+    ///
+    /// If the current thread holds a shared guard of the wallet
+    pub fn exclusive(&self, name: &str) -> WalletGuard {
+        WalletGuard::acquire(self.lock(name), LockMode::Exclusive)
+    }
+
+    /// Waits for exclusive guards of several wallets. They are taken in the order of the wallet
+    /// names, so concurrent callers can't deadlock (as long as they don't hold other guards).
+    pub fn exclusive_many(&self, names: &[&str]) -> Vec<WalletGuard> {
+        let mut names = names.to_vec();
+        names.sort_unstable();
+        names.dedup();
+        names.into_iter().map(|name| self.exclusive(name)).collect()
+    }
+}
+
+/// Guard of a wallet lock, released when dropped (it can't be sent to another thread)
+#[must_use]
+#[derive(Debug)]
+pub struct WalletGuard {
+    lock: Arc<WalletLock>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl WalletGuard {
+    fn key(lock: &Arc<WalletLock>) -> usize {
+        Arc::as_ptr(lock) as usize
+    }
+
+    fn acquire(lock: Arc<WalletLock>, mode: LockMode) -> Self {
+        let key = Self::key(&lock);
+        let reentrant = HELD_LOCKS.with(|held| match held.borrow_mut().get_mut(&key) {
+            Some((held_mode, depth)) => {
+                assert!(
+                    !(*held_mode == LockMode::Shared && mode == LockMode::Exclusive),
+                    "shared wallet lock can't be upgraded to an exclusive one"
+                );
+                *depth += 1;
+                true
+            }
+            None => false,
+        });
+        if !reentrant {
+            lock.acquire(mode);
+            HELD_LOCKS.with(|held| held.borrow_mut().insert(key, (mode, 1)));
+        }
+        WalletGuard {
+            lock,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl Drop for WalletGuard {
+    fn drop(&mut self) {
+        let key = Self::key(&self.lock);
+        let released = HELD_LOCKS.with(|held| {
+            let mut held = held.borrow_mut();
+            let (mode, depth) = held.get_mut(&key).expect("wallet lock held by the thread");
+            *depth -= 1;
+            if *depth == 0 {
+                let mode = *mode;
+                held.remove(&key);
+                Some(mode)
+            } else {
+                None
+            }
+        });
+        if let Some(mode) = released {
+            self.lock.release(mode);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    #[test]
+    fn check_exclusive_guards_serialize_writers() {
+        let locks = WalletLockMap::new();
+        let counter = Arc::new(AtomicUsize::new(0));
+        let threads = (0..8)
+            .map(|_| {
+                let locks = locks.clone();
+                let counter = counter.clone();
+                thread::spawn(move || {
+                    for _ in 0..100 {
+                        let _guard = locks.exclusive("name");
+                        // a non-atomic read-modify-write, only correct under the exclusive guard
+                        let value = counter.load(Ordering::SeqCst);
+                        thread::yield_now();
+                        counter.store(value + 1, Ordering::SeqCst);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(800, counter.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn check_shared_guards_coexist() {
+        let locks = WalletLockMap::new();
+        let _guard = locks.shared("name");
+        let other = locks.clone();
+        // another thread can read the same wallet, and write another one
+        thread::spawn(move || {
+            let _shared = other.shared("name");
+            let _exclusive = other.exclusive("other");
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn check_guards_are_reentrant() {
+        let locks = WalletLockMap::new();
+        let outer = locks.exclusive("name");
+        let inner = locks.exclusive("name");
+        let shared = locks.shared("name");
+        drop(outer);
+        drop(shared);
+        drop(inner);
+
+        // released: another thread can take it
+        let other = locks.clone();
+        thread::spawn(move || {
+            let _guard = other.exclusive("name");
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "upgraded")]
+    fn check_shared_guard_cant_be_upgraded() {
+        let locks = WalletLockMap::new();
+        let _shared = locks.shared("name");
+        let _exclusive = locks.exclusive("name");
+    }
+
+    #[test]
+    fn check_multiple_wallets_are_locked_in_order() {
+        let locks = WalletLockMap::new();
+        let threads = [["a", "b"], ["b", "a"]]
+            .iter()
+            .map(|names| {
+                let locks = locks.clone();
+                let names = *names;
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        let guards = locks.exclusive_many(&names);
+                        assert_eq!(2, guards.len());
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(1, locks.exclusive_many(&["a", "a"]).len());
+    }
+}
@@ -0,0 +1,585 @@
+//! Simplified high-level client for payments and staking on Thaler Experimental Network
+//!
+//! `ChainClient` wires up storage, Tendermint client, signer, fee policy and transaction
+//! obfuscation, so sending a payment doesn't require picking type parameters of
+//! `DefaultWalletClient` and `DefaultNetworkOpsClient`. It only uses the public `WalletClient`
+//! and `NetworkOpsClient` traits, which remain reachable through `wallet_client()` and
+//! `network_ops_client()` for anything not covered here.
+//!
+//! ```no_run
+//! use chain_core::init::coin::Coin;
+//! use client_network::ChainClient;
+//! use secstr::SecUtf8;
+//!
+//! let client = ChainClient::connect("ws://localhost:26657/websocket", ".storage")?;
+//! let passphrase = SecUtf8::from("correct horse battery staple");
+//! let enckey = client.create_wallet("Default", &passphrase)?;
+//!
+//! let address = client.address("Default", &enckey)?;
+//! println!("send funds to {}", address);
+//!
+//! let balance = client.balance("Default", &enckey)?;
+//! if balance.available > Coin::unit() {
+//!     let tx_id = client.send("Default", &enckey, address, Coin::unit())?;
+//!     println!("sent {}", hex::encode(tx_id));
+//! }
+//! # Ok::<(), client_network::ChainClientError>(())
+//! ```
+use std::collections::BTreeSet;
+use std::fmt;
+
+use chain_core::init::coin::Coin;
+use chain_core::init::network::{get_network_id, init_chain_id};
+use chain_core::state::account::{StakedStateAddress, StakedStateOpAttributes};
+use chain_core::tx::data::address::ExtendedAddr;
+use chain_core::tx::data::attribute::TxAttributes;
+use chain_core::tx::data::input::TxoPointer;
+use chain_core::tx::data::output::TxOut;
+use chain_core::tx::data::TxId;
+use chain_core::tx::TxAux;
+use client_common::storage::SledStorage;
+use client_common::tendermint::{Client, WebsocketRpcClient};
+use client_common::{Error, ErrorKind, SecKey};
+use client_core::coin_selection::BranchAndBound;
+use client_core::hd_wallet::HardwareKind;
+use client_core::service::HwKeyService;
+use client_core::signer::WalletSignerManager;
+use client_core::transaction_builder::{DefaultWalletTransactionBuilder, FeeTip};
+use client_core::types::{WalletBalance, WalletKind};
+use client_core::wallet::{DefaultWalletClient, WalletLockMap};
+use client_core::WalletClient;
+use secstr::SecUtf8;
+
+use crate::network_ops::{DefaultNetworkOpsClient, NetworkOpsClient};
+
+/// Number of blocks `DefaultWalletClient` waits for when committing a transaction
+const BLOCK_HEIGHT_ENSURE: u64 = 50;
+
+/// Errors returned by `ChainClient`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainClientError {
+    /// Given input is invalid (malformed address, insufficient funds, unknown wallet, ...)
+    InvalidInput(String),
+    /// Wrong passphrase / enckey, or the operation is not permitted
+    PermissionDenied(String),
+    /// Communication with the node failed
+    Network(String),
+    /// Reading or writing local storage failed
+    Storage(String),
+    /// Transaction or staking state failed validation
+    Validation(String),
+    /// Any other failure
+    Other(String),
+}
+
+impl fmt::Display for ChainClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainClientError::InvalidInput(message) => write!(f, "Invalid input: {}", message),
+            ChainClientError::PermissionDenied(message) => {
+                write!(f, "Permission denied: {}", message)
+            }
+            ChainClientError::Network(message) => write!(f, "Network error: {}", message),
+            ChainClientError::Storage(message) => write!(f, "Storage error: {}", message),
+            ChainClientError::Validation(message) => write!(f, "Validation error: {}", message),
+            ChainClientError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ChainClientError {}
+
+impl From<Error> for ChainClientError {
+    fn from(error: Error) -> Self {
+        let message = error.to_string();
+        match error.kind() {
+            ErrorKind::InvalidInput
+            | ErrorKind::IllegalInput
+            | ErrorKind::DeserializationError
+            | ErrorKind::SelfTransfer
+            | ErrorKind::AlreadyExists
+            | ErrorKind::SessionNotFound
+            | ErrorKind::InsufficientBalance => ChainClientError::InvalidInput(message),
+            ErrorKind::PermissionDenied | ErrorKind::DecryptionError | ErrorKind::OnlyWatchOnly => {
+                ChainClientError::PermissionDenied(message)
+            }
+            ErrorKind::ConnectionError
+            | ErrorKind::TendermintRpcError
+            | ErrorKind::BlockPruned { .. }
+            | ErrorKind::ChainIdMismatch => ChainClientError::Network(message),
+            ErrorKind::StorageError | ErrorKind::IoError => ChainClientError::Storage(message),
+            ErrorKind::ValidationError | ErrorKind::VerifyError | ErrorKind::HistoryMismatch => {
+                ChainClientError::Validation(message)
+            }
+            _ => ChainClientError::Other(message),
+        }
+    }
+}
+
+/// Result type of `ChainClient`
+pub type ChainClientResult<T> = std::result::Result<T, ChainClientError>;
+
+/// High-level client for payments and staking
+pub struct ChainClient {
+    wallet_client: Box<dyn WalletClient>,
+    network_ops_client: Box<dyn NetworkOpsClient>,
+    network_id: u8,
+    verify_staking: bool,
+}
+
+impl ChainClient {
+    /// Connects to Tendermint websocket endpoint at `node_url` (e.g.
+    /// `ws://localhost:26657/websocket`) and opens (or creates) wallet storage at `storage_path`.
+    ///
+    /// Network is initialized from chain ID in node's genesis.
+    pub fn connect(node_url: &str, storage_path: &str) -> ChainClientResult<Self> {
+        let storage = SledStorage::new(storage_path)?;
+        let tendermint_client = WebsocketRpcClient::new(node_url)?;
+        let genesis = tendermint_client.genesis()?;
+        init_chain_id(genesis.chain_id.as_str());
+
+        let hw_key_service = HwKeyService::default();
+        let wallet_locks = WalletLockMap::new();
+        let signer_manager = WalletSignerManager::new(storage.clone(), hw_key_service.clone())
+            .with_wallet_locks(wallet_locks.clone());
+        let wallet_client = DefaultWalletClient::new(
+            storage.clone(),
+            tendermint_client.clone(),
+            DefaultWalletTransactionBuilder::new(
+                signer_manager.clone(),
+                tendermint_client.clone(),
+                tendermint_client.clone(),
+                BranchAndBound::default(),
+            ),
+            Some(BLOCK_HEIGHT_ENSURE),
+            hw_key_service,
+        )
+        .with_wallet_locks(wallet_locks);
+        let network_ops_client = DefaultNetworkOpsClient::new(
+            wallet_client.clone(),
+            signer_manager,
+            tendermint_client.clone(),
+            tendermint_client.clone(),
+            tendermint_client,
+        );
+
+        Ok(Self::new(
+            Box::new(wallet_client),
+            Box::new(network_ops_client),
+            get_network_id(),
+        ))
+    }
+
+    /// Creates a client on top of already configured wallet and network operations clients
+    pub fn new(
+        wallet_client: Box<dyn WalletClient>,
+        network_ops_client: Box<dyn NetworkOpsClient>,
+        network_id: u8,
+    ) -> Self {
+        Self {
+            wallet_client,
+            network_ops_client,
+            network_id,
+            verify_staking: true,
+        }
+    }
+
+    /// Sets whether staking states fetched from the node are verified against the synced
+    /// staking root (enabled by default)
+    pub fn with_staking_verification(mut self, verify_staking: bool) -> Self {
+        self.verify_staking = verify_staking;
+        self
+    }
+
+    /// Returns underlying wallet client
+    pub fn wallet_client(&self) -> &dyn WalletClient {
+        self.wallet_client.as_ref()
+    }
+
+    /// Returns underlying network operations client
+    pub fn network_ops_client(&self) -> &dyn NetworkOpsClient {
+        self.network_ops_client.as_ref()
+    }
+
+    /// Creates a new basic wallet and returns its enckey (used to authorize all the other calls)
+    pub fn create_wallet(&self, name: &str, passphrase: &SecUtf8) -> ChainClientResult<SecKey> {
+        let (enckey, _) = self.wallet_client.new_wallet(
+            name,
+            passphrase,
+            WalletKind::Basic,
+            HardwareKind::LocalOnly,
+            None,
+        )?;
+        self.wallet_client.flush_database()?;
+        Ok(enckey)
+    }
+
+    /// Generates a new transfer address
+    pub fn address(&self, name: &str, enckey: &SecKey) -> ChainClientResult<ExtendedAddr> {
+        let address = self.wallet_client.new_transfer_address(name, enckey)?;
+        self.wallet_client.flush_database()?;
+        Ok(address)
+    }
+
+    /// Generates a new staking address
+    pub fn staking_address(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+    ) -> ChainClientResult<StakedStateAddress> {
+        let address = self.wallet_client.new_staking_address(name, enckey)?;
+        self.wallet_client.flush_database()?;
+        Ok(address)
+    }
+
+    /// Returns balance of wallet (as of the last sync)
+    pub fn balance(&self, name: &str, enckey: &SecKey) -> ChainClientResult<WalletBalance> {
+        Ok(self.wallet_client.balance(name, enckey)?)
+    }
+
+    /// Sends `amount` to `to_address` and returns id of the broadcasted transaction
+    pub fn send(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        to_address: ExtendedAddr,
+        amount: Coin,
+    ) -> ChainClientResult<TxId> {
+        let result = self.wallet_client.send_to_address(
+            name,
+            enckey,
+            amount,
+            to_address,
+            None,
+            &mut BTreeSet::new(),
+            self.network_id,
+            false,
+            FeeTip::default(),
+            None,
+        )?;
+        self.wallet_client.flush_database()?;
+        Ok(result.tx_id)
+    }
+
+    /// Deposits unspent outputs `inputs` to `to_address` as bonded stake and returns id of the
+    /// broadcasted transaction
+    pub fn stake_deposit(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        to_address: StakedStateAddress,
+        inputs: Vec<TxoPointer>,
+    ) -> ChainClientResult<TxId> {
+        if !self
+            .wallet_client
+            .has_unspent_transactions(name, enckey, &inputs)?
+        {
+            return Err(ChainClientError::InvalidInput(
+                "Given transaction inputs are not present in unspent transactions (synchronizing your wallet may help)".to_owned(),
+            ));
+        }
+        let transactions = inputs
+            .into_iter()
+            .map(|input| {
+                let output = self.wallet_client.output(name, enckey, &input)?;
+                Ok((input, output))
+            })
+            .collect::<ChainClientResult<Vec<(TxoPointer, TxOut)>>>()?;
+
+        let (transaction, tx_pending) = self
+            .network_ops_client
+            .create_deposit_bonded_stake_transaction(
+                name,
+                enckey,
+                transactions,
+                to_address,
+                StakedStateOpAttributes::new(self.network_id),
+                self.verify_staking,
+            )?;
+        self.wallet_client.broadcast_transaction(&transaction)?;
+        self.wallet_client
+            .update_tx_pending_state(name, enckey, &transaction, tx_pending)?;
+        self.wallet_client.flush_database()?;
+        Ok(transaction.tx_id())
+    }
+
+    /// Unbonds `amount` of bonded stake of `address` and returns id of the broadcasted transaction
+    pub fn unbond(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        address: StakedStateAddress,
+        amount: Coin,
+    ) -> ChainClientResult<TxId> {
+        let transaction = self.network_ops_client.create_unbond_stake_transaction(
+            name,
+            enckey,
+            address,
+            amount,
+            StakedStateOpAttributes::new(self.network_id),
+            self.verify_staking,
+        )?;
+        self.broadcast_staking_transaction(name, &address, &transaction)?;
+        Ok(transaction.tx_id())
+    }
+
+    /// Withdraws all unbonded stake of `from_address` to `to_address` and returns id of the
+    /// broadcasted transaction
+    pub fn withdraw(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        from_address: StakedStateAddress,
+        to_address: ExtendedAddr,
+    ) -> ChainClientResult<TxId> {
+        let view_key = self.wallet_client.view_key(name, enckey)?;
+        let attributes = TxAttributes::builder(self.network_id)
+            .allow_view((&view_key).into())
+            .build()
+            .map_err(|e| ChainClientError::InvalidInput(e.to_string()))?;
+
+        let (transaction, tx_pending) = self
+            .network_ops_client
+            .create_withdraw_all_unbonded_stake_transaction(
+                name,
+                enckey,
+                &from_address,
+                to_address,
+                attributes,
+                self.verify_staking,
+            )?;
+        self.broadcast_staking_transaction(name, &from_address, &transaction)?;
+        self.wallet_client
+            .update_tx_pending_state(name, enckey, &transaction, tx_pending)?;
+        self.wallet_client.flush_database()?;
+        Ok(transaction.tx_id())
+    }
+
+    fn broadcast_staking_transaction(
+        &self,
+        name: &str,
+        address: &StakedStateAddress,
+        transaction: &TxAux,
+    ) -> ChainClientResult<()> {
+        if let Err(err) = self.wallet_client.broadcast_transaction(transaction) {
+            // locally tracked nonce may be ahead of the chain now
+            let _ = self
+                .network_ops_client
+                .reconcile_nonce(name, address, self.verify_staking);
+            return Err(err.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::convert::TryFrom;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use parity_scale_codec::Encode;
+
+    use chain_core::state::account::StakedState;
+    use chain_core::state::ChainState;
+    use chain_core::tx::fee::{LinearFee, Milli};
+    use client_common::cipher::MockAbciTransactionObfuscation;
+    use client_common::storage::MemoryStorage;
+    use client_common::tendermint::mock;
+    use client_common::tendermint::types::*;
+    use client_common::Result;
+    use client_core::coin_selection::LargestFirst;
+    use client_core::service::{WalletStateMemento, WalletStateService};
+
+    #[derive(Default, Clone)]
+    struct MockClient {
+        broadcasted: Arc<AtomicUsize>,
+    }
+
+    impl Client for MockClient {
+        fn genesis(&self) -> Result<Genesis> {
+            Ok(mock::genesis())
+        }
+
+        fn status(&self) -> Result<StatusResponse> {
+            Ok(mock::status_response())
+        }
+
+        fn block(&self, _: u64) -> Result<Block> {
+            unreachable!()
+        }
+
+        fn block_batch<'a, T: Iterator<Item = &'a u64>>(&self, _heights: T) -> Result<Vec<Block>> {
+            unreachable!()
+        }
+
+        fn block_results(&self, _height: u64) -> Result<BlockResultsResponse> {
+            unreachable!()
+        }
+
+        fn block_results_batch<'a, T: Iterator<Item = &'a u64>>(
+            &self,
+            _heights: T,
+        ) -> Result<Vec<BlockResultsResponse>> {
+            unreachable!()
+        }
+
+        fn broadcast_transaction(&self, _: &[u8]) -> Result<BroadcastTxResponse> {
+            self.broadcasted.fetch_add(1, Ordering::SeqCst);
+            Ok(BroadcastTxResponse {
+                code: tendermint::abci::Code::Ok,
+                data: tendermint::abci::Data::from(vec![]),
+                log: tendermint::abci::Log::from(""),
+                hash: tendermint::abci::transaction::Hash::new([0; 32]),
+            })
+        }
+
+        fn query(
+            &self,
+            path: &str,
+            data: &[u8],
+            _height: Option<Height>,
+            _prove: bool,
+        ) -> Result<AbciQuery> {
+            assert_eq!("staking", path);
+            let staked_state = StakedState::new(
+                0,
+                Coin::new(1000).unwrap(),
+                Coin::new(500).unwrap(),
+                0,
+                StakedStateAddress::try_from(data).unwrap(),
+                None,
+            );
+
+            Ok(AbciQuery {
+                value: Some(staked_state).encode(),
+                ..Default::default()
+            })
+        }
+
+        fn query_state_batch<T: Iterator<Item = u64>>(
+            &self,
+            _heights: T,
+        ) -> Result<Vec<ChainState>> {
+            unreachable!()
+        }
+    }
+
+    fn mock_chain_client(storage: MemoryStorage, tendermint_client: MockClient) -> ChainClient {
+        let fee_algorithm =
+            LinearFee::new(Milli::try_new(0, 0).unwrap(), Milli::try_new(0, 0).unwrap());
+        let obfuscation = MockAbciTransactionObfuscation::new(tendermint_client.clone());
+        let hw_key_service = HwKeyService::default();
+        let signer_manager = WalletSignerManager::new(storage.clone(), hw_key_service.clone());
+        let wallet_client = DefaultWalletClient::new(
+            storage,
+            tendermint_client.clone(),
+            DefaultWalletTransactionBuilder::new(
+                signer_manager.clone(),
+                fee_algorithm.clone(),
+                obfuscation.clone(),
+                LargestFirst,
+            ),
+            None,
+            hw_key_service,
+        );
+        let network_ops_client = DefaultNetworkOpsClient::new(
+            wallet_client.clone(),
+            signer_manager,
+            tendermint_client,
+            fee_algorithm,
+            obfuscation,
+        );
+
+        ChainClient::new(Box::new(wallet_client), Box::new(network_ops_client), 0)
+            .with_staking_verification(false)
+    }
+
+    #[test]
+    fn check_chain_client_flow() {
+        let name = "name";
+        let storage = MemoryStorage::default();
+        let tendermint_client = MockClient::default();
+        let client = mock_chain_client(storage.clone(), tendermint_client.clone());
+
+        let enckey = client
+            .create_wallet(name, &SecUtf8::from("passphrase"))
+            .unwrap();
+        let address = client.address(name, &enckey).unwrap();
+        let staking_address = client.staking_address(name, &enckey).unwrap();
+        assert_eq!(Coin::zero(), client.balance(name, &enckey).unwrap().total);
+
+        let mut memento = WalletStateMemento::default();
+        memento.add_unspent_transaction(
+            TxoPointer::new([1; 32], 0),
+            TxOut::new(address.clone(), Coin::new(100).unwrap()),
+        );
+        memento.add_unspent_transaction(
+            TxoPointer::new([2; 32], 0),
+            TxOut::new(address.clone(), Coin::new(200).unwrap()),
+        );
+        WalletStateService::new(storage)
+            .apply_memento(name, &enckey, &memento)
+            .unwrap();
+        assert_eq!(
+            Coin::new(300).unwrap(),
+            client.balance(name, &enckey).unwrap().total
+        );
+
+        let deposit_tx_id = client
+            .stake_deposit(
+                name,
+                &enckey,
+                staking_address,
+                vec![TxoPointer::new([2; 32], 0)],
+            )
+            .unwrap();
+        let send_tx_id = client
+            .send(name, &enckey, address.clone(), Coin::new(50).unwrap())
+            .unwrap();
+        let unbond_tx_id = client
+            .unbond(name, &enckey, staking_address, Coin::new(100).unwrap())
+            .unwrap();
+        let withdraw_tx_id = client
+            .withdraw(name, &enckey, staking_address, address)
+            .unwrap();
+
+        let mut tx_ids = vec![deposit_tx_id, send_tx_id, unbond_tx_id, withdraw_tx_id];
+        tx_ids.sort();
+        tx_ids.dedup();
+        assert_eq!(4, tx_ids.len());
+        assert_eq!(4, tendermint_client.broadcasted.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn check_chain_client_errors() {
+        let name = "name";
+        let tendermint_client = MockClient::default();
+        let client = mock_chain_client(MemoryStorage::default(), tendermint_client.clone());
+
+        let enckey = client
+            .create_wallet(name, &SecUtf8::from("passphrase"))
+            .unwrap();
+        let other_enckey = client
+            .create_wallet("other", &SecUtf8::from("passphrase"))
+            .unwrap();
+        let staking_address = client.staking_address(name, &enckey).unwrap();
+
+        match client.balance(name, &other_enckey) {
+            Err(ChainClientError::PermissionDenied(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match client.stake_deposit(
+            name,
+            &enckey,
+            staking_address,
+            vec![TxoPointer::new([3; 32], 0)],
+        ) {
+            Err(ChainClientError::InvalidInput(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(0, tendermint_client.broadcasted.load(Ordering::SeqCst));
+    }
+}
@@ -6,7 +6,13 @@
 //!
 //! This crate provides and easy to use client for performing network operations on Thaler Experimental Network. Payments, on the
 //! other hand, are handled by `WalletClient` in `client-core` crate.
+//!
+//! For integrators who just need to send payments and manage stake, `ChainClient` offers both
+//! realms behind a single simplified API.
+pub mod chain_client;
 pub mod network_ops;
 
+#[doc(inline)]
+pub use self::chain_client::{ChainClient, ChainClientError, ChainClientResult};
 #[doc(inline)]
 pub use self::network_ops::NetworkOpsClient;
@@ -1,18 +1,25 @@
 //! Network operations on Thaler Experimental Network
 mod default_network_ops_client;
+mod nonce_manager;
+mod preflight;
 
 pub use self::default_network_ops_client::DefaultNetworkOpsClient;
+pub use self::nonce_manager::{NonceManager, NonceSource};
+pub use self::preflight::{PreflightCheck, PreflightCheckKind, PreflightReport};
 use chain_core::init::coin::Coin;
 use chain_core::state::account::{
-    CouncilNodeMeta, StakedState, StakedStateAddress, StakedStateOpAttributes,
+    CouncilNodeMeta, NodeEndpoint, NodeState, StakedState, StakedStateAddress,
+    StakedStateOpAttributes,
 };
 use chain_core::tx::data::address::ExtendedAddr;
 use chain_core::tx::data::attribute::TxAttributes;
 use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::data::output::TxOut;
+use chain_core::tx::data::TxId;
+use chain_core::tx::result::TxResult;
 use chain_core::tx::TxAux;
-use client_common::tendermint::types::{Genesis, StatusResponse};
-use client_common::{ErrorKind, Result, ResultExt, SecKey};
+use client_common::tendermint::types::{ChainStatus, Genesis, StatusResponse};
+use client_common::{Error, ErrorKind, Result, ResultExt, SecKey};
 use client_core::types::TransactionPending;
 
 /// Interface for performing network operations on Thaler Experimental Network
@@ -85,6 +92,49 @@ pub trait NetworkOpsClient: Send + Sync {
         verify_staking: bool,
     ) -> Result<TxAux>;
 
+    /// Creates a new transaction for replacing the endpoints announced by a council node
+    fn create_update_node_transaction(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        staking_account_address: StakedStateAddress,
+        attributes: StakedStateOpAttributes,
+        announced_endpoints: Vec<NodeEndpoint>,
+        verify_staking: bool,
+    ) -> Result<TxAux>;
+
+    /// Returns the endpoints announced by the council node of given staking address
+    fn get_announced_endpoints(
+        &self,
+        name: &str,
+        address: &StakedStateAddress,
+        verify: bool,
+    ) -> Result<Vec<NodeEndpoint>> {
+        match self.get_staked_state(name, address, verify)?.node_meta {
+            Some(NodeState::CouncilNode(validator)) => {
+                Ok(validator.council_node.announced_endpoints)
+            }
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "staking address is not a council node",
+            )),
+        }
+    }
+
+    /// Checks (without creating or broadcasting anything) whether a node join transaction with
+    /// given node metadata would be accepted: chain id, staking key, bonded amount, jailing,
+    /// nonce, consensus key and keypackage lifetime. Failed checks are reported (with
+    /// remediation hints), errors are only returned when the checks can't be done.
+    fn preflight_node_join(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        staking_account_address: StakedStateAddress,
+        attributes: &StakedStateOpAttributes,
+        node_metadata: &CouncilNodeMeta,
+        verify_staking: bool,
+    ) -> Result<PreflightReport>;
+
     /// Returns staked stake corresponding to given address
     fn get_staked_state(
         &self,
@@ -106,9 +156,29 @@ pub trait NetworkOpsClient: Send + Sync {
         verify: bool,
     ) -> Result<Option<StakedState>>;
 
+    /// Reconciles locally tracked nonce of given staking address with the chain. This should be called
+    /// when a transaction created by this client is rejected by the chain. Does nothing if nonces are
+    /// not tracked locally.
+    fn reconcile_nonce(
+        &self,
+        _name: &str,
+        _address: &StakedStateAddress,
+        _verify: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
     /// Return genesis of tendermint
     fn get_genesis(&self) -> Result<Genesis>;
 
     /// Return status response
     fn get_status(&self) -> Result<StatusResponse>;
+
+    /// Return the latest and the earliest available (not pruned) block heights of the node (and
+    /// the wallets whose history mismatched)
+    fn get_chain_status(&self) -> Result<ChainStatus>;
+
+    /// Return the result of the delivered transaction from the tx result index of the node
+    /// (`None` if the node didn't index it)
+    fn get_tx_result(&self, txid: &TxId) -> Result<Option<TxResult>>;
 }
@@ -1,22 +1,32 @@
 use parity_scale_codec::Decode;
 
+use crate::network_ops::preflight::{chain_hex_id, keypackage_lifetime, VALIDATOR_OWNER_PATH};
+use crate::network_ops::{NonceSource, PreflightCheck, PreflightCheckKind, PreflightReport};
 use crate::NetworkOpsClient;
 use chain_core::common::Timespec;
 use chain_core::init::coin::{sum_coins, Coin};
 use chain_core::state::account::{
-    CouncilNodeMeta, DepositBondTx, NodeMetadata, StakedState, StakedStateAddress,
-    StakedStateOpAttributes, StakedStateOpWitness, UnbondTx, UnjailTx, WithdrawUnbondedTx,
+    CouncilNodeMeta, DepositBondTx, NodeEndpoint, NodeMetadata, Nonce, StakedState,
+    StakedStateAddress, StakedStateOpAttributes, StakedStateOpWitness, UnbondTx, UnjailTx,
+    WithdrawUnbondedTx,
 };
-use chain_core::state::validator::NodeJoinRequestTx;
+use chain_core::state::tendermint::TendermintValidatorAddress;
+use chain_core::state::validator::{NodeJoinRequestTx, UpdateNodeTx};
 use chain_core::tx::data::address::ExtendedAddr;
 use chain_core::tx::data::attribute::TxAttributes;
 use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::data::output::TxOut;
-use chain_core::tx::fee::FeeAlgorithm;
+use chain_core::tx::data::TxId;
+use chain_core::tx::fee::{solve_output_minus_fee, FeeAlgorithm, FeeSolverError};
+use chain_core::tx::result::TxResult;
 use chain_core::tx::{TxAux, TxPublicAux};
 use chain_storage::jellyfish::SparseMerkleProof;
-use chain_tx_validation::{check_inputs_basic, check_outputs_basic, verify_unjailed};
-use client_common::tendermint::types::{AbciQueryExt, Genesis, StatusResponse};
+use chain_tx_validation::{
+    check_inputs_basic, check_outputs_basic, verify_unbond_remainder, verify_unjailed,
+};
+use client_common::tendermint::types::{
+    AbciQueryExt, ChainStatus, Genesis, GenesisExt, StatusResponse,
+};
 use client_common::tendermint::Client;
 use client_common::{
     Error, ErrorKind, Result, ResultExt, SecKey, SignedTransaction, Storage, Transaction,
@@ -43,6 +53,7 @@ where
     client: C,
     fee_algorithm: F,
     transaction_cipher: E,
+    nonce_source: NonceSource,
 }
 
 impl<W, S, C, F, E> DefaultNetworkOpsClient<W, S, C, F, E>
@@ -67,31 +78,192 @@ where
             client,
             fee_algorithm,
             transaction_cipher,
+            nonce_source: NonceSource::default(),
         }
     }
 
+    /// Sets the source of nonces used while creating staking transactions
+    pub fn with_nonce_source(mut self, nonce_source: NonceSource) -> Self {
+        self.nonce_source = nonce_source;
+        self
+    }
+
     /// Returns current underlying wallet client
     pub fn get_wallet_client(&self) -> &W {
         &self.wallet_client
     }
 
-    /// Calculate the withdraw unbounded fee
-    fn calculate_fee(&self, outputs: Vec<TxOut>, attributes: TxAttributes) -> Result<Coin> {
-        let tx = WithdrawUnbondedTx::new(0, outputs, attributes);
-        // mock the signature
-        let dummy_signer = DummySigner();
-        let tx_aux = dummy_signer.mock_txaux_for_withdraw(tx);
-        let fee = self
-            .fee_algorithm
-            .calculate_for_txaux(&tx_aux)
-            .chain(|| {
-                (
-                    ErrorKind::IllegalInput,
-                    "Calculated fee is more than the maximum allowed value",
+    /// Builds a transaction with next nonce of given staking address
+    fn with_nonce<T, B>(
+        &self,
+        address: &StakedStateAddress,
+        chain_nonce: Nonce,
+        build: B,
+    ) -> Result<T>
+    where
+        B: FnOnce(Nonce) -> Result<T>,
+    {
+        match self.nonce_source {
+            NonceSource::Chain => build(chain_nonce),
+            NonceSource::Managed(ref nonce_manager) => {
+                nonce_manager.with_next_nonce(address, chain_nonce, build)
+            }
+        }
+    }
+
+    /// Nonce which would be used for the next transaction of given staking address
+    fn next_nonce(&self, address: &StakedStateAddress, chain_nonce: Nonce) -> Nonce {
+        match self.nonce_source {
+            NonceSource::Chain => chain_nonce,
+            NonceSource::Managed(ref nonce_manager) => match nonce_manager.next_nonce(address) {
+                Some(next_nonce) if next_nonce > chain_nonce => next_nonce,
+                _ => chain_nonce,
+            },
+        }
+    }
+
+    fn preflight_chain_id(
+        &self,
+        genesis: &Genesis,
+        attributes: &StakedStateOpAttributes,
+    ) -> PreflightCheck {
+        let kind = PreflightCheckKind::ChainId;
+        let chain_id = genesis.chain_id.as_str();
+        match chain_hex_id(chain_id) {
+            Some(hex_id) if hex_id == attributes.chain_hex_id => {
+                PreflightCheck::pass(kind, format!("Connected to chain {}", chain_id))
+            }
+            Some(hex_id) => PreflightCheck::fail(
+                kind,
+                format!(
+                    "Transaction would be signed for network id {:02X}, but the node is on chain {} (network id {:02X})",
+                    attributes.chain_hex_id, chain_id, hex_id
+                ),
+                format!("Configure the client with chain id {}", chain_id),
+            ),
+            None => PreflightCheck::fail(
+                kind,
+                format!("Chain id {} doesn't end with a network id", chain_id),
+                "Check that the client is connected to a node of the right network",
+            ),
+        }
+    }
+
+    fn preflight_staking_key(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        address: &StakedStateAddress,
+    ) -> Result<PreflightCheck> {
+        let kind = PreflightCheckKind::StakingKey;
+        let found = match address {
+            StakedStateAddress::BasicRedeem(ref redeem_address) => self
+                .wallet_client
+                .find_staking_key(name, enckey, redeem_address)?
+                .is_some(),
+        };
+        if found {
+            Ok(PreflightCheck::pass(
+                kind,
+                format!("Staking address {} belongs to the wallet", address),
+            ))
+        } else {
+            Ok(PreflightCheck::fail(
+                kind,
+                format!("Staking address {} is not in wallet {}", address, name),
+                "Use a staking address of the wallet (or restore the wallet it was created in)",
+            ))
+        }
+    }
+
+    fn preflight_consensus_key(
+        &self,
+        address: &StakedStateAddress,
+        node_metadata: &CouncilNodeMeta,
+    ) -> Result<PreflightCheck> {
+        let kind = PreflightCheckKind::ConsensusKey;
+        let validator_address = TendermintValidatorAddress::from(&node_metadata.consensus_pubkey);
+        let bytes = self
+            .client
+            .query(
+                VALIDATOR_OWNER_PATH,
+                &<[u8; 20]>::from(&validator_address),
+                None,
+                false,
+            )?
+            .bytes();
+        let owner = <Option<StakedStateAddress>>::decode(&mut bytes.as_slice()).err_kind(
+            ErrorKind::DeserializationError,
+            || {
+                format!(
+                    "Cannot deserialize owner of validator address: {}",
+                    validator_address
                 )
-            })?
-            .to_coin();
-        Ok(fee)
+            },
+        )?;
+        Ok(match owner {
+            Some(ref owner) if owner != address => PreflightCheck::fail(
+                kind,
+                format!(
+                    "Consensus key (validator address {}) is already used by staking address {}",
+                    validator_address, owner
+                ),
+                "Generate a new consensus key for the node (tendermint `priv_validator_key.json`)",
+            ),
+            _ => PreflightCheck::pass(
+                kind,
+                format!(
+                    "Consensus key (validator address {}) isn't used by another staking address",
+                    validator_address
+                ),
+            ),
+        })
+    }
+
+    fn preflight_keypackage(
+        &self,
+        genesis: &Genesis,
+        node_metadata: &CouncilNodeMeta,
+    ) -> Result<PreflightCheck> {
+        let kind = PreflightCheckKind::Keypackage;
+        let lifetime = match keypackage_lifetime(&node_metadata.node_info.confidential_init) {
+            Ok(lifetime) => lifetime,
+            Err(e) => {
+                return Ok(PreflightCheck::fail(
+                    kind,
+                    format!("Invalid keypackage: {}", e),
+                    "Generate a new keypackage with the enclave of the node",
+                ))
+            }
+        };
+        let block_time = self.get_last_block_time()?;
+        // unbonding period is the same as max evidence age
+        let unbonding_period = genesis.trusting_period().as_secs();
+        let required_until = block_time.saturating_add(unbonding_period);
+        Ok(if lifetime.not_before > block_time {
+            PreflightCheck::fail(
+                kind,
+                format!(
+                    "Keypackage is not valid before {} (last block time: {})",
+                    lifetime.not_before, block_time
+                ),
+                "Check the clock of the node and generate a new keypackage",
+            )
+        } else if lifetime.not_after < required_until {
+            PreflightCheck::fail(
+                kind,
+                format!(
+                    "Keypackage expires at {}, before the end of unbonding period ({})",
+                    lifetime.not_after, required_until
+                ),
+                "Generate a new keypackage with the enclave of the node",
+            )
+        } else {
+            PreflightCheck::pass(
+                kind,
+                format!("Keypackage is valid until {}", lifetime.not_after),
+            )
+        })
     }
 
     fn get_last_block_time(&self) -> Result<Timespec> {
@@ -202,47 +374,69 @@ where
                 format!("Failed to validate staking account: {}", e),
             )
         })?;
+        let minimum_bond_remainder = self
+            .client
+            .genesis()?
+            .app_state
+            .chain(|| (ErrorKind::InvalidInput, "Missing app state in genesis"))?
+            .get_network_parameters()
+            .get_minimum_bond_remainder();
+
+        self.with_nonce(&address, staked_state.nonce, |nonce| {
+            let transaction = UnbondTx::new(address, nonce, value, attributes);
+            let tx = Transaction::UnbondStakeTransaction(transaction.clone());
+
+            let public_key = match address {
+                StakedStateAddress::BasicRedeem(ref redeem_address) => self
+                    .wallet_client
+                    .find_staking_key(name, enckey, redeem_address)?
+                    .chain(|| {
+                        (
+                            ErrorKind::InvalidInput,
+                            "Address not found in current wallet",
+                        )
+                    })?,
+            };
+            let sign_key = self.wallet_client.sign_key(name, enckey, &public_key)?;
 
-        let nonce = staked_state.nonce;
+            let signature = sign_key.sign(&tx).map(StakedStateOpWitness::new)?;
 
-        let transaction = UnbondTx::new(address, nonce, value, attributes);
-        let tx = Transaction::UnbondStakeTransaction(transaction.clone());
+            let txaux = TxAux::PublicTx(TxPublicAux::UnbondStakeTx(transaction, signature));
 
-        let public_key = match address {
-            StakedStateAddress::BasicRedeem(ref redeem_address) => self
-                .wallet_client
-                .find_staking_key(name, enckey, redeem_address)?
+            let fee = self
+                .fee_algorithm
+                .calculate_for_txaux(&txaux)
                 .chain(|| {
                     (
-                        ErrorKind::InvalidInput,
-                        "Address not found in current wallet",
+                        ErrorKind::IllegalInput,
+                        "Calculated fee is more than the maximum allowed value",
                     )
-                })?,
-        };
-        let sign_key = self.wallet_client.sign_key(name, enckey, &public_key)?;
-
-        let signature = sign_key.sign(&tx).map(StakedStateOpWitness::new)?;
-
-        let txaux = TxAux::PublicTx(TxPublicAux::UnbondStakeTx(transaction, signature));
-
-        let fee = self
-            .fee_algorithm
-            .calculate_for_txaux(&txaux)
-            .chain(|| {
-                (
-                    ErrorKind::IllegalInput,
-                    "Calculated fee is more than the maximum allowed value",
+                })?
+                .to_coin();
+            if staked_state.bonded < (value + fee).unwrap() {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "Staking account does not have enough coins to unbond (synchronizing your wallet may help)",
+                ));
+            }
+            verify_unbond_remainder(
+                &staked_state,
+                (value + fee).unwrap(),
+                minimum_bond_remainder,
+            )
+            .map_err(|_| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Unbonding would leave less than the minimum bond remainder ({}) bonded; unbond everything ({}) instead",
+                        minimum_bond_remainder,
+                        (staked_state.bonded - fee).unwrap_or_default(),
+                    ),
                 )
-            })?
-            .to_coin();
-        if staked_state.bonded < (value + fee).unwrap() {
-            return Err(Error::new(
-                ErrorKind::InvalidInput,
-                "Staking account does not have enough coins to unbond (synchronizing your wallet may help)",
-            ));
-        }
+            })?;
 
-        Ok(txaux)
+            Ok(txaux)
+        })
     }
 
     fn create_withdraw_unbonded_stake_transaction(
@@ -285,28 +479,28 @@ where
             ));
         }
 
-        let nonce = staked_state.nonce;
-
-        let transaction = WithdrawUnbondedTx::new(nonce, outputs, attributes);
-        let tx = Transaction::WithdrawUnbondedStakeTransaction(transaction.clone());
-
-        let public_key = match from_address {
-            StakedStateAddress::BasicRedeem(ref redeem_address) => self
-                .wallet_client
-                .find_staking_key(name, enckey, redeem_address)?
-                .chain(|| {
-                    (
-                        ErrorKind::InvalidInput,
-                        "Address not found in current wallet",
-                    )
-                })?,
-        };
-        let sign_key = self.wallet_client.sign_key(name, enckey, &public_key)?;
-        let signature = sign_key.sign(&tx).map(StakedStateOpWitness::new)?;
+        let tx_aux = self.with_nonce(from_address, staked_state.nonce, |nonce| {
+            let transaction = WithdrawUnbondedTx::new(nonce, outputs, attributes);
+            let tx = Transaction::WithdrawUnbondedStakeTransaction(transaction.clone());
+
+            let public_key = match from_address {
+                StakedStateAddress::BasicRedeem(ref redeem_address) => self
+                    .wallet_client
+                    .find_staking_key(name, enckey, redeem_address)?
+                    .chain(|| {
+                        (
+                            ErrorKind::InvalidInput,
+                            "Address not found in current wallet",
+                        )
+                    })?,
+            };
+            let sign_key = self.wallet_client.sign_key(name, enckey, &public_key)?;
+            let signature = sign_key.sign(&tx).map(StakedStateOpWitness::new)?;
 
-        let signed_transaction =
-            SignedTransaction::WithdrawUnbondedStakeTransaction(transaction, signature);
-        let tx_aux = self.transaction_cipher.encrypt(signed_transaction)?;
+            let signed_transaction =
+                SignedTransaction::WithdrawUnbondedStakeTransaction(transaction, signature);
+            self.transaction_cipher.encrypt(signed_transaction)
+        })?;
         let block_height = match self.wallet_client.get_current_block_height() {
             Ok(h) => h,
             Err(e) if e.kind() == ErrorKind::PermissionDenied => 0, // to make unit test pass
@@ -337,33 +531,33 @@ where
             ));
         }
 
-        let nonce = staked_state.nonce;
-
-        let transaction = UnjailTx {
-            nonce,
-            address,
-            attributes,
-        };
-        let tx = Transaction::UnjailTransaction(transaction.clone());
-
-        let public_key = match address {
-            StakedStateAddress::BasicRedeem(ref redeem_address) => self
-                .wallet_client
-                .find_staking_key(name, enckey, redeem_address)?
-                .chain(|| {
-                    (
-                        ErrorKind::InvalidInput,
-                        "Address not found in current wallet",
-                    )
-                })?,
-        };
-        let sign_key = self.wallet_client.sign_key(name, enckey, &public_key)?;
-        let signature = sign_key.sign(&tx).map(StakedStateOpWitness::new)?;
-
-        Ok(TxAux::PublicTx(TxPublicAux::UnjailTx(
-            transaction,
-            signature,
-        )))
+        self.with_nonce(&address, staked_state.nonce, |nonce| {
+            let transaction = UnjailTx {
+                nonce,
+                address,
+                attributes,
+            };
+            let tx = Transaction::UnjailTransaction(transaction.clone());
+
+            let public_key = match address {
+                StakedStateAddress::BasicRedeem(ref redeem_address) => self
+                    .wallet_client
+                    .find_staking_key(name, enckey, redeem_address)?
+                    .chain(|| {
+                        (
+                            ErrorKind::InvalidInput,
+                            "Address not found in current wallet",
+                        )
+                    })?,
+            };
+            let sign_key = self.wallet_client.sign_key(name, enckey, &public_key)?;
+            let signature = sign_key.sign(&tx).map(StakedStateOpWitness::new)?;
+
+            Ok(TxAux::PublicTx(TxPublicAux::UnjailTx(
+                transaction,
+                signature,
+            )))
+        })
     }
 
     fn create_withdraw_all_unbonded_stake_transaction(
@@ -384,15 +578,26 @@ where
             )
         })?;
 
-        let temp_output =
-            TxOut::new_with_timelock(to_address.clone(), Coin::zero(), staked_state.unbonded_from);
-        let fee = self.calculate_fee(vec![temp_output], attributes.clone())?;
-        let amount = (staked_state.unbonded - fee).chain(|| {
-            (
-                ErrorKind::IllegalInput,
-                "Calculated fee is more than the unbonded amount",
-            )
-        })?;
+        // the single output carries the whole unbonded amount minus the fee of the transaction
+        let tx_template = |amount| {
+            let output =
+                TxOut::new_with_timelock(to_address.clone(), amount, staked_state.unbonded_from);
+            let tx = WithdrawUnbondedTx::new(0, vec![output], attributes.clone());
+            // mock the signature
+            DummySigner().mock_txaux_for_withdraw(tx)
+        };
+        let (amount, _) =
+            solve_output_minus_fee(staked_state.unbonded, &self.fee_algorithm, tx_template)
+                .map_err(|e| match e {
+                    FeeSolverError::InsufficientTotal => Error::new(
+                        ErrorKind::IllegalInput,
+                        "Calculated fee is more than the unbonded amount",
+                    ),
+                    e => Error::new(
+                        ErrorKind::IllegalInput,
+                        format!("Unable to calculate the withdrawn amount: {}", e),
+                    ),
+                })?;
         let outputs = vec![TxOut::new_with_timelock(
             to_address,
             amount,
@@ -434,32 +639,202 @@ where
             )
         })?;
 
-        let transaction = NodeJoinRequestTx {
-            nonce: staked_state.nonce,
-            address: staking_account_address,
-            attributes,
-            node_meta: NodeMetadata::CouncilNode(node_metadata),
-        };
-        let tx = Transaction::NodejoinTransaction(transaction.clone());
+        self.with_nonce(&staking_account_address, staked_state.nonce, |nonce| {
+            let transaction = NodeJoinRequestTx {
+                nonce,
+                address: staking_account_address,
+                attributes,
+                node_meta: NodeMetadata::CouncilNode(node_metadata),
+            };
+            let tx = Transaction::NodejoinTransaction(transaction.clone());
+
+            let public_key = match staking_account_address {
+                StakedStateAddress::BasicRedeem(ref redeem_address) => self
+                    .wallet_client
+                    .find_staking_key(name, enckey, redeem_address)?
+                    .chain(|| {
+                        (
+                            ErrorKind::InvalidInput,
+                            "Address not found in current wallet",
+                        )
+                    })?,
+            };
+            let sign_key = self.wallet_client.sign_key(name, enckey, &public_key)?;
+            let signature = sign_key.sign(&tx).map(StakedStateOpWitness::new)?;
+
+            Ok(TxAux::PublicTx(TxPublicAux::NodeJoinTx(
+                transaction,
+                signature,
+            )))
+        })
+    }
 
-        let public_key = match staking_account_address {
-            StakedStateAddress::BasicRedeem(ref redeem_address) => self
-                .wallet_client
-                .find_staking_key(name, enckey, redeem_address)?
+    fn create_update_node_transaction(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        staking_account_address: StakedStateAddress,
+        attributes: StakedStateOpAttributes,
+        announced_endpoints: Vec<NodeEndpoint>,
+        verify_staking: bool,
+    ) -> Result<TxAux> {
+        let staked_state = self.get_staked_state(name, &staking_account_address, verify_staking)?;
+
+        if !staked_state.has_council_node_meta() {
+            return Err(Error::new(
+                ErrorKind::IllegalInput,
+                "You can only update the endpoints of a council node (synchronizing your wallet may help)",
+            ));
+        }
+
+        self.with_nonce(&staking_account_address, staked_state.nonce, |nonce| {
+            let transaction = UpdateNodeTx::new(
+                nonce,
+                staking_account_address,
+                attributes,
+                announced_endpoints,
+            )
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+            let tx = Transaction::UpdateNodeTransaction(transaction.clone());
+
+            let public_key = match staking_account_address {
+                StakedStateAddress::BasicRedeem(ref redeem_address) => self
+                    .wallet_client
+                    .find_staking_key(name, enckey, redeem_address)?
+                    .chain(|| {
+                        (
+                            ErrorKind::InvalidInput,
+                            "Address not found in current wallet",
+                        )
+                    })?,
+            };
+            let sign_key = self.wallet_client.sign_key(name, enckey, &public_key)?;
+            let signature = sign_key.sign(&tx).map(StakedStateOpWitness::new)?;
+
+            let txaux = TxAux::PublicTx(TxPublicAux::UpdateNodeTx(transaction, signature));
+
+            // the fee is paid from the bonded amount
+            let fee = self
+                .fee_algorithm
+                .calculate_for_txaux(&txaux)
                 .chain(|| {
                     (
-                        ErrorKind::InvalidInput,
-                        "Address not found in current wallet",
+                        ErrorKind::IllegalInput,
+                        "Calculated fee is more than the maximum allowed value",
                     )
-                })?,
+                })?
+                .to_coin();
+            if staked_state.bonded < fee {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "Staking account does not have enough bonded coins to pay the fee (synchronizing your wallet may help)",
+                ));
+            }
+
+            Ok(txaux)
+        })
+    }
+
+    fn preflight_node_join(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        staking_account_address: StakedStateAddress,
+        attributes: &StakedStateOpAttributes,
+        node_metadata: &CouncilNodeMeta,
+        verify_staking: bool,
+    ) -> Result<PreflightReport> {
+        let genesis = self.client.genesis()?;
+        let required_stake = genesis
+            .app_state
+            .as_ref()
+            .chain(|| (ErrorKind::InvalidInput, "Missing app state in genesis"))?
+            .network_params
+            .required_council_node_stake;
+        let address = &staking_account_address;
+
+        let mut report = PreflightReport::default();
+        report.push(self.preflight_chain_id(&genesis, attributes));
+        report.push(self.preflight_staking_key(name, enckey, address)?);
+
+        let staked_state = self.get_staking(name, address, verify_staking)?;
+        let (bonded, chain_nonce) = staked_state
+            .as_ref()
+            .map(|staked_state| (staked_state.bonded, staked_state.nonce))
+            .unwrap_or((Coin::zero(), 0));
+
+        report.push(if bonded >= required_stake {
+            PreflightCheck::pass(
+                PreflightCheckKind::BondedAmount,
+                format!("Bonded amount {} is enough", bonded),
+            )
+        } else {
+            PreflightCheck::fail(
+                PreflightCheckKind::BondedAmount,
+                format!(
+                    "Bonded amount {} is less than the required council node stake {}",
+                    bonded, required_stake
+                ),
+                format!(
+                    "Deposit at least {} more to staking address {}",
+                    (required_stake - bonded).unwrap_or_default(),
+                    address
+                ),
+            )
+        });
+
+        report.push(match staked_state {
+            Some(ref staked_state) if verify_unjailed(staked_state).is_err() => {
+                PreflightCheck::fail(
+                    PreflightCheckKind::NotJailed,
+                    format!("Staking address {} is jailed", address),
+                    "Wait for the end of the jailing period and send an unjail transaction",
+                )
+            }
+            _ => PreflightCheck::pass(
+                PreflightCheckKind::NotJailed,
+                format!("Staking address {} is not jailed", address),
+            ),
+        });
+
+        // the latest nonce on chain (staked state above may be verified at the synced height)
+        let latest_nonce = if verify_staking {
+            self.get_staking(name, address, false)?
+                .map(|staked_state| staked_state.nonce)
+                .unwrap_or(0)
+        } else {
+            chain_nonce
         };
-        let sign_key = self.wallet_client.sign_key(name, enckey, &public_key)?;
-        let signature = sign_key.sign(&tx).map(StakedStateOpWitness::new)?;
+        let nonce = self.next_nonce(address, chain_nonce);
+        report.push(if nonce == latest_nonce {
+            PreflightCheck::pass(
+                PreflightCheckKind::NonceFreshness,
+                format!("Transaction would use the next nonce {}", nonce),
+            )
+        } else if nonce < latest_nonce {
+            PreflightCheck::fail(
+                PreflightCheckKind::NonceFreshness,
+                format!(
+                    "Transaction would use nonce {}, but the chain expects {}",
+                    nonce, latest_nonce
+                ),
+                "Synchronize the wallet",
+            )
+        } else {
+            PreflightCheck::fail(
+                PreflightCheckKind::NonceFreshness,
+                format!(
+                    "Transaction would use nonce {}, but the chain expects {} (transactions of this staking address are pending)",
+                    nonce, latest_nonce
+                ),
+                "Wait until the pending transactions are committed (or rejected) and synchronize the wallet",
+            )
+        });
+
+        report.push(self.preflight_consensus_key(address, node_metadata)?);
+        report.push(self.preflight_keypackage(&genesis, node_metadata)?);
 
-        Ok(TxAux::PublicTx(TxPublicAux::NodeJoinTx(
-            transaction,
-            signature,
-        )))
+        Ok(report)
     }
 
     fn get_staking(
@@ -521,6 +896,19 @@ where
         Ok(mstaking)
     }
 
+    fn reconcile_nonce(
+        &self,
+        name: &str,
+        address: &StakedStateAddress,
+        verify: bool,
+    ) -> Result<()> {
+        if let NonceSource::Managed(ref nonce_manager) = self.nonce_source {
+            let staked_state = self.get_staked_state(name, address, verify)?;
+            nonce_manager.reconcile(address, staked_state.nonce);
+        }
+        Ok(())
+    }
+
     fn get_genesis(&self) -> Result<Genesis> {
         self.client.genesis()
     }
@@ -528,6 +916,16 @@ where
     fn get_status(&self) -> Result<StatusResponse> {
         self.client.status()
     }
+
+    fn get_chain_status(&self) -> Result<ChainStatus> {
+        let mut status = self.client.chain_status()?;
+        status.history_mismatch = self.wallet_client.history_mismatches()?;
+        Ok(status)
+    }
+
+    fn get_tx_result(&self, txid: &TxId) -> Result<Option<TxResult>> {
+        self.client.tx_result(txid)
+    }
 }
 
 fn to_timespec(time: Time) -> Timespec {
@@ -539,11 +937,19 @@ mod tests {
     use super::*;
     use secstr::SecUtf8;
 
-    use parity_scale_codec::Encode;
+    use parity_scale_codec::{Compact, Encode};
+
+    use std::str::FromStr;
 
+    use chrono::{TimeZone, Utc};
+
+    use crate::network_ops::NonceManager;
     use chain_core::init::address::RedeemAddress;
     use chain_core::init::coin::CoinError;
-    use chain_core::state::account::{StakedState, StakedStateOpAttributes, Validator};
+    use chain_core::state::account::{
+        announced_endpoints_supported, ConfidentialInit, MLSInit, NodeState, StakedState,
+        StakedStateOpAttributes, Validator,
+    };
     use chain_core::state::tendermint::BlockHeight;
     use chain_core::state::tendermint::TendermintValidatorPubKey;
     use chain_core::state::ChainState;
@@ -556,13 +962,15 @@ mod tests {
     use client_common::storage::MemoryStorage;
     use client_common::tendermint::mock;
     use client_common::tendermint::types::*;
+    use client_common::tendermint::UnauthorizedClient;
     use client_common::{seckey::derive_enckey, PrivateKey, PublicKey, Transaction};
     use client_core::hd_wallet::HardwareKind;
     use client_core::service::HwKeyService;
     use client_core::signer::WalletSignerManager;
+    use client_core::transaction_builder::UnauthorizedWalletTransactionBuilder;
     use client_core::types::WalletKind;
     use client_core::wallet::DefaultWalletClient;
-    use test_common::chain_env::mock_confidential_init;
+    use test_common::chain_env::{mock_confidential_init, DEFAULT_GENESIS_TIME};
 
     #[derive(Debug, Clone)]
     struct MockTransactionCipher;
@@ -621,6 +1029,39 @@ mod tests {
         }
     }
 
+    /// 100 base units per byte of the (compact) encoded amount of the withdrawal, so that the fee
+    /// depends on the withdrawn amount
+    #[derive(Debug, Clone, Default)]
+    struct CompactAmountFeeAlgorithm;
+
+    impl CompactAmountFeeAlgorithm {
+        fn fee_for(amount: Coin) -> Fee {
+            let size = Compact(u64::from(amount)).encode().len() as u64;
+            Fee::new(Coin::new(100 * size).unwrap())
+        }
+    }
+
+    impl FeeAlgorithm for CompactAmountFeeAlgorithm {
+        fn calculate_fee(&self, _num_bytes: usize) -> std::result::Result<Fee, CoinError> {
+            unreachable!("the fee depends on the amount")
+        }
+
+        fn calculate_for_txaux(&self, txaux: &TxAux) -> std::result::Result<Fee, CoinError> {
+            match txaux {
+                TxAux::EnclaveTx(TxEnclaveAux::WithdrawUnbondedStakeTx {
+                    payload: TxObfuscated { txpayload, .. },
+                    ..
+                }) => match PlainTxAux::decode(&mut txpayload.as_slice()) {
+                    Ok(PlainTxAux::WithdrawUnbondedStakeTx(tx)) => {
+                        Ok(Self::fee_for(tx.outputs[0].value))
+                    }
+                    _ => unreachable!("the payload of the mock signer is not encrypted"),
+                },
+                _ => unreachable!("only withdrawal transactions are estimated"),
+            }
+        }
+    }
+
     #[derive(Default, Clone)]
     pub struct MockJailedClient;
 
@@ -698,9 +1139,22 @@ mod tests {
     #[derive(Default, Clone)]
     pub struct MockClient;
 
+    impl MockClient {
+        /// Minimum bond remainder of the network
+        fn minimum_bond_remainder() -> Coin {
+            Coin::new(1000).unwrap()
+        }
+    }
+
     impl Client for MockClient {
         fn genesis(&self) -> Result<Genesis> {
-            unreachable!()
+            let mut genesis = mock::genesis();
+            genesis
+                .app_state
+                .as_mut()
+                .chain(|| (ErrorKind::InvalidInput, "Missing app state in genesis"))?
+                .minimum_bond_remainder = Some(MockClient::minimum_bond_remainder());
+            Ok(genesis)
         }
 
         fn status(&self) -> Result<StatusResponse> {
@@ -870,6 +1324,89 @@ mod tests {
         assert!(network_ops_client
             .create_unbond_stake_transaction(name, &enckey, address, value, attributes, false)
             .is_ok());
+
+        // bonded: 1000000, fee: 1
+        let unbond = |value: u64| {
+            network_ops_client.create_unbond_stake_transaction(
+                name,
+                &enckey,
+                address,
+                Coin::new(value).unwrap(),
+                StakedStateOpAttributes::new(0),
+                false,
+            )
+        };
+        let minimum = u64::from(MockClient::minimum_bond_remainder());
+        assert!(unbond(1000000 - 1 - minimum).is_ok());
+        let error = unbond(1000000 - minimum).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        assert!(error.to_string().contains("unbond everything (0.00999999)"));
+        assert!(unbond(999999).is_ok());
+    }
+
+    #[test]
+    fn check_managed_nonces_for_rapid_unbond_transactions() {
+        let name = "name";
+        let passphrase = SecUtf8::from("passphrase");
+
+        let storage = MemoryStorage::default();
+        let signer_manager = WalletSignerManager::new(storage.clone(), HwKeyService::default());
+
+        let wallet_client = DefaultWalletClient::new_read_only(storage.clone());
+
+        let (enckey, _) = wallet_client
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        let nonce_manager = NonceManager::new();
+        let network_ops_client = DefaultNetworkOpsClient::new(
+            wallet_client,
+            signer_manager,
+            MockClient::default(),
+            UnitFeeAlgorithm::default(),
+            MockTransactionCipher,
+        )
+        .with_nonce_source(NonceSource::Managed(nonce_manager.clone()));
+
+        let address = network_ops_client
+            .get_wallet_client()
+            .new_staking_address(name, &enckey)
+            .unwrap();
+
+        let unbond_nonce = || {
+            let tx_aux = network_ops_client
+                .create_unbond_stake_transaction(
+                    name,
+                    &enckey,
+                    address,
+                    Coin::zero(),
+                    StakedStateOpAttributes::new(0),
+                    false,
+                )
+                .unwrap();
+            match tx_aux {
+                TxAux::PublicTx(TxPublicAux::UnbondStakeTx(tx, _)) => tx.nonce,
+                _ => unreachable!(),
+            }
+        };
+
+        // chain still reports nonce `0` for both transactions
+        assert_eq!(0, unbond_nonce());
+        assert_eq!(1, unbond_nonce());
+        assert_eq!(Some(2), nonce_manager.next_nonce(&address));
+
+        // second transaction is rejected by the chain
+        network_ops_client
+            .reconcile_nonce(name, &address, false)
+            .unwrap();
+        assert_eq!(Some(0), nonce_manager.next_nonce(&address));
+        assert_eq!(0, unbond_nonce());
     }
 
     #[test]
@@ -1012,6 +1549,69 @@ mod tests {
         }
     }
 
+    #[test]
+    fn check_withdraw_all_unbonded_stake_transaction_with_amount_dependent_fee() {
+        let name = "name";
+        let passphrase = SecUtf8::from("passphrase");
+
+        let storage = MemoryStorage::default();
+        let signer_manager = WalletSignerManager::new(storage.clone(), HwKeyService::default());
+        let wallet_client = DefaultWalletClient::new_read_only(storage);
+        let network_ops_client = DefaultNetworkOpsClient::new(
+            wallet_client,
+            signer_manager,
+            MockClient::default(),
+            CompactAmountFeeAlgorithm::default(),
+            MockTransactionCipher,
+        );
+
+        let (enckey, _) = network_ops_client
+            .get_wallet_client()
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        let from_address = network_ops_client
+            .get_wallet_client()
+            .new_staking_address(name, &enckey)
+            .unwrap();
+
+        let (transaction, _) = network_ops_client
+            .create_withdraw_all_unbonded_stake_transaction(
+                name,
+                &enckey,
+                &from_address,
+                ExtendedAddr::OrTree([0; 32]),
+                TxAttributes::new(171),
+                false,
+            )
+            .unwrap();
+
+        let unbonded = Coin::new(2500000000000000000).unwrap();
+        match transaction {
+            TxAux::EnclaveTx(TxEnclaveAux::WithdrawUnbondedStakeTx {
+                payload: TxObfuscated { txpayload, .. },
+                ..
+            }) => match PlainTxAux::decode(&mut txpayload.as_slice()) {
+                Ok(PlainTxAux::WithdrawUnbondedStakeTx(transaction)) => {
+                    let amount = transaction.outputs[0].value;
+                    let fee = CompactAmountFeeAlgorithm::fee_for(amount).to_coin();
+                    // the fee of an empty output is less than the one of the withdrawn amount
+                    assert!(CompactAmountFeeAlgorithm::fee_for(Coin::zero()).to_coin() < fee);
+                    assert_eq!(unbonded, (amount + fee).unwrap());
+                }
+                _ => unreachable!("the payload of the mock cipher is not encrypted"),
+            },
+            _ => unreachable!(
+                "`create_withdraw_unbonded_stake_transaction()` created invalid transaction type"
+            ),
+        }
+    }
+
     #[test]
     fn check_withdraw_unbonded_stake_transaction_address_not_found() {
         let name = "name";
@@ -1158,6 +1758,98 @@ mod tests {
         }
     }
 
+    #[test]
+    fn check_update_node_transaction() {
+        let name = "name";
+        let passphrase = SecUtf8::from("passphrase");
+
+        let storage = MemoryStorage::default();
+        let signer_manager = WalletSignerManager::new(storage.clone(), HwKeyService::default());
+
+        let fee_algorithm = UnitFeeAlgorithm::default();
+
+        let wallet_client = DefaultWalletClient::new_read_only(storage.clone());
+
+        // the mock staked state is a (jailed) council node
+        let tendermint_client = MockJailedClient::default();
+        let network_ops_client = DefaultNetworkOpsClient::new(
+            wallet_client,
+            signer_manager,
+            tendermint_client,
+            fee_algorithm,
+            MockTransactionCipher,
+        );
+
+        let (enckey, _) = network_ops_client
+            .get_wallet_client()
+            .new_wallet(
+                name,
+                &passphrase,
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+
+        let from_address = network_ops_client
+            .get_wallet_client()
+            .new_staking_address(name, &enckey)
+            .unwrap();
+
+        assert!(network_ops_client
+            .get_announced_endpoints(name, &from_address, false)
+            .unwrap()
+            .is_empty());
+
+        let endpoints = vec!["sentry.example.com:26656".to_owned()];
+        if !announced_endpoints_supported() {
+            let error = network_ops_client
+                .create_update_node_transaction(
+                    name,
+                    &enckey,
+                    from_address,
+                    StakedStateOpAttributes::new(171),
+                    endpoints,
+                    false,
+                )
+                .unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidInput);
+            return;
+        }
+        let transaction = network_ops_client
+            .create_update_node_transaction(
+                name,
+                &enckey,
+                from_address,
+                StakedStateOpAttributes::new(171),
+                endpoints.clone(),
+                false,
+            )
+            .unwrap();
+        match transaction {
+            TxAux::PublicTx(TxPublicAux::UpdateNodeTx(tx, witness)) => {
+                assert_eq!(tx.announced_endpoints, endpoints);
+                let txid = tx.id();
+                let account_address = verify_tx_recover_address(&witness, &txid)
+                    .expect("Unable to verify transaction");
+                assert_eq!(account_address, from_address);
+            }
+            _ => unreachable!("`create_update_node_transaction()` created invalid transaction"),
+        }
+
+        let error = network_ops_client
+            .create_update_node_transaction(
+                name,
+                &enckey,
+                from_address,
+                StakedStateOpAttributes::new(171),
+                vec!["sentry.example.com".to_owned()],
+                false,
+            )
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+
     #[test]
     fn check_node_join_transaction() {
         let name = "name";
@@ -1228,4 +1920,301 @@ mod tests {
             _ => unreachable!("`create_node_join_tx()` created invalid transaction"),
         }
     }
+
+    /// Mock client for preflight checks of node join
+    #[derive(Clone)]
+    struct PreflightMockClient {
+        staked_state: Option<StakedState>,
+        validator_owner: Option<StakedStateAddress>,
+        block_time: Timespec,
+    }
+
+    impl Default for PreflightMockClient {
+        fn default() -> Self {
+            Self {
+                staked_state: Some(StakedState::new(
+                    0,
+                    Coin::new(2_500_000_000_000_000_000).unwrap(),
+                    Coin::zero(),
+                    0,
+                    StakedStateAddress::BasicRedeem(RedeemAddress::default()),
+                    None,
+                )),
+                validator_owner: None,
+                block_time: DEFAULT_GENESIS_TIME,
+            }
+        }
+    }
+
+    impl Client for PreflightMockClient {
+        fn genesis(&self) -> Result<Genesis> {
+            Ok(mock::genesis())
+        }
+
+        fn status(&self) -> Result<StatusResponse> {
+            let block_time = Utc.timestamp(self.block_time as i64, 0).to_rfc3339();
+            Ok(StatusResponse {
+                sync_info: status::SyncInfo {
+                    latest_block_height: Height(10),
+                    latest_block_time: Time::from_str(&block_time).unwrap(),
+                    ..mock::sync_info()
+                },
+                ..mock::status_response()
+            })
+        }
+
+        fn block(&self, _: u64) -> Result<Block> {
+            unreachable!()
+        }
+
+        fn block_batch<'a, T: Iterator<Item = &'a u64>>(&self, _heights: T) -> Result<Vec<Block>> {
+            unreachable!()
+        }
+
+        fn block_results(&self, _height: u64) -> Result<BlockResultsResponse> {
+            unreachable!()
+        }
+
+        fn block_results_batch<'a, T: Iterator<Item = &'a u64>>(
+            &self,
+            _heights: T,
+        ) -> Result<Vec<BlockResultsResponse>> {
+            unreachable!()
+        }
+
+        fn broadcast_transaction(&self, _: &[u8]) -> Result<BroadcastTxResponse> {
+            unreachable!()
+        }
+
+        fn query(
+            &self,
+            path: &str,
+            _data: &[u8],
+            _height: Option<Height>,
+            _prove: bool,
+        ) -> Result<AbciQuery> {
+            let value = match path {
+                "staking" => self.staked_state.encode(),
+                VALIDATOR_OWNER_PATH => self.validator_owner.encode(),
+                _ => unreachable!(),
+            };
+            Ok(AbciQuery {
+                value,
+                ..Default::default()
+            })
+        }
+
+        fn query_state_batch<T: Iterator<Item = u64>>(
+            &self,
+            _heights: T,
+        ) -> Result<Vec<ChainState>> {
+            unreachable!()
+        }
+    }
+
+    type PreflightNetworkOpsClient = DefaultNetworkOpsClient<
+        DefaultWalletClient<
+            MemoryStorage,
+            UnauthorizedClient,
+            UnauthorizedWalletTransactionBuilder,
+        >,
+        MemoryStorage,
+        PreflightMockClient,
+        UnitFeeAlgorithm,
+        MockTransactionCipher,
+    >;
+
+    struct Preflight {
+        network_ops_client: PreflightNetworkOpsClient,
+        nonce_manager: NonceManager,
+        enckey: SecKey,
+        address: StakedStateAddress,
+    }
+
+    impl Preflight {
+        fn new(client: PreflightMockClient) -> Self {
+            let storage = MemoryStorage::default();
+            let signer_manager = WalletSignerManager::new(storage.clone(), HwKeyService::default());
+            let wallet_client = DefaultWalletClient::new_read_only(storage);
+            let (enckey, _) = wallet_client
+                .new_wallet(
+                    "name",
+                    &SecUtf8::from("passphrase"),
+                    WalletKind::Basic,
+                    HardwareKind::LocalOnly,
+                    None,
+                )
+                .unwrap();
+            let address = wallet_client.new_staking_address("name", &enckey).unwrap();
+
+            let nonce_manager = NonceManager::new();
+            let network_ops_client = DefaultNetworkOpsClient::new(
+                wallet_client,
+                signer_manager,
+                client,
+                UnitFeeAlgorithm::default(),
+                MockTransactionCipher,
+            )
+            .with_nonce_source(NonceSource::Managed(nonce_manager.clone()));
+
+            Self {
+                network_ops_client,
+                nonce_manager,
+                enckey,
+                address,
+            }
+        }
+
+        fn run_with(
+            &self,
+            address: StakedStateAddress,
+            chain_hex_id: u8,
+            confidential_init: ConfidentialInit,
+        ) -> PreflightReport {
+            let node_metadata = CouncilNodeMeta::new_with_details(
+                "test".to_owned(),
+                None,
+                TendermintValidatorPubKey::Ed25519([0xcd; 32]),
+                confidential_init,
+            );
+            self.network_ops_client
+                .preflight_node_join(
+                    "name",
+                    &self.enckey,
+                    address,
+                    &StakedStateOpAttributes::new(chain_hex_id),
+                    &node_metadata,
+                    false,
+                )
+                .unwrap()
+        }
+
+        fn run(&self) -> PreflightReport {
+            // chain id of mock genesis: `test-chain-y3m1e6-AB`
+            self.run_with(self.address, 0xab, mock_confidential_init())
+        }
+    }
+
+    /// Asserts that only the given check failed (and it has a remediation hint)
+    fn assert_only_failure(report: &PreflightReport, kind: PreflightCheckKind) {
+        let failures = report
+            .failures()
+            .map(|check| check.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![kind], failures);
+        assert!(report.get(kind).unwrap().remediation.is_some());
+    }
+
+    #[test]
+    fn check_preflight_node_join_passes() {
+        let report = Preflight::new(PreflightMockClient::default()).run();
+        assert!(report.passed(), "{:?}", report);
+        assert_eq!(7, report.checks.len());
+    }
+
+    #[test]
+    fn check_preflight_node_join_wrong_chain_id() {
+        let preflight = Preflight::new(PreflightMockClient::default());
+        let report = preflight.run_with(preflight.address, 0x2a, mock_confidential_init());
+        assert_only_failure(&report, PreflightCheckKind::ChainId);
+    }
+
+    #[test]
+    fn check_preflight_node_join_staking_address_not_in_wallet() {
+        let preflight = Preflight::new(PreflightMockClient::default());
+        let address = StakedStateAddress::BasicRedeem(RedeemAddress([0xaa; 20]));
+        let report = preflight.run_with(address, 0xab, mock_confidential_init());
+        assert_only_failure(&report, PreflightCheckKind::StakingKey);
+    }
+
+    #[test]
+    fn check_preflight_node_join_bonded_not_enough() {
+        let client = PreflightMockClient {
+            staked_state: Some(StakedState::new(
+                0,
+                Coin::new(1_000_000).unwrap(),
+                Coin::new(2_500_000_000_000_000_000).unwrap(),
+                0,
+                StakedStateAddress::BasicRedeem(RedeemAddress::default()),
+                None,
+            )),
+            ..Default::default()
+        };
+        let report = Preflight::new(client).run();
+        assert_only_failure(&report, PreflightCheckKind::BondedAmount);
+
+        // staking account doesn't exist yet
+        let client = PreflightMockClient {
+            staked_state: None,
+            ..Default::default()
+        };
+        let report = Preflight::new(client).run();
+        assert_only_failure(&report, PreflightCheckKind::BondedAmount);
+    }
+
+    #[test]
+    fn check_preflight_node_join_jailed() {
+        let mut client = PreflightMockClient::default();
+        if let Some(ref mut staked_state) = client.staked_state {
+            staked_state.node_meta = Some(NodeState::CouncilNode(Validator {
+                council_node: CouncilNodeMeta::new(
+                    TendermintValidatorPubKey::Ed25519([0xcd; 32]),
+                    mock_confidential_init(),
+                ),
+                jailed_until: Some(DEFAULT_GENESIS_TIME + 100),
+                inactive_time: Some(0),
+                inactive_block: Some(BlockHeight::genesis()),
+                used_validator_addresses: vec![],
+            }));
+        }
+        let report = Preflight::new(client).run();
+        assert_only_failure(&report, PreflightCheckKind::NotJailed);
+    }
+
+    #[test]
+    fn check_preflight_node_join_pending_nonce() {
+        let preflight = Preflight::new(PreflightMockClient::default());
+        // a transaction created with managed nonce `0` is not yet committed
+        preflight
+            .nonce_manager
+            .with_next_nonce(&preflight.address, 0, |_| Ok(()))
+            .unwrap();
+        let report = preflight.run();
+        assert_only_failure(&report, PreflightCheckKind::NonceFreshness);
+
+        preflight.nonce_manager.reconcile(&preflight.address, 0);
+        assert!(preflight.run().passed());
+    }
+
+    #[test]
+    fn check_preflight_node_join_consensus_key_used() {
+        let client = PreflightMockClient {
+            validator_owner: Some(StakedStateAddress::BasicRedeem(RedeemAddress([0xbb; 20]))),
+            ..Default::default()
+        };
+        let report = Preflight::new(client).run();
+        assert_only_failure(&report, PreflightCheckKind::ConsensusKey);
+    }
+
+    #[test]
+    fn check_preflight_node_join_invalid_keypackage() {
+        let preflight = Preflight::new(PreflightMockClient::default());
+        let confidential_init = ConfidentialInit {
+            init_payload: MLSInit::Genesis(vec![0; 32]),
+        };
+        let report = preflight.run_with(preflight.address, 0xab, confidential_init);
+        assert_only_failure(&report, PreflightCheckKind::Keypackage);
+    }
+
+    #[test]
+    fn check_preflight_node_join_keypackage_expires_in_unbonding_period() {
+        let lifetime = keypackage_lifetime(&mock_confidential_init()).unwrap();
+        let client = PreflightMockClient {
+            // mock genesis has unbonding period (max evidence age) of 2 days
+            block_time: lifetime.not_after - 24 * 60 * 60,
+            ..Default::default()
+        };
+        let report = Preflight::new(client).run();
+        assert_only_failure(&report, PreflightCheckKind::Keypackage);
+    }
 }
@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use chain_core::state::account::{Nonce, StakedStateAddress};
+use client_common::Result;
+
+/// Source of nonces used while creating staking transactions
+#[derive(Debug, Clone)]
+pub enum NonceSource {
+    /// Use the nonce of staked state queried from the chain for every transaction
+    Chain,
+    /// Use sequential nonces handed out by given `NonceManager`
+    Managed(NonceManager),
+}
+
+impl Default for NonceSource {
+    #[inline]
+    fn default() -> Self {
+        NonceSource::Chain
+    }
+}
+
+/// Tracks last used nonce of staking addresses locally so that multiple transactions can be created
+/// for the same address before any of them is committed.
+///
+/// Nonces of different addresses are handed out independently, but concurrent requests for the same
+/// address are serialized.
+#[derive(Debug, Clone, Default)]
+pub struct NonceManager {
+    accounts: Arc<Mutex<BTreeMap<StakedStateAddress, Arc<Mutex<Option<Nonce>>>>>>,
+}
+
+impl NonceManager {
+    /// Creates a new instance of `NonceManager`
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hands out next nonce for given address to `build`. The nonce is only consumed if `build`
+    /// succeeds.
+    ///
+    /// `chain_nonce` is the nonce of staked state currently stored on chain. It is used when no
+    /// nonce is tracked locally or when the chain is ahead of local state (for example, when
+    /// transactions were sent by another client).
+    pub fn with_next_nonce<T, B>(
+        &self,
+        address: &StakedStateAddress,
+        chain_nonce: Nonce,
+        build: B,
+    ) -> Result<T>
+    where
+        B: FnOnce(Nonce) -> Result<T>,
+    {
+        let account = self.account(address);
+        let mut next_nonce = account.lock().expect("nonce manager lock poisoned");
+
+        let nonce = match *next_nonce {
+            Some(next_nonce) if next_nonce > chain_nonce => next_nonce,
+            _ => chain_nonce,
+        };
+
+        let result = build(nonce)?;
+        *next_nonce = Some(nonce + 1);
+
+        Ok(result)
+    }
+
+    /// Returns next nonce which will be handed out for given address (if it is tracked locally)
+    pub fn next_nonce(&self, address: &StakedStateAddress) -> Option<Nonce> {
+        let account = self.account(address);
+        let next_nonce = account.lock().expect("nonce manager lock poisoned");
+        *next_nonce
+    }
+
+    /// Reconciles locally tracked nonce of given address with the nonce stored on chain. This should
+    /// be called when a transaction created with a managed nonce is rejected by the chain.
+    pub fn reconcile(&self, address: &StakedStateAddress, chain_nonce: Nonce) {
+        let account = self.account(address);
+        let mut next_nonce = account.lock().expect("nonce manager lock poisoned");
+        *next_nonce = Some(chain_nonce);
+    }
+
+    /// Forgets all locally tracked nonces so that next nonces are taken from the chain
+    pub fn refresh(&self) {
+        self.accounts
+            .lock()
+            .expect("nonce manager lock poisoned")
+            .clear();
+    }
+
+    fn account(&self, address: &StakedStateAddress) -> Arc<Mutex<Option<Nonce>>> {
+        self.accounts
+            .lock()
+            .expect("nonce manager lock poisoned")
+            .entry(*address)
+            .or_default()
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    use chain_core::init::address::RedeemAddress;
+    use client_common::{Error, ErrorKind};
+
+    fn staking_address(byte: u8) -> StakedStateAddress {
+        StakedStateAddress::BasicRedeem(RedeemAddress([byte; 20]))
+    }
+
+    #[test]
+    fn check_sequential_nonces() {
+        let manager = NonceManager::new();
+        let address = staking_address(1);
+
+        assert_eq!(None, manager.next_nonce(&address));
+        assert_eq!(5, manager.with_next_nonce(&address, 5, Ok).unwrap());
+        assert_eq!(6, manager.with_next_nonce(&address, 5, Ok).unwrap());
+        assert_eq!(Some(7), manager.next_nonce(&address));
+
+        // other addresses are tracked independently
+        assert_eq!(
+            0,
+            manager.with_next_nonce(&staking_address(2), 0, Ok).unwrap()
+        );
+
+        // chain is ahead of local state
+        assert_eq!(10, manager.with_next_nonce(&address, 10, Ok).unwrap());
+    }
+
+    #[test]
+    fn check_failed_build_does_not_consume_nonce() {
+        let manager = NonceManager::new();
+        let address = staking_address(1);
+
+        assert_eq!(0, manager.with_next_nonce(&address, 0, Ok).unwrap());
+        assert!(manager
+            .with_next_nonce::<Nonce, _>(&address, 0, |_| {
+                Err(Error::new(ErrorKind::InvalidInput, "build failed"))
+            })
+            .is_err());
+        assert_eq!(1, manager.with_next_nonce(&address, 0, Ok).unwrap());
+    }
+
+    #[test]
+    fn check_reconcile_and_refresh() {
+        let manager = NonceManager::new();
+        let address = staking_address(1);
+
+        assert_eq!(3, manager.with_next_nonce(&address, 3, Ok).unwrap());
+        assert_eq!(4, manager.with_next_nonce(&address, 3, Ok).unwrap());
+
+        manager.reconcile(&address, 3);
+        assert_eq!(3, manager.with_next_nonce(&address, 3, Ok).unwrap());
+
+        manager.refresh();
+        assert_eq!(None, manager.next_nonce(&address));
+        assert_eq!(3, manager.with_next_nonce(&address, 3, Ok).unwrap());
+    }
+
+    #[test]
+    fn check_concurrent_requests_get_distinct_nonces() {
+        let manager = NonceManager::new();
+        let address = staking_address(1);
+
+        let handles = (0..8)
+            .map(|_| {
+                let manager = manager.clone();
+                thread::spawn(move || manager.with_next_nonce(&address, 0, Ok).unwrap())
+            })
+            .collect::<Vec<_>>();
+
+        let mut nonces = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>();
+        nonces.sort();
+
+        assert_eq!((0..8).collect::<Vec<Nonce>>(), nonces);
+    }
+}
@@ -0,0 +1,165 @@
+use std::fmt;
+
+use mls::extensions::LifeTimeExt;
+use mls::message::{ContentType, MLSPlaintext, Proposal};
+use mls::{Codec, DefaultCipherSuite, KeyPackage};
+use serde::{Deserialize, Serialize};
+
+use chain_core::state::account::{ConfidentialInit, MLSInit};
+pub(crate) use client_common::tendermint::types::chain_hex_id;
+
+/// Query path which returns the staking address owning a validator address
+pub(crate) const VALIDATOR_OWNER_PATH: &str = "validator_owner";
+
+/// Checks done before broadcasting a node join transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreflightCheckKind {
+    /// the transaction attributes are for the chain the node is connected to
+    ChainId,
+    /// the staking address belongs to the wallet
+    StakingKey,
+    /// bonded amount is at least the required council node stake
+    BondedAmount,
+    /// staking account is not jailed
+    NotJailed,
+    /// the nonce the transaction would be signed with is the next one expected by the chain
+    NonceFreshness,
+    /// consensus key isn't used by another staking account
+    ConsensusKey,
+    /// keypackage can be parsed and is valid for (at least) the unbonding period
+    Keypackage,
+}
+
+impl fmt::Display for PreflightCheckKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PreflightCheckKind::ChainId => "chain_id",
+            PreflightCheckKind::StakingKey => "staking_key",
+            PreflightCheckKind::BondedAmount => "bonded_amount",
+            PreflightCheckKind::NotJailed => "not_jailed",
+            PreflightCheckKind::NonceFreshness => "nonce_freshness",
+            PreflightCheckKind::ConsensusKey => "consensus_key",
+            PreflightCheckKind::Keypackage => "keypackage",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Outcome of a single preflight check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreflightCheck {
+    /// the check
+    pub kind: PreflightCheckKind,
+    /// whether the check passed
+    pub passed: bool,
+    /// what was found
+    pub message: String,
+    /// how to fix the failure (`None` if the check passed)
+    pub remediation: Option<String>,
+}
+
+impl PreflightCheck {
+    pub(crate) fn pass(kind: PreflightCheckKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            passed: true,
+            message: message.into(),
+            remediation: None,
+        }
+    }
+
+    pub(crate) fn fail(
+        kind: PreflightCheckKind,
+        message: impl Into<String>,
+        remediation: impl Into<String>,
+    ) -> Self {
+        Self {
+            kind,
+            passed: false,
+            message: message.into(),
+            remediation: Some(remediation.into()),
+        }
+    }
+}
+
+/// Report of the checks done before joining the validator set (nothing is broadcasted)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreflightReport {
+    /// outcomes of all the checks (in the order they were done)
+    pub checks: Vec<PreflightCheck>,
+}
+
+impl PreflightReport {
+    /// Returns `true` if all the checks passed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    /// Returns the outcome of given check
+    pub fn get(&self, kind: PreflightCheckKind) -> Option<&PreflightCheck> {
+        self.checks.iter().find(|check| check.kind == kind)
+    }
+
+    /// Returns the failed checks
+    pub fn failures(&self) -> impl Iterator<Item = &PreflightCheck> {
+        self.checks.iter().filter(|check| !check.passed)
+    }
+
+    pub(crate) fn push(&mut self, check: PreflightCheck) {
+        self.checks.push(check);
+    }
+}
+
+/// Parses the keypackage of node join payload (either a bare keypackage or an `Add` proposal
+/// carrying one) and returns its lifetime extension
+pub(crate) fn keypackage_lifetime(
+    confidential_init: &ConfidentialInit,
+) -> Result<LifeTimeExt, &'static str> {
+    let keypackage = match confidential_init.init_payload {
+        MLSInit::Genesis(ref keypackage) => {
+            KeyPackage::<DefaultCipherSuite>::read_bytes(keypackage)
+                .ok_or("unable to decode keypackage")?
+        }
+        MLSInit::NodeJoin { ref add, .. } => {
+            let add = MLSPlaintext::<DefaultCipherSuite>::read_bytes(add)
+                .ok_or("unable to decode add proposal")?;
+            match add.content.content {
+                ContentType::Proposal(Proposal::Add(add)) => add.key_package,
+                _ => return Err("add proposal expected"),
+            }
+        }
+    };
+    keypackage
+        .payload
+        .find_extension::<LifeTimeExt>()
+        .map_err(|_| "keypackage has no lifetime extension")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_chain_hex_id() {
+        assert_eq!(Some(0xab), chain_hex_id("test-chain-y3m1e6-AB"));
+        assert_eq!(Some(0x2a), chain_hex_id("mainnet-crypto-com-chain-2A"));
+        assert_eq!(None, chain_hex_id("test-chain-XY"));
+        assert_eq!(None, chain_hex_id("A"));
+    }
+
+    #[test]
+    fn check_keypackage_lifetime_of_invalid_payloads() {
+        let init = ConfidentialInit {
+            init_payload: MLSInit::Genesis(vec![0; 8]),
+        };
+        assert!(keypackage_lifetime(&init).is_err());
+        let init = ConfidentialInit {
+            init_payload: MLSInit::NodeJoin {
+                add: vec![0; 8],
+                commit: vec![],
+            },
+        };
+        assert!(keypackage_lifetime(&init).is_err());
+    }
+}
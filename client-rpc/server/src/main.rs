@@ -1,5 +1,6 @@
 mod program;
 mod server;
+mod startup_report;
 
 fn main() {
     crate::program::run_cli();
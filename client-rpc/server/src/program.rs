@@ -34,6 +34,20 @@ pub struct Options {
     #[structopt(name = "chain-id", short, long, help = "Full chain ID")]
     pub chain_id: String,
 
+    #[structopt(
+        name = "allow-chain-mismatch",
+        long,
+        help = "Keep broadcasting transactions when the node is on another chain than chain-id (for development)"
+    )]
+    pub allow_chain_mismatch: bool,
+
+    #[structopt(
+        name = "managed-nonces",
+        long,
+        help = "Track the nonces of the staking addresses locally, so that several staking transactions of an address can be created before the first one is committed"
+    )]
+    pub managed_nonces: bool,
+
     #[structopt(
         name = "storage-dir",
         short,
@@ -51,6 +65,13 @@ pub struct Options {
         help = "Url for connecting with tendermint websocket RPC"
     )]
     pub websocket_url: String,
+
+    #[structopt(
+        name = "archive-websocket-url",
+        long,
+        help = "Url for connecting with the tendermint websocket RPC of an archive node, from which the blocks pruned by the node are fetched"
+    )]
+    pub archive_websocket_url: Option<String>,
     #[structopt(
         name = "enable-fast-forward",
         long,
@@ -1,9 +1,11 @@
 use crate::program::Options;
+use crate::startup_report::{ConfigSummary, NodeSummary, StartupReport};
 
 use jsonrpc_http_server::{AccessControlAllowOrigin, DomainsValidation, ServerBuilder};
 use std::net::SocketAddr;
 
 use chain_core::init::network::{get_network, get_network_id, init_chain_id};
+use client_common::tendermint::{Client, WebsocketRpcClient};
 use client_common::Result;
 use client_common::{Error, ErrorKind};
 use client_core::wallet::syncer::SyncerOptions;
@@ -11,9 +13,13 @@ use client_rpc_core::RpcHandler;
 pub(crate) struct Server {
     host: String,
     port: u16,
+    chain_id: String,
     network_id: u8,
+    allow_chain_mismatch: bool,
+    managed_nonces: bool,
     storage_dir: String,
     websocket_url: String,
+    archive_websocket_url: Option<String>,
 
     sync_options: SyncerOptions,
 }
@@ -40,9 +46,13 @@ impl Server {
         Ok(Server {
             host: options.host,
             port: options.port,
+            chain_id: options.chain_id,
             network_id,
+            allow_chain_mismatch: options.allow_chain_mismatch,
+            managed_nonces: options.managed_nonces,
             storage_dir: options.storage_dir,
             websocket_url: options.websocket_url,
+            archive_websocket_url: options.archive_websocket_url,
             sync_options: SyncerOptions {
                 enable_fast_forward: options.enable_fast_forward,
                 disable_light_client: options.disable_light_client,
@@ -57,6 +67,32 @@ impl Server {
         })
     }
 
+    fn startup_report(&self) -> StartupReport {
+        let config = ConfigSummary {
+            chain_id: self.chain_id.clone(),
+            network_id: format!("{:02X}", self.network_id),
+            storage_dir: self.storage_dir.clone(),
+            bind_address: format!("{}:{}", self.host, self.port),
+            websocket_url: self.websocket_url.clone(),
+            archive_websocket_url: self.archive_websocket_url.clone(),
+            allow_chain_mismatch: self.allow_chain_mismatch,
+            managed_nonces: self.managed_nonces,
+            light_client: !self.sync_options.disable_light_client,
+            light_client_peers: if self.sync_options.disable_light_client {
+                None
+            } else {
+                Some(self.sync_options.light_client_peers.clone())
+            },
+            fast_forward: self.sync_options.enable_fast_forward,
+            address_recovery: self.sync_options.enable_address_recovery,
+            batch_size: self.sync_options.batch_size,
+            block_height_ensure: self.sync_options.block_height_ensure,
+        };
+        let status =
+            WebsocketRpcClient::new(&self.websocket_url).and_then(|client| client.status());
+        StartupReport::new(config, NodeSummary::new(status))
+    }
+
     fn create_rpc_handler(&self) -> Result<RpcHandler> {
         if cfg!(feature = "mock-enclave") {
             log::warn!("{}", "WARNING: Using mock (non-enclave) infrastructure");
@@ -64,13 +100,17 @@ impl Server {
         RpcHandler::new(
             &self.storage_dir,
             &self.websocket_url,
+            self.archive_websocket_url.as_ref().map(String::as_str),
             self.network_id,
+            self.allow_chain_mismatch,
+            self.managed_nonces,
             self.sync_options.clone(),
             None,
         )
     }
 
     pub(crate) fn start(&mut self) -> Result<()> {
+        self.startup_report().log();
         let handler = self.create_rpc_handler()?;
         let server = ServerBuilder::new(handler.io)
             // TODO: Either make CORS configurable or make it more strict
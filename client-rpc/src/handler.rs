@@ -4,35 +4,40 @@ use jsonrpc_core::IoHandler;
 use crate::rpc::multisig_rpc::{MultiSigRpc, MultiSigRpcImpl};
 use chain_core::tx::fee::FeeAlgorithm;
 use client_common::cipher::TransactionObfuscation;
-use client_common::storage::SledStorage;
-use client_common::tendermint::{types::GenesisExt, Client, WebsocketRpcClient};
+use client_common::storage::{CachedStorage, SledStorage};
+use client_common::tendermint::{
+    types::GenesisExt, ChainIdCheck, ChainIdGuard, Client, FailoverClient, WebsocketRpcClient,
+};
 use client_common::Result;
 use client_common::Storage;
-use client_core::service::HwKeyService;
+use client_core::coin_selection::BranchAndBound;
+use client_core::service::{HwKeyService, SigningSessionService};
 use client_core::signer::WalletSignerManager;
 use client_core::transaction_builder::DefaultWalletTransactionBuilder;
 use client_core::wallet::syncer::{
     spawn_light_client_supervisor, ObfuscationSyncerConfig, SyncerOptions,
 };
-use client_core::wallet::DefaultWalletClient;
-use client_network::network_ops::DefaultNetworkOpsClient;
+use client_core::wallet::{DefaultWalletClient, WalletLockMap};
+use client_network::network_ops::{DefaultNetworkOpsClient, NonceManager, NonceSource};
 
 use crate::rpc::{
     info_rpc::{InfoRpc, InfoRpcImpl},
+    signing_session_rpc::{SigningSessionRpc, SigningSessionRpcImpl},
     staking_rpc::{StakingRpc, StakingRpcImpl},
     sync_rpc::{CBindingCore, SyncRpc, SyncRpcImpl},
     transaction_rpc::{TransactionRpc, TransactionRpcImpl},
     wallet_rpc::{WalletRpc, WalletRpcImpl},
 };
 
-type AppWalletClient<O, F> = DefaultWalletClient<
-    SledStorage,
-    WebsocketRpcClient,
-    DefaultWalletTransactionBuilder<SledStorage, F, O>,
->;
+type AppStorage = CachedStorage<SledStorage>;
+/// tendermint client which refuses to broadcast if the node is on another chain, and fetches the
+/// heights pruned by the node from the archive node (if any)
+type AppClient = ChainIdGuard<FailoverClient<WebsocketRpcClient, WebsocketRpcClient>>;
+type AppWalletClient<O, F> =
+    DefaultWalletClient<AppStorage, AppClient, DefaultWalletTransactionBuilder<AppStorage, F, O>>;
 type AppOpsClient<O, F> =
-    DefaultNetworkOpsClient<AppWalletClient<O, F>, SledStorage, WebsocketRpcClient, F, O>;
-type AppSyncerConfig<O, L> = ObfuscationSyncerConfig<SledStorage, WebsocketRpcClient, O, L>;
+    DefaultNetworkOpsClient<AppWalletClient<O, F>, AppStorage, AppClient, F, O>;
+type AppSyncerConfig<O, L> = ObfuscationSyncerConfig<AppStorage, AppClient, O, L>;
 
 #[derive(Clone)]
 pub struct RpcHandler {
@@ -43,12 +48,16 @@ impl RpcHandler {
     fn new_impl(
         storage_dir: &str,
         websocket_url: &str,
+        archive_websocket_url: Option<&str>,
         network_id: u8,
+        allow_chain_mismatch: bool,
+        managed_nonces: bool,
         sync_options: SyncerOptions,
         progress_callback: Option<CBindingCore>,
     ) -> Result<Self> {
         let mut io = IoHandler::new();
-        let storage = SledStorage::new(&storage_dir)?;
+        // wallet records (keys, addresses) are read on every request: cache their decrypted values
+        let storage = CachedStorage::new(SledStorage::new(&storage_dir)?);
 
         let polling_storage = storage.clone();
         std::thread::spawn(move || {
@@ -59,21 +68,41 @@ impl RpcHandler {
             }
         });
 
-        let tendermint_client = WebsocketRpcClient::new(&websocket_url)?;
-        let obfuscation = tendermint_client.clone();
-        let fee_policy = tendermint_client.clone();
+        let node_client = WebsocketRpcClient::new(&websocket_url)?;
+        let archive_client = archive_websocket_url
+            .map(WebsocketRpcClient::new)
+            .transpose()?;
+        let obfuscation = node_client.clone();
+        let fee_policy = node_client.clone();
+        let tendermint_client = ChainIdCheck::new(network_id, allow_chain_mismatch)
+            .guard(FailoverClient::with_archive(node_client, archive_client));
+        // the wallet-mutating requests are refused on mismatch, the reads are still served
+        if let Err(err) = tendermint_client.check() {
+            log::error!("chain id check of the node failed: {}", err);
+        }
+        // all the wallet clients on the storage serialize their writes of a wallet
+        let wallet_locks = WalletLockMap::new();
 
         let wallet_client = make_wallet_client(
             storage.clone(),
             tendermint_client.clone(),
             fee_policy.clone(),
             obfuscation.clone(),
+            wallet_locks.clone(),
         )?;
+        // the nonces of the staking addresses are shared by all the staking requests
+        let nonce_source = if managed_nonces {
+            NonceSource::Managed(NonceManager::new())
+        } else {
+            NonceSource::Chain
+        };
         let ops_client = make_ops_client(
             storage.clone(),
             tendermint_client.clone(),
             fee_policy.clone(),
-            tendermint_client.clone(),
+            obfuscation.clone(),
+            wallet_locks.clone(),
+            nonce_source,
         )?;
         let handle = if sync_options.disable_light_client {
             None
@@ -94,17 +123,26 @@ impl RpcHandler {
             obfuscation.clone(),
             sync_options,
             handle.clone(),
-        );
+        )
+        .with_wallet_locks(wallet_locks.clone());
 
+        let signing_sessions = SigningSessionService::new();
         #[cfg(feature = "experimental")]
-        let multisig_rpc = MultiSigRpcImpl::new(wallet_client.clone());
+        let multisig_rpc = MultiSigRpcImpl::new(wallet_client.clone(), signing_sessions.clone());
         let transaction_rpc = TransactionRpcImpl::new(network_id);
         let staking_rpc =
             StakingRpcImpl::new(wallet_client.clone(), ops_client.clone(), network_id);
         let info_rpc = InfoRpcImpl::new(ops_client);
-
-        let sync_wallet_client =
-            make_wallet_client(storage, tendermint_client, fee_policy, obfuscation)?;
+        let signing_session_rpc =
+            SigningSessionRpcImpl::new(wallet_client.clone(), signing_sessions);
+
+        let sync_wallet_client = make_wallet_client(
+            storage,
+            tendermint_client,
+            fee_policy,
+            obfuscation,
+            wallet_locks,
+        )?;
 
         let sync_rpc =
             SyncRpcImpl::new(syncer_config, progress_callback, sync_wallet_client, handle);
@@ -117,6 +155,7 @@ impl RpcHandler {
         io.extend_with(sync_rpc.to_delegate());
         io.extend_with(wallet_rpc.to_delegate());
         io.extend_with(info_rpc.to_delegate());
+        io.extend_with(signing_session_rpc.to_delegate());
 
         Ok(RpcHandler { io })
     }
@@ -124,14 +163,20 @@ impl RpcHandler {
     pub fn new(
         storage_dir: &str,
         websocket_url: &str,
+        archive_websocket_url: Option<&str>,
         network_id: u8,
+        allow_chain_mismatch: bool,
+        managed_nonces: bool,
         sync_options: SyncerOptions,
         progress_callback: Option<CBindingCore>,
     ) -> Result<Self> {
         Self::new_impl(
             storage_dir,
             websocket_url,
+            archive_websocket_url,
             network_id,
+            allow_chain_mismatch,
+            managed_nonces,
             sync_options,
             progress_callback,
         )
@@ -143,35 +188,47 @@ impl RpcHandler {
 }
 
 fn make_wallet_client<O: TransactionObfuscation, F: FeeAlgorithm>(
-    storage: SledStorage,
-    tendermint_client: WebsocketRpcClient,
+    storage: AppStorage,
+    tendermint_client: AppClient,
     fee_policy: F,
     obfuscator: O,
+    wallet_locks: WalletLockMap,
 ) -> Result<AppWalletClient<O, F>> {
     let hw_key_service = HwKeyService::default();
-    let signer_manager = WalletSignerManager::new(storage.clone(), hw_key_service.clone());
+    let signer_manager = WalletSignerManager::new(storage.clone(), hw_key_service.clone())
+        .with_wallet_locks(wallet_locks.clone());
     Ok(DefaultWalletClient::new(
         storage,
         tendermint_client,
-        DefaultWalletTransactionBuilder::new(signer_manager, fee_policy, obfuscator),
+        DefaultWalletTransactionBuilder::new(
+            signer_manager,
+            fee_policy,
+            obfuscator,
+            BranchAndBound::default(),
+        ),
         Some(50),
         hw_key_service,
-    ))
+    )
+    .with_wallet_locks(wallet_locks))
 }
 
 fn make_ops_client<O: TransactionObfuscation, F: FeeAlgorithm>(
-    storage: SledStorage,
-    tendermint_client: WebsocketRpcClient,
+    storage: AppStorage,
+    tendermint_client: AppClient,
     fee_policy: F,
     obfuscator: O,
+    wallet_locks: WalletLockMap,
+    nonce_source: NonceSource,
 ) -> Result<AppOpsClient<O, F>> {
     let hw_key_service = HwKeyService::default();
-    let signer_manager = WalletSignerManager::new(storage.clone(), hw_key_service);
+    let signer_manager = WalletSignerManager::new(storage.clone(), hw_key_service)
+        .with_wallet_locks(wallet_locks.clone());
     let wallet_client = make_wallet_client(
         storage,
         tendermint_client.clone(),
         fee_policy.clone(),
         obfuscator.clone(),
+        wallet_locks,
     )?;
     Ok(DefaultNetworkOpsClient::new(
         wallet_client,
@@ -179,5 +236,6 @@ fn make_ops_client<O: TransactionObfuscation, F: FeeAlgorithm>(
         tendermint_client,
         fee_policy,
         obfuscator,
-    ))
+    )
+    .with_nonce_source(nonce_source))
 }
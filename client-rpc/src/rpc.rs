@@ -1,6 +1,7 @@
 pub mod info_rpc;
 #[cfg(feature = "experimental")]
 pub mod multisig_rpc;
+pub mod signing_session_rpc;
 pub mod staking_rpc;
 pub mod sync_rpc;
 pub mod sync_worker;
@@ -1,16 +1,46 @@
+use std::sync::Mutex;
+
 use jsonrpc_core::Result;
 use jsonrpc_derive::rpc;
+use serde::{Deserialize, Serialize};
 
 use crate::to_rpc_error;
-use client_common::tendermint::types::{Genesis, StatusResponse};
+use chain_core::init::coin::Coin;
+use chain_core::tx::data::input::str2txid;
+use chain_core::tx::fee::{FeeAlgorithm, LinearFee};
+use chain_core::tx::result::TxResult;
+use client_common::tendermint::types::{ChainStatus, Genesis, StatusResponse};
+use client_common::{ErrorKind, ResultExt};
+use client_core::transaction_builder::{estimate_encoded_size, TxTemplate};
 use client_network::NetworkOpsClient;
 
+/// Encoded size of a transaction and its fee
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TxSizeEstimate {
+    /// encoded size (in bytes) of the obfuscated transaction
+    pub size: usize,
+    /// fee of the transaction, according to the current fee parameters
+    pub fee: Coin,
+}
+
 #[rpc(server)]
 pub trait InfoRpc: Send + Sync {
     #[rpc(name = "genesis")]
     fn genesis(&self) -> Result<Genesis>;
     #[rpc(name = "status")]
     fn status(&self) -> Result<StatusResponse>;
+    #[rpc(name = "chain_status")]
+    fn chain_status(&self) -> Result<ChainStatus>;
+    /// result of the delivered transaction (hex encoded txid), if indexed by the node
+    #[rpc(name = "chain_txStatus")]
+    fn tx_status(&self, txid: String) -> Result<Option<TxResult>>;
+    /// current linear fee parameters of the chain (in thousandths of base unit): the fee of a
+    /// transaction is `constant + coefficient * size`
+    #[rpc(name = "chain_feeParameters")]
+    fn fee_parameters(&self) -> Result<LinearFee>;
+    /// encoded size of the transactions of given shape (as built by the wallet) and their fee
+    #[rpc(name = "chain_estimateTxSize")]
+    fn estimate_tx_size(&self, template: TxTemplate) -> Result<TxSizeEstimate>;
 }
 
 pub struct InfoRpcImpl<N>
@@ -18,6 +48,8 @@ where
     N: NetworkOpsClient,
 {
     ops_client: N,
+    /// fee parameters from the network parameters of the genesis, fetched once
+    fee_policy: Mutex<Option<LinearFee>>,
 }
 
 impl<N> InfoRpcImpl<N>
@@ -25,7 +57,30 @@ where
     N: NetworkOpsClient,
 {
     pub fn new(ops_client: N) -> Self {
-        InfoRpcImpl { ops_client }
+        InfoRpcImpl {
+            ops_client,
+            fee_policy: Mutex::new(None),
+        }
+    }
+
+    fn fee_policy(&self) -> client_common::Result<LinearFee> {
+        let mut fee_policy = self
+            .fee_policy
+            .lock()
+            .expect("lock of cached fee policy poisoned");
+        if let Some(fee_policy) = *fee_policy {
+            return Ok(fee_policy);
+        }
+
+        let policy = self
+            .ops_client
+            .get_genesis()?
+            .app_state
+            .chain(|| (ErrorKind::InvalidInput, "Missing app state in genesis"))?
+            .network_params
+            .initial_fee_policy;
+        *fee_policy = Some(policy);
+        Ok(policy)
     }
 }
 
@@ -39,4 +94,24 @@ where
     fn status(&self) -> Result<StatusResponse> {
         self.ops_client.get_status().map_err(to_rpc_error)
     }
+    fn chain_status(&self) -> Result<ChainStatus> {
+        self.ops_client.get_chain_status().map_err(to_rpc_error)
+    }
+    fn tx_status(&self, txid: String) -> Result<Option<TxResult>> {
+        let txid = str2txid(&txid).map_err(to_rpc_error)?;
+        self.ops_client.get_tx_result(&txid).map_err(to_rpc_error)
+    }
+    fn fee_parameters(&self) -> Result<LinearFee> {
+        self.fee_policy().map_err(to_rpc_error)
+    }
+    fn estimate_tx_size(&self, template: TxTemplate) -> Result<TxSizeEstimate> {
+        let size = estimate_encoded_size(&template).map_err(to_rpc_error)?;
+        let fee = self
+            .fee_policy()
+            .map_err(to_rpc_error)?
+            .calculate_fee(size)
+            .map_err(to_rpc_error)?
+            .to_coin();
+        Ok(TxSizeEstimate { size, fee })
+    }
 }
@@ -1,10 +1,13 @@
 use hex::{decode, encode};
 use jsonrpc_core::Result;
 use jsonrpc_derive::rpc;
+use parity_scale_codec::{Decode, Encode};
+use serde::Deserialize;
 
 use chain_core::common::{H256, HASH_SIZE_256};
 use chain_core::tx::data::Tx;
 use client_common::{Error, ErrorKind, PublicKey, Result as CommonResult, ResultExt, SecKey};
+use client_core::service::SigningSessionService;
 use client_core::types::AddressType;
 use client_core::wallet::WalletRequest;
 use client_core::{MultiSigWalletClient, WalletClient};
@@ -29,76 +32,139 @@ pub trait MultiSigRpc: Send + Sync {
         required_signatures: usize,
     ) -> Result<String>;
 
+    /// Starts a multi-sig session. Self public key has to be the key of an address declared in
+    /// the signing session when it's authorized with one.
     #[rpc(name = "multiSig_newSession")]
     fn new_session(
         &self,
-        request: WalletRequest,
+        auth: SigningAuth,
         message: String,
         signer_public_keys: Vec<String>,
         self_public_key: String,
     ) -> Result<String>;
 
     #[rpc(name = "multiSig_nonceCommitment")]
-    fn nonce_commitment(&self, session_id: String, enckey: SecKey) -> Result<String>;
+    fn nonce_commitment(&self, session_id: String, auth: SigningAuth) -> Result<String>;
 
     #[rpc(name = "multiSig_addNonceCommitment")]
     fn add_nonce_commitment(
         &self,
         session_id: String,
-        enckey: SecKey,
+        auth: SigningAuth,
         nonce_commitment: String,
         public_key: String,
     ) -> Result<()>;
 
     #[rpc(name = "multiSig_nonce")]
-    fn nonce(&self, session_id: String, enckey: SecKey) -> Result<String>;
+    fn nonce(&self, session_id: String, auth: SigningAuth) -> Result<String>;
 
     #[rpc(name = "multiSig_addNonce")]
     fn add_nonce(
         &self,
         session_id: String,
-        enckey: SecKey,
+        auth: SigningAuth,
         nonce: String,
         public_key: String,
     ) -> Result<()>;
 
     #[rpc(name = "multiSig_partialSign")]
-    fn partial_signature(&self, session_id: String, enckey: SecKey) -> Result<String>;
+    fn partial_signature(&self, session_id: String, auth: SigningAuth) -> Result<String>;
 
     #[rpc(name = "multiSig_addPartialSignature")]
     fn add_partial_signature(
         &self,
         session_id: String,
-        enckey: SecKey,
+        auth: SigningAuth,
         partial_signature: String,
         public_key: String,
     ) -> Result<()>;
 
+    /// Nonce commitment of current signer as a hex encoded message for the other co-signers
+    #[rpc(name = "multiSig_exportNonceCommitment")]
+    fn export_nonce_commitment(&self, session_id: String, auth: SigningAuth) -> Result<String>;
+
+    #[rpc(name = "multiSig_importNonceCommitment")]
+    fn import_nonce_commitment(
+        &self,
+        session_id: String,
+        auth: SigningAuth,
+        message: String,
+    ) -> Result<()>;
+
+    /// Nonce of current signer as a hex encoded message for the other co-signers
+    #[rpc(name = "multiSig_exportNonce")]
+    fn export_nonce(&self, session_id: String, auth: SigningAuth) -> Result<String>;
+
+    #[rpc(name = "multiSig_importNonce")]
+    fn import_nonce(&self, session_id: String, auth: SigningAuth, message: String) -> Result<()>;
+
+    /// Partial signature of current signer as a hex encoded message for the other co-signers
+    #[rpc(name = "multiSig_exportPartialSignature")]
+    fn export_partial_signature(&self, session_id: String, auth: SigningAuth) -> Result<String>;
+
+    #[rpc(name = "multiSig_importPartialSignature")]
+    fn import_partial_signature(
+        &self,
+        session_id: String,
+        auth: SigningAuth,
+        message: String,
+    ) -> Result<()>;
+
     #[rpc(name = "multiSig_signature")]
-    fn signature(&self, session_id: String, enckey: SecKey) -> Result<String>;
+    fn signature(&self, session_id: String, auth: SigningAuth) -> Result<String>;
 
     #[rpc(name = "multiSig_broadcastWithSignature")]
     fn broadcast_with_signature(
         &self,
-        request: WalletRequest,
+        auth: SigningAuth,
         session_id: String,
         unsigned_transaction: Tx,
     ) -> Result<String>;
 }
 
+/// Authorization of a call on a multi-sig session: wallet request (or only enckey of the wallet
+/// when its name isn't needed), or id of the signing session the multi-sig session was started in
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum SigningAuth {
+    SigningSession { signing_session_id: String },
+    Wallet(WalletRequest),
+    Enckey(SecKey),
+}
+
 pub struct MultiSigRpcImpl<T>
 where
     T: WalletClient,
 {
     client: T,
+    sessions: SigningSessionService,
 }
 
 impl<T> MultiSigRpcImpl<T>
 where
     T: WalletClient,
 {
-    pub fn new(client: T) -> Self {
-        MultiSigRpcImpl { client }
+    pub fn new(client: T, sessions: SigningSessionService) -> Self {
+        MultiSigRpcImpl { client, sessions }
+    }
+
+    /// Calls `f` with wallet's name (if known) and enckey to access given multi-sig session
+    fn with_auth<R, F>(&self, auth: SigningAuth, session_id: &H256, f: F) -> CommonResult<R>
+    where
+        F: FnOnce(Option<&str>, &SecKey) -> CommonResult<R>,
+    {
+        match auth {
+            SigningAuth::SigningSession { signing_session_id } => {
+                let signing_session_id = parse_hash_256(signing_session_id)?;
+                self.sessions.with_multi_sig_session(
+                    &signing_session_id,
+                    session_id,
+                    |name, enckey| f(Some(name), enckey),
+                )
+            }
+            SigningAuth::Wallet(request) => f(Some(&request.name), &request.enckey),
+            SigningAuth::Enckey(enckey) => f(None, &enckey),
+        }
     }
 }
 
@@ -155,7 +221,7 @@ where
 
     fn new_session(
         &self,
-        request: WalletRequest,
+        auth: SigningAuth,
         message: String,
         signer_public_keys: Vec<String>,
         self_public_key: String,
@@ -168,31 +234,51 @@ where
             .map_err(to_rpc_error)?;
         let self_public_key = parse_public_key(self_public_key).map_err(to_rpc_error)?;
 
-        self.client
-            .new_multi_sig_session(
+        match auth {
+            SigningAuth::SigningSession { signing_session_id } => {
+                let signing_session_id =
+                    parse_hash_256(signing_session_id).map_err(to_rpc_error)?;
+                self.sessions.new_multi_sig_session(
+                    &signing_session_id,
+                    &self_public_key,
+                    |name, enckey| {
+                        self.client.new_multi_sig_session(
+                            name,
+                            enckey,
+                            message,
+                            signer_public_keys,
+                            self_public_key.clone(),
+                        )
+                    },
+                )
+            }
+            SigningAuth::Wallet(request) => self.client.new_multi_sig_session(
                 &request.name,
                 &request.enckey,
                 message,
                 signer_public_keys,
                 self_public_key,
-            )
-            .map(serialize_hash_256)
-            .map_err(to_rpc_error)
+            ),
+            SigningAuth::Enckey(_) => Err(wallet_name_required()),
+        }
+        .map(serialize_hash_256)
+        .map_err(to_rpc_error)
     }
 
-    fn nonce_commitment(&self, session_id: String, enckey: SecKey) -> Result<String> {
+    fn nonce_commitment(&self, session_id: String, auth: SigningAuth) -> Result<String> {
         let session_id = parse_hash_256(session_id).map_err(to_rpc_error)?;
 
-        self.client
-            .nonce_commitment(&session_id, &enckey)
-            .map(serialize_hash_256)
-            .map_err(to_rpc_error)
+        self.with_auth(auth, &session_id, |_, enckey| {
+            self.client.nonce_commitment(&session_id, enckey)
+        })
+        .map(serialize_hash_256)
+        .map_err(to_rpc_error)
     }
 
     fn add_nonce_commitment(
         &self,
         session_id: String,
-        enckey: SecKey,
+        auth: SigningAuth,
         nonce_commitment: String,
         public_key: String,
     ) -> Result<()> {
@@ -200,24 +286,27 @@ where
         let nonce_commitment = parse_hash_256(nonce_commitment).map_err(to_rpc_error)?;
         let public_key = parse_public_key(public_key).map_err(to_rpc_error)?;
 
-        self.client
-            .add_nonce_commitment(&session_id, &enckey, nonce_commitment, &public_key)
-            .map_err(to_rpc_error)
+        self.with_auth(auth, &session_id, |_, enckey| {
+            self.client
+                .add_nonce_commitment(&session_id, enckey, nonce_commitment, &public_key)
+        })
+        .map_err(to_rpc_error)
     }
 
-    fn nonce(&self, session_id: String, enckey: SecKey) -> Result<String> {
+    fn nonce(&self, session_id: String, auth: SigningAuth) -> Result<String> {
         let session_id = parse_hash_256(session_id).map_err(to_rpc_error)?;
 
-        self.client
-            .nonce(&session_id, &enckey)
-            .map(serialize_hash_256)
-            .map_err(to_rpc_error)
+        self.with_auth(auth, &session_id, |_, enckey| {
+            self.client.nonce(&session_id, enckey)
+        })
+        .map(serialize_hash_256)
+        .map_err(to_rpc_error)
     }
 
     fn add_nonce(
         &self,
         session_id: String,
-        enckey: SecKey,
+        auth: SigningAuth,
         nonce: String,
         public_key: String,
     ) -> Result<()> {
@@ -225,24 +314,27 @@ where
         let nonce = parse_hash_256(nonce).map_err(to_rpc_error)?;
         let public_key = parse_public_key(public_key).map_err(to_rpc_error)?;
 
-        self.client
-            .add_nonce(&session_id, &enckey, &nonce, &public_key)
-            .map_err(to_rpc_error)
+        self.with_auth(auth, &session_id, |_, enckey| {
+            self.client
+                .add_nonce(&session_id, enckey, &nonce, &public_key)
+        })
+        .map_err(to_rpc_error)
     }
 
-    fn partial_signature(&self, session_id: String, enckey: SecKey) -> Result<String> {
+    fn partial_signature(&self, session_id: String, auth: SigningAuth) -> Result<String> {
         let session_id = parse_hash_256(session_id).map_err(to_rpc_error)?;
 
-        self.client
-            .partial_signature(&session_id, &enckey)
-            .map(serialize_hash_256)
-            .map_err(to_rpc_error)
+        self.with_auth(auth, &session_id, |_, enckey| {
+            self.client.partial_signature(&session_id, enckey)
+        })
+        .map(serialize_hash_256)
+        .map_err(to_rpc_error)
     }
 
     fn add_partial_signature(
         &self,
         session_id: String,
-        enckey: SecKey,
+        auth: SigningAuth,
         partial_signature: String,
         public_key: String,
     ) -> Result<()> {
@@ -250,36 +342,109 @@ where
         let partial_signature = parse_hash_256(partial_signature).map_err(to_rpc_error)?;
         let public_key = parse_public_key(public_key).map_err(to_rpc_error)?;
 
-        self.client
-            .add_partial_signature(&session_id, &enckey, partial_signature, &public_key)
-            .map_err(to_rpc_error)
+        self.with_auth(auth, &session_id, |_, enckey| {
+            self.client
+                .add_partial_signature(&session_id, enckey, partial_signature, &public_key)
+        })
+        .map_err(to_rpc_error)
     }
 
-    fn signature(&self, session_id: String, enckey: SecKey) -> Result<String> {
+    fn export_nonce_commitment(&self, session_id: String, auth: SigningAuth) -> Result<String> {
         let session_id = parse_hash_256(session_id).map_err(to_rpc_error)?;
 
-        self.client
-            .signature(&session_id, &enckey)
-            .map(|sig| sig.to_string())
-            .map_err(to_rpc_error)
+        self.with_auth(auth, &session_id, |_, enckey| {
+            self.client.export_nonce_commitment(&session_id, enckey)
+        })
+        .map(|message| encode(message.encode()))
+        .map_err(to_rpc_error)
+    }
+
+    fn import_nonce_commitment(
+        &self,
+        session_id: String,
+        auth: SigningAuth,
+        message: String,
+    ) -> Result<()> {
+        let session_id = parse_hash_256(session_id).map_err(to_rpc_error)?;
+        let message = parse_message(message).map_err(to_rpc_error)?;
+
+        self.with_auth(auth, &session_id, |_, enckey| {
+            self.client
+                .import_nonce_commitment(&session_id, enckey, &message)
+        })
+        .map_err(to_rpc_error)
+    }
+
+    fn export_nonce(&self, session_id: String, auth: SigningAuth) -> Result<String> {
+        let session_id = parse_hash_256(session_id).map_err(to_rpc_error)?;
+
+        self.with_auth(auth, &session_id, |_, enckey| {
+            self.client.export_nonce(&session_id, enckey)
+        })
+        .map(|message| encode(message.encode()))
+        .map_err(to_rpc_error)
+    }
+
+    fn import_nonce(&self, session_id: String, auth: SigningAuth, message: String) -> Result<()> {
+        let session_id = parse_hash_256(session_id).map_err(to_rpc_error)?;
+        let message = parse_message(message).map_err(to_rpc_error)?;
+
+        self.with_auth(auth, &session_id, |_, enckey| {
+            self.client.import_nonce(&session_id, enckey, &message)
+        })
+        .map_err(to_rpc_error)
+    }
+
+    fn export_partial_signature(&self, session_id: String, auth: SigningAuth) -> Result<String> {
+        let session_id = parse_hash_256(session_id).map_err(to_rpc_error)?;
+
+        self.with_auth(auth, &session_id, |_, enckey| {
+            self.client.export_partial_signature(&session_id, enckey)
+        })
+        .map(|message| encode(message.encode()))
+        .map_err(to_rpc_error)
+    }
+
+    fn import_partial_signature(
+        &self,
+        session_id: String,
+        auth: SigningAuth,
+        message: String,
+    ) -> Result<()> {
+        let session_id = parse_hash_256(session_id).map_err(to_rpc_error)?;
+        let message = parse_message(message).map_err(to_rpc_error)?;
+
+        self.with_auth(auth, &session_id, |_, enckey| {
+            self.client
+                .import_partial_signature(&session_id, enckey, &message)
+        })
+        .map_err(to_rpc_error)
+    }
+
+    fn signature(&self, session_id: String, auth: SigningAuth) -> Result<String> {
+        let session_id = parse_hash_256(session_id).map_err(to_rpc_error)?;
+
+        self.with_auth(auth, &session_id, |_, enckey| {
+            self.client.signature(&session_id, enckey)
+        })
+        .map(|sig| sig.to_string())
+        .map_err(to_rpc_error)
     }
 
     fn broadcast_with_signature(
         &self,
-        request: WalletRequest,
+        auth: SigningAuth,
         session_id: String,
         unsigned_transaction: Tx,
     ) -> Result<String> {
         let session_id = parse_hash_256(session_id).map_err(to_rpc_error)?;
 
         let tx_aux = self
-            .client
-            .transaction(
-                &request.name,
-                &session_id,
-                &request.enckey,
-                unsigned_transaction,
-            )
+            .with_auth(auth, &session_id, |name, enckey| {
+                let name = name.ok_or_else(wallet_name_required)?;
+                self.client
+                    .transaction(name, &session_id, enckey, unsigned_transaction)
+            })
             .map_err(to_rpc_error)?;
 
         self.client
@@ -289,6 +454,13 @@ where
     }
 }
 
+fn wallet_name_required() -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        "Wallet request (or signing session) is required",
+    )
+}
+
 fn serialize_hash_256(hash: H256) -> String {
     encode(&hash)
 }
@@ -314,6 +486,22 @@ fn parse_hash_256(hash: String) -> CommonResult<H256> {
     Ok(new_hash)
 }
 
+/// Decodes a hex encoded multi-sig session message of a co-signer
+fn parse_message<M: Decode>(message: String) -> CommonResult<M> {
+    let bytes = decode(&message).chain(|| {
+        (
+            ErrorKind::DeserializationError,
+            format!("({}) is not a valid hex string", message),
+        )
+    })?;
+    M::decode(&mut bytes.as_slice()).chain(|| {
+        (
+            ErrorKind::DeserializationError,
+            "Unable to deserialize multi-sig session message",
+        )
+    })
+}
+
 fn parse_public_keys(public_keys: Vec<String>) -> CommonResult<Vec<PublicKey>> {
     public_keys
         .into_iter()
@@ -335,6 +523,7 @@ fn parse_public_key(public_key: String) -> CommonResult<PublicKey> {
 mod test {
     use super::*;
     use secstr::SecUtf8;
+    use std::collections::BTreeMap;
 
     use chain_core::init::coin::CoinError;
     use chain_core::state::ChainState;
@@ -346,6 +535,7 @@ mod test {
     use client_common::tendermint::Client;
     use client_common::TransactionObfuscation;
     use client_common::{PrivateKey, Result as CommonResult, SignedTransaction, Transaction};
+    use client_core::coin_selection::LargestFirst;
     use client_core::hd_wallet::HardwareKind;
     use client_core::service::HwKeyService;
     use client_core::signer::WalletSignerManager;
@@ -404,12 +594,108 @@ mod test {
         );
     }
 
+    #[test]
+    fn multi_sig_session_should_be_scoped_to_signing_session() {
+        let multisig_rpc = setup_multisig_rpc();
+        let name = "Default";
+        let (enckey, _) = multisig_rpc
+            .client
+            .new_wallet(
+                name,
+                &SecUtf8::from("passphrase"),
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        let declared = multisig_rpc
+            .client
+            .new_public_key(name, &enckey, None)
+            .unwrap();
+        let undeclared = multisig_rpc
+            .client
+            .new_public_key(name, &enckey, None)
+            .unwrap();
+        let other_signer = PublicKey::from(&PrivateKey::new().unwrap());
+        let signer_public_keys = vec![
+            declared.to_string(),
+            undeclared.to_string(),
+            other_signer.to_string(),
+        ];
+
+        let mut keys = BTreeMap::new();
+        keys.insert(
+            "address".to_owned(),
+            multisig_rpc
+                .client
+                .private_key(name, &enckey, &declared)
+                .unwrap()
+                .unwrap(),
+        );
+        let signing_session_id = multisig_rpc
+            .sessions
+            .begin(name, &enckey, keys, None)
+            .map(encode)
+            .unwrap();
+        let auth = || SigningAuth::SigningSession {
+            signing_session_id: signing_session_id.clone(),
+        };
+        let message = encode([1; 32]);
+
+        assert!(multisig_rpc
+            .new_session(
+                auth(),
+                message.clone(),
+                signer_public_keys.clone(),
+                undeclared.to_string()
+            )
+            .is_err());
+        let session_id = multisig_rpc
+            .new_session(
+                auth(),
+                message.clone(),
+                signer_public_keys.clone(),
+                declared.to_string(),
+            )
+            .unwrap();
+        assert!(multisig_rpc
+            .nonce_commitment(session_id.clone(), auth())
+            .is_ok());
+
+        // multi-sig sessions started with the wallet request aren't usable in the signing session
+        let wallet_request = WalletRequest {
+            name: name.to_owned(),
+            enckey: enckey.clone(),
+        };
+        let other_session_id = multisig_rpc
+            .new_session(
+                SigningAuth::Wallet(wallet_request),
+                message,
+                signer_public_keys,
+                undeclared.to_string(),
+            )
+            .unwrap();
+        assert!(multisig_rpc
+            .nonce_commitment(other_session_id.clone(), auth())
+            .is_err());
+        assert!(multisig_rpc
+            .nonce_commitment(other_session_id, SigningAuth::Enckey(enckey))
+            .is_ok());
+
+        multisig_rpc
+            .sessions
+            .end(&parse_hash_256(signing_session_id.clone()).unwrap())
+            .unwrap();
+        assert!(multisig_rpc.nonce_commitment(session_id, auth()).is_err());
+    }
+
     fn make_test_wallet_client(storage: MemoryStorage) -> TestWalletClient {
         let signer_manager = WalletSignerManager::new(storage.clone(), HwKeyService::default());
         let transaction_builder = DefaultWalletTransactionBuilder::new(
             signer_manager,
             ZeroFeeAlgorithm::default(),
             MockTransactionCipher,
+            LargestFirst,
         );
         DefaultWalletClient::new(
             storage,
@@ -423,7 +709,7 @@ mod test {
     fn setup_multisig_rpc() -> MultiSigRpcImpl<TestWalletClient> {
         let storage = MemoryStorage::default();
         let wallet_client = make_test_wallet_client(storage);
-        MultiSigRpcImpl::new(wallet_client)
+        MultiSigRpcImpl::new(wallet_client, SigningSessionService::new())
     }
 
     #[derive(Default, Clone)]
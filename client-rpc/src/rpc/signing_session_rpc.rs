@@ -0,0 +1,289 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use jsonrpc_core::Result;
+use jsonrpc_derive::rpc;
+use parity_scale_codec::Encode;
+
+use chain_core::common::{H256, HASH_SIZE_256};
+use chain_core::state::account::{StakedStateAddress, StakedStateOpWitness};
+use chain_core::tx::data::address::ExtendedAddr;
+use client_common::{
+    Error, ErrorKind, PrivateKey, PrivateKeyAction, Result as CommonResult, ResultExt, SecKey,
+    Transaction,
+};
+use client_core::service::SigningSessionService;
+use client_core::wallet::WalletRequest;
+use client_core::WalletClient;
+
+use crate::to_rpc_error;
+
+#[rpc(server)]
+pub trait SigningSessionRpc: Send + Sync {
+    /// Starts a signing session holding private keys of given (staking or transfer) addresses
+    /// only, and returns its id. The session expires after `ttl_seconds` (60 by default, 300 at
+    /// most) or when it's ended. Multi-sig sessions of the declared keys can also be started and
+    /// signed in it (see `SigningAuth` of the `multiSig_*` calls).
+    #[rpc(name = "signingSession_begin")]
+    fn begin(
+        &self,
+        request: WalletRequest,
+        addresses: Vec<String>,
+        ttl_seconds: Option<u64>,
+    ) -> Result<String>;
+
+    /// Signs a transaction with the key of given address (declared when the session was started).
+    /// Returns hex of encoded `StakedStateOpWitness` for staking addresses and of schnorr
+    /// signature for transfer addresses.
+    #[rpc(name = "signingSession_sign")]
+    fn sign(&self, session_id: String, address: String, transaction: Transaction)
+        -> Result<String>;
+
+    #[rpc(name = "signingSession_end")]
+    fn end(&self, session_id: String) -> Result<()>;
+}
+
+pub struct SigningSessionRpcImpl<T>
+where
+    T: WalletClient,
+{
+    client: T,
+    sessions: SigningSessionService,
+}
+
+impl<T> SigningSessionRpcImpl<T>
+where
+    T: WalletClient,
+{
+    pub fn new(client: T, sessions: SigningSessionService) -> Self {
+        SigningSessionRpcImpl { client, sessions }
+    }
+
+    fn private_key(
+        &self,
+        name: &str,
+        enckey: &SecKey,
+        address: &SessionAddress,
+    ) -> CommonResult<PrivateKey> {
+        let public_key = match address {
+            SessionAddress::Staking(StakedStateAddress::BasicRedeem(redeem_address)) => {
+                self.client.find_staking_key(name, enckey, redeem_address)?
+            }
+            SessionAddress::Transfer(transfer_address) => self
+                .client
+                .get_multisig_addresses(name, enckey)?
+                .into_iter()
+                .find(|multisig_address| multisig_address.to_extended_addr() == *transfer_address)
+                .map(|multisig_address| multisig_address.self_public_key),
+        }
+        .chain(|| {
+            (
+                ErrorKind::InvalidInput,
+                format!("Address {} not found in current wallet", address),
+            )
+        })?;
+
+        self.client
+            .private_key(name, enckey, &public_key)?
+            .chain(|| {
+                (
+                    ErrorKind::InvalidInput,
+                    format!("Private key of address {} is not available", address),
+                )
+            })
+    }
+}
+
+impl<T> SigningSessionRpc for SigningSessionRpcImpl<T>
+where
+    T: WalletClient + 'static,
+{
+    fn begin(
+        &self,
+        request: WalletRequest,
+        addresses: Vec<String>,
+        ttl_seconds: Option<u64>,
+    ) -> Result<String> {
+        let keys = addresses
+            .iter()
+            .map(|address| {
+                let address = SessionAddress::from_str(address)?;
+                let private_key = self.private_key(&request.name, &request.enckey, &address)?;
+                Ok((address.to_string(), private_key))
+            })
+            .collect::<CommonResult<BTreeMap<String, PrivateKey>>>()
+            .map_err(to_rpc_error)?;
+
+        self.sessions
+            .begin(
+                &request.name,
+                &request.enckey,
+                keys,
+                ttl_seconds.map(Duration::from_secs),
+            )
+            .map(hex::encode)
+            .map_err(to_rpc_error)
+    }
+
+    fn sign(
+        &self,
+        session_id: String,
+        address: String,
+        transaction: Transaction,
+    ) -> Result<String> {
+        let session_id = parse_session_id(&session_id).map_err(to_rpc_error)?;
+        let address = SessionAddress::from_str(&address).map_err(to_rpc_error)?;
+
+        self.sessions
+            .with_key(
+                &session_id,
+                &address.to_string(),
+                |private_key| match address {
+                    SessionAddress::Staking(_) => private_key
+                        .sign(&transaction)
+                        .map(|signature| StakedStateOpWitness::new(signature).encode()),
+                    SessionAddress::Transfer(_) => private_key
+                        .schnorr_sign(&transaction)
+                        .map(|signature| signature.serialize_default().to_vec()),
+                },
+            )
+            .map(hex::encode)
+            .map_err(to_rpc_error)
+    }
+
+    fn end(&self, session_id: String) -> Result<()> {
+        let session_id = parse_session_id(&session_id).map_err(to_rpc_error)?;
+        self.sessions.end(&session_id).map_err(to_rpc_error)
+    }
+}
+
+/// Address which can be declared in a signing session
+enum SessionAddress {
+    Staking(StakedStateAddress),
+    Transfer(ExtendedAddr),
+}
+
+impl FromStr for SessionAddress {
+    type Err = Error;
+
+    fn from_str(address: &str) -> CommonResult<Self> {
+        if let Ok(staking_address) = StakedStateAddress::from_str(address) {
+            return Ok(SessionAddress::Staking(staking_address));
+        }
+        ExtendedAddr::from_str(address)
+            .map(SessionAddress::Transfer)
+            .chain(|| {
+                (
+                    ErrorKind::DeserializationError,
+                    format!("Unable to deserialize address ({})", address),
+                )
+            })
+    }
+}
+
+impl std::fmt::Display for SessionAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionAddress::Staking(address) => write!(f, "{}", address),
+            SessionAddress::Transfer(address) => write!(f, "{}", address),
+        }
+    }
+}
+
+fn parse_session_id(session_id: &str) -> CommonResult<H256> {
+    let bytes = hex::decode(session_id).chain(|| {
+        (
+            ErrorKind::DeserializationError,
+            "Unable to deserialize session id",
+        )
+    })?;
+    if bytes.len() != HASH_SIZE_256 {
+        return Err(Error::new(
+            ErrorKind::DeserializationError,
+            "Session id should be of length 32",
+        ));
+    }
+    let mut session_id: H256 = [0; HASH_SIZE_256];
+    session_id.copy_from_slice(&bytes);
+    Ok(session_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use secstr::SecUtf8;
+
+    use chain_core::init::coin::Coin;
+    use chain_core::state::account::{StakedStateOpAttributes, UnbondTx};
+    use client_common::storage::MemoryStorage;
+    use client_core::hd_wallet::HardwareKind;
+    use client_core::types::WalletKind;
+    use client_core::wallet::DefaultWalletClient;
+
+    #[test]
+    fn signing_session_should_only_sign_with_declared_addresses() {
+        let wallet_client = DefaultWalletClient::new_read_only(MemoryStorage::default());
+        let name = "Default";
+        let (enckey, _) = wallet_client
+            .new_wallet(
+                name,
+                &SecUtf8::from("passphrase"),
+                WalletKind::Basic,
+                HardwareKind::LocalOnly,
+                None,
+            )
+            .unwrap();
+        let declared = wallet_client.new_staking_address(name, &enckey).unwrap();
+        let undeclared = wallet_client.new_staking_address(name, &enckey).unwrap();
+        let transfer = wallet_client.new_transfer_address(name, &enckey).unwrap();
+
+        let rpc = SigningSessionRpcImpl::new(wallet_client, SigningSessionService::new());
+        let request = WalletRequest {
+            name: name.to_owned(),
+            enckey,
+        };
+        let session_id = rpc
+            .begin(
+                request,
+                vec![declared.to_string(), transfer.to_string()],
+                None,
+            )
+            .unwrap();
+
+        let transaction = Transaction::UnbondStakeTransaction(UnbondTx::new(
+            declared,
+            0,
+            Coin::unit(),
+            StakedStateOpAttributes::new(0),
+        ));
+        assert!(rpc
+            .sign(
+                session_id.clone(),
+                declared.to_string(),
+                transaction.clone()
+            )
+            .is_ok());
+        assert!(rpc
+            .sign(
+                session_id.clone(),
+                transfer.to_string(),
+                transaction.clone()
+            )
+            .is_ok());
+        assert!(rpc
+            .sign(
+                session_id.clone(),
+                undeclared.to_string(),
+                transaction.clone()
+            )
+            .is_err());
+
+        rpc.end(session_id.clone()).unwrap();
+        assert!(rpc
+            .sign(session_id.clone(), declared.to_string(), transaction)
+            .is_err());
+        assert!(rpc.end(session_id).is_err());
+    }
+}
@@ -3,23 +3,26 @@ use std::str::FromStr;
 
 use jsonrpc_core::Result;
 use jsonrpc_derive::rpc;
+use serde::{Deserialize, Serialize};
 
 use crate::{rpc_error_from_string, to_rpc_error};
 use chain_core::init::coin::Coin;
 use chain_core::state::account::{
-    ConfidentialInit, CouncilNodeMeta, MLSInit, StakedState, StakedStateAddress,
-    StakedStateOpAttributes,
+    announced_endpoints_supported, check_announced_endpoints, ConfidentialInit, CouncilNodeMeta,
+    MLSInit, NodeEndpoint, StakedState, StakedStateAddress, StakedStateOpAttributes,
 };
 use chain_core::state::tendermint::TendermintValidatorPubKey;
-use chain_core::tx::data::access::{TxAccess, TxAccessPolicy};
 use chain_core::tx::data::address::ExtendedAddr;
 use chain_core::tx::data::attribute::TxAttributes;
 use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::data::output::TxOut;
+use chain_core::tx::TxAux;
 use client_common::temporary_mls_init;
 use client_common::{Error, ErrorKind, PublicKey, Result as CommonResult, ResultExt, Transaction};
+use client_core::types::BroadcastResult;
 use client_core::wallet::WalletRequest;
 use client_core::WalletClient;
+use client_network::network_ops::PreflightReport;
 use client_network::NetworkOpsClient;
 
 #[rpc(server)]
@@ -30,7 +33,7 @@ pub trait StakingRpc: Send + Sync {
         request: WalletRequest,
         to_address: String,
         inputs: Vec<TxoPointer>,
-    ) -> Result<String>;
+    ) -> Result<BroadcastResult>;
 
     #[rpc(name = "staking_depositAmountStake")]
     fn deposit_amount_stake(
@@ -38,7 +41,7 @@ pub trait StakingRpc: Send + Sync {
         request: WalletRequest,
         to_address: String,
         amount: Coin,
-    ) -> Result<String>;
+    ) -> Result<BroadcastResult>;
 
     #[rpc(name = "staking_state")]
     fn state(&self, name: String, address: StakedStateAddress) -> Result<StakedState>;
@@ -49,7 +52,7 @@ pub trait StakingRpc: Send + Sync {
         request: WalletRequest,
         staking_address: String,
         amount: Coin,
-    ) -> Result<String>;
+    ) -> Result<BroadcastResult>;
 
     #[rpc(name = "staking_withdrawAllUnbondedStake")]
     fn withdraw_all_unbonded_stake(
@@ -58,10 +61,10 @@ pub trait StakingRpc: Send + Sync {
         from_address: String,
         to_address: String,
         view_keys: Vec<String>,
-    ) -> Result<String>;
+    ) -> Result<BroadcastResult>;
 
     #[rpc(name = "staking_unjail")]
-    fn unjail(&self, request: WalletRequest, unjail_address: String) -> Result<String>;
+    fn unjail(&self, request: WalletRequest, unjail_address: String) -> Result<BroadcastResult>;
 
     #[rpc(name = "staking_validatorNodeJoin")]
     fn node_join(
@@ -71,7 +74,34 @@ pub trait StakingRpc: Send + Sync {
         validator_pubkey: String,
         staking_address: String,
         keypackage: String,
-    ) -> Result<String>;
+        preflight_only: Option<bool>,
+        announced_endpoints: Option<Vec<NodeEndpoint>>,
+    ) -> Result<NodeJoinResult>;
+
+    #[rpc(name = "staking_updateNode")]
+    fn update_node(
+        &self,
+        request: WalletRequest,
+        staking_address: String,
+        announced_endpoints: Vec<NodeEndpoint>,
+    ) -> Result<BroadcastResult>;
+
+    #[rpc(name = "staking_announcedEndpoints")]
+    fn announced_endpoints(
+        &self,
+        name: String,
+        address: StakedStateAddress,
+    ) -> Result<Vec<NodeEndpoint>>;
+}
+
+/// Result of `staking_validatorNodeJoin`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NodeJoinResult {
+    /// the broadcasted transaction
+    Broadcast(BroadcastResult),
+    /// outcome of the checks (with `preflight_only`, nothing is broadcasted)
+    Preflight(PreflightReport),
 }
 
 pub struct StakingRpcImpl<T, N>
@@ -96,6 +126,36 @@ where
             network_id,
         }
     }
+
+    /// Current block height, used as the broadcast height hint of transactions which are not
+    /// tracked as pending by the wallet
+    fn current_block_height(&self) -> Result<u64> {
+        self.client.get_current_block_height().map_err(to_rpc_error)
+    }
+
+    /// Broadcasts a transaction spending the nonce of given staking address, reconciling the
+    /// locally tracked nonce with the chain if it is rejected
+    fn broadcast_staking_transaction(
+        &self,
+        request: &WalletRequest,
+        address: &StakedStateAddress,
+        transaction: &TxAux,
+    ) -> Result<()> {
+        if let Err(err) = self.client.broadcast_transaction(transaction) {
+            if let Err(reconcile_err) =
+                self.ops_client
+                    .reconcile_nonce(&request.name, address, true)
+            {
+                log::warn!(
+                    "Unable to reconcile nonce of {}: {}",
+                    address,
+                    reconcile_err
+                );
+            }
+            return Err(to_rpc_error(err));
+        }
+        Ok(())
+    }
 }
 
 impl<T, N> StakingRpc for StakingRpcImpl<T, N>
@@ -108,7 +168,7 @@ where
         request: WalletRequest,
         to_address: String,
         inputs: Vec<TxoPointer>,
-    ) -> Result<String> {
+    ) -> Result<BroadcastResult> {
         let to_address = StakedStateAddress::from_str(&to_address)
             .chain(|| {
                 (
@@ -127,6 +187,19 @@ where
             return Err( rpc_error_from_string("Given transaction inputs are not present in unspent transactions (synchronizing your wallet may help)".into()));
         }
 
+        // explicitly selected inputs override the confirmation depth of wallet
+        let immature_inputs = self
+            .client
+            .immature_inputs(&request.name, &request.enckey, &inputs)
+            .map_err(to_rpc_error)?;
+        for input in immature_inputs.iter() {
+            log::warn!(
+                "Spending input {}:{} which doesn't have enough confirmations yet",
+                hex::encode(input.id),
+                input.index
+            );
+        }
+
         let transactions = inputs
             .into_iter()
             .map(|txo_pointer| {
@@ -155,17 +228,13 @@ where
             .broadcast_transaction(&transaction)
             .map_err(to_rpc_error)?;
 
+        let broadcast_height = tx_pending.block_height;
         // update the wallet pending transaction state
         self.client
-            .update_tx_pending_state(
-                &request.name,
-                &request.enckey,
-                transaction.tx_id(),
-                tx_pending,
-            )
+            .update_tx_pending_state(&request.name, &request.enckey, &transaction, tx_pending)
             .map_err(to_rpc_error)?;
 
-        Ok(hex::encode(transaction.tx_id()))
+        Ok(BroadcastResult::new(&transaction, None, broadcast_height))
     }
 
     /// deposit amount coin to a deposit address
@@ -178,7 +247,7 @@ where
         request: WalletRequest,
         to_address: String,
         amount: Coin,
-    ) -> Result<String> {
+    ) -> Result<BroadcastResult> {
         let to_staking_address = StakedStateAddress::from_str(&to_address)
             .chain(|| {
                 (
@@ -208,7 +277,8 @@ where
                 &mut BTreeSet::new(),
                 self.network_id,
             )
-            .map_err(to_rpc_error)?;
+            .map_err(to_rpc_error)?
+            .tx_id;
 
         // 2. use the outputs[0] to deposit
         let transaction = self
@@ -242,16 +312,12 @@ where
         self.client
             .broadcast_transaction(&transaction)
             .map_err(to_rpc_error)?;
+        let broadcast_height = tx_pending.block_height;
         // update the wallet pending transaction state
         self.client
-            .update_tx_pending_state(
-                &request.name,
-                &request.enckey,
-                transaction.tx_id(),
-                tx_pending,
-            )
+            .update_tx_pending_state(&request.name, &request.enckey, &transaction, tx_pending)
             .map_err(to_rpc_error)?;
-        Ok(hex::encode(transaction.tx_id()))
+        Ok(BroadcastResult::new(&transaction, None, broadcast_height))
     }
 
     fn state(&self, name: String, address: StakedStateAddress) -> Result<StakedState> {
@@ -265,7 +331,7 @@ where
         request: WalletRequest,
         staking_address: String,
         amount: Coin,
-    ) -> Result<String> {
+    ) -> Result<BroadcastResult> {
         let attr = StakedStateOpAttributes::new(self.network_id);
         let addr = StakedStateAddress::from_str(&staking_address)
             .chain(|| {
@@ -291,11 +357,10 @@ where
             )
             .map_err(to_rpc_error)?;
 
-        self.client
-            .broadcast_transaction(&transaction)
-            .map_err(to_rpc_error)?;
+        let broadcast_height = self.current_block_height()?;
+        self.broadcast_staking_transaction(&request, &addr, &transaction)?;
 
-        Ok(hex::encode(transaction.tx_id()))
+        Ok(BroadcastResult::new(&transaction, None, broadcast_height))
     }
 
     fn withdraw_all_unbonded_stake(
@@ -304,7 +369,7 @@ where
         from_address: String,
         to_address: String,
         view_keys: Vec<String>,
-    ) -> Result<String> {
+    ) -> Result<BroadcastResult> {
         let from_address = StakedStateAddress::from_str(&from_address)
             .chain(|| {
                 (
@@ -334,16 +399,10 @@ where
 
         view_keys.insert(view_key);
 
-        let access_policies: BTreeSet<_> = view_keys
-            .iter()
-            .map(|key| TxAccessPolicy {
-                view_key: key.into(),
-                access: TxAccess::AllData,
-            })
-            .collect();
-
-        let attributes =
-            TxAttributes::new_with_access(self.network_id, access_policies.into_iter().collect());
+        let attributes = TxAttributes::builder(self.network_id)
+            .allow_view_many(view_keys.iter().map(Into::into))
+            .build()
+            .map_err(to_rpc_error)?;
 
         let (transaction, tx_pending) = self
             .ops_client
@@ -357,22 +416,16 @@ where
             )
             .map_err(to_rpc_error)?;
 
-        self.client
-            .broadcast_transaction(&transaction)
-            .map_err(to_rpc_error)?;
+        self.broadcast_staking_transaction(&request, &from_address, &transaction)?;
+        let broadcast_height = tx_pending.block_height;
         // update the wallet pending transaction state
         self.client
-            .update_tx_pending_state(
-                &request.name,
-                &request.enckey,
-                transaction.tx_id(),
-                tx_pending,
-            )
+            .update_tx_pending_state(&request.name, &request.enckey, &transaction, tx_pending)
             .map_err(to_rpc_error)?;
-        Ok(hex::encode(transaction.tx_id()))
+        Ok(BroadcastResult::new(&transaction, None, broadcast_height))
     }
 
-    fn unjail(&self, request: WalletRequest, unjail_address: String) -> Result<String> {
+    fn unjail(&self, request: WalletRequest, unjail_address: String) -> Result<BroadcastResult> {
         let unjail_address = StakedStateAddress::from_str(&unjail_address)
             .chain(|| {
                 (
@@ -395,11 +448,10 @@ where
             )
             .map_err(to_rpc_error)?;
 
-        self.client
-            .broadcast_transaction(&transaction)
-            .map_err(to_rpc_error)?;
+        let broadcast_height = self.current_block_height()?;
+        self.broadcast_staking_transaction(&request, &unjail_address, &transaction)?;
 
-        Ok(hex::encode(transaction.tx_id()))
+        Ok(BroadcastResult::new(&transaction, None, broadcast_height))
     }
 
     fn node_join(
@@ -409,7 +461,9 @@ where
         validator_pubkey: String,
         staking_addr: String,
         keypackage: String,
-    ) -> Result<String> {
+        preflight_only: Option<bool>,
+        announced_endpoints: Option<Vec<NodeEndpoint>>,
+    ) -> Result<NodeJoinResult> {
         let attributes = StakedStateOpAttributes::new(self.network_id);
         let staking_account_address = staking_addr
             .parse::<StakedStateAddress>()
@@ -420,8 +474,51 @@ where
                 )
             })
             .map_err(to_rpc_error)?;
-        let node_metadata =
-            get_node_metadata(&validator_node_name, &validator_pubkey, &keypackage)?;
+        // FIXME: MLSPlaintexts instead of keypackage
+        let keypackage = base64::decode(keypackage)
+            .err_kind(ErrorKind::InvalidInput, || "invalid base64")
+            .map_err(to_rpc_error)?;
+        let announced_endpoints = announced_endpoints.unwrap_or_default();
+        check_announced_endpoints(&announced_endpoints)
+            .map_err(|e| to_rpc_error(Error::new(ErrorKind::InvalidInput, e)))?;
+        if !announced_endpoints.is_empty() && !announced_endpoints_supported() {
+            return Err(to_rpc_error(Error::new(
+                ErrorKind::InvalidInput,
+                "Announced endpoints are not supported in the current app version",
+            )));
+        }
+
+        if preflight_only.unwrap_or(false) {
+            // keypackage is checked as given (before it's put into the add proposal)
+            let mut node_metadata = get_node_metadata(
+                &validator_node_name,
+                &validator_pubkey,
+                MLSInit::Genesis(keypackage),
+            )?;
+            node_metadata.announced_endpoints = announced_endpoints;
+            let report = self
+                .ops_client
+                .preflight_node_join(
+                    &request.name,
+                    &request.enckey,
+                    staking_account_address,
+                    &attributes,
+                    &node_metadata,
+                    true,
+                )
+                .map_err(to_rpc_error)?;
+            return Ok(NodeJoinResult::Preflight(report));
+        }
+
+        let mut node_metadata = get_node_metadata(
+            &validator_node_name,
+            &validator_pubkey,
+            MLSInit::NodeJoin {
+                add: temporary_mls_init(keypackage),
+                commit: vec![],
+            },
+        )?;
+        node_metadata.announced_endpoints = announced_endpoints;
         let transaction = self
             .ops_client
             .create_node_join_transaction(
@@ -433,11 +530,62 @@ where
                 true,
             )
             .map_err(to_rpc_error)?;
-        self.client
-            .broadcast_transaction(&transaction)
+        let broadcast_height = self.current_block_height()?;
+        self.broadcast_staking_transaction(&request, &staking_account_address, &transaction)?;
+
+        Ok(NodeJoinResult::Broadcast(BroadcastResult::new(
+            &transaction,
+            None,
+            broadcast_height,
+        )))
+    }
+
+    fn update_node(
+        &self,
+        request: WalletRequest,
+        staking_address: String,
+        announced_endpoints: Vec<NodeEndpoint>,
+    ) -> Result<BroadcastResult> {
+        let staking_address = StakedStateAddress::from_str(&staking_address)
+            .chain(|| {
+                (
+                    ErrorKind::DeserializationError,
+                    format!(
+                        "Unable to deserialize staking address ({})",
+                        staking_address
+                    ),
+                )
+            })
             .map_err(to_rpc_error)?;
 
-        Ok(hex::encode(transaction.tx_id()))
+        let attributes = StakedStateOpAttributes::new(self.network_id);
+
+        let transaction = self
+            .ops_client
+            .create_update_node_transaction(
+                &request.name,
+                &request.enckey,
+                staking_address,
+                attributes,
+                announced_endpoints,
+                true,
+            )
+            .map_err(to_rpc_error)?;
+
+        let broadcast_height = self.current_block_height()?;
+        self.broadcast_staking_transaction(&request, &staking_address, &transaction)?;
+
+        Ok(BroadcastResult::new(&transaction, None, broadcast_height))
+    }
+
+    fn announced_endpoints(
+        &self,
+        name: String,
+        address: StakedStateAddress,
+    ) -> Result<Vec<NodeEndpoint>> {
+        self.ops_client
+            .get_announced_endpoints(&name, &address, true)
+            .map_err(to_rpc_error)
     }
 }
 
@@ -445,7 +593,7 @@ where
 fn get_node_metadata(
     validator_name: &str,
     validator_pubkey: &str,
-    keypackage: &str,
+    init_payload: MLSInit,
 ) -> Result<CouncilNodeMeta> {
     let decoded_pubkey = base64::decode(validator_pubkey)
         .chain(|| {
@@ -467,20 +615,10 @@ fn get_node_metadata(
     let mut pubkey_bytes = [0; 32];
     pubkey_bytes.copy_from_slice(&decoded_pubkey);
 
-    // FIXME: MLSPlaintexts instead of keypackage
-    let keypackage = base64::decode(keypackage)
-        .err_kind(ErrorKind::InvalidInput, || "invalid base64")
-        .map_err(to_rpc_error)?;
-
     Ok(CouncilNodeMeta::new_with_details(
         validator_name.to_string(),
         None,
         TendermintValidatorPubKey::Ed25519(pubkey_bytes),
-        ConfidentialInit {
-            init_payload: MLSInit::NodeJoin {
-                add: temporary_mls_init(keypackage),
-                commit: vec![],
-            },
-        },
+        ConfidentialInit { init_payload },
     ))
 }
@@ -2,6 +2,7 @@ use super::sync_worker::SyncWorker;
 use super::sync_worker::WorkerShared;
 use crate::to_rpc_error;
 use client_common::tendermint::Client;
+use client_common::CancellationToken;
 use client_common::Storage;
 use client_common::TransactionObfuscation;
 use client_core::wallet::syncer::{
@@ -20,6 +21,7 @@ const NOTIFICATION_TIME: u64 = 2;
 const ERROR_NOTIFICATION_TIME: u64 = 30;
 pub trait CBindingCallback: Send + Sync {
     fn progress(&mut self, current: u64, start: u64, end: u64) -> i32;
+    fn reorg_detected(&mut self, _block_height: u64, _common_block_height: u64) {}
     fn set_user(&mut self, user: u64);
     fn get_user(&self) -> u64;
 }
@@ -44,6 +46,17 @@ pub struct RunSyncProgressResult {
     pub current: u64,
     pub start: u64,
     pub end: u64,
+    /// last reorg detected during sync
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reorg_detected: Option<ReorgDetectedResult>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct ReorgDetectedResult {
+    /// last synced block height before rollback
+    pub block_height: u64,
+    /// height of last common block which wallet state is rolled back to
+    pub common_block_height: u64,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -124,6 +137,7 @@ fn process_sync<S, C, O, T, L>(
     reset: bool,
     progress_callback: Option<CBindingCore>,
     recover_address: T,
+    cancellation: &CancellationToken,
 ) -> Result<()>
 where
     S: Storage + 'static,
@@ -144,13 +158,15 @@ where
     }
 
     if progress_callback.is_none() {
-        return syncer.sync(|_| true).map_err(to_rpc_error);
+        return syncer
+            .sync_cancellable(cancellation, |_| true)
+            .map_err(to_rpc_error);
     }
 
     let mut init_block_height = 0;
     let mut final_block_height = 0;
     syncer
-        .sync(|report: ProgressReport| -> bool {
+        .sync_cancellable(cancellation, |report: ProgressReport| -> bool {
             match report {
                 ProgressReport::Init {
                     start_block_height,
@@ -187,6 +203,39 @@ where
                     }
                     true
                 }
+                ProgressReport::ReorgDetected {
+                    wallet_name,
+                    block_height,
+                    common_block_height,
+                } => {
+                    log::warn!(
+                        "wallet {} rolled back from block {} to block {} because of reorg",
+                        wallet_name,
+                        block_height,
+                        common_block_height
+                    );
+                    init_block_height = common_block_height;
+                    if let Some(delegator) = &progress_callback {
+                        let mut user_callback =
+                            delegator.data.lock().expect("get cbinding callback");
+                        user_callback.reorg_detected(block_height, common_block_height);
+                    }
+                    true
+                }
+                ProgressReport::BlocksPruned {
+                    wallet_name,
+                    from_block_height,
+                    earliest_available,
+                } => {
+                    log::warn!(
+                        "wallet {} skipped blocks {} to {} pruned by the node",
+                        wallet_name,
+                        from_block_height,
+                        earliest_available - 1
+                    );
+                    init_block_height = earliest_available - 1;
+                    true
+                }
             }
         })
         .map_err(to_rpc_error)
@@ -231,6 +280,11 @@ where
             localworker.lock().expect("get sync worker lock").add(&name);
             let node = localworker.lock().expect("get sync worker lock").get(&name);
             let syncnode = node.expect("get progress callback");
+            let cancellation = syncnode
+                .lock()
+                .expect("get sync worker node lock")
+                .cancellation
+                .clone();
             let usercallback = Some(CBindingCore { data: syncnode });
             loop {
                 let result = process_sync(
@@ -239,8 +293,12 @@ where
                     reset,
                     usercallback.clone(),
                     recover_address.clone(),
+                    &cancellation,
                 );
                 log::info!("process_sync finished {} {:?}", name, result);
+                if cancellation.is_cancelled() {
+                    break;
+                }
                 if let Err(error_message) = result {
                     localworker
                         .lock()
@@ -305,6 +363,7 @@ where
                 sync_request.reset,
                 self.progress_callback.clone(),
                 self.recover_address.clone(),
+                &CancellationToken::new(),
             )?;
 
             Ok(RunSyncResult::default())
@@ -1,5 +1,6 @@
-use super::sync_rpc::{CBindingCallback, RunSyncProgressResult};
+use super::sync_rpc::{CBindingCallback, ReorgDetectedResult, RunSyncProgressResult};
 use crate::rpc_error_from_string;
+use client_common::CancellationToken;
 use jsonrpc_core::Result;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -8,7 +9,8 @@ use std::time::Instant;
 pub struct SyncWorkerNode {
     pub user_data: u64,
     pub progress: RunSyncProgressResult,
-    pub stop: bool,
+    /// cancels the running sync (and stops the sync loop)
+    pub cancellation: CancellationToken,
     counter: Instant,
 }
 impl SyncWorkerNode {
@@ -16,15 +18,15 @@ impl SyncWorkerNode {
         let mut ret = SyncWorkerNode {
             progress: RunSyncProgressResult::default(),
             user_data: 0,
-            stop: false,
+            cancellation: CancellationToken::new(),
             counter: Instant::now(),
         };
         ret.progress.name = name.to_string();
         ret
     }
-    fn set_stop(&mut self, flag: bool) {
-        log::info!("stop sync wallet {} flag {}", self.progress.name, flag);
-        self.stop = flag;
+    fn cancel(&self) {
+        log::info!("stop sync wallet {}", self.progress.name);
+        self.cancellation.cancel();
     }
     fn set_complete(&mut self) {
         self.progress.percent = 100.0;
@@ -40,6 +42,17 @@ impl CBindingCallback for SyncWorkerNode {
         self.user_data
     }
 
+    fn reorg_detected(&mut self, block_height: u64, common_block_height: u64) {
+        self.progress.message = format!(
+            "sync {} reorg detected, rolled back from block {} to block {}",
+            self.progress.name, block_height, common_block_height
+        );
+        self.progress.reorg_detected = Some(ReorgDetectedResult {
+            block_height,
+            common_block_height,
+        });
+    }
+
     fn progress(&mut self, current: u64, start: u64, end: u64) -> i32 {
         let rate = if current >= start && end > start {
             let gap: f32 = (end - start) as f32;
@@ -71,7 +84,7 @@ impl CBindingCallback for SyncWorkerNode {
         self.progress.message = status;
 
         // OK
-        if self.stop {
+        if self.cancellation.is_cancelled() {
             0 // stop
         } else {
             1 // cotninue
@@ -126,7 +139,7 @@ impl SyncWorker {
 
     pub fn stop(&self, key: &str) -> Result<()> {
         if let Some(value) = self.works.get(key) {
-            value.lock().unwrap().set_stop(true);
+            value.lock().unwrap().cancel();
             Ok(())
         } else {
             Err(rpc_error_from_string(
@@ -137,7 +150,7 @@ impl SyncWorker {
 
     pub fn get_stop(&self, key: &str) -> bool {
         if let Some(value) = self.works.get(key) {
-            value.lock().unwrap().stop
+            value.lock().unwrap().cancellation.is_cancelled()
         } else {
             true
         }
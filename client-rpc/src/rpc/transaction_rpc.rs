@@ -2,14 +2,15 @@ use jsonrpc_core::Result;
 use jsonrpc_derive::rpc;
 use serde::{Deserialize, Serialize, Serializer};
 
-use chain_core::tx::data::access::{TxAccess, TxAccessPolicy};
 use chain_core::tx::data::attribute::TxAttributes;
 use chain_core::tx::data::input::TxoPointer;
 use chain_core::tx::data::output::TxOut;
 use chain_core::tx::data::{Tx, TxId};
 use chain_core::tx::TransactionId;
 use client_common::PublicKey;
-use std::collections::BTreeSet;
+use client_core::types::{parse_payment_request, PaymentRequest};
+
+use crate::to_rpc_error;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RawTransaction {
@@ -37,6 +38,9 @@ pub trait TransactionRpc: Send + Sync {
         outputs: Vec<TxOut>,
         view_keys: Vec<PublicKey>,
     ) -> Result<RawTransaction>;
+
+    #[rpc(name = "chain_parsePaymentRequest")]
+    fn parse_payment_request(&self, uri: String) -> Result<PaymentRequest>;
 }
 
 pub struct TransactionRpcImpl {
@@ -56,16 +60,10 @@ impl TransactionRpc for TransactionRpcImpl {
         outputs: Vec<TxOut>,
         view_keys: Vec<PublicKey>,
     ) -> Result<RawTransaction> {
-        let access_policies: BTreeSet<_> = view_keys
-            .iter()
-            .map(|key| TxAccessPolicy {
-                view_key: key.into(),
-                access: TxAccess::AllData,
-            })
-            .collect();
-
-        let attributes =
-            TxAttributes::new_with_access(self.network_id, access_policies.into_iter().collect());
+        let attributes = TxAttributes::builder(self.network_id)
+            .allow_view_many(view_keys.iter().map(Into::into))
+            .build()
+            .map_err(to_rpc_error)?;
 
         let tx = Tx {
             inputs,
@@ -76,6 +74,10 @@ impl TransactionRpc for TransactionRpcImpl {
 
         Ok(RawTransaction { tx, tx_id })
     }
+
+    fn parse_payment_request(&self, uri: String) -> Result<PaymentRequest> {
+        parse_payment_request(&uri).map_err(to_rpc_error)
+    }
 }
 
 #[cfg(test)]
@@ -84,6 +86,7 @@ mod test {
     use chain_core::init::address::CroAddress;
     use chain_core::init::coin::Coin;
     use chain_core::init::network::Network;
+    use chain_core::tx::data::access::{TxAccess, TxAccessPolicy};
     use chain_core::tx::data::address::ExtendedAddr;
     use client_common::PrivateKey;
 
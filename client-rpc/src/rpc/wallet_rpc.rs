@@ -1,47 +1,365 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use jsonrpc_core::Result;
+use jsonrpc_core::{Error, ErrorCode, Result};
 use jsonrpc_derive::rpc;
-use secstr::SecUtf8;
+use secstr::{SecStr, SecUtf8};
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
 
+use chain_core::common::{decode_all_strict, Timespec, H256, HASH_SIZE_256};
 use chain_core::init::coin::Coin;
 use chain_core::tx::data::address::ExtendedAddr;
-use client_common::{PrivateKey, PublicKey, Result as CommonResult, SecKey};
-use client_core::service::WalletInfo;
-use client_core::transaction_builder::SignedTransferTransaction;
-use client_core::types::{TransactionChange, WalletBalance, WalletKind};
+use chain_core::tx::data::attribute::TxAttributes;
+use chain_core::tx::data::input::str2txid;
+use chain_core::tx::data::output::TxOut;
+use client_common::{
+    Error as CommonError, ErrorKind, PrivateKey, PublicKey, Result as CommonResult, SecKey,
+};
+use client_core::service::{BackupBlob, WalletInfo, CONTACT_PREFIX};
+use client_core::transaction_builder::{FeeTip, SignedTransferTransaction};
+use client_core::types::fee_summary::NaiveDate;
+use client_core::types::payment_request::coin_to_decimal_string;
+use client_core::types::{
+    parse_payment_request, qr_payload_for_address, AddressType, ArchiveMark, BroadcastResult,
+    CompliancePolicy, FeeDiscrepancy, FeeSummary, HistoryFilter, PaymentRequest,
+    PendingTransactionInfo, RecordClass, RetentionPolicy, TransactionChange, TransactionDetails,
+    WalletAddress, WalletBalance, WalletKind,
+};
 use client_core::wallet::{CreateWalletRequest, WalletRequest};
 #[cfg(feature = "experimental")]
 use client_core::MultiSigWalletClient;
-use client_core::{Mnemonic, UnspentTransactions, WalletClient};
+use client_core::{Mnemonic, MnemonicLanguage, UnspentTransactions, WalletClient};
 use parity_scale_codec::{Decode, Encode};
 
 use crate::{rpc_error_from_string, to_rpc_error};
-use client_core::hd_wallet::HardwareKind;
+use client_core::hd_wallet::{DerivationPath, HardwareKind};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DetailedWalletBalance {
+    #[serde(flatten)]
+    balance: WalletBalance,
+    /// only reported with the details
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min_confirmations: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupResponse {
+    /// hex encoded id of the snapshot (to base later incremental backups on)
+    snapshot_id: String,
+    /// hex encoded id of the snapshot an incremental backup is based on
+    parent_snapshot_id: Option<String>,
+    /// hex encoded backup
+    backup: String,
+}
+
+impl From<BackupBlob> for BackupResponse {
+    fn from(blob: BackupBlob) -> Self {
+        BackupResponse {
+            snapshot_id: hex::encode(&blob.snapshot_id),
+            parent_snapshot_id: blob.parent_snapshot_id.map(hex::encode),
+            backup: hex::encode(blob.encode()),
+        }
+    }
+}
+
+/// Address of the wallet with the derivation path of its key and its label
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddressDetails {
+    address: String,
+    /// `transfer` or `staking`
+    address_type: String,
+    /// `None` for the imported keys (and the ones derived before the paths were recorded)
+    derivation_path: Option<DerivationPath>,
+    label: Option<String>,
+}
+
+/// Fee summary with the amounts in (decimal) CRO
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeeSummaryResponse {
+    total: String,
+    /// (`YYYY-MM-DD` date, total of the day) pairs
+    per_day: Vec<(String, String)>,
+    tx_count: usize,
+    /// transactions whose fee reported by the chain differs from the one estimated by the wallet
+    discrepancies: Vec<FeeDiscrepancyResponse>,
+    /// `YYYY-MM-DD` date up to which the records are archived, if the summary is truncated
+    archived_until: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeeDiscrepancyResponse {
+    /// hex encoded transaction id
+    transaction_id: String,
+    estimated: String,
+    reported: String,
+}
+
+impl From<FeeSummary> for FeeSummaryResponse {
+    fn from(summary: FeeSummary) -> Self {
+        FeeSummaryResponse {
+            total: coin_to_decimal_string(summary.total),
+            per_day: summary
+                .per_day
+                .into_iter()
+                .map(|(date, fee)| (date.to_string(), coin_to_decimal_string(fee)))
+                .collect(),
+            tx_count: summary.tx_count,
+            discrepancies: summary
+                .discrepancies
+                .into_iter()
+                .map(FeeDiscrepancyResponse::from)
+                .collect(),
+            archived_until: summary.archived_until.map(|date| date.to_string()),
+        }
+    }
+}
+
+impl From<FeeDiscrepancy> for FeeDiscrepancyResponse {
+    fn from(discrepancy: FeeDiscrepancy) -> Self {
+        FeeDiscrepancyResponse {
+            transaction_id: hex::encode(discrepancy.transaction_id),
+            estimated: coin_to_decimal_string(discrepancy.estimated),
+            reported: coin_to_decimal_string(discrepancy.reported),
+        }
+    }
+}
+
+/// Error code returned when `passphrase_confirmation` doesn't match `passphrase`
+pub const PASSPHRASE_MISMATCH_ERROR_CODE: i64 = -32010;
+/// Error code returned when passphrase checks of a wallet are throttled after repeated failures
+pub const PASSPHRASE_THROTTLED_ERROR_CODE: i64 = -32011;
+/// Error code returned when the compliance policy of a wallet rejects a transfer to its own addresses
+pub const SELF_TRANSFER_ERROR_CODE: i64 = -32012;
+
+/// Converts the errors of the transfers, with a distinct code for the rejected self transfers
+fn transfer_rpc_error(error: CommonError) -> Error {
+    if error.kind() == ErrorKind::SelfTransfer {
+        Error {
+            code: ErrorCode::ServerError(SELF_TRANSFER_ERROR_CODE),
+            message: error.to_string(),
+            data: None,
+        }
+    } else {
+        to_rpc_error(error)
+    }
+}
+
+/// Resolves the address and amount of a transfer to `to_address`, which is either an address or a
+/// payment request (whose amount, if any, has to match `amount` unless it's zero)
+fn transfer_destination(to_address: &str, amount: Coin) -> Result<(ExtendedAddr, Coin)> {
+    if PaymentRequest::is_payment_request(to_address) {
+        let payment_request = parse_payment_request(to_address).map_err(to_rpc_error)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(to_rpc_error)?
+            .as_secs();
+        payment_request
+            .ensure_not_expired(now)
+            .map_err(to_rpc_error)?;
+        let amount = match payment_request.amount {
+            Some(requested) if amount == Coin::zero() || amount == requested => requested,
+            Some(requested) => {
+                return Err(rpc_error_from_string(format!(
+                    "Amount {} does not match amount {} of payment request",
+                    amount, requested
+                )))
+            }
+            None => amount,
+        };
+        Ok((payment_request.address, amount))
+    } else {
+        let address = to_address
+            .parse::<ExtendedAddr>()
+            .map_err(|err| rpc_error_from_string(format!("{}", err)))?;
+        Ok((address, amount))
+    }
+}
+
+/// Number of failed passphrase checks of a wallet before they are throttled
+const PASSPHRASE_FREE_ATTEMPTS: u32 = 3;
+/// Upper bound of the delay between passphrase checks of a wallet
+const PASSPHRASE_MAX_DELAY: Duration = Duration::from_secs(300);
+
+/// Slows down brute-forcing of wallet passphrases: after `PASSPHRASE_FREE_ATTEMPTS` consecutive
+/// failures, the next check of the wallet is only allowed after a delay which doubles with every
+/// further failure (up to `PASSPHRASE_MAX_DELAY`)
+#[derive(Debug, Default)]
+struct PassphraseThrottle {
+    /// wallet name -> (consecutive failures, time of the last failure)
+    failures: Mutex<HashMap<String, (u32, Instant)>>,
+}
+
+impl PassphraseThrottle {
+    /// Returns the remaining time until the next check of given wallet is allowed
+    fn locked_for(&self, name: &str, now: Instant) -> Option<Duration> {
+        let failures = self
+            .failures
+            .lock()
+            .expect("passphrase throttle lock poisoned");
+        let (count, last_failure) = failures.get(name)?;
+        let delay = throttle_delay(*count)?;
+        (last_failure + delay)
+            .checked_duration_since(now)
+            .filter(|remaining| *remaining > Duration::from_secs(0))
+    }
+
+    fn record(&self, name: &str, passed: bool, now: Instant) {
+        let mut failures = self
+            .failures
+            .lock()
+            .expect("passphrase throttle lock poisoned");
+        if passed {
+            failures.remove(name);
+        } else {
+            let entry = failures.entry(name.to_owned()).or_insert((0, now));
+            entry.0 = entry.0.saturating_add(1);
+            entry.1 = now;
+        }
+    }
+}
+
+/// Delay required after given number of consecutive failures (`None` if not throttled yet)
+fn throttle_delay(failures: u32) -> Option<Duration> {
+    let exponent = failures.checked_sub(PASSPHRASE_FREE_ATTEMPTS)?;
+    let delay = 2u64
+        .checked_pow(exponent)
+        .map(Duration::from_secs)
+        .unwrap_or(PASSPHRASE_MAX_DELAY);
+    Some(delay.min(PASSPHRASE_MAX_DELAY))
+}
+
+/// Checks the confirmation of the passphrase of a wallet to be created (if one was given)
+fn check_passphrase_confirmation(request: &CreateWalletRequest) -> Result<()> {
+    match request.passphrase_confirmation {
+        Some(ref confirmation)
+            if !bool::from(
+                request
+                    .passphrase
+                    .unsecure()
+                    .as_bytes()
+                    .ct_eq(confirmation.unsecure().as_bytes()),
+            ) =>
+        {
+            Err(Error {
+                code: ErrorCode::ServerError(PASSPHRASE_MISMATCH_ERROR_CODE),
+                message: "Passphrase confirmation does not match passphrase".to_owned(),
+                data: None,
+            })
+        }
+        _ => Ok(()),
+    }
+}
 
 #[rpc(server)]
 pub trait WalletRpc: Send + Sync {
+    /// Returns the balance breakdown (total, available, pending, immature and locked) of the
+    /// wallet; with `detail`, its `min_confirmations` is reported too
     #[rpc(name = "wallet_balance")]
-    fn balance(&self, request: WalletRequest) -> Result<WalletBalance>;
+    fn balance(
+        &self,
+        request: WalletRequest,
+        detail: Option<bool>,
+    ) -> Result<DetailedWalletBalance>;
+
+    #[rpc(name = "wallet_balanceDetailed")]
+    fn balance_detailed(&self, request: WalletRequest) -> Result<DetailedWalletBalance>;
+
+    #[rpc(name = "wallet_setMinConfirmations")]
+    fn set_min_confirmations(&self, request: WalletRequest, min_confirmations: u32) -> Result<()>;
+
+    #[rpc(name = "wallet_getCompliancePolicy")]
+    fn get_compliance_policy(&self, request: WalletRequest) -> Result<CompliancePolicy>;
+
+    /// Sets the compliance policy of the transfers of the wallet (self transfers are then rejected
+    /// unless `allow_self_transfer` is passed to `wallet_sendToAddress`)
+    #[rpc(name = "wallet_setCompliancePolicy")]
+    fn set_compliance_policy(&self, request: WalletRequest, policy: CompliancePolicy)
+        -> Result<()>;
+
+    #[rpc(name = "wallet_getRetentionPolicy")]
+    fn get_retention_policy(
+        &self,
+        request: WalletRequest,
+        class: RecordClass,
+    ) -> Result<RetentionPolicy>;
+
+    /// Sets the retention policy of a record class of the wallet (applied when its old records
+    /// are archived)
+    #[rpc(name = "wallet_setRetentionPolicy")]
+    fn set_retention_policy(
+        &self,
+        request: WalletRequest,
+        class: RecordClass,
+        policy: RetentionPolicy,
+    ) -> Result<()>;
+
+    /// Returns the latest record of a class archived out of the live storage of the wallet (the
+    /// transaction history up to it is truncated)
+    #[rpc(name = "wallet_archiveMark")]
+    fn archive_mark(
+        &self,
+        request: WalletRequest,
+        class: RecordClass,
+    ) -> Result<Option<ArchiveMark>>;
 
+    /// Creates a wallet; the mnemonic of a HD wallet is in given language (English by default)
     #[rpc(name = "wallet_create")]
     fn create(
         &self,
         request: CreateWalletRequest,
         walletkind: WalletKind,
         mnemonics_word_count: Option<u32>,
+        language: Option<MnemonicLanguage>,
     ) -> Result<(SecKey, Option<String>)>;
 
+    /// Checks the passphrase of an existing wallet (without returning anything on success)
+    #[rpc(name = "wallet_verifyPassphrase")]
+    fn verify_passphrase(&self, request: CreateWalletRequest) -> Result<()>;
+
+    /// Restores a HD wallet from its mnemonic; the language of the words is detected unless given
     #[rpc(name = "wallet_restore")]
-    fn restore(&self, request: CreateWalletRequest, mnemonics: Mnemonic) -> Result<SecKey>;
+    fn restore(
+        &self,
+        request: CreateWalletRequest,
+        mnemonics: SecUtf8,
+        language: Option<MnemonicLanguage>,
+    ) -> Result<SecKey>;
 
     #[rpc(name = "wallet_restoreBasic")]
     fn restore_basic(&self, request: CreateWalletRequest, view_key: SecUtf8) -> Result<SecKey>;
 
+    /// Creates a watch-only wallet tracking the addresses of given public keys
+    #[rpc(name = "wallet_createWatchOnly")]
+    fn create_watch_only(
+        &self,
+        request: CreateWalletRequest,
+        public_keys: Vec<PublicKey>,
+    ) -> Result<SecKey>;
+
+    /// Deletes the wallet and all its records (nothing is deleted if the passphrase is wrong)
     #[rpc(name = "wallet_delete")]
     fn delete(&self, request: CreateWalletRequest) -> Result<()>;
 
+    /// Renames the wallet (keeping its id) and returns its enckey under the new name
+    #[rpc(name = "wallet_rename")]
+    fn rename(&self, request: CreateWalletRequest, new_name: String) -> Result<SecKey>;
+
+    /// Re-encrypts the wallet with a new passphrase and returns its new enckey
+    #[rpc(name = "wallet_changePassphrase")]
+    fn change_passphrase(
+        &self,
+        request: CreateWalletRequest,
+        new_passphrase: SecUtf8,
+    ) -> Result<SecKey>;
+
+    /// Locks the wallet: its records decrypted with the enckey are no longer cached by the server
+    #[rpc(name = "wallet_lock")]
+    fn lock(&self, request: WalletRequest) -> Result<()>;
+
     #[rpc(name = "wallet_createStakingAddress")]
     fn create_staking_address(&self, request: WalletRequest) -> Result<String>;
 
@@ -58,9 +376,33 @@ pub trait WalletRpc: Send + Sync {
     #[rpc(name = "wallet_createTransferAddress")]
     fn create_transfer_address(&self, request: WalletRequest) -> Result<String>;
 
+    #[rpc(name = "wallet_importKey")]
+    fn import_key(
+        &self,
+        request: WalletRequest,
+        private_key: SecUtf8,
+        address_type: String,
+    ) -> Result<String>;
+
     #[rpc(name = "wallet_createTransferAddressBatch")]
     fn create_transfer_address_batch(&self, request: WalletRequest, count: u32) -> Result<u32>;
 
+    /// Creates `count` transfer addresses at once (none of them if one fails) and returns them
+    #[rpc(name = "wallet_newAddresses")]
+    fn new_addresses(&self, request: WalletRequest, count: usize) -> Result<Vec<String>>;
+
+    #[rpc(name = "wallet_createPaymentRequest")]
+    fn create_payment_request(
+        &self,
+        request: WalletRequest,
+        amount: Option<Coin>,
+        label: Option<String>,
+        expires: Option<Timespec>,
+    ) -> Result<String>;
+
+    #[rpc(name = "wallet_addressQr")]
+    fn address_qr(&self, request: WalletRequest, address: String) -> Result<String>;
+
     #[rpc(name = "wallet_createWatchTransferAddress")]
     fn create_watch_transfer_address(
         &self,
@@ -74,6 +416,14 @@ pub trait WalletRpc: Send + Sync {
     #[rpc(name = "wallet_list")]
     fn list(&self) -> Result<Vec<String>>;
 
+    /// Returns the id of the wallet, which is kept when it's renamed and never reused
+    #[rpc(name = "wallet_getId")]
+    fn get_id(&self, request: WalletRequest) -> Result<String>;
+
+    /// Returns the name of the wallet with given id
+    #[rpc(name = "wallet_byId")]
+    fn by_id(&self, wallet_id: String) -> Result<String>;
+
     #[rpc(name = "wallet_listPublicKeys")]
     fn list_public_keys(&self, request: WalletRequest) -> Result<Vec<PublicKey>>;
 
@@ -95,9 +445,43 @@ pub trait WalletRpc: Send + Sync {
         reversed: Option<bool>,
     ) -> Result<Vec<String>>;
 
+    #[rpc(name = "wallet_listAddressesDetailed")]
+    fn list_addresses_detailed(&self, request: WalletRequest) -> Result<Vec<AddressDetails>>;
+
+    /// Gives a unique label to an address of the wallet (an empty label removes it)
+    #[rpc(name = "wallet_setAddressLabel")]
+    fn set_address_label(
+        &self,
+        request: WalletRequest,
+        address: String,
+        label: String,
+    ) -> Result<()>;
+
+    #[rpc(name = "wallet_addressByLabel")]
+    fn address_by_label(&self, request: WalletRequest, label: String) -> Result<Option<String>>;
+
+    /// Adds a contact to the address book of the wallet, so that transfers can be sent to
+    /// `contact:<contact name>`
+    #[rpc(name = "wallet_addContact")]
+    fn add_contact(&self, request: WalletRequest, contact: String, address: String) -> Result<()>;
+
+    #[rpc(name = "wallet_removeContact")]
+    fn remove_contact(&self, request: WalletRequest, contact: String) -> Result<()>;
+
+    /// Contacts of the address book of the wallet (contact name -> address)
+    #[rpc(name = "wallet_listContacts")]
+    fn list_contacts(&self, request: WalletRequest) -> Result<BTreeMap<String, String>>;
+
+    /// Public keys whose recorded derivation path doesn't re-derive them from the seed
+    #[rpc(name = "wallet_verifyDerivations")]
+    fn verify_derivations(&self, request: WalletRequest) -> Result<Vec<PublicKey>>;
+
     #[rpc(name = "wallet_listUTxO")]
     fn list_utxo(&self, request: WalletRequest) -> Result<UnspentTransactions>;
 
+    /// Sends `amount` to `to_address`, the output can't be spent before the optional `valid_from`
+    /// (unix timestamp). Sending again with the same optional `idempotency_key` returns the
+    /// transaction already sent (as long as it's tracked) instead of sending another one.
     #[rpc(name = "wallet_sendToAddress")]
     fn send_to_address(
         &self,
@@ -105,6 +489,40 @@ pub trait WalletRpc: Send + Sync {
         to_address: String,
         amount: Coin,
         view_keys: Vec<String>,
+        allow_self_transfer: Option<bool>,
+        valid_from: Option<Timespec>,
+        idempotency_key: Option<String>,
+    ) -> Result<BroadcastResult>;
+
+    /// Builds and signs the transaction of `wallet_sendToAddress` without broadcasting it, returns
+    /// the hex encoded transaction (to be broadcast by other means)
+    #[rpc(name = "wallet_createRawTransaction")]
+    fn create_raw_transaction(
+        &self,
+        request: WalletRequest,
+        to_address: String,
+        amount: Coin,
+        view_keys: Vec<String>,
+        allow_self_transfer: Option<bool>,
+    ) -> Result<String>;
+
+    #[rpc(name = "wallet_estimateFee")]
+    fn estimate_fee(
+        &self,
+        request: WalletRequest,
+        to_address: String,
+        amount: Coin,
+        view_keys: Vec<String>,
+    ) -> Result<Coin>;
+
+    /// Sends the whole spendable balance of the wallet (minus the fee) to an address, returns the
+    /// id of the broadcast transaction
+    #[rpc(name = "wallet_sweep")]
+    fn sweep(
+        &self,
+        request: WalletRequest,
+        to_address: String,
+        view_keys: Vec<String>,
     ) -> Result<String>;
 
     #[rpc(name = "wallet_buildRawTransferTx")]
@@ -121,8 +539,11 @@ pub trait WalletRpc: Send + Sync {
         &self,
         request: WalletRequest,
         signed_tx: String,
-    ) -> Result<String>;
+    ) -> Result<BroadcastResult>;
 
+    /// Returns a page of the transaction history; with the optional `filter` (by direction, height
+    /// range and minimum amount), only the matching transactions are returned, newest first (so
+    /// `reversed` has to be set)
     #[rpc(name = "wallet_transactions")]
     fn transactions(
         &self,
@@ -130,8 +551,49 @@ pub trait WalletRpc: Send + Sync {
         offset: usize,
         limit: usize,
         reversed: bool,
+        filter: Option<HistoryFilter>,
     ) -> Result<Vec<TransactionChange>>;
 
+    /// Returns the details (inputs, outputs, fee and confirmations) of a transaction of the
+    /// wallet, or `null` if it's unknown
+    #[rpc(name = "wallet_getTransaction")]
+    fn get_transaction(
+        &self,
+        request: WalletRequest,
+        txid: String,
+    ) -> Result<Option<TransactionDetails>>;
+
+    /// Returns the fees paid by the wallet from `from` to `to` (inclusive `YYYY-MM-DD` UTC dates)
+    #[rpc(name = "wallet_feeSummary")]
+    fn fee_summary(
+        &self,
+        request: WalletRequest,
+        from: String,
+        to: String,
+    ) -> Result<FeeSummaryResponse>;
+
+    /// Returns the broadcast transactions which were not confirmed (or were just confirmed)
+    #[rpc(name = "wallet_pendingTransactions")]
+    fn pending_transactions(&self, request: WalletRequest) -> Result<Vec<PendingTransactionInfo>>;
+
+    /// Broadcasts a stale pending transaction again
+    #[rpc(name = "wallet_rebroadcastPendingTransaction")]
+    fn rebroadcast_pending_transaction(&self, request: WalletRequest, txid: String) -> Result<()>;
+
+    /// Broadcasts again all the pending transactions which are not confirmed yet (e.g. the ones
+    /// whose broadcast failed because the node was unreachable), returning their ids
+    #[rpc(name = "wallet_rebroadcastPending")]
+    fn rebroadcast_pending(&self, request: WalletRequest) -> Result<Vec<String>>;
+
+    /// Expires a stale pending transaction, so its inputs can be spent again
+    #[rpc(name = "wallet_expirePendingTransaction")]
+    fn expire_pending_transaction(&self, request: WalletRequest, txid: String) -> Result<()>;
+
+    /// Acknowledges the history mismatch of the wallet (reported by `chain_status`), which
+    /// unblocks its syncs and sends
+    #[rpc(name = "wallet_acknowledgeHistoryMismatch")]
+    fn acknowledge_history_mismatch(&self, request: WalletRequest) -> Result<()>;
+
     #[rpc(name = "wallet_exportTransaction")]
     fn export_plain_tx(&self, request: WalletRequest, txid: String) -> Result<String>;
 
@@ -146,6 +608,28 @@ pub trait WalletRpc: Send + Sync {
 
     #[rpc(name = "wallet_import")]
     fn import(&self, request: CreateWalletRequest, wallet_info: WalletInfo) -> Result<SecKey>;
+
+    /// Returns an encrypted full backup (snapshot) of the wallet
+    #[rpc(name = "wallet_backup")]
+    fn backup(&self, request: CreateWalletRequest, backup_key: SecUtf8) -> Result<BackupResponse>;
+
+    /// Returns an encrypted backup of the records modified since given snapshot
+    #[rpc(name = "wallet_backupIncremental")]
+    fn backup_incremental(
+        &self,
+        request: CreateWalletRequest,
+        backup_key: SecUtf8,
+        since_snapshot_id: String,
+    ) -> Result<BackupResponse>;
+
+    /// Restores a wallet from a chain of backups (a full one followed by incremental ones)
+    #[rpc(name = "wallet_restoreBackup")]
+    fn restore_backup(
+        &self,
+        request: CreateWalletRequest,
+        backup_key: SecUtf8,
+        backups: Vec<String>,
+    ) -> Result<SecKey>;
 }
 
 pub struct WalletRpcImpl<T>
@@ -154,6 +638,7 @@ where
 {
     client: T,
     network_id: u8,
+    throttle: PassphraseThrottle,
 }
 
 impl<T> WalletRpcImpl<T>
@@ -161,7 +646,34 @@ where
     T: WalletClient,
 {
     pub fn new(client: T, network_id: u8) -> Self {
-        WalletRpcImpl { client, network_id }
+        WalletRpcImpl {
+            client,
+            network_id,
+            throttle: PassphraseThrottle::default(),
+        }
+    }
+
+    /// Resolves the address and amount of a transfer to `to_address`, which is either a contact of
+    /// the wallet (`contact:<contact name>`), an address or a payment request
+    fn transfer_destination(
+        &self,
+        request: &WalletRequest,
+        to_address: &str,
+        amount: Coin,
+    ) -> Result<(ExtendedAddr, Coin)> {
+        match to_address.strip_prefix(CONTACT_PREFIX) {
+            Some(contact) => {
+                let address = self
+                    .client
+                    .find_contact(&request.name, &request.enckey, contact)
+                    .map_err(to_rpc_error)?
+                    .ok_or_else(|| {
+                        rpc_error_from_string(format!("Contact {} not found", contact))
+                    })?;
+                Ok((address, amount))
+            }
+            None => transfer_destination(to_address, amount),
+        }
     }
 }
 
@@ -169,9 +681,87 @@ impl<T> WalletRpc for WalletRpcImpl<T>
 where
     T: WalletClient + 'static,
 {
-    fn balance(&self, request: WalletRequest) -> Result<WalletBalance> {
-        self.client
+    fn balance(
+        &self,
+        request: WalletRequest,
+        detail: Option<bool>,
+    ) -> Result<DetailedWalletBalance> {
+        let balance = self
+            .client
             .balance(&request.name, &request.enckey)
+            .map_err(to_rpc_error)?;
+        let min_confirmations = if detail.unwrap_or(false) {
+            Some(
+                self.client
+                    .min_confirmations(&request.name, &request.enckey)
+                    .map_err(to_rpc_error)?,
+            )
+        } else {
+            None
+        };
+        Ok(DetailedWalletBalance {
+            balance,
+            min_confirmations,
+        })
+    }
+
+    fn balance_detailed(&self, request: WalletRequest) -> Result<DetailedWalletBalance> {
+        self.balance(request, Some(true))
+    }
+
+    fn set_min_confirmations(&self, request: WalletRequest, min_confirmations: u32) -> Result<()> {
+        self.client
+            .set_min_confirmations(&request.name, &request.enckey, min_confirmations)
+            .map_err(to_rpc_error)?;
+        self.client.flush_database().map_err(to_rpc_error)
+    }
+
+    fn get_compliance_policy(&self, request: WalletRequest) -> Result<CompliancePolicy> {
+        self.client
+            .compliance_policy(&request.name, &request.enckey)
+            .map_err(to_rpc_error)
+    }
+
+    fn set_compliance_policy(
+        &self,
+        request: WalletRequest,
+        policy: CompliancePolicy,
+    ) -> Result<()> {
+        self.client
+            .set_compliance_policy(&request.name, &request.enckey, policy)
+            .map_err(to_rpc_error)?;
+        self.client.flush_database().map_err(to_rpc_error)
+    }
+
+    fn get_retention_policy(
+        &self,
+        request: WalletRequest,
+        class: RecordClass,
+    ) -> Result<RetentionPolicy> {
+        self.client
+            .retention_policy(&request.name, &request.enckey, class)
+            .map_err(to_rpc_error)
+    }
+
+    fn set_retention_policy(
+        &self,
+        request: WalletRequest,
+        class: RecordClass,
+        policy: RetentionPolicy,
+    ) -> Result<()> {
+        self.client
+            .set_retention_policy(&request.name, &request.enckey, class, policy)
+            .map_err(to_rpc_error)?;
+        self.client.flush_database().map_err(to_rpc_error)
+    }
+
+    fn archive_mark(
+        &self,
+        request: WalletRequest,
+        class: RecordClass,
+    ) -> Result<Option<ArchiveMark>> {
+        self.client
+            .archive_mark(&request.name, &request.enckey, class)
             .map_err(to_rpc_error)
     }
 
@@ -180,18 +770,34 @@ where
         request: CreateWalletRequest,
         kind: WalletKind,
         mnemonics_word_count: Option<u32>,
+        language: Option<MnemonicLanguage>,
     ) -> Result<(SecKey, Option<String>)> {
+        check_passphrase_confirmation(&request)?;
         // TODO: add hardware wallet
-        let (enckey, mnemonic) = self
-            .client
-            .new_wallet(
+        let (enckey, mnemonic) = match (kind, language) {
+            (WalletKind::HD, Some(language)) => self
+                .client
+                .new_hd_wallet(
+                    &request.name,
+                    &request.passphrase,
+                    mnemonics_word_count,
+                    language,
+                )
+                .map(|(enckey, mnemonic)| (enckey, Some(mnemonic))),
+            (_, Some(_)) => {
+                return Err(rpc_error_from_string(
+                    "Mnemonic language is only given for HD wallets".to_owned(),
+                ))
+            }
+            (_, None) => self.client.new_wallet(
                 &request.name,
                 &request.passphrase,
                 kind,
                 HardwareKind::LocalOnly,
                 mnemonics_word_count,
-            )
-            .map_err(to_rpc_error)?;
+            ),
+        }
+        .map_err(to_rpc_error)?;
 
         self.client
             .new_staking_address(&request.name, &enckey)
@@ -212,7 +818,34 @@ where
         }
     }
 
-    fn restore(&self, request: CreateWalletRequest, mnemonic: Mnemonic) -> Result<SecKey> {
+    fn verify_passphrase(&self, request: CreateWalletRequest) -> Result<()> {
+        if let Some(remaining) = self.throttle.locked_for(&request.name, Instant::now()) {
+            return Err(Error {
+                code: ErrorCode::ServerError(PASSPHRASE_THROTTLED_ERROR_CODE),
+                message: format!(
+                    "Too many failed passphrase attempts, retry in {} seconds",
+                    remaining.as_secs() + 1
+                ),
+                data: None,
+            });
+        }
+        let result = self.client.auth_token(&request.name, &request.passphrase);
+        self.throttle
+            .record(&request.name, result.is_ok(), Instant::now());
+        result.map(|_| ()).map_err(to_rpc_error)
+    }
+
+    fn restore(
+        &self,
+        request: CreateWalletRequest,
+        mnemonics: SecUtf8,
+        language: Option<MnemonicLanguage>,
+    ) -> Result<SecKey> {
+        let mnemonic = match language {
+            Some(language) => Mnemonic::from_secstr_in(&mnemonics, language),
+            None => Mnemonic::from_secstr(&mnemonics),
+        }
+        .map_err(to_rpc_error)?;
         let enckey = self
             .client
             .restore_wallet(&request.name, &request.passphrase, &mnemonic)
@@ -231,9 +864,7 @@ where
     }
 
     fn restore_basic(&self, request: CreateWalletRequest, view_key: SecUtf8) -> Result<SecKey> {
-        let view_key =
-            PrivateKey::deserialize_from(&hex::decode(view_key.unsecure()).map_err(to_rpc_error)?)
-                .map_err(to_rpc_error)?;
+        let view_key = PrivateKey::from_hex(view_key.unsecure()).map_err(to_rpc_error)?;
         let enckey = self
             .client
             .restore_basic_wallet(&request.name, &request.passphrase, &view_key)
@@ -242,6 +873,20 @@ where
         Ok(enckey)
     }
 
+    fn create_watch_only(
+        &self,
+        request: CreateWalletRequest,
+        public_keys: Vec<PublicKey>,
+    ) -> Result<SecKey> {
+        check_passphrase_confirmation(&request)?;
+        let enckey = self
+            .client
+            .new_watch_wallet(&request.name, &request.passphrase, public_keys)
+            .map_err(to_rpc_error)?;
+        self.client.flush_database().map_err(to_rpc_error)?;
+        Ok(enckey)
+    }
+
     fn delete(&self, request: CreateWalletRequest) -> Result<()> {
         let ret = self
             .client
@@ -251,6 +896,34 @@ where
         ret
     }
 
+    fn rename(&self, request: CreateWalletRequest, new_name: String) -> Result<SecKey> {
+        let ret = self
+            .client
+            .rename_wallet(&request.name, &new_name, &request.passphrase)
+            .map_err(to_rpc_error);
+        self.client.flush_database().map_err(to_rpc_error)?;
+        ret
+    }
+
+    fn change_passphrase(
+        &self,
+        request: CreateWalletRequest,
+        new_passphrase: SecUtf8,
+    ) -> Result<SecKey> {
+        let ret = self
+            .client
+            .change_passphrase(&request.name, &request.passphrase, &new_passphrase)
+            .map_err(to_rpc_error);
+        self.client.flush_database().map_err(to_rpc_error)?;
+        ret
+    }
+
+    fn lock(&self, request: WalletRequest) -> Result<()> {
+        self.client
+            .lock_wallet(&request.name, &request.enckey)
+            .map_err(to_rpc_error)
+    }
+
     fn create_staking_address(&self, request: WalletRequest) -> Result<String> {
         let ret = self
             .client
@@ -297,6 +970,20 @@ where
         ret
     }
 
+    fn import_key(
+        &self,
+        request: WalletRequest,
+        private_key: SecUtf8,
+        address_type: String,
+    ) -> Result<String> {
+        let address_type = AddressType::from_str(&address_type).map_err(to_rpc_error)?;
+        let private_key = SecStr::new(hex::decode(private_key.unsecure()).map_err(to_rpc_error)?);
+        self.client
+            .import_private_key(&request.name, &request.enckey, &private_key, address_type)
+            .map(|address| address.to_string())
+            .map_err(to_rpc_error)
+    }
+
     fn create_transfer_address(&self, request: WalletRequest) -> Result<String> {
         let extended_address = self
             .client
@@ -308,30 +995,68 @@ where
     }
 
     fn create_transfer_address_batch(&self, request: WalletRequest, count: u32) -> Result<u32> {
-        let total_now = std::time::Instant::now();
-        for i in 0..count {
-            let now = std::time::Instant::now();
-            let progress = i as f64 / count as f64 * 100.0;
-            self.client
-                .new_transfer_address(&request.name, &request.enckey)
-                .map_err(to_rpc_error)?;
-            log::debug!(
-                "transfer-address progress {:.2}% created {}/{}  for each {} micro-seconds  total {} seconds",
-                progress,
-                i + 1,
-                count,
-                now.elapsed().as_micros(),
-                total_now.elapsed().as_secs()
-            );
-        }
+        let now = std::time::Instant::now();
+        self.client
+            .new_addresses(&request.name, &request.enckey, count as usize)
+            .map_err(to_rpc_error)?;
+        log::debug!(
+            "created {} transfer addresses in {} micro-seconds",
+            count,
+            now.elapsed().as_micros()
+        );
         self.client.flush_database().map_err(to_rpc_error)?;
         Ok(count)
     }
 
-    fn create_watch_transfer_address(
+    fn new_addresses(&self, request: WalletRequest, count: usize) -> Result<Vec<String>> {
+        let addresses = self
+            .client
+            .new_addresses(&request.name, &request.enckey, count)
+            .map_err(to_rpc_error)?;
+        self.client.flush_database().map_err(to_rpc_error)?;
+        Ok(addresses
+            .into_iter()
+            .map(|address| address.to_string())
+            .collect())
+    }
+
+    fn create_payment_request(
         &self,
         request: WalletRequest,
-        public_key: PublicKey,
+        amount: Option<Coin>,
+        label: Option<String>,
+        expires: Option<Timespec>,
+    ) -> Result<String> {
+        let uri = self
+            .client
+            .create_payment_request(&request.name, &request.enckey, amount, label, expires)
+            .map_err(to_rpc_error)?;
+
+        self.client.flush_database().map_err(to_rpc_error)?;
+        Ok(uri)
+    }
+
+    fn address_qr(&self, request: WalletRequest, address: String) -> Result<String> {
+        let address = address
+            .parse::<ExtendedAddr>()
+            .map_err(|err| rpc_error_from_string(format!("{}", err)))?;
+        let root_hash = self
+            .client
+            .find_root_hash(&request.name, &request.enckey, &address)
+            .map_err(to_rpc_error)?;
+        if root_hash.is_none() {
+            return Err(rpc_error_from_string(format!(
+                "Transfer address {} not found in wallet",
+                address
+            )));
+        }
+        Ok(qr_payload_for_address(&address))
+    }
+
+    fn create_watch_transfer_address(
+        &self,
+        request: WalletRequest,
+        public_key: PublicKey,
     ) -> Result<String> {
         let extended_address = self
             .client
@@ -344,13 +1069,12 @@ where
 
     fn get_view_key(&self, request: WalletRequest, private: bool) -> Result<String> {
         let s = if private {
-            hex::encode(
-                &self
-                    .client
-                    .view_key_private(&request.name, &request.enckey)
-                    .map_err(to_rpc_error)?
-                    .serialize(),
-            )
+            self.client
+                .view_key_private(&request.name, &request.enckey)
+                .map_err(to_rpc_error)?
+                .to_hex()
+                .unsecure()
+                .to_owned()
         } else {
             self.client
                 .view_key(&request.name, &request.enckey)
@@ -364,6 +1088,22 @@ where
         self.client.wallets().map_err(to_rpc_error)
     }
 
+    fn get_id(&self, request: WalletRequest) -> Result<String> {
+        self.client
+            .view_key(&request.name, &request.enckey)
+            .and_then(|_| self.client.wallet_id(&request.name))
+            .map_err(to_rpc_error)
+    }
+
+    fn by_id(&self, wallet_id: String) -> Result<String> {
+        self.client
+            .wallet_by_id(&wallet_id)
+            .map_err(to_rpc_error)?
+            .ok_or_else(|| {
+                rpc_error_from_string(format!("Wallet with id ({}) not found", wallet_id))
+            })
+    }
+
     fn list_public_keys(&self, request: WalletRequest) -> Result<Vec<PublicKey>> {
         self.client
             .public_keys(&request.name, &request.enckey)
@@ -409,6 +1149,81 @@ where
             .map_err(to_rpc_error)
     }
 
+    fn list_addresses_detailed(&self, request: WalletRequest) -> Result<Vec<AddressDetails>> {
+        let addresses = self
+            .client
+            .addresses_detailed(&request.name, &request.enckey)
+            .map_err(to_rpc_error)?;
+
+        Ok(addresses
+            .into_iter()
+            .map(|(address, derivation_path, label)| {
+                let address_type = match address {
+                    WalletAddress::Transfer(_) => "transfer",
+                    WalletAddress::Staking(_) => "staking",
+                };
+                AddressDetails {
+                    address: address.to_string(),
+                    address_type: address_type.to_owned(),
+                    derivation_path,
+                    label,
+                }
+            })
+            .collect())
+    }
+
+    fn set_address_label(
+        &self,
+        request: WalletRequest,
+        address: String,
+        label: String,
+    ) -> Result<()> {
+        let address = WalletAddress::from_str(&address).map_err(to_rpc_error)?;
+        self.client
+            .set_address_label(&request.name, &request.enckey, &address, &label)
+            .map_err(to_rpc_error)
+    }
+
+    fn address_by_label(&self, request: WalletRequest, label: String) -> Result<Option<String>> {
+        self.client
+            .address_by_label(&request.name, &request.enckey, &label)
+            .map(|address| address.map(|address| address.to_string()))
+            .map_err(to_rpc_error)
+    }
+
+    fn add_contact(&self, request: WalletRequest, contact: String, address: String) -> Result<()> {
+        let address = address
+            .parse::<ExtendedAddr>()
+            .map_err(|err| rpc_error_from_string(format!("{}", err)))?;
+        self.client
+            .add_contact(&request.name, &request.enckey, &contact, &address)
+            .map_err(to_rpc_error)
+    }
+
+    fn remove_contact(&self, request: WalletRequest, contact: String) -> Result<()> {
+        self.client
+            .remove_contact(&request.name, &request.enckey, &contact)
+            .map_err(to_rpc_error)
+    }
+
+    fn list_contacts(&self, request: WalletRequest) -> Result<BTreeMap<String, String>> {
+        self.client
+            .contacts(&request.name, &request.enckey)
+            .map(|contacts| {
+                contacts
+                    .into_iter()
+                    .map(|(contact, address)| (contact, address.to_string()))
+                    .collect()
+            })
+            .map_err(to_rpc_error)
+    }
+
+    fn verify_derivations(&self, request: WalletRequest) -> Result<Vec<PublicKey>> {
+        self.client
+            .verify_derivations(&request.name, &request.enckey)
+            .map_err(to_rpc_error)
+    }
+
     fn list_utxo(&self, request: WalletRequest) -> Result<UnspentTransactions> {
         self.client
             .unspent_transactions(&request.name, &request.enckey)
@@ -421,26 +1236,132 @@ where
         to_address: String,
         amount: Coin,
         view_keys: Vec<String>,
-    ) -> Result<String> {
-        let address = to_address
-            .parse::<ExtendedAddr>()
-            .map_err(|err| rpc_error_from_string(format!("{}", err)))?;
+        allow_self_transfer: Option<bool>,
+        valid_from: Option<Timespec>,
+        idempotency_key: Option<String>,
+    ) -> Result<BroadcastResult> {
+        let (address, amount) = self.transfer_destination(&request, &to_address, amount)?;
         let mut view_keys = view_keys
             .iter()
             .map(|view_key| PublicKey::from_str(view_key))
             .collect::<CommonResult<BTreeSet<PublicKey>>>()
             .map_err(to_rpc_error)?;
-        let tx_id = self
+        let result = self
             .client
             .send_to_address(
                 &request.name,
                 &request.enckey,
                 amount,
                 address,
+                valid_from,
+                &mut view_keys,
+                self.network_id,
+                allow_self_transfer.unwrap_or(false),
+                // the chain requires the exact fee, so no tip is paid until it accepts overpayment
+                FeeTip::default(),
+                idempotency_key.as_ref().map(String::as_str),
+            )
+            .map_err(transfer_rpc_error)?;
+        self.client.flush_database().map_err(to_rpc_error)?;
+        Ok(result)
+    }
+
+    fn create_raw_transaction(
+        &self,
+        request: WalletRequest,
+        to_address: String,
+        amount: Coin,
+        view_keys: Vec<String>,
+        allow_self_transfer: Option<bool>,
+    ) -> Result<String> {
+        let (address, amount) = self.transfer_destination(&request, &to_address, amount)?;
+        let mut view_keys = view_keys
+            .iter()
+            .map(|view_key| PublicKey::from_str(view_key))
+            .collect::<CommonResult<BTreeSet<PublicKey>>>()
+            .map_err(to_rpc_error)?;
+        let (signed_tx, _) = self
+            .client
+            .build_transaction(
+                &request.name,
+                &request.enckey,
+                amount,
+                address,
+                None,
                 &mut view_keys,
                 self.network_id,
+                allow_self_transfer.unwrap_or(false),
+                // the chain requires the exact fee, so no tip is paid until it accepts overpayment
+                FeeTip::default(),
+            )
+            .map_err(transfer_rpc_error)?;
+        self.client.flush_database().map_err(to_rpc_error)?;
+        Ok(hex::encode(signed_tx.signed_transaction.encode()))
+    }
+
+    fn estimate_fee(
+        &self,
+        request: WalletRequest,
+        to_address: String,
+        amount: Coin,
+        view_keys: Vec<String>,
+    ) -> Result<Coin> {
+        let to_address = to_address
+            .parse::<ExtendedAddr>()
+            .map_err(|err| rpc_error_from_string(format!("{}", err)))?;
+        let mut view_keys = view_keys
+            .iter()
+            .map(|view_key| PublicKey::from_str(view_key))
+            .collect::<CommonResult<BTreeSet<PublicKey>>>()
+            .map_err(to_rpc_error)?;
+        view_keys.insert(
+            self.client
+                .view_key(&request.name, &request.enckey)
+                .map_err(to_rpc_error)?,
+        );
+        let attributes = TxAttributes::builder(self.network_id)
+            .allow_view_many(view_keys.iter().map(Into::into))
+            .build()
+            .map_err(to_rpc_error)?;
+
+        self.client
+            .estimate_fee(
+                &request.name,
+                &request.enckey,
+                vec![TxOut::new(to_address, amount)],
+                attributes,
             )
+            .map_err(to_rpc_error)
+    }
+
+    fn sweep(
+        &self,
+        request: WalletRequest,
+        to_address: String,
+        view_keys: Vec<String>,
+    ) -> Result<String> {
+        let to_address = to_address
+            .parse::<ExtendedAddr>()
+            .map_err(|err| rpc_error_from_string(format!("{}", err)))?;
+        let mut view_keys = view_keys
+            .iter()
+            .map(|view_key| PublicKey::from_str(view_key))
+            .collect::<CommonResult<BTreeSet<PublicKey>>>()
+            .map_err(to_rpc_error)?;
+        view_keys.insert(
+            self.client
+                .view_key(&request.name, &request.enckey)
+                .map_err(to_rpc_error)?,
+        );
+        let attributes = TxAttributes::builder(self.network_id)
+            .allow_view_many(view_keys.iter().map(Into::into))
+            .build()
             .map_err(to_rpc_error)?;
+
+        let tx_id = self
+            .client
+            .sweep(&request.name, &request.enckey, to_address, attributes)
+            .map_err(transfer_rpc_error)?;
         self.client.flush_database().map_err(to_rpc_error)?;
         Ok(hex::encode(tx_id))
     }
@@ -481,16 +1402,16 @@ where
         &self,
         request: WalletRequest,
         signed_tx: String,
-    ) -> Result<String> {
+    ) -> Result<BroadcastResult> {
         let raw_data = base64::decode(&signed_tx).map_err(to_rpc_error)?;
         let signed_tx =
-            SignedTransferTransaction::decode(&mut raw_data.as_slice()).map_err(to_rpc_error)?;
-        let tx_id = self
+            decode_all_strict::<SignedTransferTransaction>(&raw_data).map_err(to_rpc_error)?;
+        let result = self
             .client
             .broadcast_signed_transfer_tx(&request.name, &request.enckey, signed_tx)
             .map_err(to_rpc_error)?;
         self.client.flush_database().map_err(to_rpc_error)?;
-        Ok(hex::encode(tx_id))
+        Ok(result)
     }
 
     fn export_plain_tx(&self, request: WalletRequest, txid: String) -> Result<String> {
@@ -517,12 +1438,102 @@ where
         offset: usize,
         limit: usize,
         reversed: bool,
+        filter: Option<HistoryFilter>,
     ) -> Result<Vec<TransactionChange>> {
+        let filter = match filter {
+            Some(filter) => filter,
+            None => {
+                return self
+                    .client
+                    .history(&request.name, &request.enckey, offset, limit, reversed)
+                    .map_err(to_rpc_error)
+            }
+        };
+        if !reversed {
+            return Err(rpc_error_from_string(
+                "Filtered transactions are only returned newest first (reversed)".to_owned(),
+            ));
+        }
+        self.client
+            .history_paged(&request.name, &request.enckey, offset, limit, &filter)
+            .map(|page| page.changes)
+            .map_err(to_rpc_error)
+    }
+
+    fn get_transaction(
+        &self,
+        request: WalletRequest,
+        txid: String,
+    ) -> Result<Option<TransactionDetails>> {
+        let txid = str2txid(&txid).map_err(to_rpc_error)?;
         self.client
-            .history(&request.name, &request.enckey, offset, limit, reversed)
+            .transaction_details(&request.name, &request.enckey, &txid)
             .map_err(to_rpc_error)
     }
 
+    fn fee_summary(
+        &self,
+        request: WalletRequest,
+        from: String,
+        to: String,
+    ) -> Result<FeeSummaryResponse> {
+        let parse_date = |date: &str| {
+            NaiveDate::from_str(date)
+                .map_err(|err| rpc_error_from_string(format!("Invalid date {}: {}", date, err)))
+        };
+        let summary = self
+            .client
+            .fee_summary(
+                &request.name,
+                &request.enckey,
+                parse_date(&from)?,
+                parse_date(&to)?,
+            )
+            .map_err(to_rpc_error)?;
+        Ok(summary.into())
+    }
+
+    fn pending_transactions(&self, request: WalletRequest) -> Result<Vec<PendingTransactionInfo>> {
+        let transactions = self
+            .client
+            .pending_transactions(&request.name, &request.enckey)
+            .map_err(to_rpc_error)?;
+        self.client.flush_database().map_err(to_rpc_error)?;
+        Ok(transactions)
+    }
+
+    fn rebroadcast_pending_transaction(&self, request: WalletRequest, txid: String) -> Result<()> {
+        let txid = str2txid(&txid).map_err(to_rpc_error)?;
+        self.client
+            .rebroadcast_pending_transaction(&request.name, &request.enckey, &txid)
+            .map_err(to_rpc_error)?;
+        self.client.flush_database().map_err(to_rpc_error)
+    }
+
+    fn rebroadcast_pending(&self, request: WalletRequest) -> Result<Vec<String>> {
+        let tx_ids = self
+            .client
+            .rebroadcast_pending(&request.name, &request.enckey)
+            .map_err(to_rpc_error)?;
+        self.client.flush_database().map_err(to_rpc_error)?;
+        Ok(tx_ids.iter().map(hex::encode).collect())
+    }
+
+    fn expire_pending_transaction(&self, request: WalletRequest, txid: String) -> Result<()> {
+        let txid = str2txid(&txid).map_err(to_rpc_error)?;
+        self.client
+            .expire_pending_transaction(&request.name, &request.enckey, &txid)
+            .map_err(to_rpc_error)?;
+        self.client.flush_database().map_err(to_rpc_error)
+    }
+
+    fn acknowledge_history_mismatch(&self, request: WalletRequest) -> Result<()> {
+        self.client
+            .acknowledge_history_mismatch(&request.name, &request.enckey)
+            .map_err(to_rpc_error)?;
+        self.client.flush_database().map_err(to_rpc_error)
+    }
+
     fn get_enc_key(&self, request: CreateWalletRequest) -> Result<SecKey> {
         self.client
             .auth_token(&request.name, &request.passphrase)
@@ -547,6 +1558,64 @@ where
         self.client.flush_database().map_err(to_rpc_error)?;
         ret
     }
+
+    fn backup(&self, request: CreateWalletRequest, backup_key: SecUtf8) -> Result<BackupResponse> {
+        self.client
+            .backup(&request.name, &request.passphrase, &backup_key)
+            .map(BackupResponse::from)
+            .map_err(to_rpc_error)
+    }
+
+    fn backup_incremental(
+        &self,
+        request: CreateWalletRequest,
+        backup_key: SecUtf8,
+        since_snapshot_id: String,
+    ) -> Result<BackupResponse> {
+        let since_snapshot_id = hex::decode(&since_snapshot_id)
+            .ok()
+            .filter(|bytes| bytes.len() == HASH_SIZE_256)
+            .map(|bytes| {
+                let mut snapshot_id: H256 = [0; HASH_SIZE_256];
+                snapshot_id.copy_from_slice(&bytes);
+                snapshot_id
+            })
+            .ok_or_else(|| rpc_error_from_string("invalid snapshot id".to_owned()))?;
+
+        self.client
+            .backup_incremental(
+                &request.name,
+                &request.passphrase,
+                &backup_key,
+                &since_snapshot_id,
+            )
+            .map(BackupResponse::from)
+            .map_err(to_rpc_error)
+    }
+
+    fn restore_backup(
+        &self,
+        request: CreateWalletRequest,
+        backup_key: SecUtf8,
+        backups: Vec<String>,
+    ) -> Result<SecKey> {
+        let blobs = backups
+            .iter()
+            .map(|backup| {
+                hex::decode(backup)
+                    .ok()
+                    .and_then(|bytes| BackupBlob::decode(&mut bytes.as_slice()).ok())
+                    .ok_or_else(|| rpc_error_from_string("invalid backup".to_owned()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let ret = self
+            .client
+            .restore_backup(&blobs, &backup_key, &request.name, &request.passphrase)
+            .map_err(to_rpc_error);
+        self.client.flush_database().map_err(to_rpc_error)?;
+        ret
+    }
 }
 
 #[cfg(test)]
@@ -555,15 +1624,18 @@ pub mod tests {
 
     use secstr::SecUtf8;
 
-    use parity_scale_codec::Encode;
+    use parity_scale_codec::{Decode, Encode};
 
+    use chain_core::init::address::RedeemAddress;
     use chain_core::init::coin::CoinError;
+    use chain_core::state::account::{DepositBondTx, StakedStateAddress, StakedStateOpAttributes};
     use chain_core::state::tendermint::BlockHeight;
     use chain_core::state::ChainState;
-    use chain_core::tx::data::input::TxoSize;
+    use chain_core::tx::data::input::{TxoPointer, TxoSize};
     use chain_core::tx::data::TxId;
     use chain_core::tx::fee::{Fee, FeeAlgorithm};
     use chain_core::tx::{PlainTxAux, TransactionId, TxAux, TxEnclaveAux, TxObfuscated};
+    use chain_tx_validation::witness::verify_tx_address;
     use client_common::storage::MemoryStorage;
     use client_common::tendermint::mock;
     use client_common::tendermint::types::*;
@@ -573,9 +1645,14 @@ pub mod tests {
         seckey::derive_enckey, Error, ErrorKind, Result as CommonResult, SignedTransaction,
         Transaction,
     };
-    use client_core::service::HwKeyService;
+    use client_core::coin_selection::LargestFirst;
+    use client_core::service::{HwKeyService, WalletStateMemento, WalletStateService};
     use client_core::signer::WalletSignerManager;
     use client_core::transaction_builder::DefaultWalletTransactionBuilder;
+    use client_core::types::{
+        parse_qr_payload, BalanceChange, PendingTransactionStatus, QrPayload, TransactionPending,
+        TransactionType,
+    };
     use client_core::wallet::DefaultWalletClient;
 
     #[derive(Default, Clone)]
@@ -765,46 +1842,146 @@ pub mod tests {
         let (create_request, wallet_request) = create_wallet_request("Default", "123456");
 
         wallet_rpc
-            .create(create_request, WalletKind::Basic, None)
+            .create(create_request, WalletKind::Basic, None, None)
             .unwrap();
+        let balance = wallet_rpc.balance(wallet_request.clone(), None).unwrap();
+        assert_eq!(WalletBalance::default(), balance.balance);
+        assert_eq!(None, balance.min_confirmations);
         assert_eq!(
-            WalletBalance::default(),
-            wallet_rpc.balance(wallet_request).unwrap()
-        )
-    }
+            serde_json::json!({
+                "total": "0",
+                "available": "0",
+                "pending": "0",
+                "immature": "0",
+                "locked": "0"
+            }),
+            serde_json::to_value(&balance).unwrap()
+        );
 
-    mod create {
-        use super::*;
+        let detailed = wallet_rpc.balance(wallet_request, Some(true)).unwrap();
+        assert_eq!(WalletBalance::default(), detailed.balance);
+        assert_eq!(Some(0), detailed.min_confirmations);
+    }
 
-        #[test]
-        fn create_duplicated_wallet_should_throw_error() {
-            let wallet_rpc = setup_wallet_rpc();
-            let (create_request, _) = create_wallet_request("Default", "123456");
+    #[test]
+    fn balance_detailed_should_report_min_confirmations() {
+        let wallet_rpc = setup_wallet_rpc();
+        let (create_request, wallet_request) = create_wallet_request("Default", "123456");
 
-            wallet_rpc
-                .create(create_request.clone(), WalletKind::Basic, None)
-                .unwrap();
+        wallet_rpc
+            .create(create_request, WalletKind::Basic, None, None)
+            .unwrap();
+        let detailed = wallet_rpc.balance_detailed(wallet_request.clone()).unwrap();
+        assert_eq!(Some(0), detailed.min_confirmations);
+        assert_eq!(WalletBalance::default(), detailed.balance);
 
-            assert_eq!(
-                to_rpc_error(Error::new(
-                    ErrorKind::InvalidInput,
-                    "Wallet with name (Default) already exists"
-                )),
-                wallet_rpc
-                    .create(create_request, WalletKind::Basic, None)
-                    .unwrap_err()
-            );
-        }
+        wallet_rpc
+            .set_min_confirmations(wallet_request.clone(), 6)
+            .unwrap();
+        let detailed = wallet_rpc.balance_detailed(wallet_request).unwrap();
+        assert_eq!(Some(6), detailed.min_confirmations);
+        assert_eq!(WalletBalance::default(), detailed.balance);
+        assert_eq!(
+            serde_json::json!({
+                "total": "0",
+                "available": "0",
+                "pending": "0",
+                "immature": "0",
+                "locked": "0",
+                "min_confirmations": 6
+            }),
+            serde_json::to_value(&detailed).unwrap()
+        );
+    }
 
-        #[test]
-        fn create_should_create_named_wallet() {
-            let wallet_rpc = setup_wallet_rpc();
+    #[test]
+    fn compliance_policy_should_reject_self_transfers_unless_allowed() {
+        let wallet_rpc = setup_wallet_rpc();
+        let (create_request, wallet_request) = create_wallet_request("Default", "123456");
 
+        wallet_rpc
+            .create(create_request, WalletKind::Basic, None, None)
+            .unwrap();
+        assert_eq!(
+            CompliancePolicy::default(),
             wallet_rpc
-                .create(
+                .get_compliance_policy(wallet_request.clone())
+                .unwrap()
+        );
+        let policy = CompliancePolicy {
+            enabled: true,
+            min_output_value: Coin::unit(),
+        };
+        wallet_rpc
+            .set_compliance_policy(wallet_request.clone(), policy)
+            .unwrap();
+        assert_eq!(
+            policy,
+            wallet_rpc
+                .get_compliance_policy(wallet_request.clone())
+                .unwrap()
+        );
+
+        let own_address = wallet_rpc
+            .create_transfer_address(wallet_request.clone())
+            .unwrap();
+        let send = |allow_self_transfer| {
+            wallet_rpc
+                .send_to_address(
+                    wallet_request.clone(),
+                    own_address.clone(),
+                    Coin::unit(),
+                    vec![],
+                    allow_self_transfer,
+                    None,
+                    None,
+                )
+                .unwrap_err()
+        };
+        assert_eq!(
+            ErrorCode::ServerError(SELF_TRANSFER_ERROR_CODE),
+            send(None).code
+        );
+        // the override passes the policy (the wallet has no funds to send though)
+        assert_ne!(
+            ErrorCode::ServerError(SELF_TRANSFER_ERROR_CODE),
+            send(Some(true)).code
+        );
+    }
+
+    mod create {
+        use super::*;
+
+        #[test]
+        fn create_duplicated_wallet_should_throw_error() {
+            let wallet_rpc = setup_wallet_rpc();
+            let (create_request, _) = create_wallet_request("Default", "123456");
+
+            wallet_rpc
+                .create(create_request.clone(), WalletKind::Basic, None, None)
+                .unwrap();
+
+            assert_eq!(
+                to_rpc_error(Error::new(
+                    ErrorKind::InvalidInput,
+                    "Wallet with name (Default) already exists"
+                )),
+                wallet_rpc
+                    .create(create_request, WalletKind::Basic, None, None)
+                    .unwrap_err()
+            );
+        }
+
+        #[test]
+        fn create_should_create_named_wallet() {
+            let wallet_rpc = setup_wallet_rpc();
+
+            wallet_rpc
+                .create(
                     create_wallet_request("Default", "123456").0,
                     WalletKind::Basic,
                     None,
+                    None,
                 )
                 .unwrap();
 
@@ -817,7 +1994,7 @@ pub mod tests {
             let (create_request, wallet_request) = create_wallet_request("Default", "123456");
 
             wallet_rpc
-                .create(create_request, WalletKind::Basic, None)
+                .create(create_request, WalletKind::Basic, None, None)
                 .unwrap();
 
             assert_eq!(
@@ -843,7 +2020,7 @@ pub mod tests {
         let (create_request, wallet_request) = create_wallet_request("Default", "123456");
 
         wallet_rpc
-            .create(create_request, WalletKind::Basic, None)
+            .create(create_request, WalletKind::Basic, None, None)
             .unwrap();
         assert_eq!(
             1,
@@ -872,7 +2049,7 @@ pub mod tests {
         let (create_request, wallet_request) = create_wallet_request("Default", "123456");
 
         wallet_rpc
-            .create(create_request, WalletKind::Basic, None)
+            .create(create_request, WalletKind::Basic, None, None)
             .unwrap();
 
         assert_eq!(
@@ -896,13 +2073,239 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn new_addresses_should_work() {
+        let wallet_rpc = setup_wallet_rpc();
+        let (create_request, wallet_request) = create_wallet_request("Default", "123456");
+
+        wallet_rpc
+            .create(create_request, WalletKind::Basic, None, None)
+            .unwrap();
+
+        let addresses = wallet_rpc
+            .new_addresses(wallet_request.clone(), 10)
+            .unwrap();
+        assert_eq!(10, addresses.iter().collect::<BTreeSet<_>>().len());
+        let transfer_addresses = wallet_rpc
+            .list_transfer_addresses(wallet_request.clone(), None, None, None)
+            .unwrap();
+        assert_eq!(11, transfer_addresses.len());
+        assert!(addresses
+            .iter()
+            .all(|address| transfer_addresses.contains(address)));
+
+        assert_eq!(
+            5,
+            wallet_rpc
+                .create_transfer_address_batch(wallet_request.clone(), 5)
+                .unwrap()
+        );
+        assert_eq!(
+            16,
+            wallet_rpc
+                .list_transfer_addresses(wallet_request, None, None, None)
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    fn create_payment_request_should_bind_fresh_address() {
+        let wallet_rpc = setup_wallet_rpc();
+        let (create_request, wallet_request) = create_wallet_request("Default", "123456");
+
+        wallet_rpc
+            .create(create_request, WalletKind::Basic, None, None)
+            .unwrap();
+
+        let uri = wallet_rpc
+            .create_payment_request(
+                wallet_request.clone(),
+                Some(Coin::new(150_000_000).unwrap()),
+                Some("invoice 42".to_owned()),
+                None,
+            )
+            .unwrap();
+        let payment_request = parse_payment_request(&uri).unwrap();
+
+        let transfer_addresses = wallet_rpc
+            .list_transfer_addresses(wallet_request, None, None, None)
+            .unwrap();
+        assert_eq!(2, transfer_addresses.len());
+        assert!(transfer_addresses.contains(&payment_request.address.to_string()));
+        assert_eq!(
+            Some(Coin::new(150_000_000).unwrap()),
+            payment_request.amount
+        );
+        assert_eq!(Some("invoice 42".to_owned()), payment_request.label);
+    }
+
+    #[test]
+    fn address_qr_should_only_encode_own_addresses() {
+        let wallet_rpc = setup_wallet_rpc();
+        let (create_request, wallet_request) = create_wallet_request("Default", "123456");
+
+        wallet_rpc
+            .create(create_request, WalletKind::Basic, None, None)
+            .unwrap();
+
+        let address = wallet_rpc
+            .create_transfer_address(wallet_request.clone())
+            .unwrap();
+        let payload = wallet_rpc
+            .address_qr(wallet_request.clone(), address.clone())
+            .unwrap();
+        match parse_qr_payload(&payload).unwrap() {
+            QrPayload::Address(parsed) => assert_eq!(address, parsed.to_string()),
+            payload => panic!("unexpected payload: {:?}", payload),
+        }
+
+        let foreign = ExtendedAddr::OrTree([1; 32]).to_string();
+        assert!(wallet_rpc.address_qr(wallet_request, foreign).is_err());
+    }
+
+    #[test]
+    fn address_labels_should_be_listed_and_unique() {
+        let wallet_rpc = setup_wallet_rpc();
+        let (create_request, wallet_request) = create_wallet_request("Default", "123456");
+
+        wallet_rpc
+            .create(create_request, WalletKind::Basic, None, None)
+            .unwrap();
+
+        let address = wallet_rpc
+            .create_transfer_address(wallet_request.clone())
+            .unwrap();
+        wallet_rpc
+            .set_address_label(
+                wallet_request.clone(),
+                address.clone(),
+                "savings".to_owned(),
+            )
+            .unwrap();
+        assert_eq!(
+            Some(address.clone()),
+            wallet_rpc
+                .address_by_label(wallet_request.clone(), "savings".to_owned())
+                .unwrap()
+        );
+
+        let details = wallet_rpc
+            .list_addresses_detailed(wallet_request.clone())
+            .unwrap();
+        let labelled = details
+            .iter()
+            .find(|details| details.address == address)
+            .unwrap();
+        assert_eq!(Some("savings".to_owned()), labelled.label);
+
+        let other_address = details
+            .iter()
+            .find(|details| details.address != address)
+            .unwrap()
+            .address
+            .clone();
+        assert!(wallet_rpc
+            .set_address_label(wallet_request, other_address, "savings".to_owned())
+            .is_err());
+    }
+
+    #[test]
+    fn contacts_should_be_listed_and_resolved() {
+        let wallet_rpc = setup_wallet_rpc();
+        let (create_request, wallet_request) = create_wallet_request("Default", "123456");
+
+        wallet_rpc
+            .create(create_request, WalletKind::Basic, None, None)
+            .unwrap();
+
+        let alice = ExtendedAddr::OrTree([1; 32]);
+        wallet_rpc
+            .add_contact(
+                wallet_request.clone(),
+                "alice".to_owned(),
+                alice.to_string(),
+            )
+            .unwrap();
+        let err = wallet_rpc
+            .add_contact(
+                wallet_request.clone(),
+                "alice".to_owned(),
+                ExtendedAddr::OrTree([2; 32]).to_string(),
+            )
+            .unwrap_err();
+        assert!(err.message.contains("already exists"));
+        assert!(wallet_rpc
+            .add_contact(
+                wallet_request.clone(),
+                "bob".to_owned(),
+                "not an address".to_owned()
+            )
+            .is_err());
+
+        let contacts = wallet_rpc.list_contacts(wallet_request.clone()).unwrap();
+        assert_eq!(1, contacts.len());
+        assert_eq!(Some(&alice.to_string()), contacts.get("alice"));
+
+        let amount = Coin::unit();
+        assert_eq!(
+            (alice.clone(), amount),
+            wallet_rpc
+                .transfer_destination(&wallet_request, "contact:alice", amount)
+                .unwrap()
+        );
+        assert_eq!(
+            (alice.clone(), amount),
+            wallet_rpc
+                .transfer_destination(&wallet_request, &alice.to_string(), amount)
+                .unwrap()
+        );
+        assert!(wallet_rpc
+            .transfer_destination(&wallet_request, "contact:bob", amount)
+            .is_err());
+
+        // the address book isn't accessible with a wrong passphrase
+        let (_, wrong_request) = create_wallet_request("Default", "wrong passphrase");
+        assert!(wallet_rpc.list_contacts(wrong_request).is_err());
+
+        wallet_rpc
+            .remove_contact(wallet_request.clone(), "alice".to_owned())
+            .unwrap();
+        assert!(wallet_rpc
+            .list_contacts(wallet_request.clone())
+            .unwrap()
+            .is_empty());
+        assert!(wallet_rpc
+            .transfer_destination(&wallet_request, "contact:alice", amount)
+            .is_err());
+    }
+
+    #[test]
+    fn send_to_expired_payment_request_should_fail() {
+        let wallet_rpc = setup_wallet_rpc();
+        let (create_request, wallet_request) = create_wallet_request("Default", "123456");
+
+        wallet_rpc
+            .create(create_request, WalletKind::Basic, None, None)
+            .unwrap();
+
+        let uri = wallet_rpc
+            .create_payment_request(wallet_request.clone(), None, None, Some(1))
+            .unwrap();
+
+        let err = wallet_rpc
+            .send_to_address(wallet_request, uri, Coin::unit(), vec![], None, None, None)
+            .unwrap_err();
+        assert!(err.message.contains("expired"));
+    }
+
     #[test]
     fn get_view_key_should_return_public_key() {
         let wallet_rpc = setup_wallet_rpc();
         let (create_request, wallet_request) = create_wallet_request("Default", "123456");
 
         wallet_rpc
-            .create(create_request, WalletKind::Basic, None)
+            .create(create_request, WalletKind::Basic, None, None)
             .unwrap();
 
         assert_eq!(
@@ -919,7 +2322,7 @@ pub mod tests {
         let wallet_rpc = setup_wallet_rpc();
         let (create_request, wallet_request) = create_wallet_request("Default", "123456");
         wallet_rpc
-            .create(create_request.clone(), WalletKind::Basic, None)
+            .create(create_request.clone(), WalletKind::Basic, None, None)
             .unwrap();
         let old_staking_address = wallet_rpc
             .list_staking_addresses(wallet_request.clone(), None, None, None)
@@ -952,6 +2355,110 @@ pub mod tests {
         assert_eq!(old_enckey, new_enckey);
     }
 
+    #[test]
+    fn renamed_wallet_should_be_found_by_id() {
+        let wallet_rpc = setup_wallet_rpc();
+        let (create_request, wallet_request) = create_wallet_request("Default", "123456");
+        wallet_rpc
+            .create(create_request.clone(), WalletKind::Basic, None, None)
+            .unwrap();
+        let wallet_id = wallet_rpc.get_id(wallet_request).unwrap();
+        assert_eq!("Default", wallet_rpc.by_id(wallet_id.clone()).unwrap());
+
+        let enckey = wallet_rpc
+            .rename(create_request, "Renamed".to_owned())
+            .unwrap();
+        assert_eq!("Renamed", wallet_rpc.by_id(wallet_id.clone()).unwrap());
+        let wallet_request = WalletRequest {
+            name: "Renamed".to_owned(),
+            enckey,
+        };
+        assert_eq!(wallet_id, wallet_rpc.get_id(wallet_request).unwrap());
+        assert!(wallet_rpc.by_id("unknown".to_owned()).is_err());
+    }
+
+    #[test]
+    fn changed_passphrase_should_replace_old_one() {
+        let wallet_rpc = setup_wallet_rpc();
+        let (create_request, wallet_request) = create_wallet_request("Default", "123456");
+        wallet_rpc
+            .create(create_request.clone(), WalletKind::Basic, None, None)
+            .unwrap();
+        let wallet_id = wallet_rpc.get_id(wallet_request).unwrap();
+
+        let enckey = wallet_rpc
+            .change_passphrase(create_request.clone(), SecUtf8::from("654321"))
+            .unwrap();
+        assert!(wallet_rpc.get_enc_key(create_request).is_err());
+        let (create_request, _) = create_wallet_request("Default", "654321");
+        assert_eq!(enckey, wallet_rpc.get_enc_key(create_request).unwrap());
+        let wallet_request = WalletRequest {
+            name: "Default".to_owned(),
+            enckey,
+        };
+        assert_eq!(wallet_id, wallet_rpc.get_id(wallet_request).unwrap());
+    }
+
+    #[test]
+    fn import_key_should_reject_invalid_and_duplicate_keys() {
+        let wallet_rpc = setup_wallet_rpc();
+        let (create_request, wallet_request) = create_wallet_request("Default", "123456");
+        wallet_rpc
+            .create(create_request, WalletKind::Basic, None, None)
+            .unwrap();
+
+        // zero, curve order and malformed keys
+        for invalid_key in &[
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141",
+            "0101",
+            "not hex",
+        ] {
+            assert!(wallet_rpc
+                .import_key(
+                    wallet_request.clone(),
+                    SecUtf8::from(*invalid_key),
+                    "transfer".to_owned(),
+                )
+                .is_err());
+        }
+
+        let private_key =
+            SecUtf8::from("0101010101010101010101010101010101010101010101010101010101010101");
+        let staking_address = wallet_rpc
+            .import_key(
+                wallet_request.clone(),
+                private_key.clone(),
+                "staking".to_owned(),
+            )
+            .unwrap();
+        assert!(wallet_rpc
+            .list_staking_addresses(wallet_request.clone(), None, None, None)
+            .unwrap()
+            .contains(&staking_address));
+
+        assert_eq!(
+            staking_address,
+            wallet_rpc
+                .import_key(
+                    wallet_request.clone(),
+                    private_key.clone(),
+                    "staking".to_owned(),
+                )
+                .unwrap()
+        );
+        assert!(wallet_rpc
+            .import_key(wallet_request.clone(), private_key, "transfer".to_owned())
+            .is_err());
+        assert!(wallet_rpc
+            .import_key(
+                wallet_request,
+                SecUtf8::from("0202020202020202020202020202020202020202020202020202020202020202"),
+                "unknown".to_owned(),
+            )
+            .is_err());
+    }
+
     #[test]
     fn list_should_list_all_wallets() {
         let wallet_rpc = setup_wallet_rpc();
@@ -963,6 +2470,7 @@ pub mod tests {
                 create_wallet_request("Default", "123456").0,
                 WalletKind::Basic,
                 None,
+                None,
             )
             .unwrap();
 
@@ -973,6 +2481,7 @@ pub mod tests {
                 create_wallet_request("Personal", "123456").0,
                 WalletKind::Basic,
                 None,
+                None,
             )
             .unwrap();
 
@@ -988,15 +2497,208 @@ pub mod tests {
         let (create_request, wallet_request) = create_wallet_request("Default", "123456");
 
         wallet_rpc
-            .create(create_request, WalletKind::Basic, None)
+            .create(create_request, WalletKind::Basic, None, None)
             .unwrap();
         assert_eq!(
             0,
             wallet_rpc
-                .transactions(wallet_request.clone(), 0, 100, false)
+                .transactions(wallet_request.clone(), 0, 100, false, None)
                 .unwrap()
                 .len()
-        )
+        );
+
+        let filter = HistoryFilter {
+            from_height: Some(1),
+            ..Default::default()
+        };
+        assert!(wallet_rpc
+            .transactions(wallet_request.clone(), 0, 100, true, Some(filter.clone()))
+            .unwrap()
+            .is_empty());
+        assert!(wallet_rpc
+            .transactions(wallet_request, 0, 100, false, Some(filter))
+            .is_err());
+    }
+
+    #[test]
+    fn fee_summary_should_parse_dates() {
+        let wallet_rpc = setup_wallet_rpc();
+        let (create_request, wallet_request) = create_wallet_request("Default", "123456");
+
+        wallet_rpc
+            .create(create_request, WalletKind::Basic, None, None)
+            .unwrap();
+        let summary = wallet_rpc
+            .fee_summary(
+                wallet_request.clone(),
+                "2020-01-01".to_owned(),
+                "2020-01-31".to_owned(),
+            )
+            .unwrap();
+        assert_eq!("0", summary.total);
+        assert_eq!(0, summary.tx_count);
+        assert!(wallet_rpc
+            .fee_summary(
+                wallet_request.clone(),
+                "2020-01-31".to_owned(),
+                "2020-01-01".to_owned()
+            )
+            .is_err());
+        assert!(wallet_rpc
+            .fee_summary(
+                wallet_request,
+                "01/01/2020".to_owned(),
+                "2020-01-31".to_owned()
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn pending_transactions_should_survive_restart() {
+        let storage = MemoryStorage::default();
+        let wallet_rpc = WalletRpcImpl::new(make_test_wallet_client(storage.clone()), 171u8);
+        let (create_request, wallet_request) = create_wallet_request("Default", "123456");
+        wallet_rpc
+            .create(create_request, WalletKind::Basic, None, None)
+            .unwrap();
+
+        // record a broadcast deposit
+        let tx = DepositBondTx::new(
+            vec![TxoPointer::new([1; 32], 0)],
+            StakedStateAddress::BasicRedeem(RedeemAddress::default()),
+            StakedStateOpAttributes::new(171),
+        );
+        let tx_aux = TxAux::EnclaveTx(TxEnclaveAux::DepositStakeTx {
+            tx: tx.clone(),
+            payload: TxObfuscated {
+                txid: tx.id(),
+                key_from: BlockHeight::genesis(),
+                init_vector: [0u8; 12],
+                txpayload: vec![],
+            },
+        });
+        let tx_pending = TransactionPending {
+            used_inputs: tx.inputs.clone(),
+            block_height: 0,
+            return_amount: Coin::zero(),
+        };
+        wallet_rpc
+            .client
+            .update_tx_pending_state(
+                &wallet_request.name,
+                &wallet_request.enckey,
+                &tx_aux,
+                tx_pending,
+            )
+            .unwrap();
+        drop(wallet_rpc);
+
+        // restart: a new client on the same storage
+        let wallet_rpc = WalletRpcImpl::new(make_test_wallet_client(storage.clone()), 171u8);
+        let pending = wallet_rpc
+            .pending_transactions(wallet_request.clone())
+            .unwrap();
+        assert_eq!(1, pending.len());
+        assert_eq!(tx_aux.tx_id(), pending[0].transaction_id);
+        assert_eq!(PendingTransactionStatus::Pending, pending[0].status);
+        assert_eq!(1, pending[0].broadcast_count);
+        assert_eq!(hex::encode(tx_aux.encode()), pending[0].raw_tx_hex);
+        assert_eq!(None, pending[0].idempotency_key);
+
+        // not stale yet: can't be re-broadcast nor expired
+        let txid = hex::encode(tx_aux.tx_id());
+        assert!(wallet_rpc
+            .rebroadcast_pending_transaction(wallet_request.clone(), txid.clone())
+            .is_err());
+        assert!(wallet_rpc
+            .expire_pending_transaction(wallet_request.clone(), txid)
+            .is_err());
+
+        // the transaction is confirmed (synced) after the restart
+        let mut memento = WalletStateMemento::default();
+        memento.remove_pending_transaction(tx_aux.tx_id());
+        memento.add_transaction_change(TransactionChange {
+            transaction_id: tx_aux.tx_id(),
+            inputs: vec![],
+            outputs: vec![],
+            fee_paid: Fee::new(Coin::zero()),
+            balance_change: BalanceChange::NoChange,
+            transaction_type: TransactionType::Deposit,
+            block_height: 1,
+            block_time: Time::now(),
+            memo: None,
+        });
+        WalletStateService::new(storage)
+            .apply_memento(&wallet_request.name, &wallet_request.enckey, &memento)
+            .unwrap();
+
+        let pending = wallet_rpc
+            .pending_transactions(wallet_request.clone())
+            .unwrap();
+        assert_eq!(1, pending.len());
+        assert_eq!(PendingTransactionStatus::Confirmed, pending[0].status);
+        // listing doesn't change the tracker: it's reported until the sync stops tracking it
+        assert_eq!(
+            pending,
+            wallet_rpc.pending_transactions(wallet_request).unwrap()
+        );
+    }
+
+    #[test]
+    fn create_raw_transaction_should_return_signed_transaction_hex() {
+        let storage = MemoryStorage::default();
+        let wallet_rpc = WalletRpcImpl::new(make_test_wallet_client(storage.clone()), 171u8);
+        let (create_request, wallet_request) = create_wallet_request("Default", "123456");
+        wallet_rpc
+            .create(create_request, WalletKind::Basic, None, None)
+            .unwrap();
+
+        let own_address = wallet_rpc
+            .create_transfer_address(wallet_request.clone())
+            .unwrap()
+            .parse::<ExtendedAddr>()
+            .unwrap();
+        let mut memento = WalletStateMemento::default();
+        memento.add_unspent_transaction(
+            TxoPointer::new([1; 32], 0),
+            TxOut::new(own_address.clone(), Coin::unit()),
+        );
+        WalletStateService::new(storage)
+            .apply_memento(&wallet_request.name, &wallet_request.enckey, &memento)
+            .unwrap();
+
+        // (the mock client panics on broadcast)
+        let raw_tx = wallet_rpc
+            .create_raw_transaction(
+                wallet_request.clone(),
+                ExtendedAddr::OrTree([9; 32]).to_string(),
+                Coin::new(1000).unwrap(),
+                vec![],
+                None,
+            )
+            .unwrap();
+        let tx_aux = TxAux::decode(&mut hex::decode(&raw_tx).unwrap().as_slice()).unwrap();
+        assert_eq!(raw_tx, hex::encode(tx_aux.encode()));
+
+        match tx_aux {
+            TxAux::EnclaveTx(TxEnclaveAux::TransferTx {
+                inputs, payload, ..
+            }) => {
+                assert_eq!(vec![TxoPointer::new([1; 32], 0)], inputs);
+                match SignedTransaction::decode(&mut payload.txpayload.as_slice()).unwrap() {
+                    SignedTransaction::TransferTransaction(tx, witness) => {
+                        assert_eq!(payload.txid, tx.id());
+                        assert!(verify_tx_address(&witness[0], &tx.id(), &own_address).is_ok());
+                    }
+                    _ => panic!("not a transfer transaction"),
+                }
+            }
+            _ => panic!("not a transfer transaction"),
+        }
+        assert!(wallet_rpc
+            .pending_transactions(wallet_request)
+            .unwrap()
+            .is_empty());
     }
 
     fn make_test_wallet_client(storage: MemoryStorage) -> TestWalletClient {
@@ -1005,6 +2707,7 @@ pub mod tests {
             signer_manager,
             ZeroFeeAlgorithm::default(),
             MockTransactionCipher,
+            LargestFirst,
         );
         DefaultWalletClient::new(
             storage,
@@ -1030,6 +2733,7 @@ pub mod tests {
             CreateWalletRequest {
                 name: name.to_owned(),
                 passphrase: passphrase.clone(),
+                passphrase_confirmation: None,
             },
             WalletRequest {
                 name: name.to_owned(),
@@ -1038,6 +2742,82 @@ pub mod tests {
         )
     }
 
+    #[test]
+    fn create_should_check_passphrase_confirmation() {
+        let wallet_rpc = setup_wallet_rpc();
+
+        let (mut request, _) = create_wallet_request("Default", "123456");
+        request.passphrase_confirmation = Some(SecUtf8::from("123465"));
+        let err = wallet_rpc
+            .create(request, WalletKind::Basic, None, None)
+            .unwrap_err();
+        assert_eq!(
+            ErrorCode::ServerError(PASSPHRASE_MISMATCH_ERROR_CODE),
+            err.code
+        );
+        assert!(wallet_rpc.list().unwrap().is_empty());
+
+        let (mut request, _) = create_wallet_request("Default", "123456");
+        request.passphrase_confirmation = Some(SecUtf8::from("123456"));
+        wallet_rpc
+            .create(request, WalletKind::Basic, None, None)
+            .unwrap();
+        assert_eq!(vec!["Default"], wallet_rpc.list().unwrap());
+    }
+
+    #[test]
+    fn verify_passphrase_should_check_passphrase_of_wallet() {
+        let wallet_rpc = setup_wallet_rpc();
+        let (request, _) = create_wallet_request("Default", "123456");
+        wallet_rpc
+            .create(request.clone(), WalletKind::Basic, None, None)
+            .unwrap();
+
+        wallet_rpc.verify_passphrase(request.clone()).unwrap();
+        for _ in 0..PASSPHRASE_FREE_ATTEMPTS {
+            let err = wallet_rpc
+                .verify_passphrase(create_wallet_request("Default", "654321").0)
+                .unwrap_err();
+            assert_eq!(ErrorCode::InternalError, err.code);
+        }
+
+        // throttled (even with the right passphrase)
+        let err = wallet_rpc.verify_passphrase(request).unwrap_err();
+        assert_eq!(
+            ErrorCode::ServerError(PASSPHRASE_THROTTLED_ERROR_CODE),
+            err.code
+        );
+    }
+
+    #[test]
+    fn passphrase_throttle_should_back_off_exponentially() {
+        let throttle = PassphraseThrottle::default();
+        let now = Instant::now();
+
+        for _ in 0..PASSPHRASE_FREE_ATTEMPTS {
+            assert_eq!(None, throttle.locked_for("Default", now));
+            throttle.record("Default", false, now);
+        }
+        assert_eq!(
+            Some(Duration::from_secs(1)),
+            throttle.locked_for("Default", now)
+        );
+        assert_eq!(None, throttle.locked_for("Personal", now));
+
+        let later = now + Duration::from_secs(1);
+        assert_eq!(None, throttle.locked_for("Default", later));
+        throttle.record("Default", false, later);
+        assert_eq!(
+            Some(Duration::from_secs(2)),
+            throttle.locked_for("Default", later)
+        );
+
+        throttle.record("Default", true, later);
+        assert_eq!(None, throttle.locked_for("Default", later));
+
+        assert_eq!(Some(PASSPHRASE_MAX_DELAY), throttle_delay(u32::max_value()));
+    }
+
     #[test]
     fn hdwallet_should_create_hd_wallet() {
         let wallet_rpc = setup_wallet_rpc();
@@ -1047,10 +2827,64 @@ pub mod tests {
                 create_wallet_request("Default", "123456").0,
                 WalletKind::HD,
                 Some(24),
+                None,
             )
             .unwrap();
     }
 
+    #[test]
+    fn hdwallet_should_be_created_and_recovered_in_language() {
+        let wallet_rpc = setup_wallet_rpc();
+
+        let (_, mnemonic) = wallet_rpc
+            .create(
+                create_wallet_request("Default", "123456").0,
+                WalletKind::HD,
+                Some(12),
+                Some(MnemonicLanguage::Korean),
+            )
+            .unwrap();
+        let mnemonic = SecUtf8::from(mnemonic.unwrap());
+        assert_eq!(
+            MnemonicLanguage::Korean,
+            Mnemonic::from_secstr(&mnemonic).unwrap().language()
+        );
+
+        let (detected_request, detected_wallet) = create_wallet_request("Detected", "123456");
+        wallet_rpc
+            .restore(detected_request, mnemonic.clone(), None)
+            .unwrap();
+        let (given_request, given_wallet) = create_wallet_request("Given", "123456");
+        wallet_rpc
+            .restore(
+                given_request,
+                mnemonic.clone(),
+                Some(MnemonicLanguage::Korean),
+            )
+            .unwrap();
+        assert!(wallet_rpc
+            .restore(
+                create_wallet_request("Wrong", "123456").0,
+                mnemonic,
+                Some(MnemonicLanguage::Spanish),
+            )
+            .is_err());
+
+        let view_key = |request: WalletRequest| wallet_rpc.get_view_key(request, false).unwrap();
+        let expected = view_key(create_wallet_request("Default", "123456").1);
+        assert_eq!(expected, view_key(detected_wallet));
+        assert_eq!(expected, view_key(given_wallet));
+
+        assert!(wallet_rpc
+            .create(
+                create_wallet_request("Basic", "123456").0,
+                WalletKind::Basic,
+                None,
+                Some(MnemonicLanguage::Korean),
+            )
+            .is_err());
+    }
+
     #[test]
     fn hdwallet_should_recover_hd_wallet() {
         let wallet_rpc = setup_wallet_rpc();
@@ -1058,7 +2892,8 @@ pub mod tests {
         wallet_rpc
             .restore(
                 create_wallet_request("Default", "123456").0,
-                Mnemonic::from_secstr(&SecUtf8::from("online hire print other clock like betray vote hollow bus insect meadow replace two tape worry quality disease cabin girl tree pudding issue radar")).unwrap()
+                SecUtf8::from("online hire print other clock like betray vote hollow bus insect meadow replace two tape worry quality disease cabin girl tree pudding issue radar"),
+                None,
             )
             .unwrap();
     }
@@ -1071,7 +2906,8 @@ pub mod tests {
         wallet_rpc
             .restore(
                 create_request,
-                Mnemonic::from_secstr(&SecUtf8::from("speed tortoise kiwi forward extend baby acoustic foil coach castle ship purchase unlock base hip erode tag keen present vibrant oyster cotton write fetch")).unwrap()
+                SecUtf8::from("speed tortoise kiwi forward extend baby acoustic foil coach castle ship purchase unlock base hip erode tag keen present vibrant oyster cotton write fetch"),
+                None,
             )
             .unwrap();
 
@@ -1103,6 +2939,9 @@ pub mod tests {
             addrs[0].clone(),
             Coin::from(1_0000u32),
             vec![viewkey],
+            None,
+            None,
+            None,
         );
         assert!(send_result.is_err());
     }
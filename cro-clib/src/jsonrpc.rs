@@ -229,7 +229,10 @@ unsafe fn create_rpc(
     let handler = RpcHandler::new(
         &storage_dir,
         &websocket_url,
+        None,
         network_id,
+        false,
+        false,
         options,
         cbindingcallback.clone(),
     )?;
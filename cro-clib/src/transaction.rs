@@ -1,13 +1,13 @@
 use crate::types::get_string;
 use crate::types::CroStakedState;
 use crate::types::{CroAddress, CroAddressPtr, CroResult};
+use chain_core::common::decode_all_strict;
 use chain_core::init::coin::Coin;
 pub use chain_core::init::network::Network;
 use chain_core::state::account::{
     StakedState, StakedStateAddress, StakedStateOpAttributes, StakedStateOpWitness, UnbondTx,
     WithdrawUnbondedTx,
 };
-use chain_core::tx::data::access::{TxAccess, TxAccessPolicy};
 use chain_core::tx::data::address::ExtendedAddr;
 use chain_core::tx::data::attribute::TxAttributes;
 use chain_core::tx::data::output::TxOut;
@@ -20,7 +20,6 @@ use client_common::{ErrorKind, Result, ResultExt, Transaction};
 use client_common::{PrivateKeyAction, PublicKey, SignedTransaction};
 use parity_scale_codec::Decode;
 use parity_scale_codec::Encode;
-use std::collections::BTreeSet;
 use std::os::raw::c_char;
 use std::ptr;
 use std::str::FromStr;
@@ -123,18 +122,14 @@ fn create_encoded_signed_withdraw(
     let staked_state = mstaking.err_kind(ErrorKind::InvalidInput, || {
         "staking address not found, sync to the latest and check staking address is correct"
     })?;
-    let access_policies = viewkeys
+    let view_keys = viewkeys
         .iter()
-        .map(|s| {
-            let view_key = s.trim();
-            let public_key = PublicKey::from_str(view_key)?;
-            Ok(TxAccessPolicy {
-                view_key: public_key.into(),
-                access: TxAccess::AllData,
-            })
-        })
-        .collect::<Result<BTreeSet<_>>>()?;
-    let attributes = TxAttributes::new_with_access(network, access_policies.into_iter().collect());
+        .map(|s| PublicKey::from_str(s.trim()))
+        .collect::<Result<Vec<_>>>()?;
+    let attributes = TxAttributes::builder(network)
+        .allow_view_many(view_keys.iter().map(Into::into))
+        .build()
+        .chain(|| (ErrorKind::InvalidInput, "Invalid transaction attributes"))?;
     let nonce = staked_state.nonce;
     let amount = staked_state.unbonded;
     let outputs = vec![TxOut::new_with_timelock(
@@ -318,6 +313,12 @@ pub unsafe extern "C" fn cro_encrypt(
 }
 
 fn broadcast_packaged_tx(tendermint_url: &str, data: &[u8]) -> Result<String> {
+    decode_all_strict::<TxAux>(data).chain(|| {
+        (
+            ErrorKind::DeserializationError,
+            "Unable to decode packaged transaction",
+        )
+    })?;
     let tendermint_client = WebsocketRpcClient::new(&tendermint_url)?;
     let result = tendermint_client.broadcast_transaction(data)?;
     let json =
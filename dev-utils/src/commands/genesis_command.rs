@@ -381,11 +381,12 @@ pub fn generate_genesis(
         rewards_config: genesis_dev_config.rewards_config,
         max_validators: 50,
     };
-    let config = InitConfig::new(
+    let mut config = InitConfig::new(
         dist,
         network_params,
         genesis_dev_config.council_nodes.clone(),
     );
+    config.minimum_bond_remainder = genesis_dev_config.minimum_bond_remainder;
     let genesis_app_hash = init_app_hash(&config, genesis_time);
 
     let validators = generate_validators(&genesis_dev_config)?;
@@ -15,6 +15,9 @@ pub struct GenesisDevConfig {
     pub distribution: BTreeMap<RedeemAddress, Coin>,
     pub required_council_node_stake: Coin,
     pub required_community_node_stake: Coin,
+    /// not specified: no minimum (and the genesis is valid before app version 2)
+    #[serde(default)]
+    pub minimum_bond_remainder: Option<Coin>,
     pub jailing_config: JailingParameters,
     pub slashing_config: SlashingParameters,
     pub rewards_config: RewardsParameters,
@@ -37,6 +40,7 @@ impl GenesisDevConfig {
             distribution: BTreeMap::new(),
             required_council_node_stake: Coin::new(1_250_000_000_000_000_000).unwrap(),
             required_community_node_stake: Coin::new(100_000_000_000).unwrap(),
+            minimum_bond_remainder: None,
             jailing_config: JailingParameters {
                 block_signing_window: 100,
                 missed_block_threshold: 50,
@@ -36,10 +36,32 @@ pub struct InitCommand {
     tendermint_command: String,
     validators: Vec<TendermintValidator>,
     genesis_time: Time,
+    base_units: bool,
+}
+
+/// Parses a distribution amount: in CRO (e.g. `25000000.5`) or in base units with `u` suffix
+/// (e.g. `2500000000000000u`). If `base_units` is set, amounts without the suffix are in base
+/// units as well.
+fn parse_amount(amount: &str, base_units: bool) -> Result<Coin> {
+    let amount = amount.trim();
+    let coin = match amount.strip_suffix('u') {
+        Some(units) => Coin::from_str(units),
+        None if base_units => Coin::from_str(amount),
+        None => Coin::from_cro_str(amount),
+    };
+    coin.chain(|| {
+        (
+            ErrorKind::InvalidInput,
+            format!(
+                "Invalid amount {} (expected CRO with up to 8 decimals, or base units with `u` suffix)",
+                amount
+            ),
+        )
+    })
 }
 
 impl InitCommand {
-    pub fn new() -> Self {
+    pub fn new(base_units: bool) -> Self {
         let expansion_cap = Coin::new(2_500_000_000_000_000_000).unwrap();
         InitCommand {
             chain_id: "".to_string(),
@@ -54,32 +76,91 @@ impl InitCommand {
             tendermint_command: "./tendermint".to_string(),
             validators: Vec::new(),
             genesis_time: Time::unix_epoch(),
+            base_units,
         }
     }
 
     /// easily add distribution wallet for development
     fn read_wallet(&mut self, id: &str, default_address: &str, default_amount: &str) {
-        let address = self.ask_string(
-            format!("wallet {}({}) address=", id, default_address).as_str(),
-            default_address,
-        );
+        loop {
+            let address = self.ask_string(
+                format!("wallet {}({}) address=", id, default_address).as_str(),
+                default_address,
+            );
 
-        let amount = self.ask_string(
-            format!("wallet {}({}) amount=", id, default_amount).as_str(),
-            default_amount,
-        );
+            let amount = self.ask_string(
+                format!("wallet {}({}) amount=", id, default_amount).as_str(),
+                default_amount,
+            );
+
+            match self.do_read_wallet(&format!("wallet {}", id), &address, &amount) {
+                Ok(()) => break,
+                Err(e) => println!("{}, please enter again", e),
+            }
+        }
+    }
+
+    fn do_read_wallet(&mut self, field: &str, address: &str, amount: &str) -> Result<()> {
+        let redeem_address = RedeemAddress::from_str(address.trim()).chain(|| {
+            (
+                ErrorKind::InvalidInput,
+                format!("Invalid {} address: {}", field, address),
+            )
+        })?;
+        if self
+            .genesis_dev_config
+            .distribution
+            .contains_key(&redeem_address)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Duplicate {} address: {}", field, address),
+            ));
+        }
+        let amount_coin = parse_amount(amount, self.base_units).map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} amount: {}", field, e.message()),
+            )
+        })?;
+        let distributable = self.distributable_coin();
+        if amount_coin > distributable {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{} amount {} CRO exceeds the remaining supply {} CRO",
+                    field, amount_coin, distributable
+                ),
+            ));
+        }
 
-        self.do_read_wallet(address, amount);
+        self.genesis_dev_config
+            .distribution
+            .insert(redeem_address, amount_coin);
+        self.remain_coin = (self.remain_coin - amount_coin)
+            .chain(|| (ErrorKind::InvalidInput, "Distribution exceeds total supply"))?;
+        self.distribution_addresses.push(address.trim().to_owned());
+        println!(
+            "distributed = {} CRO, remaining = {} CRO",
+            self.distributed_coin(),
+            self.distributable_coin()
+        );
+        Ok(())
     }
 
-    fn do_read_wallet(&mut self, address: String, amount_cro: String) {
-        let amount_u64 = (amount_cro.parse::<f64>().unwrap() * 1_0000_0000_f64) as u64;
-        let amount_coin = Coin::new(amount_u64).unwrap();
+    /// total amount distributed so far
+    fn distributed_coin(&self) -> Coin {
+        (Coin::max() - self.remain_coin).unwrap_or_default()
+    }
 
-        let distribution = &mut self.genesis_dev_config.distribution;
-        distribution.insert(RedeemAddress::from_str(&address).unwrap(), amount_coin);
-        self.remain_coin = (self.remain_coin - amount_coin).unwrap();
-        self.distribution_addresses.push(address);
+    /// amount which can still be distributed (rest of the supply is the monetary expansion cap)
+    fn distributable_coin(&self) -> Coin {
+        (self.remain_coin
+            - self
+                .genesis_dev_config
+                .rewards_config
+                .monetary_expansion_cap)
+            .unwrap_or_default()
     }
 
     fn check_chain_id(&self, chain_id: String) -> Result<()> {
@@ -129,30 +210,45 @@ impl InitCommand {
             "12500000000",
             "12500000000",
         ];
+        let default_coins = if self.base_units {
+            default_coins
+                .iter()
+                .map(|coin| format!("{}00000000", coin))
+                .collect::<Vec<_>>()
+        } else {
+            default_coins.iter().map(|coin| coin.to_string()).collect()
+        };
         println!(
-            "maximum coin to distribute = {}",
-            self.remain_coin.to_string()
+            "maximum coin to distribute = {} CRO{}",
+            self.distributable_coin(),
+            if self.base_units {
+                " (amounts are in base units)"
+            } else {
+                " (use `u` suffix for amounts in base units)"
+            }
         );
 
         assert!(42 == self.staking_account_address.len());
+        let staking_account_address = self.staking_account_address.clone();
         self.do_read_wallet(
-            self.staking_account_address.clone(),
-            "12500000000".to_string(),
-        );
+            "staking account",
+            &staking_account_address,
+            &default_coins[0],
+        )?;
 
         loop {
             let i = self.distribution_addresses.len();
-            if self.remain_coin
-                == self
-                    .genesis_dev_config
-                    .rewards_config
-                    .monetary_expansion_cap
-            {
+            let distributable = self.distributable_coin();
+            if distributable == Coin::zero() {
                 break;
             }
             let j = i - 1;
             let mut this_address = default_address.clone();
-            let mut this_coin = self.remain_coin.to_string();
+            let mut this_coin = if self.base_units {
+                u64::from(distributable).to_string()
+            } else {
+                distributable.to_string()
+            };
             if j < default_addresses.len() {
                 this_address = default_addresses[j].to_string();
             }
@@ -480,3 +576,108 @@ impl InitCommand {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS_1: &str = "0x3ae55c16800dc4bd0e3397a9d7806fb1f11639de";
+    const ADDRESS_2: &str = "0xbdf8b636b59b6dbec56eb07eb87d75dd0db3edd3";
+
+    #[test]
+    fn check_parse_amount() {
+        assert_eq!(
+            Coin::new(2_500_000_050_000_000).unwrap(),
+            parse_amount("25000000.5", false).unwrap()
+        );
+        assert_eq!(
+            Coin::new(2_500_000_000_000_000_000).unwrap(),
+            parse_amount("2500000000000000000u", false).unwrap()
+        );
+        assert_eq!(
+            Coin::new(2_500_000_000_000_000_000).unwrap(),
+            parse_amount("2500000000000000000", true).unwrap()
+        );
+        assert_eq!(Coin::new(25).unwrap(), parse_amount("25u", true).unwrap());
+        assert!(parse_amount("25000000.5", true).is_err());
+        assert!(parse_amount("25000000.5u", false).is_err());
+        assert!(parse_amount("1.000000001", false).is_err());
+        // one zero too many: above the total supply
+        assert!(parse_amount("1000000000000", false).is_err());
+        assert!(parse_amount("", false).is_err());
+    }
+
+    #[test]
+    fn check_read_wallet_in_both_denominations() {
+        let mut command = InitCommand::new(false);
+        let distributable = command.distributable_coin();
+
+        command
+            .do_read_wallet("wallet 1", ADDRESS_1, "25000000.5")
+            .unwrap();
+        command
+            .do_read_wallet("wallet 2", ADDRESS_2, "2500000000000000u")
+            .unwrap();
+
+        let amount_1 = Coin::new(2_500_000_050_000_000).unwrap();
+        let amount_2 = Coin::new(2_500_000_000_000_000).unwrap();
+        let distribution = &command.genesis_dev_config.distribution;
+        assert_eq!(amount_1, distribution[&ADDRESS_1.parse().unwrap()]);
+        assert_eq!(amount_2, distribution[&ADDRESS_2.parse().unwrap()]);
+        assert_eq!((amount_1 + amount_2).unwrap(), command.distributed_coin());
+        assert_eq!(
+            (distributable - amount_1 - amount_2).unwrap(),
+            command.distributable_coin()
+        );
+        assert_eq!(2, command.distribution_addresses.len());
+    }
+
+    #[test]
+    fn check_read_wallet_errors_name_the_field() {
+        let mut command = InitCommand::new(true);
+
+        let err = command
+            .do_read_wallet("wallet 1", "0x1234", "100")
+            .unwrap_err();
+        assert!(err.to_string().contains("wallet 1 address"));
+
+        let err = command
+            .do_read_wallet("wallet 1", ADDRESS_1, "1.5")
+            .unwrap_err();
+        assert!(err.to_string().contains("wallet 1 amount"));
+
+        command
+            .do_read_wallet("wallet 1", ADDRESS_1, "100")
+            .unwrap();
+        let err = command
+            .do_read_wallet("wallet 2", ADDRESS_1, "100")
+            .unwrap_err();
+        assert!(err.to_string().contains("Duplicate wallet 2 address"));
+        assert_eq!(1, command.distribution_addresses.len());
+    }
+
+    #[test]
+    fn check_read_wallet_rejects_total_above_supply() {
+        let mut command = InitCommand::new(false);
+        let distributable = command.distributable_coin();
+
+        let err = command
+            .do_read_wallet(
+                "wallet 1",
+                ADDRESS_1,
+                &format!("{}u", u64::from(distributable) + 1),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("exceeds the remaining supply"));
+        assert!(command.genesis_dev_config.distribution.is_empty());
+        assert_eq!(distributable, command.distributable_coin());
+
+        command
+            .do_read_wallet("wallet 1", ADDRESS_1, &distributable.to_string())
+            .unwrap();
+        assert_eq!(Coin::zero(), command.distributable_coin());
+        assert!(command
+            .do_read_wallet("wallet 2", ADDRESS_2, "0.00000001")
+            .is_err());
+    }
+}
@@ -29,7 +29,14 @@ pub enum DevUtils {
         name = "init",
         about = "Make a wallet, generate state, compute hash, and copies to tendermint's genesis.json"
     )]
-    Init,
+    Init {
+        #[structopt(
+            name = "base_units",
+            long = "base-units",
+            help = "Distribution amounts are in base units instead of CRO"
+        )]
+        base_units: bool,
+    },
 
     /// Used for running
     #[structopt(name = "run", about = "run all chain components")]
@@ -84,8 +91,8 @@ impl DevUtils {
     pub fn execute(&self) -> Result<()> {
         match self {
             DevUtils::Genesis { genesis_command } => genesis_command.execute(),
-            DevUtils::Init => {
-                let mut init_command = InitCommand::new();
+            DevUtils::Init { base_units } => {
+                let mut init_command = InitCommand::new(*base_units);
                 init_command.execute()
             }
             DevUtils::Run => {
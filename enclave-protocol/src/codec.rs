@@ -5,6 +5,7 @@ use std::{
     vec::Vec,
 };
 
+use chain_core::common::decode_all_strict;
 use parity_scale_codec::{Decode, Encode};
 
 /// Trait for writing length encoded valus to a stream
@@ -57,6 +58,6 @@ impl<T: Decode> StreamRead for T {
         let mut buffer = vec![0; size];
         reader.read_exact(&mut buffer)?;
 
-        Self::decode(&mut buffer.as_slice()).map_err(|_| io::ErrorKind::Other.into())
+        decode_all_strict(&buffer).map_err(|_| io::ErrorKind::Other.into())
     }
 }
@@ -119,6 +119,16 @@ class Address:
         :params type: [staking|transfer]'''
         return self.client.call('wallet_listStakingAddresses' if type == 'staking' else 'wallet_listTransferAddresses', [name, enckey or get_enckey()])
 
+    def list_detailed(self, name=DEFAULT_WALLET, enckey=None):
+        '''list addresses with the derivation paths of their keys
+        :param name: Name of the wallet. [default: Default]'''
+        return self.client.call('wallet_listAddressesDetailed', [name, enckey or get_enckey()])
+
+    def verify_derivations(self, name=DEFAULT_WALLET, enckey=None):
+        '''public keys whose recorded derivation path doesn't match the seed
+        :param name: Name of the wallet. [default: Default]'''
+        return self.client.call('wallet_verifyDerivations', [name, enckey or get_enckey()])
+
     def create(self, name=DEFAULT_WALLET, type='staking', enckey=None):
         '''Create address
         :param name: Name of the wallet
@@ -191,6 +201,12 @@ class Wallet:
     def delete(self, name=DEFAULT_WALLET, passphrase=None):
         return self.client.call('wallet_delete', [name, passphrase or get_passphrase()])
 
+    def verify_passphrase(self, name=DEFAULT_WALLET, passphrase=None):
+        '''check the passphrase of wallet (throttled after repeated failures)
+        :param name: Name of the wallet. [defualt: Default]
+        '''
+        return self.client.call('wallet_verifyPassphrase', [name, passphrase or get_passphrase()])
+
     def view_key(self, name=DEFAULT_WALLET, private=False, enckey=None):
         return self.client.call(
             'wallet_getViewKey',
@@ -207,7 +223,7 @@ class Wallet:
         return self.client.call(
             'wallet_sendToAddress',
             [name, enckey or get_enckey()],
-            to_address, str(amount), view_keys or [])
+            to_address, str(amount), view_keys or [])['tx_id']
 
     def sync(self, name=DEFAULT_WALLET, enckey=None):
         return self.client.call('sync', [name, enckey or get_enckey()],{"blocking":True, "reset":False, "do_loop":False})
@@ -241,42 +257,51 @@ class Staking:
         self.client = client
 
     def deposit(self, to_address, inputs, name=DEFAULT_WALLET, enckey=None):
-        return self.client.call('staking_depositStake', [name, enckey or get_enckey()], fix_address(to_address), inputs)
+        return self.client.call('staking_depositStake', [name, enckey or get_enckey()], fix_address(to_address), inputs)['tx_id']
 
     def deposit_amount(self, to_address, amount, name=DEFAULT_WALLET, enckey=None):
-        return self.client.call('staking_depositAmountStake', [name, enckey or get_enckey()], fix_address(to_address), str(amount))
+        return self.client.call('staking_depositAmountStake', [name, enckey or get_enckey()], fix_address(to_address), str(amount))['tx_id']
 
     def state(self, address, name=DEFAULT_WALLET):
         return self.client.call('staking_state', name, fix_address(address))
 
     def unbond(self, address, amount, name=DEFAULT_WALLET, enckey=None):
-        return self.client.call('staking_unbondStake', [name, enckey or get_enckey()], fix_address(address), str(amount))
+        return self.client.call('staking_unbondStake', [name, enckey or get_enckey()], fix_address(address), str(amount))['tx_id']
 
     def withdraw_all_unbonded(self, from_address, to_address, view_keys=None, name=DEFAULT_WALLET, enckey=None):
         return self.client.call(
             'staking_withdrawAllUnbondedStake',
             [name, enckey or get_enckey()],
             fix_address(from_address), to_address, view_keys or []
-        )
+        )['tx_id']
 
     def unjail(self, address, name=DEFAULT_WALLET, enckey=None):
-        return self.client.call('staking_unjail', [name, enckey or get_enckey()], fix_address(address))
+        return self.client.call('staking_unjail', [name, enckey or get_enckey()], fix_address(address))['tx_id']
 
-    def join(self, node_name, node_pubkey, node_staking_address, keypackage, name=DEFAULT_WALLET, enckey=None):
-        return self.client.call(
+    def join(self, node_name, node_pubkey, node_staking_address, keypackage, name=DEFAULT_WALLET, enckey=None, preflight_only=False, announced_endpoints=None):
+        result = self.client.call(
             'staking_validatorNodeJoin',
             [name, enckey or get_enckey()],
             node_name,
             node_pubkey,
             fix_address(node_staking_address),
-            keypackage
+            keypackage,
+            preflight_only,
+            announced_endpoints or []
         )
+        return result if preflight_only else result['tx_id']
+
+    def update_node(self, address, announced_endpoints, name=DEFAULT_WALLET, enckey=None):
+        return self.client.call('staking_updateNode', [name, enckey or get_enckey()], fix_address(address), announced_endpoints)['tx_id']
+
+    def announced_endpoints(self, address, name=DEFAULT_WALLET):
+        return self.client.call('staking_announcedEndpoints', name, fix_address(address))
 
     def build_raw_transfer_tx(self, to_address, amount, name=DEFAULT_WALLET,  enckey=None, viewkeys=[]):
         return self.client.call('wallet_buildRawTransferTx', [name, enckey or get_enckey()], to_address, amount, viewkeys)
 
     def broadcast_raw_transfer_tx(self, signed_tx, name=DEFAULT_WALLET, enckey=None):
-        return self.client.call('wallet_broadcastSignedTransferTx', [name, enckey or get_enckey()], signed_tx)
+        return self.client.call('wallet_broadcastSignedTransferTx', [name, enckey or get_enckey()], signed_tx)['tx_id']
 
     def gen_keypackage(self, path=MLS_ENCLAVE_PATH):
         if self.client.mock_mode:
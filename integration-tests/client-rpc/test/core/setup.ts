@@ -64,7 +64,7 @@ const unbondAndWithdrawStakeFromClient = async (
 	console.log(
 		`[Init] Withdrawing bonded genesis funds from "${WALLET_STAKING_ADDRESS}" to "${WALLET_TRANSFER_ADDRESS_1}"`,
 	);
-	const withdrawTxId = await asyncMiddleman(
+	const { tx_id: withdrawTxId } = await asyncMiddleman(
 		rpcClient.request("staking_withdrawAllUnbondedStake", [
 			walletRequest,
 			WALLET_STAKING_ADDRESS,
@@ -95,7 +95,7 @@ describe("HDWallet Restore Auto-sync", () => {
 			"Error when retrieving receiver view key",
 		);
 
-		const txId = await asyncMiddleman(
+		const { tx_id: txId } = await asyncMiddleman(
 			zeroFeeRpcClient.request("wallet_sendToAddress", [
 				senderWalletRequest,
 				receiverWalletTransferAddress,
@@ -105,7 +105,7 @@ describe("HDWallet transaction", () => {
 				"Error when retrieving receiver view key",
 			);
 
-			const txId = await asyncMiddleman(
+			const { tx_id: txId } = await asyncMiddleman(
 				zeroFeeRpcClient.request("wallet_sendToAddress", [
 					senderWalletRequest,
 					receiverWalletTransferAddress,
@@ -273,7 +273,7 @@ describe("HDWallet transaction", () => {
 				"Error when retrieving receiver view key",
 			);
 
-			const txId = await asyncMiddleman(
+			const { tx_id: txId } = await asyncMiddleman(
 				withFeeRpcClient.request("wallet_sendToAddress", [
 					senderWalletRequest,
 					receiverWalletTransferAddress,
@@ -185,7 +185,7 @@ describe("Staking", () => {
 			syncWallet(rpcClient, defaultWalletRequest),
 			"Error when synchronizing default wallet",
 		);
-		let txId = await asyncMiddleman(
+		let { tx_id: txId } = await asyncMiddleman(
 			rpcClient.request("wallet_sendToAddress", [
 				defaultWalletRequest,
 				transferAddress,
@@ -222,7 +222,7 @@ describe("Staking", () => {
 		console.log(
 			`[Log] Deposit ${stakingAmount} base unit stake to staking address "${stakingAddress}"`,
 		);
-		const depositStakeTxId = await asyncMiddleman(
+		const { tx_id: depositStakeTxId } = await asyncMiddleman(
 			rpcClient.request("staking_depositAmountStake", [
 				walletRequest,
 				stakingAddress,
@@ -256,7 +256,7 @@ describe("Staking", () => {
 			syncWallet(rpcClient, defaultWalletRequest),
 			"Error when synchronizing default wallet",
 		);
-		let txId = await asyncMiddleman(
+		let { tx_id: txId } = await asyncMiddleman(
 			rpcClient.request("wallet_sendToAddress", [
 				defaultWalletRequest,
 				transferAddress,
@@ -293,7 +293,7 @@ describe("Staking", () => {
 		console.log(
 			`[Log] Deposit ${stakingAmount} base unit stake to staking address "${stakingAddress}"`,
 		);
-		const depositStakeTxId = await asyncMiddleman(
+		const { tx_id: depositStakeTxId } = await asyncMiddleman(
 			rpcClient.request("staking_depositStake", [
 				walletRequest,
 				stakingAddress,
@@ -365,7 +365,7 @@ describe("Staking", () => {
 		console.log(
 			`[Log] Unbond ${unbondAmount} base unit stake from staking address "${stakingAddress}"`,
 		);
-		const unbondStakeTxId = await asyncMiddleman(
+		const { tx_id: unbondStakeTxId } = await asyncMiddleman(
 			rpcClient.request("staking_unbondStake", [
 				walletRequest,
 				stakingAddress,
@@ -453,7 +453,7 @@ describe("Staking", () => {
 			"Error when synchronizing wallet after withdraw",
 		);
 
-		const withdrawTxId = await asyncMiddleman(
+		const { tx_id: withdrawTxId } = await asyncMiddleman(
 			rpcClient.request("staking_withdrawAllUnbondedStake", [
 				walletRequest,
 				stakingAddress,
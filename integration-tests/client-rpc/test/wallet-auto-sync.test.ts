@@ -94,7 +94,7 @@ describe("Wallet Auto-sync", () => {
 			"Error when retrieving receiver view key",
 		);
 
-		const txId = await asyncMiddleman(
+		const { tx_id: txId } = await asyncMiddleman(
 			zeroFeeRpcClient.request("wallet_sendToAddress", [
 				senderWalletRequest,
 				receiverWalletTransferAddress,
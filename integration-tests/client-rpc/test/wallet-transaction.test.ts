@@ -107,7 +107,7 @@ describe("Wallet transaction", () => {
 				"Error when retrieving receiver view key",
 			);
 
-			const txId = await asyncMiddleman(
+			const { tx_id: txId } = await asyncMiddleman(
 				zeroFeeRpcClient.request("wallet_sendToAddress", [
 					senderWalletRequest,
 					receiverWalletTransferAddress,
@@ -279,7 +279,7 @@ describe("Wallet transaction", () => {
 				"Error when retrieving receiver view key",
 			);
 
-			const txId = await asyncMiddleman(
+			const { tx_id: txId } = await asyncMiddleman(
 				withFeeRpcClient.request("wallet_sendToAddress", [
 					senderWalletRequest,
 					receiverWalletTransferAddress,
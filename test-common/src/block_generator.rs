@@ -4,28 +4,28 @@ use std::str::FromStr;
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, UNIX_EPOCH};
 
-use client_common::tendermint::types::BlockResultsResponse;
+use client_common::tendermint::types::{BlockResultsResponse, SignedHeader, ValidatorSet};
+use parity_scale_codec::{Decode, Encode};
 use secstr::SecUtf8;
+use sha2::{Digest, Sha256};
 use signatory::ed25519;
 use signatory::public_key::PublicKeyed;
 use signatory_dalek::Ed25519Signer;
 use signature::Signer;
 use subtle_encoding::{base64, hex};
 use tendermint::amino_types::message::AminoMessage;
-use tendermint::lite::{Header, ValidatorSet};
+use tendermint::lite::{Header, ValidatorSet as _};
 use tendermint::{
-    account, amino_types, block, block::signed_header::SignedHeader, block::Height, chain,
-    consensus, evidence, hash, node, public_key, validator, vote, Block, Hash, PublicKey,
-    Signature, Time,
+    abci, account, amino_types, block, block::Height, chain, consensus, evidence, hash, merkle,
+    node, public_key, validator, vote, Block, Hash, PublicKey, Signature, Time,
 };
 use tendermint_light_client::{errors::Error, types::LightBlock};
 use tendermint_rpc::endpoint::status;
 
 use chain_abci::app::ChainNodeState;
 use chain_abci::staking::StakingTable;
-use chain_core::common::{MerkleTree, Timespec};
+use chain_core::common::{MerkleTree, TendermintEventKey, TendermintEventType, Timespec};
 use chain_core::compute_app_hash;
-use chain_core::init::config::NetworkParameters;
 use chain_core::init::{
     address::RedeemAddress, coin::Coin, config::InitConfig, network::Network, params,
 };
@@ -271,6 +271,7 @@ impl TestnetSpec {
                 self.per_byte_fee,
                 self.expansion_cap,
             ),
+            minimum_bond_remainder: None,
             council_nodes,
         }
     }
@@ -288,7 +289,7 @@ impl TestnetSpec {
             .validate_config_get_genesis(genesis_seconds)
             .expect("distribution validation error");
         let account_root = put_stakings(&mut store, 0, genesis_state.accounts.iter()).unwrap();
-        let network_params = NetworkParameters::Genesis(config.network_params.clone());
+        let network_params = config.get_network_parameters();
         let app_hash = compute_app_hash(
             &MerkleTree::empty(),
             &account_root,
@@ -431,7 +432,8 @@ impl BlockGenerator {
         }
     }
 
-    pub fn gen_block(&mut self, _txs: &[TxAux]) {
+    /// Generates the next block with given transactions (all of them valid)
+    pub fn gen_block(&mut self, txs: &[TxAux]) {
         let height = self
             .current_height
             .map_or(Height::default(), |height| height.increment());
@@ -454,6 +456,23 @@ impl BlockGenerator {
         let consensus_hash =
             Hash::from_str("048091BC7DDC283F77BFBF91D73C44DA58C3DF8A9CBC867405D8B7F3DAADA22F")
                 .unwrap(); // TODO real consensus hash
+        let raw_txs = txs.iter().map(Encode::encode).collect::<Vec<_>>();
+        // same as `Txs.Hash()` of tendermint
+        let data_hash = if raw_txs.is_empty() {
+            None
+        } else {
+            let tx_hashes = raw_txs
+                .iter()
+                .map(|raw| Sha256::digest(raw).to_vec())
+                .collect();
+            Some(
+                Hash::new(
+                    hash::Algorithm::Sha256,
+                    &merkle::simple_hash_from_byte_vectors(tx_hashes),
+                )
+                .unwrap(),
+            )
+        };
         let header = block::Header {
             version: block::header::Version { block: 10, app: 0 },
             chain_id: self.genesis.chain_id,
@@ -463,7 +482,7 @@ impl BlockGenerator {
             .into(),
             last_block_id,
             last_commit_hash: None,
-            data_hash: None,
+            data_hash,
             validators_hash: self.validators.hash(),
             next_validators_hash: self.validators.hash(),
             consensus_hash,
@@ -494,12 +513,21 @@ impl BlockGenerator {
         };
         let block = block::Block {
             header,
-            data: Default::default(),
+            data: abci::transaction::Data::new(raw_txs.into_iter().map(abci::Transaction::new)),
             evidence: Default::default(),
             last_commit,
         };
 
-        let state = last_state.clone();
+        let mut state = last_state.clone();
+        if !txs.is_empty() {
+            let top_level = &state.top_level;
+            state.last_apphash = compute_app_hash(
+                &MerkleTree::new(txs.iter().map(TxAux::tx_id).collect()),
+                &top_level.account_root,
+                &top_level.rewards_pool,
+                &top_level.network_params,
+            );
+        }
         self.blocks.push(BlockState {
             block,
             commit,
@@ -551,7 +579,59 @@ impl Client for GeneratorClient {
         heights.map(|height| self.block(*height)).collect()
     }
 
+    fn signed_header(&self, height: u64) -> Result<SignedHeader> {
+        Ok(self.gen.read().unwrap().signed_header(Height::from(height)))
+    }
+
+    fn validators(&self, _height: u64) -> Result<ValidatorSet> {
+        Ok(self.gen.read().unwrap().validators.clone())
+    }
+
     fn block_results(&self, height: u64) -> Result<BlockResultsResponse> {
+        let block = self.block(height)?;
+        if block.data.iter().next().is_some() {
+            // valid transaction events (with zero fees) of the transactions of the block
+            let attribute = |key: TendermintEventKey, value: String| {
+                serde_json::json!({
+                    "key": base64::encode(key.to_string()),
+                    "value": base64::encode(value),
+                })
+            };
+            let txs_results = block
+                .data
+                .iter()
+                .map(|raw| {
+                    let tx_id = TxAux::decode(&mut raw.clone().into_vec().as_slice())
+                        .unwrap()
+                        .tx_id();
+                    serde_json::json!({
+                        "code": 0,
+                        "data": null,
+                        "log": "",
+                        "info": "",
+                        "gasWanted": "0",
+                        "gasUsed": "0",
+                        "events": [{
+                            "type": TendermintEventType::ValidTransactions.to_string(),
+                            "attributes": [
+                                attribute(TendermintEventKey::Fee, "0.00000000".to_owned()),
+                                attribute(TendermintEventKey::TxId, hex::encode(tx_id)),
+                            ],
+                        }],
+                        "codespace": "",
+                    })
+                })
+                .collect::<Vec<_>>();
+            return Ok(serde_json::from_value(serde_json::json!({
+                "height": height.to_string(),
+                "txs_results": txs_results,
+                "begin_block_events": null,
+                "end_block_events": null,
+                "validator_updates": null,
+                "consensus_param_updates": null,
+            }))
+            .unwrap());
+        }
         Ok(BlockResultsResponse {
             height: Height::from(height),
             txs_results: None,
@@ -18,8 +18,8 @@ use chain_core::compute_app_hash;
 use chain_core::init::address::RedeemAddress;
 use chain_core::init::coin::Coin;
 use chain_core::init::config::{
-    InitConfig, InitNetworkParameters, JailingParameters, NetworkParameters, RewardsParameters,
-    SlashRatio, SlashingParameters,
+    InitConfig, InitNetworkParameters, JailingParameters, RewardsParameters, SlashRatio,
+    SlashingParameters,
 };
 use chain_core::state::account::{
     ConfidentialInit, CouncilNodeMeta, MLSInit, NodeMetadata, NodeName, NodeSecurityContact,
@@ -29,7 +29,7 @@ use chain_core::state::account::{
 use chain_core::state::tendermint::{
     TendermintValidatorAddress, TendermintValidatorPubKey, TendermintVotePower,
 };
-use chain_core::state::validator::NodeJoinRequestTx;
+use chain_core::state::validator::{NodeJoinRequestTx, UpdateNodeTx};
 use chain_core::tx::fee::{LinearFee, Milli};
 use chain_core::tx::witness::EcdsaSignature;
 use chain_core::tx::{data::TxId, TransactionId, TxAux, TxPublicAux};
@@ -293,11 +293,7 @@ impl ChainEnv {
             distribution.insert(acct.address, (StakedStateDestination::Bonded, share));
         }
 
-        let init_config = InitConfig::new(
-            distribution,
-            init_network_params.clone(),
-            get_nodes(&accounts),
-        );
+        let init_config = InitConfig::new(distribution, init_network_params, get_nodes(&accounts));
 
         let timestamp = Timestamp {
             seconds: DEFAULT_GENESIS_TIME as i64,
@@ -312,7 +308,7 @@ impl ChainEnv {
             &MerkleTree::empty(),
             &new_account_root,
             &genesis_state.rewards_pool,
-            &NetworkParameters::Genesis(init_network_params),
+            &init_config.get_network_parameters(),
         );
         (
             ChainEnv {
@@ -362,6 +358,28 @@ impl ChainEnv {
         TxAux::PublicTx(TxPublicAux::NodeJoinTx(tx, witness))
     }
 
+    pub fn update_node_tx(
+        &self,
+        announced_endpoints: Vec<String>,
+        nonce: u64,
+        account_index: usize,
+    ) -> TxAux {
+        let tx = UpdateNodeTx::new(
+            nonce,
+            self.accounts[account_index].staking_address(),
+            StakedStateOpAttributes::new(0),
+            announced_endpoints,
+        )
+        .unwrap();
+        let secp = secp256k1::SECP256K1;
+        let witness = StakedStateOpWitness::new(get_ecdsa_witness(
+            &secp,
+            &tx.id(),
+            &self.accounts[account_index].secret_key,
+        ));
+        TxAux::PublicTx(TxPublicAux::UpdateNodeTx(tx, witness))
+    }
+
     pub fn unbond_tx(&self, coin: Coin, nonce: u64, account_index: usize) -> TxAux {
         let tx = UnbondTx::new(
             self.accounts[account_index].staking_address(),